@@ -115,8 +115,13 @@ class DownloadOrchestrator(
             val licenseData = parsedLicense["data"] as? Map<*, *> ?: throw Exception("No license data")
             val downloadUrl = licenseData["download_url"] as? String ?: throw Exception("No download URL")
             val totalBytes = (licenseData["total_bytes"] as? Number)?.toLong() ?: 0L
-            val aaxcKey = licenseData["aaxc_key"] as? String ?: throw Exception("No AAXC key")
-            val aaxcIv = licenseData["aaxc_iv"] as? String ?: throw Exception("No AAXC IV")
+            // AAX licenses carry activation bytes instead; they are kept as a
+            // key without IV
+            val activationBytes = licenseData["activation_bytes"] as? String
+            val aaxcKey = activationBytes
+                ?: licenseData["aaxc_key"] as? String ?: throw Exception("No AAXC key")
+            val aaxcIv = if (activationBytes != null) "" else
+                licenseData["aaxc_iv"] as? String ?: throw Exception("No AAXC IV")
             @Suppress("UNCHECKED_CAST")
             val requestHeaders = licenseData["request_headers"] as? Map<String, String>
                 ?: mapOf("User-Agent" to "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0")
@@ -666,10 +671,15 @@ class DownloadOrchestrator(
             // Decrypt using FFmpeg-Kit with metadata and cover art
             val command = buildList {
                 add("-y")
-                add("-audible_key")
-                add(aaxcKey)
-                add("-audible_iv")
-                add(aaxcIv)
+                if (aaxcIv.isEmpty()) {
+                    add("-activation_bytes")
+                    add(aaxcKey)
+                } else {
+                    add("-audible_key")
+                    add(aaxcKey)
+                    add("-audible_iv")
+                    add(aaxcIv)
+                }
                 add("-i")
                 add(encryptedPath)
 
@@ -1545,12 +1555,20 @@ class DownloadOrchestrator(
 
             val taskId = taskObj.getString("task_id")
             val title = taskObj.optString("title", asin)
-            val aaxcKey = taskObj.optString("aaxc_key", null)
-            val aaxcIv = taskObj.optString("aaxc_iv", null)
-            val outputDirectory = taskObj.optString("output_directory", null)
+
+            // Keys aren't part of the task JSON; read them on their own
+            val keysParams = JSONObject().apply {
+                put("db_path", dbPath)
+                put("task_id", taskId)
+            }
+            val keysJson = JSONObject(ExpoRustBridgeModule.nativeGetConversionKeys(keysParams.toString()))
+            val keys = if (keysJson.optBoolean("success")) keysJson.optJSONObject("data") else null
+            val aaxcKey = keys?.takeUnless { it.isNull("aaxc_key") }?.getString("aaxc_key")
+            val aaxcIv = keys?.takeUnless { it.isNull("aaxc_iv") }?.getString("aaxc_iv")
+            val outputDirectory = keys?.takeUnless { it.isNull("output_directory") }?.getString("output_directory")
 
             if (aaxcKey == null || aaxcIv == null || outputDirectory == null) {
-                Log.e(TAG, "Missing conversion keys for retry: key=$aaxcKey, iv=$aaxcIv, dir=$outputDirectory")
+                Log.e(TAG, "Missing conversion keys for retry: key=${aaxcKey != null}, iv=${aaxcIv != null}, dir=$outputDirectory")
                 return@withContext false
             }
 
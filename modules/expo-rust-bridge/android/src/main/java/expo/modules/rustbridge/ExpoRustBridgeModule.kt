@@ -2163,6 +2163,7 @@ class ExpoRustBridgeModule : Module() {
     @JvmStatic external fun nativeGetAllCategories(paramsJson: String): String
     @JvmStatic external fun nativeDownloadBook(paramsJson: String): String
     @JvmStatic external fun nativeDecryptAAX(paramsJson: String): String
+    @JvmStatic external fun nativeDecryptAAXC(paramsJson: String): String
     @JvmStatic external fun nativeValidateActivationBytes(paramsJson: String): String
     @JvmStatic external fun nativeGetSupportedLocales(paramsJson: String): String
     @JvmStatic external fun nativeBuildFilePath(paramsJson: String): String
@@ -2181,6 +2182,7 @@ class ExpoRustBridgeModule : Module() {
     @JvmStatic external fun nativeCancelDownload(paramsJson: String): String
     @JvmStatic external fun nativeUpdateDownloadTaskStatus(paramsJson: String): String
     @JvmStatic external fun nativeStoreConversionKeys(paramsJson: String): String
+    @JvmStatic external fun nativeGetConversionKeys(paramsJson: String): String
 
     // Account functions
     @JvmStatic external fun nativeSaveAccount(paramsJson: String): String
@@ -66,8 +66,13 @@ class DownloadWorker(
             val licenseData = parsedLicense["data"] as? Map<*, *> ?: throw Exception("No license data")
             val downloadUrl = licenseData["download_url"] as? String ?: throw Exception("No download URL")
             val totalBytes = (licenseData["total_bytes"] as? Number)?.toLong() ?: 0L
-            val aaxcKey = licenseData["aaxc_key"] as? String ?: throw Exception("No AAXC key")
-            val aaxcIv = licenseData["aaxc_iv"] as? String ?: throw Exception("No AAXC IV")
+            // AAX licenses carry activation bytes instead; they are kept as a
+            // key without IV
+            val activationBytes = licenseData["activation_bytes"] as? String
+            val aaxcKey = activationBytes
+                ?: licenseData["aaxc_key"] as? String ?: throw Exception("No AAXC key")
+            val aaxcIv = if (activationBytes != null) "" else
+                licenseData["aaxc_iv"] as? String ?: throw Exception("No AAXC IV")
             @Suppress("UNCHECKED_CAST")
             val requestHeaders = licenseData["request_headers"] as? Map<String, String>
                 ?: mapOf("User-Agent" to "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0")
@@ -327,10 +332,15 @@ class DownloadWorker(
             // Decrypt using FFmpeg-Kit with metadata and cover art
             val command = buildList {
                 add("-y")
-                add("-audible_key")
-                add(aaxcKey)
-                add("-audible_iv")
-                add(aaxcIv)
+                if (aaxcIv.isEmpty()) {
+                    add("-activation_bytes")
+                    add(aaxcKey)
+                } else {
+                    add("-audible_key")
+                    add(aaxcKey)
+                    add("-audible_iv")
+                    add(aaxcIv)
+                }
                 add("-i")
                 add(encryptedPath)
 
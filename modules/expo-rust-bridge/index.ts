@@ -250,14 +250,11 @@ export interface DownloadTask {
   download_url: string;
   download_path: string;
   output_path: string;
-  request_headers: Record<string, string>;
   error?: string;
   retry_count: number;
   created_at: string;
   started_at?: string;
   completed_at?: string;
-  aaxc_key?: string;
-  aaxc_iv?: string;
   output_directory?: string;
 }
 
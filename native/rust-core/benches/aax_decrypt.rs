@@ -0,0 +1,53 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! AAX sample decryption throughput
+//!
+//! AAX encrypts every AAC frame as its own CBC stream, so per-sample setup
+//! cost matters as much as raw AES speed. The target is at least 100 MB/s on
+//! mid-range phones; run on-device with
+//! `cargo bench --bench aax_decrypt --target aarch64-linux-android`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rust_core::crypto::aax::AaxFileKey;
+
+/// 8 MiB of sample data per iteration
+const DATA_SIZE: usize = 8 * 1024 * 1024;
+
+fn bench_decrypt_samples(c: &mut Criterion) {
+    let key = AaxFileKey::new([0x42; 16], [0x24; 16]);
+    let mut group = c.benchmark_group("aax_decrypt_samples");
+
+    // AAC frame sizes at 32 and 64 kbps, plus a large sample for raw AES speed
+    for sample_size in [372usize, 744, 65536] {
+        let len = DATA_SIZE / sample_size * sample_size;
+        let mut data = vec![0xa5u8; len];
+
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_function(BenchmarkId::from_parameter(sample_size), |b| {
+            b.iter(|| {
+                for sample in data.chunks_mut(sample_size) {
+                    key.decrypt_sample(sample);
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decrypt_samples);
+criterion_main!(benches);
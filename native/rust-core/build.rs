@@ -1,3 +1,4 @@
 fn main() {
+    #[cfg(feature = "ios-bridge")]
     uniffi::generate_scaffolding("./src/rust_core.udl").unwrap();
 }
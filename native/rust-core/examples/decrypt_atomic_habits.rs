@@ -48,7 +48,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let activation_bytes_result = get_activation_bytes(
         &locale,
-        &account.identity.as_ref().unwrap().access_token.token
+        account.identity.as_ref().unwrap().access_token.token.expose_secret()
     ).await;
 
     let activation_bytes_hex = match activation_bytes_result {
@@ -63,7 +63,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Extract activation bytes
     let activation_bytes_hex = if let Some(ref keys) = license.decryption_keys {
         if !keys.is_empty() && keys[0].key_part_1.len() == 4 {
-            let hex = keys[0].key_part_1.iter()
+            let hex = keys[0].key_part_1.expose_secret().iter()
                 .map(|b| format!("{:02x}", b))
                 .collect::<String>();
             println!("   Activation Bytes: {}", hex);
@@ -117,7 +117,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let client = reqwest::Client::new();
     let first_response = client
         .get(format!("{}/1.0/library", api_url))
-        .header("Authorization", format!("Bearer {}", identity.access_token.token))
+        .header("Authorization", format!("Bearer {}", identity.access_token.token.expose_secret()))
         .query(&first_page_options)
         .send()
         .await?;
@@ -138,7 +138,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let http_response = client
         .get(format!("{}/1.0/library", api_url))
-        .header("Authorization", format!("Bearer {}", identity.access_token.token))
+        .header("Authorization", format!("Bearer {}", identity.access_token.token.expose_secret()))
         .query(&options)
         .send()
         .await?;
@@ -181,7 +181,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let identity = account.identity.as_ref().unwrap();
                     let retry_response = client
                         .get(format!("{}/1.0/library", api_url))
-                        .header("Authorization", format!("Bearer {}", identity.access_token.token))
+                        .header("Authorization", format!("Bearer {}", identity.access_token.token.expose_secret()))
                         .query(&options)
                         .send()
                         .await?;
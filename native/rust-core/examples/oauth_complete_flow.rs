@@ -53,12 +53,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Step 5: Show available data
     println!("\n📊 Step 5: Available authentication data");
     println!("   Bearer Tokens:");
-    println!("     - Access Token: {}...", &identity.access_token.token[..30]);
-    println!("     - Refresh Token: {}...", &identity.refresh_token[..30]);
+    println!("     - Access Token: {}...", &identity.access_token.token.expose_secret()[..30]);
+    println!("     - Refresh Token: {}...", &identity.refresh_token.expose_secret()[..30]);
     println!("   ");
     println!("   Device Credentials:");
-    println!("     - Private Key: {} chars", identity.device_private_key.len());
-    println!("     - ADP Token: {} chars", identity.adp_token.len());
+    println!("     - Private Key: {} chars", identity.device_private_key.expose_secret().len());
+    println!("     - ADP Token: {} chars", identity.adp_token.expose_secret().len());
     println!("   ");
     println!("   Session Cookies: {}", identity.cookies.len());
     for (name, value) in &identity.cookies {
@@ -111,7 +111,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     );
                     println!(
                         "Refresh Token: {}",
-                        if tokens.bearer.refresh_token.is_empty() {
+                        if tokens.bearer.refresh_token.expose_secret().is_empty() {
                             "missing"
                         } else {
                             "received"
@@ -47,7 +47,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let response = reqwest::Client::new()
         .get(api_url)
         .query(&options)
-        .header("Authorization", format!("Bearer {}", account.identity.as_ref().unwrap().access_token.token))
+        .header("Authorization", format!("Bearer {}", account.identity.as_ref().unwrap().access_token.token.expose_secret()))
         .header("Accept", "application/json")
         .send()
         .await?;
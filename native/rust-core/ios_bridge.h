@@ -182,6 +182,23 @@ char* rust_decrypt_aax(
     const char* activation_bytes
 );
 
+/**
+ * Decrypt AAXC file to M4B using the key and IV from its license
+ *
+ * @param input_path Absolute path to input AAXC file
+ * @param output_path Absolute path where M4B file should be saved
+ * @param aaxc_key 32-character hex key from the license voucher
+ * @param aaxc_iv 32-character hex IV from the license voucher
+ * @return JSON string with output_path and file_size
+ *         Caller must free with rust_free_string()
+ */
+char* rust_decrypt_aaxc(
+    const char* input_path,
+    const char* output_path,
+    const char* aaxc_key,
+    const char* aaxc_iv
+);
+
 // ============================================================================
 // UTILITY FUNCTIONS
 // ============================================================================
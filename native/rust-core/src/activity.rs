@@ -0,0 +1,309 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Long-running work signals for the host app
+//!
+//! Mobile OSes kill backgrounded apps, taking running downloads with them.
+//! Rust tells the host when long work starts and stops so the Kotlin/Swift
+//! layer can hold a foreground service or wake lock (Android) or a
+//! background task (iOS) exactly while it is needed.
+//!
+//! Work is tracked with RAII guards from `begin_work`: the work ends when
+//! the guard is dropped, including on error, cancellation or task abort, so
+//! the host is never left holding a lock. The listener is called only when
+//! the summary changes, i.e. on idle/active transitions and when the set of
+//! running work types or of their trace ids (see `trace`) changes. Expected durations are refined as work
+//! progresses and reported with the next notification and by
+//! `current_activity`.
+//!
+//! # Example
+//! ```rust,ignore
+//! let work = activity::begin_work(WorkType::Download, None);
+//! // ... download, calling work.set_expected_duration(...) as speed is known
+//! drop(work); // host is told "idle" once nothing else runs
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Kind of long-running work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkType {
+    Download,
+    Decryption,
+    Conversion,
+    LibrarySync,
+    IntegrityCheck,
+}
+
+/// Summary of running work, as sent to the host
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WorkActivity {
+    /// Any work is running
+    pub active: bool,
+
+    /// Distinct kinds of running work, sorted
+    pub work_types: Vec<WorkType>,
+
+    /// Number of running jobs
+    pub job_count: usize,
+
+    /// Longest remaining expected duration among jobs that have an
+    /// estimate (None if no job has one)
+    pub expected_remaining_secs: Option<u64>,
+
+    /// Distinct trace ids of running jobs started under one, sorted
+    #[serde(default)]
+    pub trace_ids: Vec<String>,
+}
+
+/// Receives work activity changes (implemented by the platform bridges)
+///
+/// Called on whatever thread ended or started the work; implementations
+/// must not block.
+pub trait WorkActivityListener: Send + Sync {
+    fn on_activity_changed(&self, activity: &WorkActivity);
+}
+
+struct Job {
+    work_type: WorkType,
+    /// Deadline of the current estimate
+    expected_end: Option<Instant>,
+    trace_id: Option<String>,
+}
+
+#[derive(Default)]
+struct Jobs {
+    running: HashMap<u64, Job>,
+    next_id: u64,
+    /// Last (active, work_types, trace_ids) sent, to suppress duplicate
+    /// notifications
+    last_sent: (bool, Vec<WorkType>, Vec<String>),
+}
+
+impl Jobs {
+    fn summary(&self) -> WorkActivity {
+        let mut work_types: Vec<WorkType> = self.running.values().map(|j| j.work_type).collect();
+        work_types.sort();
+        work_types.dedup();
+
+        let mut trace_ids: Vec<String> = self.running.values().filter_map(|j| j.trace_id.clone()).collect();
+        trace_ids.sort();
+        trace_ids.dedup();
+
+        let now = Instant::now();
+        let expected_remaining_secs = self
+            .running
+            .values()
+            .filter_map(|j| j.expected_end)
+            .map(|end| end.saturating_duration_since(now).as_secs())
+            .max();
+
+        WorkActivity {
+            active: !self.running.is_empty(),
+            work_types,
+            job_count: self.running.len(),
+            expected_remaining_secs,
+            trace_ids,
+        }
+    }
+
+    /// Summary if it differs from the last one sent
+    fn changed_summary(&mut self) -> Option<WorkActivity> {
+        let summary = self.summary();
+        let key = (summary.active, summary.work_types.clone(), summary.trace_ids.clone());
+        if key == self.last_sent {
+            return None;
+        }
+        self.last_sent = key;
+        Some(summary)
+    }
+}
+
+/// Running work and the listener told about it
+///
+/// The app uses the process-wide tracker through the free functions below.
+#[derive(Default)]
+pub struct WorkTracker {
+    jobs: Mutex<Jobs>,
+    listener: RwLock<Option<Arc<dyn WorkActivityListener>>>,
+}
+
+lazy_static::lazy_static! {
+    static ref TRACKER: Arc<WorkTracker> = Arc::new(WorkTracker::default());
+}
+
+impl WorkTracker {
+    fn notify(&self, activity: Option<WorkActivity>) {
+        if let Some(activity) = activity {
+            let listener = self.listener.read().unwrap().clone();
+            if let Some(listener) = listener {
+                listener.on_activity_changed(&activity);
+            }
+        }
+    }
+
+    /// See `set_work_activity_listener`
+    pub fn set_listener(&self, listener: Option<Arc<dyn WorkActivityListener>>) {
+        *self.listener.write().unwrap() = listener;
+
+        let current = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let summary = jobs.summary();
+            jobs.last_sent = (summary.active, summary.work_types.clone(), summary.trace_ids.clone());
+            summary
+        };
+        self.notify(Some(current));
+    }
+
+    /// See `current_activity`
+    pub fn current(&self) -> WorkActivity {
+        self.jobs.lock().unwrap().summary()
+    }
+
+    /// See `begin_work`
+    pub fn begin(self: &Arc<Self>, work_type: WorkType, expected_duration: Option<Duration>) -> WorkGuard {
+        let (id, changed) = {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.next_id += 1;
+            let id = jobs.next_id;
+            jobs.running.insert(
+                id,
+                Job {
+                    work_type,
+                    expected_end: expected_duration.map(|d| Instant::now() + d),
+                    trace_id: crate::trace::current(),
+                },
+            );
+            (id, jobs.changed_summary())
+        };
+        self.notify(changed);
+
+        WorkGuard {
+            tracker: Arc::clone(self),
+            id,
+        }
+    }
+}
+
+/// Install (or remove, with None) the host listener
+///
+/// The new listener is immediately told the current state, so a host that
+/// registers while work is running acquires its lock right away.
+pub fn set_work_activity_listener(listener: Option<Arc<dyn WorkActivityListener>>) {
+    TRACKER.set_listener(listener);
+}
+
+/// Currently running work
+pub fn current_activity() -> WorkActivity {
+    TRACKER.current()
+}
+
+/// Mark the start of long-running work; it ends when the guard is dropped
+///
+/// The work is listed under the current trace id, if any.
+///
+/// # Arguments
+/// * `work_type` - Kind of work
+/// * `expected_duration` - Estimate if known up front
+pub fn begin_work(work_type: WorkType, expected_duration: Option<Duration>) -> WorkGuard {
+    TRACKER.begin(work_type, expected_duration)
+}
+
+/// Running work; dropping it ends the work
+#[must_use = "work ends as soon as the guard is dropped"]
+pub struct WorkGuard {
+    tracker: Arc<WorkTracker>,
+    id: u64,
+}
+
+impl WorkGuard {
+    /// Update the remaining-time estimate (None clears it)
+    pub fn set_expected_duration(&self, remaining: Option<Duration>) {
+        if let Some(job) = self.tracker.jobs.lock().unwrap().running.get_mut(&self.id) {
+            job.expected_end = remaining.map(|d| Instant::now() + d);
+        }
+    }
+}
+
+impl Drop for WorkGuard {
+    fn drop(&mut self) {
+        let changed = {
+            let mut jobs = self.tracker.jobs.lock().unwrap();
+            jobs.running.remove(&self.id);
+            jobs.changed_summary()
+        };
+        self.tracker.notify(changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(Mutex<Vec<WorkActivity>>);
+
+    impl WorkActivityListener for Recorder {
+        fn on_activity_changed(&self, activity: &WorkActivity) {
+            self.0.lock().unwrap().push(activity.clone());
+        }
+    }
+
+    #[test]
+    fn test_work_transitions() {
+        let tracker = Arc::new(WorkTracker::default());
+        let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+        tracker.set_listener(Some(recorder.clone()));
+        assert!(!recorder.0.lock().unwrap().pop().unwrap().active);
+
+        let download = tracker.begin(WorkType::Download, None);
+        let second = tracker.begin(WorkType::Download, Some(Duration::from_secs(60)));
+        let sync = {
+            let _trace = crate::trace::enter(Some("sync-1".to_string()));
+            tracker.begin(WorkType::LibrarySync, None)
+        };
+
+        let current = tracker.current();
+        assert!(current.active);
+        assert_eq!(current.job_count, 3);
+        assert_eq!(current.trace_ids, vec!["sync-1".to_string()]);
+        assert!(current.expected_remaining_secs.unwrap() <= 60);
+
+        sync.set_expected_duration(Some(Duration::from_secs(600)));
+        assert!(tracker.current().expected_remaining_secs.unwrap() > 60);
+
+        drop(sync);
+        drop(second);
+        drop(download);
+
+        let events = recorder.0.lock().unwrap().clone();
+        let kinds: Vec<_> = events.iter().map(|e| (e.active, e.work_types.clone())).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (true, vec![WorkType::Download]),
+                (true, vec![WorkType::Download, WorkType::LibrarySync]),
+                (true, vec![WorkType::Download]),
+                (false, vec![]),
+            ]
+        );
+        assert!(!tracker.current().active);
+    }
+}
@@ -0,0 +1,435 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Listening positions and bookmarks (Whispersync)
+//!
+//! Audible keeps the last-heard position of each title, and the bookmarks,
+//! notes and clips made in its apps, so listening can continue on another
+//! device.
+//!
+//! # Endpoints
+//! - **GET** `/1.0/annotations/lastpositions?asins={asins}` - Last-heard
+//!   positions of up to `MAX_POSITION_ASINS` titles
+//! - **PUT** `/1.0/lastpositions/{asin}` - Store the last-heard position;
+//!   needs the title's ACR (Audible content reference)
+//! - **GET** `/1.0/content/{asin}/metadata?response_groups=content_reference`
+//!   - The ACR
+//! - **GET** `SIDECAR_URL?type=AUDI&key={asin}` - Bookmarks, notes, clips
+//!   and the last-heard record (undocumented; positions are strings)
+//!
+//! Only the last-heard position is written back. The sidecar write format
+//! isn't known, so bookmarks are read-only here. Local storage and merging
+//! are in `storage::listening_positions`.
+
+use crate::api::client::AudibleClient;
+use crate::api::response_groups::{ResponseGroup, ResponseGroups};
+use crate::error::{LibationError, Result};
+use crate::storage::dates::{now, parse_timestamp};
+use crate::storage::listening_positions::{
+    apply_remote_position, get_playback_position, list_annotations, record_synced_position, replace_annotations,
+    PlaybackPosition, StoredAnnotation,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::SqlitePool;
+
+/// Most ASINs per last-positions request
+pub const MAX_POSITION_ASINS: usize = 25;
+
+/// Annotation store the Audible apps sync bookmarks with
+pub const SIDECAR_URL: &str = "https://cde-ta-g7g.amazon.com/FionaCDEServiceEngine/sidecar";
+
+/// Kind of an annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum AnnotationKind {
+    LastHeard,
+    Bookmark,
+    Note,
+    Clip,
+}
+
+impl AnnotationKind {
+    /// Kind of a sidecar record type ("audible.bookmark")
+    fn from_sidecar(record_type: &str) -> Option<Self> {
+        match record_type {
+            "audible.last_heard" => Some(AnnotationKind::LastHeard),
+            "audible.bookmark" => Some(AnnotationKind::Bookmark),
+            "audible.note" => Some(AnnotationKind::Note),
+            "audible.clip" => Some(AnnotationKind::Clip),
+            _ => None,
+        }
+    }
+}
+
+/// Last-heard position of a title on Audible
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemotePosition {
+    pub asin: String,
+    pub position_ms: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A bookmark, note or clip (or the last-heard record) from the sidecar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    /// Sidecar id; empty for the last-heard record
+    pub annotation_id: String,
+    pub position_ms: i64,
+    /// End of a note or clip
+    pub end_position_ms: Option<i64>,
+    /// Note text or clip title
+    pub note: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Result of `sync_playback_position`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionSync {
+    pub position: PlaybackPosition,
+    /// Audible's position was newer and replaced the local one
+    pub pulled: bool,
+    /// The local position was newer and was sent to Audible
+    pub pushed: bool,
+    pub bookmarks: Vec<StoredAnnotation>,
+    /// Why bookmarks couldn't be refreshed (the stored ones are returned)
+    pub bookmarks_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastPositionsResponse {
+    #[serde(default)]
+    asin_last_position_heard_annots: Vec<LastPositionEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastPositionEntry {
+    asin: String,
+    last_position_heard: Option<LastPositionHeard>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastPositionHeard {
+    position_ms: Option<i64>,
+    last_updated: Option<String>,
+    /// "Exists" or "DoesNotExist"
+    status: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SidecarResponse {
+    #[serde(default)]
+    payload: SidecarPayload,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SidecarPayload {
+    #[serde(default)]
+    records: Vec<SidecarRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SidecarRecord {
+    #[serde(rename = "type")]
+    record_type: String,
+    #[serde(default)]
+    annotation_id: Option<String>,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    start_position: Option<i64>,
+    #[serde(default, deserialize_with = "lenient_ms")]
+    end_position: Option<i64>,
+    #[serde(default)]
+    creation_time: Option<String>,
+    #[serde(default)]
+    last_modification_time: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    metadata: Option<serde_json::Value>,
+}
+
+/// Milliseconds sent as a number or a string
+fn lenient_ms<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<i64>, D::Error> {
+    Ok(match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(serde_json::Value::Number(n)) => n.as_i64(),
+        Some(serde_json::Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+impl SidecarRecord {
+    fn into_annotation(self) -> Option<Annotation> {
+        let kind = AnnotationKind::from_sidecar(&self.record_type)?;
+        let position_ms = self.start_position?;
+        let note = self.text.or_else(|| {
+            let metadata = self.metadata.as_ref()?;
+            ["note", "title"]
+                .iter()
+                .find_map(|key| metadata.get(key).and_then(|v| v.as_str()).map(String::from))
+        });
+        let updated_at = self
+            .last_modification_time
+            .or(self.creation_time)
+            .and_then(|at| parse_timestamp(&at));
+
+        Some(Annotation {
+            kind,
+            annotation_id: match kind {
+                AnnotationKind::LastHeard => String::new(),
+                _ => self.annotation_id.unwrap_or_default(),
+            },
+            position_ms,
+            end_position_ms: self.end_position,
+            note,
+            updated_at,
+        })
+    }
+}
+
+impl AudibleClient {
+    /// Last-heard positions of `asins` on Audible
+    ///
+    /// Titles never played on any device are left out. Asks in batches of
+    /// `MAX_POSITION_ASINS`.
+    pub async fn get_last_positions(&self, asins: &[&str]) -> Result<Vec<RemotePosition>> {
+        let mut positions = Vec::new();
+        for batch in asins.chunks(MAX_POSITION_ASINS) {
+            let endpoint = format!("/1.0/annotations/lastpositions?asins={}", batch.join(","));
+            let response: LastPositionsResponse = self.get(&endpoint).await?;
+
+            positions.extend(response.asin_last_position_heard_annots.into_iter().filter_map(|entry| {
+                let heard = entry.last_position_heard?;
+                if heard.status.as_deref().is_some_and(|s| s != "Exists") {
+                    return None;
+                }
+                Some(RemotePosition {
+                    asin: entry.asin,
+                    position_ms: heard.position_ms?,
+                    updated_at: heard.last_updated.as_deref().and_then(parse_timestamp)?,
+                })
+            }));
+        }
+        Ok(positions)
+    }
+
+    /// ACR of a title, needed to store its position
+    pub async fn get_content_acr(&self, asin: &str) -> Result<String> {
+        let endpoint = format!(
+            "/1.0/content/{}/metadata?response_groups={}",
+            asin,
+            ResponseGroups::empty().with(ResponseGroup::ContentReference)
+        );
+        let response: serde_json::Value = self.get(&endpoint).await?;
+
+        response
+            .pointer("/content_metadata/content_reference/acr")
+            .and_then(|acr| acr.as_str())
+            .map(String::from)
+            .ok_or_else(|| LibationError::InvalidApiResponse {
+                message: format!("No content reference for {}", asin),
+                response_body: Some(response.to_string()),
+            })
+    }
+
+    /// Store the last-heard position of a title on Audible
+    ///
+    /// # Arguments
+    /// * `acr` - From `get_content_acr`
+    pub async fn put_last_position(&self, asin: &str, acr: &str, position_ms: i64) -> Result<()> {
+        if position_ms < 0 {
+            return Err(LibationError::invalid_input("Position must not be negative"));
+        }
+        let body = serde_json::json!({ "acr": acr, "asin": asin, "position_ms": position_ms });
+        let _: serde_json::Value = self.put(&format!("/1.0/lastpositions/{}", asin), body).await?;
+        Ok(())
+    }
+
+    /// Send a title's local position to Audible if it wasn't yet
+    ///
+    /// # Returns
+    /// The position with its new sync state
+    pub async fn push_playback_position(&self, pool: &SqlitePool, asin: &str) -> Result<PlaybackPosition> {
+        let position = get_playback_position(pool, asin).await?;
+        if !position.pending_push {
+            return Ok(position);
+        }
+
+        let acr = self.get_content_acr(asin).await?;
+        self.put_last_position(asin, &acr, position.position_ms).await?;
+        let updated_at = position.updated_at.clone().unwrap_or_else(now);
+        record_synced_position(pool, asin, position.position_ms, &updated_at).await?;
+        get_playback_position(pool, asin).await
+    }
+
+    /// Sync a title's position and bookmarks with Audible
+    ///
+    /// Takes Audible's position when it was set after the local one,
+    /// otherwise pushes a local position that wasn't sent yet. Bookmarks
+    /// are refreshed from the sidecar; when that fails, the stored ones are
+    /// returned with `bookmarks_error`.
+    pub async fn sync_playback_position(&self, pool: &SqlitePool, asin: &str) -> Result<PositionSync> {
+        let mut pulled = false;
+        if let Some(remote) = self.get_last_positions(&[asin]).await?.into_iter().find(|p| p.asin == asin) {
+            pulled = apply_remote_position(pool, &remote).await?;
+        }
+
+        let before = get_playback_position(pool, asin).await?;
+        let position = self.push_playback_position(pool, asin).await?;
+        let pushed = before.pending_push;
+
+        let bookmarks_error = match self.get_annotations(asin).await {
+            Ok(annotations) => {
+                replace_annotations(pool, asin, &annotations).await?;
+                None
+            }
+            Err(e) => Some(e.to_string()),
+        };
+
+        Ok(PositionSync {
+            position,
+            pulled,
+            pushed,
+            bookmarks: list_annotations(pool, asin).await?,
+            bookmarks_error,
+        })
+    }
+
+    /// Bookmarks, notes and clips of a title, plus its last-heard record
+    ///
+    /// Records of unknown types or without a position are skipped. A title
+    /// without annotations (404) has none.
+    pub async fn get_annotations(&self, asin: &str) -> Result<Vec<Annotation>> {
+        let url = format!("{}?type=AUDI&key={}", SIDECAR_URL, asin);
+        let response: SidecarResponse = match self.get_url(&url).await {
+            Ok(response) => response,
+            Err(LibationError::ApiRequestFailed { status_code: Some(404), .. }) => SidecarResponse::default(),
+            Err(e) => return Err(e),
+        };
+
+        let mut annotations: Vec<Annotation> = response
+            .payload
+            .records
+            .into_iter()
+            .filter_map(SidecarRecord::into_annotation)
+            .collect();
+        annotations.sort_by_key(|a| (a.position_ms, a.annotation_id.clone()));
+        Ok(annotations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::transport::{mock_client, MockTransport};
+    use crate::storage::progress::set_listening_position;
+    use crate::storage::{queries::insert_book, Database, NewBook};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_positions_and_annotations() {
+        let transport = Arc::new(MockTransport::new());
+        let client = mock_client(&transport);
+
+        transport.push_json(
+            200,
+            serde_json::json!({ "asin_last_position_heard_annots": [
+                { "asin": "B0PLAYED", "last_position_heard": {
+                    "position_ms": 3_214_000, "last_updated": "2024-08-18 19:58:34.489", "status": "Exists" } },
+                { "asin": "B0NEW", "last_position_heard": { "status": "DoesNotExist" } }
+            ] }),
+        );
+        let positions = client.get_last_positions(&["B0PLAYED", "B0NEW"]).await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].position_ms, 3_214_000);
+        assert_eq!(positions[0].updated_at.to_rfc3339(), "2024-08-18T19:58:34.489+00:00");
+        assert!(transport.requests()[0].url.ends_with("/1.0/annotations/lastpositions?asins=B0PLAYED,B0NEW"));
+
+        transport.push_json(200, serde_json::json!({ "content_metadata": { "content_reference": { "acr": "CR!ABC" } } }));
+        transport.push_response(204, Default::default(), Vec::new());
+        let acr = client.get_content_acr("B0PLAYED").await.unwrap();
+        client.put_last_position("B0PLAYED", &acr, 60_000).await.unwrap();
+        let put = &transport.requests()[2];
+        assert_eq!(put.method, reqwest::Method::PUT);
+        let body: serde_json::Value = serde_json::from_slice(put.body.as_deref().unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({ "acr": "CR!ABC", "asin": "B0PLAYED", "position_ms": 60_000 }));
+
+        transport.push_json(
+            200,
+            serde_json::json!({ "payload": { "records": [
+                { "type": "audible.clip", "annotationId": "c1", "startPosition": "90000", "endPosition": "95000",
+                  "creationTime": "2024-01-02 10:00:00.0", "metadata": { "title": "Great line" } },
+                { "type": "audible.bookmark", "annotationId": "b1", "startPosition": "5416500",
+                  "creationTime": "2024-01-01 10:00:00.0" },
+                { "type": "audible.last_heard", "startPosition": 120000 },
+                { "type": "audible.unknown", "startPosition": "1" }
+            ] } }),
+        );
+        let annotations = client.get_annotations("B0PLAYED").await.unwrap();
+        let kinds: Vec<AnnotationKind> = annotations.iter().map(|a| a.kind).collect();
+        assert_eq!(kinds, vec![AnnotationKind::Clip, AnnotationKind::LastHeard, AnnotationKind::Bookmark]);
+        assert_eq!(annotations[0].note.as_deref(), Some("Great line"));
+        assert_eq!(annotations[0].end_position_ms, Some(95_000));
+        assert!(transport.requests()[3].url.starts_with(SIDECAR_URL));
+    }
+
+    #[tokio::test]
+    async fn test_sync_playback_position() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0DUNE".to_string(), "Dune".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let client = mock_client(&transport);
+
+        // Played here after the last Audible update: pushed
+        set_listening_position(pool, "B0DUNE", 60_000).await.unwrap();
+        transport.push_json(
+            200,
+            serde_json::json!({ "asin_last_position_heard_annots": [{ "asin": "B0DUNE", "last_position_heard": {
+                "position_ms": 10_000, "last_updated": "2020-01-01 00:00:00.000", "status": "Exists" } }] }),
+        );
+        transport.push_json(200, serde_json::json!({ "content_metadata": { "content_reference": { "acr": "CR!DUNE" } } }));
+        transport.push_response(204, Default::default(), Vec::new());
+        transport.push_json(
+            200,
+            serde_json::json!({ "payload": { "records": [
+                { "type": "audible.bookmark", "annotationId": "b1", "startPosition": "30000" }
+            ] } }),
+        );
+        let sync = client.sync_playback_position(pool, "B0DUNE").await.unwrap();
+        assert!(sync.pushed && !sync.pulled);
+        assert_eq!((sync.position.position_ms, sync.position.pending_push), (60_000, false));
+        assert_eq!(sync.bookmarks.len(), 1);
+
+        // Moved on another device: pulled; the sidecar failing keeps the bookmarks
+        transport.push_json(
+            200,
+            serde_json::json!({ "asin_last_position_heard_annots": [{ "asin": "B0DUNE", "last_position_heard": {
+                "position_ms": 900_000, "last_updated": "2999-01-01 00:00:00.000", "status": "Exists" } }] }),
+        );
+        transport.push_json(400, serde_json::json!({ "message": "Bad request" }));
+        let sync = client.sync_playback_position(pool, "B0DUNE").await.unwrap();
+        assert!(sync.pulled && !sync.pushed);
+        assert_eq!(sync.position.position_ms, 900_000);
+        assert_eq!(sync.bookmarks.len(), 1);
+        assert!(sync.bookmarks_error.is_some());
+        assert_eq!(transport.requests().len(), 6);
+    }
+}
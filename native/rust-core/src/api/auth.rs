@@ -134,7 +134,11 @@
 //! # }
 //! ```
 
+use crate::api::debug_capture::{self, redact_json};
+use crate::clock::{AppClock, Clock};
 use crate::error::{LibationError, Result};
+use crate::secret::SecretString;
+use crate::trace::trace_eprintln;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
@@ -179,7 +183,7 @@ pub struct Account {
     /// Activation bytes for DRM removal (4-byte hex string)
     /// Maps to C# Account.DecryptKey
     /// Also called "activation bytes" in Audible terminology
-    pub decrypt_key: String,
+    pub decrypt_key: SecretString,
 
     /// OAuth identity tokens and credentials
     /// Maps to C# Account.IdentityTokens (type: Identity)
@@ -221,15 +225,15 @@ pub struct Identity {
 
     /// OAuth refresh token (used to get new access tokens)
     /// Maps to C# Identity.RefreshToken and Mkb79Auth.RefreshToken
-    pub refresh_token: String,
+    pub refresh_token: SecretString,
 
     /// Device private key for cryptographic operations
     /// Maps to C# Identity.PrivateKey and Mkb79Auth.DevicePrivateKey
-    pub device_private_key: String,
+    pub device_private_key: SecretString,
 
     /// Amazon Device Protocol token
     /// Maps to C# Identity.AdpToken and Mkb79Auth.AdpToken
-    pub adp_token: String,
+    pub adp_token: SecretString,
 
     /// Website session cookies
     /// Maps to C# Identity.Cookies and Mkb79Auth.WebsiteCookies
@@ -253,7 +257,7 @@ pub struct Identity {
 
     /// Store authentication cookie
     /// Maps to C# Identity.StoreAuthenticationCookie and Mkb79Auth.StoreAuthenticationCookie
-    pub store_authentication_cookie: String,
+    pub store_authentication_cookie: SecretString,
 
     /// Audible market/region
     /// Maps to C# Identity.Locale and Mkb79Auth.Locale
@@ -275,7 +279,7 @@ pub struct Identity {
 pub struct AccessToken {
     /// The actual token string
     /// Maps to C# AccessToken.TokenValue
-    pub token: String,
+    pub token: SecretString,
 
     /// When this token expires
     /// Maps to C# AccessToken.Expires
@@ -411,10 +415,10 @@ pub struct DeviceRegistration {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenPair {
     /// Access token string
-    pub access_token: String,
+    pub access_token: SecretString,
 
     /// Refresh token string
-    pub refresh_token: String,
+    pub refresh_token: SecretString,
 
     /// Expires in seconds
     pub expires_in: i64,
@@ -451,7 +455,7 @@ impl Account {
             account_id: trimmed.to_string(),
             account_name: trimmed.to_string(), // Default to account_id
             library_scan: true,
-            decrypt_key: String::new(),
+            decrypt_key: SecretString::default(),
             identity: None,
         })
     }
@@ -500,8 +504,8 @@ impl Account {
     /// ```
     pub fn set_decrypt_key(&mut self, key: String) {
         let trimmed = key.trim();
-        if trimmed != self.decrypt_key {
-            self.decrypt_key = trimmed.to_string();
+        if trimmed != self.decrypt_key.expose_secret() {
+            self.decrypt_key = trimmed.into();
         }
     }
 
@@ -532,10 +536,15 @@ impl Account {
     ///
     /// Related to C# token refresh logic in ApiExtended.cs and Authorize class
     pub fn needs_token_refresh(&self) -> bool {
+        self.needs_token_refresh_with_clock(&AppClock)
+    }
+
+    /// `needs_token_refresh` against a given clock
+    pub fn needs_token_refresh_with_clock(&self, clock: &dyn Clock) -> bool {
         match &self.identity {
             None => true,
             Some(identity) => {
-                let now = Utc::now();
+                let now = clock.now();
                 // Refresh if expired or expiring within 5 minutes
                 let buffer = chrono::Duration::minutes(5);
                 identity.access_token.expires_at <= now + buffer
@@ -609,7 +618,7 @@ impl Account {
         // Call the refresh_access_token function
         let token_response = refresh_access_token(
             &identity.locale,
-            &identity.refresh_token,
+            identity.refresh_token.expose_secret(),
             &identity.device_serial_number,
         )
         .await?;
@@ -617,7 +626,7 @@ impl Account {
         // Update the identity with new tokens
         identity.access_token.token = token_response.access_token;
         identity.access_token.expires_at =
-            Utc::now() + chrono::Duration::seconds(token_response.expires_in);
+            crate::clock::now() + chrono::Duration::seconds(token_response.expires_in);
 
         // Update refresh token if provided (some implementations return a new refresh token)
         if let Some(new_refresh_token) = token_response.refresh_token {
@@ -651,10 +660,10 @@ impl Account {
 
         // Call the get_activation_bytes function
         let activation_bytes =
-            get_activation_bytes(&identity.locale, &identity.access_token.token).await?;
+            get_activation_bytes(&identity.locale, identity.access_token.token.expose_secret()).await?;
 
         // Store in decrypt_key field
-        self.decrypt_key = activation_bytes.clone();
+        self.decrypt_key = activation_bytes.as_str().into();
 
         Ok(activation_bytes)
     }
@@ -668,22 +677,22 @@ impl Identity {
     /// Create a new Identity from OAuth tokens and device info
     pub fn new(
         access_token: AccessToken,
-        refresh_token: String,
-        device_private_key: String,
-        adp_token: String,
+        refresh_token: impl Into<SecretString>,
+        device_private_key: impl Into<SecretString>,
+        adp_token: impl Into<SecretString>,
         locale: Locale,
     ) -> Self {
         Self {
             access_token,
-            refresh_token,
-            device_private_key,
-            adp_token,
+            refresh_token: refresh_token.into(),
+            device_private_key: device_private_key.into(),
+            adp_token: adp_token.into(),
             cookies: HashMap::new(),
             device_serial_number: String::new(),
             device_type: String::new(),
             device_name: String::new(),
             amazon_account_id: String::new(),
-            store_authentication_cookie: String::new(),
+            store_authentication_cookie: SecretString::default(),
             locale,
             customer_info: CustomerInfo::default(),
         }
@@ -691,12 +700,17 @@ impl Identity {
 
     /// Check if the access token is expired
     pub fn is_expired(&self) -> bool {
-        Utc::now() >= self.access_token.expires_at
+        self.is_expired_with_clock(&AppClock)
+    }
+
+    /// `is_expired` against a given clock
+    pub fn is_expired_with_clock(&self, clock: &dyn Clock) -> bool {
+        clock.now() >= self.access_token.expires_at
     }
 
     /// Get time until token expiration
     pub fn time_until_expiry(&self) -> chrono::Duration {
-        self.access_token.expires_at - Utc::now()
+        self.access_token.expires_at - crate::clock::now()
     }
 }
 
@@ -857,6 +871,24 @@ impl Locale {
         format!("https://api.{}", self.domain)
     }
 
+    /// Amazon marketplace ID (US for unknown country codes)
+    pub fn marketplace_id(&self) -> &'static str {
+        match self.country_code.as_str() {
+            "us" => "AF2M0KC94RCEA",
+            "uk" => "A2I9A3Q2GNFNGQ",
+            "de" => "AN7V1F1VY261K",
+            "fr" => "A2728XDNODOQ8T",
+            "ca" => "A2CQZ5RBY40XE",
+            "au" => "AN7EY7DTAW63G",
+            "it" => "A2N7FU2W2BU2ZC",
+            "es" => "ALMIKO4SZCSAR",
+            "in" => "AJO3FBRUE6J4S",
+            "jp" => "A1QAP3MOU4173J",
+            "br" => "A10J1VAYUDTYRN",
+            _ => "AF2M0KC94RCEA",
+        }
+    }
+
     /// Get the OAuth URL for this locale
     pub fn oauth_url(&self) -> String {
         format!("https://www.amazon.com/ap/signin")
@@ -1123,21 +1155,7 @@ pub fn generate_authorization_url(
         );
 
         // Marketplace ID (locale-specific)
-        let marketplace_id = match locale.country_code.as_str() {
-            "us" => "AF2M0KC94RCEA",
-            "uk" => "A2I9A3Q2GNFNGQ",
-            "de" => "AN7V1F1VY261K",
-            "fr" => "A2728XDNODOQ8T",
-            "ca" => "A2CQZ5RBY40XE",
-            "au" => "AN7EY7DTAW63G",
-            "it" => "A2N7FU2W2BU2ZC",
-            "es" => "ALMIKO4SZCSAR",
-            "in" => "AJO3FBRUE6J4S",
-            "jp" => "A1QAP3MOU4173J",
-            "br" => "A10J1VAYUDTYRN",
-            _ => "AF2M0KC94RCEA", // Default to US
-        };
-        query.append_pair("marketPlaceId", marketplace_id);
+        query.append_pair("marketPlaceId", locale.marketplace_id());
 
         // OAuth scope and state
         query.append_pair("openid.oa2.scope", config.scope);
@@ -1225,9 +1243,9 @@ pub fn parse_authorization_callback(callback_url: &str) -> Result<String> {
 /// Token response from Audible OAuth
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenResponse {
-    pub access_token: String,
+    pub access_token: SecretString,
     #[serde(default)]
-    pub refresh_token: Option<String>,
+    pub refresh_token: Option<SecretString>,
     pub expires_in: i64, // Seconds until expiration
     pub token_type: String,
 }
@@ -1252,15 +1270,15 @@ pub struct RegistrationResponse {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BearerTokenInfo {
-    pub access_token: String,
-    pub refresh_token: String,
+    pub access_token: SecretString,
+    pub refresh_token: SecretString,
     pub expires_in: String, // String because API returns "3600" as string
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MacDmsTokenInfo {
-    pub device_private_key: String,
-    pub adp_token: String,
+    pub device_private_key: SecretString,
+    pub adp_token: SecretString,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1283,7 +1301,7 @@ pub struct Cookie {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoreAuthCookie {
-    pub cookie: String,
+    pub cookie: SecretString,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1317,7 +1335,9 @@ pub struct DeviceInfo {
 ///
 /// # Note
 /// This function makes an HTTP request to Audible's token endpoint.
-/// The client_id must match the one used in authorization URL.
+/// The client_id must match the one used in authorization URL. The raw
+/// response is only persisted by an enabled debug capture
+/// (`debug_capture::enable_debug_capture`).
 pub async fn exchange_authorization_code(
     locale: &Locale,
     authorization_code: &str,
@@ -1396,14 +1416,14 @@ pub async fn exchange_authorization_code(
         "requested_extensions": ["device_info", "customer_info"]
     });
 
-    // Log the request for debugging
-    eprintln!("=== Device Registration Request ===");
-    eprintln!("URL: {}", register_url);
-    eprintln!(
+    // Log the request for debugging (code and verifier redacted)
+    trace_eprintln!("=== Device Registration Request ===");
+    trace_eprintln!("URL: {}", register_url);
+    trace_eprintln!(
         "Body: {}",
-        serde_json::to_string_pretty(&request_body).unwrap_or_default()
+        serde_json::to_string_pretty(&redact_json(&request_body)).unwrap_or_default()
     );
-    eprintln!("===================================");
+    trace_eprintln!("===================================");
 
     // Make HTTP request
     let client = reqwest::Client::new();
@@ -1425,10 +1445,10 @@ pub async fn exchange_authorization_code(
         } else {
             error_body
         };
-        eprintln!("=== Registration Failed ===");
-        eprintln!("Status: {}", status);
-        eprintln!("Response: {}", error_preview);
-        eprintln!("===========================");
+        trace_eprintln!("=== Registration Failed ===");
+        trace_eprintln!("Status: {}", status);
+        trace_eprintln!("Response: {}", error_preview);
+        trace_eprintln!("===========================");
         return Err(LibationError::AuthenticationFailed {
             message: format!("Token exchange failed (status {})", status),
             account_id: None,
@@ -1437,12 +1457,16 @@ pub async fn exchange_authorization_code(
 
     let response_text = response.text().await.unwrap_or_default();
 
-    // Parse the response without persisting tokens or customer data to disk.
+    // Parse the response; it is only written to disk by an opt-in debug
+    // capture, and error bodies are redacted
     let register_response: serde_json::Value =
         serde_json::from_str(&response_text).map_err(|e| LibationError::InvalidApiResponse {
             message: format!("Failed to parse registration response: {}", e),
-            response_body: Some(response_text.clone()),
+            response_body: None,
         })?;
+    if let Err(e) = debug_capture::capture("registration", &register_response) {
+        trace_eprintln!("Failed to capture registration response: {}", e);
+    }
 
     // Extract full registration data
     let success = register_response
@@ -1450,14 +1474,14 @@ pub async fn exchange_authorization_code(
         .and_then(|r| r.get("success"))
         .ok_or_else(|| LibationError::InvalidApiResponse {
             message: "Success response not found in registration".to_string(),
-            response_body: Some(register_response.to_string()),
+            response_body: Some(redact_json(&register_response).to_string()),
         })?;
 
     let tokens = success
         .get("tokens")
         .ok_or_else(|| LibationError::InvalidApiResponse {
             message: "Tokens not found in registration response".to_string(),
-            response_body: Some(register_response.to_string()),
+            response_body: Some(redact_json(&register_response).to_string()),
         })?;
 
     let extensions =
@@ -1465,21 +1489,21 @@ pub async fn exchange_authorization_code(
             .get("extensions")
             .ok_or_else(|| LibationError::InvalidApiResponse {
                 message: "Extensions not found in registration response".to_string(),
-                response_body: Some(register_response.to_string()),
+                response_body: Some(redact_json(&register_response).to_string()),
             })?;
 
     // Parse bearer tokens
     let bearer: BearerTokenInfo = serde_json::from_value(tokens.get("bearer").unwrap().clone())
         .map_err(|e| LibationError::InvalidApiResponse {
             message: format!("Failed to parse bearer tokens: {}", e),
-            response_body: Some(tokens.to_string()),
+            response_body: Some(redact_json(tokens).to_string()),
         })?;
 
     // Parse MAC-DMS tokens
     let mac_dms: MacDmsTokenInfo = serde_json::from_value(tokens.get("mac_dms").unwrap().clone())
         .map_err(|e| LibationError::InvalidApiResponse {
         message: format!("Failed to parse mac_dms tokens: {}", e),
-        response_body: Some(tokens.to_string()),
+        response_body: Some(redact_json(tokens).to_string()),
     })?;
 
     // Parse website cookies
@@ -1487,7 +1511,7 @@ pub async fn exchange_authorization_code(
         serde_json::from_value(tokens.get("website_cookies").unwrap().clone()).map_err(|e| {
             LibationError::InvalidApiResponse {
                 message: format!("Failed to parse website_cookies: {}", e),
-                response_body: Some(tokens.to_string()),
+                response_body: Some(redact_json(tokens).to_string()),
             }
         })?;
 
@@ -1496,7 +1520,7 @@ pub async fn exchange_authorization_code(
         serde_json::from_value(tokens.get("store_authentication_cookie").unwrap().clone())
             .map_err(|e| LibationError::InvalidApiResponse {
                 message: format!("Failed to parse store_authentication_cookie: {}", e),
-                response_body: Some(tokens.to_string()),
+                response_body: Some(redact_json(tokens).to_string()),
             })?;
 
     // Parse device info
@@ -1504,7 +1528,7 @@ pub async fn exchange_authorization_code(
         serde_json::from_value(extensions.get("device_info").unwrap().clone()).map_err(|e| {
             LibationError::InvalidApiResponse {
                 message: format!("Failed to parse device_info: {}", e),
-                response_body: Some(extensions.to_string()),
+                response_body: Some(redact_json(extensions).to_string()),
             }
         })?;
 
@@ -1513,7 +1537,7 @@ pub async fn exchange_authorization_code(
         serde_json::from_value(extensions.get("customer_info").unwrap().clone()).map_err(|e| {
             LibationError::InvalidApiResponse {
                 message: format!("Failed to parse customer_info: {}", e),
-                response_body: Some(extensions.to_string()),
+                response_body: Some(redact_json(extensions).to_string()),
             }
         })?;
 
@@ -1652,11 +1676,39 @@ pub async fn ensure_valid_token(
     account_json: &str,
     refresh_threshold_minutes: i64,
 ) -> Result<String> {
-    use crate::storage::accounts::save_account;
+    ensure_valid_token_with_clock(pool, account_json, refresh_threshold_minutes, &AppClock).await
+}
+
+/// `ensure_valid_token` against a given clock
+pub async fn ensure_valid_token_with_clock(
+    pool: &SqlitePool,
+    account_json: &str,
+    refresh_threshold_minutes: i64,
+    clock: &dyn Clock,
+) -> Result<String> {
+    use crate::storage::accounts::{
+        clear_pending_token_refresh, get_pending_token_refresh, record_pending_token_refresh,
+        save_refreshed_account, PendingTokenRefresh,
+    };
     use chrono::Duration;
 
     // Parse account JSON
-    let mut account: Account = serde_json::from_str(account_json)
+    let mut account_value: serde_json::Value = serde_json::from_str(account_json)
+        .map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
+
+    // An earlier refresh may have been cut off before its tokens were saved;
+    // the caller's copy then still holds the old (possibly revoked) tokens
+    let mut recovered = false;
+    if let Some(pending) = get_pending_token_refresh(pool, &account_id_of(&account_value)).await? {
+        recovered = pending.apply_to(&mut account_value);
+        if recovered {
+            save_refreshed_account(pool, &pending.account_id, &account_value.to_string()).await?;
+        } else {
+            clear_pending_token_refresh(pool, &pending.account_id).await?;
+        }
+    }
+
+    let mut account: Account = serde_json::from_value(account_value)
         .map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
 
     // Check if we have identity with access token
@@ -1667,13 +1719,13 @@ pub async fn ensure_valid_token(
     })?;
 
     let expires_at = identity.access_token.expires_at;
-    let now = chrono::Utc::now();
+    let now = clock.now();
     let threshold = Duration::minutes(refresh_threshold_minutes);
     let refresh_by = expires_at - threshold;
 
     // Check if token is expired or expiring soon
     if now >= refresh_by {
-        eprintln!(
+        trace_eprintln!(
             "🔄 Access token for account '{}' is expiring soon (expires: {}, refresh by: {}). Refreshing...",
             account.account_id,
             expires_at.to_rfc3339(),
@@ -1686,14 +1738,27 @@ pub async fn ensure_valid_token(
         let device_serial = identity.device_serial_number.clone();
 
         // Refresh token
-        let token_response = refresh_access_token(&locale, &refresh_token, &device_serial).await?;
-
-        // Update account with new tokens
-        let identity_mut = account.identity.as_mut().unwrap();
+        let token_response = refresh_access_token(&locale, refresh_token.expose_secret(), &device_serial).await?;
 
         // Calculate expiry time from expires_in (seconds)
         let expires_at = now + Duration::seconds(token_response.expires_in);
 
+        // Record the tokens before anything else, so they survive the app
+        // dying before the account is saved
+        record_pending_token_refresh(
+            pool,
+            &PendingTokenRefresh {
+                account_id: account.account_id.clone(),
+                access_token: token_response.access_token.clone(),
+                expires_at: crate::storage::dates::format_timestamp(expires_at),
+                refresh_token: token_response.refresh_token.clone().filter(|t| !t.is_empty()),
+            },
+        )
+        .await?;
+
+        // Update account with new tokens
+        let identity_mut = account.identity.as_mut().unwrap();
+
         // Update access token
         identity_mut.access_token = AccessToken {
             token: token_response.access_token.clone(),
@@ -1701,9 +1766,9 @@ pub async fn ensure_valid_token(
         };
 
         // Update refresh token if Amazon returned a new one
-        if let Some(new_refresh_token) = token_response.refresh_token {
+        if let Some(new_refresh_token) = token_response.refresh_token.filter(|t| !t.is_empty()) {
             identity_mut.refresh_token = new_refresh_token;
-            eprintln!("🔑 Received new refresh token from Amazon");
+            trace_eprintln!("🔑 Received new refresh token from Amazon");
         }
 
         // Serialize updated account
@@ -1715,10 +1780,10 @@ pub async fn ensure_valid_token(
         let new_expiry_str = expires_at.to_rfc3339();
         let account_id = account.account_id.clone();
 
-        // Save to database
-        save_account(pool, &account_id, &updated_json).await?;
+        // Save to database (clears the pending record)
+        save_refreshed_account(pool, &account_id, &updated_json).await?;
 
-        eprintln!(
+        trace_eprintln!(
             "✅ Access token refreshed for account '{}'. New expiry: {}",
             account_id, new_expiry_str
         );
@@ -1727,16 +1792,26 @@ pub async fn ensure_valid_token(
     } else {
         // Token is still valid
         let time_until_expiry = expires_at - now;
-        eprintln!(
+        trace_eprintln!(
             "✓ Access token for account '{}' is still valid (expires in {} minutes)",
             account.account_id,
             time_until_expiry.num_minutes()
         );
 
+        if recovered {
+            return serde_json::to_string(&account).map_err(|e| {
+                LibationError::InvalidState(format!("Failed to serialize account: {}", e))
+            });
+        }
         Ok(account_json.to_string())
     }
 }
 
+/// `account_id` of an account JSON value ("" when missing)
+fn account_id_of(account: &serde_json::Value) -> String {
+    account["account_id"].as_str().unwrap_or_default().to_string()
+}
+
 /// Register a new device with Audible
 ///
 /// Device registration generates a private key and registers the device
@@ -1883,8 +1958,11 @@ pub async fn get_activation_bytes(locale: &Locale, access_token: &str) -> Result
 /// * `account` - The account to deregister
 ///
 /// # Errors
-/// Returns error if deregistration fails
+/// Returns error if deregistration fails, PermissionDenied if
+/// `deregister_device` is disabled (see `permissions`)
 pub async fn deregister_device(account: &Account) -> Result<()> {
+    crate::permissions::require(crate::permissions::Permission::DeregisterDevice)?;
+
     let identity =
         account
             .identity
@@ -1904,7 +1982,7 @@ pub async fn deregister_device(account: &Account) -> Result<()> {
         .delete(&api_url)
         .header(
             "Authorization",
-            format!("Bearer {}", identity.access_token.token),
+            format!("Bearer {}", identity.access_token.token.expose_secret()),
         )
         .send()
         .await
@@ -1972,7 +2050,7 @@ mod tests {
     fn test_set_decrypt_key() {
         let mut account = Account::new("test@example.com".to_string()).unwrap();
         account.set_decrypt_key("1a2b3c4d".to_string());
-        assert_eq!(account.decrypt_key, "1a2b3c4d");
+        assert_eq!(account.decrypt_key.expose_secret(), "1a2b3c4d");
     }
 
     #[test]
@@ -1997,7 +2075,7 @@ mod tests {
     fn test_needs_token_refresh_expired() {
         let mut account = Account::new("test@example.com".to_string()).unwrap();
         let expired_token = AccessToken {
-            token: "test_token".to_string(),
+            token: "test_token".into(),
             expires_at: Utc::now() - chrono::Duration::hours(1),
         };
         let identity = Identity::new(
@@ -2049,7 +2127,7 @@ mod tests {
     #[test]
     fn test_identity_is_expired() {
         let expired_token = AccessToken {
-            token: "test".to_string(),
+            token: "test".into(),
             expires_at: Utc::now() - chrono::Duration::hours(1),
         };
         let identity = Identity::new(
@@ -2065,7 +2143,7 @@ mod tests {
     #[test]
     fn test_identity_not_expired() {
         let valid_token = AccessToken {
-            token: "test".to_string(),
+            token: "test".into(),
             expires_at: Utc::now() + chrono::Duration::hours(1),
         };
         let identity = Identity::new(
@@ -2505,7 +2583,7 @@ mod tests {
                         // Step 6: Get activation bytes
                         println!("🔓 Retrieving activation bytes...\n");
 
-                        match get_activation_bytes(&locale, &token_response.bearer.access_token)
+                        match get_activation_bytes(&locale, token_response.bearer.access_token.expose_secret())
                             .await
                         {
                             Ok(_) => {
@@ -2565,21 +2643,21 @@ mod tests {
             account_id: "test@example.com".to_string(),
             account_name: "Test Account".to_string(),
             library_scan: true,
-            decrypt_key: String::new(),
+            decrypt_key: SecretString::default(),
             identity: Some(Identity {
                 access_token: AccessToken {
-                    token: "test_token".to_string(),
+                    token: "test_token".into(),
                     expires_at,
                 },
-                refresh_token: "test_refresh".to_string(),
-                device_private_key: "test_key".to_string(),
-                adp_token: "test_adp".to_string(),
+                refresh_token: "test_refresh".into(),
+                device_private_key: "test_key".into(),
+                adp_token: "test_adp".into(),
                 cookies: HashMap::new(),
                 device_serial_number: "test_serial".to_string(),
                 device_type: "test_type".to_string(),
                 device_name: "test_device".to_string(),
                 amazon_account_id: "test_amazon_id".to_string(),
-                store_authentication_cookie: "test_cookie".to_string(),
+                store_authentication_cookie: "test_cookie".into(),
                 locale: Locale::us(),
                 customer_info: CustomerInfo {
                     account_pool: "test_pool".to_string(),
@@ -2611,7 +2689,7 @@ mod tests {
 
         // Token should be unchanged
         assert_eq!(
-            result_account.identity.unwrap().access_token.token,
+            result_account.identity.unwrap().access_token.token.expose_secret(),
             "test_token"
         );
     }
@@ -2631,21 +2709,21 @@ mod tests {
             account_id: "test@example.com".to_string(),
             account_name: "Test Account".to_string(),
             library_scan: true,
-            decrypt_key: String::new(),
+            decrypt_key: SecretString::default(),
             identity: Some(Identity {
                 access_token: AccessToken {
-                    token: "old_token".to_string(),
+                    token: "old_token".into(),
                     expires_at,
                 },
-                refresh_token: "valid_refresh_token".to_string(), // Would need real token
-                device_private_key: "test_key".to_string(),
-                adp_token: "test_adp".to_string(),
+                refresh_token: "valid_refresh_token".into(), // Would need real token
+                device_private_key: "test_key".into(),
+                adp_token: "test_adp".into(),
                 cookies: HashMap::new(),
                 device_serial_number: "test_serial".to_string(),
                 device_type: "test_type".to_string(),
                 device_name: "test_device".to_string(),
                 amazon_account_id: "test_amazon_id".to_string(),
-                store_authentication_cookie: "test_cookie".to_string(),
+                store_authentication_cookie: "test_cookie".into(),
                 locale: Locale::us(),
                 customer_info: CustomerInfo {
                     account_pool: "test_pool".to_string(),
@@ -2674,4 +2752,88 @@ mod tests {
         // Would fail with invalid credentials, which is expected
         assert!(result.is_err());
     }
+
+    /// A refresh cut off before saving is picked up by the next token check
+    #[tokio::test]
+    async fn test_ensure_valid_token_recovers_pending_refresh() {
+        use crate::storage::accounts::{get_account, get_pending_token_refresh, record_pending_token_refresh, save_account, PendingTokenRefresh};
+        use crate::storage::Database;
+
+        let db = Database::new_in_memory().await.unwrap();
+        let mut account = Account::new("test@example.com".to_string()).unwrap();
+        account.set_identity(Identity::new(
+            AccessToken {
+                token: "old_token".into(),
+                expires_at: Utc::now() + chrono::Duration::hours(2),
+            },
+            "old_refresh".to_string(),
+            "key".to_string(),
+            "adp".to_string(),
+            Locale::us(),
+        ));
+        let account_json = serde_json::to_string(&account).unwrap();
+        save_account(db.pool(), &account.account_id, &account_json).await.unwrap();
+
+        record_pending_token_refresh(
+            db.pool(),
+            &PendingTokenRefresh {
+                account_id: account.account_id.clone(),
+                access_token: "new_token".into(),
+                expires_at: (Utc::now() + chrono::Duration::hours(3)).to_rfc3339(),
+                refresh_token: Some("new_refresh".into()),
+            },
+        )
+        .await
+        .unwrap();
+
+        // The caller still holds the old tokens
+        let result = ensure_valid_token(db.pool(), &account_json, 30).await.unwrap();
+        let identity = serde_json::from_str::<Account>(&result).unwrap().identity.unwrap();
+        assert_eq!(identity.access_token.token.expose_secret(), "new_token");
+        assert_eq!(identity.refresh_token.expose_secret(), "new_refresh");
+
+        assert!(get_pending_token_refresh(db.pool(), &account.account_id).await.unwrap().is_none());
+        let stored = get_account(db.pool(), &account.account_id).await.unwrap().unwrap();
+        assert!(stored.contains("new_refresh"));
+    }
+
+    #[tokio::test]
+    async fn test_token_expiry_time_travel() {
+        use crate::clock::TestClock;
+        use crate::storage::Database;
+
+        let clock = TestClock::new(Utc::now());
+        let mut account = Account::new("test@example.com".to_string()).unwrap();
+        let token = AccessToken {
+            token: "test_token".into(),
+            expires_at: clock.now() + chrono::Duration::hours(1),
+        };
+        account.set_identity(Identity::new(
+            token,
+            "refresh".to_string(),
+            "key".to_string(),
+            "adp".to_string(),
+            Locale::us(),
+        ));
+        let identity = account.identity.clone().unwrap();
+
+        assert!(!account.needs_token_refresh_with_clock(&clock));
+        assert!(!identity.is_expired_with_clock(&clock));
+
+        // A valid token is returned untouched (no refresh attempted)
+        let db = Database::new_in_memory().await.unwrap();
+        let mut account_json_value = serde_json::to_value(&account).unwrap();
+        account_json_value["locale"] = serde_json::json!({"country_code": "us"});
+        let account_json = account_json_value.to_string();
+        let result = ensure_valid_token_with_clock(db.pool(), &account_json, 30, &clock).await.unwrap();
+        assert_eq!(result, account_json);
+
+        // Inside the 5 minute refresh buffer, not yet expired
+        clock.advance(chrono::Duration::minutes(56));
+        assert!(account.needs_token_refresh_with_clock(&clock));
+        assert!(!identity.is_expired_with_clock(&clock));
+
+        clock.advance(chrono::Duration::minutes(4));
+        assert!(identity.is_expired_with_clock(&clock));
+    }
 }
@@ -58,12 +58,16 @@
 //! # Architecture
 //!
 //! ## Client Structure
-//! The `AudibleClient` wraps `reqwest::Client` and provides:
+//! The `AudibleClient` sends requests through an `HttpTransport` (see
+//! `transport`; `reqwest` by default) and provides:
 //! - Base URL management per Audible domain (.com, .co.uk, .de, etc.)
 //! - Cookie jar for session persistence
 //! - Custom headers (User-Agent, Accept, Authorization)
 //! - Timeout and connection pooling configuration
 //!
+//! Tests build the client with `AudibleClient::with_transport` and a
+//! `MockTransport` to exercise retry and parsing without a network.
+//!
 //! ## Retry Strategy (Ported from Polly - ApiExtended.cs:70-73)
 //! ```csharp
 //! // C# Reference: ApiExtended.cs
@@ -93,12 +97,15 @@
 //! ```
 
 use crate::api::auth::{Account, Identity, Locale};
+use crate::api::recording::{self, RecordingTransport, ReplayOperation};
+use crate::api::transport::{HttpRequest, HttpResponse, HttpTransport, ReqwestTransport};
 use crate::error::{LibationError, Result};
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
-use reqwest::{Client, Method, Request, Response, StatusCode};
+use reqwest::{Method, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{Mutex, Semaphore};
@@ -123,6 +130,20 @@ const INITIAL_RETRY_DELAY_SECS: u64 = 1;
 /// Reference: NetworkFileStream.cs uses HttpClient default (100 seconds)
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
+lazy_static::lazy_static! {
+    /// Time of the last successful API call per account in this process
+    static ref LAST_API_SUCCESS: std::sync::Mutex<HashMap<String, chrono::DateTime<chrono::Utc>>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
+/// Time of the last successful API call for an account since the app started
+///
+/// Bridges persist this with `storage::record_api_success` when reporting
+/// account token info.
+pub fn last_api_success(account_id: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    LAST_API_SUCCESS.lock().unwrap().get(account_id).copied()
+}
+
 /// Supported Audible API domains
 /// Reference: Cdm.Api.cs:127
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -221,6 +242,8 @@ pub struct ClientConfig {
     pub max_retries: u32,
     pub user_agent: String,
     pub enable_cookies: bool,
+    /// Delay before the first retry; doubles for each further retry
+    pub retry_delay: Duration,
 }
 
 impl Default for ClientConfig {
@@ -231,6 +254,7 @@ impl Default for ClientConfig {
             max_retries: MAX_RETRY_ATTEMPTS,
             user_agent: "Libation/11.3.0 (rust-core)".to_string(),
             enable_cookies: true,
+            retry_delay: Duration::from_secs(INITIAL_RETRY_DELAY_SECS),
         }
     }
 }
@@ -279,6 +303,11 @@ impl ClientConfigBuilder {
         self
     }
 
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.config.retry_delay = delay;
+        self
+    }
+
     pub fn build(self) -> ClientConfig {
         self.config
     }
@@ -314,10 +343,14 @@ impl ClientConfigBuilder {
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Debug)]
+///
+/// Clones share the account, connection pool and concurrency limit.
+#[derive(Debug, Clone)]
 pub struct AudibleClient {
-    /// Underlying HTTP client
-    client: Client,
+    /// Sends the built requests
+    transport: Arc<dyn HttpTransport>,
+    /// User-Agent header sent with every request
+    user_agent: HeaderValue,
     /// Account information with authentication tokens
     account: Arc<Mutex<Account>>,
     /// API base URL (e.g., https://api.audible.com)
@@ -327,6 +360,8 @@ pub struct AudibleClient {
     /// Semaphore for concurrency control
     /// Reference: ApiExtended.cs:23 (MaxConcurrency = 10)
     semaphore: Arc<Semaphore>,
+    /// Records exchanges while API recording is on (see `recording`)
+    recorder: Option<Arc<RecordingTransport>>,
 }
 
 impl AudibleClient {
@@ -356,6 +391,27 @@ impl AudibleClient {
     /// # Errors
     /// Returns error if HTTP client cannot be built
     pub fn with_config(account: Account, config: ClientConfig) -> Result<Self> {
+        // Reference: Cdm.Api.cs:46, NetworkFileStream.cs:169
+        // Reference: NetworkFileStream.cs:29 (RequestHeaders for cookies)
+        let transport = Arc::new(ReqwestTransport::new(config.timeout, config.enable_cookies)?);
+        let client = Self::with_transport(account, config, transport.clone())?;
+        Ok(match recording::recording_directory() {
+            Some(_) => client.with_recorder(Arc::new(RecordingTransport::new(transport))),
+            None => client,
+        })
+    }
+
+    /// Create a client that sends requests through the given transport
+    ///
+    /// # Arguments
+    /// * `account` - Account with valid authentication tokens
+    /// * `config` - Client configuration; `timeout` and `enable_cookies`
+    ///   are up to the transport
+    /// * `transport` - Sends the requests (e.g. `MockTransport` in tests)
+    ///
+    /// # Errors
+    /// Returns error if the account has no id or the user agent is invalid
+    pub fn with_transport(account: Account, config: ClientConfig, transport: Arc<dyn HttpTransport>) -> Result<Self> {
         // Validate account fields
         // Reference: ApiExtended.cs:31-33 (ArgumentValidator checks)
         if account.account_id.is_empty() {
@@ -364,29 +420,8 @@ impl AudibleClient {
             ));
         }
 
-        // Build HTTP client with configuration
-        // Reference: Cdm.Api.cs:46, NetworkFileStream.cs:169
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            USER_AGENT,
-            HeaderValue::from_str(&config.user_agent)
-                .map_err(|e| LibationError::InvalidInput(format!("Invalid user agent: {}", e)))?,
-        );
-        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
-
-        let mut client_builder = Client::builder()
-            .timeout(config.timeout)
-            .default_headers(headers)
-            .pool_max_idle_per_host(10) // Connection pooling
-            .pool_idle_timeout(Duration::from_secs(90));
-
-        // Enable cookie store if configured
-        // Reference: NetworkFileStream.cs:29 (RequestHeaders for cookies)
-        if config.enable_cookies {
-            client_builder = client_builder.cookie_store(true);
-        }
-
-        let client = client_builder.build()?;
+        let user_agent = HeaderValue::from_str(&config.user_agent)
+            .map_err(|e| LibationError::InvalidInput(format!("Invalid user agent: {}", e)))?;
 
         // Determine base URL from account locale or config domain
         // Reference: Cdm.Api.cs:141 (api.audible.{tld})
@@ -399,11 +434,13 @@ impl AudibleClient {
         let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENCY));
 
         Ok(Self {
-            client,
+            transport,
+            user_agent,
             account: Arc::new(Mutex::new(account)),
             base_url,
             config,
             semaphore,
+            recorder: None,
         })
     }
 
@@ -417,6 +454,49 @@ impl AudibleClient {
         &self.base_url
     }
 
+    /// A client for another Audible marketplace sharing this one's account,
+    /// connection pool and concurrency limit
+    ///
+    /// Catalog data (titles, descriptions) is localized per marketplace.
+    pub fn for_marketplace(&self, locale: &Locale) -> Self {
+        Self {
+            transport: Arc::clone(&self.transport),
+            user_agent: self.user_agent.clone(),
+            account: Arc::clone(&self.account),
+            base_url: locale.api_url(),
+            config: self.config.clone(),
+            semaphore: Arc::clone(&self.semaphore),
+            recorder: self.recorder.clone(),
+        }
+    }
+
+    /// Send through `recorder`, whose exchanges `save_recording` saves
+    pub fn with_recorder(mut self, recorder: Arc<RecordingTransport>) -> Self {
+        self.transport = recorder.clone();
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Save this client's exchanges as a replay bundle if `result` is an
+    /// error and API recording is on
+    ///
+    /// # Returns
+    /// Path of the bundle, None if nothing was saved
+    pub fn save_recording<T>(&self, operation: ReplayOperation, result: &Result<T>) -> Result<Option<PathBuf>> {
+        let (Some(recorder), Some(directory), Err(error)) = (&self.recorder, recording::recording_directory(), result) else {
+            return Ok(None);
+        };
+
+        // The marketplace this client talks to, which `for_marketplace` may
+        // have changed from the account's
+        let marketplace = Locale::all()
+            .into_iter()
+            .find(|locale| locale.api_url() == self.base_url)
+            .map_or_else(|| "us".to_string(), |locale| locale.country_code);
+        let bundle = recorder.bundle(operation, &marketplace, Some(error));
+        Ok(Some(bundle.save(&directory)?))
+    }
+
     /// Perform a GET request
     ///
     /// # Arguments
@@ -447,9 +527,15 @@ impl AudibleClient {
         T: serde::de::DeserializeOwned,
         Q: Serialize,
     {
-        let url = format!("{}{}", self.base_url, endpoint);
-        self.request_with_retry(|client, headers| client.get(&url).query(query).headers(headers))
-            .await
+        let query = serde_urlencoded::to_string(query)
+            .map_err(|e| LibationError::InvalidInput(format!("Invalid query parameters: {}", e)))?;
+        let mut url = format!("{}{}", self.base_url, endpoint);
+        if !query.is_empty() {
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(&query);
+        }
+
+        self.request_with_retry(HttpRequest::new(Method::GET, url)).await
     }
 
     /// Perform a POST request with JSON body
@@ -471,6 +557,33 @@ impl AudibleClient {
         self.request(Method::POST, endpoint, Some(body)).await
     }
 
+    /// Perform a PUT request with JSON body
+    ///
+    /// # Arguments
+    /// * `endpoint` - API endpoint path
+    /// * `body` - Request body to serialize as JSON
+    ///
+    /// # Returns
+    /// Deserialized JSON response of type `T` (an empty body reads as `null`)
+    pub async fn put<T, B>(&self, endpoint: &str, body: B) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        B: Serialize,
+    {
+        self.request(Method::PUT, endpoint, Some(body)).await
+    }
+
+    /// Perform a GET request to an absolute URL outside the API host
+    ///
+    /// Sent with the account's auth headers and the usual retries, for the
+    /// few Amazon endpoints the apps call directly (see `annotations`).
+    pub async fn get_url<T>(&self, url: &str) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.request_with_retry(HttpRequest::new(Method::GET, url.to_string())).await
+    }
+
     /// Perform a POST request with form data
     ///
     /// # Arguments
@@ -483,9 +596,17 @@ impl AudibleClient {
     where
         T: serde::de::DeserializeOwned,
     {
-        let url = format!("{}{}", self.base_url, endpoint);
-        self.request_with_retry(|client, headers| client.post(&url).headers(headers).form(form))
-            .await
+        let body = serde_urlencoded::to_string(form)
+            .map_err(|e| LibationError::InvalidInput(format!("Invalid form data: {}", e)))?;
+
+        let mut request = HttpRequest::new(Method::POST, format!("{}{}", self.base_url, endpoint));
+        request.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        request.body = Some(body.into_bytes());
+
+        self.request_with_retry(request).await
     }
 
     /// Generic HTTP request with automatic token refresh and retry logic
@@ -509,18 +630,14 @@ impl AudibleClient {
         T: serde::de::DeserializeOwned,
         B: Serialize,
     {
-        let url = format!("{}{}", self.base_url, endpoint);
+        let mut request = HttpRequest::new(method, format!("{}{}", self.base_url, endpoint));
 
-        self.request_with_retry(|client, headers| {
-            let mut req_builder = client.request(method.clone(), &url).headers(headers);
-
-            if let Some(ref b) = body {
-                req_builder = req_builder.json(b);
-            }
+        if let Some(ref b) = body {
+            request.headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            request.body = Some(serde_json::to_vec(b)?);
+        }
 
-            req_builder
-        })
-        .await
+        self.request_with_retry(request).await
     }
 
     /// Execute request with retry logic and exponential backoff
@@ -543,10 +660,9 @@ impl AudibleClient {
     /// No retry on:
     /// - 4xx client errors (except 401, 429)
     /// - Successful responses (2xx)
-    async fn request_with_retry<T, F>(&self, request_builder: F) -> Result<T>
+    async fn request_with_retry<T>(&self, request: HttpRequest) -> Result<T>
     where
         T: serde::de::DeserializeOwned,
-        F: Fn(&Client, HeaderMap) -> reqwest::RequestBuilder,
     {
         let mut attempts = 0;
         let mut last_error = None;
@@ -569,17 +685,20 @@ impl AudibleClient {
                 }
             };
 
-            // Build and send request
-            let request = request_builder(&self.client, headers).build()?;
+            // Send with this attempt's headers
+            let mut attempt = request.clone();
+            attempt.headers.extend(headers);
 
-            match self.client.execute(request).await {
+            match self.transport.send(attempt).await {
                 Ok(response) => {
-                    let status = response.status();
+                    let status = response.status;
 
                     match status {
                         // Success - parse and return response
                         s if s.is_success() => {
-                            return self.handle_success_response(response).await;
+                            let account_id = self.account.lock().await.account_id.clone();
+                            LAST_API_SUCCESS.lock().unwrap().insert(account_id, chrono::Utc::now());
+                            return self.handle_success_response(response);
                         }
 
                         // 401 Unauthorized - try token refresh once
@@ -599,58 +718,41 @@ impl AudibleClient {
                             let retry_after = self.extract_retry_after(&response);
                             return Err(LibationError::RateLimitExceeded {
                                 retry_after_seconds: retry_after,
-                                endpoint: self.extract_endpoint_from_url(response.url().as_str()),
+                                endpoint: self.extract_endpoint_from_url(&response.url),
                             });
                         }
 
                         // 5xx Server Error - retry with backoff
                         s if s.is_server_error() && attempts < self.config.max_retries => {
-                            let endpoint = self.extract_endpoint_from_url(response.url().as_str());
-                            let error_body = response.text().await.unwrap_or_default();
                             last_error = Some(LibationError::api_failed(
-                                format!("Server error: {}", error_body),
+                                format!("Server error: {}", response.text()),
                                 Some(status.as_u16()),
-                                Some(endpoint),
+                                Some(self.extract_endpoint_from_url(&response.url)),
                             ));
 
                             // Exponential backoff: 1s, 2s, 4s...
-                            let delay = Duration::from_secs(
-                                INITIAL_RETRY_DELAY_SECS * 2_u64.pow(attempts - 1),
-                            );
-                            sleep(delay).await;
+                            sleep(self.retry_delay(attempts)).await;
                             continue;
                         }
 
                         // Other errors - don't retry
                         _ => {
-                            return self.handle_error_response(response).await;
+                            return self.handle_error_response(response);
                         }
                     }
                 }
 
-                // Network error - retry with backoff
-                Err(e)
-                    if attempts < self.config.max_retries
-                        && self.is_retryable_network_error(&e) =>
+                // Transient network error - retry with backoff
+                Err(e @ LibationError::NetworkError { is_transient: true, .. })
+                    if attempts < self.config.max_retries =>
                 {
-                    last_error = Some(LibationError::network_error(
-                        format!("Network request failed: {}", e),
-                        true,
-                    ));
-
-                    let delay =
-                        Duration::from_secs(INITIAL_RETRY_DELAY_SECS * 2_u64.pow(attempts - 1));
-                    sleep(delay).await;
+                    last_error = Some(e);
+                    sleep(self.retry_delay(attempts)).await;
                     continue;
                 }
 
                 // Non-retryable network error
-                Err(e) => {
-                    return Err(LibationError::network_error(
-                        format!("Network request failed: {}", e),
-                        false,
-                    ));
-                }
+                Err(e) => return Err(e),
             }
         }
 
@@ -664,16 +766,23 @@ impl AudibleClient {
         )
     }
 
-    /// Build authentication headers from account tokens
+    /// Backoff before retrying after `attempts` failed attempts
+    fn retry_delay(&self, attempts: u32) -> Duration {
+        self.config.retry_delay * 2_u32.pow(attempts - 1)
+    }
+
+    /// Build request headers: user agent, accept and the account's token
     ///
     /// # Reference
     /// Based on Cdm.Api.cs:162-163 (Add headers to request)
     async fn build_auth_headers(&self) -> Result<HeaderMap> {
         let account = self.account.lock().await;
         let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, self.user_agent.clone());
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
 
         if let Some(ref identity) = account.identity {
-            let auth_value = format!("Bearer {}", identity.access_token.token);
+            let auth_value = format!("Bearer {}", identity.access_token.token.expose_secret());
             headers.insert(
                 AUTHORIZATION,
                 HeaderValue::from_str(&auth_value).map_err(|e| {
@@ -686,31 +795,23 @@ impl AudibleClient {
     }
 
     /// Handle successful HTTP response
-    async fn handle_success_response<T>(&self, response: Response) -> Result<T>
+    fn handle_success_response<T>(&self, response: HttpResponse) -> Result<T>
     where
         T: serde::de::DeserializeOwned,
     {
-        let status = response.status();
-        let url = response.url().clone();
-
-        // Get response text first so we can log it on parse error
-        let response_text = response
-            .text()
-            .await
-            .map_err(|e| LibationError::ApiRequestFailed {
-                message: format!("Failed to read response body: {}", e),
-                status_code: Some(status.as_u16()),
-                endpoint: Some(url.path().to_string()),
-            })?;
-
-        match serde_json::from_str::<T>(&response_text) {
+        // Keep the text so it can be logged on parse error
+        let response_text = response.text();
+        // Some writes answer 204 or 200 without a body
+        let json = if response_text.trim().is_empty() { "null" } else { response_text.as_str() };
+
+        match serde_json::from_str::<T>(json) {
             Ok(data) => Ok(data),
             Err(e) => {
                 // Extract context around the error location (800 chars)
                 let error_col = e.column();
                 let start = error_col.saturating_sub(400);
                 let end = (error_col + 400).min(response_text.len());
-                let context = &response_text[start..end];
+                let context = response_text.get(start..end).unwrap_or_default();
 
                 Err(LibationError::InvalidApiResponse {
                     message: format!(
@@ -724,31 +825,18 @@ impl AudibleClient {
     }
 
     /// Handle error HTTP response
-    async fn handle_error_response<T>(&self, response: Response) -> Result<T> {
-        let status = response.status();
-        let url = response.url().clone();
-        let error_body = response.text().await.unwrap_or_default();
-
+    fn handle_error_response<T>(&self, response: HttpResponse) -> Result<T> {
         Err(LibationError::api_failed(
-            format!("API request failed: {}", error_body),
-            Some(status.as_u16()),
-            Some(self.extract_endpoint_from_url(url.as_str())),
+            format!("API request failed: {}", response.text()),
+            Some(response.status.as_u16()),
+            Some(self.extract_endpoint_from_url(&response.url)),
         ))
     }
 
-    /// Check if a network error is retryable
-    ///
-    /// Reference: ApiExtended.cs:70 (Policy.Handle<Exception>() - retries all exceptions)
-    fn is_retryable_network_error(&self, error: &reqwest::Error) -> bool {
-        error.is_timeout() || error.is_connect() || error.is_request()
-    }
-
     /// Extract retry-after delay from response headers (in seconds)
-    fn extract_retry_after(&self, response: &Response) -> u64 {
+    fn extract_retry_after(&self, response: &HttpResponse) -> u64 {
         response
-            .headers()
-            .get("retry-after")
-            .and_then(|v| v.to_str().ok())
+            .header("retry-after")
             .and_then(|s| s.parse::<u64>().ok())
             .unwrap_or(60) // Default to 60 seconds
     }
@@ -792,34 +880,24 @@ impl AudibleClient {
             LibationError::InternalError(format!("Semaphore acquire failed: {}", e))
         })?;
 
-        let headers = self.build_auth_headers().await?;
-        let response = self.client.get(url).headers(headers).send().await?;
+        let mut request = HttpRequest::new(Method::GET, url);
+        request.headers = self.build_auth_headers().await?;
+        let response = self.transport.send(request).await?;
 
-        if !response.status().is_success() {
+        if !response.status.is_success() {
             return Err(LibationError::DownloadFailed(format!(
                 "Download failed with status: {}",
-                response.status()
+                response.status
             )));
         }
 
-        let total_size = response.content_length().unwrap_or(0);
-        let mut downloaded: u64 = 0;
-        let mut buffer = Vec::with_capacity(total_size as usize);
-
-        let mut stream = response.bytes_stream();
-        use futures_util::StreamExt; // For stream.next()
-
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            buffer.extend_from_slice(&chunk);
-            downloaded += chunk.len() as u64;
-
-            if let Some(ref mut callback) = progress_callback {
-                callback(downloaded, total_size);
-            }
+        // The transport reads the whole body, so progress is reported once
+        if let Some(ref mut callback) = progress_callback {
+            let size = response.body.len() as u64;
+            callback(size, size);
         }
 
-        Ok(buffer)
+        Ok(response.body)
     }
 
     /// Get account reference (for reading account info)
@@ -833,6 +911,7 @@ impl AudibleClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::transport::{mock_client, MockTransport};
 
     #[test]
     fn test_audible_domain_from_str() {
@@ -877,13 +956,95 @@ mod tests {
         assert_eq!(config.enable_cookies, false);
     }
 
+    #[tokio::test]
+    async fn test_request_headers_and_query() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(200, serde_json::json!({ "items": [1, 2] }));
+        let client = mock_client(&transport);
+
+        let query = [("num_results", "50"), ("response_groups", "product_desc,series")];
+        let response: Value = client.get_with_query("/1.0/library", &query).await.unwrap();
+        assert_eq!(response["items"].as_array().unwrap().len(), 2);
+
+        let request = &transport.requests()[0];
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(
+            request.url,
+            "https://api.audible.com/1.0/library?num_results=50&response_groups=product_desc%2Cseries"
+        );
+        assert_eq!(request.header("authorization"), Some("Bearer test_token"));
+        assert_eq!(request.header("user-agent"), Some("TestAgent/1.0"));
+        assert!(last_api_success("test@example.com").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_and_transient_errors() {
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(503, serde_json::json!({ "message": "busy" }));
+        transport.push_network_error(true);
+        transport.push_json(200, serde_json::json!({ "ok": true }));
+        let client = mock_client(&transport);
+
+        let body = serde_json::json!({ "asin": "B001" });
+        let response: Value = client.post("/1.0/content/B001/licenserequest", body).await.unwrap();
+        assert_eq!(response["ok"], true);
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].header("content-type"), Some("application/json"));
+        assert_eq!(requests[2].body.as_deref(), Some(br#"{"asin":"B001"}"#.as_slice()));
+
+        // Non-transient failures and exhausted retries are returned as-is
+        transport.push_network_error(false);
+        assert!(matches!(
+            client.get::<Value>("/1.0/library").await,
+            Err(LibationError::NetworkError { is_transient: false, .. })
+        ));
+        for _ in 0..3 {
+            transport.push_json(500, serde_json::json!({}));
+        }
+        assert!(matches!(
+            client.get::<Value>("/1.0/library").await,
+            Err(LibationError::ApiRequestFailed { status_code: Some(500), .. })
+        ));
+        assert_eq!(transport.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_error_responses_are_not_retried() {
+        let transport = Arc::new(MockTransport::new());
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("120"));
+        transport.push_response(429, headers, "slow down");
+        transport.push_json(404, serde_json::json!({ "message": "Not found" }));
+        transport.push_response(200, HeaderMap::new(), "not json");
+        let client = mock_client(&transport);
+
+        assert!(matches!(
+            client.get::<Value>("/1.0/library").await,
+            Err(LibationError::RateLimitExceeded { retry_after_seconds: 120, .. })
+        ));
+        match client.get::<Value>("/1.0/library/B404").await {
+            Err(LibationError::ApiRequestFailed { status_code, endpoint, .. }) => {
+                assert_eq!(status_code, Some(404));
+                assert_eq!(endpoint.as_deref(), Some("/1.0/library/B404"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            client.get::<Value>("/1.0/library").await,
+            Err(LibationError::InvalidApiResponse { .. })
+        ));
+        assert_eq!(transport.requests().len(), 3);
+    }
+
     #[tokio::test]
     async fn test_client_creation_requires_account_id() {
         let account = Account {
             account_id: "".to_string(),
             account_name: "Test".to_string(),
             library_scan: true,
-            decrypt_key: "".to_string(),
+            decrypt_key: Default::default(),
             identity: None,
         };
 
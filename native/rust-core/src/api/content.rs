@@ -33,6 +33,8 @@
 //! - Query available quality tiers and codecs
 //! - Get content URLs (download, streaming)
 //! - Batch product queries for episodes/series
+//! - Companion content (bonus audio, author interviews) from relationships
+//! - Credit cost and price of search results (see `api::pricing`)
 //!
 //! # Catalog API Endpoints
 //!
@@ -40,7 +42,7 @@
 //! **GET** `/1.0/catalog/products/{asin}`
 //!
 //! Query parameters:
-//! - `response_groups` - Comma-separated list:
+//! - `response_groups` - Comma-separated list (see `ResponseGroups`):
 //!   - `product_desc` - Description, publisher, release date
 //!   - `product_attrs` - Runtime, language, ASIN
 //!   - `media` - Available formats and URLs
@@ -51,6 +53,7 @@
 //!   - `product_extended_attrs` - Extended attributes
 //!   - `product_plans` - Subscription plan availability
 //!   - `provided_review` - User's own review
+//!   - `price` - List price, sale price and credit cost
 //! - `image_sizes` - Comma-separated sizes (e.g., "500,1024")
 //!
 //! ## Batch Product Query
@@ -63,6 +66,19 @@
 //!
 //! Reference: ApiExtended.cs:206 - Uses CatalogOptions.ResponseGroupOptions for batch queries
 //!
+//! ## Catalog Search
+//! **GET** `/1.0/catalog/products`
+//!
+//! Query parameters:
+//! - `keywords` - Search terms
+//! - `num_results` - Page size (max 50)
+//! - `page` - Page number, starting at 1
+//! - `products_sort_by` - `Relevance`
+//! - `response_groups`, `image_sizes` - Same as single product
+//!
+//! Results carry storefront links (see `api::storefront`) for titles the
+//! user doesn't own.
+//!
 //! # Content Metadata Endpoint
 //! **GET** `/1.0/content/{asin}/metadata`
 //!
@@ -75,7 +91,11 @@
 //! Reference: DownloadOptions.Factory.cs:33 - api.GetContentMetadataAsync()
 
 use crate::api::client::AudibleClient;
+use crate::api::pricing::CatalogPrice;
+use crate::api::storefront::StoreLinks;
+use crate::api::response_groups::{ResponseGroup, ResponseGroups};
 use crate::error::{LibationError, Result};
+use crate::trace::trace_eprintln;
 use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -178,6 +198,18 @@ pub enum DownloadQuality {
     Extreme,
 }
 
+impl DownloadQuality {
+    /// API value ("Low", "Normal", "High", "Extreme")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DownloadQuality::Low => "Low",
+            DownloadQuality::Normal => "Normal",
+            DownloadQuality::High => "High",
+            DownloadQuality::Extreme => "Extreme",
+        }
+    }
+}
+
 /// Chapter title nesting type
 /// Reference: DownloadOptions.Factory.cs:80 - ChapterTitlesType.Tree
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -303,6 +335,11 @@ pub struct ContentUrl {
     /// URL for streaming playback
     #[serde(rename = "streaming_url")]
     pub streaming_url: Option<String>,
+
+    /// Further offline URLs for the same file on other CDNs, when the
+    /// license offers more than one
+    #[serde(rename = "offline_urls", default)]
+    pub offline_urls: Vec<String>,
 }
 
 /// Complete content metadata
@@ -515,6 +552,114 @@ pub struct CatalogProduct {
     pub is_episode: bool,
 }
 
+/// Catalog search hit with links to buy it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogSearchResult {
+    #[serde(flatten)]
+    pub product: CatalogProduct,
+
+    /// Links into the searched marketplace's store
+    pub store_links: StoreLinks,
+
+    /// Credit cost or price in the searched marketplace, when known
+    #[serde(default)]
+    pub price: Option<CatalogPrice>,
+}
+
+/// One page of catalog search results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogSearchPage {
+    pub results: Vec<CatalogSearchResult>,
+
+    /// Matches across all pages
+    pub total_results: u32,
+}
+
+// ============================================================================
+// COMPANION CONTENT
+// ============================================================================
+
+/// Kind of companion content that comes with a title
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompanionKind {
+    /// Bonus audio (extras, behind-the-scenes, sample chapters)
+    BonusAudio,
+    /// Interview with the author
+    AuthorInterview,
+    /// PDF supplement (maps, appendices)
+    Pdf,
+}
+
+impl CompanionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompanionKind::BonusAudio => "bonus_audio",
+            CompanionKind::AuthorInterview => "author_interview",
+            CompanionKind::Pdf => "pdf",
+        }
+    }
+
+    /// Kind of the audio a child relationship points at, if it is
+    /// companion content rather than an episode or series member
+    pub fn from_relationship_type(relationship_type: &str) -> Option<Self> {
+        match relationship_type.to_ascii_lowercase().as_str() {
+            "interview" | "author_interview" => Some(CompanionKind::AuthorInterview),
+            "supplement" | "companion" | "bonus" | "bonus_material" => Some(CompanionKind::BonusAudio),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for CompanionKind {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bonus_audio" => Ok(CompanionKind::BonusAudio),
+            "author_interview" => Ok(CompanionKind::AuthorInterview),
+            "pdf" => Ok(CompanionKind::Pdf),
+            _ => Err(LibationError::InvalidInput(format!("Invalid companion kind: {}", s))),
+        }
+    }
+}
+
+/// Companion content available for a title
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompanionAsset {
+    pub kind: CompanionKind,
+    /// ASIN of companion audio; downloaded with its own license
+    pub asin: Option<String>,
+    /// Direct URL of a PDF
+    pub url: Option<String>,
+    /// Position among the title's companions
+    pub sort: Option<i32>,
+}
+
+impl CatalogProduct {
+    /// Companion audio listed in the product's relationships, in sort order
+    ///
+    /// PDFs aren't part of the catalog product; see
+    /// `download::companion::list_companion_assets`.
+    pub fn companion_assets(&self) -> Vec<CompanionAsset> {
+        let mut assets: Vec<CompanionAsset> = self
+            .relationships
+            .iter()
+            .filter(|r| r.relationship_to_product.eq_ignore_ascii_case("child"))
+            .filter_map(|r| {
+                CompanionKind::from_relationship_type(&r.relationship_type).map(|kind| CompanionAsset {
+                    kind,
+                    asin: Some(r.asin.clone()),
+                    url: None,
+                    sort: r.sort,
+                })
+            })
+            .collect();
+        assets.sort_by_key(|a| a.sort.unwrap_or(i32::MAX));
+        assets
+    }
+}
+
 // ============================================================================
 // API FUNCTIONS
 // ============================================================================
@@ -557,19 +702,7 @@ impl AudibleClient {
 
         // Include all response groups for complete product information
         // Reference: ApiExtended.cs:206-210 - CatalogOptions.ResponseGroupOptions
-        let response_groups = vec![
-            "rating",
-            "media",
-            "relationships",
-            "product_desc",
-            "contributors",
-            "provided_review",
-            "product_plans",
-            "series",
-            "category_ladders",
-            "product_extended_attrs",
-        ]
-        .join(",");
+        let response_groups = ResponseGroups::STANDARD.to_string();
 
         let params = vec![
             ("response_groups", response_groups),
@@ -661,19 +794,7 @@ impl AudibleClient {
 
         // Response groups for batch query
         // Reference: ApiExtended.cs:206-210
-        let response_groups = vec![
-            "rating",
-            "media",
-            "relationships",
-            "product_desc",
-            "contributors",
-            "provided_review",
-            "product_plans",
-            "series",
-            "category_ladders",
-            "product_extended_attrs",
-        ]
-        .join(",");
+        let response_groups = ResponseGroups::STANDARD.to_string();
 
         // Build query with comma-separated ASINs
         let asin_param = asins.join(",");
@@ -709,7 +830,7 @@ impl AudibleClient {
                 Ok(product) => products.push(product),
                 Err(e) => {
                     // Log parsing error but continue with other products
-                    eprintln!("Warning: Failed to parse product in batch: {}", e);
+                    trace_eprintln!("Warning: Failed to parse product in batch: {}", e);
                 }
             }
         }
@@ -717,6 +838,103 @@ impl AudibleClient {
         Ok(products)
     }
 
+    /// Search the catalog of this client's marketplace
+    ///
+    /// # Endpoint
+    /// `GET /1.0/catalog/products?keywords=...`
+    ///
+    /// # Arguments
+    /// * `keywords` - Search terms
+    /// * `page` - Page number, starting at 1
+    /// * `num_results` - Page size (max 50)
+    ///
+    /// # Returns
+    /// Matching products with storefront links
+    ///
+    /// # Errors
+    /// - `InvalidInput` - Empty keywords, page 0 or page size over 50
+    /// - `ApiRequestFailed` - API request failed
+    /// - `InvalidApiResponse` - Response parsing failed
+    pub async fn search_catalog(
+        &self,
+        keywords: &str,
+        page: u32,
+        num_results: u32,
+    ) -> Result<CatalogSearchPage> {
+        if keywords.trim().is_empty() {
+            return Err(LibationError::invalid_input("Search keywords are empty"));
+        }
+        if page == 0 || num_results == 0 || num_results as usize > crate::api::client::BATCH_SIZE {
+            return Err(LibationError::invalid_input(format!(
+                "Invalid search page {} of size {} (max {})",
+                page,
+                num_results,
+                crate::api::client::BATCH_SIZE
+            )));
+        }
+
+        let marketplace = self.marketplace()?;
+        let response_groups = ResponseGroups::STANDARD
+            .without(ResponseGroup::ProvidedReview)
+            .with(ResponseGroup::Price)
+            .to_string();
+
+        let params = [
+            ("keywords", keywords.trim().to_string()),
+            ("num_results", num_results.to_string()),
+            ("page", page.to_string()),
+            ("products_sort_by", "Relevance".to_string()),
+            ("response_groups", response_groups),
+            ("image_sizes", "500".to_string()),
+        ];
+
+        let query_string = params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, urlencoding::encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let url = format!("/1.0/catalog/products?{}", query_string);
+
+        let response: serde_json::Value = self.get(&url).await?;
+
+        let products_json = response
+            .get("products")
+            .and_then(|p| p.as_array())
+            .ok_or_else(|| LibationError::InvalidApiResponse {
+                message: "Missing or invalid 'products' array in response".to_string(),
+                response_body: Some(response.to_string()),
+            })?;
+
+        let mut results = Vec::with_capacity(products_json.len());
+        for product_value in products_json {
+            match serde_json::from_value::<CatalogProduct>(product_value.clone()) {
+                Ok(product) => {
+                    let store_links = crate::api::storefront::store_links(&marketplace, &product.asin)?;
+                    let price = crate::api::pricing::price_from_product(product_value, &marketplace);
+                    if let Some(price) = &price {
+                        crate::api::pricing::remember_price(price);
+                    }
+                    results.push(CatalogSearchResult { product, store_links, price });
+                }
+                Err(e) => {
+                    trace_eprintln!("Warning: Failed to parse product in search results: {}", e);
+                }
+            }
+        }
+
+        let total_results = response
+            .get("total_results")
+            .and_then(|t| t.as_u64())
+            .map(|t| t as u32)
+            .unwrap_or(results.len() as u32);
+
+        Ok(CatalogSearchPage {
+            results,
+            total_results,
+        })
+    }
+
     /// Get content metadata including chapter information
     ///
     /// # Reference
@@ -904,6 +1122,35 @@ mod tests {
         assert!(!DrmType::Adrm.is_widevine());
     }
 
+    #[test]
+    fn test_companion_assets() {
+        let product: CatalogProduct = serde_json::from_value(serde_json::json!({
+            "asin": "B0MAINBOOK",
+            "title": "Project Hail Mary",
+            "runtime_length_min": 970,
+            "language": "english",
+            "format_type": "unabridged",
+            "authors": [],
+            "narrators": [],
+            "series": [],
+            "relationships": [
+                {"asin": "B0INTERVW1", "relationship_type": "interview", "relationship_to_product": "child", "sort": 2},
+                {"asin": "B0BONUS001", "relationship_type": "supplement", "relationship_to_product": "child", "sort": 1},
+                {"asin": "B0SERIES01", "relationship_type": "series", "relationship_to_product": "parent", "sort": 1},
+                {"asin": "B0EPISODE1", "relationship_type": "episode", "relationship_to_product": "child", "sort": 3}
+            ],
+            "is_series_parent": false,
+            "is_episode": false
+        }))
+        .unwrap();
+
+        let assets = product.companion_assets();
+        assert_eq!(assets.len(), 2);
+        assert_eq!((assets[0].kind, assets[0].asin.as_deref()), (CompanionKind::BonusAudio, Some("B0BONUS001")));
+        assert_eq!((assets[1].kind, assets[1].asin.as_deref()), (CompanionKind::AuthorInterview, Some("B0INTERVW1")));
+        assert_eq!("author_interview".parse::<CompanionKind>().unwrap(), CompanionKind::AuthorInterview);
+    }
+
     #[test]
     fn test_flatten_chapters_simple() {
         let chapters = vec![
@@ -960,4 +1207,51 @@ mod tests {
         assert_eq!(chapters[0].start_offset_ms, 0);
         assert_eq!(chapters[0].length_ms, 70000);
     }
+
+    #[tokio::test]
+    async fn test_search_catalog_returns_store_links() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use std::sync::Arc;
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(200, serde_json::json!({
+            "total_results": 42,
+            "products": [{
+                "asin": "B002V5D7B0",
+                "title": "Dune",
+                "runtime_length_min": 1263,
+                "language": "english",
+                "format_type": "unabridged",
+                "authors": [],
+                "narrators": [],
+                "series": [],
+                "relationships": [],
+                "is_series_parent": false,
+                "is_episode": false,
+                "price": {
+                    "credit_price": 1.0,
+                    "is_buyable": true,
+                    "is_credit_price_eligible": true,
+                    "list_price": { "base": 24.95, "currency_code": "USD" }
+                }
+            }]
+        }));
+        let client = mock_client(&transport);
+
+        let page = client.search_catalog(" dune ", 1, 20).await.unwrap();
+        assert_eq!(page.total_results, 42);
+        assert_eq!(page.results.len(), 1);
+        assert_eq!(page.results[0].store_links.product_url, "https://www.audible.com/pd/B002V5D7B0");
+        let price = page.results[0].price.as_ref().unwrap();
+        assert_eq!(price.display, "1 credit");
+        assert_eq!(price.list_price.as_ref().unwrap().display, "$24.95");
+        assert_eq!(crate::api::pricing::cached_price("us", "B002V5D7B0").as_ref(), Some(price));
+
+        let url = &transport.requests()[0].url;
+        assert!(url.starts_with("https://api.audible.com/1.0/catalog/products?keywords=dune&num_results=20&page=1"));
+
+        assert!(client.search_catalog("  ", 1, 20).await.is_err());
+        assert!(client.search_catalog("dune", 0, 20).await.is_err());
+        assert!(client.search_catalog("dune", 1, 51).await.is_err());
+    }
 }
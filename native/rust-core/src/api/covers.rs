@@ -0,0 +1,399 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Refreshing stale cover URLs
+//!
+//! Books store the cover URLs of the library response they were imported
+//! from (`picture_id`, `picture_large`). When Audible moves its image CDN,
+//! those URLs stop working for older imports. Every cover URL ends in the
+//! same image id (`.../images/I/51abc+XYZ._SL500_.jpg`), so a refresh:
+//!
+//! 1. Re-derives both URLs on the current image host from the image id
+//! 2. Checks the 500px one with a HEAD request
+//! 3. Looks the title up in the catalog when there is no image id or the
+//!    derived URL is gone
+//!
+//! Changed rows are written in one transaction. A `picture_id` stored as a
+//! bare image id stays one; URLs keep their size.
+
+use crate::api::client::{AudibleClient, BATCH_SIZE};
+use crate::api::response_groups::{ResponseGroup, ResponseGroups};
+use crate::error::{LibationError, Result};
+use crate::trace::trace_eprintln;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Image host of current cover URLs
+pub const DEFAULT_IMAGE_HOST: &str = "https://m.media-amazon.com/images/I/";
+
+/// Size of the URL checked and stored in `picture_large`
+const CHECK_SIZE: u32 = 500;
+
+const HEAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What to refresh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoverRefreshOptions {
+    /// Books to refresh (None: the whole library)
+    pub asins: Option<Vec<String>>,
+    /// Host prefix cover URLs are derived on
+    pub image_host: String,
+    /// HEAD requests in flight at once
+    pub concurrency: usize,
+}
+
+impl Default for CoverRefreshOptions {
+    fn default() -> Self {
+        Self {
+            asins: None,
+            image_host: DEFAULT_IMAGE_HOST.to_string(),
+            concurrency: 8,
+        }
+    }
+}
+
+/// Outcome of a cover refresh, by ASIN
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverRefreshReport {
+    pub checked: usize,
+    /// URLs re-derived from the image id
+    pub rederived: Vec<String>,
+    /// URLs taken from the catalog
+    pub from_catalog: Vec<String>,
+    /// No working cover found
+    pub unresolved: Vec<String>,
+    /// Check or lookup failed (network, server error); left as they were
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProductImages {
+    asin: String,
+    #[serde(default)]
+    product_images: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProductsResponse {
+    #[serde(default)]
+    products: Vec<ProductImages>,
+}
+
+impl AudibleClient {
+    /// Current 500px cover URLs of products from the catalog
+    ///
+    /// # Returns
+    /// ASIN → URL, for products the catalog has a cover for
+    pub async fn get_cover_urls(&self, asins: &[String]) -> Result<HashMap<String, String>> {
+        let mut urls = HashMap::new();
+        for batch in asins.chunks(BATCH_SIZE) {
+            let endpoint = format!(
+                "/1.0/catalog/products?asin={}&response_groups={}&image_sizes={}",
+                urlencoding::encode(&batch.join(",")),
+                ResponseGroups::empty().with(ResponseGroup::Media),
+                CHECK_SIZE
+            );
+            let response: serde_json::Value = self.get(&endpoint).await?;
+            let response: ProductsResponse = serde_json::from_value(response.clone()).map_err(|e| {
+                LibationError::InvalidApiResponse {
+                    message: format!("Failed to parse product images: {}", e),
+                    response_body: Some(response.to_string()),
+                }
+            })?;
+            for mut product in response.products {
+                if let Some(url) = product.product_images.remove(&CHECK_SIZE.to_string()) {
+                    urls.insert(product.asin, url);
+                }
+            }
+        }
+        Ok(urls)
+    }
+}
+
+/// Image id of a cover URL or a bare image id
+///
+/// `https://m.media-amazon.com/images/I/51abc+XYZ._SL500_.jpg` → `51abc+XYZ`
+pub fn image_id(picture: &str) -> Option<&str> {
+    let picture = picture.trim();
+    let name = match picture.rfind("/images/I/") {
+        Some(i) => &picture[i + "/images/I/".len()..],
+        None if !picture.contains('/') => picture,
+        None => return None,
+    };
+    let id = name.split('.').next().unwrap_or_default();
+    (!id.is_empty()).then_some(id)
+}
+
+/// Cover URL of an image id at a size in pixels
+pub fn cover_url(image_host: &str, image_id: &str, size: u32) -> String {
+    format!("{}{}._SL{}_.jpg", image_host, image_id, size)
+}
+
+/// Size in a cover URL (`._SL500_` → 500)
+fn url_size(url: &str) -> Option<u32> {
+    let rest = &url[url.find("._SL")? + 4..];
+    rest[..rest.find('_')?].parse().ok()
+}
+
+/// New `picture_id` in the form of the old one
+fn rewrite_picture_id(old: Option<&str>, image_host: &str, id: &str) -> String {
+    match old {
+        Some(old) if !old.contains('/') => id.to_string(),
+        Some(old) => cover_url(image_host, id, url_size(old).unwrap_or(CHECK_SIZE)),
+        None => cover_url(image_host, id, CHECK_SIZE),
+    }
+}
+
+/// Whether a cover URL serves an image
+///
+/// # Errors
+/// NetworkError for connection failures and server errors, where the
+/// answer says nothing about the URL
+async fn cover_exists(http: &reqwest::Client, url: &str) -> Result<bool> {
+    let response = http
+        .head(url)
+        .send()
+        .await
+        .map_err(|e| LibationError::network_error(format!("Cover check failed: {}", e), true))?;
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(LibationError::network_error(format!("HTTP {} checking {}", status, url), true));
+    }
+    Ok(status.is_success())
+}
+
+/// Re-derive, check and store cover URLs of the library
+///
+/// Titles without a working derived URL are looked up in the catalog
+/// through `client`; without one they are reported unresolved.
+pub async fn refresh_cover_urls(
+    pool: &SqlitePool,
+    client: Option<&AudibleClient>,
+    options: &CoverRefreshOptions,
+) -> Result<CoverRefreshReport> {
+    let rows: Vec<(String, Option<String>, Option<String>)> =
+        sqlx::query_as("SELECT audible_product_id, picture_id, picture_large FROM Books ORDER BY book_id")
+            .fetch_all(pool)
+            .await?;
+    let rows: Vec<_> = match &options.asins {
+        Some(asins) => rows.into_iter().filter(|(asin, _, _)| asins.contains(asin)).collect(),
+        None => rows,
+    };
+
+    let http = reqwest::Client::builder()
+        .timeout(HEAD_TIMEOUT)
+        .build()
+        .map_err(|e| LibationError::network_error(format!("Failed to create HTTP client: {}", e), false))?;
+    let mut report = CoverRefreshReport { checked: rows.len(), ..Default::default() };
+
+    // (asin, picture_id, picture_large) to write
+    let mut updates: Vec<(String, String, String)> = Vec::new();
+    let mut lookups: Vec<(String, Option<String>)> = Vec::new();
+
+    let checks = futures_util::stream::iter(rows)
+        .map(|(asin, picture_id, picture_large)| {
+            let http = &http;
+            async move {
+                let id = picture_id
+                    .as_deref()
+                    .and_then(image_id)
+                    .or_else(|| picture_large.as_deref().and_then(image_id))
+                    .map(str::to_string);
+                let checked = match &id {
+                    Some(id) => Some(cover_exists(http, &cover_url(&options.image_host, id, CHECK_SIZE)).await),
+                    None => None,
+                };
+                (asin, picture_id, picture_large, id, checked)
+            }
+        })
+        .buffer_unordered(options.concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    for (asin, picture_id, picture_large, id, checked) in checks {
+        match (id, checked) {
+            (Some(id), Some(Ok(true))) => {
+                let new_id = rewrite_picture_id(picture_id.as_deref(), &options.image_host, &id);
+                let new_large = cover_url(&options.image_host, &id, CHECK_SIZE);
+                if picture_id.as_deref() != Some(new_id.as_str()) || picture_large.as_deref() != Some(new_large.as_str()) {
+                    report.rederived.push(asin.clone());
+                    updates.push((asin, new_id, new_large));
+                }
+            }
+            (_, Some(Err(e))) => {
+                trace_eprintln!("⚠️  Cover check for {} failed: {}", asin, e);
+                report.skipped.push(asin);
+            }
+            _ => lookups.push((asin, picture_id)),
+        }
+    }
+
+    let mut found = HashMap::new();
+    if let (Some(client), false) = (client, lookups.is_empty()) {
+        let asins: Vec<String> = lookups.iter().map(|(asin, _)| asin.clone()).collect();
+        match client.get_cover_urls(&asins).await {
+            Ok(urls) => found = urls,
+            Err(e) => {
+                trace_eprintln!("⚠️  Catalog lookup of covers failed: {}", e);
+                report.skipped.extend(asins);
+                lookups.clear();
+            }
+        }
+    }
+    for (asin, picture_id) in lookups {
+        match found.get(&asin).and_then(|url| image_id(url).map(|id| (url, id))) {
+            Some((url, id)) => {
+                let new_id = match picture_id.as_deref() {
+                    Some(old) if !old.contains('/') => id.to_string(),
+                    _ => url.clone(),
+                };
+                report.from_catalog.push(asin.clone());
+                updates.push((asin, new_id, url.clone()));
+            }
+            None => report.unresolved.push(asin),
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    for (asin, picture_id, picture_large) in &updates {
+        sqlx::query("UPDATE Books SET picture_id = ?, picture_large = ? WHERE audible_product_id = ?")
+            .bind(picture_id)
+            .bind(picture_large)
+            .bind(asin)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::transport::{mock_client, MockTransport};
+    use crate::storage::Database;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer HEAD requests: 200 for paths containing "good", 503 for
+    /// "flaky", 404 otherwise
+    async fn serve_images() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 2048];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let line = String::from_utf8_lossy(&buf[..n]).lines().next().unwrap_or_default().to_string();
+                    let status = if line.contains("good") {
+                        "200 OK"
+                    } else if line.contains("flaky") {
+                        "503 Service Unavailable"
+                    } else {
+                        "404 Not Found"
+                    };
+                    let _ = socket
+                        .write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_bytes())
+                        .await;
+                });
+            }
+        });
+        format!("http://127.0.0.1:{}/images/I/", port)
+    }
+
+    #[test]
+    fn test_image_id() {
+        assert_eq!(image_id("https://m.media-amazon.com/images/I/51abc+XYZ._SL500_.jpg"), Some("51abc+XYZ"));
+        assert_eq!(image_id("https://images-na.ssl-images-amazon.com/images/I/61xyz.jpg"), Some("61xyz"));
+        assert_eq!(image_id("51abc"), Some("51abc"));
+        assert_eq!(image_id("https://example.com/cover.jpg"), None);
+        assert_eq!(image_id(""), None);
+        assert_eq!(url_size("https://x/images/I/51abc._SL1215_.jpg"), Some(1215));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_cover_urls() {
+        let db = Database::new_in_memory().await.unwrap();
+        let host = serve_images().await;
+        let books: [(&str, Option<&str>, Option<&str>); 5] = [
+            // Old CDN, still served under the same id
+            ("B0OLDCDN", Some("https://images-na.ssl-images-amazon.com/images/I/good1._SL1215_.jpg"),
+             Some("https://images-na.ssl-images-amazon.com/images/I/good1._SL500_.jpg")),
+            // Bare image id stays bare
+            ("B0BAREID", Some("good2"), None),
+            // Gone from the CDN, and no image id at all: catalog
+            ("B0GONE", Some("https://old.example/images/I/missing._SL500_.jpg"), None),
+            ("B0NOPIC", None, None),
+            // Server error: left alone
+            ("B0FLAKY", Some("flaky3"), None),
+        ];
+        for (asin, picture_id, picture_large) in books {
+            let mut book = crate::storage::NewBook::new(asin.to_string(), asin.to_string(), "us".to_string());
+            book.picture_id = picture_id.map(str::to_string);
+            book.picture_large = picture_large.map(str::to_string);
+            crate::storage::queries::insert_book(db.pool(), &book).await.unwrap();
+        }
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(200, serde_json::json!({
+            "products": [
+                { "asin": "B0GONE", "product_images": { "500": format!("{}good4._SL500_.jpg", host) } },
+                { "asin": "B0NOPIC" }
+            ]
+        }));
+        let client = mock_client(&transport);
+        let options = CoverRefreshOptions { image_host: host.clone(), ..Default::default() };
+        let mut report = refresh_cover_urls(db.pool(), Some(&client), &options).await.unwrap();
+        report.rederived.sort();
+
+        assert_eq!(report.checked, 5);
+        assert_eq!(report.rederived, vec!["B0BAREID", "B0OLDCDN"]);
+        assert_eq!(report.from_catalog, vec!["B0GONE"]);
+        assert_eq!(report.unresolved, vec!["B0NOPIC"]);
+        assert_eq!(report.skipped, vec!["B0FLAKY"]);
+
+        let pictures = |asin: &'static str| {
+            sqlx::query_as::<_, (Option<String>, Option<String>)>(
+                "SELECT picture_id, picture_large FROM Books WHERE audible_product_id = ?",
+            )
+            .bind(asin)
+            .fetch_one(db.pool())
+        };
+        assert_eq!(
+            pictures("B0OLDCDN").await.unwrap(),
+            (Some(format!("{}good1._SL1215_.jpg", host)), Some(format!("{}good1._SL500_.jpg", host)))
+        );
+        assert_eq!(pictures("B0BAREID").await.unwrap(), (Some("good2".to_string()), Some(format!("{}good2._SL500_.jpg", host))));
+        assert_eq!(pictures("B0GONE").await.unwrap().1, Some(format!("{}good4._SL500_.jpg", host)));
+        assert_eq!(pictures("B0FLAKY").await.unwrap(), (Some("flaky3".to_string()), None));
+
+        // Current URLs are left as they are
+        let report = refresh_cover_urls(db.pool(), None, &CoverRefreshOptions {
+            asins: Some(vec!["B0OLDCDN".to_string()]),
+            ..options
+        })
+        .await
+        .unwrap();
+        assert_eq!(report.checked, 1);
+        assert!(report.rederived.is_empty() && report.unresolved.is_empty());
+    }
+}
@@ -0,0 +1,292 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Opt-in debug capture of auth responses
+//!
+//! Registration responses carry everything needed to act as the user's
+//! device (bearer and refresh tokens, the device private key, session
+//! cookies), so they are never written to disk unless a capture is enabled.
+//!
+//! # Capture
+//! - Off by default; enabled with `enable_debug_capture`
+//! - Written only to app-private storage: shared storage (`/sdcard`,
+//!   `/storage/...`) and directories readable by other users are refused
+//! - Secret fields are replaced with `"<redacted>"` unless the capture was
+//!   enabled with `include_secrets`
+//! - Encrypted with AES-128-CBC under a key supplied by the app (kept in the
+//!   platform keystore); files are `iv || ciphertext`, read back with
+//!   `read_capture`
+//!
+//! `redact_json` is also used for request logs, error bodies and API
+//! recordings (`recording`).
+
+use crate::error::{LibationError, Result};
+use aes::Aes128;
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "<redacted>";
+
+/// Keys whose values are secrets, wherever they appear
+const SECRET_KEYS: &[&str] = &[
+    "access_token",
+    "refresh_token",
+    "adp_token",
+    "device_private_key",
+    "website_cookies",
+    "store_authentication_cookie",
+    "cookie",
+    "cookies",
+    "authorization_code",
+    "code_verifier",
+    "client_id",
+];
+
+/// Path prefixes of storage other apps can read
+const SHARED_STORAGE_PREFIXES: &[&str] = &["/sdcard", "/storage/", "/mnt/sdcard", "/mnt/media_rw"];
+
+#[derive(Clone)]
+struct CaptureConfig {
+    directory: PathBuf,
+    key: [u8; 16],
+    include_secrets: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref CAPTURE: Mutex<Option<CaptureConfig>> = Mutex::new(None);
+}
+
+/// Copy of `value` with every secret field replaced by `REDACTED`
+pub fn redact_json(value: &Value) -> Value {
+    redact_json_keys(value, &[])
+}
+
+/// `redact_json`, also redacting the fields named in `extra`
+pub fn redact_json_keys(value: &Value, extra: &[&str]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let v = if is_secret_key(k) || extra.contains(&k.as_str()) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact_json_keys(v, extra)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|v| redact_json_keys(v, extra)).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Whether values under `key` are secrets
+pub fn is_secret_key(key: &str) -> bool {
+    SECRET_KEYS.contains(&key)
+}
+
+/// Whether `path` is on storage other apps or users can read
+pub fn is_world_readable(path: &Path) -> bool {
+    let display = path.to_string_lossy();
+    if SHARED_STORAGE_PREFIXES.iter().any(|p| display.starts_with(p)) {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            return metadata.permissions().mode() & 0o004 != 0;
+        }
+    }
+    false
+}
+
+/// Enable capturing registration responses
+///
+/// # Arguments
+/// * `directory` - App-private directory for captures (created if missing)
+/// * `key` - 16-byte AES key
+/// * `include_secrets` - Keep tokens, keys and cookies instead of redacting
+///
+/// # Errors
+/// PermissionDenied if `directory` is on shared or world-readable storage
+pub fn enable_debug_capture(directory: &Path, key: [u8; 16], include_secrets: bool) -> Result<()> {
+    if is_world_readable(directory) {
+        return Err(LibationError::PermissionDenied(format!(
+            "Debug captures can't be written to shared or world-readable storage: {}",
+            directory.display()
+        )));
+    }
+    std::fs::create_dir_all(directory)?;
+    if is_world_readable(directory) {
+        return Err(LibationError::PermissionDenied(format!(
+            "Debug capture directory is readable by other users: {}",
+            directory.display()
+        )));
+    }
+
+    *CAPTURE.lock().unwrap() = Some(CaptureConfig {
+        directory: directory.to_path_buf(),
+        key,
+        include_secrets,
+    });
+    Ok(())
+}
+
+/// Stop capturing; existing captures are kept
+pub fn disable_debug_capture() {
+    *CAPTURE.lock().unwrap() = None;
+}
+
+/// Capture `value` under `name` if a capture is enabled
+///
+/// # Returns
+/// Path of the capture file, None if capturing is off
+pub fn capture(name: &str, value: &Value) -> Result<Option<PathBuf>> {
+    let Some(config) = CAPTURE.lock().unwrap().clone() else {
+        return Ok(None);
+    };
+
+    let value = if config.include_secrets { value.clone() } else { redact_json(value) };
+    let plaintext = serde_json::to_vec(&value)?;
+
+    let iv: [u8; 16] = rand::random();
+    let mut buffer = vec![0u8; plaintext.len() + 16];
+    buffer[..plaintext.len()].copy_from_slice(&plaintext);
+    let ciphertext = cbc::Encryptor::<Aes128>::new(&config.key.into(), &iv.into())
+        .encrypt_padded_mut::<Pkcs7>(&mut buffer, plaintext.len())
+        .map_err(|e| LibationError::InvalidState(format!("Failed to encrypt capture: {}", e)))?;
+
+    let path = config.directory.join(format!(
+        "{}-{}.capture",
+        name,
+        crate::clock::now().format("%Y%m%dT%H%M%S%.3f")
+    ));
+    write_private(&path, &[&iv[..], ciphertext].concat())?;
+    Ok(Some(path))
+}
+
+/// Decrypt a capture file
+///
+/// # Errors
+/// InvalidInput if the file is too short or the key is wrong
+pub fn read_capture(path: &Path, key: [u8; 16]) -> Result<Value> {
+    let data = std::fs::read(path)?;
+    if data.len() < 32 {
+        return Err(LibationError::InvalidInput("Capture file is truncated".to_string()));
+    }
+
+    let (iv, ciphertext) = data.split_at(16);
+    let mut buffer = ciphertext.to_vec();
+    let iv: [u8; 16] = iv.try_into().expect("split at 16");
+    let plaintext = cbc::Decryptor::<Aes128>::new(&key.into(), &iv.into())
+        .decrypt_padded_mut::<Pkcs7>(&mut buffer)
+        .map_err(|_| LibationError::InvalidInput("Wrong key for capture file".to_string()))?;
+    serde_json::from_slice(plaintext)
+        .map_err(|_| LibationError::InvalidInput("Wrong key for capture file".to_string()))
+}
+
+/// Write `data` readable by the owner only
+pub(crate) fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FIXTURE: &str = include_str!("../../test_fixtures/registration_response.json");
+
+    /// Every string value under a secret key in the fixture
+    fn secrets(value: &Value, out: &mut Vec<String>, under_secret: bool) {
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    secrets(v, out, under_secret || SECRET_KEYS.contains(&k.as_str()));
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| secrets(v, out, under_secret)),
+            Value::String(s) if under_secret && s.len() >= 8 => out.push(s.clone()),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_no_secrets_written_outside_app_storage() {
+        let response: Value = serde_json::from_str(TEST_FIXTURE).unwrap();
+        let mut secret_values = Vec::new();
+        secrets(&response, &mut secret_values, false);
+        assert!(secret_values.len() > 5);
+
+        // Redaction removes every secret but keeps the shape
+        let redacted = serde_json::to_string(&redact_json(&response)).unwrap();
+        assert!(secret_values.iter().all(|s| !redacted.contains(s.as_str())));
+        assert!(redacted.contains("customer_info"));
+
+        // Off by default: nothing is written
+        disable_debug_capture();
+        assert!(capture("registration", &response).unwrap().is_none());
+
+        // Shared and world-readable storage is refused
+        let key = [9u8; 16];
+        for shared in ["/sdcard/Download", "/storage/emulated/0/Download"] {
+            let err = enable_debug_capture(Path::new(shared), key, true).unwrap_err();
+            assert!(matches!(err, LibationError::PermissionDenied(_)));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let open = tempfile::tempdir().unwrap();
+            std::fs::set_permissions(open.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(enable_debug_capture(open.path(), key, false).is_err());
+        }
+
+        // Captures are encrypted, owner-only and redacted by default
+        let dir = tempfile::tempdir().unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o700)).unwrap();
+        }
+        enable_debug_capture(dir.path(), key, false).unwrap();
+        let path = capture("registration", &response).unwrap().unwrap();
+        disable_debug_capture();
+        assert!(path.starts_with(dir.path()));
+        assert!(!is_world_readable(&path));
+
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("customer_info"));
+        let restored = read_capture(&path, key).unwrap();
+        assert_eq!(restored, redact_json(&response));
+        assert!(read_capture(&path, [1u8; 16]).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Marketplace health probe
+//!
+//! When Audible has an outage the app only sees failed requests. The probe
+//! tells the status banner which side is at fault, per marketplace:
+//!
+//! 1. An unauthenticated one-item catalog request. No response means the
+//!    device's network is down (`NetworkDown`); a 5xx or 429 means the
+//!    Audible API is failing (`ApiError`).
+//! 2. With an account, a one-item library request (as the `api_access`
+//!    pre-sync check does). A rejected token means the account is broken
+//!    (`AccountError`).
+//!
+//! The whole probe is time-boxed (`PROBE_TIMEOUT`) and sends each request
+//! once, without the client's retries. Results are cached for
+//! `HEALTH_CACHE_TTL` per marketplace and account, so a banner can ask on
+//! every screen.
+
+use crate::api::auth::{Account, Locale};
+use crate::api::client::{AudibleClient, ClientConfig};
+use crate::api::preflight::{CheckStatus, Remediation};
+use crate::api::transport::{HttpRequest, HttpTransport};
+use crate::api::response_groups::ResponseGroups;
+use crate::error::LibationError;
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest a probe may take
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a probe result is reused
+pub const HEALTH_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Public endpoint for the unauthenticated request
+fn public_probe_endpoint() -> String {
+    format!(
+        "/1.0/catalog/products?num_results=1&response_groups={}&keywords=audible",
+        ResponseGroups::MINIMAL
+    )
+}
+
+/// Probe results with their time, by (country code, account id)
+type HealthCache = HashMap<(String, String), (Instant, MarketplaceHealth)>;
+
+static CACHE: Mutex<Option<HealthCache>> = Mutex::new(None);
+
+/// What the probe found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    /// Audible answered and the account (if any) was accepted
+    Healthy,
+    /// No response: the device's connection is down
+    NetworkDown,
+    /// Audible answered with server errors or rate limiting
+    ApiError,
+    /// Audible works, but rejected the account
+    AccountError,
+}
+
+/// Health of one marketplace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketplaceHealth {
+    pub country_code: String,
+    pub state: HealthState,
+    /// What failed, when not healthy
+    pub message: Option<String>,
+    /// HTTP status of the failed request
+    pub status_code: Option<u16>,
+    pub remediation: Option<Remediation>,
+    /// `remediation.instructions()`
+    pub instructions: Option<String>,
+    /// Time the probe took
+    pub latency_ms: u64,
+    pub checked_at: DateTime<Utc>,
+    /// Whether the account was checked too
+    pub account_checked: bool,
+}
+
+impl MarketplaceHealth {
+    fn new(locale: &Locale, state: HealthState, started: Instant, account_checked: bool) -> Self {
+        Self {
+            country_code: locale.country_code.clone(),
+            state,
+            message: None,
+            status_code: None,
+            remediation: None,
+            instructions: None,
+            latency_ms: started.elapsed().as_millis() as u64,
+            checked_at: Utc::now(),
+            account_checked,
+        }
+    }
+
+    fn failed(mut self, message: impl Into<String>, status_code: Option<u16>, remediation: Remediation) -> Self {
+        self.message = Some(message.into());
+        self.status_code = status_code;
+        self.remediation = Some(remediation);
+        self.instructions = Some(remediation.instructions().to_string());
+        self
+    }
+}
+
+/// State for the remediation of a failed request
+fn state_for(remediation: Remediation) -> HealthState {
+    match remediation {
+        Remediation::SignIn | Remediation::ChooseMarketplace => HealthState::AccountError,
+        Remediation::CheckConnection => HealthState::NetworkDown,
+        Remediation::RetryLater => HealthState::ApiError,
+    }
+}
+
+/// Probe `locale`, and `account` in it if given, bypassing the cache
+///
+/// Never fails: errors are reported in the result.
+pub async fn probe_marketplace(
+    transport: Arc<dyn HttpTransport>,
+    locale: &Locale,
+    account: Option<&Account>,
+) -> MarketplaceHealth {
+    let started = Instant::now();
+    let account_checked = account.is_some();
+    match tokio::time::timeout(PROBE_TIMEOUT, probe(transport, locale, account, started)).await {
+        Ok(health) => health,
+        Err(_) => MarketplaceHealth::new(locale, HealthState::NetworkDown, started, account_checked).failed(
+            format!("No response within {} seconds", PROBE_TIMEOUT.as_secs()),
+            None,
+            Remediation::CheckConnection,
+        ),
+    }
+}
+
+async fn probe(
+    transport: Arc<dyn HttpTransport>,
+    locale: &Locale,
+    account: Option<&Account>,
+    started: Instant,
+) -> MarketplaceHealth {
+    let account_checked = account.is_some();
+    let health = |state| MarketplaceHealth::new(locale, state, started, account_checked);
+
+    let url = format!("{}{}", locale.api_url(), public_probe_endpoint());
+    match transport.send(HttpRequest::new(Method::GET, url)).await {
+        Err(e) => {
+            return health(HealthState::NetworkDown).failed(e.to_string(), None, Remediation::CheckConnection);
+        }
+        Ok(response) if response.status.is_server_error() || response.status.as_u16() == 429 => {
+            return health(HealthState::ApiError).failed(
+                format!("Audible API answered {}", response.status),
+                Some(response.status.as_u16()),
+                Remediation::RetryLater,
+            );
+        }
+        // Other statuses still show the API is up
+        Ok(_) => {}
+    }
+
+    let Some(account) = account else {
+        return health(HealthState::Healthy);
+    };
+    let config = ClientConfig::builder().max_retries(1).timeout(PROBE_TIMEOUT).build();
+    let client = match AudibleClient::with_transport(account.clone(), config, transport) {
+        Ok(client) => client.for_marketplace(locale),
+        Err(e) => return health(HealthState::AccountError).failed(e.to_string(), None, Remediation::SignIn),
+    };
+    let check = client.check_api_access().await;
+    match (check.status, check.remediation) {
+        (CheckStatus::Failed, Some(remediation)) => health(state_for(remediation)).failed(
+            check.message.unwrap_or_default(),
+            None,
+            remediation,
+        ),
+        _ => health(HealthState::Healthy),
+    }
+}
+
+/// Health of `locale` (and `account` in it), from the cache when probed in
+/// the last `HEALTH_CACHE_TTL` unless `force` is set
+pub async fn marketplace_health(
+    transport: Arc<dyn HttpTransport>,
+    locale: &Locale,
+    account: Option<&Account>,
+    force: bool,
+) -> MarketplaceHealth {
+    let key = (
+        locale.country_code.clone(),
+        account.map(|a| a.account_id.clone()).unwrap_or_default(),
+    );
+    if !force {
+        let cache = CACHE.lock().unwrap();
+        if let Some((probed, health)) = cache.as_ref().and_then(|c| c.get(&key)) {
+            if probed.elapsed() < HEALTH_CACHE_TTL {
+                return health.clone();
+            }
+        }
+    }
+
+    let health = probe_marketplace(transport, locale, account).await;
+    CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(key, (Instant::now(), health.clone()));
+    health
+}
+
+/// Health state an error from any API request points to
+///
+/// Lets callers that already got an error (a failed sync page) update the
+/// banner without probing.
+pub fn state_for_error(error: &LibationError) -> HealthState {
+    state_for(Remediation::for_error(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::transport::{mock_client, MockTransport};
+
+    #[tokio::test]
+    async fn test_probe_marketplace() {
+        let transport = Arc::new(MockTransport::new());
+        let account = mock_client(&transport).account().lock().await.clone();
+        let us = Locale::us();
+
+        transport.push_network_error(true);
+        let health = probe_marketplace(transport.clone(), &us, Some(&account)).await;
+        assert_eq!(health.state, HealthState::NetworkDown);
+        assert_eq!(health.remediation, Some(Remediation::CheckConnection));
+        assert_eq!(transport.requests().len(), 1);
+
+        transport.push_json(503, serde_json::json!({ "message": "Service Unavailable" }));
+        let health = probe_marketplace(transport.clone(), &us, Some(&account)).await;
+        assert_eq!((health.state, health.status_code), (HealthState::ApiError, Some(503)));
+
+        // Audible is up, but the token is rejected
+        transport.push_json(200, serde_json::json!({ "products": [] }));
+        transport.push_json(401, serde_json::json!({ "message": "Unauthorized" }));
+        let health = probe_marketplace(transport.clone(), &us, Some(&account)).await;
+        assert_eq!(health.state, HealthState::AccountError);
+        assert_eq!(health.remediation, Some(Remediation::SignIn));
+        let requests = transport.requests();
+        assert!(requests[2].header("authorization").is_none());
+        assert!(requests[3].url.starts_with("https://api.audible.com/1.0/library"));
+
+        // Cached until forced
+        let uk = Locale::from_country_code("uk").unwrap();
+        transport.push_json(200, serde_json::json!({ "products": [] }));
+        assert_eq!(marketplace_health(transport.clone(), &uk, None, false).await.state, HealthState::Healthy);
+        assert_eq!(marketplace_health(transport.clone(), &uk, None, false).await.state, HealthState::Healthy);
+        assert_eq!(transport.requests().len(), 5);
+        let health = marketplace_health(transport.clone(), &uk, None, true).await;
+        assert_eq!(health.state, HealthState::NetworkDown);
+        assert!(!health.account_checked);
+    }
+}
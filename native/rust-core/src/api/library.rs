@@ -39,7 +39,8 @@
 //! **Query Parameters:**
 //! - `num_results` - Page size (default 50, max 1000)
 //! - `page` - Page number (starts at 1)
-//! - `response_groups` - Comma-separated list of data groups to include:
+//! - `response_groups` - Comma-separated list of data groups to include
+//!   (built with `ResponseGroups`, see `response_groups`):
 //!   - `media` - Media metadata (formats, codecs)
 //!   - `product_desc` - Product description
 //!   - `product_extended_attrs` - Extended attributes
@@ -49,7 +50,7 @@
 //!   - `series` - Series information
 //!   - `category_ladders` - Category hierarchies
 //!   - `pdf_url` - PDF supplement URL
-//!   - `origin_asin` - Original ASIN
+//!   - `origin_asin` - Original ASIN and origin type (purchase, gift, loan)
 //!   - `is_finished` - Completion status
 //!   - `provided_review` - User review
 //!   - `product_plans` - Subscription plans
@@ -57,9 +58,16 @@
 //! # Pagination Pattern (from ApiExtended.cs:98-123)
 //! 1. Fetch pages concurrently (MaxConcurrency = 10)
 //! 2. Process in batches of 50 items
-//! 3. Handle episode/series parent relationships separately
+//! 3. Handle episode/series parent relationships separately (episodes and
+//!    seasons are linked to their podcast parents, see `storage::podcasts`)
 //! 4. Merge all results into single collection
 //!
+//! Page-by-page sync from the app can prefetch: while the caller imports
+//! and renders page N, page N+1 is already being fetched in the background
+//! (`sync_library_page_prefetching`). Prefetches go through the same
+//! client concurrency limit, stop while Audible is rate limiting the
+//! account, and are dropped if not claimed within `PREFETCH_TTL`.
+//!
 //! # Database Upsert Strategy (from LibraryBookImporter.cs:30-96)
 //! 1. Import books via BookImporter (creates/updates Book records)
 //! 2. Upsert LibraryBook records (account ownership)
@@ -68,16 +76,26 @@
 //! 5. Link categories via ladders
 //! 6. Mark absent books (removed from library)
 
+use crate::activity::{self, WorkType};
+use crate::cancel::CancellationToken;
 use crate::error::{LibationError, Result};
+use crate::events::{self, ProgressStage};
 use crate::api::client::AudibleClient;
 use crate::api::auth::Account;
-use crate::storage::Database;
+use crate::api::response_groups::ResponseGroups;
+use crate::storage::{queries, Database};
+use crate::storage::normalize::{canonical_asin, title_search_key, title_sort_key};
+use crate::storage::podcasts;
+use crate::storage::series_order::parse_sequence;
+use crate::storage::sync_issues::{self, SyncError, SyncStage};
 use crate::storage::models::{
     Book, NewBook, NewLibraryBook, NewContributor, NewSeries, NewCategory, NewCategoryLadder,
-    ContentType, Role, LibraryBook,
+    BenefitType, ContentType, FormatSupport, Role, LibraryBook,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use chrono::{DateTime, NaiveDate, Utc};
 
 // ============================================================================
@@ -103,9 +121,9 @@ pub struct LibraryOptions {
     pub purchased_after: Option<String>,
 
     /// Response groups (controls which fields are included)
-    /// Comma-separated string: "media,product_desc,relationships,contributors"
+    /// Sent as a comma-separated string: "media,product_desc,relationships,contributors"
     #[serde(rename = "response_groups")]
-    pub response_groups: String,
+    pub response_groups: ResponseGroups,
 
     /// Sort order (PURCHASE_DATE, TITLE, AUTHOR, etc.)
     #[serde(rename = "sort_by")]
@@ -124,21 +142,7 @@ impl Default for LibraryOptions {
             number_of_results_per_page: 50,  // Back to normal size
             page_number: 1,
             purchased_after: None,
-            response_groups: [
-                "rating",
-                "media",
-                "relationships",
-                "product_desc",
-                "contributors",
-                "provided_review",
-                "product_plans",
-                "series",
-                "category_ladders",
-                "product_extended_attrs",
-                "pdf_url",
-                "origin_asin",
-                "is_finished",
-            ].join(","),
+            response_groups: ResponseGroups::FULL,
             sort_by: "PurchaseDate".to_string(),
             image_sizes: Some("500,1215".to_string()),
         }
@@ -170,6 +174,20 @@ pub struct LibraryResponse {
     pub response_groups: Option<Vec<String>>,
 }
 
+/// Single-title response from GET /1.0/library/{asin}
+#[derive(Debug, Clone, Deserialize)]
+struct LibraryItemResponse {
+    item: LibraryItem,
+}
+
+/// Query for GET /1.0/library/{asin}
+#[derive(Debug, Serialize)]
+struct LibraryItemQuery<'a> {
+    response_groups: ResponseGroups,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_sizes: Option<&'a str>,
+}
+
 /// Individual library item from Audible API
 /// Maps to C# `Item` class in AudibleApi/Common/LibraryDtoV10.cs
 ///
@@ -322,6 +340,10 @@ pub struct LibraryItem {
     /// Original ASIN (for regional variants)
     #[serde(rename = "origin_asin", default)]
     pub origin_asin: Option<String>,
+
+    /// How the title was acquired (e.g. "Purchase", "Gift", "AudibleChannels")
+    #[serde(rename = "origin_type", default)]
+    pub origin_type: Option<String>,
 }
 
 impl LibraryItem {
@@ -344,6 +366,41 @@ impl LibraryItem {
         }
     }
 
+    /// Get ownership/benefit type
+    pub fn benefit_type(&self) -> BenefitType {
+        BenefitType::from_origin(self.origin_type.as_deref(), self.is_ayce.unwrap_or(false))
+    }
+
+    /// Whether the listed formats can be liberated
+    pub fn format_support(&self) -> FormatSupport {
+        let codecs: Vec<&str> = self
+            .available_codecs
+            .iter()
+            .flat_map(|c| [c.name.as_deref(), c.format.as_deref()])
+            .chain(self.asset_details.iter().map(|a| a.codec.as_deref()))
+            .flatten()
+            .collect();
+        FormatSupport::from_codecs(&codecs)
+    }
+
+    /// Whether an author also narrates
+    ///
+    /// Contributors are matched by ASIN when both have one, otherwise by
+    /// name (case-insensitive). Items without narrators aren't flagged.
+    pub fn narrated_by_author(&self) -> bool {
+        self.narrators.iter().any(|narrator| {
+            self.authors.iter().any(|author| match (&author.asin, &narrator.asin) {
+                (Some(a), Some(n)) => a == n,
+                _ => author.name.trim().eq_ignore_ascii_case(narrator.name.trim()),
+            })
+        })
+    }
+
+    /// Whether the narrators include a "full cast" marker
+    pub fn is_full_cast(&self) -> bool {
+        self.narrators.iter().any(|narrator| is_full_cast_marker(&narrator.name))
+    }
+
     /// Check if this is an episode
     pub fn is_episode(&self) -> bool {
         matches!(self.get_content_type(), ContentType::Episode)
@@ -354,6 +411,24 @@ impl LibraryItem {
         matches!(self.get_content_type(), ContentType::Parent)
     }
 
+    /// Podcast parents (show or season) of an episode or season
+    ///
+    /// Empty for other titles; their series come from `series`.
+    pub fn podcast_parents(&self) -> Vec<&Relationship> {
+        if !self.is_episode() && !self.is_series_parent() {
+            return Vec::new();
+        }
+        self.relationships
+            .iter()
+            .flatten()
+            .filter(|r| {
+                r.relationship_to_product
+                    .as_deref()
+                    .is_some_and(|to| to.eq_ignore_ascii_case("parent"))
+            })
+            .collect()
+    }
+
     /// Get picture ID (highest quality image)
     /// Reference: BookImporter.cs:156-160
     pub fn get_picture_id(&self) -> Option<String> {
@@ -417,6 +492,17 @@ pub struct AssetDetail {
     pub format: Option<String>,
 }
 
+/// Whether a narrator name is a full cast marker ("Full Cast",
+/// "a full-cast ensemble", "Full Cast Dramatization")
+fn is_full_cast_marker(name: &str) -> bool {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words.windows(2).any(|pair| pair[0] == "full" && pair[1] == "cast")
+}
+
 /// Person information (author, narrator)
 /// Maps to C# `Person` class in AudibleApi/Common/Person.cs
 #[derive(Debug, Clone, Deserialize)]
@@ -575,8 +661,8 @@ pub struct SyncStats {
     /// Books marked as absent (removed from library)
     pub books_absent: i32,
 
-    /// Errors encountered during sync (non-fatal)
-    pub errors: Vec<String>,
+    /// Items that failed to import (non-fatal, also kept in SyncIssues)
+    pub errors: Vec<SyncError>,
 
     /// Whether there are more pages to fetch (for pagination)
     pub has_more: bool,
@@ -588,6 +674,91 @@ impl SyncStats {
     }
 }
 
+// ============================================================================
+// PAGE PREFETCHING
+// ============================================================================
+
+/// How long a prefetched page waits to be claimed
+const PREFETCH_TTL: Duration = Duration::from_secs(60);
+
+type PageFetch = tokio::task::JoinHandle<Result<LibraryResponse>>;
+
+/// Pages fetched ahead, and until when accounts are rate limited
+#[derive(Default)]
+struct PrefetchState {
+    pages: HashMap<String, (i32, Instant, PageFetch)>,
+    throttled_until: HashMap<String, Instant>,
+}
+
+static PREFETCH: Mutex<Option<PrefetchState>> = Mutex::new(None);
+
+fn with_prefetch<T>(f: impl FnOnce(&mut PrefetchState) -> T) -> T {
+    f(PREFETCH.lock().unwrap().get_or_insert_with(PrefetchState::default))
+}
+
+/// Whether pages follow `page`
+///
+/// From `total_results` at the default page size; without it, a page with
+/// items may have more after it.
+fn page_has_more(page: i32, response: &LibraryResponse) -> bool {
+    match response.total_results {
+        Some(total) => {
+            let page_size = LibraryOptions::default().number_of_results_per_page;
+            let total_pages = (total as f32 / page_size as f32).ceil() as i32;
+            page < total_pages
+        }
+        None => !response.items.is_empty(),
+    }
+}
+
+/// The account's prefetch of `page`, if one is running or done
+///
+/// Prefetches of other pages, or past `PREFETCH_TTL`, are dropped.
+fn take_prefetched(account_id: &str, page: i32) -> Option<PageFetch> {
+    let (prefetched, started, fetch) = with_prefetch(|state| state.pages.remove(account_id))?;
+    if prefetched == page && started.elapsed() < PREFETCH_TTL {
+        Some(fetch)
+    } else {
+        fetch.abort();
+        None
+    }
+}
+
+/// Keep the account's prefetch of `page`, replacing any other
+fn put_prefetched(account_id: &str, page: i32, fetch: PageFetch) {
+    let replaced = with_prefetch(|state| state.pages.insert(account_id.to_string(), (page, Instant::now(), fetch)));
+    if let Some((_, _, old)) = replaced {
+        old.abort();
+    }
+}
+
+/// Drop the account's prefetch
+fn clear_prefetched(account_id: &str) {
+    if let Some((_, _, fetch)) = with_prefetch(|state| state.pages.remove(account_id)) {
+        fetch.abort();
+    }
+}
+
+/// Remember a rate limit answer until its Retry-After passes
+fn note_throttle<T>(account_id: &str, result: &Result<T>) {
+    if let Err(LibationError::RateLimitExceeded { retry_after_seconds, .. }) = result {
+        let until = Instant::now() + Duration::from_secs(*retry_after_seconds);
+        with_prefetch(|state| state.throttled_until.insert(account_id.to_string(), until));
+    }
+}
+
+/// Whether Audible is rate limiting the account
+fn is_throttled(account_id: &str) -> bool {
+    with_prefetch(|state| match state.throttled_until.get(account_id) {
+        Some(until) if *until > Instant::now() => true,
+        Some(_) => {
+            state.throttled_until.remove(account_id);
+            false
+        }
+        None => false,
+    })
+}
+
 // ============================================================================
 // LIBRARY SYNC IMPLEMENTATION
 // ============================================================================
@@ -628,11 +799,30 @@ impl AudibleClient {
         db: &Database,
         account: &Account,
     ) -> Result<SyncStats> {
+        self.sync_library_cancellable(db, account, &CancellationToken::new()).await
+    }
+
+    /// `sync_library` that stops when `cancel` is cancelled
+    ///
+    /// Pages are checked for cancellation before each request and books
+    /// before each import, so every book is either fully imported or
+    /// untouched. Sync issues of the books imported so far are recorded.
+    /// Absent books are only marked after a complete sync.
+    ///
+    /// # Errors
+    /// Cancelled if cancelled before the sync finished
+    pub async fn sync_library_cancellable(
+        &mut self,
+        db: &Database,
+        account: &Account,
+        cancel: &CancellationToken,
+    ) -> Result<SyncStats> {
+        let _work = activity::begin_work(WorkType::LibrarySync, None);
         let mut stats = SyncStats::new();
 
         // Fetch all library items from API
         let options = LibraryOptions::default();
-        let (items, total_count) = self.fetch_all_library_items(options).await?;
+        let (items, total_count) = self.fetch_all_library_items(options, cancel).await?;
 
         stats.total_items = items.len() as i32;
         stats.total_library_count = total_count;
@@ -642,7 +832,8 @@ impl AudibleClient {
         }
 
         // Import items into database
-        let (new_count, updated_count, errors) = self.import_items_to_db(db, &items, &account.account_id).await?;
+        let (new_count, updated_count, errors) =
+            self.import_items_to_db(db, &items, &account.account_id, cancel).await?;
 
         stats.books_added = new_count;
         stats.books_updated = updated_count;
@@ -686,28 +877,105 @@ impl AudibleClient {
         account: &Account,
         page: i32,
     ) -> Result<SyncStats> {
-        let mut stats = SyncStats::new();
+        self.sync_library_page_cancellable(db, account, page, &CancellationToken::new()).await
+    }
+
+    /// `sync_library_page` that stops when `cancel` is cancelled
+    ///
+    /// Same guarantees as `sync_library_cancellable`.
+    pub async fn sync_library_page_cancellable(
+        &mut self,
+        db: &Database,
+        account: &Account,
+        page: i32,
+        cancel: &CancellationToken,
+    ) -> Result<SyncStats> {
+        let _work = activity::begin_work(WorkType::LibrarySync, None);
 
         // Fetch single page from API
         let mut options = LibraryOptions::default();
         options.page_number = page;
 
-        let response: LibraryResponse = self
-            .get_with_query("/1.0/library", &options)
-            .await?;
+        let response: LibraryResponse = cancel
+            .run_until_cancelled(self.get_with_query("/1.0/library", &options))
+            .await??;
 
-        stats.total_items = response.items.len() as i32;
+        self.import_library_page(db, account, page, response, cancel).await
+    }
 
-        // Set total_library_count and has_more from API response
-        if let Some(total) = response.total_results {
-            stats.total_library_count = total;
-            let page_size = options.number_of_results_per_page;
-            let total_pages = (total as f32 / page_size as f32).ceil() as i32;
-            stats.has_more = page < total_pages;
-        } else {
-            // If no total provided, check if page is empty to determine has_more
-            stats.has_more = !response.items.is_empty();
+    /// `sync_library_page_cancellable` that fetches the next page in the
+    /// background while this one is imported
+    ///
+    /// The next call for the same account picks up the prefetched page
+    /// instead of fetching it. Nothing is prefetched on the last page or
+    /// while the account is rate limited; a prefetch that failed for
+    /// another reason is fetched again.
+    pub async fn sync_library_page_prefetching(
+        &mut self,
+        db: &Database,
+        account: &Account,
+        page: i32,
+        cancel: &CancellationToken,
+    ) -> Result<SyncStats> {
+        let _work = activity::begin_work(WorkType::LibrarySync, None);
+        let account_id = account.account_id.clone();
+
+        let prefetched = match take_prefetched(&account_id, page) {
+            Some(fetch) => match cancel.run_until_cancelled(fetch).await? {
+                Ok(Ok(response)) => Some(response),
+                Ok(Err(e @ LibationError::RateLimitExceeded { .. })) => return Err(e),
+                // Failed ahead of time (e.g. the network dropped): fetch again
+                _ => None,
+            },
+            None => None,
+        };
+        let response = match prefetched {
+            Some(response) => response,
+            None => {
+                let options = LibraryOptions { page_number: page, ..Default::default() };
+                let fetched = cancel
+                    .run_until_cancelled(self.get_with_query::<LibraryResponse, _>("/1.0/library", &options))
+                    .await?;
+                note_throttle(&account_id, &fetched);
+                fetched?
+            }
+        };
+
+        if page_has_more(page, &response) && !is_throttled(&account_id) {
+            let client = self.clone();
+            let next = page + 1;
+            let fetch = tokio::spawn({
+                let account_id = account_id.clone();
+                async move {
+                    let options = LibraryOptions { page_number: next, ..Default::default() };
+                    let fetched = client.get_with_query("/1.0/library", &options).await;
+                    note_throttle(&account_id, &fetched);
+                    fetched
+                }
+            });
+            put_prefetched(&account_id, next, fetch);
+        }
+
+        let stats = self.import_library_page(db, account, page, response, cancel).await;
+        if stats.is_err() {
+            clear_prefetched(&account_id);
         }
+        stats
+    }
+
+    /// Stats of a fetched library page, importing its items
+    async fn import_library_page(
+        &self,
+        db: &Database,
+        account: &Account,
+        page: i32,
+        response: LibraryResponse,
+        cancel: &CancellationToken,
+    ) -> Result<SyncStats> {
+        let mut stats = SyncStats::new();
+        stats.total_items = response.items.len() as i32;
+        stats.total_library_count = response.total_results.unwrap_or_default();
+        stats.has_more = page_has_more(page, &response);
 
         if response.items.is_empty() {
             return Ok(stats);
@@ -715,7 +983,7 @@ impl AudibleClient {
 
         // Import items into database
         let (new_count, updated_count, errors) =
-            self.import_items_to_db(db, &response.items, &account.account_id).await?;
+            self.import_items_to_db(db, &response.items, &account.account_id, cancel).await?;
 
         stats.books_added = new_count;
         stats.books_updated = updated_count;
@@ -727,6 +995,59 @@ impl AudibleClient {
         Ok(stats)
     }
 
+    /// Re-import the title behind a stored sync issue
+    ///
+    /// Fetches just that title from the library API. The issue is resolved
+    /// if the import succeeds or the title is no longer in the library;
+    /// otherwise it stays open and the new failure is in `errors`.
+    ///
+    /// # Errors
+    /// - RecordNotFound if the issue doesn't exist or is already resolved
+    /// - InvalidInput if the issue belongs to another account
+    pub async fn retry_sync_issue(
+        &mut self,
+        db: &Database,
+        account: &Account,
+        issue_id: i64,
+    ) -> Result<SyncStats> {
+        let issue = sync_issues::get_sync_issue(db.pool(), issue_id).await?;
+        if issue.account != account.account_id {
+            return Err(LibationError::invalid_input(format!(
+                "Sync issue {} belongs to account {}",
+                issue_id, issue.account
+            )));
+        }
+
+        let mut stats = SyncStats::new();
+        let options = LibraryOptions::default();
+        let query = LibraryItemQuery {
+            response_groups: options.response_groups,
+            image_sizes: options.image_sizes.as_deref(),
+        };
+        let response: LibraryItemResponse = match self
+            .get_with_query(&format!("/1.0/library/{}", issue.asin), &query)
+            .await
+        {
+            Ok(response) => response,
+            Err(LibationError::ApiRequestFailed { status_code: Some(404), .. }) => {
+                sync_issues::resolve_sync_issue(db.pool(), issue_id).await?;
+                return Ok(stats);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let items = [response.item];
+        let (new_count, updated_count, errors) =
+            self.import_items_to_db(db, &items, &account.account_id, &CancellationToken::new()).await?;
+
+        stats.total_items = 1;
+        stats.books_added = new_count;
+        stats.books_updated = updated_count;
+        stats.errors = errors;
+
+        Ok(stats)
+    }
+
     /// Fetch all library items from Audible API with pagination
     ///
     /// # Reference
@@ -740,6 +1061,7 @@ impl AudibleClient {
     ///
     /// # Arguments
     /// * `options` - Library query options (page size, filters, response groups)
+    /// * `cancel` - Checked before each page request
     ///
     /// # Returns
     /// All library items across all pages
@@ -749,14 +1071,15 @@ impl AudibleClient {
     async fn fetch_all_library_items(
         &mut self,
         mut options: LibraryOptions,
+        cancel: &CancellationToken,
     ) -> Result<(Vec<LibraryItem>, i32)> {
         let mut all_items = Vec::new();
 
         // Fetch first page
         options.page_number = 1;
-        let first_response: LibraryResponse = self
-            .get_with_query("/1.0/library", &options)
-            .await?;
+        let first_response: LibraryResponse = cancel
+            .run_until_cancelled(self.get_with_query("/1.0/library", &options))
+            .await??;
 
         all_items.extend(first_response.items);
 
@@ -764,15 +1087,17 @@ impl AudibleClient {
         if let Some(total) = first_response.total_results {
             let page_size = options.number_of_results_per_page;
             let total_pages = (total as f32 / page_size as f32).ceil() as i32;
+            events::report_items(ProgressStage::Fetching, all_items.len() as u64, total as u64);
 
             // Fetch remaining pages
             for page_num in 2..=total_pages {
                 options.page_number = page_num;
-                let response: LibraryResponse = self
-                    .get_with_query("/1.0/library", &options)
-                    .await?;
+                let response: LibraryResponse = cancel
+                    .run_until_cancelled(self.get_with_query("/1.0/library", &options))
+                    .await??;
 
                 all_items.extend(response.items);
+                events::report_items(ProgressStage::Fetching, all_items.len() as u64, total as u64);
             }
 
             Ok((all_items, total))
@@ -783,9 +1108,9 @@ impl AudibleClient {
 
             loop {
                 options.page_number = page_num;
-                let response: LibraryResponse = self
-                    .get_with_query("/1.0/library", &options)
-                    .await?;
+                let response: LibraryResponse = cancel
+                    .run_until_cancelled(self.get_with_query("/1.0/library", &options))
+                    .await??;
 
                 if response.items.is_empty() {
                     break;
@@ -815,6 +1140,12 @@ impl AudibleClient {
     /// * `db` - Database connection
     /// * `items` - Library items from API
     /// * `account_id` - Account ID for LibraryBook records
+    /// * `cancel` - Checked before each book; on cancellation the books
+    ///   imported so far keep their sync issue updates and Cancelled is
+    ///   returned
+    ///
+    /// Failures are stored in SyncIssues; open issues of titles that
+    /// imported cleanly are resolved.
     ///
     /// # Returns
     /// Tuple of (new_count, updated_count, errors)
@@ -823,62 +1154,66 @@ impl AudibleClient {
         db: &Database,
         items: &[LibraryItem],
         account_id: &str,
-    ) -> Result<(i32, i32, Vec<String>)> {
+        cancel: &CancellationToken,
+    ) -> Result<(i32, i32, Vec<SyncError>)> {
         let mut new_count = 0;
         let mut updated_count = 0;
         let mut errors = Vec::new();
 
-        // Build lookup maps for contributors, series, categories
-        let mut contributor_cache: HashMap<String, i64> = HashMap::new();
-        let mut series_cache: HashMap<String, i64> = HashMap::new();
-        let mut category_cache: HashMap<String, i64> = HashMap::new();
-
-        // Import contributors first (authors, narrators, publishers)
+        // Resolve every contributor and series on the page in a few batched
+        // statements instead of a lookup (and insert) per name
+        let mut contributors: Vec<NewContributor> = Vec::new();
+        let mut series: Vec<NewSeries> = Vec::new();
         for item in items {
-            for author in &item.authors {
-                if !contributor_cache.contains_key(&author.name) {
-                    match self.upsert_contributor(db, &author.name, author.asin.as_deref()).await {
-                        Ok(id) => { contributor_cache.insert(author.name.clone(), id); },
-                        Err(e) => errors.push(format!("Failed to import author '{}': {}", author.name, e)),
-                    }
-                }
-            }
-
-            for narrator in &item.narrators {
-                if !contributor_cache.contains_key(&narrator.name) {
-                    match self.upsert_contributor(db, &narrator.name, narrator.asin.as_deref()).await {
-                        Ok(id) => { contributor_cache.insert(narrator.name.clone(), id); },
-                        Err(e) => errors.push(format!("Failed to import narrator '{}': {}", narrator.name, e)),
-                    }
-                }
+            for person in item.authors.iter().chain(&item.narrators) {
+                contributors.push(NewContributor {
+                    name: person.name.clone(),
+                    audible_contributor_id: person.asin.clone(),
+                });
             }
-
             if let Some(ref publisher) = item.publisher {
-                if !contributor_cache.contains_key(publisher) {
-                    match self.upsert_contributor(db, publisher, None).await {
-                        Ok(id) => { contributor_cache.insert(publisher.clone(), id); },
-                        Err(e) => errors.push(format!("Failed to import publisher '{}': {}", publisher, e)),
-                    }
-                }
+                contributors.push(NewContributor::new(publisher.clone()));
+            }
+            for series_info in item.series.iter().flatten() {
+                series.push(NewSeries {
+                    audible_series_id: series_info.series_id.clone(),
+                    name: series_info.title.clone(),
+                });
+            }
+            for parent in item.podcast_parents() {
+                series.push(NewSeries {
+                    audible_series_id: parent.asin.clone(),
+                    name: parent.title.clone(),
+                });
             }
         }
 
-        // Import series
-        for item in items {
-            if let Some(series_list) = &item.series {
-                for series_info in series_list {
-                    if !series_cache.contains_key(&series_info.series_id) {
-                        match self.upsert_series(db, &series_info.series_id, series_info.title.as_deref()).await {
-                            Ok(id) => { series_cache.insert(series_info.series_id.clone(), id); },
-                            Err(e) => errors.push(format!("Failed to import series '{}': {}", series_info.series_id, e)),
-                        }
-                    }
+        let contributor_cache = match queries::upsert_contributors_batch(db.pool(), &contributors).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                for item in items.iter().filter(|i| !i.authors.is_empty() || !i.narrators.is_empty() || i.publisher.is_some()) {
+                    errors.push(SyncError::new(&item.asin, SyncStage::Contributor, &e));
                 }
+                HashMap::new()
             }
-        }
+        };
+        let series_cache = match queries::upsert_series_batch(db.pool(), &series).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                for item in items.iter().filter(|i| i.series.as_ref().is_some_and(|s| !s.is_empty()) || !i.podcast_parents().is_empty()) {
+                    errors.push(SyncError::new(&item.asin, SyncStage::Series, &e));
+                }
+                HashMap::new()
+            }
+        };
 
         // Import books and link relationships
+        let mut imported = 0;
         for item in items {
+            if cancel.is_cancelled() {
+                break;
+            }
+            imported += 1;
             match self.import_book(db, item, account_id, &contributor_cache, &series_cache).await {
                 Ok(is_new) => {
                     if is_new {
@@ -888,11 +1223,22 @@ impl AudibleClient {
                     }
                 },
                 Err(e) => {
-                    errors.push(format!("Failed to import book '{}': {}", item.asin, e));
+                    errors.push(SyncError::new(&item.asin, SyncStage::Book, &e));
                 }
             }
+            events::report_items(ProgressStage::Importing, imported as u64, items.len() as u64);
         }
 
+        let failed: HashSet<&str> = errors.iter().map(|e| e.asin.as_str()).collect();
+        let clean: Vec<String> = items[..imported]
+            .iter()
+            .filter(|item| !failed.contains(item.asin.as_str()))
+            .map(|item| item.asin.clone())
+            .collect();
+        sync_issues::record_sync_errors(db.pool(), account_id, &errors).await?;
+        sync_issues::resolve_sync_issues(db.pool(), account_id, &clean).await?;
+        cancel.check()?;
+
         Ok((new_count, updated_count, errors))
     }
 
@@ -920,25 +1266,33 @@ impl AudibleClient {
     ) -> Result<bool> {
         let pool = db.pool();
 
-        // Check if book exists
-        let existing: Option<(i64,)> = sqlx::query_as(
-            "SELECT book_id FROM Books WHERE audible_product_id = ?"
-        )
-        .bind(&item.asin)
-        .fetch_optional(pool)
-        .await?;
+        // Check if book exists (matching the unique index on the ASIN)
+        let find_existing = || async {
+            sqlx::query_scalar::<_, i64>(
+                "SELECT book_id FROM Books WHERE UPPER(TRIM(audible_product_id)) = UPPER(TRIM(?))"
+            )
+            .bind(&item.asin)
+            .fetch_optional(pool)
+            .await
+        };
 
-        let (book_id, is_new) = match existing {
-            Some((id,)) => {
+        let (book_id, is_new) = match find_existing().await? {
+            Some(id) => {
                 // Update existing book
                 self.update_book(db, id, item).await?;
                 (id, false)
             },
-            None => {
-                // Create new book
-                let id = self.create_book(db, item).await?;
-                (id, true)
-            }
+            None => match self.create_book(db, item).await? {
+                Some(id) => (id, true),
+                None => {
+                    // Created by a concurrent sync since the lookup
+                    let id = find_existing().await?.ok_or_else(|| {
+                        LibationError::internal(format!("Book {} conflicts but wasn't found", item.asin))
+                    })?;
+                    self.update_book(db, id, item).await?;
+                    (id, false)
+                }
+            },
         };
 
         // Upsert LibraryBook record
@@ -950,6 +1304,17 @@ impl AudibleClient {
         // Link series
         self.link_series(db, book_id, item, series_cache).await?;
 
+        // Link episodes and seasons to their podcast parents
+        let parents: Vec<(i64, Option<String>)> = item
+            .podcast_parents()
+            .into_iter()
+            .filter_map(|parent| {
+                let sequence = parent.sequence.clone().or_else(|| parent.sort.clone());
+                series_cache.get(&parent.asin).map(|&series_id| (series_id, sequence))
+            })
+            .collect();
+        podcasts::link_podcast_parents(db.pool(), book_id, &parents).await?;
+
         // Update user-defined metadata
         self.update_user_defined_item(db, book_id, item).await?;
 
@@ -958,9 +1323,12 @@ impl AudibleClient {
 
     /// Create new book record
     ///
+    /// The ASIN is stored in canonical form. Returns None, inserting
+    /// nothing, when a book with the ASIN already exists.
+    ///
     /// # Reference
     /// Based on `BookImporter.createNewBook()` - DtoImporterService/BookImporter.cs:74-144
-    async fn create_book(&self, db: &Database, item: &LibraryItem) -> Result<i64> {
+    async fn create_book(&self, db: &Database, item: &LibraryItem) -> Result<Option<i64>> {
         let pool = db.pool();
 
         let content_type = item.get_content_type() as i32;
@@ -999,6 +1367,13 @@ impl AudibleClient {
         let origin_asin = item.origin_asin.as_deref();
         let episode_number = item.episode_number;
         let content_delivery_type = item.content_delivery_type.as_deref();
+        let benefit_type = item.benefit_type().as_str();
+        let format_support = item.format_support().as_str();
+        let narrated_by_author = item.narrated_by_author();
+        let full_cast = item.is_full_cast();
+        let title_sort = title_sort_key(&item.title);
+        let title_search = title_search_key(&item.title, item.subtitle.as_deref());
+        let now = crate::storage::dates::now();
 
         let result = sqlx::query(
             r#"
@@ -1007,12 +1382,14 @@ impl AudibleClient {
                 content_type, locale, picture_id, picture_large, is_abridged, is_spatial,
                 date_published, language, rating_overall, rating_performance, rating_story,
                 pdf_url, is_finished, is_downloadable, is_ayce, origin_asin, episode_number,
-                content_delivery_type, created_at, updated_at
+                content_delivery_type, benefit_type, format_support, narrated_by_author, full_cast,
+                title_sort, title_search, created_at, updated_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT DO NOTHING
             "#
         )
-        .bind(&item.asin)
+        .bind(canonical_asin(&item.asin))
         .bind(&item.title)
         .bind(&item.subtitle)
         .bind(description)
@@ -1035,10 +1412,18 @@ impl AudibleClient {
         .bind(origin_asin)
         .bind(episode_number)
         .bind(content_delivery_type)
+        .bind(benefit_type)
+        .bind(format_support)
+        .bind(narrated_by_author)
+        .bind(full_cast)
+        .bind(title_sort)
+        .bind(title_search)
+        .bind(&now)
+        .bind(&now)
         .execute(pool)
         .await?;
 
-        Ok(result.last_insert_rowid())
+        Ok((result.rows_affected() > 0).then(|| result.last_insert_rowid()))
     }
 
     /// Update existing book record
@@ -1079,6 +1464,12 @@ impl AudibleClient {
         let origin_asin = item.origin_asin.as_deref();
         let episode_number = item.episode_number;
         let content_delivery_type = item.content_delivery_type.as_deref();
+        let benefit_type = item.benefit_type().as_str();
+        let format_support = item.format_support().as_str();
+        let narrated_by_author = item.narrated_by_author();
+        let full_cast = item.is_full_cast();
+        let title_sort = title_sort_key(&item.title);
+        let title_search = title_search_key(&item.title, item.subtitle.as_deref());
 
         sqlx::query(
             r#"
@@ -1088,7 +1479,8 @@ impl AudibleClient {
                 rating_overall = ?, rating_performance = ?, rating_story = ?,
                 pdf_url = ?, is_finished = ?, is_downloadable = ?, is_ayce = ?,
                 origin_asin = ?, episode_number = ?, content_delivery_type = ?,
-                updated_at = datetime('now')
+                benefit_type = ?, format_support = ?, narrated_by_author = ?, full_cast = ?,
+                title_sort = ?, title_search = ?, updated_at = ?
             WHERE book_id = ?
             "#
         )
@@ -1111,6 +1503,13 @@ impl AudibleClient {
         .bind(origin_asin)
         .bind(episode_number)
         .bind(content_delivery_type)
+        .bind(benefit_type)
+        .bind(format_support)
+        .bind(narrated_by_author)
+        .bind(full_cast)
+        .bind(title_sort)
+        .bind(title_search)
+        .bind(crate::storage::dates::now())
         .bind(book_id)
         .execute(pool)
         .await?;
@@ -1120,6 +1519,11 @@ impl AudibleClient {
 
     /// Upsert LibraryBook record (account ownership)
     ///
+    /// The book is recorded as owned by `account_id` in LibraryBookAccounts.
+    /// Its LibraryBooks owner only changes when the current owner no longer
+    /// has it, so syncing a second account that owns the same title doesn't
+    /// take the book away from the first.
+    ///
     /// # Reference
     /// Based on `LibraryBookImporter.upsertLibraryBooks()` - DtoImporterService/LibraryBookImporter.cs:30-96
     async fn upsert_library_book(
@@ -1130,45 +1534,41 @@ impl AudibleClient {
         date_added: &DateTime<Utc>,
     ) -> Result<()> {
         let pool = db.pool();
+        let date_added = crate::storage::dates::format_timestamp(*date_added);
 
-        // Check if LibraryBook exists
-        let exists: Option<(bool,)> = sqlx::query_as(
-            "SELECT is_deleted FROM LibraryBooks WHERE book_id = ?"
+        sqlx::query(
+            r#"
+            INSERT INTO LibraryBookAccounts (book_id, account, date_added, is_deleted, absent_from_last_scan)
+            VALUES (?, ?, ?, 0, 0)
+            ON CONFLICT(book_id, account) DO UPDATE SET absent_from_last_scan = 0, is_deleted = 0
+            "#
         )
         .bind(book_id)
-        .fetch_optional(pool)
+        .bind(account_id)
+        .bind(&date_added)
+        .execute(pool)
         .await?;
 
-        match exists {
-            Some(_) => {
-                // Update existing - mark as not absent, not deleted
-                sqlx::query(
-                    r#"
-                    UPDATE LibraryBooks
-                    SET account = ?, absent_from_last_scan = 0, is_deleted = 0
-                    WHERE book_id = ?
-                    "#
-                )
-                .bind(account_id)
-                .bind(book_id)
-                .execute(pool)
-                .await?;
-            },
-            None => {
-                // Insert new LibraryBook
-                sqlx::query(
-                    r#"
-                    INSERT INTO LibraryBooks (book_id, date_added, account, is_deleted, absent_from_last_scan)
-                    VALUES (?, ?, ?, 0, 0)
-                    "#
-                )
-                .bind(book_id)
-                .bind(date_added)
-                .bind(account_id)
-                .execute(pool)
-                .await?;
-            }
-        }
+        // Existing entries are marked not absent, not deleted
+        sqlx::query(
+            r#"
+            INSERT INTO LibraryBooks (book_id, date_added, account, is_deleted, absent_from_last_scan)
+            VALUES (?, ?, ?, 0, 0)
+            ON CONFLICT(book_id) DO UPDATE SET
+                absent_from_last_scan = 0,
+                is_deleted = 0,
+                account = CASE WHEN EXISTS (
+                    SELECT 1 FROM LibraryBookAccounts o
+                    WHERE o.book_id = LibraryBooks.book_id AND o.account = LibraryBooks.account
+                        AND o.is_deleted = 0 AND o.absent_from_last_scan = 0
+                ) THEN LibraryBooks.account ELSE excluded.account END
+            "#
+        )
+        .bind(book_id)
+        .bind(&date_added)
+        .bind(account_id)
+        .execute(pool)
+        .await?;
 
         Ok(())
     }
@@ -1278,19 +1678,19 @@ impl AudibleClient {
         if let Some(series_list) = &item.series {
             for series_info in series_list {
                 if let Some(&series_id) = series_cache.get(&series_info.series_id) {
-                    let sequence = series_info.sequence.as_deref().unwrap_or("0");
-                    let index = parse_series_index(sequence);
+                    let sequence = parse_sequence(series_info.sequence.as_deref().unwrap_or_default());
 
                     sqlx::query(
                         r#"
-                        INSERT INTO SeriesBooks (series_id, book_id, "order", "index")
-                        VALUES (?, ?, ?, ?)
+                        INSERT INTO SeriesBooks (series_id, book_id, "order", "index", sort_key)
+                        VALUES (?, ?, ?, ?, ?)
                         "#
                     )
                     .bind(series_id)
                     .bind(book_id)
-                    .bind(sequence)
-                    .bind(index)
+                    .bind(&sequence.raw)
+                    .bind(sequence.index())
+                    .bind(&sequence.sort_key)
                     .execute(pool)
                     .await?;
                 }
@@ -1366,72 +1766,6 @@ impl AudibleClient {
         Ok(())
     }
 
-    /// Upsert contributor
-    async fn upsert_contributor(&self, db: &Database, name: &str, asin: Option<&str>) -> Result<i64> {
-        let pool = db.pool();
-
-        // Check if exists
-        let existing: Option<(i64,)> = sqlx::query_as(
-            "SELECT contributor_id FROM Contributors WHERE name = ?"
-        )
-        .bind(name)
-        .fetch_optional(pool)
-        .await?;
-
-        match existing {
-            Some((id,)) => Ok(id),
-            None => {
-                let result = sqlx::query(
-                    "INSERT INTO Contributors (name, audible_contributor_id) VALUES (?, ?)"
-                )
-                .bind(name)
-                .bind(asin)
-                .execute(pool)
-                .await?;
-
-                Ok(result.last_insert_rowid())
-            }
-        }
-    }
-
-    /// Upsert series
-    async fn upsert_series(&self, db: &Database, series_id: &str, name: Option<&str>) -> Result<i64> {
-        let pool = db.pool();
-
-        // Check if exists
-        let existing: Option<(i64,)> = sqlx::query_as(
-            "SELECT series_id FROM Series WHERE audible_series_id = ?"
-        )
-        .bind(series_id)
-        .fetch_optional(pool)
-        .await?;
-
-        match existing {
-            Some((id,)) => {
-                // Update name if provided
-                if let Some(name) = name {
-                    sqlx::query("UPDATE Series SET name = ? WHERE series_id = ?")
-                        .bind(name)
-                        .bind(id)
-                        .execute(pool)
-                        .await?;
-                }
-                Ok(id)
-            },
-            None => {
-                let result = sqlx::query(
-                    "INSERT INTO Series (audible_series_id, name) VALUES (?, ?)"
-                )
-                .bind(series_id)
-                .bind(name)
-                .execute(pool)
-                .await?;
-
-                Ok(result.last_insert_rowid())
-            }
-        }
-    }
-
     /// Upsert supplement (PDF)
     async fn upsert_supplement(&self, db: &Database, book_id: i64, url: &str) -> Result<()> {
         let pool = db.pool();
@@ -1484,8 +1818,8 @@ impl AudibleClient {
             r#"
             SELECT b.book_id, b.audible_product_id
             FROM Books b
-            INNER JOIN LibraryBooks lb ON lb.book_id = b.book_id
-            WHERE lb.account = ? AND lb.is_deleted = 0
+            INNER JOIN LibraryBookAccounts lba ON lba.book_id = b.book_id
+            WHERE lba.account = ? AND lba.is_deleted = 0
             "#
         )
         .bind(account_id)
@@ -1496,11 +1830,26 @@ impl AudibleClient {
         let mut absent_count = 0;
         for (book_id, asin) in db_books {
             if !current_asins.contains(&asin) {
-                sqlx::query("UPDATE LibraryBooks SET absent_from_last_scan = 1 WHERE book_id = ?")
+                sqlx::query("UPDATE LibraryBookAccounts SET absent_from_last_scan = 1 WHERE book_id = ? AND account = ?")
                     .bind(book_id)
+                    .bind(account_id)
                     .execute(pool)
                     .await?;
 
+                // The library entry stays present while another account still has it
+                sqlx::query(
+                    r#"
+                    UPDATE LibraryBooks SET absent_from_last_scan = 1
+                    WHERE book_id = ?1 AND NOT EXISTS (
+                        SELECT 1 FROM LibraryBookAccounts
+                        WHERE book_id = ?1 AND is_deleted = 0 AND absent_from_last_scan = 0
+                    )
+                    "#
+                )
+                .bind(book_id)
+                .execute(pool)
+                .await?;
+
                 absent_count += 1;
             }
         }
@@ -1509,40 +1858,18 @@ impl AudibleClient {
     }
 }
 
-// ============================================================================
-// HELPER FUNCTIONS
-// ============================================================================
-
-/// Parse series index from order string
-///
-/// Converts series order strings like "1", "2.5", "Book 3" to numeric index.
-/// Falls back to 0.0 if parsing fails.
-fn parse_series_index(order: &str) -> f32 {
-    // Try to extract first number from string
-    let numbers: String = order.chars().filter(|c| c.is_ascii_digit() || *c == '.').collect();
-    numbers.parse::<f32>().unwrap_or(0.0)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn test_parse_series_index() {
-        assert_eq!(parse_series_index("1"), 1.0);
-        assert_eq!(parse_series_index("2.5"), 2.5);
-        assert_eq!(parse_series_index("Book 3"), 3.0);
-        assert_eq!(parse_series_index("10"), 10.0);
-        assert_eq!(parse_series_index("invalid"), 0.0);
-    }
+    use crate::api::response_groups::ResponseGroup;
 
     #[test]
     fn test_library_options_default() {
         let options = LibraryOptions::default();
         assert_eq!(options.number_of_results_per_page, 50);
         assert_eq!(options.page_number, 1);
-        assert!(options.response_groups.contains("media"));
-        assert!(options.response_groups.contains("contributors"));
+        assert!(options.response_groups.contains(ResponseGroup::Media));
+        assert!(options.response_groups.contains(ResponseGroup::Contributors));
     }
 
     #[test]
@@ -1584,4 +1911,316 @@ mod tests {
         assert!(item.plans.is_some());
         assert_eq!(item.plans.unwrap().len(), 1);
     }
+
+    #[test]
+    fn test_library_item_benefit_type() {
+        let parse = |extra: &str| -> LibraryItem {
+            serde_json::from_str(&format!(
+                r#"{{"asin": "B004TEST", "title": "Test", "purchase_date": "2024-01-01T00:00:00Z"{}}}"#,
+                extra
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(parse("").benefit_type(), BenefitType::Purchase);
+        assert_eq!(parse(r#", "origin_type": "Purchase""#).benefit_type(), BenefitType::Purchase);
+        assert_eq!(parse(r#", "origin_type": "Gift""#).benefit_type(), BenefitType::Gift);
+        assert_eq!(parse(r#", "origin_type": "Loan""#).benefit_type(), BenefitType::Loan);
+        assert_eq!(parse(r#", "is_ayce": true"#).benefit_type(), BenefitType::Subscription);
+        assert!(!BenefitType::Loan.permits_download());
+        assert!(BenefitType::Gift.permits_download());
+    }
+
+    #[test]
+    fn test_library_item_format_support() {
+        let parse = |extra: &str| -> LibraryItem {
+            serde_json::from_str(&format!(
+                r#"{{"asin": "B004TEST", "title": "Test", "purchase_date": "2024-01-01T00:00:00Z"{}}}"#,
+                extra
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(parse("").format_support(), FormatSupport::Unknown);
+        assert_eq!(
+            parse(r#", "available_codecs": [{"name": "aax_22_64"}, {"name": "mp4_22_64", "format": "Format4"}]"#).format_support(),
+            FormatSupport::Supported
+        );
+        // Atmos titles also offered in AAXC are fine
+        assert_eq!(
+            parse(r#", "available_codecs": [{"name": "mp4_44_128"}], "asset_details": [{"is_spatial": true, "codec": "ec+3"}]"#).format_support(),
+            FormatSupport::Supported
+        );
+        assert_eq!(
+            parse(r#", "asset_details": [{"is_spatial": true, "codec": "ec+3"}]"#).format_support(),
+            FormatSupport::WidevineOnly
+        );
+        assert_eq!(
+            parse(r#", "asset_details": [{"is_spatial": true, "codec": "ac-4"}]"#).format_support(),
+            FormatSupport::Unsupported
+        );
+        assert!(FormatSupport::WidevineOnly.permits_download(true));
+        assert!(!FormatSupport::WidevineOnly.permits_download(false));
+        assert!(!FormatSupport::Unsupported.permits_download(true));
+        assert!(FormatSupport::Unknown.permits_download(false));
+    }
+
+    #[test]
+    fn test_library_item_narration_flags() {
+        let parse = |extra: &str| -> LibraryItem {
+            serde_json::from_str(&format!(
+                r#"{{"asin": "B005TEST", "title": "Test", "purchase_date": "2024-01-01T00:00:00Z"{}}}"#,
+                extra
+            ))
+            .unwrap()
+        };
+
+        let item = parse(r#", "authors": [{"name": "Neil Gaiman", "asin": "B000AP9A6K"}], "narrators": [{"name": "neil gaiman"}]"#);
+        assert!(item.narrated_by_author());
+        assert!(!item.is_full_cast());
+
+        // Different people who share a name are told apart by ASIN
+        let item = parse(r#", "authors": [{"name": "John Smith", "asin": "B001"}], "narrators": [{"name": "John Smith", "asin": "B002"}]"#);
+        assert!(!item.narrated_by_author());
+
+        let item = parse(r#", "authors": [{"name": "Neil Gaiman"}], "narrators": [{"name": "Neil Gaiman"}, {"name": "A Full-Cast Ensemble"}]"#);
+        assert!(item.narrated_by_author());
+        assert!(item.is_full_cast());
+
+        assert!(!parse(r#", "authors": [{"name": "Neil Gaiman"}]"#).narrated_by_author());
+        assert!(!is_full_cast_marker("Fuller Castillo"));
+        assert!(is_full_cast_marker("Full Cast Dramatization"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_library_page_through_transport() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use std::sync::Arc;
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(200, serde_json::json!({
+            "items": [
+                { "asin": "B001", "title": "First", "purchase_date": "2024-01-01T00:00:00Z",
+                  "authors": [{ "name": "Stephen Fry" }], "narrators": [{ "name": "Stephen Fry" }] },
+                { "asin": "B002", "title": "Second", "purchase_date": "2024-01-02T00:00:00Z",
+                  "asset_details": [{ "is_spatial": true, "codec": "ac-4" }] }
+            ],
+            "total_results": 120
+        }));
+        let mut client = mock_client(&transport);
+        let account = client.account().lock().await.clone();
+        let db = Database::new_in_memory().await.unwrap();
+
+        let stats = client.sync_library_page(&db, &account, 2).await.unwrap();
+        assert_eq!(stats.total_items, 2);
+        assert_eq!(stats.books_added, 2);
+        assert_eq!(stats.total_library_count, 120);
+        assert!(stats.has_more);
+
+        let url = &transport.requests()[0].url;
+        assert!(url.starts_with("https://api.audible.com/1.0/library?"));
+        assert!(url.contains("page=2"));
+        assert!(crate::storage::queries::find_book_by_asin(db.pool(), "B002").await.unwrap().is_some());
+        assert_eq!(
+            crate::storage::queries::get_book_format_support(db.pool(), "B002").await.unwrap(),
+            Some(FormatSupport::Unsupported)
+        );
+
+        let params = crate::storage::BookQueryParams {
+            narrated_by_author: Some(true),
+            limit: 10,
+            ..Default::default()
+        };
+        let narrated = crate::storage::queries::list_books_with_filters(db.pool(), &params).await.unwrap();
+        assert_eq!(narrated.len(), 1);
+        assert_eq!(narrated[0].audible_product_id, "B001");
+        assert!(!narrated[0].full_cast);
+        let params = crate::storage::BookQueryParams { narrated_by_author: Some(false), ..params };
+        assert_eq!(crate::storage::queries::count_books_with_filters(db.pool(), &params).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_library_page_prefetching() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use reqwest::header::{HeaderMap, HeaderValue};
+        use std::sync::Arc;
+
+        let page = |asin: &str| serde_json::json!({
+            "items": [{ "asin": asin, "title": asin, "purchase_date": "2024-01-01T00:00:00Z" }],
+            "total_results": 120
+        });
+        let transport = Arc::new(MockTransport::new());
+        for asin in ["B001", "B002", "B003"] {
+            transport.push_json(200, page(asin));
+        }
+        let mut client = mock_client(&transport);
+        let mut account = client.account().lock().await.clone();
+        account.account_id = "prefetch-test".to_string();
+        let db = Database::new_in_memory().await.unwrap();
+        let cancel = CancellationToken::new();
+
+        // Each page is fetched once: pages 2 and 3 while the one before imports
+        for (n, asin) in [(1, "B001"), (2, "B002"), (3, "B003")] {
+            let stats = client.sync_library_page_prefetching(&db, &account, n, &cancel).await.unwrap();
+            assert_eq!(stats.books_added, 1);
+            assert_eq!(stats.has_more, n < 3);
+            assert!(crate::storage::queries::find_book_by_asin(db.pool(), asin).await.unwrap().is_some());
+        }
+        let pages: Vec<String> = transport.requests().iter().map(|r| r.url.clone()).collect();
+        assert_eq!(pages.len(), 3);
+        for (n, url) in pages.iter().enumerate() {
+            assert!(url.contains(&format!("page={}", n + 1)), "{}", url);
+        }
+
+        // A rate-limited prefetch is reported, and nothing more is prefetched
+        account.account_id = "prefetch-throttled".to_string();
+        transport.push_json(200, page("B004"));
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("120"));
+        transport.push_response(429, headers, "slow down");
+        client.sync_library_page_prefetching(&db, &account, 1, &cancel).await.unwrap();
+        assert!(matches!(
+            client.sync_library_page_prefetching(&db, &account, 2, &cancel).await,
+            Err(LibationError::RateLimitExceeded { retry_after_seconds: 120, .. })
+        ));
+        assert_eq!(transport.requests().len(), 5);
+
+        transport.push_json(200, page("B005"));
+        let stats = client.sync_library_page_prefetching(&db, &account, 2, &cancel).await.unwrap();
+        assert!(stats.has_more);
+        tokio::task::yield_now().await;
+        assert_eq!(transport.requests().len(), 6);
+        assert_eq!(transport.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_library_cancelled() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use std::sync::Arc;
+
+        let transport = Arc::new(MockTransport::new());
+        let mut client = mock_client(&transport);
+        let account = client.account().lock().await.clone();
+        let db = Database::new_in_memory().await.unwrap();
+
+        // Cancelled before the first request: nothing is fetched
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = client.sync_library_cancellable(&db, &account, &cancel).await;
+        assert!(matches!(result, Err(LibationError::Cancelled)));
+        assert!(transport.requests().is_empty());
+
+        // Fetched pages aren't imported once cancelled
+        let items: Vec<LibraryItem> = serde_json::from_value(serde_json::json!([
+            { "asin": "B001", "title": "First", "purchase_date": "2024-01-01T00:00:00Z" },
+            { "asin": "B002", "title": "Second", "purchase_date": "2024-01-02T00:00:00Z" }
+        ]))
+        .unwrap();
+        let result = client.import_items_to_db(&db, &items, &account.account_id, &cancel).await;
+        assert!(matches!(result, Err(LibationError::Cancelled)));
+        assert!(crate::storage::queries::find_book_by_asin(db.pool(), "B001").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_podcast_hierarchy() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use crate::storage::podcasts::{list_episodes_for_parent, list_podcast_shows, PodcastChildKind};
+        use std::sync::Arc;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = mock_client(&transport);
+        let account = client.account().lock().await.clone();
+        let db = Database::new_in_memory().await.unwrap();
+        let parent = |asin: &str, sequence: &str| serde_json::json!([
+            { "asin": asin, "relationship_type": "season", "relationship_to_product": "parent",
+              "sequence": sequence, "title": "Relationship Title" }
+        ]);
+        let item = |asin: &str, title: &str, content_type: &str, relationships: serde_json::Value| serde_json::json!({
+            "asin": asin, "title": title, "purchase_date": "2024-01-01T00:00:00Z",
+            "content_type": content_type, "relationships": relationships
+        });
+        let lone_parent = serde_json::json!([
+            { "asin": "P002", "relationship_to_product": "parent", "title": "Another Show" }
+        ]);
+        let items: Vec<LibraryItem> = serde_json::from_value(serde_json::json!([
+            item("P001", "The Show", "Parent", serde_json::json!([])),
+            item("S001", "Season One", "Parent", parent("P001", "1")),
+            item("E002", "Second", "Episode", parent("S001", "2")),
+            item("E001", "First", "Episode", parent("S001", "1")),
+            item("E100", "Lone Episode", "Episode", lone_parent),
+            // Books keep their series in SeriesBooks
+            item("B001", "A Book", "Product", parent("P001", "9")),
+        ]))
+        .unwrap();
+        let (added, _, errors) = client
+            .import_items_to_db(&db, &items, &account.account_id, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(added, 6);
+        assert!(errors.is_empty());
+
+        let shows = list_podcast_shows(db.pool()).await.unwrap();
+        let shows: Vec<_> = shows.iter().map(|s| (s.asin.as_str(), s.title.as_deref(), s.child_count)).collect();
+        assert_eq!(shows, vec![("P002", Some("Another Show"), 1), ("P001", Some("The Show"), 1)]);
+
+        let seasons = list_episodes_for_parent(db.pool(), "P001").await.unwrap();
+        assert_eq!(seasons.len(), 1);
+        assert_eq!(seasons[0].kind, PodcastChildKind::Season);
+        assert_eq!(seasons[0].child_count, 2);
+        let episodes = list_episodes_for_parent(db.pool(), "S001").await.unwrap();
+        let episodes: Vec<_> = episodes.iter().map(|e| (e.asin.as_str(), e.order.as_deref(), e.kind)).collect();
+        assert_eq!(
+            episodes,
+            vec![("E001", Some("1"), PodcastChildKind::Episode), ("E002", Some("2"), PodcastChildKind::Episode)]
+        );
+
+        // A resync that drops the relationship unlinks the episode
+        let items: Vec<LibraryItem> =
+            serde_json::from_value(serde_json::json!([item("E002", "Second", "Episode", serde_json::json!([]))]))
+        .unwrap();
+        client.import_items_to_db(&db, &items, &account.account_id, &CancellationToken::new()).await.unwrap();
+        assert_eq!(list_episodes_for_parent(db.pool(), "S001").await.unwrap().len(), 1);
+        assert!(list_episodes_for_parent(db.pool(), "B001").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_keeps_other_accounts_books() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use crate::storage::queries::{find_book_by_asin, find_library_book, list_books_for_account};
+        use std::sync::Arc;
+
+        let transport = Arc::new(MockTransport::new());
+        let client = mock_client(&transport);
+        let db = Database::new_in_memory().await.unwrap();
+        let cancel = CancellationToken::new();
+        let items = |asins: &[&str]| -> Vec<LibraryItem> {
+            let items: Vec<_> = asins
+                .iter()
+                .map(|asin| serde_json::json!({ "asin": asin, "title": asin, "purchase_date": "2024-01-01T00:00:00Z" }))
+                .collect();
+            serde_json::from_value(serde_json::Value::Array(items)).unwrap()
+        };
+
+        // Both accounts own B001
+        let us = items(&["B001", "B002"]);
+        client.import_items_to_db(&db, &us, "us@example.com", &cancel).await.unwrap();
+        let uk = items(&["B001", "B003"]);
+        client.import_items_to_db(&db, &uk, "uk@example.com", &cancel).await.unwrap();
+
+        let shared = find_book_by_asin(db.pool(), "B001").await.unwrap().unwrap().book_id;
+        assert_eq!(find_library_book(db.pool(), shared).await.unwrap().unwrap().account, "us@example.com");
+        let titles = |books: Vec<crate::storage::Book>| books.into_iter().map(|b| b.title).collect::<Vec<_>>();
+        assert_eq!(titles(list_books_for_account(db.pool(), "us@example.com", 50, 0).await.unwrap()), ["B001", "B002"]);
+        assert_eq!(titles(list_books_for_account(db.pool(), "uk@example.com", 50, 0).await.unwrap()), ["B001", "B003"]);
+
+        // B001 leaves the US library but stays in the UK one
+        let us = items(&["B002"]);
+        assert_eq!(client.mark_absent_books(&db, &us, "us@example.com").await.unwrap(), 1);
+        let library_book = find_library_book(db.pool(), shared).await.unwrap().unwrap();
+        assert!(!library_book.absent_from_last_scan);
+
+        // The next UK sync takes over the entry
+        client.import_items_to_db(&db, &uk, "uk@example.com", &cancel).await.unwrap();
+        assert_eq!(find_library_book(db.pool(), shared).await.unwrap().unwrap().account, "uk@example.com");
+    }
 }
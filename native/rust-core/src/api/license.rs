@@ -84,10 +84,24 @@
 //! Response: Widevine license response (binary)
 //!
 //! Reference: DownloadOptions.Factory.cs:100 - api.WidevineDrmLicense()
+//!
+//! # Format Negotiation
+//! `negotiate_download_license` tries formats in a fixed order, skipping any
+//! the pipeline cannot fully handle on this device:
+//! 1. AAX - only when activation bytes are available and the title lists an AAX codec
+//! 2. AAXC - Audible DRM with per-title key/IV (handled natively)
+//! 3. DASH - Widevine, only when the caller reports CDM support
+//!
+//! AAX and AAXC share the `Adrm` license request; the returned key length
+//! decides which one was actually granted.
 
 use crate::api::client::AudibleClient;
+use crate::api::library::CodecInfo;
 use crate::api::content::{ChapterTitlesType, Codec, ContentMetadata, DownloadQuality, DrmType};
+use crate::crypto::activation::ActivationBytes;
 use crate::error::{LibationError, Result};
+use crate::secret::{SecretBytes, SecretString};
+use crate::trace::trace_eprintln;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -151,6 +165,29 @@ pub struct LicenseRequest {
     pub spatial_codec: Option<Codec>,
 }
 
+impl LicenseRequest {
+    /// The request `build_download_license` sends
+    ///
+    /// Reference: DownloadOptions.Factory.cs:59-84
+    pub fn for_download(quality: DownloadQuality, prefer_widevine: bool) -> Self {
+        Self {
+            quality,
+            consumption_type: ConsumptionType::Download,
+            chapter_titles_type: Some(ChapterTitlesType::Tree),
+            request_spatial: Some(false),
+            aac_codec: Some(Codec::AacLc),
+            spatial_codec: Some(Codec::Ec3),
+            // API requires drm_type to be specified
+            // Reference: DownloadOptions.Factory.cs:68-112
+            drm_type: Some(if prefer_widevine {
+                DrmType::Widevine
+            } else {
+                DrmType::Adrm // Default to Audible DRM (AAX/AAXC)
+            }),
+        }
+    }
+}
+
 /// Consumption type for license request
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConsumptionType {
@@ -193,13 +230,13 @@ pub struct Voucher {
     /// - AAX: 4 bytes (activation bytes)
     /// - AAXC: 16 bytes (key part 1)
     #[serde(rename = "key")]
-    pub key: String,
+    pub key: SecretString,
 
     /// Initialization vector (Base64 encoded)
     /// - AAX: None
     /// - AAXC: 16 bytes (key part 2)
     #[serde(rename = "iv", skip_serializing_if = "Option::is_none")]
-    pub iv: Option<String>,
+    pub iv: Option<SecretString>,
 }
 
 /// Content license response
@@ -237,6 +274,7 @@ pub struct ContentLicense {
 /// Higher-level structure combining ContentLicense with decryption keys
 ///
 /// Reference: DownloadOptions.Factory.cs:41-55 - LicenseInfo private class
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadLicense {
     /// DRM type
     pub drm_type: DrmType,
@@ -250,6 +288,9 @@ pub struct DownloadLicense {
 
     /// Download URL (extracted from content_metadata or DASH manifest)
     pub download_url: String,
+
+    /// Other CDN URLs for the same file, for mirror fallback
+    pub mirror_urls: Vec<String>,
 }
 
 /// Key data for decryption
@@ -265,13 +306,13 @@ pub struct KeyData {
     /// - AAX: 4 bytes (activation bytes)
     /// - AAXC: 16 bytes
     #[serde(rename = "key_part_1")]
-    pub key_part_1: Vec<u8>,
+    pub key_part_1: SecretBytes,
 
     /// Decryption key part 2 (optional)
     /// - AAX: None
     /// - AAXC: 16 bytes
     #[serde(rename = "key_part_2", skip_serializing_if = "Option::is_none")]
-    pub key_part_2: Option<Vec<u8>>,
+    pub key_part_2: Option<SecretBytes>,
 }
 
 impl KeyData {
@@ -301,8 +342,8 @@ impl KeyData {
         };
 
         Ok(Self {
-            key_part_1: key_bytes,
-            key_part_2: iv_bytes,
+            key_part_1: key_bytes.into(),
+            key_part_2: iv_bytes.map(SecretBytes::from),
         })
     }
 
@@ -331,8 +372,8 @@ impl KeyData {
             };
 
         Ok(Self {
-            key_part_1: key_bytes,
-            key_part_2: iv_bytes,
+            key_part_1: key_bytes.into(),
+            key_part_2: iv_bytes.map(SecretBytes::from),
         })
     }
 
@@ -374,6 +415,7 @@ impl KeyData {
             Decryptor,
         };
         use sha2::{Digest, Sha256};
+        use zeroize::Zeroize;
 
         // Decode base64 ciphertext
         // Reference: ContentLicenseDtoV10.cs:38
@@ -413,37 +455,36 @@ impl KeyData {
         // Reference: ContentLicenseDtoV10.cs:44
         let plaintext_no_nulls: Vec<u8> =
             plaintext.iter().copied().take_while(|&b| b != 0).collect();
+        buffer.zeroize();
 
-        let json_str = String::from_utf8(plaintext_no_nulls).map_err(|e| {
-            LibationError::InvalidInput(format!("Decrypted license is not valid UTF-8: {}", e))
-        })?;
-
-        // Debug: print decrypted JSON
-        eprintln!("🔍 DEBUG: Decrypted voucher JSON:\n{}\n", json_str);
+        let json_str: SecretString = String::from_utf8(plaintext_no_nulls)
+            .map_err(|e| {
+                LibationError::InvalidInput(format!("Decrypted license is not valid UTF-8: {}", e))
+            })?
+            .into();
 
         // Parse JSON to get Voucher
         // Reference: ContentLicenseDtoV10.cs:46 - VoucherDtoV10.FromJson(plainText)
-        let voucher: Voucher = serde_json::from_str(&json_str).map_err(|e| {
-            LibationError::InvalidInput(format!(
-                "Failed to parse decrypted voucher JSON: {}\nJSON was: {}",
-                e, json_str
-            ))
+        let voucher: Voucher = serde_json::from_str(json_str.expose_secret()).map_err(|e| {
+            // The JSON holds the key: keep it out of the error
+            LibationError::InvalidInput(format!("Failed to parse decrypted voucher JSON: {}", e))
         })?;
 
-        eprintln!(
+        trace_eprintln!(
             "🔍 DEBUG: Voucher key length: {}, iv length: {:?}",
-            voucher.key.len(),
-            voucher.iv.as_ref().map(|s| s.len())
+            voucher.key.expose_secret().len(),
+            voucher.iv.as_ref().map(|s| s.expose_secret().len())
         );
 
         // Convert voucher to KeyData
         // Check if key is hex (32 chars) or base64 (24 chars)
-        if voucher.key.len() == 32 {
+        let iv = voucher.iv.as_ref().map(SecretString::expose_secret);
+        if voucher.key.expose_secret().len() == 32 {
             // Hex-encoded (AAXC format from decrypted license_response)
-            Self::from_hex(&voucher.key, voucher.iv.as_deref())
+            Self::from_hex(voucher.key.expose_secret(), iv)
         } else {
             // Base64-encoded (from structured voucher field)
-            Self::from_base64(&voucher.key, voucher.iv.as_deref())
+            Self::from_base64(voucher.key.expose_secret(), iv)
         }
     }
 
@@ -500,6 +541,116 @@ pub enum FileType {
     Unknown,
 }
 
+impl FileType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileType::Aax => "aax",
+            FileType::Aaxc => "aaxc",
+            FileType::Dash => "dash",
+            FileType::Mp3 => "mp3",
+            FileType::Unknown => "unknown",
+        }
+    }
+
+    /// DRM type to put in the license request for this format
+    pub fn drm_type(&self) -> DrmType {
+        match self {
+            FileType::Dash => DrmType::Widevine,
+            FileType::Mp3 => DrmType::None,
+            FileType::Aax | FileType::Aaxc | FileType::Unknown => DrmType::Adrm,
+        }
+    }
+}
+
+impl std::str::FromStr for FileType {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "aax" => Ok(FileType::Aax),
+            "aaxc" => Ok(FileType::Aaxc),
+            "dash" => Ok(FileType::Dash),
+            "mp3" => Ok(FileType::Mp3),
+            "unknown" => Ok(FileType::Unknown),
+            _ => Err(LibationError::InvalidInput(format!("Invalid file type: {}", s))),
+        }
+    }
+}
+
+/// What the download/decrypt pipeline can handle on this device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatCapabilities {
+    /// Activation bytes are known for the account (needed for AAX)
+    pub has_activation_bytes: bool,
+
+    /// A Widevine CDM is available (needed for DASH)
+    pub supports_widevine: bool,
+}
+
+impl FormatCapabilities {
+    /// What this device's pipeline can handle for `account`
+    ///
+    /// AAX needs the account's activation bytes. No Widevine CDM is wired
+    /// into the download pipeline on any platform, so DASH is never offered.
+    pub fn for_account(account: &crate::api::auth::Account) -> Self {
+        Self {
+            has_activation_bytes: ActivationBytes::from_hex(account.decrypt_key.expose_secret()).is_ok(),
+            supports_widevine: false,
+        }
+    }
+
+    /// Check if the pipeline can fully handle a file type
+    pub fn can_handle(&self, file_type: FileType) -> bool {
+        match file_type {
+            FileType::Aax => self.has_activation_bytes,
+            FileType::Aaxc | FileType::Mp3 => true,
+            FileType::Dash => self.supports_widevine,
+            FileType::Unknown => false,
+        }
+    }
+}
+
+/// Order in which download formats are tried
+pub const FORMAT_PREFERENCE: [FileType; 3] = [FileType::Aax, FileType::Aaxc, FileType::Dash];
+
+/// Build the ordered list of formats worth requesting for a title
+///
+/// # Arguments
+/// * `available_codecs` - Codecs listed for the title (library `available_codecs`);
+///   an empty list means "unknown" and does not rule out AAX
+/// * `capabilities` - What the pipeline can handle
+pub fn negotiate_format_order(
+    available_codecs: &[CodecInfo],
+    capabilities: &FormatCapabilities,
+) -> Vec<FileType> {
+    let lists_aax = available_codecs.is_empty()
+        || available_codecs.iter().any(|c| {
+            c.name
+                .as_deref()
+                .map(|n| n.to_ascii_lowercase().starts_with("aax"))
+                .unwrap_or(false)
+        });
+
+    FORMAT_PREFERENCE
+        .iter()
+        .copied()
+        .filter(|ft| capabilities.can_handle(*ft))
+        .filter(|ft| *ft != FileType::Aax || lists_aax)
+        .collect()
+}
+
+/// License chosen by format negotiation
+pub struct NegotiatedLicense {
+    /// The granted license
+    pub license: DownloadLicense,
+
+    /// Format actually granted (from the returned keys)
+    pub file_type: FileType,
+
+    /// Why earlier formats in the preference order were skipped
+    pub fallback_reasons: Vec<String>,
+}
+
 // ============================================================================
 // API FUNCTIONS
 // ============================================================================
@@ -592,22 +743,7 @@ impl AudibleClient {
         prefer_widevine: bool,
     ) -> Result<DownloadLicense> {
         // Build license request
-        // Reference: DownloadOptions.Factory.cs:59-84
-        let request = LicenseRequest {
-            quality,
-            consumption_type: ConsumptionType::Download,
-            chapter_titles_type: Some(ChapterTitlesType::Tree),
-            request_spatial: Some(false),
-            aac_codec: Some(Codec::AacLc),
-            spatial_codec: Some(Codec::Ec3),
-            // API requires drm_type to be specified
-            // Reference: DownloadOptions.Factory.cs:68-112
-            drm_type: Some(if prefer_widevine {
-                DrmType::Widevine
-            } else {
-                DrmType::Adrm // Default to Audible DRM (AAX/AAXC)
-            }),
-        };
+        let request = LicenseRequest::for_download(quality, prefer_widevine);
 
         // Request license
         let license = self.get_download_license(asin, &request).await?;
@@ -620,12 +756,23 @@ impl AudibleClient {
             .offline_url
             .clone()
             .ok_or(LibationError::MissingOfflineUrl)?;
+        let mirror_urls: Vec<String> = license
+            .content_metadata
+            .content_url
+            .offline_urls
+            .iter()
+            .filter(|url| **url != download_url)
+            .cloned()
+            .collect();
 
         // Parse voucher to keys
         // Reference: DownloadOptions.Factory.cs:46-54 - DecryptionKeys = ToKeys(license.Voucher)
         let decryption_keys = if let Some(ref voucher) = license.voucher {
             // Structured voucher with key/iv fields (already decrypted)
-            let key_data = KeyData::from_base64(&voucher.key, voucher.iv.as_deref())?;
+            let key_data = KeyData::from_base64(
+                voucher.key.expose_secret(),
+                voucher.iv.as_ref().map(SecretString::expose_secret),
+            )?;
             Some(vec![key_data])
         } else if let Some(ref license_response) = license.license_response {
             // For AAXC files, the license_response is AES-encrypted
@@ -656,9 +803,74 @@ impl AudibleClient {
             content_metadata: license.content_metadata,
             decryption_keys,
             download_url,
+            mirror_urls,
         })
     }
 
+    /// Request a download license, negotiating the format
+    ///
+    /// Formats are tried in `negotiate_format_order` order. A format is
+    /// accepted only if the returned keys resolve to a file type the
+    /// pipeline can handle; otherwise the next format is tried. Auth
+    /// errors abort immediately since every fallback would fail the same way.
+    ///
+    /// # Arguments
+    /// * `asin` - Audible product ID
+    /// * `quality` - Download quality tier
+    /// * `available_codecs` - Codecs listed for the title (may be empty)
+    /// * `capabilities` - What the pipeline can handle
+    ///
+    /// # Errors
+    /// - `InvalidDrmFormat` - No supported format could be negotiated
+    pub async fn negotiate_download_license(
+        &self,
+        asin: &str,
+        quality: DownloadQuality,
+        available_codecs: &[CodecInfo],
+        capabilities: &FormatCapabilities,
+    ) -> Result<NegotiatedLicense> {
+        let mut fallback_reasons = Vec::new();
+        let mut requested: Vec<DrmType> = Vec::new();
+
+        for preferred in negotiate_format_order(available_codecs, capabilities) {
+            let drm_type = preferred.drm_type();
+            // AAX and AAXC share one Adrm request
+            if requested.contains(&drm_type) {
+                continue;
+            }
+            requested.push(drm_type);
+
+            match self
+                .build_download_license(asin, quality, drm_type.is_widevine())
+                .await
+            {
+                Ok(license) => {
+                    let file_type = Self::determine_file_type(&license);
+                    if capabilities.can_handle(file_type) {
+                        return Ok(NegotiatedLicense {
+                            license,
+                            file_type,
+                            fallback_reasons,
+                        });
+                    }
+                    fallback_reasons.push(format!(
+                        "{}: granted {} which is not supported",
+                        preferred.as_str(),
+                        file_type.as_str()
+                    ));
+                }
+                Err(e) if e.is_auth_error() => return Err(e),
+                Err(e) => fallback_reasons.push(format!("{}: {}", preferred.as_str(), e)),
+            }
+        }
+
+        Err(LibationError::InvalidDrmFormat(format!(
+            "No supported download format for {} ({})",
+            asin,
+            fallback_reasons.join("; ")
+        )))
+    }
+
     /// Get download URL for an audiobook
     ///
     /// # Reference
@@ -847,7 +1059,7 @@ mod tests {
     #[test]
     fn test_key_data_file_type_aax() {
         let key_data = KeyData {
-            key_part_1: vec![0x01, 0x02, 0x03, 0x04], // 4 bytes
+            key_part_1: vec![0x01, 0x02, 0x03, 0x04].into(), // 4 bytes
             key_part_2: None,
         };
 
@@ -857,8 +1069,8 @@ mod tests {
     #[test]
     fn test_key_data_file_type_aaxc() {
         let key_data = KeyData {
-            key_part_1: vec![0; 16],       // 16 bytes
-            key_part_2: Some(vec![0; 16]), // 16 bytes
+            key_part_1: vec![0; 16].into(),       // 16 bytes
+            key_part_2: Some(vec![0; 16].into()), // 16 bytes
         };
 
         assert_eq!(key_data.file_type(DrmType::Adrm), FileType::Aaxc);
@@ -867,8 +1079,8 @@ mod tests {
     #[test]
     fn test_key_data_file_type_widevine() {
         let key_data = KeyData {
-            key_part_1: vec![0; 16],
-            key_part_2: Some(vec![0; 16]),
+            key_part_1: vec![0; 16].into(),
+            key_part_2: Some(vec![0; 16].into()),
         };
 
         assert_eq!(key_data.file_type(DrmType::Widevine), FileType::Dash);
@@ -882,8 +1094,46 @@ mod tests {
         let iv = general_purpose::STANDARD.encode(b"testiv1234567890");
 
         let key_data = KeyData::from_base64(&key, Some(&iv)).unwrap();
-        assert_eq!(key_data.key_part_1, b"testkey1234567890");
-        assert_eq!(key_data.key_part_2, Some(b"testiv1234567890".to_vec()));
+        assert_eq!(key_data.key_part_1.expose_secret(), b"testkey1234567890");
+        assert_eq!(key_data.key_part_2, Some(b"testiv1234567890".to_vec().into()));
+    }
+
+    fn codec(name: &str) -> CodecInfo {
+        CodecInfo {
+            name: Some(name.to_string()),
+            enhanced_codec: None,
+            format: None,
+            is_kindle_enhanced: None,
+        }
+    }
+
+    #[test]
+    fn test_negotiate_format_order() {
+        let caps = FormatCapabilities::default();
+        assert_eq!(negotiate_format_order(&[], &caps), vec![FileType::Aaxc]);
+
+        let caps = FormatCapabilities {
+            has_activation_bytes: true,
+            supports_widevine: true,
+        };
+        assert_eq!(
+            negotiate_format_order(&[codec("aax_44_128"), codec("mp4_44_128")], &caps),
+            vec![FileType::Aax, FileType::Aaxc, FileType::Dash]
+        );
+
+        // Title without an AAX codec skips AAX even with activation bytes
+        assert_eq!(
+            negotiate_format_order(&[codec("mp4_44_128")], &caps),
+            vec![FileType::Aaxc, FileType::Dash]
+        );
+    }
+
+    #[test]
+    fn test_file_type_round_trip() {
+        for ft in [FileType::Aax, FileType::Aaxc, FileType::Dash, FileType::Mp3] {
+            assert_eq!(ft.as_str().parse::<FileType>().unwrap(), ft);
+        }
+        assert!("flac".parse::<FileType>().is_err());
     }
 
     #[test]
@@ -1002,6 +1252,7 @@ mod tests {
                     // AAX: 4-byte activation bytes
                     let hex_bytes = keys[0]
                         .key_part_1
+                        .expose_secret()
                         .iter()
                         .map(|b| format!("{:02x}", b))
                         .collect::<String>();
@@ -1010,6 +1261,7 @@ mod tests {
                     // AAXC: 16-byte key
                     let hex_key = keys[0]
                         .key_part_1
+                        .expose_secret()
                         .iter()
                         .map(|b| format!("{:02x}", b))
                         .collect::<String>();
@@ -1020,6 +1272,7 @@ mod tests {
                     println!("   Key 2 Length: {} bytes", key2.len());
                     if key2.len() == 16 {
                         let hex_iv = key2
+                            .expose_secret()
                             .iter()
                             .map(|b| format!("{:02x}", b))
                             .collect::<String>();
@@ -1148,6 +1401,7 @@ mod tests {
             if !keys.is_empty() && keys[0].key_part_1.len() == 4 {
                 let hex = keys[0]
                     .key_part_1
+                    .expose_secret()
                     .iter()
                     .map(|b| format!("{:02x}", b))
                     .collect::<String>();
@@ -1179,4 +1433,39 @@ mod tests {
         println!("   • Use decryption keys to decrypt AAX/AAXC file");
         println!("   • Convert to M4B using FFmpeg");
     }
+
+    #[tokio::test]
+    async fn test_license_request_through_transport() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use std::sync::Arc;
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(200, serde_json::json!({
+            "content_license": {
+                "drm_type": "None",
+                "content_metadata": {
+                    "content_url": { "offline_url": "https://cds.audible.com/B001.mp3" }
+                }
+            }
+        }));
+        transport.push_json(200, serde_json::json!({ "content_license": { "drm_type": "Adrm" } }));
+        let client = mock_client(&transport);
+
+        let license = client.get_download_license("B001", &LicenseRequest::default()).await.unwrap();
+        assert_eq!(license.drm_type, DrmType::None);
+        assert_eq!(
+            license.content_metadata.content_url.offline_url.as_deref(),
+            Some("https://cds.audible.com/B001.mp3")
+        );
+
+        let request = &transport.requests()[0];
+        assert_eq!(request.url, "https://api.audible.com/1.0/content/B001/licenserequest");
+        let body: serde_json::Value = serde_json::from_slice(request.body.as_ref().unwrap()).unwrap();
+        assert_eq!(body["quality"], serde_json::to_value(LicenseRequest::default().quality).unwrap());
+
+        assert!(matches!(
+            client.get_download_license("B002", &LicenseRequest::default()).await,
+            Err(LibationError::InvalidApiResponse { .. })
+        ));
+    }
 }
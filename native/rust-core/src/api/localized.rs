@@ -0,0 +1,140 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Product titles from another marketplace
+//!
+//! Catalog metadata is localized per marketplace: an English audiobook
+//! bought on audible.de is listed under its German title. The app can fetch
+//! titles from a preferred marketplace (the `metadata.preferred_locale`
+//! setting, see `storage::localized_titles`) and show those instead.
+//!
+//! # Endpoint
+//! **GET** `/1.0/catalog/products?asin=A,B,C` on the preferred marketplace's
+//! API host. Products the marketplace doesn't sell are omitted from the
+//! response; they are reported with no title so callers can remember that
+//! they were checked.
+
+use crate::api::auth::Locale;
+use crate::api::client::{AudibleClient, BATCH_SIZE};
+use crate::api::response_groups::ResponseGroups;
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Title and subtitle of a product in one marketplace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalizedTitle {
+    pub asin: String,
+
+    /// Marketplace country code (e.g. "us")
+    pub locale: String,
+
+    /// None if the marketplace doesn't sell the product
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProductTitle {
+    asin: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    subtitle: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProductsResponse {
+    #[serde(default)]
+    products: Vec<ProductTitle>,
+}
+
+impl AudibleClient {
+    /// Fetch product titles from another marketplace
+    ///
+    /// # Arguments
+    /// * `locale` - Marketplace to ask
+    /// * `asins` - Products to look up (any number; batched by `BATCH_SIZE`)
+    ///
+    /// # Returns
+    /// One entry per requested ASIN, in request order
+    pub async fn get_localized_titles(&self, locale: &Locale, asins: &[String]) -> Result<Vec<LocalizedTitle>> {
+        let marketplace = self.for_marketplace(locale);
+        let mut titles = Vec::with_capacity(asins.len());
+
+        for batch in asins.chunks(BATCH_SIZE) {
+            let endpoint = format!(
+                "/1.0/catalog/products?asin={}&response_groups={}",
+                urlencoding::encode(&batch.join(",")),
+                ResponseGroups::MINIMAL
+            );
+            let response: serde_json::Value = marketplace.get(&endpoint).await?;
+            let response: ProductsResponse = serde_json::from_value(response.clone()).map_err(|e| {
+                LibationError::InvalidApiResponse {
+                    message: format!("Failed to parse localized products: {}", e),
+                    response_body: Some(response.to_string()),
+                }
+            })?;
+
+            titles.extend(localized_titles(locale, batch, response.products));
+        }
+
+        Ok(titles)
+    }
+}
+
+/// Pair requested ASINs with the returned products
+fn localized_titles(locale: &Locale, asins: &[String], products: Vec<ProductTitle>) -> Vec<LocalizedTitle> {
+    asins
+        .iter()
+        .map(|asin| {
+            let product = products
+                .iter()
+                .find(|p| &p.asin == asin)
+                .filter(|p| p.title.as_deref().is_some_and(|t| !t.trim().is_empty()));
+            LocalizedTitle {
+                asin: asin.clone(),
+                locale: locale.country_code.clone(),
+                title: product.and_then(|p| p.title.clone()),
+                subtitle: product.and_then(|p| p.subtitle.clone()).filter(|s| !s.is_empty()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localized_titles() {
+        let response: ProductsResponse = serde_json::from_str(
+            r#"{"products": [
+                {"asin": "B0TWO", "title": "Project Hail Mary", "subtitle": ""},
+                {"asin": "B0ONE", "title": "Der Marsianer", "subtitle": "Rettet Mark Watney"}
+            ]}"#,
+        )
+        .unwrap();
+        let asins = ["B0ONE", "B0TWO", "B0MISSING"].map(String::from);
+
+        let titles = localized_titles(&Locale::de(), &asins, response.products);
+        assert_eq!(titles.len(), 3);
+        assert_eq!(titles[0].title.as_deref(), Some("Der Marsianer"));
+        assert_eq!(titles[0].subtitle.as_deref(), Some("Rettet Mark Watney"));
+        assert_eq!(titles[1].subtitle, None);
+        assert_eq!(titles[2].title, None);
+        assert!(titles.iter().all(|t| t.locale == "de"));
+    }
+}
@@ -29,16 +29,30 @@
 //! - External dependency: AudibleApi NuGet package (see Libation references)
 
 pub mod auth;
+pub mod debug_capture;
+pub mod recording;
 pub mod client;
+pub mod transport;
 pub mod library;
+pub mod preflight;
+pub mod health;
 pub mod content;
 pub mod license;
 pub mod registration;
 pub mod customer;
+pub mod whispersync;
+pub mod annotations;
+pub mod localized;
+pub mod covers;
+pub mod storefront;
+pub mod pricing;
+pub mod response_groups;
 
 // Re-export commonly used types
 pub use auth::{Account, Identity};
 pub use client::{AudibleClient, AudibleDomain, ClientConfig};
+pub use transport::{HttpTransport, MockTransport, ReqwestTransport};
 pub use library::LibraryOptions;
+pub use response_groups::{ResponseGroup, ResponseGroups};
 pub use registration::{RegistrationResponse, RegistrationData};
 pub use customer::CustomerInformation;
@@ -0,0 +1,392 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Pre-sync account checks
+//!
+//! A revoked token or a mismatched marketplace used to surface as an error
+//! deep inside the first page fetch. `check_sync_readiness` runs before any
+//! pages are fetched and reports, check by check, whether the account can
+//! sync and what the user should do if not:
+//!
+//! 1. `identity` - the account is signed in and has its tokens
+//! 2. `locale` - the marketplace is one the app knows, with its own domain
+//! 3. `token` - the access token is valid, refreshing it if it's about to
+//!    expire (a rejected refresh means the device was deregistered)
+//! 4. `api_access` - one-item library request against the marketplace
+//!
+//! Checks after a failed one are skipped. Failures carry a `Remediation`
+//! the app can turn into a button (sign in again, pick the marketplace,
+//! check the connection, try later).
+
+use crate::api::auth::{ensure_valid_token_with_clock, Account, Locale};
+use crate::api::client::AudibleClient;
+use crate::api::library::{LibraryOptions, LibraryResponse};
+use crate::api::response_groups::ResponseGroups;
+use crate::clock::{AppClock, Clock};
+use crate::error::{LibationError, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Refresh tokens expiring within this many minutes (as sync does)
+const REFRESH_THRESHOLD_MINUTES: i64 = 30;
+
+/// A pre-sync check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightCheck {
+    Identity,
+    Locale,
+    Token,
+    ApiAccess,
+}
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// Not run because an earlier check failed
+    Skipped,
+}
+
+/// What the user can do about a failed check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Remediation {
+    /// Sign in to Audible again
+    SignIn,
+    /// Sign in again, choosing the marketplace the account belongs to
+    ChooseMarketplace,
+    /// Check the network connection
+    CheckConnection,
+    /// Audible is busy or failing; try again later
+    RetryLater,
+}
+
+impl Remediation {
+    /// Step to show the user
+    pub fn instructions(&self) -> &'static str {
+        match self {
+            Remediation::SignIn => "Sign in to your Audible account again.",
+            Remediation::ChooseMarketplace => {
+                "Sign in again and choose the Audible marketplace your account was created in."
+            }
+            Remediation::CheckConnection => "Check your internet connection and try again.",
+            Remediation::RetryLater => "Audible isn't responding right now. Try again in a few minutes.",
+        }
+    }
+
+    /// What to do about an error from a token refresh or API request
+    pub fn for_error(error: &LibationError) -> Self {
+        match error {
+            e if e.is_auth_error() => Remediation::SignIn,
+            LibationError::ApiRequestFailed {
+                status_code: Some(401 | 403),
+                ..
+            } => Remediation::SignIn,
+            LibationError::NetworkError { .. } | LibationError::Timeout(_) => Remediation::CheckConnection,
+            _ => Remediation::RetryLater,
+        }
+    }
+}
+
+/// Result of one check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CheckResult {
+    pub check: PreflightCheck,
+    pub status: CheckStatus,
+    /// What was found, for failed checks
+    pub message: Option<String>,
+    pub remediation: Option<Remediation>,
+}
+
+impl CheckResult {
+    fn passed(check: PreflightCheck) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Passed,
+            message: None,
+            remediation: None,
+        }
+    }
+
+    fn failed(check: PreflightCheck, message: impl Into<String>, remediation: Remediation) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Failed,
+            message: Some(message.into()),
+            remediation: Some(remediation),
+        }
+    }
+
+    fn skipped(check: PreflightCheck) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Skipped,
+            message: None,
+            remediation: None,
+        }
+    }
+}
+
+/// Whether an account can sync
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SyncReadiness {
+    pub ready: bool,
+    /// All checks, in order
+    pub checks: Vec<CheckResult>,
+    /// First failure's message
+    pub message: Option<String>,
+    /// First failure's remediation
+    pub remediation: Option<Remediation>,
+    /// `remediation.instructions()`
+    pub instructions: Option<String>,
+    /// Account JSON to sync with (tokens refreshed), once the token check passed
+    #[serde(skip)]
+    pub account_json: Option<String>,
+}
+
+impl SyncReadiness {
+    fn from_checks(checks: Vec<CheckResult>, account_json: Option<String>) -> Self {
+        let failure = checks.iter().find(|c| c.status == CheckStatus::Failed);
+        let remediation = failure.and_then(|c| c.remediation);
+        Self {
+            ready: failure.is_none(),
+            message: failure.and_then(|c| c.message.clone()),
+            remediation,
+            instructions: remediation.map(|r| r.instructions().to_string()),
+            account_json,
+            checks,
+        }
+    }
+
+    /// The account to sync with
+    ///
+    /// # Errors
+    /// InvalidState if the account isn't ready
+    pub fn account(&self) -> Result<Account> {
+        let json = self
+            .account_json
+            .as_deref()
+            .filter(|_| self.ready)
+            .ok_or_else(|| LibationError::InvalidState("Account isn't ready to sync".to_string()))?;
+        serde_json::from_str(json).map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))
+    }
+}
+
+/// Check that an account can sync, refreshing its token if needed
+///
+/// Refreshed tokens are saved like `ensure_valid_token` does.
+///
+/// # Errors
+/// Only for unusable input (invalid account JSON) or database failures;
+/// everything else is reported in the readiness
+pub async fn check_sync_readiness(pool: &SqlitePool, account_json: &str) -> Result<SyncReadiness> {
+    check_sync_readiness_with(pool, account_json, &AppClock, AudibleClient::new).await
+}
+
+/// `check_sync_readiness` against a given clock, with `make_client`
+/// building the client for the API check
+pub async fn check_sync_readiness_with(
+    pool: &SqlitePool,
+    account_json: &str,
+    clock: &dyn Clock,
+    make_client: impl FnOnce(Account) -> Result<AudibleClient>,
+) -> Result<SyncReadiness> {
+    let account: Account = serde_json::from_str(account_json)
+        .map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
+
+    let mut checks = vec![check_identity(&account)];
+    if checks[0].status == CheckStatus::Passed {
+        checks.push(check_locale(&account));
+    }
+
+    let mut refreshed = None;
+    if checks.iter().all(|c| c.status == CheckStatus::Passed) {
+        match ensure_valid_token_with_clock(pool, account_json, REFRESH_THRESHOLD_MINUTES, clock).await {
+            Ok(json) => {
+                checks.push(CheckResult::passed(PreflightCheck::Token));
+                refreshed = Some(json);
+            }
+            Err(e @ (LibationError::SqlxError(_) | LibationError::DatabaseError(_))) => return Err(e),
+            Err(e) => checks.push(CheckResult::failed(
+                PreflightCheck::Token,
+                format!("Couldn't refresh the access token: {}", e),
+                Remediation::for_error(&e),
+            )),
+        }
+    }
+
+    if let Some(json) = &refreshed {
+        let account: Account = serde_json::from_str(json)
+            .map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
+        let result = match make_client(account) {
+            Ok(client) => client.check_api_access().await,
+            Err(e) => CheckResult::failed(PreflightCheck::ApiAccess, e.to_string(), Remediation::for_error(&e)),
+        };
+        checks.push(result);
+    }
+
+    for check in [
+        PreflightCheck::Identity,
+        PreflightCheck::Locale,
+        PreflightCheck::Token,
+        PreflightCheck::ApiAccess,
+    ] {
+        if !checks.iter().any(|c| c.check == check) {
+            checks.push(CheckResult::skipped(check));
+        }
+    }
+
+    Ok(SyncReadiness::from_checks(checks, refreshed))
+}
+
+/// Signed in, with the tokens requests need
+fn check_identity(account: &Account) -> CheckResult {
+    let Some(identity) = &account.identity else {
+        return CheckResult::failed(PreflightCheck::Identity, "Account isn't signed in", Remediation::SignIn);
+    };
+    let missing: Vec<&str> = [
+        ("access token", identity.access_token.token.is_empty()),
+        ("refresh token", identity.refresh_token.is_empty()),
+    ]
+    .into_iter()
+    .filter_map(|(name, is_missing)| is_missing.then_some(name))
+    .collect();
+
+    if missing.is_empty() {
+        CheckResult::passed(PreflightCheck::Identity)
+    } else {
+        CheckResult::failed(
+            PreflightCheck::Identity,
+            format!("Account is missing its {}", missing.join(", ")),
+            Remediation::SignIn,
+        )
+    }
+}
+
+/// A known marketplace whose domain matches its country
+fn check_locale(account: &Account) -> CheckResult {
+    let Some(locale) = account.locale() else {
+        return CheckResult::failed(PreflightCheck::Locale, "Account has no marketplace", Remediation::ChooseMarketplace);
+    };
+    match Locale::from_country_code(&locale.country_code) {
+        None => CheckResult::failed(
+            PreflightCheck::Locale,
+            format!("Unknown marketplace: {:?}", locale.country_code),
+            Remediation::ChooseMarketplace,
+        ),
+        Some(known) if !known.domain.eq_ignore_ascii_case(&locale.domain) => CheckResult::failed(
+            PreflightCheck::Locale,
+            format!(
+                "Marketplace {} uses {}, but the account points at {}",
+                known.country_code, known.domain, locale.domain
+            ),
+            Remediation::ChooseMarketplace,
+        ),
+        Some(_) => CheckResult::passed(PreflightCheck::Locale),
+    }
+}
+
+impl AudibleClient {
+    /// Fetch one library item, to check the token works in this marketplace
+    pub async fn check_api_access(&self) -> CheckResult {
+        let options = LibraryOptions {
+            number_of_results_per_page: 1,
+            response_groups: ResponseGroups::MINIMAL,
+            image_sizes: None,
+            ..Default::default()
+        };
+        match self.get_with_query::<LibraryResponse, _>("/1.0/library", &options).await {
+            Ok(_) => CheckResult::passed(PreflightCheck::ApiAccess),
+            Err(e) => CheckResult::failed(
+                PreflightCheck::ApiAccess,
+                format!("Audible rejected the library request: {}", e),
+                Remediation::for_error(&e),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::transport::{mock_client, MockTransport};
+    use crate::storage::Database;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_sync_readiness() {
+        let db = Database::new_in_memory().await.unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let account = mock_client(&transport).account().lock().await.clone();
+        let account_json = serde_json::to_string(&account).unwrap();
+        let client_for = |transport: &Arc<MockTransport>| {
+            let client = mock_client(transport);
+            move |_: Account| Ok(client)
+        };
+
+        transport.push_json(200, serde_json::json!({ "items": [], "total_results": 0 }));
+        let readiness = check_sync_readiness_with(db.pool(), &account_json, &AppClock, client_for(&transport))
+            .await
+            .unwrap();
+        assert!(readiness.ready, "{:?}", readiness);
+        assert_eq!(readiness.account().unwrap().account_id, account.account_id);
+        let url = &transport.requests()[0].url;
+        assert!(url.contains("num_results=1") && url.contains("response_groups=product_desc"));
+
+        // Revoked token: the API check fails with sign-in
+        transport.push_json(401, serde_json::json!({ "message": "Unauthorized" }));
+        let readiness = check_sync_readiness_with(db.pool(), &account_json, &AppClock, client_for(&transport))
+            .await
+            .unwrap();
+        assert!(!readiness.ready);
+        assert_eq!(readiness.remediation, Some(Remediation::SignIn));
+        assert_eq!(readiness.checks[3].check, PreflightCheck::ApiAccess);
+        assert_eq!(readiness.checks[3].status, CheckStatus::Failed);
+        assert!(readiness.account().is_err());
+
+        // Mismatched marketplace: caught offline, later checks skipped
+        let mut mismatched = account.clone();
+        mismatched.identity.as_mut().unwrap().locale.domain = "audible.de".to_string();
+        let requests = transport.requests().len();
+        let readiness = check_sync_readiness_with(
+            db.pool(),
+            &serde_json::to_string(&mismatched).unwrap(),
+            &AppClock,
+            client_for(&transport),
+        )
+        .await
+        .unwrap();
+        assert_eq!(readiness.remediation, Some(Remediation::ChooseMarketplace));
+        let statuses: Vec<CheckStatus> = readiness.checks.iter().map(|c| c.status).collect();
+        assert_eq!(
+            statuses,
+            [CheckStatus::Passed, CheckStatus::Failed, CheckStatus::Skipped, CheckStatus::Skipped]
+        );
+        assert_eq!(transport.requests().len(), requests);
+
+        let mut signed_out = account;
+        signed_out.identity = None;
+        let readiness = check_sync_readiness(db.pool(), &serde_json::to_string(&signed_out).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(readiness.remediation, Some(Remediation::SignIn));
+        assert_eq!(readiness.instructions.as_deref(), Some(Remediation::SignIn.instructions()));
+    }
+}
@@ -0,0 +1,382 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Prices and credit costs of catalog products
+//!
+//! Catalog responses include a `price` object when the `price` response
+//! group is requested:
+//!
+//! ```json
+//! "price": {
+//!   "credit_price": 1.0,
+//!   "is_buyable": true,
+//!   "is_credit_price_eligible": true,
+//!   "is_free_eligible": false,
+//!   "list_price": { "base": 24.95, "currency_code": "USD", "type": "list" },
+//!   "lowest_price": { "base": 14.95, "currency_code": "USD", "type": "sale" }
+//! }
+//! ```
+//!
+//! `CatalogPrice` says whether a title costs a credit, money, nothing, or
+//! can't be bought, with amounts formatted for the marketplace's currency
+//! (`$14.95`, `14,95 €`, `￥1,500`). Products without a `price` object
+//! (older marketplaces, podcasts) have no `CatalogPrice`.
+//!
+//! Prices are cached for `PRICE_CACHE_TTL` per marketplace and ASIN, so
+//! scrolling back through results doesn't refetch them; prices change
+//! with sales, so the cache is short-lived and in memory only.
+
+use crate::api::auth::Locale;
+use crate::api::client::AudibleClient;
+use crate::api::response_groups::{ResponseGroup, ResponseGroups};
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a fetched price is reused
+pub const PRICE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Prices with the time they were seen, by (country code, ASIN)
+type PriceCache = HashMap<(String, String), (Instant, CatalogPrice)>;
+
+static CACHE: Mutex<Option<PriceCache>> = Mutex::new(None);
+
+/// How a title is paid for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostKind {
+    /// Costs credits (it may be bought with money instead)
+    Credits,
+    /// Only bought with money
+    Money,
+    /// Free, or included in the membership
+    Free,
+    /// Can't be bought in this marketplace
+    NotForSale,
+}
+
+/// An amount in a currency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: f64,
+    /// ISO 4217 code (e.g. "EUR")
+    pub currency_code: String,
+    /// Formatted for display (e.g. "14,95 €")
+    pub display: String,
+}
+
+impl Money {
+    pub fn new(amount: f64, currency_code: &str) -> Self {
+        Self {
+            amount,
+            currency_code: currency_code.to_string(),
+            display: format_money(amount, currency_code),
+        }
+    }
+}
+
+/// What a catalog product costs in a marketplace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogPrice {
+    pub asin: String,
+    /// Marketplace country code
+    pub locale: String,
+    pub cost: CostKind,
+    /// Credits it costs, when it can be bought with credits
+    pub credits: Option<u32>,
+    /// Regular price
+    pub list_price: Option<Money>,
+    /// Sale or member price, when below the list price
+    pub sale_price: Option<Money>,
+    /// Short label: "1 credit", "$14.95", "Free", "Not for sale"
+    pub display: String,
+}
+
+impl CatalogPrice {
+    /// Price the title sells for now
+    pub fn current_price(&self) -> Option<&Money> {
+        self.sale_price.as_ref().or(self.list_price.as_ref())
+    }
+}
+
+/// Format `amount` the way `currency_code`'s marketplaces write prices
+pub fn format_money(amount: f64, currency_code: &str) -> String {
+    // (symbol with its spacing, symbol goes first, decimals, decimal comma)
+    let (symbol, prefix, decimals, comma) = match currency_code {
+        "USD" => ("$", true, 2, false),
+        "CAD" => ("CA$", true, 2, false),
+        "AUD" => ("A$", true, 2, false),
+        "GBP" => ("£", true, 2, false),
+        "INR" => ("₹", true, 2, false),
+        "JPY" => ("￥", true, 0, false),
+        "EUR" => ("€", false, 2, true),
+        "BRL" => ("R$ ", true, 2, true),
+        other => (other, false, 2, false),
+    };
+
+    let fixed = format!("{:.*}", decimals, amount.abs());
+    let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+    let (group, point) = if comma { ('.', ',') } else { (',', '.') };
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(group);
+        }
+        grouped.push(digit);
+    }
+    if !fraction.is_empty() {
+        grouped.push(point);
+        grouped.push_str(fraction);
+    }
+    let sign = if amount < 0.0 { "-" } else { "" };
+
+    if prefix {
+        format!("{}{}{}", sign, symbol, grouped)
+    } else {
+        format!("{}{} {}", sign, grouped, symbol)
+    }
+}
+
+/// Parse a price object amount (`{"base": 14.95, "currency_code": "USD"}`)
+fn money(value: Option<&Value>) -> Option<Money> {
+    let value = value?;
+    let amount = value.get("base")?.as_f64()?;
+    let currency = value.get("currency_code")?.as_str()?;
+    Some(Money::new(amount, currency))
+}
+
+/// Price of a catalog product (its JSON, with the `price` group) in `locale`
+///
+/// # Returns
+/// None when the product has no `price` object
+pub fn price_from_product(product: &Value, locale: &Locale) -> Option<CatalogPrice> {
+    let asin = product.get("asin")?.as_str()?;
+    let price = product.get("price")?.as_object()?;
+    let flag = |name: &str| price.get(name).and_then(Value::as_bool).unwrap_or(false);
+
+    let list_price = money(price.get("list_price"));
+    let sale_price = money(price.get("lowest_price")).filter(|lowest| {
+        list_price.as_ref().is_none_or(|list| lowest.amount < list.amount)
+    });
+    let credits = price
+        .get("credit_price")
+        .and_then(Value::as_f64)
+        .filter(|&credits| credits > 0.0 && flag("is_credit_price_eligible"))
+        .map(|credits| credits.round() as u32);
+
+    let free = flag("is_free_eligible")
+        || sale_price.as_ref().or(list_price.as_ref()).is_some_and(|m| m.amount == 0.0);
+    let (cost, display) = if free {
+        (CostKind::Free, "Free".to_string())
+    } else if let Some(credits) = credits {
+        let label = if credits == 1 { "1 credit".to_string() } else { format!("{} credits", credits) };
+        (CostKind::Credits, label)
+    } else if !price.get("is_buyable").and_then(Value::as_bool).unwrap_or(true) {
+        (CostKind::NotForSale, "Not for sale".to_string())
+    } else if let Some(current) = sale_price.as_ref().or(list_price.as_ref()) {
+        (CostKind::Money, current.display.clone())
+    } else {
+        (CostKind::NotForSale, "Not for sale".to_string())
+    };
+
+    Some(CatalogPrice {
+        asin: asin.to_string(),
+        locale: locale.country_code.clone(),
+        cost,
+        credits,
+        list_price,
+        sale_price,
+        display,
+    })
+}
+
+/// Price seen for `asin` in the `country_code` marketplace within
+/// `PRICE_CACHE_TTL`
+pub fn cached_price(country_code: &str, asin: &str) -> Option<CatalogPrice> {
+    let cache = CACHE.lock().unwrap();
+    cache
+        .as_ref()
+        .and_then(|c| c.get(&(country_code.to_string(), asin.to_string())))
+        .filter(|(seen, _)| seen.elapsed() < PRICE_CACHE_TTL)
+        .map(|(_, price)| price.clone())
+}
+
+/// Remember a price for `cached_price`
+pub fn remember_price(price: &CatalogPrice) {
+    let mut cache = CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(HashMap::new);
+    cache.retain(|_, (seen, _)| seen.elapsed() < PRICE_CACHE_TTL);
+    cache.insert((price.locale.clone(), price.asin.clone()), (Instant::now(), price.clone()));
+}
+
+impl AudibleClient {
+    /// Prices of catalog products in this client's marketplace
+    ///
+    /// Prices seen in the last `PRICE_CACHE_TTL` are reused; the rest are
+    /// fetched in one request.
+    ///
+    /// # Endpoint
+    /// `GET /1.0/catalog/products?asin=...&response_groups=price`
+    ///
+    /// # Returns
+    /// Prices in the order of `asins`; products without a price are left out
+    ///
+    /// # Errors
+    /// - `InvalidInput` - More than 50 ASINs
+    /// - `ApiRequestFailed` - API request failed
+    pub async fn get_catalog_prices(&self, asins: &[String]) -> Result<Vec<CatalogPrice>> {
+        if asins.len() > crate::api::client::BATCH_SIZE {
+            return Err(LibationError::invalid_input(format!(
+                "Too many ASINs in batch: {} (max {})",
+                asins.len(),
+                crate::api::client::BATCH_SIZE
+            )));
+        }
+
+        let marketplace = self.marketplace()?;
+        let mut prices: HashMap<String, CatalogPrice> = asins
+            .iter()
+            .filter_map(|asin| cached_price(&marketplace.country_code, asin).map(|p| (asin.clone(), p)))
+            .collect();
+
+        let missing: Vec<&str> = asins
+            .iter()
+            .filter(|asin| !prices.contains_key(*asin))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            let url = format!(
+                "/1.0/catalog/products?asin={}&response_groups={}",
+                urlencoding::encode(&missing.join(",")),
+                ResponseGroups::empty().with(ResponseGroup::Price)
+            );
+            let response: Value = self.get(&url).await?;
+            let products = response.get("products").and_then(Value::as_array).ok_or_else(|| {
+                LibationError::InvalidApiResponse {
+                    message: "Missing or invalid 'products' array in response".to_string(),
+                    response_body: Some(response.to_string()),
+                }
+            })?;
+            for price in products.iter().filter_map(|p| price_from_product(p, &marketplace)) {
+                remember_price(&price);
+                prices.insert(price.asin.clone(), price);
+            }
+        }
+
+        Ok(asins.iter().filter_map(|asin| prices.remove(asin)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_format_money() {
+        assert_eq!(format_money(14.95, "USD"), "$14.95");
+        assert_eq!(format_money(1234.5, "GBP"), "£1,234.50");
+        assert_eq!(format_money(14.95, "EUR"), "14,95 €");
+        assert_eq!(format_money(1234.0, "EUR"), "1.234,00 €");
+        assert_eq!(format_money(1500.0, "JPY"), "￥1,500");
+        assert_eq!(format_money(9.99, "CAD"), "CA$9.99");
+        assert_eq!(format_money(29.9, "BRL"), "R$ 29,90");
+    }
+
+    #[test]
+    fn test_price_from_product() {
+        let product = |price: Value| json!({ "asin": "B002V5D7B0", "price": price });
+
+        let credit = price_from_product(
+            &product(json!({
+                "credit_price": 1.0,
+                "is_buyable": true,
+                "is_credit_price_eligible": true,
+                "list_price": { "base": 24.95, "currency_code": "EUR" },
+                "lowest_price": { "base": 14.95, "currency_code": "EUR" }
+            })),
+            &Locale::de(),
+        )
+        .unwrap();
+        assert_eq!(credit.cost, CostKind::Credits);
+        assert_eq!(credit.credits, Some(1));
+        assert_eq!(credit.display, "1 credit");
+        assert_eq!(credit.locale, "de");
+        assert_eq!(credit.current_price().unwrap().display, "14,95 €");
+
+        let cash = price_from_product(
+            &product(json!({
+                "credit_price": 0.0,
+                "is_buyable": true,
+                "is_credit_price_eligible": false,
+                "list_price": { "base": 4.99, "currency_code": "USD" },
+                "lowest_price": { "base": 4.99, "currency_code": "USD" }
+            })),
+            &Locale::us(),
+        )
+        .unwrap();
+        assert_eq!(cash.cost, CostKind::Money);
+        assert_eq!(cash.display, "$4.99");
+        assert_eq!(cash.sale_price, None);
+
+        let free = price_from_product(&product(json!({ "is_free_eligible": true })), &Locale::us()).unwrap();
+        assert_eq!(free.cost, CostKind::Free);
+
+        let unavailable = price_from_product(&product(json!({ "is_buyable": false })), &Locale::us()).unwrap();
+        assert_eq!(unavailable.cost, CostKind::NotForSale);
+
+        assert!(price_from_product(&json!({ "asin": "B0NOPRICE" }), &Locale::us()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_catalog_prices_uses_cache() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use std::sync::Arc;
+
+        let transport = Arc::new(MockTransport::new());
+        transport.push_json(200, json!({
+            "products": [{
+                "asin": "B0PRICE001",
+                "price": {
+                    "credit_price": 1.0,
+                    "is_credit_price_eligible": true,
+                    "list_price": { "base": 19.99, "currency_code": "USD" }
+                }
+            }, { "asin": "B0PRICE002" }]
+        }));
+        let client = mock_client(&transport);
+        let asins = vec!["B0PRICE001".to_string(), "B0PRICE002".to_string()];
+
+        let prices = client.get_catalog_prices(&asins).await.unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].display, "1 credit");
+        assert!(transport.requests()[0].url.contains("response_groups=price"));
+
+        // Served from the cache; only the unpriced product is asked again
+        transport.push_json(200, json!({ "products": [] }));
+        let again = client.get_catalog_prices(&asins).await.unwrap();
+        assert_eq!(again, prices);
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].url.contains("asin=B0PRICE002&"));
+
+        let too_many: Vec<String> = (0..51).map(|i| format!("B0{:08}", i)).collect();
+        assert!(client.get_catalog_prices(&too_many).await.is_err());
+    }
+}
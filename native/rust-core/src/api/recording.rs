@@ -0,0 +1,570 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Opt-in recording of API exchanges, replayable in tests
+//!
+//! Sync and license bugs usually depend on what one account's library
+//! returns. With recording on, every `AudibleClient` built by `with_config`
+//! keeps its requests and responses, and a failing operation saves them as
+//! a `ReplayBundle` the user can send in. `replay` runs the operation again
+//! against the bundle, without their credentials.
+//!
+//! # Sanitizing
+//! - Request and response headers are dropped, apart from the few that
+//!   change how a response is handled (`RECORDED_HEADERS`), so no
+//!   `Authorization` or cookie is kept
+//! - JSON bodies, form bodies and query parameters have secret fields
+//!   replaced with `"<redacted>"` (see `debug_capture::redact_json`), plus
+//!   the account's identifiers and the encrypted license voucher
+//! - Bundles are written owner-only to app-private storage
+//!
+//! Keys can't be derived without the user's device, so a license replay
+//! stops at parsing the license response.
+//!
+//! # Replay Fixtures
+//! Bundles in `test_fixtures/replay/` are replayed by `tests/replay_test.rs`,
+//! which expects each to fail with its recorded `error`. Once the bug is
+//! fixed, set `error` to null and the bundle becomes a regression test.
+
+use crate::api::auth::{AccessToken, Account, Identity, Locale};
+use crate::api::client::{AudibleClient, ClientConfig};
+use crate::api::content::DownloadQuality;
+use crate::api::debug_capture::{self, is_secret_key, is_world_readable, redact_json_keys, REDACTED};
+use crate::api::license::LicenseRequest;
+use crate::api::transport::{HttpRequest, HttpResponse, HttpTransport};
+use crate::error::{LibationError, Result};
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Bundle format version
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Exchanges kept per client; later ones are dropped and the bundle is
+/// marked `truncated`
+const MAX_EXCHANGES: usize = 500;
+
+/// Response headers kept in a recording
+const RECORDED_HEADERS: &[&str] = &["content-type", "content-range", "retry-after"];
+
+/// Fields redacted from recordings on top of `debug_capture`'s secrets
+const RECORDING_SECRET_KEYS: &[&str] = &[
+    "license_response",
+    "customer_id",
+    "amazon_account_id",
+    "user_id",
+    "email",
+    "device_serial_number",
+];
+
+lazy_static::lazy_static! {
+    static ref RECORDING_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+/// A request or response body as recorded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum RecordedBody {
+    Json(Value),
+    Text(String),
+    /// Non-text bodies keep only their length
+    Binary(usize),
+}
+
+impl RecordedBody {
+    fn record(body: &[u8], content_type: Option<&str>) -> Self {
+        if let Ok(json) = serde_json::from_slice::<Value>(body) {
+            return Self::Json(redact_json_keys(&json, RECORDING_SECRET_KEYS));
+        }
+        match std::str::from_utf8(body) {
+            Ok(text) if content_type.is_some_and(|t| t.contains("x-www-form-urlencoded")) => {
+                Self::Text(sanitize_query(text))
+            }
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Binary(body.len()),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Json(json) => json.to_string().into_bytes(),
+            Self::Text(text) => text.clone().into_bytes(),
+            Self::Binary(_) => Vec::new(),
+        }
+    }
+}
+
+/// One request and what came back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub method: String,
+    /// Path and sanitized query; the host follows the bundle's marketplace
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<RecordedBody>,
+    /// None if no response arrived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<RecordedBody>,
+    /// Network failure in place of a response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_error: Option<RecordedNetworkError>,
+}
+
+/// A transport failure as recorded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedNetworkError {
+    pub message: String,
+    pub is_transient: bool,
+}
+
+/// The operation a bundle recorded, with what `replay` needs to rerun it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplayOperation {
+    /// `AudibleClient::sync_library`
+    LibrarySync,
+    /// `AudibleClient::build_download_license`
+    DownloadLicense {
+        asin: String,
+        quality: DownloadQuality,
+        prefer_widevine: bool,
+    },
+}
+
+impl ReplayOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::LibrarySync => "library_sync",
+            Self::DownloadLicense { .. } => "download_license",
+        }
+    }
+}
+
+/// Sanitized exchanges of a failed operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayBundle {
+    pub version: u32,
+    pub operation: ReplayOperation,
+    /// Marketplace country code (e.g. "us")
+    pub marketplace: String,
+    pub recorded_at: String,
+    pub app_version: String,
+    /// Error the operation failed with, None if it succeeded
+    pub error: Option<String>,
+    /// Exchanges past `MAX_EXCHANGES` were dropped
+    #[serde(default)]
+    pub truncated: bool,
+    pub exchanges: Vec<RecordedExchange>,
+}
+
+impl ReplayBundle {
+    /// Read a bundle file
+    ///
+    /// # Errors
+    /// InvalidInput if the file isn't a bundle of a supported version
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let bundle: Self = serde_json::from_str(&json)
+            .map_err(|e| LibationError::InvalidInput(format!("Invalid replay bundle: {}", e)))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(LibationError::InvalidInput(format!(
+                "Replay bundle version {} is newer than {}",
+                bundle.version, BUNDLE_VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// Write the bundle into `directory`, readable by the owner only
+    ///
+    /// # Returns
+    /// Path of the bundle file
+    pub fn save(&self, directory: &Path) -> Result<PathBuf> {
+        let path = directory.join(format!(
+            "{}-{}.replay.json",
+            self.operation.name(),
+            crate::clock::now().format("%Y%m%dT%H%M%S%.3f")
+        ));
+        debug_capture::write_private(&path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(path)
+    }
+}
+
+/// Turn on recording for clients built from now on
+///
+/// # Arguments
+/// * `directory` - App-private directory for bundles (created if missing)
+///
+/// # Errors
+/// PermissionDenied if `directory` is on shared or world-readable storage
+pub fn enable_api_recording(directory: &Path) -> Result<()> {
+    if is_world_readable(directory) {
+        return Err(LibationError::PermissionDenied(format!(
+            "API recordings can't be written to shared or world-readable storage: {}",
+            directory.display()
+        )));
+    }
+    std::fs::create_dir_all(directory)?;
+    if is_world_readable(directory) {
+        return Err(LibationError::PermissionDenied(format!(
+            "API recording directory is readable by other users: {}",
+            directory.display()
+        )));
+    }
+
+    *RECORDING_DIR.lock().unwrap() = Some(directory.to_path_buf());
+    Ok(())
+}
+
+/// Stop recording; saved bundles are kept
+pub fn disable_api_recording() {
+    *RECORDING_DIR.lock().unwrap() = None;
+}
+
+/// Directory bundles are saved to, None if recording is off
+pub fn recording_directory() -> Option<PathBuf> {
+    RECORDING_DIR.lock().unwrap().clone()
+}
+
+/// Path and query of `url`, with secret query parameters redacted
+fn sanitize_path(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(parsed) => match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), sanitize_query(query)),
+            None => parsed.path().to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+/// `key=value&...` with secret values redacted
+fn sanitize_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_secret_key(key) || RECORDING_SECRET_KEYS.contains(&key) => {
+                format!("{}={}", key, REDACTED)
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Transport that records sanitized exchanges of another
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Arc<dyn HttpTransport>,
+    exchanges: Mutex<Vec<RecordedExchange>>,
+    truncated: Mutex<bool>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn HttpTransport>) -> Self {
+        Self {
+            inner,
+            exchanges: Mutex::new(Vec::new()),
+            truncated: Mutex::new(false),
+        }
+    }
+
+    /// Exchanges recorded so far
+    pub fn exchanges(&self) -> Vec<RecordedExchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+
+    /// Bundle of everything recorded, for `operation` in `marketplace`
+    pub fn bundle(&self, operation: ReplayOperation, marketplace: &str, error: Option<&LibationError>) -> ReplayBundle {
+        ReplayBundle {
+            version: BUNDLE_VERSION,
+            operation,
+            marketplace: marketplace.to_string(),
+            recorded_at: crate::clock::now().to_rfc3339(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            error: error.map(|e| e.to_string()),
+            truncated: *self.truncated.lock().unwrap(),
+            exchanges: self.exchanges(),
+        }
+    }
+
+    fn record(&self, exchange: RecordedExchange) {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        if exchanges.len() < MAX_EXCHANGES {
+            exchanges.push(exchange);
+        } else {
+            *self.truncated.lock().unwrap() = true;
+        }
+    }
+}
+
+impl HttpTransport for RecordingTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let mut exchange = RecordedExchange {
+                method: request.method.to_string(),
+                path: sanitize_path(&request.url),
+                request_body: request
+                    .body
+                    .as_deref()
+                    .map(|body| RecordedBody::record(body, request.header("content-type"))),
+                status: None,
+                headers: BTreeMap::new(),
+                response_body: None,
+                network_error: None,
+            };
+
+            let result = self.inner.send(request).await;
+            match &result {
+                Ok(response) => {
+                    exchange.status = Some(response.status.as_u16());
+                    exchange.headers = RECORDED_HEADERS
+                        .iter()
+                        .filter_map(|name| response.header(name).map(|v| (name.to_string(), v.to_string())))
+                        .collect();
+                    exchange.response_body =
+                        Some(RecordedBody::record(&response.body, response.header("content-type")));
+                }
+                Err(LibationError::NetworkError { message, is_transient }) => {
+                    exchange.network_error = Some(RecordedNetworkError {
+                        message: message.clone(),
+                        is_transient: *is_transient,
+                    });
+                }
+                Err(e) => {
+                    exchange.network_error = Some(RecordedNetworkError {
+                        message: e.to_string(),
+                        is_transient: false,
+                    });
+                }
+            }
+            self.record(exchange);
+            result
+        })
+    }
+}
+
+/// Transport answering from a bundle, in recorded order
+///
+/// A request that doesn't match the next recorded one (method and path)
+/// fails with a non-transient network error naming both.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    exchanges: Mutex<VecDeque<RecordedExchange>>,
+}
+
+impl ReplayTransport {
+    pub fn new(bundle: &ReplayBundle) -> Self {
+        Self {
+            exchanges: Mutex::new(bundle.exchanges.iter().cloned().collect()),
+        }
+    }
+
+    /// Recorded exchanges not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().unwrap().len()
+    }
+}
+
+impl HttpTransport for ReplayTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        let method = request.method.to_string();
+        let path = sanitize_path(&request.url);
+        let next = self.exchanges.lock().unwrap().pop_front();
+
+        Box::pin(async move {
+            let exchange = match next {
+                Some(exchange) if exchange.method == method && exchange.path == path => exchange,
+                Some(exchange) => {
+                    return Err(LibationError::network_error(
+                        format!(
+                            "Replay mismatch: recorded {} {}, got {} {}",
+                            exchange.method, exchange.path, method, path
+                        ),
+                        false,
+                    ))
+                }
+                None => {
+                    return Err(LibationError::network_error(
+                        format!("Replay has no recorded response for {} {}", method, path),
+                        false,
+                    ))
+                }
+            };
+
+            if let Some(failure) = exchange.network_error {
+                return Err(LibationError::network_error(failure.message, failure.is_transient));
+            }
+
+            let mut headers = HeaderMap::new();
+            for (name, value) in &exchange.headers {
+                if let (Ok(name), Ok(value)) = (HeaderName::try_from(name.as_str()), HeaderValue::from_str(value)) {
+                    headers.insert(name, value);
+                }
+            }
+            let status = exchange
+                .status
+                .and_then(|s| StatusCode::from_u16(s).ok())
+                .ok_or_else(|| LibationError::InvalidInput(format!("Recorded {} {} has no status", method, path)))?;
+
+            Ok(HttpResponse {
+                status,
+                url: request.url,
+                headers,
+                body: exchange.response_body.map(|b| b.to_bytes()).unwrap_or_default(),
+            })
+        })
+    }
+}
+
+/// Run a bundle's operation again against its recorded responses
+///
+/// The client acts for a placeholder account in the bundle's marketplace;
+/// a library sync imports into an in-memory database.
+///
+/// # Returns
+/// The operation's result as JSON (sync stats or the content license)
+///
+/// # Errors
+/// Whatever the operation fails with; a replay mismatch if it sends
+/// requests the recording doesn't have
+pub async fn replay(bundle: &ReplayBundle) -> Result<Value> {
+    let locale = Locale::from_country_code(&bundle.marketplace)
+        .ok_or_else(|| LibationError::InvalidInput(format!("Unknown marketplace: {}", bundle.marketplace)))?;
+
+    let mut account = Account::new("replay@example.com".to_string())?;
+    account.set_identity(Identity::new(
+        AccessToken {
+            token: "replay".into(),
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        },
+        "replay".to_string(),
+        "replay".to_string(),
+        "replay".to_string(),
+        locale,
+    ));
+    let config = ClientConfig::builder().retry_delay(std::time::Duration::ZERO).build();
+    let transport = Arc::new(ReplayTransport::new(bundle));
+    let mut client = AudibleClient::with_transport(account.clone(), config, transport)?;
+
+    match &bundle.operation {
+        ReplayOperation::LibrarySync => {
+            let db = crate::storage::Database::new_in_memory().await?;
+            let stats = client.sync_library(&db, &account).await?;
+            Ok(serde_json::to_value(stats)?)
+        }
+        ReplayOperation::DownloadLicense { asin, quality, prefer_widevine } => {
+            let request = LicenseRequest::for_download(*quality, *prefer_widevine);
+            let license = client.get_download_license(asin, &request).await?;
+            Ok(serde_json::to_value(license)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::transport::MockTransport;
+
+    #[tokio::test]
+    async fn test_failed_license_recorded_sanitized_and_replayed() {
+        let mock = Arc::new(MockTransport::new());
+        mock.push_network_error(true);
+        mock.push_json(
+            200,
+            serde_json::json!({
+                "content_license": {
+                    "asin": "B0TEST",
+                    "license_response": "c2VjcmV0IHZvdWNoZXI=",
+                    "access_token": "leaked-token"
+                }
+            }),
+        );
+
+        let recorder = Arc::new(RecordingTransport::new(mock.clone()));
+        let client = crate::api::transport::mock_client(&mock).with_recorder(recorder.clone());
+        let operation = ReplayOperation::DownloadLicense {
+            asin: "B0TEST".to_string(),
+            quality: DownloadQuality::High,
+            prefer_widevine: false,
+        };
+        let result = client
+            .get_download_license("B0TEST", &LicenseRequest::for_download(DownloadQuality::High, false))
+            .await;
+        assert!(matches!(result, Err(LibationError::InvalidApiResponse { .. })));
+
+        // Nothing is saved while recording is off
+        disable_api_recording();
+        assert!(client.save_recording(operation.clone(), &result).unwrap().is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o700)).unwrap();
+        }
+        assert!(enable_api_recording(Path::new("/sdcard/Download")).is_err());
+        enable_api_recording(dir.path()).unwrap();
+        let path = client.save_recording(operation.clone(), &result).unwrap().unwrap();
+        disable_api_recording();
+        assert!(!is_world_readable(&path));
+
+        // No token, voucher or account name survives
+        let raw = std::fs::read_to_string(&path).unwrap();
+        for secret in ["test_token", "leaked-token", "c2VjcmV0IHZvdWNoZXI=", "test@example.com", "Bearer"] {
+            assert!(!raw.contains(secret), "{} was recorded", secret);
+        }
+
+        let bundle = ReplayBundle::load(&path).unwrap();
+        assert_eq!(bundle.operation, operation);
+        assert_eq!(bundle.marketplace, "us");
+        assert_eq!(bundle.exchanges.len(), 2);
+        assert!(bundle.exchanges[0].network_error.as_ref().unwrap().is_transient);
+        assert_eq!(bundle.exchanges[1].path, "/1.0/content/B0TEST/licenserequest");
+        assert!(matches!(bundle.exchanges[1].request_body, Some(RecordedBody::Json(_))));
+
+        // Replaying takes the same path to the same error
+        let replayed = replay(&bundle).await.unwrap_err();
+        assert_eq!(Some(replayed.to_string()), bundle.error);
+
+        // A request the recording doesn't have is reported, not sent
+        let mut other = bundle.clone();
+        other.operation = ReplayOperation::DownloadLicense {
+            asin: "B0OTHER".to_string(),
+            quality: DownloadQuality::High,
+            prefer_widevine: false,
+        };
+        let err = replay(&other).await.unwrap_err();
+        assert!(err.to_string().contains("Replay mismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_sanitize_query() {
+        assert_eq!(
+            sanitize_path("https://api.audible.com/1.0/library?page=2&access_token=abc&email=a%40b.c"),
+            "/1.0/library?page=2&access_token=<redacted>&email=<redacted>"
+        );
+        assert_eq!(sanitize_path("https://api.audible.com/1.0/library"), "/1.0/library");
+    }
+}
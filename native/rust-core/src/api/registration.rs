@@ -40,6 +40,7 @@
 
 use crate::error::{LibationError, Result};
 use crate::api::auth::{AccessToken, Identity, Locale, CustomerInfo};
+use crate::secret::SecretString;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -78,15 +79,15 @@ pub struct Tokens {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BearerTokens {
-    pub access_token: String,
-    pub refresh_token: String,
+    pub access_token: SecretString,
+    pub refresh_token: SecretString,
     pub expires_in: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MacDmsTokens {
-    pub device_private_key: String,
-    pub adp_token: String,
+    pub device_private_key: SecretString,
+    pub adp_token: SecretString,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,7 +110,7 @@ pub struct WebsiteCookie {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoreAuthCookie {
-    pub cookie: String,
+    pub cookie: SecretString,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -142,15 +143,15 @@ pub struct CustomerInfoExtension {
 #[derive(Debug, Clone)]
 pub struct RegistrationData {
     pub access_token: AccessToken,
-    pub refresh_token: String,
-    pub device_private_key: String,
-    pub adp_token: String,
+    pub refresh_token: SecretString,
+    pub device_private_key: SecretString,
+    pub adp_token: SecretString,
     pub cookies: HashMap<String, String>,
     pub device_serial_number: String,
     pub device_type: String,
     pub device_name: String,
     pub amazon_account_id: String,
-    pub store_authentication_cookie: String,
+    pub store_authentication_cookie: SecretString,
     pub customer_info: CustomerInfo,
 }
 
@@ -260,8 +261,8 @@ mod tests {
         let response = RegistrationResponse::from_json(TEST_FIXTURE).unwrap();
         let tokens = &response.response.success.tokens.bearer;
 
-        assert!(tokens.access_token.starts_with("Atna|"));
-        assert!(tokens.refresh_token.starts_with("Atnr|"));
+        assert!(tokens.access_token.expose_secret().starts_with("Atna|"));
+        assert!(tokens.refresh_token.expose_secret().starts_with("Atnr|"));
         // Token expiration can vary slightly (3599-3601 seconds)
         let expires_in: i64 = tokens.expires_in.parse().unwrap();
         assert!(expires_in >= 3599 && expires_in <= 3601, "expires_in should be ~3600, got {}", expires_in);
@@ -273,13 +274,13 @@ mod tests {
         let mac_dms = &response.response.success.tokens.mac_dms;
 
         // Device private key should be RSA private key (PEM format)
-        assert!(mac_dms.device_private_key.starts_with("MII"));
-        assert!(mac_dms.device_private_key.len() > 1000);
+        assert!(mac_dms.device_private_key.expose_secret().starts_with("MII"));
+        assert!(mac_dms.device_private_key.expose_secret().len() > 1000);
 
         // ADP token should be encrypted format
-        assert!(mac_dms.adp_token.contains("{enc:"));
-        assert!(mac_dms.adp_token.contains("{key:"));
-        assert!(mac_dms.adp_token.contains("{iv:"));
+        assert!(mac_dms.adp_token.expose_secret().contains("{enc:"));
+        assert!(mac_dms.adp_token.expose_secret().contains("{key:"));
+        assert!(mac_dms.adp_token.expose_secret().contains("{iv:"));
     }
 
     #[test]
@@ -333,15 +334,15 @@ mod tests {
         let data = response.extract_data(locale).unwrap();
 
         // Verify access token
-        assert!(data.access_token.token.starts_with("Atna|"));
+        assert!(data.access_token.token.expose_secret().starts_with("Atna|"));
         assert!(data.access_token.expires_at > Utc::now());
 
         // Verify refresh token
-        assert!(data.refresh_token.starts_with("Atnr|"));
+        assert!(data.refresh_token.expose_secret().starts_with("Atnr|"));
 
         // Verify device credentials
-        assert!(data.device_private_key.starts_with("MII"));
-        assert!(data.adp_token.contains("{enc:"));
+        assert!(data.device_private_key.expose_secret().starts_with("MII"));
+        assert!(data.adp_token.expose_secret().contains("{enc:"));
 
         // Verify device info
         assert_eq!(data.device_serial_number, "B45EF975C33A7B7E8DAF4D96E39B8040");
@@ -358,7 +359,7 @@ mod tests {
         assert!(data.cookies.contains_key("at-main"));
 
         // Verify store auth cookie
-        assert!(data.store_authentication_cookie.len() > 50);
+        assert!(data.store_authentication_cookie.expose_secret().len() > 50);
     }
 
     #[test]
@@ -368,8 +369,8 @@ mod tests {
         let identity = response.to_identity(locale.clone()).unwrap();
 
         // Verify Identity fields
-        assert!(identity.access_token.token.starts_with("Atna|"));
-        assert!(identity.refresh_token.starts_with("Atnr|"));
+        assert!(identity.access_token.token.expose_secret().starts_with("Atna|"));
+        assert!(identity.refresh_token.expose_secret().starts_with("Atnr|"));
         assert_eq!(identity.device_serial_number, "B45EF975C33A7B7E8DAF4D96E39B8040");
         assert_eq!(identity.device_type, "A10KISP2GWF0E4");
         assert_eq!(identity.amazon_account_id, "amzn1.account.AGMGLSGIFYVALF2MEO4F3JJQRLSA");
@@ -415,15 +416,15 @@ mod tests {
         let tokens = &response.response.success.tokens;
 
         // Bearer tokens
-        assert!(tokens.bearer.access_token.starts_with("Atna|"));
-        assert!(tokens.bearer.refresh_token.starts_with("Atnr|"));
+        assert!(tokens.bearer.access_token.expose_secret().starts_with("Atna|"));
+        assert!(tokens.bearer.refresh_token.expose_secret().starts_with("Atnr|"));
 
         // MAC DMS tokens - device_private_key is base64-encoded (no PEM headers)
-        assert!(tokens.mac_dms.device_private_key.starts_with("MII"));
-        assert!(tokens.mac_dms.device_private_key.len() > 1000);
+        assert!(tokens.mac_dms.device_private_key.expose_secret().starts_with("MII"));
+        assert!(tokens.mac_dms.device_private_key.expose_secret().len() > 1000);
 
         // Store auth cookie is base64-encoded
-        assert!(tokens.store_authentication_cookie.cookie.len() > 50);
+        assert!(tokens.store_authentication_cookie.cookie.expose_secret().len() > 50);
     }
 
     #[test]
@@ -0,0 +1,274 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Typed `response_groups` for library, catalog and content requests
+//!
+//! The API takes the data groups to include as a comma-joined string, and
+//! silently ignores names it doesn't know, so a typo just drops fields
+//! from the response. `ResponseGroups` is a set of `ResponseGroup` names
+//! that serializes to that string (in a fixed order) and refuses unknown
+//! names when parsed.
+//!
+//! # Presets
+//! - `MINIMAL` - Title and description only
+//! - `STANDARD` - Everything a catalog product needs (ApiExtended.cs:206-210)
+//! - `FULL` - `STANDARD` plus the library-only groups
+//!   (LibraryCommands.cs:122-133)
+//!
+//! # Migrating from strings
+//! Existing comma-joined strings parse with `str::parse`, and
+//! `ResponseGroups` deserializes from the same string, so stored options
+//! keep loading:
+//!
+//! ```
+//! use rust_core::api::response_groups::{ResponseGroup, ResponseGroups};
+//!
+//! let groups: ResponseGroups = "media,series".parse().unwrap();
+//! assert_eq!(groups, ResponseGroups::empty().with(ResponseGroup::Media).with(ResponseGroup::Series));
+//! assert!("media,serise".parse::<ResponseGroups>().is_err());
+//! ```
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A data group the API can include
+///
+/// Declared in the order groups are serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResponseGroup {
+    /// Customer ratings and review count
+    Rating,
+    /// Formats, codecs, sample and cover URLs
+    Media,
+    /// Series, episodes, parent/child titles
+    Relationships,
+    /// Title, description, publisher, release date
+    ProductDesc,
+    /// Authors and narrators
+    Contributors,
+    /// The user's own review
+    ProvidedReview,
+    /// Subscription plan availability
+    ProductPlans,
+    /// Series names and positions
+    Series,
+    /// Category hierarchies
+    CategoryLadders,
+    /// Extended attributes (language, format type)
+    ProductExtendedAttrs,
+    /// PDF supplement URL (library only)
+    PdfUrl,
+    /// Original ASIN and origin type: purchase, gift, loan (library only)
+    OriginAsin,
+    /// Completion status (library only)
+    IsFinished,
+    /// Runtime, language, ASIN
+    ProductAttrs,
+    /// Whispersync for Voice companion ebook
+    Ws4v,
+    /// List price, sale price and credit cost
+    Price,
+    /// ACR and version of the content (content metadata only)
+    ContentReference,
+}
+
+impl ResponseGroup {
+    pub const ALL: [ResponseGroup; 17] = [
+        ResponseGroup::Rating,
+        ResponseGroup::Media,
+        ResponseGroup::Relationships,
+        ResponseGroup::ProductDesc,
+        ResponseGroup::Contributors,
+        ResponseGroup::ProvidedReview,
+        ResponseGroup::ProductPlans,
+        ResponseGroup::Series,
+        ResponseGroup::CategoryLadders,
+        ResponseGroup::ProductExtendedAttrs,
+        ResponseGroup::PdfUrl,
+        ResponseGroup::OriginAsin,
+        ResponseGroup::IsFinished,
+        ResponseGroup::ProductAttrs,
+        ResponseGroup::Ws4v,
+        ResponseGroup::Price,
+        ResponseGroup::ContentReference,
+    ];
+
+    /// Name sent to the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseGroup::Rating => "rating",
+            ResponseGroup::Media => "media",
+            ResponseGroup::Relationships => "relationships",
+            ResponseGroup::ProductDesc => "product_desc",
+            ResponseGroup::Contributors => "contributors",
+            ResponseGroup::ProvidedReview => "provided_review",
+            ResponseGroup::ProductPlans => "product_plans",
+            ResponseGroup::Series => "series",
+            ResponseGroup::CategoryLadders => "category_ladders",
+            ResponseGroup::ProductExtendedAttrs => "product_extended_attrs",
+            ResponseGroup::PdfUrl => "pdf_url",
+            ResponseGroup::OriginAsin => "origin_asin",
+            ResponseGroup::IsFinished => "is_finished",
+            ResponseGroup::ProductAttrs => "product_attrs",
+            ResponseGroup::Ws4v => "ws4v",
+            ResponseGroup::Price => "price",
+            ResponseGroup::ContentReference => "content_reference",
+        }
+    }
+
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+impl FromStr for ResponseGroup {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ResponseGroup::ALL
+            .into_iter()
+            .find(|group| group.as_str() == s)
+            .ok_or_else(|| LibationError::InvalidInput(format!("Unknown response group: {:?}", s)))
+    }
+}
+
+/// A set of response groups
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ResponseGroups(u32);
+
+impl ResponseGroups {
+    /// Title and description only
+    pub const MINIMAL: ResponseGroups = ResponseGroups::empty().with(ResponseGroup::ProductDesc);
+
+    /// Catalog product details
+    pub const STANDARD: ResponseGroups = ResponseGroups::empty()
+        .with(ResponseGroup::Rating)
+        .with(ResponseGroup::Media)
+        .with(ResponseGroup::Relationships)
+        .with(ResponseGroup::ProductDesc)
+        .with(ResponseGroup::Contributors)
+        .with(ResponseGroup::ProvidedReview)
+        .with(ResponseGroup::ProductPlans)
+        .with(ResponseGroup::Series)
+        .with(ResponseGroup::CategoryLadders)
+        .with(ResponseGroup::ProductExtendedAttrs);
+
+    /// Library sync: catalog details plus PDF, origin and completion
+    pub const FULL: ResponseGroups = ResponseGroups::STANDARD
+        .with(ResponseGroup::PdfUrl)
+        .with(ResponseGroup::OriginAsin)
+        .with(ResponseGroup::IsFinished);
+
+    pub const fn empty() -> Self {
+        ResponseGroups(0)
+    }
+
+    /// This set plus `group`
+    pub const fn with(self, group: ResponseGroup) -> Self {
+        ResponseGroups(self.0 | group.bit())
+    }
+
+    /// This set without `group`
+    pub const fn without(self, group: ResponseGroup) -> Self {
+        ResponseGroups(self.0 & !group.bit())
+    }
+
+    pub const fn contains(&self, group: ResponseGroup) -> bool {
+        self.0 & group.bit() != 0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Groups in serialization order
+    pub fn iter(&self) -> impl Iterator<Item = ResponseGroup> + '_ {
+        ResponseGroup::ALL.into_iter().filter(|group| self.contains(*group))
+    }
+}
+
+impl FromIterator<ResponseGroup> for ResponseGroups {
+    fn from_iter<I: IntoIterator<Item = ResponseGroup>>(iter: I) -> Self {
+        iter.into_iter().fold(ResponseGroups::empty(), ResponseGroups::with)
+    }
+}
+
+impl fmt::Display for ResponseGroups {
+    /// Comma-joined, as the API expects
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.iter().map(|group| group.as_str()).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+impl FromStr for ResponseGroups {
+    type Err = LibationError;
+
+    /// Parse a comma-joined list; whitespace around names is ignored
+    ///
+    /// # Errors
+    /// InvalidInput for an unknown or empty name
+    fn from_str(s: &str) -> Result<Self> {
+        if s.trim().is_empty() {
+            return Ok(ResponseGroups::empty());
+        }
+        s.split(',').map(|name| name.trim().parse::<ResponseGroup>()).collect()
+    }
+}
+
+impl Serialize for ResponseGroups {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResponseGroups {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_groups_round_trip() {
+        // Same string the library sync always sent
+        assert_eq!(
+            ResponseGroups::FULL.to_string(),
+            "rating,media,relationships,product_desc,contributors,provided_review,product_plans,series,\
+             category_ladders,product_extended_attrs,pdf_url,origin_asin,is_finished"
+        );
+        assert_eq!(ResponseGroups::FULL.to_string().parse::<ResponseGroups>().unwrap(), ResponseGroups::FULL);
+
+        // Order and spacing don't matter; typos are refused
+        let groups: ResponseGroups = " series , media".parse().unwrap();
+        assert_eq!(groups.to_string(), "media,series");
+        assert!("media,,series".parse::<ResponseGroups>().is_err());
+        assert!(matches!("medai".parse::<ResponseGroups>(), Err(LibationError::InvalidInput(_))));
+
+        let search = ResponseGroups::STANDARD.without(ResponseGroup::ProvidedReview);
+        assert!(!search.contains(ResponseGroup::ProvidedReview) && search.contains(ResponseGroup::Media));
+
+        let json = serde_json::to_string(&ResponseGroups::MINIMAL).unwrap();
+        assert_eq!(json, "\"product_desc\"");
+        assert!(serde_json::from_str::<ResponseGroups>("\"product_dsc\"").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Storefront links for catalog products
+//!
+//! Books the user doesn't own can't be bought through the API, so the app
+//! hands the user over to Audible: the official app if it is installed,
+//! otherwise the marketplace's website. Links always point at the
+//! marketplace the product was found in, since ASINs, prices and credit
+//! offers differ between marketplaces.
+//!
+//! # Link formats
+//! - Product page: `https://www.{domain}/pd/{asin}` (also a universal link
+//!   into the iOS app)
+//! - Purchase: `https://www.{domain}/cart/item/add?asin={asin}`; credit or
+//!   cash is chosen at checkout
+//! - App: `audible://view?section=pdp&asin={asin}&marketplace={id}`
+//! - Android intent: the app link with the product page as browser
+//!   fallback, for launching from a WebView or browser
+
+use crate::api::auth::Locale;
+use crate::api::client::AudibleClient;
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Package name of the Audible Android app
+pub const AUDIBLE_ANDROID_PACKAGE: &str = "com.audible.application";
+
+/// URL scheme of the Audible apps
+pub const AUDIBLE_APP_SCHEME: &str = "audible";
+
+/// Where to send the user for a product
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreLinks {
+    /// Marketplace country code (e.g. "de")
+    pub locale: String,
+    pub product_url: String,
+    pub purchase_url: String,
+    /// Opens the product in the Audible app
+    pub app_url: String,
+    /// `app_url` as an Android intent falling back to `product_url`
+    pub android_intent_url: String,
+}
+
+/// Links to `asin` in `locale`'s marketplace
+///
+/// # Errors
+/// InvalidInput if `asin` isn't alphanumeric
+pub fn store_links(locale: &Locale, asin: &str) -> Result<StoreLinks> {
+    if asin.is_empty() || !asin.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(LibationError::invalid_input(format!("Invalid ASIN: {:?}", asin)));
+    }
+
+    let store = format!("https://www.{}", locale.domain);
+    let product_url = format!("{}/pd/{}", store, asin);
+    let app_path = format!(
+        "view?section=pdp&asin={}&marketplace={}",
+        asin,
+        locale.marketplace_id()
+    );
+
+    Ok(StoreLinks {
+        locale: locale.country_code.clone(),
+        purchase_url: format!("{}/cart/item/add?asin={}", store, asin),
+        app_url: format!("{}://{}", AUDIBLE_APP_SCHEME, app_path),
+        android_intent_url: format!(
+            "intent://{}#Intent;scheme={};package={};S.browser_fallback_url={};end",
+            app_path,
+            AUDIBLE_APP_SCHEME,
+            AUDIBLE_ANDROID_PACKAGE,
+            urlencoding::encode(&product_url)
+        ),
+        product_url,
+    })
+}
+
+impl AudibleClient {
+    /// Marketplace this client talks to
+    ///
+    /// # Errors
+    /// InvalidState if the API host isn't a known marketplace
+    pub fn marketplace(&self) -> Result<Locale> {
+        let domain = self.base_url().trim_start_matches("https://api.");
+        Locale::all()
+            .into_iter()
+            .find(|l| l.domain == domain)
+            .ok_or_else(|| LibationError::InvalidState(format!("Unknown marketplace: {}", self.base_url())))
+    }
+
+    /// Links to `asin` in this client's marketplace
+    pub fn store_links(&self, asin: &str) -> Result<StoreLinks> {
+        store_links(&self.marketplace()?, asin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_links_per_marketplace() {
+        let us = store_links(&Locale::us(), "B002V5D7B0").unwrap();
+        assert_eq!(us.product_url, "https://www.audible.com/pd/B002V5D7B0");
+        assert_eq!(us.purchase_url, "https://www.audible.com/cart/item/add?asin=B002V5D7B0");
+        assert_eq!(us.app_url, "audible://view?section=pdp&asin=B002V5D7B0&marketplace=AF2M0KC94RCEA");
+
+        let jp = store_links(&Locale::jp(), "B002V5D7B0").unwrap();
+        assert_eq!(jp.locale, "jp");
+        assert_eq!(jp.product_url, "https://www.audible.co.jp/pd/B002V5D7B0");
+        assert!(jp.app_url.ends_with("marketplace=A1QAP3MOU4173J"));
+        assert_eq!(
+            jp.android_intent_url,
+            "intent://view?section=pdp&asin=B002V5D7B0&marketplace=A1QAP3MOU4173J\
+             #Intent;scheme=audible;package=com.audible.application;\
+             S.browser_fallback_url=https%3A%2F%2Fwww.audible.co.jp%2Fpd%2FB002V5D7B0;end"
+        );
+
+        assert!(store_links(&Locale::uk(), "B00/../x").is_err());
+        assert!(store_links(&Locale::uk(), "").is_err());
+    }
+}
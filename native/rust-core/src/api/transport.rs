@@ -0,0 +1,247 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! HTTP transport used by `AudibleClient`
+//!
+//! `AudibleClient` builds complete requests (URL, auth and user agent
+//! headers, body) and hands them to an `HttpTransport`, which only sends
+//! them. Retry, token handling and response parsing stay in the client, so
+//! they can be unit-tested by plugging in `MockTransport` instead of the
+//! network-backed `ReqwestTransport`:
+//!
+//! ```rust
+//! use rust_core::api::transport::MockTransport;
+//! use std::sync::Arc;
+//!
+//! let transport = Arc::new(MockTransport::new());
+//! transport.push_json(200, serde_json::json!({ "items": [] }));
+//! // AudibleClient::with_transport(account, config, transport.clone())
+//! ```
+
+use crate::error::{LibationError, Result};
+use futures_util::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A fully built HTTP request
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    /// Header value as text, if present and valid UTF-8
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+/// A response with its body fully read
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    /// Final URL, after redirects
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Header value as text, if present and valid UTF-8
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// Body as text (invalid UTF-8 is replaced)
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Sends HTTP requests for `AudibleClient`
+///
+/// # Errors
+/// Implementations report failures to get any response as
+/// `LibationError::NetworkError`; `is_transient` decides whether the client
+/// retries. HTTP error statuses are responses, not errors.
+pub trait HttpTransport: Send + Sync + std::fmt::Debug {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>>;
+}
+
+/// Transport backed by `reqwest`
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Build a client with connection pooling
+    ///
+    /// # Arguments
+    /// * `timeout` - Per-request timeout
+    /// * `enable_cookies` - Keep a cookie jar for session persistence
+    pub fn new(timeout: Duration, enable_cookies: bool) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .pool_max_idle_per_host(10)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .cookie_store(enable_cookies)
+            .build()?;
+
+        Ok(Self { client })
+    }
+
+    /// Use an existing `reqwest` client
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    /// Reference: ApiExtended.cs:70 (Policy.Handle<Exception>() - retries all exceptions)
+    fn is_retryable(error: &reqwest::Error) -> bool {
+        error.is_timeout() || error.is_connect() || error.is_request()
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let network_error = |e: reqwest::Error| {
+                LibationError::network_error(format!("Network request failed: {}", e), Self::is_retryable(&e))
+            };
+
+            let mut builder = self.client.request(request.method, &request.url).headers(request.headers);
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+
+            let response = builder.send().await.map_err(network_error)?;
+            let status = response.status();
+            let url = response.url().to_string();
+            let headers = response.headers().clone();
+            let body = response.bytes().await.map_err(network_error)?.to_vec();
+
+            Ok(HttpResponse { status, url, headers, body })
+        })
+    }
+}
+
+/// Scripted transport for tests
+///
+/// Responses are returned in the order they were pushed; every request is
+/// recorded. Sending with no scripted response left fails with a
+/// non-transient network error.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    responses: Mutex<VecDeque<Result<HttpResponse>>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a response
+    pub fn push_response(&self, status: u16, headers: HeaderMap, body: impl Into<Vec<u8>>) {
+        self.responses.lock().unwrap().push_back(Ok(HttpResponse {
+            status: StatusCode::from_u16(status).expect("valid status code"),
+            url: String::new(),
+            headers,
+            body: body.into(),
+        }));
+    }
+
+    /// Queue a JSON response
+    pub fn push_json(&self, status: u16, body: serde_json::Value) {
+        self.push_response(status, HeaderMap::new(), body.to_string());
+    }
+
+    /// Queue a failure to connect
+    pub fn push_network_error(&self, is_transient: bool) {
+        self.responses
+            .lock()
+            .unwrap()
+            .push_back(Err(LibationError::network_error("Mock network failure", is_transient)));
+    }
+
+    /// Requests sent so far
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Scripted responses not yet consumed
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+}
+
+impl HttpTransport for MockTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        let url = request.url.clone();
+        self.requests.lock().unwrap().push(request);
+        let next = self.responses.lock().unwrap().pop_front();
+
+        Box::pin(async move {
+            let mut response = next.unwrap_or_else(|| {
+                Err(LibationError::network_error(format!("No mock response for {}", url), false))
+            })?;
+            if response.url.is_empty() {
+                response.url = url;
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Client for the test account `test@example.com` (US marketplace, token
+/// `test_token`, no retry delay) sending through a mock
+#[cfg(test)]
+pub(crate) fn mock_client(transport: &std::sync::Arc<MockTransport>) -> crate::api::client::AudibleClient {
+    use crate::api::auth::{AccessToken, Account, Identity, Locale};
+
+    let mut account = Account::new("test@example.com".to_string()).unwrap();
+    account.set_identity(Identity::new(
+        AccessToken {
+            token: "test_token".into(),
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        },
+        "refresh".to_string(),
+        "key".to_string(),
+        "adp".to_string(),
+        Locale::us(),
+    ));
+    let config = crate::api::client::ClientConfig::builder()
+        .user_agent("TestAgent/1.0")
+        .retry_delay(Duration::ZERO)
+        .build();
+
+    crate::api::client::AudibleClient::with_transport(account, config, transport.clone()).unwrap()
+}
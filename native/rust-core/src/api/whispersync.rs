@@ -0,0 +1,252 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Read-along mapping between audio and the Kindle companion (Whispersync for Voice)
+//!
+//! When a user owns both the audiobook and its Kindle edition, Audible can
+//! provide sync points that pair audio offsets with ebook locations. With
+//! them the app can show "page 112 in the ebook" for the current position.
+//!
+//! # Endpoints
+//! - **GET** `/1.0/catalog/products/{asin}?response_groups=ws4v` - Companion
+//!   ASIN and whether the user owns it
+//! - **GET** `/1.0/content/{asin}/syncfiles` - Sync points (undocumented)
+//!
+//! Most titles have no companion. Missing companions, unowned ebooks and
+//! 403/404 responses all produce an empty mapping rather than an error, so
+//! callers can store the result and skip asking again until the user refreshes.
+//! Mappings are stored per book in `storage::read_along`.
+
+use crate::api::client::AudibleClient;
+use crate::api::response_groups::{ResponseGroup, ResponseGroups};
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Companion ebook of an audiobook
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompanionInfo {
+    /// Kindle edition ASIN
+    #[serde(rename = "ws4v_companion_asin", default)]
+    pub companion_asin: Option<String>,
+
+    /// Audible offers Whispersync for Voice for this title
+    #[serde(rename = "is_ws4v_enabled", default)]
+    pub enabled: bool,
+
+    /// The user owns the companion ebook
+    #[serde(rename = "is_ws4v_companion_asin_owned", default)]
+    pub owned: bool,
+}
+
+/// One audio offset paired with an ebook location
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncPoint {
+    /// Audio offset in milliseconds
+    #[serde(alias = "audio_position_ms")]
+    pub audio_ms: i64,
+
+    /// Kindle location
+    #[serde(alias = "ebook_position")]
+    pub location: i64,
+
+    /// Printed page label ("xii", "112"), if the ebook has page numbers
+    #[serde(alias = "page_label", default)]
+    pub page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncFilesResponse {
+    #[serde(default)]
+    sync_points: Vec<SyncPoint>,
+}
+
+/// Read-along mapping for one audiobook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadAlongMapping {
+    pub asin: String,
+
+    /// None when the title has no companion ebook
+    pub companion_asin: Option<String>,
+
+    /// Sync points ordered by audio offset (empty when unavailable)
+    pub points: Vec<SyncPoint>,
+
+    /// When the mapping was fetched (UTC ISO 8601, see `storage::dates`)
+    pub fetched_at: String,
+}
+
+/// Ebook position for an audio offset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EbookPosition {
+    pub location: i64,
+    pub page: Option<String>,
+}
+
+impl ReadAlongMapping {
+    /// A mapping with no sync points, for titles without a usable companion
+    pub fn unavailable(asin: &str, companion_asin: Option<String>) -> Self {
+        Self {
+            asin: asin.to_string(),
+            companion_asin,
+            points: Vec::new(),
+            fetched_at: crate::storage::dates::now(),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        !self.points.is_empty()
+    }
+
+    /// Ebook position for an audio offset
+    ///
+    /// The location is interpolated between the surrounding sync points;
+    /// the page is the one of the last point at or before the offset.
+    /// Offsets outside the mapped range clamp to the first or last point.
+    pub fn ebook_position_at(&self, audio_ms: i64) -> Option<EbookPosition> {
+        let after = self.points.partition_point(|p| p.audio_ms <= audio_ms);
+        let Some(before) = after.checked_sub(1).map(|i| &self.points[i]) else {
+            return self.points.first().map(|first| EbookPosition {
+                location: first.location,
+                page: first.page.clone(),
+            });
+        };
+
+        let location = match self.points.get(after) {
+            Some(next) if next.audio_ms > before.audio_ms => {
+                let span = (next.location - before.location) as f64;
+                let fraction = (audio_ms - before.audio_ms) as f64 / (next.audio_ms - before.audio_ms) as f64;
+                before.location + (span * fraction).round() as i64
+            }
+            _ => before.location,
+        };
+
+        Some(EbookPosition {
+            location,
+            page: before.page.clone(),
+        })
+    }
+
+    /// Audio offset for an ebook location (first point at or after it)
+    pub fn audio_position_at(&self, location: i64) -> Option<i64> {
+        self.points
+            .iter()
+            .find(|p| p.location >= location)
+            .or(self.points.last())
+            .map(|p| p.audio_ms)
+    }
+}
+
+/// Whether an API error means "no mapping for this title" rather than a failure
+fn is_unavailable(error: &LibationError) -> bool {
+    matches!(
+        error,
+        LibationError::ApiRequestFailed { status_code: Some(403 | 404), .. }
+    )
+}
+
+impl AudibleClient {
+    /// Companion ebook info for an audiobook
+    pub async fn get_companion_info(&self, asin: &str) -> Result<CompanionInfo> {
+        let endpoint = format!(
+            "/1.0/catalog/products/{}?response_groups={}",
+            asin,
+            ResponseGroups::empty().with(ResponseGroup::Ws4v)
+        );
+        let response: serde_json::Value = self.get(&endpoint).await?;
+
+        let product = response.get("product").unwrap_or(&response);
+        serde_json::from_value(product.clone()).map_err(|e| LibationError::InvalidApiResponse {
+            message: format!("Failed to parse companion info: {}", e),
+            response_body: Some(product.to_string()),
+        })
+    }
+
+    /// Fetch the read-along mapping of an audiobook
+    ///
+    /// # Returns
+    /// The mapping; empty (see `ReadAlongMapping::is_available`) when the
+    /// title has no companion, the user doesn't own it, or Audible has no
+    /// sync data for it
+    pub async fn fetch_read_along_mapping(&self, asin: &str) -> Result<ReadAlongMapping> {
+        let companion = match self.get_companion_info(asin).await {
+            Ok(companion) => companion,
+            Err(e) if is_unavailable(&e) => return Ok(ReadAlongMapping::unavailable(asin, None)),
+            Err(e) => return Err(e),
+        };
+
+        if !companion.enabled || !companion.owned || companion.companion_asin.is_none() {
+            return Ok(ReadAlongMapping::unavailable(asin, companion.companion_asin));
+        }
+
+        let response: SyncFilesResponse = match self.get(&format!("/1.0/content/{}/syncfiles", asin)).await {
+            Ok(response) => response,
+            Err(e) if is_unavailable(&e) => {
+                return Ok(ReadAlongMapping::unavailable(asin, companion.companion_asin))
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut mapping = ReadAlongMapping::unavailable(asin, companion.companion_asin);
+        mapping.points = response.sync_points;
+        mapping.points.sort_by_key(|p| p.audio_ms);
+        Ok(mapping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(audio_ms: i64, location: i64, page: &str) -> SyncPoint {
+        SyncPoint {
+            audio_ms,
+            location,
+            page: Some(page.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_ebook_position_at() {
+        let mut mapping = ReadAlongMapping::unavailable("B0AUDIO", Some("B0KINDLE".to_string()));
+        assert!(!mapping.is_available());
+        assert_eq!(mapping.ebook_position_at(1000), None);
+
+        mapping.points = vec![point(10_000, 100, "1"), point(20_000, 200, "2"), point(40_000, 260, "3")];
+        assert!(mapping.is_available());
+
+        let position = mapping.ebook_position_at(15_000).unwrap();
+        assert_eq!(position.location, 150);
+        assert_eq!(position.page.as_deref(), Some("1"));
+
+        assert_eq!(mapping.ebook_position_at(30_000).unwrap().location, 230);
+        assert_eq!(mapping.ebook_position_at(0).unwrap().location, 100);
+        assert_eq!(mapping.ebook_position_at(90_000).unwrap().page.as_deref(), Some("3"));
+
+        assert_eq!(mapping.audio_position_at(150), Some(20_000));
+        assert_eq!(mapping.audio_position_at(999), Some(40_000));
+    }
+
+    #[test]
+    fn test_companion_info_parsing() {
+        let json = r#"{"asin": "B0AUDIO", "ws4v_companion_asin": "B0KINDLE", "is_ws4v_enabled": true}"#;
+        let info: CompanionInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.companion_asin.as_deref(), Some("B0KINDLE"));
+        assert!(info.enabled && !info.owned);
+
+        let none: CompanionInfo = serde_json::from_str(r#"{"asin": "B0AUDIO"}"#).unwrap();
+        assert_eq!(none, CompanionInfo::default());
+    }
+}
@@ -0,0 +1,191 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Runtime detection of the available audio backend
+//!
+//! The converter, metadata editor, and decoder shell out to `ffmpeg` and
+//! `ffprobe`, which exist on desktop but never on Android or iOS. There the
+//! app links FFmpeg-Kit and runs conversions itself, passing Rust only the
+//! keys and paths. `get_audio_capabilities` reports which of these applies so
+//! the app can hide options it can't perform, and `require_native_ffmpeg`
+//! lets Rust entry points fail with `UnsupportedOperation` up front instead
+//! of a process-spawn error halfway through a job.
+
+use crate::error::{LibationError, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// FFmpeg-Kit (or another app-side FFmpeg) registered by the host app
+static EXTERNAL_FFMPEG: AtomicBool = AtomicBool::new(false);
+
+/// Where audio processing can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioBackend {
+    /// `ffmpeg`/`ffprobe` executables are on PATH; Rust runs conversions
+    Native,
+    /// The app runs FFmpeg-Kit itself; Rust only prepares keys and paths
+    External,
+    /// No FFmpeg available: download only
+    None,
+}
+
+/// Audio features available on this device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AudioCapabilities {
+    pub backend: AudioBackend,
+
+    /// `ffmpeg` executable found on PATH
+    pub ffmpeg: bool,
+
+    /// `ffprobe` executable found on PATH
+    pub ffprobe: bool,
+
+    /// Decrypt AAX/AAXC to M4B (natively or through the app)
+    pub decryption: bool,
+
+    /// Format conversion, chapter splitting, trimming via `AudioConverter`
+    pub conversion: bool,
+
+    /// Embedding tags, chapters, and cover art via `MetadataEditor`
+    pub metadata_editing: bool,
+
+    /// Reading duration, codec, and chapters via `ffprobe`
+    pub probing: bool,
+}
+
+/// Record whether the app provides FFmpeg (e.g. FFmpeg-Kit on mobile)
+pub fn set_external_ffmpeg_available(available: bool) {
+    EXTERNAL_FFMPEG.store(available, Ordering::SeqCst);
+}
+
+/// Find an executable in a PATH-style list of directories
+fn find_executable(path_var: &OsStr, name: &str) -> bool {
+    std::env::split_paths(path_var).any(|dir| {
+        let candidate = dir.join(name);
+        is_executable(&candidate)
+            || (cfg!(windows) && is_executable(&candidate.with_extension("exe")))
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Capabilities for the given PATH and app-provided FFmpeg
+fn detect(path_var: Option<&OsStr>, external: bool) -> AudioCapabilities {
+    let (ffmpeg, ffprobe) = match path_var {
+        Some(path_var) => (
+            find_executable(path_var, "ffmpeg"),
+            find_executable(path_var, "ffprobe"),
+        ),
+        None => (false, false),
+    };
+
+    let backend = if ffmpeg && ffprobe {
+        AudioBackend::Native
+    } else if external {
+        AudioBackend::External
+    } else {
+        AudioBackend::None
+    };
+    let native = backend == AudioBackend::Native;
+
+    AudioCapabilities {
+        backend,
+        ffmpeg,
+        ffprobe,
+        decryption: backend != AudioBackend::None,
+        conversion: native,
+        metadata_editing: native,
+        probing: ffprobe,
+    }
+}
+
+/// Detect the audio backend available right now
+pub fn get_audio_capabilities() -> AudioCapabilities {
+    let path_var = std::env::var_os("PATH");
+    detect(path_var.as_deref(), EXTERNAL_FFMPEG.load(Ordering::SeqCst))
+}
+
+/// Fail with `UnsupportedOperation` unless Rust can run FFmpeg itself
+pub fn require_native_ffmpeg(operation: &str) -> Result<()> {
+    let capabilities = get_audio_capabilities();
+    if capabilities.backend == AudioBackend::Native {
+        return Ok(());
+    }
+
+    let reason = match capabilities.backend {
+        AudioBackend::External => "FFmpeg is provided by the app, not the native library",
+        _ => "FFmpeg is not available on this platform",
+    };
+    Err(LibationError::UnsupportedOperation {
+        operation: operation.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn fake_executable(dir: &Path, name: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_detect_without_ffmpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        let caps = detect(Some(dir.path().as_os_str()), false);
+        assert_eq!(caps.backend, AudioBackend::None);
+        assert!(!caps.decryption && !caps.conversion && !caps.probing);
+
+        let caps = detect(None, true);
+        assert_eq!(caps.backend, AudioBackend::External);
+        assert!(caps.decryption);
+        assert!(!caps.conversion && !caps.metadata_editing);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_native_ffmpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        fake_executable(dir.path(), "ffprobe");
+        let caps = detect(Some(dir.path().as_os_str()), false);
+        assert_eq!(caps.backend, AudioBackend::None);
+        assert!(caps.probing);
+
+        fake_executable(dir.path(), "ffmpeg");
+        let path_var = std::env::join_paths(["/nonexistent", dir.path().to_str().unwrap()]).unwrap();
+        let caps = detect(Some(&path_var), true);
+        assert_eq!(caps.backend, AudioBackend::Native);
+        assert!(caps.conversion && caps.metadata_editing && caps.decryption);
+    }
+}
@@ -0,0 +1,274 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Chapter title normalization and renumbering
+//!
+//! Publishers often ship chapters titled "Track 01" or "003". Before
+//! tagging, `normalize_chapter_titles` can:
+//! 1. Replace useless titles with names from Audible's `chapter_info`
+//! 2. Rewrite titles through a template such as `"Chapter {n}: {title}"`
+//!
+//! The result is stored per book (`storage::chapters`) so the user can edit
+//! it afterward with `update_chapters` and re-tag.
+//!
+//! # Template Placeholders
+//! - `{n}` - Chapter number, starting at 1
+//! - `{nn}` - Chapter number zero-padded to the width of `{total}`
+//! - `{total}` - Number of chapters
+//! - `{title}` - Current title; empty when the title is generic
+//!
+//! Separators left dangling by an empty `{title}` are trimmed, so
+//! `"Chapter {n}: {title}"` renders "Track 01" as "Chapter 1" and
+//! `"{nn}. {title}"` renders it as "1." (the period belongs to the number).
+//! Real titles are kept as they are, trailing punctuation included.
+
+use crate::api::content::{flatten_chapters, ChapterInfo};
+use crate::audio::metadata::Chapter;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Max start-time difference when matching chapters to Audible's list
+const MATCH_TOLERANCE_MS: i64 = 2000;
+
+lazy_static! {
+    static ref GENERIC_TITLE: Regex = Regex::new(
+        r"(?i)^\s*(?:\d+\s*[-_.:]\s*)?(?:(?:track|chapter|chap\.?|ch\.?|part|section|file|kapitel|cap[ií]tulo|chapitre|hoofdstuk)\s*)?#?\s*\d*\s*$"
+    )
+    .unwrap();
+    static ref NUMBER_PREFIX: Regex = Regex::new(
+        r"(?i)^\s*(?:(?:track|chapter|chap\.?|ch\.?|part|kapitel|cap[ií]tulo|chapitre)\s*)?\d+\s*[:.\-–—)]\s*"
+    )
+    .unwrap();
+}
+
+/// How chapter titles are rewritten
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChapterNaming {
+    /// Title template (see module docs for placeholders)
+    pub template: String,
+
+    /// Only rewrite generic titles; keep real titles as they are
+    pub only_generic: bool,
+
+    /// Drop an existing "Chapter 3:" / "3." prefix before applying the
+    /// template, so renumbering doesn't stack numbers
+    pub strip_numbering: bool,
+
+    /// Take titles from Audible's chapter_info where the local ones are generic
+    pub use_audible_titles: bool,
+}
+
+impl Default for ChapterNaming {
+    fn default() -> Self {
+        Self {
+            template: "Chapter {n}: {title}".to_string(),
+            only_generic: true,
+            strip_numbering: false,
+            use_audible_titles: true,
+        }
+    }
+}
+
+/// Whether a chapter title carries no information ("Track 01", "003", "")
+pub fn is_generic_chapter_title(title: &str) -> bool {
+    GENERIC_TITLE.is_match(title)
+}
+
+/// Remove a leading "Chapter 3:" / "3." style number, if any text remains
+fn strip_chapter_number(title: &str) -> &str {
+    match NUMBER_PREFIX.find(title) {
+        Some(prefix) if prefix.end() < title.trim_end().len() => &title[prefix.end()..],
+        _ => title,
+    }
+}
+
+/// Chapters from Audible's chapter_info, flattened with ": " between levels
+pub fn chapters_from_audible(info: &ChapterInfo) -> Vec<Chapter> {
+    flatten_chapters(info.chapters.clone(), Some(": "))
+        .into_iter()
+        .map(|chapter| Chapter {
+            title: chapter.title.trim().to_string(),
+            start_ms: chapter.start_offset_ms,
+            end_ms: chapter.start_offset_ms + chapter.length_ms,
+        })
+        .collect()
+}
+
+/// Replace generic titles with Audible's title for the same chapter
+///
+/// Chapters are matched by position when both lists have the same length,
+/// otherwise by the closest start time within two seconds.
+pub fn apply_audible_titles(chapters: &mut [Chapter], audible: &[Chapter]) {
+    let same_layout = chapters.len() == audible.len();
+
+    for (i, chapter) in chapters.iter_mut().enumerate() {
+        if !is_generic_chapter_title(&chapter.title) {
+            continue;
+        }
+
+        let matched = if same_layout {
+            audible.get(i)
+        } else {
+            audible
+                .iter()
+                .filter(|a| (a.start_ms - chapter.start_ms).abs() <= MATCH_TOLERANCE_MS)
+                .min_by_key(|a| (a.start_ms - chapter.start_ms).abs())
+        };
+
+        if let Some(title) = matched.map(|a| &a.title).filter(|t| !is_generic_chapter_title(t)) {
+            chapter.title = title.clone();
+        }
+    }
+}
+
+/// Render the template for one chapter
+fn render_title(template: &str, number: usize, total: usize, title: &str) -> String {
+    let width = total.to_string().len();
+    let rendered = template
+        .replace("{nn}", &format!("{:0width$}", number, width = width))
+        .replace("{n}", &number.to_string())
+        .replace("{total}", &total.to_string())
+        .replace("{title}", title.trim());
+
+    let collapsed = rendered.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !title.trim().is_empty() {
+        return collapsed;
+    }
+
+    let is_separator = |c: char| c.is_whitespace() || ":-–—|,.".contains(c);
+    let trimmed = collapsed.trim_start_matches(is_separator);
+    let mut end = trimmed.trim_end_matches(is_separator).len();
+    // "1." numbers the chapter; the period isn't a dangling separator
+    if trimmed[..end].ends_with(|c: char| c.is_ascii_digit()) && trimmed[end..].starts_with('.') {
+        end += 1;
+    }
+
+    if end == 0 {
+        format!("Chapter {}", number)
+    } else {
+        trimmed[..end].to_string()
+    }
+}
+
+/// Normalize chapter titles for tagging
+///
+/// # Arguments
+/// * `chapters` - Chapters from the file or the download license
+/// * `naming` - Template and rules
+/// * `audible` - Audible's chapters (see `chapters_from_audible`), if known
+///
+/// # Returns
+/// Chapters with the same timing and rewritten titles
+pub fn normalize_chapter_titles(
+    chapters: &[Chapter],
+    naming: &ChapterNaming,
+    audible: Option<&[Chapter]>,
+) -> Vec<Chapter> {
+    let mut result = chapters.to_vec();
+    if let Some(audible) = audible.filter(|_| naming.use_audible_titles) {
+        apply_audible_titles(&mut result, audible);
+    }
+
+    let total = result.len();
+    for (i, chapter) in result.iter_mut().enumerate() {
+        let generic = is_generic_chapter_title(&chapter.title);
+        if naming.only_generic && !generic {
+            continue;
+        }
+
+        let title = if generic {
+            ""
+        } else if naming.strip_numbering {
+            strip_chapter_number(&chapter.title)
+        } else {
+            &chapter.title
+        };
+        chapter.title = render_title(&naming.template, i + 1, total, title);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(title: &str, start_ms: i64) -> Chapter {
+        Chapter {
+            title: title.to_string(),
+            start_ms,
+            end_ms: start_ms + 60_000,
+        }
+    }
+
+    #[test]
+    fn test_generic_titles() {
+        for title in ["Track 01", "003", "Chapter 12", "ch. 4", "01 - Track 01", "", "  ", "Kapitel 7", "#3"] {
+            assert!(is_generic_chapter_title(title), "{:?} should be generic", title);
+        }
+        for title in ["The Storm", "Chapter 1: The Storm", "Prologue", "Part One"] {
+            assert!(!is_generic_chapter_title(title), "{:?} should not be generic", title);
+        }
+    }
+
+    #[test]
+    fn test_template_and_renumbering() {
+        let chapters = vec![
+            chapter("Track 01", 0),
+            chapter("Prologue", 60_000),
+            chapter("Chapter 7: The Storm", 120_000),
+        ];
+
+        let fixed = normalize_chapter_titles(&chapters, &ChapterNaming::default(), None);
+        let titles: Vec<_> = fixed.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Chapter 1", "Prologue", "Chapter 7: The Storm"]);
+
+        let renumber = ChapterNaming {
+            template: "{nn}. {title}".to_string(),
+            only_generic: false,
+            strip_numbering: true,
+            ..Default::default()
+        };
+        let fixed = normalize_chapter_titles(&chapters, &renumber, None);
+        let titles: Vec<_> = fixed.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["1.", "2. Prologue", "3. The Storm"]);
+
+        // Only an empty title's separators are trimmed
+        assert_eq!(render_title("Chapter {n}: {title}", 4, 12, ""), "Chapter 4");
+        assert_eq!(render_title("{nn} - {title}", 4, 12, "The End."), "04 - The End.");
+        assert_eq!(render_title("{title}", 4, 12, ""), "Chapter 4");
+        assert_eq!(fixed[2].start_ms, 120_000);
+    }
+
+    #[test]
+    fn test_audible_titles() {
+        let audible = vec![chapter("Opening", 0), chapter("The Storm", 61_000), chapter("Track 3", 130_000)];
+
+        // Different layout: matched by start time
+        let mut local = vec![chapter("Track 1", 500), chapter("Track 2", 60_000)];
+        apply_audible_titles(&mut local, &audible);
+        assert_eq!(local[0].title, "Opening");
+        assert_eq!(local[1].title, "The Storm");
+
+        // Generic Audible titles fall through to the template
+        let local = vec![chapter("01", 0), chapter("02", 60_000), chapter("03", 120_000)];
+        let fixed = normalize_chapter_titles(&local, &ChapterNaming::default(), Some(&audible));
+        let titles: Vec<_> = fixed.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Opening", "The Storm", "Chapter 3"]);
+    }
+}
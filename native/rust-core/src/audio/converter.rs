@@ -46,19 +46,150 @@
 //! - If different codecs: re-encode (slower, quality loss)
 //! - AAX → M4B: Copy (both AAC)
 //! - M4B → MP3: Re-encode (AAC → MP3)
-
+//!
+//! ## Editing Primitives
+//! - `extract_range`: cut `[start, end)` out of a file into a new file
+//! - `concat_files`: join files with the FFmpeg concat demuxer
+//! - `replace_range`: swap (or drop) a time range, built from the two above
+//! - All three stream-copy (`-c copy`), so M4B edits are lossless; cut points
+//!   snap to the nearest AAC packet (~23ms)
+//!
+//! ## Progress and Cancellation
+//! - FFmpeg runs with `-progress pipe:1 -nostats`; its key=value report on
+//!   stdout is turned into `ConversionProgress` (fraction, speed as a
+//!   multiple of realtime, ETA)
+//! - Conversions run under a progress scope (e.g. in a job) also emit
+//!   `converting` progress events to the host (see `events`)
+//! - `convert_with_events` accepts a `CancellationToken`; on cancel FFmpeg
+//!   is killed and the partial output file is removed
+//!
+//! ## Channel and Sample Rate Checks
+//! - Source channels and sample rate are probed natively (`probe`) before
+//!   converting; the output is probed afterward
+//! - The output must keep the source layout unless `downsample_mono` or
+//!   `sample_rate` asks otherwise; a mismatch (e.g. a stereo source coming
+//!   out mono) fails the conversion with a report of source, requested and
+//!   actual properties, and the output is removed
+//! - Successful conversions return both in `ConversionResult`
+
+use crate::activity::{self, WorkType};
+use crate::cancel::CancellationToken;
+use crate::audio::capabilities::require_native_ffmpeg;
 use crate::audio::decoder::{AudioDecoder, AudioFormat};
+use crate::audio::probe::{probe_audio_properties, AudioProperties};
+use crate::download::adaptive::ResourceLimits;
 use crate::error::{LibationError, Result};
+use crate::events::{self, ProgressEvent, ProgressStage};
+use crate::file::temp::ScratchDir;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
-use tokio::sync::Mutex;
 
 /// Conversion progress callback type
 pub type ProgressCallback = Arc<dyn Fn(f32) + Send + Sync>;
 
+/// Detailed conversion progress callback type
+pub type ConversionProgressCallback = Arc<dyn Fn(ConversionProgress) + Send + Sync>;
+
+/// Normalized progress of a running conversion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConversionProgress {
+    /// Completed fraction (0.0 - 1.0)
+    pub fraction: f32,
+
+    /// Seconds of audio processed so far
+    pub processed_seconds: f64,
+
+    /// Total seconds of audio (0 if unknown)
+    pub total_seconds: f64,
+
+    /// Processing speed as a multiple of realtime (e.g. 25.0 = 25x)
+    pub speed: Option<f32>,
+
+    /// Estimated seconds remaining
+    pub eta_seconds: Option<f64>,
+}
+
+/// Accumulates FFmpeg `-progress` key=value lines into progress reports
+///
+/// FFmpeg writes one block per update, terminated by `progress=continue`
+/// (or `progress=end` for the last one).
+struct FfmpegProgressParser {
+    total_seconds: f64,
+    processed_seconds: f64,
+    speed: Option<f32>,
+}
+
+impl FfmpegProgressParser {
+    fn new(total_seconds: f64) -> Self {
+        Self {
+            total_seconds,
+            processed_seconds: 0.0,
+            speed: None,
+        }
+    }
+
+    /// Feed one line; returns a report when a block is complete
+    fn feed(&mut self, line: &str) -> Option<ConversionProgress> {
+        let (key, value) = line.trim().split_once('=')?;
+        match key {
+            // Despite the name, out_time_ms is in microseconds like out_time_us
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(micros) = value.parse::<i64>() {
+                    self.processed_seconds = micros.max(0) as f64 / 1_000_000.0;
+                }
+            }
+            "out_time" => {
+                if let Some(seconds) = AudioConverter::parse_timestamp(value) {
+                    self.processed_seconds = seconds;
+                }
+            }
+            "speed" => {
+                self.speed = value
+                    .trim()
+                    .trim_end_matches('x')
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|speed| *speed > 0.0);
+            }
+            "progress" => return Some(self.report()),
+            _ => {}
+        }
+        None
+    }
+
+    fn report(&self) -> ConversionProgress {
+        let fraction = if self.total_seconds > 0.0 {
+            (self.processed_seconds / self.total_seconds).clamp(0.0, 1.0) as f32
+        } else {
+            0.0
+        };
+        let eta_seconds = match self.speed {
+            Some(speed) if self.total_seconds > 0.0 => {
+                Some((self.total_seconds - self.processed_seconds).max(0.0) / speed as f64)
+            }
+            _ => None,
+        };
+
+        ConversionProgress {
+            fraction,
+            processed_seconds: self.processed_seconds,
+            total_seconds: self.total_seconds,
+            speed: self.speed,
+            eta_seconds,
+        }
+    }
+}
+
+/// Audio properties of a finished conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversionResult {
+    pub source: AudioProperties,
+    pub output: AudioProperties,
+}
+
 /// Bitrate options for lossy encoding
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Bitrate {
@@ -98,6 +229,12 @@ pub struct ConversionOptions {
 
     /// Downsample to mono (reduce file size)
     pub downsample_mono: bool,
+
+    /// Resample to this rate in Hz (None keeps the source rate)
+    pub sample_rate: Option<u32>,
+
+    /// FFmpeg encoding threads (None lets FFmpeg decide)
+    pub threads: Option<u32>,
 }
 
 impl Default for ConversionOptions {
@@ -110,10 +247,20 @@ impl Default for ConversionOptions {
             preserve_chapters: true,
             overwrite_existing: false,
             downsample_mono: false,
+            sample_rate: None,
+            threads: None,
         }
     }
 }
 
+impl ConversionOptions {
+    /// Apply device-derived resource limits (encoding threads)
+    pub fn with_limits(mut self, limits: &ResourceLimits) -> Self {
+        self.threads = limits.encoding_threads;
+        self
+    }
+}
+
 /// Audio converter
 /// Handles conversion between different audio formats using FFmpeg
 pub struct AudioConverter {
@@ -129,7 +276,7 @@ impl AudioConverter {
     /// Convert audio file to specified format
     ///
     /// Based on ConvertToMp3.cs::ProcessAsync
-    pub async fn convert(&self, input: &Path, output: &Path) -> Result<()> {
+    pub async fn convert(&self, input: &Path, output: &Path) -> Result<ConversionResult> {
         self.convert_with_progress(input, output, Arc::new(|_| {}))
             .await
     }
@@ -142,7 +289,31 @@ impl AudioConverter {
         input: &Path,
         output: &Path,
         progress_callback: ProgressCallback,
-    ) -> Result<()> {
+    ) -> Result<ConversionResult> {
+        self.convert_with_events(
+            input,
+            output,
+            Arc::new(move |progress| progress_callback(progress.fraction)),
+            None,
+        )
+        .await
+    }
+
+    /// Convert with detailed progress and optional cancellation
+    ///
+    /// Cancelling `cancel` kills FFmpeg, removes the partial output, and
+    /// returns `LibationError::Cancelled`.
+    ///
+    /// # Errors
+    /// ConversionFailed with a mismatch report if the output's channels or
+    /// sample rate differ from what the options ask for (the output is removed)
+    pub async fn convert_with_events(
+        &self,
+        input: &Path,
+        output: &Path,
+        on_progress: ConversionProgressCallback,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<ConversionResult> {
         // Validate input exists
         if !input.exists() {
             return Err(LibationError::FileNotFound(format!(
@@ -158,28 +329,64 @@ impl AudioConverter {
             ));
         }
 
+        require_native_ffmpeg("convert")?;
+
         // Detect input format
         let input_format = AudioDecoder::detect_format(input).await?;
 
         // Get duration for progress tracking
         let duration = AudioDecoder::get_duration(input).await?;
 
+        let source = Self::probe_properties(input).await?;
+
         // Check if conversion is needed
         if input_format == self.options.output_format && !self.needs_processing() {
             // Just copy the file
             tokio::fs::copy(input, output).await.map_err(|e| {
                 LibationError::FileIoError(format!("copy: {} - {}", output.display(), e))
             })?;
-            progress_callback(1.0);
-            return Ok(());
+            on_progress(ConversionProgress {
+                fraction: 1.0,
+                processed_seconds: duration,
+                total_seconds: duration,
+                speed: None,
+                eta_seconds: Some(0.0),
+            });
+            return Ok(ConversionResult {
+                source,
+                output: source,
+            });
         }
 
         // Build FFmpeg command
         let command = self.build_ffmpeg_command(input, output, input_format)?;
 
-        // Execute conversion with progress tracking
-        self.execute_conversion(&command, duration, progress_callback)
-            .await?;
+        // Ends once the conversion finishes and the callback is dropped
+        let work = activity::begin_work(WorkType::Conversion, None);
+        let operation = events::current_operation();
+        let on_progress: ConversionProgressCallback = Arc::new(move |progress: ConversionProgress| {
+            let eta = progress.eta_seconds.filter(|eta| eta.is_finite() && *eta >= 0.0);
+            if let Some(eta) = eta {
+                work.set_expected_duration(Some(std::time::Duration::from_secs_f64(eta)));
+            }
+            if let Some((category, id)) = &operation {
+                events::emit_progress(ProgressEvent {
+                    eta_secs: eta.map(|eta| eta.round() as u64),
+                    ..ProgressEvent::for_fraction(*category, id, ProgressStage::Converting, progress.fraction)
+                });
+            }
+            on_progress(progress)
+        });
+
+        // Execute conversion with progress tracking; a failed or cancelled
+        // run leaves a truncated file behind
+        if let Err(e) = self
+            .execute_conversion(&command, duration, on_progress, cancel)
+            .await
+        {
+            let _ = tokio::fs::remove_file(output).await;
+            return Err(e);
+        }
 
         // Verify output was created
         if !output.exists() {
@@ -188,7 +395,74 @@ impl AudioConverter {
             ));
         }
 
-        Ok(())
+        let actual = Self::probe_properties(output).await?;
+        if let Err(e) = self.check_output_properties(&source, &actual) {
+            let _ = tokio::fs::remove_file(output).await;
+            return Err(e);
+        }
+
+        Ok(ConversionResult {
+            source,
+            output: actual,
+        })
+    }
+
+    /// Channels and sample rate, natively or through ffprobe for formats
+    /// the native probe doesn't read
+    async fn probe_properties(path: &Path) -> Result<AudioProperties> {
+        match probe_audio_properties(path).await {
+            Ok(properties) => Ok(properties),
+            Err(native_error) => AudioDecoder::get_audio_info(path)
+                .await
+                .ok()
+                .filter(|info| info.channels > 0 && info.sample_rate > 0)
+                .map(|info| AudioProperties {
+                    channels: info.channels,
+                    sample_rate: info.sample_rate,
+                })
+                .ok_or(native_error),
+        }
+    }
+
+    /// Properties the output must have for a given source
+    fn expected_properties(&self, source: &AudioProperties) -> AudioProperties {
+        AudioProperties {
+            channels: if self.options.downsample_mono { 1 } else { source.channels },
+            sample_rate: self.options.sample_rate.unwrap_or(source.sample_rate),
+        }
+    }
+
+    /// Fail with a mismatch report if the output doesn't match the options
+    fn check_output_properties(&self, source: &AudioProperties, actual: &AudioProperties) -> Result<()> {
+        let expected = self.expected_properties(source);
+
+        let mut mismatches = Vec::new();
+        if actual.channels != expected.channels {
+            mismatches.push(format!("channels: expected {}, got {}", expected.channels, actual.channels));
+        }
+        if actual.sample_rate != expected.sample_rate {
+            mismatches.push(format!(
+                "sample rate: expected {} Hz, got {} Hz",
+                expected.sample_rate, actual.sample_rate
+            ));
+        }
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+
+        Err(LibationError::ConversionFailed(format!(
+            "Output audio doesn't match the requested channels/sample rate ({}). \
+             Source: {}; requested: {} ({}, {}); output: {}",
+            mismatches.join("; "),
+            source,
+            expected,
+            if self.options.downsample_mono { "downmix to mono" } else { "keep channels" },
+            match self.options.sample_rate {
+                Some(rate) => format!("resample to {} Hz", rate),
+                None => "keep sample rate".to_string(),
+            },
+            actual
+        )))
     }
 
     /// Split audio by chapters
@@ -199,6 +473,8 @@ impl AudioConverter {
         input: &Path,
         output_dir: &Path,
     ) -> Result<Vec<PathBuf>> {
+        require_native_ffmpeg("split_by_chapters")?;
+
         // Create output directory if it doesn't exist
         tokio::fs::create_dir_all(output_dir).await.map_err(|e| {
             LibationError::FileIoError(format!(
@@ -241,7 +517,7 @@ impl AudioConverter {
             )?;
 
             // Execute conversion
-            self.execute_conversion(&command, chapter.duration_seconds, Arc::new(|_| {}))
+            self.execute_conversion(&command, chapter.duration_seconds, Arc::new(|_| {}), None)
                 .await?;
 
             output_files.push(output_path);
@@ -250,6 +526,248 @@ impl AudioConverter {
         Ok(output_files)
     }
 
+    /// Extract the time range `[start_seconds, end_seconds)` into a new file
+    ///
+    /// Streams are copied, not re-encoded. Pass `None` as `end_seconds` to
+    /// keep everything from `start_seconds` to the end of the file.
+    pub async fn extract_range(
+        &self,
+        input: &Path,
+        output: &Path,
+        start_seconds: f64,
+        end_seconds: Option<f64>,
+    ) -> Result<()> {
+        Self::validate_range(start_seconds, end_seconds)?;
+        self.check_edit_paths(&[input], output)?;
+        require_native_ffmpeg("extract_range")?;
+
+        let command = self.build_extract_range_command(input, output, start_seconds, end_seconds);
+        let duration = end_seconds.map(|end| end - start_seconds).unwrap_or(0.0);
+        self.execute_conversion(&command, duration, Arc::new(|_| {}), None)
+            .await?;
+
+        Self::verify_output(output)
+    }
+
+    /// Concatenate files (in order) into a single output file
+    ///
+    /// All inputs must share codec parameters (e.g. pieces cut from the same
+    /// book); streams are copied, not re-encoded.
+    pub async fn concat_files(&self, inputs: &[PathBuf], output: &Path) -> Result<()> {
+        if inputs.is_empty() {
+            return Err(LibationError::InvalidInput(
+                "concat_files requires at least one input".to_string(),
+            ));
+        }
+        let input_refs: Vec<&Path> = inputs.iter().map(|p| p.as_path()).collect();
+        self.check_edit_paths(&input_refs, output)?;
+        require_native_ffmpeg("concat_files")?;
+
+        // The concat demuxer reads its inputs from a list file
+        let scratch = ScratchDir::new()?;
+        let list_path = scratch.file("concat.txt")?;
+        tokio::fs::write(&list_path, Self::build_concat_list(inputs))
+            .await
+            .map_err(|e| {
+                LibationError::FileIoError(format!("write: {} - {}", list_path.display(), e))
+            })?;
+
+        let command = self.build_concat_command(&list_path, output);
+        self.execute_conversion(&command, 0.0, Arc::new(|_| {}), None)
+            .await?;
+
+        Self::verify_output(output)
+    }
+
+    /// Replace the time range `[start_seconds, end_seconds)` of `input`
+    ///
+    /// The range is swapped for `replacement`, or simply removed when
+    /// `replacement` is `None` (e.g. dropping a duplicated chapter).
+    pub async fn replace_range(
+        &self,
+        input: &Path,
+        output: &Path,
+        start_seconds: f64,
+        end_seconds: f64,
+        replacement: Option<&Path>,
+    ) -> Result<()> {
+        Self::validate_range(start_seconds, Some(end_seconds))?;
+        let mut sources = vec![input];
+        sources.extend(replacement);
+        self.check_edit_paths(&sources, output)?;
+        require_native_ffmpeg("replace_range")?;
+
+        let ext = input.extension().and_then(|e| e.to_str()).unwrap_or("m4b");
+        let head = Self::temp_sibling(output, &format!("head.{}", ext));
+        let tail = Self::temp_sibling(output, &format!("tail.{}", ext));
+
+        // Intermediate pieces are always ours to overwrite
+        let piece_converter = AudioConverter::new(ConversionOptions {
+            overwrite_existing: true,
+            ..self.options.clone()
+        });
+
+        let result = async {
+            let mut pieces = Vec::new();
+            if start_seconds > 0.0 {
+                piece_converter
+                    .extract_range(input, &head, 0.0, Some(start_seconds))
+                    .await?;
+                pieces.push(head.clone());
+            }
+            if let Some(replacement) = replacement {
+                pieces.push(replacement.to_path_buf());
+            }
+            piece_converter
+                .extract_range(input, &tail, end_seconds, None)
+                .await?;
+            pieces.push(tail.clone());
+
+            self.concat_files(&pieces, output).await
+        }
+        .await;
+
+        let _ = tokio::fs::remove_file(&head).await;
+        let _ = tokio::fs::remove_file(&tail).await;
+        result
+    }
+
+    /// Validate an editing time range
+    fn validate_range(start_seconds: f64, end_seconds: Option<f64>) -> Result<()> {
+        if !start_seconds.is_finite() || start_seconds < 0.0 {
+            return Err(LibationError::InvalidInput(format!(
+                "Invalid range start: {}",
+                start_seconds
+            )));
+        }
+        if let Some(end) = end_seconds {
+            if !end.is_finite() || end <= start_seconds {
+                return Err(LibationError::InvalidInput(format!(
+                    "Invalid range: start {} must be before end {}",
+                    start_seconds, end
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that editing inputs exist and the output may be written
+    fn check_edit_paths(&self, inputs: &[&Path], output: &Path) -> Result<()> {
+        for input in inputs {
+            if !input.exists() {
+                return Err(LibationError::FileNotFound(format!(
+                    "{}: Input file does not exist",
+                    input.display()
+                )));
+            }
+        }
+
+        if output.exists() && !self.options.overwrite_existing {
+            return Err(LibationError::FileAlreadyExists(
+                output.to_string_lossy().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Verify that FFmpeg produced the output file
+    fn verify_output(output: &Path) -> Result<()> {
+        if !output.exists() {
+            return Err(LibationError::ConversionFailed(
+                "Output file was not created".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Path for an intermediate file next to `output`
+    ///
+    /// For pieces of the audio itself, which are as large as the output and
+    /// belong on its filesystem; small helper files go in a `ScratchDir`.
+    fn temp_sibling(output: &Path, suffix: &str) -> PathBuf {
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("audiobook");
+        output.with_file_name(format!(".{}.{}.{}", stem, uuid::Uuid::new_v4(), suffix))
+    }
+
+    /// Build FFmpeg command for extracting a time range (stream copy)
+    fn build_extract_range_command(
+        &self,
+        input: &Path,
+        output: &Path,
+        start_seconds: f64,
+        end_seconds: Option<f64>,
+    ) -> Vec<String> {
+        // -ss before -i seeks the demuxer, which is fast and exact to the packet
+        let mut cmd = vec![
+            "ffmpeg".to_string(),
+            "-ss".to_string(),
+            format!("{:.3}", start_seconds),
+            "-i".to_string(),
+            input.to_string_lossy().to_string(),
+        ];
+
+        if let Some(end) = end_seconds {
+            cmd.push("-t".to_string());
+            cmd.push(format!("{:.3}", end - start_seconds));
+        }
+
+        cmd.push("-map".to_string());
+        cmd.push("0:a".to_string());
+        cmd.push("-c".to_string());
+        cmd.push("copy".to_string());
+
+        if self.options.preserve_metadata {
+            cmd.push("-map_metadata".to_string());
+            cmd.push("0".to_string());
+        }
+
+        if self.options.overwrite_existing {
+            cmd.push("-y".to_string());
+        }
+
+        cmd.push(output.to_string_lossy().to_string());
+        cmd
+    }
+
+    /// Build FFmpeg command for the concat demuxer (stream copy)
+    fn build_concat_command(&self, list_path: &Path, output: &Path) -> Vec<String> {
+        let mut cmd = vec![
+            "ffmpeg".to_string(),
+            "-f".to_string(),
+            "concat".to_string(),
+            // Allow absolute paths in the list file
+            "-safe".to_string(),
+            "0".to_string(),
+            "-i".to_string(),
+            list_path.to_string_lossy().to_string(),
+            "-map".to_string(),
+            "0:a".to_string(),
+            "-c".to_string(),
+            "copy".to_string(),
+        ];
+
+        if self.options.overwrite_existing {
+            cmd.push("-y".to_string());
+        }
+
+        cmd.push(output.to_string_lossy().to_string());
+        cmd
+    }
+
+    /// Build concat demuxer list file contents
+    ///
+    /// Single quotes are escaped as `'\''` per the FFmpeg quoting rules.
+    fn build_concat_list(inputs: &[PathBuf]) -> String {
+        inputs
+            .iter()
+            .map(|p| format!("file '{}'\n", p.to_string_lossy().replace('\'', "'\\''")))
+            .collect()
+    }
+
     /// Build FFmpeg command for conversion
     ///
     /// Based on ConvertToMp3.cs FFmpeg command construction
@@ -318,12 +836,24 @@ impl AudioConverter {
             }
         }
 
+        // Encoding threads (reduced when the device is hot or low on battery)
+        if let Some(threads) = self.options.threads {
+            cmd.push("-threads".to_string());
+            cmd.push(threads.to_string());
+        }
+
         // Mono downsampling
         if self.options.downsample_mono {
             cmd.push("-ac".to_string());
             cmd.push("1".to_string());
         }
 
+        // Resampling
+        if let Some(rate) = self.options.sample_rate {
+            cmd.push("-ar".to_string());
+            cmd.push(rate.to_string());
+        }
+
         // Metadata preservation
         if self.options.preserve_metadata {
             cmd.push("-map_metadata".to_string());
@@ -385,16 +915,21 @@ impl AudioConverter {
 
     /// Execute FFmpeg conversion with progress tracking
     ///
-    /// Based on ConvertToMp3.cs::ConversionProgressUpdate
+    /// Based on ConvertToMp3.cs::ConversionProgressUpdate. Progress is read
+    /// from FFmpeg's `-progress` report on stdout; stderr is kept for errors.
     async fn execute_conversion(
         &self,
         command: &[String],
         total_duration: f64,
-        progress_callback: ProgressCallback,
+        on_progress: ConversionProgressCallback,
+        cancel: Option<&CancellationToken>,
     ) -> Result<()> {
         let mut child = Command::new(&command[0])
+            .args(["-progress", "pipe:1", "-nostats"])
             .args(&command[1..])
+            .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
             .spawn()
             .map_err(|e| {
                 if e.kind() == std::io::ErrorKind::NotFound {
@@ -404,79 +939,87 @@ impl AudioConverter {
                 }
             })?;
 
-        // Read stderr for progress
-        let stderr = child.stderr.take().ok_or_else(|| {
-            LibationError::FfmpegError("Failed to capture ffmpeg stderr".to_string())
-        })?;
-
-        let reader = BufReader::new(stderr);
-        let mut lines = reader.lines();
-
-        let last_progress = Arc::new(Mutex::new(0.0f32));
+        let (stdout, stderr) = match (child.stdout.take(), child.stderr.take()) {
+            (Some(stdout), Some(stderr)) => (stdout, stderr),
+            _ => {
+                return Err(LibationError::FfmpegError(
+                    "Failed to capture ffmpeg output".to_string(),
+                ))
+            }
+        };
 
         // Spawn task to read progress
         let progress_task = tokio::spawn({
-            let progress_callback = progress_callback.clone();
-            let last_progress = last_progress.clone();
+            let on_progress = on_progress.clone();
             async move {
+                let mut lines = BufReader::new(stdout).lines();
+                let mut parser = FfmpegProgressParser::new(total_duration);
+                let mut last_fraction = 0.0f32;
                 while let Ok(Some(line)) = lines.next_line().await {
-                    if let Some(progress) =
-                        Self::parse_ffmpeg_progress(&line, total_duration)
-                    {
-                        let mut last = last_progress.lock().await;
-                        if (progress - *last).abs() > 0.01 {
-                            // Update every 1%
-                            *last = progress;
-                            progress_callback(progress);
+                    if let Some(progress) = parser.feed(&line) {
+                        // Update every 1%
+                        if (progress.fraction - last_fraction).abs() > 0.01 {
+                            last_fraction = progress.fraction;
+                            on_progress(progress);
                         }
                     }
                 }
             }
         });
 
-        // Wait for FFmpeg to complete
-        let status = child.wait().await.map_err(|e| {
-            LibationError::FfmpegError(format!("FFmpeg process failed: {}", e))
-        })?;
+        // Keep the tail of stderr for error reporting
+        let stderr_task = tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            let mut tail = std::collections::VecDeque::with_capacity(5);
+            while let Ok(Some(line)) = lines.next_line().await {
+                if tail.len() == 5 {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+            tail.into_iter().collect::<Vec<_>>().join("\n")
+        });
 
-        // Wait for progress task to complete
+        // Wait for FFmpeg to complete or for cancellation
+        let status = match cancel {
+            Some(cancel) => {
+                tokio::select! {
+                    status = child.wait() => status,
+                    _ = cancel.cancelled() => {
+                        let _ = child.kill().await;
+                        progress_task.abort();
+                        stderr_task.abort();
+                        return Err(LibationError::Cancelled);
+                    }
+                }
+            }
+            None => child.wait().await,
+        }
+        .map_err(|e| LibationError::FfmpegError(format!("FFmpeg process failed: {}", e)))?;
+
+        // Wait for output readers to complete
         let _ = progress_task.await;
+        let stderr_tail = stderr_task.await.unwrap_or_default();
 
         if !status.success() {
             return Err(LibationError::ConversionFailed(format!(
-                "FFmpeg exited with status: {}",
-                status
+                "FFmpeg exited with status: {}: {}",
+                status, stderr_tail
             )));
         }
 
         // Final progress update
-        progress_callback(1.0);
+        on_progress(ConversionProgress {
+            fraction: 1.0,
+            processed_seconds: total_duration,
+            total_seconds: total_duration,
+            speed: None,
+            eta_seconds: Some(0.0),
+        });
 
         Ok(())
     }
 
-    /// Parse FFmpeg progress from stderr line
-    ///
-    /// FFmpeg outputs: "time=00:01:23.45 bitrate=64.0kbits/s"
-    fn parse_ffmpeg_progress(line: &str, total_duration: f64) -> Option<f32> {
-        // Look for "time=" in the line
-        if let Some(time_start) = line.find("time=") {
-            let time_str = &line[time_start + 5..];
-            // Extract timestamp (HH:MM:SS.ss)
-            let time_end = time_str.find(' ').unwrap_or(time_str.len());
-            let timestamp = &time_str[..time_end];
-
-            // Parse HH:MM:SS.ss
-            if let Some(elapsed_seconds) = Self::parse_timestamp(timestamp) {
-                if total_duration > 0.0 {
-                    let progress = (elapsed_seconds / total_duration).min(1.0) as f32;
-                    return Some(progress);
-                }
-            }
-        }
-        None
-    }
-
     /// Parse timestamp in format HH:MM:SS.ss to seconds
     fn parse_timestamp(timestamp: &str) -> Option<f64> {
         let parts: Vec<&str> = timestamp.split(':').collect();
@@ -492,7 +1035,7 @@ impl AudioConverter {
 
     /// Check if any processing is needed beyond format change
     fn needs_processing(&self) -> bool {
-        self.options.downsample_mono
+        self.options.downsample_mono || self.options.sample_rate.is_some()
     }
 
     /// Convert VBR quality (0-9) to approximate CBR bitrate for AAC
@@ -606,11 +1149,85 @@ mod tests {
 
     #[test]
     fn test_parse_ffmpeg_progress() {
-        let line = "frame=1234 fps=100 q=-0.0 size=1024kB time=00:01:23.45 bitrate=64.0kbits/s speed=2.0x";
-        let progress = AudioConverter::parse_ffmpeg_progress(line, 600.0);
-        assert!(progress.is_some());
-        let p = progress.unwrap();
-        assert!((p - 0.1391).abs() < 0.01); // ~83.45 / 600 = 0.139
+        let mut parser = FfmpegProgressParser::new(600.0);
+        let block = "bitrate=64.0kbits/s\nout_time_us=83450000\nout_time=00:01:23.450000\nspeed=2.0x\nprogress=continue";
+        let reports: Vec<_> = block.lines().filter_map(|line| parser.feed(line)).collect();
+        assert_eq!(reports.len(), 1);
+
+        let p = reports[0];
+        assert!((p.fraction - 0.1391).abs() < 0.01); // ~83.45 / 600 = 0.139
+        assert_eq!(p.speed, Some(2.0));
+        assert!((p.eta_seconds.unwrap() - 258.275).abs() < 0.001); // (600 - 83.45) / 2
+
+        // Speed is "N/A" until FFmpeg has a measurement
+        let mut parser = FfmpegProgressParser::new(0.0);
+        parser.feed("speed=N/A");
+        let p = parser.feed("progress=end").unwrap();
+        assert_eq!(p.fraction, 0.0);
+        assert_eq!(p.eta_seconds, None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_conversion_cancel() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stand-in for a long FFmpeg run that ignores its arguments
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("slow-ffmpeg");
+        std::fs::write(&script, "#!/bin/sh\nsleep 30\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let converter = AudioConverter::new(ConversionOptions::default());
+        let token = CancellationToken::new();
+        let command = vec![script.to_string_lossy().to_string()];
+
+        let run = converter.execute_conversion(&command, 60.0, Arc::new(|_| {}), Some(&token));
+        let cancel = async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            token.cancel();
+        };
+        let (result, _) = tokio::join!(run, cancel);
+
+        assert!(matches!(result, Err(LibationError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_convert_without_native_ffmpeg() {
+        if crate::audio::get_audio_capabilities().backend == crate::audio::AudioBackend::Native {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("book.aax");
+        std::fs::write(&input, b"not really audio").unwrap();
+
+        let converter = AudioConverter::new(ConversionOptions::default());
+        let result = converter.convert(&input, &dir.path().join("book.m4b")).await;
+        assert!(matches!(result, Err(LibationError::UnsupportedOperation { .. })));
+    }
+
+    #[test]
+    fn test_check_output_properties() {
+        let stereo = AudioProperties { channels: 2, sample_rate: 44100 };
+        let mono = AudioProperties { channels: 1, sample_rate: 44100 };
+
+        let converter = AudioConverter::new(ConversionOptions::default());
+        assert!(converter.check_output_properties(&stereo, &stereo).is_ok());
+        let err = converter.check_output_properties(&stereo, &mono).unwrap_err().to_string();
+        assert!(err.contains("channels: expected 2, got 1"), "{}", err);
+        assert!(err.contains("Source: stereo, 44100 Hz"), "{}", err);
+
+        let converter = AudioConverter::new(ConversionOptions {
+            downsample_mono: true,
+            sample_rate: Some(22050),
+            ..Default::default()
+        });
+        let resampled = AudioProperties { channels: 1, sample_rate: 22050 };
+        assert!(converter.check_output_properties(&stereo, &resampled).is_ok());
+        let err = converter.check_output_properties(&stereo, &mono).unwrap_err().to_string();
+        assert!(err.contains("sample rate: expected 22050 Hz, got 44100 Hz"), "{}", err);
+        assert!(!err.contains("channels:"), "{}", err);
     }
 
     #[test]
@@ -624,4 +1241,66 @@ mod tests {
     fn test_bitrate_default() {
         assert_eq!(Bitrate::default(), Bitrate::Vbr(2));
     }
+
+    #[test]
+    fn test_build_extract_range_command() {
+        let converter = AudioConverter::new(ConversionOptions::default());
+        let cmd = converter.build_extract_range_command(
+            Path::new("/in/book.m4b"),
+            Path::new("/out/part.m4b"),
+            90.5,
+            Some(120.0),
+        );
+        assert_eq!(&cmd[..5], &["ffmpeg", "-ss", "90.500", "-i", "/in/book.m4b"]);
+        assert!(cmd.windows(2).any(|w| w == ["-t", "29.500"]));
+        assert!(cmd.windows(2).any(|w| w == ["-c", "copy"]));
+        assert_eq!(cmd.last().unwrap(), "/out/part.m4b");
+
+        let open_ended = converter.build_extract_range_command(
+            Path::new("/in/book.m4b"),
+            Path::new("/out/tail.m4b"),
+            10.0,
+            None,
+        );
+        assert!(!open_ended.contains(&"-t".to_string()));
+    }
+
+    #[test]
+    fn test_build_concat_list_escapes_quotes() {
+        let list = AudioConverter::build_concat_list(&[
+            PathBuf::from("/a/head.m4b"),
+            PathBuf::from("/a/Ender's Game.m4b"),
+        ]);
+        assert_eq!(
+            list,
+            "file '/a/head.m4b'\nfile '/a/Ender'\\''s Game.m4b'\n"
+        );
+    }
+
+    #[test]
+    fn test_build_ffmpeg_command_threads() {
+        let limits = ResourceLimits {
+            max_concurrent_downloads: 1,
+            allow_conversion: true,
+            encoding_threads: Some(1),
+            throttled: true,
+        };
+        let converter = AudioConverter::new(ConversionOptions {
+            output_format: AudioFormat::Mp3,
+            ..ConversionOptions::default()
+        }.with_limits(&limits));
+        let cmd = converter
+            .build_ffmpeg_command(Path::new("/in/book.m4b"), Path::new("/out/book.mp3"), AudioFormat::M4b)
+            .unwrap();
+        assert!(cmd.windows(2).any(|w| w == ["-threads", "1"]));
+    }
+
+    #[test]
+    fn test_validate_range() {
+        assert!(AudioConverter::validate_range(0.0, Some(1.0)).is_ok());
+        assert!(AudioConverter::validate_range(5.0, None).is_ok());
+        assert!(AudioConverter::validate_range(5.0, Some(5.0)).is_err());
+        assert!(AudioConverter::validate_range(-1.0, None).is_err());
+        assert!(AudioConverter::validate_range(f64::NAN, None).is_err());
+    }
 }
@@ -0,0 +1,186 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Retrying tagging while another process holds the file
+//!
+//! Android's media scanner opens new audio files to index them, and
+//! tagging a file it holds fails with EBUSY (a sharing violation on
+//! Windows). The lock goes away within seconds, so tagging steps run
+//! through `retry_while_locked`: lock errors (`LibationError::is_file_locked`)
+//! are retried with exponential backoff up to `LockRetryPolicy::max_wait`,
+//! every other error fails right away. Each retry is appended to a history
+//! the caller can report.
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Tagging step being retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagStage {
+    Metadata,
+    CoverArt,
+    Chapters,
+}
+
+impl TagStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagStage::Metadata => "metadata",
+            TagStage::CoverArt => "cover_art",
+            TagStage::Chapters => "chapters",
+        }
+    }
+}
+
+/// How long to keep retrying a locked file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockRetryPolicy {
+    /// Give up once this much time has been spent waiting
+    pub max_wait: Duration,
+    /// Wait before the first retry; doubled each time
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for LockRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_wait: Duration::from_secs(30),
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(4),
+        }
+    }
+}
+
+/// One failed attempt that was retried
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockRetry {
+    pub stage: TagStage,
+    /// 1 for the first attempt
+    pub attempt: u32,
+    pub error: String,
+    /// Wait before the next attempt
+    pub delay_ms: u64,
+    /// ISO 8601 timestamp of the failure
+    pub failed_at: String,
+}
+
+/// Run `op`, retrying while it fails because the file is locked
+///
+/// # Arguments
+/// * `stage` - Recorded with each retry
+/// * `history` - Retries are appended here, including those of a call
+///   that finally fails
+///
+/// # Errors
+/// The first error that isn't a lock error, or `FileIoError` once the
+/// file is still locked after `policy.max_wait`
+pub async fn retry_while_locked<T, F, Fut>(
+    stage: TagStage,
+    policy: &LockRetryPolicy,
+    history: &mut Vec<LockRetry>,
+    mut op: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let started = Instant::now();
+    let mut delay = policy.initial_delay;
+    let mut attempt = 1;
+
+    loop {
+        let error = match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if e.is_file_locked() => e,
+            Err(e) => return Err(e),
+        };
+
+        if started.elapsed() + delay > policy.max_wait {
+            return Err(LibationError::FileIoError(format!(
+                "File still locked after {} attempts over {} ms ({}): {}",
+                attempt,
+                started.elapsed().as_millis(),
+                stage.as_str(),
+                error
+            )));
+        }
+
+        history.push(LockRetry {
+            stage,
+            attempt,
+            error: error.to_string(),
+            delay_ms: delay.as_millis() as u64,
+            failed_at: crate::storage::dates::now(),
+        });
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(policy.max_delay);
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn busy() -> LibationError {
+        LibationError::IoError(std::io::Error::from_raw_os_error(16))
+    }
+
+    #[tokio::test]
+    async fn test_retries_only_lock_errors() {
+        let policy = LockRetryPolicy {
+            max_wait: Duration::from_millis(200),
+            initial_delay: Duration::from_millis(5),
+            max_delay: Duration::from_millis(20),
+        };
+
+        // Scanner lets go after two tries
+        let mut history = Vec::new();
+        let mut calls = 0;
+        let value = retry_while_locked(TagStage::Chapters, &policy, &mut history, || {
+            calls += 1;
+            let result = if calls <= 2 { Err(busy()) } else { Ok(calls) };
+            async move { result }
+        })
+        .await
+        .unwrap();
+        assert_eq!(value, 3);
+        assert_eq!(history.iter().map(|r| (r.attempt, r.delay_ms)).collect::<Vec<_>>(), vec![(1, 5), (2, 10)]);
+
+        let ffmpeg_busy = LibationError::FfmpegError("FFmpeg failed: book.m4b: Device or resource busy".to_string());
+        assert!(ffmpeg_busy.is_file_locked());
+
+        // Other errors aren't retried
+        let mut history = Vec::new();
+        let result: Result<()> = retry_while_locked(TagStage::Metadata, &policy, &mut history, || async {
+            Err(LibationError::FileNotFound("book.m4b".to_string()))
+        })
+        .await;
+        assert!(matches!(result, Err(LibationError::FileNotFound(_))));
+        assert!(history.is_empty());
+
+        // A lock that never clears fails within max_wait
+        let mut history = Vec::new();
+        let result: Result<()> =
+            retry_while_locked(TagStage::CoverArt, &policy, &mut history, || async { Err(busy()) }).await;
+        assert!(matches!(result, Err(LibationError::FileIoError(_))));
+        assert!(history.len() >= 3 && history.iter().all(|r| r.stage == TagStage::CoverArt));
+    }
+}
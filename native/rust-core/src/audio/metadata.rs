@@ -44,7 +44,9 @@
 //! - Stored in MP3: ID3v2 CHAP frames
 //! - Format: [(title, start_ms, end_ms)]
 
+use crate::audio::chapters::{normalize_chapter_titles, ChapterNaming};
 use crate::error::{LibationError, Result};
+use crate::file::temp::ScratchDir;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::fs;
@@ -402,8 +404,9 @@ impl ChapterEditor {
         // Generate ffmetadata content
         let metadata_content = Self::generate_ffmetadata(chapters);
 
-        // Write to temporary metadata file
-        let metadata_file = file.with_extension("ffmetadata.txt");
+        // Write to temporary metadata file, removed with its scratch dir
+        let scratch = ScratchDir::new()?;
+        let metadata_file = scratch.file("chapters.ffmetadata.txt")?;
         let mut file_handle = fs::File::create(&metadata_file).await.map_err(|e| {
             LibationError::FileIoError(format!("{}: {} - {}", "create".to_string(), metadata_file.to_string_lossy().to_string(), e.to_string(),
             ))
@@ -444,9 +447,7 @@ impl ChapterEditor {
 
         // Execute FFmpeg
         MetadataEditor::execute_ffmpeg(&cmd).await?;
-
-        // Clean up metadata file
-        let _ = fs::remove_file(&metadata_file).await;
+        drop(scratch);
 
         // Replace original with temp file
         fs::rename(&temp_file, file).await.map_err(|e| {
@@ -457,6 +458,21 @@ impl ChapterEditor {
         Ok(())
     }
 
+    /// Normalize chapter titles, then embed them
+    ///
+    /// # Returns
+    /// The chapters as embedded, for storing with `storage::chapters::update_chapters`
+    pub async fn embed_normalized_chapters(
+        file: &Path,
+        chapters: &[Chapter],
+        naming: &ChapterNaming,
+        audible: Option<&[Chapter]>,
+    ) -> Result<Vec<Chapter>> {
+        let chapters = normalize_chapter_titles(chapters, naming, audible);
+        Self::embed_chapters(file, &chapters).await?;
+        Ok(chapters)
+    }
+
     /// Extract chapters from audio file
     pub async fn extract_chapters(file: &Path) -> Result<Vec<Chapter>> {
         let output = Command::new("ffprobe")
@@ -572,8 +588,9 @@ impl ChapterEditor {
 
     /// Generate FFmetadata format content
     ///
-    /// Used by FFmpeg for chapter embedding
-    fn generate_ffmetadata(chapters: &[Chapter]) -> String {
+    /// Used by FFmpeg for chapter embedding, and by the app when it tags
+    /// with FFmpeg-Kit itself
+    pub fn generate_ffmetadata(chapters: &[Chapter]) -> String {
         let mut content = String::from(";FFMETADATA1\n");
 
         for chapter in chapters {
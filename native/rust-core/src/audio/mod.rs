@@ -55,9 +55,36 @@
 //! - `ChapterEditor` - Embed/extract chapters, generate cue sheets
 //! - `SeriesInfo` - Series information
 //!
+//! ## lock_retry
+//! Tagging files another process holds:
+//! - `retry_while_locked()` - Backoff on EBUSY/sharing violations, with history
+//! - `LockRetryPolicy` - How long to keep trying (30 s by default)
+//!
+//! ## chapters
+//! Chapter title cleanup before tagging:
+//! - `normalize_chapter_titles()` - Audible titles, templates, renumbering
+//! - `ChapterNaming` - Template and rules
+//!
+//! ## probe
+//! Native channel/sample-rate probing (no FFmpeg needed):
+//! - `probe_audio_properties()` - MP4-family and MP3 files
+//! - `read_embedded_asin()` - ASIN tag of MP4-family files
+//! - Used by the converter to check outputs keep the source layout
+//!
+//! ## resume
+//! Where playback picks up again:
+//! - `resume_point()` - Stored position stepped back a few seconds, never
+//!   across a chapter start
+//! - `ResumeOptions` - Rewind and snap distances, stored in Settings
+//!
+//! ## capabilities
+//! Runtime detection of the audio backend:
+//! - `get_audio_capabilities()` - Native FFmpeg, app-provided FFmpeg-Kit, or none
+//! - Converter entry points return `UnsupportedOperation` without native FFmpeg
+//!
 //! # FFmpeg Integration
 //!
-//! This module requires FFmpeg and FFprobe to be installed and available in PATH:
+//! On desktop, this module requires FFmpeg and FFprobe to be installed and available in PATH:
 //! - FFmpeg: Audio conversion, metadata embedding, cover art handling
 //! - FFprobe: Format detection, metadata extraction, chapter reading
 //!
@@ -68,12 +95,29 @@
 //!
 //! ## Minimum Version
 //! FFmpeg 4.0 or higher is recommended for full feature support.
+//!
+//! ## Mobile
+//! Android and iOS have no FFmpeg executables. The app runs FFmpeg-Kit itself
+//! and reports it via `capabilities::set_external_ffmpeg_available`.
 
+pub mod capabilities;
+pub mod chapters;
 pub mod converter;
 pub mod decoder;
+pub mod lock_retry;
 pub mod metadata;
+pub mod probe;
+pub mod resume;
 
 // Re-export commonly used types for convenience
-pub use converter::{AudioConverter, Bitrate, ConversionOptions, ProgressCallback};
+pub use capabilities::{get_audio_capabilities, AudioBackend, AudioCapabilities};
+pub use chapters::{normalize_chapter_titles, ChapterNaming};
+pub use converter::{
+    AudioConverter, Bitrate, ConversionOptions, ConversionProgress, ConversionProgressCallback,
+    ConversionResult, ProgressCallback,
+};
 pub use decoder::{AudioDecoder, AudioFormat, AudioInfo, Codec};
+pub use lock_retry::{retry_while_locked, LockRetry, LockRetryPolicy, TagStage};
 pub use metadata::{AudioMetadata, Chapter, ChapterEditor, MetadataEditor, SeriesInfo};
+pub use probe::{probe_audio_properties, read_embedded_asin, AudioProperties};
+pub use resume::{resume_position, ResumeOptions, ResumePoint};
@@ -0,0 +1,584 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Native probing of channel count and sample rate
+//!
+//! `AudioDecoder::get_audio_info` needs `ffprobe`, which mobile devices
+//! don't have. The converter only needs the channel layout and sample rate
+//! to check that a conversion preserved them, and both can be read from
+//! the file directly:
+//! - **MP4/M4B/AAX/AAXC**: the first sound track's sample entry in `stsd`,
+//!   refined by the AAC AudioSpecificConfig in `esds` (HE-AAC decodes at
+//!   twice the core rate, HE-AACv2 to stereo)
+//! - **MP3**: the first frame header after any ID3v2 tag
+//!
+//! `read_embedded_asin` reads the ASIN tag from MP4-family files the same
+//! way, so files dropped into the watch folder can be matched without FFmpeg.
+
+use crate::audio::decoder::AudioFormat;
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Largest `moov` atom read into memory
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Bytes scanned for the first MP3 frame after the ID3v2 tag
+const MP3_SCAN_LIMIT: usize = 64 * 1024;
+
+/// AAC sampling frequency table (ISO 14496-3)
+const AAC_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// Decoded channel count and sample rate of an audio stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioProperties {
+    pub channels: u32,
+    pub sample_rate: u32,
+}
+
+impl std::fmt::Display for AudioProperties {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let layout = match self.channels {
+            1 => "mono".to_string(),
+            2 => "stereo".to_string(),
+            n => format!("{} channels", n),
+        };
+        write!(f, "{}, {} Hz", layout, self.sample_rate)
+    }
+}
+
+fn invalid(path: &Path, reason: &str) -> LibationError {
+    LibationError::InvalidAudioFile(format!("{}: {}", path.display(), reason))
+}
+
+/// Channel count and sample rate of an audio file, without FFmpeg
+///
+/// # Errors
+/// - UnsupportedAudioFormat for formats other than MP4-family and MP3
+/// - InvalidAudioFile if no audio track or frame header is found
+pub async fn probe_audio_properties(path: &Path) -> Result<AudioProperties> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || probe_blocking(&path))
+        .await
+        .map_err(|e| LibationError::InternalError(format!("Probe task failed: {}", e)))?
+}
+
+fn probe_blocking(path: &Path) -> Result<AudioProperties> {
+    let mut file = File::open(path).map_err(|e| LibationError::FileNotFound(format!("{}: {}", path.display(), e)))?;
+
+    let mut magic = [0u8; 12];
+    let read = file.read(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    if read >= 8 && &magic[4..8] == b"ftyp" {
+        return probe_mp4(&mut file, path);
+    }
+
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+    match AudioFormat::from_extension(&extension) {
+        AudioFormat::Mp3 => probe_mp3(&mut file, path),
+        _ if magic.starts_with(b"ID3") || (magic[0] == 0xFF && magic[1] & 0xE0 == 0xE0) => probe_mp3(&mut file, path),
+        format if format.is_mp4_container() => probe_mp4(&mut file, path),
+        format => Err(LibationError::UnsupportedAudioFormat(format!(
+            "Cannot probe {:?} files natively: {}",
+            format,
+            path.display()
+        ))),
+    }
+}
+
+// ============================================================================
+// MP4
+// ============================================================================
+
+fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4).map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+}
+
+/// Child atoms of `data[range]` as (type, body range)
+fn child_atoms(data: &[u8], range: Range<usize>) -> Vec<([u8; 4], Range<usize>)> {
+    let mut atoms = Vec::new();
+    let mut pos = range.start;
+
+    while pos + 8 <= range.end {
+        let Some(size) = be_u32(data, pos) else { break };
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let (header, size) = match size {
+            0 => (8, range.end - pos),
+            1 => match data.get(pos + 8..pos + 16) {
+                Some(b) => (16, u64::from_be_bytes(b.try_into().unwrap()) as usize),
+                None => break,
+            },
+            size => (8, size as usize),
+        };
+        if size < header || pos + size > range.end {
+            break;
+        }
+        atoms.push((kind, pos + header..pos + size));
+        pos += size;
+    }
+
+    atoms
+}
+
+fn find_child(data: &[u8], range: Range<usize>, kind: &[u8; 4]) -> Option<Range<usize>> {
+    child_atoms(data, range).into_iter().find(|(k, _)| k == kind).map(|(_, body)| body)
+}
+
+/// Read the `moov` atom of an MP4 file
+fn read_moov(file: &mut File, path: &Path) -> Result<Vec<u8>> {
+    let file_len = file.metadata()?.len();
+    let mut pos = 0u64;
+
+    while pos + 8 <= file_len {
+        let mut header = [0u8; 16];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut header[..8])?;
+        let size = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            0 => file_len - pos,
+            1 => {
+                file.read_exact(&mut header[8..])?;
+                u64::from_be_bytes(header[8..].try_into().unwrap())
+            }
+            size => u64::from(size),
+        };
+        if size < 8 || pos + size > file_len {
+            break;
+        }
+
+        if &header[4..8] == b"moov" {
+            if size > MAX_MOOV_SIZE {
+                return Err(invalid(path, "moov atom is too large"));
+            }
+            let mut moov = vec![0u8; size as usize];
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut moov)?;
+            return Ok(moov);
+        }
+        pos += size;
+    }
+
+    Err(invalid(path, "missing moov atom"))
+}
+
+fn probe_mp4(file: &mut File, path: &Path) -> Result<AudioProperties> {
+    let moov = read_moov(file, path)?;
+    let moov_body = 8..moov.len();
+
+    for (_, trak) in child_atoms(&moov, moov_body).into_iter().filter(|(k, _)| k == b"trak") {
+        let Some(mdia) = find_child(&moov, trak, b"mdia") else { continue };
+
+        // hdlr: version/flags, pre_defined, then the handler type
+        let is_sound = find_child(&moov, mdia.clone(), b"hdlr")
+            .and_then(|hdlr| moov.get(hdlr.start + 8..hdlr.start + 12))
+            .is_some_and(|handler| handler == b"soun");
+        if !is_sound {
+            continue;
+        }
+
+        let Some(stsd) = [b"minf", b"stbl", b"stsd"]
+            .iter()
+            .try_fold(mdia, |range, kind| find_child(&moov, range, kind))
+        else {
+            continue;
+        };
+
+        // stsd: version/flags, entry count, then sample entries
+        let Some((_, entry)) = child_atoms(&moov, stsd.start + 8..stsd.end).into_iter().next() else {
+            continue;
+        };
+        if let Some(properties) = parse_audio_sample_entry(&moov, entry) {
+            return Ok(properties);
+        }
+    }
+
+    Err(invalid(path, "no audio track found"))
+}
+
+/// ASIN tagged in an MP4-family file, without FFmpeg
+///
+/// Audible's AAX/AAXC files carry it in the iTunes `CDEK` item; M4Bs written
+/// by Libation or `MetadataEditor` use a freeform `ASIN` item or an `mdta`
+/// key. Returns None if the file has no ASIN tag.
+///
+/// # Errors
+/// - UnsupportedAudioFormat if the file isn't an MP4 container
+/// - InvalidAudioFile if the `moov` atom is missing or too large
+pub async fn read_embedded_asin(path: &Path) -> Result<Option<String>> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file =
+            File::open(&path).map_err(|e| LibationError::FileNotFound(format!("{}: {}", path.display(), e)))?;
+        let mut magic = [0u8; 8];
+        if file.read(&mut magic)? < 8 || &magic[4..8] != b"ftyp" {
+            return Err(LibationError::UnsupportedAudioFormat(format!(
+                "Not an MP4 container: {}",
+                path.display()
+            )));
+        }
+        let moov = read_moov(&mut file, &path)?;
+        Ok(find_asin_tag(&moov))
+    })
+    .await
+    .map_err(|e| LibationError::InternalError(format!("Probe task failed: {}", e)))?
+}
+
+/// ASIN from the `meta` atom under `moov/udta` or `moov`
+fn find_asin_tag(moov: &[u8]) -> Option<String> {
+    let moov_body = 8..moov.len();
+    let metas = [
+        find_child(moov, moov_body.clone(), b"udta").and_then(|udta| find_child(moov, udta, b"meta")),
+        find_child(moov, moov_body, b"meta"),
+    ];
+
+    metas.into_iter().flatten().find_map(|meta| {
+        // iTunes `meta` is a full box (version/flags first), QuickTime's isn't
+        let meta = if be_u32(moov, meta.start) == Some(0) { meta.start + 4..meta.end } else { meta };
+        let keys = find_child(moov, meta.clone(), b"keys").map(|keys| parse_mdta_keys(moov, keys)).unwrap_or_default();
+        let ilst = find_child(moov, meta, b"ilst")?;
+
+        child_atoms(moov, ilst).into_iter().find_map(|(kind, item)| {
+            let name = match &kind {
+                b"CDEK" => "asin".to_string(),
+                // Freeform item: mean, name (both full boxes), then data
+                b"----" => {
+                    let name = find_child(moov, item.clone(), b"name")?;
+                    String::from_utf8_lossy(moov.get(name.start + 4..name.end)?).into_owned()
+                }
+                index => keys.get((u32::from_be_bytes(*index) as usize).checked_sub(1)?)?.clone(),
+            };
+            if !name.eq_ignore_ascii_case("asin") {
+                return None;
+            }
+
+            // data: type indicator(4) locale(4) value
+            let data = find_child(moov, item, b"data")?;
+            let value = String::from_utf8_lossy(moov.get(data.start + 8..data.end)?).trim().to_string();
+            (!value.is_empty()).then_some(value)
+        })
+    })
+}
+
+/// Key names of a QuickTime `keys` atom, in index order (index 1 first)
+fn parse_mdta_keys(data: &[u8], keys: Range<usize>) -> Vec<String> {
+    // version/flags, entry count, then (size, namespace, name) entries
+    let mut names = Vec::new();
+    let mut pos = keys.start + 8;
+    while let Some(size) = be_u32(data, pos) {
+        let size = size as usize;
+        if size < 8 || pos + size > keys.end {
+            break;
+        }
+        names.push(String::from_utf8_lossy(&data[pos + 8..pos + size]).into_owned());
+        pos += size;
+    }
+    names
+}
+
+/// Channels and sample rate from an audio sample entry body
+fn parse_audio_sample_entry(data: &[u8], entry: Range<usize>) -> Option<AudioProperties> {
+    // reserved(6) data_reference_index(2) version(2) revision(2) vendor(4)
+    // channelcount(2) samplesize(2) compression_id(2) packet_size(2) samplerate(16.16)
+    let channels = u32::from(be_u16(data, entry.start + 16)?);
+    let sample_rate = be_u32(data, entry.start + 24)? >> 16;
+    let mut properties = AudioProperties { channels, sample_rate };
+
+    if let Some(esds) = find_child(data, entry.start + 28..entry.end, b"esds") {
+        if let Some(config) = parse_esds(&data[esds]).and_then(|asc| parse_audio_specific_config(&asc)) {
+            if config.channels > 0 {
+                properties.channels = config.channels;
+            }
+            if config.sample_rate > 0 {
+                properties.sample_rate = config.sample_rate;
+            }
+        }
+    }
+
+    (properties.channels > 0 && properties.sample_rate > 0).then_some(properties)
+}
+
+/// Descriptor tag and payload at `pos`, returning the position after it
+fn read_descriptor(data: &[u8], mut pos: usize) -> Option<(u8, Range<usize>, usize)> {
+    let tag = *data.get(pos)?;
+    pos += 1;
+
+    let mut len = 0usize;
+    for _ in 0..4 {
+        let byte = *data.get(pos)?;
+        pos += 1;
+        len = (len << 7) | usize::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let end = pos.checked_add(len)?.min(data.len());
+    Some((tag, pos..end, end))
+}
+
+/// AudioSpecificConfig bytes from an `esds` body
+fn parse_esds(esds: &[u8]) -> Option<Vec<u8>> {
+    // Full box: version/flags
+    let (tag, es, _) = read_descriptor(esds, 4)?;
+    if tag != 0x03 {
+        return None;
+    }
+
+    // ES_ID(2), flags(1) and the optional fields they announce
+    let flags = *esds.get(es.start + 2)?;
+    let mut pos = es.start + 3;
+    if flags & 0x80 != 0 {
+        pos += 2;
+    }
+    if flags & 0x40 != 0 {
+        pos += 1 + usize::from(*esds.get(pos)?);
+    }
+    if flags & 0x20 != 0 {
+        pos += 2;
+    }
+
+    let (tag, decoder_config, _) = read_descriptor(esds, pos)?;
+    if tag != 0x04 {
+        return None;
+    }
+
+    // objectTypeIndication(1) streamType(1) bufferSize(3) maxBitrate(4) avgBitrate(4)
+    let (tag, specific, _) = read_descriptor(esds, decoder_config.start + 13)?;
+    (tag == 0x05).then(|| esds[specific].to_vec())
+}
+
+/// MSB-first bit reader
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: usize) -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..bits {
+            let byte = *self.data.get(self.pos / 8)?;
+            value = (value << 1) | u32::from((byte >> (7 - self.pos % 8)) & 1);
+            self.pos += 1;
+        }
+        Some(value)
+    }
+
+    fn sample_rate(&mut self) -> Option<u32> {
+        match self.read(4)? {
+            0xF => self.read(24),
+            index => AAC_SAMPLE_RATES.get(index as usize).copied(),
+        }
+    }
+}
+
+/// Decoded output properties from an AAC AudioSpecificConfig
+fn parse_audio_specific_config(asc: &[u8]) -> Option<AudioProperties> {
+    let mut bits = BitReader { data: asc, pos: 0 };
+
+    let mut object_type = bits.read(5)?;
+    if object_type == 31 {
+        object_type = 32 + bits.read(6)?;
+    }
+    let mut sample_rate = bits.sample_rate()?;
+    let channel_config = bits.read(4)?;
+
+    // Explicit SBR (HE-AAC) / PS (HE-AACv2) signalling
+    let mut channels = channel_config;
+    if object_type == 5 || object_type == 29 {
+        sample_rate = bits.sample_rate()?;
+        if object_type == 29 && channel_config == 1 {
+            channels = 2;
+        }
+    }
+
+    Some(AudioProperties { channels, sample_rate })
+}
+
+// ============================================================================
+// MP3
+// ============================================================================
+
+fn probe_mp3(file: &mut File, path: &Path) -> Result<AudioProperties> {
+    let mut head = [0u8; 10];
+    file.read_exact(&mut head).map_err(|_| invalid(path, "file too short"))?;
+
+    // Skip an ID3v2 tag (syncsafe size, plus footer if flagged)
+    let start = if head.starts_with(b"ID3") {
+        let size = head[6..10].iter().fold(0u64, |acc, b| (acc << 7) | u64::from(b & 0x7F));
+        10 + size + if head[5] & 0x10 != 0 { 10 } else { 0 }
+    } else {
+        0
+    };
+
+    let mut buffer = Vec::with_capacity(MP3_SCAN_LIMIT);
+    file.seek(SeekFrom::Start(start))?;
+    file.take(MP3_SCAN_LIMIT as u64).read_to_end(&mut buffer)?;
+
+    buffer
+        .windows(4)
+        .find_map(|header| parse_mp3_frame_header(header.try_into().unwrap()))
+        .ok_or_else(|| invalid(path, "no MPEG audio frame found"))
+}
+
+/// Channels and sample rate from an MPEG audio frame header
+fn parse_mp3_frame_header(header: [u8; 4]) -> Option<AudioProperties> {
+    if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+
+    let version = (header[1] >> 3) & 0x03; // 0 = 2.5, 2 = 2, 3 = 1
+    let layer = (header[1] >> 1) & 0x03;
+    let bitrate_index = header[2] >> 4;
+    let rate_index = (header[2] >> 2) & 0x03;
+    if version == 1 || layer == 0 || bitrate_index == 0x0F || rate_index == 3 {
+        return None;
+    }
+
+    let base = [44100, 48000, 32000][rate_index as usize];
+    let sample_rate = match version {
+        3 => base,
+        2 => base / 2,
+        _ => base / 4,
+    };
+    let channels = if header[3] >> 6 == 3 { 1 } else { 2 };
+
+    Some(AudioProperties { channels, sample_rate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&((body.len() + 8) as u32).to_be_bytes()[..], kind, body].concat()
+    }
+
+    fn sample_entry(channels: u16, sample_rate: u32, children: &[u8]) -> Vec<u8> {
+        let mut body = [0u8; 28];
+        body[16..18].copy_from_slice(&channels.to_be_bytes());
+        body[24..28].copy_from_slice(&(sample_rate << 16).to_be_bytes());
+        [&body[..], children].concat()
+    }
+
+    fn mp4_file(entry: Vec<u8>) -> Vec<u8> {
+        let hdlr = atom(b"hdlr", &[&[0u8; 8][..], b"soun", &[0u8; 12]].concat());
+        let stsd = atom(b"stsd", &[&[0u8, 0, 0, 0, 0, 0, 0, 1][..], &atom(b"mp4a", &entry)].concat());
+        let stbl = atom(b"stbl", &stsd);
+        let minf = atom(b"minf", &stbl);
+        let mdia = atom(b"mdia", &[hdlr, minf].concat());
+        let trak = atom(b"trak", &mdia);
+        [atom(b"ftyp", b"M4B \0\0\0\0"), atom(b"moov", &trak), atom(b"mdat", &[0u8; 16])].concat()
+    }
+
+    fn esds(asc: &[u8]) -> Vec<u8> {
+        let specific = [&[0x05, asc.len() as u8][..], asc].concat();
+        let decoder_config = [&[0x04, (13 + specific.len()) as u8, 0x40, 0x15][..], &[0u8; 11], &specific].concat();
+        let es = [&[0x03, (3 + decoder_config.len()) as u8, 0, 1, 0][..], &decoder_config].concat();
+        atom(b"esds", &[&[0u8; 4][..], &es].concat())
+    }
+
+    fn probe_bytes(name: &str, bytes: &[u8]) -> Result<AudioProperties> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        probe_blocking(&path)
+    }
+
+    #[test]
+    fn test_probe_mp4() {
+        // AAC-LC stereo 44.1 kHz: object type 2, index 4, channel config 2
+        let lc = probe_bytes("book.m4b", &mp4_file(sample_entry(2, 44100, &esds(&[0x12, 0x10])))).unwrap();
+        assert_eq!(lc, AudioProperties { channels: 2, sample_rate: 44100 });
+
+        // HE-AACv2 (PS): mono 22.05 kHz core, decoded as stereo 44.1 kHz
+        let ps = probe_bytes("book.m4b", &mp4_file(sample_entry(1, 22050, &esds(&[0xEB, 0x8A, 0x08, 0x00])))).unwrap();
+        assert_eq!(ps, AudioProperties { channels: 2, sample_rate: 44100 });
+
+        // No esds: sample entry values
+        let plain = probe_bytes("book.aax", &mp4_file(sample_entry(1, 22050, &[]))).unwrap();
+        assert_eq!(plain, AudioProperties { channels: 1, sample_rate: 22050 });
+        assert_eq!(plain.to_string(), "mono, 22050 Hz");
+    }
+
+    #[tokio::test]
+    async fn test_read_embedded_asin() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, meta: Vec<u8>, under_udta: bool| {
+            let meta = atom(b"meta", &meta);
+            let moov = if under_udta { atom(b"moov", &atom(b"udta", &meta)) } else { atom(b"moov", &meta) };
+            let path = dir.path().join(name);
+            std::fs::write(&path, [atom(b"ftyp", b"aax \0\0\0\0"), moov].concat()).unwrap();
+            path
+        };
+        let data = |value: &[u8]| atom(b"data", &[&[0, 0, 0, 1, 0, 0, 0, 0][..], value].concat());
+
+        // Audible AAX: iTunes CDEK item
+        let aax = write("book.aax", [&[0u8; 4][..], &atom(b"ilst", &atom(b"CDEK", &data(b"B00AAX0001")))].concat(), true);
+        assert_eq!(read_embedded_asin(&aax).await.unwrap().as_deref(), Some("B00AAX0001"));
+
+        // Freeform ----:com.apple.iTunes:ASIN
+        let freeform = [
+            atom(b"mean", &[&[0u8; 4][..], b"com.apple.iTunes"].concat()),
+            atom(b"name", &[&[0u8; 4][..], b"ASIN"].concat()),
+            data(b" B00M4B0002 "),
+        ]
+        .concat();
+        let m4b = write("book.m4b", [&[0u8; 4][..], &atom(b"ilst", &atom(b"----", &freeform))].concat(), true);
+        assert_eq!(read_embedded_asin(&m4b).await.unwrap().as_deref(), Some("B00M4B0002"));
+
+        // QuickTime mdta keys (FFmpeg -movflags use_metadata_tags), key 2 is asin
+        let key = |name: &[u8]| [&((name.len() + 8) as u32).to_be_bytes()[..], b"mdta", name].concat();
+        let keys = atom(b"keys", &[&[0u8, 0, 0, 0, 0, 0, 0, 2][..], &key(b"title"), &key(b"asin")].concat());
+        let ilst = atom(b"ilst", &[atom(&1u32.to_be_bytes(), &data(b"Title")), atom(&2u32.to_be_bytes(), &data(b"B00MDTA003"))].concat());
+        let mdta = write("mdta.m4b", [atom(b"hdlr", &[0u8; 24]), keys, ilst].concat(), false);
+        assert_eq!(read_embedded_asin(&mdta).await.unwrap().as_deref(), Some("B00MDTA003"));
+
+        let untagged = write("untagged.m4b", [&[0u8; 4][..], &atom(b"ilst", &atom(b"\xa9nam", &data(b"Title")))].concat(), true);
+        assert_eq!(read_embedded_asin(&untagged).await.unwrap(), None);
+
+        let mp3 = dir.path().join("book.mp3");
+        std::fs::write(&mp3, b"ID3\x03\x00\x00\x00\x00\x00\x00").unwrap();
+        assert!(matches!(read_embedded_asin(&mp3).await, Err(LibationError::UnsupportedAudioFormat(_))));
+    }
+
+    #[test]
+    fn test_probe_mp3() {
+        // ID3v2 tag with 4 bytes of payload, then an MPEG-1 Layer III mono 48 kHz frame
+        let mut bytes = b"ID3\x03\x00\x00\x00\x00\x00\x04TAGS".to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xFB, 0x94, 0xC4]);
+        assert_eq!(
+            probe_bytes("book.mp3", &bytes).unwrap(),
+            AudioProperties { channels: 1, sample_rate: 48000 }
+        );
+
+        // MPEG-2 joint stereo 22.05 kHz
+        assert_eq!(
+            parse_mp3_frame_header([0xFF, 0xF3, 0x90, 0x44]),
+            Some(AudioProperties { channels: 2, sample_rate: 22050 })
+        );
+        assert!(probe_bytes("book.mp3", b"not audio at all").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Smart resume positions
+//!
+//! Picking up exactly where playback stopped usually lands mid-sentence.
+//! The players on both platforms ask for the resume position here instead
+//! of working it out themselves, so they agree:
+//!
+//! 1. Step back `rewind_ms` from the stored position, to roughly the start
+//!    of the sentence that was cut off
+//! 2. Never step back past the start of the chapter the position is in, so
+//!    the end of the previous chapter isn't replayed
+//! 3. If that lands within `snap_ms` of the chapter start, start the chapter
+//!    from the beginning
+//!
+//! Chapters are the book's stored chapter list (`storage::chapters`); books
+//! without one only clamp at 0. Options are stored in Settings.
+
+use crate::audio::metadata::Chapter;
+use crate::error::{LibationError, Result};
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+const KEY_OPTIONS: &str = "playback.resume";
+
+/// Longest rewind accepted by `set_resume_options`
+pub const MAX_REWIND_MS: i64 = 5 * 60_000;
+
+/// How far back playback resumes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ResumeOptions {
+    /// How far to step back from the stored position (0 resumes exactly)
+    pub rewind_ms: i64,
+    /// Resume at the chapter start when the rewound position is this close to it
+    pub snap_ms: i64,
+}
+
+impl Default for ResumeOptions {
+    fn default() -> Self {
+        Self {
+            rewind_ms: 10_000,
+            snap_ms: 3_000,
+        }
+    }
+}
+
+/// Where to resume playback
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ResumePoint {
+    /// Position to start playing at
+    pub position_ms: i64,
+    /// Position playback stopped at
+    pub stored_position_ms: i64,
+    /// Index of the chapter resumed in (None without chapters)
+    pub chapter_index: Option<usize>,
+    pub chapter_title: Option<String>,
+}
+
+/// Stored resume options (defaults if never set)
+pub async fn get_resume_options(pool: &SqlitePool) -> Result<ResumeOptions> {
+    Ok(get_json_setting(pool, KEY_OPTIONS).await?.unwrap_or_default())
+}
+
+/// Store the resume options
+///
+/// # Errors
+/// InvalidInput for negative values or a rewind over `MAX_REWIND_MS`
+pub async fn set_resume_options(pool: &SqlitePool, options: &ResumeOptions) -> Result<()> {
+    if !(0..=MAX_REWIND_MS).contains(&options.rewind_ms) {
+        return Err(LibationError::invalid_input(format!(
+            "Rewind must be between 0 and {} ms",
+            MAX_REWIND_MS
+        )));
+    }
+    if options.snap_ms < 0 {
+        return Err(LibationError::invalid_input("Snap distance must not be negative"));
+    }
+    set_json_setting(pool, KEY_OPTIONS, options).await
+}
+
+/// Resume position for a stored position in a book with `chapters`
+///
+/// `chapters` are ordered by start time. Positions in a gap between
+/// chapters count as part of the chapter before.
+pub fn resume_position(stored_position_ms: i64, chapters: &[Chapter], options: &ResumeOptions) -> ResumePoint {
+    let stored = stored_position_ms.max(0);
+    let chapter_index = chapters
+        .partition_point(|chapter| chapter.start_ms <= stored)
+        .checked_sub(1);
+    let chapter_start = chapter_index.map_or(0, |i| chapters[i].start_ms.max(0));
+
+    let mut position = (stored - options.rewind_ms.max(0)).max(chapter_start);
+    if position - chapter_start <= options.snap_ms {
+        position = chapter_start;
+    }
+
+    ResumePoint {
+        position_ms: position,
+        stored_position_ms,
+        chapter_index,
+        chapter_title: chapter_index.map(|i| chapters[i].title.clone()),
+    }
+}
+
+/// Resume position for a book's stored position and chapters, with the
+/// stored options
+///
+/// `rewind_ms` overrides the stored rewind for this call.
+///
+/// # Errors
+/// RecordNotFound if the book isn't in the database
+pub async fn resume_point(pool: &SqlitePool, asin: &str, rewind_ms: Option<i64>) -> Result<ResumePoint> {
+    let stored = crate::storage::progress::get_listening_position(pool, asin).await?;
+    let chapters = crate::storage::chapters::get_chapters(pool, asin).await?;
+    let mut options = get_resume_options(pool).await?;
+    if let Some(rewind_ms) = rewind_ms {
+        options.rewind_ms = rewind_ms.clamp(0, MAX_REWIND_MS);
+    }
+    Ok(resume_position(stored, &chapters, &options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::chapters::update_chapters;
+    use crate::storage::progress::set_listening_position;
+    use crate::storage::queries::insert_book;
+    use crate::storage::{Database, NewBook};
+
+    fn chapter(title: &str, start_ms: i64, end_ms: i64) -> Chapter {
+        Chapter { title: title.to_string(), start_ms, end_ms }
+    }
+
+    #[test]
+    fn test_resume_position() {
+        let chapters = vec![chapter("Opening Credits", 0, 30_000), chapter("Chapter 1", 30_000, 600_000)];
+        let options = ResumeOptions::default();
+
+        let point = resume_position(120_000, &chapters, &options);
+        assert_eq!(point.position_ms, 110_000);
+        assert_eq!(point.chapter_index, Some(1));
+        assert_eq!(point.chapter_title.as_deref(), Some("Chapter 1"));
+
+        // Never back into the previous chapter, and close to the start means the start
+        assert_eq!(resume_position(35_000, &chapters, &options).position_ms, 30_000);
+        assert_eq!(resume_position(42_000, &chapters, &options).position_ms, 30_000);
+        assert_eq!(resume_position(44_000, &chapters, &options).position_ms, 34_000);
+
+        // Without chapters only 0 bounds it
+        let point = resume_position(5_000, &[], &options);
+        assert_eq!((point.position_ms, point.chapter_index), (0, None));
+        let exact = ResumeOptions { rewind_ms: 0, snap_ms: 0 };
+        assert_eq!(resume_position(44_000, &chapters, &exact).position_ms, 44_000);
+    }
+
+    #[tokio::test]
+    async fn test_resume_point() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0RESUME01".to_string(), "Resume".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        update_chapters(pool, "B0RESUME01", &[chapter("One", 0, 60_000), chapter("Two", 60_000, 120_000)])
+            .await
+            .unwrap();
+        set_listening_position(pool, "B0RESUME01", 90_000).await.unwrap();
+
+        assert_eq!(resume_point(pool, "B0RESUME01", None).await.unwrap().position_ms, 80_000);
+        assert_eq!(resume_point(pool, "B0RESUME01", Some(60_000)).await.unwrap().position_ms, 60_000);
+
+        set_resume_options(pool, &ResumeOptions { rewind_ms: 20_000, snap_ms: 0 }).await.unwrap();
+        assert_eq!(resume_point(pool, "B0RESUME01", None).await.unwrap().position_ms, 70_000);
+        assert!(set_resume_options(pool, &ResumeOptions { rewind_ms: -1, snap_ms: 0 }).await.is_err());
+        assert!(matches!(
+            resume_point(pool, "B0MISSING0", None).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
+}
@@ -14,17 +14,63 @@
 // MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
 // GNU General Public License for more details.
 //
-// You should have received a copy of the GNU General Public License
-// along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+//! Desktop command line for the core library
+//!
+//! Runs the same sync, download and DRM removal code as the app against a
+//! LibriSync SQLite database, for debugging user issues and for use without
+//! the mobile app. Accounts are read from the database's `Accounts` table;
+//! a database copied from the app already contains them, otherwise add one
+//! with `import-account`.
+//!
+//! ```text
+//! librisync-cli --db library.db import-account account.json
+//! librisync-cli --db library.db sync
+//! librisync-cli --db library.db list --search bobiverse
+//! librisync-cli --db library.db liberate B07T2F8VJM --output ~/Audiobooks
+//! librisync-cli --db library.db export-server /mnt/nas/audiobookshelf
+//! librisync-cli decrypt book.aax book.m4b --activation-bytes 1CEB00DA
+//! ```
 
 use clap::{Parser, Subcommand};
+use rust_core::api::auth::{ensure_valid_token, Account};
+use rust_core::api::client::AudibleClient;
+use rust_core::api::content::{DownloadQuality, DrmType};
+use rust_core::api::preflight;
+use rust_core::crypto::aax::AaxDecrypter;
+use rust_core::crypto::aaxc::AaxcDecrypter;
+use rust_core::crypto::activation::ActivationBytes;
+use rust_core::cancel::CancellationToken;
+use rust_core::download::plan::{plan_liberation, LiberationOptions};
+use rust_core::file::backend::{load_backend_config, store_file};
+use rust_core::file::server_export::{self, ServerExportOptions, ServerExportReport};
+use rust_core::file::paths::{build_unique_file_path, CollisionStrategy, NamingPattern};
 use rust_core::log_from_rust;
+use rust_core::storage::receipts::{
+    self, DecryptMethod, InputSource, LiberationReceipt, NewReceipt, ReceiptFile, Toolchain,
+};
+use rust_core::storage::{accounts, jobs, queries, BookQueryParams, Database};
+use rust_core::{LibationError, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+/// User agent the CDN expects for audio downloads
+const DOWNLOAD_USER_AGENT: &str = "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0";
 
 #[derive(Parser)]
 #[command(name = "librisync-cli")]
-#[command(about = "LibriSync CLI - Desktop testing tool", long_about = None)]
+#[command(about = "LibriSync CLI - sync, download and decrypt from the desktop", long_about = None)]
 struct Cli {
+    /// LibriSync database (created if missing)
+    #[arg(long, global = true, default_value = "librisync.db")]
+    db: PathBuf,
+
+    /// Account to use (account id); defaults to the primary account
+    #[arg(long, global = true)]
+    account: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -37,46 +83,420 @@ enum Commands {
         #[arg(short, long, default_value = "Hello from CLI!")]
         message: String,
     },
-    /// Test authentication (placeholder)
-    Auth {
-        /// Email address
-        #[arg(short, long)]
-        email: String,
-        /// Password
-        #[arg(short, long)]
-        password: String,
+    /// Store an account exported from the app (serialized Account JSON)
+    ImportAccount {
+        /// JSON file
+        file: PathBuf,
     },
-    /// Test library sync (placeholder)
+    /// Sync the account's library from Audible
     Sync,
-    /// Test download (placeholder)
-    Download {
+    /// List books in the database
+    List {
+        /// Search title, author and narrator
+        #[arg(short, long)]
+        search: Option<String>,
+        #[arg(short, long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// Download a book and remove its DRM
+    Liberate {
         /// Book ASIN
         asin: String,
+        /// Library directory for the decrypted file
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+        /// Low, Normal, High or Extreme
+        #[arg(short, long, default_value = "High")]
+        quality: String,
+        /// flat_file, author_book_folder or author_series_book
+        #[arg(long, default_value = "author_series_book")]
+        naming: String,
+        /// Keep the encrypted download
+        #[arg(long)]
+        keep_encrypted: bool,
+        /// Write the liberation receipt next to the file (Book.receipt.json)
+        #[arg(long)]
+        receipt_sidecar: bool,
+        /// Show the output path and space needed without downloading
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Copy liberated books into an Audiobookshelf/Plex library folder with metadata sidecars
+    ExportServer {
+        /// Server library folder
+        output: PathBuf,
+        /// Only this book (ASIN)
+        #[arg(long)]
+        asin: Option<String>,
+        /// Don't download cover.jpg
+        #[arg(long)]
+        no_cover: bool,
+    },
+    /// Decrypt a local AAX file (or AAXC with its key and IV)
+    Decrypt {
+        input: PathBuf,
+        output: PathBuf,
+        /// AAX activation bytes (8 hex characters)
+        #[arg(long)]
+        activation_bytes: Option<String>,
+        /// AAXC key (32 hex characters)
+        #[arg(long, requires = "iv")]
+        key: Option<String>,
+        /// AAXC IV (32 hex characters)
+        #[arg(long, requires = "key")]
+        iv: Option<String>,
     },
 }
 
-fn main() {
+#[tokio::main]
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    jobs::set_job_owner("cli");
 
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     match cli.command {
         Commands::Test { message } => {
             println!("Testing Rust bridge...");
             let result = log_from_rust(message);
             println!("Result: {}", result);
         }
-        Commands::Auth { email, password } => {
-            println!("Testing authentication...");
-            println!("Email: {}", email);
-            println!("Password: {}", "*".repeat(password.len()));
-            println!("⚠️  Authentication not yet implemented");
+        Commands::ImportAccount { file } => {
+            let json = tokio::fs::read_to_string(&file).await?;
+            let account: Account = serde_json::from_str(&json)
+                .map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
+            let db = open_database(&cli.db).await?;
+            accounts::save_account(db.pool(), &account.account_id, &json).await?;
+            println!("Saved account {}", account.account_id);
         }
         Commands::Sync => {
-            println!("Testing library sync...");
-            println!("⚠️  Library sync not yet implemented");
+            let db = open_database(&cli.db).await?;
+            let account = load_account(&db, cli.account.as_deref()).await?;
+            let account_json = serde_json::to_string(&account)?;
+            let readiness = preflight::check_sync_readiness(db.pool(), &account_json).await?;
+            if !readiness.ready {
+                return Err(LibationError::InvalidState(format!(
+                    "account not ready to sync: {} {}",
+                    readiness.message.unwrap_or_default(),
+                    readiness.instructions.unwrap_or_default()
+                )));
+            }
+            let account = readiness.account()?;
+            let mut client = AudibleClient::new(account.clone())?;
+            let stats = client.sync_library(&db, &account).await?;
+
+            println!(
+                "Synced {} items: {} added, {} updated, {} absent",
+                stats.total_items, stats.books_added, stats.books_updated, stats.books_absent
+            );
+            for error in &stats.errors {
+                println!("  {} ({:?}): {}", error.asin, error.stage, error.message);
+            }
+        }
+        Commands::List { search, limit } => {
+            let db = open_database(&cli.db).await?;
+            let params = BookQueryParams {
+                search_query: search,
+                limit,
+                ..Default::default()
+            };
+            for book in queries::list_books_with_filters(db.pool(), &params).await? {
+                println!(
+                    "{}  {:>5} min  {} - {}",
+                    book.audible_product_id,
+                    book.length_in_minutes,
+                    book.authors_str.as_deref().unwrap_or("Unknown"),
+                    book.title
+                );
+            }
+        }
+        Commands::Liberate {
+            asin,
+            output,
+            quality,
+            naming,
+            keep_encrypted,
+            receipt_sidecar,
+            dry_run,
+        } => {
+            let db = open_database(&cli.db).await?;
+            let quality = parse_quality(&quality)?;
+            let naming = NamingPattern::from_string(&naming)
+                .ok_or_else(|| LibationError::invalid_input(format!("Unknown naming pattern: {}", naming)))?;
+
+            if dry_run {
+                let options = LiberationOptions {
+                    quality,
+                    naming_pattern: naming,
+                    base_directory: Some(output),
+                    ..Default::default()
+                };
+                let plan = plan_liberation(db.pool(), &asin, &options).await?;
+                println!("{} ({}, {:?})", plan.files[0].path, plan.download_format, plan.codec);
+                println!(
+                    "{} MB download, {} MB free space needed{}",
+                    plan.download_bytes / 1_000_000,
+                    plan.required_free_bytes / 1_000_000,
+                    if plan.exact_size { "" } else { " (estimated)" }
+                );
+                return Ok(());
+            }
+
+            let account = load_account(&db, cli.account.as_deref()).await?;
+
+            let receipt = liberate(&db, account, &asin, quality, &output, naming, keep_encrypted).await?;
+            let path = Path::new(&receipt.files[0].location);
+            println!("Saved {}", path.display());
+            if receipt_sidecar && path.exists() {
+                let sidecar = receipts::write_sidecar(&receipt, path).await?;
+                println!("Receipt {}", sidecar.display());
+            }
+        }
+        Commands::ExportServer { output, asin, no_cover } => {
+            let db = open_database(&cli.db).await?;
+            let options = ServerExportOptions {
+                cover: !no_cover,
+                ..Default::default()
+            };
+            let report = match asin {
+                Some(asin) => ServerExportReport {
+                    exported: vec![server_export::export_book(db.pool(), &asin, &output, options).await?],
+                    failed: Vec::new(),
+                },
+                None => server_export::export_library(db.pool(), &output, options, &CancellationToken::new()).await?,
+            };
+
+            for book in &report.exported {
+                println!("{}  {}", book.asin, book.folder);
+            }
+            for failure in &report.failed {
+                println!("{}  failed: {}", failure.asin, failure.error);
+            }
+        }
+        Commands::Decrypt {
+            input,
+            output,
+            activation_bytes,
+            key,
+            iv,
+        } => match (activation_bytes, key, iv) {
+            (Some(activation_bytes), None, None) => {
+                decrypt_aax(&input, &output, ActivationBytes::from_hex(&activation_bytes)?).await?;
+                println!("Decrypted to {}", output.display());
+            }
+            (None, Some(key), Some(iv)) => {
+                decrypt_aaxc(&input, &output, &key, &iv).await?;
+                println!("Decrypted to {}", output.display());
+            }
+            _ => {
+                return Err(LibationError::invalid_input(
+                    "Pass either --activation-bytes (AAX) or --key and --iv (AAXC)",
+                ))
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn open_database(path: &Path) -> Result<Database> {
+    let (db, recovery) = Database::open_with_recovery(path).await?;
+    db.run_startup_tasks().await?;
+    if recovery.data_lost {
+        eprintln!(
+            "warning: database was damaged and rebuilt; the damaged copy is at {}",
+            recovery.quarantined_path.as_deref().unwrap_or("(unknown)")
+        );
+    }
+    accounts::recover_pending_token_refreshes(db.pool()).await?;
+    Ok(db)
+}
+
+/// Stored account with a fresh access token
+async fn load_account(db: &Database, account_id: Option<&str>) -> Result<Account> {
+    let json = match account_id {
+        Some(id) => accounts::get_account(db.pool(), id).await?,
+        None => accounts::get_primary_account(db.pool()).await?,
+    }
+    .ok_or_else(|| LibationError::not_found("No account in the database; run import-account first"))?;
+
+    let json = ensure_valid_token(db.pool(), &json, 30).await?;
+    serde_json::from_str(&json).map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))
+}
+
+fn parse_quality(quality: &str) -> Result<DownloadQuality> {
+    match quality.to_ascii_lowercase().as_str() {
+        "low" => Ok(DownloadQuality::Low),
+        "normal" => Ok(DownloadQuality::Normal),
+        "high" => Ok(DownloadQuality::High),
+        "extreme" => Ok(DownloadQuality::Extreme),
+        _ => Err(LibationError::invalid_input(format!("Unknown quality: {}", quality))),
+    }
+}
+
+/// Download, decrypt and file a book into `library` (or the configured
+/// storage backend), returning its liberation receipt
+async fn liberate(
+    db: &Database,
+    account: Account,
+    asin: &str,
+    quality: DownloadQuality,
+    library: &Path,
+    naming: NamingPattern,
+    keep_encrypted: bool,
+) -> Result<LiberationReceipt> {
+    let book = queries::find_book_with_relations_by_asin(db.pool(), asin)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not in library (run sync first): {}", asin)))?;
+    let decrypt_key = account.decrypt_key.clone();
+
+    let client = AudibleClient::new(account)?;
+    let license = client.build_download_license(asin, quality, false).await?;
+    if license.drm_type == DrmType::Widevine {
+        return Err(LibationError::invalid_input("Widevine downloads are not supported by the CLI"));
+    }
+
+    let relative = build_unique_file_path(
+        &book.to_audio_metadata(),
+        naming,
+        "m4b",
+        CollisionStrategy::default(),
+        &[],
+        Some(library),
+    )?;
+    let output = library.join(&relative);
+    if let Some(parent) = output.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let started = Instant::now();
+    let encrypted = output.with_extension("download");
+    let headers = HashMap::from([("User-Agent".to_string(), DOWNLOAD_USER_AGENT.to_string())]);
+    println!("Downloading {}...", book.title);
+    rust_core::download::stream::download_to_file(
+        license.download_url.clone(),
+        encrypted.clone(),
+        asin.to_string(),
+        book.title.clone(),
+        headers,
+        |progress| {
+            if progress.total_bytes > 0 {
+                eprint!(
+                    "\r  {:>5.1}% of {} MB",
+                    progress.bytes_downloaded as f64 * 100.0 / progress.total_bytes as f64,
+                    progress.total_bytes / 1_000_000
+                );
+            }
+        },
+    )
+    .await?;
+    eprintln!();
+    let download_ms = started.elapsed().as_millis() as i64;
+
+    println!("Decrypting...");
+    let started = Instant::now();
+    let key = license.decryption_keys.as_ref().and_then(|keys| keys.first());
+    let decrypt_method = match (license.drm_type, key) {
+        (DrmType::None, _) => {
+            tokio::fs::copy(&encrypted, &output).await?;
+            DecryptMethod::None
         }
-        Commands::Download { asin } => {
-            println!("Testing download for ASIN: {}", asin);
-            println!("⚠️  Download not yet implemented");
+        (_, Some(key)) if key.key_part_1.len() == 16 => {
+            let iv = key
+                .key_part_2
+                .as_ref()
+                .ok_or_else(|| LibationError::invalid_input("No IV in AAXC keys"))?;
+            decrypt_aaxc(&encrypted, &output, &hex::encode(key.key_part_1.expose_secret()), &hex::encode(iv.expose_secret())).await?;
+            DecryptMethod::Aaxc
         }
+        (_, key) => {
+            // AAX: the license carries the activation bytes, or the account has them
+            let activation_bytes = match key {
+                Some(key) if key.key_part_1.len() == 4 => hex::encode(key.key_part_1.expose_secret()),
+                _ => decrypt_key.expose_secret().to_string(),
+            };
+            decrypt_aax(&encrypted, &output, ActivationBytes::from_hex(&activation_bytes)?).await?;
+            DecryptMethod::Aax
+        }
+    };
+    let decrypt_ms = started.elapsed().as_millis() as i64;
+
+    if !keep_encrypted {
+        tokio::fs::remove_file(&encrypted).await?;
+    }
+
+    // A configured storage backend (e.g. a NAS) takes the file from here;
+    // it's hashed for the receipt before it leaves
+    let file = match load_backend_config(db.pool()).await? {
+        Some(config) => {
+            let target = relative.replace('\\', "/");
+            println!("Uploading...");
+            let location = store_file(config.open()?.as_ref(), &output, &target).await?;
+            let file = ReceiptFile::hash(&output, location).await?;
+            tokio::fs::remove_file(&output).await?;
+            file
+        }
+        None => ReceiptFile::hash(&output, output.to_string_lossy()).await?,
+    };
+
+    let receipt = NewReceipt {
+        asin: asin.to_string(),
+        task_id: None,
+        source: InputSource::AudibleDownload,
+        source_ref: reqwest::Url::parse(&license.download_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string)),
+        license_id: license.content_metadata.content_reference.as_ref().map(|r| r.acr.clone()),
+        decrypt_method,
+        files: vec![file],
+        download_ms: Some(download_ms),
+        decrypt_ms: Some(decrypt_ms),
+        // AAX and AAXC are both decrypted natively
+        toolchain: Toolchain::with_ffmpeg(None),
+    };
+    receipts::record_liberation(db.pool(), &book.title, &receipt).await
+}
+
+async fn decrypt_aax(input: &Path, output: &Path, activation_bytes: ActivationBytes) -> Result<()> {
+    let stats = AaxDecrypter::new(activation_bytes)
+        .decrypt_with_stats(input, output, |progress| {
+            eprint!("\r  {:>5.1}%", progress.fraction * 100.0);
+        })
+        .await?;
+    eprintln!("\r  done at {} MB/s", stats.bytes_per_second / 1_000_000);
+    Ok(())
+}
+
+async fn decrypt_aaxc(input: &Path, output: &Path, key: &str, iv: &str) -> Result<()> {
+    let stats = AaxcDecrypter::from_hex(key, iv)?
+        .decrypt_with_stats(input, output, |progress| {
+            eprint!("\r  {:>5.1}%", progress.fraction * 100.0);
+        })
+        .await?;
+    eprintln!("\r  done at {} MB/s", stats.bytes_per_second / 1_000_000);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["librisync-cli", "--db", "x.db", "liberate", "B001", "-q", "low"]).unwrap();
+        assert_eq!(cli.db, PathBuf::from("x.db"));
+        assert!(matches!(cli.command, Commands::Liberate { ref asin, .. } if asin == "B001"));
+        assert!(Cli::try_parse_from(["librisync-cli", "decrypt", "in.aaxc", "out.m4b", "--key", "00"]).is_err());
     }
 }
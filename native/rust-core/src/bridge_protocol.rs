@@ -0,0 +1,266 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Bridge response envelope and protocol versioning
+//!
+//! The JS layer and this crate are released separately, so a staged rollout
+//! can pair new Rust with old JS. Every JNI and iOS bridge response is built
+//! here and states the protocol version its shape follows:
+//!
+//! ```json
+//! { "success": true, "protocol_version": 2, "data": { ... } }
+//! { "success": false, "protocol_version": 2, "error": "Error message" }
+//! ```
+//!
+//! At startup the JS layer calls `nativeGetBridgeInfo` with the version it
+//! was written against. Responses are then converted down to that version
+//! by the shims in `downgrade`, one version step at a time. Clients that
+//! never declare a version get the current one.
+//!
+//! # Versions
+//! - 1 - `success` with `data` or `error`
+//! - 2 - adds `protocol_version`
+//!
+//! A change to any response shape bumps `PROTOCOL_VERSION` and adds the
+//! step back to the previous version in `downgrade`.
+//!
+//! Error responses to a call made with a `trace_id` (see `trace`) echo it:
+//!
+//! ```json
+//! { "success": false, "protocol_version": 2, "error": "Error message", "trace_id": "js-42" }
+//! ```
+//!
+//! Clients only get the field when they send one, so it needs no version.
+
+use crate::error::{LibationError, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Protocol version of responses built by this crate
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Oldest protocol version responses can still be converted to
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Features JS can check for before calling the bridge functions behind them
+pub const CAPABILITIES: &[&str] = &[
+    "api_recording",
+    "batch_write",
+    "bulk_tags",
+    "cancellation",
+    "catalog_prices",
+    "cdn_mirrors",
+    "companion_content",
+    "content_filter",
+    "cover_cache",
+    "cover_refresh",
+    "debug_capture",
+    "download_buffering",
+    "download_follow_up",
+    "download_queue",
+    "format_support",
+    "fuzzy_search",
+    "integrity_check",
+    "job_history",
+    #[cfg(feature = "lan-handoff")]
+    "lan_handoff",
+    "liberation_plan",
+    "liberation_receipts",
+    "library_export",
+    "library_stats",
+    "listening_progress",
+    "localized_titles",
+    "multi_account",
+    "narration_filters",
+    "network_policy",
+    "notes",
+    "offline_licenses",
+    "permissions",
+    "podcast_episodes",
+    "post_hooks",
+    "profiles",
+    "progress_events",
+    "quiet_hours",
+    "read_along",
+    "server_export",
+    "storage_backends",
+    "storage_locations",
+    "store_links",
+    "sync_issues",
+    "sync_prefetch",
+    "sync_preflight",
+    #[cfg(feature = "telemetry")]
+    "telemetry",
+    "token_refresh_recovery",
+    "tracing",
+    "validation",
+    "watch_folder",
+];
+
+/// Version responses are shaped for, set by `negotiate`
+static CLIENT_PROTOCOL_VERSION: AtomicU32 = AtomicU32::new(PROTOCOL_VERSION);
+
+/// What the bridge supports, for `nativeGetBridgeInfo`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BridgeInfo {
+    /// Version this crate speaks natively
+    pub protocol_version: u32,
+    pub min_protocol_version: u32,
+    /// Version responses are currently shaped for
+    pub negotiated_protocol_version: u32,
+    /// Crate version
+    pub core_version: String,
+    pub capabilities: Vec<String>,
+}
+
+/// Current bridge info
+pub fn bridge_info() -> BridgeInfo {
+    BridgeInfo {
+        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: MIN_PROTOCOL_VERSION,
+        negotiated_protocol_version: client_protocol_version(),
+        core_version: env!("CARGO_PKG_VERSION").to_string(),
+        capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+/// Version to serve a client speaking `client_version`
+///
+/// Clients newer than this crate are served the current version.
+///
+/// # Errors
+/// InvalidInput if the client is older than `MIN_PROTOCOL_VERSION`
+pub fn compatible_version(client_version: u32) -> Result<u32> {
+    if client_version < MIN_PROTOCOL_VERSION {
+        return Err(LibationError::invalid_input(format!(
+            "Bridge protocol version {} is no longer supported (oldest supported: {})",
+            client_version, MIN_PROTOCOL_VERSION
+        )));
+    }
+    Ok(client_version.min(PROTOCOL_VERSION))
+}
+
+/// Shape all further responses for a client speaking `client_version`
+///
+/// # Returns
+/// The version responses will follow
+pub fn negotiate(client_version: u32) -> Result<u32> {
+    let version = compatible_version(client_version)?;
+    CLIENT_PROTOCOL_VERSION.store(version, Ordering::Relaxed);
+    Ok(version)
+}
+
+/// Version responses are currently shaped for
+pub fn client_protocol_version() -> u32 {
+    CLIENT_PROTOCOL_VERSION.load(Ordering::Relaxed)
+}
+
+/// Success response for the negotiated version
+///
+/// Debug builds answer with an error response instead when the payload
+/// doesn't match the current call's schema (see `bridge_schema`).
+pub fn success_envelope<T: Serialize>(data: T) -> String {
+    let data = serde_json::json!(data);
+    #[cfg(debug_assertions)]
+    if let Err(problem) = crate::bridge_schema::validate_current(&data) {
+        return error_envelope(&problem);
+    }
+
+    let mut envelope = Map::new();
+    envelope.insert("success".to_string(), Value::Bool(true));
+    envelope.insert("data".to_string(), data);
+    shape(envelope, client_protocol_version())
+}
+
+/// Error response for the negotiated version
+pub fn error_envelope(error: &str) -> String {
+    shape(error_fields(error), client_protocol_version())
+}
+
+/// Error response with structured `details` the caller can act on
+pub fn error_envelope_with_details<T: Serialize>(error: &str, details: T) -> String {
+    let mut envelope = error_fields(error);
+    envelope.insert("details".to_string(), serde_json::json!(details));
+    shape(envelope, client_protocol_version())
+}
+
+/// Fields every error response has, plus the current trace id if any
+fn error_fields(error: &str) -> Map<String, Value> {
+    let mut envelope = Map::new();
+    envelope.insert("success".to_string(), Value::Bool(false));
+    envelope.insert("error".to_string(), Value::String(error.to_string()));
+    if let Some(trace_id) = crate::trace::current() {
+        envelope.insert("trace_id".to_string(), Value::String(trace_id));
+    }
+    envelope
+}
+
+/// Stamp a current-version envelope and convert it down to `version`
+fn shape(mut envelope: Map<String, Value>, version: u32) -> String {
+    envelope.insert("protocol_version".to_string(), Value::from(PROTOCOL_VERSION));
+    for from in (version + 1..=PROTOCOL_VERSION).rev() {
+        downgrade(&mut envelope, from);
+    }
+    Value::Object(envelope).to_string()
+}
+
+/// Convert a response from version `from` to `from - 1`
+fn downgrade(envelope: &mut Map<String, Value>, from: u32) {
+    // 2 -> 1: version 1 has no protocol_version
+    if from == 2 {
+        envelope.remove("protocol_version");
+    }
+    if let Some(version) = envelope.get_mut("protocol_version") {
+        *version = Value::from(from - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_shims() {
+        let mut envelope = Map::new();
+        envelope.insert("success".to_string(), Value::Bool(true));
+        envelope.insert("data".to_string(), serde_json::json!({ "books": [] }));
+
+        let current: Value = serde_json::from_str(&shape(envelope.clone(), PROTOCOL_VERSION)).unwrap();
+        assert_eq!(current["protocol_version"], PROTOCOL_VERSION);
+        assert_eq!(current["data"]["books"], serde_json::json!([]));
+
+        // Version 1 clients get the original envelope
+        let legacy: Value = serde_json::from_str(&shape(envelope, 1)).unwrap();
+        assert_eq!(legacy, serde_json::json!({ "success": true, "data": { "books": [] } }));
+
+        assert_eq!(compatible_version(1).unwrap(), 1);
+        assert_eq!(compatible_version(PROTOCOL_VERSION + 3).unwrap(), PROTOCOL_VERSION);
+        assert!(matches!(compatible_version(0), Err(LibationError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_error_envelope_trace_id() {
+        let untraced: Value = serde_json::from_str(&error_envelope("boom")).unwrap();
+        assert!(untraced.get("trace_id").is_none());
+
+        let _scope = crate::trace::enter(Some("js-42".to_string()));
+        let traced: Value = serde_json::from_str(&error_envelope_with_details("boom", 7)).unwrap();
+        assert_eq!(traced["trace_id"], "js-42");
+        assert_eq!(traced["details"], 7);
+    }
+}
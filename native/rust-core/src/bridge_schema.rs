@@ -0,0 +1,314 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! JSON Schema of bridge payloads
+//!
+//! The `data` of a bridge response (see `bridge_protocol`) is whatever the
+//! Rust side serializes, and the JS layer has no way to notice when it
+//! changes. Payloads built from a Rust type are described here by JSON
+//! Schema generated from that type (`schemars`), keyed by bridge function:
+//!
+//! - `bridge_schema()` is one draft-07 document. Its `properties` map each
+//!   function to its payload's schema, and shared types are under
+//!   `definitions`. It is written to
+//!   `modules/expo-rust-bridge/bridge-schema.json` for the JS layer to
+//!   generate TypeScript types from; a test fails when the file is out of
+//!   date. Regenerate it with
+//!   `UPDATE_BRIDGE_SCHEMA=1 cargo test --lib test_bridge_schema_snapshot`.
+//! - In debug builds, `success_envelope` checks the payload of the current
+//!   call (`enter_call`) against its schema and answers with an error
+//!   response when it doesn't match, so drift shows up in development and
+//!   tests instead of as `undefined` in the UI. Release builds don't check.
+//!   iOS functions enter the name of their JNI counterpart.
+//!
+//! Payloads built ad hoc with `json!` have no schema and aren't checked. A
+//! function is covered by adding it to `payload_schemas` once its payload
+//! is a type deriving `JsonSchema`.
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{Map, Value};
+use std::cell::Cell;
+
+/// File the schema document is written to, relative to this crate
+pub const SCHEMA_FILE: &str = "../../modules/expo-rust-bridge/bridge-schema.json";
+
+thread_local! {
+    static CURRENT: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Bridge function running on this thread
+pub fn current_call() -> Option<&'static str> {
+    CURRENT.with(|current| current.get())
+}
+
+/// Make `function` the current bridge call on this thread until the scope drops
+///
+/// The previous call is restored afterwards, so scopes nest.
+pub fn enter_call(function: &'static str) -> CallScope {
+    let previous = CURRENT.with(|current| current.replace(Some(function)));
+    CallScope { previous }
+}
+
+/// Current call set by `enter_call`
+pub struct CallScope {
+    previous: Option<&'static str>,
+}
+
+impl Drop for CallScope {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
+}
+
+/// Payload schemas by bridge function
+fn payload_schemas(gen: &mut SchemaGenerator) -> Vec<(&'static str, Schema)> {
+    fn payload<T: JsonSchema>(gen: &mut SchemaGenerator, function: &'static str) -> (&'static str, Schema) {
+        (function, gen.subschema_for::<T>())
+    }
+
+    vec![
+        payload::<crate::bridge_protocol::BridgeInfo>(gen, "nativeGetBridgeInfo"),
+        payload::<crate::permissions::PermissionPolicy>(gen, "nativeGetPermissions"),
+        payload::<crate::permissions::PermissionPolicy>(gen, "nativeSetPermissions"),
+        payload::<crate::audio::capabilities::AudioCapabilities>(gen, "nativeGetAudioCapabilities"),
+        payload::<crate::api::preflight::SyncReadiness>(gen, "nativeCheckSyncReadiness"),
+        payload::<crate::audio::resume::ResumePoint>(gen, "nativeGetResumePosition"),
+        payload::<crate::audio::resume::ResumeOptions>(gen, "nativeGetResumeOptions"),
+        payload::<crate::events::QuietHours>(gen, "nativeGetQuietHours"),
+        payload::<crate::events::QuietHours>(gen, "nativeSetQuietHours"),
+        payload::<crate::activity::WorkActivity>(gen, "nativeGetWorkActivity"),
+        payload::<crate::activity::WorkActivity>(gen, "nativeSetWorkActivityListener"),
+        payload::<crate::file::cache::CacheUsage>(gen, "nativeGetCacheUsage"),
+        payload::<crate::file::cache::CacheQuota>(gen, "nativeGetCacheQuota"),
+        payload::<crate::storage::quiesce::QuiesceStatus>(gen, "nativeBeginQuiesce"),
+    ]
+}
+
+lazy_static::lazy_static! {
+    static ref SCHEMA: Value = build_schema();
+}
+
+fn build_schema() -> Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+    let payloads: Map<String, Value> = payload_schemas(&mut gen)
+        .into_iter()
+        .map(|(function, schema)| (function.to_string(), serde_json::json!(schema)))
+        .collect();
+
+    serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "BridgePayloads",
+        "description": "`data` of successful bridge responses, by bridge function",
+        "type": "object",
+        "properties": payloads,
+        "definitions": gen.definitions(),
+    })
+}
+
+/// Schema document of all covered payloads
+pub fn bridge_schema() -> &'static Value {
+    &SCHEMA
+}
+
+/// Check the payload of a `function` response against its schema
+///
+/// # Returns
+/// The first mismatch, as `path: problem`. Functions without a schema pass.
+pub fn validate_payload(function: &str, data: &Value) -> std::result::Result<(), String> {
+    match SCHEMA["properties"].get(function) {
+        Some(schema) => validate(&SCHEMA, schema, data, "data"),
+        None => Ok(()),
+    }
+}
+
+/// Check `data` against the payload schema of the current call
+pub fn validate_current(data: &Value) -> std::result::Result<(), String> {
+    match current_call() {
+        Some(function) => validate_payload(function, data)
+            .map_err(|problem| format!("Response of {} doesn't match its schema: {}", function, problem)),
+        None => Ok(()),
+    }
+}
+
+/// Check `value` against `schema`, resolving `$ref`s in `root`
+///
+/// Covers the keywords `schemars` generates; others (`format`,
+/// `description`, `default`) are ignored.
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> std::result::Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{}: not allowed", path)),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let target = reference
+            .strip_prefix("#/definitions/")
+            .and_then(|name| root["definitions"].get(name))
+            .ok_or_else(|| format!("{}: unknown schema {}", path, reference))?;
+        validate(root, target, value, path)?;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let matches = match types {
+            Value::Array(types) => types.iter().any(|t| has_type(value, t)),
+            t => has_type(value, t),
+        };
+        if !matches {
+            return Err(format!("{}: expected {}, got {}", path, types, value));
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(format!("{}: {} is not one of {}", path, value, Value::Array(allowed.clone())));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!("{}: expected {}, got {}", path, expected, value));
+        }
+    }
+    if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+        if number < minimum {
+            return Err(format!("{}: {} is below {}", path, number, minimum));
+        }
+    }
+
+    if let Some(schemas) = schema.get("allOf").and_then(Value::as_array) {
+        for schema in schemas {
+            validate(root, schema, value, path)?;
+        }
+    }
+    if let Some(schemas) = schema.get("anyOf").and_then(Value::as_array) {
+        let results: Vec<_> = schemas.iter().map(|s| validate(root, s, value, path)).collect();
+        if !results.iter().any(|r| r.is_ok()) {
+            return results.into_iter().find_map(|r| r.err()).map_or(Ok(()), Err);
+        }
+    }
+    if let Some(schemas) = schema.get("oneOf").and_then(Value::as_array) {
+        let matching = schemas.iter().filter(|s| validate(root, s, value, path).is_ok()).count();
+        if matching != 1 {
+            return Err(format!("{}: {} matches {} of the allowed shapes, not 1", path, value, matching));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for field in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            let field = field.as_str().unwrap_or_default();
+            if !object.contains_key(field) {
+                return Err(format!("{}: missing {}", path, field));
+            }
+        }
+        for (field, field_value) in object {
+            let field_path = format!("{}.{}", path, field);
+            match properties.and_then(|p| p.get(field)) {
+                Some(field_schema) => validate(root, field_schema, field_value, &field_path)?,
+                None => {
+                    if let Some(additional) = schema.get("additionalProperties") {
+                        validate(root, additional, field_value, &field_path)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate(root, item_schema, item, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `value` has the JSON Schema type `t`
+fn has_type(value: &Value, t: &Value) -> bool {
+    match t.as_str().unwrap_or_default() {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge_protocol::success_envelope;
+
+    #[test]
+    fn test_bridge_schema_snapshot() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMA_FILE);
+        let mut generated = serde_json::to_string_pretty(bridge_schema()).unwrap();
+        generated.push('\n');
+
+        if std::env::var_os("UPDATE_BRIDGE_SCHEMA").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let written = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            written == generated,
+            "{} is out of date; run `UPDATE_BRIDGE_SCHEMA=1 cargo test --lib test_bridge_schema_snapshot`",
+            SCHEMA_FILE
+        );
+    }
+
+    #[test]
+    fn test_payload_validation() {
+        let payloads = [
+            ("nativeGetBridgeInfo", serde_json::json!(crate::bridge_protocol::bridge_info())),
+            ("nativeGetPermissions", serde_json::json!(crate::permissions::policy())),
+            ("nativeGetAudioCapabilities", serde_json::json!(crate::audio::get_audio_capabilities())),
+            ("nativeGetQuietHours", serde_json::json!(crate::events::QuietHours::default())),
+            ("nativeGetWorkActivity", serde_json::json!(crate::activity::current_activity())),
+            ("nativeGetCacheQuota", serde_json::json!(crate::file::cache::CacheQuota::default())),
+        ];
+        for (function, data) in &payloads {
+            assert_eq!(validate_payload(function, data), Ok(()), "{}", function);
+        }
+
+        let mut info = payloads[0].1.clone();
+        info["protocol_version"] = serde_json::json!("2");
+        let problem = validate_payload("nativeGetBridgeInfo", &info).unwrap_err();
+        assert!(problem.starts_with("data.protocol_version"), "{}", problem);
+        info["protocol_version"] = serde_json::json!(2);
+        info.as_object_mut().unwrap().remove("capabilities");
+        assert!(validate_payload("nativeGetBridgeInfo", &info).is_err());
+
+        let quiet_hours = serde_json::json!({ "breakthrough_severity": "loud" });
+        assert!(validate_payload("nativeGetQuietHours", &quiet_hours).is_err());
+
+        // Unknown functions and calls outside a bridge function aren't checked
+        assert_eq!(validate_payload("nativeGetBooks", &serde_json::json!(7)), Ok(()));
+        assert_eq!(validate_current(&info), Ok(()));
+
+        // A drifted response becomes an error in debug builds
+        let _call = enter_call("nativeGetBridgeInfo");
+        let response: Value = serde_json::from_str(&success_envelope(&info)).unwrap();
+        assert_eq!(response["success"], false);
+        assert!(response["error"].as_str().unwrap().contains("nativeGetBridgeInfo"));
+        let response: Value = serde_json::from_str(&success_envelope(crate::bridge_protocol::bridge_info())).unwrap();
+        assert_eq!(response["success"], true);
+    }
+}
@@ -0,0 +1,398 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cancellation of long-running work
+//!
+//! Library sync, downloads, decryption, conversion, exports, scans and
+//! integrity checks all take a `CancellationToken`. Cancelling is cooperative: the work
+//! notices it at its next checkpoint and returns `LibationError::Cancelled`.
+//!
+//! # Consistency
+//! Work is never dropped in the middle of a write. Checkpoints sit between
+//! units of work (a library page or book, a scanned file, a verified file),
+//! so every unit is either fully stored or not started. Only awaits that
+//! don't change local state, such as HTTP requests and reads, are raced
+//! against the token (`CancellationToken::run_until_cancelled`). Work with
+//! external side effects cleans up after itself: a cancelled download keeps
+//! its partial file and recorded progress for resuming, a cancelled
+//! decryption removes its partial output, and a cancelled conversion kills
+//! FFmpeg and removes the partial output.
+//!
+//! # Jobs
+//! The bridge runs work under a caller-chosen job id (`register_job`), so
+//! the app can cancel it from another call with `cancel_job` while the
+//! first call is still blocked. Jobs unregister when their handle drops,
+//! which also removes the job's temp directory (`JobHandle::temp_dir`).
+//! Their start and outcome are also stored in the database (see
+//! `storage::jobs`), so the app can find them again after a restart.
+
+use crate::error::{LibationError, Result};
+use crate::file::temp::ScratchDir;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    notify: Notify,
+    children: Mutex<Vec<Weak<TokenState>>>,
+}
+
+impl TokenState {
+    fn cancel(&self) {
+        if self.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.notify.notify_waiters();
+        let children = std::mem::take(&mut *self.children.lock().unwrap());
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+}
+
+/// Shared cancel flag; clones observe the same cancellation
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token cancelled together with this one, but cancellable on its own
+    pub fn child_token(&self) -> Self {
+        let child = Self::new();
+        if self.is_cancelled() {
+            child.cancel();
+        } else {
+            let mut children = self.state.children.lock().unwrap();
+            children.retain(|c| c.strong_count() > 0);
+            children.push(Arc::downgrade(&child.state));
+        }
+        child
+    }
+
+    /// Request cancellation (idempotent)
+    pub fn cancel(&self) {
+        self.state.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Checkpoint: `Err(Cancelled)` once cancellation was requested
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(LibationError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Completes when cancellation is requested
+    pub async fn cancelled(&self) {
+        loop {
+            // Registered before the check, so a cancel in between isn't missed
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Run `future` unless cancelled first; it is dropped on cancellation,
+    /// so only use this for awaits without local side effects
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Result<F::Output> {
+        tokio::select! {
+            biased;
+            _ = self.cancelled() => Err(LibationError::Cancelled),
+            output = future => Ok(output),
+        }
+    }
+}
+
+/// Kind of cancellable job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    LibrarySync,
+    Download,
+    Decryption,
+    Conversion,
+    Export,
+    Scan,
+    IntegrityCheck,
+    LicensePrefetch,
+    Handoff,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::LibrarySync => "library_sync",
+            JobKind::Download => "download",
+            JobKind::Decryption => "decryption",
+            JobKind::Conversion => "conversion",
+            JobKind::Export => "export",
+            JobKind::Scan => "scan",
+            JobKind::IntegrityCheck => "integrity_check",
+            JobKind::LicensePrefetch => "license_prefetch",
+            JobKind::Handoff => "handoff",
+        }
+    }
+}
+
+impl std::str::FromStr for JobKind {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "library_sync" => Ok(JobKind::LibrarySync),
+            "download" => Ok(JobKind::Download),
+            "decryption" => Ok(JobKind::Decryption),
+            "conversion" => Ok(JobKind::Conversion),
+            "export" => Ok(JobKind::Export),
+            "scan" => Ok(JobKind::Scan),
+            "integrity_check" => Ok(JobKind::IntegrityCheck),
+            "license_prefetch" => Ok(JobKind::LicensePrefetch),
+            "handoff" => Ok(JobKind::Handoff),
+            _ => Err(LibationError::InvalidInput(format!("Invalid job kind: {}", s))),
+        }
+    }
+}
+
+/// A registered job, as listed for the host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub job_id: String,
+    pub kind: JobKind,
+    /// ISO 8601 timestamp
+    pub started_at: String,
+    pub cancel_requested: bool,
+}
+
+struct RegisteredJob {
+    info: JobInfo,
+    token: CancellationToken,
+}
+
+lazy_static::lazy_static! {
+    static ref JOBS: Mutex<HashMap<String, RegisteredJob>> = Mutex::new(HashMap::new());
+}
+
+/// Running job; dropping it unregisters the job
+#[must_use = "the job is unregistered as soon as the handle is dropped"]
+#[derive(Debug)]
+pub struct JobHandle {
+    job_id: String,
+    kind: JobKind,
+    token: CancellationToken,
+    temp: Mutex<Option<ScratchDir>>,
+}
+
+impl JobHandle {
+    pub fn job_id(&self) -> &str {
+        &self.job_id
+    }
+
+    pub fn kind(&self) -> JobKind {
+        self.kind
+    }
+
+    /// Token to pass to the work
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// The job's temp directory, created on first use and removed with the
+    /// handle (see `file::temp`)
+    pub fn temp_dir(&self) -> Result<PathBuf> {
+        self.with_temp_dir(|dir| Ok(dir.path().to_path_buf()))
+    }
+
+    /// Path for a temp file of the job (see `ScratchDir::file`)
+    pub fn temp_file(&self, name: &str) -> Result<PathBuf> {
+        self.with_temp_dir(|dir| dir.file(name))
+    }
+
+    fn with_temp_dir<T>(&self, f: impl FnOnce(&ScratchDir) -> Result<T>) -> Result<T> {
+        let mut temp = self.temp.lock().unwrap();
+        let dir = match &mut *temp {
+            Some(dir) => dir,
+            slot @ None => slot.insert(ScratchDir::for_job(&self.job_id)?),
+        };
+        f(dir)
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        JOBS.lock().unwrap().remove(&self.job_id);
+    }
+}
+
+/// Register a job so it can be cancelled by id
+///
+/// # Arguments
+/// * `job_id` - Id chosen by the caller, or None for a generated one
+///
+/// # Errors
+/// InvalidState if a job with this id is still running
+pub fn register_job(job_id: Option<String>, kind: JobKind) -> Result<JobHandle> {
+    let job_id = job_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let mut jobs = JOBS.lock().unwrap();
+    if jobs.contains_key(&job_id) {
+        return Err(LibationError::InvalidState(format!("Job {} is already running", job_id)));
+    }
+
+    let token = CancellationToken::new();
+    jobs.insert(
+        job_id.clone(),
+        RegisteredJob {
+            info: JobInfo {
+                job_id: job_id.clone(),
+                kind,
+                started_at: crate::clock::now().to_rfc3339(),
+                cancel_requested: false,
+            },
+            token: token.clone(),
+        },
+    );
+
+    Ok(JobHandle { job_id, kind, token, temp: Mutex::new(None) })
+}
+
+/// Cancel a running job
+///
+/// # Returns
+/// False if no job with this id is running
+pub fn cancel_job(job_id: &str) -> bool {
+    let mut jobs = JOBS.lock().unwrap();
+    match jobs.get_mut(job_id) {
+        Some(job) => {
+            job.info.cancel_requested = true;
+            job.token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Cancel every running job of a kind
+///
+/// # Returns
+/// Number of jobs cancelled
+pub fn cancel_jobs(kind: JobKind) -> usize {
+    let mut jobs = JOBS.lock().unwrap();
+    let mut cancelled = 0;
+    for job in jobs.values_mut().filter(|j| j.info.kind == kind) {
+        job.info.cancel_requested = true;
+        job.token.cancel();
+        cancelled += 1;
+    }
+    cancelled
+}
+
+/// Running jobs, oldest first
+pub fn list_jobs() -> Vec<JobInfo> {
+    let mut jobs: Vec<JobInfo> = JOBS.lock().unwrap().values().map(|j| j.info.clone()).collect();
+    jobs.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.job_id.cmp(&b.job_id)));
+    jobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancellation_token() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+        let sibling = parent.child_token();
+        assert!(child.check().is_ok());
+
+        // Children can be cancelled alone
+        sibling.cancel();
+        assert!(sibling.is_cancelled() && !parent.is_cancelled() && !child.is_cancelled());
+
+        let waiter = tokio::spawn({
+            let child = child.clone();
+            async move { child.cancelled().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        parent.cancel();
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        assert!(matches!(child.check(), Err(LibationError::Cancelled)));
+        assert!(parent.child_token().is_cancelled());
+
+        let token = CancellationToken::new();
+        assert_eq!(token.run_until_cancelled(async { 7 }).await.unwrap(), 7);
+        token.cancel();
+        let pending = token.run_until_cancelled(std::future::pending::<()>()).await;
+        assert!(matches!(pending, Err(LibationError::Cancelled)));
+    }
+
+    #[test]
+    fn test_job_registry() {
+        let job = register_job(Some("test-job-registry".to_string()), JobKind::Scan).unwrap();
+        assert!(register_job(Some("test-job-registry".to_string()), JobKind::Scan).is_err());
+        assert!(list_jobs().iter().any(|j| j.job_id == "test-job-registry"));
+
+        assert!(cancel_job("test-job-registry"));
+        assert!(job.token().is_cancelled());
+        assert!(list_jobs().iter().any(|j| j.job_id == "test-job-registry" && j.cancel_requested));
+
+        drop(job);
+        assert!(!cancel_job("test-job-registry"));
+        assert!(register_job(None, JobKind::Export).unwrap().job_id().len() > 10);
+    }
+
+    #[test]
+    fn test_job_temp_dir() {
+        let job = register_job(Some("test-job-temp-dir".to_string()), JobKind::Export).unwrap();
+        let temp = job.temp_dir().unwrap();
+        assert_eq!(job.temp_dir().unwrap(), temp);
+        let file = job.temp_file("cover art.jpg").unwrap();
+        assert_eq!(file, temp.join("cover_art.jpg"));
+        std::fs::write(&file, b"jpeg").unwrap();
+
+        // Kept while the job runs, removed with its handle
+        crate::file::temp::remove_orphaned_temp_dirs().unwrap();
+        assert!(file.exists());
+        drop(job);
+        assert!(!temp.exists());
+    }
+}
@@ -0,0 +1,168 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Time source for expiry and scheduling logic
+//!
+//! Token refresh, download URL expiry and conversion windows depend on the
+//! current time. They read it through a `Clock` so tests (and the host
+//! app's test mode) can control it:
+//!
+//! - `SystemClock` - Real time
+//! - `TestClock` - Fixed time that only moves when told to (`advance`, `set`)
+//! - `AppClock` - The process-wide clock: system time unless a test clock
+//!   was installed with `set_clock`
+//!
+//! Code that can't take a clock parameter calls `clock::now()`. Unit tests
+//! should pass a `TestClock` explicitly rather than install one, since the
+//! process-wide clock is shared by concurrently running tests.
+//!
+//! # Example
+//! ```rust
+//! use rust_core::clock::{Clock, TestClock};
+//!
+//! let clock = TestClock::new(chrono::Utc::now());
+//! let start = clock.now();
+//! clock.advance(chrono::Duration::hours(2));
+//! assert_eq!(clock.now() - start, chrono::Duration::hours(2));
+//! ```
+
+use chrono::{DateTime, FixedOffset, Local, Timelike, Utc};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current UTC time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current hour (0-23) in the device's local time zone
+    fn local_hour(&self) -> u32 {
+        self.now().with_timezone(&Local).hour()
+    }
+}
+
+/// Real time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Controllable time for tests
+///
+/// Local time uses a fixed UTC offset (UTC unless set with `with_offset`)
+/// so schedule tests don't depend on the machine's time zone.
+#[derive(Debug)]
+pub struct TestClock {
+    now: Mutex<DateTime<Utc>>,
+    offset: FixedOffset,
+}
+
+impl TestClock {
+    /// A clock stopped at `start`
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+            offset: FixedOffset::east_opt(0).unwrap(),
+        }
+    }
+
+    /// Use this UTC offset for `local_hour`
+    pub fn with_offset(mut self, offset: FixedOffset) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Move time forward (or back, with a negative duration)
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+
+    /// Jump to a point in time
+    pub fn set(&self, to: DateTime<Utc>) {
+        *self.now.lock().unwrap() = to;
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+
+    fn local_hour(&self) -> u32 {
+        self.now().with_timezone(&self.offset).hour()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref INSTALLED: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+}
+
+/// The process-wide clock
+///
+/// Reads the installed test clock if there is one, else system time. Use it
+/// as the default wherever a clock can be injected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AppClock;
+
+impl Clock for AppClock {
+    fn now(&self) -> DateTime<Utc> {
+        match INSTALLED.read().unwrap().as_ref() {
+            Some(clock) => clock.now(),
+            None => Utc::now(),
+        }
+    }
+
+    fn local_hour(&self) -> u32 {
+        match INSTALLED.read().unwrap().as_ref() {
+            Some(clock) => clock.local_hour(),
+            None => SystemClock.local_hour(),
+        }
+    }
+}
+
+/// Install a clock process-wide (None restores system time)
+pub fn set_clock(clock: Option<Arc<dyn Clock>>) {
+    *INSTALLED.write().unwrap() = clock;
+}
+
+/// Current time from the process-wide clock
+pub fn now() -> DateTime<Utc> {
+    AppClock.now()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_test_clock() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 22, 30, 0).unwrap();
+        let clock = TestClock::new(start).with_offset(FixedOffset::east_opt(2 * 3600).unwrap());
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.local_hour(), 0);
+
+        clock.advance(chrono::Duration::hours(3));
+        assert_eq!(clock.now(), start + chrono::Duration::hours(3));
+        assert_eq!(clock.local_hour(), 3);
+
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+}
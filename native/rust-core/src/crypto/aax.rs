@@ -20,46 +20,754 @@
 //! AAX file decryption (legacy Audible format)
 //!
 //! # Reference C# Sources
-//! - External dependency: FFmpeg with activation bytes
 //! - `FileLiberator/AudioDecodable.cs` - High-level decryption orchestration
 //! - `FileLiberator/ConvertToMp3.cs` - Conversion after decryption
 //! - `AaxDecrypter/MultiConvertFileProperties.cs` - File handling
+//! - FFmpeg `libavformat/mov.c` (`mov_read_adrm`, `aax_filter`) - Key derivation
 //!
 //! # AAX Format Details
 //! - Container: MP4 (M4B)
-//! - Audio codec: AAC
-//! - Encryption: AES-128 CBC mode
-//! - Key derivation: Based on activation bytes
-//! - File structure: Standard MP4 with encrypted mdat atom
+//! - Audio codec: AAC, sample entry `aavd` instead of `mp4a`
+//! - Encryption: AES-128 CBC per sample, IV reset for every sample,
+//!   trailing partial block left in the clear
+//! - Key derivation: SHA-1 over a fixed key and the activation bytes unlocks
+//!   the file key stored in the `adrm` atom
 //!
-//! # Decryption Process (Libation's approach)
-//! 1. Use activation bytes as key
-//! 2. Call FFmpeg with `-activation_bytes` parameter
-//! 3. FFmpeg command (from Libation):
-//!    ```text
-//!    ffmpeg -activation_bytes <BYTES> -i input.aax -vn -c:a copy output.m4b
-//!    ```
-//! 4. `-vn`: No video (strip cover art, will re-add later)
-//! 5. `-c:a copy`: Copy audio stream without re-encoding
+//! # Decryption Process
+//! Libation shells out to `ffmpeg -activation_bytes`, which isn't available
+//! on Android or iOS and is slow to start. This module decrypts natively:
+//! 1. Parse `moov` for the encrypted track's `adrm` atom and sample table
+//! 2. Verify the activation bytes against the `adrm` checksum
+//! 3. Stream the file to the output, decrypting each sample in place
+//! 4. Rename `aavd` → `mp4a` and `adrm` → `free` so players see plain AAC
 //!
-//! # Alternative: Native Rust Decryption
-//! - Can implement AES decryption directly
-//! - Would need to:
-//!   1. Parse MP4 structure (use mp4parse crate)
-//!   2. Locate encrypted mdat atom
-//!   3. Derive AES key from activation bytes
-//!   4. Decrypt audio data
-//!   5. Write decrypted MP4
-//! - FFmpeg approach is simpler and battle-tested
+//! The layout of the file is unchanged, so chunk offsets stay valid and no
+//! remuxing is needed. AES runs through the `aes` crate, which uses AES-NI
+//! on x86 and the ARMv8 crypto extensions on arm64 (enabled with
+//! `--cfg aes_armv8` in `.cargo/config.toml`). `benches/aax_decrypt.rs`
+//! measures sample throughput; the target is 100 MB/s on mid-range phones.
 
+use crate::activity::{self, WorkType};
+use crate::cancel::CancellationToken;
 use crate::crypto::activation::{format_activation_bytes, ActivationBytes};
 use crate::error::{LibationError, Result};
+use aes::Aes128Dec;
+use cbc::cipher::{block_padding::NoPadding, BlockDecryptMut, InnerIvInit, KeyInit, KeyIvInit};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use std::time::{Duration, Instant};
 
-/// AAX file decrypter using FFmpeg
+/// Fixed key Audible mixes into every AAX key derivation
+const FIXED_KEY: [u8; 16] = [
+    0x77, 0x21, 0x4d, 0x4b, 0x19, 0x6a, 0x87, 0xcd, 0x52, 0x00, 0x45, 0xfd, 0x20, 0xa5, 0x1d, 0x67,
+];
+
+/// Encrypted key blob at offset 8 of the `adrm` payload
+const DRM_BLOB: Range<usize> = 8..64;
+
+/// SHA-1 checksum of the intermediate key and IV at offset 68 of the `adrm` payload
+const DRM_CHECKSUM: Range<usize> = 68..88;
+
+/// Buffer size for streaming the file through
+const IO_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Bytes between progress reports
+const PROGRESS_INTERVAL: u64 = 4 * 1024 * 1024;
+
+/// Progress of a native AAX decryption
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DecryptProgress {
+    /// Fraction of the input processed (0.0 to 1.0)
+    pub fraction: f32,
+
+    /// Input bytes processed so far
+    pub bytes_processed: u64,
+
+    /// Input file size
+    pub total_bytes: u64,
+
+    /// Average throughput since the start
+    pub bytes_per_second: u64,
+}
+
+impl DecryptProgress {
+    fn new(bytes_processed: u64, total_bytes: u64, started: Instant) -> Self {
+        let elapsed = started.elapsed().as_secs_f64();
+        let fraction = if total_bytes == 0 {
+            1.0
+        } else {
+            (bytes_processed as f64 / total_bytes as f64).min(1.0) as f32
+        };
+        let bytes_per_second = if elapsed > 0.0 {
+            (bytes_processed as f64 / elapsed) as u64
+        } else {
+            0
+        };
+
+        Self {
+            fraction,
+            bytes_processed,
+            total_bytes,
+            bytes_per_second,
+        }
+    }
+}
+
+/// Per-file AES key and IV recovered from the `adrm` atom
+#[derive(Clone)]
+pub struct AaxFileKey {
+    cipher: Aes128Dec,
+    iv: [u8; 16],
+}
+
+impl AaxFileKey {
+    /// Create from a raw file key and IV
+    pub fn new(key: [u8; 16], iv: [u8; 16]) -> Self {
+        Self {
+            cipher: Aes128Dec::new(&key.into()),
+            iv,
+        }
+    }
+
+    /// Unlock the file key stored in an `adrm` atom payload
+    ///
+    /// # Errors
+    /// - InvalidDrmFormat if the payload is too short
+    /// - InvalidActivationBytes if the activation bytes don't belong to this file
+    pub fn from_adrm(adrm: &[u8], activation_bytes: &ActivationBytes) -> Result<Self> {
+        if adrm.len() < DRM_CHECKSUM.end {
+            return Err(LibationError::InvalidDrmFormat(format!(
+                "adrm atom is {} bytes, expected at least {}",
+                adrm.len(),
+                DRM_CHECKSUM.end
+            )));
+        }
+        let activation = activation_bytes.as_bytes();
+
+        let intermediate_key = Sha1::new()
+            .chain_update(FIXED_KEY)
+            .chain_update(activation)
+            .finalize();
+        let intermediate_iv = Sha1::new()
+            .chain_update(FIXED_KEY)
+            .chain_update(intermediate_key)
+            .chain_update(activation)
+            .finalize();
+
+        let checksum = Sha1::new()
+            .chain_update(&intermediate_key[..16])
+            .chain_update(&intermediate_iv[..16])
+            .finalize();
+        if checksum.as_slice() != &adrm[DRM_CHECKSUM] {
+            return Err(LibationError::InvalidActivationBytes(format!(
+                "{} do not match this AAX file",
+                format_activation_bytes(activation)
+            )));
+        }
+
+        // Only whole blocks of the 56-byte blob are encrypted
+        let mut blob = [0u8; 48];
+        blob.copy_from_slice(&adrm[DRM_BLOB][..48]);
+        let key: [u8; 16] = intermediate_key[..16].try_into().unwrap();
+        let iv: [u8; 16] = intermediate_iv[..16].try_into().unwrap();
+        let _ = cbc::Decryptor::<Aes128Dec>::new(&key.into(), &iv.into())
+            .decrypt_padded_mut::<NoPadding>(&mut blob);
+
+        // The blob starts with the activation bytes, stored little-endian
+        if !blob[..4].iter().rev().eq(activation.iter()) {
+            return Err(LibationError::InvalidActivationBytes(
+                "DRM blob did not decrypt to the activation bytes".to_string(),
+            ));
+        }
+
+        let file_key: [u8; 16] = blob[8..24].try_into().unwrap();
+        let file_iv = Sha1::new()
+            .chain_update(&blob[26..42])
+            .chain_update(file_key)
+            .chain_update(FIXED_KEY)
+            .finalize();
+
+        Ok(Self::new(file_key, file_iv[..16].try_into().unwrap()))
+    }
+
+    /// Decrypt one audio sample in place
+    ///
+    /// Every sample is its own CBC stream starting from the file IV; bytes
+    /// past the last whole block are stored unencrypted.
+    pub fn decrypt_sample(&self, sample: &mut [u8]) {
+        let len = sample.len() & !15;
+        if len == 0 {
+            return;
+        }
+        // NoPadding over whole blocks can't fail
+        let _ = cbc::Decryptor::<Aes128Dec>::inner_iv_init(self.cipher.clone(), &self.iv.into())
+            .decrypt_padded_mut::<NoPadding>(&mut sample[..len]);
+    }
+}
+
+/// One MP4 atom inside an in-memory buffer
+struct Atom {
+    kind: [u8; 4],
+    /// Offset of the atom header
+    start: usize,
+    /// Payload after the header
+    body: Range<usize>,
+}
+
+fn truncated(what: &str) -> LibationError {
+    LibationError::InvalidDrmFormat(format!("Truncated MP4 atom: {}", what))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| truncated("u32 field"))
+}
+
+fn be_u64(data: &[u8], pos: usize) -> Result<u64> {
+    data.get(pos..pos + 8)
+        .map(|b| u64::from_be_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| truncated("u64 field"))
+}
+
+/// Child atoms of `data[range]`
+fn child_atoms(data: &[u8], range: Range<usize>) -> Result<Vec<Atom>> {
+    let mut atoms = Vec::new();
+    let mut pos = range.start;
+
+    while pos + 8 <= range.end {
+        let size = be_u32(data, pos)? as u64;
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let (header, size) = match size {
+            0 => (8, (range.end - pos) as u64),
+            1 => (16, be_u64(data, pos + 8)?),
+            size => (8, size),
+        };
+        let end = pos as u64 + size;
+        if size < header as u64 || end > range.end as u64 {
+            return Err(truncated(&String::from_utf8_lossy(&kind)));
+        }
+
+        atoms.push(Atom {
+            kind,
+            start: pos,
+            body: pos + header..end as usize,
+        });
+        pos = end as usize;
+    }
+
+    Ok(atoms)
+}
+
+fn find_atom<'a>(atoms: &'a [Atom], kind: &[u8; 4]) -> Option<&'a Atom> {
+    atoms.iter().find(|atom| &atom.kind == kind)
+}
+
+/// Byte ranges of every sample in a track, from its sample table
+fn sample_ranges(data: &[u8], stbl: &[Atom]) -> Result<Vec<(u64, u32)>> {
+    let missing = |name: &str| LibationError::InvalidDrmFormat(format!("Missing {} atom", name));
+    let stsz = find_atom(stbl, b"stsz").ok_or_else(|| missing("stsz"))?;
+    let stsc = find_atom(stbl, b"stsc").ok_or_else(|| missing("stsc"))?;
+
+    let fixed_size = be_u32(data, stsz.body.start + 4)?;
+    let sample_count = be_u32(data, stsz.body.start + 8)? as usize;
+    let sample_size = |i: usize| -> Result<u32> {
+        if fixed_size != 0 {
+            Ok(fixed_size)
+        } else {
+            be_u32(data, stsz.body.start + 12 + i * 4)
+        }
+    };
+
+    let chunk_offsets = if let Some(stco) = find_atom(stbl, b"stco") {
+        let count = be_u32(data, stco.body.start + 4)? as usize;
+        (0..count)
+            .map(|i| be_u32(data, stco.body.start + 8 + i * 4).map(u64::from))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        let co64 = find_atom(stbl, b"co64").ok_or_else(|| missing("stco"))?;
+        let count = be_u32(data, co64.body.start + 4)? as usize;
+        (0..count)
+            .map(|i| be_u64(data, co64.body.start + 8 + i * 8))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    // (first_chunk, samples_per_chunk), first_chunk 1-based
+    let stsc_count = be_u32(data, stsc.body.start + 4)? as usize;
+    let runs = (0..stsc_count)
+        .map(|i| {
+            let entry = stsc.body.start + 8 + i * 12;
+            Ok((be_u32(data, entry)? as usize, be_u32(data, entry + 4)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut samples = Vec::with_capacity(sample_count);
+    let mut run = 0;
+    for (chunk, &chunk_offset) in chunk_offsets.iter().enumerate() {
+        while run + 1 < runs.len() && runs[run + 1].0 <= chunk + 1 {
+            run += 1;
+        }
+        let per_chunk = runs.get(run).map_or(0, |&(_, n)| n);
+
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            if samples.len() == sample_count {
+                break;
+            }
+            let size = sample_size(samples.len())?;
+            samples.push((offset, size));
+            offset += u64::from(size);
+        }
+    }
+
+    if samples.len() != sample_count {
+        return Err(LibationError::InvalidDrmFormat(format!(
+            "Sample table lists {} samples but chunks hold {}",
+            sample_count,
+            samples.len()
+        )));
+    }
+    Ok(samples)
+}
+
+/// Everything needed to decrypt an AAX or AAXC file in one pass
+struct AaxLayout {
+    /// `adrm` payload of the encrypted track (AAX only; AAXC keys come
+    /// from the license)
+    adrm: Option<Vec<u8>>,
+
+    /// (offset, size) of every encrypted sample, sorted by offset
+    samples: Vec<(u64, u32)>,
+
+    /// Atom type fields to overwrite in the output: (absolute offset, new type)
+    patches: Vec<(u64, [u8; 4])>,
+}
+
+impl AaxLayout {
+    /// Read the top-level atoms and parse `moov`
+    fn read(file: &mut File, file_len: u64) -> Result<Self> {
+        let mut pos = 0u64;
+        let mut patches = Vec::new();
+        let mut moov = None;
+
+        while pos + 8 <= file_len {
+            let mut header = [0u8; 16];
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut header[..8])?;
+            let kind: [u8; 4] = header[4..8].try_into().unwrap();
+            let size = match be_u32(&header, 0)? {
+                0 => file_len - pos,
+                1 => {
+                    file.read_exact(&mut header[8..])?;
+                    be_u64(&header, 8)?
+                }
+                size => u64::from(size),
+            };
+            if size < 8 || pos + size > file_len {
+                return Err(truncated(&String::from_utf8_lossy(&kind)));
+            }
+
+            match &kind {
+                // Major brand is `aax ` or `aaxc`; players expect an audiobook brand
+                b"ftyp" => patches.push((pos + 8, *b"M4B ")),
+                b"moov" => {
+                    let mut data = vec![0u8; size as usize];
+                    file.seek(SeekFrom::Start(pos))?;
+                    file.read_exact(&mut data)?;
+                    moov = Some((pos, data));
+                }
+                _ => {}
+            }
+            pos += size;
+        }
+
+        let (moov_offset, data) = moov
+            .ok_or_else(|| LibationError::InvalidDrmFormat("Missing moov atom".to_string()))?;
+        Self::from_moov(moov_offset, &data, patches)
+    }
+
+    /// Parse the start of a file as it arrives
+    ///
+    /// Returns None until `head` holds all of `moov`.
+    ///
+    /// # Errors
+    /// - InvalidDrmFormat if `mdat` comes before `moov`: samples can't be
+    ///   found before they arrive
+    fn from_head(head: &[u8]) -> Result<Option<Self>> {
+        let mut pos = 0usize;
+        let mut patches = Vec::new();
+
+        while pos + 8 <= head.len() {
+            let kind: [u8; 4] = head[pos + 4..pos + 8].try_into().unwrap();
+            let size = match be_u32(head, pos)? {
+                1 if pos + 16 > head.len() => return Ok(None),
+                1 => be_u64(head, pos + 8)?,
+                size => u64::from(size),
+            };
+
+            match &kind {
+                b"moov" if size >= 8 => {
+                    let end = pos as u64 + size;
+                    if end > head.len() as u64 {
+                        return Ok(None);
+                    }
+                    return Self::from_moov(pos as u64, &head[pos..end as usize], patches).map(Some);
+                }
+                b"mdat" => {
+                    return Err(LibationError::InvalidDrmFormat(
+                        "moov comes after mdat; the file must be downloaded before decrypting".to_string(),
+                    ))
+                }
+                _ if size < 8 => return Err(truncated(&String::from_utf8_lossy(&kind))),
+                b"ftyp" => patches.push((pos as u64 + 8, *b"M4B ")),
+                _ => {}
+            }
+            pos += size as usize;
+        }
+
+        Ok(None)
+    }
+
+    /// Parse the `moov` atom found at `moov_offset`
+    fn from_moov(moov_offset: u64, data: &[u8], mut patches: Vec<(u64, [u8; 4])>) -> Result<Self> {
+        let moov_atoms = child_atoms(data, 0..data.len())?;
+        let moov_body = moov_atoms[0].body.clone();
+
+        let mut adrm = None;
+        let mut encrypted = false;
+        let mut samples = Vec::new();
+
+        for trak in child_atoms(data, moov_body)?.iter().filter(|a| &a.kind == b"trak") {
+            let Some(stbl) = [b"mdia", b"minf", b"stbl"]
+                .iter()
+                .try_fold(trak.body.clone(), |range, kind| {
+                    let atoms = child_atoms(data, range).ok()?;
+                    find_atom(&atoms, kind).map(|atom| atom.body.clone())
+                })
+            else {
+                continue;
+            };
+            let stbl = child_atoms(data, stbl)?;
+            let Some(stsd) = find_atom(&stbl, b"stsd") else {
+                continue;
+            };
+
+            // stsd: version/flags, entry count, then sample entries
+            let entries = child_atoms(data, stsd.body.start + 8..stsd.body.end)?;
+            let Some(entry) = entries.first().filter(|entry| &entry.kind == b"aavd") else {
+                continue;
+            };
+            patches.push((moov_offset + entry.start as u64 + 4, *b"mp4a"));
+            encrypted = true;
+
+            // Audio sample entry fields take 28 bytes before the child atoms
+            let children = child_atoms(data, entry.body.start + 28..entry.body.end)?;
+            if let Some(drm) = find_atom(&children, b"adrm") {
+                patches.push((moov_offset + drm.start as u64 + 4, *b"free"));
+                adrm.get_or_insert_with(|| data[drm.body.clone()].to_vec());
+            }
+
+            samples.extend(sample_ranges(data, &stbl)?);
+        }
+
+        if !encrypted {
+            return Err(LibationError::InvalidDrmFormat(
+                "No encrypted AAX/AAXC audio track found".to_string(),
+            ));
+        }
+        samples.sort_unstable();
+
+        Ok(Self {
+            adrm,
+            samples,
+            patches,
+        })
+    }
+}
+
+/// Copy exactly `len` bytes
+fn copy_exact(reader: &mut impl Read, writer: &mut impl Write, len: u64) -> Result<()> {
+    let copied = std::io::copy(&mut reader.take(len), writer)?;
+    if copied != len {
+        return Err(truncated("mdat"));
+    }
+    Ok(())
+}
+
+/// File key of an AAX file from its `adrm` payload
+fn unlock_aax(adrm: Option<&[u8]>, activation_bytes: &ActivationBytes) -> Result<AaxFileKey> {
+    let adrm = adrm.ok_or_else(|| {
+        LibationError::InvalidDrmFormat(
+            "No adrm atom: AAXC files are decrypted with the key and IV from their license".to_string(),
+        )
+    })?;
+    AaxFileKey::from_adrm(adrm, activation_bytes)
+}
+
+/// Decrypt `input` to `output` on the current thread
+///
+/// `unlock` gets the `adrm` payload, if the file has one, and returns the
+/// file key. `cancel` is checked before each sample; the caller removes
+/// the partial output.
+fn decrypt_blocking<U, F>(
+    input: &Path,
+    output: &Path,
+    unlock: U,
+    cancel: &CancellationToken,
+    mut progress_callback: F,
+) -> Result<DecryptProgress>
+where
+    U: FnOnce(Option<&[u8]>) -> Result<AaxFileKey>,
+    F: FnMut(DecryptProgress),
+{
+    let started = Instant::now();
+    let mut file = File::open(input)?;
+    let total_bytes = file.metadata()?.len();
+
+    let layout = AaxLayout::read(&mut file, total_bytes)?;
+    let key = unlock(layout.adrm.as_deref())?;
+
+    file.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::with_capacity(IO_BUFFER_SIZE, file);
+    let mut writer = BufWriter::with_capacity(IO_BUFFER_SIZE, File::create(output)?);
+
+    let mut position = 0u64;
+    let mut next_report = PROGRESS_INTERVAL;
+    let mut sample = Vec::new();
+
+    for &(offset, size) in &layout.samples {
+        cancel.check()?;
+        if offset < position {
+            return Err(LibationError::InvalidDrmFormat(format!(
+                "Overlapping samples at offset {}",
+                offset
+            )));
+        }
+        copy_exact(&mut reader, &mut writer, offset - position)?;
+
+        sample.resize(size as usize, 0);
+        reader.read_exact(&mut sample).map_err(|_| truncated("mdat"))?;
+        key.decrypt_sample(&mut sample);
+        writer.write_all(&sample)?;
+        position = offset + u64::from(size);
+
+        if position >= next_report {
+            progress_callback(DecryptProgress::new(position, total_bytes, started));
+            next_report = position + PROGRESS_INTERVAL;
+        }
+    }
+    std::io::copy(&mut reader, &mut writer)?;
+
+    let mut output_file = writer.into_inner().map_err(|e| e.into_error())?;
+    for (offset, kind) in &layout.patches {
+        output_file.seek(SeekFrom::Start(*offset))?;
+        output_file.write_all(kind)?;
+    }
+    output_file.flush()?;
+
+    let done = DecryptProgress::new(total_bytes, total_bytes, started);
+    progress_callback(done);
+    Ok(done)
+}
+
+/// Decrypt `input` to `output` on the blocking thread pool
+///
+/// Shared by the AAX and AAXC decrypters, which only differ in how the
+/// file key is found (see `decrypt_blocking`). Registers decryption work
+/// and removes the partial output when cancelled.
+pub(crate) async fn decrypt_in_background<U, F>(
+    input: &Path,
+    output: &Path,
+    unlock: U,
+    mut progress_callback: F,
+    cancel: &CancellationToken,
+) -> Result<DecryptProgress>
+where
+    U: FnOnce(Option<&[u8]>) -> Result<AaxFileKey> + Send + 'static,
+    F: FnMut(DecryptProgress) + Send + 'static,
+{
+    if !input.exists() {
+        return Err(LibationError::FileNotFound(input.display().to_string()));
+    }
+
+    let input = input.to_path_buf();
+    let output = output.to_path_buf();
+    let cancel = cancel.clone();
+    let work = activity::begin_work(WorkType::Decryption, None);
+    tokio::task::spawn_blocking(move || {
+        let on_progress = |progress: DecryptProgress| {
+            if progress.bytes_per_second > 0 {
+                let remaining = progress.total_bytes.saturating_sub(progress.bytes_processed);
+                work.set_expected_duration(Some(Duration::from_secs_f64(
+                    remaining as f64 / progress.bytes_per_second as f64,
+                )));
+            }
+            progress_callback(progress)
+        };
+        let result = decrypt_blocking(&input, &output, unlock, &cancel, on_progress);
+        if matches!(result, Err(LibationError::Cancelled)) {
+            let _ = std::fs::remove_file(&output);
+        }
+        result
+    })
+    .await
+    .map_err(|e| LibationError::DecryptionFailed(format!("Decryption task failed: {}", e)))?
+}
+
+/// Decrypts an AAXC file as it arrives, for decrypting while downloading
+///
+/// Input bytes go in in file order and come out decrypted at the same
+/// offsets, so the output is the M4B once `finish`'s atom patches are
+/// written. The layout is only known once `moov` has arrived; files with
+/// `moov` after `mdat` can't be streamed. Samples are held back until
+/// complete, so the output only ever ends on a `checkpoint`, which is
+/// where a download resumes.
+pub struct StreamDecrypter {
+    key: AaxFileKey,
+    /// Input before the layout is known
+    head: Vec<u8>,
+    layout: Option<AaxLayout>,
+    /// Input bytes consumed once the layout is known
+    position: u64,
+    next_sample: usize,
+    /// Start of the sample being received
+    sample: Vec<u8>,
+}
+
+impl StreamDecrypter {
+    /// Decrypt a file from its first byte
+    pub fn new(key: AaxFileKey) -> Self {
+        Self {
+            key,
+            head: Vec::new(),
+            layout: None,
+            position: 0,
+            next_sample: 0,
+            sample: Vec::new(),
+        }
+    }
+
+    /// Continue a partial output
+    ///
+    /// `head` is the start of the partial output (its header is still the
+    /// input's until `finish`) and `offset` the checkpoint it was written
+    /// up to. Returns None if `head` doesn't hold all of `moov`: read more,
+    /// or start over if the output ends before it. The caller truncates the
+    /// output to the returned decrypter's `checkpoint`.
+    ///
+    /// # Errors
+    /// - InvalidDrmFormat if the header can't be streamed or isn't AAXC
+    pub fn resume(key: AaxFileKey, head: &[u8], offset: u64) -> Result<Option<Self>> {
+        let head = &head[..head.len().min(offset as usize)];
+        let Some(layout) = AaxLayout::from_head(head)? else {
+            return Ok(None);
+        };
+        check_streamable(&layout)?;
+
+        // A checkpoint never splits a sample, but don't trust a torn write
+        let mut position = offset;
+        let next_sample = layout.samples.partition_point(|&(start, size)| start + u64::from(size) <= offset);
+        if let Some(&(start, _)) = layout.samples.get(next_sample) {
+            position = position.min(start);
+        }
+
+        Ok(Some(Self {
+            key,
+            head: Vec::new(),
+            layout: Some(layout),
+            position,
+            next_sample,
+            sample: Vec::new(),
+        }))
+    }
+
+    /// Output bytes that are final; a resumed download starts here
+    pub fn checkpoint(&self) -> u64 {
+        self.position - self.sample.len() as u64
+    }
+
+    /// Decrypt the next input bytes, appending what's ready to `output`
+    ///
+    /// # Errors
+    /// - InvalidDrmFormat if the header can't be streamed, isn't AAXC, or
+    ///   lists overlapping samples
+    pub fn push(&mut self, mut input: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        if self.layout.is_none() {
+            self.head.extend_from_slice(input);
+            let Some(layout) = AaxLayout::from_head(&self.head)? else {
+                return Ok(());
+            };
+            check_streamable(&layout)?;
+            self.layout = Some(layout);
+            let head = std::mem::take(&mut self.head);
+            return self.push(&head, output);
+        }
+        let samples = &self.layout.as_ref().unwrap().samples;
+
+        while !input.is_empty() {
+            let Some(&(start, size)) = samples.get(self.next_sample) else {
+                output.extend_from_slice(input);
+                self.position += input.len() as u64;
+                break;
+            };
+
+            if self.sample.is_empty() && self.position < start {
+                let len = (start - self.position).min(input.len() as u64) as usize;
+                output.extend_from_slice(&input[..len]);
+                self.position += len as u64;
+                input = &input[len..];
+                continue;
+            }
+            if self.sample.is_empty() && self.position > start {
+                return Err(LibationError::InvalidDrmFormat(format!(
+                    "Overlapping samples at offset {}",
+                    start
+                )));
+            }
+
+            let len = (size as usize - self.sample.len()).min(input.len());
+            self.sample.extend_from_slice(&input[..len]);
+            self.position += len as u64;
+            input = &input[len..];
+
+            if self.sample.len() == size as usize {
+                self.key.decrypt_sample(&mut self.sample);
+                output.append(&mut self.sample);
+                self.next_sample += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Atom type fields to overwrite once all input is in: (offset, new type)
+    ///
+    /// # Errors
+    /// - InvalidDrmFormat if the input ended before the last sample
+    pub fn finish(self) -> Result<Vec<(u64, [u8; 4])>> {
+        match self.layout {
+            Some(layout) if self.next_sample == layout.samples.len() => Ok(layout.patches),
+            _ => Err(truncated("mdat")),
+        }
+    }
+}
+
+/// Streamed files are AAXC: an `adrm` atom means AAX, whose key isn't known
+/// up front
+fn check_streamable(layout: &AaxLayout) -> Result<()> {
+    if layout.adrm.is_some() {
+        return Err(LibationError::InvalidDrmFormat(
+            "File has an adrm atom: AAX files are decrypted with activation bytes".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// AAX file decrypter (native AES-128 CBC)
 ///
 /// # C# Reference
 /// Similar functionality to FileLiberator/AudioDecodable.cs
@@ -95,7 +803,7 @@ impl AaxDecrypter {
         Self { activation_bytes }
     }
 
-    /// Decrypt an AAX file to M4B format using FFmpeg
+    /// Decrypt an AAX file to M4B format
     ///
     /// # C# Reference
     /// Corresponds to the decryption logic in AaxcDownloadConvertBase.cs
@@ -105,17 +813,11 @@ impl AaxDecrypter {
     /// * `output` - Path to the output M4B file
     ///
     /// # Errors
-    /// - FfmpegNotFound if FFmpeg is not installed
     /// - InvalidActivationBytes if the activation bytes are incorrect
     /// - FileNotFound if the input file doesn't exist
-    /// - FfmpegError for other FFmpeg errors
-    ///
-    /// # FFmpeg Command
-    /// ```bash
-    /// ffmpeg -activation_bytes <BYTES> -i input.aax -vn -c:a copy output.m4b
-    /// ```
+    /// - InvalidDrmFormat if the file isn't a well-formed AAX file
     pub async fn decrypt_file(&self, input: &Path, output: &Path) -> Result<()> {
-        self.decrypt_with_progress(input, output, |_| {}).await
+        self.decrypt_with_stats(input, output, |_| {}).await.map(|_| ())
     }
 
     /// Decrypt an AAX file with progress tracking
@@ -158,249 +860,71 @@ impl AaxDecrypter {
     where
         F: Fn(f32) + Send + 'static,
     {
-        // Check if FFmpeg is available
-        check_ffmpeg_available().await?;
-
-        // Validate input file exists
-        if !input.exists() {
-            return Err(LibationError::FileNotFound(input.display().to_string()));
-        }
-
-        // Build FFmpeg command
-        let activation_hex = self.activation_bytes.to_hex();
-        let mut cmd = build_ffmpeg_command(input, output, &activation_hex)?;
-
-        // Execute FFmpeg with progress tracking
-        execute_ffmpeg(&mut cmd, progress_callback).await
-    }
-
-    /// Get the activation bytes as a hex string
-    pub fn activation_bytes_hex(&self) -> String {
-        self.activation_bytes.to_hex()
-    }
-}
-
-/// Check if FFmpeg is available on the system
-///
-/// # Errors
-/// - FfmpegNotFound if FFmpeg is not in PATH
-async fn check_ffmpeg_available() -> Result<()> {
-    match Command::new("ffmpeg")
-        .arg("-version")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
+        self.decrypt_with_stats(input, output, move |progress| {
+            progress_callback(progress.fraction)
+        })
         .await
-    {
-        Ok(status) if status.success() => Ok(()),
-        Ok(_) => Err(LibationError::FfmpegNotFound),
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(LibationError::FfmpegNotFound),
-        Err(e) => Err(LibationError::FfmpegError(format!(
-            "Failed to check FFmpeg availability: {}",
-            e
-        ))),
+        .map(|_| ())
     }
-}
-
-/// Build FFmpeg command for AAX decryption
-///
-/// # C# Reference
-/// Corresponds to the FFmpeg command building in Libation
-///
-/// # Arguments
-/// * `input` - Input AAX file path
-/// * `output` - Output M4B file path
-/// * `activation_bytes` - Activation bytes as hex string (8 characters)
-///
-/// # Returns
-/// Configured Command ready to execute
-fn build_ffmpeg_command(input: &Path, output: &Path, activation_bytes: &str) -> Result<Command> {
-    let mut cmd = Command::new("ffmpeg");
-
-    cmd
-        // Overwrite output file if it exists
-        .arg("-y")
-        // Set activation bytes for decryption
-        .arg("-activation_bytes")
-        .arg(activation_bytes)
-        // Input file
-        .arg("-i")
-        .arg(input)
-        // No video (strip cover art - can be re-added later)
-        .arg("-vn")
-        // Copy audio codec without re-encoding (fast)
-        .arg("-c:a")
-        .arg("copy")
-        // Output file
-        .arg(output)
-        // Capture stderr for progress parsing
-        .stderr(Stdio::piped())
-        .stdout(Stdio::null());
-
-    Ok(cmd)
-}
 
-/// Execute FFmpeg command and parse progress
-///
-/// # C# Reference
-/// Similar to progress tracking in ConvertToMp3.cs
-///
-/// # Arguments
-/// * `cmd` - FFmpeg command to execute
-/// * `progress_callback` - Callback to report progress (0.0 to 1.0)
-///
-/// # Errors
-/// - FfmpegError if FFmpeg fails
-/// - InvalidActivationBytes if activation bytes are wrong
-///
-/// # FFmpeg Progress Format
-/// FFmpeg outputs progress on stderr in this format:
-/// ```text
-/// frame=  123 fps= 45 q=-1.0 size=   12345kB time=00:12:34.56 bitrate= 123.4kbits/s speed=45.6x
-/// ```
-async fn execute_ffmpeg<F>(cmd: &mut Command, progress_callback: F) -> Result<()>
-where
-    F: Fn(f32) + Send + 'static,
-{
-    let mut child = cmd.spawn().map_err(|e| {
-        LibationError::FfmpegError(format!("Failed to spawn FFmpeg process: {}", e))
-    })?;
-
-    // Capture stderr for progress parsing
-    let stderr = child
-        .stderr
-        .take()
-        .ok_or_else(|| LibationError::FfmpegError("Failed to capture FFmpeg stderr".to_string()))?;
-
-    let mut reader = BufReader::new(stderr).lines();
-    let mut error_output = String::new();
-    let mut duration_seconds: Option<f32> = None;
-
-    // Read FFmpeg output line by line
-    while let Some(line) = reader
-        .next_line()
-        .await
-        .map_err(|e| LibationError::FfmpegError(format!("Failed to read FFmpeg output: {}", e)))?
+    /// Decrypt an AAX file, reporting bytes processed and throughput
+    ///
+    /// Runs on the blocking thread pool. Progress is reported every few MB
+    /// and once more at completion.
+    ///
+    /// # Returns
+    /// Final progress, including the average throughput
+    ///
+    /// # Errors
+    /// Same as `decrypt_file`
+    pub async fn decrypt_with_stats<F>(
+        &self,
+        input: &Path,
+        output: &Path,
+        progress_callback: F,
+    ) -> Result<DecryptProgress>
+    where
+        F: FnMut(DecryptProgress) + Send + 'static,
     {
-        // Accumulate error output for debugging
-        error_output.push_str(&line);
-        error_output.push('\n');
-
-        // Parse duration from FFmpeg output (appears early in output)
-        // Format: "Duration: 01:23:45.67, start: 0.000000, bitrate: 64 kb/s"
-        if duration_seconds.is_none() {
-            if let Some(duration) = parse_duration_from_line(&line) {
-                duration_seconds = Some(duration);
-            }
-        }
-
-        // Parse progress from FFmpeg output
-        // Format: "time=00:12:34.56"
-        if let Some(elapsed) = parse_time_from_line(&line) {
-            if let Some(total) = duration_seconds {
-                let progress = (elapsed / total).min(1.0).max(0.0);
-                progress_callback(progress);
-            }
-        }
-    }
-
-    // Wait for FFmpeg to complete
-    let status = child.wait().await.map_err(|e| {
-        LibationError::FfmpegError(format!("Failed to wait for FFmpeg process: {}", e))
-    })?;
-
-    // Check exit status
-    if !status.success() {
-        // Check for specific error patterns
-        if error_output.contains("Invalid data found when processing input")
-            || error_output.contains("activation_bytes")
-        {
-            return Err(LibationError::InvalidActivationBytes(
-                "FFmpeg failed to decrypt AAX file. The activation bytes may be incorrect."
-                    .to_string(),
-            ));
-        }
-
-        return Err(LibationError::FfmpegError(format!(
-            "FFmpeg exited with status {}. Error output:\n{}",
-            status.code().unwrap_or(-1),
-            error_output
-        )));
-    }
-
-    // Report 100% completion
-    progress_callback(1.0);
-
-    Ok(())
-}
-
-/// Parse duration from FFmpeg output line
-///
-/// # Format
-/// ```text
-/// Duration: 01:23:45.67, start: 0.000000, bitrate: 64 kb/s
-/// ```
-///
-/// # Returns
-/// Duration in seconds, or None if not found
-fn parse_duration_from_line(line: &str) -> Option<f32> {
-    if !line.contains("Duration:") {
-        return None;
-    }
-
-    // Find the duration timestamp (HH:MM:SS.mm)
-    let parts: Vec<&str> = line.split(',').collect();
-    for part in parts {
-        if part.contains("Duration:") {
-            let duration_str = part.trim().strip_prefix("Duration:")?.trim();
-            return parse_timestamp(duration_str);
-        }
+        self.decrypt_cancellable(input, output, progress_callback, &CancellationToken::new())
+            .await
     }
 
-    None
-}
-
-/// Parse time from FFmpeg progress line
-///
-/// # Format
-/// ```text
-/// frame=  123 fps= 45 q=-1.0 size=   12345kB time=00:12:34.56 bitrate= 123.4kbits/s speed=45.6x
-/// ```
-///
-/// # Returns
-/// Elapsed time in seconds, or None if not found
-fn parse_time_from_line(line: &str) -> Option<f32> {
-    // Look for "time=" pattern
-    for part in line.split_whitespace() {
-        if let Some(time_str) = part.strip_prefix("time=") {
-            return parse_timestamp(time_str);
-        }
+    /// `decrypt_with_stats` that stops when `cancel` is cancelled
+    ///
+    /// A cancelled decryption removes the partial output; the input is
+    /// never modified.
+    ///
+    /// # Errors
+    /// Same as `decrypt_file`, or Cancelled
+    pub async fn decrypt_cancellable<F>(
+        &self,
+        input: &Path,
+        output: &Path,
+        progress_callback: F,
+        cancel: &CancellationToken,
+    ) -> Result<DecryptProgress>
+    where
+        F: FnMut(DecryptProgress) + Send + 'static,
+    {
+        let activation_bytes = self.activation_bytes;
+        decrypt_in_background(
+            input,
+            output,
+            move |adrm| unlock_aax(adrm, &activation_bytes),
+            progress_callback,
+            cancel,
+        )
+        .await
     }
 
-    None
-}
-
-/// Parse timestamp in HH:MM:SS.mm format to seconds
-///
-/// # Arguments
-/// * `timestamp` - Timestamp string (e.g., "01:23:45.67")
-///
-/// # Returns
-/// Total seconds as f32, or None if parsing fails
-fn parse_timestamp(timestamp: &str) -> Option<f32> {
-    let parts: Vec<&str> = timestamp.split(':').collect();
-    if parts.len() != 3 {
-        return None;
+    /// Get the activation bytes as a hex string
+    pub fn activation_bytes_hex(&self) -> String {
+        self.activation_bytes.to_hex()
     }
-
-    let hours: f32 = parts[0].parse().ok()?;
-    let minutes: f32 = parts[1].parse().ok()?;
-    let seconds: f32 = parts[2].parse().ok()?;
-
-    Some(hours * 3600.0 + minutes * 60.0 + seconds)
 }
 
-/// Verify activation bytes by attempting to decrypt a small portion of the file
+/// Verify activation bytes against an AAX file's DRM checksum
 ///
 /// # Arguments
 /// * `file` - Path to the AAX file
@@ -409,54 +933,29 @@ fn parse_timestamp(timestamp: &str) -> Option<f32> {
 /// # Returns
 /// - Ok(true) if activation bytes are valid
 /// - Ok(false) if activation bytes are invalid
-/// - Err if verification cannot be performed
+/// - Err if the file can't be read or isn't an AAX file
 ///
-/// # Note
-/// This function is not yet implemented. It would require:
-/// 1. Creating a temporary output file
-/// 2. Running FFmpeg with -t 5 to decrypt only first 5 seconds
-/// 3. Checking if output is valid
-/// 4. Cleaning up temporary file
+/// Only the `moov` atom is read; nothing is decrypted or written.
 pub async fn verify_activation_bytes(
     file: &Path,
     activation_bytes: &ActivationBytes,
 ) -> Result<bool> {
-    // Create temporary directory for test output
-    let temp_dir = std::env::temp_dir();
-    let temp_output = temp_dir.join(format!("aax_verify_{}.m4b", std::process::id()));
-
-    // Create decrypter
-    let decrypter = AaxDecrypter::new(*activation_bytes);
-
-    // Build FFmpeg command with duration limit (only decrypt first 5 seconds)
-    let activation_hex = activation_bytes.to_hex();
-    let mut cmd = Command::new("ffmpeg");
-
-    cmd.arg("-y")
-        .arg("-activation_bytes")
-        .arg(&activation_hex)
-        .arg("-i")
-        .arg(file)
-        .arg("-t")
-        .arg("5") // Only process first 5 seconds
-        .arg("-vn")
-        .arg("-c:a")
-        .arg("copy")
-        .arg(&temp_output)
-        .stderr(Stdio::piped())
-        .stdout(Stdio::null());
-
-    // Execute FFmpeg
-    let result = execute_ffmpeg(&mut cmd, |_| {}).await;
-
-    // Clean up temporary file
-    let _ = tokio::fs::remove_file(&temp_output).await;
-
-    match result {
-        Ok(_) => Ok(true),
-        Err(LibationError::InvalidActivationBytes(_)) => Ok(false),
-        Err(e) => Err(e),
-    }
+    let path: PathBuf = file.to_path_buf();
+    let activation_bytes = *activation_bytes;
+
+    tokio::task::spawn_blocking(move || {
+        let mut file = File::open(&path)?;
+        let len = file.metadata()?.len();
+        let layout = AaxLayout::read(&mut file, len)?;
+
+        match unlock_aax(layout.adrm.as_deref(), &activation_bytes) {
+            Ok(_) => Ok(true),
+            Err(LibationError::InvalidActivationBytes(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    })
+    .await
+    .map_err(|e| LibationError::DecryptionFailed(format!("Verification task failed: {}", e)))?
 }
 
 /// Check if a file is a valid AAX file
@@ -503,61 +1002,284 @@ pub async fn is_aax_file(path: &Path) -> Result<bool> {
     }
 }
 
+
+/// Encrypted files laid out like Audible downloads, for tests
+#[cfg(test)]
+pub(crate) mod test_files {
+    use aes::Aes128;
+    use cbc::cipher::{block_padding::NoPadding, BlockEncryptMut, KeyIvInit};
+
+    pub(crate) fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    pub(crate) fn full_atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        atom(kind, &[&[0u8; 4][..], body].concat())
+    }
+
+    pub(crate) fn encrypt(key: &[u8], iv: &[u8], data: &mut [u8]) {
+        let len = data.len() & !15;
+        cbc::Encryptor::<Aes128>::new_from_slices(key, iv)
+            .unwrap()
+            .encrypt_padded_mut::<NoPadding>(&mut data[..len], len)
+            .unwrap();
+    }
+
+    /// ftyp + mdat (two chunks with junk between) + moov, with the samples
+    /// encrypted under `file_key`/`file_iv` and an `adrm` atom if given
+    pub(crate) fn build_encrypted_file(
+        brand: &[u8; 4],
+        file_key: [u8; 16],
+        file_iv: [u8; 16],
+        adrm: Option<&[u8]>,
+        plain_samples: &[Vec<u8>],
+    ) -> Vec<u8> {
+        build(brand, file_key, file_iv, adrm, plain_samples, false)
+    }
+
+    /// `build_encrypted_file` with moov before mdat, as needed to decrypt
+    /// while downloading
+    pub(crate) fn build_streamable_file(
+        brand: &[u8; 4],
+        file_key: [u8; 16],
+        file_iv: [u8; 16],
+        adrm: Option<&[u8]>,
+        plain_samples: &[Vec<u8>],
+    ) -> Vec<u8> {
+        build(brand, file_key, file_iv, adrm, plain_samples, true)
+    }
+
+    fn build(
+        brand: &[u8; 4],
+        file_key: [u8; 16],
+        file_iv: [u8; 16],
+        adrm: Option<&[u8]>,
+        plain_samples: &[Vec<u8>],
+        moov_first: bool,
+    ) -> Vec<u8> {
+        let ftyp = atom(b"ftyp", &[&brand[..], b"\0\0\0\0", &brand[..], b"M4B mp42isom"].concat());
+        // Chunk offsets are fixed-width, so moov's size doesn't depend on them
+        let moov_len = if moov_first { build_moov(adrm, plain_samples, &[0, 0]).len() } else { 0 };
+        let mut mdat_body = Vec::new();
+        let mut chunk_offsets = Vec::new();
+        let mdat_start = (ftyp.len() + moov_len + 8) as u32;
+        for (i, sample) in plain_samples.iter().enumerate() {
+            if i == 0 || i == 3 {
+                if i == 3 {
+                    mdat_body.extend_from_slice(b"junk!");
+                }
+                chunk_offsets.push(mdat_start + mdat_body.len() as u32);
+            }
+            let mut encrypted = sample.clone();
+            encrypt(&file_key, &file_iv, &mut encrypted);
+            mdat_body.extend_from_slice(&encrypted);
+        }
+
+        let moov = build_moov(adrm, plain_samples, &chunk_offsets);
+        if moov_first {
+            [ftyp, moov, atom(b"mdat", &mdat_body)].concat()
+        } else {
+            [ftyp, atom(b"mdat", &mdat_body), moov].concat()
+        }
+    }
+
+    fn build_moov(adrm: Option<&[u8]>, plain_samples: &[Vec<u8>], chunk_offsets: &[u32]) -> Vec<u8> {
+        let mut entry_body = [&[0u8; 28][..], &atom(b"esds", &[1, 2, 3])].concat();
+        if let Some(adrm) = adrm {
+            entry_body.extend_from_slice(&atom(b"adrm", adrm));
+        }
+        let stsd = full_atom(b"stsd", &[&1u32.to_be_bytes()[..], &atom(b"aavd", &entry_body)].concat());
+        let mut stsz = [0u32, plain_samples.len() as u32].map(u32::to_be_bytes).concat();
+        for sample in plain_samples {
+            stsz.extend_from_slice(&(sample.len() as u32).to_be_bytes());
+        }
+        let stsc = [2u32, 1, 3, 1, 2, 1, 1].map(u32::to_be_bytes).concat();
+        let mut stco = (chunk_offsets.len() as u32).to_be_bytes().to_vec();
+        for offset in chunk_offsets {
+            stco.extend_from_slice(&offset.to_be_bytes());
+        }
+        let stbl = atom(
+            b"stbl",
+            &[
+                stsd,
+                full_atom(b"stsz", &stsz),
+                full_atom(b"stsc", &stsc),
+                full_atom(b"stco", &stco),
+            ]
+            .concat(),
+        );
+        let trak = atom(b"trak", &atom(b"mdia", &atom(b"minf", &stbl)));
+        atom(b"moov", &trak)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::test_files::*;
     use super::*;
 
-    #[test]
-    fn test_parse_timestamp() {
-        assert_eq!(parse_timestamp("00:00:01.50"), Some(1.5));
-        assert_eq!(parse_timestamp("00:01:00.00"), Some(60.0));
-        assert_eq!(parse_timestamp("01:00:00.00"), Some(3600.0));
-        assert_eq!(parse_timestamp("01:23:45.67"), Some(5025.67));
-    }
+    /// `adrm` payload locking `file_key` to `activation`, plus the resulting file IV
+    fn build_adrm(activation: &[u8; 4], file_key: [u8; 16]) -> (Vec<u8>, [u8; 16]) {
+        let ik = Sha1::new().chain_update(FIXED_KEY).chain_update(activation).finalize();
+        let iiv = Sha1::new()
+            .chain_update(FIXED_KEY)
+            .chain_update(ik)
+            .chain_update(activation)
+            .finalize();
 
-    #[test]
-    fn test_parse_timestamp_invalid() {
-        assert_eq!(parse_timestamp("invalid"), None);
-        assert_eq!(parse_timestamp("00:00"), None);
-        assert_eq!(parse_timestamp("00:00:00:00"), None);
+        let mut blob = [0u8; 56];
+        blob[..4].copy_from_slice(&[activation[3], activation[2], activation[1], activation[0]]);
+        blob[8..24].copy_from_slice(&file_key);
+        blob[26..42].copy_from_slice(&[0x5a; 16]);
+        let file_iv = Sha1::new()
+            .chain_update(&blob[26..42])
+            .chain_update(file_key)
+            .chain_update(FIXED_KEY)
+            .finalize();
+        encrypt(&ik[..16], &iiv[..16], &mut blob[..48]);
+
+        let checksum = Sha1::new().chain_update(&ik[..16]).chain_update(&iiv[..16]).finalize();
+        let payload = [&[0u8; 8][..], &blob, &[0u8; 4], &checksum].concat();
+        (payload, file_iv[..16].try_into().unwrap())
     }
 
-    #[test]
-    fn test_parse_time_from_line() {
-        let line = "frame=  123 fps= 45 q=-1.0 size=   12345kB time=00:12:34.56 bitrate= 123.4kbits/s speed=45.6x";
-        assert_eq!(parse_time_from_line(line), Some(754.56));
+    fn build_aax(activation: &[u8; 4], plain_samples: &[Vec<u8>]) -> Vec<u8> {
+        let file_key = [0x42u8; 16];
+        let (adrm, file_iv) = build_adrm(activation, file_key);
+        build_encrypted_file(b"aax ", file_key, file_iv, Some(&adrm), plain_samples)
     }
 
-    #[test]
-    fn test_parse_time_from_line_no_time() {
-        let line = "frame=  123 fps= 45 q=-1.0 size=   12345kB bitrate= 123.4kbits/s speed=45.6x";
-        assert_eq!(parse_time_from_line(line), None);
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
     }
 
-    #[test]
-    fn test_parse_duration_from_line() {
-        let line = "  Duration: 01:23:45.67, start: 0.000000, bitrate: 64 kb/s";
-        assert_eq!(parse_duration_from_line(line), Some(5025.67));
+    #[tokio::test]
+    async fn test_native_decrypt_round_trip() {
+        let activation = ActivationBytes::from_hex("1CEB00DA").unwrap();
+        let samples: Vec<Vec<u8>> = [100usize, 37, 64, 15]
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| (0..len).map(|b| (b * 7 + i) as u8).collect())
+            .collect();
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("book.aax");
+        let output = dir.path().join("book.m4b");
+        let aax = build_aax(activation.as_bytes(), &samples);
+        std::fs::write(&input, &aax).unwrap();
+
+        assert!(verify_activation_bytes(&input, &activation).await.unwrap());
+        let wrong = ActivationBytes::from_hex("DEADBEEF").unwrap();
+        assert!(!verify_activation_bytes(&input, &wrong).await.unwrap());
+
+        let done = AaxDecrypter::new(activation)
+            .decrypt_with_stats(&input, &output, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(done.fraction, 1.0);
+        assert_eq!(done.bytes_processed, aax.len() as u64);
+
+        let m4b = std::fs::read(&output).unwrap();
+        assert_eq!(m4b.len(), aax.len());
+        let plain = [&samples[0][..], &samples[1], &samples[2], b"junk!", &samples[3]].concat();
+        assert!(contains(&m4b, &plain));
+        assert!(contains(&m4b, b"mp4a") && contains(&m4b, b"free"));
+        assert!(!contains(&m4b, b"aavd") && !contains(&m4b, b"adrm"));
+        assert_eq!(&m4b[8..12], b"M4B ");
+
+        let err = AaxDecrypter::new(wrong)
+            .decrypt_file(&input, &output)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LibationError::InvalidActivationBytes(_)));
+
+        // A cancelled decryption leaves no partial output
+        std::fs::remove_file(&output).ok();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = AaxDecrypter::new(activation)
+            .decrypt_cancellable(&input, &output, |_| {}, &cancel)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LibationError::Cancelled));
+        assert!(!output.exists());
     }
 
     #[test]
-    fn test_parse_duration_from_line_no_duration() {
-        let line =
-            "frame=  123 fps= 45 q=-1.0 size=   12345kB time=00:12:34.56 bitrate= 123.4kbits/s";
-        assert_eq!(parse_duration_from_line(line), None);
+    fn test_stream_decrypter_resumes_at_checkpoint() {
+        let (key, iv) = ([0x11u8; 16], [0x22u8; 16]);
+        let samples: Vec<Vec<u8>> = [48usize, 70, 16, 9]
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| (0..len).map(|b| (b * 3 + i) as u8).collect())
+            .collect();
+        let input = build_streamable_file(b"aaxc", key, iv, None, &samples);
+
+        // Whole file in 7-byte pieces: same bytes as the one-pass decrypter
+        let mut decrypter = StreamDecrypter::new(AaxFileKey::new(key, iv));
+        let mut output = Vec::new();
+        for piece in input.chunks(7) {
+            decrypter.push(piece, &mut output).unwrap();
+            assert_eq!(decrypter.checkpoint(), output.len() as u64);
+        }
+        let patches = decrypter.finish().unwrap();
+        assert_eq!(output.len(), input.len());
+        let plain = [&samples[0][..], &samples[1], &samples[2], b"junk!", &samples[3]].concat();
+        assert!(contains(&output, &plain));
+        assert!(patches.contains(&(8, *b"M4B ")));
+
+        // Stop inside the second sample, then resume from the partial output
+        let mut decrypter = StreamDecrypter::new(AaxFileKey::new(key, iv));
+        let mut partial = Vec::new();
+        let cut = input.len() - 9 - 5 - 16 - 30;
+        decrypter.push(&input[..cut], &mut partial).unwrap();
+        let checkpoint = decrypter.checkpoint();
+        assert!(checkpoint < cut as u64);
+        assert!(decrypter.finish().is_err());
+
+        let mut resumed = StreamDecrypter::resume(AaxFileKey::new(key, iv), &partial, checkpoint)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed.checkpoint(), checkpoint);
+        resumed.push(&input[checkpoint as usize..], &mut partial).unwrap();
+        resumed.finish().unwrap();
+        assert_eq!(partial, output);
+
+        // An output ending inside moov can't be resumed
+        assert!(StreamDecrypter::resume(AaxFileKey::new(key, iv), &partial, 40).unwrap().is_none());
+
+        // moov after mdat can't be streamed
+        let trailing = build_encrypted_file(b"aaxc", key, iv, None, &samples);
+        let mut decrypter = StreamDecrypter::new(AaxFileKey::new(key, iv));
+        let err = decrypter.push(&trailing, &mut Vec::new()).unwrap_err();
+        assert!(matches!(err, LibationError::InvalidDrmFormat(_)));
     }
 
     #[test]
-    fn test_build_ffmpeg_command() {
-        let input = PathBuf::from("input.aax");
-        let output = PathBuf::from("output.m4b");
-        let activation_bytes = "1CEB00DA";
+    fn test_decrypt_sample_leaves_tail_clear() {
+        let key = AaxFileKey::new([1; 16], [2; 16]);
+        let mut sample = vec![0xabu8; 20];
+        key.decrypt_sample(&mut sample);
+        assert_ne!(&sample[..16], &[0xab; 16]);
+        assert_eq!(&sample[16..], &[0xab; 4]);
+
+        let mut short = vec![0xabu8; 15];
+        key.decrypt_sample(&mut short);
+        assert_eq!(short, vec![0xab; 15]);
+    }
 
-        let mut cmd = build_ffmpeg_command(&input, &output, activation_bytes).unwrap();
+    #[tokio::test]
+    async fn test_rejects_non_aax_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("plain.m4b");
+        std::fs::write(&input, atom(b"ftyp", b"M4B \0\0\0\0")).unwrap();
 
-        // Command should be constructed properly
-        // We can't easily test the full command here, but we can verify it doesn't panic
-        assert!(format!("{:?}", cmd).contains("ffmpeg"));
+        let activation = ActivationBytes::from_hex("1CEB00DA").unwrap();
+        let err = verify_activation_bytes(&input, &activation).await.unwrap_err();
+        assert!(matches!(err, LibationError::InvalidDrmFormat(_)));
     }
 
     #[test]
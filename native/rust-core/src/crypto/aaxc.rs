@@ -18,199 +18,257 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 
-//! AAXC file decryption (current Audible format with Widevine DRM)
+//! AAXC file decryption (current Audible download format)
 //!
 //! # Reference C# Sources
-//! - `AaxDecrypter/AaxcDownloadSingleConverter.cs` - Single-file AAXC handling
-//! - `AaxDecrypter/AaxcDownloadMultiConverter.cs` - Multi-part AAXC handling
-//! - `AaxDecrypter/AaxcDownloadConvertBase.cs` - Base class with shared logic
-//! - `AudibleUtilities/Widevine/Cdm.cs` - Widevine CDM implementation
-//! - `AudibleUtilities/Widevine/Cdm.Api.cs` - License request/response
-//! - `AudibleUtilities/Widevine/MpegDash.cs` - MPEG-DASH manifest parsing
+//! - `AaxDecrypter/AaxcDownloadConvertBase.cs` - Opens the file with the license keys
+//! - `AaxDecrypter/KeyData.cs` - Key and IV from the license voucher
+//! - AAXClean `Mp4File` / `AavdFilter` - Per-sample decryption and box rewriting
 //!
 //! # AAXC Format Details
-//! - Delivery: MPEG-DASH (chunked HTTP streaming)
-//! - DRM: Widevine (Google's DRM system)
-//! - Encryption: AES-128 CTR mode
-//! - Manifest: MPD XML file describing chunks
-//! - Chunks: Small encrypted audio segments
+//! - Container: MP4, major brand `aaxc`
+//! - Audio codec: AAC, sample entry `aavd` instead of `mp4a`
+//! - Encryption: AES-128 CBC per sample, IV reset for every sample,
+//!   trailing partial block left in the clear (same as AAX)
+//! - Key: 16-byte key and IV from the decrypted license voucher; there is
+//!   no `adrm` atom and no activation bytes
 //!
-//! # Decryption Process (from Libation)
-//! 1. Download MPD manifest (MPEG-DASH XML)
-//! 2. Parse manifest to get:
-//!    - Initialization segment (init.mp4)
-//!    - Media segments (chunk URLs)
-//!    - PSSH (Protection System Specific Header)
-//! 3. Request Widevine license:
-//!    - Create license challenge from PSSH
-//!    - POST to Audible license server
-//!    - Receive license with content keys
-//! 4. Parse license to extract:
-//!    - Content key (KID + Key)
-//!    - IV (Initialization Vector)
-//! 5. Download all chunks
-//! 6. Decrypt each chunk with content key
-//! 7. Concatenate decrypted chunks
-//! 8. Write to M4B file
+//! # Decryption Process
+//! Libation and the app used to hand the key and IV to FFmpeg
+//! (`-audible_key`/`-audible_iv`). The sample layout is the AAX one, so
+//! this reuses the native AAX pipeline with the license key:
+//! 1. Parse `moov` for the encrypted track's sample table
+//! 2. Stream the file to the output, decrypting each sample in place
+//! 3. Rename `aavd` → `mp4a` and rewrite the `aaxc` brand to `M4B ` so
+//!    players see plain AAC
 //!
-//! # Widevine CDM (Content Decryption Module)
-//! - Client-side library for Widevine DRM
-//! - Libation uses Python's pywidevine equivalent
-//! - Need Rust equivalent or FFI to existing library
-//!
-//! # MPEG-DASH Manifest (MPD)
-//! - XML format describing media presentation
-//! - Contains URLs for init segment and media chunks
-//! - PSSH box with Widevine data
-//! - See AudibleUtilities/Widevine/MpegDash.cs for parsing
+//! Widevine-protected (DASH) downloads are a different format; see
+//! `crypto::widevine`.
 
-use crate::error::Result;
+use crate::api::license::KeyData;
+use crate::cancel::CancellationToken;
+use crate::crypto::aax::{decrypt_in_background, AaxFileKey, DecryptProgress};
+use crate::error::{LibationError, Result};
+use crate::secret::SecretBytes;
 use std::path::Path;
 
-// TODO: Port MPEG-DASH manifest structures
-// See AudibleUtilities/Widevine/MpegDash.cs
-#[derive(Debug, Clone)]
-pub struct MpdManifest {
-    pub init_segment_url: String,
-    pub media_segments: Vec<MediaSegment>,
-    pub pssh: Vec<u8>, // Protection System Specific Header
-}
-
-#[derive(Debug, Clone)]
-pub struct MediaSegment {
-    pub url: String,
-    pub duration: f64,
-    pub size: Option<u64>,
-}
-
-// TODO: Port Widevine license structures
-// See AudibleUtilities/Widevine/Cdm.Api.cs
-#[derive(Debug)]
-pub struct WidevineLicense {
-    pub content_key_id: Vec<u8>,
-    pub content_key: Vec<u8>,
-    pub iv: Option<Vec<u8>>,
+/// AAXC file decrypter (native AES-128 CBC with the license key)
+///
+/// # Example
+/// ```no_run
+/// use rust_core::crypto::aaxc::AaxcDecrypter;
+/// use std::path::Path;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let decrypter = AaxcDecrypter::from_hex(
+///     "0123456789abcdef0123456789abcdef",
+///     "fedcba9876543210fedcba9876543210",
+/// )?;
+///
+/// decrypter.decrypt_file(
+///     Path::new("input.aaxc"),
+///     Path::new("output.m4b")
+/// ).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AaxcDecrypter {
+    key: [u8; 16],
+    iv: [u8; 16],
 }
 
-// TODO: Port AAXC decrypter
-// See AaxDecrypter/AaxcDownloadConvertBase.cs
-#[derive(Debug)]
-pub struct AaxcDecrypter {
-    // TODO: Add Widevine CDM instance
-    // This is the most complex part - need Widevine library
+impl std::fmt::Debug for AaxcDecrypter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The key unlocks the book; keep it out of logs
+        f.debug_struct("AaxcDecrypter").finish_non_exhaustive()
+    }
 }
 
 impl AaxcDecrypter {
-    pub fn new() -> Result<Self> {
-        // TODO: Initialize Widevine CDM
-        // - Load device keys (may need device provisioning)
-        // - Set up license request handler
-        unimplemented!("Initialize Widevine CDM")
+    /// Create a decrypter from the raw 16-byte key and IV
+    pub fn new(key: [u8; 16], iv: [u8; 16]) -> Self {
+        Self { key, iv }
     }
 
-    // TODO: Port download and decrypt flow
-    // See AaxDecrypter/AaxcDownloadSingleConverter.cs
-    pub async fn download_and_decrypt(
-        &self,
-        manifest_url: &str,
-        output: &Path,
-    ) -> Result<()> {
-        // Steps:
-        // 1. Download MPD manifest
-        // 2. Parse manifest (see parse_mpd_manifest)
-        // 3. Request Widevine license (see request_widevine_license)
-        // 4. Download init segment
-        // 5. Download all media chunks
-        // 6. Decrypt each chunk
-        // 7. Concatenate to output file
-        unimplemented!("Port AAXC download and decrypt")
+    /// Create a decrypter from hex-encoded key and IV, as stored with
+    /// download tasks and returned by the license bridge calls
+    ///
+    /// # Errors
+    /// - InvalidInput if either value isn't 32 hex characters
+    pub fn from_hex(key_hex: &str, iv_hex: &str) -> Result<Self> {
+        Self::from_key_data(&KeyData::from_hex(key_hex, Some(iv_hex))?)
     }
 
-    // TODO: Port MPD manifest parsing
-    // See AudibleUtilities/Widevine/MpegDash.cs
-    async fn parse_mpd_manifest(&self, manifest_url: &str) -> Result<MpdManifest> {
-        // Parse XML:
-        // - <MPD> root element
-        // - <Period><AdaptationSet><Representation>
-        // - <BaseURL> for segment URLs
-        // - <SegmentList><SegmentURL> for chunks
-        // - <ContentProtection> for PSSH
-        unimplemented!("Parse MPEG-DASH manifest")
+    /// Create a decrypter from license key data
+    ///
+    /// # Errors
+    /// - InvalidInput if the key data isn't a 16-byte key and IV (AAX
+    ///   licenses carry 4-byte activation bytes instead)
+    pub fn from_key_data(key_data: &KeyData) -> Result<Self> {
+        let key: [u8; 16] = key_data.key_part_1.expose_secret().try_into().map_err(|_| {
+            LibationError::InvalidInput(format!(
+                "AAXC key is {} bytes, expected 16",
+                key_data.key_part_1.len()
+            ))
+        })?;
+        let iv = key_data
+            .key_part_2
+            .as_ref()
+            .map(SecretBytes::expose_secret)
+            .ok_or_else(|| LibationError::InvalidInput("AAXC license has no IV".to_string()))?;
+        let iv: [u8; 16] = iv.try_into().map_err(|_| {
+            LibationError::InvalidInput(format!("AAXC IV is {} bytes, expected 16", iv.len()))
+        })?;
+
+        Ok(Self::new(key, iv))
+    }
+
+    /// Per-sample key for `StreamDecrypter`, to decrypt while downloading
+    pub fn file_key(&self) -> AaxFileKey {
+        AaxFileKey::new(self.key, self.iv)
     }
 
-    // TODO: Port Widevine license request
-    // See AudibleUtilities/Widevine/Cdm.Api.cs
-    async fn request_widevine_license(&self, pssh: &[u8]) -> Result<WidevineLicense> {
-        // Steps:
-        // 1. Create license challenge from PSSH
-        //    - Use Widevine CDM library
-        //    - Generate protobuf message
-        // 2. POST challenge to Audible license server
-        //    - URL: https://www.audible.com/widevine/license
-        //    - Headers: Content-Type: application/octet-stream
-        // 3. Parse license response
-        //    - Extract content keys
-        //    - Decrypt keys with device keys
-        // 4. Return WidevineLicense
-        unimplemented!("Request Widevine license")
+    /// Decrypt an AAXC file to M4B format
+    ///
+    /// # Errors
+    /// - FileNotFound if the input file doesn't exist
+    /// - InvalidDrmFormat if the file isn't a well-formed AAXC file
+    pub async fn decrypt_file(&self, input: &Path, output: &Path) -> Result<()> {
+        self.decrypt_with_stats(input, output, |_| {}).await.map(|_| ())
     }
 
-    // TODO: Port chunk decryption
-    // AES-128 CTR mode decryption
-    async fn decrypt_chunk(&self, encrypted: &[u8], license: &WidevineLicense) -> Result<Vec<u8>> {
-        // Use content_key and iv from license
-        // AES-128-CTR decryption
-        // Use Rust crypto crates (aes, ctr)
-        unimplemented!("Decrypt audio chunk")
+    /// Decrypt an AAXC file, reporting bytes processed and throughput
+    ///
+    /// Runs on the blocking thread pool. Progress is reported every few MB
+    /// and once more at completion.
+    ///
+    /// # Errors
+    /// Same as `decrypt_file`
+    pub async fn decrypt_with_stats<F>(
+        &self,
+        input: &Path,
+        output: &Path,
+        progress_callback: F,
+    ) -> Result<DecryptProgress>
+    where
+        F: FnMut(DecryptProgress) + Send + 'static,
+    {
+        self.decrypt_cancellable(input, output, progress_callback, &CancellationToken::new())
+            .await
     }
 
-    // TODO: Port progress tracking
-    // See AaxDecrypter/AverageSpeed.cs for speed calculation
-    pub async fn download_and_decrypt_with_progress<F>(
+    /// `decrypt_with_stats` that stops when `cancel` is cancelled
+    ///
+    /// A cancelled decryption removes the partial output; the input is
+    /// never modified.
+    ///
+    /// # Errors
+    /// Same as `decrypt_file`, or Cancelled
+    pub async fn decrypt_cancellable<F>(
         &self,
-        manifest_url: &str,
+        input: &Path,
         output: &Path,
         progress_callback: F,
-    ) -> Result<()>
+        cancel: &CancellationToken,
+    ) -> Result<DecryptProgress>
     where
-        F: Fn(f32, f64) + Send + 'static, // (progress, speed_mbps)
+        F: FnMut(DecryptProgress) + Send + 'static,
     {
-        unimplemented!("Port AAXC with progress")
+        let key = self.file_key();
+        // An `adrm` atom means an AAX file; its samples aren't under this key
+        decrypt_in_background(
+            input,
+            output,
+            move |adrm| match adrm {
+                Some(_) => Err(LibationError::InvalidDrmFormat(
+                    "File has an adrm atom: AAX files are decrypted with activation bytes"
+                        .to_string(),
+                )),
+                None => Ok(key),
+            },
+            progress_callback,
+            cancel,
+        )
+        .await
     }
 }
 
-// IMPLEMENTATION CHALLENGES:
-//
-// 1. Widevine CDM Library:
-//    - No mature Rust Widevine implementation exists
-//    - Options:
-//      a) Port pywidevine logic to Rust (significant effort)
-//      b) FFI to existing C/Python library
-//      c) Use wasm-widevine if available
-//    - Need device keys for CDM (may require extraction from Chrome/Android)
-//
-// 2. Protobuf:
-//    - Widevine license protocol uses protobuf
-//    - See AudibleUtilities/Widevine/LicenseProtocol.cs for message definitions
-//    - Use prost crate for Rust protobuf
-//
-// 3. MPEG-DASH:
-//    - XML parsing is straightforward (use quick-xml crate)
-//    - Need to handle BaseURL resolution
-//    - Segment timeline vs segment list
-//
-// 4. Chunk Download:
-//    - Can be parallelized (Libation does this)
-//    - Need retry logic for network errors
-//    - Progress tracking across all chunks
-//
-// 5. Testing:
-//    - Requires real AAXC content from Audible
-//    - Need test account with purchased books
-//    - Can't use mock data (DRM is real)
-//
-// RECOMMENDATION:
-// - Start with AAX (simpler, uses FFmpeg)
-// - Defer AAXC to later phase
-// - AAXC is the most complex part of the entire project
-// - May need specialized crypto expertise
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::aax::test_files::*;
+
+    const KEY: [u8; 16] = [0x11; 16];
+    const IV: [u8; 16] = [0x22; 16];
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[tokio::test]
+    async fn test_native_decrypt_round_trip() {
+        let samples: Vec<Vec<u8>> = [48usize, 70, 16, 9]
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| (0..len).map(|b| (b * 3 + i) as u8).collect())
+            .collect();
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("book.aaxc");
+        let output = dir.path().join("book.m4b");
+        let aaxc = build_encrypted_file(b"aaxc", KEY, IV, None, &samples);
+        std::fs::write(&input, &aaxc).unwrap();
+
+        let decrypter = AaxcDecrypter::from_hex(&hex::encode(KEY), &hex::encode(IV)).unwrap();
+        let done = decrypter.decrypt_with_stats(&input, &output, |_| {}).await.unwrap();
+        assert_eq!(done.fraction, 1.0);
+
+        let m4b = std::fs::read(&output).unwrap();
+        assert_eq!(m4b.len(), aaxc.len());
+        let plain = [&samples[0][..], &samples[1], &samples[2], b"junk!", &samples[3]].concat();
+        assert!(contains(&m4b, &plain));
+        assert!(contains(&m4b, b"mp4a") && !contains(&m4b, b"aavd"));
+        assert_eq!(&m4b[8..12], b"M4B ");
+
+        // A wrong key still "decrypts", just to garbage
+        std::fs::remove_file(&output).unwrap();
+        AaxcDecrypter::new([0x33; 16], IV)
+            .decrypt_file(&input, &output)
+            .await
+            .unwrap();
+        assert!(!contains(&std::fs::read(&output).unwrap(), &samples[0]));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_aax_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("book.aax");
+        let output = dir.path().join("book.m4b");
+        let aax = build_encrypted_file(b"aax ", KEY, IV, Some(&[0u8; 88]), &[vec![0; 32]]);
+        std::fs::write(&input, aax).unwrap();
+
+        let err = AaxcDecrypter::new(KEY, IV)
+            .decrypt_file(&input, &output)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LibationError::InvalidDrmFormat(_)));
+    }
+
+    #[test]
+    fn test_key_data_validation() {
+        let aax = KeyData::from_hex("1ceb00da", None).unwrap();
+        assert!(matches!(
+            AaxcDecrypter::from_key_data(&aax),
+            Err(LibationError::InvalidInput(_))
+        ));
+
+        let no_iv = KeyData {
+            key_part_1: KEY.to_vec().into(),
+            key_part_2: None,
+        };
+        assert!(AaxcDecrypter::from_key_data(&no_iv).is_err());
+        assert!(AaxcDecrypter::from_hex(&hex::encode(KEY), "00").is_err());
+        assert_eq!(format!("{:?}", AaxcDecrypter::new(KEY, IV)), "AaxcDecrypter { .. }");
+    }
+}
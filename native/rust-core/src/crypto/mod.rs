@@ -29,7 +29,8 @@
 //!
 //! # DRM Formats
 //! - **AAX** (legacy): AES encryption with activation bytes
-//! - **AAXC** (current): Widevine DRM with chunked MPEG-DASH delivery
+//! - **AAXC** (current): AAX layout with the key and IV from the license voucher
+//! - **Widevine**: chunked MPEG-DASH delivery (not supported yet)
 //! - **Unencrypted**: Direct MP3/M4B for podcasts
 
 pub mod activation;
@@ -48,9 +49,12 @@ pub use activation::{
 // Re-export commonly used types from AAX module
 pub use aax::{
     AaxDecrypter,
+    AaxFileKey,
+    DecryptProgress,
+    StreamDecrypter,
     is_aax_file,
     verify_activation_bytes,
 };
 
-// Re-export AAXC decrypter (placeholder for now)
+// Re-export AAXC decrypter
 pub use aaxc::AaxcDecrypter;
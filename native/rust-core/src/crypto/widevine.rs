@@ -65,13 +65,14 @@
 //! 8. Return keys for content decryption
 
 use crate::error::Result;
+use crate::secret::SecretBytes;
 use serde::{Deserialize, Serialize};
 
 // TODO: Port Device structure from Widevine/Device.cs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WidevinDevice {
     pub device_id: Vec<u8>,
-    pub device_private_key: Vec<u8>, // RSA private key (DER format)
+    pub device_private_key: SecretBytes, // RSA private key (DER format)
     pub device_client_id_blob: Vec<u8>, // Signed certificate
 }
 
@@ -130,7 +131,7 @@ impl ContentDecryptionModule {
 #[derive(Debug, Clone)]
 pub struct ContentKey {
     pub key_id: Vec<u8>, // KID
-    pub key: Vec<u8>,    // Decrypted content key
+    pub key: SecretBytes, // Decrypted content key
     pub key_type: KeyType,
 }
 
@@ -0,0 +1,223 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Adaptive resource limits from device thermal/battery hints
+//!
+//! The app reports battery level, charging state, and thermal status (from
+//! `PowerManager` on Android or `ProcessInfo.thermalState` on iOS). An
+//! `AdaptivePolicy` turns those hints into `ResourceLimits` that the
+//! download manager and converter apply:
+//! - Normal: full download concurrency, conversion allowed, FFmpeg picks threads
+//! - Constrained (hot or low battery): single stream, no conversion, one encoding thread
+//! - Critical thermal: no new downloads until the device cools down
+
+use serde::{Deserialize, Serialize};
+
+/// Device thermal status, ordered from coolest to hottest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThermalStatus {
+    #[default]
+    Nominal,
+    Fair,
+    Serious,
+    Critical,
+}
+
+/// Device conditions reported by the app
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConditions {
+    /// Battery level from 0.0 (empty) to 1.0 (full)
+    pub battery_level: f32,
+
+    /// Device is plugged in
+    pub is_charging: bool,
+
+    /// Current thermal status
+    #[serde(default)]
+    pub thermal_status: ThermalStatus,
+}
+
+impl Default for DeviceConditions {
+    fn default() -> Self {
+        Self {
+            battery_level: 1.0,
+            is_charging: true,
+            thermal_status: ThermalStatus::Nominal,
+        }
+    }
+}
+
+/// Configurable policy for mapping device conditions to limits
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptivePolicy {
+    /// Battery level below which work is throttled (when not charging)
+    pub low_battery_threshold: f32,
+
+    /// Thermal status at which work is throttled
+    pub throttle_thermal_status: ThermalStatus,
+
+    /// Concurrent downloads allowed while throttled
+    pub throttled_max_downloads: usize,
+
+    /// Encoding threads allowed while throttled
+    pub throttled_encoding_threads: u32,
+
+    /// Defer conversion while throttled
+    pub defer_conversion_when_throttled: bool,
+
+    /// Stop starting new downloads at critical thermal status
+    pub pause_on_critical_thermal: bool,
+}
+
+impl Default for AdaptivePolicy {
+    fn default() -> Self {
+        Self {
+            low_battery_threshold: 0.2,
+            throttle_thermal_status: ThermalStatus::Serious,
+            throttled_max_downloads: 1,
+            throttled_encoding_threads: 1,
+            defer_conversion_when_throttled: true,
+            pause_on_critical_thermal: true,
+        }
+    }
+}
+
+/// Limits derived from device conditions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Maximum downloads that may run at once
+    pub max_concurrent_downloads: usize,
+
+    /// Conversion (decrypt/transcode) may start now
+    pub allow_conversion: bool,
+
+    /// FFmpeg `-threads` value (None lets FFmpeg decide)
+    pub encoding_threads: Option<u32>,
+
+    /// Limits are reduced from the unconstrained defaults
+    pub throttled: bool,
+}
+
+impl AdaptivePolicy {
+    /// Compute limits for the given conditions
+    ///
+    /// # Arguments
+    /// * `conditions` - Latest device conditions
+    /// * `base_concurrency` - Concurrency used when unconstrained
+    pub fn limits(&self, conditions: &DeviceConditions, base_concurrency: usize) -> ResourceLimits {
+        if self.pause_on_critical_thermal && conditions.thermal_status == ThermalStatus::Critical {
+            return ResourceLimits {
+                max_concurrent_downloads: 0,
+                allow_conversion: false,
+                encoding_threads: Some(self.throttled_encoding_threads),
+                throttled: true,
+            };
+        }
+
+        let hot = conditions.thermal_status >= self.throttle_thermal_status;
+        let low_battery =
+            !conditions.is_charging && conditions.battery_level < self.low_battery_threshold;
+
+        if hot || low_battery {
+            ResourceLimits {
+                max_concurrent_downloads: base_concurrency.min(self.throttled_max_downloads),
+                allow_conversion: !self.defer_conversion_when_throttled,
+                encoding_threads: Some(self.throttled_encoding_threads),
+                throttled: true,
+            }
+        } else {
+            ResourceLimits {
+                max_concurrent_downloads: base_concurrency,
+                allow_conversion: true,
+                encoding_threads: None,
+                throttled: false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unconstrained_limits() {
+        let limits = AdaptivePolicy::default().limits(&DeviceConditions::default(), 3);
+        assert_eq!(limits.max_concurrent_downloads, 3);
+        assert!(limits.allow_conversion);
+        assert_eq!(limits.encoding_threads, None);
+        assert!(!limits.throttled);
+    }
+
+    #[test]
+    fn test_low_battery_throttles_unless_charging() {
+        let policy = AdaptivePolicy::default();
+        let mut conditions = DeviceConditions {
+            battery_level: 0.1,
+            is_charging: false,
+            thermal_status: ThermalStatus::Nominal,
+        };
+
+        let limits = policy.limits(&conditions, 3);
+        assert_eq!(limits.max_concurrent_downloads, 1);
+        assert!(!limits.allow_conversion);
+        assert_eq!(limits.encoding_threads, Some(1));
+
+        conditions.is_charging = true;
+        assert!(!policy.limits(&conditions, 3).throttled);
+    }
+
+    #[test]
+    fn test_thermal_limits() {
+        let policy = AdaptivePolicy::default();
+        let hot = DeviceConditions {
+            thermal_status: ThermalStatus::Serious,
+            ..Default::default()
+        };
+        assert_eq!(policy.limits(&hot, 3).max_concurrent_downloads, 1);
+
+        let critical = DeviceConditions {
+            thermal_status: ThermalStatus::Critical,
+            ..Default::default()
+        };
+        assert_eq!(policy.limits(&critical, 3).max_concurrent_downloads, 0);
+
+        let lenient = AdaptivePolicy {
+            pause_on_critical_thermal: false,
+            defer_conversion_when_throttled: false,
+            ..Default::default()
+        };
+        let limits = lenient.limits(&critical, 3);
+        assert_eq!(limits.max_concurrent_downloads, 1);
+        assert!(limits.allow_conversion);
+    }
+
+    #[test]
+    fn test_policy_deserializes_partial_json() {
+        let policy: AdaptivePolicy =
+            serde_json::from_str(r#"{"low_battery_threshold": 0.5}"#).unwrap();
+        assert_eq!(policy.low_battery_threshold, 0.5);
+        assert_eq!(policy.throttled_max_downloads, 1);
+
+        let conditions: DeviceConditions = serde_json::from_str(
+            r#"{"battery_level": 0.8, "is_charging": false, "thermal_status": "serious"}"#,
+        )
+        .unwrap();
+        assert_eq!(conditions.thermal_status, ThermalStatus::Serious);
+    }
+}
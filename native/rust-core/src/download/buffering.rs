@@ -0,0 +1,261 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Write buffering of download streams
+//!
+//! Downloads are written through a buffer of `buffer_size` bytes and flushed
+//! to disk at least every `flush_interval` bytes. The defaults are the C#
+//! constants (`DOWNLOAD_BUFF_SZ` = 8 KB, `DATA_FLUSH_SZ` = 1 MB), which mean
+//! thousands of small writes per second on a fast connection.
+//!
+//! A `BufferPolicy` sets the values for a manager or stream. With
+//! `auto_tune`, a `BufferTuner` measures throughput over
+//! `TUNE_WINDOW`-long windows and sizes the buffer to hold about 50 ms of
+//! data and the flush interval to about 2 s, within fixed bounds. Per-task
+//! `BufferOverrides` pin either value. The values in use are recorded on
+//! the task (`DownloadTask::buffering`) to help diagnose slow downloads.
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Write buffer size of the C# downloader
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Flush interval of the C# downloader
+pub const DEFAULT_FLUSH_INTERVAL: u64 = 1024 * 1024;
+
+/// Length of each throughput measurement when auto-tuning
+pub const TUNE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Bounds of auto-tuned buffer sizes
+const TUNED_BUFFER_RANGE: (usize, usize) = (DEFAULT_BUFFER_SIZE, 1024 * 1024);
+
+/// Bounds of auto-tuned flush intervals
+const TUNED_FLUSH_RANGE: (u64, u64) = (DEFAULT_FLUSH_INTERVAL, 32 * 1024 * 1024);
+
+/// Bounds of explicitly set values
+const BUFFER_SIZE_LIMITS: (usize, usize) = (1024, 16 * 1024 * 1024);
+const FLUSH_INTERVAL_LIMITS: (u64, u64) = (64 * 1024, 256 * 1024 * 1024);
+
+/// Buffering for a manager or stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BufferPolicy {
+    /// Write buffer size in bytes (the starting value when auto-tuning)
+    pub buffer_size: usize,
+    /// Bytes written between flushes (the starting value when auto-tuning)
+    pub flush_interval: u64,
+    /// Adjust both to the measured throughput
+    pub auto_tune: bool,
+}
+
+impl Default for BufferPolicy {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            auto_tune: true,
+        }
+    }
+}
+
+impl BufferPolicy {
+    /// # Errors
+    /// InvalidInput if a value is outside the supported range
+    pub fn validate(&self) -> Result<()> {
+        validate_buffer_size(self.buffer_size)?;
+        validate_flush_interval(self.flush_interval)
+    }
+}
+
+/// Per-task values that replace the manager's (and aren't auto-tuned)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferOverrides {
+    pub buffer_size: Option<usize>,
+    pub flush_interval: Option<u64>,
+}
+
+impl BufferOverrides {
+    /// # Errors
+    /// InvalidInput if a value is outside the supported range
+    pub fn validate(&self) -> Result<()> {
+        if let Some(size) = self.buffer_size {
+            validate_buffer_size(size)?;
+        }
+        if let Some(interval) = self.flush_interval {
+            validate_flush_interval(interval)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer_size.is_none() && self.flush_interval.is_none()
+    }
+}
+
+/// Buffering in use by a download session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectiveBuffering {
+    pub buffer_size: usize,
+    pub flush_interval: u64,
+    /// Some value follows the measured throughput
+    pub auto_tuned: bool,
+    /// Throughput of the latest measurement window
+    pub measured_bytes_per_sec: Option<u64>,
+}
+
+fn validate_buffer_size(size: usize) -> Result<()> {
+    let (min, max) = BUFFER_SIZE_LIMITS;
+    if !(min..=max).contains(&size) {
+        return Err(LibationError::InvalidInput(format!(
+            "Buffer size must be between {} and {} bytes, got {}",
+            min, max, size
+        )));
+    }
+    Ok(())
+}
+
+fn validate_flush_interval(interval: u64) -> Result<()> {
+    let (min, max) = FLUSH_INTERVAL_LIMITS;
+    if !(min..=max).contains(&interval) {
+        return Err(LibationError::InvalidInput(format!(
+            "Flush interval must be between {} and {} bytes, got {}",
+            min, max, interval
+        )));
+    }
+    Ok(())
+}
+
+/// Buffer holding about 50 ms of data, as a power of two
+fn tuned_buffer_size(bytes_per_sec: u64) -> usize {
+    let (min, max) = TUNED_BUFFER_RANGE;
+    ((bytes_per_sec / 20) as usize).next_power_of_two().clamp(min, max)
+}
+
+/// Flush interval of about 2 s of data, in whole MB
+fn tuned_flush_interval(bytes_per_sec: u64) -> u64 {
+    let (min, max) = TUNED_FLUSH_RANGE;
+    (bytes_per_sec * 2).div_ceil(1024 * 1024).saturating_mul(1024 * 1024).clamp(min, max)
+}
+
+/// Picks a session's buffering and retunes it from throughput
+#[derive(Debug, Clone)]
+pub struct BufferTuner {
+    overrides: BufferOverrides,
+    tune: bool,
+    current: EffectiveBuffering,
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl BufferTuner {
+    /// Start a session at `now` (when the response arrived)
+    pub fn new(policy: BufferPolicy, overrides: BufferOverrides, now: Instant) -> Self {
+        let tune = policy.auto_tune && (overrides.buffer_size.is_none() || overrides.flush_interval.is_none());
+        Self {
+            overrides,
+            tune,
+            current: EffectiveBuffering {
+                buffer_size: overrides.buffer_size.unwrap_or(policy.buffer_size),
+                flush_interval: overrides.flush_interval.unwrap_or(policy.flush_interval),
+                auto_tuned: tune,
+                measured_bytes_per_sec: None,
+            },
+            window_start: now,
+            window_bytes: 0,
+        }
+    }
+
+    /// Values to use now
+    pub fn current(&self) -> EffectiveBuffering {
+        self.current
+    }
+
+    /// Record `bytes` received at `now`
+    ///
+    /// # Returns
+    /// The new values when a finished window changed them
+    pub fn record(&mut self, bytes: u64, now: Instant) -> Option<EffectiveBuffering> {
+        if !self.tune {
+            return None;
+        }
+
+        self.window_bytes += bytes;
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < TUNE_WINDOW {
+            return None;
+        }
+
+        let bytes_per_sec = (self.window_bytes as f64 / elapsed.as_secs_f64()) as u64;
+        self.window_start = now;
+        self.window_bytes = 0;
+
+        let previous = self.current;
+        self.current.measured_bytes_per_sec = Some(bytes_per_sec);
+        if self.overrides.buffer_size.is_none() {
+            self.current.buffer_size = tuned_buffer_size(bytes_per_sec);
+        }
+        if self.overrides.flush_interval.is_none() {
+            self.current.flush_interval = tuned_flush_interval(bytes_per_sec);
+        }
+
+        let changed = self.current.buffer_size != previous.buffer_size
+            || self.current.flush_interval != previous.flush_interval;
+        changed.then_some(self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_tuner() {
+        let start = Instant::now();
+        let mut tuner = BufferTuner::new(BufferPolicy::default(), BufferOverrides::default(), start);
+        assert_eq!(tuner.current().buffer_size, DEFAULT_BUFFER_SIZE);
+
+        // Nothing changes until a window has passed
+        assert!(tuner.record(40 * 1024 * 1024, start + Duration::from_secs(1)).is_none());
+
+        // 20 MB/s: 1 MB buffer (capped), 40 MB of data per 2 s capped to 32 MB
+        let tuned = tuner.record(0, start + TUNE_WINDOW).unwrap();
+        assert_eq!(tuned.buffer_size, 1024 * 1024);
+        assert_eq!(tuned.flush_interval, 32 * 1024 * 1024);
+        assert_eq!(tuned.measured_bytes_per_sec, Some(20 * 1024 * 1024));
+
+        // 200 KB/s: 16 KB buffer, flush every MB (the floor)
+        let tuned = tuner.record(400 * 1024, start + TUNE_WINDOW * 2).unwrap();
+        assert_eq!(tuned.buffer_size, 16 * 1024);
+        assert_eq!(tuned.flush_interval, DEFAULT_FLUSH_INTERVAL);
+
+        // Overrides stay pinned; fully pinned sessions don't tune
+        let overrides = BufferOverrides { buffer_size: Some(64 * 1024), flush_interval: None };
+        let mut tuner = BufferTuner::new(BufferPolicy::default(), overrides, start);
+        let tuned = tuner.record(8 * 1024 * 1024, start + TUNE_WINDOW).unwrap();
+        assert_eq!(tuned.buffer_size, 64 * 1024);
+        assert_eq!(tuned.flush_interval, 8 * 1024 * 1024);
+
+        let overrides = BufferOverrides { buffer_size: Some(64 * 1024), flush_interval: Some(4 * 1024 * 1024) };
+        let mut tuner = BufferTuner::new(BufferPolicy::default(), overrides, start);
+        assert!(tuner.record(20 * 1024 * 1024, start + TUNE_WINDOW).is_none());
+        assert!(!tuner.current().auto_tuned);
+
+        assert!(BufferOverrides { buffer_size: Some(10), flush_interval: None }.validate().is_err());
+        assert!(BufferPolicy { flush_interval: 0, ..BufferPolicy::default() }.validate().is_err());
+    }
+}
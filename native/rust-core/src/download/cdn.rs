@@ -0,0 +1,170 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! CDN mirror selection
+//!
+//! Some ISPs throttle specific Audible CDNs. When a license offers more than
+//! one content URL, the extra URLs are stored with the task as mirrors
+//! (`PersistentDownloadManager::set_mirror_urls`). While downloading, a
+//! `ThroughputMonitor` measures speed over fixed windows; once every window
+//! for `CdnPolicy::slow_period` has been below `min_bytes_per_sec`, the
+//! worker resumes the download from the next untried mirror with a Range
+//! request. A mirror is also tried when the current URL can't be reached.
+//!
+//! A connection that delivers nothing for `CdnPolicy::stall_timeout` is
+//! dead even if it never errors. The worker drops it and asks again from
+//! the last byte written; after `max_stall_restarts` restarts on one URL
+//! it moves on to the next mirror, and when none is left the task fails
+//! with `DownloadStalled` so the app can fetch a new license
+//! (`PersistentDownloadManager::update_download_url`).
+//!
+//! The host that served each task is recorded as `DownloadTask::cdn_host`.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// When a CDN counts as too slow, or as stalled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CdnPolicy {
+    /// Length of each throughput measurement (the first one is the initial
+    /// measurement after connecting)
+    pub window: Duration,
+    /// Throughput below this is slow
+    pub min_bytes_per_sec: u64,
+    /// How long throughput must stay slow before switching mirrors
+    pub slow_period: Duration,
+    /// How long a connection may go without any bytes before the request
+    /// is sent again
+    pub stall_timeout: Duration,
+    /// Restarts after a stall on one URL before moving to a mirror
+    pub max_stall_restarts: u32,
+}
+
+impl Default for CdnPolicy {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(10),
+            min_bytes_per_sec: 64 * 1024,
+            slow_period: Duration::from_secs(30),
+            stall_timeout: Duration::from_secs(30),
+            max_stall_restarts: 2,
+        }
+    }
+}
+
+/// Tracks one connection's throughput against a `CdnPolicy`
+#[derive(Debug, Clone)]
+pub struct ThroughputMonitor {
+    policy: CdnPolicy,
+    window_start: Instant,
+    window_bytes: u64,
+    slow_since: Option<Instant>,
+}
+
+impl ThroughputMonitor {
+    /// Start measuring at `now` (when the response arrived)
+    pub fn new(policy: CdnPolicy, now: Instant) -> Self {
+        Self {
+            policy,
+            window_start: now,
+            window_bytes: 0,
+            slow_since: None,
+        }
+    }
+
+    /// Record `bytes` received at `now`; true once the connection has been
+    /// slow for the whole `slow_period`
+    pub fn record(&mut self, bytes: u64, now: Instant) -> bool {
+        self.window_bytes += bytes;
+
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < self.policy.window {
+            return false;
+        }
+
+        let bytes_per_sec = self.window_bytes as f64 / elapsed.as_secs_f64();
+        if bytes_per_sec < self.policy.min_bytes_per_sec as f64 {
+            self.slow_since.get_or_insert(self.window_start);
+        } else {
+            self.slow_since = None;
+        }
+        self.window_start = now;
+        self.window_bytes = 0;
+
+        self.slow_since
+            .is_some_and(|since| now.saturating_duration_since(since) >= self.policy.slow_period)
+    }
+}
+
+/// Host part of a download URL, recorded as the serving CDN
+pub fn cdn_host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url).ok()?.host_str().map(str::to_string)
+}
+
+/// First mirror whose host hasn't been tried yet
+///
+/// Mirrors on an already tried host are skipped, since they would hit the
+/// same throttled CDN.
+pub fn next_mirror<'a>(mirrors: &'a [String], tried: &[String]) -> Option<&'a str> {
+    let tried_hosts: Vec<Option<String>> = tried.iter().map(|url| cdn_host(url)).collect();
+    mirrors
+        .iter()
+        .find(|url| !tried.contains(url) && !tried_hosts.contains(&cdn_host(url)))
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throughput_monitor() {
+        let policy = CdnPolicy {
+            window: Duration::from_secs(10),
+            min_bytes_per_sec: 1000,
+            slow_period: Duration::from_secs(20),
+            ..CdnPolicy::default()
+        };
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut monitor = ThroughputMonitor::new(policy, start);
+
+        // Initial window is fast; a single slow window isn't enough
+        assert!(!monitor.record(20_000, at(10)));
+        assert!(!monitor.record(500, at(20)));
+        // A fast window in between resets the slow period
+        assert!(!monitor.record(50_000, at(30)));
+        assert!(!monitor.record(100, at(40)));
+        assert!(!monitor.record(100, at(45)));
+        assert!(monitor.record(100, at(60)));
+    }
+
+    #[test]
+    fn test_next_mirror() {
+        let mirrors = vec![
+            "https://dze1.cloudfront.net/book.aax?x=2".to_string(),
+            "https://cds.audible.com/book.aax".to_string(),
+            "https://cdn2.audible.com/book.aax".to_string(),
+        ];
+        let tried = vec!["https://dze1.cloudfront.net/book.aax?x=1".to_string()];
+        assert_eq!(next_mirror(&mirrors, &tried), Some("https://cds.audible.com/book.aax"));
+
+        let tried = vec![tried[0].clone(), mirrors[1].clone(), mirrors[2].clone()];
+        assert_eq!(next_mirror(&mirrors, &tried), None);
+        assert_eq!(cdn_host(&mirrors[1]).as_deref(), Some("cds.audible.com"));
+        assert_eq!(cdn_host("not a url"), None);
+    }
+}
@@ -0,0 +1,245 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-chunk hash manifest for resumable downloads
+//!
+//! Byte counts alone can't tell whether a partial file survived a multi-day
+//! pause intact: aggressive storage cleaners may truncate or zero the tail of
+//! cache files. The manifest records an xxh3 hash for every complete chunk
+//! written, so a resume can re-hash the tail of the partial file and fall
+//! back to the last chunk boundary that still matches.
+//!
+//! The manifest is stored as JSON in `DownloadTasks.chunk_manifest`; a NULL
+//! column means hashing is disabled for that task.
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+/// Default chunk size for hashing (4 MB)
+pub const DEFAULT_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Hashes of each complete chunk of a partial download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    /// Chunk size in bytes
+    pub chunk_size: u64,
+
+    /// xxh3-64 hash of each complete chunk, in file order
+    pub hashes: Vec<u64>,
+}
+
+impl ChunkManifest {
+    /// Create an empty manifest
+    pub fn new(chunk_size: u64) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Number of bytes covered by complete, hashed chunks
+    pub fn hashed_len(&self) -> u64 {
+        self.hashes.len() as u64 * self.chunk_size
+    }
+
+    /// Serialize to JSON for storage
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(LibationError::from)
+    }
+
+    /// Parse from stored JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| {
+            LibationError::InvalidData(format!("Invalid chunk manifest: {}", e))
+        })
+    }
+
+    /// Verify the tail of a partial file against the manifest
+    ///
+    /// Starting from the last complete chunk present on disk, chunks are
+    /// re-hashed backwards until one matches. Hashes past the matching chunk
+    /// are dropped from the manifest.
+    ///
+    /// # Arguments
+    /// * `path` - Partial download file
+    /// * `claimed_len` - Bytes the task believes were written
+    ///
+    /// # Returns
+    /// Byte offset that is safe to resume from (a chunk boundary). Any
+    /// unhashed partial chunk after it is re-downloaded.
+    pub async fn verify_file(&mut self, path: &Path, claimed_len: u64) -> Result<u64> {
+        let file_len = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                self.hashes.clear();
+                return Ok(0);
+            }
+        };
+
+        let usable_len = file_len.min(claimed_len);
+        let complete = (usable_len / self.chunk_size).min(self.hashes.len() as u64) as usize;
+        self.hashes.truncate(complete);
+
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buffer = vec![0u8; self.chunk_size as usize];
+
+        while let Some(&expected) = self.hashes.last() {
+            let index = self.hashes.len() as u64 - 1;
+            file.seek(std::io::SeekFrom::Start(index * self.chunk_size))
+                .await?;
+            file.read_exact(&mut buffer).await?;
+
+            if xxh3_64(&buffer) == expected {
+                break;
+            }
+            self.hashes.pop();
+        }
+
+        Ok(self.hashed_len())
+    }
+}
+
+/// Incremental hasher that extends a manifest as bytes are written
+pub struct ChunkHasher {
+    manifest: ChunkManifest,
+    state: Xxh3,
+    pending: u64,
+}
+
+impl ChunkHasher {
+    /// Start hashing a fresh download
+    pub fn new(chunk_size: u64) -> Self {
+        Self::from_manifest(ChunkManifest::new(chunk_size))
+    }
+
+    /// Continue hashing at the end of the manifest's hashed range
+    pub fn from_manifest(manifest: ChunkManifest) -> Self {
+        Self {
+            manifest,
+            state: Xxh3::new(),
+            pending: 0,
+        }
+    }
+
+    /// Feed newly written bytes
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let room = (self.manifest.chunk_size - self.pending) as usize;
+            let take = room.min(data.len());
+            self.state.update(&data[..take]);
+            self.pending += take as u64;
+            data = &data[take..];
+
+            if self.pending == self.manifest.chunk_size {
+                self.manifest.hashes.push(self.state.digest());
+                self.state.reset();
+                self.pending = 0;
+            }
+        }
+    }
+
+    /// Manifest of completed chunks so far
+    pub fn manifest(&self) -> &ChunkManifest {
+        &self.manifest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_hasher_splits_across_updates() {
+        let data = sample_data(10);
+        let mut hasher = ChunkHasher::new(4);
+        hasher.update(&data[..3]);
+        hasher.update(&data[3..10]);
+
+        let manifest = hasher.manifest();
+        assert_eq!(manifest.hashes.len(), 2);
+        assert_eq!(manifest.hashes[0], xxh3_64(&data[0..4]));
+        assert_eq!(manifest.hashes[1], xxh3_64(&data[4..8]));
+        assert_eq!(manifest.hashed_len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_verify_file_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial.aax");
+        let data = sample_data(10);
+        std::fs::write(&path, &data).unwrap();
+
+        let mut hasher = ChunkHasher::new(4);
+        hasher.update(&data);
+        let mut manifest = hasher.manifest().clone();
+
+        // Partial tail chunk (bytes 8..10) is re-downloaded
+        assert_eq!(manifest.verify_file(&path, 10).await.unwrap(), 8);
+        assert_eq!(manifest.hashes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_verify_file_detects_zeroed_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial.aax");
+        let mut data = sample_data(12);
+
+        let mut hasher = ChunkHasher::new(4);
+        hasher.update(&data);
+        let mut manifest = hasher.manifest().clone();
+
+        // Same length on disk, but the last chunk was zeroed
+        data[8..12].fill(0);
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(manifest.verify_file(&path, 12).await.unwrap(), 8);
+        assert_eq!(manifest.hashes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_verify_file_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial.aax");
+        let data = sample_data(12);
+
+        let mut hasher = ChunkHasher::new(4);
+        hasher.update(&data);
+        let mut manifest = hasher.manifest().clone();
+
+        std::fs::write(&path, &data[..6]).unwrap();
+        assert_eq!(manifest.verify_file(&path, 12).await.unwrap(), 4);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(manifest.verify_file(&path, 12).await.unwrap(), 0);
+        assert!(manifest.hashes.is_empty());
+    }
+
+    #[test]
+    fn test_manifest_json_round_trip() {
+        let mut hasher = ChunkHasher::new(DEFAULT_CHUNK_SIZE);
+        hasher.update(&[1, 2, 3]);
+        let json = hasher.manifest().to_json().unwrap();
+        assert_eq!(ChunkManifest::from_json(&json).unwrap(), *hasher.manifest());
+        assert!(ChunkManifest::from_json("not json").is_err());
+    }
+}
@@ -0,0 +1,267 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Companion content downloaded alongside a book
+//!
+//! Some titles come with bonus audio or an author interview, listed as
+//! child relationships of the catalog product, and some with a PDF
+//! supplement (synced into `Books.pdf_url`). `list_companion_assets`
+//! enumerates both; the user opts in with a `CompanionPolicy`, stored in
+//! Settings, and `select_companions` picks what to fetch.
+//!
+//! - Companion audio has its own ASIN and license and goes through the
+//!   download queue like any title. Its task is linked to the book with
+//!   `PersistentDownloadManager::set_companion_of`; once it completes, the
+//!   file is recorded as a BookFiles row of the book (`record_companion_task`).
+//! - PDFs are small, unencrypted and fetched directly (`download_companion_pdf`).
+//!
+//! # Reference C# Sources
+//! - `FileLiberator/DownloadPdf.cs` - PDF supplement download
+
+use crate::api::content::{CatalogProduct, CompanionAsset, CompanionKind};
+use crate::error::{LibationError, Result};
+use crate::storage::book_files::{add_book_file, BookFile, NewBookFile};
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::path::Path;
+
+const KEY_POLICY: &str = "downloads.companions";
+
+/// Which companion content to download with a book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompanionPolicy {
+    /// Download companion content at all (off until the user opts in)
+    pub enabled: bool,
+    /// Kinds to download
+    pub kinds: Vec<CompanionKind>,
+}
+
+impl Default for CompanionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kinds: vec![CompanionKind::BonusAudio, CompanionKind::AuthorInterview, CompanionKind::Pdf],
+        }
+    }
+}
+
+/// Stored companion policy (default if never set)
+pub async fn get_companion_policy(pool: &SqlitePool) -> Result<CompanionPolicy> {
+    Ok(get_json_setting(pool, KEY_POLICY).await?.unwrap_or_default())
+}
+
+/// Store the companion policy
+pub async fn set_companion_policy(pool: &SqlitePool, policy: &CompanionPolicy) -> Result<()> {
+    set_json_setting(pool, KEY_POLICY, policy).await
+}
+
+/// Companion audio of `product` plus its PDF supplement, if the library
+/// has one
+pub async fn list_companion_assets(pool: &SqlitePool, product: &CatalogProduct) -> Result<Vec<CompanionAsset>> {
+    let mut assets = product.companion_assets();
+    let pdf_url: Option<Option<String>> =
+        sqlx::query_scalar("SELECT pdf_url FROM Books WHERE audible_product_id = ?")
+            .bind(&product.asin)
+            .fetch_optional(pool)
+            .await?;
+    if let Some(url) = pdf_url.flatten().filter(|url| !url.is_empty()) {
+        assets.push(CompanionAsset {
+            kind: CompanionKind::Pdf,
+            asin: None,
+            url: Some(url),
+            sort: None,
+        });
+    }
+    Ok(assets)
+}
+
+/// Assets to download under `policy`
+///
+/// `opt_in` overrides the policy's `enabled` for one download; the kinds
+/// still apply.
+pub fn select_companions(policy: &CompanionPolicy, assets: &[CompanionAsset], opt_in: Option<bool>) -> Vec<CompanionAsset> {
+    if !opt_in.unwrap_or(policy.enabled) {
+        return Vec::new();
+    }
+    assets
+        .iter()
+        .filter(|asset| policy.kinds.contains(&asset.kind))
+        .cloned()
+        .collect()
+}
+
+/// Record the file of a completed companion download as a BookFiles row
+/// of its book
+///
+/// The file is the converted output for tasks with conversion keys and the
+/// download itself otherwise; the digest recorded while downloading is
+/// kept only for the latter. Returns None for tasks that aren't companion
+/// downloads or haven't completed.
+pub async fn record_companion_task(pool: &SqlitePool, task_id: &str) -> Result<Option<BookFile>> {
+    let row = sqlx::query(
+        "SELECT asin, status, companion_of, companion_kind, bytes_downloaded, \
+         CASE WHEN aaxc_key IS NULL THEN download_path ELSE output_path END AS file_path, \
+         CASE WHEN aaxc_key IS NULL THEN sha256 END AS sha256 \
+         FROM DownloadTasks WHERE task_id = ?",
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| LibationError::not_found(format!("Task not found: {}", task_id)))?;
+
+    let status: String = row.try_get("status")?;
+    let parent: Option<String> = row.try_get("companion_of")?;
+    let kind: Option<String> = row.try_get("companion_kind")?;
+    let (Some(parent), Some(kind)) = (parent, kind) else {
+        return Ok(None);
+    };
+    if status != "completed" {
+        return Ok(None);
+    }
+
+    let path: String = row.try_get("file_path")?;
+    let size_bytes = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata.len(),
+        Err(_) => row.try_get::<i64, _>("bytes_downloaded")? as u64,
+    };
+    let file = NewBookFile {
+        kind: kind.parse()?,
+        source_asin: Some(row.try_get("asin")?),
+        path,
+        size_bytes,
+        task_id: Some(task_id.to_string()),
+        sha256: row.try_get("sha256")?,
+    };
+    add_book_file(pool, &parent, &file).await.map(Some)
+}
+
+/// Download the PDF supplement at `url` to `destination` and record it as
+/// a BookFiles row of `asin`
+///
+/// # Errors
+/// - NetworkError if the request fails or isn't answered with success
+/// - IoError if the file can't be written
+/// - RecordNotFound if the book isn't in the database
+pub async fn download_companion_pdf(pool: &SqlitePool, asin: &str, url: &str, destination: &Path) -> Result<BookFile> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| LibationError::NetworkError {
+            message: format!("PDF request failed: {}", e),
+            is_transient: true,
+        })?;
+    if !response.status().is_success() {
+        return Err(LibationError::NetworkError {
+            message: format!("PDF download failed: HTTP {}", response.status()),
+            is_transient: response.status().is_server_error(),
+        });
+    }
+    let bytes = response.bytes().await.map_err(|e| LibationError::NetworkError {
+        message: format!("PDF download failed: {}", e),
+        is_transient: true,
+    })?;
+
+    if let Some(parent) = destination.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(destination, &bytes).await?;
+
+    let file = NewBookFile {
+        kind: CompanionKind::Pdf,
+        source_asin: None,
+        path: destination.to_string_lossy().to_string(),
+        size_bytes: bytes.len() as u64,
+        task_id: None,
+        sha256: Some(hex::encode(Sha256::digest(&bytes))),
+    };
+    add_book_file(pool, asin, &file).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::NewBook;
+    use crate::storage::queries::insert_book;
+    use crate::storage::Database;
+
+    fn asset(kind: CompanionKind, asin: &str) -> CompanionAsset {
+        CompanionAsset { kind, asin: Some(asin.to_string()), url: None, sort: None }
+    }
+
+    #[tokio::test]
+    async fn test_select_companions() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let assets = vec![
+            asset(CompanionKind::BonusAudio, "B0BONUS001"),
+            asset(CompanionKind::AuthorInterview, "B0INTERVW1"),
+        ];
+
+        // Off until opted in, per policy or per download
+        let policy = get_companion_policy(pool).await.unwrap();
+        assert!(select_companions(&policy, &assets, None).is_empty());
+        assert_eq!(select_companions(&policy, &assets, Some(true)).len(), 2);
+
+        let interviews_only = CompanionPolicy { enabled: true, kinds: vec![CompanionKind::AuthorInterview] };
+        set_companion_policy(pool, &interviews_only).await.unwrap();
+        let policy = get_companion_policy(pool).await.unwrap();
+        let selected = select_companions(&policy, &assets, None);
+        assert_eq!(selected, vec![assets[1].clone()]);
+        assert!(select_companions(&policy, &assets, Some(false)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_record_companion_task() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0MAINBOOK".to_string(), "Main".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("Interview.mp3");
+        std::fs::write(&path, b"interview").unwrap();
+
+        sqlx::query(
+            "INSERT INTO DownloadTasks (task_id, asin, title, status, bytes_downloaded, total_bytes, \
+             download_url, download_path, output_path, request_headers, created_at, companion_of, companion_kind) \
+             VALUES ('t1', 'B0INTERVW1', 'Interview', 'downloading', 0, 9, 'https://cdn', ?, '', '{}', \
+             '2025-03-01T12:00:00Z', 'B0MAINBOOK', 'author_interview')",
+        )
+        .bind(path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .unwrap();
+
+        // Nothing until the download completes
+        assert!(record_companion_task(pool, "t1").await.unwrap().is_none());
+        sqlx::query("UPDATE DownloadTasks SET status = 'completed', sha256 = ? WHERE task_id = 't1'")
+            .bind("cd".repeat(32))
+            .execute(pool)
+            .await
+            .unwrap();
+        let file = record_companion_task(pool, "t1").await.unwrap().unwrap();
+        assert_eq!(file.asin, "B0MAINBOOK");
+        assert_eq!(file.kind, CompanionKind::AuthorInterview);
+        assert_eq!(file.source_asin.as_deref(), Some("B0INTERVW1"));
+        assert_eq!(file.size_bytes, 9);
+        assert_eq!(file.sha256, Some("cd".repeat(32)));
+        assert!(record_companion_task(pool, "missing").await.is_err());
+    }
+}
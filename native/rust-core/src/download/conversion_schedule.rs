@@ -0,0 +1,176 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Deferred conversion scheduling (two-phase liberation)
+//!
+//! Downloading is cheap on CPU; decrypting and converting is not. A
+//! `ConversionPolicy` lets the user download on Wi-Fi during the day and
+//! convert later, e.g. only while charging or inside an overnight window.
+//!
+//! When a download finishes and the policy doesn't allow conversion right
+//! now, the task is parked in `TaskStatus::AwaitingConversion` instead of
+//! `Completed`. `PersistentDownloadManager::process_pending_conversions`
+//! hands parked tasks back to the app once the policy allows it.
+
+use crate::clock::Clock;
+use crate::download::adaptive::DeviceConditions;
+use serde::{Deserialize, Serialize};
+
+/// Local time-of-day window, in whole hours
+///
+/// `start_hour == end_hour` means the whole day. Windows may wrap past
+/// midnight (22 → 6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversionWindow {
+    /// First hour inside the window (0-23)
+    pub start_hour: u32,
+
+    /// First hour after the window (0-23)
+    pub end_hour: u32,
+}
+
+impl ConversionWindow {
+    /// Whether a local hour falls inside the window
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start_hour == self.end_hour {
+            true
+        } else if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// When conversion may run after a download completes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversionPolicy {
+    /// Park every finished download until `process_pending_conversions`
+    pub always_defer: bool,
+
+    /// Only convert while the device is plugged in
+    pub require_charging: bool,
+
+    /// Only convert inside this local time window
+    pub window: Option<ConversionWindow>,
+}
+
+impl ConversionPolicy {
+    /// Whether conversion may start now
+    ///
+    /// # Arguments
+    /// * `conditions` - Latest device conditions
+    /// * `adaptive_allows` - `ResourceLimits::allow_conversion` for those conditions
+    /// * `local_hour` - Current local hour (0-23)
+    pub fn allows(&self, conditions: &DeviceConditions, adaptive_allows: bool, local_hour: u32) -> bool {
+        adaptive_allows
+            && (!self.require_charging || conditions.is_charging)
+            && self.window.is_none_or(|window| window.contains(local_hour))
+    }
+}
+
+/// Policy plus the device state it is evaluated against
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConversionGate {
+    pub policy: ConversionPolicy,
+    pub conditions: DeviceConditions,
+    pub adaptive_allows: bool,
+}
+
+impl Default for ConversionGate {
+    fn default() -> Self {
+        Self {
+            policy: ConversionPolicy::default(),
+            conditions: DeviceConditions::default(),
+            adaptive_allows: true,
+        }
+    }
+}
+
+impl ConversionGate {
+    /// Whether a just-finished download should convert immediately
+    pub fn convert_on_completion(&self, clock: &dyn Clock) -> bool {
+        !self.policy.always_defer && self.allows_now(clock)
+    }
+
+    /// Whether parked conversions may start now
+    pub fn allows_now(&self, clock: &dyn Clock) -> bool {
+        self.policy.allows(&self.conditions, self.adaptive_allows, clock.local_hour())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_contains() {
+        let day = ConversionWindow { start_hour: 9, end_hour: 17 };
+        assert!(day.contains(9) && day.contains(16));
+        assert!(!day.contains(17) && !day.contains(3));
+
+        let night = ConversionWindow { start_hour: 22, end_hour: 6 };
+        assert!(night.contains(23) && night.contains(0) && night.contains(5));
+        assert!(!night.contains(6) && !night.contains(12));
+
+        assert!(ConversionWindow { start_hour: 4, end_hour: 4 }.contains(13));
+    }
+
+    #[test]
+    fn test_policy_allows() {
+        let unplugged = DeviceConditions {
+            is_charging: false,
+            ..Default::default()
+        };
+        let charging = DeviceConditions::default();
+
+        let immediate = ConversionPolicy::default();
+        assert!(immediate.allows(&unplugged, true, 12));
+        assert!(!immediate.allows(&unplugged, false, 12));
+
+        let on_charger_at_night = ConversionPolicy {
+            require_charging: true,
+            window: Some(ConversionWindow { start_hour: 22, end_hour: 6 }),
+            ..Default::default()
+        };
+        assert!(!on_charger_at_night.allows(&unplugged, true, 23));
+        assert!(!on_charger_at_night.allows(&charging, true, 12));
+        assert!(on_charger_at_night.allows(&charging, true, 23));
+    }
+
+    #[test]
+    fn test_gate_follows_clock() {
+        use crate::clock::TestClock;
+        use chrono::TimeZone;
+
+        let gate = ConversionGate {
+            policy: ConversionPolicy {
+                window: Some(ConversionWindow { start_hour: 22, end_hour: 6 }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let clock = TestClock::new(chrono::Utc.with_ymd_and_hms(2025, 3, 1, 21, 0, 0).unwrap());
+        assert!(!gate.allows_now(&clock));
+
+        clock.advance(chrono::Duration::hours(1));
+        assert!(gate.allows_now(&clock) && gate.convert_on_completion(&clock));
+
+        clock.advance(chrono::Duration::hours(8));
+        assert!(!gate.allows_now(&clock));
+    }
+}
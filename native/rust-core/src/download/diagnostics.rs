@@ -0,0 +1,219 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Runtime diagnostics and stall watchdog
+//!
+//! When a download silently stops moving, the app needs to tell a wedged
+//! runtime apart from a slow network. `PersistentDownloadManager::diagnostics`
+//! returns a snapshot of:
+//! - Tokio runtime stats (workers, alive tasks)
+//! - Download workers, queued tasks, and free semaphore permits
+//! - SQLite pool usage
+//! - Tasks the watchdog considers stalled
+//!
+//! The `ProgressWatchdog` records the last time each active task made
+//! progress. A task with no progress for longer than the threshold is
+//! reported as stalled and flagged in task listings.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// Default time without progress before a task is considered stalled
+pub const DEFAULT_STALL_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+
+/// Tokio runtime statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeStats {
+    /// Worker threads in the runtime
+    pub workers: usize,
+
+    /// Tasks spawned and not yet completed (all subsystems)
+    pub alive_tasks: usize,
+}
+
+impl RuntimeStats {
+    /// Stats for the runtime the caller is running on (None outside a runtime)
+    pub fn current() -> Option<Self> {
+        let metrics = tokio::runtime::Handle::try_current().ok()?.metrics();
+        Some(Self {
+            workers: metrics.num_workers(),
+            alive_tasks: metrics.num_alive_tasks(),
+        })
+    }
+}
+
+/// SQLite connection pool usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStats {
+    /// Open connections
+    pub size: u32,
+
+    /// Open connections not currently in use
+    pub idle: usize,
+}
+
+impl PoolStats {
+    pub fn from_pool(pool: &SqlitePool) -> Self {
+        Self {
+            size: pool.size(),
+            idle: pool.num_idle(),
+        }
+    }
+}
+
+/// Active task with no recent progress
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StalledTask {
+    pub task_id: String,
+
+    /// Seconds since the task last made progress
+    pub seconds_without_progress: u64,
+}
+
+/// Download subsystem state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadDiagnostics {
+    /// Download workers spawned (running or waiting for a permit)
+    pub active_workers: usize,
+
+    /// Tasks waiting in the queue
+    pub queued_tasks: i64,
+
+    /// Semaphore permits not held by a worker
+    pub permits_available: usize,
+
+    /// Configured maximum concurrency
+    pub max_concurrent: usize,
+
+    /// Concurrency allowed by device conditions
+    pub concurrency_limit: usize,
+
+    /// Stall threshold in seconds
+    pub stall_threshold_seconds: u64,
+
+    /// Tasks flagged by the watchdog
+    pub stalled_tasks: Vec<StalledTask>,
+}
+
+/// Diagnostics snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostics {
+    /// None if called outside a Tokio runtime
+    pub runtime: Option<RuntimeStats>,
+    pub downloads: DownloadDiagnostics,
+    pub database: PoolStats,
+}
+
+/// Tracks the last progress time of active tasks
+pub struct ProgressWatchdog {
+    threshold: Duration,
+    last_progress: Mutex<HashMap<String, Instant>>,
+}
+
+impl ProgressWatchdog {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            last_progress: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Time without progress before a task is considered stalled
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Record progress (or the start of tracking) for a task
+    pub fn touch(&self, task_id: &str) {
+        let mut last = self.last_progress.lock().unwrap();
+        match last.get_mut(task_id) {
+            Some(instant) => *instant = Instant::now(),
+            None => {
+                last.insert(task_id.to_string(), Instant::now());
+            }
+        }
+    }
+
+    /// Stop tracking a task (finished, paused, or cancelled)
+    pub fn remove(&self, task_id: &str) {
+        self.last_progress.lock().unwrap().remove(task_id);
+    }
+
+    /// Whether a tracked task has exceeded the threshold
+    pub fn is_stalled(&self, task_id: &str) -> bool {
+        self.last_progress
+            .lock()
+            .unwrap()
+            .get(task_id)
+            .is_some_and(|instant| instant.elapsed() >= self.threshold)
+    }
+
+    /// All tracked tasks that have exceeded the threshold
+    pub fn stalled(&self) -> Vec<StalledTask> {
+        let mut stalled: Vec<StalledTask> = self
+            .last_progress
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, instant)| instant.elapsed() >= self.threshold)
+            .map(|(task_id, instant)| StalledTask {
+                task_id: task_id.clone(),
+                seconds_without_progress: instant.elapsed().as_secs(),
+            })
+            .collect();
+        stalled.sort_by_key(|task| std::cmp::Reverse(task.seconds_without_progress));
+        stalled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watchdog_flags_tasks_without_progress() {
+        let watchdog = ProgressWatchdog::new(Duration::from_secs(60));
+        watchdog.touch("slow");
+        watchdog.touch("busy");
+
+        tokio::time::advance(Duration::from_secs(45)).await;
+        watchdog.touch("busy");
+        assert!(watchdog.stalled().is_empty());
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(watchdog.is_stalled("slow"));
+        assert!(!watchdog.is_stalled("busy"));
+        assert_eq!(
+            watchdog.stalled(),
+            vec![StalledTask {
+                task_id: "slow".to_string(),
+                seconds_without_progress: 75,
+            }]
+        );
+
+        watchdog.remove("slow");
+        assert!(!watchdog.is_stalled("slow"));
+    }
+
+    #[tokio::test]
+    async fn test_runtime_stats() {
+        let stats = RuntimeStats::current().expect("inside a runtime");
+        assert!(stats.workers >= 1);
+    }
+}
@@ -0,0 +1,189 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Work chained onto a download
+//!
+//! A download can be enqueued with a `FollowUp` that the manager runs
+//! itself once the file is complete: decrypt it, then tag the book. The
+//! descriptor is stored with the task (`DownloadTasks.follow_up`), so the
+//! chain doesn't depend on the app watching for completion:
+//!
+//! - A download interrupted by an app restart resumes and still runs its
+//!   follow-up when it finishes.
+//! - A follow-up interrupted mid-way is run again from the start by
+//!   `resume_all_pending` (decryption rewrites the output; tagging is
+//!   idempotent).
+//! - A download parked by the conversion policy runs its follow-up from
+//!   `process_pending_conversions`.
+//!
+//! The task stays `decrypting` while the follow-up runs and becomes
+//! `completed` (with `output_path` set to the decrypted file) or `failed`
+//! once it ends. Its decryption keys are stored only as the task's
+//! conversion keys, so a failed follow-up can be retried like any
+//! conversion.
+
+use crate::cancel::{CancellationToken, JobKind};
+use crate::crypto::aaxc::AaxcDecrypter;
+use crate::crypto::DecryptProgress;
+use crate::error::{LibationError, Result};
+use crate::events;
+use crate::secret::SecretString;
+use crate::storage::tags::{get_book_tags, normalize_tag, set_book_tags};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+/// Work the download manager runs once a download completes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowUp {
+    /// Decrypt the downloaded AAXC file
+    #[serde(default)]
+    pub decrypt: Option<DecryptStep>,
+    /// Library tags added to the book once the file is ready
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Decrypting a finished download
+///
+/// The keys are read but never serialized: the stored descriptor and the
+/// task JSON handed to the app leave them out, and the task fills them in
+/// from its conversion keys when it is loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecryptStep {
+    /// Hex key from the license
+    #[serde(default, skip_serializing)]
+    pub aaxc_key: SecretString,
+    /// Hex IV from the license
+    #[serde(default, skip_serializing)]
+    pub aaxc_iv: SecretString,
+    /// Where the M4B is written (default: the task's `output_path`)
+    #[serde(default)]
+    pub output_path: Option<String>,
+    /// Remove the encrypted download once decrypted
+    #[serde(default)]
+    pub delete_download: bool,
+}
+
+impl FollowUp {
+    /// Nothing to do
+    pub fn is_empty(&self) -> bool {
+        self.decrypt.is_none() && self.tags.is_empty()
+    }
+
+    /// Check the descriptor before it is stored
+    ///
+    /// # Errors
+    /// InvalidInput for malformed keys or a tag without valid characters
+    pub fn validate(&self) -> Result<()> {
+        if let Some(decrypt) = &self.decrypt {
+            AaxcDecrypter::from_hex(decrypt.aaxc_key.expose_secret(), decrypt.aaxc_iv.expose_secret())?;
+        }
+        if let Some(tag) = self.tags.iter().find(|tag| normalize_tag(tag).is_none()) {
+            return Err(LibationError::invalid_input(format!("Invalid tag: {:?}", tag)));
+        }
+        Ok(())
+    }
+}
+
+/// Run a task's follow-up on its finished download
+///
+/// Decryption progress is emitted as `decryption` progress of the task.
+///
+/// # Returns
+/// Path of the finished file: the decrypted output, or the download when
+/// there is nothing to decrypt
+///
+/// # Errors
+/// Decryption errors, Cancelled, or a database error while tagging
+pub async fn run_follow_up(
+    pool: &SqlitePool,
+    task_id: &str,
+    asin: &str,
+    download_path: &str,
+    output_path: &str,
+    follow_up: &FollowUp,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    let mut finished = download_path.to_string();
+
+    if let Some(decrypt) = &follow_up.decrypt {
+        let decrypter = AaxcDecrypter::from_hex(decrypt.aaxc_key.expose_secret(), decrypt.aaxc_iv.expose_secret())?;
+        let output = decrypt.output_path.as_deref().unwrap_or(output_path);
+
+        let (id, book) = (task_id.to_string(), asin.to_string());
+        let on_progress = move |progress: DecryptProgress| {
+            events::emit_progress(events::ProgressEvent::new(
+                JobKind::Decryption,
+                &id,
+                Some(&book),
+                progress.bytes_processed,
+                progress.total_bytes,
+            ));
+        };
+        decrypter
+            .decrypt_cancellable(Path::new(download_path), Path::new(output), on_progress, cancel)
+            .await?;
+
+        if decrypt.delete_download {
+            tokio::fs::remove_file(download_path).await?;
+        }
+        finished = output.to_string();
+    }
+
+    if !follow_up.tags.is_empty() {
+        let book_id: Option<i64> = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+            .bind(asin)
+            .fetch_optional(pool)
+            .await?;
+        // Titles downloaded without being in the library have nothing to tag
+        if let Some(book_id) = book_id {
+            let mut tags = get_book_tags(pool, book_id).await?;
+            tags.extend(follow_up.tags.iter().cloned());
+            set_book_tags(pool, book_id, &tags).await?;
+        }
+    }
+
+    Ok(finished)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_follow_up() {
+        let decrypt = DecryptStep {
+            aaxc_key: "00".repeat(16).into(),
+            aaxc_iv: "11".repeat(16).into(),
+            output_path: None,
+            delete_download: false,
+        };
+        let follow_up = FollowUp { decrypt: Some(decrypt.clone()), tags: vec!["Downloaded".to_string()] };
+        assert!(follow_up.validate().is_ok());
+        assert!(!follow_up.is_empty());
+        assert!(FollowUp::default().is_empty());
+
+        let bad_key = FollowUp {
+            decrypt: Some(DecryptStep { aaxc_key: "xyz".into(), ..decrypt }),
+            tags: Vec::new(),
+        };
+        assert!(bad_key.validate().is_err());
+
+        let bad_tag = FollowUp { decrypt: None, tags: vec!["!!!".to_string()] };
+        assert!(bad_tag.validate().is_err());
+    }
+}
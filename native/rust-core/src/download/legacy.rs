@@ -0,0 +1,182 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Discovery of legacy NetworkFileStream downloads
+//!
+//! Before the persistent queue, each partial download kept its state in a
+//! `{file}.download_state.json` next to the partial file (see
+//! `StreamState::state_file_path`). Those files are invisible to
+//! `PersistentDownloadManager`, so after an app update the partial data is
+//! orphaned. `PersistentDownloadManager::import_legacy_downloads` uses this
+//! module to find them and turn each one into a queued `DownloadTask` that
+//! resumes from the bytes already on disk.
+
+use crate::download::stream::StreamState;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Suffix of legacy state files
+pub const LEGACY_STATE_SUFFIX: &str = ".download_state.json";
+
+/// A legacy state file and the partial download it describes
+#[derive(Debug, Clone)]
+pub struct LegacyDownload {
+    /// Path of the state JSON
+    pub state_path: PathBuf,
+
+    /// Parsed state
+    pub state: StreamState,
+
+    /// ASIN derived from the partial file name (`{asin}.aax`)
+    pub asin: String,
+
+    /// Bytes usable for resume: the saved position, capped at the file size
+    pub resume_bytes: u64,
+}
+
+impl LegacyDownload {
+    /// Output path for the converted audiobook (`{asin}.m4b` next to the partial file)
+    pub fn output_path(&self) -> PathBuf {
+        self.state.save_file_path.with_extension("m4b")
+    }
+}
+
+/// Legacy state file that could not be imported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedLegacyDownload {
+    pub state_path: String,
+    pub reason: String,
+}
+
+/// Result of importing legacy downloads
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyImportReport {
+    /// Task ids created for imported downloads
+    pub imported: Vec<String>,
+
+    /// State files left alone or discarded, with the reason
+    pub skipped: Vec<SkippedLegacyDownload>,
+}
+
+/// Scan a directory (non-recursively) for legacy state files
+///
+/// # Returns
+/// Downloads whose partial file still exists, plus state files that were
+/// unreadable or point at a missing file. A missing directory yields an
+/// empty result.
+pub async fn discover_legacy_downloads(
+    dir: &Path,
+) -> Result<(Vec<LegacyDownload>, Vec<SkippedLegacyDownload>)> {
+    let mut found = Vec::new();
+    let mut skipped = Vec::new();
+
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((found, skipped)),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let state_path = entry.path();
+        let is_state_file = state_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(LEGACY_STATE_SUFFIX));
+        if !is_state_file {
+            continue;
+        }
+
+        let skip = |reason: String| SkippedLegacyDownload {
+            state_path: state_path.to_string_lossy().to_string(),
+            reason,
+        };
+
+        let state = match StreamState::load(&state_path).await {
+            Ok(state) => state,
+            Err(e) => {
+                skipped.push(skip(format!("Unreadable state file: {}", e)));
+                continue;
+            }
+        };
+
+        let file_len = match tokio::fs::metadata(&state.save_file_path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                skipped.push(skip("Partial file is missing".to_string()));
+                continue;
+            }
+        };
+
+        let asin = match state.save_file_path.file_stem().and_then(|s| s.to_str()) {
+            Some(stem) if !stem.is_empty() => stem.to_string(),
+            _ => {
+                skipped.push(skip("Cannot derive ASIN from file name".to_string()));
+                continue;
+            }
+        };
+
+        let resume_bytes = state.write_position.min(file_len);
+        found.push(LegacyDownload {
+            state_path,
+            state,
+            asin,
+            resume_bytes,
+        });
+    }
+
+    found.sort_by(|a, b| a.state_path.cmp(&b.state_path));
+    Ok((found, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_discover_legacy_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let partial = dir.path().join("B0LEGACY1.aax");
+        std::fs::write(&partial, vec![7u8; 500]).unwrap();
+        let mut state = StreamState::new("https://cdn.example.com/b1".to_string(), partial);
+        state.content_length = 1000;
+        state.write_position = 600;
+        state.save().await.unwrap();
+
+        let orphan = StreamState::new(
+            "https://cdn.example.com/b2".to_string(),
+            dir.path().join("B0LEGACY2.aax"),
+        );
+        orphan.save().await.unwrap();
+
+        std::fs::write(dir.path().join("notes.json"), "{}").unwrap();
+
+        let (found, skipped) = discover_legacy_downloads(dir.path()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].asin, "B0LEGACY1");
+        // Saved position is capped at what actually reached the disk
+        assert_eq!(found[0].resume_bytes, 500);
+        assert_eq!(found[0].output_path(), dir.path().join("B0LEGACY1.m4b"));
+
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].reason, "Partial file is missing");
+
+        let missing = dir.path().join("missing");
+        let (found, skipped) = discover_legacy_downloads(&missing).await.unwrap();
+        assert!(found.is_empty() && skipped.is_empty());
+    }
+}
@@ -44,6 +44,7 @@
 //! - Saves download state to JSON for resume
 //! - Supports HTTP range requests for resume
 //! - Provides Stream interface for reading while downloading
+//! - Optionally decrypts AAXC while downloading, writing the M4B directly
 //!
 //! ### PersistentDownloadManager (persistent_manager.rs)
 //! High-level download orchestration with persistent queue that:
@@ -53,6 +54,21 @@
 //! - Provides real-time progress tracking
 //! - Automatically recovers from app restarts
 //! - Supports cancellation with proper task cleanup
+//! - Optionally hashes 4 MB chunks so resumes can detect silent truncation
+//! - Adapts concurrency to device thermal/battery hints (adaptive.rs)
+//! - Watchdog flags tasks without progress; diagnostics snapshot (diagnostics.rs)
+//! - Imports orphaned legacy state JSON downloads on startup (legacy.rs)
+//! - Defers decrypt/convert to a charging-only or scheduled window (conversion_schedule.rs)
+//! - Fails resumes of expired signed URLs up front (url_expiry.rs)
+//! - Exports/imports the queue for another device, re-requesting licenses (queue_transfer.rs)
+//! - Switches to a mirror CDN on sustained slow throughput (cdn.rs)
+//! - Restarts stalled connections with a Range request, then tries a mirror (cdn.rs)
+//! - Buffers writes, tuned to throughput or set per manager/task (buffering.rs)
+//! - Archives licenses fetched ahead of a trip for downloads started later (offline.rs)
+//! - Plans a liberation's paths, sizes and free space without doing it (plan.rs)
+//! - Fetches opted-in bonus audio, interviews and PDFs with a book (companion.rs)
+//! - Pauses Wi-Fi-only tasks on cellular and resumes them on Wi-Fi (network_policy.rs)
+//! - Decrypts and tags finished downloads itself when asked to (follow_up.rs)
 //!
 //! ## Download Flow
 //!
@@ -91,7 +107,36 @@
 pub mod stream;
 pub mod progress;
 pub mod persistent_manager;
+pub mod chunk_manifest;
+pub mod adaptive;
+pub mod diagnostics;
+pub mod legacy;
+pub mod conversion_schedule;
+pub mod quota;
+pub mod url_expiry;
+pub mod queue_transfer;
+pub mod cdn;
+pub mod buffering;
+pub mod offline;
+pub mod plan;
+pub mod companion;
+pub mod network_policy;
+pub mod follow_up;
 
 // Re-export commonly used types
 pub use progress::DownloadProgress;
 pub use persistent_manager::{PersistentDownloadManager, DownloadTask, TaskStatus};
+pub use chunk_manifest::{ChunkHasher, ChunkManifest};
+pub use adaptive::{AdaptivePolicy, DeviceConditions, ResourceLimits, ThermalStatus};
+pub use diagnostics::{Diagnostics, ProgressWatchdog, StalledTask};
+pub use legacy::LegacyImportReport;
+pub use conversion_schedule::{ConversionPolicy, ConversionWindow};
+pub use quota::{DownloadQuota, MonthlyUsage, QuotaAction, QuotaStatus};
+pub use queue_transfer::{QueueExport, QueueImportReport, QueuedItem, ResolvedDownload};
+pub use cdn::{CdnPolicy, ThroughputMonitor};
+pub use buffering::{BufferOverrides, BufferPolicy, EffectiveBuffering};
+pub use offline::{OfflineLicense, OfflinePrepReport};
+pub use plan::{LiberationOptions, LiberationPlan};
+pub use companion::CompanionPolicy;
+pub use network_policy::{NetworkPolicy, NetworkState, NetworkTransition};
+pub use follow_up::{DecryptStep, FollowUp};
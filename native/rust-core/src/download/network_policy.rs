@@ -0,0 +1,85 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Wi-Fi-only downloads
+//!
+//! The app reports the network the device is on (`ConnectivityManager` on
+//! Android, `NWPathMonitor` on iOS). A task is Wi-Fi-only when its own
+//! `wifi_only` says so or, when that is unset, when the stored
+//! `NetworkPolicy` does. On cellular:
+//! - queued Wi-Fi-only tasks wait in the queue
+//! - running Wi-Fi-only tasks are paused and flagged `network_paused`
+//!
+//! Offline pauses every running task the same way. Flagged tasks go back
+//! to the queue once the network allows them again; tasks the user paused
+//! stay paused. Until the app reports a state it is `Unknown`, which
+//! allows everything.
+
+use crate::error::Result;
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+const KEY_POLICY: &str = "downloads.network_policy";
+
+/// Network the device is on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkState {
+    #[default]
+    Unknown,
+    Wifi,
+    Cellular,
+    Offline,
+}
+
+impl NetworkState {
+    /// Whether a task may download on this network
+    pub fn allows(self, wifi_only: bool) -> bool {
+        match self {
+            NetworkState::Unknown | NetworkState::Wifi => true,
+            NetworkState::Cellular => !wifi_only,
+            NetworkState::Offline => false,
+        }
+    }
+}
+
+/// Network policy for tasks without their own `wifi_only`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkPolicy {
+    /// Only download on Wi-Fi
+    pub wifi_only: bool,
+}
+
+/// Tasks paused and resumed by a network change
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkTransition {
+    /// Running tasks paused until the network allows them
+    pub paused: Vec<String>,
+    /// Network-paused tasks put back in the queue
+    pub resumed: Vec<String>,
+}
+
+/// Stored network policy (default if never set)
+pub async fn get_network_policy(pool: &SqlitePool) -> Result<NetworkPolicy> {
+    Ok(get_json_setting(pool, KEY_POLICY).await?.unwrap_or_default())
+}
+
+/// Store the network policy
+pub async fn set_network_policy(pool: &SqlitePool, policy: &NetworkPolicy) -> Result<()> {
+    set_json_setting(pool, KEY_POLICY, policy).await
+}
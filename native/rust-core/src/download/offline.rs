@@ -0,0 +1,428 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Licenses fetched ahead of a trip
+//!
+//! Starting a download takes a license request and a HEAD request before
+//! the first byte arrives, which is slow or impossible on a flaky hotel or
+//! tethered connection. `prepare_offline` fetches licenses for chosen
+//! titles while the device is still on good Wi-Fi and archives them in
+//! OfflineLicenses: decryption keys, content metadata (chapters, codec),
+//! the signed download URL and the file size.
+//!
+//! A download later takes the archived license (`get_offline_license`)
+//! instead of requesting one, as long as its signed URL hasn't expired
+//! (see `url_expiry`); the expiry is reported per title so the app can tell
+//! how long the preparation lasts. Expired licenses are ignored and can be
+//! dropped with `prune_expired_licenses`.
+
+use crate::api::client::AudibleClient;
+use crate::api::content::DownloadQuality;
+use crate::api::license::{DownloadLicense, FormatCapabilities};
+use crate::cancel::CancellationToken;
+use crate::clock::Clock;
+use crate::download::url_expiry::{check_download_url, download_url_expires_at};
+use crate::error::{LibationError, Result};
+use crate::storage::dates;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::future::Future;
+
+/// User agent the CDN expects for HEAD and download requests
+const DOWNLOAD_USER_AGENT: &str = "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0";
+
+/// A license fetched for later, before it's archived
+#[derive(Debug, Clone)]
+pub struct FetchedLicense {
+    pub license: DownloadLicense,
+    /// Negotiated format (`FileType::as_str`)
+    pub file_type: String,
+    pub total_bytes: u64,
+}
+
+/// An archived license
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineLicense {
+    pub asin: String,
+    pub quality: DownloadQuality,
+    pub file_type: String,
+    pub license: DownloadLicense,
+    pub total_bytes: u64,
+    /// When the signed download URL stops working (None if it doesn't say)
+    pub expires_at: Option<String>,
+    pub fetched_at: String,
+}
+
+/// A title `prepare_offline` archived a license for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreparedTitle {
+    pub asin: String,
+    pub file_type: String,
+    pub total_bytes: u64,
+    pub expires_at: Option<String>,
+}
+
+/// A title `prepare_offline` couldn't fetch a license for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedTitle {
+    pub asin: String,
+    pub reason: String,
+}
+
+/// Result of `prepare_offline`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfflinePrepReport {
+    pub prepared: Vec<PreparedTitle>,
+    pub failed: Vec<FailedTitle>,
+    /// Stopped early by the cancellation token; licenses archived so far
+    /// are kept
+    pub cancelled: bool,
+}
+
+/// Fetch and archive licenses for `asins`, one at a time
+///
+/// `fetch` requests the license for one title (see `fetch_license`).
+/// Titles whose fetch fails are reported and skipped, except for
+/// authentication errors, which would fail every title the same way.
+/// Archiving replaces any earlier license for the title.
+///
+/// # Errors
+/// Authentication and database errors
+pub async fn prepare_offline<F, Fut>(
+    pool: &SqlitePool,
+    asins: &[String],
+    quality: DownloadQuality,
+    token: &CancellationToken,
+    mut fetch: F,
+) -> Result<OfflinePrepReport>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<FetchedLicense>>,
+{
+    let mut report = OfflinePrepReport::default();
+
+    for asin in asins {
+        if token.is_cancelled() {
+            report.cancelled = true;
+            break;
+        }
+
+        let fetched = match token.run_until_cancelled(fetch(asin.clone())).await {
+            Ok(Ok(fetched)) => fetched,
+            Ok(Err(e)) if e.is_auth_error() => return Err(e),
+            Ok(Err(e)) => {
+                report.failed.push(FailedTitle { asin: asin.clone(), reason: e.to_string() });
+                continue;
+            }
+            Err(_) => {
+                report.cancelled = true;
+                break;
+            }
+        };
+
+        let archived = archive_license(pool, asin, quality, &fetched).await?;
+        report.prepared.push(PreparedTitle {
+            asin: asin.clone(),
+            file_type: archived.file_type,
+            total_bytes: archived.total_bytes,
+            expires_at: archived.expires_at,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Request a license for later use and size its file
+///
+/// Negotiates the format like a direct download (AAX with the account's
+/// activation bytes, else AAXC) and sends a HEAD request for the size.
+pub async fn fetch_license(client: &AudibleClient, asin: &str, quality: DownloadQuality) -> Result<FetchedLicense> {
+    let capabilities = FormatCapabilities::for_account(&*client.account().lock().await);
+    let negotiated = client
+        .negotiate_download_license(asin, quality, &[], &capabilities)
+        .await?;
+
+    let response = reqwest::Client::new()
+        .head(&negotiated.license.download_url)
+        .header("User-Agent", DOWNLOAD_USER_AGENT)
+        .send()
+        .await
+        .map_err(|e| LibationError::NetworkError {
+            message: format!("HEAD request failed: {}", e),
+            is_transient: true,
+        })?;
+    let total_bytes = response
+        .headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    Ok(FetchedLicense {
+        file_type: negotiated.file_type.as_str().to_string(),
+        license: negotiated.license,
+        total_bytes,
+    })
+}
+
+/// Archive a fetched license, replacing the title's earlier one
+pub async fn archive_license(
+    pool: &SqlitePool,
+    asin: &str,
+    quality: DownloadQuality,
+    fetched: &FetchedLicense,
+) -> Result<OfflineLicense> {
+    let archived = OfflineLicense {
+        asin: asin.to_string(),
+        quality,
+        file_type: fetched.file_type.clone(),
+        license: fetched.license.clone(),
+        total_bytes: fetched.total_bytes,
+        expires_at: download_url_expires_at(&fetched.license.download_url).map(dates::format_timestamp),
+        fetched_at: dates::now(),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO OfflineLicenses (asin, quality, file_type, license, total_bytes, expires_at, fetched_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(asin) DO UPDATE SET
+            quality = excluded.quality,
+            file_type = excluded.file_type,
+            license = excluded.license,
+            total_bytes = excluded.total_bytes,
+            expires_at = excluded.expires_at,
+            fetched_at = excluded.fetched_at
+        "#,
+    )
+    .bind(asin)
+    .bind(quality.as_str())
+    .bind(&archived.file_type)
+    .bind(serde_json::to_string(&archived.license)?)
+    .bind(archived.total_bytes as i64)
+    .bind(&archived.expires_at)
+    .bind(&archived.fetched_at)
+    .execute(pool)
+    .await?;
+
+    Ok(archived)
+}
+
+type OfflineLicenseRow = (String, String, String, String, i64, Option<String>, String);
+
+fn from_row(row: OfflineLicenseRow) -> Result<OfflineLicense> {
+    let (asin, quality, file_type, license, total_bytes, expires_at, fetched_at) = row;
+    Ok(OfflineLicense {
+        asin,
+        quality: serde_json::from_value(serde_json::Value::String(quality))?,
+        file_type,
+        license: serde_json::from_str(&license)?,
+        total_bytes: total_bytes as u64,
+        expires_at,
+        fetched_at,
+    })
+}
+
+const LICENSE_COLUMNS: &str = "asin, quality, file_type, license, total_bytes, expires_at, fetched_at";
+
+/// Archived license for a title at `quality`, if its download URL still works
+pub async fn get_offline_license(
+    pool: &SqlitePool,
+    asin: &str,
+    quality: DownloadQuality,
+    clock: &dyn Clock,
+) -> Result<Option<OfflineLicense>> {
+    Ok(find_offline_license(pool, asin, quality)
+        .await?
+        .filter(|archived| check_download_url(asin, &archived.license.download_url, clock).is_ok()))
+}
+
+/// Archived license for a title at `quality`, expired or not
+///
+/// An expired license can't start a download, but its format and file
+/// size still describe the title.
+pub async fn find_offline_license(
+    pool: &SqlitePool,
+    asin: &str,
+    quality: DownloadQuality,
+) -> Result<Option<OfflineLicense>> {
+    let row: Option<OfflineLicenseRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM OfflineLicenses WHERE asin = ? AND quality = ?",
+        LICENSE_COLUMNS
+    ))
+    .bind(asin)
+    .bind(quality.as_str())
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(from_row).transpose()
+}
+
+/// All archived licenses, soonest to expire first (expired ones included)
+pub async fn list_offline_licenses(pool: &SqlitePool) -> Result<Vec<OfflineLicense>> {
+    let rows: Vec<OfflineLicenseRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM OfflineLicenses ORDER BY expires_at IS NULL, expires_at, asin",
+        LICENSE_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter().map(from_row).collect()
+}
+
+/// Drop a title's archived license
+///
+/// # Returns
+/// False if there was none
+pub async fn remove_offline_license(pool: &SqlitePool, asin: &str) -> Result<bool> {
+    let removed = sqlx::query("DELETE FROM OfflineLicenses WHERE asin = ?")
+        .bind(asin)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(removed > 0)
+}
+
+/// Drop archived licenses whose download URL has expired
+///
+/// # Returns
+/// Number of licenses dropped
+pub async fn prune_expired_licenses(pool: &SqlitePool, clock: &dyn Clock) -> Result<u64> {
+    let removed = sqlx::query("DELETE FROM OfflineLicenses WHERE expires_at IS NOT NULL AND expires_at <= ?")
+        .bind(dates::format_timestamp(clock.now()))
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::content::DrmType;
+    use crate::clock::TestClock;
+    use crate::storage::Database;
+    use chrono::TimeZone;
+
+    fn fetched(url: &str) -> FetchedLicense {
+        let metadata: crate::api::content::ContentMetadata = serde_json::from_value(serde_json::json!({
+            "content_url": { "offline_url": url }
+        }))
+        .unwrap();
+        FetchedLicense {
+            license: DownloadLicense {
+                drm_type: DrmType::Adrm,
+                content_metadata: metadata,
+                decryption_keys: Some(vec![crate::api::license::KeyData {
+                    key_part_1: vec![7; 16].into(),
+                    key_part_2: Some(vec![9; 16].into()),
+                }]),
+                download_url: url.to_string(),
+                mirror_urls: Vec::new(),
+            },
+            file_type: "aaxc".to_string(),
+            total_bytes: 42_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prepare_offline_archives_until_expiry() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        // Expires=1740830400 is 2025-03-01T12:00:00Z
+        let url = "https://dcdn.audible.com/p/B0TRIP.aaxc?Expires=1740830400&Signature=abc";
+        let asins = vec!["B0TRIP".to_string(), "B0GONE".to_string()];
+
+        let report = prepare_offline(pool, &asins, DownloadQuality::High, &CancellationToken::new(), |asin| async move {
+            match asin.as_str() {
+                "B0TRIP" => Ok(fetched(url)),
+                _ => Err(LibationError::not_found("Not in your library")),
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(report.prepared.len(), 1);
+        assert_eq!(report.prepared[0].expires_at.as_deref(), Some("2025-03-01T12:00:00.000Z"));
+        assert_eq!(report.failed[0].asin, "B0GONE");
+
+        let before = TestClock::new(chrono::Utc.with_ymd_and_hms(2025, 3, 1, 8, 0, 0).unwrap());
+        let archived = get_offline_license(pool, "B0TRIP", DownloadQuality::High, &before).await.unwrap().unwrap();
+        assert_eq!(archived.total_bytes, 42_000_000);
+        assert_eq!(archived.license.decryption_keys.unwrap()[0].key_part_1, vec![7; 16].into());
+        assert!(get_offline_license(pool, "B0TRIP", DownloadQuality::Low, &before).await.unwrap().is_none());
+
+        // Past the URL's expiry the archive is skipped, then pruned
+        let after = TestClock::new(chrono::Utc.with_ymd_and_hms(2025, 3, 1, 13, 0, 0).unwrap());
+        assert!(get_offline_license(pool, "B0TRIP", DownloadQuality::High, &after).await.unwrap().is_none());
+        assert_eq!(prune_expired_licenses(pool, &before).await.unwrap(), 0);
+        assert_eq!(prune_expired_licenses(pool, &after).await.unwrap(), 1);
+        assert!(list_offline_licenses(pool).await.unwrap().is_empty());
+
+        // Auth errors stop the whole preparation
+        let result = prepare_offline(pool, &asins, DownloadQuality::High, &CancellationToken::new(), |_| async {
+            Err(LibationError::TokenExpired)
+        })
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_license_uses_activation_bytes() {
+        use crate::api::transport::{mock_client, MockTransport};
+        use base64::{engine::general_purpose, Engine as _};
+        use std::sync::Arc;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answers the HEAD request for the size
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://127.0.0.1:{}/B0AAX.aax", listener.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 2048];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        // The Adrm request is granted as AAX (4-byte key, no IV)
+        let aax_license = serde_json::json!({
+            "content_license": {
+                "drm_type": "Adrm",
+                "voucher": { "key": general_purpose::STANDARD.encode([0x1c, 0xeb, 0x00, 0xda]) },
+                "content_metadata": { "content_url": { "offline_url": url } }
+            }
+        });
+        let transport = Arc::new(MockTransport::new());
+        let client = mock_client(&transport);
+
+        // Without activation bytes an AAX grant can't be decrypted
+        transport.push_json(200, aax_license.clone());
+        assert!(matches!(
+            fetch_license(&client, "B0AAX", DownloadQuality::High).await,
+            Err(LibationError::InvalidDrmFormat(_))
+        ));
+
+        client.account().lock().await.set_decrypt_key("1CEB00DA".to_string());
+        transport.push_json(200, aax_license);
+        let fetched = fetch_license(&client, "B0AAX", DownloadQuality::High).await.unwrap();
+        assert_eq!(fetched.file_type, "aax");
+        assert_eq!(fetched.total_bytes, 1234);
+    }
+}
@@ -6,17 +6,73 @@
 //! - Provides real-time progress tracking
 //! - Handles concurrent downloads with semaphore-based control
 //! - Automatically recovers from app restarts
+//! - Optionally verifies partial files with a per-chunk hash manifest on resume
+//! - Lowers concurrency when the device reports heat or low battery
+//! - Flags active tasks with no progress and reports runtime diagnostics
+//! - Imports partial downloads left behind by the legacy JSON state files
+//! - Parks finished downloads until the conversion policy allows decrypting
+//! - Exports the queue and re-imports it with freshly requested licenses
+//! - Buffers writes per `BufferPolicy`, tuned to throughput or pinned per task
+//! - Holds Wi-Fi-only tasks back on cellular (see `network_policy`)
+//! - Decrypts and tags finished downloads enqueued with a follow-up (see
+//!   `follow_up`)
+//!
+//! # Progress
+//! `bytes_downloaded` is only written by the download worker, and only
+//! for bytes flushed to the file. It never goes down within a
+//! `progress_epoch`: when a resume has to start further back (the partial
+//! file is shorter than recorded, fails chunk verification, or is gone),
+//! the epoch is bumped and the progress callback gets the reset right
+//! away. A UI keeping the largest value it has seen resets it when the
+//! epoch changes.
+//!
+//! # Resuming
+//! A resume asks for the rest of the file with a Range request. Some CDNs
+//! ignore it and answer 200 with the whole file; that response is then
+//! written from the start of a truncated file (a new epoch) instead of
+//! being appended to the partial data. A 206 must start at the requested
+//! offset and be for a file of the expected size. Once the stream ends,
+//! the file must be as long as the server said it would be.
+//!
+//! A stream that stops delivering bytes without failing is restarted the
+//! same way, from the last byte written (see `download::cdn` for when it
+//! counts as stalled and how repeated stalls escalate).
 
+use crate::api::content::CompanionKind;
+use crate::clock::{AppClock, Clock};
 use crate::error::{LibationError, Result};
+use crate::download::adaptive::{AdaptivePolicy, DeviceConditions, ResourceLimits};
+use crate::download::buffering::{BufferOverrides, BufferPolicy, BufferTuner, EffectiveBuffering};
+use crate::download::cdn::{self, CdnPolicy, ThroughputMonitor};
+use crate::download::chunk_manifest::{ChunkHasher, ChunkManifest};
+use crate::download::companion;
+use crate::download::conversion_schedule::{ConversionGate, ConversionPolicy};
+use crate::download::follow_up::{self, FollowUp};
+use crate::download::diagnostics::{
+    Diagnostics, DownloadDiagnostics, PoolStats, ProgressWatchdog, RuntimeStats,
+    DEFAULT_STALL_THRESHOLD,
+};
+use crate::download::network_policy::{self, NetworkPolicy, NetworkState, NetworkTransition};
+use crate::download::legacy::{discover_legacy_downloads, LegacyImportReport, SkippedLegacyDownload};
 use crate::download::progress::{DownloadProgress, DownloadState};
+use crate::download::queue_transfer::{QueueExport, QueueImportReport, QueuedItem, ResolvedDownload, QUEUE_EXPORT_VERSION};
+use crate::download::quota::{self, QuotaStatus};
+use crate::download::url_expiry::check_download_url;
+use crate::activity::{self, WorkGuard, WorkType};
+use crate::cancel::{CancellationToken, JobKind};
+use crate::events;
+use crate::secret::SecretString;
+use crate::trace::trace_eprintln;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::{SqlitePool, Row};
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
 use tokio::sync::{RwLock, Semaphore};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
@@ -43,6 +99,9 @@ pub enum TaskStatus {
     Validating,
     #[serde(rename = "copying")]
     Copying,
+    /// Downloaded; conversion deferred by the conversion policy
+    #[serde(rename = "awaiting_conversion")]
+    AwaitingConversion,
 }
 
 impl TaskStatus {
@@ -57,6 +116,7 @@ impl TaskStatus {
             TaskStatus::Decrypting => "decrypting",
             TaskStatus::Validating => "validating",
             TaskStatus::Copying => "copying",
+            TaskStatus::AwaitingConversion => "awaiting_conversion",
         }
     }
 
@@ -71,6 +131,7 @@ impl TaskStatus {
             "decrypting" => Ok(TaskStatus::Decrypting),
             "validating" => Ok(TaskStatus::Validating),
             "copying" => Ok(TaskStatus::Copying),
+            "awaiting_conversion" => Ok(TaskStatus::AwaitingConversion),
             _ => Err(LibationError::InvalidInput(format!("Invalid task status: {}", s))),
         }
     }
@@ -88,16 +149,69 @@ pub struct DownloadTask {
     pub download_url: String,
     pub download_path: String,
     pub output_path: String,
-    #[serde(default)]
-    pub request_headers: HashMap<String, String>,
+    /// May carry credentials; stays out of the JSON handed to the app
+    #[serde(default, skip_serializing)]
+    pub request_headers: HashMap<String, SecretString>,
     pub error: Option<String>,
     pub retry_count: i32,
     pub created_at: String,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
-    pub aaxc_key: Option<String>,
-    pub aaxc_iv: Option<String>,
+    /// Conversion keys for a retry; left out of the task JSON, the native
+    /// side reads them on their own
+    #[serde(skip_serializing)]
+    pub aaxc_key: Option<SecretString>,
+    #[serde(skip_serializing)]
+    pub aaxc_iv: Option<SecretString>,
     pub output_directory: Option<String>,
+    /// Hex SHA-256 of the downloaded file, computed while streaming
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Library account owning the title; downloaded bytes count against it
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Chunk hashes of the partial file (None when hashing is disabled)
+    #[serde(default, skip_serializing)]
+    pub chunk_manifest: Option<ChunkManifest>,
+    /// Active but without progress for longer than the stall threshold
+    #[serde(default)]
+    pub stalled: bool,
+    /// Alternate CDN URLs for the same file, tried when the current one is
+    /// slow or unreachable
+    #[serde(default)]
+    pub mirror_urls: Vec<String>,
+    /// Host of the CDN that served the download (the latest one, after a switch)
+    #[serde(default)]
+    pub cdn_host: Option<String>,
+    /// Per-task buffering that replaces the manager's
+    #[serde(default)]
+    pub buffer_overrides: BufferOverrides,
+    /// Buffering used by the latest session (None before the first one)
+    #[serde(default)]
+    pub buffering: Option<EffectiveBuffering>,
+    /// Bumped each time bytes_downloaded goes back; within an epoch it
+    /// only grows
+    #[serde(default)]
+    pub progress_epoch: u32,
+    /// Trace id of the bridge call that enqueued it; the worker runs under
+    /// it (see `trace`)
+    #[serde(default)]
+    pub trace_id: Option<String>,
+    /// ASIN of the book this task fetches companion content for (see
+    /// `download::companion`)
+    #[serde(default)]
+    pub companion_of: Option<String>,
+    #[serde(default)]
+    pub companion_kind: Option<CompanionKind>,
+    /// Only download on Wi-Fi (None follows the manager's `NetworkPolicy`)
+    #[serde(default)]
+    pub wifi_only: Option<bool>,
+    /// Paused by a network change; resumes when the network allows it
+    #[serde(default)]
+    pub network_paused: bool,
+    /// Work run by the manager once the download completes
+    #[serde(default)]
+    pub follow_up: Option<FollowUp>,
 }
 
 impl DownloadTask {
@@ -129,15 +243,92 @@ impl DownloadTask {
             && self.aaxc_key.is_some()
             && self.aaxc_iv.is_some()
     }
+
+    pub fn created(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        crate::storage::dates::parse_timestamp(&self.created_at)
+    }
+
+    pub fn started(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.started_at.as_deref().and_then(crate::storage::dates::parse_timestamp)
+    }
+
+    pub fn completed(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.completed_at.as_deref().and_then(crate::storage::dates::parse_timestamp)
+    }
 }
 
 /// Progress callback function type
 pub type ProgressCallback = Box<dyn Fn(DownloadTask) + Send + Sync>;
 
+/// Manager settings a download worker needs
+#[derive(Debug, Clone, Copy)]
+struct WorkerSettings {
+    chunk_size: Option<u64>,
+    cdn_policy: CdnPolicy,
+    buffer_policy: BufferPolicy,
+}
+
+/// Where a download response starts in the task's file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResumeStart {
+    /// At the end of the partial file (a fresh download or a 206)
+    Append,
+    /// At byte 0: the server ignored the Range request
+    Restart,
+}
+
+/// Where a response to a request from `offset` starts
+///
+/// `total_bytes` is the file size known so far (0 if unknown).
+///
+/// # Errors
+/// DownloadFailed if a 206 starts anywhere but `offset`, or is part of a
+/// file of another size
+pub(crate) fn resume_start(
+    offset: u64,
+    total_bytes: u64,
+    status: reqwest::StatusCode,
+    content_range: Option<&str>,
+) -> Result<ResumeStart> {
+    if offset == 0 {
+        return Ok(ResumeStart::Append);
+    }
+    if status != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Ok(ResumeStart::Restart);
+    }
+
+    // Content-Range: bytes 1000-1999/2000
+    let Some(range) = content_range else {
+        return Ok(ResumeStart::Append);
+    };
+    let first = range
+        .trim()
+        .strip_prefix("bytes ")
+        .and_then(|r| r.split('-').next())
+        .and_then(|first| first.trim().parse::<u64>().ok())
+        .ok_or_else(|| LibationError::DownloadFailed(format!("Invalid Content-Range: {}", range)))?;
+    if first != offset {
+        return Err(LibationError::DownloadFailed(format!(
+            "Server resumed at byte {} instead of {}",
+            first, offset
+        )));
+    }
+
+    // The complete length may be "*" when the server doesn't know it
+    let complete = range.rsplit_once('/').and_then(|(_, len)| len.trim().parse::<u64>().ok());
+    if let Some(complete) = complete.filter(|&len| total_bytes > 0 && len != total_bytes) {
+        return Err(LibationError::DownloadFailed(format!(
+            "Server resumed a file of {} bytes instead of {}",
+            complete, total_bytes
+        )));
+    }
+    Ok(ResumeStart::Append)
+}
+
 /// Active download worker handle
 struct ActiveDownload {
     handle: JoinHandle<()>,
-    cancel_tx: tokio::sync::oneshot::Sender<()>,
+    cancel: CancellationToken,
 }
 
 /// Persistent Download Manager
@@ -147,20 +338,73 @@ pub struct PersistentDownloadManager {
     semaphore: Arc<Semaphore>,
     active_downloads: Arc<RwLock<HashMap<String, ActiveDownload>>>,
     progress_callbacks: Arc<RwLock<HashMap<String, ProgressCallback>>>,
+    chunk_size: Option<u64>,
+    /// Concurrency allowed by current device conditions (<= max_concurrent)
+    concurrency_limit: Arc<AtomicUsize>,
+    watchdog: Arc<ProgressWatchdog>,
+    conversion_gate: Arc<std::sync::RwLock<ConversionGate>>,
+    clock: Arc<dyn Clock>,
+    cdn_policy: CdnPolicy,
+    buffer_policy: std::sync::RwLock<BufferPolicy>,
+    network_state: std::sync::RwLock<NetworkState>,
+    network_policy: std::sync::RwLock<NetworkPolicy>,
 }
 
 impl PersistentDownloadManager {
     /// Create a new manager with existing database pool
+    ///
+    /// Reads the stored `NetworkPolicy`; the network state is `Unknown`
+    /// until `set_network_state`.
     pub async fn new(pool: Arc<SqlitePool>, max_concurrent: usize) -> Result<Self> {
+        let network_policy = network_policy::get_network_policy(&pool).await?;
         Ok(Self {
             pool,
             max_concurrent,
             semaphore: Arc::new(Semaphore::new(max_concurrent)),
             active_downloads: Arc::new(RwLock::new(HashMap::new())),
             progress_callbacks: Arc::new(RwLock::new(HashMap::new())),
+            chunk_size: None,
+            concurrency_limit: Arc::new(AtomicUsize::new(max_concurrent)),
+            watchdog: Arc::new(ProgressWatchdog::new(DEFAULT_STALL_THRESHOLD)),
+            conversion_gate: Arc::new(std::sync::RwLock::new(ConversionGate::default())),
+            clock: Arc::new(AppClock),
+            cdn_policy: CdnPolicy::default(),
+            buffer_policy: std::sync::RwLock::new(BufferPolicy::default()),
+            network_state: std::sync::RwLock::new(NetworkState::default()),
+            network_policy: std::sync::RwLock::new(network_policy),
         })
     }
 
+    /// Use a specific clock for URL expiry and conversion windows (default:
+    /// the process-wide `AppClock`)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set how long an active task may go without progress before it is
+    /// flagged as stalled (default 5 minutes)
+    pub fn with_stall_threshold(mut self, threshold: std::time::Duration) -> Self {
+        self.watchdog = Arc::new(ProgressWatchdog::new(threshold));
+        self
+    }
+
+    /// Set when a CDN is slow enough to switch to a mirror (see `download::cdn`)
+    pub fn with_cdn_policy(mut self, policy: CdnPolicy) -> Self {
+        self.cdn_policy = policy;
+        self
+    }
+
+    /// Enable per-chunk hashing for new downloads
+    ///
+    /// Tasks started with hashing enabled keep a manifest of chunk hashes so
+    /// a resume verifies the partial file instead of trusting byte counts.
+    /// See `chunk_manifest::DEFAULT_CHUNK_SIZE`.
+    pub fn with_chunk_verification(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
     /// Enqueue a new download
     pub async fn enqueue_download(
         &self,
@@ -172,20 +416,93 @@ impl PersistentDownloadManager {
         output_path: String,
         request_headers: HashMap<String, String>,
     ) -> Result<String> {
+        self.enqueue_download_with_follow_up(
+            asin,
+            title,
+            download_url,
+            total_bytes,
+            download_path,
+            output_path,
+            request_headers,
+            None,
+        )
+        .await
+    }
+
+    /// Enqueue a new download that the manager decrypts and tags itself
+    /// once it completes (see `download::follow_up`)
+    ///
+    /// The follow-up's keys become the task's conversion keys.
+    ///
+    /// # Errors
+    /// As `enqueue_download`; InvalidInput for an invalid follow-up
+    #[allow(clippy::too_many_arguments)]
+    pub async fn enqueue_download_with_follow_up(
+        &self,
+        asin: String,
+        title: String,
+        download_url: String,
+        total_bytes: u64,
+        download_path: String,
+        output_path: String,
+        request_headers: HashMap<String, String>,
+        follow_up: Option<FollowUp>,
+    ) -> Result<String> {
+        let follow_up = follow_up.filter(|f| !f.is_empty());
+        if let Some(follow_up) = &follow_up {
+            follow_up.validate()?;
+        }
+
+        // Loaned titles can be streamed but not kept offline
+        if let Some(benefit) = crate::storage::queries::get_book_benefit_type(&self.pool, &asin).await? {
+            if !benefit.permits_download() {
+                return Err(LibationError::DownloadNotPermitted {
+                    asin,
+                    benefit_type: benefit.as_str().to_string(),
+                });
+            }
+        }
+
+        // Titles synced with only undecryptable formats would fail after
+        // downloading (whether Widevine works was settled by the license)
+        if let Some(support) = crate::storage::queries::get_book_format_support(&self.pool, &asin).await? {
+            if !support.permits_download(true) {
+                return Err(LibationError::UnsupportedDownloadFormat {
+                    asin,
+                    format_support: support.as_str().to_string(),
+                });
+            }
+        }
+
+        // Downloads count against the owning account's monthly cap
+        let account = quota::account_for_asin(&self.pool, &asin).await?;
+        if let Some(account) = &account {
+            quota::check_quota(&self.pool, account).await?;
+        }
+
+        // Fail fast on an unusable destination instead of mid-download
+        let download_target = Path::new(&download_path);
+        if let Some(download_dir) = download_target.parent().filter(|p| !p.as_os_str().is_empty()) {
+            crate::file::paths::validate_output_path(download_dir, download_target, total_bytes)?;
+        }
+
         let task_id = Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().to_rfc3339();
+        let now = crate::storage::dates::now();
 
         // Insert into database
         let headers_json = serde_json::to_string(&request_headers)
             .map_err(|e| LibationError::InvalidInput(format!("Invalid headers: {}", e)))?;
+        let follow_up_json = follow_up.as_ref().map(serde_json::to_string).transpose()?;
+        let decrypt = follow_up.as_ref().and_then(|f| f.decrypt.as_ref());
 
         sqlx::query(
             r#"
             INSERT INTO DownloadTasks (
                 task_id, asin, title, status, bytes_downloaded, total_bytes,
-                download_url, download_path, output_path, request_headers, created_at
+                download_url, download_path, output_path, request_headers, created_at, account, trace_id,
+                follow_up, aaxc_key, aaxc_iv
             )
-            VALUES (?, ?, ?, ?, 0, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, 0, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&task_id)
@@ -198,6 +515,11 @@ impl PersistentDownloadManager {
         .bind(&output_path)
         .bind(&headers_json)
         .bind(&now)
+        .bind(&account)
+        .bind(crate::trace::current())
+        .bind(&follow_up_json)
+        .bind(decrypt.map(|d| &d.aaxc_key))
+        .bind(decrypt.map(|d| &d.aaxc_iv))
         .execute(&*self.pool)
         .await?;
 
@@ -207,6 +529,110 @@ impl PersistentDownloadManager {
         Ok(task_id)
     }
 
+    /// Monthly cap status for the account owning a task
+    ///
+    /// None when the task's title isn't in the library. After
+    /// `enqueue_download` succeeds, `exceeded` means the cap is set to warn.
+    pub async fn quota_status(&self, task_id: &str) -> Result<Option<QuotaStatus>> {
+        match self.get_task(task_id).await?.account {
+            Some(account) => Ok(Some(quota::get_quota_status(&self.pool, &account).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Import partial downloads tracked by legacy state JSON files
+    ///
+    /// Each `{file}.download_state.json` in `dir` becomes a queued task that
+    /// resumes from the bytes already on disk; the JSON is deleted once the
+    /// task row exists. State files whose download is already tracked are
+    /// deleted too. Safe to call on every startup: a directory without state
+    /// files is a no-op.
+    pub async fn import_legacy_downloads(&self, dir: &Path) -> Result<LegacyImportReport> {
+        let (downloads, skipped) = discover_legacy_downloads(dir).await?;
+        let mut report = LegacyImportReport {
+            imported: Vec::new(),
+            skipped,
+        };
+
+        for legacy in downloads {
+            let state_path = legacy.state_path.to_string_lossy().to_string();
+            let download_path = legacy.state.save_file_path.to_string_lossy().to_string();
+
+            let already_tracked: Option<String> = sqlx::query_scalar(
+                "SELECT task_id FROM DownloadTasks WHERE download_path = ? LIMIT 1"
+            )
+            .bind(&download_path)
+            .fetch_optional(&*self.pool)
+            .await?;
+            if already_tracked.is_some() {
+                let _ = fs::remove_file(&legacy.state_path).await;
+                report.skipped.push(SkippedLegacyDownload {
+                    state_path,
+                    reason: "Download is already tracked".to_string(),
+                });
+                continue;
+            }
+
+            if let Some(benefit) = crate::storage::queries::get_book_benefit_type(&self.pool, &legacy.asin).await? {
+                if !benefit.permits_download() {
+                    report.skipped.push(SkippedLegacyDownload {
+                        state_path,
+                        reason: format!("Download not permitted ({})", benefit.as_str()),
+                    });
+                    continue;
+                }
+            }
+
+            let title: Option<String> = sqlx::query_scalar(
+                "SELECT title FROM Books WHERE audible_product_id = ?"
+            )
+            .bind(&legacy.asin)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+            let account = quota::account_for_asin(&self.pool, &legacy.asin).await?;
+            let task_id = Uuid::new_v4().to_string();
+            let headers_json = serde_json::to_string(&legacy.state.request_headers)
+                .map_err(|e| LibationError::InvalidInput(format!("Invalid headers: {}", e)))?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO DownloadTasks (
+                    task_id, asin, title, status, bytes_downloaded, total_bytes,
+                    download_url, download_path, output_path, request_headers, created_at, account
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&task_id)
+            .bind(&legacy.asin)
+            .bind(title.unwrap_or_else(|| legacy.asin.clone()))
+            .bind(TaskStatus::Queued.as_str())
+            .bind(legacy.resume_bytes as i64)
+            .bind(legacy.state.content_length as i64)
+            .bind(&legacy.state.url)
+            .bind(&download_path)
+            .bind(legacy.output_path().to_string_lossy().to_string())
+            .bind(&headers_json)
+            .bind(&legacy.state.timestamp)
+            .bind(&account)
+            .execute(&*self.pool)
+            .await?;
+
+            // The row is now the source of truth for this download
+            if let Err(e) = fs::remove_file(&legacy.state_path).await {
+                trace_eprintln!("⚠️  Could not remove legacy state file {}: {}", state_path, e);
+            }
+            report.imported.push(task_id);
+        }
+
+        for _ in 0..report.imported.len().min(self.max_concurrent) {
+            self.try_start_next_download().await?;
+        }
+
+        Ok(report)
+    }
+
     /// Get a task by ID
     pub async fn get_task(&self, task_id: &str) -> Result<DownloadTask> {
         let row = sqlx::query(
@@ -217,24 +643,30 @@ impl PersistentDownloadManager {
         .await
         .map_err(|_| LibationError::RecordNotFound(format!("Task not found: {}", task_id)))?;
 
-        self.row_to_task(row)
+        let mut task = self.row_to_task(row)?;
+        task.stalled = self.watchdog.is_stalled(&task.task_id);
+        Ok(task)
     }
 
     /// List all tasks, optionally filtered by status
     pub async fn list_tasks(&self, filter: Option<TaskStatus>) -> Result<Vec<DownloadTask>> {
         let rows = if let Some(status) = filter {
-            sqlx::query("SELECT * FROM DownloadTasks WHERE status = ? ORDER BY created_at DESC")
+            sqlx::query("SELECT * FROM DownloadTasks WHERE status = ? ORDER BY created_at DESC, rowid DESC")
                 .bind(status.as_str())
                 .fetch_all(&*self.pool)
                 .await?
         } else {
-            sqlx::query("SELECT * FROM DownloadTasks ORDER BY created_at DESC")
+            sqlx::query("SELECT * FROM DownloadTasks ORDER BY created_at DESC, rowid DESC")
                 .fetch_all(&*self.pool)
                 .await?
         };
 
         rows.into_iter()
-            .map(|row| self.row_to_task(row))
+            .map(|row| {
+                let mut task = self.row_to_task(row)?;
+                task.stalled = self.watchdog.is_stalled(&task.task_id);
+                Ok(task)
+            })
             .collect()
     }
 
@@ -243,6 +675,29 @@ impl PersistentDownloadManager {
         self.active_downloads.read().await.len()
     }
 
+    /// Snapshot of runtime, download queue, and database pool state
+    pub async fn diagnostics(&self) -> Result<Diagnostics> {
+        let queued_tasks: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM DownloadTasks WHERE status = ?")
+                .bind(TaskStatus::Queued.as_str())
+                .fetch_one(&*self.pool)
+                .await?;
+
+        Ok(Diagnostics {
+            runtime: RuntimeStats::current(),
+            downloads: DownloadDiagnostics {
+                active_workers: self.get_active_count().await,
+                queued_tasks,
+                permits_available: self.semaphore.available_permits(),
+                max_concurrent: self.max_concurrent,
+                concurrency_limit: self.current_concurrency_limit(),
+                stall_threshold_seconds: self.watchdog.threshold().as_secs(),
+                stalled_tasks: self.watchdog.stalled(),
+            },
+            database: PoolStats::from_pool(&self.pool),
+        })
+    }
+
     /// Pause a download
     pub async fn pause_download(&self, task_id: &str) -> Result<()> {
         // Check if actively downloading
@@ -250,8 +705,9 @@ impl PersistentDownloadManager {
 
         if let Some(download) = active.remove(task_id) {
             // Send cancellation signal
-            let _ = download.cancel_tx.send(());
+            download.cancel.cancel();
             drop(active);
+            self.watchdog.remove(task_id);
 
             // Wait briefly for graceful shutdown
             let _ = tokio::time::timeout(
@@ -269,7 +725,8 @@ impl PersistentDownloadManager {
             }
         }
 
-        Ok(())
+        // Paused by the user: not resumed by a network change
+        self.set_network_paused(task_id, false).await
     }
 
     /// Resume a paused download
@@ -282,8 +739,10 @@ impl PersistentDownloadManager {
             ));
         }
 
-        // Update status to queued
+        // Update status to queued (it waits there if the network doesn't
+        // allow it)
         self.update_task_status(task_id, TaskStatus::Queued).await?;
+        self.set_network_paused(task_id, false).await?;
 
         // Try to start it
         self.try_start_next_download().await?;
@@ -296,8 +755,9 @@ impl PersistentDownloadManager {
         // Stop if actively downloading
         let mut active = self.active_downloads.write().await;
         if let Some(download) = active.remove(task_id) {
-            let _ = download.cancel_tx.send(());
+            download.cancel.cancel();
             drop(active);
+            self.watchdog.remove(task_id);
             let _ = tokio::time::timeout(
                 tokio::time::Duration::from_secs(2),
                 download.handle
@@ -320,6 +780,9 @@ impl PersistentDownloadManager {
     }
 
     /// Retry a failed download
+    ///
+    /// A task whose download finished but whose follow-up failed runs the
+    /// follow-up again instead of downloading.
     pub async fn retry_download(&self, task_id: &str) -> Result<()> {
         let task = self.get_task(task_id).await?;
 
@@ -329,6 +792,22 @@ impl PersistentDownloadManager {
             ));
         }
 
+        if task.follow_up.is_some()
+            && task.total_bytes > 0
+            && task.bytes_downloaded >= task.total_bytes
+            && fs::metadata(&task.download_path).await.is_ok()
+        {
+            sqlx::query(
+                "UPDATE DownloadTasks SET status = ?, retry_count = retry_count + 1, error = NULL WHERE task_id = ?"
+            )
+            .bind(TaskStatus::Decrypting.as_str())
+            .bind(task_id)
+            .execute(&*self.pool)
+            .await?;
+            self.spawn_follow_up(task);
+            return Ok(());
+        }
+
         // Reset task state
         sqlx::query(
             "UPDATE DownloadTasks SET status = ?, retry_count = retry_count + 1, error = NULL WHERE task_id = ?"
@@ -343,12 +822,370 @@ impl PersistentDownloadManager {
         Ok(())
     }
 
+    /// Replace a task's download URL, e.g. after `DownloadUrlExpired`
+    ///
+    /// The downloaded bytes are kept; resume or retry the task afterwards to
+    /// continue from where it stopped. Mirrors from the old license expire
+    /// with it and are cleared; set the new license's with `set_mirror_urls`.
+    pub async fn update_download_url(&self, task_id: &str, download_url: &str) -> Result<()> {
+        let result = sqlx::query("UPDATE DownloadTasks SET download_url = ?, mirror_urls = NULL WHERE task_id = ?")
+            .bind(download_url)
+            .bind(task_id)
+            .execute(&*self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(LibationError::not_found(format!("Task not found: {}", task_id)));
+        }
+        Ok(())
+    }
+
+    /// Store alternate CDN URLs for a task's file
+    ///
+    /// A running download reads them when it needs a mirror, so they can be
+    /// set right after `enqueue_download`.
+    pub async fn set_mirror_urls(&self, task_id: &str, mirror_urls: &[String]) -> Result<()> {
+        let mirrors_json = serde_json::to_string(mirror_urls)
+            .map_err(|e| LibationError::InvalidInput(format!("Invalid mirror URLs: {}", e)))?;
+        let result = sqlx::query("UPDATE DownloadTasks SET mirror_urls = ? WHERE task_id = ?")
+            .bind(&mirrors_json)
+            .bind(task_id)
+            .execute(&*self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(LibationError::not_found(format!("Task not found: {}", task_id)));
+        }
+        Ok(())
+    }
+
+    /// Pin a task's buffering instead of following the manager's policy
+    ///
+    /// Applies from the task's next session (a running download keeps its
+    /// values until paused). Empty overrides go back to the manager's.
+    ///
+    /// # Errors
+    /// InvalidInput for out-of-range values, NotFound for unknown tasks
+    pub async fn set_task_buffering(&self, task_id: &str, overrides: BufferOverrides) -> Result<()> {
+        overrides.validate()?;
+        let overrides_json = if overrides.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&overrides)?)
+        };
+        let result = sqlx::query("UPDATE DownloadTasks SET buffer_overrides = ? WHERE task_id = ?")
+            .bind(&overrides_json)
+            .bind(task_id)
+            .execute(&*self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(LibationError::not_found(format!("Task not found: {}", task_id)));
+        }
+        Ok(())
+    }
+
+    /// Mark a task as fetching companion content of the book `parent_asin`
+    ///
+    /// When the task completes, its file is recorded with the book (see
+    /// `download::companion`); a task that already completed is recorded
+    /// right away.
+    ///
+    /// # Errors
+    /// NotFound for unknown tasks
+    pub async fn set_companion_of(&self, task_id: &str, parent_asin: &str, kind: CompanionKind) -> Result<()> {
+        let result = sqlx::query("UPDATE DownloadTasks SET companion_of = ?, companion_kind = ? WHERE task_id = ?")
+            .bind(parent_asin)
+            .bind(kind.as_str())
+            .bind(task_id)
+            .execute(&*self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(LibationError::not_found(format!("Task not found: {}", task_id)));
+        }
+        companion::record_companion_task(&self.pool, task_id).await?;
+        Ok(())
+    }
+
+    /// Set whether a task only downloads on Wi-Fi (None follows the
+    /// manager's `NetworkPolicy`)
+    ///
+    /// Applies right away: a running task the current network no longer
+    /// allows is paused until it does.
+    ///
+    /// # Errors
+    /// NotFound for unknown tasks
+    pub async fn set_task_wifi_only(&self, task_id: &str, wifi_only: Option<bool>) -> Result<NetworkTransition> {
+        let result = sqlx::query("UPDATE DownloadTasks SET wifi_only = ? WHERE task_id = ?")
+            .bind(wifi_only)
+            .bind(task_id)
+            .execute(&*self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(LibationError::not_found(format!("Task not found: {}", task_id)));
+        }
+        self.apply_network().await
+    }
+
+    /// Record the network the device is on, pausing running tasks it
+    /// doesn't allow and requeueing network-paused tasks it does
+    pub async fn set_network_state(&self, state: NetworkState) -> Result<NetworkTransition> {
+        *self.network_state.write().unwrap() = state;
+        self.apply_network().await
+    }
+
+    /// Network last reported by the app
+    pub fn network_state(&self) -> NetworkState {
+        *self.network_state.read().unwrap()
+    }
+
+    /// Store the network policy for tasks without their own `wifi_only`
+    /// and apply it to the current network
+    pub async fn set_network_policy(&self, policy: NetworkPolicy) -> Result<NetworkTransition> {
+        network_policy::set_network_policy(&self.pool, &policy).await?;
+        *self.network_policy.write().unwrap() = policy;
+        self.apply_network().await
+    }
+
+    /// Current network policy
+    pub fn network_policy(&self) -> NetworkPolicy {
+        *self.network_policy.read().unwrap()
+    }
+
+    /// Export unfinished tasks with the conversion policy and download quotas
+    ///
+    /// Queued, downloading, paused, failed and awaiting-conversion tasks are
+    /// exported oldest first (the order they would start in), one entry per
+    /// ASIN. URLs, headers, keys and paths are left out.
+    pub async fn export_queue(&self) -> Result<QueueExport> {
+        let rows = sqlx::query(
+            "SELECT asin, title, status, account FROM DownloadTasks WHERE status IN (?, ?, ?, ?, ?) ORDER BY created_at ASC, rowid ASC"
+        )
+        .bind(TaskStatus::Queued.as_str())
+        .bind(TaskStatus::Downloading.as_str())
+        .bind(TaskStatus::Paused.as_str())
+        .bind(TaskStatus::Failed.as_str())
+        .bind(TaskStatus::AwaitingConversion.as_str())
+        .fetch_all(&*self.pool)
+        .await?;
+
+        let mut items: Vec<QueuedItem> = Vec::new();
+        for row in rows {
+            let asin: String = row.try_get("asin")?;
+            if items.iter().any(|item| item.asin == asin) {
+                continue;
+            }
+            let status: String = row.try_get("status")?;
+            items.push(QueuedItem {
+                asin,
+                title: row.try_get("title")?,
+                paused: status == TaskStatus::Paused.as_str(),
+                account: row.try_get("account")?,
+            });
+        }
+
+        Ok(QueueExport {
+            version: QUEUE_EXPORT_VERSION,
+            exported_at: self.clock.now().to_rfc3339(),
+            items,
+            conversion_policy: self.conversion_policy(),
+            download_quotas: quota::list_download_quotas(&self.pool).await?,
+        })
+    }
+
+    /// Import an exported queue, re-requesting a license for every title
+    ///
+    /// The conversion policy and quotas are applied first, so imported titles
+    /// are checked against the imported caps. Each title must be in the
+    /// library, downloadable, and neither queued nor downloaded already;
+    /// `resolve` is then called to request a fresh license and pick local
+    /// paths. Titles failing any step are skipped with the reason.
+    ///
+    /// # Errors
+    /// Only database errors; per-title failures go into the report
+    pub async fn import_queue<F, Fut>(&self, export: &QueueExport, mut resolve: F) -> Result<QueueImportReport>
+    where
+        F: FnMut(&QueuedItem) -> Fut,
+        Fut: std::future::Future<Output = Result<ResolvedDownload>>,
+    {
+        self.set_conversion_policy(export.conversion_policy);
+        for cap in &export.download_quotas {
+            quota::set_download_quota(&self.pool, &cap.account, Some(cap.monthly_cap_bytes), cap.action).await?;
+        }
+
+        let mut report = QueueImportReport::default();
+        for item in &export.items {
+            let in_library: Option<i64> =
+                sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+                    .bind(&item.asin)
+                    .fetch_optional(&*self.pool)
+                    .await?;
+            if in_library.is_none() {
+                report.skip(&item.asin, "Not in library");
+                continue;
+            }
+
+            let existing: Option<String> = sqlx::query_scalar(
+                "SELECT status FROM DownloadTasks WHERE asin = ? AND status NOT IN (?, ?) LIMIT 1"
+            )
+            .bind(&item.asin)
+            .bind(TaskStatus::Failed.as_str())
+            .bind(TaskStatus::Cancelled.as_str())
+            .fetch_optional(&*self.pool)
+            .await?;
+            if let Some(status) = existing {
+                let reason = if status == TaskStatus::Completed.as_str() {
+                    "Already downloaded"
+                } else {
+                    "Already queued"
+                };
+                report.skip(&item.asin, reason);
+                continue;
+            }
+
+            let resolved = match resolve(item).await {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    report.skip(&item.asin, format!("License request failed: {}", e));
+                    continue;
+                }
+            };
+
+            let task_id = match self.enqueue_download(
+                item.asin.clone(),
+                item.title.clone(),
+                resolved.download_url,
+                resolved.total_bytes,
+                resolved.download_path,
+                resolved.output_path,
+                resolved.request_headers,
+            ).await {
+                Ok(task_id) => task_id,
+                Err(e) => {
+                    report.skip(&item.asin, e.to_string());
+                    continue;
+                }
+            };
+
+            if !resolved.mirror_urls.is_empty() {
+                self.set_mirror_urls(&task_id, &resolved.mirror_urls).await?;
+            }
+            if let Some(keys) = &resolved.conversion_keys {
+                self.store_conversion_keys(
+                    &task_id,
+                    keys.aaxc_key.expose_secret(),
+                    keys.aaxc_iv.expose_secret(),
+                    &keys.output_directory,
+                )
+                .await?;
+            }
+            if item.paused {
+                self.pause_download(&task_id).await?;
+            }
+            report.enqueued.push(task_id);
+        }
+
+        Ok(report)
+    }
+
     /// Register a progress callback for a task
     pub async fn register_progress_callback(&self, task_id: String, callback: ProgressCallback) {
         let mut callbacks = self.progress_callbacks.write().await;
         callbacks.insert(task_id, callback);
     }
 
+    /// Apply device conditions to the download concurrency
+    ///
+    /// Running downloads are never interrupted; a lower limit only stops new
+    /// ones from starting. When the limit rises, queued downloads start.
+    pub async fn apply_device_conditions(
+        &self,
+        conditions: &DeviceConditions,
+        policy: &AdaptivePolicy,
+    ) -> Result<ResourceLimits> {
+        let limits = policy.limits(conditions, self.max_concurrent);
+        {
+            let mut gate = self.conversion_gate.write().unwrap();
+            gate.conditions = *conditions;
+            gate.adaptive_allows = limits.allow_conversion;
+        }
+        let previous = self
+            .concurrency_limit
+            .swap(limits.max_concurrent_downloads, Ordering::SeqCst);
+
+        for _ in previous..limits.max_concurrent_downloads {
+            self.try_start_next_download().await?;
+        }
+
+        Ok(limits)
+    }
+
+    /// Concurrency currently allowed by device conditions
+    pub fn current_concurrency_limit(&self) -> usize {
+        self.concurrency_limit.load(Ordering::SeqCst)
+    }
+
+    /// Set when conversions may run after a download completes
+    pub fn set_conversion_policy(&self, policy: ConversionPolicy) {
+        self.conversion_gate.write().unwrap().policy = policy;
+    }
+
+    /// Current conversion policy
+    pub fn conversion_policy(&self) -> ConversionPolicy {
+        self.conversion_gate.read().unwrap().policy
+    }
+
+    /// Set write buffering for downloads started from now on (see
+    /// `download::buffering`); tasks with overrides keep those values
+    ///
+    /// # Errors
+    /// InvalidInput if a value is outside the supported range
+    pub fn set_buffer_policy(&self, policy: BufferPolicy) -> Result<()> {
+        policy.validate()?;
+        *self.buffer_policy.write().unwrap() = policy;
+        Ok(())
+    }
+
+    /// Current buffering policy
+    pub fn buffer_policy(&self) -> BufferPolicy {
+        *self.buffer_policy.read().unwrap()
+    }
+
+    /// Hand downloads parked in `AwaitingConversion` back for conversion
+    ///
+    /// Does nothing unless the conversion policy and device conditions allow
+    /// conversion now. Returned tasks are moved to `Decrypting`; the caller
+    /// is expected to start converting them right away (an app restart
+    /// mid-conversion fails them with their keys kept for retry). Tasks
+    /// with a follow-up are run by the manager and not returned.
+    pub async fn process_pending_conversions(&self) -> Result<Vec<DownloadTask>> {
+        if !self.conversion_gate.read().unwrap().allows_now(self.clock.as_ref()) {
+            return Ok(Vec::new());
+        }
+
+        let rows = sqlx::query(
+            "UPDATE DownloadTasks SET status = ? WHERE status = ? RETURNING *"
+        )
+        .bind(TaskStatus::Decrypting.as_str())
+        .bind(TaskStatus::AwaitingConversion.as_str())
+        .fetch_all(&*self.pool)
+        .await?;
+
+        let mut tasks = rows
+            .into_iter()
+            .map(|row| self.row_to_task(row))
+            .collect::<Result<Vec<_>>>()?;
+        tasks.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+        let (chained, tasks): (Vec<_>, Vec<_>) = tasks.into_iter().partition(|t| t.follow_up.is_some());
+        for task in chained {
+            self.spawn_follow_up(task);
+        }
+        Ok(tasks)
+    }
+
     /// Resume all paused/queued downloads on app restart
     pub async fn resume_all_pending(&self) -> Result<()> {
         // Update any "downloading" tasks to "queued" (these were interrupted)
@@ -358,10 +1195,20 @@ impl PersistentDownloadManager {
             .execute(&*self.pool)
             .await?;
 
+        // Follow-ups interrupted mid-way run again from the start
+        let rows = sqlx::query("SELECT * FROM DownloadTasks WHERE status = ? AND follow_up IS NOT NULL")
+            .bind(TaskStatus::Decrypting.as_str())
+            .fetch_all(&*self.pool)
+            .await?;
+        for row in rows {
+            let task = self.row_to_task(row)?;
+            self.spawn_follow_up(task);
+        }
+
         // Tasks stuck in conversion stages on restart → mark as failed
         // (in-memory conversion state is lost on restart)
         for stuck_status in &[TaskStatus::Decrypting, TaskStatus::Validating, TaskStatus::Copying] {
-            sqlx::query("UPDATE DownloadTasks SET status = ?, error = ? WHERE status = ?")
+            sqlx::query("UPDATE DownloadTasks SET status = ?, error = ? WHERE status = ? AND follow_up IS NULL")
                 .bind(TaskStatus::Failed.as_str())
                 .bind("Interrupted: app was closed during conversion")
                 .bind(stuck_status.as_str())
@@ -383,18 +1230,82 @@ impl PersistentDownloadManager {
     // Internal Methods
     // ========================================================================
 
+    /// Pause running tasks the network doesn't allow, requeue
+    /// network-paused tasks it does, and fill the free slots
+    async fn apply_network(&self) -> Result<NetworkTransition> {
+        let state = self.network_state();
+        let default_wifi_only = self.network_policy().wifi_only;
+        let mut transition = NetworkTransition::default();
+
+        let running: Vec<String> = self.active_downloads.read().await.keys().cloned().collect();
+        for task_id in running {
+            let wifi_only: Option<Option<bool>> =
+                sqlx::query_scalar("SELECT wifi_only FROM DownloadTasks WHERE task_id = ?")
+                    .bind(&task_id)
+                    .fetch_optional(&*self.pool)
+                    .await?;
+            let Some(wifi_only) = wifi_only else { continue };
+            if !state.allows(wifi_only.unwrap_or(default_wifi_only)) {
+                self.pause_download(&task_id).await?;
+                self.set_network_paused(&task_id, true).await?;
+                transition.paused.push(task_id);
+            }
+        }
+
+        let waiting: Vec<(String, Option<bool>)> = sqlx::query_as(
+            "SELECT task_id, wifi_only FROM DownloadTasks WHERE status = ? AND network_paused = 1 \
+             ORDER BY created_at ASC, rowid ASC"
+        )
+        .bind(TaskStatus::Paused.as_str())
+        .fetch_all(&*self.pool)
+        .await?;
+        for (task_id, wifi_only) in waiting {
+            if state.allows(wifi_only.unwrap_or(default_wifi_only)) {
+                sqlx::query("UPDATE DownloadTasks SET status = ?, network_paused = 0 WHERE task_id = ?")
+                    .bind(TaskStatus::Queued.as_str())
+                    .bind(&task_id)
+                    .execute(&*self.pool)
+                    .await?;
+                transition.resumed.push(task_id);
+            }
+        }
+
+        for _ in 0..self.max_concurrent {
+            self.try_start_next_download().await?;
+        }
+        Ok(transition)
+    }
+
+    async fn set_network_paused(&self, task_id: &str, paused: bool) -> Result<()> {
+        sqlx::query("UPDATE DownloadTasks SET network_paused = ? WHERE task_id = ?")
+            .bind(paused)
+            .bind(task_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
     /// Try to start the next queued download if slots available
     async fn try_start_next_download(&self) -> Result<()> {
-        // Check if we have capacity
-        if self.get_active_count().await >= self.max_concurrent {
+        // Check if we have capacity (device conditions may lower the limit)
+        if self.get_active_count().await >= self.current_concurrency_limit() {
+            return Ok(());
+        }
+
+        // Nothing downloads offline; on cellular, Wi-Fi-only tasks wait
+        let state = self.network_state();
+        if !state.allows(false) {
             return Ok(());
         }
 
-        // Get next queued task
+        // Get next queued task the network allows
         let row = sqlx::query(
-            "SELECT * FROM DownloadTasks WHERE status = ? ORDER BY created_at ASC LIMIT 1"
+            "SELECT * FROM DownloadTasks WHERE status = ? AND (? OR COALESCE(wifi_only, ?) = 0) \
+             ORDER BY created_at ASC, rowid ASC LIMIT 1"
         )
         .bind(TaskStatus::Queued.as_str())
+        .bind(state.allows(true))
+        .bind(self.network_policy().wifi_only)
         .fetch_optional(&*self.pool)
         .await?;
 
@@ -413,59 +1324,125 @@ impl PersistentDownloadManager {
         let semaphore = Arc::clone(&self.semaphore);
         let callbacks = Arc::clone(&self.progress_callbacks);
         let active = Arc::clone(&self.active_downloads);
-
-        // Create cancellation channel
-        let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
-
-        // Spawn worker
-        let handle = tokio::spawn(async move {
-            // Acquire semaphore permit
-            let _permit = semaphore.acquire().await.unwrap();
-
-            // Run download
-            let result = Self::download_worker(
-                task.clone(),
-                pool.clone(),
-                callbacks.clone(),
-                cancel_rx,
-            ).await;
+        let settings = WorkerSettings {
+            chunk_size: self.chunk_size,
+            cdn_policy: self.cdn_policy,
+            buffer_policy: self.buffer_policy(),
+        };
+        let watchdog = Arc::clone(&self.watchdog);
+        let conversion_gate = Arc::clone(&self.conversion_gate);
+        let clock = Arc::clone(&self.clock);
+
+        // Time spent waiting for a permit counts toward the stall threshold
+        watchdog.touch(&task_id);
+
+        // Cancelled by pause_download / cancel_download
+        let cancel = CancellationToken::new();
+        let worker_cancel = cancel.clone();
+
+        // Spawn worker, under the trace of the call that enqueued it
+        let trace_id = task.trace_id.clone();
+        let handle = tokio::spawn(crate::trace::with_trace(trace_id, async move {
+            // Queued downloads keep the host awake too; dropped on abort
+            let work = activity::begin_work(WorkType::Download, None);
+
+            // Acquire semaphore permit; a task paused while waiting never starts
+            let Ok(permit) = worker_cancel.run_until_cancelled(semaphore.acquire()).await else {
+                return;
+            };
+            let _permit = permit.unwrap();
+
+            // Run download (an expired signed URL would only get a 403);
+            // the worker keeps `task` current for the callbacks below
+            let mut task = task;
+            let result = match check_download_url(&task.asin, &task.download_url, clock.as_ref()) {
+                Ok(()) => Self::download_worker(
+                    &mut task,
+                    pool.clone(),
+                    callbacks.clone(),
+                    worker_cancel.clone(),
+                    settings,
+                    watchdog.clone(),
+                    &work,
+                ).await,
+                Err(e) => Err(e),
+            };
 
             // Handle result
             match result {
                 Ok(()) => {
+                    // Tasks with conversion keys still need decrypting; park
+                    // them if the conversion policy says not now
+                    let needs_conversion: bool = sqlx::query_scalar(
+                        "SELECT aaxc_key IS NOT NULL FROM DownloadTasks WHERE task_id = ?"
+                    )
+                    .bind(&task.task_id)
+                    .fetch_one(&*pool)
+                    .await
+                    .unwrap_or(false);
+                    let status = if needs_conversion
+                        && !conversion_gate.read().unwrap().convert_on_completion(clock.as_ref())
+                    {
+                        TaskStatus::AwaitingConversion
+                    } else if task.follow_up.is_some() {
+                        TaskStatus::Decrypting
+                    } else {
+                        TaskStatus::Completed
+                    };
+
                     // Mark as completed
                     let _ = sqlx::query(
                         "UPDATE DownloadTasks SET status = ?, completed_at = ? WHERE task_id = ?"
                     )
-                    .bind(TaskStatus::Completed.as_str())
-                    .bind(chrono::Utc::now().to_rfc3339())
+                    .bind(status.as_str())
+                    .bind(crate::storage::dates::format_timestamp(clock.now()))
                     .bind(&task.task_id)
                     .execute(&*pool)
                     .await;
+                    if status == TaskStatus::Completed {
+                        if let Err(e) = companion::record_companion_task(&pool, &task.task_id).await {
+                            trace_eprintln!("Failed to record companion file of {}: {}", task.task_id, e);
+                        }
+                    }
+                    events::emit_outcome(&pool, clock.as_ref(), JobKind::Download, &task.task_id, Some(&task.asin), &Ok(())).await;
+                    #[cfg(feature = "telemetry")]
+                    crate::telemetry::record_outcome(&pool, JobKind::Download, &Ok(())).await;
+
+                    // The chain continues without the app
+                    task.status = status;
+                    if task.status == TaskStatus::Decrypting {
+                        Self::finish_follow_up(&pool, clock.as_ref(), &mut task, &worker_cancel).await;
+                    }
 
                     // Notify callback
                     if let Some(cb) = callbacks.read().await.get(&task.task_id) {
-                        let mut completed_task = task.clone();
-                        completed_task.status = TaskStatus::Completed;
-                        cb(completed_task);
+                        cb(task.clone());
                     }
                 }
+                // Paused or cancelled: pause_download / cancel_download
+                // record the outcome
+                Err(LibationError::Cancelled) => {}
                 Err(e) => {
                     // Mark as failed
+                    let error = e.to_string();
                     let _ = sqlx::query(
                         "UPDATE DownloadTasks SET status = ?, error = ? WHERE task_id = ?"
                     )
                     .bind(TaskStatus::Failed.as_str())
-                    .bind(e.to_string())
+                    .bind(&error)
                     .bind(&task.task_id)
                     .execute(&*pool)
                     .await;
+                    let outcome = Err::<(), _>(e);
+                    #[cfg(feature = "telemetry")]
+                    crate::telemetry::record_outcome(&pool, JobKind::Download, &outcome).await;
+                    events::emit_outcome(&pool, clock.as_ref(), JobKind::Download, &task.task_id, Some(&task.asin), &outcome).await;
 
                     // Notify callback
                     if let Some(cb) = callbacks.read().await.get(&task.task_id) {
                         let mut failed_task = task.clone();
                         failed_task.status = TaskStatus::Failed;
-                        failed_task.error = Some(e.to_string());
+                        failed_task.error = Some(error);
                         cb(failed_task);
                     }
                 }
@@ -473,77 +1450,145 @@ impl PersistentDownloadManager {
 
             // Remove from active
             active.write().await.remove(&task.task_id);
+            watchdog.remove(&task.task_id);
 
             // Try to start next download
             // (Note: This requires access to the manager, which we don't have here)
-        });
+        }));
 
         // Store active download
         let mut active_map = self.active_downloads.write().await;
-        active_map.insert(task_id, ActiveDownload { handle, cancel_tx });
+        active_map.insert(task_id, ActiveDownload { handle, cancel });
+    }
+
+    /// Run the follow-up of a task in `Decrypting` in the background
+    fn spawn_follow_up(&self, task: DownloadTask) {
+        let pool = Arc::clone(&self.pool);
+        let callbacks = Arc::clone(&self.progress_callbacks);
+        let clock = Arc::clone(&self.clock);
+        let trace_id = task.trace_id.clone();
+        tokio::spawn(crate::trace::with_trace(trace_id, async move {
+            let mut task = task;
+            task.status = TaskStatus::Decrypting;
+            Self::finish_follow_up(&pool, clock.as_ref(), &mut task, &CancellationToken::new()).await;
+            if let Some(cb) = callbacks.read().await.get(&task.task_id) {
+                cb(task.clone());
+            }
+        }));
+    }
+
+    /// Run a downloaded task's follow-up and record how it ended
+    ///
+    /// The task ends `Completed` with `output_path` set to the finished
+    /// file, or `Failed` with its keys kept for retry; `task` is updated
+    /// to match. A cancelled follow-up is left to whoever cancelled it.
+    async fn finish_follow_up(pool: &SqlitePool, clock: &dyn Clock, task: &mut DownloadTask, cancel: &CancellationToken) {
+        let Some(follow_up) = task.follow_up.clone() else { return };
+        let _work = activity::begin_work(WorkType::Decryption, None);
+
+        let outcome = follow_up::run_follow_up(
+            pool,
+            &task.task_id,
+            &task.asin,
+            &task.download_path,
+            &task.output_path,
+            &follow_up,
+            cancel,
+        )
+        .await;
+        match &outcome {
+            Ok(output_path) => {
+                let _ = sqlx::query(
+                    "UPDATE DownloadTasks SET status = ?, output_path = ?, error = NULL, completed_at = ? WHERE task_id = ?"
+                )
+                .bind(TaskStatus::Completed.as_str())
+                .bind(output_path)
+                .bind(crate::storage::dates::format_timestamp(clock.now()))
+                .bind(&task.task_id)
+                .execute(pool)
+                .await;
+                task.status = TaskStatus::Completed;
+                task.output_path = output_path.clone();
+                task.error = None;
+                if let Err(e) = companion::record_companion_task(pool, &task.task_id).await {
+                    trace_eprintln!("Failed to record companion file of {}: {}", task.task_id, e);
+                }
+            }
+            Err(LibationError::Cancelled) => return,
+            Err(e) => {
+                let error = format!("Follow-up failed: {}", e);
+                let _ = sqlx::query("UPDATE DownloadTasks SET status = ?, error = ? WHERE task_id = ?")
+                    .bind(TaskStatus::Failed.as_str())
+                    .bind(&error)
+                    .bind(&task.task_id)
+                    .execute(pool)
+                    .await;
+                task.status = TaskStatus::Failed;
+                task.error = Some(error);
+            }
+        }
+        events::emit_outcome(pool, clock, JobKind::Decryption, &task.task_id, Some(&task.asin), &outcome).await;
     }
 
     /// Download worker coroutine
     async fn download_worker(
-        mut task: DownloadTask,
+        task: &mut DownloadTask,
         pool: Arc<SqlitePool>,
         callbacks: Arc<RwLock<HashMap<String, ProgressCallback>>>,
-        mut cancel_rx: tokio::sync::oneshot::Receiver<()>,
+        cancel: CancellationToken,
+        settings: WorkerSettings,
+        watchdog: Arc<ProgressWatchdog>,
+        work: &WorkGuard,
     ) -> Result<()> {
         // Update status to downloading
         sqlx::query(
             "UPDATE DownloadTasks SET status = ?, started_at = COALESCE(started_at, ?) WHERE task_id = ?"
         )
         .bind(TaskStatus::Downloading.as_str())
-        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(crate::storage::dates::now())
         .bind(&task.task_id)
         .execute(&*pool)
         .await?;
 
         task.status = TaskStatus::Downloading;
 
-        // Create HTTP client
-        let client = reqwest::Client::new();
-
-        // Build request with headers
-        let mut request = client.get(&task.download_url);
-        for (key, value) in &task.request_headers {
-            request = request.header(key, value);
+        // A partial file that's gone (storage cleared, app reinstalled)
+        // can only be downloaded again from the start
+        if task.bytes_downloaded > 0 && fs::metadata(&task.download_path).await.is_err() {
+            trace_eprintln!("⚠️  Partial file for {} is missing, restarting the download", task.asin);
+            Self::rewind_progress(&pool, &callbacks, task, 0).await?;
         }
 
-        // Add Range header for resumption
-        if task.bytes_downloaded > 0 {
-            request = request.header("Range", format!("bytes={}-", task.bytes_downloaded));
-        }
-
-        // Send request
-        let response = request.send().await
-            .map_err(|e| LibationError::NetworkError {
-                message: format!("Request failed: {}", e),
-                is_transient: true,
-            })?;
-
-        if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
-            return Err(LibationError::NetworkError {
-                message: format!("HTTP {}", response.status()),
-                is_transient: false,
-            });
-        }
+        // Verify the tail of the partial file against its chunk manifest
+        // before trusting bytes_downloaded for the Range request
+        let mut hasher = if let Some(mut manifest) = task.chunk_manifest.take() {
+            if task.bytes_downloaded > 0 {
+                let verified = manifest
+                    .verify_file(Path::new(&task.download_path), task.bytes_downloaded)
+                    .await?;
 
-        // Update total_bytes from Content-Length if not already known
-        if task.total_bytes == 0 {
-            if let Some(content_length) = response.content_length() {
-                let total = content_length + task.bytes_downloaded;
-                task.total_bytes = total;
-                sqlx::query(
-                    "UPDATE DownloadTasks SET total_bytes = ? WHERE task_id = ?"
-                )
-                .bind(total as i64)
-                .bind(&task.task_id)
-                .execute(&*pool)
-                .await?;
+                if verified < task.bytes_downloaded {
+                    trace_eprintln!(
+                        "⚠️  Chunk verification for {}: resuming from {} instead of {} bytes",
+                        task.asin, verified, task.bytes_downloaded
+                    );
+                    sqlx::query("UPDATE DownloadTasks SET chunk_manifest = ? WHERE task_id = ?")
+                        .bind(manifest.to_json()?)
+                        .bind(&task.task_id)
+                        .execute(&*pool)
+                        .await?;
+                    Self::rewind_progress(&pool, &callbacks, task, verified).await?;
+                }
+            } else {
+                manifest.hashes.clear();
             }
-        }
+            Some(ChunkHasher::from_manifest(manifest))
+        } else if task.bytes_downloaded == 0 {
+            settings.chunk_size.map(ChunkHasher::new)
+        } else {
+            // Started before hashing was enabled; earlier bytes can't be hashed
+            None
+        };
 
         // CRITICAL: Verify file size matches bytes_downloaded before resuming
         if task.bytes_downloaded > 0 {
@@ -551,28 +1596,19 @@ impl PersistentDownloadManager {
                 let actual_size = metadata.len();
 
                 if actual_size != task.bytes_downloaded {
-                    eprintln!(
+                    trace_eprintln!(
                         "⚠️  File size mismatch for {}: database says {} bytes, file has {} bytes",
                         task.asin, task.bytes_downloaded, actual_size
                     );
 
                     // Handle mismatch
                     if actual_size < task.bytes_downloaded {
-                        // File is smaller - update bytes_downloaded to match reality
-                        eprintln!("   → Correcting bytes_downloaded to match actual file size: {}", actual_size);
-                        task.bytes_downloaded = actual_size;
-
-                        // Update database
-                        sqlx::query(
-                            "UPDATE DownloadTasks SET bytes_downloaded = ? WHERE task_id = ?"
-                        )
-                        .bind(actual_size as i64)
-                        .bind(&task.task_id)
-                        .execute(&*pool)
-                        .await?;
+                        // File is smaller - resume from what's really there
+                        trace_eprintln!("   → Correcting bytes_downloaded to match actual file size: {}", actual_size);
+                        Self::rewind_progress(&pool, &callbacks, task, actual_size).await?;
                     } else {
                         // File is larger - truncate to expected size
-                        eprintln!("   → Truncating file from {} to {} bytes", actual_size, task.bytes_downloaded);
+                        trace_eprintln!("   → Truncating file from {} to {} bytes", actual_size, task.bytes_downloaded);
                         let mut file = fs::OpenOptions::new()
                             .write(true)
                             .open(&task.download_path)
@@ -583,8 +1619,12 @@ impl PersistentDownloadManager {
             }
         }
 
+        // The digest covers the whole file, so a resume re-reads the part
+        // already on disk once
+        let mut sha256 = Self::hash_file_prefix(Path::new(&task.download_path), task.bytes_downloaded).await?;
+
         // Open file for writing (append mode if resuming)
-        let mut file = if task.bytes_downloaded > 0 {
+        let file = if task.bytes_downloaded > 0 {
             fs::OpenOptions::new()
                 .write(true)
                 .append(true)
@@ -594,60 +1634,417 @@ impl PersistentDownloadManager {
             fs::File::create(&task.download_path).await?
         };
 
-        // Download stream
-        let mut stream = response.bytes_stream();
+        // Writes are buffered; progress is only stored for flushed bytes
+        let mut tuner = BufferTuner::new(settings.buffer_policy, task.buffer_overrides, std::time::Instant::now());
+        Self::store_buffering(&pool, task, tuner.current()).await?;
+        let mut file = BufWriter::with_capacity(tuner.current().buffer_size, file);
+        let mut unflushed: u64 = 0;
+
+        // Create HTTP client
+        let client = reqwest::Client::new();
+
+        // URLs used so far; each mirror is tried at most once per session
+        let mut download_url = task.download_url.clone();
+        let mut tried = vec![download_url.clone()];
+        // Stalls on the current URL, and in the whole session
+        let mut url_stalls: u32 = 0;
+        let mut stalls: u32 = 0;
+
         let mut last_update = tokio::time::Instant::now();
+        let mut session_start = (last_update, task.bytes_downloaded);
+        // File length the last response will leave, when the server says
+        let mut expected_len: Option<u64>;
+        // Bytes not yet added to the account's monthly usage
+        let mut unrecorded: u64 = 0;
+
+        'mirrors: loop {
+            let response = match Self::send_download_request(&client, task, &download_url).await {
+                Ok(response) => response,
+                Err(e) => match Self::next_mirror(&pool, &task.task_id, &tried).await? {
+                    Some(mirror) => {
+                        trace_eprintln!("⚠️  Download of {} failed ({}), trying mirror {}", task.asin, e, mirror);
+                        tried.push(mirror.clone());
+                        download_url = mirror;
+                        continue;
+                    }
+                    None => return Err(e),
+                },
+            };
+
+            // A server that ignored the Range header sends the whole file:
+            // start the file over instead of appending a second copy
+            let start = resume_start(
+                task.bytes_downloaded,
+                task.total_bytes,
+                response.status(),
+                response.headers().get(reqwest::header::CONTENT_RANGE).and_then(|v| v.to_str().ok()),
+            )?;
+            if start == ResumeStart::Restart {
+                trace_eprintln!(
+                    "⚠️  {} ignored the Range request for {}, restarting from 0",
+                    cdn::cdn_host(response.url().as_str()).as_deref().unwrap_or("CDN"), task.asin
+                );
+                // A file opened with `File::create` isn't in append mode:
+                // move the cursor back too, or the body lands after a hole
+                file.flush().await?;
+                file.get_mut().set_len(0).await?;
+                file.get_mut().seek(std::io::SeekFrom::Start(0)).await?;
+                unflushed = 0;
+                sha256 = Sha256::new();
+                hasher = hasher.map(|h| ChunkHasher::new(h.manifest().chunk_size));
+                Self::rewind_progress(&pool, &callbacks, task, 0).await?;
+                Self::store_progress(&pool, task, &hasher).await?;
+                session_start = (tokio::time::Instant::now(), 0);
+            }
+            expected_len = response.content_length().map(|len| task.bytes_downloaded + len);
+
+            // Record the CDN serving the task (after redirects), and keep
+            // the working URL for a later resume
+            let served_by = response.url().to_string();
+            task.cdn_host = cdn::cdn_host(&served_by);
+            task.download_url = download_url.clone();
+            tried.push(served_by);
+            sqlx::query(
+                "UPDATE DownloadTasks SET download_url = ?, cdn_host = ? WHERE task_id = ?"
+            )
+            .bind(&task.download_url)
+            .bind(&task.cdn_host)
+            .bind(&task.task_id)
+            .execute(&*pool)
+            .await?;
 
-        while let Some(chunk_result) = tokio::select! {
-            chunk = stream.next() => chunk,
-            _ = &mut cancel_rx => {
-                // Cancelled
-                return Ok(());
+            // Update total_bytes from Content-Length if not already known
+            if task.total_bytes == 0 {
+                if let Some(content_length) = response.content_length() {
+                    let total = content_length + task.bytes_downloaded;
+                    task.total_bytes = total;
+                    sqlx::query(
+                        "UPDATE DownloadTasks SET total_bytes = ? WHERE task_id = ?"
+                    )
+                    .bind(total as i64)
+                    .bind(&task.task_id)
+                    .execute(&*pool)
+                    .await?;
+                }
             }
-        } {
-            let chunk = chunk_result.map_err(|e| LibationError::NetworkError {
-                message: format!("Stream error: {}", e),
-                is_transient: true,
-            })?;
 
-            // Write chunk
-            file.write_all(&chunk).await?;
-            task.bytes_downloaded += chunk.len() as u64;
+            // Download stream
+            let mut stream = response.bytes_stream();
+            let mut monitor = ThroughputMonitor::new(settings.cdn_policy, std::time::Instant::now());
+
+            loop {
+                let next = tokio::select! {
+                    chunk = tokio::time::timeout(settings.cdn_policy.stall_timeout, stream.next()) => chunk,
+                    _ = cancel.cancelled() => {
+                        // Paused or cancelled; keep what was received for a
+                        // resume, the caller sets the final status
+                        file.flush().await?;
+                        Self::store_progress(&pool, task, &hasher).await?;
+                        Self::record_usage(&pool, task, &mut unrecorded).await?;
+                        return Err(LibationError::Cancelled);
+                    }
+                };
+
+                // Nothing received for the stall timeout: the connection is
+                // dead, ask again from what was written
+                let Ok(next) = next else {
+                    file.flush().await?;
+                    Self::store_progress(&pool, task, &hasher).await?;
+                    url_stalls += 1;
+                    stalls += 1;
+
+                    if url_stalls <= settings.cdn_policy.max_stall_restarts {
+                        trace_eprintln!(
+                            "⚠️  Download of {} stalled at {} bytes, restarting the request",
+                            task.asin, task.bytes_downloaded
+                        );
+                        continue 'mirrors;
+                    }
+                    match Self::next_mirror(&pool, &task.task_id, &tried).await? {
+                        Some(mirror) => {
+                            trace_eprintln!(
+                                "⚠️  {} keeps stalling for {}, switching to mirror {}",
+                                task.cdn_host.as_deref().unwrap_or("CDN"), task.asin, mirror
+                            );
+                            tried.push(mirror.clone());
+                            download_url = mirror;
+                            url_stalls = 0;
+                            continue 'mirrors;
+                        }
+                        None => {
+                            Self::record_usage(&pool, task, &mut unrecorded).await?;
+                            return Err(LibationError::DownloadStalled {
+                                asin: task.asin.clone(),
+                                offset: task.bytes_downloaded,
+                                stalls,
+                            });
+                        }
+                    }
+                };
+                let Some(chunk_result) = next else {
+                    break;
+                };
+
+                let chunk = chunk_result.map_err(|e| LibationError::NetworkError {
+                    message: format!("Stream error: {}", e),
+                    is_transient: true,
+                })?;
+
+                // Write chunk
+                file.write_all(&chunk).await?;
+                task.bytes_downloaded += chunk.len() as u64;
+                unrecorded += chunk.len() as u64;
+                unflushed += chunk.len() as u64;
+                sha256.update(&chunk);
+                watchdog.touch(&task.task_id);
+                if let Some(ref mut hasher) = hasher {
+                    hasher.update(&chunk);
+                }
 
-            // Update database periodically (every 1 second)
-            if last_update.elapsed() >= tokio::time::Duration::from_secs(1) {
-                sqlx::query(
-                    "UPDATE DownloadTasks SET bytes_downloaded = ? WHERE task_id = ?"
-                )
-                .bind(task.bytes_downloaded as i64)
-                .bind(&task.task_id)
-                .execute(&*pool)
-                .await?;
+                // Switch CDNs when this one stays slow
+                if monitor.record(chunk.len() as u64, std::time::Instant::now()) {
+                    match Self::next_mirror(&pool, &task.task_id, &tried).await? {
+                        Some(mirror) => {
+                            trace_eprintln!(
+                                "⚠️  {} is slow for {}, switching to mirror {}",
+                                task.cdn_host.as_deref().unwrap_or("CDN"), task.asin, mirror
+                            );
+                            // The mirror's Range request continues from here
+                            file.flush().await?;
+                            Self::store_progress(&pool, task, &hasher).await?;
+
+                            tried.push(mirror.clone());
+                            download_url = mirror;
+                            continue 'mirrors;
+                        }
+                        // Nowhere to go; check again after another slow period
+                        None => monitor = ThroughputMonitor::new(settings.cdn_policy, std::time::Instant::now()),
+                    }
+                }
 
-                // Notify callback
-                if let Some(cb) = callbacks.read().await.get(&task.task_id) {
-                    cb(task.clone());
+                // Retune buffering to the measured throughput
+                if let Some(tuned) = tuner.record(chunk.len() as u64, std::time::Instant::now()) {
+                    file.flush().await?;
+                    unflushed = 0;
+                    file = BufWriter::with_capacity(tuned.buffer_size, file.into_inner());
+                    Self::store_buffering(&pool, task, tuned).await?;
+                }
+                if unflushed >= tuner.current().flush_interval {
+                    file.flush().await?;
+                    unflushed = 0;
                 }
 
-                last_update = tokio::time::Instant::now();
+                // Update database periodically (every 1 second)
+                if last_update.elapsed() >= tokio::time::Duration::from_secs(1) {
+                    // Stored progress never runs ahead of the file
+                    file.flush().await?;
+                    unflushed = 0;
+                    Self::store_progress(&pool, task, &hasher).await?;
+                    Self::record_usage(&pool, task, &mut unrecorded).await?;
+
+                    // Estimate remaining time from this session's average speed
+                    let speed = (task.bytes_downloaded - session_start.1) as f64
+                        / session_start.0.elapsed().as_secs_f64();
+                    if speed > 0.0 && task.total_bytes > task.bytes_downloaded {
+                        let remaining = (task.total_bytes - task.bytes_downloaded) as f64;
+                        work.set_expected_duration(Some(std::time::Duration::from_secs_f64(remaining / speed)));
+                    }
+
+                    // Notify callback
+                    if let Some(cb) = callbacks.read().await.get(&task.task_id) {
+                        cb(task.clone());
+                    }
+                    events::emit_progress(events::ProgressEvent::new(
+                        JobKind::Download,
+                        &task.task_id,
+                        Some(&task.asin),
+                        task.bytes_downloaded,
+                        task.total_bytes,
+                    ));
+
+                    last_update = tokio::time::Instant::now();
+                }
             }
+
+            break;
         }
 
         // Flush file
         file.flush().await?;
 
         // Final database update
+        Self::store_progress(&pool, task, &hasher).await?;
+
+        // A stream that ended early (or ran long) isn't a finished download;
+        // progress is kept, so a resume continues from the end of the file
+        let file_len = file.get_ref().metadata().await?.len();
+        if file_len != task.bytes_downloaded {
+            return Err(LibationError::DownloadFailed(format!(
+                "File has {} bytes, but {} were downloaded",
+                file_len, task.bytes_downloaded
+            )));
+        }
+        if let Some(expected) = expected_len.filter(|&expected| expected != file_len) {
+            return Err(LibationError::NetworkError {
+                message: format!("Download ended at {} of {} bytes", file_len, expected),
+                is_transient: true,
+            });
+        }
+
+        let digest = hex::encode(sha256.finalize());
+        sqlx::query("UPDATE DownloadTasks SET sha256 = ? WHERE task_id = ?")
+            .bind(&digest)
+            .bind(&task.task_id)
+            .execute(&*pool)
+            .await?;
+        task.sha256 = Some(digest);
+        Self::record_usage(&pool, task, &mut unrecorded).await?;
+
+        Ok(())
+    }
+
+    /// Request a task's file from `url`, resuming at `bytes_downloaded`
+    async fn send_download_request(
+        client: &reqwest::Client,
+        task: &DownloadTask,
+        url: &str,
+    ) -> Result<reqwest::Response> {
+        // Build request with headers
+        let mut request = client.get(url);
+        for (key, value) in &task.request_headers {
+            request = request.header(key, value.expose_secret());
+        }
+
+        // Add Range header for resumption
+        if task.bytes_downloaded > 0 {
+            request = request.header("Range", format!("bytes={}-", task.bytes_downloaded));
+        }
+
+        // Send request
+        let response = request.send().await
+            .map_err(|e| LibationError::NetworkError {
+                message: format!("Request failed: {}", e),
+                is_transient: true,
+            })?;
+
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(LibationError::NetworkError {
+                message: format!("HTTP {}", response.status()),
+                is_transient: false,
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Next untried mirror for a task
+    ///
+    /// Read from the database each time, so mirrors set after the download
+    /// started are used too.
+    async fn next_mirror(pool: &SqlitePool, task_id: &str, tried: &[String]) -> Result<Option<String>> {
+        let mirrors_json: Option<String> = sqlx::query_scalar(
+            "SELECT mirror_urls FROM DownloadTasks WHERE task_id = ?"
+        )
+        .bind(task_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+        let mirrors: Vec<String> = mirrors_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        Ok(cdn::next_mirror(&mirrors, tried).map(str::to_string))
+    }
+
+    /// SHA-256 state after the first `len` bytes of a partial download
+    async fn hash_file_prefix(path: &Path, len: u64) -> Result<Sha256> {
+        let mut sha256 = Sha256::new();
+        if len == 0 {
+            return Ok(sha256);
+        }
+
+        let mut file = fs::File::open(path).await?.take(len);
+        let mut buffer = vec![0u8; 1024 * 1024];
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            sha256.update(&buffer[..read]);
+        }
+        Ok(sha256)
+    }
+
+    /// Store a session's progress (bytes flushed to the file)
+    ///
+    /// Only moves bytes_downloaded forward within the task's epoch, so a
+    /// session stopped by a pause that finishes writing after the next
+    /// session started can't set it back.
+    async fn store_progress(pool: &SqlitePool, task: &DownloadTask, hasher: &Option<ChunkHasher>) -> Result<()> {
         sqlx::query(
-            "UPDATE DownloadTasks SET bytes_downloaded = ? WHERE task_id = ?"
+            "UPDATE DownloadTasks SET bytes_downloaded = ?, chunk_manifest = ? \
+             WHERE task_id = ? AND progress_epoch = ? AND bytes_downloaded <= ?"
         )
         .bind(task.bytes_downloaded as i64)
+        .bind(Self::manifest_json(hasher)?)
         .bind(&task.task_id)
-        .execute(&*pool)
+        .bind(task.progress_epoch as i64)
+        .bind(task.bytes_downloaded as i64)
+        .execute(pool)
         .await?;
+        Ok(())
+    }
+
+    /// Move a task's progress back to `bytes`, starting a new epoch
+    ///
+    /// The only way bytes_downloaded goes down. The progress callback gets
+    /// the reset right away, before any progress of the new epoch.
+    async fn rewind_progress(
+        pool: &SqlitePool,
+        callbacks: &RwLock<HashMap<String, ProgressCallback>>,
+        task: &mut DownloadTask,
+        bytes: u64,
+    ) -> Result<()> {
+        task.bytes_downloaded = bytes;
+        task.progress_epoch += 1;
+        sqlx::query("UPDATE DownloadTasks SET bytes_downloaded = ?, progress_epoch = ? WHERE task_id = ?")
+            .bind(bytes as i64)
+            .bind(task.progress_epoch as i64)
+            .bind(&task.task_id)
+            .execute(pool)
+            .await?;
+
+        if let Some(cb) = callbacks.read().await.get(&task.task_id) {
+            cb(task.clone());
+        }
+        Ok(())
+    }
 
+    /// Add bytes downloaded since the last call to the task's account usage
+    async fn record_usage(pool: &SqlitePool, task: &DownloadTask, unrecorded: &mut u64) -> Result<()> {
+        if let Some(account) = &task.account {
+            quota::record_usage(pool, account, *unrecorded).await?;
+        }
+        *unrecorded = 0;
+        Ok(())
+    }
+
+    /// Record the buffering a session is using, for task details
+    async fn store_buffering(pool: &SqlitePool, task: &mut DownloadTask, buffering: EffectiveBuffering) -> Result<()> {
+        task.buffering = Some(buffering);
+        sqlx::query("UPDATE DownloadTasks SET buffering = ? WHERE task_id = ?")
+            .bind(serde_json::to_string(&buffering)?)
+            .bind(&task.task_id)
+            .execute(pool)
+            .await?;
         Ok(())
     }
 
+    /// Serialize the hasher's manifest for the chunk_manifest column
+    fn manifest_json(hasher: &Option<ChunkHasher>) -> Result<Option<String>> {
+        hasher.as_ref().map(|h| h.manifest().to_json()).transpose()
+    }
+
     /// Update task status
     pub async fn update_task_status(&self, task_id: &str, status: TaskStatus) -> Result<()> {
         sqlx::query("UPDATE DownloadTasks SET status = ? WHERE task_id = ?")
@@ -722,12 +2119,29 @@ impl PersistentDownloadManager {
             }
         }
 
+        if status == TaskStatus::Completed {
+            companion::record_companion_task(&self.pool, task_id).await?;
+        }
+
         Ok(())
     }
 
     /// Store conversion keys and output directory for a task (enables retry without re-download)
+    ///
+    /// A book with its own output root (see `file::locations`) is converted
+    /// there instead of `output_directory`.
     pub async fn store_conversion_keys(&self, task_id: &str, aaxc_key: &str, aaxc_iv: &str, output_directory: &str) -> Result<()> {
-        sqlx::query("UPDATE DownloadTasks SET aaxc_key = ?, aaxc_iv = ?, output_directory = ? WHERE task_id = ?")
+        sqlx::query(
+            r#"
+            UPDATE DownloadTasks SET aaxc_key = ?, aaxc_iv = ?,
+                output_directory = COALESCE(
+                    (SELECT u.output_root FROM UserDefinedItems u JOIN Books b ON u.book_id = b.book_id
+                     WHERE b.audible_product_id = DownloadTasks.asin),
+                    ?
+                )
+            WHERE task_id = ?
+            "#,
+        )
             .bind(aaxc_key)
             .bind(aaxc_iv)
             .bind(output_directory)
@@ -741,12 +2155,25 @@ impl PersistentDownloadManager {
     /// Convert database row to DownloadTask
     fn row_to_task(&self, row: sqlx::sqlite::SqliteRow) -> Result<DownloadTask> {
         let headers_json: String = row.try_get("request_headers")?;
-        let request_headers: HashMap<String, String> = serde_json::from_str(&headers_json)
+        let request_headers: HashMap<String, SecretString> = serde_json::from_str(&headers_json)
             .unwrap_or_default();
 
         let status_str: String = row.try_get("status")?;
         let status = TaskStatus::from_str(&status_str)?;
 
+        let aaxc_key: Option<SecretString> = row.try_get("aaxc_key").ok();
+        let aaxc_iv: Option<SecretString> = row.try_get("aaxc_iv").ok();
+        // The stored follow-up leaves out its keys; they are the conversion keys
+        let mut follow_up: Option<FollowUp> = row
+            .try_get::<Option<String>, _>("follow_up")
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok());
+        if let Some(decrypt) = follow_up.as_mut().and_then(|f| f.decrypt.as_mut()) {
+            decrypt.aaxc_key = aaxc_key.clone().unwrap_or_default();
+            decrypt.aaxc_iv = aaxc_iv.clone().unwrap_or_default();
+        }
+
         Ok(DownloadTask {
             task_id: row.try_get("task_id")?,
             asin: row.try_get("asin")?,
@@ -763,9 +2190,46 @@ impl PersistentDownloadManager {
             created_at: row.try_get("created_at")?,
             started_at: row.try_get("started_at").ok(),
             completed_at: row.try_get("completed_at").ok(),
-            aaxc_key: row.try_get("aaxc_key").ok(),
-            aaxc_iv: row.try_get("aaxc_iv").ok(),
+            aaxc_key,
+            aaxc_iv,
             output_directory: row.try_get("output_directory").ok(),
+            account: row.try_get("account").ok(),
+            sha256: row.try_get("sha256").ok(),
+            chunk_manifest: row
+                .try_get::<Option<String>, _>("chunk_manifest")
+                .ok()
+                .flatten()
+                .and_then(|json| ChunkManifest::from_json(&json).ok()),
+            stalled: false,
+            mirror_urls: row
+                .try_get::<Option<String>, _>("mirror_urls")
+                .ok()
+                .flatten()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            cdn_host: row.try_get("cdn_host").ok().flatten(),
+            buffer_overrides: row
+                .try_get::<Option<String>, _>("buffer_overrides")
+                .ok()
+                .flatten()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            buffering: row
+                .try_get::<Option<String>, _>("buffering")
+                .ok()
+                .flatten()
+                .and_then(|json| serde_json::from_str(&json).ok()),
+            progress_epoch: row.try_get::<i64, _>("progress_epoch")? as u32,
+            trace_id: row.try_get("trace_id").ok().flatten(),
+            wifi_only: row.try_get("wifi_only").ok().flatten(),
+            network_paused: row.try_get("network_paused").unwrap_or(false),
+            companion_of: row.try_get("companion_of").ok().flatten(),
+            companion_kind: row
+                .try_get::<Option<String>, _>("companion_kind")
+                .ok()
+                .flatten()
+                .and_then(|kind| kind.parse().ok()),
+            follow_up,
         })
     }
 }
@@ -793,6 +2257,453 @@ mod tests {
         let task = manager.get_task(&task_id).await.unwrap();
         assert_eq!(task.asin, "B001");
         assert_eq!(task.status, TaskStatus::Queued);
+        assert_eq!(task.trace_id, None);
+
+        // Downloads remember the trace of the call that enqueued them
+        let _trace = crate::trace::enter(Some("js-7".to_string()));
+        let task_id = manager.enqueue_download(
+            "B002".to_string(),
+            "Traced Book".to_string(),
+            "https://example.com/traced.aax".to_string(),
+            1000,
+            "/tmp/traced.aax".to_string(),
+            "/tmp/traced.m4b".to_string(),
+            HashMap::new(),
+        ).await.unwrap();
+        assert_eq!(manager.get_task(&task_id).await.unwrap().trace_id.as_deref(), Some("js-7"));
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_refuses_loaned_title() {
+        let db = Database::new_in_memory().await.unwrap();
+        let book = crate::storage::NewBook::new(
+            "B00LOAN".to_string(),
+            "Loaned Book".to_string(),
+            "us".to_string(),
+        );
+        crate::storage::queries::insert_book(db.pool(), &book).await.unwrap();
+        sqlx::query("UPDATE Books SET benefit_type = 'loan' WHERE audible_product_id = 'B00LOAN'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 3).await.unwrap();
+        let result = manager.enqueue_download(
+            "B00LOAN".to_string(), "Loaned Book".to_string(), "https://example.com/l".to_string(),
+            1000, "/tmp/loan.aax".to_string(), "/tmp/loan.m4b".to_string(), HashMap::new(),
+        ).await;
+
+        assert!(matches!(result, Err(LibationError::DownloadNotPermitted { .. })));
+        assert!(manager.list_tasks(None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conversion_keys_use_book_output_root() {
+        let db = Database::new_in_memory().await.unwrap();
+        for asin in ["B0SDCARD", "B0DEFAULT"] {
+            let book = crate::storage::NewBook::new(asin.to_string(), asin.to_string(), "us".to_string());
+            crate::storage::queries::insert_book(db.pool(), &book).await.unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO UserDefinedItems (book_id, output_root) \
+             SELECT book_id, '/storage/sdcard/Audiobooks' FROM Books WHERE audible_product_id = 'B0SDCARD'",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        // No download slots, so the tasks stay queued
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 0).await.unwrap();
+        for asin in ["B0SDCARD", "B0DEFAULT"] {
+            let task_id = manager.enqueue_download(
+                asin.to_string(), asin.to_string(), "https://example.com/b".to_string(),
+                1000, format!("/tmp/{}.aax", asin), format!("/tmp/{}.m4b", asin), HashMap::new(),
+            ).await.unwrap();
+            manager.store_conversion_keys(&task_id, "key", "iv", "content://default").await.unwrap();
+            let expected = if asin == "B0SDCARD" { "/storage/sdcard/Audiobooks" } else { "content://default" };
+            assert_eq!(manager.get_task(&task_id).await.unwrap().output_directory.as_deref(), Some(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_refuses_unsupported_format() {
+        let db = Database::new_in_memory().await.unwrap();
+        let book = crate::storage::NewBook::new("B00AC4".to_string(), "Atmos Only".to_string(), "us".to_string());
+        crate::storage::queries::insert_book(db.pool(), &book).await.unwrap();
+        sqlx::query("UPDATE Books SET format_support = 'unsupported' WHERE audible_product_id = 'B00AC4'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 0).await.unwrap();
+        let result = manager.enqueue_download(
+            "B00AC4".to_string(), "Atmos Only".to_string(), "https://example.com/a".to_string(),
+            1000, "/tmp/ac4.aax".to_string(), "/tmp/ac4.m4b".to_string(), HashMap::new(),
+        ).await;
+
+        assert!(matches!(
+            result,
+            Err(LibationError::UnsupportedDownloadFormat { ref format_support, .. }) if format_support == "unsupported"
+        ));
+        assert!(result.unwrap_err().user_message().contains("AC-4"));
+
+        // Widevine-only titles got here with a license, so they may proceed
+        sqlx::query("UPDATE Books SET format_support = 'widevine_only' WHERE audible_product_id = 'B00AC4'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert!(manager.enqueue_download(
+            "B00AC4".to_string(), "Atmos Only".to_string(), "https://example.com/a".to_string(),
+            1000, "/tmp/ac4.aax".to_string(), "/tmp/ac4.m4b".to_string(), HashMap::new(),
+        ).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_respects_download_quota() {
+        let db = Database::new_in_memory().await.unwrap();
+        let book = crate::storage::NewBook::new("B00CAP".to_string(), "Capped".to_string(), "us".to_string());
+        crate::storage::queries::insert_book(db.pool(), &book).await.unwrap();
+        sqlx::query(
+            "INSERT INTO LibraryBooks (book_id, account) \
+             SELECT book_id, 'alice' FROM Books WHERE audible_product_id = 'B00CAP'"
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+        quota::record_usage(db.pool(), "alice", 5000).await.unwrap();
+        quota::set_download_quota(db.pool(), "alice", Some(4000), quota::QuotaAction::Warn).await.unwrap();
+
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 0).await.unwrap();
+        let enqueue = || manager.enqueue_download(
+            "B00CAP".to_string(), "Capped".to_string(), "https://example.com/c".to_string(),
+            1000, "/tmp/cap.aax".to_string(), "/tmp/cap.m4b".to_string(), HashMap::new(),
+        );
+
+        // Warn: enqueued, with the account attached and the cap reported
+        let task_id = enqueue().await.unwrap();
+        assert_eq!(manager.get_task(&task_id).await.unwrap().account.as_deref(), Some("alice"));
+        assert!(manager.quota_status(&task_id).await.unwrap().unwrap().exceeded);
+
+        quota::set_download_quota(db.pool(), "alice", Some(4000), quota::QuotaAction::Block).await.unwrap();
+        let result = enqueue().await;
+        assert!(matches!(result, Err(LibationError::DownloadQuotaExceeded { cap_bytes: 4000, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_expired_url_fails_before_request() {
+        use crate::clock::TestClock;
+        use chrono::TimeZone;
+
+        let db = Database::new_in_memory().await.unwrap();
+        let clock = Arc::new(TestClock::new(chrono::Utc.with_ymd_and_hms(2025, 3, 1, 11, 0, 0).unwrap()));
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1)
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+
+        // The URL expires at 12:00; the clock has moved past it by the time
+        // the download starts
+        clock.advance(chrono::Duration::hours(2));
+        let task_id = manager.enqueue_download(
+            "B00EXP".to_string(), "Expired".to_string(),
+            "https://dcdn.audible.com/p/B00EXP.aax?Expires=1740830400&Signature=abc".to_string(),
+            1000, "/tmp/exp.aax".to_string(), "/tmp/exp.m4b".to_string(), HashMap::new(),
+        ).await.unwrap();
+
+        let mut task = manager.get_task(&task_id).await.unwrap();
+        for _ in 0..100 {
+            if task.status == TaskStatus::Failed {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            task = manager.get_task(&task_id).await.unwrap();
+        }
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert!(task.error.unwrap().contains("expired"));
+
+        manager.update_download_url(&task_id, "https://example.com/fresh.aax").await.unwrap();
+        assert_eq!(manager.get_task(&task_id).await.unwrap().download_url, "https://example.com/fresh.aax");
+        assert!(manager.update_download_url("missing", "https://example.com").await.is_err());
+    }
+
+    /// Serve `data` over HTTP with Range support; `/slow` trickles it out,
+    /// `/norange` ignores Range headers, `/stall` goes quiet halfway through
+    /// the first request and `/dead` after 1000 bytes of every request
+    async fn serve_file(data: Vec<u8>) -> u16 {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let data = Arc::new(data);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let data = Arc::clone(&data);
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let head = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                    let range = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("range: bytes="))
+                        .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+                    let offset = range
+                        .filter(|_| !head.starts_with("get /norange") && !head.starts_with("get /stallnorange"))
+                        .unwrap_or(0);
+                    let body = &data[offset..];
+                    let status = if offset > 0 {
+                        format!("206 Partial Content\r\nContent-Range: bytes {}-{}/{}", offset, data.len() - 1, data.len())
+                    } else {
+                        "200 OK".to_string()
+                    };
+                    let header = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                    if socket.write_all(header.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    let quiet_after = if head.starts_with("get /stall") && range.is_none() {
+                        Some(body.len() / 2)
+                    } else if head.starts_with("get /dead") {
+                        Some(body.len().min(1000))
+                    } else {
+                        None
+                    };
+                    if let Some(sent) = quiet_after {
+                        // Connection stays open without sending anything more
+                        let _ = socket.write_all(&body[..sent]).await;
+                        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                    } else if head.starts_with("get /slow") {
+                        for piece in body.chunks(10) {
+                            if socket.write_all(piece).await.is_err() {
+                                return;
+                            }
+                            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                        }
+                    } else {
+                        let _ = socket.write_all(body).await;
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_slow_cdn_switches_to_mirror() {
+        let db = Database::new_in_memory().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let port = serve_file(data.clone()).await;
+
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1)
+            .await
+            .unwrap()
+            .with_cdn_policy(CdnPolicy {
+                window: std::time::Duration::from_millis(50),
+                min_bytes_per_sec: 1 << 20,
+                slow_period: std::time::Duration::from_millis(100),
+                ..CdnPolicy::default()
+            });
+        let download_path = dir.path().join("book.aax").display().to_string();
+        let task_id = manager.enqueue_download(
+            "B00CDN".to_string(), "Mirrored".to_string(),
+            format!("http://127.0.0.1:{}/slow/book.aax", port),
+            0, download_path.clone(), dir.path().join("book.m4b").display().to_string(), HashMap::new(),
+        ).await.unwrap();
+        let mirror = format!("http://localhost:{}/fast/book.aax", port);
+        manager.set_mirror_urls(&task_id, std::slice::from_ref(&mirror)).await.unwrap();
+
+        let mut task = manager.get_task(&task_id).await.unwrap();
+        for _ in 0..250 {
+            if task.is_terminal() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            task = manager.get_task(&task_id).await.unwrap();
+        }
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!(task.cdn_host.as_deref(), Some("localhost"));
+        assert_eq!(task.download_url, mirror);
+        assert_eq!(task.mirror_urls, vec![mirror]);
+        assert_eq!(std::fs::read(&download_path).unwrap(), data);
+
+        // A fresh license's mirrors replace the old ones
+        manager.update_download_url(&task_id, "https://example.com/fresh.aax").await.unwrap();
+        assert!(manager.get_task(&task_id).await.unwrap().mirror_urls.is_empty());
+        assert!(manager.set_mirror_urls("missing", &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_download_restarts() {
+        let db = Database::new_in_memory().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let port = serve_file(data.clone()).await;
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 2)
+            .await
+            .unwrap()
+            .with_cdn_policy(CdnPolicy {
+                stall_timeout: std::time::Duration::from_millis(200),
+                max_stall_restarts: 1,
+                ..CdnPolicy::default()
+            });
+
+        let enqueue = |name: &'static str| {
+            let download_path = dir.path().join(format!("{}.aax", name)).display().to_string();
+            let url = format!("http://127.0.0.1:{}/{}/book.aax", port, name);
+            let output = dir.path().join(format!("{}.m4b", name)).display().to_string();
+            let manager = &manager;
+            async move {
+                let task_id = manager
+                    .enqueue_download("B00STALL".to_string(), "Stalled".to_string(), url, 20_000, download_path.clone(), output, HashMap::new())
+                    .await
+                    .unwrap();
+                (task_id, download_path)
+            }
+        };
+
+        // A stall is recovered with a Range request from where it stopped
+        let (task_id, download_path) = enqueue("stall").await;
+        let task = wait_for_task(&manager, &task_id).await;
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!(task.progress_epoch, 0);
+        assert_eq!(std::fs::read(&download_path).unwrap(), data);
+
+        // A 200 to the restarted request rewrites the file from the start,
+        // though a fresh download's file isn't opened for appending
+        let (task_id, download_path) = enqueue("stallnorange").await;
+        let task = wait_for_task(&manager, &task_id).await;
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!(task.progress_epoch, 1);
+        assert_eq!(std::fs::read(&download_path).unwrap(), data);
+
+        // Without a mirror to move to, repeated stalls ask for a new license
+        let (task_id, download_path) = enqueue("dead").await;
+        let task = wait_for_task(&manager, &task_id).await;
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert!(task.error.as_deref().unwrap_or_default().contains("stalled at byte 2000"), "{:?}", task.error);
+        assert_eq!(task.bytes_downloaded, 2000);
+        assert_eq!(std::fs::read(&download_path).unwrap(), data[..2000]);
+
+        // A mirror is tried before giving up
+        let (task_id, download_path) = enqueue("dead").await;
+        let mirror = format!("http://localhost:{}/fast/book.aax", port);
+        manager.set_mirror_urls(&task_id, std::slice::from_ref(&mirror)).await.unwrap();
+        let task = wait_for_task(&manager, &task_id).await;
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!(task.download_url, mirror);
+        assert_eq!(std::fs::read(&download_path).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_download_buffering() {
+        let db = Database::new_in_memory().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let port = serve_file(data.clone()).await;
+
+        let policy = BufferPolicy { buffer_size: 64 * 1024, flush_interval: 128 * 1024, auto_tune: false };
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1).await.unwrap();
+        manager.set_buffer_policy(policy).unwrap();
+        let download_path = dir.path().join("book.aax").display().to_string();
+        let task_id = manager.enqueue_download(
+            "B00BUF".to_string(), "Buffered".to_string(),
+            format!("http://127.0.0.1:{}/fast/book.aax", port),
+            0, download_path.clone(), dir.path().join("book.m4b").display().to_string(), HashMap::new(),
+        ).await.unwrap();
+
+        let mut task = manager.get_task(&task_id).await.unwrap();
+        for _ in 0..250 {
+            if task.is_terminal() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            task = manager.get_task(&task_id).await.unwrap();
+        }
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!(std::fs::read(&download_path).unwrap(), data);
+
+        // The values in use are part of the task details
+        let buffering = task.buffering.unwrap();
+        assert_eq!((buffering.buffer_size, buffering.flush_interval), (64 * 1024, 128 * 1024));
+        assert!(!buffering.auto_tuned);
+
+        // Per-task overrides are validated and stored
+        let overrides = BufferOverrides { buffer_size: Some(256 * 1024), flush_interval: None };
+        manager.set_task_buffering(&task_id, overrides).await.unwrap();
+        assert_eq!(manager.get_task(&task_id).await.unwrap().buffer_overrides, overrides);
+        let invalid = BufferOverrides { buffer_size: Some(1), flush_interval: None };
+        assert!(manager.set_task_buffering(&task_id, invalid).await.is_err());
+        assert!(manager.set_task_buffering("missing", overrides).await.is_err());
+        assert!(manager.set_buffer_policy(BufferPolicy { buffer_size: 0, ..policy }).is_err());
+        assert_eq!(manager.buffer_policy(), policy);
+    }
+
+    #[tokio::test]
+    async fn test_queue_export_import() {
+        use crate::download::queue_transfer::{ConversionKeys, QueueExport};
+
+        let source = Database::new_in_memory().await.unwrap();
+        let exporter = PersistentDownloadManager::new(Arc::new(source.pool().clone()), 0).await.unwrap();
+        exporter.set_conversion_policy(ConversionPolicy { require_charging: true, ..Default::default() });
+        quota::set_download_quota(source.pool(), "alice", Some(1 << 30), quota::QuotaAction::Block).await.unwrap();
+        for asin in ["B001", "B002", "B003"] {
+            exporter.enqueue_download(
+                asin.to_string(), format!("Book {}", asin), format!("https://example.com/{}.aax", asin),
+                1000, format!("/tmp/{}.aax", asin), format!("/tmp/{}.m4b", asin), HashMap::new(),
+            ).await.unwrap();
+        }
+        let second = exporter.list_tasks(None).await.unwrap().into_iter().find(|t| t.asin == "B002").unwrap();
+        exporter.pause_download(&second.task_id).await.unwrap();
+
+        let export = exporter.export_queue().await.unwrap();
+        let json = serde_json::to_string(&export).unwrap();
+        assert!(!json.contains("example.com") && !json.contains("/tmp/"));
+        let asins: Vec<_> = export.items.iter().map(|i| i.asin.as_str()).collect();
+        assert_eq!(asins, vec!["B001", "B002", "B003"]);
+        assert!(export.items[1].paused);
+
+        // On the new device B001 is already queued and B003 isn't in the library
+        let target = Database::new_in_memory().await.unwrap();
+        for asin in ["B001", "B002"] {
+            let book = crate::storage::NewBook::new(asin.to_string(), format!("Book {}", asin), "us".to_string());
+            crate::storage::queries::insert_book(target.pool(), &book).await.unwrap();
+        }
+        let importer = PersistentDownloadManager::new(Arc::new(target.pool().clone()), 0).await.unwrap();
+        importer.enqueue_download(
+            "B001".to_string(), "Book B001".to_string(), "https://example.com/old.aax".to_string(),
+            1000, "/tmp/old.aax".to_string(), "/tmp/old.m4b".to_string(), HashMap::new(),
+        ).await.unwrap();
+
+        let mut licensed = Vec::new();
+        let report = importer.import_queue(&QueueExport::from_json(&json).unwrap(), |item| {
+            licensed.push(item.asin.clone());
+            let asin = item.asin.clone();
+            async move {
+                Ok(ResolvedDownload {
+                    download_url: format!("https://cdn.example.com/{}.aax", asin),
+                    total_bytes: 2000,
+                    download_path: format!("/tmp/new-{}.aax", asin),
+                    output_path: format!("/tmp/new-{}.m4b", asin),
+                    conversion_keys: Some(ConversionKeys {
+                        aaxc_key: "00ff".into(),
+                        aaxc_iv: "ff00".into(),
+                        output_directory: "/tmp/out".to_string(),
+                    }),
+                    ..Default::default()
+                })
+            }
+        }).await.unwrap();
+
+        assert_eq!(licensed, vec!["B002"]);
+        assert_eq!(report.enqueued.len(), 1);
+        let reasons: Vec<_> = report.skipped.iter().map(|s| (s.asin.as_str(), s.reason.as_str())).collect();
+        assert_eq!(reasons, vec![("B001", "Already queued"), ("B003", "Not in library")]);
+
+        let task = importer.get_task(&report.enqueued[0]).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Paused);
+        assert_eq!(task.download_url, "https://cdn.example.com/B002.aax");
+        assert_eq!(task.aaxc_key.as_ref().map(|k| k.expose_secret()), Some("00ff"));
+        assert!(importer.conversion_policy().require_charging);
+        assert!(quota::get_download_quota(target.pool(), "alice").await.unwrap().is_some());
     }
 
     #[tokio::test]
@@ -814,6 +2725,309 @@ mod tests {
         assert_eq!(tasks.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_apply_device_conditions() {
+        use crate::download::adaptive::ThermalStatus;
+
+        let db = Database::new_in_memory().await.unwrap();
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 3).await.unwrap();
+        let policy = AdaptivePolicy::default();
+
+        let hot = DeviceConditions {
+            thermal_status: ThermalStatus::Critical,
+            ..Default::default()
+        };
+        let limits = manager.apply_device_conditions(&hot, &policy).await.unwrap();
+        assert_eq!(limits.max_concurrent_downloads, 0);
+
+        // Nothing starts while the device is critical
+        let task_id = manager.enqueue_download(
+            "B001".to_string(), "Test Book".to_string(), "https://example.com/book.aax".to_string(),
+            1000, "/tmp/hot_book.aax".to_string(), "/tmp/hot_book.m4b".to_string(), HashMap::new(),
+        ).await.unwrap();
+        assert_eq!(manager.get_active_count().await, 0);
+        assert_eq!(manager.get_task(&task_id).await.unwrap().status, TaskStatus::Queued);
+
+        manager.apply_device_conditions(&DeviceConditions::default(), &policy).await.unwrap();
+        assert_eq!(manager.current_concurrency_limit(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_wifi_only_tasks_follow_network() {
+        let db = Database::new_in_memory().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let port = serve_file(data).await;
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1).await.unwrap();
+        let enqueue = |asin: &str, path: &str| manager.enqueue_download(
+            asin.to_string(), asin.to_string(), format!("http://127.0.0.1:{}/{}", port, path),
+            0, dir.path().join(format!("{}.aax", asin)).display().to_string(),
+            dir.path().join(format!("{}.m4b", asin)).display().to_string(), HashMap::new(),
+        );
+
+        // Running on Wi-Fi, then paused when the device leaves it
+        let big = enqueue("B00BIG", "slow/big.aax").await.unwrap();
+        manager.set_network_state(NetworkState::Wifi).await.unwrap();
+        manager.set_task_wifi_only(&big, Some(true)).await.unwrap();
+        assert_eq!(manager.get_active_count().await, 1);
+        let transition = manager.set_network_state(NetworkState::Cellular).await.unwrap();
+        assert_eq!(transition.paused, vec![big.clone()]);
+        let task = manager.get_task(&big).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Paused);
+        assert!(task.network_paused);
+
+        // Tasks without their own setting follow the stored policy
+        let small = enqueue("B00SMALL", "small.aax").await.unwrap();
+        assert_eq!(wait_for_task(&manager, &small).await.status, TaskStatus::Completed);
+        manager.set_network_policy(NetworkPolicy { wifi_only: true }).await.unwrap();
+        let queued = enqueue("B00QUEUED", "queued.aax").await.unwrap();
+        assert_eq!(manager.get_active_count().await, 0);
+        assert_eq!(manager.get_task(&queued).await.unwrap().status, TaskStatus::Queued);
+        let reopened = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 0).await.unwrap();
+        assert!(reopened.network_policy().wifi_only);
+
+        // Back on Wi-Fi the network-paused task is requeued; one the user
+        // paused stays paused
+        let user_paused = enqueue("B00USER", "user.aax").await.unwrap();
+        manager.pause_download(&user_paused).await.unwrap();
+        let transition = manager.set_network_state(NetworkState::Wifi).await.unwrap();
+        assert_eq!(transition.resumed, vec![big.clone()]);
+        assert!(!manager.get_task(&big).await.unwrap().network_paused);
+        assert_eq!(manager.get_task(&user_paused).await.unwrap().status, TaskStatus::Paused);
+
+        // Offline pauses everything
+        let transition = manager.set_network_state(NetworkState::Offline).await.unwrap();
+        assert_eq!(transition.paused, vec![big.clone()]);
+        assert!(manager.set_task_wifi_only("missing", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics() {
+        use crate::download::adaptive::ThermalStatus;
+
+        let db = Database::new_in_memory().await.unwrap();
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 3)
+            .await
+            .unwrap()
+            .with_stall_threshold(std::time::Duration::from_secs(60));
+
+        // Hold downloads in the queue
+        let critical = DeviceConditions {
+            thermal_status: ThermalStatus::Critical,
+            ..Default::default()
+        };
+        manager.apply_device_conditions(&critical, &AdaptivePolicy::default()).await.unwrap();
+        manager.enqueue_download(
+            "B001".to_string(), "Test Book".to_string(), "https://example.com/book.aax".to_string(),
+            1000, "/tmp/diag_book.aax".to_string(), "/tmp/diag_book.m4b".to_string(), HashMap::new(),
+        ).await.unwrap();
+
+        let diagnostics = manager.diagnostics().await.unwrap();
+        assert!(diagnostics.runtime.is_some());
+        assert_eq!(diagnostics.downloads.active_workers, 0);
+        assert_eq!(diagnostics.downloads.queued_tasks, 1);
+        assert_eq!(diagnostics.downloads.permits_available, 3);
+        assert_eq!(diagnostics.downloads.concurrency_limit, 0);
+        assert_eq!(diagnostics.downloads.stall_threshold_seconds, 60);
+        assert!(diagnostics.downloads.stalled_tasks.is_empty());
+        assert!(diagnostics.database.size >= 1);
+
+        let tasks = manager.list_tasks(None).await.unwrap();
+        assert!(!tasks[0].stalled);
+    }
+
+    #[tokio::test]
+    async fn test_import_legacy_downloads() {
+        use crate::download::adaptive::ThermalStatus;
+        use crate::download::stream::StreamState;
+
+        let dir = tempfile::tempdir().unwrap();
+        let partial = dir.path().join("B0LEGACY.aax");
+        std::fs::write(&partial, vec![1u8; 400]).unwrap();
+        let mut state = StreamState::new("https://cdn.example.com/legacy".to_string(), partial.clone());
+        state.content_length = 1000;
+        state.write_position = 400;
+        state.request_headers.insert("User-Agent".to_string(), "Audible".to_string());
+        state.save().await.unwrap();
+
+        let db = Database::new_in_memory().await.unwrap();
+        let book = crate::storage::NewBook::new(
+            "B0LEGACY".to_string(),
+            "Legacy Book".to_string(),
+            "us".to_string(),
+        );
+        crate::storage::queries::insert_book(db.pool(), &book).await.unwrap();
+
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 3).await.unwrap();
+        // Keep the imported task queued instead of hitting the network
+        let critical = DeviceConditions {
+            thermal_status: ThermalStatus::Critical,
+            ..Default::default()
+        };
+        manager.apply_device_conditions(&critical, &AdaptivePolicy::default()).await.unwrap();
+
+        let report = manager.import_legacy_downloads(dir.path()).await.unwrap();
+        assert_eq!(report.imported.len(), 1);
+        assert!(report.skipped.is_empty());
+        assert!(!state.state_file_path().exists());
+
+        let task = manager.get_task(&report.imported[0]).await.unwrap();
+        assert_eq!(task.asin, "B0LEGACY");
+        assert_eq!(task.title, "Legacy Book");
+        assert_eq!(task.status, TaskStatus::Queued);
+        assert_eq!(task.bytes_downloaded, 400);
+        assert_eq!(task.total_bytes, 1000);
+        assert_eq!(task.download_path, partial.to_string_lossy());
+        assert_eq!(task.request_headers.get("User-Agent").map(SecretString::expose_secret), Some("Audible"));
+        assert!(task.chunk_manifest.is_none());
+
+        // A stale state file for the same download is discarded, not duplicated
+        state.save().await.unwrap();
+        let report = manager.import_legacy_downloads(dir.path()).await.unwrap();
+        assert!(report.imported.is_empty());
+        assert_eq!(report.skipped.len(), 1);
+        assert!(!state.state_file_path().exists());
+        assert_eq!(manager.list_tasks(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_process_pending_conversions() {
+        let db = Database::new_in_memory().await.unwrap();
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 3).await.unwrap();
+        manager.apply_device_conditions(
+            &DeviceConditions { is_charging: false, ..Default::default() },
+            &AdaptivePolicy::default(),
+        ).await.unwrap();
+        manager.set_conversion_policy(ConversionPolicy {
+            require_charging: true,
+            ..Default::default()
+        });
+
+        // A finished download parked by the policy
+        sqlx::query(
+            "INSERT INTO DownloadTasks (task_id, asin, title, status, bytes_downloaded, total_bytes, \
+             download_url, download_path, output_path, request_headers, aaxc_key, aaxc_iv) \
+             VALUES ('t1', 'B001', 'Book', 'awaiting_conversion', 1000, 1000, 'https://example.com/b', \
+             '/tmp/b.aax', '/tmp/b.m4b', '{}', 'key', 'iv')"
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        assert!(manager.process_pending_conversions().await.unwrap().is_empty());
+        assert_eq!(manager.get_task("t1").await.unwrap().status, TaskStatus::AwaitingConversion);
+
+        manager.apply_device_conditions(&DeviceConditions::default(), &AdaptivePolicy::default()).await.unwrap();
+        let ready = manager.process_pending_conversions().await.unwrap();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].status, TaskStatus::Decrypting);
+        assert_eq!(ready[0].aaxc_key.as_ref().map(|k| k.expose_secret()), Some("key"));
+
+        // Claimed tasks aren't handed out twice
+        assert!(manager.process_pending_conversions().await.unwrap().is_empty());
+    }
+
+    /// Poll a task until it's terminal
+    async fn wait_until_terminal(manager: &PersistentDownloadManager, task_id: &str) -> DownloadTask {
+        let mut task = manager.get_task(task_id).await.unwrap();
+        for _ in 0..250 {
+            if task.is_terminal() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            task = manager.get_task(task_id).await.unwrap();
+        }
+        task
+    }
+
+    #[tokio::test]
+    async fn test_follow_up_decrypts_and_tags() {
+        use crate::crypto::aax::test_files::build_encrypted_file;
+
+        let db = Database::new_in_memory().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let (key, iv) = ([0x11u8; 16], [0x22u8; 16]);
+        let samples: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; 32]).collect();
+        let aaxc = build_encrypted_file(b"aaxc", key, iv, None, &samples);
+        let port = serve_file(aaxc.clone()).await;
+        crate::storage::queries::insert_book(
+            db.pool(),
+            &crate::storage::NewBook::new("B00CHAIN".to_string(), "Chained".to_string(), "us".to_string()),
+        )
+        .await
+        .unwrap();
+
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1).await.unwrap();
+        let follow_up = FollowUp {
+            decrypt: Some(follow_up::DecryptStep {
+                aaxc_key: hex::encode(key).into(),
+                aaxc_iv: hex::encode(iv).into(),
+                output_path: None,
+                delete_download: true,
+            }),
+            tags: vec!["Downloaded".to_string()],
+        };
+        let download_path = dir.path().join("book.aaxc");
+        let output_path = dir.path().join("book.m4b");
+        let task_id = manager.enqueue_download_with_follow_up(
+            "B00CHAIN".to_string(), "Chained".to_string(),
+            format!("http://127.0.0.1:{}/book.aaxc", port),
+            aaxc.len() as u64, download_path.display().to_string(), output_path.display().to_string(),
+            HashMap::new(), Some(follow_up.clone()),
+        ).await.unwrap();
+
+        let task = wait_until_terminal(&manager, &task_id).await;
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!(task.output_path, output_path.display().to_string());
+        assert_eq!(&std::fs::read(&output_path).unwrap()[8..12], b"M4B ");
+        assert!(!download_path.exists());
+        assert!(task.aaxc_key.is_some());
+        // The loaded follow-up has its keys back; neither shows in the JSON
+        assert_eq!(task.follow_up.as_ref(), Some(&follow_up));
+        let json = serde_json::to_string(&task).unwrap();
+        assert!(json.contains("\"follow_up\"") && !json.contains(&hex::encode(key)) && !json.contains(&hex::encode(iv)));
+        let book_id: i64 = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = 'B00CHAIN'")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(crate::storage::tags::get_book_tags(db.pool(), book_id).await.unwrap(), vec!["downloaded"]);
+
+        // A follow-up interrupted by a restart runs again
+        std::fs::write(&download_path, &aaxc).unwrap();
+        std::fs::remove_file(&output_path).unwrap();
+        sqlx::query("UPDATE DownloadTasks SET status = 'decrypting' WHERE task_id = ?")
+            .bind(&task_id)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let restarted = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1).await.unwrap();
+        restarted.resume_all_pending().await.unwrap();
+        let task = wait_until_terminal(&restarted, &task_id).await;
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert!(output_path.exists());
+
+        // A failed follow-up keeps the download for retry
+        let bad = FollowUp { tags: Vec::new(), ..follow_up };
+        let failing = manager.enqueue_download_with_follow_up(
+            "B00BROKEN".to_string(), "Broken".to_string(),
+            format!("http://127.0.0.1:{}/book.aaxc", port),
+            aaxc.len() as u64, dir.path().join("broken.aaxc").display().to_string(),
+            dir.path().join("missing-dir/broken.m4b").display().to_string(),
+            HashMap::new(), Some(bad),
+        ).await.unwrap();
+        let task = wait_until_terminal(&manager, &failing).await;
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert!(task.error.as_deref().unwrap().starts_with("Follow-up failed"));
+        assert!(task.can_retry_conversion());
+
+        // Invalid descriptors are refused up front
+        let invalid = FollowUp { decrypt: None, tags: vec!["!!!".to_string()] };
+        assert!(manager.enqueue_download_with_follow_up(
+            "B00BAD".to_string(), "Bad".to_string(), "https://example.com/b".to_string(),
+            0, "/tmp/b.aaxc".to_string(), "/tmp/b.m4b".to_string(), HashMap::new(), Some(invalid),
+        ).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_pause_download() {
         let db = Database::new_in_memory().await.unwrap();
@@ -829,4 +3043,167 @@ mod tests {
         let task = manager.get_task(&task_id).await.unwrap();
         assert_eq!(task.status, TaskStatus::Paused);
     }
+
+    /// Poll a task until it's no longer queued or downloading
+    async fn wait_for_task(manager: &PersistentDownloadManager, task_id: &str) -> DownloadTask {
+        let mut task = manager.get_task(task_id).await.unwrap();
+        for _ in 0..250 {
+            if !matches!(task.status, TaskStatus::Queued | TaskStatus::Downloading) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            task = manager.get_task(task_id).await.unwrap();
+        }
+        task
+    }
+
+    #[tokio::test]
+    async fn test_progress_only_goes_back_with_new_epoch() {
+        use crate::clock::TestClock;
+        use chrono::TimeZone;
+
+        let db = Database::new_in_memory().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let port = serve_file(data.clone()).await;
+        let clock = Arc::new(TestClock::new(chrono::Utc.with_ymd_and_hms(2025, 3, 1, 11, 0, 0).unwrap()));
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1)
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+
+        // Paused at 8000 bytes, but only 5000 made it to the file
+        let download_path = dir.path().join("book.aax");
+        std::fs::write(&download_path, &data[..5000]).unwrap();
+        sqlx::query(
+            "INSERT INTO DownloadTasks (task_id, asin, title, status, bytes_downloaded, total_bytes, \
+             download_url, download_path, output_path, request_headers) \
+             VALUES ('t1', 'B00MONO', 'Book', 'paused', 8000, 20000, ?, ?, '/tmp/mono.m4b', '{}')"
+        )
+        .bind(format!("http://127.0.0.1:{}/fast/book.aax?Expires=1740830400&Signature=abc", port))
+        .bind(download_path.display().to_string())
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        manager.register_progress_callback("t1".to_string(), Box::new(move |task| {
+            sink.lock().unwrap().push((task.progress_epoch, task.bytes_downloaded, task.status));
+        })).await;
+
+        // Expired URL: the task fails, progress is untouched
+        clock.advance(chrono::Duration::hours(2));
+        manager.resume_download("t1").await.unwrap();
+        let task = wait_for_task(&manager, "t1").await;
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!((task.progress_epoch, task.bytes_downloaded), (0, 8000));
+
+        // Resume with a fresh URL: reset to what's on disk, then complete
+        manager.update_download_url("t1", &format!("http://127.0.0.1:{}/fast/book.aax", port)).await.unwrap();
+        manager.resume_download("t1").await.unwrap();
+        let task = wait_for_task(&manager, "t1").await;
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!((task.progress_epoch, task.bytes_downloaded), (1, 20_000));
+        assert_eq!(std::fs::read(&download_path).unwrap(), data);
+
+        // Restart: the partial file is gone, the download starts over
+        sqlx::query("UPDATE DownloadTasks SET status = 'paused', bytes_downloaded = 12000 WHERE task_id = 't1'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        std::fs::remove_file(&download_path).unwrap();
+        manager.resume_download("t1").await.unwrap();
+        let task = wait_for_task(&manager, "t1").await;
+        assert_eq!(task.status, TaskStatus::Completed, "{:?}", task.error);
+        assert_eq!((task.progress_epoch, task.bytes_downloaded), (2, 20_000));
+        assert_eq!(std::fs::read(&download_path).unwrap(), data);
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events.first(), Some(&(0, 8000, TaskStatus::Failed)));
+        assert!(events.contains(&(1, 5000, TaskStatus::Downloading)));
+        assert!(events.contains(&(2, 0, TaskStatus::Downloading)));
+        assert_eq!(events.last(), Some(&(2, 20_000, TaskStatus::Completed)));
+        for pair in events.windows(2) {
+            let ((epoch, bytes, _), (next_epoch, next_bytes, _)) = (&pair[0], &pair[1]);
+            assert!(
+                next_epoch > epoch || (next_epoch == epoch && next_bytes >= bytes),
+                "{:?}",
+                events
+            );
+        }
+    }
+
+    #[test]
+    fn test_resume_start() {
+        use reqwest::StatusCode;
+
+        assert_eq!(resume_start(0, 0, StatusCode::OK, None).unwrap(), ResumeStart::Append);
+        assert_eq!(resume_start(5000, 20000, StatusCode::OK, None).unwrap(), ResumeStart::Restart);
+        let partial = StatusCode::PARTIAL_CONTENT;
+        assert_eq!(resume_start(5000, 20000, partial, Some("bytes 5000-19999/20000")).unwrap(), ResumeStart::Append);
+        assert_eq!(resume_start(5000, 20000, partial, None).unwrap(), ResumeStart::Append);
+        assert!(resume_start(5000, 20000, partial, Some("bytes 0-19999/20000")).is_err());
+        assert!(resume_start(5000, 20000, partial, Some("items 5000")).is_err());
+
+        // Part of a file of another size (a different version of the title)
+        assert!(resume_start(5000, 20000, partial, Some("bytes 5000-29999/30000")).is_err());
+        assert_eq!(resume_start(5000, 0, partial, Some("bytes 5000-29999/30000")).unwrap(), ResumeStart::Append);
+        assert_eq!(resume_start(5000, 20000, partial, Some("bytes 5000-19999/*")).unwrap(), ResumeStart::Append);
+    }
+
+    #[tokio::test]
+    async fn test_resume_with_and_without_range_support() {
+        let db = Database::new_in_memory().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        let port = serve_file(data.clone()).await;
+        let manager = PersistentDownloadManager::new(Arc::new(db.pool().clone()), 1).await.unwrap();
+
+        for (task_id, path) in [("t206", "fast"), ("t200", "norange")] {
+            // Paused after 5000 bytes
+            let download_path = dir.path().join(format!("{}.aax", task_id));
+            std::fs::write(&download_path, &data[..5000]).unwrap();
+            sqlx::query(
+                "INSERT INTO DownloadTasks (task_id, asin, title, status, bytes_downloaded, total_bytes, \
+                 download_url, download_path, output_path, request_headers) \
+                 VALUES (?, 'B00RANGE', 'Book', 'paused', 5000, 20000, ?, ?, '/tmp/range.m4b', '{}')"
+            )
+            .bind(task_id)
+            .bind(format!("http://127.0.0.1:{}/{}/book.aax", port, path))
+            .bind(download_path.display().to_string())
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+            manager.resume_download(task_id).await.unwrap();
+            let task = wait_for_task(&manager, task_id).await;
+            assert_eq!(task.status, TaskStatus::Completed, "{}: {:?}", task_id, task.error);
+            assert_eq!(task.bytes_downloaded, 20_000);
+            assert_eq!(std::fs::read(&download_path).unwrap(), data, "{}", task_id);
+
+            // Only the restart starts a new epoch
+            let expected_epoch = if task_id == "t200" { 1 } else { 0 };
+            assert_eq!(task.progress_epoch, expected_epoch);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hash_file_prefix() {
+        let dir = std::env::temp_dir().join(format!("hash_prefix_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("book.aax");
+        let data: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &data).await.unwrap();
+
+        // Resuming from a prefix then hashing the rest matches a full hash
+        let mut sha256 = PersistentDownloadManager::hash_file_prefix(&path, 1_500_000).await.unwrap();
+        sha256.update(&data[1_500_000..]);
+        assert_eq!(sha256.finalize(), Sha256::digest(&data));
+
+        let empty = PersistentDownloadManager::hash_file_prefix(&dir.join("missing"), 0).await.unwrap();
+        assert_eq!(empty.finalize(), Sha256::digest(b""));
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
 }
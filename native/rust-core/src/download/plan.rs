@@ -0,0 +1,299 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Dry run of a liberation
+//!
+//! `plan_liberation` works out what liberating a title would produce -
+//! output paths, format and codec, file sizes and the free space needed -
+//! from the database alone: no license request, no HEAD request, no
+//! directory listing. The app shows it as a confirmation sheet, and a bad
+//! naming template or an over-long path fails here instead of after the
+//! download.
+//!
+//! Sizes are estimated from the runtime and the quality's bitrate, unless
+//! a license for the title was archived (see `offline`), which records the
+//! real file size. Collisions are only resolved against the paths passed
+//! in `existing_paths`, since the destination isn't listed.
+//!
+//! # Reference C# Sources
+//! - `FileLiberator/DownloadDecryptBook.cs` - Download, decrypt, convert steps
+//! - `FileLiberator/DownloadOptions.Factory.cs:59-77` - Quality and codec choice
+
+use crate::api::content::{Codec, DownloadQuality};
+use crate::audio::decoder::AudioFormat;
+use crate::download::offline::find_offline_license;
+use crate::error::{LibationError, Result};
+use crate::file::locations::get_book_output_root;
+use crate::file::paths::{build_unique_file_path, check_path_lengths, CollisionStrategy, NamingPattern};
+use crate::storage::queries::find_book_with_relations_by_asin;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+/// Rough size of a saved cover image
+const COVER_BYTES: u64 = 500_000;
+
+/// What to liberate a title as
+#[derive(Debug, Clone)]
+pub struct LiberationOptions {
+    pub quality: DownloadQuality,
+    /// M4b or M4a keep the downloaded audio; Mp3 re-encodes it
+    pub output_format: AudioFormat,
+    /// MP3 bitrate (ignored for other formats)
+    pub mp3_bitrate_kbps: u32,
+    pub naming_pattern: NamingPattern,
+    pub collision_strategy: CollisionStrategy,
+    /// Relative paths already taken in the destination
+    pub existing_paths: Vec<String>,
+    /// Destination directory; paths are relative to it when None. A book
+    /// with its own output root (see `file::locations`) uses that instead
+    pub base_directory: Option<PathBuf>,
+    /// Save the cover next to the audio file
+    pub include_cover: bool,
+}
+
+impl Default for LiberationOptions {
+    fn default() -> Self {
+        Self {
+            quality: DownloadQuality::High,
+            output_format: AudioFormat::M4b,
+            mp3_bitrate_kbps: 64,
+            naming_pattern: NamingPattern::AuthorSeriesBook,
+            collision_strategy: CollisionStrategy::default(),
+            existing_paths: Vec::new(),
+            base_directory: None,
+            include_cover: false,
+        }
+    }
+}
+
+/// Kind of file a liberation writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedFileKind {
+    Audio,
+    Cover,
+}
+
+/// A file a liberation would write
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub kind: PlannedFileKind,
+    pub path: String,
+    pub estimated_bytes: u64,
+}
+
+/// Result of `plan_liberation`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiberationPlan {
+    pub asin: String,
+    pub title: String,
+    pub quality: DownloadQuality,
+    /// Format that would be downloaded (`FileType::as_str`)
+    pub download_format: String,
+    /// Codec of the downloaded audio
+    pub codec: Codec,
+    pub output_format: AudioFormat,
+    /// Codec of the output file
+    pub output_codec: Codec,
+    pub files: Vec<PlannedFile>,
+    /// Size of the download
+    pub download_bytes: u64,
+    /// Sizes come from an archived license rather than an estimate
+    pub exact_size: bool,
+    /// Peak space in use while liberating: the download and its decrypted
+    /// copy, or the decrypted copy and its conversion
+    pub required_free_bytes: u64,
+}
+
+/// Work out what liberating `asin` with `options` would produce
+///
+/// Reads the database only.
+///
+/// # Errors
+/// - NotFound if the title isn't in the library
+/// - InvalidState if it can't be downloaded
+/// - InvalidInput for an encrypted or unknown output format
+/// - Template errors, and `PathValidationFailed` for an over-long path
+pub async fn plan_liberation(pool: &SqlitePool, asin: &str, options: &LiberationOptions) -> Result<LiberationPlan> {
+    if options.output_format.is_encrypted() || options.output_format == AudioFormat::Unknown {
+        return Err(LibationError::InvalidInput(format!(
+            "Can't liberate to {:?}",
+            options.output_format
+        )));
+    }
+
+    let book = find_book_with_relations_by_asin(pool, asin)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+    if !book.is_downloadable {
+        return Err(LibationError::InvalidState(format!("{} can't be downloaded", asin)));
+    }
+
+    let codec = if book.is_spatial && options.quality == DownloadQuality::Extreme {
+        Codec::Ec3
+    } else {
+        Codec::AacLc
+    };
+    let seconds = book.length_in_minutes.max(0) as u64 * 60;
+
+    let archived = find_offline_license(pool, asin, options.quality).await?;
+    let (download_format, download_bytes) = match &archived {
+        Some(license) => (license.file_type.clone(), license.total_bytes),
+        None => ("aaxc".to_string(), seconds * download_kbps(options.quality, codec) * 1000 / 8),
+    };
+
+    let (output_codec, output_bytes) = match options.output_format {
+        AudioFormat::Mp3 => (Codec::Mp3, seconds * options.mp3_bitrate_kbps as u64 * 1000 / 8),
+        _ => (codec, download_bytes),
+    };
+
+    let metadata = book.to_audio_metadata();
+    let relative = build_unique_file_path(
+        &metadata,
+        options.naming_pattern,
+        options.output_format.to_extension(),
+        options.collision_strategy,
+        &options.existing_paths,
+        None,
+    )?;
+    let base_directory = match get_book_output_root(pool, asin).await? {
+        Some(root) => Some(PathBuf::from(root)),
+        None => options.base_directory.clone(),
+    };
+    let audio_path = match &base_directory {
+        Some(base) => base.join(&relative),
+        None => PathBuf::from(&relative),
+    };
+    check_path_lengths(&audio_path, None)?;
+
+    let mut files = vec![PlannedFile {
+        kind: PlannedFileKind::Audio,
+        path: display_path(&audio_path),
+        estimated_bytes: output_bytes,
+    }];
+    if options.include_cover {
+        files.push(PlannedFile {
+            kind: PlannedFileKind::Cover,
+            path: display_path(&audio_path.with_extension("jpg")),
+            estimated_bytes: COVER_BYTES,
+        });
+    }
+
+    // The decrypted copy is the size of the download
+    let peak = match options.output_format {
+        AudioFormat::Mp3 => (download_bytes * 2).max(download_bytes + output_bytes),
+        _ => download_bytes * 2,
+    };
+    let extras: u64 = files.iter().skip(1).map(|f| f.estimated_bytes).sum();
+
+    Ok(LiberationPlan {
+        asin: asin.to_string(),
+        title: book.title.clone(),
+        quality: options.quality,
+        download_format,
+        codec,
+        output_format: options.output_format,
+        output_codec,
+        files,
+        download_bytes,
+        exact_size: archived.is_some(),
+        required_free_bytes: peak + extras,
+    })
+}
+
+/// Typical bitrate of a download at `quality`
+fn download_kbps(quality: DownloadQuality, codec: Codec) -> u64 {
+    match (quality, codec) {
+        // Dolby Atmos tracks
+        (_, Codec::Ec3 | Codec::Ac4) => 768,
+        (DownloadQuality::Low, _) => 32,
+        (DownloadQuality::Normal, _) => 64,
+        (DownloadQuality::High | DownloadQuality::Extreme, _) => 128,
+    }
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::locations::{register_storage_root, set_book_output_root, StorageRoot};
+    use crate::storage::models::NewBook;
+    use crate::storage::queries::insert_book;
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_plan_liberation() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let mut book = NewBook::new("B0PLAN0001".to_string(), "All These Worlds".to_string(), "us".to_string());
+        book.length_in_minutes = 600;
+        insert_book(pool, &book).await.unwrap();
+
+        let options = LiberationOptions {
+            naming_pattern: NamingPattern::FlatFile,
+            base_directory: Some(PathBuf::from("/library")),
+            include_cover: true,
+            ..Default::default()
+        };
+        let plan = plan_liberation(pool, "B0PLAN0001", &options).await.unwrap();
+        assert_eq!(plan.codec, Codec::AacLc);
+        assert_eq!(plan.output_codec, Codec::AacLc);
+        assert!(!plan.exact_size);
+        // 10 hours at 128 kbps
+        assert_eq!(plan.download_bytes, 576_000_000);
+        assert_eq!(plan.files[0].path, "/library/All These Worlds.m4b");
+        assert_eq!(plan.files[1].path, "/library/All These Worlds.jpg");
+        assert_eq!(plan.required_free_bytes, 2 * 576_000_000 + COVER_BYTES);
+
+        // A book's own output root wins over the base directory
+        let sd_card = tempfile::TempDir::new().unwrap();
+        let sd_path = sd_card.path().to_string_lossy().to_string();
+        let root = StorageRoot { path: sd_path.clone(), label: None };
+        register_storage_root(pool, &root).await.unwrap();
+        set_book_output_root(pool, "B0PLAN0001", Some(&sd_path)).await.unwrap();
+        let plan = plan_liberation(pool, "B0PLAN0001", &options).await.unwrap();
+        assert_eq!(plan.files[0].path, display_path(&sd_card.path().join("All These Worlds.m4b")));
+        set_book_output_root(pool, "B0PLAN0001", None).await.unwrap();
+
+        // Taken names are renamed, MP3 is sized by its own bitrate
+        let options = LiberationOptions {
+            output_format: AudioFormat::Mp3,
+            naming_pattern: NamingPattern::FlatFile,
+            existing_paths: vec!["All These Worlds.mp3".to_string()],
+            ..Default::default()
+        };
+        let plan = plan_liberation(pool, "B0PLAN0001", &options).await.unwrap();
+        assert_eq!(plan.output_codec, Codec::Mp3);
+        assert_ne!(plan.files[0].path, "All These Worlds.mp3");
+        assert_eq!(plan.files[0].estimated_bytes, 288_000_000);
+
+        let options = LiberationOptions {
+            base_directory: Some(PathBuf::from("x".repeat(300))),
+            ..Default::default()
+        };
+        assert!(matches!(
+            plan_liberation(pool, "B0PLAN0001", &options).await,
+            Err(LibationError::PathValidationFailed { .. })
+        ));
+        let options = LiberationOptions { output_format: AudioFormat::Aaxc, ..Default::default() };
+        assert!(matches!(plan_liberation(pool, "B0PLAN0001", &options).await, Err(LibationError::InvalidInput(_))));
+        assert!(plan_liberation(pool, "B0MISSING0", &LiberationOptions::default()).await.is_err());
+    }
+}
@@ -0,0 +1,155 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Export and import of the download queue
+//!
+//! A user can build a queue on Wi-Fi and carry it to another device, or keep
+//! it across a reinstall. `PersistentDownloadManager::export_queue` writes
+//! the unfinished titles in queue order together with the conversion policy
+//! and download quotas. Licenses, signed URLs, decryption keys and local
+//! paths are never exported: they are tied to the device and expire.
+//!
+//! `PersistentDownloadManager::import_queue` revalidates every title against
+//! the local library (present, downloadable, not already queued or
+//! downloaded) and asks the caller to resolve a fresh license for it before
+//! enqueueing. Titles that fail any step are reported as skipped instead of
+//! failing the whole import.
+
+use crate::download::conversion_schedule::ConversionPolicy;
+use crate::download::quota::DownloadQuota;
+use crate::error::{LibationError, Result};
+use crate::secret::SecretString;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Format version written by `export_queue`
+pub const QUEUE_EXPORT_VERSION: u32 = 1;
+
+/// Exported download queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueExport {
+    pub version: u32,
+
+    /// When the queue was exported (RFC 3339)
+    pub exported_at: String,
+
+    /// Titles in queue order (first is downloaded first)
+    pub items: Vec<QueuedItem>,
+
+    #[serde(default)]
+    pub conversion_policy: ConversionPolicy,
+
+    #[serde(default)]
+    pub download_quotas: Vec<DownloadQuota>,
+}
+
+impl QueueExport {
+    /// Parse an export, refusing versions newer than this build understands
+    pub fn from_json(json: &str) -> Result<Self> {
+        let export: QueueExport = serde_json::from_str(json)
+            .map_err(|e| LibationError::InvalidInput(format!("Invalid queue export: {}", e)))?;
+        if export.version > QUEUE_EXPORT_VERSION {
+            return Err(LibationError::InvalidInput(format!(
+                "Queue export version {} is newer than supported version {}",
+                export.version, QUEUE_EXPORT_VERSION
+            )));
+        }
+        Ok(export)
+    }
+}
+
+/// One exported title
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedItem {
+    pub asin: String,
+    pub title: String,
+
+    /// The user paused it; it is re-added paused
+    #[serde(default)]
+    pub paused: bool,
+
+    /// Library account that owned the title on the exporting device
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+/// Everything needed to enqueue a title, from a freshly requested license
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedDownload {
+    pub download_url: String,
+    pub total_bytes: u64,
+    pub download_path: String,
+    pub output_path: String,
+    pub request_headers: HashMap<String, String>,
+
+    /// Alternate CDN URLs offered by the license
+    pub mirror_urls: Vec<String>,
+
+    /// AAXC key and IV (hex) plus the conversion output directory
+    pub conversion_keys: Option<ConversionKeys>,
+}
+
+/// Keys stored with a task so it can be converted after download
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionKeys {
+    pub aaxc_key: SecretString,
+    pub aaxc_iv: SecretString,
+    pub output_directory: String,
+}
+
+/// Exported title that was not enqueued
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedQueueItem {
+    pub asin: String,
+    pub reason: String,
+}
+
+/// Result of importing a queue
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueImportReport {
+    /// Task ids created, in queue order
+    pub enqueued: Vec<String>,
+
+    /// Titles left out, with the reason
+    pub skipped: Vec<SkippedQueueItem>,
+}
+
+impl QueueImportReport {
+    pub(crate) fn skip(&mut self, asin: &str, reason: impl Into<String>) {
+        self.skipped.push(SkippedQueueItem {
+            asin: asin.to_string(),
+            reason: reason.into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_version_check() {
+        let json = r#"{"version": 1, "exported_at": "2025-03-01T12:00:00Z", "items": [{"asin": "B001", "title": "One"}]}"#;
+        let export = QueueExport::from_json(json).unwrap();
+        assert_eq!(export.items[0].asin, "B001");
+        assert!(!export.items[0].paused);
+        assert_eq!(export.conversion_policy, ConversionPolicy::default());
+
+        let newer = r#"{"version": 2, "exported_at": "2025-03-01T12:00:00Z", "items": []}"#;
+        assert!(matches!(QueueExport::from_json(newer), Err(LibationError::InvalidInput(_))));
+        assert!(QueueExport::from_json("not json").is_err());
+    }
+}
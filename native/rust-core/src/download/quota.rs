@@ -0,0 +1,345 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-account download statistics and monthly quotas
+//!
+//! The download worker adds every byte it writes to `DownloadUsage`, keyed
+//! by the account that owns the title and the UTC month (`YYYY-MM`). A user
+//! on a limited data plan or a shared device can set a monthly cap per
+//! account in `DownloadQuotas`; once the month's usage reaches it,
+//! `PersistentDownloadManager::enqueue_download` either refuses new downloads
+//! (`QuotaAction::Block`) or lets them through for the app to warn about
+//! (`QuotaAction::Warn`).
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::str::FromStr;
+
+/// What happens when an account's monthly cap is reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaAction {
+    /// Allow the download; the app shows a warning
+    #[default]
+    Warn,
+    /// Refuse new downloads until next month
+    Block,
+}
+
+impl QuotaAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaAction::Warn => "warn",
+            QuotaAction::Block => "block",
+        }
+    }
+}
+
+impl FromStr for QuotaAction {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "warn" => Ok(QuotaAction::Warn),
+            "block" => Ok(QuotaAction::Block),
+            _ => Err(LibationError::InvalidInput(format!("Unknown quota action: {}", s))),
+        }
+    }
+}
+
+/// Bytes downloaded for one account in one month
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonthlyUsage {
+    pub account: String,
+
+    /// UTC month, `YYYY-MM`
+    pub month: String,
+
+    pub bytes_downloaded: u64,
+}
+
+/// User-set monthly download cap for an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadQuota {
+    pub account: String,
+    pub monthly_cap_bytes: u64,
+    pub action: QuotaAction,
+}
+
+/// An account's usage this month against its cap
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaStatus {
+    pub account: String,
+    pub month: String,
+    pub bytes_downloaded: u64,
+
+    /// None when no cap is set
+    pub quota: Option<DownloadQuota>,
+
+    /// Usage has reached the cap
+    pub exceeded: bool,
+}
+
+/// Current UTC month as `YYYY-MM`
+pub fn current_month() -> String {
+    chrono::Utc::now().format("%Y-%m").to_string()
+}
+
+/// Account that owns a title in the library (None if not in the library)
+pub(crate) async fn account_for_asin(pool: &SqlitePool, asin: &str) -> Result<Option<String>> {
+    let account = sqlx::query_scalar(
+        r#"
+        SELECT lb.account FROM LibraryBooks lb
+        JOIN Books b ON b.book_id = lb.book_id
+        WHERE b.audible_product_id = ?
+        ORDER BY lb.date_added ASC
+        LIMIT 1
+        "#,
+    )
+    .bind(asin)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(account)
+}
+
+/// Add downloaded bytes to the account's total for the current month
+pub(crate) async fn record_usage(pool: &SqlitePool, account: &str, bytes: u64) -> Result<()> {
+    if bytes == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO DownloadUsage (account, month, bytes_downloaded)
+        VALUES (?, ?, ?)
+        ON CONFLICT(account, month)
+        DO UPDATE SET bytes_downloaded = bytes_downloaded + excluded.bytes_downloaded
+        "#,
+    )
+    .bind(account)
+    .bind(current_month())
+    .bind(bytes as i64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Monthly usage, newest month first
+///
+/// # Arguments
+/// * `account` - Limit to one account (None for all)
+/// * `months` - Limit to the most recent N months (None for all)
+pub async fn get_download_usage(
+    pool: &SqlitePool,
+    account: Option<&str>,
+    months: Option<u32>,
+) -> Result<Vec<MonthlyUsage>> {
+    let since = months.map(|months| {
+        let now = chrono::Utc::now().date_naive();
+        now.checked_sub_months(chrono::Months::new(months.saturating_sub(1)))
+            .unwrap_or(now)
+            .format("%Y-%m")
+            .to_string()
+    });
+
+    let rows = sqlx::query(
+        r#"
+        SELECT account, month, bytes_downloaded FROM DownloadUsage
+        WHERE (? IS NULL OR account = ?) AND (? IS NULL OR month >= ?)
+        ORDER BY month DESC, account ASC
+        "#,
+    )
+    .bind(account)
+    .bind(account)
+    .bind(&since)
+    .bind(&since)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(MonthlyUsage {
+                account: row.try_get("account")?,
+                month: row.try_get("month")?,
+                bytes_downloaded: row.try_get::<i64, _>("bytes_downloaded")? as u64,
+            })
+        })
+        .collect()
+}
+
+/// Set or clear (None) an account's monthly cap
+pub async fn set_download_quota(
+    pool: &SqlitePool,
+    account: &str,
+    monthly_cap_bytes: Option<u64>,
+    action: QuotaAction,
+) -> Result<()> {
+    match monthly_cap_bytes {
+        Some(cap) => {
+            sqlx::query(
+                r#"
+                INSERT INTO DownloadQuotas (account, monthly_cap_bytes, action)
+                VALUES (?, ?, ?)
+                ON CONFLICT(account)
+                DO UPDATE SET monthly_cap_bytes = excluded.monthly_cap_bytes, action = excluded.action
+                "#,
+            )
+            .bind(account)
+            .bind(cap as i64)
+            .bind(action.as_str())
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM DownloadQuotas WHERE account = ?")
+                .bind(account)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// An account's monthly cap, if one is set
+pub async fn get_download_quota(pool: &SqlitePool, account: &str) -> Result<Option<DownloadQuota>> {
+    let row = sqlx::query("SELECT monthly_cap_bytes, action FROM DownloadQuotas WHERE account = ?")
+        .bind(account)
+        .fetch_optional(pool)
+        .await?;
+
+    row.map(|row| {
+        let action: String = row.try_get("action")?;
+        Ok(DownloadQuota {
+            account: account.to_string(),
+            monthly_cap_bytes: row.try_get::<i64, _>("monthly_cap_bytes")? as u64,
+            action: action.parse()?,
+        })
+    })
+    .transpose()
+}
+
+/// All monthly caps, by account
+pub async fn list_download_quotas(pool: &SqlitePool) -> Result<Vec<DownloadQuota>> {
+    let rows = sqlx::query("SELECT account, monthly_cap_bytes, action FROM DownloadQuotas ORDER BY account ASC")
+        .fetch_all(pool)
+        .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let action: String = row.try_get("action")?;
+            Ok(DownloadQuota {
+                account: row.try_get("account")?,
+                monthly_cap_bytes: row.try_get::<i64, _>("monthly_cap_bytes")? as u64,
+                action: action.parse()?,
+            })
+        })
+        .collect()
+}
+
+/// Usage this month against the account's cap
+pub async fn get_quota_status(pool: &SqlitePool, account: &str) -> Result<QuotaStatus> {
+    let month = current_month();
+    let bytes_downloaded: i64 = sqlx::query_scalar(
+        "SELECT bytes_downloaded FROM DownloadUsage WHERE account = ? AND month = ?"
+    )
+    .bind(account)
+    .bind(&month)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(0);
+    let bytes_downloaded = bytes_downloaded as u64;
+
+    let quota = get_download_quota(pool, account).await?;
+    let exceeded = quota
+        .as_ref()
+        .is_some_and(|quota| bytes_downloaded >= quota.monthly_cap_bytes);
+
+    Ok(QuotaStatus {
+        account: account.to_string(),
+        month,
+        bytes_downloaded,
+        quota,
+        exceeded,
+    })
+}
+
+/// Fail with `DownloadQuotaExceeded` if the account's cap blocks new downloads
+///
+/// # Returns
+/// The quota status, so callers can surface a warning when the cap is
+/// exceeded but only set to warn
+pub(crate) async fn check_quota(pool: &SqlitePool, account: &str) -> Result<QuotaStatus> {
+    let status = get_quota_status(pool, account).await?;
+
+    if let Some(quota) = status.quota.as_ref().filter(|q| status.exceeded && q.action == QuotaAction::Block) {
+        return Err(LibationError::DownloadQuotaExceeded {
+            account: account.to_string(),
+            used_bytes: status.bytes_downloaded,
+            cap_bytes: quota.monthly_cap_bytes,
+        });
+    }
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_usage_and_quota() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        record_usage(pool, "alice", 600).await.unwrap();
+        record_usage(pool, "alice", 400).await.unwrap();
+        record_usage(pool, "bob", 50).await.unwrap();
+        sqlx::query("INSERT INTO DownloadUsage (account, month, bytes_downloaded) VALUES ('alice', '2000-01', 7)")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let usage = get_download_usage(pool, Some("alice"), None).await.unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].month, current_month());
+        assert_eq!(usage[0].bytes_downloaded, 1000);
+
+        let recent = get_download_usage(pool, None, Some(1)).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert!(recent.iter().all(|u| u.month == current_month()));
+
+        // No cap: never exceeded
+        let status = check_quota(pool, "alice").await.unwrap();
+        assert!(status.quota.is_none() && !status.exceeded);
+
+        set_download_quota(pool, "alice", Some(1000), QuotaAction::Warn).await.unwrap();
+        let status = check_quota(pool, "alice").await.unwrap();
+        assert!(status.exceeded);
+
+        set_download_quota(pool, "alice", Some(1000), QuotaAction::Block).await.unwrap();
+        let err = check_quota(pool, "alice").await.unwrap_err();
+        assert!(matches!(err, LibationError::DownloadQuotaExceeded { used_bytes: 1000, .. }));
+        assert!(check_quota(pool, "bob").await.is_ok());
+
+        set_download_quota(pool, "alice", None, QuotaAction::Block).await.unwrap();
+        assert!(get_download_quota(pool, "alice").await.unwrap().is_none());
+    }
+}
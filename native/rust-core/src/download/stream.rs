@@ -32,27 +32,45 @@
 //! - Download speed throttling support (lines 46-48, 282-296)
 //! - Chunk size: 8KB (line 65: DOWNLOAD_BUFF_SZ = 8 * 1024)
 //!
+//! Both sizes are the defaults of a `BufferPolicy` (see `download::buffering`)
+//! and are tuned to the measured throughput unless set with `with_buffering`.
+//!
 //! # Resume Mechanism (from NetworkFileStream.cs lines 220-244)
 //! 1. Send Range header: bytes={WritePosition}-
 //! 2. Server responds with 206 Partial Content
 //! 3. Verify ContentRange.Length matches expected total size
 //! 4. Continue writing from WritePosition
-
+//!
+//! A server that ignores the Range header answers 200 with the whole file;
+//! the partial file is then truncated and written again from offset 0.
+//!
+//! # Decrypting While Downloading
+//! With `with_decryption`, AAXC samples are decrypted as they arrive and the
+//! file written is the final M4B. Decryption keeps the length, so the input
+//! and output offsets match; `write_position` only advances to the
+//! decrypter's checkpoint, the end of the last whole sample written out.
+
+use crate::crypto::aax::{AaxFileKey, StreamDecrypter};
+use crate::crypto::aaxc::AaxcDecrypter;
 use crate::error::{LibationError, Result};
+use crate::download::buffering::{BufferOverrides, BufferPolicy, BufferTuner};
+use crate::download::persistent_manager::{resume_start, ResumeStart};
 use crate::download::progress::{DownloadProgress, ProgressTracker, DownloadState as ProgressState};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::fs::{File, OpenOptions};
-use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
 use reqwest::{Client, StatusCode};
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 
 // Constants from NetworkFileStream.cs
-const DOWNLOAD_BUFF_SZ: usize = 8 * 1024; // 8KB chunks (line 65)
-const DATA_FLUSH_SZ: u64 = 1024 * 1024; // Flush every 1MB (line 69)
 const MAX_RETRIES: u32 = 5; // Maximum retry attempts
 
+/// First read of a partial output when resuming decryption; grows until it
+/// holds all of `moov`
+const RESUME_HEAD_SIZE: u64 = 64 * 1024;
+
 /// Persistent download state for resume support
 ///
 /// Based on NetworkFileStream.cs JSON serialization (lines 21-38)
@@ -76,6 +94,10 @@ pub struct StreamState {
     /// Request headers to include
     #[serde(default)]
     pub request_headers: std::collections::HashMap<String, String>,
+
+    /// The file holds decrypted output (see `ResumableStream::with_decryption`)
+    #[serde(default)]
+    pub decrypted: bool,
 }
 
 impl StreamState {
@@ -88,6 +110,7 @@ impl StreamState {
             write_position: 0,
             timestamp: chrono::Utc::now().to_rfc3339(),
             request_headers: std::collections::HashMap::new(),
+            decrypted: false,
         }
     }
 
@@ -136,6 +159,12 @@ pub struct ResumableStream {
 
     /// Retry configuration
     max_retries: u32,
+
+    /// Write buffering
+    buffering: BufferPolicy,
+
+    /// AAXC key to decrypt with while downloading
+    decrypter: Option<AaxcDecrypter>,
 }
 
 impl ResumableStream {
@@ -174,6 +203,8 @@ impl ResumableStream {
             state,
             progress_tracker: None,
             max_retries: MAX_RETRIES,
+            buffering: BufferPolicy::default(),
+            decrypter: None,
         })
     }
 
@@ -211,9 +242,33 @@ impl ResumableStream {
             state,
             progress_tracker: None,
             max_retries: MAX_RETRIES,
+            buffering: BufferPolicy::default(),
+            decrypter: None,
         })
     }
 
+    /// Set write buffering (default: 8KB buffer, 1MB flushes, auto-tuned)
+    ///
+    /// # Errors
+    /// InvalidInput if a value is outside the supported range
+    pub fn with_buffering(&mut self, policy: BufferPolicy) -> Result<()> {
+        policy.validate()?;
+        self.buffering = policy;
+        Ok(())
+    }
+
+    /// Decrypt AAXC samples as they arrive, writing the final M4B
+    ///
+    /// The file must have `moov` before `mdat`, as Audible's AAXC files do.
+    /// A partial download that wasn't decrypted is started over.
+    pub fn with_decryption(&mut self, decrypter: AaxcDecrypter) {
+        if !self.state.decrypted {
+            self.state.write_position = 0;
+            self.state.decrypted = true;
+        }
+        self.decrypter = Some(decrypter);
+    }
+
     /// Initialize progress tracking
     pub fn with_progress(&mut self, asin: String, title: String) {
         self.progress_tracker = Some(ProgressTracker::new(
@@ -231,6 +286,13 @@ impl ResumableStream {
     where
         F: FnMut(DownloadProgress) + Send,
     {
+        // Appending encrypted bytes to decrypted ones would corrupt the file
+        if self.state.decrypted && self.decrypter.is_none() {
+            return Err(LibationError::InvalidState(
+                "Partial download is decrypted; resume it with the same key".to_string()
+            ));
+        }
+
         // If already complete, nothing to do
         if self.state.write_position == self.state.content_length
             && self.state.content_length > 0
@@ -299,6 +361,11 @@ impl ResumableStream {
     where
         F: FnMut(DownloadProgress) + Send,
     {
+        if let Some(decrypter) = &self.decrypter {
+            let key = decrypter.file_key();
+            return self.download_decrypting(key, progress_callback).await;
+        }
+
         // Request next byte range
         let response = self.request_next_byte_range().await?;
 
@@ -309,14 +376,14 @@ impl ResumableStream {
             .open(&self.state.save_file_path)
             .await?;
 
-        let mut writer = BufWriter::with_capacity(DOWNLOAD_BUFF_SZ, file);
+        let mut tuner = BufferTuner::new(self.buffering, BufferOverrides::default(), std::time::Instant::now());
+        let mut writer = BufWriter::with_capacity(tuner.current().buffer_size, file);
 
         // Get response stream
         let mut stream = response.bytes_stream();
 
         // Track bytes for periodic flush
-        let mut bytes_since_flush = 0u64;
-        let mut next_flush = self.state.write_position + DATA_FLUSH_SZ;
+        let mut next_flush = self.state.write_position + tuner.current().flush_interval;
 
         // Download loop
         while let Some(chunk_result) = stream.next().await {
@@ -328,14 +395,19 @@ impl ResumableStream {
 
             // Update position
             self.state.write_position += chunk_len;
-            bytes_since_flush += chunk_len;
+
+            // Retune to the measured throughput
+            if let Some(tuned) = tuner.record(chunk_len, std::time::Instant::now()) {
+                writer.flush().await?;
+                writer = BufWriter::with_capacity(tuned.buffer_size, writer.into_inner());
+                next_flush = next_flush.min(self.state.write_position + tuned.flush_interval);
+            }
 
             // Flush periodically
             if self.state.write_position >= next_flush {
                 writer.flush().await?;
                 self.state.save().await?;
-                next_flush = self.state.write_position + DATA_FLUSH_SZ;
-                bytes_since_flush = 0;
+                next_flush = self.state.write_position + tuner.current().flush_interval;
 
                 // Update progress
                 if let Some(ref mut tracker) = self.progress_tracker {
@@ -368,6 +440,124 @@ impl ResumableStream {
         Ok(())
     }
 
+    /// `download_internal` for `with_decryption`
+    async fn download_decrypting<F>(&mut self, key: AaxFileKey, progress_callback: &mut F) -> Result<()>
+    where
+        F: FnMut(DownloadProgress) + Send,
+    {
+        let mut decrypter = self.resume_decrypter(key.clone()).await?;
+        let resumed_at = self.state.write_position;
+        let response = self.request_next_byte_range().await?;
+        if self.state.write_position < resumed_at {
+            decrypter = StreamDecrypter::new(key);
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.state.save_file_path)
+            .await?;
+
+        let mut tuner = BufferTuner::new(self.buffering, BufferOverrides::default(), std::time::Instant::now());
+        let mut writer = BufWriter::with_capacity(tuner.current().buffer_size, file);
+        let mut stream = response.bytes_stream();
+        let mut next_flush = self.state.write_position + tuner.current().flush_interval;
+        let mut decrypted = Vec::new();
+
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = chunk_result?;
+            decrypted.clear();
+            decrypter.push(&chunk, &mut decrypted)?;
+            writer.write_all(&decrypted).await?;
+
+            if let Some(tuned) = tuner.record(chunk.len() as u64, std::time::Instant::now()) {
+                writer.flush().await?;
+                writer = BufWriter::with_capacity(tuned.buffer_size, writer.into_inner());
+                next_flush = next_flush.min(decrypter.checkpoint() + tuned.flush_interval);
+            }
+
+            // Only flushed output is safe to resume from
+            if decrypter.checkpoint() >= next_flush {
+                writer.flush().await?;
+                self.state.write_position = decrypter.checkpoint();
+                self.state.save().await?;
+                next_flush = self.state.write_position + tuner.current().flush_interval;
+
+                if let Some(ref mut tracker) = self.progress_tracker {
+                    tracker.update(self.state.write_position, self.state.content_length);
+                    if tracker.should_update() {
+                        progress_callback(tracker.clone_progress());
+                    }
+                }
+            }
+        }
+
+        writer.flush().await?;
+        drop(writer);
+        let checkpoint = decrypter.checkpoint();
+
+        if checkpoint < self.state.content_length {
+            self.state.write_position = checkpoint;
+            self.state.save().await?;
+            return Err(LibationError::DownloadFailed(format!(
+                "Download incomplete: {}/{} bytes",
+                checkpoint, self.state.content_length
+            )));
+        }
+
+        // Rewrite the header before recording the download as complete
+        let patches = decrypter.finish()?;
+        let mut file = OpenOptions::new().write(true).open(&self.state.save_file_path).await?;
+        for (offset, atom_type) in patches {
+            file.seek(std::io::SeekFrom::Start(offset)).await?;
+            file.write_all(&atom_type).await?;
+        }
+        file.flush().await?;
+
+        self.state.write_position = checkpoint;
+        self.state.save().await?;
+
+        if let Some(ref mut tracker) = self.progress_tracker {
+            tracker.force_update(self.state.write_position);
+            progress_callback(tracker.clone_progress());
+        }
+
+        Ok(())
+    }
+
+    /// Pick up decryption at the saved position, truncating the file to the
+    /// last whole sample, or start over if the header isn't all there
+    async fn resume_decrypter(&mut self, key: AaxFileKey) -> Result<StreamDecrypter> {
+        let path = self.state.save_file_path.clone();
+        let offset = self.state.write_position;
+
+        if offset > 0 && path.exists() {
+            let mut file = File::open(&path).await?;
+            let mut head_size = RESUME_HEAD_SIZE;
+            loop {
+                let mut head = vec![0u8; head_size.min(offset) as usize];
+                file.seek(std::io::SeekFrom::Start(0)).await?;
+                file.read_exact(&mut head).await?;
+
+                if let Some(decrypter) = StreamDecrypter::resume(key.clone(), &head, offset)? {
+                    self.state.write_position = decrypter.checkpoint();
+                    truncate(&path, self.state.write_position).await?;
+                    return Ok(decrypter);
+                }
+                if head_size >= offset {
+                    break;
+                }
+                head_size *= 4;
+            }
+        }
+
+        self.state.write_position = 0;
+        if path.exists() {
+            truncate(&path, 0).await?;
+        }
+        Ok(StreamDecrypter::new(key))
+    }
+
     /// Request next byte range from server
     ///
     /// Based on RequestNextByteRangeAsync (lines 220-244)
@@ -391,12 +581,15 @@ impl ResumableStream {
         // Handle response status
         match response.status() {
             StatusCode::OK => {
-                // Full content (no resume support or starting from beginning)
-                if self.state.write_position > 0 {
-                    // Server doesn't support ranges, need to start over
-                    return Err(LibationError::DownloadFailed(
-                        "Server does not support range requests".to_string()
-                    ));
+                // Full content (no resume support or starting from beginning):
+                // start the file over instead of appending a second copy
+                let start = resume_start(self.state.write_position, self.state.content_length, StatusCode::OK, None)?;
+                if start == ResumeStart::Restart {
+                    if self.state.save_file_path.exists() {
+                        truncate(&self.state.save_file_path, 0).await?;
+                    }
+                    self.state.write_position = 0;
+                    self.state.save().await?;
                 }
 
                 // Get total content length
@@ -418,6 +611,12 @@ impl ResumableStream {
                     .get("content-range")
                     .and_then(|v| v.to_str().ok())
                     .ok_or_else(|| LibationError::DownloadFailed("No Content-Range header".to_string()))?;
+                resume_start(
+                    self.state.write_position,
+                    self.state.content_length,
+                    StatusCode::PARTIAL_CONTENT,
+                    Some(content_range),
+                )?;
 
                 // Parse Content-Range: bytes 1000-1999/2000
                 let total_size = content_range
@@ -468,6 +667,12 @@ impl ResumableStream {
     }
 }
 
+async fn truncate(path: &Path, len: u64) -> Result<()> {
+    let file = OpenOptions::new().write(true).open(path).await?;
+    file.set_len(len).await?;
+    Ok(())
+}
+
 /// Convenience function to download a file with progress tracking
 ///
 /// Port of the common download pattern from DownloadDecryptBook.cs
@@ -495,6 +700,36 @@ where
     stream.download(progress_callback).await
 }
 
+/// Download an AAXC file and decrypt it on the fly, writing the M4B to
+/// `output_path`
+///
+/// Resumes from a saved state like `download_to_file`, which must have been
+/// written with the same key.
+pub async fn download_and_decrypt<F>(
+    url: String,
+    output_path: PathBuf,
+    decrypter: AaxcDecrypter,
+    asin: String,
+    title: String,
+    request_headers: std::collections::HashMap<String, String>,
+    progress_callback: F,
+) -> Result<()>
+where
+    F: FnMut(DownloadProgress) + Send,
+{
+    let state_path = output_path.with_extension("download_state.json");
+
+    let mut stream = if state_path.exists() {
+        ResumableStream::from_state(&state_path).await?
+    } else {
+        ResumableStream::new(url, output_path, request_headers).await?
+    };
+
+    stream.with_decryption(decrypter);
+    stream.with_progress(asin, title);
+    stream.download(progress_callback).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -523,5 +758,157 @@ mod tests {
         let state_path = state.state_file_path();
         assert_eq!(state_path, PathBuf::from("/tmp/download.download_state.json"));
     }
+
+    /// Serves `data` over HTTP, honouring `Range: bytes=N-` except under
+    /// `/norange`
+    async fn serve_file(data: Vec<u8>) -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let data = std::sync::Arc::new(data);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let data = std::sync::Arc::clone(&data);
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let head = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                    let offset = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("range: bytes="))
+                        .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+                        .filter(|_| !head.starts_with("get /norange"))
+                        .unwrap_or(0);
+                    let status = if offset > 0 {
+                        format!("206 Partial Content\r\nContent-Range: bytes {}-{}/{}", offset, data.len() - 1, data.len())
+                    } else {
+                        "200 OK".to_string()
+                    };
+                    let body = &data[offset..];
+                    let header = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                    if socket.write_all(header.as_bytes()).await.is_ok() {
+                        let _ = socket.write_all(body).await;
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_download_and_decrypt_resumes_partial_output() {
+        use crate::crypto::aax::test_files::build_streamable_file;
+
+        let (key, iv) = ([0x31u8; 16], [0x42u8; 16]);
+        let samples: Vec<Vec<u8>> = [4000usize, 3000, 1600, 900]
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| (0..len).map(|b| (b * 7 + i) as u8).collect())
+            .collect();
+        let input = build_streamable_file(b"aaxc", key, iv, None, &samples);
+
+        let mut expected = Vec::new();
+        let mut full = StreamDecrypter::new(AaxFileKey::new(key, iv));
+        full.push(&input, &mut expected).unwrap();
+        for (offset, atom_type) in full.finish().unwrap() {
+            let offset = offset as usize;
+            expected[offset..offset + 4].copy_from_slice(&atom_type);
+        }
+
+        // A previous run stopped partway through the second sample, with its
+        // last bytes torn
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("book.m4b");
+        let mut partial = Vec::new();
+        let mut earlier = StreamDecrypter::new(AaxFileKey::new(key, iv));
+        earlier.push(&input[..input.len() - 3000], &mut partial).unwrap();
+        partial.extend_from_slice(b"torn");
+        std::fs::write(&output, &partial).unwrap();
+
+        let port = serve_file(input.clone()).await;
+        let mut state = StreamState::new(format!("http://127.0.0.1:{}/book.aaxc", port), output.clone());
+        state.content_length = input.len() as u64;
+        state.write_position = partial.len() as u64;
+        state.decrypted = true;
+        state.save().await.unwrap();
+
+        // Resuming a decrypted partial output needs the key
+        let mut stream = ResumableStream::from_state(&state.state_file_path()).await.unwrap();
+        assert!(matches!(stream.download(|_| {}).await, Err(LibationError::InvalidState(_))));
+
+        download_and_decrypt(
+            state.url.clone(),
+            output.clone(),
+            AaxcDecrypter::new(key, iv),
+            "B000TEST".to_string(),
+            "Test".to_string(),
+            Default::default(),
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&output).unwrap(), expected);
+        assert!(!state.state_file_path().exists());
+    }
+
+    #[tokio::test]
+    async fn test_resume_restarts_when_range_is_ignored() {
+        use crate::crypto::aax::test_files::build_streamable_file;
+
+        let (key, iv) = ([0x31u8; 16], [0x42u8; 16]);
+        let samples: Vec<Vec<u8>> = [4000usize, 3000, 1600, 900]
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| (0..len).map(|b| (b * 7 + i) as u8).collect())
+            .collect();
+        let input = build_streamable_file(b"aaxc", key, iv, None, &samples);
+        let mut expected = Vec::new();
+        let mut full = StreamDecrypter::new(AaxFileKey::new(key, iv));
+        full.push(&input, &mut expected).unwrap();
+        for (offset, atom_type) in full.finish().unwrap() {
+            let offset = offset as usize;
+            expected[offset..offset + 4].copy_from_slice(&atom_type);
+        }
+        let port = serve_file(input.clone()).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        // Plain download: a 200 to the Range request rewrites the file
+        let plain = dir.path().join("book.aaxc");
+        std::fs::write(&plain, &input[..2000]).unwrap();
+        let mut state = StreamState::new(format!("http://127.0.0.1:{}/norange/book.aaxc", port), plain.clone());
+        state.content_length = input.len() as u64;
+        state.write_position = 2000;
+        state.save().await.unwrap();
+        download_to_file(state.url.clone(), plain.clone(), "B000TEST".to_string(), "Test".to_string(), Default::default(), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&plain).unwrap(), input);
+
+        // Decrypting: the decrypter starts over with the file
+        let output = dir.path().join("book.m4b");
+        let mut partial = Vec::new();
+        StreamDecrypter::new(AaxFileKey::new(key, iv)).push(&input[..input.len() - 3000], &mut partial).unwrap();
+        std::fs::write(&output, &partial).unwrap();
+        let mut state = StreamState::new(format!("http://127.0.0.1:{}/norange/book.aaxc", port), output.clone());
+        state.content_length = input.len() as u64;
+        state.write_position = partial.len() as u64;
+        state.decrypted = true;
+        state.save().await.unwrap();
+        download_and_decrypt(
+            state.url.clone(),
+            output.clone(),
+            AaxcDecrypter::new(key, iv),
+            "B000TEST".to_string(),
+            "Test".to_string(),
+            Default::default(),
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), expected);
+    }
 }
 
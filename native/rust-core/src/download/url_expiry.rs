@@ -0,0 +1,113 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Expiry of signed CDN download URLs
+//!
+//! Download URLs from a license are signed and stop working after a while
+//! (an hour for some CDNs). A task resumed after its URL expired gets a
+//! 403 instead of data; checking the signature's expiry first gives the app
+//! a clear `DownloadUrlExpired` so it can request a new license and call
+//! `PersistentDownloadManager::update_download_url`.
+//!
+//! Recognized signatures:
+//! - CloudFront / Akamai style `Expires=<unix seconds>`
+//! - S3 presigned `X-Amz-Date=<YYYYMMDDTHHMMSSZ>&X-Amz-Expires=<seconds>`
+//!
+//! URLs without either are assumed not to expire.
+
+use crate::clock::Clock;
+use crate::error::{LibationError, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// Treat URLs expiring this soon as already expired, so the request
+/// doesn't race the deadline
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// When a signed download URL stops working (None if it doesn't say)
+pub fn download_url_expires_at(url: &str) -> Option<DateTime<Utc>> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.into_owned())
+    };
+
+    if let Some(expires) = param("Expires").and_then(|v| v.parse::<i64>().ok()) {
+        return DateTime::from_timestamp(expires, 0);
+    }
+
+    let signed_at = NaiveDateTime::parse_from_str(&param("X-Amz-Date")?, "%Y%m%dT%H%M%SZ").ok()?;
+    let valid_for = param("X-Amz-Expires")?.parse::<i64>().ok()?;
+    Some(signed_at.and_utc() + chrono::Duration::seconds(valid_for))
+}
+
+/// Fail if a task's download URL has expired (or is about to)
+///
+/// # Errors
+/// DownloadUrlExpired with the expiry time
+pub fn check_download_url(asin: &str, url: &str, clock: &dyn Clock) -> Result<()> {
+    match download_url_expires_at(url) {
+        Some(expires_at) if expires_at <= clock.now() + chrono::Duration::seconds(EXPIRY_MARGIN_SECS) => {
+            Err(LibationError::DownloadUrlExpired {
+                asin: asin.to_string(),
+                expired_at: expires_at.to_rfc3339(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_download_url_expires_at() {
+        let cloudfront = "https://dcdn.audible.com/p/B0TEST.aax?Expires=1740830400&Signature=abc&Key-Pair-Id=K1";
+        assert_eq!(
+            download_url_expires_at(cloudfront),
+            Some(Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap())
+        );
+
+        let s3 = "https://bucket.s3.amazonaws.com/B0TEST.aaxc?X-Amz-Date=20250301T110000Z&X-Amz-Expires=3600&X-Amz-Signature=abc";
+        assert_eq!(
+            download_url_expires_at(s3),
+            Some(Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap())
+        );
+
+        assert_eq!(download_url_expires_at("https://example.com/file.mp3"), None);
+        assert_eq!(download_url_expires_at("not a url"), None);
+    }
+
+    #[test]
+    fn test_check_download_url_time_travel() {
+        let url = "https://dcdn.audible.com/p/B0TEST.aax?Expires=1740830400&Signature=abc";
+        let clock = TestClock::new(Utc.with_ymd_and_hms(2025, 3, 1, 11, 0, 0).unwrap());
+        assert!(check_download_url("B0TEST", url, &clock).is_ok());
+
+        // Within the safety margin counts as expired
+        clock.advance(chrono::Duration::seconds(59 * 60 + 30));
+        assert!(matches!(
+            check_download_url("B0TEST", url, &clock),
+            Err(LibationError::DownloadUrlExpired { .. })
+        ));
+
+        clock.advance(chrono::Duration::days(1));
+        assert!(check_download_url("B0TEST", "https://example.com/file.mp3", &clock).is_ok());
+    }
+}
@@ -42,7 +42,7 @@
 //! - `NullReferenceException` → `MissingRequiredField`
 //!
 //! ### Audio Processing (from FileLiberator, AaxDecrypter)
-//! - FFmpeg failures → `FfmpegError`, `FfmpegNotFound`
+//! - FFmpeg failures → `FfmpegError`, `FfmpegNotFound`, `UnsupportedOperation`
 //! - Format detection failures → `UnsupportedAudioFormat`, `InvalidAudioFile`
 
 use thiserror::Error;
@@ -195,6 +195,22 @@ pub enum LibationError {
     #[error("Invalid download URL: {0}")]
     InvalidDownloadUrl(String),
 
+    /// Signed download URL has expired; a new license is needed
+    #[error("Download URL for {asin} expired at {expired_at}")]
+    DownloadUrlExpired {
+        asin: String,
+        expired_at: String,
+    },
+
+    /// Download stopped receiving data on every URL it had; a new license
+    /// (with fresh URLs) is needed
+    #[error("Download of {asin} stalled at byte {offset} after {stalls} stalls; refresh the license")]
+    DownloadStalled {
+        asin: String,
+        offset: u64,
+        stalls: u32,
+    },
+
     /// Content license missing offline URL (maps to InvalidDataException in DownloadOptions.cs)
     #[error("Content license doesn't contain an offline URL")]
     MissingOfflineUrl,
@@ -203,6 +219,29 @@ pub enum LibationError {
     #[error("Failed to get mpeg-dash content download URL")]
     MpegDashUrlFailed,
 
+    /// Title's license (e.g. a loan) doesn't allow offline download
+    #[error("Download not permitted for {asin}: {benefit_type} titles cannot be downloaded")]
+    DownloadNotPermitted {
+        asin: String,
+        benefit_type: String,
+    },
+
+    /// Title is only offered in formats the pipeline can't decrypt
+    #[error("No supported download format for {asin}: {format_support}")]
+    UnsupportedDownloadFormat {
+        asin: String,
+        /// `FormatSupport` string, e.g. "widevine_only"
+        format_support: String,
+    },
+
+    /// Account reached its user-set monthly download cap
+    #[error("Monthly download cap reached for {account}: {used_bytes} of {cap_bytes} bytes used")]
+    DownloadQuotaExceeded {
+        account: String,
+        used_bytes: u64,
+        cap_bytes: u64,
+    },
+
     // ===== Audio/Conversion Errors =====
     // Corresponds to audio processing in FileLiberator, ConvertToMp3.cs
 
@@ -226,6 +265,14 @@ pub enum LibationError {
     #[error("FFmpeg not found. Please install FFmpeg and ensure it's in your PATH.")]
     FfmpegNotFound,
 
+    /// Operation needs an audio backend this platform doesn't have
+    /// (see `audio::capabilities::get_audio_capabilities`)
+    #[error("Unsupported operation '{operation}': {reason}")]
+    UnsupportedOperation {
+        operation: String,
+        reason: String,
+    },
+
     /// Audio file is corrupted or has invalid metadata
     #[error("Invalid audio file: {0}")]
     InvalidAudioFile(String),
@@ -264,6 +311,15 @@ pub enum LibationError {
     #[error("File already exists: {0}")]
     FileAlreadyExists(String),
 
+    /// Output path failed pre-flight validation (see `file::paths::validate_output_path`)
+    #[error("Path validation failed [{code}] for {path}: {message}")]
+    PathValidationFailed {
+        /// Which check failed (see `file::paths::PathCheck::code`)
+        code: String,
+        path: String,
+        message: String,
+    },
+
     /// Download directory doesn't exist (maps to ArgumentException in NetworkFileStream.cs)
     #[error("Download directory does not exist: {0}")]
     DownloadDirectoryNotFound(String),
@@ -396,7 +452,7 @@ pub enum LibationError {
 
     /// JNI bridge error (Android only)
     #[error("JNI error: {0}")]
-    #[cfg(target_os = "android")]
+    #[cfg(all(feature = "android-bridge", target_os = "android"))]
     JniError(String),
 }
 
@@ -534,15 +590,50 @@ impl LibationError {
                 | LibationError::InvalidPath(_)
                 | LibationError::FileAlreadyExists(_)
                 | LibationError::DownloadDirectoryNotFound(_)
+                | LibationError::PathValidationFailed { .. }
         )
     }
 
+    /// Check if error is because another process holds the file
+    ///
+    /// Returns `true` for EBUSY/ETXTBSY and Windows sharing or lock
+    /// violations, whether raised directly or reported in FFmpeg's output.
+    /// On Android the media scanner briefly holds files it is indexing.
+    pub fn is_file_locked(&self) -> bool {
+        const LOCK_MESSAGES: &[&str] = &[
+            "resource busy",
+            "text file busy",
+            "sharing violation",
+            "lock violation",
+            "locked a portion of the file",
+            "being used by another process",
+            "os error 16)",
+            "os error 26)",
+        ];
+
+        match self {
+            LibationError::IoError(e) => {
+                e.kind() == std::io::ErrorKind::ResourceBusy
+                    || matches!(e.raw_os_error(), Some(16) | Some(26))
+                    || (cfg!(windows) && matches!(e.raw_os_error(), Some(32) | Some(33)))
+            }
+            LibationError::FileIoError(message)
+            | LibationError::FfmpegError(message)
+            | LibationError::ConversionFailed(message) => {
+                let message = message.to_lowercase();
+                LOCK_MESSAGES.iter().any(|m| message.contains(m))
+            }
+            _ => false,
+        }
+    }
+
     /// Check if error is related to DRM/crypto operations
     pub fn is_crypto_error(&self) -> bool {
         matches!(
             self,
             LibationError::DecryptionFailed(_)
                 | LibationError::InvalidDrmFormat(_)
+                | LibationError::UnsupportedDownloadFormat { .. }
                 | LibationError::WidevineCdmError { .. }
                 | LibationError::InvalidCdmFile { .. }
                 | LibationError::ActivationBytesNotFound(_)
@@ -575,6 +666,9 @@ impl LibationError {
             LibationError::FfmpegNotFound => {
                 "FFmpeg is required but not found. Please install FFmpeg and ensure it's in your PATH.".to_string()
             }
+            LibationError::UnsupportedOperation { operation, .. } => {
+                format!("'{}' isn't available on this device.", operation)
+            }
             LibationError::ActivationBytesNotFound(account) => {
                 format!("Activation bytes not found for account '{}'. Please provide activation bytes to decrypt AAX files.", account)
             }
@@ -595,6 +689,25 @@ impl LibationError {
                     have / 1_000_000
                 )
             }
+            LibationError::PathValidationFailed { path, message, .. } => {
+                format!("Cannot write to '{}': {}. Please choose a different location.", path, message)
+            }
+            LibationError::DownloadNotPermitted { benefit_type, .. } => {
+                format!("This title is a {} and its license doesn't allow downloading. You can still listen to it in the Audible app.", benefit_type)
+            }
+            LibationError::UnsupportedDownloadFormat { format_support, .. } => {
+                if format_support == "widevine_only" {
+                    "This title is only available with Widevine DRM, which this device can't decrypt yet.".to_string()
+                } else {
+                    "This title is only available in a format (such as Dolby Atmos AC-4) that can't be decrypted yet.".to_string()
+                }
+            }
+            LibationError::DownloadQuotaExceeded { cap_bytes, .. } => {
+                format!(
+                    "You've reached this month's download limit of {} MB for this account. Raise the limit in settings or wait until next month.",
+                    cap_bytes / 1_000_000
+                )
+            }
             LibationError::RateLimitExceeded { retry_after_seconds, .. } => {
                 format!(
                     "API rate limit exceeded. Please wait {} seconds before trying again.",
@@ -614,6 +727,9 @@ impl LibationError {
             LibationError::DownloadInterrupted => {
                 "Download was interrupted. Please try again.".to_string()
             }
+            LibationError::DownloadStalled { .. } => {
+                "The download server stopped sending data. Please start the download again.".to_string()
+            }
             LibationError::ImportValidation { error_count, errors } => {
                 let error_list = errors.iter()
                     .take(3)
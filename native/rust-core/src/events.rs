@@ -0,0 +1,869 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Work-finished events for host notifications
+//!
+//! When a bridge job (library sync, decryption, export, ...) or a download
+//! ends, the host listener gets a `WorkEvent` it can turn into a system
+//! notification. Each event carries its category and a severity, and
+//! whether the user's notification settings say to keep it quiet, so
+//! Android and iOS apply the same policy:
+//!
+//! - `QuietHours::muted_categories` are never notified
+//! - inside the quiet hours window only events at or above
+//!   `breakthrough_severity` (errors, by default) are notified
+//!
+//! Suppressed events are still delivered, flagged `suppressed`, so the app
+//! can update badges or an in-app list without alerting. Quiet hours are
+//! stored in the settings table and evaluated at the local hour of the
+//! app clock.
+//!
+//! Running work also reports `ProgressEvent`s: downloads, decryptions,
+//! library syncs (fetching, then importing), conversions, and anything
+//! else run as a job (`storage::jobs::run_job`). Each carries the stage
+//! the work is in, its fraction done, byte or item counts where they apply,
+//! and an ETA extrapolated from progress since the stage started. Code
+//! running under `with_progress_scope` reports with `report_items` /
+//! `report_fraction` without knowing its job id.
+//!
+//! Events are queued and handed to the listener on a delivery thread, so a
+//! host whose JS thread is busy never stalls the work. While the host
+//! catches up, progress of the same job is coalesced (latest wins) and the
+//! queue is capped at `MAX_QUEUE_DEPTH` by dropping the oldest progress;
+//! work events are never coalesced or dropped. Hosts that would rather
+//! poll read the latest progress of each running operation with
+//! `current_progress`, which works with or without a listener.
+//!
+//! A `MediaScanEvent` asks the host to index liberated files with the
+//! platform's media scanner (see `download::post_hooks`); like work events
+//! it is never dropped.
+
+use crate::cancel::JobKind;
+use crate::clock::Clock;
+use crate::download::ConversionWindow;
+use crate::error::{LibationError, Result};
+use crate::storage::jobs::JobStatus;
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Condvar, Mutex, Once, RwLock};
+use std::time::{Duration, Instant};
+
+const KEY_QUIET_HOURS: &str = "notifications.quiet_hours";
+
+/// Events waiting for the host before the oldest progress is dropped
+pub const MAX_QUEUE_DEPTH: usize = 64;
+
+/// Progress not updated for this long is left out of `current_progress`
+/// (its work ended without an outcome event)
+pub const STALE_PROGRESS: Duration = Duration::from_secs(10 * 60);
+
+/// How much an event deserves the user's attention, least first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSeverity {
+    /// Nothing to act on (cancelled work)
+    Info,
+    /// Work finished
+    Success,
+    /// Finished, but with something to look at
+    Warning,
+    /// Work failed
+    Error,
+}
+
+impl EventSeverity {
+    /// Severity of work that ended with `status`
+    pub fn for_status(status: JobStatus) -> Self {
+        match status {
+            JobStatus::Running | JobStatus::Cancelled => EventSeverity::Info,
+            JobStatus::Completed => EventSeverity::Success,
+            JobStatus::Failed | JobStatus::Interrupted => EventSeverity::Error,
+        }
+    }
+}
+
+/// Notification settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct QuietHours {
+    /// Apply the window below
+    pub enabled: bool,
+    /// First local hour inside quiet hours (0-23)
+    pub start_hour: u32,
+    /// First local hour after quiet hours (0-23); equal to `start_hour`
+    /// means all day
+    pub end_hour: u32,
+    /// Events at or above this severity are notified even in quiet hours
+    pub breakthrough_severity: EventSeverity,
+    /// Categories that are never notified
+    pub muted_categories: Vec<JobKind>,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_hour: 22,
+            end_hour: 7,
+            breakthrough_severity: EventSeverity::Error,
+            muted_categories: Vec::new(),
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether an event should be kept quiet at `local_hour`
+    pub fn suppresses(&self, category: JobKind, severity: EventSeverity, local_hour: u32) -> bool {
+        if self.muted_categories.contains(&category) {
+            return true;
+        }
+        let window = ConversionWindow {
+            start_hour: self.start_hour,
+            end_hour: self.end_hour,
+        };
+        self.enabled && window.contains(local_hour) && severity < self.breakthrough_severity
+    }
+}
+
+/// Stored notification settings (default if never set)
+pub async fn get_quiet_hours(pool: &SqlitePool) -> Result<QuietHours> {
+    Ok(get_json_setting(pool, KEY_QUIET_HOURS).await?.unwrap_or_default())
+}
+
+/// Store notification settings
+///
+/// # Errors
+/// - InvalidInput if an hour is above 23
+pub async fn set_quiet_hours(pool: &SqlitePool, quiet_hours: &QuietHours) -> Result<()> {
+    if quiet_hours.start_hour > 23 || quiet_hours.end_hour > 23 {
+        return Err(LibationError::invalid_input("Quiet hours must be between 0 and 23"));
+    }
+    set_json_setting(pool, KEY_QUIET_HOURS, quiet_hours).await
+}
+
+/// A job or download that ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkEvent {
+    pub category: JobKind,
+    /// Job id, or task id for downloads
+    pub id: String,
+    /// Title the work was for, if it was for one
+    pub asin: Option<String>,
+    pub status: JobStatus,
+    pub severity: EventSeverity,
+    /// Error message of failed work
+    pub error: Option<String>,
+    /// Keep it quiet: muted category, or quiet hours below the
+    /// breakthrough severity
+    pub suppressed: bool,
+    /// Trace id the work ran under (see `trace`)
+    pub trace_id: Option<String>,
+    pub finished_at: String,
+}
+
+impl WorkEvent {
+    /// Event for work that ended with `outcome`, judged against the stored
+    /// notification settings
+    ///
+    /// Settings that can't be read count as the defaults; an event is
+    /// never lost to them.
+    pub async fn for_outcome<T>(
+        pool: &SqlitePool,
+        clock: &dyn Clock,
+        category: JobKind,
+        id: &str,
+        asin: Option<&str>,
+        outcome: &Result<T>,
+    ) -> Self {
+        let (status, error) = match outcome {
+            Ok(_) => (JobStatus::Completed, None),
+            Err(LibationError::Cancelled) => (JobStatus::Cancelled, None),
+            Err(e) => (JobStatus::Failed, Some(e.to_string())),
+        };
+        let severity = EventSeverity::for_status(status);
+        let quiet_hours = get_quiet_hours(pool).await.unwrap_or_default();
+
+        WorkEvent {
+            category,
+            id: id.to_string(),
+            asin: asin.map(str::to_string),
+            status,
+            severity,
+            error,
+            suppressed: quiet_hours.suppresses(category, severity, clock.local_hour()),
+            trace_id: crate::trace::current(),
+            finished_at: crate::storage::dates::format_timestamp(clock.now()),
+        }
+    }
+}
+
+/// What running work is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressStage {
+    /// Fetching from Audible (library pages, licenses)
+    Fetching,
+    /// Writing fetched library items to the database
+    Importing,
+    Downloading,
+    Decrypting,
+    Converting,
+    Exporting,
+    Scanning,
+    Verifying,
+    /// Moving files to another app or device
+    Transferring,
+}
+
+impl ProgressStage {
+    /// Stage of work of `category` that reports no other
+    pub fn for_kind(category: JobKind) -> Self {
+        match category {
+            JobKind::LibrarySync | JobKind::LicensePrefetch => ProgressStage::Fetching,
+            JobKind::Download => ProgressStage::Downloading,
+            JobKind::Decryption => ProgressStage::Decrypting,
+            JobKind::Conversion => ProgressStage::Converting,
+            JobKind::Export => ProgressStage::Exporting,
+            JobKind::Scan => ProgressStage::Scanning,
+            JobKind::IntegrityCheck => ProgressStage::Verifying,
+            JobKind::Handoff => ProgressStage::Transferring,
+        }
+    }
+}
+
+/// Progress of a running job or download
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    pub category: JobKind,
+    /// Job id, or task id for downloads
+    pub id: String,
+    /// Title the work is for, if it is for one
+    pub asin: Option<String>,
+    pub stage: ProgressStage,
+    /// 0.0 to 1.0, of the current stage
+    pub fraction: f32,
+    /// Byte counts, for stages that move bytes (0 otherwise)
+    pub bytes_processed: u64,
+    pub total_bytes: u64,
+    /// Item counts, for stages that go through items (library items)
+    pub items_processed: Option<u64>,
+    pub total_items: Option<u64>,
+    /// Estimated seconds until the stage is done
+    pub eta_secs: Option<u64>,
+    /// Earlier updates replaced by this one while the host was busy
+    pub coalesced: u32,
+}
+
+fn fraction_of(processed: u64, total: u64) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        (processed as f64 / total as f64).min(1.0) as f32
+    }
+}
+
+impl ProgressEvent {
+    /// Byte progress, in the category's default stage
+    pub fn new(category: JobKind, id: &str, asin: Option<&str>, bytes_processed: u64, total_bytes: u64) -> Self {
+        ProgressEvent {
+            category,
+            id: id.to_string(),
+            asin: asin.map(str::to_string),
+            stage: ProgressStage::for_kind(category),
+            fraction: fraction_of(bytes_processed, total_bytes),
+            bytes_processed,
+            total_bytes,
+            items_processed: None,
+            total_items: None,
+            eta_secs: None,
+            coalesced: 0,
+        }
+    }
+
+    /// Item progress in `stage`
+    pub fn for_items(category: JobKind, id: &str, stage: ProgressStage, processed: u64, total: u64) -> Self {
+        ProgressEvent {
+            stage,
+            fraction: fraction_of(processed, total),
+            items_processed: Some(processed),
+            total_items: Some(total),
+            ..ProgressEvent::new(category, id, None, 0, 0)
+        }
+    }
+
+    /// Progress known only as a fraction, in `stage`
+    pub fn for_fraction(category: JobKind, id: &str, stage: ProgressStage, fraction: f32) -> Self {
+        ProgressEvent {
+            stage,
+            fraction: fraction.clamp(0.0, 1.0),
+            ..ProgressEvent::new(category, id, None, 0, 0)
+        }
+    }
+
+    pub fn with_stage(self, stage: ProgressStage) -> Self {
+        ProgressEvent { stage, ..self }
+    }
+}
+
+/// Latest progress of an operation, with where its stage started
+#[derive(Debug)]
+struct TrackedProgress {
+    stage_started: Instant,
+    stage_start_fraction: f32,
+    updated: Instant,
+    latest: ProgressEvent,
+}
+
+/// Latest progress per (category, id)
+type ProgressTracker = HashMap<(JobKind, String), TrackedProgress>;
+
+static PROGRESS: Mutex<Option<ProgressTracker>> = Mutex::new(None);
+
+/// Record `event` as its operation's latest progress, filling in its ETA
+///
+/// The ETA extrapolates the rate since the stage started; it is only
+/// estimated once some progress was made.
+fn track_progress(event: &mut ProgressEvent, now: Instant) {
+    let mut progress = PROGRESS.lock().unwrap();
+    let progress = progress.get_or_insert_with(HashMap::new);
+    progress.retain(|_, tracked| now.duration_since(tracked.updated) < STALE_PROGRESS);
+
+    let key = (event.category, event.id.clone());
+    let tracked = match progress.get_mut(&key) {
+        Some(tracked) if tracked.latest.stage == event.stage && event.fraction >= tracked.stage_start_fraction => tracked,
+        _ => {
+            let tracked = TrackedProgress {
+                stage_started: now,
+                stage_start_fraction: event.fraction,
+                updated: now,
+                latest: event.clone(),
+            };
+            progress.entry(key).insert_entry(tracked).into_mut()
+        }
+    };
+
+    let done = event.fraction - tracked.stage_start_fraction;
+    if event.eta_secs.is_none() && done > 0.0 {
+        let elapsed = now.duration_since(tracked.stage_started).as_secs_f64();
+        event.eta_secs = Some((elapsed / done as f64 * (1.0 - event.fraction) as f64).round() as u64);
+    }
+    tracked.updated = now;
+    tracked.latest = event.clone();
+}
+
+/// Drop the progress of work that ended
+fn forget_progress(category: JobKind, id: &str) {
+    if let Some(progress) = PROGRESS.lock().unwrap().as_mut() {
+        progress.remove(&(category, id.to_string()));
+    }
+}
+
+/// Latest progress of each running operation, by category and id
+///
+/// For hosts that poll instead of (or as well as) listening. Entries go
+/// when their work ends, or after `STALE_PROGRESS` without an update.
+pub fn current_progress() -> Vec<ProgressEvent> {
+    let progress = PROGRESS.lock().unwrap();
+    let mut current: Vec<ProgressEvent> = progress
+        .iter()
+        .flat_map(|p| p.values())
+        .filter(|tracked| tracked.updated.elapsed() < STALE_PROGRESS)
+        .map(|tracked| tracked.latest.clone())
+        .collect();
+    current.sort_by(|a, b| (a.category.as_str(), &a.id).cmp(&(b.category.as_str(), &b.id)));
+    current
+}
+
+thread_local! {
+    /// Operation reported on by `report_items` / `report_fraction`
+    static OPERATION: RefCell<Option<(JobKind, String)>> = const { RefCell::new(None) };
+}
+
+/// Run `future` as operation `id` of `category`: progress it reports with
+/// `report_items` / `report_fraction` is emitted for that operation
+pub async fn with_progress_scope<F: Future>(category: JobKind, id: &str, future: F) -> F::Output {
+    let operation = Some((category, id.to_string()));
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let previous = OPERATION.with(|current| current.replace(operation.clone()));
+        let poll = future.as_mut().poll(cx);
+        OPERATION.with(|current| *current.borrow_mut() = previous);
+        poll
+    })
+    .await
+}
+
+/// Operation of the current progress scope, for reporting from work
+/// spawned off it (which runs outside the scope)
+pub fn current_operation() -> Option<(JobKind, String)> {
+    OPERATION.with(|current| current.borrow().clone())
+}
+
+/// Report item progress of the current operation (see
+/// `with_progress_scope`); does nothing outside one
+pub fn report_items(stage: ProgressStage, processed: u64, total: u64) {
+    if let Some((category, id)) = current_operation() {
+        emit_progress(ProgressEvent::for_items(category, &id, stage, processed, total));
+    }
+}
+
+/// Report fractional progress of the current operation, with an ETA if
+/// the work knows better than extrapolation; does nothing outside one
+pub fn report_fraction(stage: ProgressStage, fraction: f32, eta_secs: Option<u64>) {
+    if let Some((category, id)) = current_operation() {
+        emit_progress(ProgressEvent {
+            eta_secs,
+            ..ProgressEvent::for_fraction(category, &id, stage, fraction)
+        });
+    }
+}
+
+/// Files for the host to hand to the platform's media scanner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaScanEvent {
+    pub asin: String,
+    /// Local paths of the book's files
+    pub paths: Vec<String>,
+}
+
+/// An event waiting for the host
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostEvent {
+    Progress(ProgressEvent),
+    /// Terminal: work completed, failed or was cancelled
+    Work(WorkEvent),
+    MediaScan(MediaScanEvent),
+}
+
+impl HostEvent {
+    fn is_progress_of(&self, category: JobKind, id: &str) -> bool {
+        matches!(self, HostEvent::Progress(p) if p.category == category && p.id == id)
+    }
+}
+
+/// Events the host hasn't taken yet, coalesced per job
+#[derive(Debug)]
+pub struct EventQueue {
+    max_depth: usize,
+    pending: VecDeque<HostEvent>,
+    dropped: u64,
+}
+
+impl EventQueue {
+    pub fn new(max_depth: usize) -> Self {
+        EventQueue {
+            max_depth,
+            pending: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Queue `event`
+    ///
+    /// Progress replaces queued progress of the same job in place. A new
+    /// progress event on a full queue evicts the oldest progress event, or
+    /// is dropped if only work events are queued. Work events always go
+    /// in, after removing the job's queued progress.
+    pub fn push(&mut self, event: HostEvent) {
+        match event {
+            HostEvent::Progress(mut progress) => {
+                let queued = self
+                    .pending
+                    .iter_mut()
+                    .find(|e| e.is_progress_of(progress.category, &progress.id));
+                if let Some(HostEvent::Progress(queued)) = queued {
+                    progress.coalesced += queued.coalesced + 1;
+                    *queued = progress;
+                    return;
+                }
+
+                if self.pending.len() >= self.max_depth {
+                    self.dropped += 1;
+                    match self.pending.iter().position(|e| matches!(e, HostEvent::Progress(_))) {
+                        Some(oldest) => {
+                            self.pending.remove(oldest);
+                        }
+                        None => return,
+                    }
+                }
+                self.pending.push_back(HostEvent::Progress(progress));
+            }
+            HostEvent::Work(work) => {
+                // Progress of work that ended is stale
+                self.pending.retain(|e| !e.is_progress_of(work.category, &work.id));
+                self.pending.push_back(HostEvent::Work(work));
+            }
+            HostEvent::MediaScan(scan) => self.pending.push_back(HostEvent::MediaScan(scan)),
+        }
+    }
+
+    /// Oldest queued event
+    pub fn pop(&mut self) -> Option<HostEvent> {
+        self.pending.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Progress events dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Receives work and progress events (implemented by the platform bridges)
+///
+/// Called on the event delivery thread, one event at a time; a slow
+/// listener only makes progress coalesce.
+pub trait WorkEventListener: Send + Sync {
+    fn on_work_event(&self, event: &WorkEvent);
+
+    /// Progress of running work; ignored unless implemented
+    fn on_progress_event(&self, _event: &ProgressEvent) {}
+
+    /// Files to media-scan; ignored unless implemented
+    fn on_media_scan(&self, _event: &MediaScanEvent) {}
+}
+
+static LISTENER: RwLock<Option<Arc<dyn WorkEventListener>>> = RwLock::new(None);
+
+lazy_static::lazy_static! {
+    static ref QUEUE: Mutex<EventQueue> = Mutex::new(EventQueue::new(MAX_QUEUE_DEPTH));
+}
+
+static QUEUED: Condvar = Condvar::new();
+static START_DELIVERY: Once = Once::new();
+
+/// Install (or remove, with None) the host listener
+///
+/// Events still queued for a removed listener are discarded.
+pub fn set_work_event_listener(listener: Option<Arc<dyn WorkEventListener>>) {
+    if listener.is_none() {
+        QUEUE.lock().unwrap().clear();
+    }
+    *LISTENER.write().unwrap() = listener;
+}
+
+/// Whether anyone listens; lets callers skip building events
+pub fn has_listener() -> bool {
+    LISTENER.read().unwrap().is_some()
+}
+
+/// Hand queued events to the listener, forever
+fn deliver_events() {
+    loop {
+        let event = {
+            let mut queue = QUEUE.lock().unwrap();
+            loop {
+                if let Some(event) = queue.pop() {
+                    break event;
+                }
+                queue = QUEUED.wait(queue).unwrap();
+            }
+        };
+
+        let listener = LISTENER.read().unwrap().clone();
+        if let Some(listener) = listener {
+            match &event {
+                HostEvent::Progress(progress) => listener.on_progress_event(progress),
+                HostEvent::Work(work) => listener.on_work_event(work),
+                HostEvent::MediaScan(scan) => listener.on_media_scan(scan),
+            }
+        }
+    }
+}
+
+fn enqueue(event: HostEvent) {
+    if !has_listener() {
+        return;
+    }
+    START_DELIVERY.call_once(|| {
+        std::thread::Builder::new()
+            .name("work-events".to_string())
+            .spawn(deliver_events)
+            .expect("Failed to start event delivery thread");
+    });
+    QUEUE.lock().unwrap().push(event);
+    QUEUED.notify_one();
+}
+
+/// Queue `event` for the host listener
+pub fn emit(event: &WorkEvent) {
+    forget_progress(event.category, &event.id);
+    enqueue(HostEvent::Work(event.clone()));
+}
+
+/// Record progress for `current_progress` and queue it for the host
+/// listener (coalesced while it's busy)
+pub fn emit_progress(mut event: ProgressEvent) {
+    track_progress(&mut event, Instant::now());
+    enqueue(HostEvent::Progress(event));
+}
+
+/// Queue a media scan request for the host listener
+pub fn emit_media_scan(event: MediaScanEvent) {
+    enqueue(HostEvent::MediaScan(event));
+}
+
+/// Build and emit the event for work that ended with `outcome`, if anyone
+/// listens (see `WorkEvent::for_outcome`)
+pub async fn emit_outcome<T>(
+    pool: &SqlitePool,
+    clock: &dyn Clock,
+    category: JobKind,
+    id: &str,
+    asin: Option<&str>,
+    outcome: &Result<T>,
+) {
+    if has_listener() {
+        emit(&WorkEvent::for_outcome(pool, clock, category, id, asin, outcome).await);
+    } else {
+        forget_progress(category, id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use crate::storage::Database;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_quiet_hours_suppression() {
+        let quiet = QuietHours {
+            enabled: true,
+            muted_categories: vec![JobKind::Export],
+            ..Default::default()
+        };
+        // 22:00-07:00: successes wait, errors break through
+        assert!(quiet.suppresses(JobKind::Download, EventSeverity::Success, 23));
+        assert!(quiet.suppresses(JobKind::LibrarySync, EventSeverity::Warning, 3));
+        assert!(!quiet.suppresses(JobKind::Download, EventSeverity::Error, 23));
+        assert!(!quiet.suppresses(JobKind::Download, EventSeverity::Success, 12));
+
+        // Muted categories are quiet all day, even when quiet hours are off
+        let off = QuietHours { enabled: false, ..quiet };
+        assert!(off.suppresses(JobKind::Export, EventSeverity::Error, 12));
+        assert!(!off.suppresses(JobKind::Download, EventSeverity::Success, 23));
+    }
+
+    #[tokio::test]
+    async fn test_work_event_for_outcome() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let night = TestClock::new(chrono::Utc.with_ymd_and_hms(2025, 3, 1, 23, 30, 0).unwrap());
+
+        // Default settings never suppress
+        let event = WorkEvent::for_outcome(pool, &night, JobKind::Download, "t1", Some("B001"), &Ok(())).await;
+        assert_eq!((event.status, event.severity, event.suppressed), (JobStatus::Completed, EventSeverity::Success, false));
+
+        set_quiet_hours(pool, &QuietHours { enabled: true, ..Default::default() }).await.unwrap();
+        let event = WorkEvent::for_outcome(pool, &night, JobKind::Download, "t1", Some("B001"), &Ok(())).await;
+        assert!(event.suppressed);
+
+        let failed: Result<()> = Err(LibationError::InvalidState("disk full".to_string()));
+        let event = WorkEvent::for_outcome(pool, &night, JobKind::LibrarySync, "sync-1", None, &failed).await;
+        assert_eq!(event.severity, EventSeverity::Error);
+        assert!(!event.suppressed);
+        assert!(event.error.unwrap().contains("disk full"));
+
+        let cancelled: Result<()> = Err(LibationError::Cancelled);
+        let event = WorkEvent::for_outcome(pool, &night, JobKind::LibrarySync, "sync-2", None, &cancelled).await;
+        assert_eq!((event.status, event.severity), (JobStatus::Cancelled, EventSeverity::Info));
+
+        let bad = QuietHours { start_hour: 24, ..Default::default() };
+        assert!(matches!(set_quiet_hours(pool, &bad).await, Err(LibationError::InvalidInput(_))));
+    }
+
+    fn work_event(id: &str, status: JobStatus) -> WorkEvent {
+        WorkEvent {
+            category: JobKind::Download,
+            id: id.to_string(),
+            asin: None,
+            status,
+            severity: EventSeverity::for_status(status),
+            error: None,
+            suppressed: false,
+            trace_id: None,
+            finished_at: "2025-03-01T23:30:00Z".to_string(),
+        }
+    }
+
+    fn progress(id: &str, bytes: u64) -> HostEvent {
+        HostEvent::Progress(ProgressEvent::new(JobKind::Download, id, None, bytes, 100))
+    }
+
+    #[test]
+    fn test_event_queue_coalesces_progress() {
+        let mut queue = EventQueue::new(3);
+
+        // Latest progress wins, in the job's original place
+        queue.push(progress("a", 10));
+        queue.push(progress("b", 10));
+        queue.push(progress("a", 20));
+        queue.push(progress("a", 30));
+        assert_eq!(queue.len(), 2);
+        match queue.pop() {
+            Some(HostEvent::Progress(p)) => assert_eq!((p.id.as_str(), p.bytes_processed, p.coalesced), ("a", 30, 2)),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // A terminal event replaces the job's queued progress
+        queue.push(progress("a", 40));
+        queue.push(HostEvent::Work(work_event("a", JobStatus::Completed)));
+        assert_eq!(queue.len(), 2);
+
+        // Full: the oldest progress goes, work events stay
+        queue.push(progress("c", 10));
+        queue.push(progress("d", 10));
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.len(), 3);
+        queue.push(HostEvent::Work(work_event("c", JobStatus::Failed)));
+        queue.push(HostEvent::Work(work_event("e", JobStatus::Cancelled)));
+        queue.push(HostEvent::Work(work_event("f", JobStatus::Completed)));
+        queue.push(progress("g", 10));
+        assert_eq!(queue.dropped(), 2);
+
+        let ids: Vec<_> = std::iter::from_fn(|| queue.pop())
+            .map(|e| match e {
+                HostEvent::Progress(p) => format!("progress {}", p.id),
+                HostEvent::Work(w) => format!("{:?} {}", w.status, w.id),
+                HostEvent::MediaScan(m) => format!("scan {}", m.asin),
+            })
+            .collect();
+        assert_eq!(ids, ["Completed a", "Failed c", "Cancelled e", "Completed f", "progress g"]);
+        assert!(queue.is_empty());
+
+        // Only work events queued: new progress is dropped
+        let mut queue = EventQueue::new(1);
+        queue.push(HostEvent::Work(work_event("a", JobStatus::Completed)));
+        queue.push(progress("b", 10));
+        assert_eq!((queue.len(), queue.dropped()), (1, 1));
+    }
+
+    #[test]
+    fn test_progress_eta_and_polling() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut event = ProgressEvent::for_items(JobKind::LibrarySync, "poll-1", ProgressStage::Fetching, 50, 100);
+        track_progress(&mut event, at(0));
+        assert_eq!(event.eta_secs, None);
+
+        // A new stage starts its own estimate: a quarter done in 10s, 30s to go
+        let mut event = ProgressEvent::for_items(JobKind::LibrarySync, "poll-1", ProgressStage::Importing, 0, 200);
+        track_progress(&mut event, at(5));
+        assert_eq!(event.eta_secs, None);
+        let mut event = ProgressEvent::for_items(JobKind::LibrarySync, "poll-1", ProgressStage::Importing, 50, 200);
+        track_progress(&mut event, at(15));
+        assert_eq!(event.eta_secs, Some(30));
+
+        let polled = current_progress().into_iter().find(|p| p.id == "poll-1").unwrap();
+        assert_eq!((polled.stage, polled.items_processed, polled.total_items), (ProgressStage::Importing, Some(50), Some(200)));
+        assert_eq!(polled.eta_secs, Some(30));
+
+        // Gone once the work ends
+        emit(&WorkEvent { category: JobKind::LibrarySync, ..work_event("poll-1", JobStatus::Completed) });
+        assert!(current_progress().iter().all(|p| p.id != "poll-1"));
+    }
+
+    #[tokio::test]
+    async fn test_progress_scope_reports_for_its_operation() {
+        report_items(ProgressStage::Importing, 1, 2);
+        with_progress_scope(JobKind::Export, "scope-1", async {
+            tokio::task::yield_now().await;
+            assert_eq!(current_operation(), Some((JobKind::Export, "scope-1".to_string())));
+            report_fraction(ProgressStage::Exporting, 0.5, Some(7));
+        })
+        .await;
+        assert_eq!(current_operation(), None);
+
+        let polled = current_progress().into_iter().find(|p| p.id == "scope-1").unwrap();
+        assert_eq!(
+            (polled.category, polled.stage, polled.fraction, polled.eta_secs),
+            (JobKind::Export, ProgressStage::Exporting, 0.5, Some(7))
+        );
+        forget_progress(JobKind::Export, "scope-1");
+    }
+
+    /// Blocks on its first event until released, recording the rest
+    struct SlowListener {
+        release: Mutex<std::sync::mpsc::Receiver<()>>,
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl WorkEventListener for SlowListener {
+        fn on_work_event(&self, event: &WorkEvent) {
+            if event.id.starts_with("slow-") {
+                self.seen.lock().unwrap().push(format!("done {}", event.id));
+            }
+        }
+
+        fn on_progress_event(&self, event: &ProgressEvent) {
+            if !event.id.starts_with("slow-") {
+                return;
+            }
+            if self.seen.lock().unwrap().is_empty() {
+                let _ = self.release.lock().unwrap().recv();
+            }
+            self.seen.lock().unwrap().push(format!("{} {}", event.id, event.bytes_processed));
+        }
+    }
+
+    #[test]
+    fn test_busy_listener_gets_latest_progress_and_terminal_event() {
+        let (release, wait) = std::sync::mpsc::channel();
+        let listener = Arc::new(SlowListener {
+            release: Mutex::new(wait),
+            seen: Mutex::new(Vec::new()),
+        });
+        set_work_event_listener(Some(listener.clone()));
+
+        emit_progress(ProgressEvent::new(JobKind::Download, "slow-1", None, 1, 100));
+        // Let the delivery thread pick up the first event and block
+        while !QUEUE.lock().unwrap().is_empty() {
+            std::thread::yield_now();
+        }
+        for bytes in 2..=500 {
+            emit_progress(ProgressEvent::new(JobKind::Download, "slow-2", None, bytes, 1000));
+        }
+        emit(&work_event("slow-1", JobStatus::Completed));
+        release.send(()).unwrap();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while listener.seen.lock().unwrap().len() < 3 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        set_work_event_listener(None);
+
+        let seen = listener.seen.lock().unwrap().clone();
+        assert_eq!(seen, ["slow-1 1", "slow-2 500", "done slow-1"]);
+    }
+}
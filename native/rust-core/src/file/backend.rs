@@ -0,0 +1,562 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Storage backends for liberated files
+//!
+//! A liberated book is written locally first, then handed to a
+//! `StorageBackend` (`store_file`), which puts it where the user keeps
+//! their library:
+//! - `LocalBackend` - a directory on the device
+//! - `WebDavBackend` - a WebDAV server (Nextcloud, most NAS systems)
+//! - SMB shares are written through the share mounted by the OS (Android
+//!   storage provider, iOS Files, a desktop mount) and use `LocalBackend`
+//!   on the mount path
+//!
+//! Targets are relative `/`-separated paths under the backend's root.
+//!
+//! # Capabilities
+//! Backends differ in what they can do, so `store_file` asks
+//! (`StorageBackend::capabilities`):
+//! - `resume` - an interrupted upload continues where it stopped
+//! - `rename` - the file is uploaded as `<target>.part` and renamed when
+//!   complete, so a partial file never shows up under its final name
+//!
+//! What a backend claims can be checked against the real destination with
+//! `probe_capabilities` (a mounted share may not support renames).
+//!
+//! The configured backend is stored in Settings (`storage.backend`, see
+//! `load_backend_config`).
+
+use crate::error::{LibationError, Result};
+use crate::file::temp::ScratchDir;
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderValue, CONTENT_LENGTH};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+/// Settings key of the configured backend
+pub const BACKEND_SETTING: &str = "storage.backend";
+
+/// Suffix of files still being uploaded on backends that can rename
+pub const PARTIAL_SUFFIX: &str = ".part";
+
+/// What a backend supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendCapabilities {
+    /// Interrupted uploads continue from the stored size
+    pub resume: bool,
+    /// Files can be renamed in place
+    pub rename: bool,
+}
+
+/// Destination of liberated files
+///
+/// # Errors
+/// Implementations report unreachable destinations as
+/// `LibationError::NetworkError` and refused requests as
+/// `UnexpectedStatusCode` or `PermissionDenied`.
+pub trait StorageBackend: Send + Sync + std::fmt::Debug {
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Where `target` ends up (path or URL), as stored in the library
+    fn location(&self, target: &str) -> String;
+
+    /// Size of a stored file, None if it doesn't exist
+    fn stored_size<'a>(&'a self, target: &'a str) -> BoxFuture<'a, Result<Option<u64>>>;
+
+    /// Write `source` to `target`, starting at byte `offset`
+    ///
+    /// `offset` 0 replaces the target; a larger offset appends the rest of
+    /// `source` to the `offset` bytes already stored and is only used when
+    /// the backend can resume.
+    fn write<'a>(&'a self, source: &'a Path, target: &'a str, offset: u64) -> BoxFuture<'a, Result<()>>;
+
+    /// Rename `from` to `to`, replacing `to`
+    fn rename<'a>(&'a self, from: &'a str, to: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Delete a stored file (missing files are not an error)
+    fn remove<'a>(&'a self, target: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Configured backend, as stored in Settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BackendConfig {
+    Local {
+        root: String,
+    },
+    WebDav {
+        /// Collection URL the library goes under
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    /// SMB share mounted by the OS
+    Smb {
+        mount_path: String,
+    },
+}
+
+impl BackendConfig {
+    /// Copy safe to show or log (password replaced)
+    pub fn redacted(&self) -> Self {
+        match self {
+            BackendConfig::WebDav { url, username, password } => BackendConfig::WebDav {
+                url: url.clone(),
+                username: username.clone(),
+                password: password.as_ref().map(|_| "********".to_string()),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Backend for this configuration
+    ///
+    /// # Errors
+    /// InvalidInput for an empty root or an invalid WebDAV URL
+    pub fn open(&self) -> Result<Arc<dyn StorageBackend>> {
+        match self {
+            BackendConfig::Local { root } | BackendConfig::Smb { mount_path: root } => {
+                if root.trim().is_empty() {
+                    return Err(LibationError::invalid_input("Storage root is empty"));
+                }
+                Ok(Arc::new(LocalBackend::new(PathBuf::from(root))))
+            }
+            BackendConfig::WebDav { url, username, password } => Ok(Arc::new(WebDavBackend::new(
+                url,
+                username.clone(),
+                password.clone(),
+            )?)),
+        }
+    }
+}
+
+/// The configured backend (None if liberated files stay where they are written)
+pub async fn load_backend_config(pool: &SqlitePool) -> Result<Option<BackendConfig>> {
+    crate::storage::settings::get_json_setting(pool, BACKEND_SETTING).await
+}
+
+/// Store the backend configuration (None removes it)
+pub async fn save_backend_config(pool: &SqlitePool, config: Option<&BackendConfig>) -> Result<()> {
+    match config {
+        Some(config) => crate::storage::settings::set_json_setting(pool, BACKEND_SETTING, config).await,
+        None => crate::storage::settings::delete_setting(pool, BACKEND_SETTING).await,
+    }
+}
+
+/// Upload a liberated file to `target` on `backend`
+///
+/// Resumes from the stored size on backends that can resume, and uploads
+/// to `<target>.part` and renames on backends that can rename. The upload
+/// is checked against the source size.
+///
+/// # Returns
+/// The stored location (`StorageBackend::location`)
+pub async fn store_file(backend: &dyn StorageBackend, source: &Path, target: &str) -> Result<String> {
+    let size = tokio::fs::metadata(source)
+        .await
+        .map_err(|_| LibationError::FileNotFound(source.display().to_string()))?
+        .len();
+    let capabilities = backend.capabilities();
+    let staging = if capabilities.rename {
+        format!("{}{}", target, PARTIAL_SUFFIX)
+    } else {
+        target.to_string()
+    };
+
+    let offset = if capabilities.resume {
+        match backend.stored_size(&staging).await? {
+            Some(stored) if stored <= size => stored,
+            _ => 0,
+        }
+    } else {
+        0
+    };
+    if offset < size || size == 0 {
+        backend.write(source, &staging, offset).await?;
+    }
+
+    let stored = backend.stored_size(&staging).await?.unwrap_or(0);
+    if stored != size {
+        return Err(LibationError::FileSizeMismatch { expected: size, actual: stored });
+    }
+
+    if capabilities.rename {
+        backend.rename(&staging, target).await?;
+    }
+    Ok(backend.location(target))
+}
+
+/// Check what `backend` can actually do at its destination
+///
+/// Writes, appends to and renames a small probe file, then removes it. A
+/// capability is only reported if the backend claims it and it worked.
+///
+/// # Errors
+/// If the probe file can't be written at all
+pub async fn probe_capabilities(backend: &dyn StorageBackend) -> Result<BackendCapabilities> {
+    let claimed = backend.capabilities();
+    let probe_dir = ScratchDir::new()?;
+    let probe = probe_dir.file("probe")?;
+    let name = format!(".librisync-probe-{}", uuid::Uuid::new_v4());
+    let renamed = format!("{}{}", name, PARTIAL_SUFFIX);
+
+    let result = async {
+        tokio::fs::write(&probe, b"probe").await?;
+        backend.write(&probe, &name, 0).await?;
+
+        let resume = claimed.resume && {
+            tokio::fs::write(&probe, b"probe-resumed").await?;
+            backend.write(&probe, &name, 5).await.is_ok()
+                && backend.stored_size(&name).await.ok().flatten() == Some(13)
+        };
+        let rename = claimed.rename
+            && backend.rename(&name, &renamed).await.is_ok()
+            && backend.stored_size(&renamed).await.ok().flatten().is_some();
+
+        Ok(BackendCapabilities { resume, rename })
+    }
+    .await;
+
+    let _ = backend.remove(&name).await;
+    let _ = backend.remove(&renamed).await;
+    result
+}
+
+/// Split a target into its path segments
+///
+/// # Errors
+/// InvalidPath for empty targets and `..`, absolute or prefixed components
+fn target_segments(target: &str) -> Result<Vec<&str>> {
+    let segments: Vec<&str> = target.split('/').filter(|s| !s.is_empty() && *s != ".").collect();
+    let escapes = Path::new(target)
+        .components()
+        .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)));
+    if segments.is_empty() || escapes || segments.contains(&"..") {
+        return Err(LibationError::InvalidPath(format!("Invalid storage target: {}", target)));
+    }
+    Ok(segments)
+}
+
+// ============================================================================
+// LOCAL / MOUNTED
+// ============================================================================
+
+/// Directory on the device or a mounted share
+#[derive(Debug, Clone)]
+pub struct LocalBackend {
+    root: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, target: &str) -> Result<PathBuf> {
+        Ok(target_segments(target)?.iter().fold(self.root.clone(), |path, s| path.join(s)))
+    }
+}
+
+impl StorageBackend for LocalBackend {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities { resume: true, rename: true }
+    }
+
+    fn location(&self, target: &str) -> String {
+        self.path(target)
+            .unwrap_or_else(|_| self.root.join(target))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn stored_size<'a>(&'a self, target: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        Box::pin(async move {
+            match tokio::fs::metadata(self.path(target)?).await {
+                Ok(metadata) => Ok(Some(metadata.len())),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn write<'a>(&'a self, source: &'a Path, target: &'a str, offset: u64) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let path = self.path(target)?;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+
+            let mut input = tokio::fs::File::open(source).await?;
+            let mut output = tokio::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(offset == 0)
+                .open(&path)
+                .await?;
+            if offset > 0 {
+                input.seek(std::io::SeekFrom::Start(offset)).await?;
+                output.set_len(offset).await?;
+                output.seek(std::io::SeekFrom::Start(offset)).await?;
+            }
+
+            tokio::io::copy(&mut input, &mut output).await?;
+            output.flush().await?;
+            output.sync_all().await?;
+            Ok(())
+        })
+    }
+
+    fn rename<'a>(&'a self, from: &'a str, to: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let to = self.path(to)?;
+            if let Some(parent) = to.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::rename(self.path(from)?, to).await?;
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(&'a self, target: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(target)?).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+// ============================================================================
+// WEBDAV
+// ============================================================================
+
+/// WebDAV server
+///
+/// Uploads are streamed with PUT, parent collections are created with
+/// MKCOL and renames use MOVE. WebDAV has no standard partial PUT, so
+/// uploads can't resume.
+#[derive(Debug, Clone)]
+pub struct WebDavBackend {
+    base_url: url::Url,
+    username: Option<String>,
+    password: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WebDavBackend {
+    /// # Errors
+    /// InvalidInput if `url` isn't an http(s) URL
+    pub fn new(url: &str, username: Option<String>, password: Option<String>) -> Result<Self> {
+        let mut base_url = url::Url::parse(url.trim())
+            .map_err(|e| LibationError::invalid_input(format!("Invalid WebDAV URL {}: {}", url, e)))?;
+        if !matches!(base_url.scheme(), "http" | "https") {
+            return Err(LibationError::invalid_input(format!("WebDAV URL must be http(s): {}", url)));
+        }
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(30))
+            .build()?;
+        Ok(Self { base_url, username, password, client })
+    }
+
+    fn url(&self, target: &str) -> Result<url::Url> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| LibationError::invalid_input("WebDAV URL can't have a path"))?
+            .pop_if_empty()
+            .extend(target_segments(target)?);
+        Ok(url)
+    }
+
+    fn request(&self, method: Method, url: url::Url) -> reqwest::RequestBuilder {
+        let builder = self.client.request(method, url);
+        match &self.username {
+            Some(username) => builder.basic_auth(username, self.password.as_ref()),
+            None => builder,
+        }
+    }
+
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        builder.send().await.map_err(|e| {
+            LibationError::network_error(
+                format!("WebDAV request failed: {}", e),
+                e.is_timeout() || e.is_connect(),
+            )
+        })
+    }
+
+    fn check(&self, response: &reqwest::Response) -> Result<()> {
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(LibationError::PermissionDenied(format!(
+                "WebDAV server refused {}",
+                response.url()
+            ))),
+            status => Err(LibationError::UnexpectedStatusCode {
+                status_code: status.as_u16(),
+                host: self.base_url.host_str().unwrap_or_default().to_string(),
+            }),
+        }
+    }
+
+    /// Create the collections above `target` (existing ones are fine)
+    async fn create_parents(&self, target: &str) -> Result<()> {
+        let segments = target_segments(target)?;
+        let mut url = self.base_url.clone();
+        for segment in &segments[..segments.len() - 1] {
+            url.path_segments_mut()
+                .map_err(|_| LibationError::invalid_input("WebDAV URL can't have a path"))?
+                .pop_if_empty()
+                .push(segment)
+                .push("");
+            let mkcol = Method::from_bytes(b"MKCOL").expect("valid method");
+            let response = self.send(self.request(mkcol, url.clone())).await?;
+            // 405: the collection already exists
+            if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+                self.check(&response)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for WebDavBackend {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities { resume: false, rename: true }
+    }
+
+    fn location(&self, target: &str) -> String {
+        self.url(target)
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| format!("{}{}", self.base_url, target))
+    }
+
+    fn stored_size<'a>(&'a self, target: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        Box::pin(async move {
+            let response = self.send(self.request(Method::HEAD, self.url(target)?)).await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            self.check(&response)?;
+            Ok(response
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok()))
+        })
+    }
+
+    fn write<'a>(&'a self, source: &'a Path, target: &'a str, offset: u64) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if offset > 0 {
+                return Err(LibationError::UnsupportedOperation {
+                    operation: "resume upload".to_string(),
+                    reason: "WebDAV can't append to a stored file".to_string(),
+                });
+            }
+            self.create_parents(target).await?;
+
+            let file = tokio::fs::File::open(source).await?;
+            let size = file.metadata().await?.len();
+            let builder = self
+                .request(Method::PUT, self.url(target)?)
+                .header(CONTENT_LENGTH, HeaderValue::from(size))
+                .body(reqwest::Body::from(file));
+            let response = self.send(builder).await?;
+            self.check(&response)
+        })
+    }
+
+    fn rename<'a>(&'a self, from: &'a str, to: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.create_parents(to).await?;
+            let destination = self.url(to)?.to_string();
+            let builder = self
+                .request(Method::from_bytes(b"MOVE").expect("valid method"), self.url(from)?)
+                .header("Destination", destination)
+                .header("Overwrite", "T");
+            let response = self.send(builder).await?;
+            self.check(&response)
+        })
+    }
+
+    fn remove<'a>(&'a self, target: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let response = self.send(self.request(Method::DELETE, self.url(target)?)).await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(());
+            }
+            self.check(&response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_store_file_on_local_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book.m4b");
+        tokio::fs::write(&source, b"0123456789").await.unwrap();
+        let backend = LocalBackend::new(dir.path().join("nas"));
+
+        // An interrupted upload resumes from the partial file
+        tokio::fs::create_dir_all(dir.path().join("nas/Author")).await.unwrap();
+        tokio::fs::write(dir.path().join("nas/Author/Book.m4b.part"), b"0123").await.unwrap();
+        let location = store_file(&backend, &source, "Author/Book.m4b").await.unwrap();
+        assert_eq!(PathBuf::from(&location), dir.path().join("nas/Author/Book.m4b"));
+        assert_eq!(tokio::fs::read(&location).await.unwrap(), b"0123456789");
+        assert!(!dir.path().join("nas/Author/Book.m4b.part").exists());
+
+        assert!(matches!(
+            store_file(&backend, &source, "../escape.m4b").await,
+            Err(LibationError::InvalidPath(_))
+        ));
+        assert_eq!(
+            probe_capabilities(&backend).await.unwrap(),
+            BackendCapabilities { resume: true, rename: true }
+        );
+
+        // Configuration round-trips through Settings
+        let db = Database::new_in_memory().await.unwrap();
+        let config = BackendConfig::WebDav {
+            url: "https://nas.local/dav/Audiobooks".to_string(),
+            username: Some("me".to_string()),
+            password: Some("secret".to_string()),
+        };
+        save_backend_config(db.pool(), Some(&config)).await.unwrap();
+        assert_eq!(load_backend_config(db.pool()).await.unwrap(), Some(config.clone()));
+        assert!(!serde_json::to_string(&config.redacted()).unwrap().contains("secret"));
+
+        let webdav = config.open().unwrap();
+        assert!(!webdav.capabilities().resume);
+        assert_eq!(webdav.location("A B/Book.m4b"), "https://nas.local/dav/Audiobooks/A%20B/Book.m4b");
+    }
+}
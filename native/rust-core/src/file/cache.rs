@@ -0,0 +1,349 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cache quotas
+//!
+//! Covers, audio samples and per-job temp directories live under the app's
+//! cache directory (`temp::cache_root`), each with a size limit the user
+//! sets (`CacheQuota`, stored in Settings):
+//!
+//! - Covers and samples are written with `put_cached_file`, which
+//!   evicts the least recently used files once the cache is over its
+//!   quota. `cached_file` marks a file as used.
+//! - Temp directories (`temp::ScratchDir`) are removed by their jobs;
+//!   enforcing the temp quota only removes entries no running job holds.
+//!
+//! All quotas are enforced once per process at startup (in the background,
+//! see `Database::run_startup_tasks`) and when they change. The file work
+//! runs on a blocking thread.
+//! `get_cache_usage` reports each cache's size against its quota.
+
+use crate::error::{LibationError, Result};
+use crate::file::temp::{cache_root, is_live, safe_file_name, scratch_root};
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const KEY_QUOTA: &str = "storage.cache_quota";
+
+const MB: u64 = 1024 * 1024;
+
+/// Subdirectories of the cache root; prefixed, since without an app cache
+/// directory the root is the shared system temp directory
+const COVERS_SUBDIR: &str = "librisync-covers";
+const SAMPLES_SUBDIR: &str = "librisync-samples";
+
+/// A cache with its own quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheKind {
+    Covers,
+    Samples,
+    Temp,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 3] = [CacheKind::Covers, CacheKind::Samples, CacheKind::Temp];
+}
+
+/// Size limits of the caches, in MB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CacheQuota {
+    pub covers_max_mb: u64,
+    pub samples_max_mb: u64,
+    pub temp_max_mb: u64,
+}
+
+impl Default for CacheQuota {
+    fn default() -> Self {
+        Self {
+            covers_max_mb: 200,
+            samples_max_mb: 100,
+            temp_max_mb: 2048,
+        }
+    }
+}
+
+impl CacheQuota {
+    /// Limit of a cache in bytes
+    pub fn max_bytes(&self, kind: CacheKind) -> u64 {
+        let mb = match kind {
+            CacheKind::Covers => self.covers_max_mb,
+            CacheKind::Samples => self.samples_max_mb,
+            CacheKind::Temp => self.temp_max_mb,
+        };
+        mb.saturating_mul(MB)
+    }
+}
+
+/// Size of one cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CacheSize {
+    pub kind: CacheKind,
+    pub path: String,
+    pub bytes: u64,
+    pub files: usize,
+    pub max_bytes: u64,
+}
+
+/// Size of all caches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CacheUsage {
+    pub caches: Vec<CacheSize>,
+    pub total_bytes: u64,
+}
+
+/// What enforcing a quota removed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEviction {
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
+
+/// Directory of a cache
+pub fn cache_dir(kind: CacheKind) -> PathBuf {
+    match kind {
+        CacheKind::Covers => cache_root().join(COVERS_SUBDIR),
+        CacheKind::Samples => cache_root().join(SAMPLES_SUBDIR),
+        CacheKind::Temp => scratch_root(),
+    }
+}
+
+/// Stored cache quota (defaults if never set)
+pub async fn get_cache_quota(pool: &SqlitePool) -> Result<CacheQuota> {
+    Ok(get_json_setting(pool, KEY_QUOTA).await?.unwrap_or_default())
+}
+
+/// Store the cache quota and enforce it
+///
+/// # Errors
+/// InvalidInput if a limit is 0
+pub async fn set_cache_quota(pool: &SqlitePool, quota: &CacheQuota) -> Result<Vec<CacheEviction>> {
+    if CacheKind::ALL.iter().any(|&kind| quota.max_bytes(kind) == 0) {
+        return Err(LibationError::invalid_input("Cache limits must be at least 1 MB"));
+    }
+    set_json_setting(pool, KEY_QUOTA, quota).await?;
+    enforce_cache_quotas(pool).await
+}
+
+/// Store `bytes` in a cache as `name` and evict older files over the quota
+///
+/// The name is sanitized like temp file names.
+///
+/// # Errors
+/// InvalidInput for the temp cache, which is written through `ScratchDir`
+pub async fn put_cached_file(pool: &SqlitePool, kind: CacheKind, name: &str, bytes: &[u8]) -> Result<PathBuf> {
+    if kind == CacheKind::Temp {
+        return Err(LibationError::invalid_input("Temp files are written through a scratch directory"));
+    }
+    let max_bytes = get_cache_quota(pool).await?.max_bytes(kind);
+    let (dir, name, bytes) = (cache_dir(kind), name.to_string(), bytes.to_vec());
+    blocking(move || put_in(&dir, &name, &bytes, max_bytes)).await
+}
+
+fn put_in(dir: &Path, name: &str, bytes: &[u8], max_bytes: u64) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let name = safe_file_name(name);
+    let path = dir.join(&name);
+    std::fs::write(&path, bytes)?;
+    evict_in(dir, max_bytes, |entry| entry == name)?;
+    Ok(path)
+}
+
+/// Path of a cached file, if present, marking it as recently used
+pub fn cached_file(kind: CacheKind, name: &str) -> Option<PathBuf> {
+    let path = cache_dir(kind).join(safe_file_name(name));
+    touch(&path).ok()?;
+    Some(path)
+}
+
+fn touch(path: &Path) -> std::io::Result<()> {
+    std::fs::File::options().write(true).open(path)?.set_modified(SystemTime::now())
+}
+
+/// Evict from a cache until it is within its quota
+///
+/// Least recently used entries go first; temp directories held by this
+/// process are kept.
+pub async fn enforce_cache_quota(pool: &SqlitePool, kind: CacheKind) -> Result<CacheEviction> {
+    let max_bytes = get_cache_quota(pool).await?.max_bytes(kind);
+    let dir = cache_dir(kind);
+    blocking(move || match kind {
+        CacheKind::Temp => evict_in(&dir, max_bytes, is_live),
+        _ => evict_in(&dir, max_bytes, |_| false),
+    })
+    .await
+}
+
+/// `enforce_cache_quota` for every cache, in `CacheKind::ALL` order
+pub async fn enforce_cache_quotas(pool: &SqlitePool) -> Result<Vec<CacheEviction>> {
+    let mut evictions = Vec::new();
+    for kind in CacheKind::ALL {
+        evictions.push(enforce_cache_quota(pool, kind).await?);
+    }
+    Ok(evictions)
+}
+
+/// Run cache file work on a blocking thread
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| LibationError::InternalError(format!("Cache task failed: {}", e)))?
+}
+
+/// Size of each cache against its quota
+pub async fn get_cache_usage(pool: &SqlitePool) -> Result<CacheUsage> {
+    let quota = get_cache_quota(pool).await?;
+    let mut caches = Vec::new();
+    for kind in CacheKind::ALL {
+        let dir = cache_dir(kind);
+        let (bytes, files) = dir_size(&dir)?;
+        caches.push(CacheSize {
+            kind,
+            path: dir.to_string_lossy().to_string(),
+            bytes,
+            files,
+            max_bytes: quota.max_bytes(kind),
+        });
+    }
+    let total_bytes = caches.iter().map(|c| c.bytes).sum();
+    Ok(CacheUsage { caches, total_bytes })
+}
+
+/// Bytes and files under `path` (0 if missing)
+fn dir_size(path: &Path) -> Result<(u64, usize)> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e.into()),
+    };
+    if !metadata.is_dir() {
+        return Ok((metadata.len(), 1));
+    }
+    let mut total = (0, 0);
+    for entry in std::fs::read_dir(path)? {
+        let (bytes, files) = dir_size(&entry?.path())?;
+        total = (total.0 + bytes, total.1 + files);
+    }
+    Ok(total)
+}
+
+/// Remove the oldest entries of `dir` until it holds at most `max_bytes`,
+/// never removing entries `keep` matches
+fn evict_in(dir: &Path, max_bytes: u64, keep: impl Fn(&str) -> bool) -> Result<CacheEviction> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(CacheEviction::default()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut sized = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        // Gone since the listing (removed by its job)
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let (bytes, _) = dir_size(&entry.path())?;
+        sized.push((modified, entry.file_name().to_string_lossy().to_string(), entry.path(), bytes));
+    }
+    let mut total: u64 = sized.iter().map(|(_, _, _, bytes)| bytes).sum();
+    sized.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut eviction = CacheEviction::default();
+    for (_, name, path, bytes) in sized {
+        if total <= max_bytes {
+            break;
+        }
+        if keep(&name) {
+            continue;
+        }
+        let removed = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        if removed.is_ok() {
+            total -= bytes;
+            eviction.removed += 1;
+            eviction.freed_bytes += bytes;
+        }
+    }
+    Ok(eviction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use std::time::Duration;
+
+    fn age(path: &Path, seconds: u64) {
+        let time = SystemTime::now() - Duration::from_secs(seconds);
+        std::fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let covers = dir.path().join("covers");
+
+        let a = put_in(&covers, "B0000000A.jpg", &[0; 400], 1000).unwrap();
+        let b = put_in(&covers, "B0000000B.jpg", &[0; 400], 1000).unwrap();
+        age(&a, 60);
+        age(&b, 30);
+        // `a` was used since, so `b` is the least recently used
+        touch(&a).unwrap();
+
+        let c = put_in(&covers, "B0000000C.jpg", &[0; 400], 1000).unwrap();
+        assert!(a.exists() && !b.exists() && c.exists());
+        assert_eq!(dir_size(&covers).unwrap(), (800, 2));
+
+        // A file over the whole quota is still kept
+        let big = put_in(&covers, "B0000000D.jpg", &[0; 1500], 1000).unwrap();
+        assert!(big.exists() && !a.exists() && !c.exists());
+
+        // Kept entries are skipped
+        let eviction = evict_in(&covers, 0, |name| name == "B0000000D.jpg").unwrap();
+        assert_eq!(eviction, CacheEviction::default());
+        let eviction = evict_in(&covers, 0, |_| false).unwrap();
+        assert_eq!((eviction.removed, eviction.freed_bytes), (1, 1500));
+        assert_eq!(evict_in(&dir.path().join("missing"), 0, |_| false).unwrap(), CacheEviction::default());
+    }
+
+    #[tokio::test]
+    async fn test_cache_quota() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        assert_eq!(get_cache_quota(pool).await.unwrap(), CacheQuota::default());
+        let quota = CacheQuota { covers_max_mb: 50, ..Default::default() };
+        set_cache_quota(pool, &quota).await.unwrap();
+        assert_eq!(get_cache_quota(pool).await.unwrap().max_bytes(CacheKind::Covers), 50 * MB);
+        assert!(set_cache_quota(pool, &CacheQuota { temp_max_mb: 0, ..quota }).await.is_err());
+        assert!(put_cached_file(pool, CacheKind::Temp, "x", b"x").await.is_err());
+
+        let usage = get_cache_usage(pool).await.unwrap();
+        let kinds: Vec<CacheKind> = usage.caches.iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, CacheKind::ALL);
+        assert_eq!(usage.caches[0].max_bytes, 50 * MB);
+    }
+}
@@ -0,0 +1,220 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Local cover art
+//!
+//! Covers are downloaded from `picture_large` (else from `picture_id`)
+//! into the cover cache (`cache::CacheKind::Covers`), which sits under the
+//! app's cache directory and is kept within its quota by evicting the
+//! least recently used covers. The local path is recorded in
+//! `Books.cover_path` so the UI can show covers offline.
+//!
+//! Cached files are named after the ASIN and the image id, so a book
+//! whose cover changes (see `api::covers`) gets the new one instead of the
+//! stale file. An evicted cover is downloaded again the next time it is
+//! asked for.
+
+use crate::api::covers::{cover_url, image_id, DEFAULT_IMAGE_HOST};
+use crate::error::{LibationError, Result};
+use crate::file::cache::{cached_file, put_cached_file, CacheKind};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Size covers are downloaded at when only an image id is known
+const COVER_SIZE: u32 = 500;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// URL a book's cover is downloaded from
+fn source_url(picture_id: Option<&str>, picture_large: Option<&str>) -> Option<String> {
+    let is_url = |picture: &&str| picture.starts_with("http://") || picture.starts_with("https://");
+    if let Some(url) = picture_large.filter(is_url).or_else(|| picture_id.filter(is_url)) {
+        return Some(url.to_string());
+    }
+    let id = picture_id.and_then(image_id)?;
+    Some(cover_url(DEFAULT_IMAGE_HOST, id, COVER_SIZE))
+}
+
+/// Cache file name of a cover (`B0ABC-51abcXYZ.jpg`)
+fn cover_file_name(asin: &str, url: &str) -> String {
+    format!("{}-{}.jpg", asin, image_id(url).unwrap_or("cover"))
+}
+
+/// Local path of a book's cover
+///
+/// Returns the cached cover, downloading it first when `download` is set
+/// and it isn't cached. `Books.cover_path` is updated to match, and
+/// cleared when the cover is gone.
+///
+/// # Returns
+/// None when the book has no cover, or it isn't cached and `download` is
+/// off
+///
+/// # Errors
+/// NotFound for an unknown ASIN; NetworkError when the download fails
+pub async fn get_cover_path(pool: &SqlitePool, asin: &str, download: bool) -> Result<Option<PathBuf>> {
+    let row: Option<(Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT picture_id, picture_large, cover_path FROM Books WHERE audible_product_id = ?",
+    )
+    .bind(asin)
+    .fetch_optional(pool)
+    .await?;
+    let (picture_id, picture_large, stored) =
+        row.ok_or_else(|| LibationError::not_found(format!("Book {} not found", asin)))?;
+
+    let path = match source_url(picture_id.as_deref(), picture_large.as_deref()) {
+        Some(url) => {
+            let name = cover_file_name(asin, &url);
+            match cached_file(CacheKind::Covers, &name) {
+                Some(path) => Some(path),
+                None if download => {
+                    let bytes = download_cover(&url).await?;
+                    Some(put_cached_file(pool, CacheKind::Covers, &name, &bytes).await?)
+                }
+                None => None,
+            }
+        }
+        None => None,
+    };
+
+    let recorded = path.as_ref().map(|p| p.to_string_lossy().to_string());
+    if recorded != stored {
+        sqlx::query("UPDATE Books SET cover_path = ? WHERE audible_product_id = ?")
+            .bind(&recorded)
+            .bind(asin)
+            .execute(pool)
+            .await?;
+    }
+    Ok(path)
+}
+
+/// Fetch a cover image
+async fn download_cover(url: &str) -> Result<Vec<u8>> {
+    let http = reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| LibationError::network_error(format!("Failed to create HTTP client: {}", e), false))?;
+    let response = http
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| LibationError::network_error(format!("Failed to download cover art: {}", e), true))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(LibationError::network_error(
+            format!("HTTP {} when downloading cover art", status),
+            status.is_server_error(),
+        ));
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| LibationError::network_error(format!("Failed to read cover art bytes: {}", e), true))?;
+    if bytes.is_empty() {
+        return Err(LibationError::network_error("Empty cover art response", true));
+    }
+    Ok(bytes.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve a small body for paths containing "good", 404 otherwise
+    async fn serve_covers() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 2048];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let line = String::from_utf8_lossy(&buf[..n]).lines().next().unwrap_or_default().to_string();
+                    let response = if line.contains("good") {
+                        "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: 4\r\nConnection: close\r\n\r\njpeg"
+                    } else {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    };
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://127.0.0.1:{}/images/I/", port)
+    }
+
+    async fn stored_path(db: &Database, asin: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT cover_path FROM Books WHERE audible_product_id = ?")
+            .bind(asin)
+            .fetch_one(db.pool())
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_source_url() {
+        assert_eq!(
+            source_url(Some("51abc"), Some("https://x/images/I/51abc._SL500_.jpg")).as_deref(),
+            Some("https://x/images/I/51abc._SL500_.jpg")
+        );
+        assert_eq!(
+            source_url(Some("51abc"), None).as_deref(),
+            Some("https://m.media-amazon.com/images/I/51abc._SL500_.jpg")
+        );
+        assert_eq!(source_url(None, None), None);
+        assert_eq!(cover_file_name("B0ABC", "https://x/images/I/51abc._SL500_.jpg"), "B0ABC-51abc.jpg");
+    }
+
+    #[tokio::test]
+    async fn test_get_cover_path() {
+        let db = Database::new_in_memory().await.unwrap();
+        let host = serve_covers().await;
+        let books = [
+            ("B0COVERGOOD", Some(format!("{}good1._SL500_.jpg", host))),
+            ("B0COVERGONE", Some(format!("{}missing1._SL500_.jpg", host))),
+            ("B0COVERNONE", None),
+        ];
+        for (asin, picture_large) in books {
+            let mut book = crate::storage::NewBook::new(asin.to_string(), asin.to_string(), "us".to_string());
+            book.picture_large = picture_large;
+            crate::storage::queries::insert_book(db.pool(), &book).await.unwrap();
+        }
+
+        // Not cached yet, and not downloaded
+        assert_eq!(get_cover_path(db.pool(), "B0COVERGOOD", false).await.unwrap(), None);
+
+        let path = get_cover_path(db.pool(), "B0COVERGOOD", true).await.unwrap().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"jpeg");
+        assert!(path.ends_with("B0COVERGOOD-good1.jpg"));
+        assert_eq!(stored_path(&db, "B0COVERGOOD").await, Some(path.to_string_lossy().to_string()));
+
+        // Cached covers are served offline
+        assert_eq!(get_cover_path(db.pool(), "B0COVERGOOD", false).await.unwrap(), Some(path.clone()));
+
+        // An evicted cover clears the recorded path
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(get_cover_path(db.pool(), "B0COVERGOOD", false).await.unwrap(), None);
+        assert_eq!(stored_path(&db, "B0COVERGOOD").await, None);
+
+        assert!(get_cover_path(db.pool(), "B0COVERGONE", true).await.is_err());
+        assert_eq!(get_cover_path(db.pool(), "B0COVERNONE", true).await.unwrap(), None);
+        assert!(get_cover_path(db.pool(), "B0UNKNOWN", true).await.is_err());
+    }
+}
@@ -0,0 +1,793 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Companion-device handoff over the local network
+//!
+//! Pushes a liberated book from one device running the app to another
+//! (phone to tablet) without downloading it from Audible again. Only built
+//! with the `lan-handoff` feature.
+//!
+//! The receiving device binds a `HandoffReceiver`, shows its pairing code
+//! and advertises itself over mDNS (`SERVICE_TYPE`). The sending device
+//! finds it with `discover_peers`, the user types the code, and
+//! `send_book` streams the file. On Android the host must hold a
+//! `WifiManager.MulticastLock` while advertising or browsing.
+//!
+//! # Protocol
+//! Messages are JSON frames prefixed with their length (u32, big endian);
+//! the audio follows the offer as raw bytes:
+//!
+//! ```text
+//! receiver                         sender
+//!    hello {version, nonce}   ->
+//!                             <-   auth {nonce, proof}
+//!    accepted {proof}         ->
+//!                             <-   offer {asin, title, size, sha256, ...}
+//!    ready                    ->
+//!                             <-   <size bytes of audio>
+//!                             <-   done {mac}
+//!    received {output_path}   ->
+//! ```
+//!
+//! Both sides prove they know the pairing code with HMAC-SHA256 over both
+//! nonces, and `done` carries an HMAC (keyed from the code and nonces) over
+//! the offer frame and the file's SHA-256, so the metadata and audio are
+//! checked end to end. Either side may answer `rejected {reason}` instead.
+//! A receiver accepts a single connection, so a wrong code ends the
+//! session and a new code has to be shown. The audio is not encrypted in
+//! transit; it is meant for a home network.
+//!
+//! The receiver only takes books already in its library (both devices sync
+//! the same account) that don't have a file yet. The file is named with
+//! the receiver's naming pattern, recorded as the book's download and gets
+//! a liberation receipt with source `handoff`.
+
+use crate::cancel::CancellationToken;
+use crate::error::{LibationError, Result};
+use crate::file::integrity::sha256_file;
+use crate::file::paths::{build_unique_file_path, filesystem_info, CollisionStrategy, NamingPattern};
+use crate::storage::queries::{find_book_with_relations_by_asin, get_book_file_path};
+use crate::storage::receipts::{
+    record_liberation, DecryptMethod, InputSource, LiberationReceipt, NewReceipt, ReceiptFile, Toolchain,
+};
+use hmac::{Hmac, Mac};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// mDNS service type receivers advertise
+pub const SERVICE_TYPE: &str = "_librisync._tcp.local.";
+
+/// Handoff protocol version; both sides must match
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Largest JSON frame accepted
+const MAX_FRAME_LEN: u32 = 64 * 1024;
+
+/// Audio is streamed in chunks of this size
+const CHUNK_LEN: usize = 256 * 1024;
+
+/// How long to wait for the other side to connect, answer or send data
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Callback for transfer progress
+pub type HandoffProgressCallback = Arc<dyn Fn(HandoffProgress) + Send + Sync>;
+
+/// Bytes transferred so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandoffProgress {
+    pub bytes_transferred: u64,
+    pub total_bytes: u64,
+}
+
+/// Book offered by the sender
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandoffOffer {
+    pub asin: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    /// Extension of the audio file ("m4b", "mp3", ...)
+    pub extension: String,
+    pub size_bytes: u64,
+    /// Hex SHA-256 of the audio
+    pub sha256: String,
+}
+
+/// A receiver found on the network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandoffPeer {
+    /// Name the receiver shows for itself
+    pub device_name: String,
+    /// mDNS instance name (unique per receiver session)
+    pub instance: String,
+    pub addresses: Vec<String>,
+    pub port: u16,
+}
+
+/// Book stored by a receiver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceivedBook {
+    /// Device name of the sender
+    pub sender: String,
+    pub receipt: LiberationReceipt,
+}
+
+/// Book delivered by `send_book`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentBook {
+    pub asin: String,
+    /// Device name of the receiver
+    pub receiver: String,
+    /// Where the receiver stored the file
+    pub output_path: String,
+    pub size_bytes: u64,
+    pub transfer_ms: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Hello { version: u32, device_name: String, nonce: String },
+    Auth { device_name: String, nonce: String, proof: String },
+    Accepted { proof: String },
+    Offer(HandoffOffer),
+    Ready,
+    Done { mac: String },
+    Received { output_path: String },
+    Rejected { reason: String },
+}
+
+/// Random six-digit pairing code
+pub fn generate_pairing_code() -> String {
+    format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
+
+/// Advertises a receiver over mDNS until dropped
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Advertisement {
+    /// Advertise `device_name` on `port` on all interfaces
+    pub fn start(device_name: &str, port: u16) -> Result<Self> {
+        let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+        let instance = format!("librisync-{}", &uuid::Uuid::new_v4().simple().to_string()[..12]);
+        let version = PROTOCOL_VERSION.to_string();
+        let properties = [("name", device_name), ("v", version.as_str())];
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance,
+            &format!("{}.local.", instance),
+            (),
+            port,
+            &properties[..],
+        )
+        .map_err(mdns_error)?
+        .enable_addr_auto();
+        let fullname = info.get_fullname().to_string();
+        daemon.register(info).map_err(mdns_error)?;
+        Ok(Self { daemon, fullname })
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Receivers that answer within `timeout`
+///
+/// Receivers speaking another protocol version are left out.
+pub async fn discover_peers(timeout: Duration) -> Result<Vec<HandoffPeer>> {
+    let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+    let events = daemon.browse(SERVICE_TYPE).map_err(mdns_error)?;
+    let deadline = tokio::time::Instant::now() + timeout;
+    let version = PROTOCOL_VERSION.to_string();
+
+    let mut peers: Vec<HandoffPeer> = Vec::new();
+    while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, events.recv_async()).await {
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+        if info.get_property_val_str("v") != Some(version.as_str()) {
+            continue;
+        }
+        let instance = info
+            .get_fullname()
+            .strip_suffix(&format!(".{}", SERVICE_TYPE))
+            .unwrap_or(info.get_fullname())
+            .to_string();
+        let mut addresses: Vec<String> = info.get_addresses().iter().map(|a| a.to_string()).collect();
+        addresses.sort();
+        let peer = HandoffPeer {
+            device_name: info.get_property_val_str("name").unwrap_or(&instance).to_string(),
+            instance,
+            addresses,
+            port: info.get_port(),
+        };
+        match peers.iter_mut().find(|p| p.instance == peer.instance) {
+            Some(existing) => *existing = peer,
+            None => peers.push(peer),
+        }
+    }
+
+    let _ = daemon.shutdown();
+    Ok(peers)
+}
+
+/// Waits for one book from another device
+pub struct HandoffReceiver {
+    listener: TcpListener,
+    device_name: String,
+    pairing_code: String,
+    naming_pattern: NamingPattern,
+    collision_strategy: CollisionStrategy,
+    on_progress: Option<HandoffProgressCallback>,
+    cancel: CancellationToken,
+}
+
+impl HandoffReceiver {
+    /// Listen on `addr` (port 0 picks a free port) with a new pairing code
+    pub async fn bind(addr: SocketAddr, device_name: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            device_name: device_name.into(),
+            pairing_code: generate_pairing_code(),
+            naming_pattern: NamingPattern::AuthorSeriesBook,
+            collision_strategy: CollisionStrategy::default(),
+            on_progress: None,
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    pub fn with_naming_pattern(mut self, pattern: NamingPattern) -> Self {
+        self.naming_pattern = pattern;
+        self
+    }
+
+    pub fn with_collision_strategy(mut self, strategy: CollisionStrategy) -> Self {
+        self.collision_strategy = strategy;
+        self
+    }
+
+    pub fn with_progress(mut self, on_progress: HandoffProgressCallback) -> Self {
+        self.on_progress = Some(on_progress);
+        self
+    }
+
+    /// Use a code shown to the user earlier instead of a new one
+    ///
+    /// # Errors
+    /// InvalidInput if `code` is empty
+    pub fn with_pairing_code(mut self, code: impl Into<String>) -> Result<Self> {
+        let code = code.into();
+        if code.trim().is_empty() {
+            return Err(LibationError::invalid_input("Pairing code must not be empty"));
+        }
+        self.pairing_code = code;
+        Ok(self)
+    }
+
+    /// Stop waiting or receiving when `cancel` is cancelled
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Code the sender must enter; show it to the user
+    pub fn pairing_code(&self) -> &str {
+        &self.pairing_code
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Advertise this receiver over mDNS until the returned value is dropped
+    pub fn advertise(&self) -> Result<Advertisement> {
+        Advertisement::start(&self.device_name, self.local_addr()?.port())
+    }
+
+    /// Accept one sender and store its book under `library_dir`
+    ///
+    /// Waits until a sender connects or the receiver is cancelled.
+    ///
+    /// # Errors
+    /// - InvalidInput if the sender didn't know the pairing code
+    /// - RecordNotFound / InvalidState if the book isn't in this library or
+    ///   already has a file (the sender is told why)
+    /// - DownloadFailed if the audio doesn't match the offer
+    pub async fn receive(self, pool: &SqlitePool, library_dir: &Path) -> Result<ReceivedBook> {
+        let (mut stream, peer_addr) = self.cancel.run_until_cancelled(self.listener.accept()).await??;
+
+        let nonce = new_nonce();
+        write_message(
+            &mut stream,
+            &Message::Hello {
+                version: PROTOCOL_VERSION,
+                device_name: self.device_name.clone(),
+                nonce: nonce.clone(),
+            },
+        )
+        .await?;
+
+        let (sender, sender_nonce) = match read_message(&mut stream).await?.0 {
+            Message::Auth {
+                device_name,
+                nonce: sender_nonce,
+                proof,
+            } => {
+                let expected = keyed(&self.pairing_code, "sender", &[nonce.as_bytes(), sender_nonce.as_bytes()]);
+                if !verify(expected, &proof) {
+                    reject(&mut stream, "Wrong pairing code").await;
+                    return Err(LibationError::invalid_input(format!(
+                        "Wrong pairing code from {} ({})",
+                        device_name, peer_addr
+                    )));
+                }
+                (device_name, sender_nonce)
+            }
+            other => return Err(unexpected(other)),
+        };
+        let proof = keyed(&self.pairing_code, "receiver", &[sender_nonce.as_bytes(), nonce.as_bytes()]);
+        write_message(&mut stream, &Message::Accepted { proof: hex::encode(proof.finalize().into_bytes()) }).await?;
+
+        let (offer, offer_frame) = match read_message(&mut stream).await? {
+            (Message::Offer(offer), frame) => (offer, frame),
+            (other, _) => return Err(unexpected(other)),
+        };
+        let (output, title) = match self.prepare_output(pool, library_dir, &offer).await {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                reject(&mut stream, &e.to_string()).await;
+                return Err(e);
+            }
+        };
+        write_message(&mut stream, &Message::Ready).await?;
+
+        let started = Instant::now();
+        let partial = output.with_extension(format!("{}.partial", offer.extension));
+        let sha256 = match self.receive_audio(&mut stream, &partial, offer.size_bytes).await {
+            Ok(sha256) => sha256,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(e);
+            }
+        };
+
+        let session = session_key(&self.pairing_code, &nonce, &sender_nonce);
+        let authentic = match read_message(&mut stream).await {
+            Ok((Message::Done { mac }, _)) => verify(keyed_raw(&session, &[&offer_frame, sha256.as_bytes()]), &mac),
+            _ => false,
+        };
+        if sha256 != offer.sha256 || !authentic {
+            let _ = tokio::fs::remove_file(&partial).await;
+            reject(&mut stream, "Received audio doesn't match the offer").await;
+            return Err(LibationError::DownloadFailed(format!(
+                "Audio from {} doesn't match what was offered",
+                sender
+            )));
+        }
+        tokio::fs::rename(&partial, &output).await?;
+
+        let output_path = output.display().to_string();
+        let receipt = NewReceipt {
+            asin: offer.asin.clone(),
+            task_id: None,
+            source: InputSource::Handoff,
+            source_ref: Some(sender.clone()),
+            license_id: None,
+            decrypt_method: DecryptMethod::None,
+            files: vec![ReceiptFile {
+                location: output_path.clone(),
+                size_bytes: offer.size_bytes as i64,
+                sha256,
+            }],
+            download_ms: Some(started.elapsed().as_millis() as i64),
+            decrypt_ms: None,
+            toolchain: Toolchain::with_ffmpeg(None),
+        };
+        let receipt = record_liberation(pool, &title, &receipt).await?;
+        write_message(&mut stream, &Message::Received { output_path }).await?;
+
+        Ok(ReceivedBook { sender, receipt })
+    }
+
+    /// Output path for an offered book, with its parent created
+    async fn prepare_output(&self, pool: &SqlitePool, library_dir: &Path, offer: &HandoffOffer) -> Result<(PathBuf, String)> {
+        if offer.extension.is_empty() || !offer.extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(LibationError::invalid_input(format!("Invalid file extension: {:?}", offer.extension)));
+        }
+        let book = find_book_with_relations_by_asin(pool, &offer.asin)
+            .await?
+            .ok_or_else(|| LibationError::not_found(format!("Not in library on this device: {}", offer.asin)))?;
+        if let Some(existing) = get_book_file_path(pool, &offer.asin).await? {
+            if Path::new(&existing).exists() {
+                return Err(LibationError::InvalidState(format!("Already on this device: {}", existing)));
+            }
+        }
+        // Optimistic when the platform can't report free space
+        if let Some(existing) = library_dir.ancestors().find(|p| p.exists()) {
+            if let Some(available) = filesystem_info(existing)?.available_bytes {
+                if available < offer.size_bytes {
+                    return Err(LibationError::InsufficientDiskSpace {
+                        need: offer.size_bytes,
+                        have: available,
+                    });
+                }
+            }
+        }
+
+        let relative = build_unique_file_path(
+            &book.to_audio_metadata(),
+            self.naming_pattern,
+            &offer.extension,
+            self.collision_strategy,
+            &[],
+            Some(library_dir),
+        )?;
+        let output = library_dir.join(relative);
+        if let Some(parent) = output.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok((output, book.title))
+    }
+
+    /// Write `size` bytes from the sender to `partial`, returning their SHA-256
+    async fn receive_audio(&self, stream: &mut TcpStream, partial: &Path, size: u64) -> Result<String> {
+        let mut file = tokio::fs::File::create(partial).await?;
+        let mut sha256 = Sha256::new();
+        let mut buffer = vec![0u8; CHUNK_LEN];
+        let mut received = 0u64;
+        while received < size {
+            self.cancel.check()?;
+            let want = (size - received).min(CHUNK_LEN as u64) as usize;
+            let read = with_timeout(stream.read(&mut buffer[..want])).await??;
+            if read == 0 {
+                return Err(LibationError::FileSizeMismatch {
+                    expected: size,
+                    actual: received,
+                });
+            }
+            sha256.update(&buffer[..read]);
+            file.write_all(&buffer[..read]).await?;
+            received += read as u64;
+            if let Some(on_progress) = &self.on_progress {
+                on_progress(HandoffProgress {
+                    bytes_transferred: received,
+                    total_bytes: size,
+                });
+            }
+        }
+        file.sync_all().await?;
+        Ok(hex::encode(sha256.finalize()))
+    }
+}
+
+/// Send a liberated book to the receiver at `addr`
+///
+/// # Arguments
+/// * `pairing_code` - Code shown by the receiver
+/// * `device_name` - This device's name, shown to the receiver
+/// * `cancel` - Stops the transfer between chunks; the receiver discards
+///   the partial file
+///
+/// # Errors
+/// - RecordNotFound if the book has no local file
+/// - InvalidInput if the receiver didn't accept the pairing code
+/// - InvalidState if the receiver refused the book (reason included)
+pub async fn send_book(
+    pool: &SqlitePool,
+    asin: &str,
+    addr: SocketAddr,
+    pairing_code: &str,
+    device_name: &str,
+    on_progress: Option<HandoffProgressCallback>,
+    cancel: &CancellationToken,
+) -> Result<SentBook> {
+    let book = find_book_with_relations_by_asin(pool, asin)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+    let path = get_book_file_path(pool, asin)
+        .await?
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .ok_or_else(|| LibationError::not_found(format!("No local file for {}", asin)))?;
+    let offer = HandoffOffer {
+        asin: asin.to_string(),
+        title: book.title.clone(),
+        authors: book.to_audio_metadata().authors,
+        extension: path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default(),
+        size_bytes: tokio::fs::metadata(&path).await?.len(),
+        sha256: sha256_file(&path).await?,
+    };
+
+    let mut stream = cancel.run_until_cancelled(with_timeout(TcpStream::connect(addr))).await???;
+    let (receiver, receiver_nonce) = match read_message(&mut stream).await?.0 {
+        Message::Hello {
+            version,
+            device_name,
+            nonce,
+        } => {
+            if version != PROTOCOL_VERSION {
+                reject(&mut stream, "Unsupported protocol version").await;
+                return Err(LibationError::InvalidState(format!(
+                    "{} speaks handoff protocol {}, this device {}; update the app on both",
+                    device_name, version, PROTOCOL_VERSION
+                )));
+            }
+            (device_name, nonce)
+        }
+        other => return Err(unexpected(other)),
+    };
+
+    let nonce = new_nonce();
+    let proof = keyed(pairing_code, "sender", &[receiver_nonce.as_bytes(), nonce.as_bytes()]);
+    write_message(
+        &mut stream,
+        &Message::Auth {
+            device_name: device_name.to_string(),
+            nonce: nonce.clone(),
+            proof: hex::encode(proof.finalize().into_bytes()),
+        },
+    )
+    .await?;
+    match read_message(&mut stream).await?.0 {
+        Message::Accepted { proof } => {
+            let expected = keyed(pairing_code, "receiver", &[nonce.as_bytes(), receiver_nonce.as_bytes()]);
+            if !verify(expected, &proof) {
+                return Err(LibationError::invalid_input(format!(
+                    "{} couldn't prove it knows the pairing code",
+                    receiver
+                )));
+            }
+        }
+        Message::Rejected { reason } => {
+            return Err(LibationError::invalid_input(format!("{} refused: {}", receiver, reason)))
+        }
+        other => return Err(unexpected(other)),
+    }
+
+    let offer_frame = write_message(&mut stream, &Message::Offer(offer.clone())).await?;
+    match read_message(&mut stream).await?.0 {
+        Message::Ready => {}
+        other => return Err(refused(&receiver, other)),
+    }
+
+    let started = Instant::now();
+    let mut file = tokio::fs::File::open(&path).await?;
+    let mut buffer = vec![0u8; CHUNK_LEN];
+    let mut sent = 0u64;
+    while sent < offer.size_bytes {
+        cancel.check()?;
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Err(LibationError::FileSizeMismatch {
+                expected: offer.size_bytes,
+                actual: sent,
+            });
+        }
+        with_timeout(stream.write_all(&buffer[..read])).await??;
+        sent += read as u64;
+        if let Some(on_progress) = &on_progress {
+            on_progress(HandoffProgress {
+                bytes_transferred: sent,
+                total_bytes: offer.size_bytes,
+            });
+        }
+    }
+
+    let session = session_key(pairing_code, &receiver_nonce, &nonce);
+    let mac = keyed_raw(&session, &[&offer_frame, offer.sha256.as_bytes()]);
+    write_message(&mut stream, &Message::Done { mac: hex::encode(mac.finalize().into_bytes()) }).await?;
+    match read_message(&mut stream).await?.0 {
+        Message::Received { output_path } => Ok(SentBook {
+            asin: offer.asin,
+            receiver,
+            output_path,
+            size_bytes: offer.size_bytes,
+            transfer_ms: started.elapsed().as_millis() as i64,
+        }),
+        other => Err(refused(&receiver, other)),
+    }
+}
+
+fn new_nonce() -> String {
+    let mut nonce = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    hex::encode(nonce)
+}
+
+/// HMAC keyed with the pairing code over a role label and `parts`
+fn keyed(pairing_code: &str, label: &str, parts: &[&[u8]]) -> HmacSha256 {
+    let mut mac = keyed_raw(pairing_code.as_bytes(), &[b"librisync-handoff/", label.as_bytes()]);
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+fn keyed_raw(key: &[u8], parts: &[&[u8]]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+/// Key for the final MAC, bound to this session's nonces
+fn session_key(pairing_code: &str, receiver_nonce: &str, sender_nonce: &str) -> Vec<u8> {
+    keyed(pairing_code, "session", &[receiver_nonce.as_bytes(), sender_nonce.as_bytes()])
+        .finalize()
+        .into_bytes()
+        .to_vec()
+}
+
+/// Constant-time check of a hex MAC
+fn verify(expected: HmacSha256, proof: &str) -> bool {
+    hex::decode(proof).map(|proof| expected.verify_slice(&proof).is_ok()).unwrap_or(false)
+}
+
+async fn with_timeout<F: std::future::Future>(future: F) -> Result<F::Output> {
+    tokio::time::timeout(IO_TIMEOUT, future)
+        .await
+        .map_err(|_| LibationError::network_error("Handoff peer stopped responding", true))
+}
+
+/// Write a frame, returning its JSON
+async fn write_message(stream: &mut TcpStream, message: &Message) -> Result<Vec<u8>> {
+    let frame = serde_json::to_vec(message)?;
+    with_timeout(async {
+        stream.write_u32(frame.len() as u32).await?;
+        stream.write_all(&frame).await
+    })
+    .await??;
+    Ok(frame)
+}
+
+/// Read a frame, returning the message and its JSON
+async fn read_message(stream: &mut TcpStream) -> Result<(Message, Vec<u8>)> {
+    let len = with_timeout(stream.read_u32()).await??;
+    if len > MAX_FRAME_LEN {
+        return Err(LibationError::InvalidData(format!("Handoff frame too large: {} bytes", len)));
+    }
+    let mut frame = vec![0u8; len as usize];
+    with_timeout(stream.read_exact(&mut frame)).await??;
+    Ok((serde_json::from_slice(&frame)?, frame))
+}
+
+/// Tell the other side why the exchange ends; it may already be gone
+async fn reject(stream: &mut TcpStream, reason: &str) {
+    let _ = write_message(stream, &Message::Rejected { reason: reason.to_string() }).await;
+}
+
+fn refused(peer: &str, message: Message) -> LibationError {
+    match message {
+        Message::Rejected { reason } => LibationError::InvalidState(format!("{} refused: {}", peer, reason)),
+        other => unexpected(other),
+    }
+}
+
+fn unexpected(message: Message) -> LibationError {
+    match message {
+        Message::Rejected { reason } => LibationError::InvalidState(format!("Handoff refused: {}", reason)),
+        other => LibationError::InvalidData(format!("Unexpected handoff message: {:?}", other)),
+    }
+}
+
+fn mdns_error(e: mdns_sd::Error) -> LibationError {
+    LibationError::network_error(format!("mDNS: {}", e), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::NewBook;
+    use crate::storage::{queries, Database};
+    use std::net::{Ipv4Addr, SocketAddrV4};
+    use tempfile::TempDir;
+
+    async fn library_with(asin: &str) -> Database {
+        let db = Database::new_in_memory().await.unwrap();
+        let book = NewBook::new(asin.to_string(), "Heaven's River".to_string(), "us".to_string());
+        queries::upsert_book(db.pool(), &book).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_handoff_over_loopback() {
+        let dir = TempDir::new().unwrap();
+        let phone = library_with("B08G9PRS1K").await;
+        let tablet = library_with("B08G9PRS1K").await;
+        let source = dir.path().join("phone.m4b");
+        let audio = vec![7u8; CHUNK_LEN * 2 + 123];
+        tokio::fs::write(&source, &audio).await.unwrap();
+        queries::set_book_file_path(phone.pool(), "B08G9PRS1K", "Heaven's River", &source.display().to_string())
+            .await
+            .unwrap();
+        let loopback = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+        let cancel = CancellationToken::new();
+
+        // Wrong code: refused, and the receiver is done
+        let receiver = HandoffReceiver::bind(loopback, "Tablet").await.unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let wrong = if receiver.pairing_code() == "000000" { "000001" } else { "000000" };
+        let library = dir.path().join("tablet");
+        let (received, sent) = tokio::join!(
+            receiver.receive(tablet.pool(), &library),
+            send_book(phone.pool(), "B08G9PRS1K", addr, wrong, "Phone", None, &cancel)
+        );
+        assert!(matches!(received, Err(LibationError::InvalidInput(_))));
+        assert!(matches!(sent, Err(LibationError::InvalidInput(_))));
+        assert!(queries::get_book_file_path(tablet.pool(), "B08G9PRS1K").await.unwrap().is_none());
+
+        // Right code: stored, recorded and receipted on the tablet
+        let receiver = HandoffReceiver::bind(loopback, "Tablet")
+            .await
+            .unwrap()
+            .with_naming_pattern(NamingPattern::FlatFile);
+        let addr = receiver.local_addr().unwrap();
+        let code = receiver.pairing_code().to_string();
+        let progress = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = progress.clone();
+        let on_progress: HandoffProgressCallback = Arc::new(move |p| sink.lock().unwrap().push(p));
+        let (received, sent) = tokio::join!(
+            receiver.receive(tablet.pool(), &library),
+            send_book(phone.pool(), "B08G9PRS1K", addr, &code, "Phone", Some(on_progress), &cancel)
+        );
+        let received = received.unwrap();
+        let sent = sent.unwrap();
+
+        assert_eq!(received.sender, "Phone");
+        assert_eq!(sent.receiver, "Tablet");
+        assert_eq!(received.receipt.source, InputSource::Handoff);
+        assert_eq!(received.receipt.files[0].location, sent.output_path);
+        assert_eq!(received.receipt.files[0].sha256, hex::encode(Sha256::digest(&audio)));
+        assert_eq!(tokio::fs::read(&sent.output_path).await.unwrap(), audio);
+        assert_eq!(
+            queries::get_book_file_path(tablet.pool(), "B08G9PRS1K").await.unwrap(),
+            Some(sent.output_path.clone())
+        );
+        assert_eq!(progress.lock().unwrap().last().unwrap().bytes_transferred, audio.len() as u64);
+
+        // The tablet has it now
+        let receiver = HandoffReceiver::bind(loopback, "Tablet").await.unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let code = receiver.pairing_code().to_string();
+        let (received, sent) = tokio::join!(
+            receiver.receive(tablet.pool(), &library),
+            send_book(phone.pool(), "B08G9PRS1K", addr, &code, "Phone", None, &cancel)
+        );
+        assert!(matches!(received, Err(LibationError::InvalidState(_))));
+        assert!(matches!(sent, Err(LibationError::InvalidState(msg)) if msg.contains("Already on this device")));
+    }
+}
@@ -0,0 +1,667 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Integrity re-verification of liberated files
+//!
+//! Files on SD cards rot. An optional job, run from the host's scheduler,
+//! re-hashes liberated files and compares them with the digests stored in
+//! `FileIntegrity`:
+//! 1. The first check of a file records its SHA-256 and a sample digest
+//!    (xxh3 over evenly spaced regions) as the baseline. When the file is
+//!    the download itself, it must match the SHA-256 recorded while
+//!    downloading.
+//! 2. Later checks compare the size and either the sample digest
+//!    (`VerifyMode::Sampled`, fast) or the full SHA-256 (`VerifyMode::Full`).
+//!
+//! Each run checks files not verified within `IntegrityPolicy::interval_days`,
+//! least recently checked first, up to `max_files_per_run`, so a large
+//! library is spread over several runs.
+//!
+//! A corrupted file stays marked until it is liberated again, and its book
+//! is set to `LiberatedStatus::Error`. `reliberate_corrupted` clears the
+//! book's download state so the standard download pipeline picks it up.
+//! Missing files are only reported: an unmounted SD card isn't corruption.
+//! Files behind content:// URIs can't be read here and are skipped.
+
+use crate::activity::{self, WorkType};
+use crate::cancel::CancellationToken;
+use crate::clock::Clock;
+use crate::error::{LibationError, Result};
+use crate::storage::queries::clear_book_download_state;
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use xxhash_rust::xxh3::Xxh3;
+
+const KEY_POLICY: &str = "integrity.policy";
+
+/// Regions read for a sample digest
+const SAMPLE_COUNT: u64 = 16;
+
+/// Length of each sampled region
+const SAMPLE_LEN: u64 = 64 * 1024;
+
+/// How much of each file is re-hashed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyMode {
+    /// Size and sampled regions
+    #[default]
+    Sampled,
+    /// Every byte
+    Full,
+}
+
+/// When and how the verification job runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntegrityPolicy {
+    /// The job is opt-in
+    pub enabled: bool,
+    pub mode: VerifyMode,
+    /// Re-check a file after this many days
+    pub interval_days: u32,
+    /// Files per run (None checks everything due)
+    pub max_files_per_run: Option<u32>,
+}
+
+impl Default for IntegrityPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: VerifyMode::Sampled,
+            interval_days: 30,
+            max_files_per_run: None,
+        }
+    }
+}
+
+/// Progress of a running check
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityProgress {
+    /// Files due in this run
+    pub total: usize,
+    pub checked: usize,
+    pub corrupted: usize,
+    pub missing: usize,
+    pub bytes_hashed: u64,
+    /// File being checked
+    pub current_asin: Option<String>,
+}
+
+/// Called before each file and once at the end
+pub type IntegrityProgressCallback = Arc<dyn Fn(IntegrityProgress) + Send + Sync>;
+
+/// A file that failed verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorruptedFile {
+    pub asin: String,
+    pub title: String,
+    pub path: String,
+    pub problem: String,
+    /// ISO 8601 timestamp of the failed check
+    pub checked_at: String,
+}
+
+/// Summary of one run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub mode: VerifyMode,
+    pub checked: usize,
+    /// Files matching their digests
+    pub ok: usize,
+    /// Files checked for the first time, whose digests were recorded
+    pub baselined: usize,
+    pub corrupted: Vec<CorruptedFile>,
+    /// ASINs whose file wasn't found
+    pub missing: Vec<String>,
+    /// Files that can't be read directly (content:// URIs)
+    pub skipped: usize,
+    pub bytes_hashed: u64,
+    /// Stopped early by the cancellation token
+    pub cancelled: bool,
+    pub started_at: String,
+    pub finished_at: String,
+}
+
+/// Stored policy (default if never set)
+pub async fn get_integrity_policy(pool: &SqlitePool) -> Result<IntegrityPolicy> {
+    Ok(get_json_setting(pool, KEY_POLICY).await?.unwrap_or_default())
+}
+
+/// Store the policy
+///
+/// # Errors
+/// - InvalidInput if `interval_days` is 0
+pub async fn set_integrity_policy(pool: &SqlitePool, policy: &IntegrityPolicy) -> Result<()> {
+    if policy.interval_days == 0 {
+        return Err(LibationError::invalid_input("Verification interval must be at least one day"));
+    }
+    set_json_setting(pool, KEY_POLICY, policy).await
+}
+
+#[derive(sqlx::FromRow)]
+struct DueFile {
+    task_id: String,
+    asin: String,
+    title: String,
+    download_path: String,
+    output_path: String,
+    download_sha256: Option<String>,
+    baseline_task_id: Option<String>,
+    size_bytes: Option<i64>,
+    sha256: Option<String>,
+    sample_digest: Option<String>,
+}
+
+impl DueFile {
+    /// Stored digests, if they belong to the current file
+    fn baseline(&self) -> Option<(u64, &str, &str)> {
+        if self.baseline_task_id.as_deref() != Some(self.task_id.as_str()) {
+            return None;
+        }
+        Some((self.size_bytes? as u64, self.sha256.as_deref()?, self.sample_digest.as_deref()?))
+    }
+}
+
+enum Outcome {
+    Ok,
+    Baselined,
+    Corrupted(String),
+    Missing,
+    Skipped,
+}
+
+/// Liberated files due for a check, least recently checked first
+async fn due_files(pool: &SqlitePool, policy: &IntegrityPolicy, clock: &dyn Clock) -> Result<Vec<DueFile>> {
+    let checked_before = crate::storage::dates::format_timestamp(clock.now() - chrono::Duration::days(policy.interval_days.into()));
+    let limit = policy.max_files_per_run.map_or(-1, i64::from);
+
+    let files = sqlx::query_as::<_, DueFile>(
+        r#"
+        WITH latest AS (
+            SELECT
+                task_id,
+                asin,
+                download_path,
+                output_path,
+                sha256,
+                ROW_NUMBER() OVER (PARTITION BY asin ORDER BY completed_at DESC) as rn
+            FROM DownloadTasks
+            WHERE status = 'completed' AND output_path <> ''
+        )
+        SELECT
+            latest.task_id,
+            latest.asin,
+            b.title,
+            latest.download_path,
+            latest.output_path,
+            latest.sha256 as download_sha256,
+            f.task_id as baseline_task_id,
+            f.size_bytes,
+            f.sha256,
+            f.sample_digest
+        FROM latest
+        JOIN Books b ON b.audible_product_id = latest.asin
+        LEFT JOIN FileIntegrity f ON f.asin = latest.asin
+        WHERE latest.rn = 1
+          AND (
+            f.asin IS NULL
+            OR f.task_id <> latest.task_id
+            OR (f.status <> 'corrupted' AND f.checked_at < ?)
+          )
+        ORDER BY f.checked_at IS NOT NULL, f.checked_at
+        LIMIT ?
+        "#,
+    )
+    .bind(checked_before)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(files)
+}
+
+/// Run one verification pass
+///
+/// Runs regardless of `policy.enabled`; the scheduler checks that.
+///
+/// # Arguments
+/// * `cancel` - Stops the run after the current file; the report covers
+///   the files checked so far
+pub async fn run_integrity_check(
+    pool: &SqlitePool,
+    policy: &IntegrityPolicy,
+    clock: &dyn Clock,
+    on_progress: Option<IntegrityProgressCallback>,
+    cancel: &CancellationToken,
+) -> Result<IntegrityReport> {
+    let _work = activity::begin_work(WorkType::IntegrityCheck, None);
+    let files = due_files(pool, policy, clock).await?;
+
+    let mut report = IntegrityReport {
+        mode: policy.mode,
+        started_at: crate::storage::dates::format_timestamp(clock.now()),
+        ..Default::default()
+    };
+    let mut progress = IntegrityProgress {
+        total: files.len(),
+        ..Default::default()
+    };
+
+    for file in &files {
+        if cancel.is_cancelled() {
+            report.cancelled = true;
+            break;
+        }
+        if let Some(cb) = &on_progress {
+            progress.current_asin = Some(file.asin.clone());
+            cb(progress.clone());
+        }
+
+        let checked_at = crate::storage::dates::format_timestamp(clock.now());
+        let (outcome, hashed) = verify_file(pool, file, policy.mode, &checked_at).await?;
+        report.bytes_hashed += hashed;
+        progress.bytes_hashed += hashed;
+
+        match outcome {
+            Outcome::Ok => report.ok += 1,
+            Outcome::Baselined => report.baselined += 1,
+            Outcome::Corrupted(problem) => {
+                progress.corrupted += 1;
+                report.corrupted.push(CorruptedFile {
+                    asin: file.asin.clone(),
+                    title: file.title.clone(),
+                    path: file.output_path.clone(),
+                    problem,
+                    checked_at,
+                });
+            }
+            Outcome::Missing => {
+                progress.missing += 1;
+                report.missing.push(file.asin.clone());
+            }
+            Outcome::Skipped => report.skipped += 1,
+        }
+        report.checked += 1;
+        progress.checked += 1;
+    }
+
+    if let Some(cb) = &on_progress {
+        progress.current_asin = None;
+        cb(progress);
+    }
+    report.finished_at = crate::storage::dates::format_timestamp(clock.now());
+
+    Ok(report)
+}
+
+/// Check one file and store the result
+///
+/// # Returns
+/// The outcome and the number of bytes hashed
+async fn verify_file(pool: &SqlitePool, file: &DueFile, mode: VerifyMode, checked_at: &str) -> Result<(Outcome, u64)> {
+    if file.output_path.contains("://") {
+        return Ok((Outcome::Skipped, 0));
+    }
+
+    let path = Path::new(&file.output_path);
+    let Ok(metadata) = fs::metadata(path).await else {
+        sqlx::query("UPDATE FileIntegrity SET status = 'missing', problem = NULL, checked_at = ? WHERE asin = ?")
+            .bind(checked_at)
+            .bind(&file.asin)
+            .execute(pool)
+            .await?;
+        return Ok((Outcome::Missing, 0));
+    };
+    let size = metadata.len();
+
+    let Some((expected_size, expected_sha256, expected_sample)) = file.baseline() else {
+        let sha256 = sha256_file(path).await?;
+        let sample = sample_digest(path, size).await?;
+        let problem = match &file.download_sha256 {
+            Some(digest) if file.download_path == file.output_path && !digest.eq_ignore_ascii_case(&sha256) => {
+                Some("Does not match the digest recorded while downloading".to_string())
+            }
+            _ => None,
+        };
+        record_baseline(pool, file, size, &sha256, &sample, problem.as_deref(), checked_at).await?;
+
+        let outcome = match problem {
+            Some(problem) => {
+                mark_book_error(pool, &file.asin).await?;
+                Outcome::Corrupted(problem)
+            }
+            None => Outcome::Baselined,
+        };
+        return Ok((outcome, size));
+    };
+
+    let (problem, hashed) = if size != expected_size {
+        (Some(format!("Size changed from {} to {} bytes", expected_size, size)), 0)
+    } else {
+        match mode {
+            VerifyMode::Sampled => {
+                let matches = sample_digest(path, size).await? == expected_sample;
+                (
+                    (!matches).then(|| "Sampled regions don't match".to_string()),
+                    sample_offsets(size).len() as u64 * SAMPLE_LEN.min(size),
+                )
+            }
+            VerifyMode::Full => {
+                let matches = sha256_file(path).await?.eq_ignore_ascii_case(expected_sha256);
+                ((!matches).then(|| "SHA-256 doesn't match".to_string()), size)
+            }
+        }
+    };
+
+    sqlx::query("UPDATE FileIntegrity SET status = ?, problem = ?, checked_at = ? WHERE asin = ?")
+        .bind(if problem.is_some() { "corrupted" } else { "ok" })
+        .bind(&problem)
+        .bind(checked_at)
+        .bind(&file.asin)
+        .execute(pool)
+        .await?;
+
+    match problem {
+        Some(problem) => {
+            mark_book_error(pool, &file.asin).await?;
+            Ok((Outcome::Corrupted(problem), hashed))
+        }
+        None => Ok((Outcome::Ok, hashed)),
+    }
+}
+
+async fn record_baseline(
+    pool: &SqlitePool,
+    file: &DueFile,
+    size: u64,
+    sha256: &str,
+    sample: &str,
+    problem: Option<&str>,
+    checked_at: &str,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO FileIntegrity
+            (asin, task_id, path, size_bytes, sha256, sample_digest, status, problem, baseline_at, checked_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(asin) DO UPDATE SET
+            task_id = excluded.task_id,
+            path = excluded.path,
+            size_bytes = excluded.size_bytes,
+            sha256 = excluded.sha256,
+            sample_digest = excluded.sample_digest,
+            status = excluded.status,
+            problem = excluded.problem,
+            baseline_at = excluded.baseline_at,
+            checked_at = excluded.checked_at
+        "#,
+    )
+    .bind(&file.asin)
+    .bind(&file.task_id)
+    .bind(&file.output_path)
+    .bind(size as i64)
+    // A file that is already bad keeps the digest it should have had
+    .bind(if problem.is_some() { file.download_sha256.as_deref().unwrap_or(sha256) } else { sha256 })
+    .bind(sample)
+    .bind(if problem.is_some() { "corrupted" } else { "ok" })
+    .bind(problem)
+    .bind(checked_at)
+    .bind(checked_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Flag the book as failed so the library shows it needs attention
+async fn mark_book_error(pool: &SqlitePool, asin: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE UserDefinedItems SET book_status = 2
+        WHERE book_id = (SELECT book_id FROM Books WHERE audible_product_id = ?)
+        "#,
+    )
+    .bind(asin)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Hex SHA-256 of a whole file
+pub(crate) async fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).await?;
+    let mut sha256 = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buffer[..read]);
+    }
+    Ok(hex::encode(sha256.finalize()))
+}
+
+/// Start offsets of the sampled regions (first and last included)
+fn sample_offsets(size: u64) -> Vec<u64> {
+    if size <= SAMPLE_COUNT * SAMPLE_LEN {
+        return (0..size).step_by(SAMPLE_LEN as usize).collect();
+    }
+    let last = size - SAMPLE_LEN;
+    (0..SAMPLE_COUNT).map(|i| last * i / (SAMPLE_COUNT - 1)).collect()
+}
+
+/// xxh3 over the file size and the sampled regions
+async fn sample_digest(path: &Path, size: u64) -> Result<String> {
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = Xxh3::new();
+    hasher.update(&size.to_le_bytes());
+
+    let mut buffer = vec![0u8; SAMPLE_LEN as usize];
+    for offset in sample_offsets(size) {
+        let len = SAMPLE_LEN.min(size - offset) as usize;
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.read_exact(&mut buffer[..len]).await?;
+        hasher.update(&buffer[..len]);
+    }
+    Ok(format!("{:016x}", hasher.digest()))
+}
+
+/// Files currently marked corrupted
+pub async fn list_corrupted_files(pool: &SqlitePool) -> Result<Vec<CorruptedFile>> {
+    let files = sqlx::query_as::<_, (String, String, String, Option<String>, String)>(
+        r#"
+        SELECT f.asin, b.title, f.path, f.problem, f.checked_at
+        FROM FileIntegrity f
+        JOIN Books b ON b.audible_product_id = f.asin
+        WHERE f.status = 'corrupted'
+        ORDER BY b.title_sort
+        "#,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(asin, title, path, problem, checked_at)| CorruptedFile {
+        asin,
+        title,
+        path,
+        problem: problem.unwrap_or_default(),
+        checked_at,
+    })
+    .collect();
+
+    Ok(files)
+}
+
+/// Reset corrupted books so the standard download pipeline liberates them
+/// again
+///
+/// The corrupted file is deleted and the book's download state cleared.
+/// ASINs that aren't marked corrupted are ignored.
+///
+/// # Returns
+/// The ASINs that were reset
+///
+/// # Errors
+/// PermissionDenied if `delete_files` is disabled (see `permissions`)
+pub async fn reliberate_corrupted(pool: &SqlitePool, asins: &[String]) -> Result<Vec<String>> {
+    crate::permissions::require(crate::permissions::Permission::DeleteFiles)?;
+
+    let mut reset = Vec::new();
+    for asin in asins {
+        let corrupted: Option<String> =
+            sqlx::query_scalar("SELECT asin FROM FileIntegrity WHERE asin = ? AND status = 'corrupted'")
+                .bind(asin)
+                .fetch_optional(pool)
+                .await?;
+        if corrupted.is_none() {
+            continue;
+        }
+
+        clear_book_download_state(pool, asin, true).await?;
+        sqlx::query("DELETE FROM FileIntegrity WHERE asin = ?")
+            .bind(asin)
+            .execute(pool)
+            .await?;
+        reset.push(asin.clone());
+    }
+
+    Ok(reset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use crate::storage::queries::{insert_book, set_book_file_path};
+    use crate::storage::{Database, NewBook};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_sample_offsets() {
+        assert_eq!(sample_offsets(100), vec![0]);
+        let offsets = sample_offsets(10 * 1024 * 1024);
+        assert_eq!(offsets.len() as u64, SAMPLE_COUNT);
+        assert_eq!(*offsets.last().unwrap(), 10 * 1024 * 1024 - SAMPLE_LEN);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_check() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let dir = tempfile::tempdir().unwrap();
+        let clock = TestClock::new(chrono::Utc::now());
+        let cancel = CancellationToken::new();
+
+        for asin in ["B0GOOD", "B0ROT", "B0GONE"] {
+            let book_id = insert_book(pool, &NewBook::new(asin.to_string(), asin.to_string(), "us".to_string()))
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO UserDefinedItems (book_id, book_status) VALUES (?, 1)")
+                .bind(book_id)
+                .execute(pool)
+                .await
+                .unwrap();
+            let path = dir.path().join(format!("{}.m4b", asin));
+            std::fs::write(&path, vec![7u8; 3 * 1024 * 1024]).unwrap();
+            set_book_file_path(pool, asin, asin, path.to_str().unwrap()).await.unwrap();
+        }
+
+        // First run records baselines
+        let policy = IntegrityPolicy::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let on_progress: IntegrityProgressCallback = Arc::new(move |p| sink.lock().unwrap().push(p));
+        let report = run_integrity_check(pool, &policy, &clock, Some(on_progress), &cancel).await.unwrap();
+        assert_eq!((report.checked, report.baselined, report.ok), (3, 3, 0));
+        assert_eq!(events.lock().unwrap().len(), 4);
+        assert_eq!(events.lock().unwrap().last().unwrap().checked, 3);
+
+        // Nothing is due again until the interval has passed
+        let report = run_integrity_check(pool, &policy, &clock, None, &cancel).await.unwrap();
+        assert_eq!(report.checked, 0);
+
+        // Flip one byte in the middle of a sampled region, delete another file
+        let rot_path = dir.path().join("B0ROT.m4b");
+        let mut data = std::fs::read(&rot_path).unwrap();
+        let offset = sample_offsets(data.len() as u64)[5] as usize + 10;
+        data[offset] ^= 0xff;
+        std::fs::write(&rot_path, &data).unwrap();
+        std::fs::remove_file(dir.path().join("B0GONE.m4b")).unwrap();
+
+        clock.advance(chrono::Duration::days(31));
+        let report = run_integrity_check(pool, &policy, &clock, None, &cancel).await.unwrap();
+        assert_eq!((report.checked, report.ok), (3, 1));
+        assert_eq!(report.missing, vec!["B0GONE"]);
+        assert_eq!(report.corrupted.len(), 1);
+        assert_eq!(report.corrupted[0].asin, "B0ROT");
+
+        let status: i32 = sqlx::query_scalar(
+            "SELECT u.book_status FROM UserDefinedItems u JOIN Books b ON u.book_id = b.book_id \
+             WHERE b.audible_product_id = 'B0ROT'",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!(status, 2);
+
+        // Corruption is sticky until the book is liberated again
+        clock.advance(chrono::Duration::days(31));
+        let report = run_integrity_check(pool, &policy, &clock, None, &cancel).await.unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(list_corrupted_files(pool).await.unwrap().len(), 1);
+
+        let reset = reliberate_corrupted(pool, &["B0ROT".to_string(), "B0GOOD".to_string()]).await.unwrap();
+        assert_eq!(reset, vec!["B0ROT"]);
+        assert!(!rot_path.exists());
+        assert!(list_corrupted_files(pool).await.unwrap().is_empty());
+
+        // Cancelled runs stop before the next file
+        clock.advance(chrono::Duration::days(31));
+        cancel.cancel();
+        let report = run_integrity_check(pool, &policy, &clock, None, &cancel).await.unwrap();
+        assert!(report.cancelled);
+        assert_eq!(report.checked, 0);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_policy() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        assert_eq!(get_integrity_policy(pool).await.unwrap(), IntegrityPolicy::default());
+
+        let policy = IntegrityPolicy {
+            enabled: true,
+            mode: VerifyMode::Full,
+            interval_days: 7,
+            max_files_per_run: Some(20),
+        };
+        set_integrity_policy(pool, &policy).await.unwrap();
+        assert_eq!(get_integrity_policy(pool).await.unwrap(), policy);
+
+        let invalid = IntegrityPolicy { interval_days: 0, ..policy };
+        assert!(matches!(set_integrity_policy(pool, &invalid).await, Err(LibationError::InvalidInput(_))));
+    }
+}
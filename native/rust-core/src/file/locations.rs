@@ -0,0 +1,209 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-book storage locations
+//!
+//! Books are liberated into the output directory the app passes in, but a
+//! book can be given its own output root instead, e.g. to keep a few large
+//! books on the SD card. Overrides are stored on UserDefinedItems
+//! (`output_root`) and honored by:
+//! - `PersistentDownloadManager::store_conversion_keys` - the task's
+//!   output directory becomes the override
+//! - `download::plan_liberation` - planned paths are under the override
+//! - `LibraryScanner::scan_and_update` - override roots are scanned too
+//!
+//! A root must be registered first (`register_storage_root`), which is when
+//! the app has been granted access to it. Registered roots are stored in
+//! Settings (`storage.roots`). Filesystem roots are checked for
+//! writability when registered and again when a book is pointed at them,
+//! since an SD card can be removed in between; `content://` roots can't be
+//! probed from Rust and count as writable once registered.
+
+use crate::error::{LibationError, Result};
+use crate::file::paths::validate_output_path;
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::Path;
+
+const KEY_ROOTS: &str = "storage.roots";
+
+/// A place books can be liberated to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageRoot {
+    /// Directory path or `content://` tree URI
+    pub path: String,
+    /// Name shown to the user ("SD card")
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// Registered roots, in registration order
+pub async fn list_storage_roots(pool: &SqlitePool) -> Result<Vec<StorageRoot>> {
+    Ok(get_json_setting(pool, KEY_ROOTS).await?.unwrap_or_default())
+}
+
+/// Register a root, or update its label
+///
+/// # Errors
+/// - InvalidInput for an empty path
+/// - `PathValidationFailed` if a filesystem root isn't a writable directory
+pub async fn register_storage_root(pool: &SqlitePool, root: &StorageRoot) -> Result<()> {
+    if root.path.trim().is_empty() {
+        return Err(LibationError::invalid_input("Storage root path is empty"));
+    }
+    check_writable(&root.path)?;
+
+    let mut roots = list_storage_roots(pool).await?;
+    match roots.iter_mut().find(|r| r.path == root.path) {
+        Some(existing) => existing.label = root.label.clone(),
+        None => roots.push(root.clone()),
+    }
+    set_json_setting(pool, KEY_ROOTS, &roots).await
+}
+
+/// Forget a root
+///
+/// # Errors
+/// - InvalidState if books still use it
+pub async fn unregister_storage_root(pool: &SqlitePool, path: &str) -> Result<()> {
+    let in_use: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM UserDefinedItems WHERE output_root = ?")
+        .bind(path)
+        .fetch_one(pool)
+        .await?;
+    if in_use > 0 {
+        return Err(LibationError::InvalidState(format!(
+            "{} book(s) are still stored under {}",
+            in_use, path
+        )));
+    }
+
+    let mut roots = list_storage_roots(pool).await?;
+    roots.retain(|r| r.path != path);
+    set_json_setting(pool, KEY_ROOTS, &roots).await
+}
+
+/// Output root override of a book (None: the default directory)
+pub async fn get_book_output_root(pool: &SqlitePool, asin: &str) -> Result<Option<String>> {
+    let root: Option<Option<String>> = sqlx::query_scalar(
+        "SELECT u.output_root FROM UserDefinedItems u JOIN Books b ON u.book_id = b.book_id \
+         WHERE b.audible_product_id = ?",
+    )
+    .bind(asin)
+    .fetch_optional(pool)
+    .await?;
+    Ok(root.flatten())
+}
+
+/// Set (or clear, with None) a book's output root
+///
+/// Only affects liberations from now on; files already written stay where
+/// they are.
+///
+/// # Errors
+/// - NotFound if the book isn't in the library
+/// - InvalidInput if the root isn't registered
+/// - `PathValidationFailed` if a filesystem root isn't writable
+pub async fn set_book_output_root(pool: &SqlitePool, asin: &str, root: Option<&str>) -> Result<()> {
+    if let Some(root) = root {
+        if !list_storage_roots(pool).await?.iter().any(|r| r.path == root) {
+            return Err(LibationError::InvalidInput(format!("Storage root is not registered: {}", root)));
+        }
+        check_writable(root)?;
+    }
+
+    let book_id: i64 = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+        .bind(asin)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+
+    sqlx::query(
+        "INSERT INTO UserDefinedItems (book_id, output_root) VALUES (?, ?) \
+         ON CONFLICT(book_id) DO UPDATE SET output_root = excluded.output_root",
+    )
+    .bind(book_id)
+    .bind(root)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Distinct output roots books are overridden to
+pub async fn list_output_roots_in_use(pool: &SqlitePool) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar(
+        "SELECT DISTINCT output_root FROM UserDefinedItems WHERE output_root IS NOT NULL ORDER BY output_root",
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Fail unless a filesystem root is a writable directory
+fn check_writable(root: &str) -> Result<()> {
+    if root.starts_with("content://") {
+        return Ok(());
+    }
+    let root = Path::new(root);
+    validate_output_path(root, root, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::NewBook;
+    use crate::storage::queries::insert_book;
+    use crate::storage::Database;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_book_output_root() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0BIGBOOK1".to_string(), "Big Book".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        let sd_card = TempDir::new().unwrap();
+        let sd_path = sd_card.path().to_string_lossy().to_string();
+
+        // Roots must be registered and exist
+        assert!(matches!(
+            set_book_output_root(pool, "B0BIGBOOK1", Some(&sd_path)).await,
+            Err(LibationError::InvalidInput(_))
+        ));
+        let missing = StorageRoot { path: "/no/such/card".to_string(), label: None };
+        assert!(matches!(
+            register_storage_root(pool, &missing).await,
+            Err(LibationError::PathValidationFailed { .. })
+        ));
+
+        let root = StorageRoot { path: sd_path.clone(), label: Some("SD card".to_string()) };
+        register_storage_root(pool, &root).await.unwrap();
+        register_storage_root(pool, &root).await.unwrap();
+        assert_eq!(list_storage_roots(pool).await.unwrap(), vec![root]);
+
+        set_book_output_root(pool, "B0BIGBOOK1", Some(&sd_path)).await.unwrap();
+        assert_eq!(get_book_output_root(pool, "B0BIGBOOK1").await.unwrap(), Some(sd_path.clone()));
+        assert_eq!(list_output_roots_in_use(pool).await.unwrap(), vec![sd_path.clone()]);
+        assert!(set_book_output_root(pool, "B0MISSING0", Some(&sd_path)).await.is_err());
+
+        // In-use roots stay registered
+        assert!(matches!(unregister_storage_root(pool, &sd_path).await, Err(LibationError::InvalidState(_))));
+        set_book_output_root(pool, "B0BIGBOOK1", None).await.unwrap();
+        assert_eq!(get_book_output_root(pool, "B0BIGBOOK1").await.unwrap(), None);
+        unregister_storage_root(pool, &sd_path).await.unwrap();
+        assert!(list_storage_roots(pool).await.unwrap().is_empty());
+    }
+}
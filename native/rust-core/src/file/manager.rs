@@ -33,7 +33,7 @@
 
 use crate::audio::metadata::AudioMetadata;
 use crate::error::{LibationError, Result};
-use crate::file::paths::{avoid_collision, get_safe_filename, PathTemplate};
+use crate::file::paths::{avoid_collision, get_safe_filename, CollisionStrategy, PathTemplate};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::fs;
@@ -52,12 +52,24 @@ const RETRY_DELAY: Duration = Duration::from_millis(100);
 pub struct FileManager {
     /// Base library directory
     library_path: PathBuf,
+
+    /// How organized files are renamed when their path is taken
+    collision_strategy: CollisionStrategy,
 }
 
 impl FileManager {
     /// Create a new file manager
     pub fn new(library_path: PathBuf) -> Self {
-        Self { library_path }
+        Self {
+            library_path,
+            collision_strategy: CollisionStrategy::default(),
+        }
+    }
+
+    /// Set how `organize_audiobook` renames books whose path is taken
+    pub fn with_collision_strategy(mut self, strategy: CollisionStrategy) -> Self {
+        self.collision_strategy = strategy;
+        self
     }
 
     /// Get the library path
@@ -260,11 +272,14 @@ impl FileManager {
     ///
     /// # Reference: `FileManager/FileUtility.cs` (inferred from Libation behavior)
     pub async fn check_disk_space(&self, path: &Path, required_bytes: u64) -> Result<bool> {
-        // Use fs2 crate for cross-platform disk space checking
-        // For now, return true (optimistic)
-        // TODO: Implement actual disk space checking using fs2 or sysinfo crate
-        let _ = (path, required_bytes);
-        Ok(true)
+        // The target may not exist yet; measure the nearest existing ancestor
+        let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+            return Ok(true);
+        };
+
+        // Optimistic when the platform can't report free space
+        let info = crate::file::paths::filesystem_info(existing)?;
+        Ok(info.available_bytes.is_none_or(|available| available >= required_bytes))
     }
 
     /// Verify file integrity by checking size
@@ -307,7 +322,13 @@ impl FileManager {
             .await?;
 
         // Generate safe filename (handles collisions)
-        let target_path = get_safe_filename(&self.library_path, metadata, template, extension)?;
+        let target_path = get_safe_filename(
+            &self.library_path,
+            metadata,
+            template,
+            extension,
+            self.collision_strategy,
+        )?;
 
         // Ensure parent directory exists
         if let Some(parent) = target_path.parent() {
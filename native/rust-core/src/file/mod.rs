@@ -20,16 +20,41 @@
 
 //! File management and path utilities
 //!
-//! This module handles file operations, path generation, and naming templates.
+//! This module handles file operations, path generation, and naming templates,
+//! imports files dropped into a watch folder (`watch_folder`) and
+//! re-verifies liberated files against stored digests (`integrity`) and
+//! stores liberated files on local, WebDAV or mounted SMB storage
+//! (`backend`). `server_export` lays liberated books out with metadata
+//! sidecars for Audiobookshelf and Plex. With the `lan-handoff` feature,
+//! `handoff` sends liberated books to the app on another device.
+//! `locations` keeps per-book output roots, e.g. large books on an SD card.
+//! Intermediate files go in per-job temp directories (`temp`) that are
+//! removed when the job ends. `cache` keeps the cover, sample and temp
+//! caches within the sizes the user sets, and `covers` downloads covers
+//! into the cover cache for offline display. `post_hooks` moves,
+//! media-scans or announces a book once it is liberated.
 //!
 //! # Reference C# Sources
 //! - `FileManager/` - File utilities and operations
 //! - `LibationFileManager/` - Libation-specific file operations
 //! - `FileManager/NamingTemplate/` - Template system for file naming
 
+pub mod backend;
+pub mod cache;
+pub mod covers;
+#[cfg(feature = "lan-handoff")]
+pub mod handoff;
+pub mod integrity;
+pub mod locations;
 pub mod manager;
 pub mod paths;
+pub mod post_hooks;
+pub mod server_export;
+pub mod temp;
+pub mod watch_folder;
 
 // Re-export commonly used types
+pub use backend::{BackendCapabilities, BackendConfig, StorageBackend};
 pub use manager::FileManager;
-pub use paths::PathBuilder;
+pub use paths::{CollisionStrategy, PathBuilder};
+pub use watch_folder::{WatchFolderImporter, WatchImportReport};
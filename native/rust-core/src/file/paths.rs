@@ -31,12 +31,28 @@
 //! - Sanitize for filesystem compatibility
 //! - Handle path length limits
 //! - Avoid filename collisions
+//!
+//! # Collisions
+//! Two books with the same title and author render to the same path with
+//! simple templates. `PathBuilder::build_unique_path` and
+//! `build_unique_file_path` detect this and rename the later book according
+//! to a `CollisionStrategy`. Names are compared after Unicode NFC
+//! normalization and lowercasing, because the filesystems audiobooks end up
+//! on (APFS, exFAT/FAT32 SD cards, NTFS) treat "Café" and "CAFÉ" as the
+//! same file.
+//!
+//! # Path Validation
+//! `validate_output_path` runs before long operations (downloads, conversions)
+//! so an unusable destination fails fast with a `PathValidationFailed` error
+//! naming the check that failed, instead of a raw IO error minutes later.
 
 use crate::audio::metadata::AudioMetadata;
 use crate::error::{LibationError, Result};
 use regex::Regex;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
 
 // Platform-specific path limits (in bytes for UTF-8)
 #[cfg(target_os = "windows")]
@@ -258,6 +274,9 @@ impl PathTemplate {
 pub struct PathBuilder {
     base_directory: PathBuf,
     template: PathTemplate,
+    collision_strategy: CollisionStrategy,
+    /// Collision keys of paths handed out by `build_unique_path`
+    reserved: HashSet<String>,
 }
 
 impl PathBuilder {
@@ -265,9 +284,29 @@ impl PathBuilder {
         Self {
             base_directory,
             template,
+            collision_strategy: CollisionStrategy::default(),
+            reserved: HashSet::new(),
         }
     }
 
+    /// Set how `build_unique_path` renames colliding books
+    pub fn with_collision_strategy(mut self, strategy: CollisionStrategy) -> Self {
+        self.collision_strategy = strategy;
+        self
+    }
+
+    /// Build a path that doesn't collide with an existing file or with a
+    /// path this builder returned earlier
+    ///
+    /// Use one builder for a whole batch so books that haven't been written
+    /// yet still count as taken.
+    pub fn build_unique_path(&mut self, metadata: &AudioMetadata, extension: &str) -> Result<PathBuf> {
+        let path = self.build_path(metadata, extension)?;
+        let path = resolve_collision(&path, metadata, self.collision_strategy, &self.reserved);
+        self.reserved.insert(collision_key(&path));
+        Ok(path)
+    }
+
     /// Build full path from metadata
     ///
     /// # Reference: `LibationFileManager/Configuration.cs` and `FileManager/FileUtility.cs`
@@ -577,36 +616,313 @@ pub fn truncate_component(text: &str, max_bytes: usize) -> String {
 ///
 /// # Reference: `FileManager/FileUtility.cs` GetValidFilename()
 pub fn avoid_collision(path: &Path) -> PathBuf {
-    if !path.exists() {
+    let reserved = HashSet::new();
+    if !is_taken(path, &reserved) {
         return path.to_path_buf();
     }
+    increment_suffix(path, |candidate| is_taken(candidate, &reserved))
+}
+
+/// How to rename a book whose path is already taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionStrategy {
+    /// "Title (1).m4b", "Title (2).m4b", ...
+    #[default]
+    IncrementSuffix,
+
+    /// "Title [B0XXXXXXXX].m4b"
+    AppendAsin,
+
+    /// "Title - Narrator.m4b"
+    AppendNarrator,
+}
 
-    let parent = path.parent().unwrap_or_else(|| Path::new(""));
-    let stem = path
-        .file_stem()
+impl CollisionStrategy {
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "increment" | "increment_suffix" => Some(CollisionStrategy::IncrementSuffix),
+            "asin" | "append_asin" => Some(CollisionStrategy::AppendAsin),
+            "narrator" | "append_narrator" => Some(CollisionStrategy::AppendNarrator),
+            _ => None,
+        }
+    }
+}
+
+/// Key under which a case- and normalization-insensitive filesystem sees a path
+pub fn collision_key(path: &Path) -> String {
+    path.to_string_lossy().nfc().collect::<String>().to_lowercase()
+}
+
+/// Whether a path is reserved, exists, or differs from an existing entry
+/// of its directory only by case or Unicode normalization
+fn is_taken(path: &Path, reserved: &HashSet<String>) -> bool {
+    if reserved.contains(&collision_key(path)) || path.exists() {
+        return true;
+    }
+
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return false;
+    };
+    let key = collision_key(Path::new(name));
+    std::fs::read_dir(parent)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| collision_key(Path::new(&entry.file_name())) == key)
+        })
+        .unwrap_or(false)
+}
+
+/// `path` with `suffix` added to the file stem, truncating the stem to fit
+fn with_stem_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+    let extension = path
+        .extension()
         .and_then(|s| s.to_str())
-        .unwrap_or("file");
-    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        .map(|e| format!(".{}", e))
+        .unwrap_or_default();
 
+    let max_stem = MAX_COMPONENT_LENGTH.saturating_sub(extension.len() + suffix.len());
+    let stem = truncate_component(stem, max_stem);
+    path.with_file_name(format!("{}{}{}", stem, suffix, extension))
+}
+
+/// First "stem (n).ext" not taken
+fn increment_suffix(path: &Path, taken: impl Fn(&Path) -> bool) -> PathBuf {
     let mut counter = 1;
     loop {
-        let new_name = if extension.is_empty() {
-            format!("{} ({})", stem, counter)
-        } else {
-            format!("{} ({}).{}", stem, counter, extension)
-        };
+        let candidate = with_stem_suffix(path, &format!(" ({})", counter));
+        // Safety limit
+        if !taken(&candidate) || counter >= 9999 {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
 
-        let new_path = parent.join(new_name);
-        if !new_path.exists() {
-            return new_path;
+/// Rename a colliding path according to `strategy`
+///
+/// ASIN and narrator strategies fall back to incrementing when the book has
+/// no ASIN/narrator or the decorated name is taken as well.
+fn resolve_with(
+    path: &Path,
+    metadata: &AudioMetadata,
+    strategy: CollisionStrategy,
+    taken: impl Fn(&Path) -> bool,
+) -> PathBuf {
+    if !taken(path) {
+        return path.to_path_buf();
+    }
+
+    let suffix = match strategy {
+        CollisionStrategy::IncrementSuffix => None,
+        CollisionStrategy::AppendAsin => metadata
+            .asin
+            .as_deref()
+            .map(|asin| format!(" [{}]", sanitize_filename(asin))),
+        CollisionStrategy::AppendNarrator => metadata
+            .narrators
+            .first()
+            .map(|narrator| format!(" - {}", sanitize_filename(narrator))),
+    };
+
+    let base = match suffix {
+        Some(suffix) => {
+            let decorated = with_stem_suffix(path, &suffix);
+            if !taken(&decorated) {
+                return decorated;
+            }
+            decorated
         }
+        None => path.to_path_buf(),
+    };
+    increment_suffix(&base, taken)
+}
 
-        counter += 1;
-        if counter > 9999 {
-            // Safety limit
-            return new_path;
+/// Pick a free path for a book
+///
+/// A path is taken when it is in `reserved` (collision keys, see
+/// `collision_key`) or exists on disk, compared case- and
+/// normalization-insensitively.
+pub fn resolve_collision(
+    path: &Path,
+    metadata: &AudioMetadata,
+    strategy: CollisionStrategy,
+    reserved: &HashSet<String>,
+) -> PathBuf {
+    resolve_with(path, metadata, strategy, |candidate| is_taken(candidate, reserved))
+}
+
+/// Pre-flight check performed by `validate_output_path`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathCheck {
+    /// Base directory exists
+    Exists,
+    /// Base path is a directory
+    IsDirectory,
+    /// Directory accepts new files
+    Writable,
+    /// Enough free space for the operation
+    FreeSpace,
+    /// Each path component fits the filesystem's name limit
+    ComponentLength,
+    /// Whole path fits the platform path limit
+    PathLength,
+}
+
+impl PathCheck {
+    /// Stable error code for the app to branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            PathCheck::Exists => "PATH_NOT_FOUND",
+            PathCheck::IsDirectory => "PATH_NOT_DIRECTORY",
+            PathCheck::Writable => "PATH_NOT_WRITABLE",
+            PathCheck::FreeSpace => "INSUFFICIENT_SPACE",
+            PathCheck::ComponentLength => "NAME_TOO_LONG",
+            PathCheck::PathLength => "PATH_TOO_LONG",
+        }
+    }
+
+    fn fail(&self, path: &Path, message: String) -> LibationError {
+        LibationError::PathValidationFailed {
+            code: self.code().to_string(),
+            path: path.display().to_string(),
+            message,
+        }
+    }
+}
+
+/// Filesystem limits for a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilesystemInfo {
+    /// Bytes available to unprivileged users (None if unknown)
+    pub available_bytes: Option<u64>,
+    /// Maximum filename length in bytes
+    pub max_component_length: usize,
+}
+
+/// Query free space and filename limit of the filesystem holding `path`
+///
+/// Uses `statvfs` on Unix (Android/iOS/macOS/Linux). Removable storage
+/// formatted as FAT/exFAT can report limits different from the default.
+#[cfg(unix)]
+pub fn filesystem_info(path: &Path) -> Result<FilesystemInfo> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| LibationError::InvalidPath(path.display().to_string()))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    // SAFETY: c_path is NUL-terminated and stat is a valid out-pointer
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(LibationError::FileIoError(format!(
+            "statvfs: {} - {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        )));
+    }
+
+    let name_max = stat.f_namemax as usize;
+    Ok(FilesystemInfo {
+        available_bytes: Some(stat.f_bavail as u64 * stat.f_frsize as u64),
+        max_component_length: if name_max == 0 {
+            MAX_COMPONENT_LENGTH
+        } else {
+            name_max.min(MAX_COMPONENT_LENGTH)
+        },
+    })
+}
+
+/// Query free space and filename limit (unknown on this platform)
+#[cfg(not(unix))]
+pub fn filesystem_info(_path: &Path) -> Result<FilesystemInfo> {
+    Ok(FilesystemInfo {
+        available_bytes: None,
+        max_component_length: MAX_COMPONENT_LENGTH,
+    })
+}
+
+/// Validate an output location before starting a long operation
+///
+/// Checks, in order: base directory exists and is a directory, path and
+/// component lengths, writability (by creating a probe file), and free space.
+///
+/// # Arguments
+/// * `base_dir` - Existing directory the output is written under
+/// * `target` - Full output path (may include subdirectories not yet created)
+/// * `required_bytes` - Space the operation needs (0 skips the free space check)
+///
+/// # Errors
+/// `PathValidationFailed` with the code of the first failing `PathCheck`
+pub fn validate_output_path(base_dir: &Path, target: &Path, required_bytes: u64) -> Result<()> {
+    let metadata = std::fs::metadata(base_dir).map_err(|e| {
+        PathCheck::Exists.fail(base_dir, format!("directory is not accessible ({})", e))
+    })?;
+    if !metadata.is_dir() {
+        return Err(PathCheck::IsDirectory.fail(base_dir, "not a directory".to_string()));
+    }
+
+    let info = filesystem_info(base_dir)?;
+    check_path_lengths(target, Some(info.max_component_length))?;
+
+    let probe = base_dir.join(format!(".librisync_write_test_{}", uuid::Uuid::new_v4()));
+    std::fs::write(&probe, b"")
+        .map_err(|e| PathCheck::Writable.fail(base_dir, format!("cannot create files ({})", e)))?;
+    let _ = std::fs::remove_file(&probe);
+
+    if let Some(available) = info.available_bytes {
+        if required_bytes > available {
+            return Err(PathCheck::FreeSpace.fail(
+                base_dir,
+                format!(
+                    "need {} MB, only {} MB available",
+                    required_bytes / 1_000_000,
+                    available / 1_000_000
+                ),
+            ));
         }
     }
+
+    Ok(())
+}
+
+/// Check path and component lengths without touching the filesystem
+///
+/// `max_component_length` is the filesystem's name limit if known (see
+/// `filesystem_info`); None uses the platform default.
+///
+/// # Errors
+/// `PathValidationFailed` with `PATH_TOO_LONG` or `NAME_TOO_LONG`
+pub fn check_path_lengths(target: &Path, max_component_length: Option<usize>) -> Result<()> {
+    let path_len = target.as_os_str().len();
+    if path_len > MAX_PATH_LENGTH {
+        return Err(PathCheck::PathLength.fail(
+            target,
+            format!("path is {} bytes, limit is {}", path_len, MAX_PATH_LENGTH),
+        ));
+    }
+
+    let max_component_length = max_component_length.unwrap_or(MAX_COMPONENT_LENGTH);
+    if let Some(component) = target
+        .components()
+        .map(|c| c.as_os_str())
+        .find(|c| c.len() > max_component_length)
+    {
+        return Err(PathCheck::ComponentLength.fail(
+            target,
+            format!(
+                "'{}' is {} bytes, filesystem limit is {}",
+                component.to_string_lossy(),
+                component.len(),
+                max_component_length
+            ),
+        ));
+    }
+
+    Ok(())
 }
 
 /// Naming pattern for audiobook files
@@ -689,6 +1005,38 @@ pub fn build_file_path(
     Ok(with_ext)
 }
 
+/// Build a relative file path that doesn't collide with other books
+///
+/// For destinations Rust can't list (e.g. Android SAF folders), pass the
+/// relative paths already used there in `existing`. When `base_directory`
+/// is given, files under it count as taken too.
+pub fn build_unique_file_path(
+    metadata: &AudioMetadata,
+    pattern: NamingPattern,
+    extension: &str,
+    strategy: CollisionStrategy,
+    existing: &[String],
+    base_directory: Option<&Path>,
+) -> Result<String> {
+    let relative = build_file_path(metadata, pattern, extension)?;
+
+    let resolved = match base_directory {
+        Some(base) => {
+            let reserved: HashSet<String> = existing.iter().map(|p| collision_key(&base.join(p))).collect();
+            let resolved = resolve_collision(&base.join(&relative), metadata, strategy, &reserved);
+            resolved.strip_prefix(base).unwrap_or(&resolved).to_path_buf()
+        }
+        None => {
+            let reserved: HashSet<String> = existing.iter().map(|p| collision_key(Path::new(p))).collect();
+            resolve_with(Path::new(&relative), metadata, strategy, |candidate| {
+                reserved.contains(&collision_key(candidate))
+            })
+        }
+    };
+
+    Ok(resolved.to_string_lossy().replace('\\', "/"))
+}
+
 /// Get a safe, unique filename
 ///
 /// # Reference: `FileManager/FileUtility.cs` GetValidFilename()
@@ -697,12 +1045,11 @@ pub fn get_safe_filename(
     metadata: &AudioMetadata,
     template: &PathTemplate,
     extension: &str,
+    strategy: CollisionStrategy,
 ) -> Result<PathBuf> {
-    let builder = PathBuilder::new(base_path.to_path_buf(), template.clone());
-    let path = builder.build_path(metadata, extension)?;
-
-    // Avoid collision
-    Ok(avoid_collision(&path))
+    PathBuilder::new(base_path.to_path_buf(), template.clone())
+        .with_collision_strategy(strategy)
+        .build_unique_path(metadata, extension)
 }
 
 #[cfg(test)]
@@ -785,6 +1132,137 @@ mod tests {
         assert_eq!(sanitize_filename("  test...  "), "test");
     }
 
+    fn validation_code(result: Result<()>) -> String {
+        match result {
+            Err(LibationError::PathValidationFailed { code, .. }) => code,
+            other => panic!("expected PathValidationFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_output_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("Author").join("Book.m4b");
+        assert!(validate_output_path(dir.path(), &target, 1024).is_ok());
+
+        let missing = dir.path().join("missing");
+        assert_eq!(
+            validation_code(validate_output_path(&missing, &missing.join("a.m4b"), 0)),
+            "PATH_NOT_FOUND"
+        );
+
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, b"x").unwrap();
+        assert_eq!(
+            validation_code(validate_output_path(&file, &file.join("a.m4b"), 0)),
+            "PATH_NOT_DIRECTORY"
+        );
+
+        let long_name = dir.path().join(format!("{}.m4b", "a".repeat(300)));
+        assert_eq!(
+            validation_code(validate_output_path(dir.path(), &long_name, 0)),
+            "NAME_TOO_LONG"
+        );
+
+        assert_eq!(
+            validation_code(validate_output_path(dir.path(), &target, u64::MAX)),
+            "INSUFFICIENT_SPACE"
+        );
+    }
+
+    #[test]
+    fn test_collision_strategies() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = test_metadata();
+        let path = dir.path().join("Test Book.m4b");
+        let none = HashSet::new();
+
+        assert_eq!(resolve_collision(&path, &metadata, CollisionStrategy::AppendAsin, &none), path);
+
+        std::fs::write(&path, b"x").unwrap();
+        let resolve = |strategy| resolve_collision(&path, &metadata, strategy, &none);
+        assert_eq!(resolve(CollisionStrategy::IncrementSuffix), dir.path().join("Test Book (1).m4b"));
+        assert_eq!(resolve(CollisionStrategy::AppendAsin), dir.path().join("Test Book [B001TEST].m4b"));
+        assert_eq!(resolve(CollisionStrategy::AppendNarrator), dir.path().join("Test Book - Jane Smith.m4b"));
+
+        // Decorated name taken too: increment it
+        std::fs::write(dir.path().join("Test Book [B001TEST].m4b"), b"x").unwrap();
+        assert_eq!(resolve(CollisionStrategy::AppendAsin), dir.path().join("Test Book [B001TEST] (1).m4b"));
+
+        // No narrator to append: increment
+        let anonymous = AudioMetadata { narrators: Vec::new(), ..test_metadata() };
+        assert_eq!(
+            resolve_collision(&path, &anonymous, CollisionStrategy::AppendNarrator, &none),
+            dir.path().join("Test Book (1).m4b")
+        );
+    }
+
+    #[test]
+    fn test_collision_case_and_unicode_insensitive() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = test_metadata();
+
+        // Differs only by case: the same file on APFS/exFAT/NTFS
+        std::fs::write(dir.path().join("test book.m4b"), b"x").unwrap();
+        assert_eq!(
+            avoid_collision(&dir.path().join("Test Book.m4b")),
+            dir.path().join("Test Book (1).m4b")
+        );
+
+        // Decomposed (as stored by HFS+) vs precomposed "Café"
+        std::fs::write(dir.path().join("Cafe\u{301}.m4b"), b"x").unwrap();
+        let precomposed = dir.path().join("Caf\u{e9}.m4b");
+        assert_eq!(
+            resolve_collision(&precomposed, &metadata, CollisionStrategy::IncrementSuffix, &HashSet::new()),
+            dir.path().join("Caf\u{e9} (1).m4b")
+        );
+        assert_eq!(collision_key(Path::new("CAF\u{c9}")), collision_key(Path::new("cafe\u{301}")));
+    }
+
+    #[test]
+    fn test_build_unique_path_reserves_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut builder = PathBuilder::new(dir.path().to_path_buf(), PathTemplate::author_book_folder())
+            .with_collision_strategy(CollisionStrategy::AppendAsin);
+
+        let first = builder.build_unique_path(&test_metadata(), "m4b").unwrap();
+        let other = AudioMetadata { asin: Some("B002TEST".to_string()), ..test_metadata() };
+        let second = builder.build_unique_path(&other, "m4b").unwrap();
+
+        assert_eq!(first, dir.path().join("John Doe/Test Book/Test Book.m4b"));
+        assert_eq!(second, dir.path().join("John Doe/Test Book/Test Book [B002TEST].m4b"));
+    }
+
+    #[test]
+    fn test_build_unique_file_path() {
+        let metadata = test_metadata();
+        let existing = vec!["john doe/test series 1 - test book/TEST SERIES 1 - TEST BOOK.m4b".to_string()];
+
+        let path = build_unique_file_path(
+            &metadata,
+            NamingPattern::AuthorSeriesBook,
+            "m4b",
+            CollisionStrategy::AppendNarrator,
+            &existing,
+            None,
+        )
+        .unwrap();
+        assert_eq!(path, "John Doe/Test Series 1 - Test Book/Test Series 1 - Test Book - Jane Smith.m4b");
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Test Book.m4b"), b"x").unwrap();
+        let path = build_unique_file_path(
+            &metadata,
+            NamingPattern::FlatFile,
+            "m4b",
+            CollisionStrategy::IncrementSuffix,
+            &[],
+            Some(dir.path()),
+        )
+        .unwrap();
+        assert_eq!(path, "Test Book (1).m4b");
+    }
+
     #[cfg(target_os = "windows")]
     #[test]
     fn test_windows_reserved_names() {
@@ -0,0 +1,441 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Post-liberation hooks
+//!
+//! Once a liberation receipt is recorded, `run_post_hooks` hands the book
+//! on as configured in Settings (`PostHookConfig`), hook by hook in order:
+//!
+//! - `Move` / `Copy` the book's local files (and receipt sidecar) into a
+//!   folder, e.g. one a sync app uploads. A move updates the receipt and
+//!   the book's file path, so later hooks see the new location.
+//! - `MediaScan` asks the host to index the files (`events::MediaScanEvent`);
+//!   the platform does the scan.
+//! - `Webhook` POSTs the receipt JSON to a companion service.
+//!
+//! Hooks failing with a transient error (network, 5xx, a file held by the
+//! media scanner) are retried with backoff up to `max_attempts`. A failed
+//! hook doesn't undo the liberation or stop the hooks after it; each
+//! outcome is reported.
+
+use crate::error::{LibationError, Result};
+use crate::events::{self, MediaScanEvent};
+use crate::file::watch_folder::move_file;
+use crate::storage::receipts::{relocate_file, sidecar_path, LiberationReceipt};
+use crate::storage::settings::{get_json_setting, set_json_setting};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const KEY_HOOKS: &str = "liberation.post_hooks";
+
+/// Per-request timeout of webhooks
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most attempts a hook may be given
+const MAX_ATTEMPTS: u32 = 10;
+
+/// An action run after liberation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostHook {
+    /// Move the files into `directory`
+    Move { directory: String },
+    /// Copy the files into `directory`
+    Copy { directory: String },
+    /// Ask the host to media-scan the files
+    MediaScan,
+    /// POST the receipt JSON to `url`
+    Webhook {
+        url: String,
+        /// Extra request headers, e.g. an API key
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+/// Post-liberation hooks and how hard to try them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostHookConfig {
+    pub enabled: bool,
+    /// Run in order
+    pub hooks: Vec<PostHook>,
+    /// Attempts per hook (1 = no retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after
+    pub retry_delay_ms: u64,
+}
+
+impl Default for PostHookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hooks: Vec::new(),
+            max_attempts: 3,
+            retry_delay_ms: 2000,
+        }
+    }
+}
+
+impl PostHookConfig {
+    /// # Errors
+    /// InvalidInput for a relative or empty directory, a non-HTTP(S) URL,
+    /// or `max_attempts` outside 1-10
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_ATTEMPTS).contains(&self.max_attempts) {
+            return Err(LibationError::invalid_input(format!(
+                "max_attempts must be between 1 and {}",
+                MAX_ATTEMPTS
+            )));
+        }
+        for hook in &self.hooks {
+            match hook {
+                PostHook::Move { directory } | PostHook::Copy { directory } => {
+                    if !Path::new(directory).is_absolute() {
+                        return Err(LibationError::invalid_input(format!(
+                            "Hook directory must be an absolute path: {:?}",
+                            directory
+                        )));
+                    }
+                }
+                PostHook::Webhook { url, .. } => {
+                    let parsed = url::Url::parse(url)
+                        .map_err(|e| LibationError::invalid_input(format!("Invalid webhook URL {}: {}", url, e)))?;
+                    if !matches!(parsed.scheme(), "http" | "https") {
+                        return Err(LibationError::invalid_input(format!("Webhook URL must be HTTP(S): {}", url)));
+                    }
+                }
+                PostHook::MediaScan => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How one hook went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookOutcome {
+    pub hook: PostHook,
+    pub attempts: u32,
+    /// Last error, None if the hook succeeded
+    pub error: Option<String>,
+}
+
+/// Result of `run_post_hooks`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostHookReport {
+    /// The receipt with the files' final locations
+    pub receipt: LiberationReceipt,
+    /// One per configured hook; empty when hooks are off
+    pub outcomes: Vec<HookOutcome>,
+}
+
+impl PostHookReport {
+    pub fn all_succeeded(&self) -> bool {
+        self.outcomes.iter().all(|o| o.error.is_none())
+    }
+}
+
+/// Stored post-hook config (default if never set)
+pub async fn get_post_hooks(pool: &SqlitePool) -> Result<PostHookConfig> {
+    Ok(get_json_setting(pool, KEY_HOOKS).await?.unwrap_or_default())
+}
+
+/// Store the post-hook config
+///
+/// # Errors
+/// InvalidInput if the config doesn't validate
+pub async fn set_post_hooks(pool: &SqlitePool, config: &PostHookConfig) -> Result<()> {
+    config.validate()?;
+    set_json_setting(pool, KEY_HOOKS, config).await
+}
+
+/// Run the configured hooks for a just-recorded receipt
+///
+/// # Errors
+/// Only if the config can't be read; hook failures are in the outcomes
+pub async fn run_post_hooks(pool: &SqlitePool, receipt: LiberationReceipt) -> Result<PostHookReport> {
+    let config = get_post_hooks(pool).await?;
+    let mut report = PostHookReport {
+        receipt,
+        outcomes: Vec::new(),
+    };
+    if !config.enabled {
+        return Ok(report);
+    }
+
+    let client = reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build()?;
+    for hook in &config.hooks {
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match run_hook(pool, &client, hook, &mut report.receipt).await {
+                Ok(()) => break None,
+                Err(e) if attempts < config.max_attempts && (e.is_retryable() || e.is_file_locked()) => {
+                    let delay = config.retry_delay_ms.saturating_mul(1 << (attempts - 1).min(16));
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                }
+                Err(e) => {
+                    crate::trace::trace_eprintln!("Post-hook {:?} failed for {}: {}", hook, report.receipt.asin, e);
+                    break Some(e.to_string());
+                }
+            }
+        };
+        report.outcomes.push(HookOutcome {
+            hook: hook.clone(),
+            attempts,
+            error,
+        });
+    }
+    Ok(report)
+}
+
+async fn run_hook(
+    pool: &SqlitePool,
+    client: &reqwest::Client,
+    hook: &PostHook,
+    receipt: &mut LiberationReceipt,
+) -> Result<()> {
+    match hook {
+        PostHook::Move { directory } => move_files(pool, receipt, Path::new(directory)).await,
+        PostHook::Copy { directory } => {
+            let directory = Path::new(directory);
+            tokio::fs::create_dir_all(directory).await?;
+            for file in &receipt.files {
+                let source = Path::new(&file.location);
+                if source.is_file() {
+                    copy_with_sidecar(source, &destination(source, directory)).await?;
+                }
+            }
+            Ok(())
+        }
+        PostHook::MediaScan => {
+            if !events::has_listener() {
+                return Err(LibationError::InvalidState("No host listener to media-scan files".to_string()));
+            }
+            let paths: Vec<String> = receipt
+                .files
+                .iter()
+                .filter(|f| Path::new(&f.location).is_file())
+                .map(|f| f.location.clone())
+                .collect();
+            if !paths.is_empty() {
+                events::emit_media_scan(MediaScanEvent {
+                    asin: receipt.asin.clone(),
+                    paths,
+                });
+            }
+            Ok(())
+        }
+        PostHook::Webhook { url, headers } => {
+            let mut request = client.post(url).json(&*receipt);
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+            let response = request.send().await.map_err(|e| {
+                LibationError::network_error(
+                    format!("Webhook request failed: {}", e),
+                    e.is_timeout() || e.is_connect() || e.is_request(),
+                )
+            })?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(LibationError::api_failed(
+                    format!("Webhook returned {}", status),
+                    Some(status.as_u16()),
+                    Some(url.clone()),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Move each local file into `directory`, recording its new location
+///
+/// A file already moved by an earlier attempt is only recorded.
+async fn move_files(pool: &SqlitePool, receipt: &mut LiberationReceipt, directory: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(directory).await?;
+    for position in 0..receipt.files.len() {
+        let source = PathBuf::from(&receipt.files[position].location);
+        let target = destination(&source, directory);
+        if target == source {
+            continue;
+        }
+        if source.is_file() {
+            move_file(&source, &target).await?;
+            let sidecar = sidecar_path(&source);
+            if sidecar.is_file() {
+                move_file(&sidecar, &sidecar_path(&target)).await?;
+            }
+        } else if !target.is_file() {
+            // Not a local file (content URI, backend location)
+            continue;
+        }
+
+        let location = target.display().to_string();
+        relocate_file(pool, receipt.receipt_id, position, &location).await?;
+        receipt.files[position].location = location;
+    }
+    Ok(())
+}
+
+async fn copy_with_sidecar(source: &Path, target: &Path) -> Result<()> {
+    tokio::fs::copy(source, target).await?;
+    let sidecar = sidecar_path(source);
+    if sidecar.is_file() {
+        tokio::fs::copy(&sidecar, sidecar_path(target)).await?;
+    }
+    Ok(())
+}
+
+fn destination(source: &Path, directory: &Path) -> PathBuf {
+    match source.file_name() {
+        Some(name) => directory.join(name),
+        None => directory.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::get_book_file_path;
+    use crate::storage::receipts::{record_liberation, DecryptMethod, InputSource, NewReceipt, ReceiptFile, Toolchain};
+    use crate::storage::Database;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answers each request with the next status, sending the request
+    /// bodies down the channel
+    async fn serve_statuses(statuses: Vec<u16>) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for status in statuses {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut request = Vec::new();
+                let mut buf = vec![0u8; 8192];
+                // Headers, then the body by Content-Length
+                loop {
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length: ").map(str::to_string))
+                            .and_then(|l| l.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            let _ = sender.send(text[end + 4..].to_string());
+                            break;
+                        }
+                    }
+                }
+                let response = format!("HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_post_hooks_move_and_webhook_with_retries() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("library");
+        let synced = dir.path().join("synced");
+        let copies = dir.path().join("copies");
+        std::fs::create_dir_all(&library).unwrap();
+        let output = library.join("Book.m4b");
+        std::fs::write(&output, b"liberated").unwrap();
+
+        let receipt = record_liberation(
+            pool,
+            "Book",
+            &NewReceipt {
+                asin: "B0HOOKS".to_string(),
+                task_id: None,
+                source: InputSource::LocalFile,
+                source_ref: None,
+                license_id: None,
+                decrypt_method: DecryptMethod::None,
+                files: vec![ReceiptFile::hash(&output, output.display().to_string()).await.unwrap()],
+                download_ms: None,
+                decrypt_ms: None,
+                toolchain: Toolchain::with_ffmpeg(None),
+            },
+        )
+        .await
+        .unwrap();
+        std::fs::write(sidecar_path(&output), b"{}").unwrap();
+
+        // Off by default
+        let report = run_post_hooks(pool, receipt.clone()).await.unwrap();
+        assert!(report.outcomes.is_empty());
+        assert!(output.is_file());
+
+        // 503 is retried; 404 isn't
+        let (url, mut bodies) = serve_statuses(vec![503, 200, 404]).await;
+        let mut config = PostHookConfig {
+            enabled: true,
+            hooks: vec![
+                PostHook::Move { directory: synced.display().to_string() },
+                PostHook::Copy { directory: copies.display().to_string() },
+                PostHook::Webhook { url: url.clone(), headers: BTreeMap::new() },
+                PostHook::Webhook { url, headers: BTreeMap::new() },
+            ],
+            max_attempts: 3,
+            retry_delay_ms: 0,
+        };
+        config.max_attempts = 0;
+        assert!(set_post_hooks(pool, &config).await.is_err());
+        config.max_attempts = 3;
+        set_post_hooks(pool, &config).await.unwrap();
+
+        let report = run_post_hooks(pool, receipt).await.unwrap();
+        let moved = synced.join("Book.m4b");
+        assert_eq!(report.outcomes.len(), 4);
+        assert_eq!(report.outcomes[0].error, None);
+        assert_eq!(report.receipt.files[0].location, moved.display().to_string());
+        assert!(moved.is_file() && !output.exists());
+        assert!(sidecar_path(&moved).is_file());
+        assert_eq!(
+            get_book_file_path(pool, "B0HOOKS").await.unwrap(),
+            Some(moved.display().to_string())
+        );
+
+        // The copy is taken from where the move put the file
+        assert_eq!(report.outcomes[1].error, None);
+        assert_eq!(std::fs::read(copies.join("Book.m4b")).unwrap(), b"liberated");
+
+        assert_eq!((report.outcomes[2].attempts, report.outcomes[2].error.as_deref()), (2, None));
+        let posted: LiberationReceipt = serde_json::from_str(&bodies.recv().await.unwrap()).unwrap();
+        assert_eq!(posted.files[0].location, moved.display().to_string());
+        assert_eq!(report.outcomes[3].attempts, 1);
+        assert!(report.outcomes[3].error.as_deref().unwrap().contains("404"));
+        assert!(!report.all_succeeded());
+    }
+}
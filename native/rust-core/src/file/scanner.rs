@@ -22,9 +22,19 @@
 //!
 //! Scans download directory for audio files, extracts metadata (ASIN),
 //! and updates the database with file paths.
+//!
+//! The database is only written once the whole directory has been scanned,
+//! in one transaction, so a cancelled scan (`with_cancellation`) leaves all
+//! file paths as they were.
+//!
+//! Books with their own output root (see `locations`) are looked for there
+//! too. While such a root is unavailable (SD card removed), its books keep
+//! their file paths instead of being reported missing.
 
 use crate::audio::metadata::MetadataEditor;
+use crate::cancel::CancellationToken;
 use crate::error::{LibationError, Result};
+use crate::file::locations::list_output_roots_in_use;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use std::path::{Path, PathBuf};
@@ -48,16 +58,28 @@ pub struct ScanResults {
 /// Library scanner
 pub struct LibraryScanner {
     pool: SqlitePool,
+    cancel: CancellationToken,
 }
 
 impl LibraryScanner {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Stop scanning (with `LibationError::Cancelled`) when `cancel` is
+    /// cancelled
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
     }
 
     /// Scan directory and update database
     ///
-    /// 1. Recursively scan directory for audio files (.m4b, .mp3, .m4a, .aac)
+    /// 1. Recursively scan directory, and the books' own output roots, for
+    ///    audio files (.m4b, .mp3, .m4a, .aac)
     /// 2. Extract ASIN from metadata tags
     /// 3. Update database: set file_path for books with matching ASIN
     /// 4. Mark books in database without file_path as missing
@@ -86,13 +108,48 @@ impl LibraryScanner {
             files_unmatched: 0,
         };
 
-        // First, clear all file paths (we'll rebuild them)
-        sqlx::query("UPDATE UserDefinedItems SET file_path = NULL")
-            .execute(&self.pool)
-            .await?;
-
         // Scan directory recursively
-        self.scan_recursive(directory, &mut results).await?;
+        let mut found = Vec::new();
+        self.scan_recursive(directory, &mut results, &mut found).await?;
+
+        // Per-book output roots outside the directory
+        let mut unavailable = Vec::new();
+        for root in list_output_roots_in_use(&self.pool).await? {
+            let root_path = Path::new(&root);
+            if root_path.starts_with(directory) {
+                continue;
+            }
+            if root_path.is_dir() {
+                self.scan_recursive(root_path, &mut results, &mut found).await?;
+            } else {
+                unavailable.push(root);
+            }
+        }
+        self.cancel.check()?;
+
+        // Rebuild file paths from what was found, except under roots that
+        // couldn't be scanned
+        let unavailable_json = serde_json::to_string(&unavailable)
+            .map_err(|e| LibationError::InvalidState(format!("Failed to encode roots: {}", e)))?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE UserDefinedItems SET file_path = NULL \
+             WHERE output_root IS NULL OR output_root NOT IN (SELECT value FROM json_each(?))",
+        )
+        .bind(&unavailable_json)
+        .execute(&mut *tx)
+        .await?;
+        for (book_id, path) in &found {
+            let rows_affected = sqlx::query("UPDATE UserDefinedItems SET file_path = ? WHERE book_id = ?")
+                .bind(path)
+                .bind(book_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            if rows_affected > 0 {
+                results.books_updated += 1;
+            }
+        }
 
         // Count books still without file paths (missing from filesystem)
         let missing_count: i64 = sqlx::query_scalar(
@@ -102,9 +159,10 @@ impl LibraryScanner {
             WHERE file_path IS NULL
             "#,
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
         results.books_missing = missing_count as usize;
+        tx.commit().await?;
 
         Ok(results)
     }
@@ -114,6 +172,7 @@ impl LibraryScanner {
         &'a self,
         dir: &'a Path,
         results: &'a mut ScanResults,
+        found: &'a mut Vec<(i64, String)>,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + 'a>> {
         Box::pin(async move {
             let mut entries = fs::read_dir(dir).await.map_err(|e| {
@@ -131,14 +190,15 @@ impl LibraryScanner {
                     e
                 ))
             })? {
+                self.cancel.check()?;
                 let path = entry.path();
 
                 if path.is_dir() {
                     // Recurse into subdirectories
-                    self.scan_recursive(&path, results).await?;
+                    self.scan_recursive(&path, results, found).await?;
                 } else if self.is_audio_file(&path) {
                     results.files_found += 1;
-                    self.process_audio_file(&path, results).await?;
+                    self.process_audio_file(&path, results, found).await?;
                 }
             }
 
@@ -158,8 +218,13 @@ impl LibraryScanner {
         }
     }
 
-    /// Process a single audio file
-    async fn process_audio_file(&self, path: &Path, results: &mut ScanResults) -> Result<()> {
+    /// Process a single audio file, collecting its book if it matches one
+    async fn process_audio_file(
+        &self,
+        path: &Path,
+        results: &mut ScanResults,
+        found: &mut Vec<(i64, String)>,
+    ) -> Result<()> {
         // Extract metadata (this might fail if FFmpeg not available)
         let metadata = match MetadataEditor::extract_metadata(path).await {
             Ok(meta) => meta,
@@ -191,21 +256,7 @@ impl LibraryScanner {
         if let Some(book_id) = book_id {
             // Found matching book in database
             results.books_matched += 1;
-
-            // Update file_path in UserDefinedItems
-            let path_str = path.to_string_lossy().to_string();
-            let rows_affected = sqlx::query(
-                "UPDATE UserDefinedItems SET file_path = ? WHERE book_id = ?",
-            )
-            .bind(&path_str)
-            .bind(book_id)
-            .execute(&self.pool)
-            .await?
-            .rows_affected();
-
-            if rows_affected > 0 {
-                results.books_updated += 1;
-            }
+            found.push((book_id, path.to_string_lossy().to_string()));
         } else {
             // ASIN not found in database
             results.files_unmatched += 1;
@@ -252,4 +303,51 @@ mod tests {
         assert_eq!(results.books_matched, 0);
         assert_eq!(results.books_updated, 0);
     }
+
+    #[tokio::test]
+    async fn test_scan_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("book.m4b"), b"").unwrap();
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let book = crate::storage::NewBook::new("B0SCAN".to_string(), "Scan".to_string(), "us".to_string());
+        let book_id = crate::storage::queries::insert_book(pool, &book).await.unwrap();
+        sqlx::query("INSERT INTO UserDefinedItems (book_id, file_path) VALUES (?, '/old/book.m4b')")
+            .bind(book_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        // A cancelled scan leaves the stored paths alone
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let scanner = LibraryScanner::new(pool.clone()).with_cancellation(cancel);
+        let result = scanner.scan_and_update(temp_dir.path()).await;
+        assert!(matches!(result, Err(LibationError::Cancelled)));
+        assert_eq!(scanner.find_book_by_asin("B0SCAN").await.unwrap().as_deref(), Some("/old/book.m4b"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_keeps_books_on_unavailable_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        for (asin, root) in [("B0SDCARD", Some("/storage/ejected")), ("B0INTERNAL", None)] {
+            let book = crate::storage::NewBook::new(asin.to_string(), asin.to_string(), "us".to_string());
+            let book_id = crate::storage::queries::insert_book(pool, &book).await.unwrap();
+            sqlx::query("INSERT INTO UserDefinedItems (book_id, file_path, output_root) VALUES (?, ?, ?)")
+                .bind(book_id)
+                .bind(format!("/old/{}.m4b", asin))
+                .bind(root)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+
+        let scanner = LibraryScanner::new(pool.clone());
+        let results = scanner.scan_and_update(temp_dir.path()).await.unwrap();
+        assert_eq!(results.books_missing, 1);
+        assert_eq!(scanner.find_book_by_asin("B0SDCARD").await.unwrap().as_deref(), Some("/old/B0SDCARD.m4b"));
+        assert_eq!(scanner.find_book_by_asin("B0INTERNAL").await.unwrap(), None);
+    }
 }
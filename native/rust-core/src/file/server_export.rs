@@ -0,0 +1,404 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Export liberated books for Audiobookshelf and Plex
+//!
+//! Copies liberated files into the folder layout audiobook servers scan,
+//! with metadata sidecars next to each file, so the server picks up
+//! titles, series order, narrators and chapters without manual matching:
+//!
+//! ```text
+//! <root>/Author/Series/Vol. 1 - Title/Title.m4b
+//!                                     metadata.json   (Audiobookshelf)
+//!                                     metadata.opf    (OPF 2.0, calibre series tags)
+//!                                     Title.cue       (chapters, Plex/players)
+//!                                     cover.jpg
+//! <root>/Author/Title/...             (books without a series)
+//! ```
+//!
+//! Exporting again skips audio files already there with the same size and
+//! rewrites the sidecars, so a bulk export can be rerun after every
+//! liberation run.
+
+use crate::audio::metadata::{AudioMetadata, Chapter, ChapterEditor, MetadataEditor};
+use crate::cancel::CancellationToken;
+use crate::error::{LibationError, Result};
+use crate::file::paths::{sanitize_filename, sanitize_path_component};
+use crate::storage::profiles::BOOK_IN_ACTIVE_PROFILE;
+use crate::storage::queries::{self, BookWithRelations};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+/// Which sidecars to write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerExportOptions {
+    /// Audiobookshelf `metadata.json`
+    pub metadata_json: bool,
+    /// OPF 2.0 `metadata.opf`
+    pub opf: bool,
+    /// CUE sheet with the chapters
+    pub cue: bool,
+    /// Download `cover.jpg`
+    pub cover: bool,
+}
+
+impl Default for ServerExportOptions {
+    fn default() -> Self {
+        Self { metadata_json: true, opf: true, cue: true, cover: true }
+    }
+}
+
+/// One exported book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedBook {
+    pub asin: String,
+    /// Book folder
+    pub folder: String,
+    /// Files written, relative to the folder
+    pub files: Vec<String>,
+    /// Whether the audio file was already there
+    pub audio_skipped: bool,
+}
+
+/// A book that couldn't be exported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportFailure {
+    pub asin: String,
+    pub error: String,
+}
+
+/// Result of a bulk export
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerExportReport {
+    pub exported: Vec<ExportedBook>,
+    pub failed: Vec<ExportFailure>,
+}
+
+/// Export one liberated book into `root`
+///
+/// # Errors
+/// - RecordNotFound if the book isn't in the library or not liberated
+/// - FileNotFound if its file isn't a local file (e.g. on a WebDAV backend)
+pub async fn export_book(
+    pool: &SqlitePool,
+    asin: &str,
+    root: &Path,
+    options: ServerExportOptions,
+) -> Result<ExportedBook> {
+    let book = queries::find_book_with_relations_by_asin(pool, asin)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+    let source = queries::get_book_file_path(pool, asin)
+        .await?
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| LibationError::not_found(format!("Book is not liberated: {}", asin)))?;
+    let source = PathBuf::from(source);
+    if !source.is_file() {
+        return Err(LibationError::FileNotFound(source.display().to_string()));
+    }
+
+    let metadata = book.to_audio_metadata();
+    let relative = book_folder(&metadata);
+    let folder = root.join(&relative);
+    tokio::fs::create_dir_all(&folder).await?;
+
+    let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("m4b");
+    let audio_name = format!("{}.{}", sanitize_filename(&metadata.title), extension);
+    let audio_skipped = copy_if_changed(&source, &folder.join(&audio_name)).await?;
+    let mut files = vec![audio_name.clone()];
+
+    let chapters = crate::storage::chapters::get_chapters(pool, asin).await?;
+    if options.metadata_json {
+        let genres = book_genres(pool, book.book_id).await?;
+        let tags = crate::storage::tags::get_book_tags(pool, book.book_id).await?;
+        let json = audiobookshelf_metadata(&book, &metadata, &chapters, &genres, &tags);
+        tokio::fs::write(folder.join("metadata.json"), serde_json::to_vec_pretty(&json)?).await?;
+        files.push("metadata.json".to_string());
+    }
+    if options.opf {
+        tokio::fs::write(folder.join("metadata.opf"), opf_metadata(&book, &metadata)).await?;
+        files.push("metadata.opf".to_string());
+    }
+    if options.cue && !chapters.is_empty() {
+        let cue_name = format!("{}.cue", sanitize_filename(&metadata.title));
+        let cue = ChapterEditor::generate_cue_sheet(&metadata, &chapters, &audio_name);
+        tokio::fs::write(folder.join(&cue_name), cue).await?;
+        files.push(cue_name);
+    }
+    // Servers fall back to the embedded cover, so a failed download is not an error
+    if let (true, Some(url)) = (options.cover, metadata.cover_art_url.as_deref()) {
+        if MetadataEditor::download_cover_art(url, &folder.join("cover.jpg")).await.is_ok() {
+            files.push("cover.jpg".to_string());
+        }
+    }
+
+    Ok(ExportedBook {
+        asin: asin.to_string(),
+        folder: folder.to_string_lossy().into_owned(),
+        files,
+        audio_skipped,
+    })
+}
+
+/// Export the active profile's liberated books into `root`
+///
+/// Books that fail are reported and skipped.
+///
+/// # Errors
+/// Cancellation (checked before each book)
+pub async fn export_library(
+    pool: &SqlitePool,
+    root: &Path,
+    options: ServerExportOptions,
+    cancel: &CancellationToken,
+) -> Result<ServerExportReport> {
+    let asins: Vec<String> = sqlx::query_scalar(&format!(
+        r#"
+        SELECT DISTINCT b.audible_product_id
+        FROM Books b
+        JOIN DownloadTasks d ON d.asin = b.audible_product_id
+        WHERE d.status = 'completed' AND d.output_path <> '' AND {}
+        ORDER BY b.title_sort
+        "#,
+        BOOK_IN_ACTIVE_PROFILE
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let mut report = ServerExportReport::default();
+    for asin in asins {
+        cancel.check()?;
+        match export_book(pool, &asin, root, options).await {
+            Ok(book) => report.exported.push(book),
+            Err(e) => report.failed.push(ExportFailure { asin, error: e.to_string() }),
+        }
+    }
+    Ok(report)
+}
+
+/// `Author/Series/Vol. N - Title` or `Author/Title`
+///
+/// Audiobookshelf reads the series position from the `Vol. N - ` prefix.
+fn book_folder(metadata: &AudioMetadata) -> PathBuf {
+    let author = metadata.authors.first().map(String::as_str).unwrap_or("Unknown Author");
+    let mut folder = PathBuf::from(sanitize_path_component(author));
+    let title = sanitize_path_component(&metadata.title);
+
+    match &metadata.series {
+        Some(series) => {
+            folder.push(sanitize_path_component(&series.name));
+            match &series.position {
+                Some(position) => folder.push(format!("Vol. {} - {}", position, title)),
+                None => folder.push(title),
+            }
+        }
+        None => folder.push(title),
+    }
+    folder
+}
+
+/// Copy `source` to `target` unless a file of the same size is there
+///
+/// # Returns
+/// Whether the copy was skipped
+async fn copy_if_changed(source: &Path, target: &Path) -> Result<bool> {
+    let size = tokio::fs::metadata(source).await?.len();
+    if let Ok(existing) = tokio::fs::metadata(target).await {
+        if existing.len() == size {
+            return Ok(true);
+        }
+    }
+
+    let partial = target.with_extension("part");
+    tokio::fs::copy(source, &partial).await?;
+    tokio::fs::rename(&partial, target).await?;
+    Ok(false)
+}
+
+/// Genre names of a book, from its category ladders
+async fn book_genres(pool: &SqlitePool, book_id: i64) -> Result<Vec<String>> {
+    let genres = sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT c.name
+        FROM BookCategories bc
+        JOIN CategoryLadders cl ON cl.category_ladder_id = bc.category_ladder_id
+        JOIN Categories c ON c.audible_category_id = cl.ladder
+            OR c.audible_category_id IN (
+                SELECT value FROM json_each(CASE WHEN json_valid(cl.ladder) THEN cl.ladder ELSE '[]' END)
+            )
+        WHERE bc.book_id = ? AND c.name IS NOT NULL
+        ORDER BY c.name
+        "#,
+    )
+    .bind(book_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(genres)
+}
+
+/// Audiobookshelf `metadata.json` (chapter times in seconds)
+fn audiobookshelf_metadata(
+    book: &BookWithRelations,
+    metadata: &AudioMetadata,
+    chapters: &[Chapter],
+    genres: &[String],
+    tags: &[String],
+) -> serde_json::Value {
+    let published = book.published_on();
+    serde_json::json!({
+        "tags": tags,
+        "chapters": chapters.iter().enumerate().map(|(id, chapter)| serde_json::json!({
+            "id": id,
+            "start": chapter.start_ms as f64 / 1000.0,
+            "end": chapter.end_ms as f64 / 1000.0,
+            "title": chapter.title,
+        })).collect::<Vec<_>>(),
+        "title": metadata.title,
+        "subtitle": book.subtitle,
+        "authors": metadata.authors,
+        "narrators": metadata.narrators,
+        "series": metadata.format_series().into_iter().collect::<Vec<_>>(),
+        "genres": genres,
+        "publishedYear": published.map(|date| date.format("%Y").to_string()),
+        "publishedDate": published.map(|date| date.format(crate::storage::dates::DATE_FORMAT).to_string()),
+        "publisher": metadata.publisher,
+        "description": metadata.description,
+        "isbn": null,
+        "asin": book.audible_product_id,
+        "language": metadata.language,
+        "explicit": false,
+        "abridged": book.is_abridged,
+    })
+}
+
+/// OPF 2.0 package metadata, with calibre series tags
+fn opf_metadata(book: &BookWithRelations, metadata: &AudioMetadata) -> String {
+    let mut opf = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"2.0\" unique-identifier=\"asin\">\n\
+         \x20 <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:opf=\"http://www.idpf.org/2007/opf\">\n",
+    );
+    let mut element = |line: String| {
+        opf.push_str("    ");
+        opf.push_str(&line);
+        opf.push('\n');
+    };
+
+    element(format!("<dc:title>{}</dc:title>", xml_escape(&metadata.title)));
+    if let Some(subtitle) = &book.subtitle {
+        element(format!("<dc:subtitle>{}</dc:subtitle>", xml_escape(subtitle)));
+    }
+    for author in &metadata.authors {
+        element(format!("<dc:creator opf:role=\"aut\">{}</dc:creator>", xml_escape(author)));
+    }
+    for narrator in &metadata.narrators {
+        element(format!("<dc:contributor opf:role=\"nrt\">{}</dc:contributor>", xml_escape(narrator)));
+    }
+    if let Some(publisher) = &metadata.publisher {
+        element(format!("<dc:publisher>{}</dc:publisher>", xml_escape(publisher)));
+    }
+    if let Some(date) = book.published_on() {
+        element(format!("<dc:date>{}</dc:date>", date.format(crate::storage::dates::DATE_FORMAT)));
+    }
+    if let Some(language) = &metadata.language {
+        element(format!("<dc:language>{}</dc:language>", xml_escape(language)));
+    }
+    if let Some(description) = metadata.description.as_deref().filter(|d| !d.is_empty()) {
+        element(format!("<dc:description>{}</dc:description>", xml_escape(description)));
+    }
+    element(format!(
+        "<dc:identifier id=\"asin\" opf:scheme=\"ASIN\">{}</dc:identifier>",
+        xml_escape(&book.audible_product_id)
+    ));
+    if let Some(series) = &metadata.series {
+        element(format!("<meta name=\"calibre:series\" content=\"{}\"/>", xml_escape(&series.name)));
+        if let Some(position) = &series.position {
+            element(format!("<meta name=\"calibre:series_index\" content=\"{}\"/>", xml_escape(position)));
+        }
+    }
+
+    opf.push_str("  </metadata>\n</package>\n");
+    opf
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Database, NewBook, NewContributor};
+
+    #[tokio::test]
+    async fn test_export_book_for_audiobookshelf() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut book = NewBook::new("B0EXPORT01".to_string(), "Heaven's River".to_string(), "us".to_string());
+        book.length_in_minutes = 60;
+        book.date_published = chrono::NaiveDate::from_ymd_opt(2020, 9, 24);
+        let book_id = queries::insert_book(pool, &book).await.unwrap();
+        let author = queries::upsert_contributor(pool, &NewContributor::new("Dennis E. Taylor".to_string()))
+            .await
+            .unwrap();
+        queries::add_book_contributor(pool, book_id, author, crate::storage::Role::Author as i32, 0)
+            .await
+            .unwrap();
+        crate::storage::chapters::update_chapters(pool, "B0EXPORT01", &[
+            Chapter { title: "Opening <Credits>".to_string(), start_ms: 0, end_ms: 30_000 },
+            Chapter { title: "Chapter 1".to_string(), start_ms: 30_000, end_ms: 3_600_000 },
+        ])
+        .await
+        .unwrap();
+
+        let source = dir.path().join("liberated.m4b");
+        tokio::fs::write(&source, b"audio").await.unwrap();
+        queries::set_book_file_path(pool, "B0EXPORT01", "Heaven's River", &source.to_string_lossy())
+            .await
+            .unwrap();
+
+        let root = dir.path().join("abs");
+        let options = ServerExportOptions { cover: false, ..Default::default() };
+        let report = export_library(pool, &root, options, &CancellationToken::new()).await.unwrap();
+        assert!(report.failed.is_empty());
+        let exported = &report.exported[0];
+        let folder = root.join("Dennis E. Taylor").join("Heaven's River");
+        assert_eq!(PathBuf::from(&exported.folder), folder);
+        assert_eq!(exported.files, vec!["Heaven's River.m4b", "metadata.json", "metadata.opf", "Heaven's River.cue"]);
+        assert_eq!(std::fs::read(folder.join("Heaven's River.m4b")).unwrap(), b"audio");
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(folder.join("metadata.json")).unwrap()).unwrap();
+        assert_eq!(json["authors"], serde_json::json!(["Dennis E. Taylor"]));
+        assert_eq!(json["publishedYear"], "2020");
+        assert_eq!(json["chapters"][1]["start"], 30.0);
+        let opf = std::fs::read_to_string(folder.join("metadata.opf")).unwrap();
+        assert!(opf.contains("<dc:title>Heaven&apos;s River</dc:title>"));
+        assert!(opf.contains("opf:scheme=\"ASIN\">B0EXPORT01<"));
+
+        // Exporting again leaves the audio alone
+        assert!(export_book(pool, "B0EXPORT01", &root, options).await.unwrap().audio_skipped);
+    }
+}
@@ -0,0 +1,245 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-job temp directories
+//!
+//! Intermediate files (FFmpeg list and metadata files, the legacy
+//! downloader's cache, backend probes) go into a `ScratchDir` under one
+//! temp root instead of wherever each stage picked:
+//!
+//! - A job's directory is `JobHandle::temp_dir`; other work creates an
+//!   anonymous one with `ScratchDir::new`
+//! - The directory is removed when its guard drops, so work that succeeds,
+//!   fails or is cancelled leaves nothing behind
+//! - Directories a crashed process left behind are removed on the next
+//!   start (`remove_orphaned_temp_dirs`, run once per process by
+//!   `Database::run_startup_tasks`); directories held by this process are
+//!   kept
+//!
+//! Android cache paths leave little room, so directory names are a short
+//! hash of the job id and file names are sanitized and shortened
+//! (`ScratchDir::file`). The root is the app's cache directory when the
+//! host sets it (`set_temp_root`), else `system_temp_dir`.
+
+use crate::error::Result;
+use crate::file::paths::check_path_lengths;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+/// Subdirectory of the temp root holding scratch directories
+const SCRATCH_SUBDIR: &str = "lsj";
+
+/// Longest file name `ScratchDir::file` hands out
+pub const MAX_TEMP_NAME_LEN: usize = 64;
+
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Scratch directory names held by this process
+static LIVE: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Use `root` (the app's cache directory) for temp files, or go back to
+/// `system_temp_dir` with None
+pub fn set_temp_root(root: Option<PathBuf>) {
+    *ROOT.write().unwrap() = root;
+}
+
+/// `TMPDIR`, then `TEMP`, then the platform default (`/data/local/tmp` on
+/// Android)
+pub fn system_temp_dir() -> PathBuf {
+    std::env::var_os("TMPDIR")
+        .or_else(|| std::env::var_os("TEMP"))
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// The app's cache directory if set, else `system_temp_dir`
+///
+/// Also holds the cover and sample caches (see `file::cache`).
+pub fn cache_root() -> PathBuf {
+    ROOT.read().unwrap().clone().unwrap_or_else(system_temp_dir)
+}
+
+/// Directory scratch directories are created in
+pub fn scratch_root() -> PathBuf {
+    cache_root().join(SCRATCH_SUBDIR)
+}
+
+/// Whether this process holds the scratch directory `name`
+pub(crate) fn is_live(name: &str) -> bool {
+    LIVE.lock().unwrap().as_ref().is_some_and(|live| live.contains(name))
+}
+
+/// Short, filesystem-safe directory name for a job id
+fn dir_name(job_id: &str) -> String {
+    let digest = Sha256::digest(job_id.as_bytes());
+    format!("j{}", &hex::encode(digest)[..12])
+}
+
+/// A temp directory removed when dropped
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    name: String,
+}
+
+impl ScratchDir {
+    /// Anonymous scratch directory under the temp root
+    pub fn new() -> Result<Self> {
+        Self::for_job(&uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Scratch directory of `job_id` under the temp root
+    ///
+    /// Leftovers of an earlier run of the same job id are removed first.
+    pub fn for_job(job_id: &str) -> Result<Self> {
+        Self::create_in(&scratch_root(), job_id)
+    }
+
+    fn create_in(root: &Path, job_id: &str) -> Result<Self> {
+        let name = dir_name(job_id);
+        let path = root.join(&name);
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir_all(&path)?;
+        LIVE.lock().unwrap().get_or_insert_with(HashSet::new).insert(name.clone());
+        Ok(Self { path, name })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path for a file named after `name` in the directory
+    ///
+    /// Characters other than ASCII letters, digits and `.-_` become `_`, and
+    /// long names are cut to `MAX_TEMP_NAME_LEN` bytes, keeping the
+    /// extension.
+    ///
+    /// # Errors
+    /// `PathValidationFailed` if the path is still too long for the platform
+    pub fn file(&self, name: &str) -> Result<PathBuf> {
+        let path = self.path.join(safe_file_name(name));
+        check_path_lengths(&path, None)?;
+        Ok(path)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+        if let Some(live) = LIVE.lock().unwrap().as_mut() {
+            live.remove(&self.name);
+        }
+    }
+}
+
+/// Sanitized file name of at most `MAX_TEMP_NAME_LEN` bytes
+pub(crate) fn safe_file_name(name: &str) -> String {
+    let mut safe: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    if safe.is_empty() || safe.chars().all(|c| c == '.') {
+        safe = "file".to_string();
+    }
+    if safe.len() <= MAX_TEMP_NAME_LEN {
+        return safe;
+    }
+
+    let extension = match safe.rfind('.') {
+        Some(dot) if dot > 0 && safe.len() - dot <= 16 => safe[dot..].to_string(),
+        _ => String::new(),
+    };
+    let stem_len = MAX_TEMP_NAME_LEN - extension.len();
+    format!("{}{}", &safe[..stem_len], extension)
+}
+
+/// Remove scratch directories this process doesn't hold, left behind by a
+/// process that was killed
+///
+/// # Returns
+/// Number of directories removed
+pub fn remove_orphaned_temp_dirs() -> Result<usize> {
+    remove_orphaned_in(&scratch_root())
+}
+
+fn remove_orphaned_in(root: &Path) -> Result<usize> {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let live = LIVE.lock().unwrap().clone().unwrap_or_default();
+
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if live.contains(&name) {
+            continue;
+        }
+        let result = if entry.file_type()?.is_dir() {
+            std::fs::remove_dir_all(entry.path())
+        } else {
+            std::fs::remove_file(entry.path())
+        };
+        if result.is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scratch_dir_lifecycle() {
+        let root = TempDir::new().unwrap();
+        let scratch = ScratchDir::create_in(root.path(), "sync-2025-03-01T12:00:00.000Z-library").unwrap();
+        let path = scratch.path().to_path_buf();
+        assert!(path.is_dir());
+        assert_eq!(path.file_name().unwrap().len(), 13);
+
+        // Crash leftovers go, directories in use stay
+        let orphan = root.path().join(dir_name("killed-job"));
+        std::fs::create_dir_all(orphan.join("nested")).unwrap();
+        assert_eq!(remove_orphaned_in(root.path()).unwrap(), 1);
+        assert!(!orphan.exists());
+        assert!(path.is_dir());
+
+        std::fs::write(scratch.file("concat.txt").unwrap(), b"file 'a.m4b'").unwrap();
+        drop(scratch);
+        assert!(!path.exists());
+        assert_eq!(remove_orphaned_in(&root.path().join("missing")).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("Project Hail Mary: Part 1.m4b"), "Project_Hail_Mary__Part_1.m4b");
+        assert_eq!(safe_file_name(".."), "file");
+
+        let long = format!("{}.ffmetadata", "a".repeat(200));
+        let safe = safe_file_name(&long);
+        assert_eq!(safe.len(), MAX_TEMP_NAME_LEN);
+        assert!(safe.ends_with(".ffmetadata"));
+    }
+}
@@ -0,0 +1,442 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Auto-import of files dropped into a watch folder
+//!
+//! Some users download AAX files on a desktop and sync them to the phone.
+//! The app can point a watch folder at the synced directory and scan it on
+//! start or from a scheduled job. Each scan:
+//! 1. Finds AAX/AAXC/M4B/M4A files (hidden files are ignored; files modified
+//!    within `min_file_age` are left for the next scan, as they may still be
+//!    syncing)
+//! 2. Reads the embedded ASIN natively and matches it against the library
+//! 3. Moves M4B/M4A files into the library structure, or decrypts AAX files
+//!    there when activation bytes are available, removing the source
+//! 4. Records the new file as the book's download, with a liberation
+//!    receipt (see `storage::receipts`)
+//!
+//! Files that can't be imported stay in the folder and are reported with the
+//! reason, so they can be fixed and picked up by a later scan. A cancelled
+//! scan (`with_cancellation`) stops between files; files already imported
+//! stay imported.
+
+use crate::audio::probe::read_embedded_asin;
+use crate::cancel::CancellationToken;
+use crate::crypto::aax::AaxDecrypter;
+use crate::crypto::activation::ActivationBytes;
+use crate::error::{LibationError, Result};
+use crate::file::paths::{build_unique_file_path, CollisionStrategy, NamingPattern};
+use crate::storage::queries::{find_book_with_relations_by_asin, get_book_file_path};
+use crate::storage::receipts::{record_liberation, DecryptMethod, InputSource, NewReceipt, ReceiptFile, Toolchain};
+use crate::storage::settings::{delete_setting, get_setting, set_setting};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const KEY_PATH: &str = "watch_folder.path";
+
+/// Extensions picked up by a scan
+const WATCHED_EXTENSIONS: [&str; 4] = ["aax", "aaxc", "m4b", "m4a"];
+
+/// Files modified more recently than this are assumed to be still syncing
+pub const DEFAULT_MIN_FILE_AGE: Duration = Duration::from_secs(60);
+
+/// Configured watch folder, if any
+pub async fn get_watch_folder(pool: &SqlitePool) -> Result<Option<String>> {
+    get_setting(pool, KEY_PATH).await
+}
+
+/// Set or clear (None) the watch folder
+///
+/// # Errors
+/// - InvalidInput if the path isn't an existing directory
+pub async fn set_watch_folder(pool: &SqlitePool, path: Option<&str>) -> Result<()> {
+    match path {
+        Some(path) => {
+            if !Path::new(path).is_dir() {
+                return Err(LibationError::invalid_input(format!(
+                    "Watch folder is not a directory: {}",
+                    path
+                )));
+            }
+            set_setting(pool, KEY_PATH, path).await
+        }
+        None => delete_setting(pool, KEY_PATH).await,
+    }
+}
+
+/// How an imported file reached the library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    /// Already DRM-free; moved as is
+    Moved,
+    /// AAX decrypted to M4B
+    Liberated,
+}
+
+/// File moved into the library
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedFile {
+    pub source_path: String,
+    pub asin: String,
+    pub title: String,
+    pub output_path: String,
+    pub action: ImportAction,
+}
+
+/// File left in the watch folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    /// Embedded ASIN, if one was read
+    pub asin: Option<String>,
+    pub reason: String,
+}
+
+/// Result of scanning the watch folder
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchImportReport {
+    pub imported: Vec<ImportedFile>,
+    pub skipped: Vec<SkippedFile>,
+    /// Files too recently modified to import yet
+    pub pending: Vec<String>,
+}
+
+/// Imports files from a watch folder into the library
+pub struct WatchFolderImporter<'a> {
+    pool: &'a SqlitePool,
+    library_dir: PathBuf,
+    naming_pattern: NamingPattern,
+    collision_strategy: CollisionStrategy,
+    activation_bytes: Option<ActivationBytes>,
+    min_file_age: Duration,
+    cancel: CancellationToken,
+}
+
+impl<'a> WatchFolderImporter<'a> {
+    pub fn new(pool: &'a SqlitePool, library_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            pool,
+            library_dir: library_dir.into(),
+            naming_pattern: NamingPattern::AuthorSeriesBook,
+            collision_strategy: CollisionStrategy::default(),
+            activation_bytes: None,
+            min_file_age: DEFAULT_MIN_FILE_AGE,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    pub fn with_naming_pattern(mut self, pattern: NamingPattern) -> Self {
+        self.naming_pattern = pattern;
+        self
+    }
+
+    pub fn with_collision_strategy(mut self, strategy: CollisionStrategy) -> Self {
+        self.collision_strategy = strategy;
+        self
+    }
+
+    /// Activation bytes for liberating AAX files; without them AAX files are skipped
+    pub fn with_activation_bytes(mut self, activation_bytes: ActivationBytes) -> Self {
+        self.activation_bytes = Some(activation_bytes);
+        self
+    }
+
+    pub fn with_min_file_age(mut self, age: Duration) -> Self {
+        self.min_file_age = age;
+        self
+    }
+
+    /// Stop between files when `cancel` is cancelled
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Scan `folder` (recursively) and import what can be matched
+    ///
+    /// # Errors
+    /// - FileNotFound if `folder` doesn't exist
+    /// - Cancelled if cancelled before all files were handled
+    /// - Database errors; per-file failures are reported as skipped instead
+    pub async fn scan(&self, folder: &Path) -> Result<WatchImportReport> {
+        if !folder.is_dir() {
+            return Err(LibationError::FileNotFound(format!(
+                "Watch folder not found: {}",
+                folder.display()
+            )));
+        }
+
+        let mut candidates = Vec::new();
+        collect_candidates(folder, &mut candidates)?;
+        candidates.sort();
+
+        let mut report = WatchImportReport::default();
+        let now = crate::clock::now();
+
+        for path in candidates {
+            self.cancel.check()?;
+            let modified = std::fs::metadata(&path)?.modified()?;
+            let age = (now - chrono::DateTime::<chrono::Utc>::from(modified))
+                .to_std()
+                .unwrap_or_default();
+            if age < self.min_file_age {
+                report.pending.push(path.display().to_string());
+                continue;
+            }
+
+            let asin = match read_embedded_asin(&path).await {
+                Ok(Some(asin)) => asin,
+                Ok(None) => {
+                    report.skipped.push(skipped(&path, None, "No embedded ASIN"));
+                    continue;
+                }
+                Err(e) => {
+                    report.skipped.push(skipped(&path, None, e.to_string()));
+                    continue;
+                }
+            };
+
+            match self.import_file(&path, &asin).await {
+                Ok(imported) => report.imported.push(imported),
+                Err(e @ LibationError::SqlxError(_)) => return Err(e),
+                Err(e) => report.skipped.push(skipped(&path, Some(asin), e.to_string())),
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn import_file(&self, path: &Path, asin: &str) -> Result<ImportedFile> {
+        let book = find_book_with_relations_by_asin(self.pool, asin)
+            .await?
+            .ok_or_else(|| LibationError::not_found(format!("Not in library: {}", asin)))?;
+
+        if let Some(existing) = get_book_file_path(self.pool, asin).await? {
+            if Path::new(&existing).exists() {
+                return Err(LibationError::InvalidState(format!("Already downloaded to {}", existing)));
+            }
+        }
+
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let (output_extension, action) = match extension.as_str() {
+            "aax" => ("m4b", ImportAction::Liberated),
+            "aaxc" => {
+                return Err(LibationError::invalid_input(
+                    "AAXC files need a license voucher; download the title from Audible instead",
+                ))
+            }
+            other => (other, ImportAction::Moved),
+        };
+
+        let relative = build_unique_file_path(
+            &book.to_audio_metadata(),
+            self.naming_pattern,
+            output_extension,
+            self.collision_strategy,
+            &[],
+            Some(&self.library_dir),
+        )?;
+        let output = self.library_dir.join(relative);
+        if let Some(parent) = output.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let started = Instant::now();
+        let decrypt_method = match action {
+            ImportAction::Moved => {
+                move_file(path, &output).await?;
+                DecryptMethod::None
+            }
+            ImportAction::Liberated => {
+                let activation_bytes = self
+                    .activation_bytes
+                    .ok_or_else(|| LibationError::invalid_input("Activation bytes are required to liberate AAX files"))?;
+                let partial = output.with_extension("m4b.partial");
+                if let Err(e) = AaxDecrypter::new(activation_bytes).decrypt_file(path, &partial).await {
+                    let _ = tokio::fs::remove_file(&partial).await;
+                    return Err(e);
+                }
+                tokio::fs::rename(&partial, &output).await?;
+                tokio::fs::remove_file(path).await?;
+                DecryptMethod::Aax
+            }
+        };
+        let decrypt_ms = started.elapsed().as_millis() as i64;
+
+        let output_path = output.display().to_string();
+        let receipt = NewReceipt {
+            asin: asin.to_string(),
+            task_id: None,
+            source: InputSource::WatchFolder,
+            source_ref: Some(path.display().to_string()),
+            license_id: None,
+            decrypt_method,
+            files: vec![ReceiptFile::hash(&output, output_path.as_str()).await?],
+            download_ms: None,
+            decrypt_ms: Some(decrypt_ms),
+            toolchain: Toolchain::with_ffmpeg(None),
+        };
+        let receipt = record_liberation(self.pool, &book.title, &receipt).await?;
+        let report = crate::file::post_hooks::run_post_hooks(self.pool, receipt).await?;
+
+        Ok(ImportedFile {
+            source_path: path.display().to_string(),
+            asin: asin.to_string(),
+            title: book.title,
+            output_path: report.receipt.files[0].location.clone(),
+            action,
+        })
+    }
+}
+
+fn skipped(path: &Path, asin: Option<String>, reason: impl Into<String>) -> SkippedFile {
+    SkippedFile {
+        path: path.display().to_string(),
+        asin,
+        reason: reason.into(),
+    }
+}
+
+/// Watched audio files under `dir`, skipping hidden files and directories
+fn collect_candidates(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_candidates(&path, files)?;
+        } else if file_type.is_file()
+            && path
+                .extension()
+                .is_some_and(|e| WATCHED_EXTENSIONS.iter().any(|w| e.eq_ignore_ascii_case(w)))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Rename, or copy and delete when the folder is on another filesystem
+pub(crate) async fn move_file(source: &Path, destination: &Path) -> Result<()> {
+    if tokio::fs::rename(source, destination).await.is_ok() {
+        return Ok(());
+    }
+
+    if let Err(e) = tokio::fs::copy(source, destination).await {
+        let _ = tokio::fs::remove_file(destination).await;
+        return Err(LibationError::FileIoError(format!(
+            "Failed to copy {} to {}: {}",
+            source.display(),
+            destination.display(),
+            e
+        )));
+    }
+    tokio::fs::remove_file(source).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Database, NewBook};
+
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&((body.len() + 8) as u32).to_be_bytes()[..], kind, body].concat()
+    }
+
+    /// Minimal MP4 tagged with an Audible `CDEK` ASIN item
+    fn tagged_mp4(asin: Option<&str>) -> Vec<u8> {
+        let ilst = match asin {
+            Some(asin) => atom(b"ilst", &atom(b"CDEK", &atom(b"data", &[&[0u8, 0, 0, 1, 0, 0, 0, 0][..], asin.as_bytes()].concat()))),
+            None => atom(b"ilst", &[]),
+        };
+        let meta = atom(b"meta", &[&[0u8; 4][..], &ilst].concat());
+        [atom(b"ftyp", b"M4B \0\0\0\0"), atom(b"moov", &atom(b"udta", &meta)), atom(b"mdat", &[0u8; 16])].concat()
+    }
+
+    #[tokio::test]
+    async fn test_scan_watch_folder() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        for asin in ["B0WATCH001", "B0WATCH002"] {
+            let book = NewBook::new(asin.to_string(), format!("Book {}", asin), "us".to_string());
+            crate::storage::queries::insert_book(pool, &book).await.unwrap();
+        }
+
+        let watch = tempfile::tempdir().unwrap();
+        let library = tempfile::tempdir().unwrap();
+        let nested = watch.path().join("Desktop sync");
+        std::fs::create_dir(&nested).unwrap();
+        std::fs::write(nested.join("one.m4b"), tagged_mp4(Some("B0WATCH001"))).unwrap();
+        std::fs::write(watch.path().join("two.aax"), tagged_mp4(Some("B0WATCH002"))).unwrap();
+        std::fs::write(watch.path().join("stranger.m4b"), tagged_mp4(Some("B0UNKNOWN"))).unwrap();
+        std::fs::write(watch.path().join("untagged.m4b"), tagged_mp4(None)).unwrap();
+        std::fs::write(watch.path().join(".one.m4b.syncing"), b"partial").unwrap();
+        std::fs::write(watch.path().join("notes.txt"), b"not audio").unwrap();
+
+        set_watch_folder(pool, Some(&watch.path().display().to_string())).await.unwrap();
+        let folder = get_watch_folder(pool).await.unwrap().unwrap();
+        assert!(set_watch_folder(pool, Some("/no/such/folder")).await.is_err());
+
+        // Freshly written files wait until they stop changing
+        let importer = WatchFolderImporter::new(pool, library.path()).with_naming_pattern(NamingPattern::FlatFile);
+        let report = importer.scan(Path::new(&folder)).await.unwrap();
+        assert_eq!(report.pending.len(), 4);
+        assert!(report.imported.is_empty() && report.skipped.is_empty());
+
+        let importer = importer.with_min_file_age(Duration::ZERO);
+        let report = importer.scan(Path::new(&folder)).await.unwrap();
+        assert_eq!(report.imported.len(), 1);
+        let imported = &report.imported[0];
+        assert_eq!((imported.asin.as_str(), imported.action), ("B0WATCH001", ImportAction::Moved));
+        assert_eq!(imported.output_path, library.path().join("Book B0WATCH001.m4b").display().to_string());
+        assert!(Path::new(&imported.output_path).exists() && !nested.join("one.m4b").exists());
+        assert_eq!(
+            get_book_file_path(pool, "B0WATCH001").await.unwrap().as_deref(),
+            Some(imported.output_path.as_str())
+        );
+
+        let reasons: Vec<(&str, Option<&str>)> = report
+            .skipped
+            .iter()
+            .map(|s| (Path::new(&s.path).file_name().unwrap().to_str().unwrap(), s.asin.as_deref()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![("stranger.m4b", Some("B0UNKNOWN")), ("two.aax", Some("B0WATCH002")), ("untagged.m4b", None)]
+        );
+        assert!(report.skipped[1].reason.contains("Activation bytes"));
+
+        // A second copy of an imported book is left alone
+        std::fs::write(watch.path().join("again.m4b"), tagged_mp4(Some("B0WATCH001"))).unwrap();
+        let report = importer.scan(Path::new(&folder)).await.unwrap();
+        assert!(report.imported.is_empty());
+        assert!(report.skipped[0].reason.contains("Already downloaded"));
+
+        set_watch_folder(pool, None).await.unwrap();
+        assert_eq!(get_watch_folder(pool).await.unwrap(), None);
+    }
+}
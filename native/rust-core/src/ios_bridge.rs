@@ -39,6 +39,7 @@
 //! ```json
 //! {
 //!   "success": true,
+//!   "protocol_version": 2,
 //!   "data": { ... }
 //! }
 //! ```
@@ -46,9 +47,12 @@
 //! ```json
 //! {
 //!   "success": false,
+//!   "protocol_version": 2,
 //!   "error": "Error message"
 //! }
 //! ```
+//! Older callers can ask for an earlier response shape through
+//! `rust_get_bridge_info` (see `bridge_protocol`).
 //!
 //! # Memory Management
 //! **CRITICAL**: All string pointers returned from Rust functions MUST be freed
@@ -111,31 +115,19 @@ fn string_to_c_str(s: String) -> *mut c_char {
 /// Convert Rust result to JSON response string
 fn result_to_json<T: Serialize>(result: crate::Result<T>) -> String {
     match result {
-        Ok(data) => serde_json::json!({
-            "success": true,
-            "data": data
-        }).to_string(),
-        Err(e) => serde_json::json!({
-            "success": false,
-            "error": e.to_string()
-        }).to_string(),
+        Ok(data) => success_response(data),
+        Err(e) => error_response(&e.to_string()),
     }
 }
 
-/// Create success response JSON
+/// Create success response JSON (see `bridge_protocol`)
 fn success_response<T: Serialize>(data: T) -> String {
-    serde_json::json!({
-        "success": true,
-        "data": data
-    }).to_string()
+    crate::bridge_protocol::success_envelope(data)
 }
 
-/// Create error response JSON
+/// Create error response JSON (see `bridge_protocol`)
 fn error_response(error: &str) -> String {
-    serde_json::json!({
-        "success": false,
-        "error": error
-    }).to_string()
+    crate::bridge_protocol::error_envelope(error)
 }
 
 /// Wrap a function call with panic catching
@@ -496,6 +488,11 @@ pub extern "C" fn rust_get_activation_bytes(
 
 /// Initialize database at specified path
 ///
+/// Checks integrity first and repairs or rebuilds a damaged database,
+/// quarantining the damaged file. When `recovery.data_lost` is true the app
+/// should offer to restore from a backup. Tokens from a refresh that was
+/// interrupted before they were saved are merged into their accounts.
+///
 /// # Arguments
 /// * `db_path` - Absolute path to SQLite database file
 ///
@@ -505,7 +502,16 @@ pub extern "C" fn rust_get_activation_bytes(
 /// {
 ///   "success": true,
 ///   "data": {
-///     "initialized": true
+///     "initialized": true,
+///     "recovery": {
+///       "database_path": "...",
+///       "outcome": "healthy",     // "reindexed", "rebuilt" or "recreated"
+///       "integrity_errors": [],
+///       "quarantined_path": null,
+///       "tables": [],             // per-table rows salvaged when rebuilt
+///       "data_lost": false
+///     },
+///     "recovered_token_refreshes": ["user@example.com"]  // accounts whose interrupted refresh was saved
 ///   }
 /// }
 /// ```
@@ -518,10 +524,15 @@ pub extern "C" fn rust_init_database(db_path: *const c_char) -> *mut c_char {
         let db_path = c_str_to_string(db_path)?;
 
         let result = RUNTIME.block_on(async {
-            let _db = crate::storage::Database::new(&db_path).await?;
+            let (db, recovery) = crate::storage::Database::open_with_recovery(&db_path).await?;
+            db.run_startup_tasks().await?;
+            let recovered_token_refreshes =
+                crate::storage::accounts::recover_pending_token_refreshes(db.pool()).await?;
 
             let response = serde_json::json!({
                 "initialized": true,
+                "recovery": recovery,
+                "recovered_token_refreshes": recovered_token_refreshes,
             });
 
             Ok::<_, crate::LibationError>(response)
@@ -730,10 +741,12 @@ pub extern "C" fn rust_search_books(
 
         let result = RUNTIME.block_on(async {
             let db = crate::storage::Database::new(&db_path).await?;
+            let filter = crate::storage::content_filter::get_content_filter(db.pool()).await?;
             let books = crate::storage::queries::search_books_by_title(
                 db.pool(),
                 &query,
                 50, // Default limit
+                filter.active_exclusions(),
             ).await?;
 
             let response = serde_json::json!({
@@ -749,6 +762,56 @@ pub extern "C" fn rust_search_books(
     string_to_c_str(response)
 }
 
+/// Where to resume playback of a book
+///
+/// Same rules as on Android (see `crate::audio::resume`): the stored
+/// position stepped back by the rewind option, never past the start of its
+/// chapter.
+///
+/// # Arguments
+/// * `db_path` - Absolute path to SQLite database file
+/// * `asin` - Book ASIN
+/// * `rewind_ms` - Rewind for this call, or a negative value for the stored option
+///
+/// # Returns
+/// JSON string with format:
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "position_ms": 3590000,
+///     "stored_position_ms": 3600000,
+///     "chapter_index": 4,
+///     "chapter_title": "Chapter 4"
+///   }
+/// }
+/// ```
+///
+/// # Safety
+/// Caller must free the returned string with `rust_free_string()`
+#[no_mangle]
+pub extern "C" fn rust_get_resume_position(
+    db_path: *const c_char,
+    asin: *const c_char,
+    rewind_ms: i64,
+) -> *mut c_char {
+    let _call = crate::bridge_schema::enter_call("nativeGetResumePosition");
+    let response = catch_panic(|| {
+        let db_path = c_str_to_string(db_path)?;
+        let asin = c_str_to_string(asin)?;
+        let rewind_ms = (rewind_ms >= 0).then_some(rewind_ms);
+
+        let result = RUNTIME.block_on(async {
+            let db = crate::storage::Database::new(&db_path).await?;
+            crate::audio::resume::resume_point(db.pool(), &asin, rewind_ms).await
+        });
+
+        Ok(result_to_json(result))
+    });
+
+    string_to_c_str(response)
+}
+
 // ============================================================================
 // DOWNLOAD/DECRYPT FUNCTIONS
 // ============================================================================
@@ -801,7 +864,8 @@ pub extern "C" fn rust_download_book(
 ///   "success": true,
 ///   "data": {
 ///     "output_path": "/path/to/book.m4b",
-///     "file_size": 123456789
+///     "file_size": 123456789,
+///     "bytes_per_second": 250000000
 ///   }
 /// }
 /// ```
@@ -827,7 +891,9 @@ pub extern "C" fn rust_decrypt_aax(
             let input_path = std::path::Path::new(&input_path);
             let output_path = std::path::Path::new(&output_path);
 
-            decrypter.decrypt_file(input_path, output_path).await?;
+            let stats = decrypter
+                .decrypt_with_stats(input_path, output_path, |_| {})
+                .await?;
 
             let file_size = tokio::fs::metadata(output_path)
                 .await
@@ -837,6 +903,63 @@ pub extern "C" fn rust_decrypt_aax(
             let response = serde_json::json!({
                 "output_path": output_path.to_string_lossy(),
                 "file_size": file_size,
+                "bytes_per_second": stats.bytes_per_second,
+            });
+
+            Ok::<_, crate::LibationError>(response)
+        })?;
+
+        Ok(success_response(result))
+    });
+
+    string_to_c_str(response)
+}
+
+/// Decrypt AAXC file to M4B using the key and IV from its license
+///
+/// # Arguments
+/// * `input_path` - Absolute path to input AAXC file
+/// * `output_path` - Absolute path where M4B file should be saved
+/// * `aaxc_key` - 32-character hex key from the license voucher
+/// * `aaxc_iv` - 32-character hex IV from the license voucher
+///
+/// # Returns
+/// JSON string with the same format as `rust_decrypt_aax`
+///
+/// # Safety
+/// Caller must free the returned string with `rust_free_string()`
+#[no_mangle]
+pub extern "C" fn rust_decrypt_aaxc(
+    input_path: *const c_char,
+    output_path: *const c_char,
+    aaxc_key: *const c_char,
+    aaxc_iv: *const c_char,
+) -> *mut c_char {
+    let response = catch_panic(|| {
+        let input_path = c_str_to_string(input_path)?;
+        let output_path = c_str_to_string(output_path)?;
+        let aaxc_key = c_str_to_string(aaxc_key)?;
+        let aaxc_iv = c_str_to_string(aaxc_iv)?;
+
+        let decrypter = crate::crypto::aaxc::AaxcDecrypter::from_hex(&aaxc_key, &aaxc_iv)?;
+
+        let result = RUNTIME.block_on(async {
+            let input_path = std::path::Path::new(&input_path);
+            let output_path = std::path::Path::new(&output_path);
+
+            let stats = decrypter
+                .decrypt_with_stats(input_path, output_path, |_| {})
+                .await?;
+
+            let file_size = tokio::fs::metadata(output_path)
+                .await
+                .map(|m| m.len())
+                .unwrap_or(0);
+
+            let response = serde_json::json!({
+                "output_path": output_path.to_string_lossy(),
+                "file_size": file_size,
+                "bytes_per_second": stats.bytes_per_second,
             });
 
             Ok::<_, crate::LibationError>(response)
@@ -920,6 +1043,184 @@ pub extern "C" fn rust_get_supported_locales() -> *mut c_char {
     string_to_c_str(response)
 }
 
+/// Get the bridge protocol versions and capabilities
+///
+/// Call at startup with the protocol version the JS layer was written
+/// against; all later responses are shaped for that version (see
+/// `bridge_protocol`).
+///
+/// # Arguments
+/// * `client_protocol_version` - Version the caller speaks, or 0 to leave
+///   the negotiated version unchanged
+///
+/// # Returns
+/// JSON string with format:
+/// ```json
+/// {
+///   "success": true,
+///   "protocol_version": 1,
+///   "data": {
+///     "protocol_version": 2,
+///     "min_protocol_version": 1,
+///     "negotiated_protocol_version": 1,
+///     "core_version": "0.0.1",
+///     "capabilities": ["batch_write", "cdn_mirrors", ...]
+///   }
+/// }
+/// ```
+///
+/// # Safety
+/// Caller must free the returned string with `rust_free_string()`
+#[no_mangle]
+pub extern "C" fn rust_get_bridge_info(client_protocol_version: u32) -> *mut c_char {
+    let _call = crate::bridge_schema::enter_call("nativeGetBridgeInfo");
+    let response = catch_panic(|| {
+        if client_protocol_version != 0 {
+            crate::bridge_protocol::negotiate(client_protocol_version)?;
+        }
+
+        Ok(success_response(crate::bridge_protocol::bridge_info()))
+    });
+
+    string_to_c_str(response)
+}
+
+// ============================================================================
+// WORK ACTIVITY
+// ============================================================================
+
+/// Forwards work activity changes to a C callback
+struct CallbackActivityListener(extern "C" fn(*const c_char));
+
+impl crate::activity::WorkActivityListener for CallbackActivityListener {
+    fn on_activity_changed(&self, activity: &crate::activity::WorkActivity) {
+        if let Ok(Ok(json)) = serde_json::to_string(activity).map(CString::new) {
+            (self.0)(json.as_ptr());
+        }
+    }
+}
+
+/// Register the callback for long-running work
+///
+/// The callback receives the work activity JSON whenever work starts or
+/// stops, so the app can begin a background task while work is active and
+/// end it when Rust reports idle. It is called right away with the current
+/// state and may be called from any thread. The string is only valid during
+/// the call and must not be freed.
+///
+/// # Arguments
+/// * `callback` - Callback, or NULL to unregister
+///
+/// # Returns
+/// JSON string with format:
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "active": true,
+///     "work_types": ["download"],
+///     "job_count": 1,
+///     "expected_remaining_secs": 340,
+///     "trace_ids": []
+///   }
+/// }
+/// ```
+///
+/// # Safety
+/// Caller must free the returned string with `rust_free_string()`
+#[no_mangle]
+pub extern "C" fn rust_set_work_activity_callback(
+    callback: Option<extern "C" fn(*const c_char)>,
+) -> *mut c_char {
+    let response = catch_panic(|| {
+        crate::activity::set_work_activity_listener(callback.map(|cb| {
+            std::sync::Arc::new(CallbackActivityListener(cb))
+                as std::sync::Arc<dyn crate::activity::WorkActivityListener>
+        }));
+
+        Ok(success_response(crate::activity::current_activity()))
+    });
+
+    string_to_c_str(response)
+}
+
+/// Get the current long-running work state
+///
+/// # Returns
+/// Same JSON as `rust_set_work_activity_callback`
+///
+/// # Safety
+/// Caller must free the returned string with `rust_free_string()`
+#[no_mangle]
+pub extern "C" fn rust_get_work_activity() -> *mut c_char {
+    let _call = crate::bridge_schema::enter_call("nativeGetWorkActivity");
+    let response = catch_panic(|| Ok(success_response(crate::activity::current_activity())));
+
+    string_to_c_str(response)
+}
+
+/// Get the latest progress of each running operation
+///
+/// # Returns
+/// `{ "progress": [...] }`, progress events as described at
+/// `nativeSetWorkEventListener` in the Android bridge
+///
+/// # Safety
+/// Caller must free the returned string with `rust_free_string()`
+#[no_mangle]
+pub extern "C" fn rust_get_progress() -> *mut c_char {
+    let _call = crate::bridge_schema::enter_call("nativeGetProgress");
+    let response = catch_panic(|| {
+        Ok(success_response(serde_json::json!({ "progress": crate::events::current_progress() })))
+    });
+
+    string_to_c_str(response)
+}
+
+// ============================================================================
+// WORK EVENTS
+// ============================================================================
+
+/// Forwards work events to a C callback
+struct CallbackWorkEventListener(extern "C" fn(*const c_char));
+
+impl crate::events::WorkEventListener for CallbackWorkEventListener {
+    fn on_work_event(&self, event: &crate::events::WorkEvent) {
+        if let Ok(Ok(json)) = serde_json::to_string(event).map(CString::new) {
+            (self.0)(json.as_ptr());
+        }
+    }
+}
+
+/// Register the callback for finished work
+///
+/// The callback receives the work event JSON when a job ends or a download
+/// completes or fails, with `severity`, `category` and a `suppressed` flag
+/// computed from the quiet hours settings (see `crate::events`). It may be
+/// called from any thread. The string is only valid during the call and
+/// must not be freed.
+///
+/// # Arguments
+/// * `callback` - Callback, or NULL to unregister
+///
+/// # Safety
+/// Caller must free the returned string with `rust_free_string()`
+#[no_mangle]
+pub extern "C" fn rust_set_work_event_callback(
+    callback: Option<extern "C" fn(*const c_char)>,
+) -> *mut c_char {
+    let response = catch_panic(|| {
+        crate::events::set_work_event_listener(callback.map(|cb| {
+            std::sync::Arc::new(CallbackWorkEventListener(cb))
+                as std::sync::Arc<dyn crate::events::WorkEventListener>
+        }));
+
+        Ok(success_response(serde_json::json!({})))
+    });
+
+    string_to_c_str(response)
+}
+
 // ============================================================================
 // MEMORY MANAGEMENT
 // ============================================================================
@@ -31,12 +31,16 @@
 //! 2. **Error Handling**: All errors are caught and returned as JSON error responses
 //! 3. **Async Runtime**: Tokio runtime is used to execute async Rust functions
 //! 4. **No Panics**: All panics are caught to prevent crashes across FFI boundary
+//! 5. **Tracing**: Every JSON params object may carry a `trace_id`, which
+//!    prefixes Rust log lines, is echoed in error responses and follows
+//!    downloads started by the call (see `trace`)
 //!
 //! # Response Format
 //! All functions return JSON strings with this structure:
 //! ```json
 //! {
 //!   "success": true,
+//!   "protocol_version": 2,
 //!   "data": { ... }
 //! }
 //! ```
@@ -44,9 +48,12 @@
 //! ```json
 //! {
 //!   "success": false,
+//!   "protocol_version": 2,
 //!   "error": "Error message"
 //! }
 //! ```
+//! Older JS can ask for an earlier response shape through
+//! `nativeGetBridgeInfo` (see `bridge_protocol`).
 
 use jni::objects::{JClass, JString};
 use jni::sys::jstring;
@@ -65,15 +72,44 @@ lazy_static::lazy_static! {
     // Global download manager cache (db_path -> manager instance)
     static ref DOWNLOAD_MANAGERS: Mutex<HashMap<String, std::sync::Arc<crate::download::PersistentDownloadManager>>> =
         Mutex::new(HashMap::new());
+
+    // Serializes manager creation so two calls don't build one each
+    static ref DOWNLOAD_MANAGER_INIT: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+
+    // Latest device conditions and adaptive policy reported by the app
+    static ref DEVICE_STATE: Mutex<(crate::download::DeviceConditions, crate::download::AdaptivePolicy)> =
+        Mutex::new(Default::default());
+
+    // When finished downloads may be decrypted (two-phase liberation)
+    static ref CONVERSION_POLICY: Mutex<crate::download::ConversionPolicy> =
+        Mutex::new(Default::default());
+
+    // Write buffering of download streams
+    static ref BUFFER_POLICY: Mutex<crate::download::BufferPolicy> =
+        Mutex::new(Default::default());
+
+    // Latest network state reported by the app (Wi-Fi-only downloads)
+    static ref NETWORK_STATE: Mutex<crate::download::NetworkState> =
+        Mutex::new(Default::default());
+
+    // Test-mode clock installed by nativeSetTestClock
+    static ref TEST_CLOCK: Mutex<Option<std::sync::Arc<crate::clock::TestClock>>> = Mutex::new(None);
+
+    // Progress of the running integrity check, for polling
+    static ref INTEGRITY_PROGRESS: Mutex<Option<crate::file::integrity::IntegrityProgress>> = Mutex::new(None);
 }
 
+// Progress of the running handoff, for polling
+#[cfg(feature = "lan-handoff")]
+static HANDOFF_PROGRESS: Mutex<Option<crate::file::handoff::HandoffProgress>> = Mutex::new(None);
+
 /// Get or create a download manager for the given database path
 async fn get_or_create_manager(
     db_path: &str,
 ) -> crate::Result<std::sync::Arc<crate::download::PersistentDownloadManager>> {
-    let mut managers = DOWNLOAD_MANAGERS.lock().unwrap();
+    let _init = DOWNLOAD_MANAGER_INIT.lock().await;
 
-    if let Some(manager) = managers.get(db_path) {
+    if let Some(manager) = DOWNLOAD_MANAGERS.lock().unwrap().get(db_path) {
         return Ok(std::sync::Arc::clone(manager));
     }
 
@@ -83,13 +119,31 @@ async fn get_or_create_manager(
         std::sync::Arc::new(db.pool().clone()),
         3, // max concurrent downloads
     )
-    .await?;
+    .await?
+    .with_chunk_verification(crate::download::chunk_manifest::DEFAULT_CHUNK_SIZE);
+
+    // Apply the latest device conditions before anything starts
+    let (conditions, policy) = *DEVICE_STATE.lock().unwrap();
+    manager.apply_device_conditions(&conditions, &policy).await?;
+    manager.set_conversion_policy(*CONVERSION_POLICY.lock().unwrap());
+    manager.set_buffer_policy(*BUFFER_POLICY.lock().unwrap())?;
+    let network_state = *NETWORK_STATE.lock().unwrap();
+    manager.set_network_state(network_state).await?;
 
     // On fresh process start, mark stuck conversion tasks as failed
     manager.resume_all_pending().await?;
 
+    // Adopt partial downloads left by the pre-queue downloader so an app
+    // update doesn't throw them away; never block startup on this
+    if let Err(e) = manager.import_legacy_downloads(&legacy_download_dir()).await {
+        crate::trace::trace_eprintln!("⚠️  Legacy download import failed: {}", e);
+    }
+
     let manager_arc = std::sync::Arc::new(manager);
-    managers.insert(db_path.to_string(), std::sync::Arc::clone(&manager_arc));
+    DOWNLOAD_MANAGERS
+        .lock()
+        .unwrap()
+        .insert(db_path.to_string(), std::sync::Arc::clone(&manager_arc));
 
     Ok(manager_arc)
 }
@@ -98,6 +152,11 @@ async fn get_or_create_manager(
 // HELPER FUNCTIONS
 // ============================================================================
 
+/// Cache directory used by the legacy `nativeDownloadBook` downloader
+fn legacy_download_dir() -> std::path::PathBuf {
+    crate::file::temp::system_temp_dir().join("audiobooks")
+}
+
 /// Convert JString to Rust String
 fn jstring_to_string(env: &mut JNIEnv, jstr: JString) -> crate::Result<String> {
     env.get_string(&jstr).map(|s| s.into()).map_err(|e| {
@@ -105,38 +164,38 @@ fn jstring_to_string(env: &mut JNIEnv, jstr: JString) -> crate::Result<String> {
     })
 }
 
+/// Make the call's `trace_id` and `function` current until the returned
+/// scopes drop (see `trace` and `bridge_schema`)
+fn enter_trace(
+    function: &'static str,
+    params_str: &crate::Result<String>,
+) -> (crate::trace::TraceScope, crate::bridge_schema::CallScope) {
+    let trace_id = params_str.as_ref().ok().and_then(|params| crate::trace::trace_id_from_params(params));
+    (crate::trace::enter(trace_id), crate::bridge_schema::enter_call(function))
+}
+
 /// Convert Rust result to JSON response string
+#[cfg(test)]
 fn result_to_json<T: Serialize>(result: crate::Result<T>) -> String {
     match result {
-        Ok(data) => serde_json::json!({
-            "success": true,
-            "data": data
-        })
-        .to_string(),
-        Err(e) => serde_json::json!({
-            "success": false,
-            "error": e.to_string()
-        })
-        .to_string(),
+        Ok(data) => success_response(data),
+        Err(e) => error_response(&e.to_string()),
     }
 }
 
-/// Create success response JSON
+/// Create success response JSON (see `bridge_protocol`)
 fn success_response<T: Serialize>(data: T) -> String {
-    serde_json::json!({
-        "success": true,
-        "data": data
-    })
-    .to_string()
+    crate::bridge_protocol::success_envelope(data)
 }
 
-/// Create error response JSON
+/// Create error response JSON (see `bridge_protocol`)
 fn error_response(error: &str) -> String {
-    serde_json::json!({
-        "success": false,
-        "error": error
-    })
-    .to_string()
+    crate::bridge_protocol::error_envelope(error)
+}
+
+/// Create error response JSON with structured details (see `bridge_protocol`)
+fn error_response_with_details<T: Serialize>(error: &str, details: T) -> String {
+    crate::bridge_protocol::error_envelope_with_details(error, details)
 }
 
 /// Wrap a function call with panic catching
@@ -224,6 +283,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGenera
                 .into_raw();
         }
     };
+    let _trace = crate::trace::enter(crate::trace::trace_id_from_params(&params_str));
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -300,6 +360,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeParseO
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeParseOAuthCallback", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -370,6 +431,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeExchan
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeExchangeAuthCode", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -421,6 +483,147 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeExchan
         .into_raw()
 }
 
+/// Enable or disable the encrypted debug capture of registration responses
+///
+/// Off by default. Captures go to app-private storage only; tokens, keys
+/// and cookies are redacted unless `include_secrets` is set.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "enabled": true,
+///   "directory": "/data/data/.../files/debug_captures", // required when enabled
+///   "key": "00112233445566778899aabbccddeeff",          // 16-byte hex AES key, from the keystore
+///   "include_secrets": false                            // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "enabled": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetDebugCapture(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetDebugCapture", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            enabled: bool,
+            directory: Option<String>,
+            key: Option<String>,
+            #[serde(default)]
+            include_secrets: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            if !params.enabled {
+                crate::api::debug_capture::disable_debug_capture();
+                return Ok(success_response(serde_json::json!({ "enabled": false })));
+            }
+
+            let directory = params.directory.ok_or_else(|| {
+                crate::LibationError::InvalidInput("directory is required to enable capture".to_string())
+            })?;
+            let key: [u8; 16] = params
+                .key
+                .as_deref()
+                .and_then(|k| hex::decode(k).ok())
+                .and_then(|k| k.try_into().ok())
+                .ok_or_else(|| crate::LibationError::InvalidInput("key must be 16 bytes of hex".to_string()))?;
+
+            crate::api::debug_capture::enable_debug_capture(
+                std::path::Path::new(&directory),
+                key,
+                params.include_secrets,
+            )?;
+            Ok(success_response(serde_json::json!({ "enabled": true })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Enable or disable recording API exchanges for bug reports
+///
+/// Off by default. While on, a failed library sync or license request
+/// saves its sanitized requests and responses as a replay bundle
+/// (`*.replay.json`) in `directory`, which must be app-private.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "enabled": true,
+///   "directory": "/data/data/.../files/api_recordings" // required when enabled
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "enabled": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetApiRecording(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetApiRecording", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            enabled: bool,
+            directory: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            if !params.enabled {
+                crate::api::recording::disable_api_recording();
+                return Ok(success_response(serde_json::json!({ "enabled": false })));
+            }
+
+            let directory = params.directory.ok_or_else(|| {
+                crate::LibationError::InvalidInput("directory is required to enable recording".to_string())
+            })?;
+            crate::api::recording::enable_api_recording(std::path::Path::new(&directory))?;
+            Ok(success_response(serde_json::json!({ "enabled": true })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
 /// Refresh access token using refresh token
 ///
 /// # Arguments (JSON string)
@@ -451,6 +654,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRefres
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRefreshAccessToken", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -526,6 +730,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeEnsure
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeEnsureValidToken", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -624,6 +829,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAct
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetActivationBytes", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -677,10 +883,19 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAct
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
-///   "account_json": "{...}" // serialized Account object
+///   "account_json": "{...}", // serialized Account object
+///   "job_id": "sync-1" // optional, for nativeCancelJob and nativeGetJob
 /// }
 /// ```
 ///
+/// The account is checked first (see `nativeCheckSyncReadiness`), and its
+/// token refreshed if needed. A failed check ends the call before any page
+/// is fetched, with `details.stage: "preflight"`; errors during the sync
+/// have `details.stage: "sync"`. A cancelled sync keeps the books stored
+/// before the cancel and fails with a cancellation error. With API
+/// recording on (`nativeSetApiRecording`), a failed sync saves a replay
+/// bundle.
+///
 /// # Returns (JSON)
 /// ```json
 /// {
@@ -694,6 +909,15 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAct
 ///   }
 /// }
 /// ```
+///
+/// # Returns on a failed check (JSON)
+/// ```json
+/// {
+///   "success": false,
+///   "error": "Account not ready to sync: Unknown marketplace: \"xx\"",
+///   "details": { "stage": "preflight", "readiness": { "ready": false, "remediation": "choose_marketplace", ... } }
+/// }
+/// ```
 #[no_mangle]
 pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLibrary(
     mut env: JNIEnv,
@@ -701,12 +925,15 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSyncLibrary", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
             account_json: String,
+            #[serde(default)]
+            job_id: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -714,20 +941,41 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let account: crate::api::auth::Account = serde_json::from_str(&params.account_json)
-                .map_err(|e| {
-                    crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
-                })?;
-
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::LibrarySync)?;
             let result = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
 
+                let readiness = crate::api::preflight::check_sync_readiness(db.pool(), &params.account_json).await?;
+                if !readiness.ready {
+                    return Ok::<_, crate::LibationError>(Err(readiness));
+                }
+                let account = readiness.account()?;
+
                 let mut client = crate::api::client::AudibleClient::new(account.clone())?;
 
-                client.sync_library(&db, &account).await
+                let stats = crate::storage::jobs::run_job(
+                    db.pool(),
+                    &job,
+                    client.sync_library_cancellable(&db, &account, job.token()),
+                )
+                .await;
+                if let Err(e) = client.save_recording(crate::api::recording::ReplayOperation::LibrarySync, &stats) {
+                    crate::trace::trace_eprintln!("Failed to save API recording: {}", e);
+                }
+                Ok(Ok(stats))
             })?;
 
-            Ok(success_response(result))
+            match result {
+                Ok(Ok(stats)) => Ok(success_response(stats)),
+                Ok(Err(e)) => Ok(error_response_with_details(
+                    &e.to_string(),
+                    serde_json::json!({ "stage": "sync" }),
+                )),
+                Err(readiness) => Ok(error_response_with_details(
+                    &format!("Account not ready to sync: {}", readiness.message.as_deref().unwrap_or_default()),
+                    serde_json::json!({ "stage": "preflight", "readiness": readiness }),
+                )),
+            }
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -742,13 +990,20 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
 /// Synchronize a single page of library from Audible API
 ///
 /// This allows for progressive UI updates by fetching one page at a time.
+/// Call `nativeCheckSyncReadiness` before the first page.
+///
+/// With `prefetch`, the next page is fetched in the background while the
+/// app handles this one, and the next call picks it up. Prefetching pauses
+/// while Audible is rate limiting the account.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
 ///   "account_json": "{...}", // serialized Account object
-///   "page": 1 // page number (1-indexed)
+///   "page": 1, // page number (1-indexed)
+///   "prefetch": true, // optional, default false
+///   "job_id": "sync-1" // optional, for nativeCancelJob and nativeGetJob
 /// }
 /// ```
 ///
@@ -762,7 +1017,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
 ///     "books_added": 10,
 ///     "books_updated": 40,
 ///     "books_absent": 0,
-///     "errors": [],
+///     "errors": [],  // [{ "asin", "stage", "code", "message" }], see nativeListSyncIssues
 ///     "has_more": true
 ///   }
 /// }
@@ -774,6 +1029,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSyncLibraryPage", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -781,6 +1037,10 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
             db_path: String,
             account_json: String,
             page: i32,
+            #[serde(default)]
+            prefetch: bool,
+            #[serde(default)]
+            job_id: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -788,25 +1048,37 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::LibrarySync)?;
             let result = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
 
-                // Ensure token is valid before making API calls
-                let account_json = crate::api::auth::ensure_valid_token(
-                    db.pool(),
-                    &params.account_json,
-                    30, // Refresh if expiring within 30 minutes
-                )
-                .await?;
+                crate::storage::jobs::run_job(db.pool(), &job, async {
+                    // Ensure token is valid before making API calls
+                    let account_json = crate::api::auth::ensure_valid_token(
+                        db.pool(),
+                        &params.account_json,
+                        30, // Refresh if expiring within 30 minutes
+                    )
+                    .await?;
 
-                let account: crate::api::auth::Account = serde_json::from_str(&account_json)
-                    .map_err(|e| {
-                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
-                    })?;
+                    let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                        .map_err(|e| {
+                            crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                        })?;
 
-                let mut client = crate::api::client::AudibleClient::new(account.clone())?;
+                    let mut client = crate::api::client::AudibleClient::new(account.clone())?;
 
-                client.sync_library_page(&db, &account, params.page).await
+                    if params.prefetch {
+                        client
+                            .sync_library_page_prefetching(&db, &account, params.page, job.token())
+                            .await
+                    } else {
+                        client
+                            .sync_library_page_cancellable(&db, &account, params.page, job.token())
+                            .await
+                    }
+                })
+                .await
             })?;
 
             Ok(success_response(result))
@@ -821,14 +1093,19 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
         .into_raw()
 }
 
-/// Get books from database with pagination
+/// Check that an account can sync, before fetching anything
+///
+/// Runs the checks `nativeSyncLibrary` starts with: signed in, known
+/// marketplace, valid token (refreshed and saved if about to expire) and
+/// a one-item library request. Failed checks come with a remediation
+/// (`sign_in`, `choose_marketplace`, `check_connection`, `retry_later`)
+/// and instructions to show.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
-///   "offset": 0,
-///   "limit": 50
+///   "account_json": "{...}"
 /// }
 /// ```
 ///
@@ -837,25 +1114,33 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSyncLi
 /// {
 ///   "success": true,
 ///   "data": {
-///     "books": [...],
-///     "total_count": 150
+///     "ready": false,
+///     "checks": [
+///       { "check": "identity", "status": "passed", "message": null, "remediation": null },
+///       { "check": "locale", "status": "passed", "message": null, "remediation": null },
+///       { "check": "token", "status": "failed", "message": "Couldn't refresh the access token: ...", "remediation": "sign_in" },
+///       { "check": "api_access", "status": "skipped", "message": null, "remediation": null }
+///     ],
+///     "message": "Couldn't refresh the access token: ...",
+///     "remediation": "sign_in",
+///     "instructions": "Sign in to your Audible account again."
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBooks(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCheckSyncReadiness(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeCheckSyncReadiness", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            offset: i64,
-            limit: i64,
+            account_json: String,
         }
 
         match (move || -> crate::Result<String> {
@@ -863,64 +1148,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
+            let readiness = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let books = crate::storage::queries::list_books_with_relations(
-                    db.pool(),
-                    params.limit,
-                    params.offset,
-                )
-                .await?;
-                let total_count = crate::storage::queries::count_books(db.pool()).await?;
-
-                // Convert BookWithRelations to JSON with arrays for authors/narrators
-                let books_json: Vec<serde_json::Value> = books.iter().map(|book| {
-                    serde_json::json!({
-                        "id": book.book_id,
-                        "audible_product_id": book.audible_product_id,
-                        "title": book.title,
-                        "subtitle": book.subtitle,
-                        "description": book.description,
-                        "duration_seconds": book.length_in_minutes * 60,
-                        "language": book.language,
-                        "rating": book.rating_overall,
-                        "cover_url": book.picture_large,
-                        "release_date": book.date_published,
-                        "purchase_date": book.purchase_date,
-                        "created_at": book.created_at,
-                        "updated_at": book.updated_at,
-                        "authors": book.authors_str.as_ref()
-                            .map(|s| s.split(", ").filter(|a| !a.is_empty()).collect::<Vec<_>>())
-                            .unwrap_or_default(),
-                        "narrators": book.narrators_str.as_ref()
-                            .map(|s| s.split(", ").filter(|n| !n.is_empty()).collect::<Vec<_>>())
-                            .unwrap_or_default(),
-                        "publisher": book.publisher,
-                        "series_name": book.series_name,
-                        "series_sequence": book.series_sequence,
-                        "file_path": null,  // TODO: Add when download manager implemented
-                        "pdf_url": book.pdf_url,
-                        "is_finished": book.is_finished,
-                        "is_downloadable": book.is_downloadable,
-                        "is_ayce": book.is_ayce,
-                        "origin_asin": book.origin_asin,
-                        "episode_number": book.episode_number,
-                        "content_delivery_type": book.content_delivery_type,
-                        "is_abridged": book.is_abridged,
-                        "is_spatial": book.is_spatial,
-                        "source": book.source.as_deref().unwrap_or("audible"),
-                    })
-                }).collect();
-
-                let response = serde_json::json!({
-                    "books": books_json,
-                    "total_count": total_count,
-                });
-
-                Ok::<_, crate::LibationError>(response)
+                crate::api::preflight::check_sync_readiness(db.pool(), &params.account_json).await
             })?;
 
-            Ok(success_response(result))
+            Ok(success_response(readiness))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -932,13 +1165,20 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
         .into_raw()
 }
 
-/// Get a single book by exact ASIN with all relations
+/// Status of the Audible API, for a status banner
+///
+/// Probes each marketplace with a time-boxed request (10 s) and, with an
+/// account, a one-item library request, telling apart `network_down` (no
+/// response), `api_error` (Audible is failing) and `account_error` (the
+/// account was rejected). Results are reused for a minute unless `force`
+/// is set. Marketplaces default to the account's, else `us`.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../libation.db",
-///   "asin": "B07T2F8VJM"
+///   "account_json": "{...}",      // optional
+///   "marketplaces": ["us", "uk"], // optional
+///   "force": false                // optional, probe again even if cached
 /// }
 /// ```
 ///
@@ -946,22 +1186,41 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
 /// ```json
 /// {
 ///   "success": true,
-///   "data": { book object with all fields }
+///   "data": {
+///     "marketplaces": [
+///       {
+///         "country_code": "us",
+///         "state": "api_error",
+///         "message": "Audible API answered 503 Service Unavailable",
+///         "status_code": 503,
+///         "remediation": "retry_later",
+///         "instructions": "Audible isn't responding right now. Try again in a few minutes.",
+///         "latency_ms": 412,
+///         "checked_at": "2025-03-01T12:00:00Z",
+///         "account_checked": true
+///       }
+///     ]
+///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBookByAsin(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetApiHealth(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetApiHealth", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            db_path: String,
-            asin: String,
+            #[serde(default)]
+            account_json: Option<String>,
+            #[serde(default)]
+            marketplaces: Option<Vec<String>>,
+            #[serde(default)]
+            force: bool,
         }
 
         match (move || -> crate::Result<String> {
@@ -969,59 +1228,35 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
-                let db = crate::storage::Database::new(&params.db_path).await?;
-                let book = crate::storage::queries::find_book_with_relations_by_asin(
-                    db.pool(),
-                    &params.asin,
-                )
-                .await?;
+            let account: Option<crate::api::auth::Account> = params
+                .account_json
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
+            let codes = params.marketplaces.unwrap_or_else(|| {
+                vec![account
+                    .as_ref()
+                    .and_then(|a| a.locale())
+                    .map_or_else(|| "us".to_string(), |l| l.country_code.clone())]
+            });
+            let locales = codes
+                .iter()
+                .map(|code| {
+                    crate::api::auth::Locale::from_country_code(code).ok_or_else(|| {
+                        crate::LibationError::invalid_input(format!("Unsupported marketplace: {}", code))
+                    })
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
 
-                if let Some(book) = book {
-                    let book_json = serde_json::json!({
-                        "id": book.book_id,
-                        "audible_product_id": book.audible_product_id,
-                        "title": book.title,
-                        "subtitle": book.subtitle,
-                        "description": book.description,
-                        "duration_seconds": book.length_in_minutes * 60,
-                        "language": book.language,
-                        "rating": book.rating_overall,
-                        "cover_url": book.picture_large,
-                        "release_date": book.date_published,
-                        "purchase_date": book.purchase_date,
-                        "created_at": book.created_at,
-                        "updated_at": book.updated_at,
-                        "authors": book.authors_str.as_ref()
-                            .map(|s| s.split(", ").filter(|a| !a.is_empty()).collect::<Vec<_>>())
-                            .unwrap_or_default(),
-                        "narrators": book.narrators_str.as_ref()
-                            .map(|s| s.split(", ").filter(|n| !n.is_empty()).collect::<Vec<_>>())
-                            .unwrap_or_default(),
-                        "publisher": book.publisher,
-                        "series_name": book.series_name,
-                        "series_sequence": book.series_sequence,
-                        "pdf_url": book.pdf_url,
-                        "is_finished": book.is_finished,
-                        "is_downloadable": book.is_downloadable,
-                        "is_ayce": book.is_ayce,
-                        "origin_asin": book.origin_asin,
-                        "episode_number": book.episode_number,
-                        "content_delivery_type": book.content_delivery_type,
-                        "is_abridged": book.is_abridged,
-                        "is_spatial": book.is_spatial,
-                        "source": book.source.as_deref().unwrap_or("audible"),
-                    });
-                    Ok::<_, crate::LibationError>(book_json)
-                } else {
-                    Err(crate::LibationError::not_found(format!(
-                        "Book not found: {}",
-                        params.asin
-                    )))
-                }
-            })?;
+            let transport: std::sync::Arc<dyn crate::api::transport::HttpTransport> = std::sync::Arc::new(
+                crate::api::transport::ReqwestTransport::new(crate::api::health::PROBE_TIMEOUT, false)?,
+            );
+            let marketplaces = RUNTIME.block_on(futures_util::future::join_all(locales.iter().map(|locale| {
+                crate::api::health::marketplace_health(transport.clone(), locale, account.as_ref(), params.force)
+            })));
 
-            Ok(success_response(result))
+            Ok(success_response(serde_json::json!({ "marketplaces": marketplaces })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1033,14 +1268,16 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
         .into_raw()
 }
 
-/// Search books by title
+/// List unresolved library sync issues
+///
+/// Titles that failed to import during a sync stay listed until a later
+/// sync (or `nativeRetrySyncIssue`) imports them cleanly.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
-///   "query": "harry potter",
-///   "limit": 20
+///   "account": "amzn1.account.XXX"  // optional, all accounts when omitted
 /// }
 /// ```
 ///
@@ -1049,24 +1286,36 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
 /// {
 ///   "success": true,
 ///   "data": {
-///     "books": [...]
+///     "issues": [
+///       {
+///         "issue_id": 3,
+///         "account": "amzn1.account.XXX",
+///         "asin": "B07B4JYXB5",
+///         "stage": "book",  // "contributor", "series" or "book"
+///         "code": "database",
+///         "message": "Database error: ...",
+///         "occurrences": 2,
+///         "first_seen": "2025-06-01T10:00:00+00:00",
+///         "last_seen": "2025-06-08T10:00:00+00:00"
+///       }
+///     ]
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearchBooks(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListSyncIssues(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListSyncIssues", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            query: String,
-            limit: i64,
+            account: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -1074,23 +1323,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearch
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
+            let issues = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let books = crate::storage::queries::search_books_by_title(
-                    db.pool(),
-                    &params.query,
-                    params.limit,
-                )
-                .await?;
-
-                let response = serde_json::json!({
-                    "books": books,
-                });
-
-                Ok::<_, crate::LibationError>(response)
+                crate::storage::sync_issues::list_sync_issues(db.pool(), params.account.as_deref()).await
             })?;
 
-            Ok(success_response(result))
+            Ok(success_response(serde_json::json!({ "issues": issues })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1102,19 +1340,18 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearch
         .into_raw()
 }
 
-/// Get books with search, filter, and sort parameters
+/// Retry or dismiss a library sync issue
+///
+/// `retry` re-imports just that title from the library API; `dismiss`
+/// marks the issue resolved without retrying.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
-///   "offset": 0,
-///   "limit": 100,
-///   "search_query": "harry potter",  // optional
-///   "series_name": "Harry Potter",   // optional
-///   "category": "Fantasy",           // optional
-///   "sort_field": "title",           // "title" | "release_date" | "date_added" | "series" | "length"
-///   "sort_direction": "asc"          // "asc" | "desc"
+///   "account_json": "{...}", // serialized Account object
+///   "issue_id": 3,
+///   "action": "retry"        // "retry" (default) or "dismiss"
 /// }
 /// ```
 ///
@@ -1123,31 +1360,33 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearch
 /// {
 ///   "success": true,
 ///   "data": {
-///     "books": [...],
-///     "total_count": 123
+///     "total_items": 1,
+///     "total_library_count": 0,
+///     "books_added": 0,
+///     "books_updated": 1,
+///     "books_absent": 0,
+///     "errors": [],  // non-empty if the retry failed again
+///     "has_more": false
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBooksWithFilters(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRetrySyncIssue(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRetrySyncIssue", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            offset: i64,
-            limit: i64,
-            search_query: Option<String>,
-            series_name: Option<String>,
-            category: Option<String>,
-            sort_field: Option<String>,
-            sort_direction: Option<String>,
-            source: Option<String>,
+            account_json: String,
+            issue_id: i64,
+            #[serde(default)]
+            action: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -1158,91 +1397,37 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
             let result = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
 
-                // Build query parameters
-                let mut query_params = crate::storage::BookQueryParams {
-                    search_query: params.search_query,
-                    series_name: params.series_name,
-                    category: params.category,
-                    source: params.source,
-                    sort_field: None,
-                    sort_direction: None,
-                    limit: params.limit,
-                    offset: params.offset,
-                };
-
-                // Parse sort field
-                if let Some(field) = params.sort_field {
-                    query_params.sort_field = match field.as_str() {
-                        "title" => Some(crate::storage::SortField::Title),
-                        "release_date" => Some(crate::storage::SortField::ReleaseDate),
-                        "date_added" => Some(crate::storage::SortField::DateAdded),
-                        "series" => Some(crate::storage::SortField::Series),
-                        "length" => Some(crate::storage::SortField::Length),
-                        _ => None,
-                    };
-                }
-
-                // Parse sort direction
-                if let Some(dir) = params.sort_direction {
-                    query_params.sort_direction = match dir.as_str() {
-                        "asc" => Some(crate::storage::SortDirection::Asc),
-                        "desc" => Some(crate::storage::SortDirection::Desc),
-                        _ => None,
-                    };
+                match params.action.as_deref().unwrap_or("retry") {
+                    "retry" => {}
+                    "dismiss" => {
+                        crate::storage::sync_issues::get_sync_issue(db.pool(), params.issue_id).await?;
+                        crate::storage::sync_issues::resolve_sync_issue(db.pool(), params.issue_id).await?;
+                        return Ok(crate::api::library::SyncStats::new());
+                    }
+                    other => {
+                        return Err(crate::LibationError::InvalidInput(format!(
+                            "Unknown sync issue action: {}",
+                            other
+                        )))
+                    }
                 }
 
-                let books =
-                    crate::storage::queries::list_books_with_filters(db.pool(), &query_params)
-                        .await?;
-                let total_count =
-                    crate::storage::queries::count_books_with_filters(db.pool(), &query_params)
-                        .await?;
+                // Ensure token is valid before making API calls
+                let account_json = crate::api::auth::ensure_valid_token(
+                    db.pool(),
+                    &params.account_json,
+                    30, // Refresh if expiring within 30 minutes
+                )
+                .await?;
 
-                // Convert BookWithRelations to JSON with arrays for authors/narrators
-                let books_json: Vec<serde_json::Value> = books.iter().map(|book| {
-                    serde_json::json!({
-                        "id": book.book_id,
-                        "audible_product_id": book.audible_product_id,
-                        "title": book.title,
-                        "subtitle": book.subtitle,
-                        "description": book.description,
-                        "duration_seconds": book.length_in_minutes * 60,
-                        "language": book.language,
-                        "rating": book.rating_overall,
-                        "cover_url": book.picture_large,
-                        "release_date": book.date_published,
-                        "purchase_date": book.purchase_date,
-                        "created_at": book.created_at,
-                        "updated_at": book.updated_at,
-                        "authors": book.authors_str.as_ref()
-                            .map(|s| s.split(", ").filter(|a| !a.is_empty()).collect::<Vec<_>>())
-                            .unwrap_or_default(),
-                        "narrators": book.narrators_str.as_ref()
-                            .map(|s| s.split(", ").filter(|n| !n.is_empty()).collect::<Vec<_>>())
-                            .unwrap_or_default(),
-                        "publisher": book.publisher,
-                        "series_name": book.series_name,
-                        "series_sequence": book.series_sequence,
-                        "file_path": null,
-                        "pdf_url": book.pdf_url,
-                        "is_finished": book.is_finished,
-                        "is_downloadable": book.is_downloadable,
-                        "is_ayce": book.is_ayce,
-                        "origin_asin": book.origin_asin,
-                        "episode_number": book.episode_number,
-                        "content_delivery_type": book.content_delivery_type,
-                        "is_abridged": book.is_abridged,
-                        "is_spatial": book.is_spatial,
-                        "source": book.source.as_deref().unwrap_or("audible"),
-                    })
-                }).collect();
+                let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
 
-                let response = serde_json::json!({
-                    "books": books_json,
-                    "total_count": total_count,
-                });
+                let mut client = crate::api::client::AudibleClient::new(account.clone())?;
 
-                Ok::<_, crate::LibationError>(response)
+                client.retry_sync_issue(&db, &account, params.issue_id).await
             })?;
 
             Ok(success_response(result))
@@ -1257,12 +1442,17 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
         .into_raw()
 }
 
-/// Get all unique series names from library
+/// Check books for metadata problems and store them as a report
+///
+/// Checks for missing title/authors/narrators, zero runtime, missing cover,
+/// links to missing or unnamed series, and stored chapters that don't add
+/// up to the runtime. Replaces the stored issues of the checked books.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../libation.db"
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM"  // optional, re-check one book after fixing it
 /// }
 /// ```
 ///
@@ -1271,22 +1461,28 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
 /// {
 ///   "success": true,
 ///   "data": {
-///     "series": ["Harry Potter", "Lord of the Rings", ...]
+///     "books_checked": 412,
+///     "books_with_issues": 9,
+///     "errors": 4,
+///     "warnings": 6,
+///     "infos": 2
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAllSeries(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeValidateLibrary(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeValidateLibrary", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
+            asin: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -1294,18 +1490,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAll
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
+            let summary = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let series = crate::storage::queries::list_all_series(db.pool()).await?;
-
-                let response = serde_json::json!({
-                    "series": series,
-                });
-
-                Ok::<_, crate::LibationError>(response)
+                crate::storage::validation::validate_library(db.pool(), params.asin.as_deref()).await
             })?;
 
-            Ok(success_response(result))
+            Ok(success_response(summary))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1317,12 +1507,13 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAll
         .into_raw()
 }
 
-/// Get all unique categories/genres from library
+/// List metadata problems found by the last validation pass
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../libation.db"
+///   "db_path": "/data/data/.../libation.db",
+///   "min_severity": "warning"  // optional: "info" (default), "warning" or "error"
 /// }
 /// ```
 ///
@@ -1331,22 +1522,33 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAll
 /// {
 ///   "success": true,
 ///   "data": {
-///     "categories": ["Fantasy", "Science Fiction", ...]
+///     "issues": [
+///       {
+///         "asin": "B07T2F8VJM",
+///         "title": "All These Worlds",
+///         "check": "chapter_duration_mismatch",
+///         "severity": "warning",
+///         "message": "24 chapters add up to 512 min, runtime is 560 min",
+///         "detected_at": "2025-06-01T10:00:00+00:00"
+///       }
+///     ]
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAllCategories(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetValidationReport(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetValidationReport", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
+            min_severity: Option<crate::storage::validation::Severity>,
         }
 
         match (move || -> crate::Result<String> {
@@ -1354,18 +1556,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAll
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
+            let issues = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let categories = crate::storage::queries::list_all_categories(db.pool()).await?;
-
-                let response = serde_json::json!({
-                    "categories": categories,
-                });
-
-                Ok::<_, crate::LibationError>(response)
+                crate::storage::validation::list_validation_issues(db.pool(), params.min_severity).await
             })?;
 
-            Ok(success_response(result))
+            Ok(success_response(serde_json::json!({ "issues": issues })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1377,45 +1573,20 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAll
         .into_raw()
 }
 
-// ============================================================================
-// DOWNLOAD FUNCTIONS
-// ============================================================================
-
-/// Download audiobook file
-///
-/// # Arguments (JSON string)
-/// ```json
-/// {
-///   "asin": "B012345678",
-///   "access_token": "...",
-///   "locale_code": "us",
-///   "output_path": "/storage/emulated/0/Download/book.aax"
-/// }
-/// ```
+/// Get the ebook position for an audio offset (Whispersync for Voice)
 ///
-/// # Returns (JSON)
-/// ```json
-/// {
-///   "success": true,
-///   "data": {
-///     "bytes_downloaded": 123456789,
-///     "output_path": "/storage/emulated/0/Download/book.aax"
-///   }
-/// }
-/// ```
-
-// ============================================================================
-// DECRYPTION FUNCTIONS
-// ============================================================================
-
-/// Decrypt AAX file to M4B using activation bytes
+/// Uses the stored read-along mapping. When `refresh` is true, or nothing
+/// is stored yet and `account_json` is given, the mapping is fetched from
+/// Audible first and stored.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "input_path": "/storage/emulated/0/Download/book.aax",
-///   "output_path": "/storage/emulated/0/Download/book.m4b",
-///   "activation_bytes": "1CEB00DA"
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07B4JYXB5",
+///   "position_ms": 3600000,
+///   "account_json": "{...}", // optional, needed to fetch
+///   "refresh": false         // optional
 /// }
 /// ```
 ///
@@ -1424,25 +1595,32 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAll
 /// {
 ///   "success": true,
 ///   "data": {
-///     "output_path": "/storage/emulated/0/Download/book.m4b",
-///     "file_size": 123456789
+///     "fetched": true,   // false if the mapping was never fetched
+///     "available": true, // false if the title has no usable companion
+///     "companion_asin": "B00KINDLE1",
+///     "position": { "location": 2450, "page": "112" },  // null when unavailable
+///     "fetched_at": "2025-06-01T10:00:00+00:00"
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDecryptAAX(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetReadAlongPosition(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetReadAlongPosition", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            input_path: String,
-            output_path: String,
-            activation_bytes: String,
+            db_path: String,
+            asin: String,
+            position_ms: i64,
+            account_json: Option<String>,
+            #[serde(default)]
+            refresh: bool,
         }
 
         match (move || -> crate::Result<String> {
@@ -1450,30 +1628,52 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDecryp
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let activation_bytes =
-                crate::crypto::activation::ActivationBytes::from_hex(&params.activation_bytes)?;
-
-            let result = RUNTIME.block_on(async {
-                let decrypter = crate::crypto::aax::AaxDecrypter::new(activation_bytes);
-
-                let input_path = std::path::Path::new(&params.input_path);
-                let output_path = std::path::Path::new(&params.output_path);
-
-                decrypter.decrypt_file(input_path, output_path).await?;
+            let mapping = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let stored = crate::storage::read_along::get_read_along_mapping(db.pool(), &params.asin).await?;
+                if stored.is_some() && !params.refresh {
+                    return Ok(stored);
+                }
 
-                let file_size = tokio::fs::metadata(output_path)
-                    .await
-                    .map(|m| m.len())
-                    .unwrap_or(0);
+                let Some(account_json) = params.account_json.as_deref() else {
+                    if params.refresh {
+                        return Err(crate::LibationError::InvalidInput(
+                            "account_json is required to refresh".to_string(),
+                        ));
+                    }
+                    return Ok(None);
+                };
 
-                let response = serde_json::json!({
-                    "output_path": params.output_path,
-                    "file_size": file_size,
-                });
+                // Ensure token is valid before making API calls
+                let account_json = crate::api::auth::ensure_valid_token(db.pool(), account_json, 30).await?;
+                let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
 
-                Ok::<_, crate::LibationError>(response)
+                let client = crate::api::client::AudibleClient::new(account)?;
+                let mapping = client.fetch_read_along_mapping(&params.asin).await?;
+                crate::storage::read_along::save_read_along_mapping(db.pool(), &mapping).await?;
+                Ok::<_, crate::LibationError>(Some(mapping))
             })?;
 
+            let result = match mapping {
+                Some(mapping) => serde_json::json!({
+                    "fetched": true,
+                    "available": mapping.is_available(),
+                    "companion_asin": mapping.companion_asin,
+                    "position": mapping.ebook_position_at(params.position_ms),
+                    "fetched_at": mapping.fetched_at,
+                }),
+                None => serde_json::json!({
+                    "fetched": false,
+                    "available": false,
+                    "companion_asin": null,
+                    "position": null,
+                    "fetched_at": null,
+                }),
+            };
+
             Ok(success_response(result))
         })() {
             Ok(result) => result,
@@ -1486,16 +1686,17 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDecryp
         .into_raw()
 }
 
-// ============================================================================
-// DATABASE FUNCTIONS
-// ============================================================================
-
-/// Initialize database at specified path
+/// Set or clear the preferred metadata marketplace
+///
+/// Book lists show titles from this marketplace once fetched with
+/// `nativeFetchLocalizedTitles`, e.g. English titles for books bought on
+/// audible.de. `null` shows each book's own title again.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../libation.db"
+///   "db_path": "/data/data/.../libation.db",
+///   "locale": "us"  // country code or null
 /// }
 /// ```
 ///
@@ -1503,23 +1704,23 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDecryp
 /// ```json
 /// {
 ///   "success": true,
-///   "data": {
-///     "initialized": true
-///   }
+///   "data": { "locale": "us" }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeInitDatabase(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetPreferredMetadataLocale(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetPreferredMetadataLocale", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
+            locale: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -1527,17 +1728,15 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeInitDa
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
-                let _db = crate::storage::Database::new(&params.db_path).await?;
-
-                let response = serde_json::json!({
-                    "initialized": true,
-                });
+            let locale = RUNTIME.block_on(async {
+                use crate::storage::localized_titles;
 
-                Ok::<_, crate::LibationError>(response)
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                localized_titles::set_preferred_metadata_locale(db.pool(), params.locale.as_deref()).await?;
+                localized_titles::get_preferred_metadata_locale(db.pool()).await
             })?;
 
-            Ok(success_response(result))
+            Ok(success_response(serde_json::json!({ "locale": locale })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1549,16 +1748,18 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeInitDa
         .into_raw()
 }
 
-// ============================================================================
-// UTILITY FUNCTIONS
-// ============================================================================
-
-/// Validate activation bytes format
+/// Fetch titles from the preferred metadata marketplace for books that
+/// don't have one yet
+///
+/// Call repeatedly (e.g. after each library sync) until `remaining` is 0.
+/// Does nothing when no preferred marketplace is set.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "activation_bytes": "1CEB00DA"
+///   "db_path": "/data/data/.../libation.db",
+///   "account_json": "{...}",
+///   "limit": 200  // optional, books per call
 /// }
 /// ```
 ///
@@ -1567,22 +1768,33 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeInitDa
 /// {
 ///   "success": true,
 ///   "data": {
-///     "valid": true
+///     "locale": "us",  // null if none is set
+///     "fetched": 200,  // books looked up
+///     "found": 187,    // books the marketplace sells
+///     "remaining": 42
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeValidateActivationBytes(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeFetchLocalizedTitles(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeFetchLocalizedTitles", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            activation_bytes: String,
+            db_path: String,
+            account_json: String,
+            #[serde(default = "default_limit")]
+            limit: i64,
+        }
+
+        fn default_limit() -> i64 {
+            200
         }
 
         match (move || -> crate::Result<String> {
@@ -1590,15 +1802,51 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeValida
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let valid =
-                crate::crypto::activation::ActivationBytes::from_hex(&params.activation_bytes)
-                    .is_ok();
+            let result = RUNTIME.block_on(async {
+                use crate::storage::localized_titles;
 
-            let response = serde_json::json!({
-                "valid": valid,
-            });
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let Some(code) = localized_titles::get_preferred_metadata_locale(db.pool()).await? else {
+                    return Ok(serde_json::json!({
+                        "locale": null,
+                        "fetched": 0,
+                        "found": 0,
+                        "remaining": 0,
+                    }));
+                };
+                let locale = crate::api::auth::Locale::from_country_code(&code)
+                    .ok_or_else(|| crate::LibationError::InvalidState(format!("Unsupported marketplace: {}", code)))?;
+
+                let asins =
+                    localized_titles::list_books_missing_localized_title(db.pool(), &code, params.limit).await?;
+                let mut found = 0;
+                if !asins.is_empty() {
+                    // Ensure token is valid before making API calls
+                    let account_json =
+                        crate::api::auth::ensure_valid_token(db.pool(), &params.account_json, 30).await?;
+                    let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                        .map_err(|e| {
+                            crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                        })?;
+
+                    let client = crate::api::client::AudibleClient::new(account)?;
+                    let titles = client.get_localized_titles(&locale, &asins).await?;
+                    found = titles.iter().filter(|t| t.title.is_some()).count();
+                    localized_titles::save_localized_titles(db.pool(), &titles).await?;
+                }
 
-            Ok(success_response(response))
+                let remaining =
+                    localized_titles::list_books_missing_localized_title(db.pool(), &code, i64::MAX).await?;
+
+                Ok::<_, crate::LibationError>(serde_json::json!({
+                    "locale": code,
+                    "fetched": asins.len(),
+                    "found": found,
+                    "remaining": remaining.len(),
+                }))
+            })?;
+
+            Ok(success_response(result))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1610,11 +1858,21 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeValida
         .into_raw()
 }
 
-/// Get list of supported locales
+/// Search the Audible catalog
+///
+/// Each result carries links into the searched marketplace's store, for
+/// books the user doesn't own.
 ///
 /// # Arguments (JSON string)
 /// ```json
-/// {}
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "account_json": "{...}",
+///   "keywords": "dune",
+///   "page": 1,           // optional, starting at 1
+///   "num_results": 20,   // optional, max 50
+///   "locale": "de"       // optional, defaults to the account's marketplace
+/// }
 /// ```
 ///
 /// # Returns (JSON)
@@ -1622,27 +1880,110 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeValida
 /// {
 ///   "success": true,
 ///   "data": {
-///     "locales": [
-///       {"country_code": "us", "name": "United States", "domain": "audible.com"},
-///       ...
-///     ]
+///     "total_results": 42,
+///     "results": [{
+///       "asin": "B002V5D7B0", "title": "Dune", ...,
+///       "owned": false,
+///       "store_links": {
+///         "locale": "de",
+///         "product_url": "https://www.audible.de/pd/B002V5D7B0",
+///         "purchase_url": "https://www.audible.de/cart/item/add?asin=B002V5D7B0",
+///         "app_url": "audible://view?section=pdp&asin=B002V5D7B0&marketplace=AN7V1F1VY261K",
+///         "android_intent_url": "intent://view?...;end"
+///       },
+///       "price": {            // null when the marketplace gives no price
+///         "asin": "B002V5D7B0",
+///         "locale": "de",
+///         "cost": "credits",  // credits | money | free | not_for_sale
+///         "credits": 1,
+///         "list_price": { "amount": 24.95, "currency_code": "EUR", "display": "24,95 €" },
+///         "sale_price": null,
+///         "display": "1 credit"
+///       }
+///     }]
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetSupportedLocales(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearchCatalog(
     mut env: JNIEnv,
     _class: JClass,
-    _params_json: JString,
+    params_json: JString,
 ) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSearchCatalog", &params_str_result);
+
     let response = catch_panic(move || {
-        let locales = crate::api::auth::Locale::all();
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_json: String,
+            keywords: String,
+            #[serde(default = "default_page")]
+            page: u32,
+            #[serde(default = "default_num_results")]
+            num_results: u32,
+            locale: Option<String>,
+        }
 
-        let response = serde_json::json!({
-            "locales": locales,
-        });
+        fn default_page() -> u32 {
+            1
+        }
 
-        success_response(response)
+        fn default_num_results() -> u32 {
+            20
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+
+                // Ensure token is valid before making API calls
+                let account_json =
+                    crate::api::auth::ensure_valid_token(db.pool(), &params.account_json, 30).await?;
+                let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
+
+                let mut client = crate::api::client::AudibleClient::new(account)?;
+                if let Some(code) = &params.locale {
+                    let locale = crate::api::auth::Locale::from_country_code(code).ok_or_else(|| {
+                        crate::LibationError::InvalidInput(format!("Unsupported marketplace: {}", code))
+                    })?;
+                    client = client.for_marketplace(&locale);
+                }
+
+                let page = client
+                    .search_catalog(&params.keywords, params.page, params.num_results)
+                    .await?;
+
+                let mut results = Vec::with_capacity(page.results.len());
+                for result in page.results {
+                    let owned =
+                        crate::storage::queries::find_book_by_asin(db.pool(), &result.product.asin)
+                            .await?
+                            .is_some();
+                    let mut value = serde_json::to_value(&result)?;
+                    value["owned"] = serde_json::json!(owned);
+                    results.push(value);
+                }
+
+                Ok::<_, crate::LibationError>(serde_json::json!({
+                    "total_results": page.total_results,
+                    "results": results,
+                }))
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
     });
 
     env.new_string(response)
@@ -1650,14 +1991,18 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetSup
         .into_raw()
 }
 
-/// Build file path using naming pattern
+/// Credit cost and price of catalog products
+///
+/// Prices fetched in the last few minutes (including by
+/// `nativeSearchCatalog`) are served from memory.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
-///   "asin": "B07T2F8VJM",
-///   "naming_pattern": "author_series_book"  // or "flat_file", "author_book_folder"
+///   "account_json": "{...}",
+///   "asins": ["B002V5D7B0", "B00B5HZGUG"],   // max 50
+///   "locale": "de"       // optional, defaults to the account's marketplace
 /// }
 /// ```
 ///
@@ -1666,24 +2011,26 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetSup
 /// {
 ///   "success": true,
 ///   "data": {
-///     "file_path": "Dennis E. Taylor/Bobiverse 3 - All These Worlds/Bobiverse 3 - All These Worlds.m4b"
+///     "prices": [{ "asin": "B002V5D7B0", "cost": "credits", "credits": 1, "display": "1 credit", ... }]
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBuildFilePath(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCatalogPrices(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetCatalogPrices", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            asin: String,
-            naming_pattern: String,
+            account_json: String,
+            asins: Vec<String>,
+            locale: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -1691,35 +2038,29 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBuildF
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
-                // Get book metadata
+            let prices = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let book = crate::storage::queries::find_book_with_relations_by_asin(
-                    db.pool(),
-                    &params.asin,
-                )
-                .await?
-                .ok_or_else(|| {
-                    crate::LibationError::not_found(format!("Book not found: {}", params.asin))
-                })?;
 
-                // Convert to AudioMetadata
-                let metadata = book.to_audio_metadata();
-
-                // Parse naming pattern
-                let pattern =
-                    crate::file::paths::NamingPattern::from_string(&params.naming_pattern)
-                        .unwrap_or(crate::file::paths::NamingPattern::AuthorSeriesBook);
+                // Ensure token is valid before making API calls
+                let account_json =
+                    crate::api::auth::ensure_valid_token(db.pool(), &params.account_json, 30).await?;
+                let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
 
-                // Build path
-                let file_path = crate::file::paths::build_file_path(&metadata, pattern, "m4b")?;
+                let mut client = crate::api::client::AudibleClient::new(account)?;
+                if let Some(code) = &params.locale {
+                    let locale = crate::api::auth::Locale::from_country_code(code).ok_or_else(|| {
+                        crate::LibationError::InvalidInput(format!("Unsupported marketplace: {}", code))
+                    })?;
+                    client = client.for_marketplace(&locale);
+                }
 
-                Ok::<_, crate::LibationError>(serde_json::json!({
-                    "file_path": file_path,
-                }))
+                client.get_catalog_prices(&params.asins).await
             })?;
 
-            Ok(success_response(result))
+            Ok(success_response(serde_json::json!({ "prices": prices })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1731,13 +2072,13 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBuildF
         .into_raw()
 }
 
-/// Get customer information from Audible API
+/// Storefront links for a product
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "locale_code": "us",
-///   "access_token": "Atna|..."
+///   "asin": "B002V5D7B0",
+///   "locale": "uk"
 /// }
 /// ```
 ///
@@ -1746,25 +2087,28 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBuildF
 /// {
 ///   "success": true,
 ///   "data": {
-///     "name": "John Doe",
-///     "given_name": "John",
-///     "email": "john@example.com"
+///     "locale": "uk",
+///     "product_url": "https://www.audible.co.uk/pd/B002V5D7B0",
+///     "purchase_url": "https://www.audible.co.uk/cart/item/add?asin=B002V5D7B0",
+///     "app_url": "audible://view?section=pdp&asin=B002V5D7B0&marketplace=A2I9A3Q2GNFNGQ",
+///     "android_intent_url": "intent://view?...;end"
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCustomerInformation(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetStoreLinks(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetStoreLinks", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            locale_code: String,
-            access_token: String,
+            asin: String,
+            locale: String,
         }
 
         match (move || -> crate::Result<String> {
@@ -1772,57 +2116,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCus
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
-                // Get locale
-                let locale = match params.locale_code.as_str() {
-                    "us" => crate::api::auth::Locale::us(),
-                    "uk" => crate::api::auth::Locale::uk(),
-                    "de" => crate::api::auth::Locale::de(),
-                    "fr" => crate::api::auth::Locale::fr(),
-                    "ca" => crate::api::auth::Locale::ca(),
-                    "au" => crate::api::auth::Locale::au(),
-                    "it" => crate::api::auth::Locale::it(),
-                    "es" => crate::api::auth::Locale::es(),
-                    "in" => crate::api::auth::Locale::in_(),
-                    "jp" => crate::api::auth::Locale::jp(),
-                    _ => {
-                        return Err(crate::LibationError::InvalidInput(format!(
-                            "Unknown locale: {}",
-                            params.locale_code
-                        )))
-                    }
-                };
-
-                // Create identity with access token
-                let access_token = crate::api::auth::AccessToken {
-                    token: params.access_token.clone(),
-                    expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
-                };
-
-                let identity = crate::api::auth::Identity::new(
-                    access_token,
-                    String::new(), // refresh_token - not needed for this call
-                    String::new(), // device_private_key - not needed
-                    String::new(), // adp_token - not needed
-                    locale.clone(),
-                );
-
-                // Create account with identity
-                let account = crate::api::auth::Account {
-                    account_id: "temp".to_string(),
-                    account_name: "temp".to_string(),
-                    library_scan: true,
-                    decrypt_key: String::new(),
-                    identity: Some(identity),
-                };
-
-                let client = crate::api::client::AudibleClient::new(account)?;
-                let customer_info = client.get_customer_information().await?;
-
-                Ok::<_, crate::LibationError>(customer_info)
+            let locale = crate::api::auth::Locale::from_country_code(&params.locale).ok_or_else(|| {
+                crate::LibationError::InvalidInput(format!("Unsupported marketplace: {}", params.locale))
             })?;
+            let links = crate::api::storefront::store_links(&locale, &params.asin)?;
 
-            Ok(success_response(result))
+            Ok(success_response(links))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -1834,50 +2133,44 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCus
         .into_raw()
 }
 
-/// Download and decrypt an audiobook
+/// Get books from database with pagination
 ///
-/// # Parameters
-/// JSON string with:
+/// Books hidden by the content filter are left out.
+///
+/// # Arguments (JSON string)
 /// ```json
 /// {
-///   "accountJson": "{ ... }",  // Complete account JSON with identity
-///   "asin": "B07T2F8VJM",
-///   "outputDirectory": "/path/to/save",
-///   "quality": "High"  // "Low", "Normal", "High", "Extreme"
+///   "db_path": "/data/data/.../libation.db",
+///   "offset": 0,
+///   "limit": 50
 /// }
 /// ```
 ///
-/// # Returns
-/// JSON response:
+/// # Returns (JSON)
 /// ```json
 /// {
 ///   "success": true,
 ///   "data": {
-///     "outputPath": "/path/to/file.m4b",
-///     "fileSize": 148080000,
-///     "duration": 9783.3
+///     "books": [...],
+///     "total_count": 150
 ///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDownloadBook(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBooks(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetBooks", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            #[serde(rename = "accountJson")]
-            account_json: String,
-            asin: String,
-            #[serde(rename = "outputDirectory")]
-            output_directory: String,
-            quality: String,
-            #[serde(rename = "dbPath")]
-            db_path: Option<String>,
+            db_path: String,
+            offset: i64,
+            limit: i64,
         }
 
         match (move || -> crate::Result<String> {
@@ -1886,214 +2179,9320 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDownlo
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
             let result = RUNTIME.block_on(async {
-                // TODO: Add ensure_valid_token() here once we pass db_path in params
-                // For now, WorkManager backup handles token refresh
-
-                // Parse account
-                let account: crate::api::auth::Account = serde_json::from_str(&params.account_json)
-                    .map_err(|e| {
-                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
-                    })?;
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let filter = crate::storage::content_filter::get_content_filter(db.pool()).await?;
+                let title_locale =
+                    crate::storage::localized_titles::get_preferred_metadata_locale(db.pool()).await?;
+                let (books, total_count) = if filter.active_exclusions().is_empty() && title_locale.is_none() {
+                    (
+                        crate::storage::queries::list_books_with_relations(
+                            db.pool(),
+                            params.limit,
+                            params.offset,
+                        )
+                        .await?,
+                        crate::storage::queries::count_books(db.pool()).await?,
+                    )
+                } else {
+                    let mut query_params = crate::storage::BookQueryParams {
+                        limit: params.limit,
+                        offset: params.offset,
+                        title_locale,
+                        ..Default::default()
+                    };
+                    filter.apply(&mut query_params);
+                    (
+                        crate::storage::queries::list_books_with_filters(db.pool(), &query_params).await?,
+                        crate::storage::queries::count_books_with_filters(db.pool(), &query_params).await?,
+                    )
+                };
 
-                // Parse quality
-                let quality = match params.quality.as_str() {
-                    "Low" => crate::api::content::DownloadQuality::Low,
-                    "Normal" => crate::api::content::DownloadQuality::Normal,
-                    "High" => crate::api::content::DownloadQuality::High,
-                    "Extreme" => crate::api::content::DownloadQuality::Extreme,
-                    _ => crate::api::content::DownloadQuality::High,
+                // Convert BookWithRelations to JSON with arrays for authors/narrators
+                let books_json: Vec<serde_json::Value> = books.iter().map(|book| {
+                    serde_json::json!({
+                        "id": book.book_id,
+                        "audible_product_id": book.audible_product_id,
+                        "title": book.title,
+                        "subtitle": book.subtitle,
+                        "alternate_title": book.alternate_title,
+                        "description": book.description,
+                        "duration_seconds": book.length_in_minutes * 60,
+                        "language": book.language,
+                        "rating": book.rating_overall,
+                        "cover_url": book.picture_large,
+                        "release_date": book.date_published,
+                        "purchase_date": book.purchase_date,
+                        "created_at": book.created_at,
+                        "updated_at": book.updated_at,
+                        "authors": book.authors_str.as_ref()
+                            .map(|s| s.split(", ").filter(|a| !a.is_empty()).collect::<Vec<_>>())
+                            .unwrap_or_default(),
+                        "narrators": book.narrators_str.as_ref()
+                            .map(|s| s.split(", ").filter(|n| !n.is_empty()).collect::<Vec<_>>())
+                            .unwrap_or_default(),
+                        "publisher": book.publisher,
+                        "series_name": book.series_name,
+                        "series_sequence": book.series_sequence,
+                        "series_order": book.series_order,
+                        "file_path": null,  // TODO: Add when download manager implemented
+                        "pdf_url": book.pdf_url,
+                        "is_finished": book.is_finished,
+                        "is_downloadable": book.is_downloadable,
+                        "is_ayce": book.is_ayce,
+                        "origin_asin": book.origin_asin,
+                        "episode_number": book.episode_number,
+                        "content_delivery_type": book.content_delivery_type,
+                        "benefit_type": book.benefit_type.as_deref().unwrap_or("unknown"),
+                        "format_support": book.format_support.as_deref().unwrap_or("unknown"),
+                        "narrated_by_author": book.narrated_by_author,
+                        "full_cast": book.full_cast,
+                        "is_abridged": book.is_abridged,
+                        "is_spatial": book.is_spatial,
+                        "source": book.source.as_deref().unwrap_or("audible"),
+                    })
+                }).collect();
+
+                let response = serde_json::json!({
+                    "books": books_json,
+                    "total_count": total_count,
+                });
+
+                Ok::<_, crate::LibationError>(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get a single book by exact ASIN with all relations
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { book object with all fields }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBookByAsin(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetBookByAsin", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let book = crate::storage::queries::find_book_with_relations_by_asin(
+                    db.pool(),
+                    &params.asin,
+                )
+                .await?;
+
+                if let Some(book) = book {
+                    let book_json = serde_json::json!({
+                        "id": book.book_id,
+                        "audible_product_id": book.audible_product_id,
+                        "title": book.title,
+                        "subtitle": book.subtitle,
+                        "description": book.description,
+                        "duration_seconds": book.length_in_minutes * 60,
+                        "language": book.language,
+                        "rating": book.rating_overall,
+                        "cover_url": book.picture_large,
+                        "release_date": book.date_published,
+                        "purchase_date": book.purchase_date,
+                        "created_at": book.created_at,
+                        "updated_at": book.updated_at,
+                        "authors": book.authors_str.as_ref()
+                            .map(|s| s.split(", ").filter(|a| !a.is_empty()).collect::<Vec<_>>())
+                            .unwrap_or_default(),
+                        "narrators": book.narrators_str.as_ref()
+                            .map(|s| s.split(", ").filter(|n| !n.is_empty()).collect::<Vec<_>>())
+                            .unwrap_or_default(),
+                        "publisher": book.publisher,
+                        "series_name": book.series_name,
+                        "series_sequence": book.series_sequence,
+                        "series_order": book.series_order,
+                        "pdf_url": book.pdf_url,
+                        "is_finished": book.is_finished,
+                        "is_downloadable": book.is_downloadable,
+                        "is_ayce": book.is_ayce,
+                        "origin_asin": book.origin_asin,
+                        "episode_number": book.episode_number,
+                        "content_delivery_type": book.content_delivery_type,
+                        "benefit_type": book.benefit_type.as_deref().unwrap_or("unknown"),
+                        "format_support": book.format_support.as_deref().unwrap_or("unknown"),
+                        "narrated_by_author": book.narrated_by_author,
+                        "full_cast": book.full_cast,
+                        "is_abridged": book.is_abridged,
+                        "is_spatial": book.is_spatial,
+                        "source": book.source.as_deref().unwrap_or("audible"),
+                    });
+                    Ok::<_, crate::LibationError>(book_json)
+                } else {
+                    Err(crate::LibationError::not_found(format!(
+                        "Book not found: {}",
+                        params.asin
+                    )))
+                }
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Search books by title
+///
+/// With `full_text`, searches titles, subtitles, descriptions, authors,
+/// narrators and series instead, matching word prefixes and returning the
+/// best matches first (see `queries::search_books_fts`).
+///
+/// Books hidden by the content filter are left out.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "query": "harry potter",
+///   "limit": 20,
+///   "full_text": true  // optional, default false
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "books": [...]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearchBooks(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSearchBooks", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            query: String,
+            limit: i64,
+            #[serde(default)]
+            full_text: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let filter = crate::storage::content_filter::get_content_filter(db.pool()).await?;
+                let books = if params.full_text {
+                    crate::storage::queries::search_books_fts(
+                        db.pool(),
+                        &params.query,
+                        params.limit,
+                        filter.active_exclusions(),
+                    )
+                    .await?
+                } else {
+                    crate::storage::queries::search_books_by_title(
+                        db.pool(),
+                        &params.query,
+                        params.limit,
+                        filter.active_exclusions(),
+                    )
+                    .await?
                 };
 
-                // Create client
-                let client = crate::api::client::AudibleClient::new(account)?;
+                let response = serde_json::json!({
+                    "books": books,
+                });
+
+                Ok::<_, crate::LibationError>(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get books with search, filter, and sort parameters
+///
+/// Books hidden by the content filter are left out.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "offset": 0,
+///   "limit": 100,
+///   "search_query": "harry potter",  // optional
+///   "series_name": "Harry Potter",   // optional
+///   "category": "Fantasy",           // optional
+///   "tag": "favorite",               // optional
+///   "purchased_from": "2023-01-01",  // optional, inclusive (YYYY-MM-DD)
+///   "purchased_to": "2023-12-31",    // optional, inclusive
+///   "released_from": "2020-01-01",   // optional, inclusive
+///   "released_to": "2020-12-31",     // optional, inclusive
+///   "min_minutes": 60,               // optional runtime bound
+///   "max_minutes": 600,              // optional runtime bound
+///   "narrated_by_author": true,      // optional
+///   "full_cast": false,              // optional
+///   "sort_field": "title",           // "title" | "release_date" | "date_added" | "series" | "length"
+///   "sort_direction": "asc"          // "asc" | "desc"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "books": [...],
+///     "total_count": 123
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBooksWithFilters(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetBooksWithFilters", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            offset: i64,
+            limit: i64,
+            search_query: Option<String>,
+            series_name: Option<String>,
+            category: Option<String>,
+            sort_field: Option<String>,
+            sort_direction: Option<String>,
+            source: Option<String>,
+            purchased_from: Option<String>,
+            purchased_to: Option<String>,
+            released_from: Option<String>,
+            released_to: Option<String>,
+            min_minutes: Option<i64>,
+            max_minutes: Option<i64>,
+            tag: Option<String>,
+            narrated_by_author: Option<bool>,
+            full_cast: Option<bool>,
+        }
+
+        fn parse_date(value: Option<String>) -> crate::Result<Option<chrono::NaiveDate>> {
+            value
+                .map(|v| {
+                    chrono::NaiveDate::parse_from_str(&v, "%Y-%m-%d").map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid date '{}': {}", v, e))
+                    })
+                })
+                .transpose()
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+
+                let purchase_date = crate::storage::DateRange {
+                    from: parse_date(params.purchased_from)?,
+                    to: parse_date(params.purchased_to)?,
+                };
+                let release_date = crate::storage::DateRange {
+                    from: parse_date(params.released_from)?,
+                    to: parse_date(params.released_to)?,
+                };
+                let runtime = crate::storage::RuntimeRange {
+                    min_minutes: params.min_minutes,
+                    max_minutes: params.max_minutes,
+                };
+
+                // Build query parameters
+                let mut query_params = crate::storage::BookQueryParams {
+                    search_query: params.search_query,
+                    series_name: params.series_name,
+                    category: params.category,
+                    source: params.source,
+                    tag: params.tag,
+                    purchase_date: Some(purchase_date),
+                    release_date: Some(release_date),
+                    runtime: Some(runtime),
+                    narrated_by_author: params.narrated_by_author,
+                    full_cast: params.full_cast,
+                    excluded_categories: Vec::new(),
+                    title_locale: crate::storage::localized_titles::get_preferred_metadata_locale(db.pool()).await?,
+                    sort_field: None,
+                    sort_direction: None,
+                    limit: params.limit,
+                    offset: params.offset,
+                };
+                crate::storage::content_filter::get_content_filter(db.pool())
+                    .await?
+                    .apply(&mut query_params);
+
+                // Parse sort field
+                if let Some(field) = params.sort_field {
+                    query_params.sort_field = match field.as_str() {
+                        "title" => Some(crate::storage::SortField::Title),
+                        "release_date" => Some(crate::storage::SortField::ReleaseDate),
+                        "date_added" => Some(crate::storage::SortField::DateAdded),
+                        "series" => Some(crate::storage::SortField::Series),
+                        "length" => Some(crate::storage::SortField::Length),
+                        _ => None,
+                    };
+                }
+
+                // Parse sort direction
+                if let Some(dir) = params.sort_direction {
+                    query_params.sort_direction = match dir.as_str() {
+                        "asc" => Some(crate::storage::SortDirection::Asc),
+                        "desc" => Some(crate::storage::SortDirection::Desc),
+                        _ => None,
+                    };
+                }
+
+                let books =
+                    crate::storage::queries::list_books_with_filters(db.pool(), &query_params)
+                        .await?;
+                let total_count =
+                    crate::storage::queries::count_books_with_filters(db.pool(), &query_params)
+                        .await?;
+
+                // Convert BookWithRelations to JSON with arrays for authors/narrators
+                let books_json: Vec<serde_json::Value> = books.iter().map(|book| {
+                    serde_json::json!({
+                        "id": book.book_id,
+                        "audible_product_id": book.audible_product_id,
+                        "title": book.title,
+                        "subtitle": book.subtitle,
+                        "alternate_title": book.alternate_title,
+                        "description": book.description,
+                        "duration_seconds": book.length_in_minutes * 60,
+                        "language": book.language,
+                        "rating": book.rating_overall,
+                        "cover_url": book.picture_large,
+                        "release_date": book.date_published,
+                        "purchase_date": book.purchase_date,
+                        "created_at": book.created_at,
+                        "updated_at": book.updated_at,
+                        "authors": book.authors_str.as_ref()
+                            .map(|s| s.split(", ").filter(|a| !a.is_empty()).collect::<Vec<_>>())
+                            .unwrap_or_default(),
+                        "narrators": book.narrators_str.as_ref()
+                            .map(|s| s.split(", ").filter(|n| !n.is_empty()).collect::<Vec<_>>())
+                            .unwrap_or_default(),
+                        "publisher": book.publisher,
+                        "series_name": book.series_name,
+                        "series_sequence": book.series_sequence,
+                        "series_order": book.series_order,
+                        "file_path": null,
+                        "pdf_url": book.pdf_url,
+                        "is_finished": book.is_finished,
+                        "is_downloadable": book.is_downloadable,
+                        "is_ayce": book.is_ayce,
+                        "origin_asin": book.origin_asin,
+                        "episode_number": book.episode_number,
+                        "content_delivery_type": book.content_delivery_type,
+                        "benefit_type": book.benefit_type.as_deref().unwrap_or("unknown"),
+                        "format_support": book.format_support.as_deref().unwrap_or("unknown"),
+                        "narrated_by_author": book.narrated_by_author,
+                        "full_cast": book.full_cast,
+                        "is_abridged": book.is_abridged,
+                        "is_spatial": book.is_spatial,
+                        "source": book.source.as_deref().unwrap_or("audible"),
+                    })
+                }).collect();
+
+                let response = serde_json::json!({
+                    "books": books_json,
+                    "total_count": total_count,
+                });
+
+                Ok::<_, crate::LibationError>(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Search the library, best matches first
+///
+/// Matches titles, authors, narrators and series ignoring case and
+/// accents, and with `fuzzy` tolerates small typos. Honors the content
+/// filter and preferred title language like `nativeGetBooksWithFilters`.
+///
+/// # Arguments (JSON)
+/// ```json
+/// {
+///   "db_path": "/path/to/db.sqlite",
+///   "query": "bronte jane eyer",
+///   "fuzzy": true,   // optional, default true
+///   "limit": 50,     // optional, default 50
+///   "offset": 0      // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "hits": [{
+///       "book": {...},
+///       "score": 5.4,
+///       // UTF-16 ranges into the book's fields, for highlighting
+///       "matches": [{ "field": "title", "start": 0, "end": 4, "fuzzy": false }]
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearchLibrary(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSearchLibrary", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            query: String,
+            #[serde(default = "default_true")]
+            fuzzy: bool,
+            #[serde(default = "default_limit")]
+            limit: i64,
+            #[serde(default)]
+            offset: i64,
+        }
+
+        fn default_true() -> bool {
+            true
+        }
+
+        fn default_limit() -> i64 {
+            50
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+
+                let mut query_params = crate::storage::BookQueryParams {
+                    title_locale: crate::storage::localized_titles::get_preferred_metadata_locale(db.pool()).await?,
+                    limit: params.limit,
+                    offset: params.offset,
+                    ..Default::default()
+                };
+                crate::storage::content_filter::get_content_filter(db.pool())
+                    .await?
+                    .apply(&mut query_params);
+
+                let hits =
+                    crate::storage::search::search_library(db.pool(), &params.query, &query_params, params.fuzzy)
+                        .await?;
+                Ok::<_, crate::LibationError>(serde_json::json!({ "hits": hits }))
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Re-derive and check stale cover URLs after an image CDN change
+///
+/// URLs are rebuilt on the current image host from their image id and
+/// checked with a HEAD request; titles without a working one are looked
+/// up in the catalog when `account_json` is given.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "account_json": "{...}",      // optional, for the catalog fallback
+///   "asins": ["B012345678"]       // optional, default the whole library
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "checked": 412,
+///     "rederived": ["B012345678"],
+///     "from_catalog": [],
+///     "unresolved": [],
+///     "skipped": []        // network or server errors; try again later
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRefreshCoverUrls(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRefreshCoverUrls", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(default)]
+            account_json: Option<String>,
+            #[serde(default)]
+            asins: Option<Vec<String>>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let report = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let client = match &params.account_json {
+                    Some(account_json) => {
+                        let account_json = crate::api::auth::ensure_valid_token(db.pool(), account_json, 30).await?;
+                        let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                            .map_err(|e| {
+                                crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                            })?;
+                        Some(crate::api::client::AudibleClient::new(account)?)
+                    }
+                    None => None,
+                };
+                let options = crate::api::covers::CoverRefreshOptions {
+                    asins: params.asins,
+                    ..Default::default()
+                };
+                crate::api::covers::refresh_cover_urls(db.pool(), client.as_ref(), &options).await
+            })?;
+
+            Ok(success_response(report))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Local path of a book's cover, for showing covers offline
+///
+/// Covers are kept in the cover cache under the directory set with
+/// `nativeSetTempRoot`, within the covers quota. A cover that isn't cached
+/// is downloaded first unless `download` is false.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678",
+///   "download": true      // optional, default true
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "asin": "B012345678",
+///     "path": ".../cache/librisync-covers/B012345678-51abcXYZ.jpg"   // null without a cover
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCoverPath(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetCoverPath", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            #[serde(default = "default_true")]
+            download: bool,
+        }
+
+        fn default_true() -> bool {
+            true
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let path = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::covers::get_cover_path(db.pool(), &params.asin, params.download).await
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "asin": params.asin,
+                "path": path.map(|p| p.to_string_lossy().to_string()),
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List podcast shows in the library (for expandable show rows)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "shows": [{ "asin": "B08K56V638", "title": "The Show", "child_count": 24 }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListPodcastShows(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListPodcastShows", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let shows = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::podcasts::list_podcast_shows(db.pool()).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "shows": shows })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List the episodes and seasons of a podcast show or season, in order
+///
+/// Seasons (`"kind": "season"`) are expanded with another call using
+/// their ASIN.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B08K56V638"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "episodes": [{
+///       "asin": "B08K5BXC1Z",
+///       "title": "Episode 1",
+///       "kind": "episode",
+///       "order": "1",
+///       "episode_number": 1,
+///       "length_in_minutes": 42,
+///       "child_count": 0
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListEpisodesForParent(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListEpisodesForParent", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let episodes = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::podcasts::list_episodes_for_parent(db.pool(), &params.asin).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "episodes": episodes })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List user tags with book counts (for the filter UI)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "tags": [{ "name": "favorite", "book_count": 12 }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListTags(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListTags", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let tags = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::tags::list_tags(db.pool()).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "tags": tags })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Add and/or remove tags on every book matching a filter
+///
+/// Filter keys match `nativeGetBooksWithFilters`; an empty filter matches
+/// the whole library.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "search_query": "dune",          // optional
+///   "series_name": "Dune",           // optional
+///   "category": "Science Fiction",   // optional
+///   "source": "audible",             // optional
+///   "tag": "to_read",                // optional
+///   "add": ["favorite"],             // optional
+///   "remove": ["to_read"]            // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "matched_books": 6
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBulkUpdateTags(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeBulkUpdateTags", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            search_query: Option<String>,
+            series_name: Option<String>,
+            category: Option<String>,
+            source: Option<String>,
+            tag: Option<String>,
+            #[serde(default)]
+            add: Vec<String>,
+            #[serde(default)]
+            remove: Vec<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let matched = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let filter = crate::storage::BookQueryParams {
+                    search_query: params.search_query,
+                    series_name: params.series_name,
+                    category: params.category,
+                    source: params.source,
+                    tag: params.tag,
+                    ..Default::default()
+                };
+
+                // Resolve removals first so "remove a, add a" leaves the tag on
+                let removed =
+                    crate::storage::tags::remove_tags_from_books(db.pool(), &filter, &params.remove)
+                        .await?;
+                let added =
+                    crate::storage::tags::add_tags_to_books(db.pool(), &filter, &params.add)
+                        .await?;
+                Ok::<u64, crate::LibationError>(removed.max(added))
+            })?;
+
+            Ok(success_response(serde_json::json!({ "matched_books": matched })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Rename a tag, or merge several tags into one
+///
+/// Renaming onto an existing tag merges them.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "sources": ["scifi", "science_fiction"],
+///   "target": "sci_fi"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "tags": [{ "name": "sci_fi", "book_count": 14 }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeMergeTags(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeMergeTags", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            sources: Vec<String>,
+            target: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let tags = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::tags::merge_tags(db.pool(), &params.sources, &params.target).await?;
+                crate::storage::tags::list_tags(db.pool()).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "tags": tags })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Delete a tag from all books
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "tag": "to_read"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "tags": [{ "name": "favorite", "book_count": 12 }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDeleteTag(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDeleteTag", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            tag: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let tags = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::tags::delete_tag(db.pool(), &params.tag).await?;
+                crate::storage::tags::list_tags(db.pool()).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "tags": tags })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Import tags from Libation's space-delimited tag strings
+///
+/// For tags exported from Libation desktop or read from an older
+/// database. Strings are parsed the way Libation sanitizes them
+/// (lowercased, punctuation dropped, split on whitespace). Books keep
+/// their tags and gain the imported ones.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "entries": [{ "asin": "B012345678", "tags": "Sci-Fi  to_read" }]
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "books_tagged": 1,
+///     "unknown_asins": [],
+///     "tags": [{ "name": "scifi", "book_count": 1 }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeImportLegacyTags(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeImportLegacyTags", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Entry {
+            asin: String,
+            tags: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            entries: Vec<Entry>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let entries: Vec<(String, String)> =
+                params.entries.into_iter().map(|entry| (entry.asin, entry.tags)).collect();
+            let (import, tags) = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let import = crate::storage::tags::import_legacy_tags(db.pool(), &entries).await?;
+                let tags = crate::storage::tags::list_tags(db.pool()).await?;
+                Ok::<_, crate::LibationError>((import, tags))
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "books_tagged": import.books_tagged,
+                "unknown_asins": import.unknown_asins,
+                "tags": tags,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List the active profile's notes on a book
+///
+/// Notes are private and local: library sync never touches them.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "notes": [{
+///       "note_id": 3,
+///       "asin": "B012345678",
+///       "text": "Great monologue",
+///       "position_ms": 3600000,  // null for notes on the whole book
+///       "created_at": "2025-01-01T03:00:00.000Z",
+///       "updated_at": "2025-01-01T03:00:00.000Z"
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListNotes(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListNotes", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let notes = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::notes::list_notes(db.pool(), &params.asin).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "notes": notes })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Add a note to a book, or replace one with `note_id`
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678",       // needed for new notes
+///   "note_id": 3,               // optional, edits this note
+///   "text": "Great monologue",
+///   "position_ms": 3600000      // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "note": { "note_id": 3, ... } } // as in nativeListNotes
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSaveNote(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSaveNote", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(default)]
+            asin: Option<String>,
+            #[serde(default)]
+            note_id: Option<i64>,
+            text: String,
+            #[serde(default)]
+            position_ms: Option<i64>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let note = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                match (params.note_id, params.asin.as_deref()) {
+                    (Some(note_id), _) => {
+                        crate::storage::notes::update_note(db.pool(), note_id, &params.text, params.position_ms).await
+                    }
+                    (None, Some(asin)) => {
+                        crate::storage::notes::add_note(db.pool(), asin, &params.text, params.position_ms).await
+                    }
+                    (None, None) => Err(crate::LibationError::InvalidInput(
+                        "asin or note_id is required".to_string(),
+                    )),
+                }
+            })?;
+
+            Ok(success_response(serde_json::json!({ "note": note })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Delete a note
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "note_id": 3
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "deleted": true } // false if there was no such note
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDeleteNote(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDeleteNote", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            note_id: i64,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let deleted = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::notes::delete_note(db.pool(), params.note_id).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "deleted": deleted })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Full-text search over the active profile's notes
+///
+/// Every word must match the start of a word in the note, ignoring case
+/// and accents. Best matches come first.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "query": "bender mono",
+///   "limit": 50 // optional, default 50
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "results": [{
+///       "note_id": 3, "asin": "B012345678", "text": "Great Bender monologue", ...,
+///       "title": "We Are Legion",
+///       "snippet": "Great [Bender] [monologue]"
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSearchNotes(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSearchNotes", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            query: String,
+            #[serde(default = "default_limit")]
+            limit: i64,
+        }
+
+        fn default_limit() -> i64 {
+            50
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let results = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::notes::search_notes(db.pool(), &params.query, params.limit).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "results": results })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Store a book's playback position
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678",
+///   "position_ms": 3600000
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "progress_percent": 42.5 }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetListeningPosition(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetListeningPosition", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            position_ms: i64,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let percent = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::progress::set_listening_position(db.pool(), &params.asin, params.position_ms).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "progress_percent": percent })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get a book's playback position, synced with Audible when an account is given
+///
+/// With `account_json`, Audible's last-heard position replaces the local
+/// one when it was set later, a newer local position is pushed, and
+/// bookmarks are refreshed (see `api::annotations`). If Audible can't be
+/// reached, the local position and stored bookmarks are returned with
+/// `sync_error`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678",
+///   "account_json": "{...}"  // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "position": {
+///       "asin": "B012345678",
+///       "position_ms": 3600000,
+///       "updated_at": "2025-01-01T12:00:00.000Z",
+///       "synced_at": "2025-01-01T12:00:05.000Z",
+///       "pending_push": false
+///     },
+///     "bookmarks": [{ "kind": "bookmark", "annotation_id": "...", "position_ms": 120000, ... }],
+///     "pulled": true,
+///     "pushed": false,
+///     "sync_error": null,
+///     "bookmarks_error": null
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetPlaybackPosition(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetPlaybackPosition", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            #[serde(default)]
+            account_json: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let pool = db.pool();
+
+                let synced = match &params.account_json {
+                    Some(account_json) => Some(
+                        async {
+                            let account_json = crate::api::auth::ensure_valid_token(pool, account_json, 30).await?;
+                            let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                                .map_err(|e| {
+                                    crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                                })?;
+                            let client = crate::api::client::AudibleClient::new(account)?;
+                            client.sync_playback_position(pool, &params.asin).await
+                        }
+                        .await,
+                    ),
+                    None => None,
+                };
+
+                let response = match synced {
+                    Some(Ok(sync)) => serde_json::json!({
+                        "position": sync.position,
+                        "bookmarks": sync.bookmarks,
+                        "pulled": sync.pulled,
+                        "pushed": sync.pushed,
+                        "sync_error": null,
+                        "bookmarks_error": sync.bookmarks_error,
+                    }),
+                    other => serde_json::json!({
+                        "position": crate::storage::listening_positions::get_playback_position(pool, &params.asin).await?,
+                        "bookmarks": crate::storage::listening_positions::list_annotations(pool, &params.asin).await?,
+                        "pulled": false,
+                        "pushed": false,
+                        "sync_error": other.and_then(|r| r.err()).map(|e| e.to_string()),
+                        "bookmarks_error": null,
+                    }),
+                };
+
+                Ok::<_, crate::LibationError>(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Store a book's playback position and send it to Audible when an account is given
+///
+/// The position is always stored locally first. If Audible can't be
+/// reached, it stays pending (`position.pending_push`) and is sent by the
+/// next `nativeGetPlaybackPosition` or `nativeSetPlaybackPosition` with an
+/// account.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678",
+///   "position_ms": 3600000,
+///   "account_json": "{...}"  // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "progress_percent": 42.5,
+///     "position": { "asin": "B012345678", "position_ms": 3600000, "pending_push": false, ... },
+///     "pushed": true,
+///     "sync_error": null
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetPlaybackPosition(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetPlaybackPosition", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            position_ms: i64,
+            #[serde(default)]
+            account_json: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let pool = db.pool();
+                let percent =
+                    crate::storage::progress::set_listening_position(pool, &params.asin, params.position_ms).await?;
+
+                let pushed = match &params.account_json {
+                    Some(account_json) => Some(
+                        async {
+                            let account_json = crate::api::auth::ensure_valid_token(pool, account_json, 30).await?;
+                            let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                                .map_err(|e| {
+                                    crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                                })?;
+                            let client = crate::api::client::AudibleClient::new(account)?;
+                            client.push_playback_position(pool, &params.asin).await
+                        }
+                        .await,
+                    ),
+                    None => None,
+                };
+
+                let position = crate::storage::listening_positions::get_playback_position(pool, &params.asin).await?;
+                Ok::<_, crate::LibationError>(serde_json::json!({
+                    "progress_percent": percent,
+                    "pushed": matches!(pushed, Some(Ok(_))),
+                    "sync_error": pushed.and_then(|r| r.err()).map(|e| e.to_string()),
+                    "position": position,
+                }))
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List books started but not finished, most recently played first
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "limit": 20,   // optional, default 20
+///   "offset": 0    // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "books": [{
+///       "asin": "B012345678",
+///       "title": "Dune",
+///       "authors_str": "Frank Herbert",
+///       "picture_id": "51abc",
+///       "length_in_minutes": 1260,
+///       "position_ms": 3600000,
+///       "progress_percent": 4.76,
+///       "progress_updated_at": "2025-01-01T12:00:00Z"
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListInProgress(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListInProgress", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(default = "default_limit")]
+            limit: i64,
+            #[serde(default)]
+            offset: i64,
+        }
+
+        fn default_limit() -> i64 {
+            20
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let books = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::progress::list_in_progress(db.pool(), params.limit, params.offset).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "books": books })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Where to resume playback of a book
+///
+/// Steps back `rewind_ms` from the stored position (see
+/// `nativeSetListeningPosition`), but never past the start of the chapter
+/// the position is in, and starts the chapter over when that lands within
+/// `snap_ms` of its start. Both platforms use this so they resume at the
+/// same spot.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678",
+///   "rewind_ms": 10000 // optional, overrides the stored option
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "position_ms": 3590000,
+///     "stored_position_ms": 3600000,
+///     "chapter_index": 4,          // null if the book has no stored chapters
+///     "chapter_title": "Chapter 4"
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetResumePosition(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetResumePosition", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            #[serde(default)]
+            rewind_ms: Option<i64>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let point = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::audio::resume::resume_point(db.pool(), &params.asin, params.rewind_ms).await
+            })?;
+
+            Ok(success_response(point))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the smart resume options
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "rewind_ms": 10000, "snap_ms": 3000 }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetResumeOptions(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetResumeOptions", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let options = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::audio::resume::get_resume_options(db.pool()).await
+            })?;
+
+            Ok(success_response(options))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the smart resume options
+///
+/// `rewind_ms` is at most 300000 (five minutes); 0 resumes exactly where
+/// playback stopped.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "options": { "rewind_ms": 15000, "snap_ms": 3000 } // omitted fields use defaults
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// { "success": true, "data": { "options": { "rewind_ms": 15000, "snap_ms": 3000 } } }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetResumeOptions(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetResumeOptions", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            options: crate::audio::resume::ResumeOptions,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::audio::resume::set_resume_options(db.pool(), &params.options).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "options": params.options })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Apply many small writes in one transaction
+///
+/// Each operation runs in its own savepoint: a failing one is rolled back
+/// and reported while the rest commit, unless `all_or_nothing` is set.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "operations": [
+///     { "op": "set_setting", "key": "ui.theme", "value": "dark" },
+///     { "op": "delete_setting", "key": "ui.old" },
+///     { "op": "set_tags", "asin": "B012345678", "tags": ["favorite"] },
+///     { "op": "set_finished", "asin": "B012345678", "is_finished": true },
+///     { "op": "set_user_rating", "asin": "B012345678", "overall": 4.5, "performance": 5, "story": 4 }
+///   ],
+///   "all_or_nothing": false  // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "results": [{ "index": 0, "ok": true, "error": null }],
+///     "applied": 1,
+///     "failed": 0,
+///     "committed": true
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBatchWrite(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeBatchWrite", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            operations: Vec<crate::storage::batch::BatchOperation>,
+            #[serde(default)]
+            all_or_nothing: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let report = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::batch::execute_batch(db.pool(), &params.operations, params.all_or_nothing).await
+            })?;
+
+            Ok(success_response(report))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Normalize chapter titles for a book and store the result for tagging
+///
+/// Uses `chapters` when given, otherwise the book's stored chapters, and
+/// falls back to Audible's `chapter_info` when neither exists.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "asin": "B012345678",
+///   "chapters": [{ "title": "Track 01", "start_ms": 0, "end_ms": 61000 }],  // optional
+///   "chapter_info": { "chapters": [...] },  // optional, from the content license
+///   "naming": {                              // optional, defaults shown
+///     "template": "Chapter {n}: {title}",
+///     "only_generic": true,
+///     "strip_numbering": false,
+///     "use_audible_titles": true
+///   },
+///   "save": true
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "chapters": [{ "title": "Opening Credits", "start_ms": 0, "end_ms": 61000 }],
+///     "ffmetadata": ";FFMETADATA1\n..."
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeNormalizeChapters(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeNormalizeChapters", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            chapters: Option<Vec<crate::audio::Chapter>>,
+            chapter_info: Option<crate::api::content::ChapterInfo>,
+            #[serde(default)]
+            naming: crate::audio::ChapterNaming,
+            #[serde(default = "default_true")]
+            save: bool,
+        }
+
+        fn default_true() -> bool {
+            true
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let chapters = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let audible = params
+                    .chapter_info
+                    .as_ref()
+                    .map(crate::audio::chapters::chapters_from_audible);
+
+                let source = match params.chapters {
+                    Some(chapters) => chapters,
+                    None => {
+                        let stored = crate::storage::chapters::get_chapters(db.pool(), &params.asin).await?;
+                        if stored.is_empty() {
+                            audible.clone().unwrap_or_default()
+                        } else {
+                            stored
+                        }
+                    }
+                };
+                if source.is_empty() {
+                    return Err(crate::LibationError::invalid_input(format!(
+                        "No chapters to normalize for {}",
+                        params.asin
+                    )));
+                }
+
+                let chapters = crate::audio::normalize_chapter_titles(&source, &params.naming, audible.as_deref());
+                if params.save {
+                    crate::storage::chapters::update_chapters(db.pool(), &params.asin, &chapters).await?;
+                }
+                Ok(chapters)
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "ffmetadata": crate::audio::ChapterEditor::generate_ffmetadata(&chapters),
+                "chapters": chapters,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the stored chapters of a book
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "asin": "B012345678"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "chapters": [{ "title": "Opening Credits", "start_ms": 0, "end_ms": 61000 }],
+///     "ffmetadata": ";FFMETADATA1\n..."
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetChapters(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetChapters", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let chapters = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::chapters::get_chapters(db.pool(), &params.asin).await
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "ffmetadata": crate::audio::ChapterEditor::generate_ffmetadata(&chapters),
+                "chapters": chapters,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Replace a book's chapters with user-edited ones
+///
+/// With `file_path`, the chapters are also re-embedded when native FFmpeg
+/// is available; otherwise the app re-tags with the returned `ffmetadata`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "asin": "B012345678",
+///   "chapters": [{ "title": "Opening Credits", "start_ms": 0, "end_ms": 61000 }],
+///   "file_path": "/storage/emulated/0/Audiobooks/Book.m4b"  // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "chapters": [...],
+///     "ffmetadata": ";FFMETADATA1\n...",
+///     "embedded": false,
+///     "lock_retries": [] // [{ "stage", "attempt", "error", "delay_ms", "failed_at" }]
+///   }
+/// }
+/// ```
+///
+/// Embedding is retried for up to 30 s while another process (usually the
+/// media scanner) holds the file; `lock_retries` lists the waits.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUpdateChapters(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeUpdateChapters", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            chapters: Vec<crate::audio::Chapter>,
+            file_path: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let mut lock_retries = Vec::new();
+            let embedded = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::chapters::update_chapters(db.pool(), &params.asin, &params.chapters).await?;
+
+                let native = crate::audio::get_audio_capabilities().backend == crate::audio::AudioBackend::Native;
+                match &params.file_path {
+                    Some(path) if native => {
+                        crate::audio::retry_while_locked(
+                            crate::audio::TagStage::Chapters,
+                            &crate::audio::LockRetryPolicy::default(),
+                            &mut lock_retries,
+                            || crate::audio::ChapterEditor::embed_chapters(std::path::Path::new(path), &params.chapters),
+                        )
+                        .await?;
+                        Ok::<_, crate::LibationError>(true)
+                    }
+                    _ => Ok(false),
+                }
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "ffmetadata": crate::audio::ChapterEditor::generate_ffmetadata(&params.chapters),
+                "chapters": params.chapters,
+                "embedded": embedded,
+                "lock_retries": lock_retries,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get all unique series names from library
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "series": ["Harry Potter", "Lord of the Rings", ...]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAllSeries(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetAllSeries", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let series = crate::storage::queries::list_all_series(db.pool()).await?;
+
+                let response = serde_json::json!({
+                    "series": series,
+                });
+
+                Ok::<_, crate::LibationError>(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the next unfinished book after a book in each of its series
+///
+/// Series order follows the parsed sequence ("Prequel", "0.5", "1", "1-3",
+/// "2", then text entries); an omnibus isn't next after a book it contains.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B012345678"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "next": [{
+///       "series_id": 3,
+///       "series_name": "The Expanse",
+///       "book_id": 12,
+///       "asin": "B0123456789",
+///       "title": "Caliban's War",
+///       "sequence": "2",
+///       "is_finished": false
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetNextInSeries(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetNextInSeries", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let next = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let book = crate::storage::queries::find_book_by_asin(db.pool(), &params.asin)
+                    .await?
+                    .ok_or_else(|| crate::LibationError::not_found(format!("Book {}", params.asin)))?;
+                crate::storage::series_order::next_in_series(db.pool(), book.book_id).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "next": next })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get all unique categories/genres from library
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "categories": ["Fantasy", "Science Fiction", ...]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAllCategories(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetAllCategories", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let categories = crate::storage::queries::list_all_categories(db.pool()).await?;
+
+                let response = serde_json::json!({
+                    "categories": categories,
+                });
+
+                Ok::<_, crate::LibationError>(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// DECRYPTION FUNCTIONS
+// ============================================================================
+
+/// Decrypt AAX file to M4B using activation bytes (native AES, no FFmpeg)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "input_path": "/storage/emulated/0/Download/book.aax",
+///   "output_path": "/storage/emulated/0/Download/book.m4b",
+///   "activation_bytes": "1CEB00DA",
+///   "job_id": "decrypt-1", // optional, for nativeCancelJob and nativeGetJob
+///   "db_path": "/data/data/.../libation.db" // optional, records the job
+/// }
+/// ```
+///
+/// A cancelled decryption removes the partial output. With `db_path` the
+/// job and its output file are recorded for `nativeGetJob`.
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "output_path": "/storage/emulated/0/Download/book.m4b",
+///     "file_size": 123456789,
+///     "bytes_per_second": 250000000
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDecryptAAX(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDecryptAAX", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            input_path: String,
+            output_path: String,
+            activation_bytes: String,
+            #[serde(default)]
+            job_id: Option<String>,
+            #[serde(default)]
+            db_path: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let activation_bytes =
+                crate::crypto::activation::ActivationBytes::from_hex(&params.activation_bytes)?;
+
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Decryption)?;
+            let result = RUNTIME.block_on(async {
+                let db = match params.db_path.as_deref() {
+                    Some(path) => Some(crate::storage::Database::new(path).await?),
+                    None => None,
+                };
+
+                let work = async {
+                    let decrypter = crate::crypto::aax::AaxDecrypter::new(activation_bytes);
+
+                    let input_path = std::path::Path::new(&params.input_path);
+                    let output_path = std::path::Path::new(&params.output_path);
+
+                    let job_id = job.job_id().to_string();
+                    let on_progress = move |progress: crate::crypto::DecryptProgress| {
+                        crate::events::emit_progress(crate::events::ProgressEvent::new(
+                            crate::cancel::JobKind::Decryption,
+                            &job_id,
+                            None,
+                            progress.bytes_processed,
+                            progress.total_bytes,
+                        ));
+                    };
+                    let stats = decrypter
+                        .decrypt_cancellable(input_path, output_path, on_progress, job.token())
+                        .await?;
+
+                    let file_size = tokio::fs::metadata(output_path)
+                        .await
+                        .map(|m| m.len())
+                        .unwrap_or(0);
+
+                    if let Some(db) = &db {
+                        crate::storage::jobs::add_job_artifacts(
+                            db.pool(),
+                            job.job_id(),
+                            &[crate::storage::jobs::JobArtifact::file(params.output_path.as_str())],
+                        )
+                        .await?;
+                    }
+
+                    let response = serde_json::json!({
+                        "output_path": params.output_path,
+                        "file_size": file_size,
+                        "bytes_per_second": stats.bytes_per_second,
+                    });
+
+                    Ok::<_, crate::LibationError>(response)
+                };
+
+                match &db {
+                    Some(db) => crate::storage::jobs::run_job(db.pool(), &job, work).await,
+                    None => work.await,
+                }
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Decrypt AAXC file to M4B using the license key and IV (native AES, no FFmpeg)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "input_path": "/storage/emulated/0/Download/book.aaxc",
+///   "output_path": "/storage/emulated/0/Download/book.m4b",
+///   "aaxc_key": "hex-key", // 32 hex chars, from the license
+///   "aaxc_iv": "hex-iv", // 32 hex chars
+///   "job_id": "decrypt-1", // optional, for nativeCancelJob and nativeGetJob
+///   "db_path": "/data/data/.../libation.db" // optional, records the job
+/// }
+/// ```
+///
+/// A cancelled decryption removes the partial output. With `db_path` the
+/// job and its output file are recorded for `nativeGetJob`.
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "output_path": "/storage/emulated/0/Download/book.m4b",
+///     "file_size": 123456789,
+///     "bytes_per_second": 250000000
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDecryptAAXC(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDecryptAAXC", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            input_path: String,
+            output_path: String,
+            aaxc_key: String,
+            aaxc_iv: String,
+            #[serde(default)]
+            job_id: Option<String>,
+            #[serde(default)]
+            db_path: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let decrypter =
+                crate::crypto::aaxc::AaxcDecrypter::from_hex(&params.aaxc_key, &params.aaxc_iv)?;
+
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Decryption)?;
+            let result = RUNTIME.block_on(async {
+                let db = match params.db_path.as_deref() {
+                    Some(path) => Some(crate::storage::Database::new(path).await?),
+                    None => None,
+                };
+
+                let work = async {
+                    let input_path = std::path::Path::new(&params.input_path);
+                    let output_path = std::path::Path::new(&params.output_path);
+
+                    let job_id = job.job_id().to_string();
+                    let on_progress = move |progress: crate::crypto::DecryptProgress| {
+                        crate::events::emit_progress(crate::events::ProgressEvent::new(
+                            crate::cancel::JobKind::Decryption,
+                            &job_id,
+                            None,
+                            progress.bytes_processed,
+                            progress.total_bytes,
+                        ));
+                    };
+                    let stats = decrypter
+                        .decrypt_cancellable(input_path, output_path, on_progress, job.token())
+                        .await?;
+
+                    let file_size = tokio::fs::metadata(output_path)
+                        .await
+                        .map(|m| m.len())
+                        .unwrap_or(0);
+
+                    if let Some(db) = &db {
+                        crate::storage::jobs::add_job_artifacts(
+                            db.pool(),
+                            job.job_id(),
+                            &[crate::storage::jobs::JobArtifact::file(params.output_path.as_str())],
+                        )
+                        .await?;
+                    }
+
+                    let response = serde_json::json!({
+                        "output_path": params.output_path,
+                        "file_size": file_size,
+                        "bytes_per_second": stats.bytes_per_second,
+                    });
+
+                    Ok::<_, crate::LibationError>(response)
+                };
+
+                match &db {
+                    Some(db) => crate::storage::jobs::run_job(db.pool(), &job, work).await,
+                    None => work.await,
+                }
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// DATABASE FUNCTIONS
+// ============================================================================
+
+/// Initialize database at specified path
+///
+/// Checks integrity first and repairs or rebuilds a damaged database,
+/// quarantining the damaged file. When `recovery.data_lost` is true the app
+/// should offer to restore from a backup. Tokens from a refresh that was
+/// interrupted before they were saved are merged into their accounts.
+///
+/// A new install gets the latest schema in one step (`created: true`) and
+/// the optional `seed` rows; on later launches `seed` is ignored.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "seed": {                        // optional
+///     "preferred_locale": "uk",      // marketplace country code
+///     "settings": { "playback.resume": { "rewind_ms": 15000 } }
+///   }
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "initialized": true,
+///     "created": true,        // schema created by this call (new install)
+///     "seeded_settings": 2,   // seed settings stored
+///     "recovery": {
+///       "database_path": "...",
+///       "outcome": "healthy",     // "reindexed", "rebuilt" or "recreated"
+///       "integrity_errors": [],
+///       "quarantined_path": null,
+///       "tables": [],             // per-table rows salvaged when rebuilt
+///       "data_lost": false
+///     },
+///     "recovered_token_refreshes": ["user@example.com"]  // accounts whose interrupted refresh was saved
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeInitDatabase(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeInitDatabase", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(default)]
+            seed: Option<crate::storage::bootstrap::BootstrapSeed>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let (db, recovery) =
+                    crate::storage::Database::open_with_recovery(&params.db_path).await?;
+                db.run_startup_tasks().await?;
+                let seeded_settings = match &params.seed {
+                    Some(seed) if db.was_created() => {
+                        crate::storage::bootstrap::apply_seed(db.pool(), seed).await?
+                    }
+                    _ => 0,
+                };
+                let recovered_token_refreshes =
+                    crate::storage::accounts::recover_pending_token_refreshes(db.pool()).await?;
+
+                let response = serde_json::json!({
+                    "initialized": true,
+                    "created": db.was_created(),
+                    "seeded_settings": seeded_settings,
+                    "recovery": recovery,
+                    "recovered_token_refreshes": recovered_token_refreshes,
+                });
+
+                Ok::<_, crate::LibationError>(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Pause database writes for an OS backup
+///
+/// Checkpoints the WAL into the database file and holds the write lock, so
+/// a backup taken now copies a complete, unchanging file. Writes wait
+/// until `nativeEndQuiesce` (up to the 30 s busy timeout, then fail); the
+/// lock is released after `max_duration_ms` if the end call never comes.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "wait_ms": 10000,          // optional, time for writes in flight to finish
+///   "max_duration_ms": 25000   // optional, at most 300000
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "db_path": "/data/data/.../libation.db",
+///     "since": "2025-03-01T12:00:00Z",
+///     "expires_at": "2025-03-01T12:00:25Z"
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBeginQuiesce(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeBeginQuiesce", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(default)]
+            wait_ms: Option<u64>,
+            #[serde(default)]
+            max_duration_ms: Option<u64>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let wait = params
+                .wait_ms
+                .map_or(crate::storage::quiesce::DEFAULT_QUIESCE_WAIT, std::time::Duration::from_millis);
+            let max_duration = params
+                .max_duration_ms
+                .map_or(crate::storage::quiesce::DEFAULT_MAX_QUIESCE, std::time::Duration::from_millis);
+            let status = RUNTIME.block_on(crate::storage::quiesce::begin_quiesce(
+                std::path::Path::new(&params.db_path),
+                wait,
+                max_duration,
+            ))?;
+
+            Ok(success_response(status))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Let database writes continue after an OS backup
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "released": true }  // false if it had already expired
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeEndQuiesce(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeEndQuiesce", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let released =
+                RUNTIME.block_on(crate::storage::quiesce::end_quiesce(std::path::Path::new(&params.db_path)));
+            Ok(success_response(serde_json::json!({ "released": released })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// UTILITY FUNCTIONS
+// ============================================================================
+
+/// Validate activation bytes format
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "activation_bytes": "1CEB00DA"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "valid": true
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeValidateActivationBytes(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeValidateActivationBytes", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            activation_bytes: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let valid =
+                crate::crypto::activation::ActivationBytes::from_hex(&params.activation_bytes)
+                    .is_ok();
+
+            let response = serde_json::json!({
+                "valid": valid,
+            });
+
+            Ok(success_response(response))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get list of supported locales
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {}
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "locales": [
+///       {"country_code": "us", "name": "United States", "domain": "audible.com"},
+///       ...
+///     ]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetSupportedLocales(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let response = catch_panic(move || {
+        let locales = crate::api::auth::Locale::all();
+
+        let response = serde_json::json!({
+            "locales": locales,
+        });
+
+        success_response(response)
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the bridge protocol versions and capabilities
+///
+/// Call at startup with the protocol version the JS layer was written
+/// against; all later responses are shaped for that version (see
+/// `bridge_protocol`). Without `client_protocol_version` nothing changes.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "client_protocol_version": 1  // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "protocol_version": 1,
+///   "data": {
+///     "protocol_version": 2,
+///     "min_protocol_version": 1,
+///     "negotiated_protocol_version": 1,
+///     "core_version": "0.0.1",
+///     "capabilities": ["batch_write", "cdn_mirrors", ...]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBridgeInfo(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetBridgeInfo", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            client_protocol_version: Option<u32>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            if let Some(version) = params.client_protocol_version {
+                crate::bridge_protocol::negotiate(version)?;
+            }
+
+            Ok(success_response(crate::bridge_protocol::bridge_info()))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the permission policy (operations disabled by the host)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {}
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "denied": ["clear_library", "delete_files"], "locked": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetPermissions(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let _call = crate::bridge_schema::enter_call("nativeGetPermissions");
+    let response = catch_panic(move || success_response(crate::permissions::policy()));
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Disable destructive operations for this process
+///
+/// Disabled operations fail with "Permission denied" however they are
+/// reached (see `permissions`): `clear_library`, `delete_files`,
+/// `remove_accounts`, `deregister_device`. With `lock`, the policy can't
+/// be changed again until the app restarts; set it at startup.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "denied": ["clear_library", "delete_files", "remove_accounts", "deregister_device"],
+///   "locked": true   // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// The policy now in effect, as `nativeGetPermissions`.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetPermissions(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetPermissions", &params_str_result);
+
+    let response = catch_panic(move || {
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let policy: crate::permissions::PermissionPolicy = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            crate::permissions::set_policy(policy)?;
+            Ok(success_response(crate::permissions::policy()))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Build file path using naming pattern
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",
+///   "naming_pattern": "author_series_book",  // or "flat_file", "author_book_folder"
+///   "collision_strategy": "append_asin",     // optional: "increment_suffix" (default), "append_asin", "append_narrator"
+///   "existing_paths": ["Dennis E. Taylor/..."],  // optional, relative paths already used at the destination
+///   "base_directory": "/storage/.../Audiobooks"  // optional, files under it count as taken
+/// }
+/// ```
+///
+/// Paths are compared case- and Unicode-normalization-insensitively; a
+/// colliding path is renamed using `collision_strategy`.
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "file_path": "Dennis E. Taylor/Bobiverse 3 - All These Worlds/Bobiverse 3 - All These Worlds.m4b"
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeBuildFilePath(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeBuildFilePath", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            naming_pattern: String,
+            collision_strategy: Option<String>,
+            #[serde(default)]
+            existing_paths: Vec<String>,
+            base_directory: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                // Get book metadata
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let book = crate::storage::queries::find_book_with_relations_by_asin(
+                    db.pool(),
+                    &params.asin,
+                )
+                .await?
+                .ok_or_else(|| {
+                    crate::LibationError::not_found(format!("Book not found: {}", params.asin))
+                })?;
+
+                // Convert to AudioMetadata
+                let metadata = book.to_audio_metadata();
+
+                // Parse naming pattern
+                let pattern =
+                    crate::file::paths::NamingPattern::from_string(&params.naming_pattern)
+                        .unwrap_or(crate::file::paths::NamingPattern::AuthorSeriesBook);
+
+                let strategy = params
+                    .collision_strategy
+                    .as_deref()
+                    .and_then(crate::file::paths::CollisionStrategy::from_string)
+                    .unwrap_or_default();
+
+                // Build path
+                let file_path = crate::file::paths::build_unique_file_path(
+                    &metadata,
+                    pattern,
+                    "m4b",
+                    strategy,
+                    &params.existing_paths,
+                    params.base_directory.as_deref().map(std::path::Path::new),
+                )?;
+
+                Ok::<_, crate::LibationError>(serde_json::json!({
+                    "file_path": file_path,
+                }))
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Preview a liberation without downloading anything
+///
+/// Resolves the output path (and cover path), download format and codec,
+/// file sizes and the free space needed, from the database alone. Sizes
+/// are estimates unless a license was archived with `nativePrepareOffline`
+/// (`exact_size`). Template errors and over-long paths fail here, so show
+/// the result as a confirmation before `nativeDownloadBook`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",
+///   "quality": "High",                        // optional
+///   "output_format": "m4b",                   // optional: m4b, m4a or mp3
+///   "mp3_bitrate_kbps": 64,                   // optional
+///   "naming_pattern": "author_series_book",   // optional
+///   "collision_strategy": "append_asin",      // optional
+///   "existing_paths": ["..."],                // optional
+///   "base_directory": "/storage/emulated/0/Audiobooks",  // optional
+///   "include_cover": true                     // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "asin": "B07T2F8VJM",
+///     "title": "All These Worlds",
+///     "quality": "High",
+///     "download_format": "aaxc",
+///     "codec": "AAC_LC",
+///     "output_format": "M4b",
+///     "output_codec": "AAC_LC",
+///     "files": [
+///       { "kind": "audio", "path": "/storage/.../All These Worlds.m4b", "estimated_bytes": 576000000 },
+///       { "kind": "cover", "path": "/storage/.../All These Worlds.jpg", "estimated_bytes": 500000 }
+///     ],
+///     "download_bytes": 576000000,
+///     "exact_size": false,
+///     "required_free_bytes": 1152500000
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativePlanLiberation(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativePlanLiberation", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            quality: Option<crate::api::content::DownloadQuality>,
+            output_format: Option<String>,
+            mp3_bitrate_kbps: Option<u32>,
+            naming_pattern: Option<String>,
+            collision_strategy: Option<String>,
+            #[serde(default)]
+            existing_paths: Vec<String>,
+            base_directory: Option<String>,
+            #[serde(default)]
+            include_cover: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let defaults = crate::download::plan::LiberationOptions::default();
+            let options = crate::download::plan::LiberationOptions {
+                quality: params.quality.unwrap_or(defaults.quality),
+                output_format: params
+                    .output_format
+                    .as_deref()
+                    .map(crate::audio::decoder::AudioFormat::from_extension)
+                    .unwrap_or(defaults.output_format),
+                mp3_bitrate_kbps: params.mp3_bitrate_kbps.unwrap_or(defaults.mp3_bitrate_kbps),
+                naming_pattern: match params.naming_pattern.as_deref() {
+                    Some(name) => crate::file::paths::NamingPattern::from_string(name).ok_or_else(|| {
+                        crate::LibationError::invalid_input(format!("Unknown naming pattern: {}", name))
+                    })?,
+                    None => defaults.naming_pattern,
+                },
+                collision_strategy: params
+                    .collision_strategy
+                    .as_deref()
+                    .and_then(crate::file::paths::CollisionStrategy::from_string)
+                    .unwrap_or_default(),
+                existing_paths: params.existing_paths,
+                base_directory: params.base_directory.map(std::path::PathBuf::from),
+                include_cover: params.include_cover,
+            };
+
+            let plan = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::download::plan::plan_liberation(db.pool(), &params.asin, &options).await
+            })?;
+
+            Ok(success_response(plan))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set or clear the watch folder for auto-import
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "path": "/storage/emulated/0/Sync/Audiobooks"  // null to stop watching
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "path": "/storage/emulated/0/Sync/Audiobooks"
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetWatchFolder(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetWatchFolder", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            path: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::watch_folder::set_watch_folder(db.pool(), params.path.as_deref()).await?;
+
+                Ok::<_, crate::LibationError>(serde_json::json!({
+                    "path": params.path,
+                }))
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Import new files from the watch folder
+///
+/// Call on app start or from a scheduled job. Files are matched by their
+/// embedded ASIN; M4B/M4A files are moved into the library and AAX files
+/// are decrypted into it when `activation_bytes` is given. Files modified
+/// in the last `min_file_age_secs` (default 60) may still be syncing and
+/// are left for the next scan.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "library_dir": "/storage/.../Audiobooks",
+///   "naming_pattern": "author_series_book",  // optional
+///   "collision_strategy": "append_asin",     // optional
+///   "activation_bytes": "1CEB00DA",          // optional, needed for AAX
+///   "min_file_age_secs": 60,                 // optional
+///   "job_id": "scan-1"                       // optional, for nativeCancelJob and nativeGetJob
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "configured": true,   // false (and nothing else) if no watch folder is set
+///     "folder": "/storage/emulated/0/Sync/Audiobooks",
+///     "imported": [{
+///       "source_path": ".../book.aax",
+///       "asin": "B07T2F8VJM",
+///       "title": "All These Worlds",
+///       "output_path": "/storage/.../Audiobooks/Dennis E. Taylor/.../All These Worlds.m4b",
+///       "action": "liberated"   // or "moved"
+///     }],
+///     "skipped": [{ "path": ".../other.m4b", "asin": null, "reason": "No embedded ASIN" }],
+///     "pending": [".../still-syncing.m4b"]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeScanWatchFolder(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeScanWatchFolder", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            library_dir: String,
+            naming_pattern: Option<String>,
+            collision_strategy: Option<String>,
+            activation_bytes: Option<String>,
+            min_file_age_secs: Option<u64>,
+            #[serde(default)]
+            job_id: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Scan)?;
+            let result = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let Some(folder) = crate::file::watch_folder::get_watch_folder(db.pool()).await? else {
+                    return Ok::<_, crate::LibationError>(serde_json::json!({ "configured": false }));
+                };
+
+                let mut importer = crate::file::WatchFolderImporter::new(db.pool(), &params.library_dir)
+                    .with_cancellation(job.token().clone());
+                if let Some(pattern) = params
+                    .naming_pattern
+                    .as_deref()
+                    .and_then(crate::file::paths::NamingPattern::from_string)
+                {
+                    importer = importer.with_naming_pattern(pattern);
+                }
+                if let Some(strategy) = params
+                    .collision_strategy
+                    .as_deref()
+                    .and_then(crate::file::paths::CollisionStrategy::from_string)
+                {
+                    importer = importer.with_collision_strategy(strategy);
+                }
+                if let Some(hex) = params.activation_bytes.as_deref() {
+                    importer = importer
+                        .with_activation_bytes(crate::crypto::activation::ActivationBytes::from_hex(hex)?);
+                }
+                if let Some(secs) = params.min_file_age_secs {
+                    importer = importer.with_min_file_age(std::time::Duration::from_secs(secs));
+                }
+
+                let report = crate::storage::jobs::run_job(db.pool(), &job, async {
+                    let report = importer.scan(std::path::Path::new(&folder)).await?;
+                    let imported: Vec<_> = report
+                        .imported
+                        .iter()
+                        .map(|file| crate::storage::jobs::JobArtifact::file(file.output_path.as_str()))
+                        .collect();
+                    crate::storage::jobs::add_job_artifacts(db.pool(), job.job_id(), &imported).await?;
+                    Ok(report)
+                })
+                .await?;
+                let mut response = serde_json::to_value(&report)?;
+                response["configured"] = serde_json::json!(true);
+                response["folder"] = serde_json::json!(folder);
+
+                Ok(response)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get customer information from Audible API
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "locale_code": "us",
+///   "access_token": "Atna|..."
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "name": "John Doe",
+///     "given_name": "John",
+///     "email": "john@example.com"
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCustomerInformation(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetCustomerInformation", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            locale_code: String,
+            access_token: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                // Get locale
+                let locale = match params.locale_code.as_str() {
+                    "us" => crate::api::auth::Locale::us(),
+                    "uk" => crate::api::auth::Locale::uk(),
+                    "de" => crate::api::auth::Locale::de(),
+                    "fr" => crate::api::auth::Locale::fr(),
+                    "ca" => crate::api::auth::Locale::ca(),
+                    "au" => crate::api::auth::Locale::au(),
+                    "it" => crate::api::auth::Locale::it(),
+                    "es" => crate::api::auth::Locale::es(),
+                    "in" => crate::api::auth::Locale::in_(),
+                    "jp" => crate::api::auth::Locale::jp(),
+                    _ => {
+                        return Err(crate::LibationError::InvalidInput(format!(
+                            "Unknown locale: {}",
+                            params.locale_code
+                        )))
+                    }
+                };
+
+                // Create identity with access token
+                let access_token = crate::api::auth::AccessToken {
+                    token: params.access_token.clone().into(),
+                    expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+                };
+
+                let identity = crate::api::auth::Identity::new(
+                    access_token,
+                    String::new(), // refresh_token - not needed for this call
+                    String::new(), // device_private_key - not needed
+                    String::new(), // adp_token - not needed
+                    locale.clone(),
+                );
+
+                // Create account with identity
+                let account = crate::api::auth::Account {
+                    account_id: "temp".to_string(),
+                    account_name: "temp".to_string(),
+                    library_scan: true,
+                    decrypt_key: Default::default(),
+                    identity: Some(identity),
+                };
+
+                let client = crate::api::client::AudibleClient::new(account)?;
+                let customer_info = client.get_customer_information().await?;
+
+                Ok::<_, crate::LibationError>(customer_info)
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Download and decrypt an audiobook
+///
+/// # Parameters
+/// JSON string with:
+/// ```json
+/// {
+///   "accountJson": "{ ... }",  // Complete account JSON with identity
+///   "asin": "B07T2F8VJM",
+///   "outputDirectory": "/path/to/save",
+///   "quality": "High"  // "Low", "Normal", "High", "Extreme"
+/// }
+/// ```
+///
+/// # Returns
+/// JSON response:
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "outputPath": "/path/to/file.m4b",
+///     "fileSize": 148080000,
+///     "duration": 9783.3
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDownloadBook(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDownloadBook", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            #[serde(rename = "accountJson")]
+            account_json: String,
+            asin: String,
+            // Still sent by the app; downloads now land in the cache
+            #[serde(rename = "outputDirectory")]
+            _output_directory: String,
+            quality: String,
+            #[serde(rename = "dbPath")]
+            db_path: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                // TODO: Add ensure_valid_token() here once we pass db_path in params
+                // For now, WorkManager backup handles token refresh
+
+                // Parse account
+                let account: crate::api::auth::Account = serde_json::from_str(&params.account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
+
+                // Parse quality
+                let quality = match params.quality.as_str() {
+                    "Low" => crate::api::content::DownloadQuality::Low,
+                    "Normal" => crate::api::content::DownloadQuality::Normal,
+                    "High" => crate::api::content::DownloadQuality::High,
+                    "Extreme" => crate::api::content::DownloadQuality::Extreme,
+                    _ => crate::api::content::DownloadQuality::High,
+                };
+
+                // Create client
+                let client = crate::api::client::AudibleClient::new(account)?;
+
+                // Get download license
+                let license = client.build_download_license(&params.asin, quality, false).await;
+                let operation = crate::api::recording::ReplayOperation::DownloadLicense {
+                    asin: params.asin.clone(),
+                    quality,
+                    prefer_widevine: false,
+                };
+                if let Err(e) = client.save_recording(operation, &license) {
+                    crate::trace::trace_eprintln!("Failed to save API recording: {}", e);
+                }
+                let license = license?;
+
+                // Extract AAXC keys
+                let (key_hex, iv_hex) = if let Some(ref keys) = license.decryption_keys {
+                    if !keys.is_empty() && keys[0].key_part_1.len() == 16 {
+                        let key = keys[0]
+                            .key_part_1
+                            .expose_secret()
+                            .iter()
+                            .map(|b| format!("{:02x}", b))
+                            .collect::<String>();
+                        let iv = if let Some(ref iv_bytes) = keys[0].key_part_2 {
+                            iv_bytes
+                                .expose_secret()
+                                .iter()
+                                .map(|b| format!("{:02x}", b))
+                                .collect::<String>()
+                        } else {
+                            return Err(crate::LibationError::InvalidInput(
+                                "No IV in AAXC keys".to_string(),
+                            ));
+                        };
+                        (key, iv)
+                    } else {
+                        return Err(crate::LibationError::InvalidInput(
+                            "Unsupported key format (only AAXC supported)".to_string(),
+                        ));
+                    }
+                } else {
+                    return Err(crate::LibationError::InvalidInput(
+                        "No decryption keys in license".to_string(),
+                    ));
+                };
+
+                // Download encrypted file to cache directory
+                // (TypeScript layer will copy to user's chosen directory after decryption)
+                let audiobooks_cache = legacy_download_dir();
+                let _ = std::fs::create_dir_all(&audiobooks_cache);
+
+                let encrypted_path = audiobooks_cache.join(format!("{}.aax", params.asin)).to_string_lossy().to_string();
+                let decrypted_path = audiobooks_cache.join(format!("{}.m4b", params.asin)).to_string_lossy().to_string();
+
+                // Download with reqwest
+                let user_agent = "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0";
+                let http_client = reqwest::Client::new();
+                let response = http_client
+                    .get(&license.download_url)
+                    .header("User-Agent", user_agent)
+                    .send()
+                    .await
+                    .map_err(|e| crate::LibationError::NetworkError {
+                        message: format!("Download request failed: {}", e),
+                        is_transient: true,
+                    })?;
+
+                if !response.status().is_success() {
+                    return Err(crate::LibationError::NetworkError {
+                        message: format!("HTTP {}", response.status()),
+                        is_transient: false,
+                    });
+                }
+
+                use futures_util::StreamExt;
+                use tokio::io::AsyncWriteExt;
+
+                let mut file = tokio::fs::File::create(&encrypted_path)
+                    .await
+                    .map_err(|e| {
+                        crate::LibationError::internal(format!(
+                            "Failed to create file {}: {}",
+                            encrypted_path, e
+                        ))
+                    })?;
+
+                let mut stream = response.bytes_stream();
+                while let Some(chunk) = stream.next().await {
+                    let chunk = chunk.map_err(|e| crate::LibationError::NetworkError {
+                        message: format!("Stream error: {}", e),
+                        is_transient: true,
+                    })?;
+                    file.write_all(&chunk).await.map_err(|e| {
+                        crate::LibationError::internal(format!("Write failed: {}", e))
+                    })?;
+                }
+                file.flush()
+                    .await
+                    .map_err(|e| crate::LibationError::internal(format!("Flush failed: {}", e)))?;
+
+                // Return encrypted file path and decryption keys
+                // The app decrypts with nativeDecryptAAXC
+                let file_metadata = tokio::fs::metadata(&encrypted_path).await.map_err(|e| {
+                    crate::LibationError::not_found(format!(
+                        "Downloaded file not found: {} ({})",
+                        encrypted_path, e
+                    ))
+                })?;
+
+                // Fetch book metadata from database if db_path provided
+                let book_metadata = if let Some(ref db_path) = params.db_path {
+                    let db = crate::storage::Database::new(db_path).await?;
+                    crate::storage::queries::find_book_with_relations_by_asin(
+                        db.pool(),
+                        &params.asin,
+                    )
+                    .await?
+                } else {
+                    None
+                };
+
+                // Return decryption info and metadata for nativeDecryptAAXC
+                #[derive(Serialize)]
+                struct BookMetadata {
+                    title: String,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    subtitle: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    authors: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    narrators: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    publisher: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    series_name: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    series_sequence: Option<f32>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    description: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    date_published: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    language: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    picture_large: Option<String>,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    picture_id: Option<String>,
+                    audible_asin: String,
+                }
+
+                #[derive(Serialize)]
+                struct DownloadResultWithKeys {
+                    #[serde(rename = "encryptedPath")]
+                    encrypted_path: String,
+                    #[serde(rename = "outputPath")]
+                    output_path: String,
+                    #[serde(rename = "fileSize")]
+                    file_size: u64,
+                    #[serde(rename = "aaxcKey")]
+                    aaxc_key: String,
+                    #[serde(rename = "aaxcIv")]
+                    aaxc_iv: String,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    metadata: Option<BookMetadata>,
+                }
+
+                let metadata = book_metadata.map(|b| BookMetadata {
+                    title: b.title,
+                    subtitle: b.subtitle,
+                    authors: b.authors_str,
+                    narrators: b.narrators_str,
+                    publisher: b.publisher,
+                    series_name: b.series_name,
+                    series_sequence: b.series_sequence,
+                    description: Some(b.description),
+                    date_published: b.date_published,
+                    language: b.language,
+                    picture_large: b.picture_large,
+                    picture_id: b.picture_id,
+                    audible_asin: params.asin.clone(),
+                });
+
+                Ok::<_, crate::LibationError>(DownloadResultWithKeys {
+                    encrypted_path,
+                    output_path: decrypted_path,
+                    file_size: file_metadata.len(),
+                    aaxc_key: key_hex,
+                    aaxc_iv: iv_hex,
+                    metadata,
+                })
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// LICENSE FUNCTIONS
+// ============================================================================
+
+/// Get download license without downloading
+///
+/// This allows getting the license info (URL, keys, size) to enqueue in download manager
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "accountJson": "{ ... }",
+///   "asin": "B07T2F8VJM",
+///   "quality": "High",
+///   "db_path": "/data/data/.../libation.db"  // Optional: records negotiated format
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "download_url": "https://...",
+///     "mirror_urls": ["https://..."],  // Other CDNs for the same file
+///     "file_type": "aaxc",            // or "aax"
+///     "total_bytes": 72000000,
+///     "aaxc_key": "...",              // AAXC; null for AAX
+///     "aaxc_iv": "...",
+///     "activation_bytes": null,       // AAX: hex activation bytes
+///     "request_headers": {"User-Agent": "..."}
+///   }
+/// }
+/// ```
+///
+/// With `db_path`, a title synced as offering only formats this path can't
+/// decrypt (see `FormatSupport`) fails with `UnsupportedDownloadFormat`
+/// before any license is requested, and a license archived by
+/// `nativePrepareOffline` for the same quality is used while its download
+/// URL is still valid.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDownloadLicense(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetDownloadLicense", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            #[serde(rename = "accountJson")]
+            account_json: String,
+            asin: String,
+            quality: String,
+            #[serde(default)]
+            db_path: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let result = RUNTIME.block_on(async {
+                // TODO: Add ensure_valid_token() here once we pass db_path in params
+                // For now, WorkManager backup handles token refresh
+
+                let account: crate::api::auth::Account = serde_json::from_str(&params.account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
+
+                let quality = match params.quality.as_str() {
+                    "Low" => crate::api::content::DownloadQuality::Low,
+                    "Normal" => crate::api::content::DownloadQuality::Normal,
+                    "High" => crate::api::content::DownloadQuality::High,
+                    "Extreme" => crate::api::content::DownloadQuality::Extreme,
+                    _ => crate::api::content::DownloadQuality::High,
+                };
+
+                // AAX only with the account's activation bytes; no Widevine
+                let capabilities = crate::api::license::FormatCapabilities::for_account(&account);
+
+                // Fail before any license request when the title was synced
+                // with only formats this path can't handle
+                let db = match params.db_path {
+                    Some(ref db_path) => Some(crate::storage::Database::new(db_path).await?),
+                    None => None,
+                };
+                if let Some(ref db) = db {
+                    let support = crate::storage::queries::get_book_format_support(db.pool(), &params.asin).await?;
+                    if let Some(support) = support.filter(|s| !s.permits_download(capabilities.supports_widevine)) {
+                        return Err(crate::LibationError::UnsupportedDownloadFormat {
+                            asin: params.asin.clone(),
+                            format_support: support.as_str().to_string(),
+                        });
+                    }
+                }
+
+                // A license archived by nativePrepareOffline saves the
+                // license and HEAD requests while its URL still works
+                let archived = match db {
+                    Some(ref db) => {
+                        crate::download::offline::get_offline_license(
+                            db.pool(),
+                            &params.asin,
+                            quality,
+                            &crate::clock::AppClock,
+                        )
+                        .await?
+                    }
+                    None => None,
+                };
+                let (file_type, license, archived_bytes) = match archived {
+                    Some(archived) => (archived.file_type, archived.license, Some(archived.total_bytes)),
+                    None => {
+                        let client = crate::api::client::AudibleClient::new(account)?;
+                        let negotiated = client
+                            .negotiate_download_license(
+                                &params.asin,
+                                quality,
+                                &[],
+                                &capabilities,
+                            )
+                            .await?;
+                        (negotiated.file_type.as_str().to_string(), negotiated.license, None)
+                    }
+                };
+
+                if let Some(ref db) = db {
+                    crate::storage::queries::set_book_download_format(db.pool(), &params.asin, &file_type).await?;
+                }
+
+                // AAXC keys, or the activation bytes of an AAX license
+                let keys = license
+                    .decryption_keys
+                    .as_ref()
+                    .and_then(|keys| keys.first())
+                    .ok_or_else(|| crate::LibationError::InvalidInput("No decryption keys in license".to_string()))?;
+                let (aaxc_key, aaxc_iv, activation_bytes) = match (keys.key_part_1.len(), &keys.key_part_2) {
+                    (16, Some(iv)) => (
+                        Some(hex::encode(keys.key_part_1.expose_secret())),
+                        Some(hex::encode(iv.expose_secret())),
+                        None,
+                    ),
+                    (16, None) => {
+                        return Err(crate::LibationError::InvalidInput("No IV in AAXC keys".to_string()));
+                    }
+                    (4, None) => (None, None, Some(hex::encode(keys.key_part_1.expose_secret()))),
+                    _ => {
+                        return Err(crate::LibationError::InvalidInput(
+                            "Unsupported key format (only AAX and AAXC supported)".to_string(),
+                        ));
+                    }
+                };
+
+                // Build request headers
+                let mut request_headers = std::collections::HashMap::new();
+                request_headers.insert(
+                    "User-Agent".to_string(),
+                    "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0".to_string(),
+                );
+
+                // Get file size from HTTP HEAD request
+                let total_bytes = match archived_bytes {
+                    Some(total_bytes) => total_bytes,
+                    None => {
+                        let http_client = reqwest::Client::new();
+                        let head_response = http_client
+                            .head(&license.download_url)
+                            .header("User-Agent", "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0")
+                            .send()
+                            .await
+                            .map_err(|e| crate::LibationError::NetworkError {
+                                message: format!("HEAD request failed: {}", e),
+                                is_transient: true,
+                            })?;
+
+                        head_response
+                            .headers()
+                            .get("content-length")
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(|s| s.parse::<u64>().ok())
+                            .unwrap_or(0)
+                    }
+                };
+
+                #[derive(Serialize)]
+                struct LicenseInfo {
+                    download_url: String,
+                    mirror_urls: Vec<String>,
+                    file_type: String,
+                    total_bytes: u64,
+                    aaxc_key: Option<String>,
+                    aaxc_iv: Option<String>,
+                    activation_bytes: Option<String>,
+                    request_headers: std::collections::HashMap<String, String>,
+                }
+
+                Ok::<_, crate::LibationError>(LicenseInfo {
+                    download_url: license.download_url,
+                    mirror_urls: license.mirror_urls,
+                    file_type,
+                    total_bytes,
+                    aaxc_key,
+                    aaxc_iv,
+                    activation_bytes,
+                    request_headers,
+                })
+            })?;
+
+            Ok(success_response(result))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Fetch and archive download licenses for titles to download later
+///
+/// Run before a trip while on good Wi-Fi; `nativeGetDownloadLicense` with
+/// a `db_path` then uses the archived license instead of requesting one,
+/// until its download URL expires (`expires_at` per title). Titles are
+/// fetched one at a time; failures are reported per title.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "account_json": "{ ... }",
+///   "asins": ["B07T2F8VJM", "B08G9PRS1K"],
+///   "quality": "High",          // optional
+///   "job_id": "offline-1"       // optional, for nativeCancelJob and nativeGetJob
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "prepared": [{ "asin": "B07T2F8VJM", "file_type": "aaxc", "total_bytes": 72000000, "expires_at": "2025-03-01T12:00:00.000Z" }],
+///     "failed": [{ "asin": "B08G9PRS1K", "reason": "..." }],
+///     "cancelled": false
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativePrepareOffline(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativePrepareOffline", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_json: String,
+            asins: Vec<String>,
+            #[serde(default = "default_quality")]
+            quality: crate::api::content::DownloadQuality,
+            #[serde(default)]
+            job_id: Option<String>,
+        }
+
+        fn default_quality() -> crate::api::content::DownloadQuality {
+            crate::api::content::DownloadQuality::High
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let report = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let account_json = crate::api::auth::ensure_valid_token(db.pool(), &params.account_json, 30).await?;
+                let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                    .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
+                let client = crate::api::client::AudibleClient::new(account)?;
+
+                let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::LicensePrefetch)?;
+                let quality = params.quality;
+                crate::storage::jobs::run_job(
+                    db.pool(),
+                    &job,
+                    crate::download::offline::prepare_offline(
+                        db.pool(),
+                        &params.asins,
+                        quality,
+                        job.token(),
+                        |asin| {
+                            let client = &client;
+                            async move { crate::download::offline::fetch_license(client, &asin, quality).await }
+                        },
+                    ),
+                )
+                .await
+            })?;
+
+            Ok(success_response(report))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Licenses archived by `nativePrepareOffline`
+///
+/// Keys and URLs are left out. With `prune`, licenses whose download URL
+/// has expired are dropped first.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "prune": true   // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "pruned": 1,
+///     "licenses": [{ "asin": "B07T2F8VJM", "quality": "High", "file_type": "aaxc", "total_bytes": 72000000,
+///                    "expires_at": "2025-03-01T12:00:00.000Z", "fetched_at": "2025-03-01T08:00:00.000Z" }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListOfflineLicenses(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListOfflineLicenses", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(default)]
+            prune: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let pruned = if params.prune {
+                    crate::download::offline::prune_expired_licenses(db.pool(), &crate::clock::AppClock).await?
+                } else {
+                    0
+                };
+                let licenses: Vec<_> = crate::download::offline::list_offline_licenses(db.pool())
+                    .await?
+                    .into_iter()
+                    .map(|l| {
+                        serde_json::json!({
+                            "asin": l.asin,
+                            "quality": l.quality,
+                            "file_type": l.file_type,
+                            "total_bytes": l.total_bytes,
+                            "expires_at": l.expires_at,
+                            "fetched_at": l.fetched_at,
+                        })
+                    })
+                    .collect();
+
+                Ok(success_response(serde_json::json!({
+                    "pruned": pruned,
+                    "licenses": licenses,
+                })))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Drop a title's archived license
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db", "asin": "B07T2F8VJM" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// { "success": true, "data": { "removed": true } }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRemoveOfflineLicense(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRemoveOfflineLicense", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let removed = crate::download::offline::remove_offline_license(db.pool(), &params.asin).await?;
+                Ok(success_response(serde_json::json!({ "removed": removed })))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// DOWNLOAD MANAGER FUNCTIONS
+// ============================================================================
+
+/// Enqueue a download in the persistent download manager
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B001",
+///   "title": "Book Title",
+///   "download_url": "https://...",
+///   "total_bytes": 10000000,
+///   "download_path": "/cache/B001.aax",
+///   "output_path": "/output/B001.m4b",
+///   "request_headers": {"User-Agent": "..."},
+///   "mirror_urls": ["https://..."],  // Optional: fallback CDNs from the license
+///   "companion_of": "B07T2F8VJM",  // Optional: book this is companion audio of
+///   "companion_kind": "author_interview",  // Required with companion_of
+///   "follow_up": {                 // Optional: run by the manager on completion
+///     "decrypt": {
+///       "aaxc_key": "hex-key",
+///       "aaxc_iv": "hex-iv",
+///       "output_path": "/output/B001.m4b",  // Optional: defaults to output_path
+///       "delete_download": true
+///     },
+///     "tags": ["downloaded"]
+///   }
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "task_id": "uuid-string",
+///     "quota_warning": null
+///   }
+/// }
+/// ```
+///
+/// `quota_warning` is the account's quota status when its monthly cap is
+/// exceeded but set to warn. A cap set to block fails the call instead.
+///
+/// With `follow_up` the manager decrypts and tags the book itself once the
+/// download completes, across app restarts; the task stays `decrypting`
+/// until then and ends `completed` with the M4B as its `output_path`.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeEnqueueDownload(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeEnqueueDownload", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            title: String,
+            download_url: String,
+            total_bytes: u64,
+            download_path: String,
+            output_path: String,
+            request_headers: std::collections::HashMap<String, String>,
+            #[serde(default)]
+            mirror_urls: Vec<String>,
+            #[serde(default)]
+            companion_of: Option<String>,
+            #[serde(default)]
+            companion_kind: Option<crate::api::content::CompanionKind>,
+            #[serde(default)]
+            follow_up: Option<crate::download::FollowUp>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+            if params.companion_of.is_some() && params.companion_kind.is_none() {
+                return Err(crate::LibationError::invalid_input("companion_kind is required with companion_of"));
+            }
+
+            let (task_id, quota_warning) = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+
+                let task_id = manager
+                    .enqueue_download_with_follow_up(
+                        params.asin,
+                        params.title,
+                        params.download_url,
+                        params.total_bytes,
+                        params.download_path,
+                        params.output_path,
+                        params.request_headers,
+                        params.follow_up,
+                    )
+                    .await?;
+                if !params.mirror_urls.is_empty() {
+                    manager.set_mirror_urls(&task_id, &params.mirror_urls).await?;
+                }
+                if let (Some(parent), Some(kind)) = (&params.companion_of, params.companion_kind) {
+                    manager.set_companion_of(&task_id, parent, kind).await?;
+                }
+                let quota_warning = manager
+                    .quota_status(&task_id)
+                    .await?
+                    .filter(|status| status.exceeded);
+
+                Ok::<_, crate::LibationError>((task_id, quota_warning))
+            })?;
+
+            let response = serde_json::json!({
+                "task_id": task_id,
+                "quota_warning": quota_warning,
+            });
+
+            Ok(success_response(response))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Export the download queue for another device or a reinstall
+///
+/// Unfinished titles are exported in queue order with the conversion
+/// policy and download quotas. Licenses, URLs, keys and paths are not.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "version": 1,
+///     "exported_at": "2025-03-01T12:00:00+00:00",
+///     "items": [{ "asin": "B001", "title": "Book Title", "paused": false, "account": "alice" }],
+///     "conversion_policy": { "always_defer": false, "require_charging": true, "window": null },
+///     "download_quotas": [{ "account": "alice", "monthly_cap_bytes": 5000000000, "action": "warn" }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeExportDownloadQueue(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeExportDownloadQueue", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let export = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.export_queue().await
+            })?;
+
+            Ok(success_response(export))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Import an exported download queue
+///
+/// Applies the exported conversion policy and quotas, then requests a fresh
+/// license for every title that is in the library and not already queued
+/// or downloaded, and enqueues it (paused if it was paused). Titles that
+/// fail are listed in `skipped`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "account_json": "{...}",
+///   "export_json": "{...}",          // data of nativeExportDownloadQueue
+///   "output_directory": "content://...",
+///   "quality": "High",               // optional
+///   "cache_dir": "/data/.../cache"   // optional, encrypted files go to {cache_dir}/audiobooks
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "enqueued": ["uuid-string"],
+///     "skipped": [{ "asin": "B002", "reason": "Already downloaded" }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeImportDownloadQueue(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeImportDownloadQueue", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_json: String,
+            export_json: String,
+            output_directory: String,
+            #[serde(default)]
+            quality: Option<String>,
+            #[serde(default)]
+            cache_dir: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+            let export = crate::download::QueueExport::from_json(&params.export_json)?;
+
+            let report = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let account_json =
+                    crate::api::auth::ensure_valid_token(db.pool(), &params.account_json, 30).await?;
+                let account: crate::api::auth::Account = serde_json::from_str(&account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
+                let client = crate::api::client::AudibleClient::new(account)?;
+
+                let quality = match params.quality.as_deref() {
+                    Some("Low") => crate::api::content::DownloadQuality::Low,
+                    Some("Normal") => crate::api::content::DownloadQuality::Normal,
+                    Some("Extreme") => crate::api::content::DownloadQuality::Extreme,
+                    _ => crate::api::content::DownloadQuality::High,
+                };
+                let audiobooks_cache = match &params.cache_dir {
+                    Some(dir) => std::path::Path::new(dir).join("audiobooks"),
+                    None => legacy_download_dir(),
+                };
+                let _ = std::fs::create_dir_all(&audiobooks_cache);
+
+                *CONVERSION_POLICY.lock().unwrap() = export.conversion_policy;
+                let manager = get_or_create_manager(&params.db_path).await?;
+                let client = &client;
+                let audiobooks_cache = &audiobooks_cache;
+                let output_directory = params.output_directory.as_str();
+
+                manager
+                    .import_queue(&export, |item| {
+                        let asin = item.asin.clone();
+                        async move {
+                            let user_agent = "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0";
+                            let license = client.build_download_license(&asin, quality, false).await?;
+
+                            let key = license
+                                .decryption_keys
+                                .as_ref()
+                                .and_then(|keys| keys.first())
+                                .filter(|key| key.key_part_1.len() == 16)
+                                .ok_or_else(|| {
+                                    crate::LibationError::InvalidInput(
+                                        "Unsupported key format (only AAXC supported)".to_string(),
+                                    )
+                                })?;
+                            let iv = key.key_part_2.as_ref().ok_or_else(|| {
+                                crate::LibationError::InvalidInput("No IV in AAXC keys".to_string())
+                            })?;
+
+                            let total_bytes = reqwest::Client::new()
+                                .head(&license.download_url)
+                                .header("User-Agent", user_agent)
+                                .send()
+                                .await
+                                .ok()
+                                .and_then(|response| response.content_length())
+                                .unwrap_or(0);
+
+                            let mut request_headers = std::collections::HashMap::new();
+                            request_headers.insert("User-Agent".to_string(), user_agent.to_string());
+
+                            Ok(crate::download::ResolvedDownload {
+                                download_url: license.download_url,
+                                mirror_urls: license.mirror_urls,
+                                total_bytes,
+                                download_path: audiobooks_cache
+                                    .join(format!("{}.aax", asin))
+                                    .to_string_lossy()
+                                    .to_string(),
+                                output_path: audiobooks_cache
+                                    .join(format!("{}.m4b", asin))
+                                    .to_string_lossy()
+                                    .to_string(),
+                                request_headers,
+                                conversion_keys: Some(crate::download::queue_transfer::ConversionKeys {
+                                    aaxc_key: hex::encode(key.key_part_1.expose_secret()).into(),
+                                    aaxc_iv: hex::encode(iv.expose_secret()).into(),
+                                    output_directory: output_directory.to_string(),
+                                }),
+                            })
+                        }
+                    })
+                    .await
+            })?;
+
+            Ok(success_response(report))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get download task status
+///
+/// `bytes_downloaded` never decreases for the same `progress_epoch`. A
+/// higher epoch means the download had to restart from an earlier byte
+/// (partial file missing, truncated or corrupt), so progress cached for
+/// the old epoch should be dropped.
+///
+/// Request headers and conversion keys are left out; the native retry
+/// reads the keys with `nativeGetConversionKeys`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "task_id": "uuid-string"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "task_id": "...",
+///     "asin": "B001",
+///     "title": "Book Title",
+///     "status": "downloading",
+///     "bytes_downloaded": 5000000,
+///     "total_bytes": 10000000,
+///     "progress_epoch": 0,
+///     "buffer_overrides": { "buffer_size": null, "flush_interval": null },
+///     "buffering": {              // null before the first session
+///       "buffer_size": 65536,
+///       "flush_interval": 4194304,
+///       "auto_tuned": true,
+///       "measured_bytes_per_sec": 1800000
+///     },
+///     ...
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDownloadTask(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetDownloadTask", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let task = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.get_task(&params.task_id).await
+            })?;
+
+            Ok(success_response(task))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List download tasks with optional filter
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "filter": "downloading"  // optional: "queued", "downloading", "completed", "failed", etc.
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "tasks": [...]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListDownloadTasks(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListDownloadTasks", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            filter: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let tasks = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+
+                let filter = if let Some(ref f) = params.filter {
+                    Some(crate::download::TaskStatus::from_str(f)?)
+                } else {
+                    None
+                };
+
+                manager.list_tasks(filter).await
+            })?;
+
+            let response = serde_json::json!({
+                "tasks": tasks,
+            });
+
+            Ok(success_response(response))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the audio features available on this device
+///
+/// Rust can't see FFmpeg-Kit inside the app, so the app reports it with
+/// `external_ffmpeg`; the flag is remembered for later calls.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "external_ffmpeg": true  // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "backend": "external",  // "native" | "external" | "none"
+///     "ffmpeg": false,
+///     "ffprobe": false,
+///     "decryption": true,
+///     "conversion": false,
+///     "metadata_editing": false,
+///     "probing": false
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAudioCapabilities(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetAudioCapabilities", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            external_ffmpeg: Option<bool>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            if let Some(available) = params.external_ffmpeg {
+                crate::audio::capabilities::set_external_ffmpeg_available(available);
+            }
+
+            Ok(success_response(crate::audio::get_audio_capabilities()))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get runtime and download diagnostics
+///
+/// Use when downloads appear stuck: reports whether the runtime is alive,
+/// how many workers hold permits, queue depth, DB pool usage, and tasks
+/// without progress for longer than the stall threshold.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "runtime": { "workers": 8, "alive_tasks": 5 },
+///     "downloads": {
+///       "active_workers": 2,
+///       "queued_tasks": 4,
+///       "permits_available": 1,
+///       "max_concurrent": 3,
+///       "concurrency_limit": 3,
+///       "stall_threshold_seconds": 300,
+///       "stalled_tasks": [{ "task_id": "...", "seconds_without_progress": 412 }]
+///     },
+///     "database": { "size": 2, "idle": 1 }
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDiagnostics(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetDiagnostics", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let diagnostics = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.diagnostics().await
+            })?;
+
+            Ok(success_response(diagnostics))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Import partial downloads tracked by legacy state JSON files
+///
+/// Runs automatically for the legacy cache directory when the manager is
+/// created; call this for any other directory the app used to download to.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "directory": "/data/user/0/.../cache/audiobooks"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "imported": ["task-uuid"],
+///     "skipped": [{ "state_path": ".../B0XYZ.download_state.json", "reason": "Partial file is missing" }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeImportLegacyDownloads(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeImportLegacyDownloads", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            directory: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let report = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager
+                    .import_legacy_downloads(std::path::Path::new(&params.directory))
+                    .await
+            })?;
+
+            Ok(success_response(report))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Pause a download
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "task_id": "uuid-string"
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativePauseDownload(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativePauseDownload", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.pause_download(&params.task_id).await
+            })?;
+
+            Ok(success_response(serde_json::json!({"success": true})))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Resume a paused download
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "task_id": "uuid-string"
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeResumeDownload(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeResumeDownload", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.resume_download(&params.task_id).await
+            })?;
+
+            Ok(success_response(serde_json::json!({"success": true})))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Replace the download URL of a task whose URL expired
+///
+/// Call after a failure with "Download URL ... expired" and a fresh license,
+/// then resume or retry the task; downloaded bytes are kept.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "task_id": "uuid-string",
+///   "download_url": "https://..."
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUpdateDownloadUrl(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeUpdateDownloadUrl", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+            download_url: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.update_download_url(&params.task_id, &params.download_url).await
+            })?;
+
+            Ok(success_response(serde_json::json!({"success": true})))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Cancel a download
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "task_id": "uuid-string"
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCancelDownload(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeCancelDownload", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.cancel_download(&params.task_id).await
+            })?;
+
+            Ok(success_response(serde_json::json!({"success": true})))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// DOWNLOAD TASK STATUS UPDATE FUNCTIONS
+// ============================================================================
+
+/// Update download task status from Kotlin (for post-download conversion stages)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "task_id": "uuid-string",
+///   "status": "decrypting",
+///   "error": null,
+///   "output_path": "content://..."
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUpdateDownloadTaskStatus(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeUpdateDownloadTaskStatus", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+            status: String,
+            error: Option<String>,
+            output_path: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let status = crate::download::TaskStatus::from_str(&params.status)?;
+
+            RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager
+                    .update_task_status_with_details(
+                        &params.task_id,
+                        status,
+                        params.error.as_deref(),
+                        params.output_path.as_deref(),
+                    )
+                    .await
+            })?;
+
+            Ok(success_response(serde_json::json!({"success": true})))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Store conversion keys and output directory for a download task
+///
+/// A book with its own storage root (`nativeSetBookOutputRoot`) keeps
+/// that root as the task's `output_directory` instead.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "task_id": "uuid-string",
+///   "aaxc_key": "hex-key",
+///   "aaxc_iv": "hex-iv",        // "" when aaxc_key holds AAX activation bytes
+///   "output_directory": "content://..."
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeStoreConversionKeys(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeStoreConversionKeys", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+            aaxc_key: String,
+            aaxc_iv: String,
+            output_directory: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager
+                    .store_conversion_keys(
+                        &params.task_id,
+                        &params.aaxc_key,
+                        &params.aaxc_iv,
+                        &params.output_directory,
+                    )
+                    .await
+            })?;
+
+            Ok(success_response(serde_json::json!({"success": true})))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the stored conversion keys of a download task
+///
+/// For the native conversion retry only; not exposed to JS.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "task_id": "uuid-string"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "aaxc_key": "hex-key",        // null when none were stored
+///     "aaxc_iv": "hex-iv",
+///     "output_directory": "content://..."
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetConversionKeys(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetConversionKeys", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let task = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.get_task(&params.task_id).await
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "aaxc_key": task.aaxc_key.as_ref().map(|k| k.expose_secret()),
+                "aaxc_iv": task.aaxc_iv.as_ref().map(|iv| iv.expose_secret()),
+                "output_directory": task.output_directory,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Report device battery/thermal conditions
+///
+/// Updates the concurrency limit of every download manager. Running
+/// downloads continue; the returned limits tell the app whether to start
+/// conversions and how many encoding threads to use.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "battery_level": 0.15,
+///   "is_charging": false,
+///   "thermal_status": "nominal",   // nominal | fair | serious | critical
+///   "policy": {                    // Optional: replaces the current policy
+///     "low_battery_threshold": 0.2,
+///     "throttle_thermal_status": "serious",
+///     "throttled_max_downloads": 1,
+///     "throttled_encoding_threads": 1,
+///     "defer_conversion_when_throttled": true,
+///     "pause_on_critical_thermal": true
+///   }
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "max_concurrent_downloads": 1,
+///     "allow_conversion": false,
+///     "encoding_threads": 1,
+///     "throttled": true
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetDeviceConditions(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetDeviceConditions", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            #[serde(flatten)]
+            conditions: crate::download::DeviceConditions,
+            #[serde(default)]
+            policy: Option<crate::download::AdaptivePolicy>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let policy = {
+                let mut state = DEVICE_STATE.lock().unwrap();
+                state.0 = params.conditions;
+                if let Some(policy) = params.policy {
+                    state.1 = policy;
+                }
+                state.1
+            };
+
+            let managers: Vec<_> = DOWNLOAD_MANAGERS
+                .lock()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect();
+
+            let limits = RUNTIME.block_on(async {
+                for manager in &managers {
+                    manager
+                        .apply_device_conditions(&params.conditions, &policy)
+                        .await?;
+                }
+                // Report limits for the default concurrency of 3
+                Ok::<_, crate::LibationError>(policy.limits(&params.conditions, 3))
+            })?;
+
+            Ok(success_response(limits))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set when finished downloads may be decrypted
+///
+/// Downloads that finish while conversion isn't allowed end up in
+/// `awaiting_conversion` instead of `completed`. Applies to every download
+/// manager.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "always_defer": false,      // park every download until processed
+///   "require_charging": true,   // only convert while plugged in
+///   "window": { "start_hour": 22, "end_hour": 6 }  // optional local-time window
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "always_defer": false, "require_charging": true, "window": { "start_hour": 22, "end_hour": 6 } }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetConversionPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetConversionPolicy", &params_str_result);
+
+    let response = catch_panic(move || {
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let policy: crate::download::ConversionPolicy = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            *CONVERSION_POLICY.lock().unwrap() = policy;
+            for manager in DOWNLOAD_MANAGERS.lock().unwrap().values() {
+                manager.set_conversion_policy(policy);
+            }
+
+            Ok(success_response(policy))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set write buffering of downloads
+///
+/// Applies to every download manager, from each download's next session.
+/// With `auto_tune`, both values are starting points adjusted to the
+/// measured throughput.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "buffer_size": 8192,        // bytes
+///   "flush_interval": 1048576,  // bytes between flushes to disk
+///   "auto_tune": true
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "buffer_size": 8192, "flush_interval": 1048576, "auto_tune": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetDownloadBuffering(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetDownloadBuffering", &params_str_result);
+
+    let response = catch_panic(move || {
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let policy: crate::download::BufferPolicy = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+            policy.validate()?;
+
+            *BUFFER_POLICY.lock().unwrap() = policy;
+            for manager in DOWNLOAD_MANAGERS.lock().unwrap().values() {
+                manager.set_buffer_policy(policy)?;
+            }
+
+            Ok(success_response(policy))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Pin a download task's write buffering
+///
+/// Omitted values follow `nativeSetDownloadBuffering`; pinned values
+/// aren't auto-tuned. Applies from the task's next session.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "task_id": "uuid-string",
+///   "buffer_size": 262144,      // optional
+///   "flush_interval": 8388608   // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "buffer_size": 262144, "flush_interval": 8388608 }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetTaskBuffering(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetTaskBuffering", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+            #[serde(flatten)]
+            overrides: crate::download::BufferOverrides,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.set_task_buffering(&params.task_id, params.overrides).await
+            })?;
+
+            Ok(success_response(params.overrides))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Report the network the device is on
+///
+/// Applies to every download manager: running Wi-Fi-only tasks are paused
+/// on cellular, every running task is paused offline, and tasks paused
+/// this way are requeued once the network allows them. Queued tasks the
+/// network doesn't allow wait in the queue.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "state": "cellular"   // wifi | cellular | offline | unknown
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "paused": ["uuid-string"],   // flagged network_paused
+///     "resumed": []
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetNetworkState(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetNetworkState", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            state: crate::download::NetworkState,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            *NETWORK_STATE.lock().unwrap() = params.state;
+            let managers: Vec<_> = DOWNLOAD_MANAGERS
+                .lock()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect();
+
+            let transition = RUNTIME.block_on(async {
+                let mut transition = crate::download::NetworkTransition::default();
+                for manager in &managers {
+                    let changed = manager.set_network_state(params.state).await?;
+                    transition.paused.extend(changed.paused);
+                    transition.resumed.extend(changed.resumed);
+                }
+                Ok::<_, crate::LibationError>(transition)
+            })?;
+
+            Ok(success_response(transition))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the network policy of tasks without their own `wifi_only`
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "wifi_only": false }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetNetworkPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetNetworkPolicy", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let policy = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                Ok::<_, crate::LibationError>(manager.network_policy())
+            })?;
+
+            Ok(success_response(policy))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the network policy of tasks without their own `wifi_only`
+///
+/// Stored in the database and applied to the current network right away.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "wifi_only": true
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "paused": ["uuid-string"], "resumed": [] }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetNetworkPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetNetworkPolicy", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(flatten)]
+            policy: crate::download::NetworkPolicy,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let transition = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.set_network_policy(params.policy).await
+            })?;
+
+            Ok(success_response(transition))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set whether a download task only downloads on Wi-Fi
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "task_id": "uuid-string",
+///   "wifi_only": true   // null follows nativeSetNetworkPolicy
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "paused": [], "resumed": [] }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetTaskWifiOnly(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetTaskWifiOnly", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            task_id: String,
+            #[serde(default)]
+            wifi_only: Option<bool>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let transition = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.set_task_wifi_only(&params.task_id, params.wifi_only).await
+            })?;
+
+            Ok(success_response(transition))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Claim downloads awaiting conversion, if the policy allows converting now
+///
+/// Call when the device starts charging or the schedule window opens.
+/// Returned tasks are moved to `decrypting` and include their conversion
+/// keys; start converting them right away.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "tasks": [...]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeProcessPendingConversions(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeProcessPendingConversions", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let tasks = RUNTIME.block_on(async {
+                let manager = get_or_create_manager(&params.db_path).await?;
+                manager.process_pending_conversions().await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "tasks": tasks })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get per-account download statistics and quota status
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "account": "user@example.com",  // optional, all accounts when omitted
+///   "months": 6                      // optional, most recent N months
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "usage": [
+///       { "account": "user@example.com", "month": "2025-06", "bytes_downloaded": 734003200 }
+///     ],
+///     "quotas": [
+///       {
+///         "account": "user@example.com",
+///         "month": "2025-06",
+///         "bytes_downloaded": 734003200,
+///         "quota": { "account": "user@example.com", "monthly_cap_bytes": 2000000000, "action": "warn" },
+///         "exceeded": false
+///       }
+///     ]
+///   }
+/// }
+/// ```
+///
+/// `quotas` has the current month's status for every account in `usage`
+/// or with a cap set.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDownloadStats(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetDownloadStats", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account: Option<String>,
+            months: Option<u32>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let (usage, quotas) = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let pool = db.pool();
+                let usage = crate::download::quota::get_download_usage(
+                    pool,
+                    params.account.as_deref(),
+                    params.months,
+                )
+                .await?;
+
+                let mut accounts: Vec<String> = match &params.account {
+                    Some(account) => vec![account.clone()],
+                    None => {
+                        let mut accounts: Vec<String> =
+                            sqlx::query_scalar("SELECT account FROM DownloadQuotas")
+                                .fetch_all(pool)
+                                .await?;
+                        accounts.extend(usage.iter().map(|u| u.account.clone()));
+                        accounts
+                    }
+                };
+                accounts.sort();
+                accounts.dedup();
+
+                let mut quotas = Vec::with_capacity(accounts.len());
+                for account in &accounts {
+                    quotas.push(crate::download::quota::get_quota_status(pool, account).await?);
+                }
+
+                Ok::<_, crate::LibationError>((usage, quotas))
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "usage": usage,
+                "quotas": quotas,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Codec, bitrate and size statistics of liberated files
+///
+/// Bitrates are averages from file size and runtime, so users can spot
+/// old low-bitrate files worth liberating again.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "low_bitrate_kbps": 32,    // optional, default 32
+///   "include_files": false,    // optional, per-file entries
+///   "job_id": "stats-1"        // optional, for nativeCancelJob and nativeGetJob
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "total_books": 412,
+///     "liberated_books": 160,
+///     "missing_files": 2,
+///     "total_bytes": 21474836480,
+///     "total_minutes": 96000,
+///     "low_bitrate_kbps": 32,
+///     "low_bitrate_files": 41,
+///     "formats": [{
+///       "format": "m4b",
+///       "codec": "aac",
+///       "file_count": 117,
+///       "total_bytes": 17179869184,
+///       "total_minutes": 70000,
+///       "average_bitrate_kbps": 33,
+///       "bytes_per_hour": 14725602
+///     }],
+///     "files": []
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetLibraryStats(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetLibraryStats", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            #[serde(default = "default_low_bitrate")]
+            low_bitrate_kbps: u32,
+            #[serde(default)]
+            include_files: bool,
+            #[serde(default)]
+            job_id: Option<String>,
+        }
+
+        fn default_low_bitrate() -> u32 {
+            crate::storage::library_stats::DEFAULT_LOW_BITRATE_KBPS
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Export)?;
+            let mut stats = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::jobs::run_job(
+                    db.pool(),
+                    &job,
+                    crate::storage::library_stats::library_stats(db.pool(), params.low_bitrate_kbps, job.token()),
+                )
+                .await
+            })?;
+            if !params.include_files {
+                stats.files.clear();
+            }
+
+            Ok(success_response(stats))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Export the liberated file statistics
+///
+/// CSV has one row per file (asin, title, format, codec, size_bytes,
+/// length_in_minutes, bitrate_kbps, bytes_per_hour, path); JSON has the
+/// full report as returned by `nativeGetLibraryStats` with files.
+///
+/// Starts an Export job in the background and returns its id. Files are
+/// written in chunks, with Export progress events for the job id (also
+/// from `nativeGetProgress`), and `nativeCancelJob` stops it between
+/// chunks. The outcome comes as a work event and from `nativeGetJob`,
+/// with `output_path` as the job's artifact. The file only appears at
+/// `output_path` once it is complete; a failed or cancelled export removes
+/// what it wrote and leaves an existing file there untouched.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "output_path": "/storage/emulated/0/Download/library_stats.csv",
+///   "format": "csv",           // "csv" (default) or "json"
+///   "low_bitrate_kbps": 32,    // optional, default 32
+///   "job_id": "export-1"       // optional, for nativeCancelJob and nativeGetJob
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "job_id": "export-1", "output_path": "/storage/.../library_stats.csv" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeExportLibraryStats(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeExportLibraryStats", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            output_path: String,
+            #[serde(default = "default_format")]
+            format: String,
+            #[serde(default = "default_low_bitrate")]
+            low_bitrate_kbps: u32,
+            #[serde(default)]
+            job_id: Option<String>,
+        }
+
+        fn default_format() -> String {
+            "csv".to_string()
+        }
+
+        fn default_low_bitrate() -> u32 {
+            crate::storage::library_stats::DEFAULT_LOW_BITRATE_KBPS
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+            let format: crate::storage::library_stats::StatsExportFormat = params.format.parse()?;
+
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Export)?;
+            let job_id = job.job_id().to_string();
+            let db = RUNTIME.block_on(crate::storage::Database::new(&params.db_path))?;
+            let output_path = params.output_path.clone();
+            RUNTIME.spawn(async move {
+                let result = crate::storage::jobs::run_job(db.pool(), &job, async {
+                    let stats =
+                        crate::storage::library_stats::library_stats(db.pool(), params.low_bitrate_kbps, job.token())
+                            .await?;
+                    crate::storage::library_stats::export_library_stats(
+                        &stats,
+                        std::path::Path::new(&params.output_path),
+                        format,
+                        Some(export_progress_callback(job.job_id())),
+                        job.token(),
+                    )
+                    .await?;
+                    crate::storage::jobs::add_job_artifacts(
+                        db.pool(),
+                        job.job_id(),
+                        &[crate::storage::jobs::JobArtifact::file(params.output_path.as_str())],
+                    )
+                    .await
+                })
+                .await;
+                if let Err(e) = result {
+                    crate::trace::trace_eprintln!("Library stats export {} failed: {}", job.job_id(), e);
+                }
+            });
+
+            Ok(success_response(serde_json::json!({
+                "job_id": job_id,
+                "output_path": output_path,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Export the library to a CSV or JSON file
+///
+/// One entry per book of the active profile: asin, title, subtitle,
+/// authors, narrators, series, account, purchase_date, date_published,
+/// length_in_minutes, locale, liberated_status, pdf_status, community and
+/// personal ratings, and tags. CSV joins lists with ", " and writes series
+/// as "Name #order"; JSON keeps them as arrays.
+///
+/// Runs as a background Export job like `nativeExportLibraryStats`:
+/// returns the job id at once, reports progress events for it,
+/// `nativeCancelJob` stops it between chunks, and the file only appears at
+/// `output_path` once it is complete.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "output_path": "/storage/emulated/0/Download/library.csv",
+///   "format": "csv",           // "csv" (default) or "json"
+///   "job_id": "export-1"       // optional, for nativeCancelJob and nativeGetJob
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "job_id": "export-1", "output_path": "/storage/.../library.csv" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeExportLibrary(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeExportLibrary", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            output_path: String,
+            #[serde(default = "default_format")]
+            format: String,
+            #[serde(default)]
+            job_id: Option<String>,
+        }
+
+        fn default_format() -> String {
+            "csv".to_string()
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+            let format: crate::storage::export::ExportFormat = params.format.parse()?;
+
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Export)?;
+            let job_id = job.job_id().to_string();
+            let db = RUNTIME.block_on(crate::storage::Database::new(&params.db_path))?;
+            let output_path = params.output_path.clone();
+            RUNTIME.spawn(async move {
+                let result = crate::storage::jobs::run_job(db.pool(), &job, async {
+                    crate::storage::export::export_library(
+                        db.pool(),
+                        std::path::Path::new(&params.output_path),
+                        format,
+                        Some(export_progress_callback(job.job_id())),
+                        job.token(),
+                    )
+                    .await?;
+                    crate::storage::jobs::add_job_artifacts(
+                        db.pool(),
+                        job.job_id(),
+                        &[crate::storage::jobs::JobArtifact::file(params.output_path.as_str())],
+                    )
+                    .await
+                })
+                .await;
+                if let Err(e) = result {
+                    crate::trace::trace_eprintln!("Library export {} failed: {}", job.job_id(), e);
+                }
+            });
+
+            Ok(success_response(serde_json::json!({
+                "job_id": job_id,
+                "output_path": output_path,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Progress callback for an export job, as Export progress events for
+/// `job_id` (see `nativeSetWorkEventListener` and `nativeGetProgress`)
+fn export_progress_callback(job_id: &str) -> crate::storage::export::ExportProgressCallback {
+    let job_id = job_id.to_string();
+    std::sync::Arc::new(move |progress: crate::storage::export::ExportProgress| {
+        crate::events::emit_progress(crate::events::ProgressEvent {
+            bytes_processed: progress.bytes_written,
+            ..crate::events::ProgressEvent::for_items(
+                crate::cancel::JobKind::Export,
+                &job_id,
+                crate::events::ProgressStage::Exporting,
+                progress.rows_written as u64,
+                progress.total_rows as u64,
+            )
+        })
+    })
+}
+
+/// Export liberated books for Audiobookshelf / Plex
+///
+/// Copies each liberated file into `Author/Series/Vol. N - Title/` under
+/// `output_dir` with `metadata.json`, `metadata.opf`, a CUE sheet and
+/// `cover.jpg`. Without `asin`, all of the active profile's liberated books
+/// are exported; books that fail are listed in `failed`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "output_dir": "/storage/emulated/0/Audiobookshelf",
+///   "asin": "B07T2F8VJM",        // optional, one book
+///   "options": {                 // optional, all true by default
+///     "metadata_json": true, "opf": true, "cue": true, "cover": true
+///   },
+///   "job_id": "export-2"         // optional, for nativeCancelJob and nativeGetJob
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "exported": [
+///       {
+///         "asin": "B07T2F8VJM",
+///         "folder": "/storage/.../Dennis E. Taylor/Bobiverse/Vol. 1 - We Are Legion",
+///         "files": ["We Are Legion.m4b", "metadata.json", "metadata.opf", "We Are Legion.cue", "cover.jpg"],
+///         "audio_skipped": false
+///       }
+///     ],
+///     "failed": [{ "asin": "B0...", "error": "File not found: ..." }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeExportForServer(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeExportForServer", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            output_dir: String,
+            #[serde(default)]
+            asin: Option<String>,
+            #[serde(default)]
+            options: crate::file::server_export::ServerExportOptions,
+            #[serde(default)]
+            job_id: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Export)?;
+            let report = RUNTIME.block_on(async {
+                use crate::file::server_export::{export_book, export_library, ServerExportReport};
+
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let root = std::path::Path::new(&params.output_dir);
+                crate::storage::jobs::run_job(db.pool(), &job, async {
+                    let report = match params.asin {
+                        Some(asin) => ServerExportReport {
+                            exported: vec![export_book(db.pool(), &asin, root, params.options).await?],
+                            failed: Vec::new(),
+                        },
+                        None => export_library(db.pool(), root, params.options, job.token()).await?,
+                    };
+                    let folders: Vec<_> = report
+                        .exported
+                        .iter()
+                        .map(|book| crate::storage::jobs::JobArtifact::directory(book.folder.as_str()))
+                        .collect();
+                    crate::storage::jobs::add_job_artifacts(db.pool(), job.job_id(), &folders).await?;
+                    Ok(report)
+                })
+                .await
+            })?;
+
+            Ok(success_response(report))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the integrity verification policy
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../audible.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "enabled": false,
+///     "mode": "sampled",         // or "full"
+///     "interval_days": 30,
+///     "max_files_per_run": null
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetIntegrityPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetIntegrityPolicy", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let policy = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::integrity::get_integrity_policy(db.pool()).await
+            })?;
+
+            Ok(success_response(policy))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the integrity verification policy
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "policy": { "enabled": true, "mode": "sampled", "interval_days": 30, "max_files_per_run": 50 }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetIntegrityPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetIntegrityPolicy", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            policy: crate::file::integrity::IntegrityPolicy,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::integrity::set_integrity_policy(db.pool(), &params.policy).await
+            })?;
+
+            Ok(success_response(params.policy))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Re-verify liberated files against their stored digests
+///
+/// Meant to be called from a scheduled background job; blocks until the
+/// run ends. Progress can be polled with `nativeGetIntegrityCheckProgress`
+/// and the run stopped with `nativeCancelIntegrityCheck`. Does nothing
+/// while the policy is disabled unless `force` is set.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "mode": "full",   // optional, overrides the policy's mode
+///   "force": false,   // optional, run even if the policy is disabled
+///   "job_id": "integrity-1" // optional, for nativeCancelJob and nativeGetJob
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "ran": true,
+///     "report": {
+///       "mode": "sampled",
+///       "checked": 50,
+///       "ok": 46,
+///       "baselined": 2,
+///       "corrupted": [{
+///         "asin": "B012345678",
+///         "title": "Dune",
+///         "path": "/storage/.../Dune.m4b",
+///         "problem": "Sampled regions don't match",
+///         "checked_at": "2025-01-01T03:00:00Z"
+///       }],
+///       "missing": ["B087654321"],
+///       "skipped": 0,
+///       "bytes_hashed": 52428800,
+///       "cancelled": false,
+///       "started_at": "2025-01-01T03:00:00Z",
+///       "finished_at": "2025-01-01T03:02:10Z"
+///     }
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRunIntegrityCheck(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRunIntegrityCheck", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            mode: Option<crate::file::integrity::VerifyMode>,
+            #[serde(default)]
+            force: bool,
+            #[serde(default)]
+            job_id: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let report = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let mut policy = crate::file::integrity::get_integrity_policy(db.pool()).await?;
+                if !policy.enabled && !params.force {
+                    return Ok::<_, crate::LibationError>(None);
+                }
+                if let Some(mode) = params.mode {
+                    policy.mode = mode;
+                }
+
+                let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::IntegrityCheck)?;
+                let on_progress: crate::file::integrity::IntegrityProgressCallback =
+                    std::sync::Arc::new(|progress| *INTEGRITY_PROGRESS.lock().unwrap() = Some(progress));
+                let report = crate::storage::jobs::run_job(
+                    db.pool(),
+                    &job,
+                    crate::file::integrity::run_integrity_check(
+                        db.pool(),
+                        &policy,
+                        &crate::clock::AppClock,
+                        Some(on_progress),
+                        job.token(),
+                    ),
+                )
+                .await;
+                *INTEGRITY_PROGRESS.lock().unwrap() = None;
+                Ok(Some(report?))
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "ran": report.is_some(),
+                "report": report,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Progress of the running integrity check
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {}
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "running": true,
+///     "progress": {
+///       "total": 50,
+///       "checked": 12,
+///       "corrupted": 1,
+///       "missing": 0,
+///       "bytes_hashed": 12582912,
+///       "current_asin": "B012345678"
+///     }
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetIntegrityCheckProgress(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let response = catch_panic(move || {
+        let progress = INTEGRITY_PROGRESS.lock().unwrap().clone();
+        success_response(serde_json::json!({
+            "running": progress.is_some(),
+            "progress": progress,
+        }))
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Stop the running integrity check after the current file
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {}
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCancelIntegrityCheck(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let response = catch_panic(move || {
+        crate::cancel::cancel_jobs(crate::cancel::JobKind::IntegrityCheck);
+        success_response(serde_json::json!({ "success": true }))
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List files marked corrupted by integrity checks
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../audible.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "files": [{
+///       "asin": "B012345678",
+///       "title": "Dune",
+///       "path": "/storage/.../Dune.m4b",
+///       "problem": "Sampled regions don't match",
+///       "checked_at": "2025-01-01T03:00:00Z"
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListCorruptedFiles(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListCorruptedFiles", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let files = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::integrity::list_corrupted_files(db.pool()).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "files": files })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Reset corrupted books for re-liberation
+///
+/// Deletes the corrupted files and clears the books' download state; the
+/// app then downloads them again through the normal queue.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "asins": ["B012345678"]
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// { "success": true, "data": { "reset": ["B012345678"] } }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeReliberateCorruptedFiles(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeReliberateCorruptedFiles", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asins: Vec<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let reset = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::integrity::reliberate_corrupted(db.pool(), &params.asins).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "reset": reset })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set or clear an account's monthly download cap
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "account": "user@example.com",
+///   "monthly_cap_bytes": 2000000000,  // null removes the cap
+///   "action": "warn"                  // "warn" (default) or "block"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "account": "user@example.com",
+///     "month": "2025-06",
+///     "bytes_downloaded": 734003200,
+///     "quota": { "account": "user@example.com", "monthly_cap_bytes": 2000000000, "action": "warn" },
+///     "exceeded": false
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetDownloadQuota(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetDownloadQuota", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account: String,
+            monthly_cap_bytes: Option<u64>,
+            #[serde(default)]
+            action: crate::download::QuotaAction,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let status = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::download::quota::set_download_quota(
+                    db.pool(),
+                    &params.account,
+                    params.monthly_cap_bytes,
+                    params.action,
+                )
+                .await?;
+                crate::download::quota::get_quota_status(db.pool(), &params.account).await
+            })?;
+
+            Ok(success_response(status))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// ACCOUNT FUNCTIONS
+// ============================================================================
+
+/// Save account to database
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "account_json": "{ ... }"  // Complete account JSON
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "saved": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSaveAccount(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSaveAccount", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_json: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+
+                // Extract account_id from JSON
+                let account: serde_json::Value = serde_json::from_str(&params.account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
+
+                let account_id = account["account_id"].as_str().ok_or_else(|| {
+                    crate::LibationError::InvalidInput("Missing account_id".to_string())
+                })?;
+
+                crate::storage::accounts::save_account(db.pool(), account_id, &params.account_json)
+                    .await?;
+
+                Ok(success_response(serde_json::json!({"saved": true})))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get primary account from database
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "account": "{ ... }"  // Complete account JSON or null if none
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetPrimaryAccount(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetPrimaryAccount", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let account_json = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::accounts::get_primary_account(db.pool()).await
+            })?;
+
+            let response = serde_json::json!({
+                "account": account_json,
+            });
+
+            Ok(success_response(response))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get non-secret token and device details of an account
+///
+/// For troubleshooting sign-in problems. Tokens, keys and activation bytes
+/// are never included.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "account_id": "account-id"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "account_id": "account-id",
+///     "access_token_expires_at": "2025-01-01T12:00:00Z",
+///     "access_token_expires_in_secs": 3540,
+///     "access_token_expired": false,
+///     "has_refresh_token": true,
+///     "refresh_token_issued_at": "2024-06-01T08:00:00+00:00",
+///     "refresh_token_age_days": 214,
+///     "device_registered_at": "2024-06-01T08:00:00+00:00",
+///     "device_name": "LibriSync",
+///     "device_serial_number": "...",
+///     "last_api_success_at": "2025-01-01T11:02:00+00:00",
+///     ...
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetAccountTokenInfo(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetAccountTokenInfo", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let info = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                if let Some(at) = crate::api::client::last_api_success(&params.account_id) {
+                    crate::storage::accounts::record_api_success(db.pool(), &params.account_id, at).await?;
+                }
+                crate::storage::accounts::get_account_token_info(db.pool(), &params.account_id).await
+            })?;
+
+            Ok(success_response(info))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Delete account from database
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "account_id": "account-id"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "deleted": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDeleteAccount(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDeleteAccount", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::accounts::delete_account(db.pool(), &params.account_id).await?;
+
+                Ok(success_response(serde_json::json!({"deleted": true})))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// List all signed-in accounts
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "accounts": [{
+///       "account_id": "us@example.com",
+///       "account_name": "Jane",
+///       "locale_code": "us",
+///       "profile_id": "default",
+///       "last_library_sync": "2025-01-01T10:00:00.000Z",
+///       "book_count": 312,
+///       "account": "{ ... }"  // Complete account JSON, for syncing it
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListAccounts(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListAccounts", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let accounts = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let mut accounts = Vec::new();
+                for summary in crate::storage::accounts::list_accounts(db.pool()).await? {
+                    let account_json = crate::storage::accounts::get_account(db.pool(), &summary.account_id).await?;
+                    let mut value = serde_json::to_value(&summary)?;
+                    value["account"] = serde_json::json!(account_json);
+                    accounts.push(value);
+                }
+                Ok::<_, crate::LibationError>(accounts)
+            })?;
+
+            Ok(success_response(serde_json::json!({ "accounts": accounts })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Remove an account with its library
+///
+/// Books only this account owns are removed; books another signed-in
+/// account owns as well stay in the library. Liberated files are kept.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "account_id": "uk@example.com"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "removed": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRemoveAccount(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRemoveAccount", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::accounts::remove_account(db.pool(), &params.account_id).await?;
+
+                Ok(success_response(serde_json::json!({"removed": true})))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Clear download state for all books
+///
+/// Resets download status but keeps all book metadata.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "books_updated": 123 }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearDownloadState(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeClearDownloadState", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let books_updated =
+                    crate::storage::queries::clear_download_state(db.pool()).await?;
+                Ok(success_response(
+                    serde_json::json!({"books_updated": books_updated}),
+                ))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the downloaded file path for a book by ASIN
+///
+/// Returns the file path if a completed download exists.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "asin": "B07NP9L44Y"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "file_path": "/storage/path/to/book.m4b" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBookFilePath(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetBookFilePath", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let file_path =
+                    crate::storage::queries::get_book_file_path(db.pool(), &params.asin).await?;
+                Ok(success_response(
+                    serde_json::json!({"file_path": file_path}),
+                ))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Clear download state for a single book by ASIN
+///
+/// This resets the download status for a specific book, clearing book_status,
+/// last_downloaded, and related fields in UserDefinedItems table.
+/// Also deletes any download tasks for the book to reset to default state.
+/// Optionally deletes the downloaded file from disk.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "asin": "B07NP9L44Y",
+///   "delete_file": false
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "cleared": true, "file_deleted": false, "deleted_path": null }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearBookDownloadState(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeClearBookDownloadState", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            #[serde(default)]
+            delete_file: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let deleted_path = crate::storage::queries::clear_book_download_state(
+                    db.pool(),
+                    &params.asin,
+                    params.delete_file,
+                )
+                .await?;
+
+                Ok(success_response(serde_json::json!({
+                    "cleared": true,
+                    "file_deleted": deleted_path.is_some(),
+                    "deleted_path": deleted_path
+                })))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the file path for a book manually
+///
+/// Allows users to mark a book as downloaded by associating it with an
+/// existing audio file on disk. Creates a download task with status "completed".
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db",
+///   "asin": "B07NP9L44Y",
+///   "title": "Book Title",
+///   "file_path": "/storage/emulated/0/Download/book.m4b"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "task_id": "uuid-string" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetBookFilePath(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetBookFilePath", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            title: String,
+            file_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let task_id = crate::storage::queries::set_book_file_path(
+                    db.pool(),
+                    &params.asin,
+                    &params.title,
+                    &params.file_path,
+                )
+                .await?;
+
+                Ok(success_response(serde_json::json!({"task_id": task_id})))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Clear all library data (for testing)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../audible.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "deleted": true }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearLibrary(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeClearLibrary", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::queries::clear_library(db.pool()).await?;
+                Ok(success_response(serde_json::json!({"deleted": true})))
+            })
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// CONTENT FILTER
+// ============================================================================
+
+/// Get the parental content filter settings
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "enabled": true,
+///     "excluded_categories": ["Erotica"],
+///     "pin_set": true
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetContentFilter(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetContentFilter", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let filter = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::content_filter::get_content_filter(db.pool()).await
+            })?;
+
+            Ok(success_response(filter))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Update the parental content filter
+///
+/// Only the given fields change. Once a PIN is set, `pin` is required to
+/// turn the filter off, edit the exclusions or change the PIN; a missing
+/// or incorrect PIN fails with "Permission denied".
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "pin": "1234",                          // optional, current PIN
+///   "enabled": true,                        // optional
+///   "excluded_categories": ["Erotica"],     // optional, category ids or names
+///   "new_pin": "5678",                      // optional, 4-12 digits
+///   "remove_pin": false                     // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// The updated settings, as `nativeGetContentFilter`.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUpdateContentFilter(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeUpdateContentFilter", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            pin: Option<String>,
+            enabled: Option<bool>,
+            excluded_categories: Option<Vec<String>>,
+            new_pin: Option<String>,
+            #[serde(default)]
+            remove_pin: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let filter = RUNTIME.block_on(async {
+                use crate::storage::content_filter;
+
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let pin = params.pin.as_deref();
+
+                // Exclusions first so a new PIN doesn't invalidate `pin`
+                if let Some(ref categories) = params.excluded_categories {
+                    content_filter::set_excluded_categories(db.pool(), categories, pin).await?;
+                }
+                if let Some(enabled) = params.enabled {
+                    content_filter::set_content_filter_enabled(db.pool(), enabled, pin).await?;
+                }
+                if params.remove_pin || params.new_pin.is_some() {
+                    content_filter::set_content_filter_pin(db.pool(), pin, params.new_pin.as_deref()).await?;
+                }
+
+                content_filter::get_content_filter(db.pool()).await
+            })?;
+
+            Ok(success_response(filter))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// PROFILES
+// ============================================================================
+
+/// List profiles
+///
+/// The active profile scopes the library, progress, stats and settings
+/// calls; see `nativeSwitchProfile`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "profiles": [
+///       {
+///         "profile_id": "default",
+///         "name": "Default",
+///         "is_active": true,
+///         "created_at": "2025-01-01T10:00:00.000Z",
+///         "account_ids": ["user@example.com"]
+///       }
+///     ]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListProfiles(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListProfiles", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let profiles = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::profiles::list_profiles(db.pool()).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "profiles": profiles })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Create a profile
+///
+/// The new profile is not activated. Accounts added while it is active
+/// join it.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "name": "Kids"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// The new profile, as in `nativeListProfiles`.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCreateProfile(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeCreateProfile", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            name: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let profile = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::profiles::create_profile(db.pool(), &params.name).await
+            })?;
+
+            Ok(success_response(profile))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Switch the active profile
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "profile_id": "default"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// The now active profile, as in `nativeListProfiles`.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSwitchProfile(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSwitchProfile", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            profile_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let profile = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::profiles::switch_profile(db.pool(), &params.profile_id).await?;
+                crate::storage::profiles::active_profile(db.pool()).await
+            })?;
+
+            Ok(success_response(profile))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Delete a profile with its accounts, their library and its settings
+///
+/// Liberated files are kept. Deleting the active profile activates the
+/// default profile, which can't be deleted.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "profile_id": "3f2b..."
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "active_profile_id": "default" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDeleteProfile(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDeleteProfile", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            profile_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let active = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::profiles::delete_profile(db.pool(), &params.profile_id).await?;
+                crate::storage::profiles::active_profile(db.pool()).await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "active_profile_id": active.profile_id })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Move an account, and the books it owns, to another profile
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "account_id": "user@example.com",
+///   "profile_id": "3f2b..."
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// { "success": true, "data": { "moved": true } }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeMoveAccountToProfile(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeMoveAccountToProfile", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            account_id: String,
+            profile_id: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::profiles::move_account_to_profile(db.pool(), &params.account_id, &params.profile_id)
+                    .await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "moved": true })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// STORAGE BACKENDS
+// ============================================================================
+
+/// Get the storage backend for liberated files
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "backend": {                       // null: files stay where the app writes them
+///       "kind": "web_dav",               // "local", "web_dav" or "smb"
+///       "url": "https://nas.local/dav/Audiobooks",
+///       "username": "me",
+///       "password": "********"
+///     },
+///     "capabilities": { "resume": false, "rename": true }
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetStorageBackend(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetStorageBackend", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let config = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::backend::load_backend_config(db.pool()).await
+            })?;
+            let capabilities = match &config {
+                Some(config) => Some(config.open()?.capabilities()),
+                None => None,
+            };
+
+            Ok(success_response(serde_json::json!({
+                "backend": config.map(|c| c.redacted()),
+                "capabilities": capabilities,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the storage backend for liberated files
+///
+/// The destination is probed (a small file is written, appended to,
+/// renamed and deleted) before the configuration is saved, so an
+/// unreachable server or read-only share fails here. A `password` of
+/// `"********"` keeps the stored password.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "backend": { "kind": "smb", "mount_path": "/storage/..." }  // null to remove
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// As `nativeGetStorageBackend`, with the probed capabilities.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetStorageBackend(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetStorageBackend", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            backend: Option<crate::file::BackendConfig>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let (config, capabilities) = RUNTIME.block_on(async {
+                use crate::file::backend::{self, BackendConfig};
+
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let Some(mut config) = params.backend else {
+                    backend::save_backend_config(db.pool(), None).await?;
+                    return Ok::<_, crate::LibationError>((None, None));
+                };
+
+                // The app only ever sees the redacted password
+                if let BackendConfig::WebDav { password, .. } = &mut config {
+                    if password.as_deref() == Some("********") {
+                        *password = match backend::load_backend_config(db.pool()).await? {
+                            Some(BackendConfig::WebDav { password, .. }) => password,
+                            _ => None,
+                        };
+                    }
+                }
+
+                let capabilities = backend::probe_capabilities(config.open()?.as_ref()).await?;
+                backend::save_backend_config(db.pool(), Some(&config)).await?;
+                Ok((Some(config.redacted()), Some(capabilities)))
+            })?;
+
+            Ok(success_response(serde_json::json!({
+                "backend": config,
+                "capabilities": capabilities,
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Move a liberated file to the configured storage backend
+///
+/// Called after conversion with the local output file. Interrupted uploads
+/// resume on backends that support it (call again with the same target).
+/// The local file is deleted after a verified upload and the book's file
+/// path is set to the stored location. With `receipt`, the local file is
+/// hashed first and a liberation receipt is recorded for the stored
+/// location (see `nativeRecordLiberationReceipt`).
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",
+///   "source_path": "/data/data/.../cache/book.m4b",
+///   "target": "Author/Series/Book.m4b",   // relative to the backend root
+///   "receipt": { "task_id": "uuid", "decrypt_method": "aaxc", "license_id": "CR!..." } // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "location": "https://nas.local/dav/Audiobooks/Author/Series/Book.m4b" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeStoreLiberatedFile(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeStoreLiberatedFile", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            source_path: String,
+            target: String,
+            receipt: Option<LiberationDetails>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let location = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let config = crate::file::backend::load_backend_config(db.pool())
+                    .await?
+                    .ok_or_else(|| crate::LibationError::invalid_input("No storage backend configured"))?;
+                let book = crate::storage::queries::find_book_by_asin(db.pool(), &params.asin)
+                    .await?
+                    .ok_or_else(|| crate::LibationError::not_found(format!("Book not found: {}", params.asin)))?;
+
+                let source = std::path::Path::new(&params.source_path);
+                let location =
+                    crate::file::backend::store_file(config.open()?.as_ref(), source, &params.target).await?;
+                match params.receipt {
+                    Some(details) => {
+                        let file = crate::storage::receipts::ReceiptFile::hash(source, location.as_str()).await?;
+                        let receipt = details.into_receipt(&params.asin, vec![file]);
+                        crate::storage::receipts::record_liberation(db.pool(), &book.title, &receipt).await?;
+                    }
+                    None => {
+                        crate::storage::queries::set_book_file_path(db.pool(), &params.asin, &book.title, &location)
+                            .await?;
+                    }
+                }
+                tokio::fs::remove_file(source).await?;
+                Ok::<_, crate::LibationError>(location)
+            })?;
+
+            Ok(success_response(serde_json::json!({ "location": location })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// STORAGE LOCATIONS
+// ============================================================================
+
+/// List registered storage roots
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "roots": [{ "path": "/storage/1A2B-3C4D/Audiobooks", "label": "SD card" }],
+///     "in_use": ["/storage/1A2B-3C4D/Audiobooks"]  // roots books are stored under
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetStorageRoots(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetStorageRoots", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let (roots, in_use) = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let roots = crate::file::locations::list_storage_roots(db.pool()).await?;
+                let in_use = crate::file::locations::list_output_roots_in_use(db.pool()).await?;
+                Ok::<_, crate::LibationError>((roots, in_use))
+            })?;
+
+            Ok(success_response(serde_json::json!({ "roots": roots, "in_use": in_use })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Register a storage root books can be stored under
+///
+/// Call once the app has access to the location. Registering it again
+/// updates the label. Fails if a filesystem path isn't a writable
+/// directory; `content://` tree URIs aren't checked.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "root": { "path": "/storage/1A2B-3C4D/Audiobooks", "label": "SD card" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRegisterStorageRoot(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRegisterStorageRoot", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            root: crate::file::locations::StorageRoot,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::locations::register_storage_root(db.pool(), &params.root).await
+            })?;
+
+            Ok(success_response(params.root))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Forget a storage root
+///
+/// Fails while books are still stored under it.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "path": "/storage/1A2B-3C4D/Audiobooks"
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUnregisterStorageRoot(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeUnregisterStorageRoot", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::locations::unregister_storage_root(db.pool(), &params.path).await
+            })?;
+
+            Ok(success_response(serde_json::json!({})))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Store a book under its own storage root, or back under the default
+///
+/// Later liberations of the book go to the root instead of the output
+/// directory passed with the download (see `nativeStoreConversionKeys`),
+/// and library scans look for it there. The root must be registered and,
+/// for filesystem paths, writable. Files already liberated aren't moved.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",
+///   "output_root": "/storage/1A2B-3C4D/Audiobooks"  // null for the default directory
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "asin": "B07T2F8VJM", "output_root": "/storage/1A2B-3C4D/Audiobooks" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetBookOutputRoot(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetBookOutputRoot", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            output_root: Option<String>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::locations::set_book_output_root(db.pool(), &params.asin, params.output_root.as_deref())
+                    .await
+            })?;
+
+            Ok(success_response(serde_json::json!({ "asin": params.asin, "output_root": params.output_root })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// COMPANION CONTENT
+// ============================================================================
+
+/// List a title's companion content and what the companion policy picks
+///
+/// Companion audio comes from the catalog product's relationships, the PDF
+/// from the synced library. Download the `selected` audio like any title
+/// (`nativeGetDownloadLicense`, then `nativeEnqueueDownload` with
+/// `companion_of`), and PDFs with `nativeDownloadCompanionPdf`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "accountJson": "{ ... }",
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",
+///   "opt_in": true  // Optional: overrides the policy's `enabled` for this book
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "assets": [
+///       { "kind": "author_interview", "asin": "B07T2INTRV", "url": null, "sort": 1 },
+///       { "kind": "pdf", "asin": null, "url": "https://...", "sort": null }
+///     ],
+///     "selected": [{ "kind": "pdf", "asin": null, "url": "https://...", "sort": null }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCompanionAssets(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetCompanionAssets", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            #[serde(rename = "accountJson")]
+            account_json: String,
+            db_path: String,
+            asin: String,
+            #[serde(default)]
+            opt_in: Option<bool>,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let (assets, selected) = RUNTIME.block_on(async {
+                let account: crate::api::auth::Account = serde_json::from_str(&params.account_json)
+                    .map_err(|e| {
+                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
+                    })?;
+                let client = crate::api::client::AudibleClient::new(account)?;
+                let product = client.get_catalog_product(&params.asin).await?;
+
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let assets = crate::download::companion::list_companion_assets(db.pool(), &product).await?;
+                let policy = crate::download::companion::get_companion_policy(db.pool()).await?;
+                let selected = crate::download::companion::select_companions(&policy, &assets, params.opt_in);
+                Ok::<_, crate::LibationError>((assets, selected))
+            })?;
+
+            Ok(success_response(serde_json::json!({ "assets": assets, "selected": selected })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the companion content policy
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "enabled": false, "kinds": ["bonus_audio", "author_interview", "pdf"] }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCompanionPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetCompanionPolicy", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let policy = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::download::companion::get_companion_policy(db.pool()).await
+            })?;
+
+            Ok(success_response(policy))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the companion content policy
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "policy": { "enabled": true, "kinds": ["author_interview", "pdf"] }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetCompanionPolicy(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetCompanionPolicy", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            policy: crate::download::companion::CompanionPolicy,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::download::companion::set_companion_policy(db.pool(), &params.policy).await
+            })?;
+
+            Ok(success_response(params.policy))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the post-liberation hooks
+///
+/// # Arguments (JSON string)
+/// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "enabled": false, "hooks": [], "max_attempts": 3, "retry_delay_ms": 2000 }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetPostHooks(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetPostHooks", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let config = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::post_hooks::get_post_hooks(db.pool()).await
+            })?;
+
+            Ok(success_response(config))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the post-liberation hooks, run in order after each liberation
+///
+/// The media scan hook needs an `onMediaScan` method on the work event
+/// listener (see `nativeSetWorkEventListener`).
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "config": {
+///     "enabled": true,
+///     "hooks": [
+///       { "type": "move", "directory": "/storage/emulated/0/Sync/Audiobooks" },  // or "copy"
+///       { "type": "media_scan" },
+///       { "type": "webhook", "url": "https://example.com/hook", "headers": { "X-Api-Key": "..." } }
+///     ],
+///     "max_attempts": 3,       // 1-10, per hook
+///     "retry_delay_ms": 2000   // doubled for each retry
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetPostHooks(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetPostHooks", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            config: crate::file::post_hooks::PostHookConfig,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::post_hooks::set_post_hooks(db.pool(), &params.config).await
+            })?;
+
+            Ok(success_response(params.config))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Download a book's PDF supplement and record it with the book
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",
+///   "url": "https://...",
+///   "destination": "/storage/emulated/0/Audiobooks/Author/Title/Title.pdf"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// The recorded `BookFile`:
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "file_id": 3,
+///     "asin": "B07T2F8VJM",
+///     "kind": "pdf",
+///     "source_asin": null,
+///     "path": "/storage/emulated/0/Audiobooks/Author/Title/Title.pdf",
+///     "size_bytes": 1048576,
+///     "task_id": null,
+///     "sha256": "9f86d081884c7d65...",
+///     "created_at": "2025-03-01T12:00:00Z"
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDownloadCompanionPdf(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDownloadCompanionPdf", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            url: String,
+            destination: String,
+        }
+
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let file = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::download::companion::download_companion_pdf(
+                    db.pool(),
+                    &params.asin,
+                    &params.url,
+                    std::path::Path::new(&params.destination),
+                )
+                .await
+            })?;
 
-                // Get download license
-                let license = client
-                    .build_download_license(&params.asin, quality, false)
-                    .await?;
+            Ok(success_response(file))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
 
-                // Extract AAXC keys
-                let (key_hex, iv_hex) = if let Some(ref keys) = license.decryption_keys {
-                    if !keys.is_empty() && keys[0].key_part_1.len() == 16 {
-                        let key = keys[0]
-                            .key_part_1
-                            .iter()
-                            .map(|b| format!("{:02x}", b))
-                            .collect::<String>();
-                        let iv = if let Some(ref iv_bytes) = keys[0].key_part_2 {
-                            iv_bytes
-                                .iter()
-                                .map(|b| format!("{:02x}", b))
-                                .collect::<String>()
-                        } else {
-                            return Err(crate::LibationError::InvalidInput(
-                                "No IV in AAXC keys".to_string(),
-                            ));
-                        };
-                        (key, iv)
-                    } else {
-                        return Err(crate::LibationError::InvalidInput(
-                            "Unsupported key format (only AAXC supported)".to_string(),
-                        ));
-                    }
-                } else {
-                    return Err(crate::LibationError::InvalidInput(
-                        "No decryption keys in license".to_string(),
-                    ));
-                };
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
 
-                // Download encrypted file to cache directory
-                // (TypeScript layer will copy to user's chosen directory after decryption)
-                let cache_dir = std::env::var("TMPDIR")
-                    .or_else(|_| std::env::var("TEMP"))
-                    .unwrap_or_else(|_| "/data/local/tmp".to_string());
+/// List a book's companion files (bonus audio, interviews, PDFs)
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM"
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "files": [{ "file_id": 3, "kind": "author_interview", "source_asin": "B07T2INTRV", ... }] }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListBookFiles(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListBookFiles", &params_str_result);
 
-                // Create audiobooks subdirectory in cache
-                let audiobooks_cache = format!("{}/audiobooks", cache_dir.trim_end_matches('/'));
-                let _ = std::fs::create_dir_all(&audiobooks_cache);
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+        }
 
-                let encrypted_path = format!("{}/{}.aax", audiobooks_cache, params.asin);
-                let decrypted_path = format!("{}/{}.m4b", audiobooks_cache, params.asin);
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-                // Download with reqwest
-                let user_agent = "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0";
-                let http_client = reqwest::Client::new();
-                let response = http_client
-                    .get(&license.download_url)
-                    .header("User-Agent", user_agent)
-                    .send()
-                    .await
-                    .map_err(|e| crate::LibationError::NetworkError {
-                        message: format!("Download request failed: {}", e),
-                        is_transient: true,
-                    })?;
+            let files = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::storage::book_files::list_book_files(db.pool(), &params.asin).await
+            })?;
 
-                if !response.status().is_success() {
-                    return Err(crate::LibationError::NetworkError {
-                        message: format!("HTTP {}", response.status()),
-                        is_transient: false,
-                    });
-                }
+            Ok(success_response(serde_json::json!({ "files": files })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
 
-                use futures_util::StreamExt;
-                use tokio::io::AsyncWriteExt;
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
 
-                let mut file = tokio::fs::File::create(&encrypted_path)
-                    .await
-                    .map_err(|e| {
-                        crate::LibationError::internal(format!(
-                            "Failed to create file {}: {}",
-                            encrypted_path, e
-                        ))
-                    })?;
+// ============================================================================
+// LIBERATION RECEIPTS
+// ============================================================================
 
-                let mut stream = response.bytes_stream();
-                while let Some(chunk) = stream.next().await {
-                    let chunk = chunk.map_err(|e| crate::LibationError::NetworkError {
-                        message: format!("Stream error: {}", e),
-                        is_transient: true,
-                    })?;
-                    file.write_all(&chunk).await.map_err(|e| {
-                        crate::LibationError::internal(format!("Write failed: {}", e))
-                    })?;
-                }
-                file.flush()
-                    .await
-                    .map_err(|e| crate::LibationError::internal(format!("Flush failed: {}", e)))?;
+/// How the app liberated a file, for a receipt (see `storage::receipts`)
+#[derive(Deserialize)]
+struct LiberationDetails {
+    /// Download task to mark completed; a completed task is created if omitted
+    task_id: Option<String>,
+    /// Defaults to "audible_download"
+    source: Option<crate::storage::receipts::InputSource>,
+    source_ref: Option<String>,
+    license_id: Option<String>,
+    decrypt_method: crate::storage::receipts::DecryptMethod,
+    download_ms: Option<i64>,
+    decrypt_ms: Option<i64>,
+    /// FFmpeg-Kit version, if it did the conversion
+    ffmpeg_version: Option<String>,
+}
 
-                // Return encrypted file path and decryption keys
-                // The TypeScript/Kotlin layer will use FFmpeg-Kit to decrypt
-                let file_metadata = tokio::fs::metadata(&encrypted_path).await.map_err(|e| {
-                    crate::LibationError::not_found(format!(
-                        "Downloaded file not found: {}",
-                        encrypted_path
-                    ))
-                })?;
+impl LiberationDetails {
+    fn into_receipt(
+        self,
+        asin: &str,
+        files: Vec<crate::storage::receipts::ReceiptFile>,
+    ) -> crate::storage::receipts::NewReceipt {
+        crate::storage::receipts::NewReceipt {
+            asin: asin.to_string(),
+            task_id: self.task_id,
+            source: self.source.unwrap_or(crate::storage::receipts::InputSource::AudibleDownload),
+            source_ref: self.source_ref,
+            license_id: self.license_id,
+            decrypt_method: self.decrypt_method,
+            files,
+            download_ms: self.download_ms,
+            decrypt_ms: self.decrypt_ms,
+            toolchain: crate::storage::receipts::Toolchain::with_ffmpeg(self.ffmpeg_version),
+        }
+    }
+}
 
-                // Fetch book metadata from database if db_path provided
-                let book_metadata = if let Some(ref db_path) = params.db_path {
-                    let db = crate::storage::Database::new(db_path).await?;
-                    crate::storage::queries::find_book_with_relations_by_asin(
-                        db.pool(),
-                        &params.asin,
-                    )
-                    .await?
-                } else {
-                    None
-                };
+/// Record the liberation receipt of a finished book and mark it liberated
+///
+/// The receipt and the completed download task are written in one
+/// transaction. Files given by `path` are sized and hashed here; files
+/// Rust can't read (`content://` URIs) are passed already hashed. The
+/// first file becomes the book's file path. With `sidecar: true` the
+/// receipt is also written next to a local first file as
+/// `<name>.receipt.json`.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",
+///   "task_id": "uuid-string",              // optional
+///   "source": "audible_download",          // or "watch_folder", "local_file"
+///   "source_ref": "cds.audible.com",       // optional, never a signed URL
+///   "license_id": "CR!...",                // optional, the license's ACR
+///   "decrypt_method": "aaxc",              // "aax", "aaxc" or "none"
+///   "download_ms": 81234,                  // optional
+///   "decrypt_ms": 20411,                   // optional
+///   "ffmpeg_version": "6.0",               // optional
+///   "files": [
+///     { "path": "/storage/.../Book.m4b" },
+///     { "location": "content://...", "size_bytes": 1234, "sha256": "hex" }
+///   ],
+///   "sidecar": false
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "receipt": { "receipt_id": 1, "task_id": "uuid-string", "files": [...], "toolchain": {...}, ... },
+///     "sidecar_path": null,
+///     "post_hooks": [                      // empty unless post-hooks are on (nativeSetPostHooks)
+///       { "hook": { "type": "move", "directory": "/storage/.../Sync" }, "attempts": 1, "error": null }
+///     ]
+///   }
+/// }
+/// ```
+///
+/// The configured post-hooks run after the receipt is recorded; a move
+/// hook's new location is in the returned receipt. Hook failures are
+/// reported in `post_hooks`, not as an error.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeRecordLiberationReceipt(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeRecordLiberationReceipt", &params_str_result);
 
-                // Return decryption info and metadata for FFmpeg-Kit to use
-                #[derive(Serialize)]
-                struct BookMetadata {
-                    title: String,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    subtitle: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    authors: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    narrators: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    publisher: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    series_name: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    series_sequence: Option<f32>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    description: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    date_published: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    language: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    picture_large: Option<String>,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    picture_id: Option<String>,
-                    audible_asin: String,
-                }
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum FileParam {
+            Hashed(crate::storage::receipts::ReceiptFile),
+            Local { path: String },
+        }
 
-                #[derive(Serialize)]
-                struct DownloadResultWithKeys {
-                    #[serde(rename = "encryptedPath")]
-                    encrypted_path: String,
-                    #[serde(rename = "outputPath")]
-                    output_path: String,
-                    #[serde(rename = "fileSize")]
-                    file_size: u64,
-                    #[serde(rename = "aaxcKey")]
-                    aaxc_key: String,
-                    #[serde(rename = "aaxcIv")]
-                    aaxc_iv: String,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    metadata: Option<BookMetadata>,
-                }
+        #[derive(Deserialize)]
+        struct Params {
+            db_path: String,
+            asin: String,
+            files: Vec<FileParam>,
+            #[serde(default)]
+            sidecar: bool,
+            #[serde(flatten)]
+            details: LiberationDetails,
+        }
 
-                let metadata = book_metadata.map(|b| BookMetadata {
-                    title: b.title,
-                    subtitle: b.subtitle,
-                    authors: b.authors_str,
-                    narrators: b.narrators_str,
-                    publisher: b.publisher,
-                    series_name: b.series_name,
-                    series_sequence: b.series_sequence,
-                    description: Some(b.description),
-                    date_published: b.date_published,
-                    language: b.language,
-                    picture_large: b.picture_large,
-                    picture_id: b.picture_id,
-                    audible_asin: params.asin.clone(),
-                });
+        match (move || -> crate::Result<String> {
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let book = crate::storage::queries::find_book_by_asin(db.pool(), &params.asin)
+                    .await?
+                    .ok_or_else(|| crate::LibationError::not_found(format!("Book not found: {}", params.asin)))?;
+
+                let mut files = Vec::with_capacity(params.files.len());
+                for file in params.files {
+                    files.push(match file {
+                        FileParam::Hashed(file) => file,
+                        FileParam::Local { path } => {
+                            crate::storage::receipts::ReceiptFile::hash(std::path::Path::new(&path), path.as_str())
+                                .await?
+                        }
+                    });
+                }
 
-                Ok::<_, crate::LibationError>(DownloadResultWithKeys {
-                    encrypted_path,
-                    output_path: decrypted_path,
-                    file_size: file_metadata.len(),
-                    aaxc_key: key_hex,
-                    aaxc_iv: iv_hex,
-                    metadata,
-                })
-            })?;
+                let receipt = params.details.into_receipt(&params.asin, files);
+                let receipt =
+                    crate::storage::receipts::record_liberation(db.pool(), &book.title, &receipt).await?;
 
-            Ok(success_response(result))
+                let output = std::path::Path::new(&receipt.files[0].location);
+                let sidecar_path = if params.sidecar && output.is_file() {
+                    Some(crate::storage::receipts::write_sidecar(&receipt, output).await?)
+                } else {
+                    None
+                };
+
+                // A move hook takes the sidecar along
+                let report = crate::file::post_hooks::run_post_hooks(db.pool(), receipt).await?;
+                let sidecar_path = sidecar_path.map(|_| {
+                    crate::storage::receipts::sidecar_path(std::path::Path::new(&report.receipt.files[0].location))
+                });
+
+                Ok(success_response(serde_json::json!({
+                    "receipt": report.receipt,
+                    "sidecar_path": sidecar_path,
+                    "post_hooks": report.outcomes,
+                })))
+            })
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2105,20 +11504,14 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDownlo
         .into_raw()
 }
 
-// ============================================================================
-// LICENSE FUNCTIONS
-// ============================================================================
-
-/// Get download license without downloading
-///
-/// This allows getting the license info (URL, keys, size) to enqueue in download manager
+/// Liberation receipts of a book, or of whatever produced a file
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "accountJson": "{ ... }",
-///   "asin": "B07T2F8VJM",
-///   "quality": "High"
+///   "db_path": "/data/data/.../libation.db",
+///   "asin": "B07T2F8VJM",     // either this
+///   "sha256": "hex"           // or this
 /// }
 /// ```
 ///
@@ -2126,30 +11519,24 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDownlo
 /// ```json
 /// {
 ///   "success": true,
-///   "data": {
-///     "download_url": "https://...",
-///     "total_bytes": 72000000,
-///     "aaxc_key": "...",
-///     "aaxc_iv": "...",
-///     "request_headers": {"User-Agent": "..."}
-///   }
+///   "data": { "receipts": [ ... ] }   // newest first
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDownloadLicense(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListLiberationReceipts(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListLiberationReceipts", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            #[serde(rename = "accountJson")]
-            account_json: String,
-            asin: String,
-            quality: String,
+            db_path: String,
+            asin: Option<String>,
+            sha256: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -2157,103 +11544,22 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDow
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let result = RUNTIME.block_on(async {
-                // TODO: Add ensure_valid_token() here once we pass db_path in params
-                // For now, WorkManager backup handles token refresh
-
-                let account: crate::api::auth::Account = serde_json::from_str(&params.account_json)
-                    .map_err(|e| {
-                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
-                    })?;
-
-                let quality = match params.quality.as_str() {
-                    "Low" => crate::api::content::DownloadQuality::Low,
-                    "Normal" => crate::api::content::DownloadQuality::Normal,
-                    "High" => crate::api::content::DownloadQuality::High,
-                    "Extreme" => crate::api::content::DownloadQuality::Extreme,
-                    _ => crate::api::content::DownloadQuality::High,
-                };
-
-                let client = crate::api::client::AudibleClient::new(account)?;
-                let license = client
-                    .build_download_license(&params.asin, quality, false)
-                    .await?;
-
-                // Extract AAXC keys
-                let (key_hex, iv_hex) = if let Some(ref keys) = license.decryption_keys {
-                    if !keys.is_empty() && keys[0].key_part_1.len() == 16 {
-                        let key = keys[0]
-                            .key_part_1
-                            .iter()
-                            .map(|b| format!("{:02x}", b))
-                            .collect::<String>();
-                        let iv = if let Some(ref iv_bytes) = keys[0].key_part_2 {
-                            iv_bytes
-                                .iter()
-                                .map(|b| format!("{:02x}", b))
-                                .collect::<String>()
-                        } else {
-                            return Err(crate::LibationError::InvalidInput(
-                                "No IV in AAXC keys".to_string(),
-                            ));
-                        };
-                        (key, iv)
-                    } else {
+            RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let receipts = match (params.asin, params.sha256) {
+                    (Some(asin), None) => crate::storage::receipts::list_receipts(db.pool(), &asin).await?,
+                    (None, Some(sha256)) => {
+                        crate::storage::receipts::find_receipts_by_sha256(db.pool(), &sha256).await?
+                    }
+                    _ => {
                         return Err(crate::LibationError::InvalidInput(
-                            "Unsupported key format (only AAXC supported)".to_string(),
-                        ));
+                            "Pass either asin or sha256".to_string(),
+                        ))
                     }
-                } else {
-                    return Err(crate::LibationError::InvalidInput(
-                        "No decryption keys in license".to_string(),
-                    ));
                 };
 
-                // Build request headers
-                let mut request_headers = std::collections::HashMap::new();
-                request_headers.insert(
-                    "User-Agent".to_string(),
-                    "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0".to_string(),
-                );
-
-                // Get file size from HTTP HEAD request
-                let http_client = reqwest::Client::new();
-                let head_response = http_client
-                    .head(&license.download_url)
-                    .header("User-Agent", "Audible/671 CFNetwork/1240.0.4 Darwin/20.6.0")
-                    .send()
-                    .await
-                    .map_err(|e| crate::LibationError::NetworkError {
-                        message: format!("HEAD request failed: {}", e),
-                        is_transient: true,
-                    })?;
-
-                let total_bytes = head_response
-                    .headers()
-                    .get("content-length")
-                    .and_then(|v| v.to_str().ok())
-                    .and_then(|s| s.parse::<u64>().ok())
-                    .unwrap_or(0);
-
-                #[derive(Serialize)]
-                struct LicenseInfo {
-                    download_url: String,
-                    total_bytes: u64,
-                    aaxc_key: String,
-                    aaxc_iv: String,
-                    request_headers: std::collections::HashMap<String, String>,
-                }
-
-                Ok::<_, crate::LibationError>(LicenseInfo {
-                    download_url: license.download_url,
-                    total_bytes,
-                    aaxc_key: key_hex,
-                    aaxc_iv: iv_hex,
-                    request_headers,
-                })
-            })?;
-
-            Ok(success_response(result))
+                Ok(success_response(serde_json::json!({ "receipts": receipts })))
+            })
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2266,23 +11572,41 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDow
 }
 
 // ============================================================================
-// DOWNLOAD MANAGER FUNCTIONS
+// HANDOFF (lan-handoff feature)
 // ============================================================================
 
-/// Enqueue a download in the persistent download manager
+/// New pairing code for `nativeReceiveHandoff`, to show before waiting
+///
+/// # Returns (JSON)
+/// ```json
+/// { "success": true, "data": { "pairing_code": "042917" } }
+/// ```
+#[cfg(feature = "lan-handoff")]
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCreateHandoffPairingCode(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let response = catch_panic(move || {
+        success_response(serde_json::json!({
+            "pairing_code": crate::file::handoff::generate_pairing_code(),
+        }))
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Devices waiting in `nativeReceiveHandoff` on the local network
+///
+/// Browses mDNS for `timeout_ms`. The host must hold a
+/// `WifiManager.MulticastLock` meanwhile.
 ///
 /// # Arguments (JSON string)
 /// ```json
-/// {
-///   "db_path": "/data/data/.../libation.db",
-///   "asin": "B001",
-///   "title": "Book Title",
-///   "download_url": "https://...",
-///   "total_bytes": 10000000,
-///   "download_path": "/cache/B001.aax",
-///   "output_path": "/output/B001.m4b",
-///   "request_headers": {"User-Agent": "..."}
-/// }
+/// { "timeout_ms": 3000 }   // optional
 /// ```
 ///
 /// # Returns (JSON)
@@ -2290,29 +11614,30 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDow
 /// {
 ///   "success": true,
 ///   "data": {
-///     "task_id": "uuid-string"
+///     "peers": [{ "device_name": "Tablet", "instance": "librisync-1a2b3c4d5e6f",
+///                 "addresses": ["192.168.1.23"], "port": 40123 }]
 ///   }
 /// }
 /// ```
+#[cfg(feature = "lan-handoff")]
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeEnqueueDownload(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDiscoverHandoffPeers(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeDiscoverHandoffPeers", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            db_path: String,
-            asin: String,
-            title: String,
-            download_url: String,
-            total_bytes: u64,
-            download_path: String,
-            output_path: String,
-            request_headers: std::collections::HashMap<String, String>,
+            #[serde(default = "default_timeout_ms")]
+            timeout_ms: u64,
+        }
+
+        fn default_timeout_ms() -> u64 {
+            3000
         }
 
         match (move || -> crate::Result<String> {
@@ -2320,27 +11645,10 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeEnqueu
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let task_id = RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-
-                manager
-                    .enqueue_download(
-                        params.asin,
-                        params.title,
-                        params.download_url,
-                        params.total_bytes,
-                        params.download_path,
-                        params.output_path,
-                        params.request_headers,
-                    )
-                    .await
-            })?;
-
-            let response = serde_json::json!({
-                "task_id": task_id,
-            });
-
-            Ok(success_response(response))
+            let peers = RUNTIME.block_on(crate::file::handoff::discover_peers(std::time::Duration::from_millis(
+                params.timeout_ms,
+            )))?;
+            Ok(success_response(serde_json::json!({ "peers": peers })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2352,13 +11660,24 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeEnqueu
         .into_raw()
 }
 
-/// Get download task status
+/// Wait for a book from another device and store it in the library
+///
+/// Blocks until a sender connects and the transfer ends, so call it off
+/// the main thread; `nativeCancelJob` stops waiting. The device is
+/// advertised over mDNS while waiting (hold a `MulticastLock`). One
+/// sender is accepted: after a wrong pairing code, start over with a new
+/// code. Poll `nativeGetHandoffProgress` for progress.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
-///   "task_id": "uuid-string"
+///   "library_dir": "/storage/.../Audiobooks",
+///   "device_name": "Tablet",
+///   "pairing_code": "042917",                // from nativeCreateHandoffPairingCode
+///   "naming_pattern": "author_series_book",  // optional
+///   "port": 0,                               // optional, 0 picks a free port
+///   "job_id": "handoff-1"                    // optional, for nativeCancelJob and nativeGetJob
 /// }
 /// ```
 ///
@@ -2367,29 +11686,33 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeEnqueu
 /// {
 ///   "success": true,
 ///   "data": {
-///     "task_id": "...",
-///     "asin": "B001",
-///     "title": "Book Title",
-///     "status": "downloading",
-///     "bytes_downloaded": 5000000,
-///     "total_bytes": 10000000,
-///     ...
+///     "sender": "Phone",
+///     "receipt": { "receipt_id": 3, "asin": "B08G9PRS1K", "source": "handoff", "files": [...], ... }
 ///   }
 /// }
 /// ```
+#[cfg(feature = "lan-handoff")]
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDownloadTask(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeReceiveHandoff(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeReceiveHandoff", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            task_id: String,
+            library_dir: String,
+            device_name: String,
+            pairing_code: String,
+            naming_pattern: Option<String>,
+            #[serde(default)]
+            port: u16,
+            #[serde(default)]
+            job_id: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -2397,12 +11720,35 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDow
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let task = RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-                manager.get_task(&params.task_id).await
+            let received = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Handoff)?;
+                let on_progress: crate::file::handoff::HandoffProgressCallback =
+                    std::sync::Arc::new(|progress| *HANDOFF_PROGRESS.lock().unwrap() = Some(progress));
+
+                let addr = std::net::SocketAddr::from(([0, 0, 0, 0], params.port));
+                let mut receiver = crate::file::handoff::HandoffReceiver::bind(addr, params.device_name)
+                    .await?
+                    .with_pairing_code(params.pairing_code)?
+                    .with_progress(on_progress)
+                    .with_cancellation(job.token().clone());
+                if let Some(pattern) = params
+                    .naming_pattern
+                    .as_deref()
+                    .and_then(crate::file::paths::NamingPattern::from_string)
+                {
+                    receiver = receiver.with_naming_pattern(pattern);
+                }
+                let _advertisement = receiver.advertise()?;
+
+                let library_dir = std::path::PathBuf::from(&params.library_dir);
+                let received =
+                    crate::storage::jobs::run_job(db.pool(), &job, receiver.receive(db.pool(), &library_dir)).await;
+                *HANDOFF_PROGRESS.lock().unwrap() = None;
+                received
             })?;
 
-            Ok(success_response(task))
+            Ok(success_response(received))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2414,13 +11760,21 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDow
         .into_raw()
 }
 
-/// List download tasks with optional filter
+/// Send a liberated book to a device waiting in `nativeReceiveHandoff`
+///
+/// Blocks until the receiver has stored the book; `nativeCancelJob`
+/// aborts the transfer and the receiver discards the partial file.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../libation.db",
-///   "filter": "downloading"  // optional: "queued", "downloading", "completed", "failed", etc.
+///   "asin": "B08G9PRS1K",
+///   "address": "192.168.1.23",     // from nativeDiscoverHandoffPeers
+///   "port": 40123,
+///   "pairing_code": "042917",      // shown on the receiver
+///   "device_name": "Phone",
+///   "job_id": "handoff-2"          // optional
 /// }
 /// ```
 ///
@@ -2428,24 +11782,31 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetDow
 /// ```json
 /// {
 ///   "success": true,
-///   "data": {
-///     "tasks": [...]
-///   }
+///   "data": { "asin": "B08G9PRS1K", "receiver": "Tablet", "output_path": "/storage/.../Book.m4b",
+///             "size_bytes": 72000000, "transfer_ms": 41230 }
 /// }
 /// ```
+#[cfg(feature = "lan-handoff")]
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListDownloadTasks(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSendHandoff(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSendHandoff", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            filter: Option<String>,
+            asin: String,
+            address: std::net::IpAddr,
+            port: u16,
+            pairing_code: String,
+            device_name: String,
+            #[serde(default)]
+            job_id: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -2453,23 +11814,31 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListDo
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let tasks = RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-
-                let filter = if let Some(ref f) = params.filter {
-                    Some(crate::download::TaskStatus::from_str(f)?)
-                } else {
-                    None
-                };
+            let sent = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                let job = crate::cancel::register_job(params.job_id, crate::cancel::JobKind::Handoff)?;
+                let on_progress: crate::file::handoff::HandoffProgressCallback =
+                    std::sync::Arc::new(|progress| *HANDOFF_PROGRESS.lock().unwrap() = Some(progress));
 
-                manager.list_tasks(filter).await
+                let sent = crate::storage::jobs::run_job(
+                    db.pool(),
+                    &job,
+                    crate::file::handoff::send_book(
+                        db.pool(),
+                        &params.asin,
+                        std::net::SocketAddr::new(params.address, params.port),
+                        &params.pairing_code,
+                        &params.device_name,
+                        Some(on_progress),
+                        job.token(),
+                    ),
+                )
+                .await;
+                *HANDOFF_PROGRESS.lock().unwrap() = None;
+                sent
             })?;
 
-            let response = serde_json::json!({
-                "tasks": tasks,
-            });
-
-            Ok(success_response(response))
+            Ok(success_response(sent))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2481,28 +11850,73 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListDo
         .into_raw()
 }
 
-/// Pause a download
+/// Progress of the running handoff, sending or receiving
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "running": true, "progress": { "bytes_transferred": 1048576, "total_bytes": 72000000 } }
+/// }
+/// ```
+#[cfg(feature = "lan-handoff")]
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetHandoffProgress(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let response = catch_panic(move || {
+        let progress = *HANDOFF_PROGRESS.lock().unwrap();
+        success_response(serde_json::json!({
+            "running": progress.is_some(),
+            "progress": progress,
+        }))
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+// ============================================================================
+// JOBS
+// ============================================================================
+
+/// Cancel a running job started with a `job_id`
+///
+/// Library syncs, AAX decryption, stats exports, watch folder scans,
+/// integrity checks and offline preparation take an optional `job_id`; the
+/// call running the job then fails with a cancellation error (integrity
+/// checks and offline preparation report `cancelled: true`). Work
+/// already stored is kept. Downloads are cancelled with
+/// `nativeCancelDownload`.
 ///
 /// # Arguments (JSON string)
 /// ```json
+/// { "job_id": "sync-1" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
 /// {
-///   "db_path": "/data/data/.../libation.db",
-///   "task_id": "uuid-string"
+///   "success": true,
+///   "data": { "cancelled": true } // false if no such job is running
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativePauseDownload(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCancelJob(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeCancelJob", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            db_path: String,
-            task_id: String,
+            job_id: String,
         }
 
         match (move || -> crate::Result<String> {
@@ -2510,12 +11924,8 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativePauseD
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-                manager.pause_download(&params.task_id).await
-            })?;
-
-            Ok(success_response(serde_json::json!({"success": true})))
+            let cancelled = crate::cancel::cancel_job(&params.job_id);
+            Ok(success_response(serde_json::json!({ "cancelled": cancelled })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2527,28 +11937,75 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativePauseD
         .into_raw()
 }
 
-/// Resume a paused download
+/// List running jobs, oldest first
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {}
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "jobs": [{
+///       "job_id": "sync-1",
+///       "kind": "library_sync", // download, decryption, conversion, export, scan, integrity_check
+///       "started_at": "2025-01-01T03:00:00+00:00",
+///       "cancel_requested": false
+///     }]
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListJobs(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let response = catch_panic(move || {
+        success_response(serde_json::json!({ "jobs": crate::cancel::list_jobs() }))
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Set the directory jobs keep their temp files in
+///
+/// Pass the app's cache directory; each job gets a short-named
+/// subdirectory that is removed when the job ends, and directories left
+/// by a killed process are removed on the next start. The cover and
+/// sample caches are kept there too (see `nativeGetCacheUsage`). `null`
+/// goes back to the system temp directory.
 ///
 /// # Arguments (JSON string)
 /// ```json
+/// { "path": "/data/user/0/com.librisync/cache" } // or null
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
 /// {
-///   "db_path": "/data/data/.../libation.db",
-///   "task_id": "uuid-string"
+///   "success": true,
+///   "data": { "scratch_root": "/data/user/0/com.librisync/cache/lsj" }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeResumeDownload(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetTempRoot(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetTempRoot", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
-            db_path: String,
-            task_id: String,
+            path: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -2556,12 +12013,9 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeResume
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-                manager.resume_download(&params.task_id).await
-            })?;
-
-            Ok(success_response(serde_json::json!({"success": true})))
+            crate::file::temp::set_temp_root(params.path.filter(|p| !p.is_empty()).map(std::path::PathBuf::from));
+            let scratch_root = crate::file::temp::scratch_root();
+            Ok(success_response(serde_json::json!({ "scratch_root": scratch_root.to_string_lossy() })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2573,28 +12027,40 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeResume
         .into_raw()
 }
 
-/// Cancel a download
+/// Size of the cover, sample and temp caches against their quotas
 ///
 /// # Arguments (JSON string)
 /// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
 /// {
-///   "db_path": "/data/data/.../libation.db",
-///   "task_id": "uuid-string"
+///   "success": true,
+///   "data": {
+///     "caches": [
+///       { "kind": "covers", "path": ".../cache/librisync-covers", "bytes": 52428800, "files": 812, "max_bytes": 209715200 },
+///       { "kind": "samples", "path": ".../cache/librisync-samples", "bytes": 0, "files": 0, "max_bytes": 104857600 },
+///       { "kind": "temp", "path": ".../cache/lsj", "bytes": 1048576, "files": 2, "max_bytes": 2147483648 }
+///     ],
+///     "total_bytes": 53477376
+///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCancelDownload(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCacheUsage(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetCacheUsage", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            task_id: String,
         }
 
         match (move || -> crate::Result<String> {
@@ -2602,12 +12068,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCancel
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-                manager.cancel_download(&params.task_id).await
+            let usage = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::cache::get_cache_usage(db.pool()).await
             })?;
 
-            Ok(success_response(serde_json::json!({"success": true})))
+            Ok(success_response(usage))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2619,38 +12085,33 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeCancel
         .into_raw()
 }
 
-// ============================================================================
-// DOWNLOAD TASK STATUS UPDATE FUNCTIONS
-// ============================================================================
-
-/// Update download task status from Kotlin (for post-download conversion stages)
+/// Get the cache size limits
 ///
 /// # Arguments (JSON string)
 /// ```json
+/// { "db_path": "/data/data/.../libation.db" }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
 /// {
-///   "db_path": "/data/data/.../audible.db",
-///   "task_id": "uuid-string",
-///   "status": "decrypting",
-///   "error": null,
-///   "output_path": "content://..."
+///   "success": true,
+///   "data": { "covers_max_mb": 200, "samples_max_mb": 100, "temp_max_mb": 2048 }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUpdateDownloadTaskStatus(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetCacheQuota(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetCacheQuota", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            task_id: String,
-            status: String,
-            error: Option<String>,
-            output_path: Option<String>,
         }
 
         match (move || -> crate::Result<String> {
@@ -2658,21 +12119,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUpdate
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let status = crate::download::TaskStatus::from_str(&params.status)?;
-
-            RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-                manager
-                    .update_task_status_with_details(
-                        &params.task_id,
-                        status,
-                        params.error.as_deref(),
-                        params.output_path.as_deref(),
-                    )
-                    .await
+            let quota = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::cache::get_cache_quota(db.pool()).await
             })?;
 
-            Ok(success_response(serde_json::json!({"success": true})))
+            Ok(success_response(quota))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2684,34 +12136,40 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeUpdate
         .into_raw()
 }
 
-/// Store conversion keys and output directory for a download task
+/// Set the cache size limits and evict down to them
+///
+/// Least recently used covers and samples go first; temp directories of
+/// running jobs are kept. Each limit must be at least 1 MB.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../audible.db",
-///   "task_id": "uuid-string",
-///   "aaxc_key": "hex-key",
-///   "aaxc_iv": "hex-iv",
-///   "output_directory": "content://..."
+///   "db_path": "/data/data/.../libation.db",
+///   "quota": { "covers_max_mb": 50, "samples_max_mb": 20, "temp_max_mb": 1024 }
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "removed": 310, "freed_bytes": 31457280 }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeStoreConversionKeys(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetCacheQuota(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetCacheQuota", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            task_id: String,
-            aaxc_key: String,
-            aaxc_iv: String,
-            output_directory: String,
+            quota: crate::file::cache::CacheQuota,
         }
 
         match (move || -> crate::Result<String> {
@@ -2719,19 +12177,15 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeStoreC
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
-                let manager = get_or_create_manager(&params.db_path).await?;
-                manager
-                    .store_conversion_keys(
-                        &params.task_id,
-                        &params.aaxc_key,
-                        &params.aaxc_iv,
-                        &params.output_directory,
-                    )
-                    .await
+            let evictions = RUNTIME.block_on(async {
+                let db = crate::storage::Database::new(&params.db_path).await?;
+                crate::file::cache::set_cache_quota(db.pool(), &params.quota).await
             })?;
 
-            Ok(success_response(serde_json::json!({"success": true})))
+            Ok(success_response(serde_json::json!({
+                "removed": evictions.iter().map(|e| e.removed).sum::<usize>(),
+                "freed_bytes": evictions.iter().map(|e| e.freed_bytes).sum::<u64>(),
+            })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2743,17 +12197,17 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeStoreC
         .into_raw()
 }
 
-// ============================================================================
-// ACCOUNT FUNCTIONS
-// ============================================================================
-
-/// Save account to database
+/// Look up a job by id, running or finished
+///
+/// Jobs are stored when they start, so after the app process was killed
+/// the UI can find out how a job it started ended. Jobs the process was
+/// running when it died are reported as `interrupted`.
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../audible.db",
-///   "account_json": "{ ... }"  // Complete account JSON
+///   "db_path": "/data/data/.../libation.db",
+///   "job_id": "export-2"
 /// }
 /// ```
 ///
@@ -2761,22 +12215,36 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeStoreC
 /// ```json
 /// {
 ///   "success": true,
-///   "data": { "saved": true }
+///   "data": {
+///     "job": {                   // null if no such job was recorded
+///       "job_id": "export-2",
+///       "kind": "export",
+///       "status": "completed",   // running, failed, cancelled, interrupted
+///       "started_at": "2025-01-01T03:00:00.000Z",
+///       "updated_at": "2025-01-01T03:02:10.000Z",
+///       "finished_at": "2025-01-01T03:02:10.000Z",
+///       "error": null,
+///       "owner": "app",          // "cli" for jobs the CLI ran
+///       "artifacts": [{ "kind": "directory", "reference": "/storage/.../We Are Legion" }] // or file, book
+///     },
+///     "cancel_requested": false
+///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSaveAccount(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetJob(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetJob", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            account_json: String,
+            job_id: String,
         }
 
         match (move || -> crate::Result<String> {
@@ -2784,24 +12252,18 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSaveAc
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
+            let job = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-
-                // Extract account_id from JSON
-                let account: serde_json::Value = serde_json::from_str(&params.account_json)
-                    .map_err(|e| {
-                        crate::LibationError::InvalidInput(format!("Invalid account JSON: {}", e))
-                    })?;
-
-                let account_id = account["account_id"].as_str().ok_or_else(|| {
-                    crate::LibationError::InvalidInput("Missing account_id".to_string())
-                })?;
-
-                crate::storage::accounts::save_account(db.pool(), account_id, &params.account_json)
-                    .await?;
-
-                Ok(success_response(serde_json::json!({"saved": true})))
-            })
+                crate::storage::jobs::get_job(db.pool(), &params.job_id).await
+            })?;
+            let cancel_requested = crate::cancel::list_jobs()
+                .iter()
+                .any(|j| j.job_id == params.job_id && j.cancel_requested);
+
+            Ok(success_response(serde_json::json!({
+                "job": job,
+                "cancel_requested": cancel_requested,
+            })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2813,12 +12275,13 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSaveAc
         .into_raw()
 }
 
-/// Get primary account from database
+/// List recently started jobs, newest first
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../audible.db"
+///   "db_path": "/data/data/.../libation.db",
+///   "limit": 50 // optional, default 50
 /// }
 /// ```
 ///
@@ -2826,23 +12289,28 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSaveAc
 /// ```json
 /// {
 ///   "success": true,
-///   "data": {
-///     "account": "{ ... }"  // Complete account JSON or null if none
-///   }
+///   "data": { "jobs": [...] } // as `job` in nativeGetJob
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetPrimaryAccount(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeListRecentJobs(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeListRecentJobs", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
+            #[serde(default = "default_limit")]
+            limit: i64,
+        }
+
+        fn default_limit() -> i64 {
+            50
         }
 
         match (move || -> crate::Result<String> {
@@ -2850,16 +12318,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetPri
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            let account_json = RUNTIME.block_on(async {
+            let jobs = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                crate::storage::accounts::get_primary_account(db.pool()).await
+                crate::storage::jobs::list_recent_jobs(db.pool(), params.limit).await
             })?;
 
-            let response = serde_json::json!({
-                "account": account_json,
-            });
-
-            Ok(success_response(response))
+            Ok(success_response(serde_json::json!({ "jobs": jobs })))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -2871,107 +12335,293 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetPri
         .into_raw()
 }
 
-/// Delete account from database
+// ============================================================================
+// WORK ACTIVITY
+// ============================================================================
+
+/// Forwards work activity changes to a Kotlin listener object
+struct JniActivityListener {
+    vm: jni::JavaVM,
+    listener: jni::objects::GlobalRef,
+}
+
+impl crate::activity::WorkActivityListener for JniActivityListener {
+    fn on_activity_changed(&self, activity: &crate::activity::WorkActivity) {
+        let Ok(json) = serde_json::to_string(activity) else {
+            return;
+        };
+        let Ok(mut env) = self.vm.attach_current_thread() else {
+            eprintln!("⚠️  Failed to attach thread for work activity callback");
+            return;
+        };
+        let Ok(jjson) = env.new_string(json) else {
+            return;
+        };
+
+        let result = env.call_method(
+            self.listener.as_obj(),
+            "onWorkActivityChanged",
+            "(Ljava/lang/String;)V",
+            &[jni::objects::JValue::Object(&jjson)],
+        );
+        if result.is_err() {
+            // Never leave a pending exception on a Rust worker thread
+            let _ = env.exception_clear();
+        }
+    }
+}
+
+/// Register the host listener for long-running work
+///
+/// The listener must have a method `onWorkActivityChanged(String)`, called
+/// with the work activity JSON (see below) whenever work starts or stops, so
+/// the app can start a foreground service / hold a wake lock while work is
+/// active and release it when Rust reports idle. It is called right away
+/// with the current state and may be called from any thread.
+///
+/// # Arguments
+/// * `listener` - Listener object, or null to unregister
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": {
+///     "active": true,
+///     "work_types": ["download", "decryption"],  // also "conversion", "library_sync", "integrity_check"
+///     "job_count": 2,
+///     "expected_remaining_secs": 340,  // null if unknown
+///     "trace_ids": ["js-42"]  // trace ids the running work was started under
+///   }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetWorkActivityListener(
+    env: JNIEnv,
+    _class: JClass,
+    listener: jni::objects::JObject,
+) -> jstring {
+    let listener = if listener.is_null() {
+        Ok(None)
+    } else {
+        env.get_java_vm()
+            .and_then(|vm| Ok((vm, env.new_global_ref(&listener)?)))
+            .map(|(vm, listener)| Some(JniActivityListener { vm, listener }))
+            .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid listener: {}", e)))
+    };
+
+    let _call = crate::bridge_schema::enter_call("nativeSetWorkActivityListener");
+    let response = catch_panic(move || {
+        match listener {
+            Ok(listener) => {
+                crate::activity::set_work_activity_listener(
+                    listener.map(|l| std::sync::Arc::new(l) as std::sync::Arc<dyn crate::activity::WorkActivityListener>),
+                );
+                success_response(crate::activity::current_activity())
+            }
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the current long-running work state
+///
+/// For hosts that poll instead of registering a listener.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {}
+/// ```
+///
+/// # Returns (JSON)
+/// Same data as `nativeSetWorkActivityListener`.
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetWorkActivity(
+    env: JNIEnv,
+    _class: JClass,
+    _params_json: JString,
+) -> jstring {
+    let _call = crate::bridge_schema::enter_call("nativeGetWorkActivity");
+    let response = catch_panic(|| success_response(crate::activity::current_activity()));
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
+/// Get the latest progress of each running operation
+///
+/// For hosts that poll instead of handling `onProgressEvent`. An
+/// operation is listed from its first progress report until its work
+/// event (or 10 minutes without progress).
 ///
 /// # Arguments (JSON string)
 /// ```json
-/// {
-///   "db_path": "/data/data/.../audible.db",
-///   "account_id": "account-id"
-/// }
+/// {}
 /// ```
 ///
 /// # Returns (JSON)
 /// ```json
 /// {
 ///   "success": true,
-///   "data": { "deleted": true }
+///   "data": {
+///     "progress": [{ "category": "library_sync", "id": "job-1", "stage": "importing", ... }]
+///   }
 /// }
 /// ```
+/// Entries are progress events as described at `nativeSetWorkEventListener`.
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeDeleteAccount(
-    mut env: JNIEnv,
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetProgress(
+    env: JNIEnv,
     _class: JClass,
-    params_json: JString,
+    _params_json: JString,
 ) -> jstring {
-    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _call = crate::bridge_schema::enter_call("nativeGetProgress");
+    let response = catch_panic(|| {
+        success_response(serde_json::json!({ "progress": crate::events::current_progress() }))
+    });
 
-    let response = catch_panic(move || {
-        #[derive(Deserialize)]
-        struct Params {
-            db_path: String,
-            account_id: String,
-        }
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
 
-        match (move || -> crate::Result<String> {
-            let params_str = params_str_result?;
-            let params: Params = serde_json::from_str(&params_str)
-                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+// ============================================================================
+// WORK EVENTS
+// ============================================================================
 
-            RUNTIME.block_on(async {
-                let db = crate::storage::Database::new(&params.db_path).await?;
-                crate::storage::accounts::delete_account(db.pool(), &params.account_id).await?;
+/// Forwards work events to a Kotlin listener object
+struct JniWorkEventListener {
+    vm: jni::JavaVM,
+    listener: jni::objects::GlobalRef,
+}
 
-                Ok(success_response(serde_json::json!({"deleted": true})))
-            })
-        })() {
-            Ok(result) => result,
-            Err(e) => error_response(&e.to_string()),
+impl JniWorkEventListener {
+    fn call(&self, method: &str, json: String) {
+        let Ok(mut env) = self.vm.attach_current_thread() else {
+            eprintln!("⚠️  Failed to attach thread for work event callback");
+            return;
+        };
+        let Ok(jjson) = env.new_string(json) else {
+            return;
+        };
+
+        let result = env.call_method(
+            self.listener.as_obj(),
+            method,
+            "(Ljava/lang/String;)V",
+            &[jni::objects::JValue::Object(&jjson)],
+        );
+        if result.is_err() {
+            // Never leave a pending exception on a Rust worker thread
+            let _ = env.exception_clear();
         }
-    });
+    }
+}
 
-    env.new_string(response)
-        .expect("Failed to create Java string")
-        .into_raw()
+impl crate::events::WorkEventListener for JniWorkEventListener {
+    fn on_work_event(&self, event: &crate::events::WorkEvent) {
+        if let Ok(json) = serde_json::to_string(event) {
+            self.call("onWorkEvent", json);
+        }
+    }
+
+    fn on_progress_event(&self, event: &crate::events::ProgressEvent) {
+        if let Ok(json) = serde_json::to_string(event) {
+            self.call("onProgressEvent", json);
+        }
+    }
+
+    fn on_media_scan(&self, event: &crate::events::MediaScanEvent) {
+        if let Ok(json) = serde_json::to_string(event) {
+            self.call("onMediaScan", json);
+        }
+    }
 }
 
-/// Clear download state for all books
+/// Register the host listener for finished work
 ///
-/// Resets download status but keeps all book metadata.
+/// The listener must have a method `onWorkEvent(String)`, called with the
+/// event JSON (see below) when a library sync, decryption, export or other
+/// job ends, and when a download completes or fails. `suppressed` applies
+/// the quiet hours settings (`nativeSetQuietHours`): post no alerting
+/// notification for suppressed events.
 ///
-/// # Arguments (JSON string)
+/// An optional `onProgressEvent(String)` gets progress of running
+/// downloads, decryptions, library syncs, conversions and other jobs (see
+/// below). Events are delivered one at a time on a Rust thread; while the
+/// listener is busy, progress of the same job is coalesced to the latest
+/// update and old progress is dropped past 64 queued events. Work events
+/// are always delivered. `nativeGetProgress` returns the latest progress
+/// without a listener.
+///
+/// An optional `onMediaScan(String)` gets `{ "asin": "B07...", "paths": [...] }`
+/// when a liberation post-hook asks for liberated files to be media-scanned
+/// (`MediaScannerConnection.scanFile`). Always delivered.
+///
+/// # Arguments
+/// * `listener` - Listener object, or null to unregister
+///
+/// # Event (JSON)
 /// ```json
 /// {
-///   "db_path": "/data/data/.../audible.db"
+///   "category": "download",      // job kind: "library_sync", "decryption", "export", ...
+///   "id": "task-or-job-id",
+///   "asin": "B07...",            // null for jobs not about one title
+///   "status": "completed",       // or "failed", "cancelled"
+///   "severity": "success",       // "info" | "success" | "warning" | "error"
+///   "error": null,
+///   "suppressed": false,
+///   "trace_id": "js-42",         // null unless the work was started with one
+///   "finished_at": "2025-03-01T23:30:00Z"
 /// }
 /// ```
 ///
-/// # Returns (JSON)
+/// # Progress event (JSON)
 /// ```json
 /// {
-///   "success": true,
-///   "data": { "books_updated": 123 }
+///   "category": "download",      // job kind, as in work events
+///   "id": "task-or-job-id",
+///   "asin": "B07...",            // null for jobs not about one title
+///   "stage": "downloading",      // "fetching" | "importing" | "downloading" | "decrypting"
+///                                // | "converting" | "exporting" | "scanning" | "verifying"
+///                                // | "transferring"
+///   "fraction": 0.42,            // of the current stage
+///   "bytes_processed": 30240000, // 0 for stages that don't move bytes
+///   "total_bytes": 72000000,
+///   "items_processed": null,     // library items, while fetching or importing
+///   "total_items": null,
+///   "eta_secs": 95,              // null until some progress was made
+///   "coalesced": 3               // updates skipped while the listener was busy
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearDownloadState(
-    mut env: JNIEnv,
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetWorkEventListener(
+    env: JNIEnv,
     _class: JClass,
-    params_json: JString,
+    listener: jni::objects::JObject,
 ) -> jstring {
-    let params_str_result = jstring_to_string(&mut env, params_json);
+    let listener = if listener.is_null() {
+        Ok(None)
+    } else {
+        env.get_java_vm()
+            .and_then(|vm| Ok((vm, env.new_global_ref(&listener)?)))
+            .map(|(vm, listener)| Some(JniWorkEventListener { vm, listener }))
+            .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid listener: {}", e)))
+    };
 
     let response = catch_panic(move || {
-        #[derive(Deserialize)]
-        struct Params {
-            db_path: String,
-        }
-
-        match (move || -> crate::Result<String> {
-            let params_str = params_str_result?;
-            let params: Params = serde_json::from_str(&params_str)
-                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
-
-            RUNTIME.block_on(async {
-                let db = crate::storage::Database::new(&params.db_path).await?;
-                let books_updated =
-                    crate::storage::queries::clear_download_state(db.pool()).await?;
-                Ok(success_response(
-                    serde_json::json!({"books_updated": books_updated}),
-                ))
-            })
-        })() {
-            Ok(result) => result,
+        match listener {
+            Ok(listener) => {
+                crate::events::set_work_event_listener(
+                    listener.map(|l| std::sync::Arc::new(l) as std::sync::Arc<dyn crate::events::WorkEventListener>),
+                );
+                success_response(serde_json::json!({}))
+            }
             Err(e) => error_response(&e.to_string()),
         }
     });
@@ -2981,38 +12631,39 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearD
         .into_raw()
 }
 
-/// Get the downloaded file path for a book by ASIN
-///
-/// Returns the file path if a completed download exists.
+/// Get the notification quiet hours settings
 ///
 /// # Arguments (JSON string)
 /// ```json
-/// {
-///   "db_path": "/data/data/.../audible.db",
-///   "asin": "B07NP9L44Y"
-/// }
+/// { "db_path": "/data/data/.../audible.db" }
 /// ```
 ///
 /// # Returns (JSON)
 /// ```json
 /// {
 ///   "success": true,
-///   "data": { "file_path": "/storage/path/to/book.m4b" }
+///   "data": {
+///     "enabled": false,
+///     "start_hour": 22,
+///     "end_hour": 7,
+///     "breakthrough_severity": "error",  // notified even in quiet hours
+///     "muted_categories": []             // never notified, e.g. ["export"]
+///   }
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBookFilePath(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetQuietHours(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetQuietHours", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            asin: String,
         }
 
         match (move || -> crate::Result<String> {
@@ -3020,14 +12671,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
+            let quiet_hours = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let file_path =
-                    crate::storage::queries::get_book_file_path(db.pool(), &params.asin).await?;
-                Ok(success_response(
-                    serde_json::json!({"file_path": file_path}),
-                ))
-            })
+                crate::events::get_quiet_hours(db.pool()).await
+            })?;
+
+            Ok(success_response(quiet_hours))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -3039,44 +12688,29 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetBoo
         .into_raw()
 }
 
-/// Clear download state for a single book by ASIN
-///
-/// This resets the download status for a specific book, clearing book_status,
-/// last_downloaded, and related fields in UserDefinedItems table.
-/// Also deletes any download tasks for the book to reset to default state.
-/// Optionally deletes the downloaded file from disk.
+/// Set the notification quiet hours settings
 ///
 /// # Arguments (JSON string)
 /// ```json
 /// {
 ///   "db_path": "/data/data/.../audible.db",
-///   "asin": "B07NP9L44Y",
-///   "delete_file": false
-/// }
-/// ```
-///
-/// # Returns (JSON)
-/// ```json
-/// {
-///   "success": true,
-///   "data": { "cleared": true, "file_deleted": false, "deleted_path": null }
+///   "quiet_hours": { "enabled": true, "start_hour": 22, "end_hour": 7 }  // omitted fields take defaults
 /// }
 /// ```
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearBookDownloadState(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetQuietHours(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetQuietHours", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            asin: String,
-            #[serde(default)]
-            delete_file: bool,
+            quiet_hours: crate::events::QuietHours,
         }
 
         match (move || -> crate::Result<String> {
@@ -3086,19 +12720,10 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearB
 
             RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let deleted_path = crate::storage::queries::clear_book_download_state(
-                    db.pool(),
-                    &params.asin,
-                    params.delete_file,
-                )
-                .await?;
+                crate::events::set_quiet_hours(db.pool(), &params.quiet_hours).await
+            })?;
 
-                Ok(success_response(serde_json::json!({
-                    "cleared": true,
-                    "file_deleted": deleted_path.is_some(),
-                    "deleted_path": deleted_path
-                })))
-            })
+            Ok(success_response(params.quiet_hours))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -3110,43 +12735,45 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearB
         .into_raw()
 }
 
-/// Set the file path for a book manually
+// ============================================================================
+// TELEMETRY (telemetry feature)
+// ============================================================================
+
+/// Export the opt-in usage counters
 ///
-/// Allows users to mark a book as downloaded by associating it with an
-/// existing audio file on disk. Creates a download task with status "completed".
+/// Counts only: no titles, ASINs, accounts or paths. Shown to the user to
+/// copy into a bug report.
 ///
 /// # Arguments (JSON string)
 /// ```json
-/// {
-///   "db_path": "/data/data/.../audible.db",
-///   "asin": "B07NP9L44Y",
-///   "title": "Book Title",
-///   "file_path": "/storage/emulated/0/Download/book.m4b"
-/// }
+/// { "db_path": "/data/data/.../audible.db" }
 /// ```
 ///
 /// # Returns (JSON)
 /// ```json
 /// {
 ///   "success": true,
-///   "data": { "task_id": "uuid-string" }
+///   "data": {
+///     "enabled": true,
+///     "since": "2025-03-01T12:00:00Z",   // null while off
+///     "counters": { "library_sync.completed": 12, "download.failed": 1, "error.network": 1, "liberation": 9 }
+///   }
 /// }
 /// ```
+#[cfg(feature = "telemetry")]
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetBookFilePath(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeGetTelemetryCounters(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeGetTelemetryCounters", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
-            asin: String,
-            title: String,
-            file_path: String,
         }
 
         match (move || -> crate::Result<String> {
@@ -3154,18 +12781,12 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetBoo
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
+            let export = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                let task_id = crate::storage::queries::set_book_file_path(
-                    db.pool(),
-                    &params.asin,
-                    &params.title,
-                    &params.file_path,
-                )
-                .await?;
+                crate::telemetry::export_counters(db.pool()).await
+            })?;
 
-                Ok(success_response(serde_json::json!({"task_id": task_id})))
-            })
+            Ok(success_response(export))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -3177,34 +12798,32 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetBoo
         .into_raw()
 }
 
-/// Clear all library data (for testing)
+/// Opt in to or out of the usage counters
+///
+/// Off by default. Opting out deletes every counter.
 ///
 /// # Arguments (JSON string)
 /// ```json
-/// {
-///   "db_path": "/data/data/.../audible.db"
-/// }
+/// { "db_path": "/data/data/.../audible.db", "enabled": true }
 /// ```
 ///
 /// # Returns (JSON)
-/// ```json
-/// {
-///   "success": true,
-///   "data": { "deleted": true }
-/// }
-/// ```
+/// Same data as `nativeGetTelemetryCounters`.
+#[cfg(feature = "telemetry")]
 #[no_mangle]
-pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearLibrary(
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetTelemetryEnabled(
     mut env: JNIEnv,
     _class: JClass,
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetTelemetryEnabled", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
         struct Params {
             db_path: String,
+            enabled: bool,
         }
 
         match (move || -> crate::Result<String> {
@@ -3212,11 +12831,13 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeClearL
             let params: Params = serde_json::from_str(&params_str)
                 .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
 
-            RUNTIME.block_on(async {
+            let export = RUNTIME.block_on(async {
                 let db = crate::storage::Database::new(&params.db_path).await?;
-                crate::storage::queries::clear_library(db.pool()).await?;
-                Ok(success_response(serde_json::json!({"deleted": true})))
-            })
+                crate::telemetry::set_enabled(db.pool(), params.enabled).await?;
+                crate::telemetry::export_counters(db.pool()).await
+            })?;
+
+            Ok(success_response(export))
         })() {
             Ok(result) => result,
             Err(e) => error_response(&e.to_string()),
@@ -3239,6 +12860,7 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeInsert
     params_json: JString,
 ) -> jstring {
     let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeInsertLibrivoxBook", &params_str_result);
 
     let response = catch_panic(move || {
         #[derive(Deserialize)]
@@ -3289,6 +12911,96 @@ pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeInsert
         .into_raw()
 }
 
+// ============================================================================
+// TEST CLOCK
+// ============================================================================
+
+/// Control the clock used for token, download URL and schedule expiry
+///
+/// For the app's test mode: freeze time, jump to a point in time or move it
+/// forward to exercise expiry paths. The first call freezes the clock at
+/// `now` (or the current time); `reset` returns to real time.
+///
+/// # Arguments (JSON string)
+/// ```json
+/// {
+///   "now": "2025-03-01T12:00:00Z",  // optional
+///   "advance_secs": 3600,           // optional
+///   "reset": false                  // optional
+/// }
+/// ```
+///
+/// # Returns (JSON)
+/// ```json
+/// {
+///   "success": true,
+///   "data": { "test_mode": true, "now": "2025-03-01T13:00:00+00:00" }
+/// }
+/// ```
+#[no_mangle]
+pub extern "C" fn Java_expo_modules_rustbridge_ExpoRustBridgeModule_nativeSetTestClock(
+    mut env: JNIEnv,
+    _class: JClass,
+    params_json: JString,
+) -> jstring {
+    let params_str_result = jstring_to_string(&mut env, params_json);
+    let _trace = enter_trace("nativeSetTestClock", &params_str_result);
+
+    let response = catch_panic(move || {
+        #[derive(Deserialize)]
+        struct Params {
+            now: Option<chrono::DateTime<chrono::Utc>>,
+            advance_secs: Option<i64>,
+            #[serde(default)]
+            reset: bool,
+        }
+
+        match (move || -> crate::Result<String> {
+            use crate::clock::Clock;
+
+            let params_str = params_str_result?;
+            let params: Params = serde_json::from_str(&params_str)
+                .map_err(|e| crate::LibationError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+
+            let mut installed = TEST_CLOCK.lock().unwrap();
+            if params.reset {
+                *installed = None;
+                crate::clock::set_clock(None);
+                return Ok(success_response(serde_json::json!({
+                    "test_mode": false,
+                    "now": crate::clock::now().to_rfc3339(),
+                })));
+            }
+
+            let clock = installed
+                .get_or_insert_with(|| {
+                    let clock = std::sync::Arc::new(crate::clock::TestClock::new(chrono::Utc::now()));
+                    crate::clock::set_clock(Some(clock.clone()));
+                    clock
+                })
+                .clone();
+            if let Some(now) = params.now {
+                clock.set(now);
+            }
+            if let Some(secs) = params.advance_secs {
+                clock.advance(chrono::Duration::seconds(secs));
+            }
+
+            Ok(success_response(serde_json::json!({
+                "test_mode": true,
+                "now": clock.now().to_rfc3339(),
+            })))
+        })() {
+            Ok(result) => result,
+            Err(e) => error_response(&e.to_string()),
+        }
+    });
+
+    env.new_string(response)
+        .expect("Failed to create Java string")
+        .into_raw()
+}
+
 // ============================================================================
 // TESTS
 // ============================================================================
@@ -3328,6 +13040,51 @@ mod tests {
         assert!(json.contains("test error"));
     }
 
+    #[test]
+    fn test_download_task_response_has_no_key_material() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("tasks.db").to_string_lossy().to_string();
+        let key = "0123456789abcdef0123456789abcdef";
+        let iv = "fedcba9876543210fedcba9876543210";
+
+        // Same calls as nativeGetDownloadTask / nativeListDownloadTasks
+        let (task, tasks) = RUNTIME.block_on(async {
+            let manager = get_or_create_manager(&db_path).await.unwrap();
+            let headers = HashMap::from([("Authorization".to_string(), "Bearer secret-token".to_string())]);
+            let task_id = manager
+                .enqueue_download(
+                    "B001".to_string(), "Book".to_string(), "https://example.com/B001.aax".to_string(),
+                    1000, "/tmp/B001.aax".to_string(), "/tmp/B001.m4b".to_string(), headers,
+                )
+                .await
+                .unwrap();
+            manager.store_conversion_keys(&task_id, key, iv, "/out").await.unwrap();
+            (manager.get_task(&task_id).await.unwrap(), manager.list_tasks(None).await.unwrap())
+        });
+        assert!(task.aaxc_key.is_some());
+
+        for response in [success_response(task), success_response(serde_json::json!({ "tasks": tasks }))] {
+            assert!(response.contains("\"success\":true"));
+            for secret in [key, iv, "secret-token", "request_headers"] {
+                assert!(!response.contains(secret), "{} leaked: {}", secret, response);
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_progress_is_per_job() {
+        for (job_id, rows_written) in [("export-a", 10), ("export-b", 30)] {
+            let on_progress = export_progress_callback(job_id);
+            on_progress(crate::storage::export::ExportProgress { rows_written, total_rows: 40, bytes_written: 512 });
+        }
+
+        let progress = crate::events::current_progress();
+        let find = |id: &str| progress.iter().find(|p| p.id == id).cloned().unwrap();
+        let a = find("export-a");
+        assert_eq!((a.category, a.items_processed, a.total_items, a.bytes_processed), (crate::cancel::JobKind::Export, Some(10), Some(40), 512));
+        assert_eq!(find("export-b").items_processed, Some(30));
+    }
+
     #[test]
     fn test_catch_panic_normal() {
         let result = catch_panic(|| "normal result".to_string());
@@ -17,18 +17,49 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+//! LibriSync core library
+//!
+//! Audible authentication, library sync, downloads, DRM removal, audio
+//! processing and storage, ported from Libation. The modules below are the
+//! library API and don't depend on any FFI layer, so other Rust programs
+//! (such as a desktop CLI) can reuse the same sync and liberation code with
+//! `default-features = false`.
+//!
+//! # Features
+//! - `android-bridge` (default) - JNI bridge for the Expo module, built for
+//!   Android targets
+//! - `ios-bridge` (default) - C FFI bridge, built for iOS targets, and the
+//!   UniFFI scaffolding
+//! - `cli` - the `librisync-cli` desktop binary
+//! - `lan-handoff` - sending liberated books to another device on the local
+//!   network (`file::handoff`)
+//! - `telemetry` - opt-in local usage counters the user can export for bug
+//!   reports (`telemetry`)
+
+#[cfg(feature = "ios-bridge")]
 uniffi::setup_scaffolding!();
 
 // JNI bridge for Android (DO NOT MODIFY - existing bridge)
-#[cfg(target_os = "android")]
+#[cfg(feature = "android-bridge")]
 mod jni_bridge;
 
 // C FFI bridge for iOS
-#[cfg(target_os = "ios")]
+#[cfg(all(feature = "ios-bridge", target_os = "ios"))]
 pub mod ios_bridge;
 
 // Core modules
 pub mod error;
+pub mod activity;
+pub mod bridge_protocol;
+pub mod bridge_schema;
+pub mod cancel;
+pub mod clock;
+pub mod events;
+pub mod permissions;
+pub mod secret;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod trace;
 pub mod api;
 pub mod crypto;
 pub mod download;
@@ -40,7 +71,7 @@ pub mod file;
 pub use error::{LibationError, Result};
 
 // Existing log_from_rust function (DO NOT MODIFY - used by existing bridge)
-#[uniffi::export]
+#[cfg_attr(feature = "ios-bridge", uniffi::export)]
 pub fn log_from_rust(message: String) -> String {
     let log_message = format!("Rust native module says: {message}");
     println!("{log_message}");
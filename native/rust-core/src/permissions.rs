@@ -0,0 +1,150 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Runtime permissions for destructive operations
+//!
+//! The host app can turn off operations that lose data or access (for
+//! example in a kid-mode build). The check is made by the core functions
+//! themselves, so a disabled operation fails with
+//! `LibationError::PermissionDenied` whichever bridge or UI path reaches
+//! it:
+//!
+//! | Permission          | Checked by |
+//! |---------------------|------------|
+//! | `clear_library`     | `queries::clear_library` |
+//! | `delete_files`      | `queries::clear_book_download_state` with `delete_file`, `integrity::reliberate_corrupted` |
+//! | `remove_accounts`   | `accounts::delete_account`, `profiles::delete_profile` |
+//! | `deregister_device` | `auth::deregister_device` |
+//!
+//! Everything is allowed by default. The policy is per process: the host
+//! sets it at startup (`set_policy`), and may lock it so nothing can turn
+//! the operations back on until the app restarts.
+
+use crate::error::{LibationError, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+
+/// An operation the host can disable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    ClearLibrary,
+    DeleteFiles,
+    RemoveAccounts,
+    DeregisterDevice,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 4] = [
+        Permission::ClearLibrary,
+        Permission::DeleteFiles,
+        Permission::RemoveAccounts,
+        Permission::DeregisterDevice,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ClearLibrary => "clear_library",
+            Permission::DeleteFiles => "delete_files",
+            Permission::RemoveAccounts => "remove_accounts",
+            Permission::DeregisterDevice => "deregister_device",
+        }
+    }
+}
+
+/// Disabled operations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PermissionPolicy {
+    pub denied: BTreeSet<Permission>,
+    /// No further changes until restart
+    pub locked: bool,
+}
+
+impl PermissionPolicy {
+    /// # Errors
+    /// PermissionDenied if `permission` is disabled
+    pub fn check(&self, permission: Permission) -> Result<()> {
+        if self.denied.contains(&permission) {
+            return Err(LibationError::PermissionDenied(format!(
+                "{} is disabled on this device",
+                permission.as_str()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Replace the policy with `new`
+    ///
+    /// # Errors
+    /// PermissionDenied if this policy is locked
+    pub fn replace(&mut self, new: PermissionPolicy) -> Result<()> {
+        if self.locked {
+            return Err(LibationError::PermissionDenied(
+                "Permissions are locked until the app restarts".to_string(),
+            ));
+        }
+        *self = new;
+        Ok(())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref POLICY: RwLock<PermissionPolicy> = RwLock::new(PermissionPolicy::default());
+}
+
+/// Fail unless `permission` is allowed
+pub fn require(permission: Permission) -> Result<()> {
+    POLICY.read().unwrap().check(permission)
+}
+
+/// Current policy
+pub fn policy() -> PermissionPolicy {
+    POLICY.read().unwrap().clone()
+}
+
+/// Set the process-wide policy
+///
+/// # Errors
+/// PermissionDenied if the policy was locked
+pub fn set_policy(policy: PermissionPolicy) -> Result<()> {
+    POLICY.write().unwrap().replace(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locked_policy_denies_and_stays() {
+        let mut policy = PermissionPolicy::default();
+        assert!(Permission::ALL.iter().all(|p| policy.check(*p).is_ok()));
+
+        let kid_mode: PermissionPolicy =
+            serde_json::from_str(r#"{ "denied": ["clear_library", "delete_files"], "locked": true }"#).unwrap();
+        policy.replace(kid_mode).unwrap();
+        assert!(matches!(
+            policy.check(Permission::ClearLibrary),
+            Err(LibationError::PermissionDenied(_))
+        ));
+        assert!(policy.check(Permission::DeregisterDevice).is_ok());
+
+        assert!(policy.replace(PermissionPolicy::default()).is_err());
+        assert!(policy.check(Permission::DeleteFiles).is_err());
+    }
+}
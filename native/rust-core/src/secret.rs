@@ -0,0 +1,202 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Secrets in memory
+//!
+//! Access and refresh tokens, device private keys, ADP tokens, store
+//! cookies and decryption keys are held in `SecretString` / `SecretBytes`
+//! instead of plain `String` / `Vec<u8>`:
+//!
+//! - The buffer is zeroed when the value is dropped, so copies don't linger
+//!   in freed memory
+//! - `Debug` prints `<redacted>`, so structs holding secrets can be logged
+//! - The value is read with `expose_secret`, which keeps every use easy to
+//!   find
+//!
+//! Serializing writes the value, for the database and the keychain (the
+//! account JSON), which have to round-trip. Types handed to the app mark
+//! their secret fields `#[serde(skip_serializing)]`, as `DownloadTask`
+//! does for its conversion keys and request headers.
+
+use crate::api::debug_capture::REDACTED;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::sqlite::{Sqlite, SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef};
+use std::fmt;
+use zeroize::Zeroize;
+
+/// A string that is zeroed on drop and redacted in `Debug`
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret itself
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretString({})", REDACTED)
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+impl sqlx::Type<Sqlite> for SecretString {
+    fn type_info() -> SqliteTypeInfo {
+        <String as sqlx::Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <String as sqlx::Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> sqlx::Encode<'q, Sqlite> for SecretString {
+    fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'q>>) -> IsNull {
+        <String as sqlx::Encode<'q, Sqlite>>::encode(self.0.clone(), args)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, Sqlite> for SecretString {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        <String as sqlx::Decode<'r, Sqlite>>::decode(value).map(Self)
+    }
+}
+
+/// Bytes that are zeroed on drop and redacted in `Debug`
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(value: impl Into<Vec<u8>>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret itself
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes({})", REDACTED)
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
+impl Serialize for SecretBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<u8>::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let token = SecretString::new("Atna|secret-token");
+        assert_eq!(format!("{:?}", token), "SecretString(<redacted>)");
+        assert!(!format!("{:?}", Some(token.clone())).contains("secret-token"));
+        assert_eq!(token.expose_secret(), "Atna|secret-token");
+
+        // Serialization keeps the value for persisted JSON
+        let json = serde_json::to_string(&token).unwrap();
+        assert_eq!(json, "\"Atna|secret-token\"");
+        assert_eq!(serde_json::from_str::<SecretString>(&json).unwrap(), token);
+
+        let key = SecretBytes::new(vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(format!("{:?}", key), "SecretBytes(<redacted>)");
+        assert_eq!(serde_json::to_string(&key).unwrap(), "[222,173,190,239]");
+    }
+
+    #[tokio::test]
+    async fn test_secret_string_in_sqlite() {
+        let db = crate::storage::Database::new_in_memory().await.unwrap();
+        let secret: SecretString = sqlx::query_scalar("SELECT ?")
+            .bind(SecretString::new("refresh"))
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(secret.expose_secret(), "refresh");
+    }
+}
@@ -10,9 +10,25 @@
 //!
 //! Functions for saving and retrieving account data from SQLite.
 //! Accounts are stored as JSON in the database for flexibility.
+//!
+//! A token refresh can invalidate the old refresh token, so refreshed tokens
+//! must never be lost between the network call and the account write. They
+//! are first recorded in `PendingTokenRefreshes`, then merged into the
+//! account by `save_refreshed_account`, which clears the record in the same
+//! transaction. `recover_pending_token_refreshes` finishes any merge that an
+//! app kill interrupted.
+//!
+//! Several accounts (e.g. US and UK) can be signed in at once. Each syncs
+//! its own library: LibraryBookAccounts records every account that owns a
+//! book, and LibraryBooks keeps one owner per book, which only changes when
+//! that owner no longer has it.
 
 use crate::error::{LibationError, Result};
-use sqlx::SqlitePool;
+use crate::secret::SecretString;
+use crate::storage::dates::{self, normalize_timestamp, parse_timestamp};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqliteConnection, SqlitePool};
 
 /// Save or update account in database
 ///
@@ -24,23 +40,33 @@ use sqlx::SqlitePool;
 /// # Returns
 /// Success status
 pub async fn save_account(pool: &SqlitePool, account_id: &str, account_json: &str) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    write_account(&mut conn, account_id, account_json).await
+}
+
+async fn write_account(conn: &mut SqliteConnection, account_id: &str, account_json: &str) -> Result<()> {
     // Parse JSON to extract key fields
     let account: serde_json::Value = serde_json::from_str(account_json)
         .map_err(|e| LibationError::InvalidInput(format!("Invalid account JSON: {}", e)))?;
 
     let account_name = account["account_name"].as_str().unwrap_or(account_id);
 
+    // Serialized `Account`s only carry the locale inside the identity
     let locale_code = account["locale"]["country_code"]
         .as_str()
+        .or_else(|| account["identity"]["locale"]["country_code"].as_str())
         .ok_or_else(|| LibationError::InvalidInput("Missing locale country_code".to_string()))?;
 
     // Extract identity JSON
     let identity_json = account["identity"].to_string();
 
     // Extract token expiry if available
-    let token_expires_at = account["identity"]["access_token"]["expires_at"].as_str();
+    let token_expires_at = account["identity"]["access_token"]["expires_at"]
+        .as_str()
+        .map(|at| normalize_timestamp(at).unwrap_or_else(|| at.to_string()));
 
     let decrypt_key = account["decrypt_key"].as_str();
+    let now = dates::now();
 
     // Insert or replace account
     sqlx::query(
@@ -51,15 +77,30 @@ pub async fn save_account(pool: &SqlitePool, account_id: &str, account_json: &st
             locale_code,
             identity_json,
             token_expires_at,
-            decrypt_key
-        ) VALUES (?, ?, ?, ?, ?, ?)
+            decrypt_key,
+            registered_at,
+            refresh_token_issued_at,
+            updated_at,
+            profile_id
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?,
+            -- New accounts join the active profile
+            COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default'))
         ON CONFLICT(account_id) DO UPDATE SET
             account_name = excluded.account_name,
             locale_code = excluded.locale_code,
             identity_json = excluded.identity_json,
             token_expires_at = excluded.token_expires_at,
             decrypt_key = excluded.decrypt_key,
-            updated_at = CURRENT_TIMESTAMP
+            -- A new device serial means the device was registered again
+            registered_at = CASE
+                WHEN json_extract(Accounts.identity_json, '$.device_serial_number')
+                    IS json_extract(excluded.identity_json, '$.device_serial_number')
+                THEN Accounts.registered_at ELSE excluded.registered_at END,
+            refresh_token_issued_at = CASE
+                WHEN json_extract(Accounts.identity_json, '$.refresh_token')
+                    IS json_extract(excluded.identity_json, '$.refresh_token')
+                THEN Accounts.refresh_token_issued_at ELSE excluded.refresh_token_issued_at END,
+            updated_at = excluded.updated_at
         "#,
     )
     .bind(account_id)
@@ -68,12 +109,152 @@ pub async fn save_account(pool: &SqlitePool, account_id: &str, account_json: &st
     .bind(&identity_json)
     .bind(token_expires_at)
     .bind(decrypt_key)
+    .bind(&now)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Tokens from a refresh that may not have reached the account yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PendingTokenRefresh {
+    pub account_id: String,
+    pub access_token: SecretString,
+    /// RFC 3339
+    pub expires_at: String,
+    /// New refresh token, when Amazon rotated it
+    pub refresh_token: Option<SecretString>,
+}
+
+impl PendingTokenRefresh {
+    /// Write the tokens into an account JSON value
+    ///
+    /// Skipped (returning false) unless they expire later than the account's
+    /// current access token, so a login made after the interrupted refresh
+    /// isn't overwritten with older tokens.
+    pub fn apply_to(&self, account: &mut serde_json::Value) -> bool {
+        let current_expiry = account
+            .pointer("/identity/access_token/expires_at")
+            .and_then(|v| v.as_str())
+            .and_then(parse_timestamp);
+        let is_newer = match (parse_timestamp(&self.expires_at), current_expiry) {
+            (Some(pending), Some(current)) => pending > current,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if !is_newer {
+            return false;
+        }
+
+        let identity = &mut account["identity"];
+        identity["access_token"]["token"] = self.access_token.expose_secret().into();
+        identity["access_token"]["expires_at"] = self.expires_at.clone().into();
+        if let Some(ref refresh_token) = self.refresh_token {
+            identity["refresh_token"] = refresh_token.expose_secret().into();
+        }
+        true
+    }
+}
+
+/// Record refreshed tokens before they are merged into the account
+///
+/// Call this as soon as the refresh response arrives. A newer record for
+/// the same account replaces an older one.
+pub async fn record_pending_token_refresh(pool: &SqlitePool, pending: &PendingTokenRefresh) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO PendingTokenRefreshes (account_id, access_token, expires_at, refresh_token)
+        VALUES (?, ?, ?, ?)
+        "#,
+    )
+    .bind(&pending.account_id)
+    .bind(&pending.access_token)
+    .bind(&pending.expires_at)
+    .bind(&pending.refresh_token)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
+/// Refreshed tokens recorded for an account and not yet saved
+pub async fn get_pending_token_refresh(pool: &SqlitePool, account_id: &str) -> Result<Option<PendingTokenRefresh>> {
+    let pending = sqlx::query_as::<_, PendingTokenRefresh>(
+        "SELECT account_id, access_token, expires_at, refresh_token FROM PendingTokenRefreshes WHERE account_id = ?",
+    )
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(pending)
+}
+
+/// Drop the pending refresh record of an account
+pub async fn clear_pending_token_refresh(pool: &SqlitePool, account_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM PendingTokenRefreshes WHERE account_id = ?")
+        .bind(account_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Save an account carrying refreshed tokens and clear its pending record
+///
+/// Both happen in one transaction, so the tokens are always either in the
+/// account or still in the pending record.
+pub async fn save_refreshed_account(pool: &SqlitePool, account_id: &str, account_json: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    write_account(&mut tx, account_id, account_json).await?;
+    sqlx::query("UPDATE Accounts SET last_token_refresh = ? WHERE account_id = ?")
+        .bind(dates::now())
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM PendingTokenRefreshes WHERE account_id = ?")
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Merge refreshed tokens left behind by an interrupted refresh
+///
+/// Run at startup. Records for accounts that no longer exist, or that are
+/// older than the account's tokens, are dropped.
+///
+/// # Returns
+/// IDs of the accounts whose tokens were recovered
+pub async fn recover_pending_token_refreshes(pool: &SqlitePool) -> Result<Vec<String>> {
+    let pending = sqlx::query_as::<_, PendingTokenRefresh>(
+        "SELECT account_id, access_token, expires_at, refresh_token FROM PendingTokenRefreshes",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut recovered = Vec::new();
+    for refresh in pending {
+        let mut account: serde_json::Value = match get_account(pool, &refresh.account_id).await? {
+            Some(account_json) => serde_json::from_str(&account_json)?,
+            None => serde_json::Value::Null,
+        };
+
+        if account.is_object() && refresh.apply_to(&mut account) {
+            save_refreshed_account(pool, &refresh.account_id, &account.to_string()).await?;
+            recovered.push(refresh.account_id);
+        } else {
+            clear_pending_token_refresh(pool, &refresh.account_id).await?;
+        }
+    }
+
+    Ok(recovered)
+}
+
 /// Get account from database by account_id
 ///
 /// # Arguments
@@ -144,7 +325,8 @@ pub async fn get_primary_account(pool: &SqlitePool) -> Result<Option<String>> {
         r#"
         SELECT account_id
         FROM Accounts
-        ORDER BY created_at ASC
+        ORDER BY profile_id = COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default') DESC,
+            created_at ASC
         LIMIT 1
         "#,
     )
@@ -158,6 +340,40 @@ pub async fn get_primary_account(pool: &SqlitePool) -> Result<Option<String>> {
     }
 }
 
+/// An account as listed for account management
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AccountSummary {
+    pub account_id: String,
+    pub account_name: String,
+    pub locale_code: String,
+    pub profile_id: String,
+    pub last_library_sync: Option<String>,
+    /// Books in the account's library
+    pub book_count: i64,
+}
+
+/// List all accounts, oldest first
+pub async fn list_accounts(pool: &SqlitePool) -> Result<Vec<AccountSummary>> {
+    let accounts = sqlx::query_as::<_, AccountSummary>(
+        r#"
+        SELECT
+            a.account_id,
+            a.account_name,
+            a.locale_code,
+            a.profile_id,
+            a.last_library_sync,
+            (SELECT COUNT(*) FROM LibraryBookAccounts lba
+             WHERE lba.account = a.account_id AND lba.is_deleted = 0) AS book_count
+        FROM Accounts a
+        ORDER BY a.created_at ASC, a.account_id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(accounts)
+}
+
 /// Update token expiry timestamp
 ///
 /// # Arguments
@@ -173,11 +389,12 @@ pub async fn update_token_expiry(
         r#"
         UPDATE Accounts
         SET token_expires_at = ?,
-            last_token_refresh = CURRENT_TIMESTAMP
+            last_token_refresh = ?
         WHERE account_id = ?
         "#,
     )
-    .bind(expires_at)
+    .bind(normalize_timestamp(expires_at).unwrap_or_else(|| expires_at.to_string()))
+    .bind(dates::now())
     .bind(account_id)
     .execute(pool)
     .await?;
@@ -194,10 +411,30 @@ pub async fn update_last_sync(pool: &SqlitePool, account_id: &str) -> Result<()>
     sqlx::query(
         r#"
         UPDATE Accounts
-        SET last_library_sync = CURRENT_TIMESTAMP
+        SET last_library_sync = ?
         WHERE account_id = ?
         "#,
     )
+    .bind(dates::now())
+    .bind(account_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a successful Audible API call for an account
+///
+/// Older timestamps than the stored one are ignored.
+pub async fn record_api_success(pool: &SqlitePool, account_id: &str, at: DateTime<Utc>) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE Accounts
+        SET last_api_success_at = ?1
+        WHERE account_id = ?2 AND (last_api_success_at IS NULL OR last_api_success_at < ?1)
+        "#,
+    )
+    .bind(dates::format_timestamp(at))
     .bind(account_id)
     .execute(pool)
     .await?;
@@ -205,12 +442,106 @@ pub async fn update_last_sync(pool: &SqlitePool, account_id: &str) -> Result<()>
     Ok(())
 }
 
+/// Non-secret token and device details of an account, for debugging auth
+///
+/// Never contains tokens, keys, cookies or activation bytes themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountTokenInfo {
+    pub account_id: String,
+    pub account_name: String,
+    pub locale_code: String,
+
+    /// Access token expiry (RFC 3339)
+    pub access_token_expires_at: Option<String>,
+    /// Seconds until the access token expires (negative once expired)
+    pub access_token_expires_in_secs: Option<i64>,
+    pub access_token_expired: bool,
+    /// Last access token refresh
+    pub last_token_refresh: Option<String>,
+
+    pub has_refresh_token: bool,
+    /// When the current refresh token was first stored
+    pub refresh_token_issued_at: Option<String>,
+    pub refresh_token_age_days: Option<i64>,
+
+    /// When this device was registered with Audible
+    pub device_registered_at: Option<String>,
+    pub device_name: Option<String>,
+    pub device_serial_number: Option<String>,
+    pub device_type: Option<String>,
+
+    pub has_activation_bytes: bool,
+    pub last_api_success_at: Option<String>,
+    pub last_library_sync: Option<String>,
+}
+
+/// Token and device details of an account (see `AccountTokenInfo`)
+///
+/// # Errors
+/// RecordNotFound if the account doesn't exist
+pub async fn get_account_token_info(pool: &SqlitePool, account_id: &str) -> Result<AccountTokenInfo> {
+    let row = sqlx::query("SELECT * FROM Accounts WHERE account_id = ?")
+        .bind(account_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Account not found: {}", account_id)))?;
+
+    let identity_json: String = row.try_get("identity_json")?;
+    let identity: serde_json::Value = serde_json::from_str(&identity_json).map_err(|e| {
+        LibationError::InvalidState(format!("Corrupt identity JSON in database: {}", e))
+    })?;
+    let text = |pointer: &str| {
+        identity
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+
+    let now = Utc::now();
+    let access_token_expires_at = text("/access_token/expires_at").or(row.try_get("token_expires_at")?);
+    let expires_in = access_token_expires_at
+        .as_deref()
+        .and_then(parse_timestamp)
+        .map(|expires| (expires - now).num_seconds());
+    let refresh_token_issued_at: Option<String> = row.try_get("refresh_token_issued_at")?;
+    let decrypt_key: Option<String> = row.try_get("decrypt_key")?;
+
+    Ok(AccountTokenInfo {
+        account_id: row.try_get("account_id")?,
+        account_name: row.try_get("account_name")?,
+        locale_code: row.try_get("locale_code")?,
+        access_token_expired: expires_in.is_none_or(|secs| secs <= 0),
+        access_token_expires_in_secs: expires_in,
+        access_token_expires_at,
+        last_token_refresh: row.try_get("last_token_refresh")?,
+        has_refresh_token: text("/refresh_token").is_some(),
+        refresh_token_age_days: refresh_token_issued_at
+            .as_deref()
+            .and_then(parse_timestamp)
+            .map(|issued| (now - issued).num_days()),
+        refresh_token_issued_at,
+        device_registered_at: row.try_get("registered_at")?,
+        device_name: text("/device_name"),
+        device_serial_number: text("/device_serial_number"),
+        device_type: text("/device_type"),
+        has_activation_bytes: decrypt_key.is_some_and(|key| !key.is_empty()),
+        last_api_success_at: row.try_get("last_api_success_at")?,
+        last_library_sync: row.try_get("last_library_sync")?,
+    })
+}
+
 /// Delete account from database
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
 /// * `account_id` - Account identifier
+///
+/// # Errors
+/// PermissionDenied if `remove_accounts` is disabled (see `permissions`)
 pub async fn delete_account(pool: &SqlitePool, account_id: &str) -> Result<()> {
+    crate::permissions::require(crate::permissions::Permission::RemoveAccounts)?;
+
     sqlx::query("DELETE FROM Accounts WHERE account_id = ?")
         .bind(account_id)
         .execute(pool)
@@ -219,6 +550,75 @@ pub async fn delete_account(pool: &SqlitePool, account_id: &str) -> Result<()> {
     Ok(())
 }
 
+/// Remove an account with its library
+///
+/// Books only this account owns are deleted with their positions, tags and
+/// chapters; books another account owns as well pass to that account.
+/// Liberated files and download history are kept. Use `delete_account` to
+/// only forget the account's sign-in.
+///
+/// # Errors
+/// - RecordNotFound if the account doesn't exist
+/// - PermissionDenied if `remove_accounts` is disabled (see `permissions`)
+pub async fn remove_account(pool: &SqlitePool, account_id: &str) -> Result<()> {
+    crate::permissions::require(crate::permissions::Permission::RemoveAccounts)?;
+
+    let mut tx = pool.begin().await?;
+    let exists: Option<String> = sqlx::query_scalar("SELECT account_id FROM Accounts WHERE account_id = ?")
+        .bind(account_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if exists.is_none() {
+        return Err(LibationError::not_found(format!("Account not found: {}", account_id)));
+    }
+
+    sqlx::query(
+        r#"
+        DELETE FROM Books WHERE book_id IN (
+            SELECT lba.book_id FROM LibraryBookAccounts lba
+            WHERE lba.account = ?1 AND NOT EXISTS (
+                SELECT 1 FROM LibraryBookAccounts other
+                WHERE other.book_id = lba.book_id AND other.account != ?1
+            )
+        )
+        "#,
+    )
+    .bind(account_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM LibraryBookAccounts WHERE account = ?")
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Shared books go to the other owner that still has them, if any
+    sqlx::query(
+        r#"
+        UPDATE LibraryBooks SET account = (
+            SELECT lba.account FROM LibraryBookAccounts lba
+            WHERE lba.book_id = LibraryBooks.book_id
+            ORDER BY lba.is_deleted, lba.absent_from_last_scan, lba.date_added
+            LIMIT 1
+        )
+        WHERE account = ? AND EXISTS (SELECT 1 FROM LibraryBookAccounts lba WHERE lba.book_id = LibraryBooks.book_id)
+        "#,
+    )
+    .bind(account_id)
+    .execute(&mut *tx)
+    .await?;
+
+    for sql in [
+        "DELETE FROM PendingTokenRefreshes WHERE account_id = ?",
+        "DELETE FROM Accounts WHERE account_id = ?",
+    ] {
+        sqlx::query(sql).bind(account_id).execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,4 +679,164 @@ mod tests {
         let primary_json: serde_json::Value = serde_json::from_str(&primary).unwrap();
         assert_eq!(primary_json["account_id"], "first@example.com");
     }
+
+    #[tokio::test]
+    async fn test_account_token_info() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        let account = |refresh_token: &str, serial: &str| {
+            serde_json::json!({
+                "account_id": "test@example.com",
+                "account_name": "Test",
+                "locale": {"country_code": "us"},
+                "identity": {
+                    "access_token": {"token": "secret-access", "expires_at": "2099-01-01T00:00:00Z"},
+                    "refresh_token": refresh_token,
+                    "device_private_key": "secret-key",
+                    "device_serial_number": serial,
+                    "device_name": "LibriSync Pixel",
+                    "device_type": "A10KISP2GWF0E4"
+                },
+                "decrypt_key": "12345678"
+            })
+            .to_string()
+        };
+
+        save_account(pool, "test@example.com", &account("secret-refresh", "SERIAL1")).await.unwrap();
+        sqlx::query("UPDATE Accounts SET registered_at = '2024-01-01 00:00:00', refresh_token_issued_at = '2024-01-01 00:00:00'")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        // Refreshing the access token keeps registration and refresh token dates
+        save_account(pool, "test@example.com", &account("secret-refresh", "SERIAL1")).await.unwrap();
+        let info = get_account_token_info(pool, "test@example.com").await.unwrap();
+        assert_eq!(info.device_registered_at.as_deref(), Some("2024-01-01 00:00:00"));
+        assert!(info.refresh_token_age_days.unwrap() > 300);
+        assert!(!info.access_token_expired && info.has_refresh_token && info.has_activation_bytes);
+        assert_eq!(info.device_serial_number.as_deref(), Some("SERIAL1"));
+
+        let serialized = serde_json::to_string(&info).unwrap();
+        assert!(!serialized.contains("secret") && !serialized.contains("12345678"));
+
+        // Re-registering the device resets both
+        save_account(pool, "test@example.com", &account("secret-refresh-2", "SERIAL2")).await.unwrap();
+        let info = get_account_token_info(pool, "test@example.com").await.unwrap();
+        assert_ne!(info.device_registered_at.as_deref(), Some("2024-01-01 00:00:00"));
+        assert_eq!(info.refresh_token_age_days, Some(0));
+
+        let earlier = Utc::now() - chrono::Duration::hours(1);
+        record_api_success(pool, "test@example.com", Utc::now()).await.unwrap();
+        record_api_success(pool, "test@example.com", earlier).await.unwrap();
+        let info = get_account_token_info(pool, "test@example.com").await.unwrap();
+        assert!(parse_timestamp(info.last_api_success_at.as_deref().unwrap()).unwrap() > earlier);
+
+        assert!(matches!(
+            get_account_token_info(pool, "missing@example.com").await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recover_pending_token_refreshes() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        // Serialized `Account`s have no top-level locale
+        let account = serde_json::json!({
+            "account_id": "test@example.com",
+            "account_name": "Test",
+            "identity": {
+                "access_token": {"token": "old-access", "expires_at": "2025-01-01T00:00:00Z"},
+                "refresh_token": "old-refresh",
+                "device_serial_number": "SERIAL1",
+                "locale": {"country_code": "de"}
+            }
+        });
+        save_account(pool, "test@example.com", &account.to_string()).await.unwrap();
+
+        let pending = |account_id: &str, expires_at: &str| PendingTokenRefresh {
+            account_id: account_id.to_string(),
+            access_token: "new-access".into(),
+            expires_at: expires_at.to_string(),
+            refresh_token: Some("new-refresh".into()),
+        };
+        record_pending_token_refresh(pool, &pending("test@example.com", "2025-01-01T01:00:00Z")).await.unwrap();
+        record_pending_token_refresh(pool, &pending("gone@example.com", "2025-01-01T01:00:00Z")).await.unwrap();
+
+        // The app died here; the next start merges the tokens
+        let recovered = recover_pending_token_refreshes(pool).await.unwrap();
+        assert_eq!(recovered, vec!["test@example.com".to_string()]);
+
+        let stored: serde_json::Value =
+            serde_json::from_str(&get_account(pool, "test@example.com").await.unwrap().unwrap()).unwrap();
+        assert_eq!(stored["identity"]["access_token"]["token"], "new-access");
+        assert_eq!(stored["identity"]["refresh_token"], "new-refresh");
+        assert_eq!(stored["locale"]["country_code"], "de");
+        assert!(get_account_token_info(pool, "test@example.com").await.unwrap().last_token_refresh.is_some());
+        assert!(get_pending_token_refresh(pool, "gone@example.com").await.unwrap().is_none());
+
+        // Tokens older than the stored ones are dropped
+        record_pending_token_refresh(pool, &PendingTokenRefresh {
+            access_token: "stale-access".into(),
+            ..pending("test@example.com", "2024-06-01T00:00:00Z")
+        })
+        .await
+        .unwrap();
+        assert!(recover_pending_token_refreshes(pool).await.unwrap().is_empty());
+        assert!(get_pending_token_refresh(pool, "test@example.com").await.unwrap().is_none());
+        let stored = get_account(pool, "test@example.com").await.unwrap().unwrap();
+        assert!(stored.contains("new-access"));
+    }
+
+    #[tokio::test]
+    async fn test_list_and_remove_accounts() {
+        use crate::storage::queries::{find_book_by_asin, find_library_book, insert_book, insert_library_book};
+        use crate::storage::{NewBook, NewLibraryBook};
+
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        for (account_id, country) in [("us@example.com", "us"), ("uk@example.com", "uk")] {
+            let account = serde_json::json!({
+                "account_id": account_id,
+                "account_name": account_id,
+                "locale": {"country_code": country},
+                "identity": {"access_token": {"token": "a"}, "refresh_token": "b", "device_serial_number": "c"}
+            });
+            save_account(pool, account_id, &account.to_string()).await.unwrap();
+        }
+
+        let mut book_ids = Vec::new();
+        for asin in ["B0SHARED", "B0USONLY"] {
+            let book = NewBook::new(asin.to_string(), asin.to_string(), "us".to_string());
+            let book_id = insert_book(pool, &book).await.unwrap();
+            insert_library_book(pool, &NewLibraryBook { book_id, account: "us@example.com".to_string() })
+                .await
+                .unwrap();
+            book_ids.push(book_id);
+        }
+        sqlx::query("INSERT INTO LibraryBookAccounts (book_id, account, date_added) VALUES (?, 'uk@example.com', ?)")
+            .bind(book_ids[0])
+            .bind(dates::now())
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let accounts = list_accounts(pool).await.unwrap();
+        let summary: Vec<_> = accounts.iter().map(|a| (a.account_id.as_str(), a.locale_code.as_str(), a.book_count)).collect();
+        assert_eq!(summary, [("uk@example.com", "uk", 1), ("us@example.com", "us", 2)]);
+
+        // The shared book passes to the UK account; the other one goes
+        remove_account(pool, "us@example.com").await.unwrap();
+        assert_eq!(find_library_book(pool, book_ids[0]).await.unwrap().unwrap().account, "uk@example.com");
+        assert!(find_book_by_asin(pool, "B0USONLY").await.unwrap().is_none());
+        assert!(get_account(pool, "us@example.com").await.unwrap().is_none());
+        assert_eq!(list_accounts(pool).await.unwrap().len(), 1);
+
+        assert!(matches!(
+            remove_account(pool, "us@example.com").await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
 }
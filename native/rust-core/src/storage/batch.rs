@@ -0,0 +1,269 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Batched writes
+//!
+//! The app sometimes persists many small updates at once (an import of
+//! finished flags, a settings restore). Instead of one bridge call per
+//! update, it sends a list of typed operations to `execute_batch`, which
+//! runs them in a single transaction. Each operation runs inside its own
+//! savepoint, so a failing one is rolled back on its own and reported in
+//! the per-operation results while the others still commit. With
+//! `all_or_nothing`, the first failure rolls back the whole batch instead.
+
+use crate::error::{LibationError, Result};
+use crate::storage::tags::{prune_unused_tags, replace_book_tags, sync_tag_blob};
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, SqliteConnection, SqlitePool};
+
+/// Most operations accepted in one batch
+pub const MAX_BATCH_OPERATIONS: usize = 5_000;
+
+/// One write in a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    /// Insert or replace a setting
+    SetSetting { key: String, value: String },
+    /// Remove a setting
+    DeleteSetting { key: String },
+    /// Replace a book's tags
+    SetTags { asin: String, tags: Vec<String> },
+    /// Mark a book finished or not
+    SetFinished { asin: String, is_finished: bool },
+    /// Set the user's own rating (0-5 per aspect)
+    SetUserRating {
+        asin: String,
+        overall: f32,
+        performance: f32,
+        story: f32,
+    },
+}
+
+/// Outcome of one operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchOperationResult {
+    /// Position in the submitted list
+    pub index: usize,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// Outcome of a batch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchReport {
+    /// One entry per operation that was run, in order
+    pub results: Vec<BatchOperationResult>,
+    /// Operations whose changes were committed
+    pub applied: usize,
+    pub failed: usize,
+    /// False when `all_or_nothing` rolled everything back
+    pub committed: bool,
+}
+
+/// Run operations in one transaction, each in its own savepoint
+///
+/// # Arguments
+/// * `all_or_nothing` - Roll back the whole batch at the first failure
+///   (later operations aren't run)
+///
+/// # Errors
+/// - InvalidInput if the batch has more than `MAX_BATCH_OPERATIONS`
+/// - Database errors from opening or committing the transaction; failures
+///   of single operations are reported in the results instead
+pub async fn execute_batch(
+    pool: &SqlitePool,
+    operations: &[BatchOperation],
+    all_or_nothing: bool,
+) -> Result<BatchReport> {
+    if operations.len() > MAX_BATCH_OPERATIONS {
+        return Err(LibationError::invalid_input(format!(
+            "Batch has {} operations; at most {} are allowed",
+            operations.len(),
+            MAX_BATCH_OPERATIONS
+        )));
+    }
+
+    let mut report = BatchReport::default();
+    let mut tx = pool.begin().await?;
+
+    for (index, operation) in operations.iter().enumerate() {
+        // A nested transaction is a SAVEPOINT; dropping it rolls back to it
+        let mut savepoint = (&mut tx).begin().await?;
+        match apply(&mut savepoint, operation).await {
+            Ok(()) => {
+                savepoint.commit().await?;
+                report.results.push(BatchOperationResult { index, ok: true, error: None });
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                report.failed += 1;
+                report.results.push(BatchOperationResult {
+                    index,
+                    ok: false,
+                    error: Some(e.to_string()),
+                });
+                if all_or_nothing {
+                    tx.rollback().await?;
+                    return Ok(report);
+                }
+            }
+        }
+    }
+
+    prune_unused_tags(&mut tx).await?;
+    tx.commit().await?;
+    report.applied = operations.len() - report.failed;
+    report.committed = true;
+
+    Ok(report)
+}
+
+async fn apply(conn: &mut SqliteConnection, operation: &BatchOperation) -> Result<()> {
+    match operation {
+        BatchOperation::SetSetting { key, value } => {
+            sqlx::query(
+                r#"
+                INSERT INTO Settings (profile_id, key, value, updated_at)
+                VALUES (COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default'), ?, ?, ?)
+                ON CONFLICT(profile_id, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+                "#,
+            )
+            .bind(key)
+            .bind(value)
+            .bind(crate::storage::dates::now())
+            .execute(&mut *conn)
+            .await?;
+        }
+        BatchOperation::DeleteSetting { key } => {
+            sqlx::query(
+                "DELETE FROM Settings WHERE key = ? \
+                 AND profile_id = COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default')",
+            )
+                .bind(key)
+                .execute(&mut *conn)
+                .await?;
+        }
+        BatchOperation::SetTags { asin, tags } => {
+            let book_id = user_item_book_id(conn, asin).await?;
+            replace_book_tags(conn, book_id, tags).await?;
+            sync_tag_blob(conn, book_id).await?;
+        }
+        BatchOperation::SetFinished { asin, is_finished } => {
+            let book_id = user_item_book_id(conn, asin).await?;
+            sqlx::query("UPDATE UserDefinedItems SET is_finished = ? WHERE book_id = ?")
+                .bind(is_finished)
+                .bind(book_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        BatchOperation::SetUserRating { asin, overall, performance, story } => {
+            if [overall, performance, story].iter().any(|r| !(0.0..=5.0).contains(*r)) {
+                return Err(LibationError::invalid_input("Ratings must be between 0 and 5"));
+            }
+            let book_id = user_item_book_id(conn, asin).await?;
+            sqlx::query(
+                r#"
+                UPDATE UserDefinedItems
+                SET user_rating_overall = ?, user_rating_performance = ?, user_rating_story = ?
+                WHERE book_id = ?
+                "#,
+            )
+            .bind(overall)
+            .bind(performance)
+            .bind(story)
+            .bind(book_id)
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Book id for `asin`, making sure it has a UserDefinedItems row
+async fn user_item_book_id(conn: &mut SqliteConnection, asin: &str) -> Result<i64> {
+    let book_id: i64 = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+        .bind(asin)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+
+    sqlx::query("INSERT OR IGNORE INTO UserDefinedItems (book_id) VALUES (?)")
+        .bind(book_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(book_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::{find_user_defined_item, insert_book};
+    use crate::storage::settings::get_setting;
+    use crate::storage::tags::get_book_tags;
+    use crate::storage::{Database, NewBook};
+
+    #[tokio::test]
+    async fn test_execute_batch() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let book_id = insert_book(pool, &NewBook::new("B0BATCH".to_string(), "Batch".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+
+        let operations: Vec<BatchOperation> = serde_json::from_value(serde_json::json!([
+            { "op": "set_setting", "key": "ui.theme", "value": "dark" },
+            { "op": "set_tags", "asin": "B0BATCH", "tags": ["Sci-Fi", "favorite"] },
+            { "op": "set_finished", "asin": "B0MISSING", "is_finished": true },
+            { "op": "set_finished", "asin": "B0BATCH", "is_finished": true },
+            { "op": "set_user_rating", "asin": "B0BATCH", "overall": 9.0, "performance": 1.0, "story": 1.0 }
+        ]))
+        .unwrap();
+
+        // Failing operations are rolled back alone
+        let report = execute_batch(pool, &operations, false).await.unwrap();
+        assert!(report.committed);
+        assert_eq!((report.applied, report.failed), (3, 2));
+        let failed: Vec<usize> = report.results.iter().filter(|r| !r.ok).map(|r| r.index).collect();
+        assert_eq!(failed, vec![2, 4]);
+        assert!(report.results[2].error.as_deref().unwrap().contains("B0MISSING"));
+
+        assert_eq!(get_setting(pool, "ui.theme").await.unwrap().as_deref(), Some("dark"));
+        assert_eq!(get_book_tags(pool, book_id).await.unwrap(), vec!["favorite", "scifi"]);
+        let item = find_user_defined_item(pool, book_id).await.unwrap().unwrap();
+        assert!(item.is_finished);
+        assert_eq!(item.tags.as_deref(), Some("favorite scifi"));
+        assert_eq!(item.user_rating_overall, 0.0);
+
+        // All or nothing stops at the first failure and keeps nothing
+        let operations = vec![
+            BatchOperation::DeleteSetting { key: "ui.theme".to_string() },
+            BatchOperation::SetTags { asin: "B0BATCH".to_string(), tags: vec![] },
+            BatchOperation::SetFinished { asin: "B0MISSING".to_string(), is_finished: false },
+            BatchOperation::SetFinished { asin: "B0BATCH".to_string(), is_finished: false },
+        ];
+        let report = execute_batch(pool, &operations, true).await.unwrap();
+        assert!(!report.committed);
+        assert_eq!((report.applied, report.failed, report.results.len()), (0, 1, 3));
+        assert_eq!(get_setting(pool, "ui.theme").await.unwrap().as_deref(), Some("dark"));
+        assert_eq!(get_book_tags(pool, book_id).await.unwrap().len(), 2);
+
+        let too_many = vec![BatchOperation::DeleteSetting { key: "x".to_string() }; MAX_BATCH_OPERATIONS + 1];
+        assert!(matches!(execute_batch(pool, &too_many, false).await, Err(LibationError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,204 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Companion files stored with a book
+//!
+//! The liberated audiobook itself is tracked by its download task; bonus
+//! audio, author interviews and PDF supplements downloaded alongside it
+//! (see `download::companion`) are BookFiles rows of the book, each with
+//! its kind and the SHA-256 of its content when it was written. A path is
+//! listed once per book, so recording a file again updates its row.
+
+use crate::api::content::CompanionKind;
+use crate::error::{LibationError, Result};
+use crate::storage::dates;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+/// A companion file of a book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookFile {
+    pub file_id: i64,
+    /// ASIN of the book the file belongs to
+    pub asin: String,
+    pub kind: CompanionKind,
+    /// ASIN of companion audio (None for PDFs)
+    pub source_asin: Option<String>,
+    pub path: String,
+    pub size_bytes: u64,
+    /// Download task that fetched it
+    pub task_id: Option<String>,
+    /// Hex SHA-256 of the file as written (None if it wasn't computed)
+    pub sha256: Option<String>,
+    pub created_at: String,
+}
+
+/// A companion file to record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewBookFile {
+    pub kind: CompanionKind,
+    #[serde(default)]
+    pub source_asin: Option<String>,
+    pub path: String,
+    pub size_bytes: u64,
+    #[serde(default)]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+const FILE_COLUMNS: &str = "f.file_id, b.audible_product_id AS asin, f.kind, f.source_asin, f.path, \
+                            f.size_bytes, f.task_id, f.sha256, f.created_at";
+
+fn row_to_file(row: sqlx::sqlite::SqliteRow) -> Result<BookFile> {
+    let kind: String = row.try_get("kind")?;
+    Ok(BookFile {
+        file_id: row.try_get("file_id")?,
+        asin: row.try_get("asin")?,
+        kind: kind.parse()?,
+        source_asin: row.try_get("source_asin")?,
+        path: row.try_get("path")?,
+        size_bytes: row.try_get::<i64, _>("size_bytes")? as u64,
+        task_id: row.try_get("task_id")?,
+        sha256: row.try_get("sha256")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// Record a companion file of a book, or update the row for its path
+///
+/// # Errors
+/// InvalidInput for an empty path, RecordNotFound if the book isn't in the
+/// database
+pub async fn add_book_file(pool: &SqlitePool, asin: &str, file: &NewBookFile) -> Result<BookFile> {
+    if file.path.trim().is_empty() {
+        return Err(LibationError::invalid_input("Book file path is empty"));
+    }
+    let book_id: i64 = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+        .bind(asin)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+
+    sqlx::query(
+        "INSERT INTO BookFiles (book_id, kind, source_asin, path, size_bytes, task_id, sha256, created_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+         ON CONFLICT(book_id, path) DO UPDATE SET kind = excluded.kind, source_asin = excluded.source_asin, \
+         size_bytes = excluded.size_bytes, task_id = excluded.task_id, sha256 = excluded.sha256, \
+         created_at = excluded.created_at",
+    )
+    .bind(book_id)
+    .bind(file.kind.as_str())
+    .bind(&file.source_asin)
+    .bind(&file.path)
+    .bind(file.size_bytes as i64)
+    .bind(&file.task_id)
+    .bind(file.sha256.as_deref().map(str::to_ascii_lowercase))
+    .bind(dates::now())
+    .execute(pool)
+    .await?;
+
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM BookFiles f JOIN Books b ON b.book_id = f.book_id WHERE f.book_id = ? AND f.path = ?",
+        FILE_COLUMNS
+    ))
+    .bind(book_id)
+    .bind(&file.path)
+    .fetch_one(pool)
+    .await?;
+    row_to_file(row)
+}
+
+/// Companion files of a book, oldest first
+pub async fn list_book_files(pool: &SqlitePool, asin: &str) -> Result<Vec<BookFile>> {
+    sqlx::query(&format!(
+        "SELECT {} FROM BookFiles f JOIN Books b ON b.book_id = f.book_id \
+         WHERE b.audible_product_id = ? ORDER BY f.file_id",
+        FILE_COLUMNS
+    ))
+    .bind(asin)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(row_to_file)
+    .collect()
+}
+
+/// Forget a companion file (the file itself is left alone)
+///
+/// Returns whether there was such a row.
+pub async fn remove_book_file(pool: &SqlitePool, file_id: i64) -> Result<bool> {
+    let removed = sqlx::query("DELETE FROM BookFiles WHERE file_id = ?")
+        .bind(file_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(removed > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::NewBook;
+    use crate::storage::queries::insert_book;
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_book_files() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0MAINBOOK".to_string(), "Main".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+
+        let interview = NewBookFile {
+            kind: CompanionKind::AuthorInterview,
+            source_asin: Some("B0INTERVW1".to_string()),
+            path: "/books/Main/Interview.m4b".to_string(),
+            size_bytes: 1_000,
+            task_id: Some("task-1".to_string()),
+            sha256: Some("AB".repeat(32)),
+        };
+        let pdf = NewBookFile {
+            kind: CompanionKind::Pdf,
+            source_asin: None,
+            path: "/books/Main/Main.pdf".to_string(),
+            size_bytes: 2_000,
+            task_id: None,
+            sha256: None,
+        };
+        add_book_file(pool, "B0MAINBOOK", &interview).await.unwrap();
+        let recorded = add_book_file(pool, "B0MAINBOOK", &pdf).await.unwrap();
+        assert_eq!((recorded.asin.as_str(), recorded.kind), ("B0MAINBOOK", CompanionKind::Pdf));
+
+        // Same path again updates the row
+        let updated = add_book_file(pool, "B0MAINBOOK", &NewBookFile { size_bytes: 3_000, ..pdf }).await.unwrap();
+        assert_eq!(updated.file_id, recorded.file_id);
+        let files = list_book_files(pool, "B0MAINBOOK").await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].source_asin.as_deref(), Some("B0INTERVW1"));
+        assert_eq!(files[0].sha256, Some("ab".repeat(32)));
+        assert_eq!(files[1].size_bytes, 3_000);
+
+        assert!(remove_book_file(pool, recorded.file_id).await.unwrap());
+        assert!(!remove_book_file(pool, recorded.file_id).await.unwrap());
+        assert_eq!(list_book_files(pool, "B0MAINBOOK").await.unwrap().len(), 1);
+        assert!(matches!(
+            add_book_file(pool, "B0MISSING0", &interview).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
+}
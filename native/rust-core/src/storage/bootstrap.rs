@@ -0,0 +1,247 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Schema bootstrap for brand-new databases
+//!
+//! Running every migration in turn on first launch rewrites tables that
+//! were created a few statements earlier, which is slow on old devices. An
+//! empty database instead gets the latest schema from `schema.sql` in one
+//! transaction, with `_migrations` marked up to date, so `run_migrations`
+//! has nothing left to do. Existing databases still migrate step by step.
+//!
+//! `schema.sql` is generated from a database built by the migrations; a
+//! test fails when a new migration isn't reflected in it. Regenerate it with
+//! `UPDATE_SCHEMA_SNAPSHOT=1 cargo test --lib test_schema_snapshot`.
+//!
+//! The host can pass a `BootstrapSeed` with rows for a new install (the
+//! preferred marketplace, default settings). It is applied once, when the
+//! schema was just created (`Database::was_created`).
+
+use crate::api::auth::Locale;
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, SqlitePool};
+use std::collections::BTreeMap;
+
+/// Latest schema, generated from the migrations
+const SCHEMA: &str = include_str!("schema.sql");
+
+/// Rows the migrations insert along with the schema
+const BASE_ROWS: &str = r#"
+INSERT INTO Contributors (contributor_id, name, audible_contributor_id) VALUES (-1, '', NULL);
+INSERT INTO Categories (category_id, audible_category_id, name) VALUES (-1, '', '');
+INSERT INTO Profiles (profile_id, name, is_active, created_at)
+VALUES ('default', 'Default', 1, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+"#;
+
+/// Setting key of the preferred metadata marketplace (see `localized_titles`)
+const KEY_PREFERRED_LOCALE: &str = "metadata.preferred_locale";
+
+/// Rows for a brand-new install
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BootstrapSeed {
+    /// Preferred metadata marketplace, a country code from the locale
+    /// registry ("us", "uk", "de", ...)
+    pub preferred_locale: Option<String>,
+    /// Settings of the default profile; strings are stored as they are,
+    /// other values as JSON
+    pub settings: BTreeMap<String, serde_json::Value>,
+}
+
+/// Create the latest schema if the database is empty
+///
+/// Runs in one transaction, so an interrupted bootstrap leaves an empty
+/// database that is bootstrapped again on the next open.
+///
+/// # Returns
+/// Whether the schema was created (false for databases that have tables)
+pub async fn bootstrap_schema(pool: &SqlitePool) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let objects: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master")
+        .fetch_one(&mut *tx)
+        .await?;
+    if objects > 0 {
+        return Ok(false);
+    }
+
+    tx.execute(SCHEMA).await?;
+    tx.execute(BASE_ROWS).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Insert the seed rows, leaving settings that are already set alone
+///
+/// # Returns
+/// Number of settings inserted
+///
+/// # Errors
+/// InvalidInput if `preferred_locale` isn't a supported marketplace or a
+/// setting key is empty
+pub async fn apply_seed(pool: &SqlitePool, seed: &BootstrapSeed) -> Result<usize> {
+    let mut rows: Vec<(String, String)> = Vec::new();
+    if let Some(code) = &seed.preferred_locale {
+        let locale = Locale::from_country_code(code)
+            .ok_or_else(|| LibationError::invalid_input(format!("Unsupported marketplace: {}", code)))?;
+        rows.push((KEY_PREFERRED_LOCALE.to_string(), locale.country_code));
+    }
+    for (key, value) in &seed.settings {
+        if key.trim().is_empty() {
+            return Err(LibationError::invalid_input("Setting key is empty"));
+        }
+        let value = match value {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        rows.push((key.clone(), value));
+    }
+
+    let now = crate::storage::dates::now();
+    let mut tx = pool.begin().await?;
+    let mut inserted = 0;
+    for (key, value) in &rows {
+        inserted += sqlx::query(
+            "INSERT OR IGNORE INTO Settings (profile_id, key, value, updated_at) VALUES ('default', ?, ?, ?)",
+        )
+        .bind(key)
+        .bind(value)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as usize;
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::migrations::{apply_migrations, run_migrations};
+    use crate::storage::Database;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn empty_pool() -> SqlitePool {
+        SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap()
+    }
+
+    /// `schema.sql` contents for the schema of `pool`
+    async fn schema_snapshot(pool: &SqlitePool) -> String {
+        let objects: Vec<String> = sqlx::query_scalar(
+            "SELECT sql FROM sqlite_master \
+             WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%' \
+             AND name NOT IN (SELECT name FROM pragma_table_list WHERE type = 'shadow') \
+             ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 WHEN 'view' THEN 2 ELSE 3 END, rowid",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        let migrations: Vec<(i64, String)> = sqlx::query_as("SELECT id, name FROM _migrations ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap();
+
+        let mut sql = String::from(
+            "-- Latest schema for new databases (see storage::bootstrap)\n\
+             -- Generated by `UPDATE_SCHEMA_SNAPSHOT=1 cargo test --lib test_schema_snapshot`; do not edit\n\n",
+        );
+        for object in objects {
+            sql.push_str(&object);
+            sql.push_str(";\n\n");
+        }
+        let values: Vec<String> = migrations
+            .iter()
+            .map(|(id, name)| format!("    ({}, '{}')", id, name))
+            .collect();
+        sql.push_str(&format!("INSERT INTO _migrations (id, name) VALUES\n{};\n", values.join(",\n")));
+        sql
+    }
+
+    async fn row_counts(pool: &SqlitePool) -> Vec<(String, i64)> {
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        let mut counts = Vec::new();
+        for table in tables {
+            let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"{}\"", table))
+                .fetch_one(pool)
+                .await
+                .unwrap();
+            counts.push((table, count));
+        }
+        counts
+    }
+
+    #[tokio::test]
+    async fn test_schema_snapshot() {
+        let migrated = empty_pool().await;
+        apply_migrations(&migrated).await.unwrap();
+        let snapshot = schema_snapshot(&migrated).await;
+
+        if std::env::var_os("UPDATE_SCHEMA_SNAPSHOT").is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/storage/schema.sql");
+            std::fs::write(path, &snapshot).unwrap();
+            return;
+        }
+        assert!(
+            snapshot == SCHEMA,
+            "schema.sql doesn't match the migrations; regenerate it with \
+             UPDATE_SCHEMA_SNAPSHOT=1 cargo test --lib test_schema_snapshot"
+        );
+
+        // A bootstrapped database looks like a migrated one
+        let bootstrapped = empty_pool().await;
+        assert!(bootstrap_schema(&bootstrapped).await.unwrap());
+        run_migrations(&bootstrapped).await.unwrap();
+        assert_eq!(schema_snapshot(&bootstrapped).await, snapshot);
+        assert_eq!(row_counts(&bootstrapped).await, row_counts(&migrated).await);
+
+        // Only empty databases are bootstrapped
+        assert!(!bootstrap_schema(&bootstrapped).await.unwrap());
+        assert!(!bootstrap_schema(&migrated).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_apply_seed() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        assert!(db.was_created());
+
+        let mut seed = BootstrapSeed { preferred_locale: Some("UK".to_string()), ..Default::default() };
+        seed.settings.insert("playback.resume".to_string(), serde_json::json!({ "rewind_ms": 15000 }));
+        seed.settings.insert("content_filter.enabled".to_string(), serde_json::json!("true"));
+        assert_eq!(apply_seed(pool, &seed).await.unwrap(), 3);
+
+        let locale = crate::storage::localized_titles::get_preferred_metadata_locale(pool).await.unwrap();
+        assert_eq!(locale.as_deref(), Some("uk"));
+        let resume = crate::audio::resume::get_resume_options(pool).await.unwrap();
+        assert_eq!(resume.rewind_ms, 15000);
+
+        // Values set since are kept
+        crate::storage::settings::set_setting(pool, "content_filter.enabled", "false").await.unwrap();
+        assert_eq!(apply_seed(pool, &seed).await.unwrap(), 0);
+        let enabled = crate::storage::settings::get_setting(pool, "content_filter.enabled").await.unwrap();
+        assert_eq!(enabled.as_deref(), Some("false"));
+
+        let unknown = BootstrapSeed { preferred_locale: Some("xx".to_string()), ..Default::default() };
+        assert!(matches!(apply_seed(pool, &unknown).await, Err(LibationError::InvalidInput(_))));
+    }
+}
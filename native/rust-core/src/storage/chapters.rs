@@ -0,0 +1,156 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-book chapter lists used for tagging
+//!
+//! Chapters normalized before tagging (see `audio::chapters`) are kept in
+//! `BookChapters` so the user can correct them later and re-tag the file
+//! without redoing the download.
+
+use crate::audio::metadata::Chapter;
+use crate::error::{LibationError, Result};
+use sqlx::{Row, SqlitePool};
+
+/// Book id for an ASIN
+async fn book_id_for_asin(pool: &SqlitePool, asin: &str) -> Result<i64> {
+    sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+        .bind(asin)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))
+}
+
+/// Check that chapters are non-empty, ordered, and don't overlap
+fn validate_chapters(chapters: &[Chapter]) -> Result<()> {
+    let mut previous_end = 0;
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        if chapter.title.trim().is_empty() {
+            return Err(LibationError::invalid_input(format!("Chapter {} has no title", i + 1)));
+        }
+        if chapter.start_ms < previous_end || chapter.end_ms <= chapter.start_ms {
+            return Err(LibationError::invalid_input(format!(
+                "Chapter {} ({}) has invalid timing {}-{} ms",
+                i + 1,
+                chapter.title,
+                chapter.start_ms,
+                chapter.end_ms
+            )));
+        }
+        previous_end = chapter.end_ms;
+    }
+
+    Ok(())
+}
+
+/// Stored chapters of a book, in order (empty if none were saved)
+pub async fn get_chapters(pool: &SqlitePool, asin: &str) -> Result<Vec<Chapter>> {
+    let book_id = book_id_for_asin(pool, asin).await?;
+    let rows = sqlx::query(
+        "SELECT title, start_ms, end_ms FROM BookChapters WHERE book_id = ? ORDER BY chapter_index",
+    )
+    .bind(book_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(Chapter {
+                title: row.try_get("title")?,
+                start_ms: row.try_get("start_ms")?,
+                end_ms: row.try_get("end_ms")?,
+            })
+        })
+        .collect()
+}
+
+/// Replace the stored chapters of a book
+///
+/// # Errors
+/// - RecordNotFound if the book isn't in the database
+/// - InvalidInput if a title is empty or chapters overlap or are out of order
+pub async fn update_chapters(pool: &SqlitePool, asin: &str, chapters: &[Chapter]) -> Result<()> {
+    validate_chapters(chapters)?;
+    let book_id = book_id_for_asin(pool, asin).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM BookChapters WHERE book_id = ?")
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (index, chapter) in chapters.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO BookChapters (book_id, chapter_index, title, start_ms, end_ms) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(book_id)
+        .bind(index as i64)
+        .bind(chapter.title.trim())
+        .bind(chapter.start_ms)
+        .bind(chapter.end_ms)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{queries::insert_book, Database, NewBook};
+
+    fn chapter(title: &str, start_ms: i64, end_ms: i64) -> Chapter {
+        Chapter {
+            title: title.to_string(),
+            start_ms,
+            end_ms,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_chapters() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0CHAP".to_string(), "Book".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+
+        assert!(get_chapters(pool, "B0CHAP").await.unwrap().is_empty());
+
+        let chapters = vec![chapter("Opening", 0, 1000), chapter(" The Storm ", 1000, 5000)];
+        update_chapters(pool, "B0CHAP", &chapters).await.unwrap();
+        let stored = get_chapters(pool, "B0CHAP").await.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1].title, "The Storm");
+        assert_eq!(stored[1].end_ms, 5000);
+
+        // Replaced, not appended
+        update_chapters(pool, "B0CHAP", &chapters[..1]).await.unwrap();
+        assert_eq!(get_chapters(pool, "B0CHAP").await.unwrap().len(), 1);
+
+        let overlapping = vec![chapter("A", 0, 2000), chapter("B", 1000, 3000)];
+        assert!(matches!(
+            update_chapters(pool, "B0CHAP", &overlapping).await,
+            Err(LibationError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            update_chapters(pool, "MISSING", &chapters).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
+}
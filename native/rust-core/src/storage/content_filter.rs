@@ -0,0 +1,227 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Parental content filter
+//!
+//! Families sharing a device can hide titles by category. The filter is a
+//! list of excluded category ladder entries (a category id or name, matched
+//! against the book's ladders like the `category` filter of
+//! `BookQueryParams`). While enabled, list, count and search queries skip
+//! any book with a matching ladder.
+//!
+//! A PIN can protect the filter: once set, turning the filter off, editing
+//! the exclusions or changing the PIN requires it. Turning the filter on
+//! never does. Only a salted SHA-256 of the PIN is stored.
+
+use crate::error::{LibationError, Result};
+use crate::storage::queries::BookQueryParams;
+use crate::storage::settings::{delete_setting, get_json_setting, get_setting, set_json_setting, set_setting};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+const KEY_ENABLED: &str = "content_filter.enabled";
+const KEY_EXCLUDED: &str = "content_filter.excluded_categories";
+const KEY_PIN_HASH: &str = "content_filter.pin_hash";
+
+/// Content filter settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentFilter {
+    pub enabled: bool,
+
+    /// Category ids or names whose books are hidden
+    pub excluded_categories: Vec<String>,
+
+    /// A PIN protects the filter
+    pub pin_set: bool,
+}
+
+impl ContentFilter {
+    /// Categories to exclude right now (empty while disabled)
+    pub fn active_exclusions(&self) -> &[String] {
+        if self.enabled {
+            &self.excluded_categories
+        } else {
+            &[]
+        }
+    }
+
+    /// Add the active exclusions to book query parameters
+    pub fn apply(&self, params: &mut BookQueryParams) {
+        params.excluded_categories = self.active_exclusions().to_vec();
+    }
+}
+
+/// Salted SHA-256 of a PIN, as "salt:hash" in hex
+fn hash_pin(pin: &str, salt: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(pin.as_bytes());
+    format!("{}:{}", hex::encode(salt), hex::encode(hasher.finalize()))
+}
+
+/// Check a PIN against the stored hash, if a PIN is set
+async fn verify_pin(pool: &SqlitePool, pin: Option<&str>) -> Result<()> {
+    let Some(stored) = get_setting(pool, KEY_PIN_HASH).await? else {
+        return Ok(());
+    };
+
+    let salt = stored
+        .split_once(':')
+        .and_then(|(salt, _)| hex::decode(salt).ok())
+        .ok_or_else(|| LibationError::InvalidData("Stored content filter PIN is malformed".to_string()))?;
+
+    // Compare every byte so timing doesn't reveal the matching prefix
+    let matches = pin.is_some_and(|pin| {
+        let computed = hash_pin(pin, &salt);
+        computed.len() == stored.len()
+            && computed.bytes().zip(stored.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    });
+
+    if matches {
+        Ok(())
+    } else {
+        Err(LibationError::PermissionDenied("Incorrect content filter PIN".to_string()))
+    }
+}
+
+/// Current content filter settings
+pub async fn get_content_filter(pool: &SqlitePool) -> Result<ContentFilter> {
+    Ok(ContentFilter {
+        enabled: get_setting(pool, KEY_ENABLED).await?.as_deref() == Some("true"),
+        excluded_categories: get_json_setting(pool, KEY_EXCLUDED).await?.unwrap_or_default(),
+        pin_set: get_setting(pool, KEY_PIN_HASH).await?.is_some(),
+    })
+}
+
+/// Turn the filter on or off
+///
+/// # Errors
+/// PermissionDenied when turning it off with a missing or wrong PIN
+pub async fn set_content_filter_enabled(pool: &SqlitePool, enabled: bool, pin: Option<&str>) -> Result<()> {
+    if !enabled {
+        verify_pin(pool, pin).await?;
+    }
+    set_setting(pool, KEY_ENABLED, if enabled { "true" } else { "false" }).await
+}
+
+/// Replace the excluded categories
+///
+/// Blank entries are dropped and duplicates removed.
+///
+/// # Errors
+/// PermissionDenied if a PIN is set and `pin` is missing or wrong
+pub async fn set_excluded_categories(pool: &SqlitePool, categories: &[String], pin: Option<&str>) -> Result<()> {
+    verify_pin(pool, pin).await?;
+
+    let mut excluded: Vec<String> = Vec::new();
+    for category in categories.iter().map(|c| c.trim()).filter(|c| !c.is_empty()) {
+        if !excluded.iter().any(|e| e.eq_ignore_ascii_case(category)) {
+            excluded.push(category.to_string());
+        }
+    }
+    set_json_setting(pool, KEY_EXCLUDED, &excluded).await
+}
+
+/// Set, change or remove (`new_pin` None) the PIN
+///
+/// # Arguments
+/// * `current_pin` - Required if a PIN is already set
+/// * `new_pin` - 4 to 12 digits
+///
+/// # Errors
+/// - PermissionDenied if `current_pin` is missing or wrong
+/// - InvalidInput if `new_pin` isn't 4 to 12 digits
+pub async fn set_content_filter_pin(pool: &SqlitePool, current_pin: Option<&str>, new_pin: Option<&str>) -> Result<()> {
+    verify_pin(pool, current_pin).await?;
+
+    match new_pin {
+        Some(pin) => {
+            if !(4..=12).contains(&pin.len()) || !pin.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(LibationError::invalid_input("PIN must be 4 to 12 digits"));
+            }
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut salt);
+            set_setting(pool, KEY_PIN_HASH, &hash_pin(pin, &salt)).await
+        }
+        None => delete_setting(pool, KEY_PIN_HASH).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::{add_book_category, insert_book, list_books_with_filters, search_books_by_title};
+    use crate::storage::{Database, NewBook};
+
+    async fn add_book(pool: &SqlitePool, asin: &str, title: &str, ladder: &str) {
+        let book_id = insert_book(pool, &NewBook::new(asin.to_string(), title.to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        let ladder_id: i64 = sqlx::query_scalar(
+            "INSERT INTO CategoryLadders (audible_ladder_id, ladder) VALUES (?, ?) RETURNING category_ladder_id",
+        )
+        .bind(format!("ladder-{}", asin))
+        .bind(ladder)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        add_book_category(pool, book_id, ladder_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_content_filter() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        add_book(pool, "B0KIDS", "Dragon Tales", r#"["Children's Audiobooks"]"#).await;
+        add_book(pool, "B0ADULT", "Dragon Nights", r#"["Romance","Erotica"]"#).await;
+
+        set_excluded_categories(pool, &["Erotica".to_string(), " erotica ".to_string()], None)
+            .await
+            .unwrap();
+        set_content_filter_enabled(pool, true, None).await.unwrap();
+        set_content_filter_pin(pool, None, Some("1234")).await.unwrap();
+
+        let filter = get_content_filter(pool).await.unwrap();
+        assert_eq!(filter.excluded_categories, vec!["Erotica"]);
+        assert!(filter.enabled && filter.pin_set);
+
+        let mut params = BookQueryParams { limit: 10, ..Default::default() };
+        filter.apply(&mut params);
+        let books = list_books_with_filters(pool, &params).await.unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].audible_product_id, "B0KIDS");
+        assert_eq!(search_books_by_title(pool, "dragon", 10, filter.active_exclusions()).await.unwrap().len(), 1);
+
+        // Loosening the filter needs the PIN
+        assert!(matches!(
+            set_content_filter_enabled(pool, false, Some("0000")).await,
+            Err(LibationError::PermissionDenied(_))
+        ));
+        assert!(set_excluded_categories(pool, &[], None).await.is_err());
+        assert!(set_content_filter_pin(pool, None, None).await.is_err());
+        assert!(set_content_filter_pin(pool, Some("1234"), Some("12ab")).await.is_err());
+
+        set_content_filter_enabled(pool, false, Some("1234")).await.unwrap();
+        let filter = get_content_filter(pool).await.unwrap();
+        assert!(filter.active_exclusions().is_empty());
+        assert_eq!(search_books_by_title(pool, "dragon", 10, filter.active_exclusions()).await.unwrap().len(), 2);
+
+        set_content_filter_pin(pool, Some("1234"), None).await.unwrap();
+        assert!(!get_content_filter(pool).await.unwrap().pin_set);
+    }
+}
@@ -41,20 +41,29 @@
 //! - Normal synchronous mode (balance safety/speed)
 
 use crate::error::{LibationError, Result};
+use crate::trace::trace_eprintln;
 use sqlx::{
     sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
     ConnectOptions, Executor,
 };
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Mutex;
 use std::time::Duration;
 
+lazy_static::lazy_static! {
+    /// Databases whose startup tasks ran in this process
+    static ref STARTED_DATABASES: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
 /// Database manager - handles connection pooling and operations
 /// Maps to C# `LibationContext` class in LibationContext.cs
 #[derive(Debug, Clone)]
 pub struct Database {
     pool: SqlitePool,
     path: Option<PathBuf>, // None for in-memory databases
+    created: bool,         // Schema was bootstrapped by this open
 }
 
 impl Database {
@@ -107,10 +116,12 @@ impl Database {
         // Configure database with pragmas
         Self::configure_database(&pool).await?;
 
-        // Run migrations
+        // Create the latest schema for a new install, else run migrations
+        let created = Self::bootstrap(&pool).await?;
         let db = Self {
             pool,
             path: Some(path.to_path_buf()),
+            created,
         };
         db.migrate().await?;
 
@@ -135,7 +146,8 @@ impl Database {
 
         Self::configure_database(&pool).await?;
 
-        let db = Self { pool, path: None };
+        let created = Self::bootstrap(&pool).await?;
+        let db = Self { pool, path: None, created };
         db.migrate().await?;
 
         Ok(db)
@@ -156,6 +168,14 @@ impl Database {
         Ok(())
     }
 
+    /// Create the latest schema if the database is empty (see
+    /// `storage::bootstrap`)
+    async fn bootstrap(pool: &SqlitePool) -> Result<bool> {
+        crate::storage::bootstrap::bootstrap_schema(pool)
+            .await
+            .map_err(|e| LibationError::MigrationFailed(e.to_string()))
+    }
+
     /// Run database migrations
     ///
     /// Applies all pending migrations to bring the database schema up to date.
@@ -176,6 +196,46 @@ impl Database {
         &self.pool
     }
 
+    /// Work done once per process when the app (or CLI) starts, after the
+    /// database opened
+    ///
+    /// Settles jobs an earlier process of this program left running (see
+    /// `storage::jobs::mark_interrupted_jobs`) and removes the temp
+    /// directories it left behind (`file::temp::remove_orphaned_temp_dirs`,
+    /// on a blocking thread), then enforces the cache quotas in the
+    /// background (`file::cache::enforce_cache_quotas`). Called by the init
+    /// entry points; later calls for the same database do nothing, and
+    /// in-memory databases are skipped.
+    pub async fn run_startup_tasks(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !STARTED_DATABASES.lock().unwrap().insert(path.clone()) {
+            return Ok(());
+        }
+
+        crate::storage::jobs::mark_interrupted_jobs(&self.pool).await?;
+        if let Ok(Err(e)) = tokio::task::spawn_blocking(crate::file::temp::remove_orphaned_temp_dirs).await {
+            trace_eprintln!("Failed to remove orphaned temp directories: {}", e);
+        }
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::file::cache::enforce_cache_quotas(&pool).await {
+                trace_eprintln!("Failed to enforce cache quotas: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    /// Whether this open created the schema, i.e. the database is a new
+    /// install
+    ///
+    /// Seed rows for new installs are applied when this is true (see
+    /// `storage::bootstrap::apply_seed`).
+    pub fn was_created(&self) -> bool {
+        self.created
+    }
+
     /// Get database file path
     ///
     /// Returns `None` for in-memory databases
@@ -464,4 +524,34 @@ mod tests {
 
         assert!(is_ok, "Database integrity check failed");
     }
+
+    #[tokio::test]
+    async fn test_startup_tasks_run_once_per_process() {
+        use crate::cancel::JobKind;
+        use crate::storage::jobs::{get_job, start_job, JobStatus};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("startup.db");
+        let db = Database::new(&path).await.unwrap();
+        start_job(db.pool(), "test-startup-orphan", JobKind::Scan).await.unwrap();
+        let orphan_dir = crate::file::temp::scratch_root().join("test-startup-orphan");
+        std::fs::create_dir_all(&orphan_dir).unwrap();
+
+        // Opening again leaves running jobs alone; the first startup settles them
+        let db = Database::new(&path).await.unwrap();
+        async fn status(db: &Database, job_id: &str) -> JobStatus {
+            get_job(db.pool(), job_id).await.unwrap().unwrap().status
+        }
+        assert_eq!(status(&db, "test-startup-orphan").await, JobStatus::Running);
+        assert!(orphan_dir.exists());
+        db.run_startup_tasks().await.unwrap();
+        assert_eq!(status(&db, "test-startup-orphan").await, JobStatus::Interrupted);
+        assert!(!orphan_dir.exists());
+
+        // A job started after startup isn't touched by later calls
+        start_job(db.pool(), "test-startup-later", JobKind::Scan).await.unwrap();
+        db.run_startup_tasks().await.unwrap();
+        Database::new(&path).await.unwrap().run_startup_tasks().await.unwrap();
+        assert_eq!(status(&db, "test-startup-later").await, JobStatus::Running);
+    }
 }
@@ -0,0 +1,163 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Date and timestamp storage format
+//!
+//! SQLite has no date type, so dates are TEXT and compared as text. That
+//! only orders correctly if every value has the same shape, which used to
+//! not be the case: SQLite defaults wrote `2024-01-01 10:00:00`, chrono
+//! wrote `2024-01-01T10:00:00.123456+00:00`, and values with a local offset
+//! sorted by local time.
+//!
+//! # Formats
+//! - Timestamps: UTC, fixed width, millisecond precision
+//!   (`2024-01-01T10:00:00.000Z`, see `TIMESTAMP_FORMAT`). In SQL the same
+//!   shape is `strftime('%Y-%m-%dT%H:%M:%fZ', ...)`.
+//! - Calendar dates (release dates): `YYYY-MM-DD`, as published, without
+//!   time zone conversion
+//!
+//! Write timestamps with `format_timestamp` or `now`, read them with
+//! `parse_timestamp`, which also accepts the older shapes. Migration 28
+//! (`utc_timestamps`) rewrote existing rows; `TIMESTAMP_COLUMNS` and
+//! `DATE_COLUMNS` list the columns it covers.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+/// chrono format of stored timestamps
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+
+/// chrono format of stored calendar dates
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Timestamp columns, by table
+pub const TIMESTAMP_COLUMNS: &[(&str, &[&str])] = &[
+    ("Books", &["created_at", "updated_at"]),
+    ("LibraryBooks", &["date_added"]),
+    ("UserDefinedItems", &["last_downloaded", "progress_updated_at"]),
+    ("DownloadTasks", &["created_at", "started_at", "completed_at"]),
+    (
+        "Accounts",
+        &[
+            "token_expires_at",
+            "created_at",
+            "updated_at",
+            "last_token_refresh",
+            "last_library_sync",
+            "registered_at",
+            "refresh_token_issued_at",
+            "last_api_success_at",
+        ],
+    ),
+    ("SyncIssues", &["first_seen", "last_seen", "resolved_at"]),
+    ("ReadAlongMappings", &["fetched_at"]),
+    ("Settings", &["updated_at"]),
+    ("LocalizedTitles", &["fetched_at"]),
+    ("ValidationIssues", &["detected_at"]),
+    ("PendingTokenRefreshes", &["expires_at", "created_at"]),
+    ("FileIntegrity", &["baseline_at", "checked_at"]),
+];
+
+/// Calendar date columns, by table
+pub const DATE_COLUMNS: &[(&str, &[&str])] = &[("Books", &["date_published"])];
+
+/// Stored form of `at`
+pub fn format_timestamp(at: DateTime<Utc>) -> String {
+    at.format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Stored form of the current time (from `clock::now`)
+pub fn now() -> String {
+    format_timestamp(crate::clock::now())
+}
+
+/// Parse a stored timestamp
+///
+/// Accepts RFC 3339 with any offset (converted to UTC), SQLite's
+/// `YYYY-MM-DD HH:MM:SS[.fff]` and offset-less ISO 8601 (both UTC), and
+/// bare dates (midnight UTC).
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(at) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(at.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(value, DATE_FORMAT)
+        .ok()
+        .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight").and_utc())
+}
+
+/// Parse a stored calendar date
+///
+/// Takes the date as written when the value starts with `YYYY-MM-DD`
+/// (a release date of `2020-05-01T00:00:00+02:00` is May 1), else the UTC
+/// date of a timestamp.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    value
+        .get(..10)
+        .and_then(|prefix| NaiveDate::parse_from_str(prefix, DATE_FORMAT).ok())
+        .or_else(|| parse_timestamp(value).map(|at| at.date_naive()))
+}
+
+/// `value` in the stored timestamp format, None if it isn't a timestamp
+pub fn normalize_timestamp(value: &str) -> Option<String> {
+    parse_timestamp(value).map(format_timestamp)
+}
+
+/// `value` in the stored date format, None if it isn't a date
+pub fn normalize_date(value: &str) -> Option<String> {
+    parse_date(value).map(|date| date.format(DATE_FORMAT).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_timestamps_normalize_to_sortable_utc() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap();
+        assert_eq!(format_timestamp(at), "2024-01-01T10:00:00.000Z");
+
+        for legacy in [
+            "2024-01-01 10:00:00",
+            "2024-01-01T10:00:00+00:00",
+            "2024-01-01T10:00:00.000000Z",
+            "2024-01-01T12:00:00+02:00",
+            "2024-01-01T05:00:00-05:00",
+            "2024-01-01T10:00:00",
+        ] {
+            assert_eq!(normalize_timestamp(legacy).as_deref(), Some("2024-01-01T10:00:00.000Z"), "{}", legacy);
+        }
+        assert_eq!(normalize_timestamp("2024-01-01").as_deref(), Some("2024-01-01T00:00:00.000Z"));
+        assert_eq!(normalize_timestamp("soon"), None);
+
+        // Late evening in New York is the next day in UTC, and sorts after
+        // an earlier UTC time on that day
+        let evening = normalize_timestamp("2024-01-01T23:30:00-05:00").unwrap();
+        let morning = normalize_timestamp("2024-01-02 03:00:00").unwrap();
+        assert!(evening > morning);
+
+        // Calendar dates keep the written date
+        assert_eq!(normalize_date("2020-05-01T00:00:00+02:00").as_deref(), Some("2020-05-01"));
+        assert_eq!(normalize_date("2020-05-01").as_deref(), Some("2020-05-01"));
+        assert_eq!(normalize_date("May 2020"), None);
+    }
+}
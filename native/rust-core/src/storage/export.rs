@@ -0,0 +1,560 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Chunked file exports
+//!
+//! Exports of large libraries are written in chunks of `EXPORT_CHUNK_ROWS`
+//! rows instead of being built in memory and written at once:
+//!
+//! - Progress is reported after each chunk (`ExportProgress`)
+//! - Cancellation is checked between chunks
+//! - Rows go to a `.partial` file next to the output, renamed over it once
+//!   everything is written. On failure or cancellation the partial file is
+//!   removed and an existing file at the output path is left as it was.
+//!
+//! `export_library` dumps the library (titles, contributors, series,
+//! purchase date, liberation status, ratings) this way, one row per book,
+//! like Libation's library export.
+
+use crate::cancel::CancellationToken;
+use crate::error::{LibationError, Result};
+use crate::storage::models::{LiberatedStatus, Role};
+use crate::storage::profiles::BOOK_IN_ACTIVE_PROFILE;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Rows written between progress reports and cancellation checks
+pub const EXPORT_CHUNK_ROWS: usize = 500;
+
+/// Progress of a running export
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportProgress {
+    pub rows_written: usize,
+    pub total_rows: usize,
+    pub bytes_written: u64,
+}
+
+/// Called after each chunk
+pub type ExportProgressCallback = Arc<dyn Fn(ExportProgress) + Send + Sync>;
+
+/// Export output written through a partial file
+///
+/// Dropping it before `finish` removes the partial file.
+pub struct ExportFile {
+    output: PathBuf,
+    partial: PathBuf,
+    writer: Option<BufWriter<tokio::fs::File>>,
+    progress: ExportProgress,
+    on_progress: Option<ExportProgressCallback>,
+}
+
+impl ExportFile {
+    /// Start writing `total_rows` rows to `output`
+    pub async fn create(output: &Path, total_rows: usize, on_progress: Option<ExportProgressCallback>) -> Result<Self> {
+        if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut partial = output.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let file = tokio::fs::File::create(&partial).await?;
+
+        Ok(Self {
+            output: output.to_path_buf(),
+            partial,
+            writer: Some(BufWriter::new(file)),
+            progress: ExportProgress { total_rows, ..Default::default() },
+            on_progress,
+        })
+    }
+
+    /// Path rows are written to until `finish`
+    pub fn partial_path(&self) -> &Path {
+        &self.partial
+    }
+
+    /// Write text that isn't a row (header, footer)
+    pub async fn write_text(&mut self, text: &str) -> Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write_all(text.as_bytes()).await?;
+            self.progress.bytes_written += text.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Write a chunk of `rows` rows and report progress
+    pub async fn write_chunk(&mut self, text: &str, rows: usize) -> Result<()> {
+        self.write_text(text).await?;
+        self.progress.rows_written += rows;
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(self.progress.clone());
+        }
+        Ok(())
+    }
+
+    /// Flush and move the file to the output path
+    pub async fn finish(mut self) -> Result<ExportProgress> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush().await?;
+            writer.into_inner().sync_all().await?;
+        }
+        tokio::fs::rename(&self.partial, &self.output).await?;
+        Ok(self.progress.clone())
+    }
+}
+
+impl Drop for ExportFile {
+    fn drop(&mut self) {
+        // Not finished: failed or cancelled
+        if self.writer.take().is_some() {
+            let _ = std::fs::remove_file(&self.partial);
+        }
+    }
+}
+
+/// Write `rows` to `output` in chunks
+///
+/// Each row is rendered with `render`; rows are separated by `separator`
+/// and surrounded by `header` and `footer`.
+///
+/// # Errors
+/// - Cancelled if `cancel` fires between chunks
+/// - IoError if the file can't be written
+///
+/// The partial file is removed in both cases.
+#[allow(clippy::too_many_arguments)]
+pub async fn write_chunked<T>(
+    output: &Path,
+    header: &str,
+    rows: &[T],
+    render: impl Fn(&T) -> Result<String>,
+    separator: &str,
+    footer: &str,
+    on_progress: Option<ExportProgressCallback>,
+    cancel: &CancellationToken,
+) -> Result<ExportProgress> {
+    let mut file = ExportFile::create(output, rows.len(), on_progress).await?;
+    file.write_text(header).await?;
+
+    for (index, chunk) in rows.chunks(EXPORT_CHUNK_ROWS).enumerate() {
+        cancel.check()?;
+        let mut text = String::new();
+        for (i, row) in chunk.iter().enumerate() {
+            if index > 0 || i > 0 {
+                text.push_str(separator);
+            }
+            text.push_str(&render(row)?);
+        }
+        file.write_chunk(&text, chunk.len()).await?;
+    }
+
+    cancel.check()?;
+    file.write_text(footer).await?;
+    file.finish().await
+}
+
+/// Quote a CSV field when needed
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Library export file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One row per book, lists joined with ", "
+    Csv,
+    /// An array of `LibraryExportRow`
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(LibationError::invalid_input(format!("Unknown export format: {}", s))),
+        }
+    }
+}
+
+/// A book's place in a series
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedSeries {
+    pub name: String,
+    pub order: Option<String>,
+}
+
+/// One book of the library export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryExportRow {
+    pub asin: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub authors: Vec<String>,
+    pub narrators: Vec<String>,
+    pub series: Vec<ExportedSeries>,
+    pub account: Option<String>,
+    /// When the book was added to the library (UTC ISO 8601)
+    pub purchase_date: Option<String>,
+    pub date_published: Option<String>,
+    pub length_in_minutes: i32,
+    pub locale: String,
+    pub liberated_status: LiberatedStatus,
+    pub pdf_status: Option<LiberatedStatus>,
+    pub community_rating_overall: f32,
+    pub community_rating_performance: f32,
+    pub community_rating_story: f32,
+    pub my_rating_overall: f32,
+    pub my_rating_performance: f32,
+    pub my_rating_story: f32,
+    pub tags: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ExportBookRow {
+    book_id: i64,
+    asin: String,
+    title: String,
+    subtitle: Option<String>,
+    account: Option<String>,
+    date_added: Option<String>,
+    date_published: Option<String>,
+    length_in_minutes: i32,
+    locale: String,
+    book_status: Option<i32>,
+    pdf_status: Option<i32>,
+    rating_overall: f32,
+    rating_performance: f32,
+    rating_story: f32,
+    user_rating_overall: Option<f32>,
+    user_rating_performance: Option<f32>,
+    user_rating_story: Option<f32>,
+    tags: Option<String>,
+}
+
+/// Books of the active profile's library, by title
+///
+/// Books removed from the library are left out.
+pub async fn library_export_rows(pool: &SqlitePool) -> Result<Vec<LibraryExportRow>> {
+    let books: Vec<ExportBookRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT
+            b.book_id,
+            b.audible_product_id AS asin,
+            b.title,
+            b.subtitle,
+            lb.account,
+            lb.date_added,
+            b.date_published,
+            b.length_in_minutes,
+            b.locale,
+            u.book_status,
+            u.pdf_status,
+            b.rating_overall,
+            b.rating_performance,
+            b.rating_story,
+            u.user_rating_overall,
+            u.user_rating_performance,
+            u.user_rating_story,
+            u.tags
+        FROM Books b
+        LEFT JOIN LibraryBooks lb ON lb.book_id = b.book_id
+        LEFT JOIN UserDefinedItems u ON u.book_id = b.book_id
+        WHERE COALESCE(lb.is_deleted, 0) = 0 AND {}
+        ORDER BY COALESCE(b.title_sort, b.title) COLLATE NOCASE, b.book_id
+        "#,
+        BOOK_IN_ACTIVE_PROFILE
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let contributors: Vec<(i64, i32, String)> = sqlx::query_as(
+        r#"
+        SELECT bc.book_id, bc.role, c.name
+        FROM BookContributors bc
+        JOIN Contributors c ON c.contributor_id = bc.contributor_id
+        ORDER BY bc.book_id, bc.role, bc."order"
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut people: HashMap<(i64, i32), Vec<String>> = HashMap::new();
+    for (book_id, role, name) in contributors {
+        people.entry((book_id, role)).or_default().push(name);
+    }
+
+    let series: Vec<(i64, Option<String>, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT sb.book_id, s.name, sb."order"
+        FROM SeriesBooks sb
+        JOIN Series s ON s.series_id = sb.series_id
+        ORDER BY sb.book_id, s.name COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut book_series: HashMap<i64, Vec<ExportedSeries>> = HashMap::new();
+    for (book_id, name, order) in series {
+        book_series.entry(book_id).or_default().push(ExportedSeries {
+            name: name.unwrap_or_default(),
+            order: order.filter(|o| !o.is_empty()),
+        });
+    }
+
+    Ok(books
+        .into_iter()
+        .map(|book| LibraryExportRow {
+            authors: people.remove(&(book.book_id, Role::Author as i32)).unwrap_or_default(),
+            narrators: people.remove(&(book.book_id, Role::Narrator as i32)).unwrap_or_default(),
+            series: book_series.remove(&book.book_id).unwrap_or_default(),
+            asin: book.asin,
+            title: book.title,
+            subtitle: book.subtitle,
+            account: book.account,
+            purchase_date: book.date_added,
+            date_published: book.date_published,
+            length_in_minutes: book.length_in_minutes,
+            locale: book.locale,
+            liberated_status: LiberatedStatus::from_i32(book.book_status.unwrap_or_default()),
+            pdf_status: book.pdf_status.map(LiberatedStatus::from_i32),
+            community_rating_overall: book.rating_overall,
+            community_rating_performance: book.rating_performance,
+            community_rating_story: book.rating_story,
+            my_rating_overall: book.user_rating_overall.unwrap_or_default(),
+            my_rating_performance: book.user_rating_performance.unwrap_or_default(),
+            my_rating_story: book.user_rating_story.unwrap_or_default(),
+            tags: book.tags.filter(|t| !t.is_empty()),
+        })
+        .collect())
+}
+
+/// Header row of the library CSV
+const LIBRARY_CSV_HEADER: &str = "asin,title,subtitle,authors,narrators,series,account,purchase_date,\
+date_published,length_in_minutes,locale,liberated_status,pdf_status,community_rating_overall,\
+community_rating_performance,community_rating_story,my_rating_overall,my_rating_performance,my_rating_story,tags\n";
+
+/// Name of a liberation status in the CSV
+fn status_name(status: LiberatedStatus) -> &'static str {
+    match status {
+        LiberatedStatus::NotLiberated => "NotLiberated",
+        LiberatedStatus::Liberated => "Liberated",
+        LiberatedStatus::Error => "Error",
+    }
+}
+
+/// One library CSV row, with the line break
+fn library_csv_row(row: &LibraryExportRow) -> String {
+    let optional = |value: &Option<String>| csv_field(value.as_deref().unwrap_or_default());
+    let series: Vec<String> = row
+        .series
+        .iter()
+        .map(|s| match &s.order {
+            Some(order) => format!("{} #{}", s.name, order),
+            None => s.name.clone(),
+        })
+        .collect();
+
+    let fields = [
+        csv_field(&row.asin),
+        csv_field(&row.title),
+        optional(&row.subtitle),
+        csv_field(&row.authors.join(", ")),
+        csv_field(&row.narrators.join(", ")),
+        csv_field(&series.join(", ")),
+        optional(&row.account),
+        optional(&row.purchase_date),
+        optional(&row.date_published),
+        row.length_in_minutes.to_string(),
+        csv_field(&row.locale),
+        status_name(row.liberated_status).to_string(),
+        row.pdf_status.map(status_name).unwrap_or_default().to_string(),
+        row.community_rating_overall.to_string(),
+        row.community_rating_performance.to_string(),
+        row.community_rating_story.to_string(),
+        row.my_rating_overall.to_string(),
+        row.my_rating_performance.to_string(),
+        row.my_rating_story.to_string(),
+        optional(&row.tags),
+    ];
+    format!("{}\n", fields.join(","))
+}
+
+/// Write the library to `output_path`
+///
+/// Same guarantees as `write_chunked`: progress after each chunk,
+/// cancellation between them, and no partial file on failure.
+pub async fn export_library(
+    pool: &SqlitePool,
+    output_path: &Path,
+    format: ExportFormat,
+    on_progress: Option<ExportProgressCallback>,
+    cancel: &CancellationToken,
+) -> Result<ExportProgress> {
+    let rows = library_export_rows(pool).await?;
+    match format {
+        ExportFormat::Csv => {
+            write_chunked(
+                output_path,
+                LIBRARY_CSV_HEADER,
+                &rows,
+                |row| Ok(library_csv_row(row)),
+                "",
+                "",
+                on_progress,
+                cancel,
+            )
+            .await
+        }
+        ExportFormat::Json => {
+            write_chunked(
+                output_path,
+                "[",
+                &rows,
+                |row| Ok(serde_json::to_string(row)?),
+                ",",
+                "]",
+                on_progress,
+                cancel,
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::LibationError;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_write_chunked() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("export.csv");
+        let rows: Vec<usize> = (0..1_200).collect();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let on_progress: ExportProgressCallback = Arc::new(move |p| sink.lock().unwrap().push(p.rows_written));
+        let progress = write_chunked(
+            &output,
+            "n\n",
+            &rows,
+            |n| Ok(n.to_string()),
+            "\n",
+            "\n",
+            Some(on_progress),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![500, 1_000, 1_200]);
+        assert_eq!(progress.total_rows, 1_200);
+        let text = std::fs::read_to_string(&output).unwrap();
+        assert_eq!(text.lines().count(), 1_201);
+        assert_eq!(progress.bytes_written, text.len() as u64);
+        assert!(!dir.path().join("export.csv.partial").exists());
+
+        // A cancelled export leaves the earlier file and no partial one
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = write_chunked(&output, "n\n", &rows, |n| Ok(n.to_string()), "\n", "\n", None, &cancel).await;
+        assert!(matches!(result, Err(LibationError::Cancelled)));
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), text);
+        assert!(!dir.path().join("export.csv.partial").exists());
+    }
+
+    #[tokio::test]
+    async fn test_export_library() {
+        use crate::storage::models::{NewContributor, NewLibraryBook, NewSeries};
+        use crate::storage::{queries, Database, NewBook};
+
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut book = NewBook::new("B001".to_string(), "Mort, a \"Discworld\" Novel".to_string(), "us".to_string());
+        book.rating_overall = 4.5;
+        let mort = queries::insert_book(pool, &book).await.unwrap();
+        let removed = queries::insert_book(pool, &NewBook::new("B002".to_string(), "Gone".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        for (book_id, is_deleted) in [(mort, 0), (removed, 1)] {
+            queries::insert_library_book(pool, &NewLibraryBook { book_id, account: "me@example.com".to_string() })
+                .await
+                .unwrap();
+            sqlx::query("UPDATE LibraryBooks SET date_added = '2024-03-01T10:00:00Z', is_deleted = ? WHERE book_id = ?")
+                .bind(is_deleted)
+                .bind(book_id)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        for (name, role, order) in [("Terry Pratchett", Role::Author, 0), ("Nigel Planer", Role::Narrator, 0)] {
+            let contributor = queries::upsert_contributor(pool, &NewContributor::new(name.to_string())).await.unwrap();
+            queries::add_book_contributor(pool, mort, contributor, role as i32, order).await.unwrap();
+        }
+        let series = NewSeries { audible_series_id: "S1".to_string(), name: Some("Discworld".to_string()) };
+        let series_id = queries::upsert_series(pool, &series).await.unwrap();
+        queries::add_book_to_series(pool, series_id, mort, Some("4".to_string()), 4.0).await.unwrap();
+        sqlx::query("INSERT INTO UserDefinedItems (book_id, book_status, user_rating_overall, tags) VALUES (?, 1, 5, 'comfort')")
+            .bind(mort)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let rows = library_export_rows(pool).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.authors, vec!["Terry Pratchett"]);
+        assert_eq!(row.narrators, vec!["Nigel Planer"]);
+        assert_eq!(row.series, vec![ExportedSeries { name: "Discworld".to_string(), order: Some("4".to_string()) }]);
+        assert_eq!(row.purchase_date.as_deref(), Some("2024-03-01T10:00:00Z"));
+        assert_eq!(row.liberated_status, LiberatedStatus::Liberated);
+        assert_eq!((row.community_rating_overall, row.my_rating_overall), (4.5, 5.0));
+
+        let csv_path = dir.path().join("library.csv");
+        let cancel = CancellationToken::new();
+        let progress = export_library(pool, &csv_path, "CSV".parse().unwrap(), None, &cancel).await.unwrap();
+        assert_eq!(progress.rows_written, 1);
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], LIBRARY_CSV_HEADER.trim_end());
+        assert!(lines[1].starts_with(
+            "B001,\"Mort, a \"\"Discworld\"\" Novel\",,Terry Pratchett,Nigel Planer,Discworld #4,me@example.com,2024-03-01T10:00:00Z,"
+        ));
+        assert!(lines[1].contains(",Liberated,,4.5,"));
+        assert!("xml".parse::<ExportFormat>().is_err());
+
+        let json_path = dir.path().join("library.json");
+        export_library(pool, &json_path, ExportFormat::Json, None, &cancel).await.unwrap();
+        let parsed: Vec<LibraryExportRow> = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(parsed, rows);
+    }
+}
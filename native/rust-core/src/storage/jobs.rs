@@ -0,0 +1,442 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistent job records
+//!
+//! Jobs run by the bridge (library syncs, decryptions, scans, exports and
+//! integrity checks) get a row in Jobs under their job id, with kind,
+//! status, timestamps, the error they failed with and the artifacts they
+//! produced. Download tasks already have durable ids in DownloadTasks.
+//!
+//! The row outlives the process: when the app comes back after being
+//! killed, the UI can look its job up by id and show how it ended, or
+//! that it was interrupted. What is running right now is still decided by
+//! the in-memory registry in `crate::cancel`; `mark_interrupted_jobs`
+//! settles rows a previous process left running.
+//!
+//! The CLI shares the app's database, so each row records the program
+//! that ran it (`Jobs.owner`, see `set_job_owner`), and a process only
+//! settles rows of its own program.
+
+use crate::cancel::{JobHandle, JobKind};
+use crate::error::{LibationError, Result};
+use crate::storage::dates;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Finished jobs kept; older ones are removed when a job starts
+const KEEP_FINISHED_JOBS: i64 = 200;
+
+/// Owner of jobs recorded by the app
+pub const APP_JOB_OWNER: &str = "app";
+
+static JOB_OWNER: OnceLock<&'static str> = OnceLock::new();
+
+/// Name the program whose jobs this process records (default
+/// `APP_JOB_OWNER`); set once at startup, later calls are ignored
+pub fn set_job_owner(owner: &'static str) {
+    let _ = JOB_OWNER.set(owner);
+}
+
+fn job_owner() -> &'static str {
+    JOB_OWNER.get().copied().unwrap_or(APP_JOB_OWNER)
+}
+
+/// Job status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// The process stopped while the job was running
+    Interrupted,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "running" => Ok(JobStatus::Running),
+            "completed" => Ok(JobStatus::Completed),
+            "failed" => Ok(JobStatus::Failed),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            "interrupted" => Ok(JobStatus::Interrupted),
+            _ => Err(LibationError::InvalidInput(format!("Invalid job status: {}", s))),
+        }
+    }
+}
+
+/// Kind of thing a job produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// File path
+    File,
+    /// Folder path
+    Directory,
+    /// Book ASIN
+    Book,
+}
+
+impl ArtifactKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArtifactKind::File => "file",
+            ArtifactKind::Directory => "directory",
+            ArtifactKind::Book => "book",
+        }
+    }
+}
+
+impl FromStr for ArtifactKind {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "file" => Ok(ArtifactKind::File),
+            "directory" => Ok(ArtifactKind::Directory),
+            "book" => Ok(ArtifactKind::Book),
+            _ => Err(LibationError::InvalidInput(format!("Invalid artifact kind: {}", s))),
+        }
+    }
+}
+
+/// Something a job created
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobArtifact {
+    pub kind: ArtifactKind,
+    /// Path or ASIN
+    pub reference: String,
+}
+
+impl JobArtifact {
+    pub fn file(path: impl Into<String>) -> Self {
+        Self { kind: ArtifactKind::File, reference: path.into() }
+    }
+
+    pub fn directory(path: impl Into<String>) -> Self {
+        Self { kind: ArtifactKind::Directory, reference: path.into() }
+    }
+
+    pub fn book(asin: impl Into<String>) -> Self {
+        Self { kind: ArtifactKind::Book, reference: asin.into() }
+    }
+}
+
+/// Stored job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub job_id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub started_at: String,
+    pub updated_at: String,
+    pub finished_at: Option<String>,
+    /// Error message of failed and interrupted jobs
+    pub error: Option<String>,
+    /// Program that ran the job (None for rows from before owners were
+    /// recorded)
+    pub owner: Option<String>,
+    pub artifacts: Vec<JobArtifact>,
+}
+
+/// Record a job as running
+///
+/// A finished job with the same id is replaced, artifacts included.
+pub async fn start_job(pool: &SqlitePool, job_id: &str, kind: JobKind) -> Result<()> {
+    let now = dates::now();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM JobArtifacts WHERE job_id = ?")
+        .bind(job_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO Jobs (job_id, kind, status, started_at, updated_at, finished_at, error, owner)
+        VALUES (?, ?, ?, ?, ?, NULL, NULL, ?)
+        ON CONFLICT(job_id) DO UPDATE SET
+            kind = excluded.kind,
+            status = excluded.status,
+            started_at = excluded.started_at,
+            updated_at = excluded.updated_at,
+            finished_at = NULL,
+            error = NULL,
+            owner = excluded.owner
+        "#,
+    )
+    .bind(job_id)
+    .bind(kind.as_str())
+    .bind(JobStatus::Running.as_str())
+    .bind(&now)
+    .bind(&now)
+    .bind(job_owner())
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM Jobs WHERE job_id IN (
+            SELECT job_id FROM Jobs WHERE finished_at IS NOT NULL
+            ORDER BY finished_at DESC LIMIT -1 OFFSET ?
+        )
+        "#,
+    )
+    .bind(KEEP_FINISHED_JOBS)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Link artifacts to a job
+pub async fn add_job_artifacts(pool: &SqlitePool, job_id: &str, artifacts: &[JobArtifact]) -> Result<()> {
+    let now = dates::now();
+    let mut tx = pool.begin().await?;
+    for artifact in artifacts {
+        sqlx::query(
+            "INSERT OR IGNORE INTO JobArtifacts (job_id, kind, reference, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(job_id)
+        .bind(artifact.kind.as_str())
+        .bind(&artifact.reference)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    sqlx::query("UPDATE Jobs SET updated_at = ? WHERE job_id = ?")
+        .bind(&now)
+        .bind(job_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Record how a job ended: completed, cancelled (on `LibationError::Cancelled`)
+/// or failed with the error message
+pub async fn finish_job<T>(pool: &SqlitePool, job_id: &str, outcome: &Result<T>) -> Result<()> {
+    let (status, error) = match outcome {
+        Ok(_) => (JobStatus::Completed, None),
+        Err(LibationError::Cancelled) => (JobStatus::Cancelled, None),
+        Err(e) => (JobStatus::Failed, Some(e.to_string())),
+    };
+    let now = dates::now();
+
+    sqlx::query("UPDATE Jobs SET status = ?, error = ?, updated_at = ?, finished_at = ? WHERE job_id = ?")
+        .bind(status.as_str())
+        .bind(error)
+        .bind(&now)
+        .bind(&now)
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Run `work` under a registered job, recording its start and outcome
+///
+/// Progress `work` reports with `events::report_items` / `report_fraction`
+/// is emitted for the job, and its outcome is sent to the host as a work
+/// event (see `events`).
+///
+/// # Errors
+/// The error of `work`, or of recording the job
+pub async fn run_job<T, F>(pool: &SqlitePool, job: &JobHandle, work: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    start_job(pool, job.job_id(), job.kind()).await?;
+    let outcome = crate::events::with_progress_scope(job.kind(), job.job_id(), work).await;
+    let recorded = finish_job(pool, job.job_id(), &outcome).await;
+    crate::events::emit_outcome(pool, &crate::clock::AppClock, job.kind(), job.job_id(), None, &outcome).await;
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::record_outcome(pool, job.kind(), &outcome).await;
+    let value = outcome?;
+    recorded?;
+    Ok(value)
+}
+
+/// Mark jobs left running by a previous process of this program as
+/// interrupted
+///
+/// Run once at process start (see `Database::run_startup_tasks`). Jobs of
+/// another program sharing the database (the CLI, the app) are left alone,
+/// and so are jobs registered in this process (`crate::cancel::list_jobs`).
+/// Rows without an owner predate owners and count as the app's.
+///
+/// # Returns
+/// Number of jobs marked
+pub async fn mark_interrupted_jobs(pool: &SqlitePool) -> Result<u64> {
+    let live: Vec<String> = crate::cancel::list_jobs().into_iter().map(|j| j.job_id).collect();
+    let running: Vec<String> = sqlx::query_scalar(
+        "SELECT job_id FROM Jobs WHERE status = ? AND COALESCE(owner, ?) = ?",
+    )
+    .bind(JobStatus::Running.as_str())
+    .bind(APP_JOB_OWNER)
+    .bind(job_owner())
+    .fetch_all(pool)
+    .await?;
+
+    let now = dates::now();
+    let mut marked = 0;
+    for job_id in running.iter().filter(|id| !live.contains(id)) {
+        marked += sqlx::query(
+            "UPDATE Jobs SET status = ?, error = ?, updated_at = ?, finished_at = ? WHERE job_id = ? AND status = ?",
+        )
+        .bind(JobStatus::Interrupted.as_str())
+        .bind("The app stopped before the job finished")
+        .bind(&now)
+        .bind(&now)
+        .bind(job_id)
+        .bind(JobStatus::Running.as_str())
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(marked)
+}
+
+/// Look up a job by id
+pub async fn get_job(pool: &SqlitePool, job_id: &str) -> Result<Option<JobRecord>> {
+    let row = sqlx::query("SELECT * FROM Jobs WHERE job_id = ?")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?;
+
+    match row {
+        Some(row) => Ok(Some(job_from_row(pool, &row).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Most recently started jobs, newest first
+pub async fn list_recent_jobs(pool: &SqlitePool, limit: i64) -> Result<Vec<JobRecord>> {
+    let rows = sqlx::query("SELECT * FROM Jobs ORDER BY started_at DESC, rowid DESC LIMIT ?")
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    let mut jobs = Vec::with_capacity(rows.len());
+    for row in &rows {
+        jobs.push(job_from_row(pool, row).await?);
+    }
+    Ok(jobs)
+}
+
+async fn job_from_row(pool: &SqlitePool, row: &sqlx::sqlite::SqliteRow) -> Result<JobRecord> {
+    let job_id: String = row.try_get("job_id")?;
+    let kind: String = row.try_get("kind")?;
+    let status: String = row.try_get("status")?;
+
+    let artifact_rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT kind, reference FROM JobArtifacts WHERE job_id = ? ORDER BY rowid",
+    )
+    .bind(&job_id)
+    .fetch_all(pool)
+    .await?;
+    let artifacts = artifact_rows
+        .into_iter()
+        .map(|(kind, reference)| Ok(JobArtifact { kind: kind.parse()?, reference }))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(JobRecord {
+        job_id,
+        kind: kind.parse()?,
+        status: status.parse()?,
+        started_at: row.try_get("started_at")?,
+        updated_at: row.try_get("updated_at")?,
+        finished_at: row.try_get("finished_at")?,
+        error: row.try_get("error")?,
+        owner: row.try_get("owner")?,
+        artifacts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancel::register_job;
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_job_rows_survive_their_process() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        let job = register_job(Some("test-jobs-export".to_string()), JobKind::Export).unwrap();
+        let path = run_job(pool, &job, async {
+            add_job_artifacts(pool, job.job_id(), &[JobArtifact::file("/exports/stats.csv")]).await?;
+            Ok("/exports/stats.csv")
+        })
+        .await
+        .unwrap();
+        assert_eq!(path, "/exports/stats.csv");
+        drop(job);
+
+        let record = get_job(pool, "test-jobs-export").await.unwrap().unwrap();
+        assert_eq!(record.status, JobStatus::Completed);
+        assert!(record.finished_at.is_some());
+        assert_eq!(record.artifacts, vec![JobArtifact::file("/exports/stats.csv")]);
+
+        let job = register_job(Some("test-jobs-scan".to_string()), JobKind::Scan).unwrap();
+        let failed: Result<()> = run_job(pool, &job, async { Err(LibationError::Cancelled) }).await;
+        assert!(failed.is_err());
+        drop(job);
+        assert_eq!(get_job(pool, "test-jobs-scan").await.unwrap().unwrap().status, JobStatus::Cancelled);
+
+        // One job still running here, one left behind by a killed process
+        let live = register_job(Some("test-jobs-live".to_string()), JobKind::LibrarySync).unwrap();
+        start_job(pool, live.job_id(), live.kind()).await.unwrap();
+        start_job(pool, "test-jobs-orphan", JobKind::Decryption).await.unwrap();
+        // Running in the CLI, which shares the database
+        start_job(pool, "test-jobs-cli", JobKind::LibrarySync).await.unwrap();
+        sqlx::query("UPDATE Jobs SET owner = 'cli' WHERE job_id = 'test-jobs-cli'")
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(mark_interrupted_jobs(pool).await.unwrap(), 1);
+
+        let orphan = get_job(pool, "test-jobs-orphan").await.unwrap().unwrap();
+        assert_eq!(orphan.status, JobStatus::Interrupted);
+        assert!(orphan.error.is_some());
+        assert_eq!(orphan.owner.as_deref(), Some(APP_JOB_OWNER));
+        assert_eq!(get_job(pool, "test-jobs-live").await.unwrap().unwrap().status, JobStatus::Running);
+        assert_eq!(get_job(pool, "test-jobs-cli").await.unwrap().unwrap().status, JobStatus::Running);
+        assert_eq!(list_recent_jobs(pool, 10).await.unwrap().len(), 5);
+    }
+}
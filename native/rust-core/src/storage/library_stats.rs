@@ -0,0 +1,423 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Codec and bitrate statistics of liberated files
+//!
+//! Helps decide whether older low-bitrate files are worth liberating again.
+//! Each liberated book's file (the latest completed download task) is
+//! measured on disk. The bitrate is the average over the book's runtime,
+//! from the file size, so it includes container overhead. The codec comes
+//! from the stored download format when known, otherwise from the file
+//! extension.
+//!
+//! Only the active profile's books are counted (see `storage::profiles`).
+//!
+//! The report can be exported per file as CSV or as JSON
+//! (`export_library_stats`), in chunks that can be cancelled.
+
+use crate::cancel::CancellationToken;
+use crate::error::{LibationError, Result};
+use crate::storage::export::{csv_field, write_chunked, ExportProgress, ExportProgressCallback};
+use crate::storage::models::{AudioFormat, Codec};
+use crate::storage::profiles::BOOK_IN_ACTIVE_PROFILE;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Default threshold for `LibraryStats::low_bitrate_files`
+pub const DEFAULT_LOW_BITRATE_KBPS: u32 = 32;
+
+/// One liberated file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiberatedFileStats {
+    pub asin: String,
+    pub title: String,
+    pub path: String,
+    /// Lowercase file extension ("m4b", "mp3")
+    pub format: String,
+    /// "aac", "aac-lc", "xhe-aac", "mp3", "ec-3", ... or "unknown"
+    pub codec: String,
+    /// None when the file is missing
+    pub size_bytes: Option<u64>,
+    pub length_in_minutes: i32,
+    /// Average bitrate (None without size or runtime)
+    pub bitrate_kbps: Option<u32>,
+    pub bytes_per_hour: Option<u64>,
+}
+
+/// Totals for one format and codec
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatTotals {
+    pub format: String,
+    pub codec: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub total_minutes: i64,
+    pub average_bitrate_kbps: Option<u32>,
+    pub bytes_per_hour: Option<u64>,
+}
+
+/// Library-wide file statistics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryStats {
+    pub total_books: i64,
+    pub liberated_books: usize,
+    /// Liberated books whose file is gone
+    pub missing_files: usize,
+    pub total_bytes: u64,
+    /// Runtime of the liberated files found on disk
+    pub total_minutes: i64,
+    pub low_bitrate_kbps: u32,
+    /// Files at or below `low_bitrate_kbps`
+    pub low_bitrate_files: usize,
+    /// Largest total first
+    pub formats: Vec<FormatTotals>,
+    pub files: Vec<LiberatedFileStats>,
+}
+
+/// Export file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsExportFormat {
+    /// One row per file
+    Csv,
+    /// The whole `LibraryStats`
+    Json,
+}
+
+impl FromStr for StatsExportFormat {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(LibationError::invalid_input(format!("Unknown export format: {}", s))),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct LiberatedRow {
+    asin: String,
+    title: String,
+    length_in_minutes: i32,
+    output_path: String,
+    last_downloaded_format: Option<i64>,
+}
+
+/// Average bitrate in kbps of `size_bytes` over `length_in_minutes`
+pub fn average_bitrate_kbps(size_bytes: u64, length_in_minutes: i32) -> Option<u32> {
+    (length_in_minutes > 0)
+        .then(|| (size_bytes as f64 * 8.0 / (length_in_minutes as f64 * 60.0) / 1000.0).round() as u32)
+}
+
+fn bytes_per_hour(size_bytes: u64, length_in_minutes: i64) -> Option<u64> {
+    (length_in_minutes > 0).then(|| size_bytes * 60 / length_in_minutes as u64)
+}
+
+/// Codec name from a stored download format, else from the extension
+fn codec_name(stored_format: Option<i64>, extension: &str) -> String {
+    let stored = stored_format.map(AudioFormat::deserialize).map(|f| f.codec);
+    if let Some(codec) = stored.filter(|c| *c != Codec::Unknown) {
+        return codec.to_string().to_ascii_lowercase();
+    }
+
+    match extension {
+        "m4b" | "m4a" | "mp4" | "aac" => "aac",
+        "mp3" => "mp3",
+        "aax" => "aax",
+        "aaxc" => "aaxc",
+        "flac" => "flac",
+        "opus" => "opus",
+        "ogg" => "vorbis",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+/// Measure all liberated files
+///
+/// # Arguments
+/// * `low_bitrate_kbps` - Files at or below this bitrate are counted in
+///   `low_bitrate_files`
+/// * `cancel` - Checked before each file
+pub async fn library_stats(
+    pool: &SqlitePool,
+    low_bitrate_kbps: u32,
+    cancel: &CancellationToken,
+) -> Result<LibraryStats> {
+    let total_books = crate::storage::queries::count_books(pool).await?;
+
+    let rows = sqlx::query_as::<_, LiberatedRow>(&format!(
+        r#"
+        WITH latest AS (
+            SELECT
+                asin,
+                output_path,
+                ROW_NUMBER() OVER (PARTITION BY asin ORDER BY completed_at DESC) as rn
+            FROM DownloadTasks
+            WHERE status = 'completed' AND output_path <> ''
+        )
+        SELECT
+            b.audible_product_id as asin,
+            b.title,
+            b.length_in_minutes,
+            latest.output_path,
+            u.last_downloaded_format
+        FROM latest
+        JOIN Books b ON b.audible_product_id = latest.asin
+        LEFT JOIN UserDefinedItems u ON u.book_id = b.book_id
+        WHERE latest.rn = 1 AND {}
+        ORDER BY b.title_sort
+        "#,
+        BOOK_IN_ACTIVE_PROFILE
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let mut files = Vec::with_capacity(rows.len());
+    for row in rows {
+        cancel.check()?;
+        let extension = Path::new(&row.output_path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let size_bytes = tokio::fs::metadata(&row.output_path).await.ok().map(|m| m.len());
+
+        files.push(LiberatedFileStats {
+            codec: codec_name(row.last_downloaded_format, &extension),
+            format: extension,
+            bitrate_kbps: size_bytes.and_then(|size| average_bitrate_kbps(size, row.length_in_minutes)),
+            bytes_per_hour: size_bytes.and_then(|size| bytes_per_hour(size, row.length_in_minutes.into())),
+            size_bytes,
+            length_in_minutes: row.length_in_minutes,
+            asin: row.asin,
+            title: row.title,
+            path: row.output_path,
+        });
+    }
+
+    Ok(summarize(total_books, files, low_bitrate_kbps))
+}
+
+fn summarize(total_books: i64, files: Vec<LiberatedFileStats>, low_bitrate_kbps: u32) -> LibraryStats {
+    let mut totals: BTreeMap<(String, String), (usize, u64, i64)> = BTreeMap::new();
+    let mut stats = LibraryStats {
+        total_books,
+        liberated_books: files.len(),
+        missing_files: 0,
+        total_bytes: 0,
+        total_minutes: 0,
+        low_bitrate_kbps,
+        low_bitrate_files: 0,
+        formats: Vec::new(),
+        files: Vec::new(),
+    };
+
+    for file in &files {
+        let Some(size) = file.size_bytes else {
+            stats.missing_files += 1;
+            continue;
+        };
+        stats.total_bytes += size;
+        stats.total_minutes += i64::from(file.length_in_minutes);
+        if file.bitrate_kbps.is_some_and(|kbps| kbps <= low_bitrate_kbps) {
+            stats.low_bitrate_files += 1;
+        }
+
+        let entry = totals.entry((file.format.clone(), file.codec.clone())).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+        entry.2 += i64::from(file.length_in_minutes);
+    }
+
+    stats.formats = totals
+        .into_iter()
+        .map(|((format, codec), (file_count, total_bytes, total_minutes))| FormatTotals {
+            format,
+            codec,
+            file_count,
+            total_bytes,
+            total_minutes,
+            average_bitrate_kbps: i32::try_from(total_minutes)
+                .ok()
+                .and_then(|minutes| average_bitrate_kbps(total_bytes, minutes)),
+            bytes_per_hour: bytes_per_hour(total_bytes, total_minutes),
+        })
+        .collect();
+    stats.formats.sort_by_key(|f| std::cmp::Reverse(f.total_bytes));
+    stats.files = files;
+
+    stats
+}
+
+/// Header row of the CSV export
+const CSV_HEADER: &str = "asin,title,format,codec,size_bytes,length_in_minutes,bitrate_kbps,bytes_per_hour,path\n";
+
+/// One CSV row, without the line break
+fn file_to_csv_row(file: &LiberatedFileStats) -> String {
+    let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    let row = [
+        csv_field(&file.asin),
+        csv_field(&file.title),
+        csv_field(&file.format),
+        csv_field(&file.codec),
+        optional(file.size_bytes),
+        file.length_in_minutes.to_string(),
+        optional(file.bitrate_kbps.map(u64::from)),
+        optional(file.bytes_per_hour),
+        csv_field(&file.path),
+    ];
+    row.join(",")
+}
+
+/// Per-file report as CSV
+pub fn files_to_csv(files: &[LiberatedFileStats]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    for file in files {
+        csv.push_str(&file_to_csv_row(file));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Write the report to `output_path`
+///
+/// Files are written in chunks (see `storage::export`), reporting progress
+/// and checking `cancel` between them. The output only appears once it is
+/// complete; a failed or cancelled export leaves no partial file.
+pub async fn export_library_stats(
+    stats: &LibraryStats,
+    output_path: &Path,
+    format: StatsExportFormat,
+    on_progress: Option<ExportProgressCallback>,
+    cancel: &CancellationToken,
+) -> Result<ExportProgress> {
+    match format {
+        StatsExportFormat::Csv => {
+            write_chunked(
+                output_path,
+                CSV_HEADER,
+                &stats.files,
+                |file| Ok(format!("{}\n", file_to_csv_row(file))),
+                "",
+                "",
+                on_progress,
+                cancel,
+            )
+            .await
+        }
+        StatsExportFormat::Json => {
+            // `files` is the last field: write the rest, then the files array in chunks
+            let summary = LibraryStats { files: Vec::new(), ..stats.clone() };
+            let summary = serde_json::to_string(&summary)?;
+            let header = summary
+                .strip_suffix("[]}")
+                .map(|head| format!("{}[", head))
+                .ok_or_else(|| LibationError::invalid_input("Unexpected stats layout"))?;
+            write_chunked(
+                output_path,
+                &header,
+                &stats.files,
+                |file| Ok(serde_json::to_string(file)?),
+                ",",
+                "]}",
+                on_progress,
+                cancel,
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::{insert_book, set_book_file_path};
+    use crate::storage::{Database, NewBook};
+
+    #[tokio::test]
+    async fn test_library_stats() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let dir = tempfile::tempdir().unwrap();
+
+        // 60 minutes at 32 kbps is 14.4 MB; at 128 kbps 57.6 MB
+        let books = [
+            ("B0OLD", "Old, \"Low\" Rip", "old.m4b", 14_400_000usize),
+            ("B0NEW", "New Rip", "new.m4b", 57_600_000),
+            ("B0MP3", "Mp3 Rip", "rip.mp3", 28_800_000),
+            ("B0GONE", "Gone", "gone.m4b", 0),
+        ];
+        for (asin, title, file, size) in books {
+            let mut book = NewBook::new(asin.to_string(), title.to_string(), "us".to_string());
+            book.length_in_minutes = 60;
+            insert_book(pool, &book).await.unwrap();
+            let path = dir.path().join(file);
+            if size > 0 {
+                std::fs::File::create(&path).unwrap().set_len(size as u64).unwrap();
+            }
+            set_book_file_path(pool, asin, title, path.to_str().unwrap()).await.unwrap();
+        }
+        insert_book(pool, &NewBook::new("B0NONE".to_string(), "Not Liberated".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO UserDefinedItems (book_id, last_downloaded_format) \
+             SELECT book_id, ? FROM Books WHERE audible_product_id = 'B0NEW'",
+        )
+        .bind(AudioFormat::new(Codec::XHeAac, 128, 44100, 2).serialize())
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let stats = library_stats(pool, DEFAULT_LOW_BITRATE_KBPS, &CancellationToken::new()).await.unwrap();
+        assert_eq!((stats.total_books, stats.liberated_books, stats.missing_files), (5, 4, 1));
+        assert_eq!(stats.low_bitrate_files, 1);
+        assert_eq!(stats.total_minutes, 180);
+
+        let old = stats.files.iter().find(|f| f.asin == "B0OLD").unwrap();
+        assert_eq!((old.codec.as_str(), old.bitrate_kbps), ("aac", Some(32)));
+        assert_eq!(old.bytes_per_hour, Some(14_400_000));
+        let new = stats.files.iter().find(|f| f.asin == "B0NEW").unwrap();
+        assert_eq!((new.codec.as_str(), new.bitrate_kbps), ("xhe-aac", Some(128)));
+
+        // One total per format and codec, largest first
+        let formats: Vec<(&str, &str, usize)> =
+            stats.formats.iter().map(|f| (f.format.as_str(), f.codec.as_str(), f.file_count)).collect();
+        assert_eq!(formats, vec![("m4b", "xhe-aac", 1), ("mp3", "mp3", 1), ("m4b", "aac", 1)]);
+        assert_eq!(stats.formats[1].average_bitrate_kbps, Some(64));
+
+        let csv_path = dir.path().join("stats.csv");
+        let cancel = CancellationToken::new();
+        export_library_stats(&stats, &csv_path, "CSV".parse().unwrap(), None, &cancel).await.unwrap();
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv.contains("B0OLD,\"Old, \"\"Low\"\" Rip\",m4b,aac,14400000,60,32,14400000,"));
+        assert!(csv.contains("B0GONE,Gone,m4b,aac,,60,,,"));
+        assert!("xml".parse::<StatsExportFormat>().is_err());
+
+        let json_path = dir.path().join("stats.json");
+        let progress = export_library_stats(&stats, &json_path, StatsExportFormat::Json, None, &cancel).await.unwrap();
+        assert_eq!(progress.rows_written, 4);
+        let parsed: LibraryStats = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(parsed, stats);
+    }
+}
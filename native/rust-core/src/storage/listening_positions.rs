@@ -0,0 +1,262 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Positions and bookmarks synced with Audible (see `api::annotations`)
+//!
+//! The player's position stays where `progress` keeps it. `ListeningPositions`
+//! holds what was last agreed with Audible:
+//!
+//! - One `last_heard` row per title: the position both sides had at the
+//!   last sync, with the time it was set. A local position set after it is
+//!   waiting to be pushed (`PlaybackPosition::pending_push`).
+//! - Bookmarks, notes and clips, mirrored from Audible on each sync.
+//!
+//! When both sides moved, the position set last wins. Rows are keyed by
+//! ASIN, so they outlive a library clear like receipts do.
+
+use crate::api::annotations::{Annotation, AnnotationKind, RemotePosition};
+use crate::error::{LibationError, Result};
+use crate::storage::dates::{format_timestamp, now, parse_timestamp};
+use crate::storage::progress::set_listening_position_at;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+
+/// A title's playback position and its sync state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackPosition {
+    pub asin: String,
+    /// Local position (0 if never played)
+    pub position_ms: i64,
+    /// When the local position was set
+    pub updated_at: Option<String>,
+    /// When it was last fetched from or pushed to Audible
+    pub synced_at: Option<String>,
+    /// Set locally after the last sync
+    pub pending_push: bool,
+}
+
+/// A stored bookmark, note or clip
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct StoredAnnotation {
+    pub asin: String,
+    pub kind: AnnotationKind,
+    pub annotation_id: String,
+    pub position_ms: i64,
+    pub end_position_ms: Option<i64>,
+    pub note: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Playback position of a title with its sync state
+///
+/// # Errors
+/// RecordNotFound if no book has this ASIN
+pub async fn get_playback_position(pool: &SqlitePool, asin: &str) -> Result<PlaybackPosition> {
+    let (position_ms, updated_at, synced_updated_at, synced_at): (
+        Option<i64>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    ) = sqlx::query_as(
+        r#"
+        SELECT u.position_ms, u.progress_updated_at, lp.updated_at, lp.synced_at
+        FROM Books b
+        LEFT JOIN UserDefinedItems u ON u.book_id = b.book_id
+        LEFT JOIN ListeningPositions lp
+            ON lp.asin = b.audible_product_id AND lp.kind = 'last_heard' AND lp.annotation_id = ''
+        WHERE b.audible_product_id = ?
+        "#,
+    )
+    .bind(asin)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+
+    let local = updated_at.as_deref().and_then(parse_timestamp);
+    let synced = synced_updated_at.as_deref().and_then(parse_timestamp);
+    let pending_push = match (local, synced) {
+        (Some(local), Some(synced)) => local > synced,
+        (Some(_), None) => position_ms.is_some(),
+        (None, _) => false,
+    };
+
+    Ok(PlaybackPosition {
+        asin: asin.to_string(),
+        position_ms: position_ms.unwrap_or(0),
+        updated_at,
+        synced_at,
+        pending_push,
+    })
+}
+
+/// Record that Audible has `position_ms`, set at `updated_at`
+pub async fn record_synced_position(pool: &SqlitePool, asin: &str, position_ms: i64, updated_at: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO ListeningPositions (asin, kind, annotation_id, position_ms, updated_at, synced_at)
+        VALUES (?, 'last_heard', '', ?, ?, ?)
+        ON CONFLICT(asin, kind, annotation_id) DO UPDATE SET
+            position_ms = excluded.position_ms,
+            updated_at = excluded.updated_at,
+            synced_at = excluded.synced_at
+        "#,
+    )
+    .bind(asin)
+    .bind(position_ms)
+    .bind(updated_at)
+    .bind(now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Take Audible's position when it was set after the local one
+///
+/// # Returns
+/// Whether the local position was replaced
+pub async fn apply_remote_position(pool: &SqlitePool, remote: &RemotePosition) -> Result<bool> {
+    let local = get_playback_position(pool, &remote.asin).await?;
+    let local_at = local.updated_at.as_deref().and_then(parse_timestamp);
+    let remote_at = format_timestamp(remote.updated_at);
+
+    if local_at.is_some_and(|at| at >= remote.updated_at) {
+        // Same position on both sides: nothing left to push
+        if local_at == Some(remote.updated_at) || local.position_ms == remote.position_ms {
+            record_synced_position(pool, &remote.asin, remote.position_ms, local.updated_at.as_deref().unwrap_or(&remote_at))
+                .await?;
+        }
+        return Ok(false);
+    }
+
+    set_listening_position_at(pool, &remote.asin, remote.position_ms, &remote_at).await?;
+    record_synced_position(pool, &remote.asin, remote.position_ms, &remote_at).await?;
+    Ok(true)
+}
+
+/// Replace a title's stored bookmarks, notes and clips
+///
+/// The last-heard record is ignored; positions go through
+/// `apply_remote_position`.
+pub async fn replace_annotations(pool: &SqlitePool, asin: &str, annotations: &[Annotation]) -> Result<()> {
+    let synced_at = now();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM ListeningPositions WHERE asin = ? AND kind <> 'last_heard'")
+        .bind(asin)
+        .execute(&mut *tx)
+        .await?;
+    for annotation in annotations.iter().filter(|a| a.kind != AnnotationKind::LastHeard) {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO ListeningPositions
+                (asin, kind, annotation_id, position_ms, end_position_ms, note, updated_at, synced_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(asin)
+        .bind(annotation.kind)
+        .bind(&annotation.annotation_id)
+        .bind(annotation.position_ms)
+        .bind(annotation.end_position_ms)
+        .bind(&annotation.note)
+        .bind(annotation.updated_at.map(format_timestamp))
+        .bind(&synced_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// A title's bookmarks, notes and clips in playback order
+pub async fn list_annotations(pool: &SqlitePool, asin: &str) -> Result<Vec<StoredAnnotation>> {
+    let annotations = sqlx::query_as::<_, StoredAnnotation>(
+        r#"
+        SELECT asin, kind, annotation_id, position_ms, end_position_ms, note, updated_at
+        FROM ListeningPositions
+        WHERE asin = ? AND kind <> 'last_heard'
+        ORDER BY position_ms, annotation_id
+        "#,
+    )
+    .bind(asin)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::progress::set_listening_position;
+    use crate::storage::{queries::insert_book, Database, NewBook};
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn test_position_sync_state() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0DUNE".to_string(), "Dune".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+
+        let position = get_playback_position(pool, "B0DUNE").await.unwrap();
+        assert_eq!((position.position_ms, position.pending_push), (0, false));
+
+        // Played here, not yet pushed
+        set_listening_position(pool, "B0DUNE", 60_000).await.unwrap();
+        let position = get_playback_position(pool, "B0DUNE").await.unwrap();
+        assert!(position.pending_push);
+
+        // An older remote position doesn't replace it
+        let older = RemotePosition {
+            asin: "B0DUNE".to_string(),
+            position_ms: 10_000,
+            updated_at: Utc::now() - Duration::hours(1),
+        };
+        assert!(!apply_remote_position(pool, &older).await.unwrap());
+        assert_eq!(get_playback_position(pool, "B0DUNE").await.unwrap().position_ms, 60_000);
+
+        // Pushing records it
+        record_synced_position(pool, "B0DUNE", 60_000, position.updated_at.as_deref().unwrap()).await.unwrap();
+        assert!(!get_playback_position(pool, "B0DUNE").await.unwrap().pending_push);
+
+        // A newer remote position wins and isn't pushed back
+        let newer = RemotePosition {
+            asin: "B0DUNE".to_string(),
+            position_ms: 900_000,
+            updated_at: Utc::now() + Duration::minutes(1),
+        };
+        assert!(apply_remote_position(pool, &newer).await.unwrap());
+        let position = get_playback_position(pool, "B0DUNE").await.unwrap();
+        assert_eq!((position.position_ms, position.pending_push), (900_000, false));
+
+        let bookmark = |id: &str, position_ms| Annotation {
+            kind: AnnotationKind::Bookmark,
+            annotation_id: id.to_string(),
+            position_ms,
+            end_position_ms: None,
+            note: None,
+            updated_at: None,
+        };
+        replace_annotations(pool, "B0DUNE", &[bookmark("b2", 5_000), bookmark("b1", 1_000)]).await.unwrap();
+        replace_annotations(pool, "B0DUNE", &[bookmark("b2", 5_000), bookmark("b3", 3_000)]).await.unwrap();
+        let ids: Vec<String> = list_annotations(pool, "B0DUNE").await.unwrap().into_iter().map(|a| a.annotation_id).collect();
+        assert_eq!(ids, vec!["b3", "b2"]);
+        assert_eq!(get_playback_position(pool, "B0DUNE").await.unwrap().position_ms, 900_000);
+    }
+}
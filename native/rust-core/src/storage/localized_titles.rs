@@ -0,0 +1,209 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Stored titles from a preferred metadata marketplace (see `api::localized`)
+//!
+//! Books keep the title of the marketplace they were bought in. When the
+//! `metadata.preferred_locale` setting names another marketplace, titles
+//! fetched from it are stored in `LocalizedTitles` and list queries with
+//! `BookQueryParams::title_locale` set return them instead, falling back to
+//! the original title for books that marketplace doesn't sell. Searches match
+//! either title.
+
+use crate::api::auth::Locale;
+use crate::api::localized::LocalizedTitle;
+use crate::error::{LibationError, Result};
+use crate::storage::normalize::{title_search_key, title_sort_key};
+use crate::storage::settings::{delete_setting, get_setting, set_setting};
+use sqlx::{Row, SqlitePool};
+
+const KEY_PREFERRED_LOCALE: &str = "metadata.preferred_locale";
+
+/// Preferred metadata marketplace country code (None: use each book's own)
+pub async fn get_preferred_metadata_locale(pool: &SqlitePool) -> Result<Option<String>> {
+    get_setting(pool, KEY_PREFERRED_LOCALE).await
+}
+
+/// Set or clear (None) the preferred metadata marketplace
+///
+/// # Errors
+/// InvalidInput if the country code isn't a supported marketplace
+pub async fn set_preferred_metadata_locale(pool: &SqlitePool, country_code: Option<&str>) -> Result<()> {
+    match country_code {
+        Some(code) => {
+            let locale = Locale::from_country_code(code)
+                .ok_or_else(|| LibationError::invalid_input(format!("Unsupported marketplace: {}", code)))?;
+            set_setting(pool, KEY_PREFERRED_LOCALE, &locale.country_code).await
+        }
+        None => delete_setting(pool, KEY_PREFERRED_LOCALE).await,
+    }
+}
+
+/// Save fetched titles, replacing earlier ones for the same marketplace
+///
+/// # Returns
+/// Number saved (titles of books not in the database are skipped)
+pub async fn save_localized_titles(pool: &SqlitePool, titles: &[LocalizedTitle]) -> Result<usize> {
+    let fetched_at = crate::storage::dates::now();
+    let mut tx = pool.begin().await?;
+    let mut saved = 0;
+
+    for entry in titles {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO LocalizedTitles (book_id, locale, title, subtitle, title_sort, title_search, fetched_at)
+            SELECT book_id, ?, ?, ?, ?, ?, ? FROM Books WHERE audible_product_id = ?
+            ON CONFLICT(book_id, locale)
+            DO UPDATE SET title = excluded.title,
+                          subtitle = excluded.subtitle,
+                          title_sort = excluded.title_sort,
+                          title_search = excluded.title_search,
+                          fetched_at = excluded.fetched_at
+            "#,
+        )
+        .bind(&entry.locale)
+        .bind(&entry.title)
+        .bind(&entry.subtitle)
+        .bind(entry.title.as_deref().map(title_sort_key))
+        .bind(entry.title.as_deref().map(|t| title_search_key(t, entry.subtitle.as_deref())))
+        .bind(&fetched_at)
+        .bind(&entry.asin)
+        .execute(&mut *tx)
+        .await?;
+        saved += result.rows_affected() as usize;
+    }
+
+    tx.commit().await?;
+    Ok(saved)
+}
+
+/// ASINs of Audible books with no stored title for a marketplace
+///
+/// Books bought in that marketplace already carry its title and are skipped.
+pub async fn list_books_missing_localized_title(pool: &SqlitePool, locale: &str, limit: i64) -> Result<Vec<String>> {
+    let asins = sqlx::query_scalar(
+        r#"
+        SELECT b.audible_product_id FROM Books b
+        WHERE COALESCE(b.source, 'audible') = 'audible'
+          AND b.locale <> ?1
+          AND NOT EXISTS (SELECT 1 FROM LocalizedTitles lt WHERE lt.book_id = b.book_id AND lt.locale = ?1)
+        ORDER BY b.book_id
+        LIMIT ?2
+        "#,
+    )
+    .bind(locale)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(asins)
+}
+
+/// All stored marketplace titles of a book
+pub async fn get_localized_titles(pool: &SqlitePool, asin: &str) -> Result<Vec<LocalizedTitle>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT lt.locale, lt.title, lt.subtitle
+        FROM LocalizedTitles lt
+        JOIN Books b ON b.book_id = lt.book_id
+        WHERE b.audible_product_id = ?
+        ORDER BY lt.locale
+        "#,
+    )
+    .bind(asin)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(LocalizedTitle {
+                asin: asin.to_string(),
+                locale: row.try_get("locale")?,
+                title: row.try_get("title")?,
+                subtitle: row.try_get("subtitle")?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::{insert_book, list_books_with_filters, count_books_with_filters, BookQueryParams};
+    use crate::storage::{Database, NewBook, SortField, SortDirection};
+
+    fn title(asin: &str, title: Option<&str>) -> LocalizedTitle {
+        LocalizedTitle {
+            asin: asin.to_string(),
+            locale: "us".to_string(),
+            title: title.map(String::from),
+            subtitle: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preferred_titles() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        for (asin, name) in [("B0MARS", "Der Marsianer"), ("B0LOCAL", "Die Vermessung der Welt"), ("B0US", "Artemis")] {
+            let locale = if asin == "B0US" { "us" } else { "de" };
+            insert_book(pool, &NewBook::new(asin.to_string(), name.to_string(), locale.to_string()))
+                .await
+                .unwrap();
+        }
+
+        assert!(set_preferred_metadata_locale(pool, Some("xx")).await.is_err());
+        set_preferred_metadata_locale(pool, Some("US")).await.unwrap();
+        let locale = get_preferred_metadata_locale(pool).await.unwrap().unwrap();
+        assert_eq!(locale, "us");
+
+        assert_eq!(list_books_missing_localized_title(pool, &locale, 10).await.unwrap(), vec!["B0MARS", "B0LOCAL"]);
+        let saved = save_localized_titles(
+            pool,
+            &[title("B0MARS", Some("The Martian")), title("B0LOCAL", None), title("B0UNKNOWN", Some("X"))],
+        )
+        .await
+        .unwrap();
+        assert_eq!(saved, 2);
+        assert!(list_books_missing_localized_title(pool, &locale, 10).await.unwrap().is_empty());
+
+        let mut params = BookQueryParams {
+            limit: 10,
+            sort_field: Some(SortField::Title),
+            sort_direction: Some(SortDirection::Asc),
+            title_locale: Some(locale),
+            ..Default::default()
+        };
+        let books = list_books_with_filters(pool, &params).await.unwrap();
+        let titles: Vec<_> = books.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["Artemis", "Die Vermessung der Welt", "The Martian"]);
+        assert_eq!(books[2].alternate_title.as_deref(), Some("Der Marsianer"));
+        assert_eq!(books[1].alternate_title, None);
+
+        // Either title matches a search
+        params.search_query = Some("marsianer".to_string());
+        assert_eq!(list_books_with_filters(pool, &params).await.unwrap()[0].title, "The Martian");
+        params.search_query = Some("martian".to_string());
+        assert_eq!(count_books_with_filters(pool, &params).await.unwrap(), 1);
+
+        params.title_locale = None;
+        params.search_query = None;
+        let books = list_books_with_filters(pool, &params).await.unwrap();
+        assert_eq!(books[1].title, "Der Marsianer");
+
+        assert_eq!(get_localized_titles(pool, "B0MARS").await.unwrap()[0].title.as_deref(), Some("The Martian"));
+    }
+}
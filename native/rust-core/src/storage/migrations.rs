@@ -32,13 +32,24 @@
 //! we implement migrations as runtime SQL execution for mobile compatibility.
 
 use crate::error::Result;
+use crate::storage::dates::{normalize_date, normalize_timestamp, DATE_COLUMNS, TIMESTAMP_COLUMNS};
+use crate::storage::normalize::{canonical_asin, title_search_key, title_sort_key};
+use crate::storage::series_order::parse_sequence;
+use crate::storage::tags::{parse_legacy_tags, replace_book_tags};
 use sqlx::{Executor, SqlitePool};
 
 /// Run all database migrations
 ///
 /// This function creates the database schema and applies any pending migrations.
-/// Migrations are tracked in the `_migrations` table.
+/// Migrations are tracked in the `_migrations` table. Empty databases get
+/// the latest schema in one step instead (see `storage::bootstrap`).
 pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
+    crate::storage::bootstrap::bootstrap_schema(pool).await?;
+    apply_migrations(pool).await
+}
+
+/// Apply pending migrations one by one
+pub(crate) async fn apply_migrations(pool: &SqlitePool) -> Result<()> {
     // Create migrations tracking table
     create_migrations_table(pool).await?;
 
@@ -48,6 +59,49 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     run_migration(pool, 3, "accounts", create_accounts_table(pool)).await?;
     run_migration(pool, 4, "download_conversion_columns", add_download_conversion_columns(pool)).await?;
     run_migration(pool, 5, "add_source_column", add_source_column(pool)).await?;
+    run_migration(pool, 6, "add_download_format_column", add_download_format_column(pool)).await?;
+    run_migration(pool, 7, "add_chunk_manifest_column", add_chunk_manifest_column(pool)).await?;
+    run_migration(pool, 8, "add_benefit_type_column", add_benefit_type_column(pool)).await?;
+    run_migration(pool, 9, "add_title_sort_columns", add_title_sort_columns(pool)).await?;
+    run_migration(pool, 10, "add_range_filter_indexes", add_range_filter_indexes(pool)).await?;
+    run_migration(pool, 11, "tags_tables", create_tags_tables(pool)).await?;
+    run_migration(pool, 12, "download_usage", create_download_usage_tables(pool)).await?;
+    run_migration(pool, 13, "book_chapters", create_book_chapters_table(pool)).await?;
+    run_migration(pool, 14, "add_sha256_column", add_sha256_column(pool)).await?;
+    run_migration(pool, 15, "sync_issues", create_sync_issues_table(pool)).await?;
+    run_migration(pool, 16, "read_along_mappings", create_read_along_table(pool)).await?;
+    run_migration(pool, 17, "settings", create_settings_table(pool)).await?;
+    run_migration(pool, 18, "account_token_tracking", add_account_tracking_columns(pool)).await?;
+    run_migration(pool, 19, "localized_titles", create_localized_titles_table(pool)).await?;
+    run_migration(pool, 20, "validation_issues", create_validation_issues_table(pool)).await?;
+    run_migration(pool, 21, "download_cdn_columns", add_cdn_columns(pool)).await?;
+    run_migration(pool, 22, "add_format_support_column", add_format_support_column(pool)).await?;
+    run_migration(pool, 23, "listening_progress_columns", add_listening_progress_columns(pool)).await?;
+    run_migration(pool, 24, "narration_flag_columns", add_narration_flag_columns(pool)).await?;
+    run_migration(pool, 25, "pending_token_refreshes", create_pending_token_refreshes_table(pool)).await?;
+    run_migration(pool, 26, "file_integrity", create_file_integrity_table(pool)).await?;
+    run_migration(pool, 27, "download_buffering_columns", add_download_buffering_columns(pool)).await?;
+    run_migration(pool, 28, "utc_timestamps", normalize_date_columns(pool)).await?;
+    run_migration(pool, 29, "profiles", create_profiles(pool)).await?;
+    run_migration(pool, 30, "jobs", create_jobs(pool)).await?;
+    run_migration(pool, 31, "notes", create_notes(pool)).await?;
+    run_migration(pool, 32, "liberation_receipts", create_liberation_receipts(pool)).await?;
+    run_migration(pool, 33, "offline_licenses", create_offline_licenses(pool)).await?;
+    run_migration(pool, 34, "download_progress_epoch", add_progress_epoch_column(pool)).await?;
+    run_migration(pool, 35, "download_trace_id", add_download_trace_id_column(pool)).await?;
+    run_migration(pool, 36, "book_output_root", add_output_root_column(pool)).await?;
+    run_migration(pool, 37, "book_files", create_book_files(pool)).await?;
+    run_migration(pool, 38, "series_sort_key", add_series_sort_key(pool)).await?;
+    run_migration(pool, 39, "books_search", create_books_search(pool)).await?;
+    run_migration(pool, 40, "listening_positions", create_listening_positions(pool)).await?;
+    run_migration(pool, 41, "download_network_columns", add_download_network_columns(pool)).await?;
+    run_migration(pool, 42, "podcast_episodes", create_podcast_episodes(pool)).await?;
+    run_migration(pool, 43, "book_cover_path", add_book_cover_path(pool)).await?;
+    run_migration(pool, 44, "download_follow_up", add_download_follow_up(pool)).await?;
+    run_migration(pool, 45, "library_book_accounts", create_library_book_accounts(pool)).await?;
+    run_migration(pool, 46, "merge_duplicate_asins", merge_duplicate_books(pool)).await?;
+    run_migration(pool, 47, "job_owner", add_job_owner(pool)).await?;
+    run_migration(pool, 48, "book_file_sha256", add_book_file_sha256(pool)).await?;
 
     Ok(())
 }
@@ -366,17 +420,51 @@ mod tests {
         let expected_tables = vec![
             "Accounts",
             "BookCategories",
+            "BookChapters",
             "BookContributors",
+            "BookFiles",
+            "BookTags",
             "Books",
+            "BooksSearch",
+            "BooksSearch_config",
+            "BooksSearch_content",
+            "BooksSearch_data",
+            "BooksSearch_docsize",
+            "BooksSearch_idx",
             "Categories",
             "CategoryLadders",
             "Contributors",
+            "DownloadQuotas",
             "DownloadTasks",
+            "DownloadUsage",
+            "FileIntegrity",
+            "JobArtifacts",
+            "Jobs",
+            "LiberationReceiptFiles",
+            "LiberationReceipts",
+            "LibraryBookAccounts",
             "LibraryBooks",
+            "ListeningPositions",
+            "LocalizedTitles",
+            "Notes",
+            "NotesSearch",
+            "NotesSearch_config",
+            "NotesSearch_data",
+            "NotesSearch_docsize",
+            "NotesSearch_idx",
+            "OfflineLicenses",
+            "PendingTokenRefreshes",
+            "PodcastEpisodes",
+            "Profiles",
+            "ReadAlongMappings",
             "Series",
             "SeriesBooks",
+            "Settings",
             "Supplements",
+            "SyncIssues",
+            "Tags",
             "UserDefinedItems",
+            "ValidationIssues",
         ];
 
         assert_eq!(tables, expected_tables, "Missing or extra tables");
@@ -410,6 +498,175 @@ mod tests {
 
         assert_eq!(fk_enabled, 1, "Foreign keys not enabled");
     }
+
+    #[tokio::test]
+    async fn test_tags_migration_imports_legacy_column() {
+        use crate::storage::{queries, NewBook, NewUserDefinedItem};
+
+        let db = Database::new_in_memory()
+            .await
+            .expect("Failed to create database");
+        let book = NewBook::new("B0TAGMIG1".to_string(), "Tagged".to_string(), "us".to_string());
+        let book_id = queries::insert_book(db.pool(), &book).await.unwrap();
+        queries::insert_user_defined_item(db.pool(), &NewUserDefinedItem::new(book_id)).await.unwrap();
+        sqlx::query("UPDATE UserDefinedItems SET tags = 'favorite  To_Read' WHERE book_id = ?")
+            .bind(book_id)
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        create_tags_tables(db.pool()).await.expect("Migration failed");
+
+        let tags = crate::storage::tags::get_book_tags(db.pool(), book_id).await.unwrap();
+        assert_eq!(tags, vec!["favorite", "to_read"]);
+    }
+
+    #[tokio::test]
+    async fn test_date_columns_migrate_to_utc() {
+        use crate::storage::{queries, NewBook, NewLibraryBook};
+
+        let db = Database::new_in_memory()
+            .await
+            .expect("Failed to create database");
+        let book = NewBook::new("B0DATEMIG".to_string(), "Dated".to_string(), "us".to_string());
+        let book_id = queries::insert_book(db.pool(), &book).await.unwrap();
+        queries::insert_library_book(
+            db.pool(),
+            &NewLibraryBook { book_id, account: "test@example.com".to_string() },
+        )
+        .await
+        .unwrap();
+
+        // New rows are written in UTC ISO 8601, defaults included
+        let (created_at, updated_at): (String, String) =
+            sqlx::query_as("SELECT created_at, updated_at FROM Books WHERE book_id = ?")
+                .bind(book_id)
+                .fetch_one(db.pool())
+                .await
+                .unwrap();
+        assert!(created_at.ends_with('Z') && created_at.contains('T'), "{}", created_at);
+        assert_eq!(crate::storage::dates::normalize_timestamp(&updated_at), Some(updated_at));
+
+        // Rows written in the older shapes are rewritten
+        sqlx::query(
+            "UPDATE LibraryBooks SET date_added = '2023-07-04T01:00:00+02:00' WHERE book_id = ?",
+        )
+        .bind(book_id)
+        .execute(db.pool())
+        .await
+        .unwrap();
+        sqlx::query(
+            "UPDATE Books SET date_published = '2020-05-01T00:00:00+02:00', created_at = '2020-05-02 08:00:00' \
+             WHERE book_id = ?",
+        )
+        .bind(book_id)
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        normalize_date_columns(db.pool()).await.expect("Migration failed");
+
+        let (date_added,): (String,) = sqlx::query_as("SELECT date_added FROM LibraryBooks WHERE book_id = ?")
+            .bind(book_id)
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(date_added, "2023-07-03T23:00:00.000Z");
+        let (published, created_at): (String, String) =
+            sqlx::query_as("SELECT date_published, created_at FROM Books WHERE book_id = ?")
+                .bind(book_id)
+                .fetch_one(db.pool())
+                .await
+                .unwrap();
+        assert_eq!(published, "2020-05-01");
+        assert_eq!(created_at, "2020-05-02T08:00:00.000Z");
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_asins_are_merged() {
+        use crate::storage::{queries, NewBook, NewUserDefinedItem};
+
+        let db = Database::new_in_memory()
+            .await
+            .expect("Failed to create database");
+        let pool = db.pool();
+        // Databases from before the unique index
+        pool.execute("DROP INDEX idx_books_asin_key").await.unwrap();
+
+        // Spellings the column's own UNIQUE constraint lets through
+        let mut ids = Vec::new();
+        for asin in ["b0dupe0001", "B0DUPE0001 ", "B0DUPE0001", "librivox_7"] {
+            let book = NewBook::new(format!("B0SEED{}", ids.len()), asin.to_string(), "us".to_string());
+            let book_id = queries::insert_book(pool, &book).await.unwrap();
+            sqlx::query("UPDATE Books SET audible_product_id = ? WHERE book_id = ?")
+                .bind(asin)
+                .bind(book_id)
+                .execute(pool)
+                .await
+                .unwrap();
+            queries::insert_user_defined_item(pool, &NewUserDefinedItem::new(book_id)).await.unwrap();
+            ids.push(book_id);
+        }
+
+        // Downloaded through the first copy, progress and a rating on the second
+        sqlx::query(
+            "UPDATE UserDefinedItems SET book_status = 1, last_downloaded = '2024-01-02T00:00:00.000Z' WHERE book_id = ?",
+        )
+        .bind(ids[0])
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "UPDATE UserDefinedItems SET progress_percent = 40, progress_updated_at = '2024-02-01T00:00:00.000Z', \
+             user_rating_overall = 4, is_finished = 1 WHERE book_id = ?",
+        )
+        .bind(ids[1])
+        .execute(pool)
+        .await
+        .unwrap();
+        crate::storage::tags::set_book_tags(pool, ids[1], &["favorite".to_string()]).await.unwrap();
+        sqlx::query(
+            "INSERT INTO OfflineLicenses (asin, quality, file_type, license, total_bytes, fetched_at) \
+             VALUES ('b0dupe0001', 'High', 'aaxc', '{}', 1, '2024-01-01T00:00:00.000Z')",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        merge_duplicate_books(pool).await.expect("Migration failed");
+
+        let books: Vec<(i64, String)> =
+            sqlx::query_as("SELECT book_id, audible_product_id FROM Books ORDER BY book_id")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        // The copy already stored canonically is kept
+        assert_eq!(books, vec![(ids[2], "B0DUPE0001".to_string()), (ids[3], "librivox_7".to_string())]);
+
+        let (status, progress, rating, finished): (i64, f64, f64, bool) = sqlx::query_as(
+            "SELECT book_status, progress_percent, user_rating_overall, is_finished FROM UserDefinedItems WHERE book_id = ?",
+        )
+        .bind(ids[2])
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!((status, progress, rating, finished), (1, 40.0, 4.0, true));
+        let tags = crate::storage::tags::get_book_tags(pool, ids[2]).await.unwrap();
+        assert_eq!(tags, vec!["favorite"]);
+        let licenses: Vec<String> = sqlx::query_scalar("SELECT asin FROM OfflineLicenses")
+            .fetch_all(pool)
+            .await
+            .unwrap();
+        assert_eq!(licenses, vec!["B0DUPE0001"]);
+
+        // The unique index keeps variants out
+        let variant = NewBook::new("b0dupe0001".to_string(), "Again".to_string(), "us".to_string());
+        assert!(queries::insert_book(pool, &variant).await.is_err());
+        sqlx::query("INSERT INTO Books (audible_product_id, title, length_in_minutes, locale) VALUES (' b0dupe0001', 'x', 0, 'us')")
+            .execute(pool)
+            .await
+            .expect_err("Duplicate ASIN inserted");
+    }
 }
 
 /// Create download_tasks table for Download Manager
@@ -559,3 +816,1429 @@ async fn add_source_column(pool: &SqlitePool) -> Result<()> {
 
     Ok(())
 }
+
+/// Add download_format column to Books table
+///
+/// Records which download path (aax, aaxc, dash, mp3) was negotiated the
+/// last time a license was fetched for the book.
+async fn add_download_format_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Books')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"download_format".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN download_format TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add chunk_manifest column to DownloadTasks table
+///
+/// Stores the per-chunk hash manifest (JSON) used to verify partial files
+/// on resume. NULL when chunk hashing is disabled for the task.
+async fn add_chunk_manifest_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"chunk_manifest".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN chunk_manifest TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add benefit_type column to Books table
+///
+/// Records how the title was acquired (purchase, subscription, gift, loan)
+/// so restricted licenses can be flagged and refused for liberation.
+async fn add_benefit_type_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Books')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"benefit_type".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN benefit_type TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add title_sort and title_search columns to Books table
+///
+/// Keys are computed in Rust (see `storage::normalize`), so existing rows
+/// are backfilled here and new rows get them at import time.
+async fn add_title_sort_columns(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Books')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"title_sort".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN title_sort TEXT").await?;
+    }
+    if !columns.contains(&"title_search".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN title_search TEXT").await?;
+    }
+
+    let books: Vec<(i64, String, Option<String>)> =
+        sqlx::query_as("SELECT book_id, title, subtitle FROM Books")
+            .fetch_all(pool)
+            .await?;
+
+    let mut tx = pool.begin().await?;
+    for (book_id, title, subtitle) in books {
+        sqlx::query("UPDATE Books SET title_sort = ?, title_search = ? WHERE book_id = ?")
+            .bind(title_sort_key(&title))
+            .bind(title_search_key(&title, subtitle.as_deref()))
+            .bind(book_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    pool.execute("CREATE INDEX IF NOT EXISTS idx_books_title_sort ON Books(title_sort)").await?;
+
+    Ok(())
+}
+
+/// Index columns used by release date and runtime range filters
+///
+/// `LibraryBooks.date_added` (purchase date) is already indexed.
+async fn add_range_filter_indexes(pool: &SqlitePool) -> Result<()> {
+    pool.execute("CREATE INDEX IF NOT EXISTS idx_books_date_published ON Books(date_published)").await?;
+    pool.execute("CREATE INDEX IF NOT EXISTS idx_books_length ON Books(length_in_minutes)").await?;
+
+    Ok(())
+}
+
+/// Create Tags/BookTags and import the space-delimited tags column
+///
+/// `UserDefinedItems.tags` stays as a mirror (see `storage::tags`).
+async fn create_tags_tables(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS Tags (
+            tag_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE  -- lowercase, alphanumeric + underscore
+        );
+
+        CREATE TABLE IF NOT EXISTS BookTags (
+            book_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (book_id, tag_id),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES Tags(tag_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_book_tags_tag ON BookTags(tag_id);
+        "#,
+    )
+    .await?;
+
+    let items: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT book_id, tags FROM UserDefinedItems WHERE tags IS NOT NULL AND tags != ''"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut tx = pool.begin().await?;
+    for (book_id, tags) in items {
+        let tags = parse_legacy_tags(&tags);
+        replace_book_tags(&mut tx, book_id, &tags).await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Create DownloadUsage/DownloadQuotas and record each task's account
+///
+/// See `download::quota`.
+async fn create_download_usage_tables(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS DownloadUsage (
+            account TEXT NOT NULL,
+            month TEXT NOT NULL,  -- UTC, YYYY-MM
+            bytes_downloaded INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (account, month)
+        );
+
+        CREATE TABLE IF NOT EXISTS DownloadQuotas (
+            account TEXT PRIMARY KEY,
+            monthly_cap_bytes INTEGER NOT NULL,
+            action TEXT NOT NULL DEFAULT 'warn'  -- "warn" or "block"
+        );
+        "#,
+    )
+    .await?;
+
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"account".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN account TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Create BookChapters for edited chapter lists (see `storage::chapters`)
+async fn create_book_chapters_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS BookChapters (
+            book_id INTEGER NOT NULL,
+            chapter_index INTEGER NOT NULL,  -- 0-based position
+            title TEXT NOT NULL,
+            start_ms INTEGER NOT NULL,
+            end_ms INTEGER NOT NULL,
+            PRIMARY KEY (book_id, chapter_index),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add sha256 column to DownloadTasks
+///
+/// Hex SHA-256 of the downloaded file, computed while streaming.
+async fn add_sha256_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"sha256".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN sha256 TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Create SyncIssues for per-item library sync failures (see `storage::sync_issues`)
+async fn create_sync_issues_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS SyncIssues (
+            issue_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account TEXT NOT NULL,
+            asin TEXT NOT NULL,
+            stage TEXT NOT NULL,  -- "contributor", "series" or "book"
+            code TEXT NOT NULL,
+            message TEXT NOT NULL,
+            occurrences INTEGER NOT NULL DEFAULT 1,
+            first_seen TEXT NOT NULL,
+            last_seen TEXT NOT NULL,
+            resolved_at TEXT  -- NULL while unresolved
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_sync_issues_open ON SyncIssues(account, asin) WHERE resolved_at IS NULL;
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Create ReadAlongMappings for Whispersync for Voice data (see `storage::read_along`)
+async fn create_read_along_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS ReadAlongMappings (
+            book_id INTEGER PRIMARY KEY,
+            companion_asin TEXT,  -- Kindle edition, NULL if none
+            sync_points TEXT NOT NULL DEFAULT '[]',  -- JSON array, empty when unavailable
+            fetched_at TEXT NOT NULL,
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Create Settings, a key/value store for app settings (see `storage::settings`)
+async fn create_settings_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS Settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add device registration, refresh token and API activity timestamps to Accounts
+///
+/// Existing accounts take their creation time as registration time.
+async fn add_account_tracking_columns(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Accounts')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for column in ["registered_at", "refresh_token_issued_at", "last_api_success_at"] {
+        if !columns.iter().any(|c| c == column) {
+            pool.execute(format!("ALTER TABLE Accounts ADD COLUMN {} TEXT", column).as_str())
+                .await?;
+        }
+    }
+
+    pool.execute(
+        "UPDATE Accounts SET registered_at = COALESCE(registered_at, created_at), \
+         refresh_token_issued_at = COALESCE(refresh_token_issued_at, created_at)"
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Create LocalizedTitles, product titles from other marketplaces
+/// (see `storage::localized_titles`)
+async fn create_localized_titles_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS LocalizedTitles (
+            book_id INTEGER NOT NULL,
+            locale TEXT NOT NULL,  -- Marketplace country code
+            title TEXT,  -- NULL if the marketplace doesn't sell the book
+            subtitle TEXT,
+            title_sort TEXT,
+            title_search TEXT,
+            fetched_at TEXT NOT NULL,
+            PRIMARY KEY (book_id, locale),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Create ValidationIssues, metadata problems found by the validation pass
+/// (see `storage::validation`)
+async fn create_validation_issues_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS ValidationIssues (
+            book_id INTEGER NOT NULL,
+            check_code TEXT NOT NULL,  -- e.g. "missing_authors", "zero_runtime"
+            severity TEXT NOT NULL,  -- "error", "warning" or "info"
+            message TEXT NOT NULL,
+            detected_at TEXT NOT NULL,
+            PRIMARY KEY (book_id, check_code),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add mirror_urls and cdn_host columns to DownloadTasks
+///
+/// mirror_urls is a JSON array of alternate CDN URLs for the same file;
+/// cdn_host is the host that served (or is serving) the download.
+async fn add_cdn_columns(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"mirror_urls".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN mirror_urls TEXT").await?;
+    }
+    if !columns.contains(&"cdn_host".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN cdn_host TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add format_support column to Books table
+///
+/// Whether the title's listed formats can be liberated (see
+/// `models::FormatSupport`), set at sync time.
+async fn add_format_support_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Books')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"format_support".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN format_support TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add listening progress columns to UserDefinedItems
+///
+/// progress_percent is materialized from position_ms and the book's length
+/// whenever a position is written (see `progress`), and indexed so "in
+/// progress" lists don't need to compute it.
+async fn add_listening_progress_columns(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('UserDefinedItems')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"position_ms".to_string()) {
+        pool.execute("ALTER TABLE UserDefinedItems ADD COLUMN position_ms INTEGER").await?;
+    }
+    if !columns.contains(&"progress_percent".to_string()) {
+        pool.execute("ALTER TABLE UserDefinedItems ADD COLUMN progress_percent REAL NOT NULL DEFAULT 0").await?;
+    }
+    if !columns.contains(&"progress_updated_at".to_string()) {
+        pool.execute("ALTER TABLE UserDefinedItems ADD COLUMN progress_updated_at TEXT").await?;
+    }
+
+    pool.execute(
+        "CREATE INDEX IF NOT EXISTS idx_user_items_progress ON UserDefinedItems(progress_percent, progress_updated_at)"
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add narrated_by_author and full_cast columns to Books table
+///
+/// Both are derived from the contributor lists at sync time; existing rows
+/// get them on their next sync.
+async fn add_narration_flag_columns(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Books')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"narrated_by_author".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN narrated_by_author INTEGER NOT NULL DEFAULT 0").await?;
+    }
+    if !columns.contains(&"full_cast".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN full_cast INTEGER NOT NULL DEFAULT 0").await?;
+    }
+
+    Ok(())
+}
+
+/// Create PendingTokenRefreshes table
+///
+/// Write-ahead record of refreshed tokens, kept until they are saved into
+/// the account (see `accounts::recover_pending_token_refreshes`).
+async fn create_pending_token_refreshes_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS PendingTokenRefreshes (
+            account_id TEXT PRIMARY KEY,
+            access_token TEXT NOT NULL,
+            expires_at TEXT NOT NULL,  -- ISO 8601 timestamp
+            refresh_token TEXT,  -- Only when Amazon rotated it
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Create FileIntegrity, digests and last verification of liberated files
+/// (see `file::integrity`)
+async fn create_file_integrity_table(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS FileIntegrity (
+            asin TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,  -- Download task the digests belong to
+            path TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            sample_digest TEXT NOT NULL,  -- xxh3 of sampled regions
+            status TEXT NOT NULL DEFAULT 'ok',  -- "ok", "corrupted" or "missing"
+            problem TEXT,  -- Why the last check failed
+            baseline_at TEXT NOT NULL,
+            checked_at TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_file_integrity_checked ON FileIntegrity(checked_at);
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add buffer_overrides and buffering columns to DownloadTasks
+///
+/// buffer_overrides is a JSON `BufferOverrides` pinning the task's write
+/// buffering; buffering is the JSON `EffectiveBuffering` of the latest
+/// session (see `download::buffering`).
+async fn add_download_buffering_columns(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"buffer_overrides".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN buffer_overrides TEXT").await?;
+    }
+    if !columns.contains(&"buffering".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN buffering TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Store timestamps as UTC ISO 8601 and dates as `YYYY-MM-DD`
+///
+/// Rewrites existing values (see `storage::dates`); values that don't
+/// parse are left alone. The `updated_at` triggers are recreated to write
+/// the same format, and insert triggers convert `DEFAULT CURRENT_TIMESTAMP`
+/// values, which SQLite writes as `YYYY-MM-DD HH:MM:SS`.
+async fn normalize_date_columns(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    // The update triggers would overwrite updated_at while rows are rewritten
+    tx.execute(
+        r#"
+        DROP TRIGGER IF EXISTS update_books_timestamp;
+        DROP TRIGGER IF EXISTS update_accounts_timestamp;
+        "#,
+    )
+    .await?;
+
+    rewrite_columns(&mut tx, TIMESTAMP_COLUMNS, normalize_timestamp).await?;
+    rewrite_columns(&mut tx, DATE_COLUMNS, normalize_date).await?;
+
+    tx.execute(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS update_books_timestamp
+        AFTER UPDATE ON Books
+        FOR EACH ROW
+        BEGIN
+            UPDATE Books SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE book_id = NEW.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS update_accounts_timestamp
+        AFTER UPDATE ON Accounts
+        FOR EACH ROW
+        BEGIN
+            UPDATE Accounts SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE account_id = NEW.account_id;
+        END;
+
+        -- Inserts into Books and Accounts also fire the update triggers above,
+        -- which set updated_at
+        CREATE TRIGGER IF NOT EXISTS utc_books_created
+        AFTER INSERT ON Books
+        FOR EACH ROW
+        BEGIN
+            UPDATE Books SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE book_id = NEW.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS utc_accounts_created
+        AFTER INSERT ON Accounts
+        FOR EACH ROW
+        BEGIN
+            UPDATE Accounts SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE account_id = NEW.account_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS utc_library_books_added
+        AFTER INSERT ON LibraryBooks
+        FOR EACH ROW
+        BEGIN
+            UPDATE LibraryBooks SET date_added = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.date_added), NEW.date_added)
+            WHERE book_id = NEW.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS utc_download_tasks_created
+        AFTER INSERT ON DownloadTasks
+        FOR EACH ROW
+        BEGIN
+            UPDATE DownloadTasks SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE task_id = NEW.task_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS utc_pending_token_refreshes_created
+        AFTER INSERT ON PendingTokenRefreshes
+        FOR EACH ROW
+        BEGIN
+            UPDATE PendingTokenRefreshes SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE account_id = NEW.account_id;
+        END;
+        "#,
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Replace the text values of `tables`' columns with `normalize`d ones
+async fn rewrite_columns(
+    conn: &mut sqlx::SqliteConnection,
+    tables: &[(&str, &[&str])],
+    normalize: fn(&str) -> Option<String>,
+) -> Result<()> {
+    for (table, columns) in tables {
+        for column in *columns {
+            let rows: Vec<(i64, String)> = sqlx::query_as(&format!(
+                "SELECT rowid, {column} FROM {table} WHERE typeof({column}) = 'text'"
+            ))
+            .fetch_all(&mut *conn)
+            .await?;
+
+            for (rowid, value) in rows {
+                let Some(normalized) = normalize(&value) else { continue };
+                if normalized != value {
+                    sqlx::query(&format!("UPDATE {table} SET {column} = ? WHERE rowid = ?"))
+                        .bind(normalized)
+                        .bind(rowid)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Create Profiles and give accounts and settings a profile (see `storage::profiles`)
+///
+/// Existing accounts and settings go to the default profile, which starts
+/// active. Settings is rebuilt because its primary key becomes
+/// (profile_id, key).
+async fn create_profiles(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    tx.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS Profiles (
+            profile_id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            is_active INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+
+        INSERT OR IGNORE INTO Profiles (profile_id, name, is_active, created_at)
+        VALUES ('default', 'Default', 1, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+        "#,
+    )
+    .await?;
+
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('Accounts')")
+        .fetch_all(&mut *tx)
+        .await?;
+    if !columns.contains(&"profile_id".to_string()) {
+        tx.execute("ALTER TABLE Accounts ADD COLUMN profile_id TEXT NOT NULL DEFAULT 'default'")
+            .await?;
+    }
+
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('Settings')")
+        .fetch_all(&mut *tx)
+        .await?;
+    if !columns.contains(&"profile_id".to_string()) {
+        tx.execute(
+            r#"
+            CREATE TABLE Settings_new (
+                profile_id TEXT NOT NULL DEFAULT 'default',
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (profile_id, key)
+            );
+
+            INSERT INTO Settings_new (profile_id, key, value, updated_at)
+            SELECT 'default', key, value, updated_at FROM Settings;
+
+            DROP TABLE Settings;
+            ALTER TABLE Settings_new RENAME TO Settings;
+            "#,
+        )
+        .await?;
+    }
+
+    tx.execute("CREATE INDEX IF NOT EXISTS idx_accounts_profile ON Accounts(profile_id)")
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Create Jobs and JobArtifacts (see `storage::jobs`)
+async fn create_jobs(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS Jobs (
+            job_id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            finished_at TEXT,
+            error TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS JobArtifacts (
+            job_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            reference TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (job_id, kind, reference),
+            FOREIGN KEY (job_id) REFERENCES Jobs(job_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_jobs_status ON Jobs(status);
+        CREATE INDEX IF NOT EXISTS idx_jobs_started ON Jobs(started_at);
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Create Notes and its NotesSearch full-text index (see `storage::notes`)
+///
+/// NotesSearch is an external-content FTS5 table over Notes.text, kept in
+/// step by triggers.
+async fn create_notes(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    tx.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS Notes (
+            note_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            book_id INTEGER NOT NULL,
+            profile_id TEXT NOT NULL DEFAULT 'default',
+            text TEXT NOT NULL,
+            position_ms INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_notes_book ON Notes(book_id, profile_id);
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS NotesSearch USING fts5(
+            text,
+            content = 'Notes',
+            content_rowid = 'note_id',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS notes_search_insert AFTER INSERT ON Notes BEGIN
+            INSERT INTO NotesSearch (rowid, text) VALUES (new.note_id, new.text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS notes_search_delete AFTER DELETE ON Notes BEGIN
+            INSERT INTO NotesSearch (NotesSearch, rowid, text) VALUES ('delete', old.note_id, old.text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS notes_search_update AFTER UPDATE OF text ON Notes BEGIN
+            INSERT INTO NotesSearch (NotesSearch, rowid, text) VALUES ('delete', old.note_id, old.text);
+            INSERT INTO NotesSearch (rowid, text) VALUES (new.note_id, new.text);
+        END;
+        "#,
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Create LiberationReceipts and their output files (see `storage::receipts`)
+///
+/// Receipts outlive the book and its download task, so there are no
+/// foreign keys to them; output hashes are indexed for lookups by content.
+async fn create_liberation_receipts(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS LiberationReceipts (
+            receipt_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            asin TEXT NOT NULL,
+            task_id TEXT NOT NULL,
+            source TEXT NOT NULL,
+            source_ref TEXT,
+            license_id TEXT,
+            decrypt_method TEXT NOT NULL,
+            download_ms INTEGER,
+            decrypt_ms INTEGER,
+            toolchain TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS LiberationReceiptFiles (
+            receipt_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            location TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            PRIMARY KEY (receipt_id, position),
+            FOREIGN KEY (receipt_id) REFERENCES LiberationReceipts(receipt_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_receipts_asin ON LiberationReceipts(asin);
+        CREATE INDEX IF NOT EXISTS idx_receipt_files_sha256 ON LiberationReceiptFiles(sha256);
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Create OfflineLicenses, licenses fetched ahead of a trip (see
+/// `download::offline`)
+async fn create_offline_licenses(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS OfflineLicenses (
+            asin TEXT PRIMARY KEY,
+            quality TEXT NOT NULL,
+            file_type TEXT NOT NULL,
+            license TEXT NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            expires_at TEXT,
+            fetched_at TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_offline_licenses_expires ON OfflineLicenses(expires_at);
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add progress_epoch column to DownloadTasks
+///
+/// Counts the times a task's bytes_downloaded went back (see
+/// `DownloadTask::progress_epoch`).
+async fn add_progress_epoch_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"progress_epoch".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN progress_epoch INTEGER NOT NULL DEFAULT 0").await?;
+    }
+
+    Ok(())
+}
+
+/// Add trace_id column to DownloadTasks
+///
+/// Trace id of the bridge call that enqueued the download (see `trace`).
+async fn add_download_trace_id_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"trace_id".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN trace_id TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add output_root column to UserDefinedItems
+///
+/// Per-book output root override (see `file::locations`).
+async fn add_output_root_column(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('UserDefinedItems')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"output_root".to_string()) {
+        pool.execute("ALTER TABLE UserDefinedItems ADD COLUMN output_root TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Create BookFiles, companion files stored with a book (see
+/// `storage::book_files`), and link DownloadTasks fetching them to the book
+async fn create_book_files(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS BookFiles (
+            file_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            book_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,  -- CompanionKind: "bonus_audio", "author_interview" or "pdf"
+            source_asin TEXT,  -- ASIN of companion audio
+            path TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            task_id TEXT,  -- Download task that fetched it
+            created_at TEXT NOT NULL,
+            UNIQUE (book_id, path),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+        "#,
+    )
+    .await?;
+
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"companion_of".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN companion_of TEXT").await?;
+    }
+    if !columns.contains(&"companion_kind".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN companion_kind TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Sort key for series sequences (see `series_order`)
+///
+/// Also reparses `"index"`, which the float parse got wrong for ranges
+/// ("1-3" was 13).
+async fn add_series_sort_key(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('SeriesBooks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"sort_key".to_string()) {
+        pool.execute("ALTER TABLE SeriesBooks ADD COLUMN sort_key TEXT").await?;
+    }
+
+    let rows: Vec<(i64, i64, Option<String>)> =
+        sqlx::query_as(r#"SELECT series_id, book_id, "order" FROM SeriesBooks"#)
+            .fetch_all(pool)
+            .await?;
+
+    let mut tx = pool.begin().await?;
+    for (series_id, book_id, order) in rows {
+        let sequence = parse_sequence(order.as_deref().unwrap_or_default());
+        sqlx::query(r#"UPDATE SeriesBooks SET "index" = ?, sort_key = ? WHERE series_id = ? AND book_id = ?"#)
+            .bind(sequence.index())
+            .bind(&sequence.sort_key)
+            .bind(series_id)
+            .bind(book_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    pool.execute("CREATE INDEX IF NOT EXISTS idx_series_books_sort_key ON SeriesBooks(series_id, sort_key)").await?;
+
+    Ok(())
+}
+
+/// Create the BooksSearch full-text index (see `queries::search_books_fts`)
+///
+/// Authors, narrators and series live in other tables, so BooksSearch
+/// keeps its own copy of the text instead of being an external-content
+/// table. BookSearchText builds a book's row; triggers on every table it
+/// reads from replace the row when the text changes.
+async fn create_books_search(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    tx.execute(
+        r#"
+        CREATE VIEW IF NOT EXISTS BookSearchText AS
+        SELECT
+            b.book_id,
+            b.title,
+            b.subtitle,
+            b.description,
+            (SELECT GROUP_CONCAT(c.name, ', ') FROM BookContributors bc
+             JOIN Contributors c ON c.contributor_id = bc.contributor_id
+             WHERE bc.book_id = b.book_id AND bc.role = 1) AS authors,
+            (SELECT GROUP_CONCAT(c.name, ', ') FROM BookContributors bc
+             JOIN Contributors c ON c.contributor_id = bc.contributor_id
+             WHERE bc.book_id = b.book_id AND bc.role = 2) AS narrators,
+            (SELECT GROUP_CONCAT(s.name, ', ') FROM SeriesBooks sb
+             JOIN Series s ON s.series_id = sb.series_id
+             WHERE sb.book_id = b.book_id) AS series
+        FROM Books b;
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS BooksSearch USING fts5(
+            title,
+            subtitle,
+            description,
+            authors,
+            narrators,
+            series,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS books_search_insert AFTER INSERT ON Books BEGIN
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_update AFTER UPDATE OF title, subtitle, description ON Books BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_delete AFTER DELETE ON Books BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_contributor_insert AFTER INSERT ON BookContributors BEGIN
+            DELETE FROM BooksSearch WHERE rowid = new.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_contributor_delete AFTER DELETE ON BookContributors BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = old.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_series_insert AFTER INSERT ON SeriesBooks BEGIN
+            DELETE FROM BooksSearch WHERE rowid = new.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_series_delete AFTER DELETE ON SeriesBooks BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = old.book_id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_contributor_rename AFTER UPDATE OF name ON Contributors BEGIN
+            DELETE FROM BooksSearch
+            WHERE rowid IN (SELECT book_id FROM BookContributors WHERE contributor_id = new.contributor_id);
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText
+            WHERE book_id IN (SELECT book_id FROM BookContributors WHERE contributor_id = new.contributor_id);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS books_search_series_rename AFTER UPDATE OF name ON Series BEGIN
+            DELETE FROM BooksSearch
+            WHERE rowid IN (SELECT book_id FROM SeriesBooks WHERE series_id = new.series_id);
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText
+            WHERE book_id IN (SELECT book_id FROM SeriesBooks WHERE series_id = new.series_id);
+        END;
+
+        DELETE FROM BooksSearch;
+        INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+        SELECT * FROM BookSearchText;
+        "#,
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Create ListeningPositions (see `storage::listening_positions`)
+async fn create_listening_positions(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS ListeningPositions (
+            asin TEXT NOT NULL,
+            kind TEXT NOT NULL,  -- AnnotationKind: "last_heard", "bookmark", "note" or "clip"
+            annotation_id TEXT NOT NULL DEFAULT '',  -- Audible's id; empty for last_heard
+            position_ms INTEGER NOT NULL,
+            end_position_ms INTEGER,  -- End of a note or clip
+            note TEXT,  -- Note text or clip title
+            updated_at TEXT,  -- When it was set, on whichever device
+            synced_at TEXT NOT NULL,  -- When it was fetched from or pushed to Audible
+            PRIMARY KEY (asin, kind, annotation_id)
+        );
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add wifi_only and network_paused columns to DownloadTasks
+///
+/// Per-task Wi-Fi-only override and the flag of tasks paused by a network
+/// change (see `download::network_policy`).
+async fn add_download_network_columns(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"wifi_only".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN wifi_only INTEGER").await?;
+    }
+    if !columns.contains(&"network_paused".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN network_paused INTEGER NOT NULL DEFAULT 0").await?;
+    }
+
+    Ok(())
+}
+
+/// Create PodcastEpisodes, links from episodes and seasons to their
+/// podcast parents (see `storage::podcasts`)
+async fn create_podcast_episodes(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS PodcastEpisodes (
+            series_id INTEGER NOT NULL,  -- Series row of the parent's ASIN
+            book_id INTEGER NOT NULL,  -- Episode, or a season of a show
+            "order" TEXT,  -- Sequence under the parent as sent
+            sort_key TEXT,  -- See series_order
+            PRIMARY KEY (series_id, book_id),
+            FOREIGN KEY (series_id) REFERENCES Series(series_id) ON DELETE CASCADE,
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_podcast_episodes_book ON PodcastEpisodes(book_id);
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add Books.cover_path, the cached cover file of a book (see
+/// `file::covers`).
+async fn add_book_cover_path(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Books')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"cover_path".to_string()) {
+        pool.execute("ALTER TABLE Books ADD COLUMN cover_path TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add DownloadTasks.follow_up, work the manager runs when a download
+/// completes (see `download::follow_up`).
+async fn add_download_follow_up(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('DownloadTasks')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"follow_up".to_string()) {
+        pool.execute("ALTER TABLE DownloadTasks ADD COLUMN follow_up TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Create LibraryBookAccounts, every account that owns a book (see
+/// `storage::accounts`), filled from the owners in LibraryBooks.
+async fn create_library_book_accounts(pool: &SqlitePool) -> Result<()> {
+    pool.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS LibraryBookAccounts (
+            book_id INTEGER NOT NULL,
+            account TEXT NOT NULL,  -- Account ID/email
+            date_added TEXT NOT NULL,  -- Purchase date in this account
+            is_deleted INTEGER NOT NULL DEFAULT 0,
+            absent_from_last_scan INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (book_id, account),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_library_book_accounts_account ON LibraryBookAccounts(account);
+
+        INSERT OR IGNORE INTO LibraryBookAccounts (book_id, account, date_added, is_deleted, absent_from_last_scan)
+        SELECT book_id, account, date_added, is_deleted, absent_from_last_scan FROM LibraryBooks;
+        "#,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Tables whose rows belong to a book, moved to the kept copy when
+/// duplicates are merged. A row the kept copy already has stays behind and
+/// goes with the duplicate.
+const BOOK_CHILD_TABLES: [&str; 15] = [
+    "UserDefinedItems",
+    "LibraryBooks",
+    "LibraryBookAccounts",
+    "Supplements",
+    "BookContributors",
+    "SeriesBooks",
+    "BookCategories",
+    "BookTags",
+    "BookChapters",
+    "ReadAlongMappings",
+    "LocalizedTitles",
+    "ValidationIssues",
+    "Notes",
+    "BookFiles",
+    "PodcastEpisodes",
+];
+
+/// Tables that refer to books by ASIN
+const ASIN_TABLES: [&str; 6] = [
+    "DownloadTasks",
+    "SyncIssues",
+    "FileIntegrity",
+    "LiberationReceipts",
+    "OfflineLicenses",
+    "ListeningPositions",
+];
+
+/// Merge Books rows whose ASINs differ only by case or padding, store
+/// ASINs in canonical form and add the unique index that keeps them
+/// merged (see `storage::normalize::canonical_asin`)
+///
+/// The copy already stored under the canonical ASIN is kept, else the
+/// oldest. The duplicate's user data is merged into it: download state
+/// from whichever copy was downloaded last, listening progress from
+/// whichever was updated last, the finished flag if either is finished,
+/// and ratings, legacy tags and output folder where the kept copy has
+/// none. Everything else the duplicate owns (tags, notes, files, account
+/// ownership, ...) moves over unless the kept copy has its own.
+async fn merge_duplicate_books(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let rows: Vec<(i64, String)> =
+        sqlx::query_as("SELECT book_id, audible_product_id FROM Books ORDER BY book_id")
+            .fetch_all(&mut *tx)
+            .await?;
+
+    let mut groups: std::collections::BTreeMap<String, Vec<(i64, String)>> = Default::default();
+    for (book_id, asin) in rows {
+        groups.entry(canonical_asin(&asin).to_ascii_uppercase()).or_default().push((book_id, asin));
+    }
+
+    for books in groups.into_values() {
+        let keep_at = books.iter().position(|(_, asin)| *asin == canonical_asin(asin)).unwrap_or(0);
+        let (keep, keep_asin) = &books[keep_at];
+
+        for (index, (duplicate, duplicate_asin)) in books.iter().enumerate() {
+            if index == keep_at {
+                continue;
+            }
+            merge_book_into(&mut tx, *duplicate, *keep).await?;
+            rename_asin(&mut tx, duplicate_asin, &canonical_asin(keep_asin)).await?;
+        }
+
+        let canonical = canonical_asin(keep_asin);
+        if *keep_asin != canonical {
+            sqlx::query("UPDATE Books SET audible_product_id = ? WHERE book_id = ?")
+                .bind(&canonical)
+                .bind(keep)
+                .execute(&mut *tx)
+                .await?;
+            rename_asin(&mut tx, keep_asin, &canonical).await?;
+        }
+    }
+
+    tx.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_books_asin_key ON Books(UPPER(TRIM(audible_product_id)))",
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Merge one duplicate book into the copy that is kept, then delete it
+async fn merge_book_into(conn: &mut sqlx::SqliteConnection, duplicate: i64, keep: i64) -> Result<()> {
+    // Download state travels together, from the copy downloaded last (or
+    // the only one ever liberated)
+    sqlx::query(
+        r#"
+        UPDATE UserDefinedItems SET
+            book_status = d.book_status,
+            pdf_status = d.pdf_status,
+            last_downloaded = d.last_downloaded,
+            last_downloaded_version = d.last_downloaded_version,
+            last_downloaded_format = d.last_downloaded_format,
+            last_downloaded_file_version = d.last_downloaded_file_version
+        FROM (SELECT * FROM UserDefinedItems WHERE book_id = ?) AS d
+        WHERE UserDefinedItems.book_id = ?
+          AND (d.last_downloaded > COALESCE(UserDefinedItems.last_downloaded, '')
+               OR (UserDefinedItems.last_downloaded IS NULL AND UserDefinedItems.book_status = 0))
+        "#,
+    )
+    .bind(duplicate)
+    .bind(keep)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE UserDefinedItems SET
+            position_ms = d.position_ms,
+            progress_percent = d.progress_percent,
+            progress_updated_at = d.progress_updated_at
+        FROM (SELECT * FROM UserDefinedItems WHERE book_id = ?) AS d
+        WHERE UserDefinedItems.book_id = ?
+          AND d.progress_updated_at > COALESCE(UserDefinedItems.progress_updated_at, '')
+        "#,
+    )
+    .bind(duplicate)
+    .bind(keep)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE UserDefinedItems SET
+            is_finished = MAX(UserDefinedItems.is_finished, d.is_finished),
+            tags = COALESCE(UserDefinedItems.tags, d.tags),
+            output_root = COALESCE(UserDefinedItems.output_root, d.output_root),
+            user_rating_overall = IIF(UserDefinedItems.user_rating_overall = 0, d.user_rating_overall, UserDefinedItems.user_rating_overall),
+            user_rating_performance = IIF(UserDefinedItems.user_rating_performance = 0, d.user_rating_performance, UserDefinedItems.user_rating_performance),
+            user_rating_story = IIF(UserDefinedItems.user_rating_story = 0, d.user_rating_story, UserDefinedItems.user_rating_story)
+        FROM (SELECT * FROM UserDefinedItems WHERE book_id = ?) AS d
+        WHERE UserDefinedItems.book_id = ?
+        "#,
+    )
+    .bind(duplicate)
+    .bind(keep)
+    .execute(&mut *conn)
+    .await?;
+
+    // A copy still in the library wins over one missing from the last scan
+    sqlx::query(
+        r#"
+        DELETE FROM LibraryBooks
+        WHERE book_id = ? AND absent_from_last_scan = 1
+          AND EXISTS (SELECT 1 FROM LibraryBooks WHERE book_id = ? AND absent_from_last_scan = 0)
+        "#,
+    )
+    .bind(keep)
+    .bind(duplicate)
+    .execute(&mut *conn)
+    .await?;
+
+    for table in BOOK_CHILD_TABLES {
+        sqlx::query(&format!("UPDATE OR IGNORE {table} SET book_id = ? WHERE book_id = ?"))
+            .bind(keep)
+            .bind(duplicate)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    sqlx::query("DELETE FROM Books WHERE book_id = ?")
+        .bind(duplicate)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Point rows that refer to a book by `from` at `to`
+///
+/// Where both exist, the listening position set last is kept; for the
+/// other tables the row already under `to` is.
+async fn rename_asin(conn: &mut sqlx::SqliteConnection, from: &str, to: &str) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        DELETE FROM ListeningPositions
+        WHERE asin = ? AND EXISTS (
+            SELECT 1 FROM ListeningPositions p
+            WHERE p.asin = ? AND p.kind = ListeningPositions.kind AND p.annotation_id = ListeningPositions.annotation_id
+              AND COALESCE(p.updated_at, '') > COALESCE(ListeningPositions.updated_at, '')
+        )
+        "#,
+    )
+    .bind(to)
+    .bind(from)
+    .execute(&mut *conn)
+    .await?;
+
+    for table in ASIN_TABLES {
+        sqlx::query(&format!("UPDATE OR IGNORE {table} SET asin = ? WHERE asin = ?"))
+            .bind(to)
+            .bind(from)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query(&format!("DELETE FROM {table} WHERE asin = ?"))
+            .bind(from)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Add Jobs.owner, the program that ran a job, so the app and the CLI
+/// only settle their own interrupted jobs (see `storage::jobs`).
+async fn add_job_owner(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('Jobs')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"owner".to_string()) {
+        pool.execute("ALTER TABLE Jobs ADD COLUMN owner TEXT").await?;
+    }
+
+    Ok(())
+}
+
+/// Add BookFiles.sha256, the digest of a companion file when it was
+/// written, like DownloadTasks.sha256 for the download itself.
+async fn add_book_file_sha256(pool: &SqlitePool) -> Result<()> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM pragma_table_info('BookFiles')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !columns.contains(&"sha256".to_string()) {
+        pool.execute("ALTER TABLE BookFiles ADD COLUMN sha256 TEXT").await?;
+    }
+
+    Ok(())
+}
+
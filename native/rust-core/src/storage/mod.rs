@@ -31,12 +31,68 @@
 //!
 //! # Database Schema
 //! - Books: Core book metadata (title, ASIN, runtime, etc.)
+//! - BooksSearch: FTS5 index of book text, kept up to date by triggers
+//!   (see `queries::search_books_fts`)
 //! - LibraryBooks: User ownership/library membership
+//! - LibraryBookAccounts: Every account owning a book (see `accounts`)
 //! - Contributors: Authors and narrators
 //! - Series: Book series information
-//! - Categories: Genres and tags
+//! - Categories: Genres
+//! - Tags/BookTags: User tags (see `tags`)
+//! - SyncIssues: Per-item library sync failures (see `sync_issues`)
+//! - ReadAlongMappings: Audio/ebook sync points (see `read_along`)
+//! - Settings: Key/value app settings (see `settings`, `content_filter`)
+//! - LocalizedTitles: Titles from a preferred marketplace (see `localized_titles`)
+//! - ValidationIssues: Metadata problems per book (see `validation`)
+//! - Profiles: People sharing the device; accounts and settings belong to
+//!   one (see `profiles`)
+//! - Jobs/JobArtifacts: Long-running bridge jobs and what they produced
+//!   (see `jobs`)
+//! - Notes: Private per-book notes with the NotesSearch full-text index
+//!   (see `notes`)
+//! - LiberationReceipts/LiberationReceiptFiles: How each liberated file
+//!   was produced, with output hashes (see `receipts`)
+//! - OfflineLicenses: Licenses fetched ahead of a trip (see
+//!   `download::offline`)
+//! - ListeningPositions: Positions and bookmarks synced with Audible (see
+//!   `listening_positions`)
+//! - PodcastEpisodes: Episodes and seasons under their podcast parents
+//!   (see `podcasts`)
 //! - Many-to-many junction tables for relationships
 //!
+//! Timestamps are stored as UTC ISO 8601 and dates as `YYYY-MM-DD` so
+//! they sort as text (see `dates`).
+//!
+//! Listening positions are stored with a materialized completion percent
+//! for fast "in progress" lists (see `progress`).
+//!
+//! Codec, bitrate and size per hour of liberated files, with CSV/JSON
+//! export, are reported by `library_stats`. Large exports are written in
+//! cancellable chunks with progress (see `export`), which also exports the
+//! whole library as CSV or JSON (`export::export_library`).
+//!
+//! Series sequences ("0.5", "1-3", "Prequel") are parsed into sort keys
+//! for series order and next-up (see `series_order`).
+//!
+//! Ranked, typo-tolerant search with match offsets for highlighting is in
+//! `search`.
+//!
+//! Many small writes from the app can be sent as one batch with per-write
+//! savepoints (see `batch`).
+//!
+//! Companion files liberated with a book (bonus audio, interviews, PDFs)
+//! are listed in `book_files`.
+//!
+//! New installs get the latest schema in one step instead of running every
+//! migration, optionally with seed settings (see `bootstrap`).
+//!
+//! Host apps pause writes around OS backups with `quiesce`, so the backup
+//! copies a complete database file.
+//!
+//! Startup should open the database with `Database::open_with_recovery`,
+//! which checks integrity and repairs or rebuilds a damaged file (see
+//! `recovery`).
+//!
 //! # Usage Example
 //! ```no_run
 //! use rust_core::storage::{Database, queries, models::NewBook};
@@ -60,17 +116,44 @@
 //! ```
 
 pub mod accounts;
+pub mod batch;
+pub mod book_files;
+pub mod bootstrap;
+pub mod chapters;
+pub mod content_filter;
 pub mod database;
+pub mod dates;
+pub mod export;
+pub mod jobs;
+pub mod library_stats;
+pub mod listening_positions;
+pub mod localized_titles;
 pub mod migrations;
 pub mod models;
+pub mod normalize;
+pub mod notes;
+pub mod podcasts;
+pub mod profiles;
+pub mod progress;
+pub mod quiesce;
 pub mod queries;
+pub mod read_along;
+pub mod receipts;
+pub mod recovery;
+pub mod search;
+pub mod series_order;
+pub mod settings;
+pub mod sync_issues;
+pub mod tags;
+pub mod validation;
 
 // Re-export commonly used types
 pub use database::{Database, DatabaseStats};
 pub use models::{
-    AudioFormat, Book, BookCategory, BookContributor, Category, CategoryLadder, Codec,
-    ContentType, Contributor, LiberatedStatus, LibraryBook, NewBook, NewCategory,
+    AudioFormat, BenefitType, Book, BookCategory, BookContributor, Category, CategoryLadder, Codec,
+    ContentType, Contributor, FormatSupport, LiberatedStatus, LibraryBook, NewBook, NewCategory,
     NewCategoryLadder, NewContributor, NewLibraryBook, NewSeries, NewUserDefinedItem, Rating,
     Role, Series, SeriesBook, Supplement, UserDefinedItem,
 };
-pub use queries::{BookQueryParams, SortDirection, SortField};
+pub use queries::{BookQueryParams, DateRange, RuntimeRange, SortDirection, SortField};
+pub use tags::TagCount;
@@ -128,6 +128,147 @@ impl Codec {
     }
 }
 
+/// How a title came into the library
+///
+/// Gifted and loaned titles carry restricted licenses; loans can be streamed
+/// but not downloaded for offline use, so they are refused for liberation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenefitType {
+    Purchase,
+    /// Audible Plus catalog or other membership benefit
+    Subscription,
+    Gift,
+    Loan,
+    Unknown,
+}
+
+impl BenefitType {
+    /// Classify from the library API's `origin_type` and `is_ayce` fields
+    pub fn from_origin(origin_type: Option<&str>, is_ayce: bool) -> Self {
+        let origin = origin_type.unwrap_or_default().to_ascii_lowercase();
+        if origin.contains("gift") {
+            BenefitType::Gift
+        } else if origin.contains("loan") || origin.contains("lend") {
+            BenefitType::Loan
+        } else if is_ayce || origin.contains("subscription") || origin.contains("channels") {
+            BenefitType::Subscription
+        } else if origin.contains("purchase") || origin.is_empty() {
+            BenefitType::Purchase
+        } else {
+            BenefitType::Unknown
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BenefitType::Purchase => "purchase",
+            BenefitType::Subscription => "subscription",
+            BenefitType::Gift => "gift",
+            BenefitType::Loan => "loan",
+            BenefitType::Unknown => "unknown",
+        }
+    }
+
+    /// Whether the license allows downloading for offline use
+    pub fn permits_download(&self) -> bool {
+        !matches!(self, BenefitType::Loan)
+    }
+}
+
+impl std::str::FromStr for BenefitType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "purchase" => BenefitType::Purchase,
+            "subscription" => BenefitType::Subscription,
+            "gift" => BenefitType::Gift,
+            "loan" => BenefitType::Loan,
+            _ => BenefitType::Unknown,
+        })
+    }
+}
+
+/// Whether a title's listed formats can be liberated
+///
+/// Classified at sync time from the library's codec list, so a title the
+/// pipeline can't decrypt is flagged before anyone tries to download it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatSupport {
+    /// An Audible DRM (AAX/AAXC) or MP3 format is offered
+    Supported,
+    /// Only Widevine (DASH) formats are offered
+    WidevineOnly,
+    /// Only formats that can't be decrypted yet (Widevine-only AC-4)
+    Unsupported,
+    /// No codec information
+    Unknown,
+}
+
+impl FormatSupport {
+    /// Classify from codec names (library `available_codecs` names/formats
+    /// and `asset_details` codecs), e.g. "aax_44_128", "mp4_22_64", "ec+3", "ac-4"
+    pub fn from_codecs(codecs: &[&str]) -> Self {
+        let mut widevine = false;
+        let mut ac4 = false;
+        for codec in codecs {
+            let codec: String = codec
+                .to_ascii_lowercase()
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .collect();
+            if codec.contains("ac4") {
+                ac4 = true;
+            } else if codec.contains("ec3") {
+                widevine = true;
+            } else if ["aax", "mp4", "aac", "mp3", "format4"].iter().any(|p| codec.starts_with(p)) {
+                return FormatSupport::Supported;
+            }
+        }
+
+        if widevine {
+            FormatSupport::WidevineOnly
+        } else if ac4 {
+            FormatSupport::Unsupported
+        } else {
+            FormatSupport::Unknown
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FormatSupport::Supported => "supported",
+            FormatSupport::WidevineOnly => "widevine_only",
+            FormatSupport::Unsupported => "unsupported",
+            FormatSupport::Unknown => "unknown",
+        }
+    }
+
+    /// Whether a download can succeed (unknown titles are given a chance)
+    pub fn permits_download(&self, supports_widevine: bool) -> bool {
+        match self {
+            FormatSupport::Supported | FormatSupport::Unknown => true,
+            FormatSupport::WidevineOnly => supports_widevine,
+            FormatSupport::Unsupported => false,
+        }
+    }
+}
+
+impl std::str::FromStr for FormatSupport {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "supported" => FormatSupport::Supported,
+            "widevine_only" => FormatSupport::WidevineOnly,
+            "unsupported" => FormatSupport::Unsupported,
+            _ => FormatSupport::Unknown,
+        })
+    }
+}
+
 // ============================================================================
 // VALUE OBJECTS
 // ============================================================================
@@ -273,6 +414,24 @@ pub struct Book {
     pub episode_number: Option<i32>,
     #[sqlx(default)]
     pub content_delivery_type: Option<String>,
+    /// BenefitType as string (purchase, subscription, gift, loan)
+    #[sqlx(default)]
+    pub benefit_type: Option<String>,
+    /// FormatSupport as string (supported, widevine_only, unsupported)
+    #[sqlx(default)]
+    pub format_support: Option<String>,
+    /// An author is also a narrator
+    #[sqlx(default)]
+    pub narrated_by_author: bool,
+    /// Narrated by a full cast
+    #[sqlx(default)]
+    pub full_cast: bool,
+    /// Normalized sort key (see `storage::normalize`)
+    #[sqlx(default)]
+    pub title_sort: Option<String>,
+    /// Normalized title + subtitle for search
+    #[sqlx(default)]
+    pub title_search: Option<String>,
 
     // Timestamps
     pub created_at: DateTime<Utc>,
@@ -285,6 +444,22 @@ impl Book {
         ContentType::from_i32(self.content_type)
     }
 
+    /// Get benefit type (unknown until the book is synced)
+    pub fn get_benefit_type(&self) -> BenefitType {
+        self.benefit_type
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(BenefitType::Unknown)
+    }
+
+    /// Get format support (unknown until the book is synced)
+    pub fn get_format_support(&self) -> FormatSupport {
+        self.format_support
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(FormatSupport::Unknown)
+    }
+
     /// Get product rating
     pub fn get_rating(&self) -> Rating {
         Rating::new(self.rating_overall, self.rating_performance, self.rating_story)
@@ -378,16 +553,11 @@ impl UserDefinedItem {
         self.last_downloaded_format.map(AudioFormat::deserialize)
     }
 
-    /// Parse tags into vector
+    /// Parse tags into vector (see `tags::parse_legacy_tags`)
     pub fn get_tags(&self) -> Vec<String> {
         self.tags
-            .as_ref()
-            .map(|t| {
-                t.split_whitespace()
-                    .filter(|s| !s.is_empty())
-                    .map(String::from)
-                    .collect()
-            })
+            .as_deref()
+            .map(crate::storage::tags::parse_legacy_tags)
             .unwrap_or_default()
     }
 }
@@ -0,0 +1,180 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Title sort keys, search normalization and ASINs
+//!
+//! Computed once at import time and stored in `Books.title_sort` and
+//! `Books.title_search` so list and search queries can use plain indexed
+//! comparisons instead of per-query `COLLATE NOCASE`.
+//!
+//! - Sort key: folded title with a leading English article removed
+//!   ("The Hobbit" sorts under H)
+//! - Search key: folded title and subtitle ("Les Misérables" matches "miserables")
+//!
+//! Folding applies NFKD decomposition, drops combining marks, lowercases,
+//! and collapses whitespace. `fold_with_offsets` also maps the folded text
+//! back to the original, for highlighting matches.
+//!
+//! ASINs are stored in canonical form (`canonical_asin`), and Books has a
+//! unique index on `UPPER(TRIM(audible_product_id))`, so an ASIN that
+//! differs only by case or padding can't become a second book.
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Leading articles ignored when sorting
+const SORT_ARTICLES: [&str; 3] = ["the", "a", "an"];
+
+/// Fold text for case- and accent-insensitive comparison
+pub fn fold(text: &str) -> String {
+    let folded: String = text
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Fold text, keeping where each folded character came from
+///
+/// Returns the same string as `fold`, plus the UTF-16 range of the
+/// original character each folded character came from (UTF-16 so the
+/// ranges index JavaScript strings directly).
+pub fn fold_with_offsets(text: &str) -> (String, Vec<(usize, usize)>) {
+    let mut folded = String::new();
+    let mut offsets: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+
+    for c in text.chars() {
+        let end = start + c.len_utf16();
+        // A combining mark belongs to the character before it
+        if is_combining_mark(c) {
+            if let Some(last) = offsets.last_mut() {
+                last.1 = end;
+            }
+            start = end;
+            continue;
+        }
+        for f in c.nfkd().filter(|c| !is_combining_mark(*c)).flat_map(char::to_lowercase) {
+            if f.is_whitespace() {
+                if folded.is_empty() || folded.ends_with(' ') {
+                    continue;
+                }
+                folded.push(' ');
+            } else {
+                folded.push(f);
+            }
+            offsets.push((start, end));
+        }
+        start = end;
+    }
+
+    if folded.ends_with(' ') {
+        folded.pop();
+        offsets.pop();
+    }
+    (folded, offsets)
+}
+
+/// Sort key for a title
+pub fn title_sort_key(title: &str) -> String {
+    let folded = fold(title);
+    let trimmed = folded.trim_start_matches(|c: char| !c.is_alphanumeric());
+
+    for article in SORT_ARTICLES {
+        if let Some(rest) = trimmed.strip_prefix(article) {
+            if rest.starts_with(' ') && rest.len() > 1 {
+                return rest.trim_start().to_string();
+            }
+        }
+    }
+
+    trimmed.to_string()
+}
+
+/// Search key for a title and optional subtitle
+pub fn title_search_key(title: &str, subtitle: Option<&str>) -> String {
+    match subtitle {
+        Some(sub) if !sub.is_empty() => fold(&format!("{} {}", title, sub)),
+        _ => fold(title),
+    }
+}
+
+/// Canonical form of an ASIN
+///
+/// Audible ASINs are alphanumeric and uppercase; other product ids
+/// (`librivox_123`) keep their case. Surrounding whitespace is dropped.
+pub fn canonical_asin(asin: &str) -> String {
+    let asin = asin.trim();
+    if asin.chars().all(|c| c.is_ascii_alphanumeric()) {
+        asin.to_ascii_uppercase()
+    } else {
+        asin.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Les Misérables"), "les miserables");
+        assert_eq!(fold("  Ｆｕｌｌ   Width "), "full width");
+        assert_eq!(fold("ÆON Flux"), "æon flux");
+    }
+
+    #[test]
+    fn test_fold_with_offsets() {
+        for text in ["Les Mise\u{301}rables", "  Ｆｕｌｌ   Width ", "J.R.R. Tolkien", "Brontë 😀 sisters"] {
+            let (folded, offsets) = fold_with_offsets(text);
+            assert_eq!(folded, fold(text));
+            assert_eq!(folded.chars().count(), offsets.len());
+        }
+
+        // A decomposed accent stays with its letter; the emoji is 2 UTF-16 units
+        let (folded, offsets) = fold_with_offsets("e\u{301}😀x");
+        assert_eq!(folded, "e😀x");
+        assert_eq!(offsets, vec![(0, 2), (2, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn test_title_sort_key() {
+        assert_eq!(title_sort_key("The Hobbit"), "hobbit");
+        assert_eq!(title_sort_key("A Game of Thrones"), "game of thrones");
+        assert_eq!(title_sort_key("An Absolutely Remarkable Thing"), "absolutely remarkable thing");
+        assert_eq!(title_sort_key("...The End"), "end");
+        // Articles only stripped as whole words, never to an empty key
+        assert_eq!(title_sort_key("Theory of Everything"), "theory of everything");
+        assert_eq!(title_sort_key("The"), "the");
+        assert_eq!(title_sort_key("Éric's Journey"), "eric's journey");
+    }
+
+    #[test]
+    fn test_title_search_key() {
+        assert_eq!(title_search_key("Dune", Some("Deluxe Édition")), "dune deluxe edition");
+        assert_eq!(title_search_key("Dune", Some("")), "dune");
+        assert_eq!(title_search_key("Dune", None), "dune");
+    }
+
+    #[test]
+    fn test_canonical_asin() {
+        assert_eq!(canonical_asin(" b0abc123xy\n"), "B0ABC123XY");
+        assert_eq!(canonical_asin("B0ABC123XY"), "B0ABC123XY");
+        assert_eq!(canonical_asin("librivox_123"), "librivox_123");
+    }
+}
@@ -0,0 +1,249 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Private per-book notes
+//!
+//! Notes are the user's own text about a book, optionally pinned to a
+//! position in the audio. Unlike clips they never leave the device: library
+//! sync doesn't read or write them, and they belong to the profile that
+//! wrote them. They live in the database, so database exports carry them.
+//!
+//! Note text is indexed in the NotesSearch FTS5 table, kept in step by
+//! triggers; `search_notes` matches every word of the query as a prefix.
+
+use crate::error::{LibationError, Result};
+use crate::storage::dates;
+use crate::storage::profiles::ACTIVE_PROFILE_SQL;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// A note on a book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Note {
+    pub note_id: i64,
+    pub asin: String,
+    pub text: String,
+    /// Position in the book the note is about
+    pub position_ms: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A note found by `search_notes`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NoteSearchHit {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub note: Note,
+    pub title: String,
+    /// Matching part of the text, matches wrapped in `[` `]`
+    pub snippet: String,
+}
+
+const NOTE_COLUMNS: &str =
+    "n.note_id, b.audible_product_id AS asin, n.text, n.position_ms, n.created_at, n.updated_at";
+
+fn validate(text: &str, position_ms: Option<i64>) -> Result<()> {
+    if text.trim().is_empty() {
+        return Err(LibationError::InvalidInput("Note text is empty".to_string()));
+    }
+    if position_ms.is_some_and(|p| p < 0) {
+        return Err(LibationError::InvalidInput("Note position can't be negative".to_string()));
+    }
+    Ok(())
+}
+
+/// Add a note to a book for the active profile
+///
+/// # Errors
+/// InvalidInput for empty text or a negative position, RecordNotFound if
+/// the book isn't in the database
+pub async fn add_note(pool: &SqlitePool, asin: &str, text: &str, position_ms: Option<i64>) -> Result<Note> {
+    validate(text, position_ms)?;
+    let book_id: i64 = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+        .bind(asin)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+
+    let now = dates::now();
+    let note_id = sqlx::query(&format!(
+        "INSERT INTO Notes (book_id, profile_id, text, position_ms, created_at, updated_at) \
+         VALUES (?, {}, ?, ?, ?, ?)",
+        ACTIVE_PROFILE_SQL
+    ))
+    .bind(book_id)
+    .bind(text)
+    .bind(position_ms)
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    get_note(pool, note_id)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Note not found: {}", note_id)))
+}
+
+/// Note of the active profile by id
+pub async fn get_note(pool: &SqlitePool, note_id: i64) -> Result<Option<Note>> {
+    let note = sqlx::query_as::<_, Note>(&format!(
+        "SELECT {} FROM Notes n JOIN Books b ON b.book_id = n.book_id \
+         WHERE n.note_id = ? AND n.profile_id = {}",
+        NOTE_COLUMNS, ACTIVE_PROFILE_SQL
+    ))
+    .bind(note_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(note)
+}
+
+/// Replace a note's text and position
+///
+/// # Errors
+/// InvalidInput for empty text or a negative position, RecordNotFound if
+/// the active profile has no such note
+pub async fn update_note(pool: &SqlitePool, note_id: i64, text: &str, position_ms: Option<i64>) -> Result<Note> {
+    validate(text, position_ms)?;
+    let updated = sqlx::query(&format!(
+        "UPDATE Notes SET text = ?, position_ms = ?, updated_at = ? WHERE note_id = ? AND profile_id = {}",
+        ACTIVE_PROFILE_SQL
+    ))
+    .bind(text)
+    .bind(position_ms)
+    .bind(dates::now())
+    .bind(note_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Err(LibationError::not_found(format!("Note not found: {}", note_id)));
+    }
+
+    get_note(pool, note_id)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Note not found: {}", note_id)))
+}
+
+/// Delete a note of the active profile
+///
+/// # Returns
+/// False if there was no such note
+pub async fn delete_note(pool: &SqlitePool, note_id: i64) -> Result<bool> {
+    let deleted = sqlx::query(&format!(
+        "DELETE FROM Notes WHERE note_id = ? AND profile_id = {}",
+        ACTIVE_PROFILE_SQL
+    ))
+    .bind(note_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(deleted > 0)
+}
+
+/// The active profile's notes on a book, by position (unpinned notes last)
+/// then creation time
+pub async fn list_notes(pool: &SqlitePool, asin: &str) -> Result<Vec<Note>> {
+    let notes = sqlx::query_as::<_, Note>(&format!(
+        "SELECT {} FROM Notes n JOIN Books b ON b.book_id = n.book_id \
+         WHERE b.audible_product_id = ? AND n.profile_id = {} \
+         ORDER BY n.position_ms IS NULL, n.position_ms, n.created_at, n.note_id",
+        NOTE_COLUMNS, ACTIVE_PROFILE_SQL
+    ))
+    .bind(asin)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(notes)
+}
+
+/// Full-text search over the active profile's notes, best matches first
+///
+/// Every word of `query` must match the start of a word in the note
+/// (case and accent insensitive). FTS5 query syntax is not interpreted.
+pub async fn search_notes(pool: &SqlitePool, query: &str, limit: i64) -> Result<Vec<NoteSearchHit>> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let hits = sqlx::query_as::<_, NoteSearchHit>(&format!(
+        "SELECT {}, b.title, snippet(NotesSearch, 0, '[', ']', '…', 12) AS snippet \
+         FROM NotesSearch \
+         JOIN Notes n ON n.note_id = NotesSearch.rowid \
+         JOIN Books b ON b.book_id = n.book_id \
+         WHERE NotesSearch MATCH ? AND n.profile_id = {} \
+         ORDER BY bm25(NotesSearch), n.note_id \
+         LIMIT ?",
+        NOTE_COLUMNS, ACTIVE_PROFILE_SQL
+    ))
+    .bind(terms.join(" "))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::profiles::{create_profile, switch_profile};
+    use crate::storage::{queries::insert_book, Database, NewBook};
+
+    #[tokio::test]
+    async fn test_notes_crud_and_search() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0LEGION".to_string(), "We Are Legion".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+
+        let general = add_note(pool, "B0LEGION", "Lend to Sam after the café trip", None).await.unwrap();
+        let pinned = add_note(pool, "B0LEGION", "Great Bender monologue", Some(3_600_000)).await.unwrap();
+        assert!(add_note(pool, "B0LEGION", "  ", None).await.is_err());
+        assert!(matches!(add_note(pool, "MISSING", "x", None).await, Err(LibationError::RecordNotFound(_))));
+
+        let notes = list_notes(pool, "B0LEGION").await.unwrap();
+        assert_eq!(notes.iter().map(|n| n.note_id).collect::<Vec<_>>(), vec![pinned.note_id, general.note_id]);
+
+        // Prefix, accent-insensitive, and updates reindex
+        let hits = search_notes(pool, "cafe len", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].title, "We Are Legion");
+        assert!(hits[0].snippet.contains("[café]"));
+        update_note(pool, pinned.note_id, "Replicant politics chapter", Some(3_700_000)).await.unwrap();
+        assert!(search_notes(pool, "bender", 10).await.unwrap().is_empty());
+        assert_eq!(search_notes(pool, "replicant \"politics", 10).await.unwrap().len(), 1);
+
+        // Private to the profile that wrote them
+        let kid = create_profile(pool, "Kid").await.unwrap();
+        switch_profile(pool, &kid.profile_id).await.unwrap();
+        assert!(list_notes(pool, "B0LEGION").await.unwrap().is_empty());
+        assert!(search_notes(pool, "replicant", 10).await.unwrap().is_empty());
+        assert!(!delete_note(pool, general.note_id).await.unwrap());
+        switch_profile(pool, "default").await.unwrap();
+
+        assert!(delete_note(pool, general.note_id).await.unwrap());
+        assert!(search_notes(pool, "cafe", 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Podcast shows, seasons and episodes
+//!
+//! Library sync stores episodes as books like any other title. The parent
+//! relationships of episodes and of parent items (seasons, shows) are kept
+//! in `PodcastEpisodes`, which links each child to the `Series` row of its
+//! parent's ASIN. A show is then a tree: show → episodes, or show →
+//! seasons → episodes. Each link keeps the child's sequence the way
+//! `SeriesBooks` does, so children list in Audible's order (see
+//! `series_order`).
+
+use crate::error::Result;
+use crate::storage::models::ContentType;
+use crate::storage::series_order::parse_sequence;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// What a child of a podcast parent is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PodcastChildKind {
+    /// A parent item itself (a season); list its children in turn
+    Season,
+    Episode,
+}
+
+/// An episode or season under a podcast parent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PodcastChild {
+    pub asin: String,
+    pub title: String,
+    pub kind: PodcastChildKind,
+    /// Sequence as sent by Audible, for display
+    pub order: Option<String>,
+    pub episode_number: Option<i32>,
+    pub length_in_minutes: i64,
+    /// Children of this child (seasons' episodes)
+    pub child_count: i64,
+}
+
+/// A podcast show: a parent that isn't itself a season of another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PodcastShow {
+    pub asin: String,
+    /// Title of the show's own library item, else the relationship's
+    pub title: Option<String>,
+    pub child_count: i64,
+}
+
+/// Replace the podcast parents of a book
+///
+/// `parents` pairs each parent's `Series` id with the book's sequence
+/// under it.
+pub async fn link_podcast_parents(pool: &SqlitePool, book_id: i64, parents: &[(i64, Option<String>)]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM PodcastEpisodes WHERE book_id = ?")
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+    for (series_id, sequence) in parents {
+        let sequence = parse_sequence(sequence.as_deref().unwrap_or_default());
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO PodcastEpisodes (series_id, book_id, "order", sort_key)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(series_id)
+        .bind(book_id)
+        .bind(Some(&sequence.raw).filter(|raw| !raw.is_empty()))
+        .bind(&sequence.sort_key)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[derive(sqlx::FromRow)]
+struct PodcastChildRow {
+    asin: String,
+    title: String,
+    content_type: i32,
+    order: Option<String>,
+    episode_number: Option<i32>,
+    length_in_minutes: i64,
+    child_count: i64,
+}
+
+/// Episodes and seasons of a show or season, in Audible's order
+///
+/// Empty when the ASIN isn't a podcast parent.
+pub async fn list_episodes_for_parent(pool: &SqlitePool, parent_asin: &str) -> Result<Vec<PodcastChild>> {
+    let rows: Vec<PodcastChildRow> = sqlx::query_as(
+        r#"
+        SELECT b.audible_product_id AS asin, b.title, b.content_type, pe."order", b.episode_number,
+               b.length_in_minutes,
+               (SELECT COUNT(*) FROM PodcastEpisodes c
+                JOIN Series cs ON cs.series_id = c.series_id
+                WHERE cs.audible_series_id = b.audible_product_id) AS child_count
+        FROM PodcastEpisodes pe
+        JOIN Series s ON s.series_id = pe.series_id
+        JOIN Books b ON b.book_id = pe.book_id
+        WHERE s.audible_series_id = ?
+        ORDER BY pe.sort_key, b.episode_number, b.title COLLATE NOCASE
+        "#,
+    )
+    .bind(parent_asin)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| PodcastChild {
+            asin: row.asin,
+            title: row.title,
+            kind: if ContentType::from_i32(row.content_type) == ContentType::Parent {
+                PodcastChildKind::Season
+            } else {
+                PodcastChildKind::Episode
+            },
+            order: row.order,
+            episode_number: row.episode_number,
+            length_in_minutes: row.length_in_minutes,
+            child_count: row.child_count,
+        })
+        .collect())
+}
+
+/// Podcast shows in the library, by title
+pub async fn list_podcast_shows(pool: &SqlitePool) -> Result<Vec<PodcastShow>> {
+    let rows: Vec<(String, Option<String>, i64)> = sqlx::query_as(
+        r#"
+        SELECT s.audible_series_id, COALESCE(b.title, s.name), COUNT(pe.book_id)
+        FROM Series s
+        JOIN PodcastEpisodes pe ON pe.series_id = s.series_id
+        LEFT JOIN Books b ON b.audible_product_id = s.audible_series_id
+        WHERE NOT EXISTS (SELECT 1 FROM PodcastEpisodes up WHERE up.book_id = b.book_id)
+        GROUP BY s.series_id
+        ORDER BY COALESCE(b.title, s.name) COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(asin, title, child_count)| PodcastShow { asin, title, child_count })
+        .collect())
+}
@@ -0,0 +1,306 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Profiles for people sharing a device
+//!
+//! A profile groups Audible accounts. Exactly one profile is active; the
+//! library views (book lists and counts, search, in-progress shelves,
+//! library stats) only show books owned by the active profile's accounts,
+//! and settings (`Settings.profile_id`) belong to the active profile.
+//! Listening positions are stored per book, so they follow the book's
+//! owning account into its profile.
+//!
+//! Books owned by no account (local imports) are shown in every profile.
+//! Books owned by an account without an `Accounts` row belong to the
+//! default profile.
+//!
+//! The default profile (`DEFAULT_PROFILE_ID`) always exists and holds
+//! everything from before profiles; it can't be deleted.
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Profile holding data from before profiles existed
+pub const DEFAULT_PROFILE_ID: &str = "default";
+
+/// SQL for the active profile's id
+pub(crate) const ACTIVE_PROFILE_SQL: &str =
+    "COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default')";
+
+/// WHERE condition keeping books (alias `b`) visible in the active profile
+pub(crate) const BOOK_IN_ACTIVE_PROFILE: &str =
+    "(NOT EXISTS (SELECT 1 FROM LibraryBooks lbp WHERE lbp.book_id = b.book_id) \
+     OR EXISTS (SELECT 1 FROM LibraryBooks lbp LEFT JOIN Accounts ap ON ap.account_id = lbp.account \
+     WHERE lbp.book_id = b.book_id AND COALESCE(ap.profile_id, 'default') = \
+     COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default')))";
+
+/// A person's view of the library
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Profile {
+    pub profile_id: String,
+    pub name: String,
+    pub is_active: bool,
+    pub created_at: String,
+    /// Accounts in this profile
+    #[sqlx(skip)]
+    pub account_ids: Vec<String>,
+}
+
+/// All profiles, oldest first
+pub async fn list_profiles(pool: &SqlitePool) -> Result<Vec<Profile>> {
+    let mut profiles = sqlx::query_as::<_, Profile>(
+        "SELECT profile_id, name, is_active, created_at FROM Profiles ORDER BY created_at, profile_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for profile in &mut profiles {
+        profile.account_ids =
+            sqlx::query_scalar("SELECT account_id FROM Accounts WHERE profile_id = ? ORDER BY created_at")
+                .bind(&profile.profile_id)
+                .fetch_all(pool)
+                .await?;
+    }
+
+    Ok(profiles)
+}
+
+/// The active profile
+pub async fn active_profile(pool: &SqlitePool) -> Result<Profile> {
+    let id: String = sqlx::query_scalar(&format!("SELECT {}", ACTIVE_PROFILE_SQL))
+        .fetch_one(pool)
+        .await?;
+    list_profiles(pool)
+        .await?
+        .into_iter()
+        .find(|p| p.profile_id == id)
+        .ok_or_else(|| LibationError::InvalidState(format!("Active profile is missing: {}", id)))
+}
+
+/// Create a profile (not activated)
+///
+/// # Errors
+/// InvalidInput if `name` is blank
+pub async fn create_profile(pool: &SqlitePool, name: &str) -> Result<Profile> {
+    let name = require_name(name)?;
+    let profile_id = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO Profiles (profile_id, name, is_active, created_at) VALUES (?, ?, 0, ?)")
+        .bind(&profile_id)
+        .bind(&name)
+        .bind(crate::storage::dates::now())
+        .execute(pool)
+        .await?;
+
+    list_profiles(pool)
+        .await?
+        .into_iter()
+        .find(|p| p.profile_id == profile_id)
+        .ok_or_else(|| LibationError::InvalidState("Created profile is missing".to_string()))
+}
+
+/// Make `profile_id` the active profile
+///
+/// # Errors
+/// RecordNotFound if the profile doesn't exist
+pub async fn switch_profile(pool: &SqlitePool, profile_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let exists: Option<String> = sqlx::query_scalar("SELECT profile_id FROM Profiles WHERE profile_id = ?")
+        .bind(profile_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if exists.is_none() {
+        return Err(not_found(profile_id));
+    }
+
+    sqlx::query("UPDATE Profiles SET is_active = (profile_id = ?)")
+        .bind(profile_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Move an account (and the books it owns) to another profile
+///
+/// # Errors
+/// RecordNotFound if the account or profile doesn't exist
+pub async fn move_account_to_profile(pool: &SqlitePool, account_id: &str, profile_id: &str) -> Result<()> {
+    let exists: Option<String> = sqlx::query_scalar("SELECT profile_id FROM Profiles WHERE profile_id = ?")
+        .bind(profile_id)
+        .fetch_optional(pool)
+        .await?;
+    if exists.is_none() {
+        return Err(not_found(profile_id));
+    }
+
+    let result = sqlx::query("UPDATE Accounts SET profile_id = ? WHERE account_id = ?")
+        .bind(profile_id)
+        .bind(account_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(LibationError::not_found(format!("Account not found: {}", account_id)));
+    }
+    Ok(())
+}
+
+/// Delete a profile with its accounts, their library and its settings
+///
+/// Liberated files and download history are kept. If the profile was
+/// active, the default profile becomes active.
+///
+/// # Errors
+/// - InvalidInput for the default profile
+/// - RecordNotFound if the profile doesn't exist
+/// - PermissionDenied if `remove_accounts` is disabled (see `permissions`)
+pub async fn delete_profile(pool: &SqlitePool, profile_id: &str) -> Result<()> {
+    crate::permissions::require(crate::permissions::Permission::RemoveAccounts)?;
+    if profile_id == DEFAULT_PROFILE_ID {
+        return Err(LibationError::invalid_input("The default profile can't be deleted"));
+    }
+
+    let mut tx = pool.begin().await?;
+    let was_active: Option<bool> = sqlx::query_scalar("SELECT is_active FROM Profiles WHERE profile_id = ?")
+        .bind(profile_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(was_active) = was_active else {
+        return Err(not_found(profile_id));
+    };
+
+    // Books go with their library entries, positions, tags and chapters
+    sqlx::query(
+        r#"
+        DELETE FROM Books WHERE book_id IN (
+            SELECT lb.book_id FROM LibraryBooks lb
+            JOIN Accounts a ON a.account_id = lb.account
+            WHERE a.profile_id = ?
+        )
+        "#,
+    )
+    .bind(profile_id)
+    .execute(&mut *tx)
+    .await?;
+
+    for sql in [
+        "DELETE FROM PendingTokenRefreshes WHERE account_id IN (SELECT account_id FROM Accounts WHERE profile_id = ?)",
+        "DELETE FROM Accounts WHERE profile_id = ?",
+        "DELETE FROM Settings WHERE profile_id = ?",
+        "DELETE FROM Notes WHERE profile_id = ?",
+        "DELETE FROM Profiles WHERE profile_id = ?",
+    ] {
+        sqlx::query(sql).bind(profile_id).execute(&mut *tx).await?;
+    }
+
+    if was_active {
+        sqlx::query("UPDATE Profiles SET is_active = 1 WHERE profile_id = ?")
+            .bind(DEFAULT_PROFILE_ID)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+fn require_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(LibationError::invalid_input("Profile name is empty"));
+    }
+    Ok(name.to_string())
+}
+
+fn not_found(profile_id: &str) -> LibationError {
+    LibationError::not_found(format!("Profile not found: {}", profile_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::{count_books, insert_book, insert_library_book, list_books_with_filters};
+    use crate::storage::settings::{get_setting, set_setting};
+    use crate::storage::{BookQueryParams, Database, NewBook, NewLibraryBook};
+
+    async fn add_account(pool: &SqlitePool, account_id: &str) {
+        let account = serde_json::json!({
+            "account_id": account_id,
+            "account_name": account_id,
+            "locale": {"country_code": "us", "name": "United States", "domain": "audible.com", "with_username": true},
+            "identity": null,
+        });
+        crate::storage::accounts::save_account(pool, account_id, &account.to_string()).await.unwrap();
+    }
+
+    async fn add_book(pool: &SqlitePool, asin: &str, account: &str) {
+        let book_id = insert_book(pool, &NewBook::new(asin.to_string(), asin.to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        insert_library_book(pool, &NewLibraryBook { book_id, account: account.to_string() })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_profiles_separate_libraries_and_settings() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        let profiles = list_profiles(pool).await.unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert!(profiles[0].is_active);
+        assert_eq!(profiles[0].profile_id, DEFAULT_PROFILE_ID);
+
+        add_account(pool, "parent@example.com").await;
+        add_book(pool, "B0PARENT01", "parent@example.com").await;
+        set_setting(pool, "metadata.preferred_locale", "us").await.unwrap();
+
+        // Accounts added while a profile is active join it
+        let kid = create_profile(pool, " Kid ").await.unwrap();
+        assert_eq!(kid.name, "Kid");
+        switch_profile(pool, &kid.profile_id).await.unwrap();
+        add_account(pool, "kid@example.com").await;
+        add_book(pool, "B0KID00001", "kid@example.com").await;
+
+        let params = BookQueryParams { limit: 10, ..Default::default() };
+        let asins = |books: Vec<crate::storage::queries::BookWithRelations>| {
+            books.into_iter().map(|b| b.audible_product_id).collect::<Vec<_>>()
+        };
+        assert_eq!(asins(list_books_with_filters(pool, &params).await.unwrap()), vec!["B0KID00001"]);
+        assert_eq!(count_books(pool).await.unwrap(), 1);
+        assert_eq!(get_setting(pool, "metadata.preferred_locale").await.unwrap(), None);
+        set_setting(pool, "metadata.preferred_locale", "de").await.unwrap();
+
+        switch_profile(pool, DEFAULT_PROFILE_ID).await.unwrap();
+        assert_eq!(asins(list_books_with_filters(pool, &params).await.unwrap()), vec!["B0PARENT01"]);
+        assert_eq!(get_setting(pool, "metadata.preferred_locale").await.unwrap().as_deref(), Some("us"));
+
+        let profiles = list_profiles(pool).await.unwrap();
+        assert_eq!(profiles[1].account_ids, vec!["kid@example.com"]);
+
+        // Deleting the active profile falls back to the default profile
+        switch_profile(pool, &kid.profile_id).await.unwrap();
+        delete_profile(pool, &kid.profile_id).await.unwrap();
+        assert_eq!(active_profile(pool).await.unwrap().profile_id, DEFAULT_PROFILE_ID);
+        assert!(crate::storage::accounts::get_account(pool, "kid@example.com").await.unwrap().is_none());
+        assert!(crate::storage::queries::find_book_by_asin(pool, "B0KID00001").await.unwrap().is_none());
+        assert_eq!(count_books(pool).await.unwrap(), 1);
+
+        assert!(delete_profile(pool, DEFAULT_PROFILE_ID).await.is_err());
+        assert!(matches!(switch_profile(pool, "nope").await, Err(LibationError::RecordNotFound(_))));
+    }
+}
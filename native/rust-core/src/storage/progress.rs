@@ -0,0 +1,212 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Listening progress
+//!
+//! The player's last position is stored on `UserDefinedItems` together with
+//! the completion percent it works out to. The percent is computed once per
+//! position write instead of per query, and the column is indexed, so the
+//! "in progress" shelf is a plain indexed read (`list_in_progress`).
+//!
+//! Positions belong to books, so each profile sees the positions of its own
+//! books (see `storage::profiles`).
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use crate::storage::profiles::BOOK_IN_ACTIVE_PROFILE;
+use sqlx::SqlitePool;
+
+/// Slim list entry for progress shelves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct BookProgress {
+    pub asin: String,
+    pub title: String,
+    pub authors_str: Option<String>,
+    pub picture_id: Option<String>,
+    pub length_in_minutes: i32,
+    pub position_ms: i64,
+    /// 0-100
+    pub progress_percent: f64,
+    /// ISO 8601 timestamp of the last position write
+    pub progress_updated_at: Option<String>,
+}
+
+impl BookProgress {
+    /// When the position was last written
+    pub fn progress_updated(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.progress_updated_at.as_deref().and_then(crate::storage::dates::parse_timestamp)
+    }
+}
+
+/// Completion percent (0-100) of a position within a book
+///
+/// Books without a known length count as 0%.
+pub fn progress_percent(position_ms: i64, length_in_minutes: i32) -> f64 {
+    if length_in_minutes <= 0 {
+        return 0.0;
+    }
+    let length_ms = length_in_minutes as f64 * 60_000.0;
+    (position_ms as f64 / length_ms * 100.0).clamp(0.0, 100.0)
+}
+
+/// Store the playback position of a book and materialize its percent
+///
+/// # Returns
+/// The stored completion percent
+///
+/// # Errors
+/// - InvalidInput if `position_ms` is negative
+/// - RecordNotFound if no book has this ASIN
+pub async fn set_listening_position(pool: &SqlitePool, asin: &str, position_ms: i64) -> Result<f64> {
+    set_listening_position_at(pool, asin, position_ms, &crate::storage::dates::now()).await
+}
+
+/// `set_listening_position` with the time the position was set (a stored
+/// timestamp), for positions that come from another device
+pub async fn set_listening_position_at(pool: &SqlitePool, asin: &str, position_ms: i64, at: &str) -> Result<f64> {
+    if position_ms < 0 {
+        return Err(LibationError::invalid_input("Position must not be negative"));
+    }
+
+    let (book_id, length_in_minutes): (i64, i32) =
+        sqlx::query_as("SELECT book_id, length_in_minutes FROM Books WHERE audible_product_id = ?")
+            .bind(asin)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+
+    let percent = progress_percent(position_ms, length_in_minutes);
+    sqlx::query(
+        r#"
+        INSERT INTO UserDefinedItems (book_id, position_ms, progress_percent, progress_updated_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(book_id) DO UPDATE SET
+            position_ms = excluded.position_ms,
+            progress_percent = excluded.progress_percent,
+            progress_updated_at = excluded.progress_updated_at
+        "#,
+    )
+    .bind(book_id)
+    .bind(position_ms)
+    .bind(percent)
+    .bind(at)
+    .execute(pool)
+    .await?;
+
+    Ok(percent)
+}
+
+/// Stored playback position of a book (0 if it was never played)
+///
+/// # Errors
+/// RecordNotFound if no book has this ASIN
+pub async fn get_listening_position(pool: &SqlitePool, asin: &str) -> Result<i64> {
+    let position: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT u.position_ms
+        FROM Books b
+        LEFT JOIN UserDefinedItems u ON u.book_id = b.book_id
+        WHERE b.audible_product_id = ?
+        "#,
+    )
+    .bind(asin)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", asin)))?;
+
+    Ok(position.unwrap_or(0))
+}
+
+/// The active profile's books started but not finished, most recently played first
+pub async fn list_in_progress(pool: &SqlitePool, limit: i64, offset: i64) -> Result<Vec<BookProgress>> {
+    let books = sqlx::query_as::<_, BookProgress>(&format!(
+        r#"
+        SELECT
+            b.audible_product_id as asin,
+            b.title,
+            (SELECT GROUP_CONCAT(c.name, ', ') FROM BookContributors bc
+             JOIN Contributors c ON bc.contributor_id = c.contributor_id
+             WHERE bc.book_id = b.book_id AND bc.role = 1) as authors_str,
+            b.picture_id,
+            b.length_in_minutes,
+            u.position_ms,
+            u.progress_percent,
+            u.progress_updated_at
+        FROM UserDefinedItems u
+        JOIN Books b ON u.book_id = b.book_id
+        WHERE u.progress_percent > 0 AND u.progress_percent < 100 AND u.is_finished = 0
+            AND {}
+        ORDER BY u.progress_updated_at DESC
+        LIMIT ? OFFSET ?
+        "#,
+        BOOK_IN_ACTIVE_PROFILE
+    ))
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(books)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::insert_book;
+    use crate::storage::{Database, NewBook};
+
+    #[test]
+    fn test_progress_percent() {
+        assert_eq!(progress_percent(30 * 60_000, 60), 50.0);
+        assert_eq!(progress_percent(90 * 60_000, 60), 100.0);
+        assert_eq!(progress_percent(1000, 0), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_list_in_progress() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        for asin in ["B0FIRST", "B0SECOND", "B0UNSTARTED"] {
+            let mut book = NewBook::new(asin.to_string(), asin.to_string(), "us".to_string());
+            book.length_in_minutes = 100;
+            insert_book(pool, &book).await.unwrap();
+        }
+
+        assert_eq!(get_listening_position(pool, "B0FIRST").await.unwrap(), 0);
+        assert_eq!(set_listening_position(pool, "B0FIRST", 25 * 60_000).await.unwrap(), 25.0);
+        assert_eq!(get_listening_position(pool, "B0FIRST").await.unwrap(), 25 * 60_000);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        set_listening_position(pool, "B0SECOND", 60_000).await.unwrap();
+
+        let shelf = list_in_progress(pool, 10, 0).await.unwrap();
+        let asins: Vec<&str> = shelf.iter().map(|b| b.asin.as_str()).collect();
+        assert_eq!(asins, vec!["B0SECOND", "B0FIRST"]);
+        assert_eq!(shelf[1].position_ms, 25 * 60_000);
+
+        // Reaching the end takes the book off the shelf
+        set_listening_position(pool, "B0SECOND", 100 * 60_000).await.unwrap();
+        assert_eq!(list_in_progress(pool, 10, 0).await.unwrap().len(), 1);
+
+        assert!(matches!(
+            set_listening_position(pool, "B0MISSING", 0).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+        assert!(matches!(
+            set_listening_position(pool, "B0FIRST", -1).await,
+            Err(LibationError::InvalidInput(_))
+        ));
+    }
+}
@@ -36,9 +36,15 @@
 
 use crate::error::{LibationError, Result};
 use crate::storage::models::*;
-use chrono::Utc;
+use crate::storage::normalize::{canonical_asin, fold, title_search_key, title_sort_key};
+use crate::storage::dates;
+use crate::storage::profiles::BOOK_IN_ACTIVE_PROFILE;
+use crate::storage::series_order::parse_sequence;
+use crate::trace::trace_eprintln;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{Executor, SqlitePool};
+use sqlx::{Executor, QueryBuilder, Sqlite, SqlitePool};
+use std::collections::{HashMap, HashSet};
 
 // ============================================================================
 // BOOK QUERIES
@@ -54,11 +60,12 @@ pub async fn insert_book(pool: &SqlitePool, book: &NewBook) -> Result<i64> {
             audible_product_id, title, subtitle, description, length_in_minutes,
             content_type, locale, picture_id, picture_large,
             is_abridged, is_spatial, date_published, language,
-            rating_overall, rating_performance, rating_story
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            rating_overall, rating_performance, rating_story,
+            title_sort, title_search
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
     )
-    .bind(&book.audible_product_id)
+    .bind(canonical_asin(&book.audible_product_id))
     .bind(&book.title)
     .bind(&book.subtitle)
     .bind(&book.description)
@@ -74,6 +81,8 @@ pub async fn insert_book(pool: &SqlitePool, book: &NewBook) -> Result<i64> {
     .bind(book.rating_overall)
     .bind(book.rating_performance)
     .bind(book.rating_story)
+    .bind(title_sort_key(&book.title))
+    .bind(title_search_key(&book.title, book.subtitle.as_deref()))
     .execute(pool)
     .await?;
 
@@ -81,8 +90,11 @@ pub async fn insert_book(pool: &SqlitePool, book: &NewBook) -> Result<i64> {
 }
 
 /// Find book by ASIN (audible product ID)
+///
+/// Case and surrounding whitespace are ignored, like the unique index on
+/// the ASIN.
 pub async fn find_book_by_asin(pool: &SqlitePool, asin: &str) -> Result<Option<Book>> {
-    let book = sqlx::query_as::<_, Book>("SELECT * FROM Books WHERE audible_product_id = ?")
+    let book = sqlx::query_as::<_, Book>("SELECT * FROM Books WHERE UPPER(TRIM(audible_product_id)) = UPPER(TRIM(?))")
         .bind(asin)
         .fetch_optional(pool)
         .await?;
@@ -108,7 +120,8 @@ pub async fn update_book(pool: &SqlitePool, book: &Book) -> Result<()> {
             title = ?, subtitle = ?, description = ?, length_in_minutes = ?,
             content_type = ?, picture_id = ?, picture_large = ?,
             is_abridged = ?, is_spatial = ?, date_published = ?, language = ?,
-            rating_overall = ?, rating_performance = ?, rating_story = ?
+            rating_overall = ?, rating_performance = ?, rating_story = ?,
+            title_sort = ?, title_search = ?
         WHERE book_id = ?
         "#,
     )
@@ -126,6 +139,8 @@ pub async fn update_book(pool: &SqlitePool, book: &Book) -> Result<()> {
     .bind(book.rating_overall)
     .bind(book.rating_performance)
     .bind(book.rating_story)
+    .bind(title_sort_key(&book.title))
+    .bind(title_search_key(&book.title, book.subtitle.as_deref()))
     .bind(book.book_id)
     .execute(pool)
     .await?;
@@ -136,7 +151,7 @@ pub async fn update_book(pool: &SqlitePool, book: &Book) -> Result<()> {
 /// List all books with pagination (basic - no relations)
 pub async fn list_books(pool: &SqlitePool, limit: i64, offset: i64) -> Result<Vec<Book>> {
     let books = sqlx::query_as::<_, Book>(
-        "SELECT * FROM Books ORDER BY title LIMIT ? OFFSET ?",
+        "SELECT * FROM Books ORDER BY title_sort LIMIT ? OFFSET ?",
     )
     .bind(limit)
     .bind(offset)
@@ -154,6 +169,9 @@ pub struct BookWithRelations {
     pub audible_product_id: String,
     pub title: String,
     pub subtitle: Option<String>,
+    /// Original title when `title` comes from the preferred marketplace
+    #[sqlx(default)]
+    pub alternate_title: Option<String>,
     pub description: String,
     pub length_in_minutes: i32,
     pub content_type: i32,
@@ -174,6 +192,15 @@ pub struct BookWithRelations {
     pub origin_asin: Option<String>,
     pub episode_number: Option<i32>,
     pub content_delivery_type: Option<String>,
+    #[sqlx(default)]
+    pub benefit_type: Option<String>,
+    /// FormatSupport as string (see `models::FormatSupport`)
+    #[sqlx(default)]
+    pub format_support: Option<String>,
+    #[sqlx(default)]
+    pub narrated_by_author: bool,
+    #[sqlx(default)]
+    pub full_cast: bool,
     pub created_at: String,
     pub updated_at: String,
 
@@ -187,6 +214,9 @@ pub struct BookWithRelations {
     pub publisher: Option<String>,
     pub series_name: Option<String>,
     pub series_sequence: Option<f32>,
+    /// Raw series sequence ("1-3", "Prequel")
+    #[sqlx(default)]
+    pub series_order: Option<String>,
     pub purchase_date: Option<String>,
 }
 
@@ -234,11 +264,29 @@ impl BookWithRelations {
             cover_art_url: self.picture_large.clone(),
         }
     }
+
+    /// Release date (see `storage::dates`)
+    pub fn published_on(&self) -> Option<NaiveDate> {
+        self.date_published.as_deref().and_then(dates::parse_date)
+    }
+
+    /// When the book was added to the library
+    pub fn purchased_at(&self) -> Option<DateTime<Utc>> {
+        self.purchase_date.as_deref().and_then(dates::parse_timestamp)
+    }
+
+    pub fn created(&self) -> Option<DateTime<Utc>> {
+        dates::parse_timestamp(&self.created_at)
+    }
+
+    pub fn updated(&self) -> Option<DateTime<Utc>> {
+        dates::parse_timestamp(&self.updated_at)
+    }
 }
 
 /// List books with all related data (authors, narrators, series, etc.)
 pub async fn list_books_with_relations(pool: &SqlitePool, limit: i64, offset: i64) -> Result<Vec<BookWithRelations>> {
-    let books = sqlx::query_as::<_, BookWithRelations>(
+    let books = sqlx::query_as::<_, BookWithRelations>(&format!(
         r#"
         WITH book_authors AS (
             SELECT
@@ -271,7 +319,8 @@ pub async fn list_books_with_relations(pool: &SqlitePool, limit: i64, offset: i6
                 sb.book_id,
                 s.name as series_name,
                 sb."index" as series_sequence,
-                ROW_NUMBER() OVER (PARTITION BY sb.book_id ORDER BY sb."index") as rn
+                sb."order" as series_order,
+                ROW_NUMBER() OVER (PARTITION BY sb.book_id ORDER BY sb.sort_key) as rn
             FROM SeriesBooks sb
             JOIN Series s ON sb.series_id = s.series_id
         )
@@ -300,6 +349,10 @@ pub async fn list_books_with_relations(pool: &SqlitePool, limit: i64, offset: i6
             b.origin_asin,
             b.episode_number,
             b.content_delivery_type,
+            b.benefit_type,
+            b.format_support,
+            b.narrated_by_author,
+            b.full_cast,
             b.created_at,
             b.updated_at,
             COALESCE(b.source, 'audible') as source,
@@ -308,6 +361,7 @@ pub async fn list_books_with_relations(pool: &SqlitePool, limit: i64, offset: i6
             bp.publisher,
             bs.series_name,
             bs.series_sequence,
+            bs.series_order,
             lb.date_added as purchase_date
         FROM Books b
         LEFT JOIN book_authors ba ON b.book_id = ba.book_id
@@ -315,10 +369,12 @@ pub async fn list_books_with_relations(pool: &SqlitePool, limit: i64, offset: i6
         LEFT JOIN book_publishers bp ON b.book_id = bp.book_id
         LEFT JOIN book_series bs ON b.book_id = bs.book_id AND bs.rn = 1
         LEFT JOIN LibraryBooks lb ON b.book_id = lb.book_id
-        ORDER BY b.title
+        WHERE {}
+        ORDER BY b.title_sort
         LIMIT ? OFFSET ?
         "#,
-    )
+        BOOK_IN_ACTIVE_PROFILE
+    ))
     .bind(limit)
     .bind(offset)
     .fetch_all(pool)
@@ -362,7 +418,8 @@ pub async fn find_book_with_relations_by_asin(pool: &SqlitePool, asin: &str) ->
                 sb.book_id,
                 s.name as series_name,
                 sb."index" as series_sequence,
-                ROW_NUMBER() OVER (PARTITION BY sb.book_id ORDER BY sb."index") as rn
+                sb."order" as series_order,
+                ROW_NUMBER() OVER (PARTITION BY sb.book_id ORDER BY sb.sort_key) as rn
             FROM SeriesBooks sb
             JOIN Series s ON sb.series_id = s.series_id
         )
@@ -391,6 +448,10 @@ pub async fn find_book_with_relations_by_asin(pool: &SqlitePool, asin: &str) ->
             b.origin_asin,
             b.episode_number,
             b.content_delivery_type,
+            b.benefit_type,
+            b.format_support,
+            b.narrated_by_author,
+            b.full_cast,
             b.created_at,
             b.updated_at,
             COALESCE(b.source, 'audible') as source,
@@ -399,6 +460,7 @@ pub async fn find_book_with_relations_by_asin(pool: &SqlitePool, asin: &str) ->
             bp.publisher,
             bs.series_name,
             bs.series_sequence,
+            bs.series_order,
             lb.date_added as purchase_date
         FROM Books b
         LEFT JOIN book_authors ba ON b.book_id = ba.book_id
@@ -416,9 +478,9 @@ pub async fn find_book_with_relations_by_asin(pool: &SqlitePool, asin: &str) ->
     Ok(book)
 }
 
-/// Count total books
+/// Count the active profile's books
 pub async fn count_books(pool: &SqlitePool) -> Result<i64> {
-    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM Books")
+    let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM Books b WHERE {}", BOOK_IN_ACTIVE_PROFILE))
         .fetch_one(pool)
         .await?;
 
@@ -441,6 +503,30 @@ pub enum SortDirection {
     Desc,
 }
 
+/// Inclusive date range; either end may be open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    /// January 1 through December 31 of a year
+    pub fn year(year: i32) -> Option<Self> {
+        Some(Self {
+            from: Some(NaiveDate::from_ymd_opt(year, 1, 1)?),
+            to: Some(NaiveDate::from_ymd_opt(year, 12, 31)?),
+        })
+    }
+}
+
+/// Inclusive runtime range in minutes; either end may be open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuntimeRange {
+    pub min_minutes: Option<i64>,
+    pub max_minutes: Option<i64>,
+}
+
 /// Filter and search parameters for book queries
 #[derive(Debug, Clone, Default)]
 pub struct BookQueryParams {
@@ -448,13 +534,96 @@ pub struct BookQueryParams {
     pub series_name: Option<String>,   // Filter by series
     pub category: Option<String>,      // Filter by genre/category
     pub source: Option<String>,        // Filter by source (audible, librivox)
+    pub tag: Option<String>,           // Filter by user tag
+    pub purchase_date: Option<DateRange>, // Filter by date added to library
+    pub release_date: Option<DateRange>,  // Filter by publication date
+    pub runtime: Option<RuntimeRange>,    // Filter by length in minutes
+    pub narrated_by_author: Option<bool>, // Filter by author narration
+    pub full_cast: Option<bool>,          // Filter by full cast narration
+    pub excluded_categories: Vec<String>, // Hide books in these categories (content filter)
+    pub title_locale: Option<String>,     // Prefer titles from this marketplace (see localized_titles)
     pub sort_field: Option<SortField>,
     pub sort_direction: Option<SortDirection>,
     pub limit: i64,
     pub offset: i64,
 }
 
-/// List books with relations, supporting search, filter, and sort
+/// Add purchase date, release date, and runtime range filters
+///
+/// Dates are compared as text against stored ISO 8601 values (plain
+/// `YYYY-MM-DD` or full timestamps), with an exclusive upper bound on the
+/// following day so the comparisons stay index-friendly. Runtime bounds are
+/// bound as text and converted by the INTEGER column affinity.
+fn push_range_filters(
+    params: &BookQueryParams,
+    where_clauses: &mut Vec<&'static str>,
+    bind_values: &mut Vec<String>,
+) {
+    let mut push_dates = |range: &DateRange, from_clause: &'static str, to_clause: &'static str| {
+        if let Some(from) = range.from {
+            where_clauses.push(from_clause);
+            bind_values.push(from.format("%Y-%m-%d").to_string());
+        }
+        if let Some(next_day) = range.to.and_then(|to| to.succ_opt()) {
+            where_clauses.push(to_clause);
+            bind_values.push(next_day.format("%Y-%m-%d").to_string());
+        }
+    };
+
+    if let Some(ref range) = params.purchase_date {
+        push_dates(range, "lb.date_added >= ?", "lb.date_added < ?");
+    }
+    if let Some(ref range) = params.release_date {
+        push_dates(range, "b.date_published >= ?", "b.date_published < ?");
+    }
+
+    if let Some(ref range) = params.runtime {
+        if let Some(min) = range.min_minutes {
+            where_clauses.push("b.length_in_minutes >= ?");
+            bind_values.push(min.to_string());
+        }
+        if let Some(max) = range.max_minutes {
+            where_clauses.push("b.length_in_minutes <= ?");
+            bind_values.push(max.to_string());
+        }
+    }
+}
+
+/// Add narrated-by-author and full cast filters
+fn push_narration_filters(
+    params: &BookQueryParams,
+    where_clauses: &mut Vec<&'static str>,
+    bind_values: &mut Vec<String>,
+) {
+    if let Some(narrated_by_author) = params.narrated_by_author {
+        where_clauses.push("b.narrated_by_author = ?");
+        bind_values.push(i32::from(narrated_by_author).to_string());
+    }
+    if let Some(full_cast) = params.full_cast {
+        where_clauses.push("b.full_cast = ?");
+        bind_values.push(i32::from(full_cast).to_string());
+    }
+}
+
+/// Hide books with a category ladder matching any excluded category
+///
+/// Matched like the `category` filter, so an entry can be a category id or name.
+fn push_category_exclusions(
+    excluded_categories: &[String],
+    where_clauses: &mut Vec<&'static str>,
+    bind_values: &mut Vec<String>,
+) {
+    for category in excluded_categories {
+        where_clauses.push(
+            "NOT EXISTS (SELECT 1 FROM BookCategories bc \
+             JOIN CategoryLadders cl ON bc.category_ladder_id = cl.category_ladder_id \
+             WHERE bc.book_id = b.book_id AND cl.ladder LIKE ?)"
+        );
+        bind_values.push(format!("%{}%", category));
+    }
+}
+
+/// List the active profile's books with relations, supporting search, filter, and sort
 pub async fn list_books_with_filters(
     pool: &SqlitePool,
     params: &BookQueryParams,
@@ -467,11 +636,13 @@ pub async fn list_books_with_filters(
     if let Some(ref search) = params.search_query {
         let pattern = format!("%{}%", search);
         where_clauses.push(
-            "(b.title LIKE ? OR b.subtitle LIKE ? OR book_authors.authors LIKE ? \
+            "(b.title_search LIKE ? OR EXISTS (SELECT 1 FROM LocalizedTitles lts \
+             WHERE lts.book_id = b.book_id AND lts.title_search LIKE ?) \
+             OR book_authors.authors LIKE ? \
              OR book_narrators.narrators LIKE ? OR book_series_first.series_name LIKE ?)"
         );
-        bind_values.push(pattern.clone());
-        bind_values.push(pattern.clone());
+        bind_values.push(format!("%{}%", fold(search)));
+        bind_values.push(format!("%{}%", fold(search)));
         bind_values.push(pattern.clone());
         bind_values.push(pattern.clone());
         bind_values.push(pattern);
@@ -499,6 +670,20 @@ pub async fn list_books_with_filters(
         bind_values.push(source.clone());
     }
 
+    // Tag filter
+    if let Some(ref tag) = params.tag {
+        where_clauses.push(
+            "EXISTS (SELECT 1 FROM BookTags bt JOIN Tags t ON bt.tag_id = t.tag_id \
+             WHERE bt.book_id = b.book_id AND t.name = ?)"
+        );
+        bind_values.push(crate::storage::tags::normalize_tag(tag).unwrap_or_default());
+    }
+
+    push_range_filters(params, &mut where_clauses, &mut bind_values);
+    push_narration_filters(params, &mut where_clauses, &mut bind_values);
+    push_category_exclusions(&params.excluded_categories, &mut where_clauses, &mut bind_values);
+    where_clauses.push(BOOK_IN_ACTIVE_PROFILE);
+
     let where_clause = if where_clauses.is_empty() {
         String::new()
     } else {
@@ -507,21 +692,21 @@ pub async fn list_books_with_filters(
 
     // Build ORDER BY clause
     let order_clause = match (params.sort_field, params.sort_direction) {
-        (Some(SortField::Title), Some(SortDirection::Asc)) => "ORDER BY b.title ASC",
-        (Some(SortField::Title), Some(SortDirection::Desc)) => "ORDER BY b.title DESC",
+        (Some(SortField::Title), Some(SortDirection::Asc)) => "ORDER BY COALESCE(lt.title_sort, b.title_sort) ASC",
+        (Some(SortField::Title), Some(SortDirection::Desc)) => "ORDER BY COALESCE(lt.title_sort, b.title_sort) DESC",
         (Some(SortField::ReleaseDate), Some(SortDirection::Asc)) => "ORDER BY b.date_published ASC",
         (Some(SortField::ReleaseDate), Some(SortDirection::Desc)) => "ORDER BY b.date_published DESC",
         (Some(SortField::DateAdded), Some(SortDirection::Asc)) => "ORDER BY lb.date_added ASC",
         (Some(SortField::DateAdded), Some(SortDirection::Desc)) => "ORDER BY lb.date_added DESC",
-        (Some(SortField::Length), Some(SortDirection::Asc)) => "ORDER BY b.length_in_minutes ASC, b.title ASC",
-        (Some(SortField::Length), Some(SortDirection::Desc)) => "ORDER BY b.length_in_minutes DESC, b.title ASC",
+        (Some(SortField::Length), Some(SortDirection::Asc)) => "ORDER BY b.length_in_minutes ASC, b.title_sort ASC",
+        (Some(SortField::Length), Some(SortDirection::Desc)) => "ORDER BY b.length_in_minutes DESC, b.title_sort ASC",
         (Some(SortField::Series), Some(SortDirection::Asc)) => {
-            "ORDER BY CASE WHEN book_series_first.series_name IS NULL THEN 1 ELSE 0 END, book_series_first.series_name ASC, book_series_first.series_sequence ASC"
+            "ORDER BY CASE WHEN book_series_first.series_name IS NULL THEN 1 ELSE 0 END, book_series_first.series_name ASC, book_series_first.series_sort_key ASC"
         },
         (Some(SortField::Series), Some(SortDirection::Desc)) => {
-            "ORDER BY CASE WHEN book_series_first.series_name IS NULL THEN 1 ELSE 0 END, book_series_first.series_name DESC, book_series_first.series_sequence DESC"
+            "ORDER BY CASE WHEN book_series_first.series_name IS NULL THEN 1 ELSE 0 END, book_series_first.series_name DESC, book_series_first.series_sort_key DESC"
         },
-        _ => "ORDER BY b.title ASC", // Default
+        _ => "ORDER BY COALESCE(lt.title_sort, b.title_sort) ASC", // Default
     };
 
     // Build complete query
@@ -559,20 +744,23 @@ pub async fn list_books_with_filters(
                 sb.book_id,
                 s.name as series_name,
                 sb."index" as series_sequence,
-                ROW_NUMBER() OVER (PARTITION BY sb.book_id ORDER BY sb."index", s.name) as rn
+                sb."order" as series_order,
+                sb.sort_key as series_sort_key,
+                ROW_NUMBER() OVER (PARTITION BY sb.book_id ORDER BY sb.sort_key, s.name) as rn
             FROM SeriesBooks sb
             JOIN Series s ON sb.series_id = s.series_id
         ),
         book_series_first AS (
-            SELECT book_id, series_name, series_sequence
+            SELECT book_id, series_name, series_sequence, series_order, series_sort_key
             FROM book_series
             WHERE rn = 1
         )
         SELECT
             b.book_id,
             b.audible_product_id,
-            b.title,
-            b.subtitle,
+            COALESCE(lt.title, b.title) as title,
+            CASE WHEN lt.title IS NULL THEN b.subtitle ELSE lt.subtitle END as subtitle,
+            CASE WHEN lt.title <> b.title THEN b.title END as alternate_title,
             b.description,
             b.length_in_minutes,
             b.content_type,
@@ -593,6 +781,10 @@ pub async fn list_books_with_filters(
             b.origin_asin,
             b.episode_number,
             b.content_delivery_type,
+            b.benefit_type,
+            b.format_support,
+            b.narrated_by_author,
+            b.full_cast,
             b.created_at,
             b.updated_at,
             COALESCE(b.source, 'audible') as source,
@@ -601,6 +793,7 @@ pub async fn list_books_with_filters(
             book_publishers.publisher,
             book_series_first.series_name,
             book_series_first.series_sequence,
+            book_series_first.series_order,
             lb.date_added as purchase_date
         FROM Books b
         LEFT JOIN LibraryBooks lb ON b.book_id = lb.book_id
@@ -608,6 +801,7 @@ pub async fn list_books_with_filters(
         LEFT JOIN book_narrators ON b.book_id = book_narrators.book_id
         LEFT JOIN book_publishers ON b.book_id = book_publishers.book_id
         LEFT JOIN book_series_first ON b.book_id = book_series_first.book_id
+        LEFT JOIN LocalizedTitles lt ON b.book_id = lt.book_id AND lt.locale = ?
         {}
         {}
         LIMIT ? OFFSET ?
@@ -617,7 +811,8 @@ pub async fn list_books_with_filters(
     );
 
     // Build query with bindings
-    let mut q = sqlx::query_as::<_, BookWithRelations>(&query);
+    // The localized title join comes before the WHERE clause
+    let mut q = sqlx::query_as::<_, BookWithRelations>(&query).bind(params.title_locale.as_deref());
 
     for value in bind_values {
         q = q.bind(value);
@@ -643,11 +838,13 @@ pub async fn count_books_with_filters(
     if let Some(ref search) = params.search_query {
         let pattern = format!("%{}%", search);
         where_clauses.push(
-            "(b.title LIKE ? OR b.subtitle LIKE ? OR book_authors.authors LIKE ? \
+            "(b.title_search LIKE ? OR EXISTS (SELECT 1 FROM LocalizedTitles lts \
+             WHERE lts.book_id = b.book_id AND lts.title_search LIKE ?) \
+             OR book_authors.authors LIKE ? \
              OR book_narrators.narrators LIKE ? OR book_series.series_name LIKE ?)"
         );
-        bind_values.push(pattern.clone());
-        bind_values.push(pattern.clone());
+        bind_values.push(format!("%{}%", fold(search)));
+        bind_values.push(format!("%{}%", fold(search)));
         bind_values.push(pattern.clone());
         bind_values.push(pattern.clone());
         bind_values.push(pattern);
@@ -675,6 +872,20 @@ pub async fn count_books_with_filters(
         bind_values.push(source.clone());
     }
 
+    // Tag filter
+    if let Some(ref tag) = params.tag {
+        where_clauses.push(
+            "EXISTS (SELECT 1 FROM BookTags bt JOIN Tags t ON bt.tag_id = t.tag_id \
+             WHERE bt.book_id = b.book_id AND t.name = ?)"
+        );
+        bind_values.push(crate::storage::tags::normalize_tag(tag).unwrap_or_default());
+    }
+
+    push_range_filters(params, &mut where_clauses, &mut bind_values);
+    push_narration_filters(params, &mut where_clauses, &mut bind_values);
+    push_category_exclusions(&params.excluded_categories, &mut where_clauses, &mut bind_values);
+    where_clauses.push(BOOK_IN_ACTIVE_PROFILE);
+
     let where_clause = if where_clauses.is_empty() {
         String::new()
     } else {
@@ -759,16 +970,82 @@ pub async fn list_all_categories(pool: &SqlitePool) -> Result<Vec<String>> {
 }
 
 /// Search books by title
-pub async fn search_books_by_title(pool: &SqlitePool, query: &str, limit: i64) -> Result<Vec<Book>> {
-    let search_pattern = format!("%{}%", query);
-    let books = sqlx::query_as::<_, Book>(
-        "SELECT * FROM Books WHERE title LIKE ? OR subtitle LIKE ? ORDER BY title LIMIT ?",
-    )
-    .bind(&search_pattern)
-    .bind(&search_pattern)
-    .bind(limit)
-    .fetch_all(pool)
-    .await?;
+///
+/// # Arguments
+/// * `excluded_categories` - Content filter exclusions (see `content_filter`)
+pub async fn search_books_by_title(
+    pool: &SqlitePool,
+    query: &str,
+    limit: i64,
+    excluded_categories: &[String],
+) -> Result<Vec<Book>> {
+    let mut where_clauses = vec!["b.title_search LIKE ?"];
+    let mut bind_values = vec![format!("%{}%", fold(query))];
+    push_category_exclusions(excluded_categories, &mut where_clauses, &mut bind_values);
+    where_clauses.push(BOOK_IN_ACTIVE_PROFILE);
+
+    let sql = format!(
+        "SELECT b.* FROM Books b WHERE {} ORDER BY b.title_sort LIMIT ?",
+        where_clauses.join(" AND ")
+    );
+    let mut q = sqlx::query_as::<_, Book>(&sql);
+    for value in bind_values {
+        q = q.bind(value);
+    }
+
+    let books = q.bind(limit).fetch_all(pool).await?;
+
+    Ok(books)
+}
+
+/// BooksSearch column weights for ranking: title, subtitle, description,
+/// authors, narrators, series
+const FTS_WEIGHTS: &str = "10.0, 4.0, 1.0, 8.0, 3.0, 6.0";
+
+/// Full-text search over titles, subtitles, descriptions, authors,
+/// narrators and series, best matches first
+///
+/// Every word of `query` must match the start of a word in one of the
+/// fields (case and accent insensitive), so "harr pot" finds "Harry
+/// Potter". Matches in titles and authors rank above matches in
+/// descriptions. FTS5 query syntax is not interpreted.
+///
+/// # Arguments
+/// * `excluded_categories` - Content filter exclusions (see `content_filter`)
+pub async fn search_books_fts(
+    pool: &SqlitePool,
+    query: &str,
+    limit: i64,
+    excluded_categories: &[String],
+) -> Result<Vec<Book>> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut where_clauses = vec!["BooksSearch MATCH ?"];
+    let mut bind_values = vec![terms.join(" ")];
+    push_category_exclusions(excluded_categories, &mut where_clauses, &mut bind_values);
+    where_clauses.push(BOOK_IN_ACTIVE_PROFILE);
+
+    let sql = format!(
+        "SELECT b.* FROM BooksSearch \
+         JOIN Books b ON b.book_id = BooksSearch.rowid \
+         WHERE {} \
+         ORDER BY bm25(BooksSearch, {}), b.title_sort \
+         LIMIT ?",
+        where_clauses.join(" AND "),
+        FTS_WEIGHTS
+    );
+    let mut q = sqlx::query_as::<_, Book>(&sql);
+    for value in bind_values {
+        q = q.bind(value);
+    }
+
+    let books = q.bind(limit).fetch_all(pool).await?;
 
     Ok(books)
 }
@@ -788,6 +1065,8 @@ pub async fn delete_book(pool: &SqlitePool, book_id: i64) -> Result<()> {
 // ============================================================================
 
 /// Insert a new library book entry
+///
+/// The account is also recorded as owning the book (LibraryBookAccounts).
 pub async fn insert_library_book(pool: &SqlitePool, library_book: &NewLibraryBook) -> Result<()> {
     sqlx::query(
         r#"
@@ -800,6 +1079,16 @@ pub async fn insert_library_book(pool: &SqlitePool, library_book: &NewLibraryBoo
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO LibraryBookAccounts (book_id, account, date_added)
+        SELECT book_id, account, date_added FROM LibraryBooks WHERE book_id = ?
+        "#,
+    )
+    .bind(library_book.book_id)
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
 
@@ -814,9 +1103,16 @@ pub async fn find_library_book(pool: &SqlitePool, book_id: i64) -> Result<Option
 }
 
 /// List all library books for an account
+///
+/// Includes books that another account owns as well.
 pub async fn list_library_books_by_account(pool: &SqlitePool, account: &str) -> Result<Vec<LibraryBook>> {
     let books = sqlx::query_as::<_, LibraryBook>(
-        "SELECT * FROM LibraryBooks WHERE account = ? AND is_deleted = 0 ORDER BY date_added DESC",
+        r#"
+        SELECT book_id, date_added, account, is_deleted, absent_from_last_scan
+        FROM LibraryBookAccounts
+        WHERE account = ? AND is_deleted = 0
+        ORDER BY date_added DESC
+        "#,
     )
     .bind(account)
     .fetch_all(pool)
@@ -825,6 +1121,34 @@ pub async fn list_library_books_by_account(pool: &SqlitePool, account: &str) ->
     Ok(books)
 }
 
+/// Books owned by an account, by title
+///
+/// Unlike the library views, this isn't limited to the active profile, and
+/// it includes books another account owns as well.
+pub async fn list_books_for_account(
+    pool: &SqlitePool,
+    account_id: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Book>> {
+    let books = sqlx::query_as::<_, Book>(
+        r#"
+        SELECT b.* FROM Books b
+        JOIN LibraryBookAccounts lba ON lba.book_id = b.book_id
+        WHERE lba.account = ? AND lba.is_deleted = 0
+        ORDER BY b.title_sort
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(account_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(books)
+}
+
 // ============================================================================
 // USER DEFINED ITEM QUERIES
 // ============================================================================
@@ -856,6 +1180,7 @@ pub async fn find_user_defined_item(pool: &SqlitePool, book_id: i64) -> Result<O
 
 /// Update user defined item
 pub async fn update_user_defined_item(pool: &SqlitePool, item: &UserDefinedItem) -> Result<()> {
+    let mut tx = pool.begin().await?;
     sqlx::query(
         r#"
         UPDATE UserDefinedItems SET
@@ -879,9 +1204,13 @@ pub async fn update_user_defined_item(pool: &SqlitePool, item: &UserDefinedItem)
     .bind(&item.last_downloaded_file_version)
     .bind(item.is_finished)
     .bind(item.book_id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    // Keep Tags/BookTags in step with the legacy column
+    crate::storage::tags::replace_book_tags(&mut tx, item.book_id, &item.get_tags()).await?;
+    tx.commit().await?;
+
     Ok(())
 }
 
@@ -919,6 +1248,61 @@ pub async fn upsert_contributor(pool: &SqlitePool, contributor: &NewContributor)
     Ok(result.last_insert_rowid())
 }
 
+/// Rows per statement in the batch upserts (two bound values per row, kept
+/// under SQLite's 999 parameter limit)
+const BATCH_ROWS: usize = 400;
+
+/// Find or create many contributors at once, keyed by name
+///
+/// Names that already exist keep their row whatever their Audible ID (the
+/// lowest contributor_id wins if a name has several); the rest are inserted
+/// with the first Audible ID given for them. One lookup and at most one
+/// insert per 400 names, in a single transaction.
+///
+/// Returns contributor_id by name
+pub async fn upsert_contributors_batch(
+    pool: &SqlitePool,
+    contributors: &[NewContributor],
+) -> Result<HashMap<String, i64>> {
+    let mut unique: Vec<&NewContributor> = Vec::new();
+    let mut seen: HashSet<&str> = HashSet::new();
+    for contributor in contributors {
+        if seen.insert(contributor.name.as_str()) {
+            unique.push(contributor);
+        }
+    }
+
+    let mut ids: HashMap<String, i64> = HashMap::with_capacity(unique.len());
+    let mut tx = pool.begin().await?;
+    for chunk in unique.chunks(BATCH_ROWS) {
+        let mut lookup = QueryBuilder::<Sqlite>::new(
+            "SELECT name, MIN(contributor_id) FROM Contributors WHERE name IN (",
+        );
+        let mut names = lookup.separated(", ");
+        for contributor in chunk {
+            names.push_bind(&contributor.name);
+        }
+        lookup.push(") GROUP BY name");
+        let existing: Vec<(String, i64)> = lookup.build_query_as().fetch_all(&mut *tx).await?;
+        ids.extend(existing);
+
+        let missing: Vec<&&NewContributor> = chunk.iter().filter(|c| !ids.contains_key(&c.name)).collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let mut insert = QueryBuilder::<Sqlite>::new("INSERT INTO Contributors (name, audible_contributor_id) ");
+        insert.push_values(missing, |mut row, contributor| {
+            row.push_bind(&contributor.name).push_bind(&contributor.audible_contributor_id);
+        });
+        insert.push(" RETURNING name, contributor_id");
+        let inserted: Vec<(String, i64)> = insert.build_query_as().fetch_all(&mut *tx).await?;
+        ids.extend(inserted);
+    }
+    tx.commit().await?;
+
+    Ok(ids)
+}
+
 /// Find contributors by book ID and role
 pub async fn find_contributors_by_book(pool: &SqlitePool, book_id: i64, role: i32) -> Result<Vec<Contributor>> {
     let contributors = sqlx::query_as::<_, Contributor>(
@@ -1010,7 +1394,49 @@ pub async fn upsert_series(pool: &SqlitePool, series: &NewSeries) -> Result<i64>
     Ok(result.last_insert_rowid())
 }
 
+/// Insert or update many series at once, keyed by Audible series ID
+///
+/// Like `upsert_series`, a given name replaces the stored one and a missing
+/// name keeps it. One statement per 400 series, in a single transaction.
+///
+/// Returns series_id by audible_series_id
+pub async fn upsert_series_batch(pool: &SqlitePool, series: &[NewSeries]) -> Result<HashMap<String, i64>> {
+    // Last name given wins, as with repeated upsert_series calls
+    let mut unique: Vec<&NewSeries> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for entry in series {
+        match positions.get(entry.audible_series_id.as_str()) {
+            Some(&i) if entry.name.is_some() => unique[i] = entry,
+            Some(_) => {}
+            None => {
+                positions.insert(entry.audible_series_id.as_str(), unique.len());
+                unique.push(entry);
+            }
+        }
+    }
+
+    let mut ids: HashMap<String, i64> = HashMap::with_capacity(unique.len());
+    let mut tx = pool.begin().await?;
+    for chunk in unique.chunks(BATCH_ROWS) {
+        let mut upsert = QueryBuilder::<Sqlite>::new("INSERT INTO Series (audible_series_id, name) ");
+        upsert.push_values(chunk, |mut row, entry| {
+            row.push_bind(&entry.audible_series_id).push_bind(&entry.name);
+        });
+        upsert.push(
+            " ON CONFLICT(audible_series_id) DO UPDATE SET name = COALESCE(excluded.name, Series.name) \
+             RETURNING audible_series_id, series_id",
+        );
+        let rows: Vec<(String, i64)> = upsert.build_query_as().fetch_all(&mut *tx).await?;
+        ids.extend(rows);
+    }
+    tx.commit().await?;
+
+    Ok(ids)
+}
+
 /// Link book to series
+///
+/// The sort key is computed from `order` (see `series_order`).
 pub async fn add_book_to_series(
     pool: &SqlitePool,
     series_id: i64,
@@ -1018,16 +1444,18 @@ pub async fn add_book_to_series(
     order: Option<String>,
     index: f32,
 ) -> Result<()> {
+    let sort_key = parse_sequence(order.as_deref().unwrap_or_default()).sort_key;
     sqlx::query(
         r#"
-        INSERT OR REPLACE INTO SeriesBooks (series_id, book_id, "order", "index")
-        VALUES (?, ?, ?, ?)
+        INSERT OR REPLACE INTO SeriesBooks (series_id, book_id, "order", "index", sort_key)
+        VALUES (?, ?, ?, ?, ?)
         "#,
     )
     .bind(series_id)
     .bind(book_id)
     .bind(order)
     .bind(index)
+    .bind(sort_key)
     .execute(pool)
     .await?;
 
@@ -1043,7 +1471,7 @@ pub async fn find_series_by_book(pool: &SqlitePool, book_id: i64) -> Result<Vec<
         FROM Series s
         INNER JOIN SeriesBooks sb ON s.series_id = sb.series_id
         WHERE sb.book_id = ?
-        ORDER BY sb."index"
+        ORDER BY sb.sort_key
         "#,
     )
     .bind(book_id)
@@ -1178,41 +1606,56 @@ pub async fn find_supplements_by_book(pool: &SqlitePool, book_id: i64) -> Result
 /// Upsert book (insert or update if ASIN exists)
 ///
 /// This is a common operation when syncing from Audible API.
-/// Returns the book_id (either existing or newly created).
+/// Returns the book_id (either existing or newly created). A book inserted
+/// by a concurrent sync between the lookup and the insert is updated
+/// instead of failing on the unique ASIN.
 pub async fn upsert_book(pool: &SqlitePool, book: &NewBook) -> Result<i64> {
     // Check if book exists
     if let Some(existing) = find_book_by_asin(pool, &book.audible_product_id).await? {
-        // Update existing book
-        let mut updated = existing;
-        updated.title = book.title.clone();
-        updated.subtitle = book.subtitle.clone();
-        updated.description = book.description.clone();
-        updated.length_in_minutes = book.length_in_minutes;
-        updated.content_type = book.content_type;
-        updated.picture_id = book.picture_id.clone();
-        updated.picture_large = book.picture_large.clone();
-        updated.is_abridged = book.is_abridged;
-        updated.is_spatial = book.is_spatial;
-        updated.date_published = book.date_published;
-        updated.language = book.language.clone();
-        updated.rating_overall = book.rating_overall;
-        updated.rating_performance = book.rating_performance;
-        updated.rating_story = book.rating_story;
-        updated.updated_at = Utc::now();
-
-        update_book(pool, &updated).await?;
-        Ok(updated.book_id)
-    } else {
-        // Insert new book
-        let book_id = insert_book(pool, book).await?;
-
-        // Create default UserDefinedItem for the book
-        insert_user_defined_item(pool, &NewUserDefinedItem::new(book_id)).await?;
+        return update_book_from(pool, existing, book).await;
+    }
 
-        Ok(book_id)
+    match insert_book(pool, book).await {
+        Ok(book_id) => {
+            // Create default UserDefinedItem for the book
+            insert_user_defined_item(pool, &NewUserDefinedItem::new(book_id)).await?;
+            Ok(book_id)
+        }
+        Err(LibationError::SqlxError(error))
+            if matches!(&error, sqlx::Error::Database(e) if e.is_unique_violation()) =>
+        {
+            match find_book_by_asin(pool, &book.audible_product_id).await? {
+                Some(existing) => update_book_from(pool, existing, book).await,
+                None => Err(LibationError::SqlxError(error)),
+            }
+        }
+        Err(e) => Err(e),
     }
 }
 
+/// Update an existing book with synced metadata
+async fn update_book_from(pool: &SqlitePool, existing: Book, book: &NewBook) -> Result<i64> {
+    let mut updated = existing;
+    updated.title = book.title.clone();
+    updated.subtitle = book.subtitle.clone();
+    updated.description = book.description.clone();
+    updated.length_in_minutes = book.length_in_minutes;
+    updated.content_type = book.content_type;
+    updated.picture_id = book.picture_id.clone();
+    updated.picture_large = book.picture_large.clone();
+    updated.is_abridged = book.is_abridged;
+    updated.is_spatial = book.is_spatial;
+    updated.date_published = book.date_published;
+    updated.language = book.language.clone();
+    updated.rating_overall = book.rating_overall;
+    updated.rating_performance = book.rating_performance;
+    updated.rating_story = book.rating_story;
+    updated.updated_at = Utc::now();
+
+    update_book(pool, &updated).await?;
+    Ok(updated.book_id)
+}
+
 /// Clear all library data (for testing)
 ///
 /// Deletes all books and related data from the database.
@@ -1268,11 +1711,16 @@ pub async fn clear_download_state(pool: &SqlitePool) -> Result<i64> {
 /// # Returns
 /// * `Ok(file_path)` - Returns the file path if it existed and was deleted, None otherwise
 /// * `Err` if book not found or database error
+/// * `Err(PermissionDenied)` with `delete_file` if `delete_files` is disabled (see `permissions`)
 pub async fn clear_book_download_state(
     pool: &SqlitePool,
     asin: &str,
     delete_file: bool,
 ) -> Result<Option<String>> {
+    if delete_file {
+        crate::permissions::require(crate::permissions::Permission::DeleteFiles)?;
+    }
+
     // First verify the book exists
     let book = find_book_by_asin(pool, asin).await?;
     if book.is_none() {
@@ -1307,7 +1755,7 @@ pub async fn clear_book_download_state(
                     Some(path.clone())
                 }
                 Err(e) => {
-                    eprintln!("[clear_book_download_state] Failed to delete file {}: {}", path, e);
+                    trace_eprintln!("[clear_book_download_state] Failed to delete file {}: {}", path, e);
                     None
                 }
             }
@@ -1377,28 +1825,84 @@ pub async fn get_book_file_path(pool: &SqlitePool, asin: &str) -> Result<Option<
     Ok(file_path)
 }
 
+/// Get the SHA-256 recorded when a book's file was downloaded
+///
+/// The digest is computed while streaming, so integrity checks can compare
+/// against it without hashing the download twice. Companion audio is
+/// looked up in BookFiles, where the digest is kept with the file record;
+/// the liberated book itself has no BookFiles row and falls back to its
+/// most recent completed download task. Returns None if the book hasn't
+/// been downloaded or was downloaded before digests were recorded.
+pub async fn get_book_file_sha256(pool: &SqlitePool, asin: &str) -> Result<Option<String>> {
+    let sha256: Option<String> = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(
+            (SELECT sha256
+             FROM BookFiles
+             WHERE source_asin = ? AND sha256 IS NOT NULL
+             ORDER BY created_at DESC
+             LIMIT 1),
+            (SELECT sha256
+             FROM DownloadTasks
+             WHERE asin = ? AND status = 'completed'
+             ORDER BY completed_at DESC
+             LIMIT 1)
+        )
+        "#,
+    )
+    .bind(asin)
+    .bind(asin)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(sha256)
+}
+
+/// Find books whose downloaded file has the given SHA-256 (for dedupe)
+pub async fn find_asins_by_file_sha256(pool: &SqlitePool, sha256: &str) -> Result<Vec<String>> {
+    let sha256 = sha256.to_ascii_lowercase();
+    let asins = sqlx::query_scalar(
+        r#"
+        SELECT asin
+        FROM DownloadTasks
+        WHERE sha256 = ? AND status = 'completed'
+        UNION
+        SELECT source_asin
+        FROM BookFiles
+        WHERE sha256 = ? AND source_asin IS NOT NULL
+        ORDER BY asin
+        "#,
+    )
+    .bind(&sha256)
+    .bind(&sha256)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(asins)
+}
+
 /// Set the file path for a book by creating a manually completed download task.
 ///
 /// This allows users to mark a book as downloaded by associating it with an
 /// existing audio file on disk. Creates a download task with status "completed".
 ///
 /// # Arguments
-/// * `pool` - Database connection pool
+/// * `executor` - Database connection pool, or a transaction
 /// * `asin` - Audible product ID (ASIN)
 /// * `title` - Book title
 /// * `file_path` - Absolute path to the audio file
 ///
 /// # Returns
 /// * `Ok(task_id)` - ID of the created download task
-pub async fn set_book_file_path(
-    pool: &SqlitePool,
+pub async fn set_book_file_path<'e>(
+    executor: impl Executor<'e, Database = Sqlite>,
     asin: &str,
     title: &str,
     file_path: &str,
 ) -> Result<String> {
     use uuid::Uuid;
     let task_id = Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().to_rfc3339();
+    let now = crate::storage::dates::now();
 
     sqlx::query(
         r#"
@@ -1417,16 +1921,24 @@ pub async fn set_book_file_path(
     .bind(&now)
     .bind(&now)
     .bind(&now)
-    .execute(pool)
+    .execute(executor)
     .await?;
 
     Ok(task_id)
 }
 
+/// Delete all library data
+///
+/// # Errors
+/// PermissionDenied if `clear_library` is disabled (see `permissions`)
 pub async fn clear_library(pool: &SqlitePool) -> Result<()> {
+    crate::permissions::require(crate::permissions::Permission::ClearLibrary)?;
+
     // Delete in correct order to respect foreign keys
+    sqlx::query("DELETE FROM LibraryBookAccounts").execute(pool).await?;
     sqlx::query("DELETE FROM LibraryBooks").execute(pool).await?;
     sqlx::query("DELETE FROM SeriesBooks").execute(pool).await?;
+    sqlx::query("DELETE FROM PodcastEpisodes").execute(pool).await?;
     sqlx::query("DELETE FROM BookContributors").execute(pool).await?;
     sqlx::query("DELETE FROM BookCategories").execute(pool).await?;
     sqlx::query("DELETE FROM UserDefinedItems").execute(pool).await?;
@@ -1470,8 +1982,8 @@ pub async fn insert_librivox_book(
             content_type, locale, picture_large, source,
             is_abridged, is_spatial, language,
             rating_overall, rating_performance, rating_story,
-            is_finished, is_downloadable, is_ayce
-        ) VALUES (?, ?, NULL, ?, ?, 1, ?, ?, 'librivox', 0, 0, ?, 0.0, 0.0, 0.0, 0, 1, 0)
+            is_finished, is_downloadable, is_ayce, title_sort, title_search
+        ) VALUES (?, ?, NULL, ?, ?, 1, ?, ?, 'librivox', 0, 0, ?, 0.0, 0.0, 0.0, 0, 1, 0, ?, ?)
         "#,
     )
     .bind(&product_id)
@@ -1481,6 +1993,8 @@ pub async fn insert_librivox_book(
     .bind(language)
     .bind(cover_url)
     .bind(language)
+    .bind(title_sort_key(title))
+    .bind(title_search_key(title, None))
     .execute(pool)
     .await?;
 
@@ -1513,9 +2027,67 @@ pub async fn insert_librivox_book(
     Ok(book_id)
 }
 
+/// Record the download format negotiated for a book
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `asin` - Audible product ID (ASIN)
+/// * `format` - Format string (see `FileType::as_str`)
+///
+/// # Returns
+/// * `Ok(true)` if the book exists and was updated
+pub async fn set_book_download_format(pool: &SqlitePool, asin: &str, format: &str) -> Result<bool> {
+    let result = sqlx::query("UPDATE Books SET download_format = ? WHERE audible_product_id = ?")
+        .bind(format)
+        .bind(asin)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the download format last negotiated for a book
+pub async fn get_book_download_format(pool: &SqlitePool, asin: &str) -> Result<Option<String>> {
+    let format: Option<Option<String>> =
+        sqlx::query_scalar("SELECT download_format FROM Books WHERE audible_product_id = ?")
+            .bind(asin)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(format.flatten())
+}
+
+/// Get the benefit type recorded for a book at sync time
+///
+/// Returns `None` if the book isn't in the database or predates benefit tracking.
+pub async fn get_book_benefit_type(pool: &SqlitePool, asin: &str) -> Result<Option<BenefitType>> {
+    let benefit: Option<Option<String>> =
+        sqlx::query_scalar("SELECT benefit_type FROM Books WHERE audible_product_id = ?")
+            .bind(asin)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(benefit.flatten().and_then(|s| s.parse().ok()))
+}
+
+/// Get the format support recorded for a book at sync time
+///
+/// Returns `None` if the book isn't in the database or predates format tracking.
+pub async fn get_book_format_support(pool: &SqlitePool, asin: &str) -> Result<Option<FormatSupport>> {
+    let support: Option<Option<String>> =
+        sqlx::query_scalar("SELECT format_support FROM Books WHERE audible_product_id = ?")
+            .bind(asin)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(support.flatten().and_then(|s| s.parse().ok()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::content::CompanionKind;
+    use crate::storage::book_files::{add_book_file, NewBookFile};
     use crate::storage::database::Database;
 
     #[tokio::test]
@@ -1563,6 +2135,33 @@ mod tests {
 
         let found = find_book_by_id(db.pool(), book_id1).await.expect("Failed to find book");
         assert_eq!(found.unwrap().title, "Test Book Updated");
+
+        // The same ASIN with different case or padding is the same book
+        let mut variant = new_book.clone();
+        variant.audible_product_id = " b012345679 ".to_string();
+        assert_eq!(upsert_book(db.pool(), &variant).await.unwrap(), book_id1);
+        assert!(insert_book(db.pool(), &variant).await.is_err());
+
+        let mut fresh = new_book.clone();
+        fresh.audible_product_id = "b0123456aa".to_string();
+        let fresh_id = upsert_book(db.pool(), &fresh).await.unwrap();
+        let stored = find_book_by_id(db.pool(), fresh_id).await.unwrap().unwrap();
+        assert_eq!(stored.audible_product_id, "B0123456AA");
+    }
+
+    #[tokio::test]
+    async fn test_book_download_format() {
+        let db = Database::new_in_memory().await.unwrap();
+        let new_book = NewBook::new("B000FMT001".to_string(), "Format".to_string(), "us".to_string());
+        insert_book(db.pool(), &new_book).await.unwrap();
+
+        assert_eq!(get_book_download_format(db.pool(), "B000FMT001").await.unwrap(), None);
+        assert!(set_book_download_format(db.pool(), "B000FMT001", "aaxc").await.unwrap());
+        assert_eq!(
+            get_book_download_format(db.pool(), "B000FMT001").await.unwrap(),
+            Some("aaxc".to_string())
+        );
+        assert!(!set_book_download_format(db.pool(), "B000MISSING", "aax").await.unwrap());
     }
 
     #[tokio::test]
@@ -1584,6 +2183,42 @@ mod tests {
         assert_eq!(contributor_id, contributor_id2);
     }
 
+    #[tokio::test]
+    async fn test_batch_contributor_and_series_upserts() {
+        let db = Database::new_in_memory().await.expect("Failed to create database");
+        let existing = upsert_contributor(db.pool(), &NewContributor::new("Ray Porter".to_string())).await.unwrap();
+        let series_id = upsert_series(db.pool(), &NewSeries {
+            audible_series_id: "S1".to_string(),
+            name: Some("Bobiverse".to_string()),
+        }).await.unwrap();
+
+        // More names than one statement takes, with repeats
+        let mut contributors: Vec<NewContributor> =
+            (0..450).map(|i| NewContributor::new(format!("Host {}", i))).collect();
+        contributors.push(NewContributor {
+            name: "Ray Porter".to_string(),
+            audible_contributor_id: Some("B00RP".to_string()),
+        });
+        contributors.push(NewContributor::new("Host 7".to_string()));
+        let ids = upsert_contributors_batch(db.pool(), &contributors).await.unwrap();
+        assert_eq!(ids.len(), 451);
+        assert_eq!(ids["Ray Porter"], existing);
+        assert_eq!(ids["Host 7"], upsert_contributor(db.pool(), &NewContributor::new("Host 7".to_string())).await.unwrap());
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM Contributors WHERE contributor_id > 0")
+            .fetch_one(db.pool()).await.unwrap();
+        assert_eq!(rows, 451);
+
+        let series = vec![
+            NewSeries { audible_series_id: "S1".to_string(), name: None },
+            NewSeries { audible_series_id: "S2".to_string(), name: Some("Expeditionary Force".to_string()) },
+        ];
+        let ids = upsert_series_batch(db.pool(), &series).await.unwrap();
+        assert_eq!(ids["S1"], series_id);
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM Series ORDER BY name")
+            .fetch_all(db.pool()).await.unwrap();
+        assert_eq!(names, vec!["Bobiverse", "Expeditionary Force"]);
+    }
+
     #[tokio::test]
     async fn test_list_books_with_filters_sorts_by_length() {
         let db = Database::new_in_memory().await.expect("Failed to create database");
@@ -1644,4 +2279,209 @@ mod tests {
             vec!["Long Book", "Medium Book", "Short Book"]
         );
     }
+
+    #[tokio::test]
+    async fn test_title_sort_and_folded_search() {
+        let db = Database::new_in_memory().await.expect("Failed to create database");
+
+        for (asin, title) in [
+            ("B000000011", "The Hobbit"),
+            ("B000000012", "Dune"),
+            ("B000000013", "Les Misérables"),
+        ] {
+            let book = NewBook::new(asin.to_string(), title.to_string(), "us".to_string());
+            let book_id = insert_book(db.pool(), &book).await.expect("Failed to insert book");
+            insert_library_book(
+                db.pool(),
+                &NewLibraryBook {
+                    book_id,
+                    account: "test@example.com".to_string(),
+                },
+            )
+            .await
+            .expect("Failed to insert library book");
+        }
+
+        let params = BookQueryParams {
+            sort_field: Some(SortField::Title),
+            sort_direction: Some(SortDirection::Asc),
+            limit: 10,
+            offset: 0,
+            ..Default::default()
+        };
+        let sorted = list_books_with_filters(db.pool(), &params)
+            .await
+            .expect("Failed to list books");
+        assert_eq!(
+            sorted.iter().map(|book| book.title.as_str()).collect::<Vec<_>>(),
+            vec!["Dune", "The Hobbit", "Les Misérables"]
+        );
+
+        let params = BookQueryParams {
+            search_query: Some("MISERABLES".to_string()),
+            ..params
+        };
+        let found = list_books_with_filters(db.pool(), &params)
+            .await
+            .expect("Failed to search books");
+        assert_eq!(found.len(), 1);
+        assert_eq!(count_books_with_filters(db.pool(), &params).await.unwrap(), 1);
+
+        let found = search_books_by_title(db.pool(), "misérables", 10, &[]).await.unwrap();
+        assert_eq!(found[0].title, "Les Misérables");
+    }
+
+    #[tokio::test]
+    async fn test_search_books_fts() {
+        let db = Database::new_in_memory().await.expect("Failed to create database");
+        let pool = db.pool();
+
+        let mut potter = NewBook::new("B000000031".to_string(), "Harry Potter and the Philosopher's Stone".to_string(), "us".to_string());
+        potter.description = "A boy learns he is a wizard.".to_string();
+        let potter_id = insert_book(pool, &potter).await.unwrap();
+        let mut guide = NewBook::new("B000000032".to_string(), "Fantastic Beasts".to_string(), "us".to_string());
+        guide.description = "A companion to the Harry Potter books.".to_string();
+        let guide_id = insert_book(pool, &guide).await.unwrap();
+
+        let rowling = upsert_contributor(pool, &NewContributor::new("J.K. Rowling".to_string())).await.unwrap();
+        add_book_contributor(pool, potter_id, rowling, 1, 0).await.unwrap();
+        let fry = upsert_contributor(pool, &NewContributor::new("Stephen Fry".to_string())).await.unwrap();
+        add_book_contributor(pool, potter_id, fry, 2, 0).await.unwrap();
+        let mut series = NewSeries::new("SERIES1".to_string());
+        series.name = Some("Wizarding World".to_string());
+        let series_id = upsert_series(pool, &series).await.unwrap();
+        add_book_to_series(pool, series_id, guide_id, Some("1".to_string()), 1.0).await.unwrap();
+
+        let titles = |books: Vec<Book>| books.into_iter().map(|b| b.title).collect::<Vec<_>>();
+
+        // Prefixes, and title matches rank above description matches
+        let found = titles(search_books_fts(pool, "harr pot", 10, &[]).await.unwrap());
+        assert_eq!(found, vec!["Harry Potter and the Philosopher's Stone", "Fantastic Beasts"]);
+        assert_eq!(titles(search_books_fts(pool, "rowling fry", 10, &[]).await.unwrap()).len(), 1);
+        assert_eq!(titles(search_books_fts(pool, "wizarding", 10, &[]).await.unwrap()), vec!["Fantastic Beasts"]);
+        assert!(search_books_fts(pool, "  ", 10, &[]).await.unwrap().is_empty());
+        assert!(search_books_fts(pool, "\"unbalanced", 10, &[]).await.unwrap().is_empty());
+
+        // The index follows renames, new links and deletions
+        sqlx::query("UPDATE Series SET name = 'Hogwarts Library' WHERE series_id = ?")
+            .bind(series_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert!(search_books_fts(pool, "wizarding", 10, &[]).await.unwrap().is_empty());
+        assert_eq!(search_books_fts(pool, "hogwarts", 10, &[]).await.unwrap().len(), 1);
+        delete_book(pool, potter_id).await.unwrap();
+        assert!(search_books_fts(pool, "rowling", 10, &[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_date_and_runtime_range_filters() {
+        let db = Database::new_in_memory().await.expect("Failed to create database");
+
+        for (asin, title, published, minutes, purchased) in [
+            ("B000000021", "Bought 2022", "2015-06-01", 300, "2022-12-31 23:59:59"),
+            ("B000000022", "Bought 2023", "2023-03-15", 600, "2023-07-04T10:00:00+00:00"),
+            ("B000000023", "Bought 2024", "2023-12-31", 900, "2024-01-01 00:00:00"),
+        ] {
+            let mut book = NewBook::new(asin.to_string(), title.to_string(), "us".to_string());
+            book.date_published = Some(NaiveDate::parse_from_str(published, "%Y-%m-%d").unwrap());
+            book.length_in_minutes = minutes;
+            let book_id = insert_book(db.pool(), &book).await.expect("Failed to insert book");
+            insert_library_book(
+                db.pool(),
+                &NewLibraryBook {
+                    book_id,
+                    account: "test@example.com".to_string(),
+                },
+            )
+            .await
+            .expect("Failed to insert library book");
+            sqlx::query("UPDATE LibraryBooks SET date_added = ? WHERE book_id = ?")
+                .bind(purchased)
+                .bind(book_id)
+                .execute(db.pool())
+                .await
+                .unwrap();
+        }
+
+        let titles = |books: Vec<BookWithRelations>| {
+            books.into_iter().map(|book| book.title).collect::<Vec<_>>()
+        };
+
+        let params = BookQueryParams {
+            purchase_date: DateRange::year(2023),
+            limit: 10,
+            offset: 0,
+            ..Default::default()
+        };
+        assert_eq!(titles(list_books_with_filters(db.pool(), &params).await.unwrap()), vec!["Bought 2023"]);
+        assert_eq!(count_books_with_filters(db.pool(), &params).await.unwrap(), 1);
+
+        // Upper bound is inclusive of the whole day
+        let params = BookQueryParams {
+            purchase_date: None,
+            release_date: Some(DateRange {
+                from: NaiveDate::from_ymd_opt(2023, 1, 1),
+                to: NaiveDate::from_ymd_opt(2023, 12, 31),
+            }),
+            ..params
+        };
+        assert_eq!(
+            titles(list_books_with_filters(db.pool(), &params).await.unwrap()),
+            vec!["Bought 2023", "Bought 2024"]
+        );
+
+        let params = BookQueryParams {
+            runtime: Some(RuntimeRange {
+                min_minutes: None,
+                max_minutes: Some(600),
+            }),
+            ..params
+        };
+        assert_eq!(titles(list_books_with_filters(db.pool(), &params).await.unwrap()), vec!["Bought 2023"]);
+        assert_eq!(count_books_with_filters(db.pool(), &params).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_book_file_sha256() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        set_book_file_path(pool, "B0HASHED1", "One", "/books/one.m4b").await.unwrap();
+        set_book_file_path(pool, "B0HASHED2", "Two", "/books/two.m4b").await.unwrap();
+        assert_eq!(get_book_file_sha256(pool, "B0HASHED1").await.unwrap(), None);
+
+        let digest = "ab".repeat(32);
+        sqlx::query("UPDATE DownloadTasks SET sha256 = ?")
+            .bind(&digest)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        assert_eq!(get_book_file_sha256(pool, "B0HASHED1").await.unwrap(), Some(digest.clone()));
+        assert_eq!(
+            find_asins_by_file_sha256(pool, &digest.to_uppercase()).await.unwrap(),
+            vec!["B0HASHED1", "B0HASHED2"]
+        );
+        assert_eq!(get_book_file_sha256(pool, "MISSING").await.unwrap(), None);
+
+        // Companion files have their digest in BookFiles
+        let file = NewBookFile {
+            kind: CompanionKind::AuthorInterview,
+            source_asin: Some("B0INTERVW1".to_string()),
+            path: "/books/one/Interview.m4b".to_string(),
+            size_bytes: 9,
+            task_id: None,
+            sha256: Some(digest.clone()),
+        };
+        insert_book(pool, &NewBook::new("B0HASHED1".to_string(), "One".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        add_book_file(pool, "B0HASHED1", &file).await.unwrap();
+        assert_eq!(get_book_file_sha256(pool, "B0INTERVW1").await.unwrap(), Some(digest.clone()));
+        assert_eq!(
+            find_asins_by_file_sha256(pool, &digest).await.unwrap(),
+            vec!["B0HASHED1", "B0HASHED2", "B0INTERVW1"]
+        );
+    }
 }
@@ -0,0 +1,230 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Write pauses for OS backups
+//!
+//! Android auto-backup copies the database file while the app may be
+//! writing, and a copy taken mid-write (or without the WAL) restores as a
+//! corrupt or stale library. The host brackets the backup window with
+//! `begin_quiesce` and `end_quiesce`:
+//!
+//! 1. `begin_quiesce` checkpoints the WAL into the database file and takes
+//!    the write lock on a connection of its own (`BEGIN IMMEDIATE`),
+//!    waiting up to `wait` for writes in flight to finish. The file on disk
+//!    is then complete and stays unchanged.
+//! 2. While quiesced, writers in any process block on the lock. They wait
+//!    up to the connections' busy timeout (30 s) and then fail as locked.
+//! 3. `end_quiesce` releases the lock. A quiesce the host forgets to end is
+//!    released after `max_duration`, which defaults to less than the busy
+//!    timeout, so writes paused by it resume instead of failing.
+//!
+//! Readers are never blocked.
+
+use crate::error::{LibationError, Result};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Default time to wait for writes in flight
+pub const DEFAULT_QUIESCE_WAIT: Duration = Duration::from_secs(10);
+
+/// Default release of a quiesce that isn't ended (below the 30 s busy timeout)
+pub const DEFAULT_MAX_QUIESCE: Duration = Duration::from_secs(25);
+
+/// Longest `max_duration` accepted
+pub const MAX_QUIESCE: Duration = Duration::from_secs(5 * 60);
+
+/// A held write lock
+struct Quiesce {
+    id: u64,
+    conn: SqliteConnection,
+    status: QuiesceStatus,
+}
+
+/// Quiesced databases by path
+static QUIESCED: Mutex<Option<HashMap<PathBuf, Quiesce>>> = Mutex::new(None);
+
+static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+/// A quiesced database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct QuiesceStatus {
+    pub db_path: String,
+    pub since: DateTime<Utc>,
+    /// When the lock is released if `end_quiesce` isn't called
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Pause writes to the database at `db_path` and checkpoint its WAL
+///
+/// # Arguments
+/// * `wait` - How long to wait for the write lock
+/// * `max_duration` - Release after this long if not ended (at most `MAX_QUIESCE`)
+///
+/// # Errors
+/// - InvalidState if the database is already quiesced
+/// - InvalidInput if `max_duration` is over `MAX_QUIESCE`
+/// - A locked-database error if writes didn't finish within `wait`, or
+///   InvalidState if they kept writing past it
+pub async fn begin_quiesce(db_path: &Path, wait: Duration, max_duration: Duration) -> Result<QuiesceStatus> {
+    if max_duration > MAX_QUIESCE {
+        return Err(LibationError::invalid_input(format!(
+            "A quiesce can last at most {} seconds",
+            MAX_QUIESCE.as_secs()
+        )));
+    }
+    let key = db_path.to_path_buf();
+    if is_quiesced(db_path) {
+        return Err(LibationError::InvalidState(format!("{} is already quiesced", db_path.display())));
+    }
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(db_path)
+        .busy_timeout(wait)
+        .disable_statement_logging()
+        .connect()
+        .await?;
+    let deadline = tokio::time::Instant::now() + wait;
+    loop {
+        // A checkpoint can't run inside the write transaction, so it runs
+        // first; a write that slips in before the lock is taken leaves WAL
+        // frames behind and the lock is tried again
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&mut conn).await?;
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut conn).await?;
+        if wal_len(db_path) == 0 {
+            break;
+        }
+        sqlx::query("ROLLBACK").execute(&mut conn).await?;
+        if tokio::time::Instant::now() >= deadline {
+            let _ = conn.close().await;
+            return Err(LibationError::InvalidState("Writes didn't pause for the WAL checkpoint".to_string()));
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let since = Utc::now();
+    let status = QuiesceStatus {
+        db_path: db_path.to_string_lossy().to_string(),
+        since,
+        expires_at: since + chrono::Duration::from_std(max_duration).unwrap_or_default(),
+    };
+    let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    QUIESCED
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(key.clone(), Quiesce { id, conn, status: status.clone() });
+
+    tokio::spawn(async move {
+        tokio::time::sleep(max_duration).await;
+        release(&key, Some(id)).await;
+    });
+    Ok(status)
+}
+
+/// Let writes to the database at `db_path` continue
+///
+/// # Returns
+/// Whether it was quiesced (false after the quiesce expired)
+pub async fn end_quiesce(db_path: &Path) -> bool {
+    release(db_path, None).await
+}
+
+/// Current quiesce of the database at `db_path`
+pub fn quiesce_status(db_path: &Path) -> Option<QuiesceStatus> {
+    QUIESCED
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|q| q.get(db_path))
+        .map(|q| q.status.clone())
+}
+
+pub fn is_quiesced(db_path: &Path) -> bool {
+    quiesce_status(db_path).is_some()
+}
+
+/// Length of the database's WAL file (0 if there is none)
+fn wal_len(db_path: &Path) -> u64 {
+    let mut wal = db_path.as_os_str().to_owned();
+    wal.push("-wal");
+    std::fs::metadata(wal).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Release the quiesce of `db_path` (only quiesce `id`, if given)
+async fn release(db_path: &Path, id: Option<u64>) -> bool {
+    let quiesce = {
+        let mut quiesced = QUIESCED.lock().unwrap();
+        let map = quiesced.get_or_insert_with(HashMap::new);
+        match map.get(db_path) {
+            Some(q) if id.is_none_or(|id| q.id == id) => map.remove(db_path),
+            _ => None,
+        }
+    };
+    let Some(mut quiesce) = quiesce else {
+        return false;
+    };
+    let _ = sqlx::query("ROLLBACK").execute(&mut quiesce.conn).await;
+    let _ = quiesce.conn.close().await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::settings::{get_setting, set_setting};
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_quiesce() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.db");
+        let db = Database::new(&path).await.unwrap();
+        set_setting(db.pool(), "backup.marker", "before").await.unwrap();
+
+        let status = begin_quiesce(&path, Duration::from_secs(1), DEFAULT_MAX_QUIESCE).await.unwrap();
+        assert_eq!(quiesce_status(&path), Some(status));
+        assert!(begin_quiesce(&path, Duration::from_secs(1), DEFAULT_MAX_QUIESCE).await.is_err());
+
+        // The database file alone has everything
+        let copy = dir.path().join("backup.db");
+        std::fs::copy(&path, &copy).unwrap();
+        let restored = Database::new(&copy).await.unwrap();
+        assert_eq!(get_setting(restored.pool(), "backup.marker").await.unwrap().as_deref(), Some("before"));
+
+        // Writes wait for the end
+        let pool = db.pool().clone();
+        let write = tokio::spawn(async move { set_setting(&pool, "backup.marker", "after").await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!write.is_finished());
+        assert!(end_quiesce(&path).await);
+        write.await.unwrap().unwrap();
+        assert!(!end_quiesce(&path).await);
+
+        // A quiesce that isn't ended is released
+        begin_quiesce(&path, Duration::from_secs(1), Duration::from_millis(100)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!is_quiesced(&path));
+        set_setting(db.pool(), "backup.marker", "later").await.unwrap();
+        assert!(begin_quiesce(&path, Duration::from_secs(1), MAX_QUIESCE * 2).await.is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Stored read-along mappings (see `api::whispersync`)
+//!
+//! Titles without a companion are stored with no sync points, so the app
+//! can tell "checked, nothing there" from "never fetched".
+
+use crate::api::whispersync::ReadAlongMapping;
+use crate::error::{LibationError, Result};
+use crate::storage::dates::normalize_timestamp;
+use sqlx::{Row, SqlitePool};
+
+/// Save (or replace) the mapping of a book
+///
+/// # Errors
+/// RecordNotFound if the book isn't in the database
+pub async fn save_read_along_mapping(pool: &SqlitePool, mapping: &ReadAlongMapping) -> Result<()> {
+    let book_id: i64 = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+        .bind(&mapping.asin)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Book not found: {}", mapping.asin)))?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO ReadAlongMappings (book_id, companion_asin, sync_points, fetched_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(book_id)
+        DO UPDATE SET companion_asin = excluded.companion_asin,
+                      sync_points = excluded.sync_points,
+                      fetched_at = excluded.fetched_at
+        "#,
+    )
+    .bind(book_id)
+    .bind(&mapping.companion_asin)
+    .bind(serde_json::to_string(&mapping.points)?)
+    .bind(normalize_timestamp(&mapping.fetched_at).unwrap_or_else(|| mapping.fetched_at.clone()))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Stored mapping of a book (None if never fetched)
+pub async fn get_read_along_mapping(pool: &SqlitePool, asin: &str) -> Result<Option<ReadAlongMapping>> {
+    let row = sqlx::query(
+        r#"
+        SELECT r.companion_asin, r.sync_points, r.fetched_at
+        FROM ReadAlongMappings r
+        JOIN Books b ON b.book_id = r.book_id
+        WHERE b.audible_product_id = ?
+        "#,
+    )
+    .bind(asin)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|row| {
+        let points: String = row.try_get("sync_points")?;
+        Ok(ReadAlongMapping {
+            asin: asin.to_string(),
+            companion_asin: row.try_get("companion_asin")?,
+            points: serde_json::from_str(&points)?,
+            fetched_at: row.try_get("fetched_at")?,
+        })
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::whispersync::SyncPoint;
+    use crate::storage::{queries::insert_book, Database, NewBook};
+
+    #[tokio::test]
+    async fn test_save_read_along_mapping() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        insert_book(pool, &NewBook::new("B0AUDIO".to_string(), "Book".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+
+        assert!(get_read_along_mapping(pool, "B0AUDIO").await.unwrap().is_none());
+
+        let none = ReadAlongMapping::unavailable("B0AUDIO", None);
+        save_read_along_mapping(pool, &none).await.unwrap();
+        let stored = get_read_along_mapping(pool, "B0AUDIO").await.unwrap().unwrap();
+        assert!(!stored.is_available());
+
+        let mut mapping = ReadAlongMapping::unavailable("B0AUDIO", Some("B0KINDLE".to_string()));
+        mapping.points = vec![SyncPoint { audio_ms: 0, location: 10, page: None }];
+        save_read_along_mapping(pool, &mapping).await.unwrap();
+        assert_eq!(get_read_along_mapping(pool, "B0AUDIO").await.unwrap(), Some(mapping));
+
+        assert!(matches!(
+            save_read_along_mapping(pool, &ReadAlongMapping::unavailable("MISSING", None)).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,511 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Liberation receipts
+//!
+//! Every liberated file gets a receipt: where the input came from, the
+//! license (ACR) it was decrypted with, the decrypt method, each output
+//! file with its size and SHA-256, how long the steps took, and the
+//! toolchain that produced it. `record_liberation` writes the receipt and
+//! marks the book's download completed in one transaction, so a book is
+//! never liberated without a receipt or the other way round.
+//!
+//! Receipts are kept when a book is removed or re-liberated; the latest
+//! one describes the current file. Output hashes are indexed, so a file
+//! can be traced back to its receipt (`find_receipts_by_sha256`), and a
+//! receipt can be copied next to the file as a sidecar JSON
+//! (`write_sidecar`).
+
+use crate::audio::capabilities::{get_audio_capabilities, AudioBackend};
+use crate::error::{LibationError, Result};
+use crate::storage::{dates, queries};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Where the liberated audio came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputSource {
+    /// Downloaded from Audible
+    AudibleDownload,
+    /// Picked up from the watch folder
+    WatchFolder,
+    /// A file the user pointed the app at
+    LocalFile,
+    /// Sent from another device running the app (see `file::handoff`)
+    Handoff,
+}
+
+impl InputSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InputSource::AudibleDownload => "audible_download",
+            InputSource::WatchFolder => "watch_folder",
+            InputSource::LocalFile => "local_file",
+            InputSource::Handoff => "handoff",
+        }
+    }
+}
+
+impl FromStr for InputSource {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "audible_download" => Ok(InputSource::AudibleDownload),
+            "watch_folder" => Ok(InputSource::WatchFolder),
+            "local_file" => Ok(InputSource::LocalFile),
+            "handoff" => Ok(InputSource::Handoff),
+            other => Err(LibationError::InvalidInput(format!("Unknown input source: {}", other))),
+        }
+    }
+}
+
+/// How the input was decrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecryptMethod {
+    /// AAX with activation bytes
+    Aax,
+    /// AAXC with the license voucher's key and IV
+    Aaxc,
+    /// Not encrypted; copied or moved as is
+    None,
+}
+
+impl DecryptMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecryptMethod::Aax => "aax",
+            DecryptMethod::Aaxc => "aaxc",
+            DecryptMethod::None => "none",
+        }
+    }
+}
+
+impl FromStr for DecryptMethod {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "aax" => Ok(DecryptMethod::Aax),
+            "aaxc" => Ok(DecryptMethod::Aaxc),
+            "none" => Ok(DecryptMethod::None),
+            other => Err(LibationError::InvalidInput(format!("Unknown decrypt method: {}", other))),
+        }
+    }
+}
+
+/// Software that produced the output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Toolchain {
+    /// Version of this crate
+    pub core_version: String,
+    pub audio_backend: AudioBackend,
+    /// FFmpeg (or FFmpeg-Kit) version, if FFmpeg was involved
+    pub ffmpeg_version: Option<String>,
+}
+
+impl Toolchain {
+    /// This build and audio backend, with the FFmpeg version when FFmpeg
+    /// produced the output (the app reports FFmpeg-Kit's)
+    pub fn with_ffmpeg(ffmpeg_version: Option<String>) -> Self {
+        Self {
+            core_version: env!("CARGO_PKG_VERSION").to_string(),
+            audio_backend: get_audio_capabilities().backend,
+            ffmpeg_version,
+        }
+    }
+}
+
+/// Version of the `ffmpeg` on PATH, from the first line of
+/// `ffmpeg -version` ("ffmpeg version 6.1.1 ...")
+pub async fn native_ffmpeg_version() -> Option<String> {
+    let output = tokio::process::Command::new("ffmpeg").arg("-version").output().await.ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next()?.split_whitespace().nth(2).map(str::to_string)
+}
+
+/// An output file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptFile {
+    /// Local path or storage backend location
+    pub location: String,
+    pub size_bytes: i64,
+    /// Hex SHA-256 of the contents
+    pub sha256: String,
+}
+
+impl ReceiptFile {
+    /// Size and hash `path`, recording it at `location`
+    ///
+    /// Files handed to a storage backend are hashed locally before upload,
+    /// so `location` can differ from `path`.
+    pub async fn hash(path: &Path, location: impl Into<String>) -> Result<Self> {
+        let size_bytes = tokio::fs::metadata(path).await?.len() as i64;
+        Ok(Self {
+            location: location.into(),
+            size_bytes,
+            sha256: crate::file::integrity::sha256_file(path).await?,
+        })
+    }
+}
+
+/// Receipt to record with `record_liberation`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewReceipt {
+    pub asin: String,
+    /// Existing download task to complete; a completed task is created
+    /// when None
+    #[serde(default)]
+    pub task_id: Option<String>,
+    pub source: InputSource,
+    /// Source file path or CDN host; never a signed URL
+    #[serde(default)]
+    pub source_ref: Option<String>,
+    /// Audible Content Reference of the license
+    #[serde(default)]
+    pub license_id: Option<String>,
+    pub decrypt_method: DecryptMethod,
+    /// The first file is the book's file
+    pub files: Vec<ReceiptFile>,
+    #[serde(default)]
+    pub download_ms: Option<i64>,
+    #[serde(default)]
+    pub decrypt_ms: Option<i64>,
+    pub toolchain: Toolchain,
+}
+
+/// A recorded receipt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiberationReceipt {
+    pub receipt_id: i64,
+    pub asin: String,
+    /// Download task marked completed with the receipt
+    pub task_id: String,
+    pub source: InputSource,
+    pub source_ref: Option<String>,
+    pub license_id: Option<String>,
+    pub decrypt_method: DecryptMethod,
+    pub files: Vec<ReceiptFile>,
+    pub download_ms: Option<i64>,
+    pub decrypt_ms: Option<i64>,
+    pub toolchain: Toolchain,
+    pub created_at: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct ReceiptRow {
+    receipt_id: i64,
+    asin: String,
+    task_id: String,
+    source: String,
+    source_ref: Option<String>,
+    license_id: Option<String>,
+    decrypt_method: String,
+    download_ms: Option<i64>,
+    decrypt_ms: Option<i64>,
+    toolchain: String,
+    created_at: String,
+}
+
+const RECEIPT_COLUMNS: &str = "r.receipt_id, r.asin, r.task_id, r.source, r.source_ref, r.license_id, \
+     r.decrypt_method, r.download_ms, r.decrypt_ms, r.toolchain, r.created_at";
+
+/// Record a receipt and mark the book liberated with its first file
+///
+/// With `receipt.task_id` that task is set completed with the file as its
+/// output; otherwise a completed task is created, as `set_book_file_path`
+/// does. Both happen in one transaction with the receipt.
+///
+/// # Errors
+/// InvalidInput without files, RecordNotFound for an unknown `task_id`
+pub async fn record_liberation(pool: &SqlitePool, title: &str, receipt: &NewReceipt) -> Result<LiberationReceipt> {
+    let output = receipt
+        .files
+        .first()
+        .ok_or_else(|| LibationError::InvalidInput("A liberation receipt needs an output file".to_string()))?;
+    let toolchain = serde_json::to_string(&receipt.toolchain)?;
+    let now = dates::now();
+
+    let mut tx = pool.begin().await?;
+
+    let task_id = match &receipt.task_id {
+        Some(task_id) => {
+            let updated = sqlx::query(
+                "UPDATE DownloadTasks SET status = 'completed', output_path = ?, error = NULL, \
+                 completed_at = COALESCE(completed_at, ?) WHERE task_id = ? AND asin = ?",
+            )
+            .bind(&output.location)
+            .bind(&now)
+            .bind(task_id)
+            .bind(&receipt.asin)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if updated == 0 {
+                return Err(LibationError::not_found(format!(
+                    "Download task not found for {}: {}",
+                    receipt.asin, task_id
+                )));
+            }
+            task_id.clone()
+        }
+        None => queries::set_book_file_path(&mut *tx, &receipt.asin, title, &output.location).await?,
+    };
+
+    let receipt_id = sqlx::query(
+        r#"
+        INSERT INTO LiberationReceipts
+            (asin, task_id, source, source_ref, license_id, decrypt_method,
+             download_ms, decrypt_ms, toolchain, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&receipt.asin)
+    .bind(&task_id)
+    .bind(receipt.source.as_str())
+    .bind(&receipt.source_ref)
+    .bind(&receipt.license_id)
+    .bind(receipt.decrypt_method.as_str())
+    .bind(receipt.download_ms)
+    .bind(receipt.decrypt_ms)
+    .bind(&toolchain)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+
+    for (position, file) in receipt.files.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO LiberationReceiptFiles (receipt_id, position, location, size_bytes, sha256) \
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(receipt_id)
+        .bind(position as i64)
+        .bind(&file.location)
+        .bind(file.size_bytes)
+        .bind(file.sha256.to_ascii_lowercase())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    #[cfg(feature = "telemetry")]
+    if let Err(e) = crate::telemetry::record(pool, crate::telemetry::Counter::Liberation).await {
+        crate::trace::trace_eprintln!("Failed to record telemetry counter: {}", e);
+    }
+
+    get_receipt(pool, receipt_id)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Receipt not found: {}", receipt_id)))
+}
+
+/// Receipt by id
+pub async fn get_receipt(pool: &SqlitePool, receipt_id: i64) -> Result<Option<LiberationReceipt>> {
+    let row = sqlx::query_as::<_, ReceiptRow>(&format!(
+        "SELECT {} FROM LiberationReceipts r WHERE r.receipt_id = ?",
+        RECEIPT_COLUMNS
+    ))
+    .bind(receipt_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => Ok(Some(load_receipt(pool, row).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Receipts of a book, newest first
+pub async fn list_receipts(pool: &SqlitePool, asin: &str) -> Result<Vec<LiberationReceipt>> {
+    let rows = sqlx::query_as::<_, ReceiptRow>(&format!(
+        "SELECT {} FROM LiberationReceipts r WHERE r.asin = ? ORDER BY r.receipt_id DESC",
+        RECEIPT_COLUMNS
+    ))
+    .bind(asin)
+    .fetch_all(pool)
+    .await?;
+
+    load_receipts(pool, rows).await
+}
+
+/// Receipts that produced a file with this SHA-256, newest first
+pub async fn find_receipts_by_sha256(pool: &SqlitePool, sha256: &str) -> Result<Vec<LiberationReceipt>> {
+    let rows = sqlx::query_as::<_, ReceiptRow>(&format!(
+        "SELECT DISTINCT {} FROM LiberationReceipts r \
+         JOIN LiberationReceiptFiles f ON f.receipt_id = r.receipt_id \
+         WHERE f.sha256 = ? ORDER BY r.receipt_id DESC",
+        RECEIPT_COLUMNS
+    ))
+    .bind(sha256.to_ascii_lowercase())
+    .fetch_all(pool)
+    .await?;
+
+    load_receipts(pool, rows).await
+}
+
+async fn load_receipts(pool: &SqlitePool, rows: Vec<ReceiptRow>) -> Result<Vec<LiberationReceipt>> {
+    let mut receipts = Vec::with_capacity(rows.len());
+    for row in rows {
+        receipts.push(load_receipt(pool, row).await?);
+    }
+    Ok(receipts)
+}
+
+async fn load_receipt(pool: &SqlitePool, row: ReceiptRow) -> Result<LiberationReceipt> {
+    let files = sqlx::query_as::<_, (String, i64, String)>(
+        "SELECT location, size_bytes, sha256 FROM LiberationReceiptFiles WHERE receipt_id = ? ORDER BY position",
+    )
+    .bind(row.receipt_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(location, size_bytes, sha256)| ReceiptFile { location, size_bytes, sha256 })
+    .collect();
+
+    Ok(LiberationReceipt {
+        receipt_id: row.receipt_id,
+        asin: row.asin,
+        task_id: row.task_id,
+        source: row.source.parse()?,
+        source_ref: row.source_ref,
+        license_id: row.license_id,
+        decrypt_method: row.decrypt_method.parse()?,
+        files,
+        download_ms: row.download_ms,
+        decrypt_ms: row.decrypt_ms,
+        toolchain: serde_json::from_str(&row.toolchain)?,
+        created_at: row.created_at,
+    })
+}
+
+/// Record that a receipt's file now lives at `location`
+///
+/// Moving the first file also moves the book's file (the output path of
+/// the receipt's task); size and hash are unchanged.
+///
+/// # Errors
+/// RecordNotFound if the receipt has no file at `position`
+pub async fn relocate_file(pool: &SqlitePool, receipt_id: i64, position: usize, location: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let updated = sqlx::query("UPDATE LiberationReceiptFiles SET location = ? WHERE receipt_id = ? AND position = ?")
+        .bind(location)
+        .bind(receipt_id)
+        .bind(position as i64)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if updated == 0 {
+        return Err(LibationError::not_found(format!(
+            "Receipt {} has no file {}",
+            receipt_id, position
+        )));
+    }
+
+    if position == 0 {
+        sqlx::query(
+            "UPDATE DownloadTasks SET output_path = ? \
+             WHERE task_id = (SELECT task_id FROM LiberationReceipts WHERE receipt_id = ?)",
+        )
+        .bind(location)
+        .bind(receipt_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Sidecar path for an output file: `Book.m4b` -> `Book.receipt.json`
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    output.with_extension("receipt.json")
+}
+
+/// Write `receipt` as pretty JSON next to a local output file
+///
+/// Written to a temporary file and renamed, so a sidecar is never partial.
+///
+/// # Returns
+/// Path of the sidecar
+pub async fn write_sidecar(receipt: &LiberationReceipt, output: &Path) -> Result<PathBuf> {
+    let path = sidecar_path(output);
+    let partial = path.with_extension("json.partial");
+    tokio::fs::write(&partial, serde_json::to_vec_pretty(receipt)?).await?;
+    tokio::fs::rename(&partial, &path).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{queries::get_book_file_path, Database};
+    use sha2::{Digest, Sha256};
+
+    #[tokio::test]
+    async fn test_receipt_recorded_with_completion() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("Bobiverse.m4b");
+        tokio::fs::write(&output, b"decrypted audio").await.unwrap();
+
+        let mut new = NewReceipt {
+            asin: "B0BOBIVERSE".to_string(),
+            task_id: None,
+            source: InputSource::AudibleDownload,
+            source_ref: Some("cds.audible.com".to_string()),
+            license_id: Some("CR!ABC".to_string()),
+            decrypt_method: DecryptMethod::Aaxc,
+            files: Vec::new(),
+            download_ms: Some(1200),
+            decrypt_ms: Some(300),
+            toolchain: Toolchain::with_ffmpeg(Some("6.1".to_string())),
+        };
+
+        // Nothing is recorded for a receipt that can't be written
+        assert!(record_liberation(pool, "Bobiverse", &new).await.is_err());
+        new.files.push(ReceiptFile::hash(&output, output.display().to_string()).await.unwrap());
+        new.task_id = Some("missing".to_string());
+        assert!(matches!(
+            record_liberation(pool, "Bobiverse", &new).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+        assert!(get_book_file_path(pool, "B0BOBIVERSE").await.unwrap().is_none());
+        assert!(list_receipts(pool, "B0BOBIVERSE").await.unwrap().is_empty());
+
+        new.task_id = None;
+        let receipt = record_liberation(pool, "Bobiverse", &new).await.unwrap();
+        assert_eq!(receipt.files[0].sha256, hex::encode(Sha256::digest(b"decrypted audio")));
+        assert_eq!(receipt.files[0].size_bytes, 15);
+        assert_eq!(
+            get_book_file_path(pool, "B0BOBIVERSE").await.unwrap().as_deref(),
+            Some(output.display().to_string().as_str())
+        );
+
+        let found = find_receipts_by_sha256(pool, &receipt.files[0].sha256.to_uppercase()).await.unwrap();
+        assert_eq!(found, vec![receipt.clone()]);
+
+        let sidecar = write_sidecar(&receipt, &output).await.unwrap();
+        assert_eq!(sidecar, dir.path().join("Bobiverse.receipt.json"));
+        let written: LiberationReceipt = serde_json::from_slice(&tokio::fs::read(&sidecar).await.unwrap()).unwrap();
+        assert_eq!(written, receipt);
+    }
+}
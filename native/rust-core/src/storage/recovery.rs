@@ -0,0 +1,494 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Safe-mode startup and corruption recovery
+//!
+//! After filesystem corruption `Database::new` fails on every call and the
+//! app can't start. `Database::open_with_recovery` is meant for app startup:
+//! it runs `PRAGMA integrity_check` before migrating and, if the file is
+//! damaged, tries in order:
+//!
+//! 1. **Reindex** - `REINDEX` fixes corrupt indexes without touching data
+//! 2. **Rebuild** - a fresh database is created next to the damaged one
+//!    and every readable row is copied into it, table by table and, where
+//!    a bulk copy hits a bad page, in small rowid ranges
+//! 3. **Recreate** - if not even the schema is readable, an empty database
+//!
+//! The damaged file (with its WAL) is never deleted; it is moved aside as
+//! `{name}.corrupt-{timestamp}`. The returned `RecoveryReport` tells the app
+//! what happened and whether data may be lost, so it can offer to restore
+//! from a backup.
+
+use crate::error::{LibationError, Result};
+use crate::storage::database::Database;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions};
+use sqlx::{ConnectOptions, Row};
+use std::path::{Path, PathBuf};
+
+/// Integrity problems reported by `integrity_check` (at most this many)
+const MAX_INTEGRITY_ERRORS: u32 = 100;
+
+/// Rows copied per statement when a bulk copy fails
+const SALVAGE_CHUNK_ROWS: i64 = 256;
+
+/// Suffixes of SQLite's companion files
+const COMPANION_SUFFIXES: [&str; 2] = ["-wal", "-shm"];
+
+/// What opening the database took
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryOutcome {
+    /// Integrity check passed (or the database was new)
+    Healthy,
+    /// Indexes were rebuilt; no data lost
+    Reindexed,
+    /// Readable rows were copied into a fresh database
+    Rebuilt,
+    /// Nothing was readable; the database is empty
+    Recreated,
+}
+
+/// Rows salvaged from one table during a rebuild
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableRecovery {
+    pub table: String,
+    pub rows_recovered: u64,
+
+    /// Every row was read back; false if some were unreadable
+    pub complete: bool,
+
+    /// First error hit while reading the table
+    pub error: Option<String>,
+}
+
+/// Result of `Database::open_with_recovery`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    pub database_path: String,
+    pub outcome: RecoveryOutcome,
+
+    /// Problems found by the integrity check on open
+    pub integrity_errors: Vec<String>,
+
+    /// Where the damaged file was moved (None unless rebuilt or recreated)
+    pub quarantined_path: Option<String>,
+
+    /// Per-table results of a rebuild
+    pub tables: Vec<TableRecovery>,
+
+    /// Some data could not be recovered; offer restore-from-backup
+    pub data_lost: bool,
+}
+
+impl RecoveryReport {
+    fn new(path: &Path, outcome: RecoveryOutcome) -> Self {
+        Self {
+            database_path: path.display().to_string(),
+            outcome,
+            integrity_errors: Vec::new(),
+            quarantined_path: None,
+            tables: Vec::new(),
+            data_lost: false,
+        }
+    }
+}
+
+/// Whether an error means the file itself is damaged (SQLITE_CORRUPT or
+/// SQLITE_NOTADB, including extended codes)
+pub fn is_corruption_error(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| matches!(code & 0xff, 11 | 26)),
+        _ => false,
+    }
+}
+
+/// Open the file without migrating or changing its journal mode
+async fn open_raw(path: &Path) -> std::result::Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(false)
+        .disable_statement_logging();
+
+    SqlitePoolOptions::new().max_connections(1).connect_with(options).await
+}
+
+/// Integrity problems of the file (empty when healthy)
+async fn integrity_errors(pool: &SqlitePool) -> std::result::Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<String> = sqlx::query_scalar(&format!("PRAGMA integrity_check({})", MAX_INTEGRITY_ERRORS))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().filter(|row| row != "ok").collect())
+}
+
+/// Integrity problems, treating corruption errors as problems
+///
+/// # Errors
+/// Errors that don't indicate corruption (permissions, I/O)
+async fn check_file(path: &Path) -> Result<Vec<String>> {
+    let result = async {
+        let pool = open_raw(path).await?;
+        let errors = integrity_errors(&pool).await;
+        pool.close().await;
+        errors
+    }
+    .await;
+
+    match result {
+        Ok(errors) => Ok(errors),
+        Err(e) if is_corruption_error(&e) => Ok(vec![e.to_string()]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Try REINDEX; true if the file passes the integrity check afterwards
+async fn try_reindex(path: &Path) -> bool {
+    let result = async {
+        let pool = open_raw(path).await?;
+        sqlx::query("REINDEX").execute(&pool).await?;
+        let errors = integrity_errors(&pool).await;
+        pool.close().await;
+        errors
+    }
+    .await;
+
+    matches!(result, Ok(errors) if errors.is_empty())
+}
+
+/// `path` with a suffix appended to the file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Move a database file and its WAL/SHM companions
+fn move_database(from: &Path, to: &Path) -> Result<()> {
+    std::fs::rename(from, to).map_err(|e| {
+        LibationError::FileIoError(format!("Failed to move {} to {}: {}", from.display(), to.display(), e))
+    })?;
+    for suffix in COMPANION_SUFFIXES {
+        let companion = with_suffix(from, suffix);
+        if companion.exists() {
+            let _ = std::fs::rename(&companion, with_suffix(to, suffix));
+        }
+    }
+    Ok(())
+}
+
+/// Delete a database file and its companions, ignoring missing files
+fn remove_database(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    for suffix in COMPANION_SUFFIXES {
+        let _ = std::fs::remove_file(with_suffix(path, suffix));
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Column names of a table in the given schema (empty if it doesn't exist)
+async fn table_columns(conn: &mut SqliteConnection, schema: &str, table: &str) -> std::result::Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query(&format!("PRAGMA {}.table_info({})", schema, quote_ident(table)))
+        .fetch_all(&mut *conn)
+        .await?;
+
+    rows.iter().map(|row| row.try_get::<String, _>("name")).collect()
+}
+
+/// Copy one table from `damaged` into `main`, salvaging what it can
+async fn copy_table(conn: &mut SqliteConnection, table: &str) -> TableRecovery {
+    let mut recovery = TableRecovery {
+        table: table.to_string(),
+        rows_recovered: 0,
+        complete: true,
+        error: None,
+    };
+
+    let columns = match (table_columns(conn, "main", table).await, table_columns(conn, "damaged", table).await) {
+        (Ok(fresh), Ok(old)) => fresh.into_iter().filter(|c| old.contains(c)).collect::<Vec<_>>(),
+        (Err(e), _) | (_, Err(e)) => {
+            recovery.complete = false;
+            recovery.error = Some(e.to_string());
+            return recovery;
+        }
+    };
+    if columns.is_empty() {
+        // Table didn't exist before this schema version
+        return recovery;
+    }
+
+    let column_list = columns.iter().map(|c| quote_ident(c)).collect::<Vec<_>>().join(", ");
+    let insert = format!(
+        "INSERT OR IGNORE INTO main.{table} ({cols}) SELECT {cols} FROM damaged.{table}",
+        table = quote_ident(table),
+        cols = column_list
+    );
+
+    match sqlx::query(&insert).execute(&mut *conn).await {
+        Ok(result) => {
+            recovery.rows_recovered = result.rows_affected();
+            return recovery;
+        }
+        Err(e) => {
+            recovery.complete = false;
+            recovery.error = Some(e.to_string());
+        }
+    }
+
+    // The bulk copy is one statement, so nothing was inserted; retry in
+    // rowid ranges, then row by row within ranges that still fail
+    let max_rowid: i64 = match sqlx::query_scalar::<_, Option<i64>>(&format!("SELECT max(rowid) FROM damaged.{}", quote_ident(table)))
+        .fetch_one(&mut *conn)
+        .await
+    {
+        Ok(max) => max.unwrap_or(0),
+        Err(_) => return recovery,
+    };
+
+    let ranged = format!("{} WHERE rowid BETWEEN ? AND ?", insert);
+    let mut start = 1;
+    while start <= max_rowid {
+        let end = start + SALVAGE_CHUNK_ROWS - 1;
+        match sqlx::query(&ranged).bind(start).bind(end).execute(&mut *conn).await {
+            Ok(result) => recovery.rows_recovered += result.rows_affected(),
+            Err(_) => {
+                for rowid in start..=end.min(max_rowid) {
+                    if let Ok(result) = sqlx::query(&ranged).bind(rowid).bind(rowid).execute(&mut *conn).await {
+                        recovery.rows_recovered += result.rows_affected();
+                    }
+                }
+            }
+        }
+        start = end + 1;
+    }
+
+    recovery
+}
+
+/// Copy every readable row of the damaged file into a fresh database
+///
+/// # Returns
+/// Per-table results, or None if the damaged file's schema is unreadable
+async fn salvage_into(fresh: &Database, damaged: &Path) -> Result<Option<Vec<TableRecovery>>> {
+    let mut conn = fresh.pool().acquire().await?;
+
+    // Rows arrive in table order, not dependency order
+    sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+    let attached = match sqlx::query("ATTACH DATABASE ? AS damaged")
+        .bind(damaged.display().to_string())
+        .execute(&mut *conn)
+        .await
+    {
+        Ok(_) => true,
+        Err(e) if is_corruption_error(&e) => false,
+        Err(e) => return Err(e.into()),
+    };
+
+    let readable = attached
+        && sqlx::query_scalar::<_, i64>("SELECT count(*) FROM damaged.sqlite_master")
+            .fetch_one(&mut *conn)
+            .await
+            .is_ok();
+
+    let mut tables = Vec::new();
+    if readable {
+        // Full-text indexes (virtual tables and their shadow tables) are
+        // refilled by triggers as their content tables are copied
+        let names: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM pragma_table_list WHERE schema = 'main' AND type = 'table' \
+             AND name NOT LIKE 'sqlite_%' AND name != '_migrations' ORDER BY name",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for name in names {
+            tables.push(copy_table(&mut conn, &name).await);
+        }
+    }
+
+    if attached {
+        sqlx::query("DETACH DATABASE damaged").execute(&mut *conn).await?;
+    }
+    sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await?;
+
+    Ok(readable.then_some(tables))
+}
+
+impl Database {
+    /// Open the database for app startup, recovering from corruption
+    ///
+    /// Runs an integrity check before migrating. A damaged file is repaired
+    /// by reindexing or, failing that, rebuilt from its readable rows (see
+    /// the module docs); the damaged file is quarantined next to the
+    /// original. The regular `Database::new` skips the check, so use this
+    /// once at startup and `new` afterwards.
+    ///
+    /// # Errors
+    /// Errors that aren't corruption (e.g. permissions) and failures to
+    /// create the replacement database
+    pub async fn open_with_recovery<P: AsRef<Path>>(database_path: P) -> Result<(Self, RecoveryReport)> {
+        let path = database_path.as_ref();
+
+        if !path.exists() {
+            return Ok((Self::new(path).await?, RecoveryReport::new(path, RecoveryOutcome::Healthy)));
+        }
+
+        let integrity_errors = check_file(path).await?;
+        if integrity_errors.is_empty() {
+            return Ok((Self::new(path).await?, RecoveryReport::new(path, RecoveryOutcome::Healthy)));
+        }
+
+        let mut report = RecoveryReport::new(path, RecoveryOutcome::Reindexed);
+        report.integrity_errors = integrity_errors;
+
+        if try_reindex(path).await {
+            return Ok((Self::new(path).await?, report));
+        }
+
+        // Build the replacement beside the original so a crash mid-rebuild
+        // leaves the damaged file in place for the next attempt
+        let rebuilding = with_suffix(path, ".rebuilding");
+        remove_database(&rebuilding);
+        let fresh = Self::new(&rebuilding).await?;
+        let salvaged = salvage_into(&fresh, path).await;
+        fresh.checkpoint().await?;
+        fresh.close().await?;
+
+        let salvaged = match salvaged {
+            Ok(salvaged) => salvaged,
+            Err(e) => {
+                remove_database(&rebuilding);
+                return Err(e);
+            }
+        };
+
+        let quarantined = with_suffix(
+            path,
+            &format!(".corrupt-{}", crate::clock::now().format("%Y%m%d-%H%M%S")),
+        );
+        move_database(path, &quarantined)?;
+        move_database(&rebuilding, path)?;
+
+        report.quarantined_path = Some(quarantined.display().to_string());
+        match salvaged {
+            Some(tables) => {
+                report.outcome = RecoveryOutcome::Rebuilt;
+                report.data_lost = tables.iter().any(|t| !t.complete);
+                report.tables = tables;
+            }
+            None => {
+                report.outcome = RecoveryOutcome::Recreated;
+                report.data_lost = true;
+            }
+        }
+
+        Ok((Self::new(path).await?, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::insert_book;
+    use crate::storage::NewBook;
+
+    fn temp_db_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("librisync-recovery-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("library.db")
+    }
+
+    #[tokio::test]
+    async fn test_healthy_database_opens_normally() {
+        let path = temp_db_path("healthy");
+        let (db, report) = Database::open_with_recovery(&path).await.unwrap();
+        insert_book(db.pool(), &NewBook::new("B001".to_string(), "One".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        db.close().await.unwrap();
+
+        let (db, report_again) = Database::open_with_recovery(&path).await.unwrap();
+        assert_eq!(report.outcome, RecoveryOutcome::Healthy);
+        assert_eq!(report_again.outcome, RecoveryOutcome::Healthy);
+        assert!(report_again.quarantined_path.is_none());
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM Books").fetch_one(db.pool()).await.unwrap();
+        assert_eq!(count, 1);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_file_is_quarantined_and_recreated() {
+        let path = temp_db_path("garbage");
+        std::fs::write(&path, vec![0x5a; 8192]).unwrap();
+
+        let (db, report) = Database::open_with_recovery(&path).await.unwrap();
+        assert_eq!(report.outcome, RecoveryOutcome::Recreated);
+        assert!(report.data_lost);
+        assert!(!report.integrity_errors.is_empty());
+
+        let quarantined = PathBuf::from(report.quarantined_path.unwrap());
+        assert_eq!(std::fs::read(&quarantined).unwrap(), vec![0x5a; 8192]);
+        assert!(db.check_integrity().await.unwrap());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_damaged_pages_are_salvaged() {
+        let path = temp_db_path("damaged");
+        let db = Database::new(&path).await.unwrap();
+        for i in 0..2000 {
+            let book = NewBook::new(format!("B{:06}", i), format!("Book number {} {}", i, "x".repeat(200)), "us".to_string());
+            insert_book(db.pool(), &book).await.unwrap();
+        }
+        crate::storage::notes::add_note(db.pool(), "B000000", "Bookmark the chapter on recovery", None)
+            .await
+            .unwrap();
+        db.checkpoint().await.unwrap();
+        db.close().await.unwrap();
+
+        // Wipe a Books leaf page (b-tree page type 0x0D; index pages are
+        // interleaved with it) in the middle of the file
+        let mut bytes = std::fs::read(&path).unwrap();
+        let page_size = 4096;
+        let page = (bytes.len() / page_size / 2..)
+            .find(|page| bytes[page * page_size] == 0x0d)
+            .unwrap();
+        bytes[page * page_size..(page + 1) * page_size].fill(0xff);
+        std::fs::write(&path, &bytes).unwrap();
+
+        let (db, report) = Database::open_with_recovery(&path).await.unwrap();
+        assert_eq!(report.outcome, RecoveryOutcome::Rebuilt);
+        assert!(report.data_lost);
+        assert!(PathBuf::from(report.quarantined_path.unwrap()).exists());
+        let books = report.tables.iter().find(|t| t.table == "Books").unwrap();
+        assert!(!books.complete);
+        assert!(db.check_integrity().await.unwrap());
+
+        // Only the rows on the wiped page are lost
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM Books").fetch_one(db.pool()).await.unwrap();
+        assert_eq!(count as u64, books.rows_recovered);
+        assert!(count > 1900 && count < 2000, "{} books recovered", count);
+        let hits = crate::storage::notes::search_notes(db.pool(), "recovery", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
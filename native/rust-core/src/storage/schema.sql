@@ -0,0 +1,708 @@
+-- Latest schema for new databases (see storage::bootstrap)
+-- Generated by `UPDATE_SCHEMA_SNAPSHOT=1 cargo test --lib test_schema_snapshot`; do not edit
+
+CREATE TABLE _migrations (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+CREATE TABLE Books (
+    book_id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Immutable core fields
+    audible_product_id TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    subtitle TEXT,
+    description TEXT NOT NULL DEFAULT '',
+    length_in_minutes INTEGER NOT NULL,
+    content_type INTEGER NOT NULL DEFAULT 1,  -- ContentType enum (Product=1, Episode=2, Parent=4)
+    locale TEXT NOT NULL,
+
+    -- Mutable metadata
+    picture_id TEXT,
+    picture_large TEXT,
+
+    -- Book details
+    is_abridged INTEGER NOT NULL DEFAULT 0,
+    is_spatial INTEGER NOT NULL DEFAULT 0,
+    date_published TEXT,  -- ISO 8601 date (YYYY-MM-DD)
+    language TEXT,
+
+    -- Product rating (aggregate community rating - embedded Rating entity)
+    rating_overall REAL NOT NULL DEFAULT 0.0,
+    rating_performance REAL NOT NULL DEFAULT 0.0,
+    rating_story REAL NOT NULL DEFAULT 0.0,
+
+    -- Additional metadata from API
+    pdf_url TEXT,  -- PDF companion file URL
+    is_finished INTEGER NOT NULL DEFAULT 0,  -- Has user finished listening
+    is_downloadable INTEGER NOT NULL DEFAULT 1,  -- Can be downloaded
+    is_ayce INTEGER NOT NULL DEFAULT 0,  -- Audible Plus Catalog title
+    origin_asin TEXT,  -- Original ASIN (for regional variants)
+    episode_number INTEGER,  -- Episode number (for podcasts)
+    content_delivery_type TEXT,  -- SinglePartBook, MultiPartBook, etc.
+
+    -- Timestamps
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+, source TEXT NOT NULL DEFAULT 'audible', download_format TEXT, benefit_type TEXT, title_sort TEXT, title_search TEXT, format_support TEXT, narrated_by_author INTEGER NOT NULL DEFAULT 0, full_cast INTEGER NOT NULL DEFAULT 0, cover_path TEXT);
+
+CREATE TABLE LibraryBooks (
+    book_id INTEGER PRIMARY KEY,  -- 1:1 with Books, also primary key
+    date_added TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    account TEXT NOT NULL,  -- Account ID/email
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    absent_from_last_scan INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+);
+
+CREATE TABLE UserDefinedItems (
+    book_id INTEGER PRIMARY KEY,  -- 1:1 with Books
+
+    -- User tags (space-delimited, lowercase, alphanumeric + underscore)
+    tags TEXT,
+
+    -- User rating (personal, not aggregate - embedded Rating entity)
+    user_rating_overall REAL NOT NULL DEFAULT 0.0,
+    user_rating_performance REAL NOT NULL DEFAULT 0.0,
+    user_rating_story REAL NOT NULL DEFAULT 0.0,
+
+    -- Liberation status (LiberatedStatus enum: NotLiberated=0, Liberated=1, Error=2)
+    book_status INTEGER NOT NULL DEFAULT 0,
+    pdf_status INTEGER,  -- Nullable
+
+    -- Download tracking
+    last_downloaded TEXT,  -- ISO 8601 timestamp
+    last_downloaded_version TEXT,  -- Libation version string
+    last_downloaded_format INTEGER,  -- AudioFormat serialized as i64
+    last_downloaded_file_version TEXT,  -- Audio file version string
+
+    -- User state
+    is_finished INTEGER NOT NULL DEFAULT 0, position_ms INTEGER, progress_percent REAL NOT NULL DEFAULT 0, progress_updated_at TEXT, output_root TEXT,  -- Has user finished listening?
+
+    FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+);
+
+CREATE TABLE Contributors (
+    contributor_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    audible_contributor_id TEXT,
+    UNIQUE(name, audible_contributor_id)
+);
+
+CREATE TABLE Series (
+    series_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audible_series_id TEXT NOT NULL UNIQUE,
+    name TEXT
+);
+
+CREATE TABLE Categories (
+    category_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audible_category_id TEXT,
+    name TEXT
+);
+
+CREATE TABLE CategoryLadders (
+    category_ladder_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    audible_ladder_id TEXT NOT NULL UNIQUE,
+    ladder TEXT NOT NULL  -- JSON array of category IDs representing the path
+);
+
+CREATE TABLE Supplements (
+    supplement_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    book_id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+);
+
+CREATE TABLE BookContributors (
+    book_id INTEGER NOT NULL,
+    contributor_id INTEGER NOT NULL,
+    role INTEGER NOT NULL,  -- Role enum (Author=1, Narrator=2, Publisher=3)
+    "order" INTEGER NOT NULL DEFAULT 0,  -- Order within role (quoted keyword)
+    FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE,
+    FOREIGN KEY (contributor_id) REFERENCES Contributors(contributor_id) ON DELETE CASCADE,
+    PRIMARY KEY (book_id, contributor_id, role)
+);
+
+CREATE TABLE SeriesBooks (
+    series_id INTEGER NOT NULL,
+    book_id INTEGER NOT NULL,
+    "order" TEXT,  -- Order string (e.g., "1", "2.5", "Book 3")
+    "index" REAL NOT NULL DEFAULT 0.0, sort_key TEXT,  -- Numeric index extracted from order string
+    FOREIGN KEY (series_id) REFERENCES Series(series_id) ON DELETE CASCADE,
+    FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE,
+    PRIMARY KEY (series_id, book_id)
+);
+
+CREATE TABLE BookCategories (
+    book_id INTEGER NOT NULL,
+    category_ladder_id INTEGER NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE,
+    FOREIGN KEY (category_ladder_id) REFERENCES CategoryLadders(category_ladder_id) ON DELETE CASCADE,
+    PRIMARY KEY (book_id, category_ladder_id)
+);
+
+CREATE TABLE DownloadTasks (
+    task_id TEXT PRIMARY KEY,  -- UUID
+    asin TEXT NOT NULL,
+    title TEXT NOT NULL,
+    status TEXT NOT NULL,  -- "queued", "downloading", "paused", "completed", "failed", "cancelled"
+
+    -- Download progress
+    bytes_downloaded INTEGER NOT NULL DEFAULT 0,
+    total_bytes INTEGER NOT NULL DEFAULT 0,
+
+    -- Download info
+    download_url TEXT NOT NULL,
+    download_path TEXT NOT NULL,  -- Cache path for encrypted file
+    output_path TEXT NOT NULL,    -- Final path after decryption
+    request_headers TEXT NOT NULL, -- JSON object with HTTP headers
+
+    -- Error tracking
+    error TEXT,
+    retry_count INTEGER NOT NULL DEFAULT 0,
+
+    -- Timestamps
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TEXT,
+    completed_at TEXT
+, aaxc_key TEXT, aaxc_iv TEXT, output_directory TEXT, chunk_manifest TEXT, account TEXT, sha256 TEXT, mirror_urls TEXT, cdn_host TEXT, buffer_overrides TEXT, buffering TEXT, progress_epoch INTEGER NOT NULL DEFAULT 0, trace_id TEXT, companion_of TEXT, companion_kind TEXT, wifi_only INTEGER, network_paused INTEGER NOT NULL DEFAULT 0, follow_up TEXT);
+
+CREATE TABLE Accounts (
+    account_id TEXT PRIMARY KEY,  -- Unique account identifier (email or username)
+    account_name TEXT NOT NULL,
+    locale_code TEXT NOT NULL,    -- Country code (e.g., "us", "uk", "de")
+
+    -- Identity JSON (contains all auth data)
+    -- Stores access_token, refresh_token, device info, cookies, etc.
+    identity_json TEXT NOT NULL,
+
+    -- Token expiry tracking
+    token_expires_at TEXT,        -- ISO 8601 timestamp
+
+    -- Account settings
+    library_scan INTEGER NOT NULL DEFAULT 1,
+    decrypt_key TEXT,             -- Activation bytes (8 hex chars)
+
+    -- Timestamps
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_token_refresh TEXT,      -- Last successful token refresh
+    last_library_sync TEXT        -- Last successful library sync
+, registered_at TEXT, refresh_token_issued_at TEXT, last_api_success_at TEXT, profile_id TEXT NOT NULL DEFAULT 'default');
+
+CREATE TABLE Tags (
+            tag_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE  -- lowercase, alphanumeric + underscore
+        );
+
+CREATE TABLE BookTags (
+            book_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (book_id, tag_id),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES Tags(tag_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE DownloadUsage (
+            account TEXT NOT NULL,
+            month TEXT NOT NULL,  -- UTC, YYYY-MM
+            bytes_downloaded INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (account, month)
+        );
+
+CREATE TABLE DownloadQuotas (
+            account TEXT PRIMARY KEY,
+            monthly_cap_bytes INTEGER NOT NULL,
+            action TEXT NOT NULL DEFAULT 'warn'  -- "warn" or "block"
+        );
+
+CREATE TABLE BookChapters (
+            book_id INTEGER NOT NULL,
+            chapter_index INTEGER NOT NULL,  -- 0-based position
+            title TEXT NOT NULL,
+            start_ms INTEGER NOT NULL,
+            end_ms INTEGER NOT NULL,
+            PRIMARY KEY (book_id, chapter_index),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE SyncIssues (
+            issue_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account TEXT NOT NULL,
+            asin TEXT NOT NULL,
+            stage TEXT NOT NULL,  -- "contributor", "series" or "book"
+            code TEXT NOT NULL,
+            message TEXT NOT NULL,
+            occurrences INTEGER NOT NULL DEFAULT 1,
+            first_seen TEXT NOT NULL,
+            last_seen TEXT NOT NULL,
+            resolved_at TEXT  -- NULL while unresolved
+        );
+
+CREATE TABLE ReadAlongMappings (
+            book_id INTEGER PRIMARY KEY,
+            companion_asin TEXT,  -- Kindle edition, NULL if none
+            sync_points TEXT NOT NULL DEFAULT '[]',  -- JSON array, empty when unavailable
+            fetched_at TEXT NOT NULL,
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE LocalizedTitles (
+            book_id INTEGER NOT NULL,
+            locale TEXT NOT NULL,  -- Marketplace country code
+            title TEXT,  -- NULL if the marketplace doesn't sell the book
+            subtitle TEXT,
+            title_sort TEXT,
+            title_search TEXT,
+            fetched_at TEXT NOT NULL,
+            PRIMARY KEY (book_id, locale),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE ValidationIssues (
+            book_id INTEGER NOT NULL,
+            check_code TEXT NOT NULL,  -- e.g. "missing_authors", "zero_runtime"
+            severity TEXT NOT NULL,  -- "error", "warning" or "info"
+            message TEXT NOT NULL,
+            detected_at TEXT NOT NULL,
+            PRIMARY KEY (book_id, check_code),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE PendingTokenRefreshes (
+            account_id TEXT PRIMARY KEY,
+            access_token TEXT NOT NULL,
+            expires_at TEXT NOT NULL,  -- ISO 8601 timestamp
+            refresh_token TEXT,  -- Only when Amazon rotated it
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+CREATE TABLE FileIntegrity (
+            asin TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL,  -- Download task the digests belong to
+            path TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            sample_digest TEXT NOT NULL,  -- xxh3 of sampled regions
+            status TEXT NOT NULL DEFAULT 'ok',  -- "ok", "corrupted" or "missing"
+            problem TEXT,  -- Why the last check failed
+            baseline_at TEXT NOT NULL,
+            checked_at TEXT NOT NULL
+        );
+
+CREATE TABLE Profiles (
+            profile_id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            is_active INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+
+CREATE TABLE "Settings" (
+                profile_id TEXT NOT NULL DEFAULT 'default',
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (profile_id, key)
+            );
+
+CREATE TABLE Jobs (
+            job_id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            finished_at TEXT,
+            error TEXT
+        , owner TEXT);
+
+CREATE TABLE JobArtifacts (
+            job_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            reference TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (job_id, kind, reference),
+            FOREIGN KEY (job_id) REFERENCES Jobs(job_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE Notes (
+            note_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            book_id INTEGER NOT NULL,
+            profile_id TEXT NOT NULL DEFAULT 'default',
+            text TEXT NOT NULL,
+            position_ms INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE VIRTUAL TABLE NotesSearch USING fts5(
+            text,
+            content = 'Notes',
+            content_rowid = 'note_id',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+CREATE TABLE LiberationReceipts (
+            receipt_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            asin TEXT NOT NULL,
+            task_id TEXT NOT NULL,
+            source TEXT NOT NULL,
+            source_ref TEXT,
+            license_id TEXT,
+            decrypt_method TEXT NOT NULL,
+            download_ms INTEGER,
+            decrypt_ms INTEGER,
+            toolchain TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+
+CREATE TABLE LiberationReceiptFiles (
+            receipt_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            location TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            PRIMARY KEY (receipt_id, position),
+            FOREIGN KEY (receipt_id) REFERENCES LiberationReceipts(receipt_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE OfflineLicenses (
+            asin TEXT PRIMARY KEY,
+            quality TEXT NOT NULL,
+            file_type TEXT NOT NULL,
+            license TEXT NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            expires_at TEXT,
+            fetched_at TEXT NOT NULL
+        );
+
+CREATE TABLE BookFiles (
+            file_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            book_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,  -- CompanionKind: "bonus_audio", "author_interview" or "pdf"
+            source_asin TEXT,  -- ASIN of companion audio
+            path TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            task_id TEXT,  -- Download task that fetched it
+            created_at TEXT NOT NULL, sha256 TEXT,
+            UNIQUE (book_id, path),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE VIRTUAL TABLE BooksSearch USING fts5(
+            title,
+            subtitle,
+            description,
+            authors,
+            narrators,
+            series,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+CREATE TABLE ListeningPositions (
+            asin TEXT NOT NULL,
+            kind TEXT NOT NULL,  -- AnnotationKind: "last_heard", "bookmark", "note" or "clip"
+            annotation_id TEXT NOT NULL DEFAULT '',  -- Audible's id; empty for last_heard
+            position_ms INTEGER NOT NULL,
+            end_position_ms INTEGER,  -- End of a note or clip
+            note TEXT,  -- Note text or clip title
+            updated_at TEXT,  -- When it was set, on whichever device
+            synced_at TEXT NOT NULL,  -- When it was fetched from or pushed to Audible
+            PRIMARY KEY (asin, kind, annotation_id)
+        );
+
+CREATE TABLE PodcastEpisodes (
+            series_id INTEGER NOT NULL,  -- Series row of the parent's ASIN
+            book_id INTEGER NOT NULL,  -- Episode, or a season of a show
+            "order" TEXT,  -- Sequence under the parent as sent
+            sort_key TEXT,  -- See series_order
+            PRIMARY KEY (series_id, book_id),
+            FOREIGN KEY (series_id) REFERENCES Series(series_id) ON DELETE CASCADE,
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE TABLE LibraryBookAccounts (
+            book_id INTEGER NOT NULL,
+            account TEXT NOT NULL,  -- Account ID/email
+            date_added TEXT NOT NULL,  -- Purchase date in this account
+            is_deleted INTEGER NOT NULL DEFAULT 0,
+            absent_from_last_scan INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (book_id, account),
+            FOREIGN KEY (book_id) REFERENCES Books(book_id) ON DELETE CASCADE
+        );
+
+CREATE INDEX idx_books_asin ON Books(audible_product_id);
+
+CREATE INDEX idx_books_locale ON Books(locale);
+
+CREATE INDEX idx_books_title ON Books(title);
+
+CREATE INDEX idx_books_content_type ON Books(content_type);
+
+CREATE INDEX idx_books_updated_at ON Books(updated_at);
+
+CREATE INDEX idx_library_books_account ON LibraryBooks(account);
+
+CREATE INDEX idx_library_books_date_added ON LibraryBooks(date_added);
+
+CREATE INDEX idx_library_books_is_deleted ON LibraryBooks(is_deleted);
+
+CREATE INDEX idx_contributors_name ON Contributors(name);
+
+CREATE INDEX idx_contributors_audible_id ON Contributors(audible_contributor_id);
+
+CREATE INDEX idx_book_contributors_book ON BookContributors(book_id, role, "order");
+
+CREATE INDEX idx_book_contributors_contributor ON BookContributors(contributor_id);
+
+CREATE INDEX idx_series_audible_id ON Series(audible_series_id);
+
+CREATE INDEX idx_series_books_series ON SeriesBooks(series_id, "index");
+
+CREATE INDEX idx_series_books_book ON SeriesBooks(book_id);
+
+CREATE INDEX idx_categories_audible_id ON Categories(audible_category_id);
+
+CREATE INDEX idx_category_ladders_audible_id ON CategoryLadders(audible_ladder_id);
+
+CREATE INDEX idx_supplements_book ON Supplements(book_id);
+
+CREATE INDEX idx_download_tasks_status ON DownloadTasks(status);
+
+CREATE INDEX idx_download_tasks_asin ON DownloadTasks(asin);
+
+CREATE INDEX idx_download_tasks_created_at ON DownloadTasks(created_at);
+
+CREATE INDEX idx_accounts_locale ON Accounts(locale_code);
+
+CREATE INDEX idx_accounts_updated ON Accounts(updated_at);
+
+CREATE INDEX idx_books_source ON Books(source);
+
+CREATE INDEX idx_books_title_sort ON Books(title_sort);
+
+CREATE INDEX idx_books_date_published ON Books(date_published);
+
+CREATE INDEX idx_books_length ON Books(length_in_minutes);
+
+CREATE INDEX idx_book_tags_tag ON BookTags(tag_id);
+
+CREATE INDEX idx_sync_issues_open ON SyncIssues(account, asin) WHERE resolved_at IS NULL;
+
+CREATE INDEX idx_user_items_progress ON UserDefinedItems(progress_percent, progress_updated_at);
+
+CREATE INDEX idx_file_integrity_checked ON FileIntegrity(checked_at);
+
+CREATE INDEX idx_accounts_profile ON Accounts(profile_id);
+
+CREATE INDEX idx_jobs_status ON Jobs(status);
+
+CREATE INDEX idx_jobs_started ON Jobs(started_at);
+
+CREATE INDEX idx_notes_book ON Notes(book_id, profile_id);
+
+CREATE INDEX idx_receipts_asin ON LiberationReceipts(asin);
+
+CREATE INDEX idx_receipt_files_sha256 ON LiberationReceiptFiles(sha256);
+
+CREATE INDEX idx_offline_licenses_expires ON OfflineLicenses(expires_at);
+
+CREATE INDEX idx_series_books_sort_key ON SeriesBooks(series_id, sort_key);
+
+CREATE INDEX idx_podcast_episodes_book ON PodcastEpisodes(book_id);
+
+CREATE INDEX idx_library_book_accounts_account ON LibraryBookAccounts(account);
+
+CREATE UNIQUE INDEX idx_books_asin_key ON Books(UPPER(TRIM(audible_product_id)));
+
+CREATE VIEW BookSearchText AS
+        SELECT
+            b.book_id,
+            b.title,
+            b.subtitle,
+            b.description,
+            (SELECT GROUP_CONCAT(c.name, ', ') FROM BookContributors bc
+             JOIN Contributors c ON c.contributor_id = bc.contributor_id
+             WHERE bc.book_id = b.book_id AND bc.role = 1) AS authors,
+            (SELECT GROUP_CONCAT(c.name, ', ') FROM BookContributors bc
+             JOIN Contributors c ON c.contributor_id = bc.contributor_id
+             WHERE bc.book_id = b.book_id AND bc.role = 2) AS narrators,
+            (SELECT GROUP_CONCAT(s.name, ', ') FROM SeriesBooks sb
+             JOIN Series s ON s.series_id = sb.series_id
+             WHERE sb.book_id = b.book_id) AS series
+        FROM Books b;
+
+CREATE TRIGGER update_books_timestamp
+        AFTER UPDATE ON Books
+        FOR EACH ROW
+        BEGIN
+            UPDATE Books SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE book_id = NEW.book_id;
+        END;
+
+CREATE TRIGGER update_accounts_timestamp
+        AFTER UPDATE ON Accounts
+        FOR EACH ROW
+        BEGIN
+            UPDATE Accounts SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE account_id = NEW.account_id;
+        END;
+
+CREATE TRIGGER utc_books_created
+        AFTER INSERT ON Books
+        FOR EACH ROW
+        BEGIN
+            UPDATE Books SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE book_id = NEW.book_id;
+        END;
+
+CREATE TRIGGER utc_accounts_created
+        AFTER INSERT ON Accounts
+        FOR EACH ROW
+        BEGIN
+            UPDATE Accounts SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE account_id = NEW.account_id;
+        END;
+
+CREATE TRIGGER utc_library_books_added
+        AFTER INSERT ON LibraryBooks
+        FOR EACH ROW
+        BEGIN
+            UPDATE LibraryBooks SET date_added = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.date_added), NEW.date_added)
+            WHERE book_id = NEW.book_id;
+        END;
+
+CREATE TRIGGER utc_download_tasks_created
+        AFTER INSERT ON DownloadTasks
+        FOR EACH ROW
+        BEGIN
+            UPDATE DownloadTasks SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE task_id = NEW.task_id;
+        END;
+
+CREATE TRIGGER utc_pending_token_refreshes_created
+        AFTER INSERT ON PendingTokenRefreshes
+        FOR EACH ROW
+        BEGIN
+            UPDATE PendingTokenRefreshes SET created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', NEW.created_at), NEW.created_at)
+            WHERE account_id = NEW.account_id;
+        END;
+
+CREATE TRIGGER notes_search_insert AFTER INSERT ON Notes BEGIN
+            INSERT INTO NotesSearch (rowid, text) VALUES (new.note_id, new.text);
+        END;
+
+CREATE TRIGGER notes_search_delete AFTER DELETE ON Notes BEGIN
+            INSERT INTO NotesSearch (NotesSearch, rowid, text) VALUES ('delete', old.note_id, old.text);
+        END;
+
+CREATE TRIGGER notes_search_update AFTER UPDATE OF text ON Notes BEGIN
+            INSERT INTO NotesSearch (NotesSearch, rowid, text) VALUES ('delete', old.note_id, old.text);
+            INSERT INTO NotesSearch (rowid, text) VALUES (new.note_id, new.text);
+        END;
+
+CREATE TRIGGER books_search_insert AFTER INSERT ON Books BEGIN
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+CREATE TRIGGER books_search_update AFTER UPDATE OF title, subtitle, description ON Books BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+CREATE TRIGGER books_search_delete AFTER DELETE ON Books BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+        END;
+
+CREATE TRIGGER books_search_contributor_insert AFTER INSERT ON BookContributors BEGIN
+            DELETE FROM BooksSearch WHERE rowid = new.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+CREATE TRIGGER books_search_contributor_delete AFTER DELETE ON BookContributors BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = old.book_id;
+        END;
+
+CREATE TRIGGER books_search_series_insert AFTER INSERT ON SeriesBooks BEGIN
+            DELETE FROM BooksSearch WHERE rowid = new.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = new.book_id;
+        END;
+
+CREATE TRIGGER books_search_series_delete AFTER DELETE ON SeriesBooks BEGIN
+            DELETE FROM BooksSearch WHERE rowid = old.book_id;
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText WHERE book_id = old.book_id;
+        END;
+
+CREATE TRIGGER books_search_contributor_rename AFTER UPDATE OF name ON Contributors BEGIN
+            DELETE FROM BooksSearch
+            WHERE rowid IN (SELECT book_id FROM BookContributors WHERE contributor_id = new.contributor_id);
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText
+            WHERE book_id IN (SELECT book_id FROM BookContributors WHERE contributor_id = new.contributor_id);
+        END;
+
+CREATE TRIGGER books_search_series_rename AFTER UPDATE OF name ON Series BEGIN
+            DELETE FROM BooksSearch
+            WHERE rowid IN (SELECT book_id FROM SeriesBooks WHERE series_id = new.series_id);
+            INSERT INTO BooksSearch (rowid, title, subtitle, description, authors, narrators, series)
+            SELECT * FROM BookSearchText
+            WHERE book_id IN (SELECT book_id FROM SeriesBooks WHERE series_id = new.series_id);
+        END;
+
+INSERT INTO _migrations (id, name) VALUES
+    (1, 'initial_schema'),
+    (2, 'download_tasks'),
+    (3, 'accounts'),
+    (4, 'download_conversion_columns'),
+    (5, 'add_source_column'),
+    (6, 'add_download_format_column'),
+    (7, 'add_chunk_manifest_column'),
+    (8, 'add_benefit_type_column'),
+    (9, 'add_title_sort_columns'),
+    (10, 'add_range_filter_indexes'),
+    (11, 'tags_tables'),
+    (12, 'download_usage'),
+    (13, 'book_chapters'),
+    (14, 'add_sha256_column'),
+    (15, 'sync_issues'),
+    (16, 'read_along_mappings'),
+    (17, 'settings'),
+    (18, 'account_token_tracking'),
+    (19, 'localized_titles'),
+    (20, 'validation_issues'),
+    (21, 'download_cdn_columns'),
+    (22, 'add_format_support_column'),
+    (23, 'listening_progress_columns'),
+    (24, 'narration_flag_columns'),
+    (25, 'pending_token_refreshes'),
+    (26, 'file_integrity'),
+    (27, 'download_buffering_columns'),
+    (28, 'utc_timestamps'),
+    (29, 'profiles'),
+    (30, 'jobs'),
+    (31, 'notes'),
+    (32, 'liberation_receipts'),
+    (33, 'offline_licenses'),
+    (34, 'download_progress_epoch'),
+    (35, 'download_trace_id'),
+    (36, 'book_output_root'),
+    (37, 'book_files'),
+    (38, 'series_sort_key'),
+    (39, 'books_search'),
+    (40, 'listening_positions'),
+    (41, 'download_network_columns'),
+    (42, 'podcast_episodes'),
+    (43, 'book_cover_path'),
+    (44, 'download_follow_up'),
+    (45, 'library_book_accounts'),
+    (46, 'merge_duplicate_asins'),
+    (47, 'job_owner'),
+    (48, 'book_file_sha256');
@@ -0,0 +1,334 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Ranked library search with typo tolerance
+//!
+//! `search_library` matches every word of the query against titles,
+//! authors, narrators and series, all folded (see `normalize`), so
+//! "bronte" finds "Brontë" however the name was encoded. Query words match
+//! anywhere in a field, so "silmar" finds "The Silmarillion". With
+//! `fuzzy`, a word of 4 or more letters also matches a word, or the start
+//! of a longer one, within one edit (two for 8 or more letters; swapping
+//! neighbours counts as one), so "tolkein" finds Tolkien.
+//!
+//! Candidates are the books `list_books_with_filters` returns for the same
+//! filters; scoring runs in Rust. A book matches when every query word
+//! matches one of its fields. Exact matches at the start of a word score
+//! highest, then exact matches inside a word, then fuzzy ones, weighted by
+//! field (title first, narrator last). Each hit lists where the words
+//! matched, as UTF-16 ranges into the returned book's fields.
+
+use crate::error::Result;
+use crate::storage::normalize::{fold, fold_with_offsets};
+use crate::storage::queries::{list_books_with_filters, BookQueryParams, BookWithRelations};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Shortest query word matched fuzzily
+const MIN_FUZZY_LEN: usize = 4;
+
+/// Field of a book a query word matched, named as in the book's JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Title,
+    Subtitle,
+    AlternateTitle,
+    AuthorsStr,
+    NarratorsStr,
+    SeriesName,
+}
+
+impl SearchField {
+    fn weight(&self) -> f32 {
+        match self {
+            SearchField::Title => 1.0,
+            SearchField::AuthorsStr => 0.9,
+            SearchField::AlternateTitle | SearchField::SeriesName => 0.8,
+            SearchField::Subtitle => 0.6,
+            SearchField::NarratorsStr => 0.5,
+        }
+    }
+}
+
+/// Where a query word matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchSpan {
+    pub field: SearchField,
+    /// UTF-16 offsets into the field's value
+    pub start: usize,
+    pub end: usize,
+    /// Matched within the typo tolerance rather than exactly
+    pub fuzzy: bool,
+}
+
+/// A book found by `search_library`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub book: BookWithRelations,
+    pub score: f32,
+    pub matches: Vec<MatchSpan>,
+}
+
+/// Search the library, best matches first
+///
+/// `params` filters the candidates like `list_books_with_filters` (its
+/// `search_query` and sort are ignored); `limit` and `offset` page the
+/// ranked hits. Equal scores keep title order.
+pub async fn search_library(
+    pool: &SqlitePool,
+    query: &str,
+    params: &BookQueryParams,
+    fuzzy: bool,
+) -> Result<Vec<SearchHit>> {
+    let folded = fold(query);
+    let terms: Vec<Vec<char>> = folded
+        .split(' ')
+        .map(|term| term.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|term| !term.is_empty())
+        .map(|term| term.chars().collect())
+        .collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let candidates = list_books_with_filters(
+        pool,
+        &BookQueryParams {
+            search_query: None,
+            sort_field: None,
+            sort_direction: None,
+            limit: i64::MAX,
+            offset: 0,
+            ..params.clone()
+        },
+    )
+    .await?;
+
+    let mut hits: Vec<SearchHit> = candidates
+        .into_iter()
+        .filter_map(|book| score_book(book, &terms, fuzzy))
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    Ok(hits
+        .into_iter()
+        .skip(params.offset.max(0) as usize)
+        .take(params.limit.max(0) as usize)
+        .collect())
+}
+
+/// A field value folded for matching
+struct FoldedField {
+    field: SearchField,
+    chars: Vec<char>,
+    offsets: Vec<(usize, usize)>,
+    /// Char ranges of alphanumeric runs
+    words: Vec<(usize, usize)>,
+}
+
+impl FoldedField {
+    fn new(field: SearchField, value: &str) -> Self {
+        let (folded, offsets) = fold_with_offsets(value);
+        let chars: Vec<char> = folded.chars().collect();
+        let mut words = Vec::new();
+        let mut start = None;
+        for (i, c) in chars.iter().enumerate() {
+            match (c.is_alphanumeric(), start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    words.push((s, i));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            words.push((s, chars.len()));
+        }
+        Self { field, chars, offsets, words }
+    }
+
+    fn span(&self, start: usize, end: usize, fuzzy: bool) -> MatchSpan {
+        MatchSpan {
+            field: self.field,
+            start: self.offsets[start].0,
+            end: self.offsets[end - 1].1,
+            fuzzy,
+        }
+    }
+
+    /// Best match of `term` in this field: score and char range
+    fn best_match(&self, term: &[char], fuzzy: bool) -> Option<(f32, MatchSpan)> {
+        let weight = self.field.weight();
+
+        // Exact: prefer an occurrence at the start of a word
+        let mut inner = None;
+        for start in 0..self.chars.len().saturating_sub(term.len() - 1) {
+            if self.chars[start..start + term.len()] != *term {
+                continue;
+            }
+            let at_word_start = start == 0 || !self.chars[start - 1].is_alphanumeric();
+            if at_word_start {
+                return Some((weight * 3.0, self.span(start, start + term.len(), false)));
+            }
+            inner.get_or_insert(start);
+        }
+        if let Some(start) = inner {
+            return Some((weight * 2.0, self.span(start, start + term.len(), false)));
+        }
+
+        if !fuzzy || term.len() < MIN_FUZZY_LEN {
+            return None;
+        }
+        let max_distance = if term.len() >= 8 { 2 } else { 1 };
+
+        // Fuzzy: a whole word, or the start of a longer one (still typing)
+        let mut best: Option<(usize, usize, usize)> = None;
+        for &(start, end) in &self.words {
+            let word = &self.chars[start..end];
+            let mut candidates = vec![(word, end)];
+            if word.len() > term.len() {
+                candidates.push((&word[..term.len()], start + term.len()));
+            }
+            for (candidate, candidate_end) in candidates {
+                let distance = edit_distance(term, candidate);
+                if distance <= max_distance && best.is_none_or(|(d, _, _)| distance < d) {
+                    best = Some((distance, start, candidate_end));
+                }
+            }
+        }
+        best.map(|(distance, start, end)| (weight / (1 + distance) as f32, self.span(start, end, true)))
+    }
+}
+
+/// Score a book against every term; None unless all of them match
+fn score_book(book: BookWithRelations, terms: &[Vec<char>], fuzzy: bool) -> Option<SearchHit> {
+    let fields: Vec<FoldedField> = [
+        (SearchField::Title, Some(book.title.as_str())),
+        (SearchField::Subtitle, book.subtitle.as_deref()),
+        (SearchField::AlternateTitle, book.alternate_title.as_deref()),
+        (SearchField::AuthorsStr, book.authors_str.as_deref()),
+        (SearchField::NarratorsStr, book.narrators_str.as_deref()),
+        (SearchField::SeriesName, book.series_name.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(field, value)| value.filter(|v| !v.is_empty()).map(|v| FoldedField::new(field, v)))
+    .collect();
+
+    let mut score = 0.0;
+    let mut matches = Vec::with_capacity(terms.len());
+    for term in terms {
+        let (term_score, span) = fields
+            .iter()
+            .filter_map(|field| field.best_match(term, fuzzy))
+            .max_by(|a, b| a.0.total_cmp(&b.0))?;
+        score += term_score;
+        if !matches.contains(&span) {
+            matches.push(span);
+        }
+    }
+
+    Some(SearchHit { book, score, matches })
+}
+
+/// Optimal string alignment distance: Levenshtein plus adjacent swaps
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = d;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::{NewBook, NewContributor, NewLibraryBook};
+    use crate::storage::queries::{add_book_contributor, insert_book, insert_library_book, upsert_contributor};
+    use crate::storage::Database;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance(&chars("tolkein"), &chars("tolkien")), 1);
+        assert_eq!(edit_distance(&chars("hobit"), &chars("hobbit")), 1);
+        assert_eq!(edit_distance(&chars("dune"), &chars("dune")), 0);
+        assert_eq!(edit_distance(&chars("abc"), &chars("")), 3);
+    }
+
+    #[tokio::test]
+    async fn test_search_library() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        for (asin, title, author) in [
+            ("B0SEARCH01", "The Silmarillion", "J.R.R. Tolkien"),
+            ("B0SEARCH02", "Jane Eyre", "Charlotte Bronte\u{308}"),
+            ("B0SEARCH03", "Tolkien: A Biography", "Humphrey Carpenter"),
+        ] {
+            let book_id = insert_book(pool, &NewBook::new(asin.to_string(), title.to_string(), "us".to_string()))
+                .await
+                .unwrap();
+            insert_library_book(pool, &NewLibraryBook { book_id, account: "test".to_string() })
+                .await
+                .unwrap();
+            let contributor_id = upsert_contributor(pool, &NewContributor::new(author.to_string())).await.unwrap();
+            add_book_contributor(pool, book_id, contributor_id, 1, 0).await.unwrap();
+        }
+        let params = BookQueryParams { limit: 10, ..Default::default() };
+
+        // Title matches outrank author matches; offsets point into the field
+        let hits = search_library(pool, "TOLKIEN", &params, false).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].book.audible_product_id, "B0SEARCH03");
+        assert_eq!(hits[0].matches[0], MatchSpan { field: SearchField::Title, start: 0, end: 7, fuzzy: false });
+        assert_eq!(hits[1].matches[0].field, SearchField::AuthorsStr);
+        assert_eq!((hits[1].matches[0].start, hits[1].matches[0].end), (7, 14));
+
+        // Decomposed diacritics fold; the highlight covers the mark too
+        let hits = search_library(pool, "brontë", &params, false).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].matches[0].start, hits[0].matches[0].end), (10, 17));
+
+        // Typos and prefixes only with fuzzy; every word has to match
+        assert!(search_library(pool, "tolkein silmarilion", &params, false).await.unwrap().is_empty());
+        let hits = search_library(pool, "tolkein silmarilion", &params, true).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].book.audible_product_id, "B0SEARCH01");
+        assert!(hits[0].matches.iter().all(|m| m.fuzzy));
+        assert!(search_library(pool, "eyre zzzz", &params, true).await.unwrap().is_empty());
+        let hits = search_library(pool, "silmar", &params, true).await.unwrap();
+        assert!(!hits[0].matches[0].fuzzy);
+        assert!(search_library(pool, " , ", &params, true).await.unwrap().is_empty());
+    }
+}
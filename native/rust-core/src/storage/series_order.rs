@@ -0,0 +1,301 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Series sequence parsing and ordering
+//!
+//! Audible's series sequences are free text: "1", "0.5", "1-3" (an
+//! omnibus), "Book 3", "3a", "Prequel", "Novella". Parsing them as floats
+//! sorted "1-3" as 13 and every text entry as 0. Each sequence is now
+//! parsed once at import into a `SeriesSequence`, and `SeriesBooks` stores:
+//!
+//! - `"order"`: the raw sequence, for display
+//! - `"index"`: the first number in it (0 when there is none)
+//! - `sort_key`: a text key ordering every entry deterministically
+//!
+//! Entries sort as prequels, then numbered entries (by first number, then
+//! last number of a range, then letter suffix), then other text entries
+//! alphabetically, then entries without a sequence. Ties fall back to the
+//! folded raw text, so equal keys only come from equal sequences.
+
+use crate::error::Result;
+use crate::storage::normalize::fold;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+
+/// Words that make an entry a prequel
+const PREQUEL_WORDS: [&str; 2] = ["prequel", "prologue"];
+
+/// Digits kept on each side of the decimal point in sort keys
+///
+/// Key fields are separated by spaces, which sort before every digit and
+/// letter, so "3" sorts before "3a".
+const KEY_INT_DIGITS: usize = 8;
+const KEY_FRACTION_DIGITS: usize = 4;
+
+/// What a sequence is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceKind {
+    /// "Prequel", "Prequel 2"
+    Prequel,
+    /// "1", "0.5", "1-3", "Book 3", "3a"
+    Number,
+    /// Text without a number ("Novella", "Companion")
+    Text,
+    /// Empty
+    Missing,
+}
+
+/// A parsed series sequence
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeriesSequence {
+    pub raw: String,
+    pub kind: SequenceKind,
+    /// First number ("1-3" → 1)
+    pub start: Option<f32>,
+    /// Last number of a range ("1-3" → 3), else `start`
+    pub end: Option<f32>,
+    pub sort_key: String,
+}
+
+impl SeriesSequence {
+    /// Value stored in `SeriesBooks."index"`
+    pub fn index(&self) -> f32 {
+        self.start.unwrap_or(0.0)
+    }
+}
+
+/// A number found in a sequence, as digit strings so keys don't round
+#[derive(Debug, Clone, PartialEq)]
+struct Number {
+    int: String,
+    fraction: String,
+}
+
+impl Number {
+    fn value(&self) -> f32 {
+        format!("{}.{}", self.int, self.fraction).parse().unwrap_or(0.0)
+    }
+
+    /// Fixed-width text that sorts like the number
+    fn key(&self) -> String {
+        let int = self.int.trim_start_matches('0');
+        let int = if int.len() > KEY_INT_DIGITS { "9".repeat(KEY_INT_DIGITS) } else { int.to_string() };
+        let fraction: String = self.fraction.chars().take(KEY_FRACTION_DIGITS).collect();
+        format!("{:0>iw$}.{:0<fw$}", int, fraction, iw = KEY_INT_DIGITS, fw = KEY_FRACTION_DIGITS)
+    }
+}
+
+/// Read a number at `chars[i..]`, returning it and the index after it
+fn number_at(chars: &[char], i: usize) -> Option<(Number, usize)> {
+    let mut j = i;
+    while j < chars.len() && chars[j].is_ascii_digit() {
+        j += 1;
+    }
+    if j == i {
+        return None;
+    }
+    let int: String = chars[i..j].iter().collect();
+    // "2.5" and "2,5"
+    if j + 1 < chars.len() && (chars[j] == '.' || chars[j] == ',') && chars[j + 1].is_ascii_digit() {
+        let start = j + 1;
+        let mut k = start;
+        while k < chars.len() && chars[k].is_ascii_digit() {
+            k += 1;
+        }
+        return Some((Number { int, fraction: chars[start..k].iter().collect() }, k));
+    }
+    Some((Number { int, fraction: String::new() }, j))
+}
+
+/// End of a range starting at `chars[i..]` ("-3", " – 3", " to 3")
+fn range_end_at(chars: &[char], i: usize) -> Option<Number> {
+    let rest: String = chars[i..].iter().collect();
+    let rest = rest.trim_start();
+    let rest = ["-", "–", "—", "to "]
+        .iter()
+        .find_map(|sep| rest.strip_prefix(sep))?
+        .trim_start();
+    let rest: Vec<char> = rest.chars().collect();
+    number_at(&rest, 0).map(|(number, _)| number)
+}
+
+/// Parse a series sequence
+pub fn parse_sequence(raw: &str) -> SeriesSequence {
+    let folded = fold(raw);
+    let chars: Vec<char> = folded.chars().collect();
+
+    let found = chars
+        .iter()
+        .position(|c| c.is_ascii_digit())
+        .and_then(|i| number_at(&chars, i));
+    let prequel = PREQUEL_WORDS.iter().any(|word| folded.contains(word));
+
+    let (kind, start, end, sort_key) = match found {
+        _ if folded.is_empty() => (SequenceKind::Missing, None, None, "3".to_string()),
+        Some((start, next)) => {
+            let end = range_end_at(&chars, next).unwrap_or_else(|| start.clone());
+            let suffix: String = chars[next..].iter().take_while(|c| c.is_alphabetic()).collect();
+            let (kind, group) = if prequel { (SequenceKind::Prequel, 0) } else { (SequenceKind::Number, 1) };
+            let key = format!("{} {} {} {} {}", group, start.key(), end.key(), suffix, folded);
+            (kind, Some(start.value()), Some(end.value()), key)
+        }
+        // Empty number fields, so before numbered prequels
+        None if prequel => (SequenceKind::Prequel, None, None, format!("0     {}", folded)),
+        None => (SequenceKind::Text, None, None, format!("2 {}", folded)),
+    };
+
+    SeriesSequence { raw: raw.trim().to_string(), kind, start, end, sort_key }
+}
+
+/// A book in a series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SeriesEntry {
+    pub series_id: i64,
+    pub series_name: Option<String>,
+    pub book_id: i64,
+    pub asin: String,
+    pub title: String,
+    /// Raw sequence
+    pub sequence: Option<String>,
+    pub is_finished: bool,
+}
+
+/// Books of a series in series order
+pub async fn series_entries(pool: &SqlitePool, series_id: i64) -> Result<Vec<SeriesEntry>> {
+    let entries = sqlx::query_as::<_, SeriesEntry>(
+        r#"
+        SELECT sb.series_id, s.name as series_name, b.book_id, b.audible_product_id as asin, b.title,
+               sb."order" as sequence, b.is_finished
+        FROM SeriesBooks sb
+        JOIN Series s ON s.series_id = sb.series_id
+        JOIN Books b ON b.book_id = sb.book_id
+        WHERE sb.series_id = ?
+        ORDER BY sb.sort_key, b.title_sort, b.book_id
+        "#,
+    )
+    .bind(series_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Whether `candidate` comes after `current` for next-up purposes
+///
+/// A numbered entry is only next if it starts after the end of the
+/// current one, so an omnibus ("1-3") isn't next after book 1 and book 2
+/// isn't next after the omnibus.
+fn follows(current: &SeriesSequence, candidate: &SeriesSequence) -> bool {
+    if candidate.sort_key <= current.sort_key {
+        return false;
+    }
+    match (current.kind, candidate.kind, current.end, candidate.start) {
+        (SequenceKind::Number, SequenceKind::Number, Some(end), Some(start)) => start > end,
+        _ => true,
+    }
+}
+
+/// The next unfinished book after `book_id` in each of its series
+///
+/// Series where nothing unfinished follows are left out.
+pub async fn next_in_series(pool: &SqlitePool, book_id: i64) -> Result<Vec<SeriesEntry>> {
+    let series: Vec<(i64, Option<String>)> =
+        sqlx::query_as(r#"SELECT series_id, "order" FROM SeriesBooks WHERE book_id = ? ORDER BY sort_key"#)
+            .bind(book_id)
+            .fetch_all(pool)
+            .await?;
+
+    let mut next = Vec::new();
+    for (series_id, order) in series {
+        let current = parse_sequence(order.as_deref().unwrap_or_default());
+        let found = series_entries(pool, series_id).await?.into_iter().find(|entry| {
+            entry.book_id != book_id
+                && !entry.is_finished
+                && follows(&current, &parse_sequence(entry.sequence.as_deref().unwrap_or_default()))
+        });
+        next.extend(found);
+    }
+
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::{NewBook, NewSeries};
+    use crate::storage::{queries, Database};
+
+    #[test]
+    fn test_parse_sequence() {
+        let book = parse_sequence("Book 3");
+        assert_eq!((book.kind, book.start, book.end), (SequenceKind::Number, Some(3.0), Some(3.0)));
+        assert_eq!(parse_sequence("2.5").index(), 2.5);
+        assert_eq!(parse_sequence("invalid").index(), 0.0);
+
+        let omnibus = parse_sequence("Books 1-3");
+        assert_eq!((omnibus.start, omnibus.end), (Some(1.0), Some(3.0)));
+        assert_eq!(parse_sequence("1 – 3").end, Some(3.0));
+        assert_eq!(parse_sequence("Prequel").kind, SequenceKind::Prequel);
+        assert_eq!(parse_sequence(" ").kind, SequenceKind::Missing);
+
+        let mut sequences = vec!["", "Novella", "10", "2", "1-3", "1", "3a", "3", "0.5", "Companion", "Prequel", "1.5"];
+        sequences.sort_by_key(|raw| parse_sequence(raw).sort_key);
+        assert_eq!(
+            sequences,
+            vec!["Prequel", "0.5", "1", "1-3", "1.5", "2", "3", "3a", "10", "Companion", "Novella", ""]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_next_in_series() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+        let series_id = queries::upsert_series(pool, &NewSeries::new("S1".to_string())).await.unwrap();
+        let mut ids = Vec::new();
+        for (i, sequence) in ["Prequel", "1", "1-3", "2", "3", "Novella"].iter().enumerate() {
+            let book = NewBook::new(format!("B00000000{}", i), format!("Book {}", sequence), "us".to_string());
+            let book_id = queries::insert_book(pool, &book).await.unwrap();
+            let parsed = parse_sequence(sequence);
+            queries::add_book_to_series(pool, series_id, book_id, Some(sequence.to_string()), parsed.index())
+                .await
+                .unwrap();
+            ids.push(book_id);
+        }
+
+        let order: Vec<String> = series_entries(pool, series_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|e| e.sequence)
+            .collect();
+        assert_eq!(order, vec!["Prequel", "1", "1-3", "2", "3", "Novella"]);
+
+        let next = |book_id| async move { next_in_series(pool, book_id).await.unwrap() };
+        assert_eq!(next(ids[0]).await[0].sequence.as_deref(), Some("1"));
+        assert_eq!(next(ids[1]).await[0].sequence.as_deref(), Some("2"));
+        assert_eq!(next(ids[2]).await[0].sequence.as_deref(), Some("Novella"));
+
+        // Finished books are skipped
+        sqlx::query("UPDATE Books SET is_finished = 1 WHERE book_id = ?")
+            .bind(ids[3])
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(next(ids[1]).await[0].sequence.as_deref(), Some("3"));
+        assert!(next(ids[5]).await.is_empty());
+    }
+}
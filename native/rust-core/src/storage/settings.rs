@@ -0,0 +1,93 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! App settings stored in the library database
+//!
+//! Settings that must travel with the library (rather than living in the
+//! host app's preferences) are kept in the `Settings` key/value table.
+//! Keys are namespaced by feature, e.g. `content_filter.enabled`. Values are
+//! plain text; structured values are stored as JSON via `get_json_setting`
+//! and `set_json_setting`.
+//!
+//! Settings belong to a profile (see `storage::profiles`); these functions
+//! read and write the active profile's settings.
+
+use crate::error::{LibationError, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::SqlitePool;
+
+/// Value of a setting (None if never set)
+pub async fn get_setting(pool: &SqlitePool, key: &str) -> Result<Option<String>> {
+    let value = sqlx::query_scalar(
+        "SELECT value FROM Settings WHERE key = ? \
+         AND profile_id = COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default')",
+    )
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(value)
+}
+
+/// Insert or replace a setting
+pub async fn set_setting(pool: &SqlitePool, key: &str, value: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO Settings (profile_id, key, value, updated_at)
+        VALUES (COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default'), ?, ?, ?)
+        ON CONFLICT(profile_id, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(key)
+    .bind(value)
+    .bind(crate::storage::dates::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a setting, reverting it to its default
+pub async fn delete_setting(pool: &SqlitePool, key: &str) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM Settings WHERE key = ? \
+         AND profile_id = COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default')",
+    )
+        .bind(key)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// JSON-encoded setting (None if never set)
+///
+/// # Errors
+/// InvalidData if the stored value doesn't parse as `T`
+pub async fn get_json_setting<T: DeserializeOwned>(pool: &SqlitePool, key: &str) -> Result<Option<T>> {
+    get_setting(pool, key)
+        .await?
+        .map(|value| {
+            serde_json::from_str(&value)
+                .map_err(|e| LibationError::InvalidData(format!("Setting {} is malformed: {}", key, e)))
+        })
+        .transpose()
+}
+
+/// Store a setting as JSON
+pub async fn set_json_setting<T: Serialize + ?Sized>(pool: &SqlitePool, key: &str, value: &T) -> Result<()> {
+    set_setting(pool, key, &serde_json::to_string(value)?).await
+}
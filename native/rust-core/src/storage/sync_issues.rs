@@ -0,0 +1,294 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-item library sync failures
+//!
+//! A library sync keeps going when a single title fails to import. Each
+//! failure is reported as a `SyncError` in `SyncStats.errors` and kept in
+//! `SyncIssues` until the title imports cleanly, so the app can list what
+//! is missing and retry it (`AudibleClient::retry_sync_issue`). Repeated
+//! failures of the same title and stage update one row instead of adding
+//! new ones.
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::str::FromStr;
+
+/// Import step that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStage {
+    /// Author, narrator or publisher
+    Contributor,
+    Series,
+    Book,
+}
+
+impl SyncStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncStage::Contributor => "contributor",
+            SyncStage::Series => "series",
+            SyncStage::Book => "book",
+        }
+    }
+}
+
+impl FromStr for SyncStage {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "contributor" => Ok(SyncStage::Contributor),
+            "series" => Ok(SyncStage::Series),
+            "book" => Ok(SyncStage::Book),
+            _ => Err(LibationError::InvalidInput(format!("Unknown sync stage: {}", s))),
+        }
+    }
+}
+
+/// A non-fatal failure while importing one library item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncError {
+    /// Title being imported when the failure happened
+    pub asin: String,
+    pub stage: SyncStage,
+
+    /// Error category: "auth", "network", "database", "invalid_data" or "internal"
+    pub code: String,
+    pub message: String,
+}
+
+impl SyncError {
+    pub fn new(asin: &str, stage: SyncStage, error: &LibationError) -> Self {
+        Self {
+            asin: asin.to_string(),
+            stage,
+            code: error_code(error).to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Stored, unresolved sync failure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncIssue {
+    pub issue_id: i64,
+    pub account: String,
+    pub asin: String,
+    pub stage: SyncStage,
+    pub code: String,
+    pub message: String,
+
+    /// Number of syncs that hit this failure
+    pub occurrences: i64,
+    pub first_seen: String,
+    pub last_seen: String,
+}
+
+/// Coarse error category, stable across message wording changes
+fn error_code(error: &LibationError) -> &'static str {
+    if error.is_auth_error() {
+        "auth"
+    } else if error.is_retryable() || matches!(error, LibationError::NetworkError { .. }) {
+        "network"
+    } else {
+        match error {
+            LibationError::SqlxError(_)
+            | LibationError::DatabaseError(_)
+            | LibationError::QueryFailed(_)
+            | LibationError::RecordNotFound(_) => "database",
+            LibationError::InvalidApiResponse { .. }
+            | LibationError::InvalidInput(_)
+            | LibationError::SerdeJsonError(_) => "invalid_data",
+            _ => "internal",
+        }
+    }
+}
+
+/// Store sync failures for an account
+///
+/// A failure for a title and stage that is already open bumps its
+/// occurrence count and replaces the message.
+pub async fn record_sync_errors(pool: &SqlitePool, account: &str, errors: &[SyncError]) -> Result<()> {
+    let now = crate::storage::dates::now();
+    let mut tx = pool.begin().await?;
+
+    for error in errors {
+        let updated = sqlx::query(
+            r#"
+            UPDATE SyncIssues
+            SET code = ?, message = ?, occurrences = occurrences + 1, last_seen = ?
+            WHERE account = ? AND asin = ? AND stage = ? AND resolved_at IS NULL
+            "#,
+        )
+        .bind(&error.code)
+        .bind(&error.message)
+        .bind(&now)
+        .bind(account)
+        .bind(&error.asin)
+        .bind(error.stage.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            sqlx::query(
+                r#"
+                INSERT INTO SyncIssues (account, asin, stage, code, message, first_seen, last_seen)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(account)
+            .bind(&error.asin)
+            .bind(error.stage.as_str())
+            .bind(&error.code)
+            .bind(&error.message)
+            .bind(&now)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Mark open issues resolved for titles that imported without errors
+pub async fn resolve_sync_issues(pool: &SqlitePool, account: &str, asins: &[String]) -> Result<()> {
+    let now = crate::storage::dates::now();
+    let mut tx = pool.begin().await?;
+
+    for asin in asins {
+        sqlx::query(
+            "UPDATE SyncIssues SET resolved_at = ? WHERE account = ? AND asin = ? AND resolved_at IS NULL"
+        )
+        .bind(&now)
+        .bind(account)
+        .bind(asin)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Mark a single issue resolved (e.g. dismissed by the user)
+pub async fn resolve_sync_issue(pool: &SqlitePool, issue_id: i64) -> Result<()> {
+    sqlx::query("UPDATE SyncIssues SET resolved_at = ? WHERE issue_id = ? AND resolved_at IS NULL")
+        .bind(crate::storage::dates::now())
+        .bind(issue_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+fn row_to_issue(row: sqlx::sqlite::SqliteRow) -> Result<SyncIssue> {
+    let stage: String = row.try_get("stage")?;
+    Ok(SyncIssue {
+        issue_id: row.try_get("issue_id")?,
+        account: row.try_get("account")?,
+        asin: row.try_get("asin")?,
+        stage: stage.parse()?,
+        code: row.try_get("code")?,
+        message: row.try_get("message")?,
+        occurrences: row.try_get("occurrences")?,
+        first_seen: row.try_get("first_seen")?,
+        last_seen: row.try_get("last_seen")?,
+    })
+}
+
+/// Unresolved issues, most recent first
+///
+/// # Arguments
+/// * `account` - Limit to one account (None for all)
+pub async fn list_sync_issues(pool: &SqlitePool, account: Option<&str>) -> Result<Vec<SyncIssue>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT * FROM SyncIssues
+        WHERE resolved_at IS NULL AND (? IS NULL OR account = ?)
+        ORDER BY last_seen DESC, issue_id DESC
+        "#,
+    )
+    .bind(account)
+    .bind(account)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter().map(row_to_issue).collect()
+}
+
+/// An unresolved issue by id
+///
+/// # Errors
+/// RecordNotFound if there is no such issue or it is already resolved
+pub async fn get_sync_issue(pool: &SqlitePool, issue_id: i64) -> Result<SyncIssue> {
+    let row = sqlx::query("SELECT * FROM SyncIssues WHERE issue_id = ? AND resolved_at IS NULL")
+        .bind(issue_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| LibationError::not_found(format!("Sync issue not found: {}", issue_id)))?;
+
+    row_to_issue(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_sync_issues() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        let errors = vec![
+            SyncError::new("B0BROKEN", SyncStage::Book, &LibationError::DatabaseError("locked".to_string())),
+            SyncError::new("B0SERIES", SyncStage::Series, &LibationError::network_error("reset", true)),
+        ];
+        assert_eq!(errors[0].code, "database");
+        assert_eq!(errors[1].code, "network");
+
+        record_sync_errors(pool, "alice", &errors).await.unwrap();
+        record_sync_errors(pool, "alice", &errors[..1]).await.unwrap();
+        record_sync_errors(pool, "bob", &errors[..1]).await.unwrap();
+
+        let issues = list_sync_issues(pool, Some("alice")).await.unwrap();
+        assert_eq!(issues.len(), 2);
+        let broken = issues.iter().find(|i| i.asin == "B0BROKEN").unwrap();
+        assert_eq!(broken.occurrences, 2);
+        assert_eq!(broken.stage, SyncStage::Book);
+        assert_eq!(list_sync_issues(pool, None).await.unwrap().len(), 3);
+
+        // A clean import resolves only that account's issues for the title
+        resolve_sync_issues(pool, "alice", &["B0BROKEN".to_string()]).await.unwrap();
+        let issues = list_sync_issues(pool, Some("alice")).await.unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].asin, "B0SERIES");
+        assert_eq!(list_sync_issues(pool, Some("bob")).await.unwrap().len(), 1);
+
+        let issue_id = issues[0].issue_id;
+        assert_eq!(get_sync_issue(pool, issue_id).await.unwrap().stage, SyncStage::Series);
+        resolve_sync_issue(pool, issue_id).await.unwrap();
+        assert!(matches!(
+            get_sync_issue(pool, issue_id).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,549 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! User tag management
+//!
+//! Tags live in `Tags` and the `BookTags` junction table. The legacy
+//! space-delimited `UserDefinedItems.tags` column (Libation's format) is kept
+//! as a mirror and rewritten whenever a book's tags change, so existing
+//! readers of `UserDefinedItem::get_tags` keep working.
+//!
+//! Tag names follow Libation's rules: lowercase, alphanumeric and underscore.
+//!
+//! # Legacy Format
+//! Libation stores a book's tags as one string, sanitized on every write
+//! (`UserDefinedItem.Tags` in `DataLayer/EfClasses/UserDefinedItem.cs`).
+//! `parse_legacy_tags` reads that string, from this database's mirror
+//! column or a desktop import, the way Libation does:
+//!
+//! | Legacy input                       | Tags                      |
+//! |------------------------------------|---------------------------|
+//! | Spaces, tabs, line breaks, NBSP    | Separate tags             |
+//! | `Favorite`, `HÖRBUCH`              | `favorite`, `hörbuch`     |
+//! | `sci-fi`, `#1`, `don't`            | `scifi`, `1`, `dont`      |
+//! | `a,b` (no space)                   | `ab` (commas don't split) |
+//! | `!!!`, `📚`                        | Dropped                   |
+//! | `fav Fav FAV`                      | `fav` once                |
+//! | Decomposed accents (`e` + U+0301)  | Composed (`é`)            |
+//!
+//! Tags are returned sorted, and `format_legacy_tags` writes them back as
+//! Libation would: sorted, space-separated, no string for no tags.
+
+use crate::error::{LibationError, Result};
+use crate::storage::queries::{list_books_with_filters, BookQueryParams};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use unicode_normalization::UnicodeNormalization;
+
+/// Tag with the number of books carrying it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TagCount {
+    pub name: String,
+    pub book_count: i64,
+}
+
+/// Tags imported from another library
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LegacyTagImport {
+    /// Books that got tags
+    pub books_tagged: usize,
+    /// ASINs not in the database
+    pub unknown_asins: Vec<String>,
+}
+
+/// Normalize a tag name (None if nothing usable remains)
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let normalized: String = tag
+        .trim()
+        .nfc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+
+    (!normalized.is_empty()).then_some(normalized)
+}
+
+/// Normalize a list of tags, dropping duplicates and unusable names
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = tags.iter().filter_map(|t| normalize_tag(t)).collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+/// Tags of a legacy tag string (see the module docs for the mapping)
+pub fn parse_legacy_tags(legacy: &str) -> Vec<String> {
+    let tags: Vec<String> = legacy.split_whitespace().map(String::from).collect();
+    normalize_tags(&tags)
+}
+
+/// Legacy tag string for tags (None for no tags)
+pub fn format_legacy_tags(tags: &[String]) -> Option<String> {
+    let tags = normalize_tags(tags);
+    (!tags.is_empty()).then(|| tags.join(" "))
+}
+
+/// Normalize a tag the caller must supply
+fn require_tag(tag: &str) -> Result<String> {
+    normalize_tag(tag)
+        .ok_or_else(|| LibationError::InvalidInput(format!("Invalid tag name: '{}'", tag)))
+}
+
+/// Get or create a tag, returning its id
+async fn upsert_tag(conn: &mut SqliteConnection, name: &str) -> Result<i64> {
+    sqlx::query("INSERT OR IGNORE INTO Tags (name) VALUES (?)")
+        .bind(name)
+        .execute(&mut *conn)
+        .await?;
+
+    let tag_id: i64 = sqlx::query_scalar("SELECT tag_id FROM Tags WHERE name = ?")
+        .bind(name)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(tag_id)
+}
+
+/// Rewrite the legacy tags column of a book from BookTags
+pub(crate) async fn sync_tag_blob(conn: &mut SqliteConnection, book_id: i64) -> Result<()> {
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT t.name FROM BookTags bt JOIN Tags t ON bt.tag_id = t.tag_id \
+         WHERE bt.book_id = ? ORDER BY t.name",
+    )
+    .bind(book_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let blob = format_legacy_tags(&names);
+
+    sqlx::query("INSERT OR IGNORE INTO UserDefinedItems (book_id) VALUES (?)")
+        .bind(book_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE UserDefinedItems SET tags = ? WHERE book_id = ?")
+        .bind(blob)
+        .bind(book_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Delete tags no book carries anymore
+pub(crate) async fn prune_unused_tags(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query("DELETE FROM Tags WHERE tag_id NOT IN (SELECT DISTINCT tag_id FROM BookTags)")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Replace a book's tags without touching the legacy column
+pub(crate) async fn replace_book_tags(
+    conn: &mut SqliteConnection,
+    book_id: i64,
+    tags: &[String],
+) -> Result<()> {
+    sqlx::query("DELETE FROM BookTags WHERE book_id = ?")
+        .bind(book_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for name in normalize_tags(tags) {
+        let tag_id = upsert_tag(conn, &name).await?;
+        sqlx::query("INSERT OR IGNORE INTO BookTags (book_id, tag_id) VALUES (?, ?)")
+            .bind(book_id)
+            .bind(tag_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Book ids matching a filter (ignores limit/offset)
+async fn matching_book_ids(pool: &SqlitePool, filter: &BookQueryParams) -> Result<Vec<i64>> {
+    let params = BookQueryParams {
+        limit: -1,
+        offset: 0,
+        ..filter.clone()
+    };
+    let books = list_books_with_filters(pool, &params).await?;
+    Ok(books.into_iter().map(|book| book.book_id).collect())
+}
+
+/// All tags with book counts, ordered by name
+pub async fn list_tags(pool: &SqlitePool) -> Result<Vec<TagCount>> {
+    let tags = sqlx::query_as::<_, TagCount>(
+        "SELECT t.name, COUNT(bt.book_id) as book_count FROM Tags t \
+         LEFT JOIN BookTags bt ON t.tag_id = bt.tag_id \
+         GROUP BY t.tag_id ORDER BY t.name",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags)
+}
+
+/// Tags of a single book, ordered by name
+pub async fn get_book_tags(pool: &SqlitePool, book_id: i64) -> Result<Vec<String>> {
+    let tags: Vec<String> = sqlx::query_scalar(
+        "SELECT t.name FROM BookTags bt JOIN Tags t ON bt.tag_id = t.tag_id \
+         WHERE bt.book_id = ? ORDER BY t.name",
+    )
+    .bind(book_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags)
+}
+
+/// Replace all tags of a single book
+pub async fn set_book_tags(pool: &SqlitePool, book_id: i64, tags: &[String]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    replace_book_tags(&mut tx, book_id, tags).await?;
+    sync_tag_blob(&mut tx, book_id).await?;
+    prune_unused_tags(&mut tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Add tags to every book matching a filter
+///
+/// # Returns
+/// Number of books matched
+pub async fn add_tags_to_books(
+    pool: &SqlitePool,
+    filter: &BookQueryParams,
+    tags: &[String],
+) -> Result<u64> {
+    let names = normalize_tags(tags);
+    let book_ids = matching_book_ids(pool, filter).await?;
+    if names.is_empty() || book_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut tag_ids = Vec::with_capacity(names.len());
+    for name in &names {
+        tag_ids.push(upsert_tag(&mut tx, name).await?);
+    }
+
+    for &book_id in &book_ids {
+        for &tag_id in &tag_ids {
+            sqlx::query("INSERT OR IGNORE INTO BookTags (book_id, tag_id) VALUES (?, ?)")
+                .bind(book_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sync_tag_blob(&mut tx, book_id).await?;
+    }
+    tx.commit().await?;
+
+    Ok(book_ids.len() as u64)
+}
+
+/// Remove tags from every book matching a filter
+///
+/// # Returns
+/// Number of books matched
+pub async fn remove_tags_from_books(
+    pool: &SqlitePool,
+    filter: &BookQueryParams,
+    tags: &[String],
+) -> Result<u64> {
+    let names = normalize_tags(tags);
+    let book_ids = matching_book_ids(pool, filter).await?;
+    if names.is_empty() || book_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut tx = pool.begin().await?;
+    for &book_id in &book_ids {
+        for name in &names {
+            sqlx::query(
+                "DELETE FROM BookTags WHERE book_id = ? \
+                 AND tag_id = (SELECT tag_id FROM Tags WHERE name = ?)",
+            )
+            .bind(book_id)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sync_tag_blob(&mut tx, book_id).await?;
+    }
+    prune_unused_tags(&mut tx).await?;
+    tx.commit().await?;
+
+    Ok(book_ids.len() as u64)
+}
+
+/// Rename a tag; renaming onto an existing tag merges the two
+pub async fn rename_tag(pool: &SqlitePool, from: &str, to: &str) -> Result<()> {
+    merge_tags(pool, &[from.to_string()], to).await
+}
+
+/// Merge source tags into a target tag (created if missing)
+///
+/// Books carrying any source tag carry the target afterwards, and the
+/// source tags are deleted.
+pub async fn merge_tags(pool: &SqlitePool, sources: &[String], target: &str) -> Result<()> {
+    let target = require_tag(target)?;
+    let sources: Vec<String> = sources
+        .iter()
+        .map(|s| require_tag(s))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|s| *s != target)
+        .collect();
+
+    let mut tx = pool.begin().await?;
+    let target_id = upsert_tag(&mut tx, &target).await?;
+
+    for source in &sources {
+        let source_id: Option<i64> = sqlx::query_scalar("SELECT tag_id FROM Tags WHERE name = ?")
+            .bind(source)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(source_id) = source_id else { continue };
+
+        let book_ids: Vec<i64> = sqlx::query_scalar("SELECT book_id FROM BookTags WHERE tag_id = ?")
+            .bind(source_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO BookTags (book_id, tag_id) \
+             SELECT book_id, ? FROM BookTags WHERE tag_id = ?",
+        )
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM Tags WHERE tag_id = ?")
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for book_id in book_ids {
+            sync_tag_blob(&mut tx, book_id).await?;
+        }
+    }
+
+    prune_unused_tags(&mut tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Add tags from another library's legacy tag strings, keyed by ASIN
+///
+/// Each book keeps its tags and gains the parsed ones, so importing the
+/// same data twice changes nothing. Runs in one transaction.
+pub async fn import_legacy_tags(pool: &SqlitePool, entries: &[(String, String)]) -> Result<LegacyTagImport> {
+    let mut result = LegacyTagImport::default();
+    let mut tx = pool.begin().await?;
+
+    for (asin, legacy) in entries {
+        let tags = parse_legacy_tags(legacy);
+        let book_id: Option<i64> = sqlx::query_scalar("SELECT book_id FROM Books WHERE audible_product_id = ?")
+            .bind(asin)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(book_id) = book_id else {
+            result.unknown_asins.push(asin.clone());
+            continue;
+        };
+        if tags.is_empty() {
+            continue;
+        }
+
+        for name in &tags {
+            let tag_id = upsert_tag(&mut tx, name).await?;
+            sqlx::query("INSERT OR IGNORE INTO BookTags (book_id, tag_id) VALUES (?, ?)")
+                .bind(book_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sync_tag_blob(&mut tx, book_id).await?;
+        result.books_tagged += 1;
+    }
+
+    tx.commit().await?;
+    Ok(result)
+}
+
+/// Delete a tag from all books
+pub async fn delete_tag(pool: &SqlitePool, tag: &str) -> Result<()> {
+    let name = require_tag(tag)?;
+    let mut tx = pool.begin().await?;
+
+    let book_ids: Vec<i64> = sqlx::query_scalar(
+        "SELECT bt.book_id FROM BookTags bt JOIN Tags t ON bt.tag_id = t.tag_id WHERE t.name = ?",
+    )
+    .bind(&name)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // BookTags rows go with the tag (ON DELETE CASCADE)
+    sqlx::query("DELETE FROM Tags WHERE name = ?")
+        .bind(&name)
+        .execute(&mut *tx)
+        .await?;
+
+    for book_id in book_ids {
+        sync_tag_blob(&mut tx, book_id).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::queries::{find_user_defined_item, insert_book, insert_library_book};
+    use crate::storage::{Database, NewBook, NewLibraryBook};
+
+    async fn insert_books(db: &Database, titles: &[&str]) -> Vec<i64> {
+        let mut ids = Vec::new();
+        for (i, title) in titles.iter().enumerate() {
+            let book = NewBook::new(format!("B00000TAG{}", i), title.to_string(), "us".to_string());
+            let book_id = insert_book(db.pool(), &book).await.unwrap();
+            insert_library_book(
+                db.pool(),
+                &NewLibraryBook {
+                    book_id,
+                    account: "test@example.com".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+            ids.push(book_id);
+        }
+        ids
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag(" Sci-Fi "), Some("scifi".to_string()));
+        assert_eq!(normalize_tag("to_read"), Some("to_read".to_string()));
+        assert_eq!(normalize_tag("!!"), None);
+    }
+
+    #[test]
+    fn test_parse_legacy_tags() {
+        assert_eq!(parse_legacy_tags("Favorite  To_Read"), strings(&["favorite", "to_read"]));
+        assert_eq!(parse_legacy_tags("a\tb\r\nc\u{a0}d"), strings(&["a", "b", "c", "d"]));
+        assert_eq!(parse_legacy_tags("sci-fi #1 don't"), strings(&["1", "dont", "scifi"]));
+        assert_eq!(parse_legacy_tags("a,b"), strings(&["ab"]));
+        assert_eq!(parse_legacy_tags("HÖRBUCH Cafe\u{301}"), strings(&["café", "hörbuch"]));
+        assert_eq!(parse_legacy_tags("fav Fav FAV !!! 📚"), strings(&["fav"]));
+        assert!(parse_legacy_tags("  \n ").is_empty());
+
+        assert_eq!(format_legacy_tags(&strings(&["to_read", "Favorite"])).as_deref(), Some("favorite to_read"));
+        assert_eq!(format_legacy_tags(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_import_legacy_tags() {
+        let db = Database::new_in_memory().await.unwrap();
+        let ids = insert_books(&db, &["Dune", "Emma"]).await;
+        set_book_tags(db.pool(), ids[0], &strings(&["owned"])).await.unwrap();
+
+        let entries = vec![
+            ("B00000TAG0".to_string(), "Sci-Fi  favorite".to_string()),
+            ("B00000TAG1".to_string(), "".to_string()),
+            ("B0UNKNOWN0".to_string(), "lost".to_string()),
+        ];
+        let result = import_legacy_tags(db.pool(), &entries).await.unwrap();
+        assert_eq!(result.books_tagged, 1);
+        assert_eq!(result.unknown_asins, strings(&["B0UNKNOWN0"]));
+        assert_eq!(get_book_tags(db.pool(), ids[0]).await.unwrap(), strings(&["favorite", "owned", "scifi"]));
+
+        // The mirror column reads back the same, and a second import is a no-op
+        let item = find_user_defined_item(db.pool(), ids[0]).await.unwrap().unwrap();
+        assert_eq!(item.tags.as_deref(), Some("favorite owned scifi"));
+        assert_eq!(item.get_tags(), strings(&["favorite", "owned", "scifi"]));
+        import_legacy_tags(db.pool(), &entries).await.unwrap();
+        assert_eq!(get_book_tags(db.pool(), ids[0]).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_tagging_over_filter() {
+        let db = Database::new_in_memory().await.unwrap();
+        let ids = insert_books(&db, &["Dune", "Dune Messiah", "Emma"]).await;
+
+        let dune = BookQueryParams {
+            search_query: Some("dune".to_string()),
+            ..Default::default()
+        };
+        let tagged = add_tags_to_books(db.pool(), &dune, &strings(&["Sci-Fi", "favorite"])).await.unwrap();
+        assert_eq!(tagged, 2);
+        set_book_tags(db.pool(), ids[2], &strings(&["favorite", "classic"])).await.unwrap();
+
+        assert_eq!(
+            list_tags(db.pool()).await.unwrap(),
+            vec![
+                TagCount { name: "classic".to_string(), book_count: 1 },
+                TagCount { name: "favorite".to_string(), book_count: 3 },
+                TagCount { name: "scifi".to_string(), book_count: 2 },
+            ]
+        );
+
+        // Legacy column mirrors the normalized tags
+        let item = find_user_defined_item(db.pool(), ids[0]).await.unwrap().unwrap();
+        assert_eq!(item.tags.as_deref(), Some("favorite scifi"));
+
+        let favorites = BookQueryParams {
+            tag: Some("favorite".to_string()),
+            ..Default::default()
+        };
+        remove_tags_from_books(db.pool(), &favorites, &strings(&["favorite"])).await.unwrap();
+        assert_eq!(get_book_tags(db.pool(), ids[0]).await.unwrap(), vec!["scifi"]);
+        assert_eq!(get_book_tags(db.pool(), ids[2]).await.unwrap(), vec!["classic"]);
+        assert!(list_tags(db.pool()).await.unwrap().iter().all(|t| t.name != "favorite"));
+    }
+
+    #[tokio::test]
+    async fn test_rename_merges_into_existing_tag() {
+        let db = Database::new_in_memory().await.unwrap();
+        let ids = insert_books(&db, &["Dune", "Emma"]).await;
+        set_book_tags(db.pool(), ids[0], &strings(&["scifi"])).await.unwrap();
+        set_book_tags(db.pool(), ids[1], &strings(&["science_fiction", "classic"])).await.unwrap();
+
+        rename_tag(db.pool(), "science_fiction", "SciFi").await.unwrap();
+        assert_eq!(
+            list_tags(db.pool()).await.unwrap(),
+            vec![
+                TagCount { name: "classic".to_string(), book_count: 1 },
+                TagCount { name: "scifi".to_string(), book_count: 2 },
+            ]
+        );
+        let item = find_user_defined_item(db.pool(), ids[1]).await.unwrap().unwrap();
+        assert_eq!(item.tags.as_deref(), Some("classic scifi"));
+
+        delete_tag(db.pool(), "scifi").await.unwrap();
+        assert!(get_book_tags(db.pool(), ids[0]).await.unwrap().is_empty());
+        let item = find_user_defined_item(db.pool(), ids[0]).await.unwrap().unwrap();
+        assert_eq!(item.tags, None);
+
+        assert!(rename_tag(db.pool(), "classic", "--").await.is_err());
+    }
+}
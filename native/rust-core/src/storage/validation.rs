@@ -0,0 +1,440 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Metadata validation report
+//!
+//! A validation pass checks every book for data the app can't display or
+//! tag correctly: missing authors, zero runtime, no cover, series links to
+//! missing or unnamed series, and stored chapters that don't add up to the
+//! book's length. Problems are kept in `ValidationIssues` with a severity,
+//! so the app can list them and the user can fix them one by one. Running
+//! the pass again (for the library or a single book) replaces that scope's
+//! issues, so fixed problems disappear.
+
+use crate::error::{LibationError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::str::FromStr;
+
+/// Allowed difference between the chapter total and the runtime, which
+/// Audible rounds to whole minutes
+const CHAPTER_TOLERANCE_MS: i64 = 90_000;
+
+/// How serious an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Cosmetic or informational
+    Info,
+    /// Displays or tags poorly
+    Warning,
+    /// Wrong or unusable data
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(LibationError::InvalidInput(format!("Unknown severity: {}", s))),
+        }
+    }
+}
+
+/// Consistency check that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationCheck {
+    MissingTitle,
+    MissingAuthors,
+    MissingNarrators,
+    ZeroRuntime,
+    MissingCover,
+    BrokenSeriesLink,
+    ChapterDurationMismatch,
+}
+
+impl ValidationCheck {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationCheck::MissingTitle => "missing_title",
+            ValidationCheck::MissingAuthors => "missing_authors",
+            ValidationCheck::MissingNarrators => "missing_narrators",
+            ValidationCheck::ZeroRuntime => "zero_runtime",
+            ValidationCheck::MissingCover => "missing_cover",
+            ValidationCheck::BrokenSeriesLink => "broken_series_link",
+            ValidationCheck::ChapterDurationMismatch => "chapter_duration_mismatch",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ValidationCheck::MissingTitle
+            | ValidationCheck::MissingAuthors
+            | ValidationCheck::ZeroRuntime
+            | ValidationCheck::BrokenSeriesLink => Severity::Error,
+            ValidationCheck::MissingCover | ValidationCheck::ChapterDurationMismatch => Severity::Warning,
+            ValidationCheck::MissingNarrators => Severity::Info,
+        }
+    }
+}
+
+impl FromStr for ValidationCheck {
+    type Err = LibationError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "missing_title" => Ok(ValidationCheck::MissingTitle),
+            "missing_authors" => Ok(ValidationCheck::MissingAuthors),
+            "missing_narrators" => Ok(ValidationCheck::MissingNarrators),
+            "zero_runtime" => Ok(ValidationCheck::ZeroRuntime),
+            "missing_cover" => Ok(ValidationCheck::MissingCover),
+            "broken_series_link" => Ok(ValidationCheck::BrokenSeriesLink),
+            "chapter_duration_mismatch" => Ok(ValidationCheck::ChapterDurationMismatch),
+            _ => Err(LibationError::InvalidInput(format!("Unknown validation check: {}", s))),
+        }
+    }
+}
+
+/// Stored problem with one book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub asin: String,
+    pub title: String,
+    pub check: ValidationCheck,
+    pub severity: Severity,
+    pub message: String,
+    pub detected_at: String,
+}
+
+/// Result of a validation pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationSummary {
+    pub books_checked: usize,
+    pub books_with_issues: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+}
+
+/// What the checks need to know about a book
+#[derive(Debug, Clone, Default)]
+struct BookFacts {
+    book_id: i64,
+    title: String,
+    length_in_minutes: i64,
+    is_parent: bool,
+    has_cover: bool,
+    authors: i64,
+    narrators: i64,
+    broken_series: i64,
+    chapters: i64,
+    chapters_ms: i64,
+}
+
+/// Failed checks for a book, with a message for each
+fn check_book(book: &BookFacts) -> Vec<(ValidationCheck, String)> {
+    let mut issues = Vec::new();
+
+    if book.title.trim().is_empty() {
+        issues.push((ValidationCheck::MissingTitle, "Book has no title".to_string()));
+    }
+    // Podcast parents are containers without audio or credits of their own
+    if !book.is_parent {
+        if book.authors == 0 {
+            issues.push((ValidationCheck::MissingAuthors, "No author is linked".to_string()));
+        }
+        if book.narrators == 0 {
+            issues.push((ValidationCheck::MissingNarrators, "No narrator is linked".to_string()));
+        }
+        if book.length_in_minutes <= 0 {
+            issues.push((ValidationCheck::ZeroRuntime, "Runtime is zero".to_string()));
+        }
+    }
+    if !book.has_cover {
+        issues.push((ValidationCheck::MissingCover, "No cover image".to_string()));
+    }
+    if book.broken_series > 0 {
+        issues.push((
+            ValidationCheck::BrokenSeriesLink,
+            format!("{} series link(s) point to a missing or unnamed series", book.broken_series),
+        ));
+    }
+
+    let runtime_ms = book.length_in_minutes * 60_000;
+    if book.chapters > 0 && runtime_ms > 0 && (book.chapters_ms - runtime_ms).abs() > CHAPTER_TOLERANCE_MS {
+        issues.push((
+            ValidationCheck::ChapterDurationMismatch,
+            format!(
+                "{} chapters add up to {} min, runtime is {} min",
+                book.chapters,
+                book.chapters_ms / 60_000,
+                book.length_in_minutes
+            ),
+        ));
+    }
+
+    issues
+}
+
+/// Facts for all books, or the book with `asin`
+async fn load_book_facts(pool: &SqlitePool, asin: Option<&str>) -> Result<Vec<BookFacts>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            b.book_id,
+            b.title,
+            b.length_in_minutes,
+            b.content_type,
+            COALESCE(NULLIF(TRIM(b.picture_id), ''), NULLIF(TRIM(b.picture_large), '')) IS NOT NULL AS has_cover,
+            (SELECT COUNT(*) FROM BookContributors bc WHERE bc.book_id = b.book_id AND bc.role = 1) AS authors,
+            (SELECT COUNT(*) FROM BookContributors bc WHERE bc.book_id = b.book_id AND bc.role = 2) AS narrators,
+            (SELECT COUNT(*) FROM SeriesBooks sb LEFT JOIN Series s ON s.series_id = sb.series_id
+             WHERE sb.book_id = b.book_id AND (s.series_id IS NULL OR TRIM(COALESCE(s.name, '')) = '')) AS broken_series,
+            (SELECT COUNT(*) FROM BookChapters c WHERE c.book_id = b.book_id) AS chapters,
+            (SELECT COALESCE(SUM(c.end_ms - c.start_ms), 0) FROM BookChapters c WHERE c.book_id = b.book_id) AS chapters_ms
+        FROM Books b
+        WHERE ? IS NULL OR b.audible_product_id = ?
+        "#,
+    )
+    .bind(asin)
+    .bind(asin)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let content_type: i64 = row.try_get("content_type")?;
+            Ok(BookFacts {
+                book_id: row.try_get("book_id")?,
+                title: row.try_get("title")?,
+                length_in_minutes: row.try_get("length_in_minutes")?,
+                is_parent: content_type == 4,
+                has_cover: row.try_get("has_cover")?,
+                authors: row.try_get("authors")?,
+                narrators: row.try_get("narrators")?,
+                broken_series: row.try_get("broken_series")?,
+                chapters: row.try_get("chapters")?,
+                chapters_ms: row.try_get("chapters_ms")?,
+            })
+        })
+        .collect()
+}
+
+/// Check books and replace their stored issues
+///
+/// # Arguments
+/// * `asin` - Check only this book (None for the whole library)
+///
+/// # Errors
+/// RecordNotFound if `asin` is given and isn't in the database
+pub async fn validate_library(pool: &SqlitePool, asin: Option<&str>) -> Result<ValidationSummary> {
+    let books = load_book_facts(pool, asin).await?;
+    if let (Some(asin), true) = (asin, books.is_empty()) {
+        return Err(LibationError::not_found(format!("Book not found: {}", asin)));
+    }
+
+    let now = crate::storage::dates::now();
+    let mut summary = ValidationSummary {
+        books_checked: books.len(),
+        ..Default::default()
+    };
+    let mut tx = pool.begin().await?;
+
+    match asin {
+        Some(_) => {
+            sqlx::query("DELETE FROM ValidationIssues WHERE book_id = ?")
+                .bind(books[0].book_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM ValidationIssues").execute(&mut *tx).await?;
+        }
+    }
+
+    for book in &books {
+        let issues = check_book(book);
+        if !issues.is_empty() {
+            summary.books_with_issues += 1;
+        }
+
+        for (check, message) in issues {
+            match check.severity() {
+                Severity::Error => summary.errors += 1,
+                Severity::Warning => summary.warnings += 1,
+                Severity::Info => summary.infos += 1,
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO ValidationIssues (book_id, check_code, severity, message, detected_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(book.book_id)
+            .bind(check.as_str())
+            .bind(check.severity().as_str())
+            .bind(&message)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+/// Stored issues from the last validation pass, most severe first
+///
+/// # Arguments
+/// * `min_severity` - Leave out less severe issues (None for all)
+pub async fn list_validation_issues(pool: &SqlitePool, min_severity: Option<Severity>) -> Result<Vec<ValidationIssue>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT b.audible_product_id, b.title, v.check_code, v.severity, v.message, v.detected_at
+        FROM ValidationIssues v
+        JOIN Books b ON b.book_id = v.book_id
+        ORDER BY CASE v.severity WHEN 'error' THEN 0 WHEN 'warning' THEN 1 ELSE 2 END, b.title, v.check_code
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut issues = Vec::with_capacity(rows.len());
+    for row in rows {
+        let severity: Severity = row.try_get::<String, _>("severity")?.parse()?;
+        if min_severity.is_some_and(|min| severity < min) {
+            continue;
+        }
+        issues.push(ValidationIssue {
+            asin: row.try_get("audible_product_id")?,
+            title: row.try_get("title")?,
+            check: row.try_get::<String, _>("check_code")?.parse()?,
+            severity,
+            message: row.try_get("message")?,
+            detected_at: row.try_get("detected_at")?,
+        });
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::metadata::Chapter;
+    use crate::storage::queries::{add_book_contributor, insert_book, upsert_contributor};
+    use crate::storage::{Database, NewBook, NewContributor, Role};
+
+    #[test]
+    fn test_check_book() {
+        let complete = BookFacts {
+            title: "Complete".to_string(),
+            length_in_minutes: 600,
+            has_cover: true,
+            authors: 1,
+            narrators: 1,
+            chapters: 10,
+            chapters_ms: 600 * 60_000 + 25_000,
+            ..Default::default()
+        };
+        assert!(check_book(&complete).is_empty());
+
+        let chapters_short = BookFacts { chapters_ms: 500 * 60_000, ..complete.clone() };
+        let issues = check_book(&chapters_short);
+        assert_eq!(issues[0].0, ValidationCheck::ChapterDurationMismatch);
+        assert_eq!(issues[0].1, "10 chapters add up to 500 min, runtime is 600 min");
+
+        let parent = BookFacts { title: "Podcast".to_string(), is_parent: true, has_cover: true, ..Default::default() };
+        assert!(check_book(&parent).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_validation_report() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        let mut good = NewBook::new("B0GOOD".to_string(), "Good".to_string(), "us".to_string());
+        good.length_in_minutes = 60;
+        good.picture_id = Some("51abc".to_string());
+        let good_id = insert_book(pool, &good).await.unwrap();
+        for (name, role) in [("Author", Role::Author), ("Narrator", Role::Narrator)] {
+            let contributor_id = upsert_contributor(pool, &NewContributor::new(name.to_string())).await.unwrap();
+            add_book_contributor(pool, good_id, contributor_id, role as i32, 0).await.unwrap();
+        }
+        let bad_id = insert_book(pool, &NewBook::new("B0BAD".to_string(), "Bad".to_string(), "us".to_string()))
+            .await
+            .unwrap();
+        let series_id: i64 = sqlx::query_scalar("INSERT INTO Series (audible_series_id, name) VALUES ('S1', NULL) RETURNING series_id")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO SeriesBooks (series_id, book_id, \"order\") VALUES (?, ?, '1')")
+            .bind(series_id)
+            .bind(bad_id)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let summary = validate_library(pool, None).await.unwrap();
+        assert_eq!((summary.books_checked, summary.books_with_issues), (2, 1));
+        assert_eq!((summary.errors, summary.warnings, summary.infos), (3, 1, 1));
+
+        let issues = list_validation_issues(pool, None).await.unwrap();
+        let checks: Vec<_> = issues.iter().map(|i| (i.severity, i.check)).collect();
+        assert_eq!(
+            checks,
+            vec![
+                (Severity::Error, ValidationCheck::BrokenSeriesLink),
+                (Severity::Error, ValidationCheck::MissingAuthors),
+                (Severity::Error, ValidationCheck::ZeroRuntime),
+                (Severity::Warning, ValidationCheck::MissingCover),
+                (Severity::Info, ValidationCheck::MissingNarrators),
+            ]
+        );
+        assert!(issues.iter().all(|i| i.asin == "B0BAD"));
+        assert_eq!(list_validation_issues(pool, Some(Severity::Warning)).await.unwrap().len(), 4);
+
+        // Chapters edited to a wrong total are flagged; re-checking one book keeps the others' issues
+        let chapters = vec![Chapter { title: "One".to_string(), start_ms: 0, end_ms: 30 * 60_000 }];
+        crate::storage::chapters::update_chapters(pool, "B0GOOD", &chapters).await.unwrap();
+        let summary = validate_library(pool, Some("B0GOOD")).await.unwrap();
+        assert_eq!((summary.books_checked, summary.warnings), (1, 1));
+        let issues = list_validation_issues(pool, None).await.unwrap();
+        assert_eq!(issues.len(), 6);
+        assert!(issues.iter().any(|i| i.asin == "B0GOOD" && i.check == ValidationCheck::ChapterDurationMismatch));
+
+        assert!(matches!(
+            validate_library(pool, Some("B0MISSING")).await,
+            Err(LibationError::RecordNotFound(_))
+        ));
+    }
+}
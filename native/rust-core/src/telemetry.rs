@@ -0,0 +1,271 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is a Rust port of Libation (https://github.com/rmcrackan/Libation)
+// Original work Copyright (C) Libation contributors
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+
+//! Opt-in usage counters for bug reports
+//!
+//! When the user turns them on, finished jobs and downloads bump local
+//! counters: how many syncs, downloads, decryptions, liberations and so on
+//! completed or failed, and which category each failure fell into. Nothing
+//! else is kept — no ASINs, titles, account ids, paths or timestamps per
+//! event — and nothing is ever sent anywhere. The user exports the numbers
+//! (`export_counters`) and attaches them to a bug report if they want.
+//!
+//! Counters are off by default, live in the `Settings` table under
+//! `telemetry.` (so they belong to the active profile) and are deleted
+//! when the user turns them off. The module is only built with the
+//! `telemetry` feature.
+
+use crate::cancel::JobKind;
+use crate::error::{LibationError, Result};
+use crate::storage::settings::{get_setting, set_setting};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+const KEY_ENABLED: &str = "telemetry.enabled";
+const KEY_SINCE: &str = "telemetry.since";
+const COUNTER_PREFIX: &str = "telemetry.count.";
+
+/// Coarse category of a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Network,
+    Auth,
+    Api,
+    File,
+    Crypto,
+    Other,
+}
+
+impl ErrorCategory {
+    pub fn of(error: &LibationError) -> Self {
+        if error.is_auth_error() {
+            ErrorCategory::Auth
+        } else if error.is_crypto_error() {
+            ErrorCategory::Crypto
+        } else if error.is_file_error() {
+            ErrorCategory::File
+        } else if matches!(
+            error,
+            LibationError::NetworkError { .. }
+                | LibationError::Timeout(_)
+                | LibationError::DownloadInterrupted
+        ) {
+            ErrorCategory::Network
+        } else if matches!(
+            error,
+            LibationError::ApiRequestFailed { .. }
+                | LibationError::InvalidApiResponse { .. }
+                | LibationError::RateLimitExceeded { .. }
+        ) {
+            ErrorCategory::Api
+        } else {
+            ErrorCategory::Other
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Network => "network",
+            ErrorCategory::Auth => "auth",
+            ErrorCategory::Api => "api",
+            ErrorCategory::File => "file",
+            ErrorCategory::Crypto => "crypto",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+/// Something worth counting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// A job or download finished
+    Completed(JobKind),
+    /// A job or download failed
+    Failed(JobKind),
+    /// A failure, by category
+    Error(ErrorCategory),
+    /// A book was liberated (a receipt was recorded)
+    Liberation,
+}
+
+impl Counter {
+    /// Name in the export, e.g. `download.completed` or `error.network`
+    pub fn name(&self) -> String {
+        match self {
+            Counter::Completed(kind) => format!("{}.completed", kind.as_str()),
+            Counter::Failed(kind) => format!("{}.failed", kind.as_str()),
+            Counter::Error(category) => format!("error.{}", category.as_str()),
+            Counter::Liberation => "liberation".to_string(),
+        }
+    }
+}
+
+/// Counters as the user exports them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryExport {
+    pub enabled: bool,
+    /// When counting was turned on (None while off)
+    pub since: Option<String>,
+    /// Counter name → count; counters never bumped are left out
+    pub counters: BTreeMap<String, u64>,
+}
+
+/// Whether the user opted in
+pub async fn is_enabled(pool: &SqlitePool) -> Result<bool> {
+    Ok(get_setting(pool, KEY_ENABLED).await?.as_deref() == Some("true"))
+}
+
+/// Opt in or out
+///
+/// Opting out deletes every counter; opting in again starts from zero.
+pub async fn set_enabled(pool: &SqlitePool, enabled: bool) -> Result<()> {
+    if enabled {
+        if !is_enabled(pool).await? {
+            set_setting(pool, KEY_SINCE, &crate::storage::dates::now()).await?;
+        }
+        return set_setting(pool, KEY_ENABLED, "true").await;
+    }
+
+    sqlx::query(
+        "DELETE FROM Settings WHERE key LIKE 'telemetry.%' \
+         AND profile_id = COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default')",
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Bump `counter` if the user opted in
+pub async fn record(pool: &SqlitePool, counter: Counter) -> Result<()> {
+    if !is_enabled(pool).await? {
+        return Ok(());
+    }
+
+    // One statement, so concurrent jobs can't lose counts
+    sqlx::query(
+        r#"
+        INSERT INTO Settings (profile_id, key, value, updated_at)
+        VALUES (COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default'), ?, '1', ?)
+        ON CONFLICT(profile_id, key) DO UPDATE
+            SET value = CAST(value AS INTEGER) + 1, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(format!("{}{}", COUNTER_PREFIX, counter.name()))
+    .bind(crate::storage::dates::now())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Count work of `kind` that ended with `outcome`
+///
+/// Cancelled work isn't counted. Counting never fails the work: errors
+/// are logged and dropped.
+pub async fn record_outcome<T>(pool: &SqlitePool, kind: JobKind, outcome: &Result<T>) {
+    let counters = match outcome {
+        Ok(_) => vec![Counter::Completed(kind)],
+        Err(LibationError::Cancelled) => return,
+        Err(e) => vec![Counter::Failed(kind), Counter::Error(ErrorCategory::of(e))],
+    };
+    for counter in counters {
+        if let Err(e) = record(pool, counter).await {
+            crate::trace::trace_eprintln!("Failed to record telemetry counter: {}", e);
+        }
+    }
+}
+
+/// Everything counted so far, for the user to share
+pub async fn export_counters(pool: &SqlitePool) -> Result<TelemetryExport> {
+    let enabled = is_enabled(pool).await?;
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT key, value FROM Settings WHERE key LIKE 'telemetry.count.%' \
+         AND profile_id = COALESCE((SELECT profile_id FROM Profiles WHERE is_active = 1), 'default')",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let counters = rows
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let name = key.strip_prefix(COUNTER_PREFIX)?.to_string();
+            Some((name, value.parse().ok()?))
+        })
+        .collect();
+
+    Ok(TelemetryExport {
+        enabled,
+        since: if enabled { get_setting(pool, KEY_SINCE).await? } else { None },
+        counters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+
+    #[tokio::test]
+    async fn test_counters_are_opt_in_and_cleared_on_opt_out() {
+        let db = Database::new_in_memory().await.unwrap();
+        let pool = db.pool();
+
+        // Off by default: nothing is counted
+        record_outcome(pool, JobKind::LibrarySync, &Ok(())).await;
+        let export = export_counters(pool).await.unwrap();
+        assert!(!export.enabled && export.counters.is_empty());
+
+        set_enabled(pool, true).await.unwrap();
+        record_outcome(pool, JobKind::LibrarySync, &Ok(())).await;
+        record_outcome(pool, JobKind::LibrarySync, &Ok(())).await;
+        let failed: Result<()> = Err(LibationError::network_error("reset", true));
+        record_outcome(pool, JobKind::Download, &failed).await;
+        record_outcome(pool, JobKind::Download, &Err::<(), _>(LibationError::Cancelled)).await;
+        record(pool, Counter::Liberation).await.unwrap();
+
+        let export = export_counters(pool).await.unwrap();
+        assert!(export.enabled && export.since.is_some());
+        let expected: BTreeMap<String, u64> = [
+            ("download.failed", 1),
+            ("error.network", 1),
+            ("library_sync.completed", 2),
+            ("liberation", 1),
+        ]
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), count))
+        .collect();
+        assert_eq!(export.counters, expected);
+
+        set_enabled(pool, false).await.unwrap();
+        let export = export_counters(pool).await.unwrap();
+        assert!(!export.enabled && export.since.is_none() && export.counters.is_empty());
+    }
+
+    #[test]
+    fn test_error_categories() {
+        assert_eq!(ErrorCategory::of(&LibationError::TokenExpired), ErrorCategory::Auth);
+        assert_eq!(ErrorCategory::of(&LibationError::InvalidDrmFormat("x".into())), ErrorCategory::Crypto);
+        assert_eq!(ErrorCategory::of(&LibationError::FileNotFound("x".into())), ErrorCategory::File);
+        assert_eq!(ErrorCategory::of(&LibationError::api_failed("x", Some(500), None)), ErrorCategory::Api);
+        assert_eq!(ErrorCategory::of(&LibationError::Timeout(30)), ErrorCategory::Network);
+        assert_eq!(ErrorCategory::of(&LibationError::internal("x")), ErrorCategory::Other);
+    }
+}
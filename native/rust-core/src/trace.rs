@@ -0,0 +1,155 @@
+// LibriSync - Audible Library Sync for Mobile
+// Copyright (C) 2025 Henning Berge
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Request tracing IDs
+//!
+//! Any bridge call can carry a `trace_id` in its params JSON, chosen by the
+//! JS layer and written to its own logs. While the call runs, the id is
+//! the current trace (`current`), and:
+//! - log lines written with `trace_eprintln!` start with `[trace_id]`
+//! - error responses include it (see `bridge_protocol`)
+//! - work it starts carries it on: download tasks store it and run their
+//!   worker under it, and work activity lists the traces of running work
+//!
+//! The current trace is per thread, which covers everything a bridge call
+//! awaits in `block_on`. Work spawned onto the runtime runs under a trace
+//! with `with_trace`.
+//!
+//! Ids are at most 64 characters of ASCII letters, digits and `-_.:`; any
+//! other value is ignored rather than failing the call.
+
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::future::Future;
+
+/// Longest accepted trace id
+pub const MAX_TRACE_ID_LEN: usize = 64;
+
+thread_local! {
+    static CURRENT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Trace id of the work running on this thread
+pub fn current() -> Option<String> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Whether `trace_id` is usable as a trace id
+pub fn is_valid_trace_id(trace_id: &str) -> bool {
+    !trace_id.is_empty()
+        && trace_id.len() <= MAX_TRACE_ID_LEN
+        && trace_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// The `trace_id` of a bridge call's params JSON, if present and valid
+pub fn trace_id_from_params(params_json: &str) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Traced {
+        trace_id: Option<String>,
+    }
+
+    serde_json::from_str::<Traced>(params_json)
+        .ok()
+        .and_then(|traced| traced.trace_id)
+        .filter(|trace_id| is_valid_trace_id(trace_id))
+}
+
+/// Make `trace_id` current on this thread until the scope drops
+///
+/// The previous trace is restored afterwards, so scopes nest.
+pub fn enter(trace_id: Option<String>) -> TraceScope {
+    let previous = CURRENT.with(|current| current.replace(trace_id));
+    TraceScope { previous }
+}
+
+/// Current trace set by `enter`
+#[must_use = "the trace is only current until the scope is dropped"]
+pub struct TraceScope {
+    previous: Option<String>,
+}
+
+impl Drop for TraceScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Run `future` with `trace_id` current whenever it is polled
+///
+/// For work spawned onto the runtime, which doesn't inherit the spawning
+/// thread's trace.
+pub async fn with_trace<F: Future>(trace_id: Option<String>, future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let _scope = enter(trace_id.clone());
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// `eprintln!`, prefixed with the current trace id if there is one
+macro_rules! trace_eprintln {
+    ($($arg:tt)*) => {
+        match $crate::trace::current() {
+            Some(trace_id) => eprintln!("[{}] {}", trace_id, format_args!($($arg)*)),
+            None => eprintln!($($arg)*),
+        }
+    };
+}
+pub(crate) use trace_eprintln;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_id_from_params() {
+        assert_eq!(
+            trace_id_from_params(r#"{"db_path": "/db", "trace_id": "js-42:dl.7"}"#),
+            Some("js-42:dl.7".to_string())
+        );
+        assert_eq!(trace_id_from_params(r#"{"db_path": "/db"}"#), None);
+        assert_eq!(trace_id_from_params(r#"{"trace_id": "two words"}"#), None);
+        assert_eq!(trace_id_from_params(&format!(r#"{{"trace_id": "{}"}}"#, "a".repeat(65))), None);
+        assert_eq!(trace_id_from_params(r#"{"trace_id": 7}"#), None);
+        assert_eq!(trace_id_from_params("not json"), None);
+    }
+
+    #[tokio::test]
+    async fn test_trace_scopes() {
+        assert_eq!(current(), None);
+        {
+            let _outer = enter(Some("outer".to_string()));
+            {
+                let _inner = enter(Some("inner".to_string()));
+                assert_eq!(current().as_deref(), Some("inner"));
+            }
+            assert_eq!(current().as_deref(), Some("outer"));
+        }
+        assert_eq!(current(), None);
+
+        // Spawned work only has the trace it is given
+        let spawned = tokio::spawn(with_trace(Some("spawned".to_string()), async {
+            tokio::task::yield_now().await;
+            current()
+        }));
+        assert_eq!(spawned.await.unwrap().as_deref(), Some("spawned"));
+        assert_eq!(current(), None);
+    }
+}
@@ -70,7 +70,7 @@ fn test_account_ready_for_activation_bytes() {
     assert!(account.identity.is_some());
 
     let identity = account.identity.as_ref().unwrap();
-    assert!(!identity.access_token.token.is_empty());
+    assert!(!identity.access_token.token.expose_secret().is_empty());
     assert_eq!(identity.locale.country_code, "us");
 
     // Account should not need token refresh
@@ -160,7 +160,7 @@ fn test_activation_bytes_flow_preparation() {
     println!("\n=== Activation Bytes Request Preparation ===");
     println!("Account: {}", account.account_name);
     println!("Locale: {}", identity.locale.name);
-    println!("Access Token: {}...", &identity.access_token.token[..30]);
+    println!("Access Token: {}...", &identity.access_token.token.expose_secret()[..30]);
     println!("API URL: {}", identity.locale.api_url());
 
     // Build request details
@@ -169,10 +169,10 @@ fn test_activation_bytes_flow_preparation() {
         identity.locale.domain
     );
     println!("Endpoint: {}", endpoint_url);
-    println!("Authorization: Bearer {}...", &identity.access_token.token[..20]);
+    println!("Authorization: Bearer {}...", &identity.access_token.token.expose_secret()[..20]);
     println!("===========================================\n");
 
-    assert!(!identity.access_token.token.is_empty());
+    assert!(!identity.access_token.token.expose_secret().is_empty());
     assert_eq!(identity.locale.domain, "audible.com");
 }
 
@@ -201,15 +201,15 @@ fn test_decrypt_key_storage() {
         .expect("Failed to create account");
 
     // Initially empty
-    assert!(account.decrypt_key.is_empty());
+    assert!(account.decrypt_key.expose_secret().is_empty());
 
     // Set activation bytes
     let activation_bytes = "1a2b3c4d";
     account.set_decrypt_key(activation_bytes.to_string());
 
-    assert_eq!(account.decrypt_key, activation_bytes);
+    assert_eq!(account.decrypt_key.expose_secret(), activation_bytes);
 
-    println!("✅ Decrypt key stored: {}", account.decrypt_key);
+    println!("✅ Decrypt key stored: {}", account.decrypt_key.expose_secret());
 }
 
 /// Test activation bytes in different byte orders
@@ -274,18 +274,18 @@ fn test_complete_activation_bytes_workflow() {
         identity.locale.domain
     );
     println!("   Endpoint: {}", endpoint);
-    println!("   Token: {}...", &identity.access_token.token[..30]);
+    println!("   Token: {}...", &identity.access_token.token.expose_secret()[..30]);
 
     // Step 6: Simulate response parsing
     println!("🔍 Step 6: Simulate activation bytes extraction");
     let simulated_activation_bytes = "1a2b3c4d";
     account.set_decrypt_key(simulated_activation_bytes.to_string());
-    println!("   ✅ Activation bytes: {}", account.decrypt_key);
+    println!("   ✅ Activation bytes: {}", account.decrypt_key.expose_secret());
 
     // Step 7: Verify storage
     println!("💾 Step 7: Verify activation bytes stored");
-    assert_eq!(account.decrypt_key, simulated_activation_bytes);
-    assert!(!account.decrypt_key.is_empty());
+    assert_eq!(account.decrypt_key.expose_secret(), simulated_activation_bytes);
+    assert!(!account.decrypt_key.expose_secret().is_empty());
     println!("   ✅ Decrypt key stored in account");
 
     println!("=========================================");
@@ -293,6 +293,6 @@ fn test_complete_activation_bytes_workflow() {
     println!("\n📊 Account Summary:");
     println!("   Name: {}", account.account_name);
     println!("   Locale: {}", identity.locale.name);
-    println!("   Activation Bytes: {}", account.decrypt_key);
+    println!("   Activation Bytes: {}", account.decrypt_key.expose_secret());
     println!("   Ready for DRM removal: ✅");
 }
@@ -49,7 +49,7 @@ fn load_test_account() -> Result<Account, Box<dyn std::error::Error>> {
         account_id: success.extensions.device_info.device_serial_number.clone(),
         account_name: success.extensions.customer_info.name.clone(),
         library_scan: true,
-        decrypt_key: String::new(),
+        decrypt_key: Default::default(),
         identity: Some(identity),
     };
 
@@ -74,7 +74,7 @@ async fn test_download_book_with_manager() -> Result<(), Box<dyn std::error::Err
         let device_serial = account.account_id.clone();
 
         let new_tokens =
-            rust_core::api::auth::refresh_access_token(&locale, &refresh_token, &device_serial)
+            rust_core::api::auth::refresh_access_token(&locale, refresh_token.expose_secret(), &device_serial)
                 .await?;
 
         println!("   ✓ Token refreshed");
@@ -41,14 +41,14 @@ fn test_extract_all_tokens() {
         .expect("Failed to extract registration data");
 
     // Bearer tokens
-    assert!(data.access_token.token.starts_with("Atna|"));
-    assert!(data.refresh_token.starts_with("Atnr|"));
+    assert!(data.access_token.token.expose_secret().starts_with("Atna|"));
+    assert!(data.refresh_token.expose_secret().starts_with("Atnr|"));
     assert!(data.access_token.expires_at > chrono::Utc::now());
 
     // MAC DMS tokens
-    assert!(data.device_private_key.len() > 1000);
-    assert!(data.adp_token.contains("{enc:"));
-    assert!(data.adp_token.contains("{key:"));
+    assert!(data.device_private_key.expose_secret().len() > 1000);
+    assert!(data.adp_token.expose_secret().contains("{enc:"));
+    assert!(data.adp_token.expose_secret().contains("{key:"));
 
     // Device info
     assert_eq!(data.device_serial_number, "B45EF975C33A7B7E8DAF4D96E39B8040");
@@ -67,7 +67,7 @@ fn test_extract_all_tokens() {
     assert!(data.cookies.len() >= 5);
 
     // Store auth cookie
-    assert!(data.store_authentication_cookie.len() > 50);
+    assert!(data.store_authentication_cookie.expose_secret().len() > 50);
 
     println!("✅ All tokens extracted successfully");
 }
@@ -82,8 +82,8 @@ fn test_create_identity_from_real_data() {
         .expect("Failed to create identity");
 
     // Verify Identity has all required fields
-    assert!(identity.access_token.token.len() > 50);
-    assert!(identity.refresh_token.len() > 50);
+    assert!(identity.access_token.token.expose_secret().len() > 50);
+    assert!(identity.refresh_token.expose_secret().len() > 50);
     assert_eq!(identity.device_serial_number, "B45EF975C33A7B7E8DAF4D96E39B8040");
     assert_eq!(identity.device_type, "A10KISP2GWF0E4");
     assert_eq!(identity.amazon_account_id, "amzn1.account.AGMGLSGIFYVALF2MEO4F3JJQRLSA");
@@ -197,18 +197,18 @@ fn test_device_credentials_complete() {
         .expect("Failed to extract data");
 
     // Verify device private key (RSA key in PEM-like format, but base64 only)
-    assert!(data.device_private_key.starts_with("MII"));
-    assert!(data.device_private_key.len() > 1500); // RSA-2048 keys are ~1700 chars
+    assert!(data.device_private_key.expose_secret().starts_with("MII"));
+    assert!(data.device_private_key.expose_secret().len() > 1500); // RSA-2048 keys are ~1700 chars
 
     // Verify ADP token has encrypted structure
-    assert!(data.adp_token.contains("{enc:"));
-    assert!(data.adp_token.contains("{key:"));
-    assert!(data.adp_token.contains("{iv:"));
-    assert!(data.adp_token.contains("{name:"));
-    assert!(data.adp_token.contains("{serial:"));
+    assert!(data.adp_token.expose_secret().contains("{enc:"));
+    assert!(data.adp_token.expose_secret().contains("{key:"));
+    assert!(data.adp_token.expose_secret().contains("{iv:"));
+    assert!(data.adp_token.expose_secret().contains("{name:"));
+    assert!(data.adp_token.expose_secret().contains("{serial:"));
 
     // Verify store auth cookie
-    assert!(data.store_authentication_cookie.len() > 50);
+    assert!(data.store_authentication_cookie.expose_secret().len() > 50);
 
     println!("✅ Device credentials complete and properly formatted");
 }
@@ -246,8 +246,8 @@ fn test_ready_for_library_sync() {
         .expect("Failed to create identity");
 
     // Verify we have everything needed for API calls
-    assert!(!identity.access_token.token.is_empty());
-    assert!(!identity.refresh_token.is_empty());
+    assert!(!identity.access_token.token.expose_secret().is_empty());
+    assert!(!identity.refresh_token.expose_secret().is_empty());
     assert!(!identity.device_serial_number.is_empty());
     assert!(!identity.device_type.is_empty());
     assert!(!identity.amazon_account_id.is_empty());
@@ -318,17 +318,17 @@ fn test_print_registration_summary() {
     println!("   Account Pool: {}", data.customer_info.account_pool);
 
     println!("\n🔑 Tokens:");
-    println!("   Access Token: {}...", &data.access_token.token[..30]);
-    println!("   Refresh Token: {}...", &data.refresh_token[..30]);
+    println!("   Access Token: {}...", &data.access_token.token.expose_secret()[..30]);
+    println!("   Refresh Token: {}...", &data.refresh_token.expose_secret()[..30]);
     println!("   Expires At: {}", data.access_token.expires_at);
-    println!("   Device Private Key: {} chars", data.device_private_key.len());
-    println!("   ADP Token: {} chars", data.adp_token.len());
+    println!("   Device Private Key: {} chars", data.device_private_key.expose_secret().len());
+    println!("   ADP Token: {} chars", data.adp_token.expose_secret().len());
 
     println!("\n🍪 Cookies:");
     for (name, value) in &data.cookies {
         println!("   {}: {}...", name, &value[..value.len().min(30)]);
     }
 
-    println!("\n🏪 Store Auth Cookie: {} chars", data.store_authentication_cookie.len());
+    println!("\n🏪 Store Auth Cookie: {} chars", data.store_authentication_cookie.expose_secret().len());
     println!("================================\n");
 }
@@ -160,8 +160,8 @@ async fn test_01_interactive_oauth_registration() -> Result<()> {
     ).await?;
 
     println!("\n✅ Token Exchange Successful!");
-    print_row("Access Token", &truncate(&token_response.bearer.access_token, 40));
-    print_row("Refresh Token", &truncate(&token_response.bearer.refresh_token, 40));
+    print_row("Access Token", &truncate(token_response.bearer.access_token.expose_secret(), 40));
+    print_row("Refresh Token", &truncate(token_response.bearer.refresh_token.expose_secret(), 40));
     print_row("Expires In", &format!("{} seconds", token_response.bearer.expires_in));
 
     // Step 5: Parse full registration response
@@ -293,7 +293,7 @@ async fn test_02_library_sync() -> Result<()> {
     let api_url = identity.locale.api_url();
 
     print_row("API URL", &api_url);
-    print_row("Access Token", &truncate(&identity.access_token.token, 40));
+    print_row("Access Token", &truncate(identity.access_token.token.expose_secret(), 40));
 
     // Step 3: Fetch library
     print_section("Step 3: Fetch Library from Audible");
@@ -311,7 +311,7 @@ async fn test_02_library_sync() -> Result<()> {
     let client = reqwest::Client::new();
     let library_response: LibraryResponse = client
         .get(format!("{}/1.0/library", api_url))
-        .header("Authorization", format!("Bearer {}", identity.access_token.token))
+        .header("Authorization", format!("Bearer {}", identity.access_token.token.expose_secret()))
         .query(&options)
         .send()
         .await?
@@ -401,7 +401,7 @@ async fn test_03_token_refresh() -> Result<()> {
     let identity = account.identity.as_ref().unwrap();
 
     print_row("Account", &account.account_name);
-    print_row("Current Token", &truncate(&identity.access_token.token, 40));
+    print_row("Current Token", &truncate(identity.access_token.token.expose_secret(), 40));
     print_row("Expires At", &identity.access_token.expires_at.to_string());
 
     // Step 2: Check current state
@@ -427,8 +427,8 @@ async fn test_03_token_refresh() -> Result<()> {
     let new_token = &new_identity.access_token.token;
 
     println!("\n✅ Token Refresh Successful!");
-    print_row("Old Token", &truncate(&old_token, 40));
-    print_row("New Token", &truncate(new_token, 40));
+    print_row("Old Token", &truncate(old_token.expose_secret(), 40));
+    print_row("New Token", &truncate(new_token.expose_secret(), 40));
     print_row("New Expires At", &new_identity.access_token.expires_at.to_string());
     print_row("Time Until Expiry", &format!("{:?}", new_identity.time_until_expiry()));
 
@@ -459,7 +459,7 @@ async fn test_03_token_refresh() -> Result<()> {
     let client = reqwest::Client::new();
     let library_response: LibraryResponse = client
         .get(format!("{}/1.0/library", api_url))
-        .header("Authorization", format!("Bearer {}", new_identity.access_token.token))
+        .header("Authorization", format!("Bearer {}", new_identity.access_token.token.expose_secret()))
         .query(&options)
         .send()
         .await?
@@ -506,9 +506,9 @@ async fn test_04_activation_bytes() -> Result<()> {
     let mut account = load_credentials()?;
 
     print_row("Account", &account.account_name);
-    print_row("Current Activation Bytes", &account.decrypt_key);
+    print_row("Current Activation Bytes", account.decrypt_key.expose_secret());
 
-    if !account.decrypt_key.is_empty() {
+    if !account.decrypt_key.expose_secret().is_empty() {
         println!("\n💡 Account already has activation bytes");
         println!("   We'll fetch them again to verify");
     }
@@ -530,7 +530,7 @@ async fn test_04_activation_bytes() -> Result<()> {
 
     println!("\n🔄 Calling license endpoint...");
     print_row("Endpoint", &endpoint);
-    print_row("Authorization", &format!("Bearer {}...", truncate(&identity.access_token.token, 30)));
+    print_row("Authorization", &format!("Bearer {}...", truncate(identity.access_token.token.expose_secret(), 30)));
 
     let activation_bytes = account.get_activation_bytes().await?;
 
@@ -636,7 +636,7 @@ async fn test_05_complete_flow() -> Result<()> {
     let client = reqwest::Client::new();
     let library: LibraryResponse = client
         .get(format!("{}/1.0/library", api_url))
-        .header("Authorization", format!("Bearer {}", access_token))
+        .header("Authorization", format!("Bearer {}", access_token.expose_secret()))
         .query(&options)
         .send()
         .await?
@@ -657,7 +657,7 @@ async fn test_05_complete_flow() -> Result<()> {
     }
 
     // Get activation bytes (if not already present)
-    if account.decrypt_key.is_empty() {
+    if account.decrypt_key.expose_secret().is_empty() {
         print_section("Getting Activation Bytes");
 
         let activation_bytes = account.get_activation_bytes().await?;
@@ -666,7 +666,7 @@ async fn test_05_complete_flow() -> Result<()> {
         save_credentials(&account)?;
     } else {
         print_section("Activation Bytes");
-        println!("✅ Already have activation bytes: {}", account.decrypt_key);
+        println!("✅ Already have activation bytes: {}", account.decrypt_key.expose_secret());
     }
 
     // Final summary
@@ -679,7 +679,7 @@ async fn test_05_complete_flow() -> Result<()> {
     print_row("Name", &account.account_name);
     print_row("Locale", &locale_name);
     print_row("Library Size", &library.total_results.unwrap_or(0).to_string());
-    print_row("Activation Bytes", &account.decrypt_key);
+    print_row("Activation Bytes", account.decrypt_key.expose_secret());
     print_row("Token Valid", &(!account.needs_token_refresh()).to_string());
 
     println!("\n✅ Ready for:");
@@ -0,0 +1,35 @@
+//! Replay of recorded API bundles
+//!
+//! Runs every bundle in `test_fixtures/replay/` against its recorded
+//! responses (see `rust_core::api::recording`). A bundle with an `error`
+//! must still fail with it; one whose `error` is null must succeed.
+
+use rust_core::api::recording::{replay, ReplayBundle};
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn test_replay_bundles() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_fixtures/replay");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_string_lossy().ends_with(".replay.json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no bundles in {:?}", dir);
+
+    for path in paths {
+        let bundle = ReplayBundle::load(&path).unwrap();
+        let outcome = replay(&bundle).await.map_err(|e| e.to_string());
+        match (&bundle.error, outcome) {
+            (Some(expected), Err(actual)) => assert_eq!(&actual, expected, "{}", path.display()),
+            (None, Ok(_)) => {}
+            (expected, actual) => panic!(
+                "{}: recorded {:?}, replayed {:?}",
+                path.display(),
+                expected,
+                actual.map(|_| "success")
+            ),
+        }
+    }
+}